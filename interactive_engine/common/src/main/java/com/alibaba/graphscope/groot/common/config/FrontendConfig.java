@@ -31,4 +31,9 @@ public class FrontendConfig {
 
     public static final Config<Integer> WRITE_QUEUE_BUFFER_MAX_COUNT =
             Config.intConfig("write.queue.buffer.max.count", 1024000);
+
+    // the strategies generating the primary keys of vertices by labels, e.g.
+    // `person:auto_increment,event:snowflake`, the others are inserted with primary keys
+    public static final Config<String> VERTEX_ID_STRATEGY =
+            Config.stringConfig("frontend.vertex.id.strategy", "");
 }
@@ -50,6 +50,25 @@ public class StoreConfig {
     public static final Config<String> STORE_WAL_DIR =
             Config.stringConfig("store.rocksdb.wal.dir", "");
 
+    // the directory of the sealed graphs served read-only, of a sub directory per partition
+    public static final Config<String> STORE_SEALED_PATH =
+            Config.stringConfig("store.sealed.path", "");
+
     public static final Config<Integer> STORE_COMPACT_THREAD_NUM =
             Config.intConfig("store.compact.thread.num", 1);
+
+    // how long the writes throttled by the store are retried before they use up the retries
+    public static final Config<Long> STORE_WRITE_THROTTLE_MAX_WAIT_MS =
+            Config.longConfig("store.write.throttle.max.wait.ms", 600000L);
+
+    // the meta raft group replicating the schema and the partition routing among store nodes, which
+    // is started with the engine if the peers are set, e.g. `1@host1:port1,2@host2:port2`
+    public static final Config<String> STORE_RAFT_PEERS =
+            Config.stringConfig("store.raft.peers", "");
+
+    public static final Config<Integer> STORE_RAFT_NODE_ID =
+            Config.intConfig("store.raft.node.id", 0);
+
+    public static final Config<String> STORE_RAFT_DATA_PATH =
+            Config.stringConfig("store.raft.data.path", "./raft");
 }
@@ -8,5 +8,5 @@ then
     echo "cargo not exit, skip compile"
 else
     cd ../executor/ir/core
-    cargo build --release
+    cargo build --release --features graphql
 fi
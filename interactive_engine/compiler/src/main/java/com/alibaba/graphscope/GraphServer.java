@@ -35,6 +35,7 @@ import com.alibaba.graphscope.gremlin.antlr4x.parser.GremlinAntlr4Parser;
 import com.alibaba.graphscope.gremlin.antlr4x.visitor.GraphBuilderVisitor;
 import com.alibaba.graphscope.gremlin.integration.result.GraphProperties;
 import com.alibaba.graphscope.gremlin.integration.result.TestGraphFactory;
+import com.alibaba.graphscope.graphql.GraphQLHttpServer;
 import com.alibaba.graphscope.gremlin.service.IrGremlinServer;
 import com.google.common.collect.ImmutableMap;
 import com.google.common.io.Resources;
@@ -60,6 +61,7 @@ public class GraphServer {
 
     private IrGremlinServer gremlinServer;
     private CypherBootstrapper cypherBootstrapper;
+    private GraphQLHttpServer graphQLServer;
 
     public GraphServer(
             Configs configs,
@@ -120,6 +122,15 @@ public class GraphServer {
                             FrontendConfig.QUERY_EXECUTION_TIMEOUT_MS.get(this.configs) + "ms"),
                     false);
         }
+        if (!FrontendConfig.GRAPHQL_SERVER_DISABLED.get(configs)) {
+            // the fields are resolved into the plans of the ir core, which only pegasus executes
+            if (!"pegasus".equals(FrontendConfig.ENGINE_TYPE.get(configs))) {
+                throw new IllegalArgumentException("graphql server is only supported by pegasus");
+            }
+            this.graphQLServer =
+                    new GraphQLHttpServer(configs, idGenerator, metaQueryCallback, executionClient);
+            this.graphQLServer.start();
+        }
     }
 
     private Path getNeo4jHomePath() throws IOException {
@@ -157,6 +168,9 @@ public class GraphServer {
         if (!FrontendConfig.GREMLIN_SERVER_DISABLED.get(configs) && this.gremlinServer != null) {
             this.gremlinServer.close();
         }
+        if (this.graphQLServer != null) {
+            this.graphQLServer.close();
+        }
     }
 
     public static void main(String[] args) throws Exception {
@@ -20,6 +20,7 @@ import com.alibaba.graphscope.common.config.Configs;
 import com.alibaba.graphscope.common.config.PegasusConfig;
 import com.alibaba.graphscope.common.exception.*;
 import com.alibaba.graphscope.common.intermediate.ArgAggFn;
+import com.alibaba.graphscope.common.intermediate.ArgElementMap;
 import com.alibaba.graphscope.common.intermediate.ArgUtils;
 import com.alibaba.graphscope.common.intermediate.InterOpCollection;
 import com.alibaba.graphscope.common.intermediate.MatchSentence;
@@ -301,10 +302,25 @@ public class IrPlan implements Closeable {
                 Pointer ptrProject = irCoreLib.initProjectOperator(true);
                 exprWithAlias.forEach(
                         p -> {
-                            String expr = (String) p.getValue0();
                             FfiAlias.ByValue alias = (FfiAlias.ByValue) p.getValue1();
-                            FfiResult error =
-                                    irCoreLib.addProjectExprAlias(ptrProject, expr, alias);
+                            FfiResult error;
+                            if (p.getValue0() instanceof ArgElementMap) {
+                                ArgElementMap elementMap = (ArgElementMap) p.getValue0();
+                                error =
+                                        irCoreLib.addProjectElementMap(
+                                                ptrProject,
+                                                ArgUtils.asNameOrId(elementMap.getTag()),
+                                                alias);
+                                for (String key : elementMap.getKeys()) {
+                                    if (error.code != ResultCode.Success) {
+                                        break;
+                                    }
+                                    error = irCoreLib.addProjectElementMapKey(ptrProject, key);
+                                }
+                            } else {
+                                String expr = (String) p.getValue0();
+                                error = irCoreLib.addProjectExprAlias(ptrProject, expr, alias);
+                            }
                             if (error.code != ResultCode.Success) {
                                 throw new InterOpIllegalArgException(
                                         baseOp.getClass(),
@@ -312,6 +328,15 @@ public class IrPlan implements Closeable {
                                         "append returns " + error.getMsg());
                             }
                         });
+                if (op.isSelectTags()) {
+                    FfiResult error = irCoreLib.setProjectSelectTags(ptrProject, true);
+                    if (error.code != ResultCode.Success) {
+                        throw new InterOpIllegalArgException(
+                                baseOp.getClass(),
+                                "selectTags",
+                                "setProjectSelectTags returns " + error.getMsg());
+                    }
+                }
                 return ptrProject;
             }
         },
@@ -56,12 +56,16 @@ public class RpcExecutionClient extends ExecutionClient<RpcChannel> {
             rpcClientRef.compareAndSet(null, new RpcClient(channelFetcher.fetch()));
         }
         RpcClient rpcClient = rpcClientRef.get();
-        PegasusClient.JobRequest jobRequest =
+        byte[] resource = request.getRequestResource();
+        PegasusClient.JobRequest.Builder jobRequestBuilder =
                 PegasusClient.JobRequest.newBuilder()
                         .setPlan(
                                 ByteString.copyFrom(
-                                        (byte[]) request.getRequestPhysical().getContent()))
-                        .build();
+                                        (byte[]) request.getRequestPhysical().getContent()));
+        if (resource != null) {
+            jobRequestBuilder.setResource(ByteString.copyFrom(resource));
+        }
+        PegasusClient.JobRequest jobRequest = jobRequestBuilder.build();
         PegasusClient.JobConfig jobConfig =
                 PegasusClient.JobConfig.newBuilder()
                         .setJobId(request.getRequestId().longValue())
@@ -83,6 +87,10 @@ public class RpcExecutionClient extends ExecutionClient<RpcChannel> {
                     @Override
                     public void process(PegasusClient.JobResponse jobResponse) {
                         try {
+                            if (request.isResponseEncoded()) {
+                                listener.onResponse(jobResponse.getResp().toByteArray());
+                                return;
+                            }
                             listener.onNext(
                                     IrResult.Results.parseFrom(jobResponse.getResp()).getRecord());
                         } catch (Exception e) {
@@ -29,16 +29,48 @@ public class ExecutionRequest {
     private final String requestName;
     private final LogicalPlan requestLogical;
     private final PhysicalPlan requestPhysical;
+    // the request of the client in GraphBinary, which the engine binds the parameters of and
+    // responds to in GraphBinary, null if the results are returned in protobuf
+    private final byte[] requestResource;
+    // the results are encoded by the engine as the plan sinks them, rather than in protobuf
+    private final boolean responseEncoded;
 
     public ExecutionRequest(
             BigInteger requestId,
             String requestName,
             LogicalPlan requestLogical,
             PhysicalPlan requestPhysical) {
+        this(requestId, requestName, requestLogical, requestPhysical, null);
+    }
+
+    public ExecutionRequest(
+            BigInteger requestId,
+            String requestName,
+            LogicalPlan requestLogical,
+            PhysicalPlan requestPhysical,
+            byte[] requestResource) {
+        this(
+                requestId,
+                requestName,
+                requestLogical,
+                requestPhysical,
+                requestResource,
+                requestResource != null);
+    }
+
+    public ExecutionRequest(
+            BigInteger requestId,
+            String requestName,
+            LogicalPlan requestLogical,
+            PhysicalPlan requestPhysical,
+            byte[] requestResource,
+            boolean responseEncoded) {
         this.requestId = requestId;
         this.requestName = requestName;
         this.requestLogical = requestLogical;
         this.requestPhysical = requestPhysical;
+        this.requestResource = requestResource;
+        this.responseEncoded = responseEncoded;
     }
 
     public BigInteger getRequestId() {
@@ -56,4 +88,16 @@ public class ExecutionRequest {
     public PhysicalPlan getRequestPhysical() {
         return requestPhysical;
     }
+
+    public byte[] getRequestResource() {
+        return requestResource;
+    }
+
+    /**
+     * the results are handled by {@code ExecutionResponseListener#onResponse(byte[])} if true
+     * @return
+     */
+    public boolean isResponseEncoded() {
+        return responseEncoded;
+    }
 }
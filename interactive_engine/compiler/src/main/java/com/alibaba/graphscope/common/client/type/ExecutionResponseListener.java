@@ -24,6 +24,15 @@ import com.alibaba.graphscope.gaia.proto.IrResult;
 public interface ExecutionResponseListener {
     void onNext(IrResult.Record record);
 
+    /**
+     * handle the response encoded by the engine for the request of the client,
+     * see {@code ExecutionRequest#isResponseEncoded()}
+     * @param response
+     */
+    default void onResponse(byte[] response) {
+        throw new UnsupportedOperationException("response encoded by the engine is not supported");
+    }
+
     void onCompleted();
 
     void onError(Throwable t);
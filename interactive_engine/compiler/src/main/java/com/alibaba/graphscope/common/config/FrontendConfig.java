@@ -29,6 +29,13 @@ public class FrontendConfig {
     public static final Config<Integer> NEO4J_BOLT_SERVER_PORT =
             Config.intConfig("neo4j.bolt.server.port", 7687);
 
+    // the GraphQL read API derived from the graph schema, served by the pegasus engine only
+    public static final Config<Boolean> GRAPHQL_SERVER_DISABLED =
+            Config.boolConfig("graphql.server.disabled", true);
+
+    public static final Config<Integer> GRAPHQL_SERVER_PORT =
+            Config.intConfig("graphql.server.port", 8184);
+
     public static final Config<Integer> QUERY_EXECUTION_TIMEOUT_MS =
             Config.intConfig("query.execution.timeout.ms", 3000000);
 
@@ -60,4 +67,9 @@ public class FrontendConfig {
 
     public static final Config<Integer> PER_QUERY_STREAM_BUFFER_MAX_CAPACITY =
             Config.intConfig("per.query.stream.buffer.max.capacity", 256);
+
+    // the results of the gremlin clients in GraphBinary are encoded by the engine, and forwarded
+    // to the clients as they are
+    public static final Config<Boolean> GREMLIN_GRAPHBINARY_PASSTHROUGH_ENABLED =
+            Config.boolConfig("gremlin.graphbinary.passthrough.enabled", false);
 }
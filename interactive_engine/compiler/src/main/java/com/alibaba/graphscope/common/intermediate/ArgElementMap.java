@@ -0,0 +1,55 @@
+/*
+ * Copyright 2020 Alibaba Group Holding Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+package com.alibaba.graphscope.common.intermediate;
+
+import com.google.common.base.Objects;
+
+import java.util.List;
+
+// represent elementMap(..) of a tag, as an expression of ProjectOp, which is projected into the map
+// of the id, the label and the properties of the given keys, or all the properties if none is given
+public class ArgElementMap {
+    // "" indicates HEAD
+    private String tag;
+    private List<String> keys;
+
+    public ArgElementMap(String tag, List<String> keys) {
+        this.tag = tag;
+        this.keys = keys;
+    }
+
+    public String getTag() {
+        return tag;
+    }
+
+    public List<String> getKeys() {
+        return keys;
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) return true;
+        if (o == null || getClass() != o.getClass()) return false;
+        ArgElementMap that = (ArgElementMap) o;
+        return Objects.equal(tag, that.tag) && Objects.equal(keys, that.keys);
+    }
+
+    @Override
+    public int hashCode() {
+        return Objects.hashCode(tag, keys);
+    }
+}
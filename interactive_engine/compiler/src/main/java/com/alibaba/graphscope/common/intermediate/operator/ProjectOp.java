@@ -28,6 +28,9 @@ public class ProjectOp extends InterOpBase {
     // List of Pair<expr, alias>
     private Optional<OpArg> exprWithAlias;
 
+    // project the tags as in select(..), where the traversers missing any of the tags are filtered
+    private boolean isSelectTags;
+
     public ProjectOp() {
         super();
         exprWithAlias = Optional.empty();
@@ -67,4 +70,12 @@ public class ProjectOp extends InterOpBase {
     public void setExprWithAlias(OpArg projectExpr) {
         this.exprWithAlias = Optional.of(projectExpr);
     }
+
+    public boolean isSelectTags() {
+        return isSelectTags;
+    }
+
+    public void setSelectTags(boolean isSelectTags) {
+        this.isSelectTags = isSelectTags;
+    }
 }
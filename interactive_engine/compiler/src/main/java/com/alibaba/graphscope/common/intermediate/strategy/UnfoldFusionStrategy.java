@@ -58,9 +58,13 @@ public class UnfoldFusionStrategy implements InterOpStrategy {
                     }
                 }
 
+                // elementMap().unfold()
+                Object exprArg = projectOp.getExprWithAlias().get().getArg();
+                if (!(exprArg instanceof List)) {
+                    continue;
+                }
                 List<Pair<String, FfiAlias.ByValue>> pairList =
-                        (List<Pair<String, FfiAlias.ByValue>>)
-                                projectOp.getExprWithAlias().get().getArg();
+                        (List<Pair<String, FfiAlias.ByValue>>) exprArg;
 
                 // select("a", "b").unfold()
                 if (pairList.size() >= 2) {
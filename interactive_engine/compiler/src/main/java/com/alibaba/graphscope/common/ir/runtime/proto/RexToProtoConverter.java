@@ -18,7 +18,6 @@ package com.alibaba.graphscope.common.ir.runtime.proto;
 
 import com.alibaba.graphscope.common.ir.rex.RexGraphVariable;
 import com.alibaba.graphscope.common.ir.tools.AliasInference;
-import com.alibaba.graphscope.common.ir.tools.GraphStdOperatorTable;
 import com.alibaba.graphscope.gaia.proto.Common;
 import com.alibaba.graphscope.gaia.proto.DataType;
 import com.alibaba.graphscope.gaia.proto.OuterExpression;
@@ -225,32 +224,8 @@ public class RexToProtoConverter extends RexVisitorImpl<OuterExpression.Expressi
     private OuterExpression.Expression visitUnaryOperator(RexCall call) {
         SqlOperator operator = call.getOperator();
         RexNode operand = call.getOperands().get(0);
-        switch (operator.getKind()) {
-                // convert IS_NOT_NULL to NOT(IS_NULL(XX))
-            case IS_NOT_NULL:
-                return OuterExpression.Expression.newBuilder()
-                        .addOperators(
-                                Utils.protoOperator(GraphStdOperatorTable.NOT).toBuilder()
-                                        .setNodeType(
-                                                Utils.protoIrDataType(call.getType(), isColumnId)))
-                        .addOperators(
-                                OuterExpression.ExprOpr.newBuilder()
-                                        .setBrace(OuterExpression.ExprOpr.Brace.LEFT_BRACE))
-                        .addAllOperators(
-                                visitUnaryOperator(
-                                                GraphStdOperatorTable.IS_NULL,
-                                                operand,
-                                                call.getType())
-                                        .getOperatorsList())
-                        .addOperators(
-                                OuterExpression.ExprOpr.newBuilder()
-                                        .setBrace(OuterExpression.ExprOpr.Brace.RIGHT_BRACE))
-                        .build();
-            case IS_NULL:
-            case NOT:
-            default:
-                return visitUnaryOperator(operator, operand, call.getType());
-        }
+        // IS_NOT_NULL is converted to EXISTS, e.g., has('name'), which the store checks
+        return visitUnaryOperator(operator, operand, call.getType());
     }
 
     private OuterExpression.Expression visitUnaryOperator(
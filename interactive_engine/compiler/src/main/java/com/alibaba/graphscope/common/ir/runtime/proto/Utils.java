@@ -236,6 +236,10 @@ public abstract class Utils {
                 return OuterExpression.ExprOpr.newBuilder()
                         .setLogical(OuterExpression.Logical.ISNULL)
                         .build();
+            case IS_NOT_NULL:
+                return OuterExpression.ExprOpr.newBuilder()
+                        .setLogical(OuterExpression.Logical.EXISTS)
+                        .build();
             case SEARCH:
                 return OuterExpression.ExprOpr.newBuilder()
                         .setLogical(OuterExpression.Logical.WITHIN)
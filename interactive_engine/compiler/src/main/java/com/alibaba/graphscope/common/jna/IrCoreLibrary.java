@@ -101,6 +101,11 @@ public interface IrCoreLibrary extends Library {
 
     FfiResult.ByValue addProjectExprAlias(Pointer project, String expr, FfiAlias.ByValue alias);
 
+    FfiResult.ByValue addProjectElementMap(
+            Pointer project, FfiNameOrId.ByValue tag, FfiAlias.ByValue alias);
+
+    FfiResult.ByValue addProjectElementMapKey(Pointer project, String key);
+
     FfiResult.ByValue addProjectExprPbAlias(
             Pointer project, FfiPbPointer.ByValue pbPointer, FfiAlias.ByValue alias);
 
@@ -211,6 +216,8 @@ public interface IrCoreLibrary extends Library {
 
     FfiResult.ByValue addParamsTable(Pointer params, FfiNameOrId.ByValue table);
 
+    FfiResult.ByValue addParamsExcludedTable(Pointer params, FfiNameOrId.ByValue table);
+
     FfiResult.ByValue addParamsColumn(Pointer params, FfiNameOrId.ByValue column);
 
     FfiResult.ByValue setParamsRange(Pointer params, int lower, int upper);
@@ -223,8 +230,26 @@ public interface IrCoreLibrary extends Library {
 
     FfiResult.ByValue setParamsSampleRatio(Pointer params, double sampleRatio);
 
+    FfiResult.ByValue setParamsValidTime(Pointer params, long start, long end);
+
     FfiResult.ByValue getKeyName(int keyId, FfiKeyType keyType);
 
+    /**
+     * the GraphQL schema derived from the schema of {@link #setSchema(String)}, as the message
+     */
+    FfiResult.ByValue getGraphqlSchema();
+
+    /**
+     * resolve the root field at {@code index} of a GraphQL query into the plan, sunk in GraphSON;
+     * the message is the response key of the field, or empty if {@code index} is beyond the fields
+     * @param query
+     * @param variables the variables in json, or empty
+     * @param index
+     * @param plan initialized by {@link #initLogicalPlan()}
+     * @return
+     */
+    FfiResult.ByValue resolveGraphql(String query, String variables, int index, Pointer plan);
+
     FfiResult.ByValue addParamsExtra(Pointer params, String key, String value);
 
     Pointer initSinkGraphOperator(String graphName);
@@ -237,6 +262,8 @@ public interface IrCoreLibrary extends Library {
 
     FfiResult.ByValue addProjectMeta(Pointer project, FfiPbPointer.ByValue meta);
 
+    FfiResult.ByValue setProjectSelectTags(Pointer project, boolean selectTags);
+
     FfiResult.ByValue addGroupbyKeyValueMeta(Pointer groupBy, FfiPbPointer.ByValue meta);
 
     FfiResult.ByValue addPatternMeta(Pointer pattern, FfiPbPointer.ByValue meta);
@@ -267,8 +294,58 @@ public interface IrCoreLibrary extends Library {
 
     FfiResult.ByValue setSampleWeightVariable(Pointer sample, FfiPbPointer.ByValue weightVariable);
 
+    FfiResult.ByValue setSampleKeyVariable(Pointer sample, FfiPbPointer.ByValue keyVariable);
+
     FfiResult.ByValue appendSampleOperator(
             Pointer plan, Pointer sample, int parent, IntByReference oprIdx);
 
+    Pointer initAlgorithmOperator(FfiAlgorithmKind kind);
+
+    FfiResult.ByValue setAlgorithmParams(Pointer algorithm, Pointer params);
+
+    FfiResult.ByValue setAlgorithmMaxIterations(Pointer algorithm, int maxIterations);
+
+    FfiResult.ByValue setAlgorithmDamping(Pointer algorithm, double damping);
+
+    FfiResult.ByValue setAlgorithmGlobal(Pointer algorithm, boolean global);
+
+    FfiResult.ByValue setAlgorithmWeight(Pointer algorithm, String weight);
+
+    FfiResult.ByValue setAlgorithmSource(Pointer algorithm, String source);
+
+    FfiResult.ByValue setAlgorithmAlias(Pointer algorithm, FfiAlias.ByValue alias);
+
+    FfiResult.ByValue appendAlgorithmOperator(
+            Pointer plan, Pointer algorithm, int parent, IntByReference oprIdx);
+
+    Pointer initKHopOperator(FfiDirection direction, int hops, boolean within);
+
+    FfiResult.ByValue setKHopStartTag(Pointer kHop, FfiNameOrId.ByValue startTag);
+
+    FfiResult.ByValue setKHopParams(Pointer kHop, Pointer params);
+
+    FfiResult.ByValue setKHopAlias(Pointer kHop, FfiAlias.ByValue alias);
+
+    FfiResult.ByValue addKHopAggFn(
+            Pointer kHop, FfiVariable.ByValue aggVal, FfiAggOpt aggOpt, FfiAlias.ByValue alias);
+
+    FfiResult.ByValue appendKHopOperator(
+            Pointer plan, Pointer kHop, int parent, IntByReference oprIdx);
+
+    Pointer initMergeOperator(FfiMergeKind kind);
+
+    FfiResult.ByValue setMergeLabel(Pointer merge, FfiNameOrId.ByValue label);
+
+    FfiResult.ByValue setMergeEndpoints(
+            Pointer merge, FfiNameOrId.ByValue srcTag, FfiNameOrId.ByValue dstTag);
+
+    FfiResult.ByValue addMergeProperty(
+            Pointer merge, FfiMergeSet set, FfiNameOrId.ByValue key, String value);
+
+    FfiResult.ByValue setMergeAlias(Pointer merge, FfiAlias.ByValue alias);
+
+    FfiResult.ByValue appendMergeOperator(
+            Pointer plan, Pointer merge, int parent, IntByReference oprIdx);
+
     void destroyCstrPointer(Pointer cstr);
 }
@@ -27,7 +27,9 @@ public enum FfiAggOpt implements IntEnum<FfiAggOpt> {
     ToList,
     ToSet,
     Avg,
-    First;
+    First,
+    ApproxCountDistinct,
+    HeavyHitters;
 
     @Override
     public int getInt() {
@@ -0,0 +1,48 @@
+/*
+ * Copyright 2020 Alibaba Group Holding Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+package com.alibaba.graphscope.common.jna.type;
+
+import com.alibaba.graphscope.common.jna.IntEnum;
+
+public enum FfiAlgorithmKind implements IntEnum<FfiAlgorithmKind> {
+    /// PageRank along the out edges
+    PageRank,
+    /// Weakly connected components
+    Wcc,
+    /// Communities by label propagation
+    Lpa,
+    /// The number of triangles per vertex
+    TriangleCount,
+    /// The local clustering coefficient per vertex
+    ClusteringCoefficient,
+    /// The distance of the shortest path from the source vertices along the out edges
+    ShortestPath;
+
+    @Override
+    public int getInt() {
+        return this.ordinal();
+    }
+
+    @Override
+    public FfiAlgorithmKind getEnum(int i) {
+        FfiAlgorithmKind opts[] = values();
+        if (i < opts.length && i >= 0) {
+            return opts[i];
+        }
+        return null;
+    }
+}
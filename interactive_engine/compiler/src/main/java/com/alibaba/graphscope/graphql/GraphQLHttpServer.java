@@ -0,0 +1,314 @@
+/*
+ * Copyright 2020 Alibaba Group Holding Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+package com.alibaba.graphscope.graphql;
+
+import com.alibaba.graphscope.common.client.ExecutionClient;
+import com.alibaba.graphscope.common.client.type.ExecutionRequest;
+import com.alibaba.graphscope.common.client.type.ExecutionResponseListener;
+import com.alibaba.graphscope.common.config.Configs;
+import com.alibaba.graphscope.common.config.FrontendConfig;
+import com.alibaba.graphscope.common.config.PegasusConfig;
+import com.alibaba.graphscope.common.config.QueryTimeoutConfig;
+import com.alibaba.graphscope.common.ir.runtime.PhysicalPlan;
+import com.alibaba.graphscope.common.ir.tools.QueryIdGenerator;
+import com.alibaba.graphscope.common.jna.IrCoreLibrary;
+import com.alibaba.graphscope.common.jna.type.FfiData;
+import com.alibaba.graphscope.common.jna.type.FfiResult;
+import com.alibaba.graphscope.common.jna.type.ResultCode;
+import com.alibaba.graphscope.common.manager.IrMetaQueryCallback;
+import com.alibaba.graphscope.common.store.IrMeta;
+import com.alibaba.graphscope.gaia.proto.IrResult;
+import com.fasterxml.jackson.databind.JsonNode;
+import com.fasterxml.jackson.databind.ObjectMapper;
+import com.google.common.collect.ImmutableMap;
+import com.google.common.collect.Lists;
+import com.google.common.collect.Maps;
+import com.sun.jna.Pointer;
+import com.sun.net.httpserver.HttpExchange;
+import com.sun.net.httpserver.HttpServer;
+
+import org.apache.tinkerpop.gremlin.structure.io.graphson.GraphSONMapper;
+import org.apache.tinkerpop.gremlin.structure.io.graphson.GraphSONVersion;
+import org.slf4j.Logger;
+import org.slf4j.LoggerFactory;
+
+import java.io.IOException;
+import java.io.InputStream;
+import java.io.OutputStream;
+import java.math.BigInteger;
+import java.net.InetSocketAddress;
+import java.nio.charset.StandardCharsets;
+import java.util.List;
+import java.util.Map;
+import java.util.concurrent.CompletableFuture;
+import java.util.concurrent.ExecutorService;
+import java.util.concurrent.Executors;
+import java.util.concurrent.TimeUnit;
+
+/**
+ * serve the GraphQL read API derived from the graph schema, where each root field of a query is
+ * resolved into a plan by the ir core, see {@link IrCoreLibrary#resolveGraphql}, and its objects
+ * are sunk by the engine in GraphSON.
+ * <p>
+ * POST /graphql with a json body of {"query": .., "variables": {..}} responds
+ * {"data": {field: [..]}} or {"errors": [{"message": ..}]}, and GET /graphql/schema responds the
+ * schema in the schema definition language.
+ */
+public class GraphQLHttpServer implements AutoCloseable {
+    private static final Logger logger = LoggerFactory.getLogger(GraphQLHttpServer.class);
+    private static final IrCoreLibrary LIB = IrCoreLibrary.INSTANCE;
+    // the GraphQL queries are small, the larger bodies are rejected before they are parsed
+    private static final int MAX_BODY_BYTES = 1 << 20;
+
+    private final Configs configs;
+    private final QueryIdGenerator idGenerator;
+    private final IrMetaQueryCallback metaQueryCallback;
+    private final ExecutionClient executionClient;
+    private final ObjectMapper jsonMapper;
+    private final org.apache.tinkerpop.shaded.jackson.databind.ObjectMapper graphsonMapper;
+
+    private HttpServer httpServer;
+    private ExecutorService executor;
+
+    public GraphQLHttpServer(
+            Configs configs,
+            QueryIdGenerator idGenerator,
+            IrMetaQueryCallback metaQueryCallback,
+            ExecutionClient executionClient) {
+        this.configs = configs;
+        this.idGenerator = idGenerator;
+        this.metaQueryCallback = metaQueryCallback;
+        this.executionClient = executionClient;
+        this.jsonMapper = new ObjectMapper();
+        this.graphsonMapper =
+                GraphSONMapper.build().version(GraphSONVersion.V3_0).create().createMapper();
+    }
+
+    public void start() throws IOException {
+        int port = FrontendConfig.GRAPHQL_SERVER_PORT.get(configs);
+        this.httpServer = HttpServer.create(new InetSocketAddress(port), 0);
+        this.executor = Executors.newCachedThreadPool();
+        this.httpServer.setExecutor(this.executor);
+        this.httpServer.createContext("/graphql/schema", this::handleSchema);
+        this.httpServer.createContext("/graphql", this::handleQuery);
+        this.httpServer.start();
+        logger.info("graphql server started on port {}", port);
+    }
+
+    private void handleSchema(HttpExchange exchange) throws IOException {
+        try {
+            if (!"GET".equals(exchange.getRequestMethod())) {
+                respond(exchange, 405, "text/plain", "method not allowed");
+                return;
+            }
+            String sdl;
+            synchronized (LIB) {
+                IrMeta irMeta = metaQueryCallback.beforeExec();
+                try {
+                    checkFfiResult(LIB.setSchema(irMeta.getSchema().schemaJson()));
+                    FfiResult result = LIB.getGraphqlSchema();
+                    checkFfiResult(result);
+                    sdl = result.getMsg();
+                } finally {
+                    metaQueryCallback.afterExec(irMeta);
+                }
+            }
+            respond(exchange, 200, "text/plain", sdl);
+        } catch (Exception e) {
+            logger.error("get graphql schema fail", e);
+            respond(exchange, 500, "text/plain", String.valueOf(e.getMessage()));
+        } finally {
+            exchange.close();
+        }
+    }
+
+    private void handleQuery(HttpExchange exchange) throws IOException {
+        try {
+            if (!"/graphql".equals(exchange.getRequestURI().getPath())) {
+                respond(exchange, 404, "text/plain", "not found");
+                return;
+            }
+            if (!"POST".equals(exchange.getRequestMethod())) {
+                respond(exchange, 405, "text/plain", "method not allowed");
+                return;
+            }
+            byte[] body = readBody(exchange.getRequestBody());
+            if (body == null) {
+                respond(exchange, 413, "text/plain", "request body is too large");
+                return;
+            }
+            Object response;
+            try {
+                JsonNode request = jsonMapper.readTree(body);
+                JsonNode query = request.get("query");
+                if (query == null || !query.isTextual()) {
+                    throw new IllegalArgumentException("the query of the request is not given");
+                }
+                JsonNode variables = request.get("variables");
+                String variablesJson =
+                        (variables == null || variables.isNull()) ? "" : variables.toString();
+                response = ImmutableMap.of("data", execute(query.asText(), variablesJson));
+            } catch (Exception e) {
+                logger.error("execute graphql query fail", e);
+                Map<String, Object> error =
+                        ImmutableMap.of("message", String.valueOf(e.getMessage()));
+                response = ImmutableMap.of("errors", Lists.newArrayList(error));
+            }
+            respond(exchange, 200, "application/json", jsonMapper.writeValueAsString(response));
+        } finally {
+            exchange.close();
+        }
+    }
+
+    // resolve and execute the root fields of the query one by one, in the order of the query
+    private Map<String, Object> execute(String query, String variables) throws Exception {
+        Map<String, Object> data = Maps.newLinkedHashMap();
+        for (int index = 0; ; ++index) {
+            String field;
+            byte[] physicalBytes;
+            synchronized (LIB) {
+                IrMeta irMeta = metaQueryCallback.beforeExec();
+                Pointer ptrPlan = null;
+                try {
+                    checkFfiResult(LIB.setSchema(irMeta.getSchema().schemaJson()));
+                    ptrPlan = LIB.initLogicalPlan();
+                    FfiResult result = LIB.resolveGraphql(query, variables, index, ptrPlan);
+                    checkFfiResult(result);
+                    field = result.getMsg();
+                    if (field.isEmpty()) {
+                        return data;
+                    }
+                    physicalBytes = buildPhysicalPlan(ptrPlan);
+                } finally {
+                    if (ptrPlan != null) {
+                        LIB.destroyLogicalPlan(ptrPlan);
+                    }
+                    metaQueryCallback.afterExec(irMeta);
+                }
+            }
+            data.put(field, submit(physicalBytes));
+        }
+    }
+
+    private byte[] buildPhysicalPlan(Pointer ptrPlan) {
+        // hack way to notify shuffle
+        int servers = PegasusConfig.PEGASUS_HOSTS.get(configs).split(",").length;
+        int workers = PegasusConfig.PEGASUS_WORKER_NUM.get(configs);
+        FfiData.ByValue buffer = LIB.buildPhysicalPlan(ptrPlan, workers, servers);
+        try {
+            checkFfiResult(buffer.error);
+            return buffer.getBytes();
+        } finally {
+            buffer.close();
+        }
+    }
+
+    private List<Object> submit(byte[] physicalBytes) throws Exception {
+        BigInteger queryId = idGenerator.generateId();
+        String queryName = idGenerator.generateName(queryId);
+        ExecutionRequest request =
+                new ExecutionRequest(
+                        queryId,
+                        queryName,
+                        null,
+                        new PhysicalPlan(physicalBytes, ""),
+                        null,
+                        true);
+        List<Object> objects = Lists.newArrayList();
+        CompletableFuture<List<Object>> future = new CompletableFuture<>();
+        long timeoutMs = FrontendConfig.QUERY_EXECUTION_TIMEOUT_MS.get(configs);
+        executionClient.submit(
+                request,
+                new ExecutionResponseListener() {
+                    @Override
+                    public void onNext(IrResult.Record record) {
+                        throw new UnsupportedOperationException(
+                                "the results of graphql are encoded in graphson");
+                    }
+
+                    @Override
+                    public void onResponse(byte[] response) {
+                        try {
+                            synchronized (objects) {
+                                objects.add(graphsonMapper.readValue(response, Object.class));
+                            }
+                        } catch (IOException e) {
+                            future.completeExceptionally(e);
+                        }
+                    }
+
+                    @Override
+                    public void onCompleted() {
+                        future.complete(objects);
+                    }
+
+                    @Override
+                    public void onError(Throwable t) {
+                        future.completeExceptionally(t);
+                    }
+                },
+                new QueryTimeoutConfig(timeoutMs));
+        return future.get(timeoutMs, TimeUnit.MILLISECONDS);
+    }
+
+    // read the body up to MAX_BODY_BYTES, or return null if it is larger
+    private static byte[] readBody(InputStream input) throws IOException {
+        byte[] body = new byte[MAX_BODY_BYTES + 1];
+        int length = 0;
+        int read;
+        while (length < body.length
+                && (read = input.read(body, length, body.length - length)) != -1) {
+            length += read;
+        }
+        if (length > MAX_BODY_BYTES) {
+            return null;
+        }
+        byte[] result = new byte[length];
+        System.arraycopy(body, 0, result, 0, length);
+        return result;
+    }
+
+    private static void respond(HttpExchange exchange, int code, String contentType, String body)
+            throws IOException {
+        byte[] bytes = body.getBytes(StandardCharsets.UTF_8);
+        exchange.getResponseHeaders().set("Content-Type", contentType + "; charset=utf-8");
+        exchange.sendResponseHeaders(code, bytes.length);
+        try (OutputStream output = exchange.getResponseBody()) {
+            output.write(bytes);
+        }
+    }
+
+    private static void checkFfiResult(FfiResult result) {
+        if (result == null || result.code != ResultCode.Success) {
+            throw new IllegalStateException(
+                    "call libc returns "
+                            + (result == null ? "null" : result.code.name())
+                            + ", msg is "
+                            + (result == null ? "" : result.getMsg()));
+        }
+    }
+
+    @Override
+    public void close() {
+        if (this.httpServer != null) {
+            this.httpServer.stop(0);
+        }
+        if (this.executor != null) {
+            this.executor.shutdown();
+        }
+    }
+}
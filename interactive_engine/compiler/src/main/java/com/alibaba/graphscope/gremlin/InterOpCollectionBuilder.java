@@ -31,6 +31,7 @@ import com.alibaba.graphscope.gremlin.transform.TraversalParentTransformFactory;
 import org.apache.tinkerpop.gremlin.process.traversal.Step;
 import org.apache.tinkerpop.gremlin.process.traversal.Traversal;
 import org.apache.tinkerpop.gremlin.process.traversal.step.TraversalParent;
+import org.apache.tinkerpop.gremlin.process.traversal.step.branch.BranchStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.branch.UnionStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.filter.*;
 import org.apache.tinkerpop.gremlin.process.traversal.step.map.*;
@@ -94,6 +95,9 @@ public class InterOpCollectionBuilder {
                 opList.add(StepTransformFactory.WHERE_END_STEP.apply(step));
             } else if (Utils.equalClass(step, UnionStep.class)) {
                 opList.add(StepTransformFactory.UNION_STEP.apply(step));
+            } else if (Utils.equalClass(step, BranchStep.class)) {
+                opList.addAll(
+                        TraversalParentTransformFactory.BRANCH_STEP.apply((TraversalParent) step));
             } else if (Utils.equalClass(step, TraversalMapStep.class)) {
                 opList.add(StepTransformFactory.TRAVERSAL_MAP_STEP.apply(step));
             } else if (Utils.equalClass(step, SelectOneStep.class)) {
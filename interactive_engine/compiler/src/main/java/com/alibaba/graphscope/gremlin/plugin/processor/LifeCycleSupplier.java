@@ -19,6 +19,7 @@ package com.alibaba.graphscope.gremlin.plugin.processor;
 import com.alibaba.graphscope.common.client.ExecutionClient;
 import com.alibaba.graphscope.common.client.type.ExecutionRequest;
 import com.alibaba.graphscope.common.config.Configs;
+import com.alibaba.graphscope.common.config.FrontendConfig;
 import com.alibaba.graphscope.common.config.QueryTimeoutConfig;
 import com.alibaba.graphscope.common.ir.tools.GraphPlanner;
 import com.alibaba.graphscope.common.ir.tools.QueryCache;
@@ -30,8 +31,14 @@ import com.alibaba.graphscope.gremlin.resultx.GremlinResultProcessor;
 import com.alibaba.graphscope.gremlin.resultx.ResultSchema;
 import com.google.common.base.Preconditions;
 
+import io.netty.buffer.ByteBuf;
+import io.netty.buffer.ByteBufUtil;
+
+import org.apache.tinkerpop.gremlin.driver.MessageSerializer;
+import org.apache.tinkerpop.gremlin.driver.ser.GraphBinaryMessageSerializerV1;
 import org.apache.tinkerpop.gremlin.groovy.engine.GremlinExecutor;
 import org.apache.tinkerpop.gremlin.server.Context;
+import org.apache.tinkerpop.gremlin.server.handler.StateKey;
 
 import java.math.BigInteger;
 import java.util.List;
@@ -102,7 +109,12 @@ public class LifeCycleSupplier implements Supplier<GremlinExecutor.LifeCycle> {
                                                 resultSchema,
                                                 statusCallback,
                                                 timeoutConfig);
-                                if (value.result != null && value.result.isCompleted) {
+                                // the results grouped are reduced into a single map by the listener
+                                byte[] resource =
+                                        resultSchema.isGroupBy ? null : getGraphBinaryRequest();
+                                if (resource == null
+                                        && value.result != null
+                                        && value.result.isCompleted) {
                                     List<IrResult.Results> records = value.result.records;
                                     records.forEach(k -> listener.onNext(k.getRecord()));
                                     listener.onCompleted();
@@ -112,7 +124,8 @@ public class LifeCycleSupplier implements Supplier<GremlinExecutor.LifeCycle> {
                                                     queryId,
                                                     queryName,
                                                     summary.getLogicalPlan(),
-                                                    summary.getPhysicalPlan()),
+                                                    summary.getPhysicalPlan(),
+                                                    resource),
                                             listener,
                                             timeoutConfig);
                                 }
@@ -124,4 +137,26 @@ public class LifeCycleSupplier implements Supplier<GremlinExecutor.LifeCycle> {
                         })
                 .create();
     }
+
+    // the request of the client in GraphBinary, whose results are encoded by the engine, or null if
+    // the results are encoded by the serializer of the client
+    private byte[] getGraphBinaryRequest() throws Exception {
+        if (!FrontendConfig.GREMLIN_GRAPHBINARY_PASSTHROUGH_ENABLED.get(configs)) {
+            return null;
+        }
+        MessageSerializer serializer =
+                ctx.getChannelHandlerContext().channel().attr(StateKey.SERIALIZER).get();
+        if (!(serializer instanceof GraphBinaryMessageSerializerV1)) {
+            return null;
+        }
+        ByteBuf request =
+                ((GraphBinaryMessageSerializerV1) serializer)
+                        .serializeRequestAsBinary(
+                                ctx.getRequestMessage(), ctx.getChannelHandlerContext().alloc());
+        try {
+            return ByteBufUtil.getBytes(request);
+        } finally {
+            request.release();
+        }
+    }
 }
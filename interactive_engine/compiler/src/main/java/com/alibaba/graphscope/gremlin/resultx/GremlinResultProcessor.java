@@ -28,10 +28,12 @@ import com.google.common.collect.Lists;
 import com.google.common.collect.Maps;
 
 import io.grpc.Status;
+import io.netty.buffer.Unpooled;
 
 import org.apache.tinkerpop.gremlin.driver.message.ResponseMessage;
 import org.apache.tinkerpop.gremlin.driver.message.ResponseStatusCode;
 import org.apache.tinkerpop.gremlin.server.Context;
+import org.apache.tinkerpop.gremlin.server.handler.Frame;
 
 import java.util.List;
 import java.util.Map;
@@ -72,6 +74,13 @@ public class GremlinResultProcessor implements ExecutionResponseListener {
         }
     }
 
+    @Override
+    public void onResponse(byte[] response) {
+        // the partial results encoded in GraphBinary by the engine, which precede the final
+        // response written in finishRecord()
+        ctx.getChannelHandlerContext().writeAndFlush(new Frame(Unpooled.wrappedBuffer(response)));
+    }
+
     @Override
     public void onCompleted() {
         try {
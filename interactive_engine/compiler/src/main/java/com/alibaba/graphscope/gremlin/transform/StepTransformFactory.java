@@ -18,6 +18,7 @@ package com.alibaba.graphscope.gremlin.transform;
 
 import com.alibaba.graphscope.common.exception.OpArgIllegalException;
 import com.alibaba.graphscope.common.intermediate.ArgAggFn;
+import com.alibaba.graphscope.common.intermediate.ArgElementMap;
 import com.alibaba.graphscope.common.intermediate.ArgUtils;
 import com.alibaba.graphscope.common.intermediate.InterOpCollection;
 import com.alibaba.graphscope.common.intermediate.MatchSentence;
@@ -197,6 +198,18 @@ public enum StepTransformFactory implements Function<Step, InterOpBase> {
         @Override
         public InterOpBase apply(Step step) {
             ProjectOp op = new ProjectOp();
+            if (step instanceof ElementMapStep) { // elementMap(..)
+                String[] mapKeys = ((ElementMapStep) step).getPropertyKeys();
+                ArgElementMap elementMap = new ArgElementMap("", Arrays.asList(mapKeys));
+                op.setExprWithAlias(
+                        new OpArg<>(
+                                elementMap,
+                                (ArgElementMap map) -> {
+                                    FfiAlias.ByValue alias = ArgUtils.asNoneAlias();
+                                    return Arrays.asList(Pair.with(map, alias));
+                                }));
+                return op;
+            }
             String expr =
                     TraversalParentTransformFactory.PROJECT_BY_STEP
                             .getSubTraversalAsExpr((new ExprArg(Collections.singletonList(step))))
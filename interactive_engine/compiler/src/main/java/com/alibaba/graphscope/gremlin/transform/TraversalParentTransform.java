@@ -223,6 +223,20 @@ public interface TraversalParentTransform extends Function<TraversalParent, List
         }
     }
 
+    // has('name') -> where(values('name')), hasNot('name') -> not(properties('name')), which filter
+    // by the existence of the property rather than its value, i.e., 'exists @.name'
+    default Optional<String> getExistsExpr(Traversal.Admin subTraversal) {
+        if (subTraversal != null
+                && subTraversal.getSteps().size() == 1
+                && subTraversal.getStartStep() instanceof PropertiesStep) {
+            String[] keys = ((PropertiesStep) subTraversal.getStartStep()).getPropertyKeys();
+            if (keys.length == 1) {
+                return Optional.of("exists @." + keys[0]);
+            }
+        }
+        return Optional.empty();
+    }
+
     // @ -> as_none_var
     // @a -> as_var_tag_only("a")
     // @.name -> as_var_property_only("name")
@@ -20,6 +20,7 @@ import com.alibaba.graphscope.common.exception.OpArgIllegalException;
 import com.alibaba.graphscope.common.intermediate.ArgAggFn;
 import com.alibaba.graphscope.common.intermediate.ArgUtils;
 import com.alibaba.graphscope.common.intermediate.ArgVariable;
+import com.alibaba.graphscope.common.intermediate.InterOpCollection;
 import com.alibaba.graphscope.common.intermediate.operator.*;
 import com.alibaba.graphscope.common.jna.type.*;
 import com.alibaba.graphscope.gremlin.InterOpCollectionBuilder;
@@ -34,12 +35,15 @@ import com.google.common.collect.Lists;
 
 import org.apache.tinkerpop.gremlin.process.traversal.*;
 import org.apache.tinkerpop.gremlin.process.traversal.lambda.IdentityTraversal;
+import org.apache.tinkerpop.gremlin.process.traversal.lambda.PredicateTraversal;
 import org.apache.tinkerpop.gremlin.process.traversal.step.TraversalParent;
+import org.apache.tinkerpop.gremlin.process.traversal.step.branch.BranchStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.filter.*;
 import org.apache.tinkerpop.gremlin.process.traversal.step.map.CountGlobalStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.map.FoldStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.map.OrderGlobalStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.map.SelectOneStep;
+import org.apache.tinkerpop.gremlin.process.traversal.step.map.SelectStep;
 import org.apache.tinkerpop.gremlin.process.traversal.step.util.EmptyStep;
 import org.apache.tinkerpop.gremlin.process.traversal.util.ConnectiveP;
 import org.apache.tinkerpop.gremlin.process.traversal.util.TraversalHelper;
@@ -110,6 +114,8 @@ public enum TraversalParentTransformFactory implements TraversalParentTransform
             }
             ProjectOp op = new ProjectOp();
             op.setExprWithAlias(new OpArg(projectExprWithAlias));
+            // select(..) filters the traversers missing any of the tags, while project(..) does not
+            op.setSelectTags(parent instanceof SelectStep || parent instanceof SelectOneStep);
             interOpList.add(op);
             return interOpList;
         }
@@ -559,7 +565,9 @@ public enum TraversalParentTransformFactory implements TraversalParentTransform
                         "each where(..) is corresponding to exact one expression, multiple filter"
                                 + " conditions should be defined in different wheres");
             }
-            Optional<String> singleExpr = exprRes.getSingleExpr();
+            Optional<String> existsExpr = getExistsExpr(subTraversal);
+            Optional<String> singleExpr =
+                    existsExpr.isPresent() ? existsExpr : exprRes.getSingleExpr();
             if (singleExpr.isPresent()) {
                 String expr = singleExpr.get();
                 SelectOp selectOp = new SelectOp();
@@ -600,7 +608,9 @@ public enum TraversalParentTransformFactory implements TraversalParentTransform
                         "each not(..) is corresponding to exact one expression, "
                                 + "multiple anti conditions should be defined in different nos");
             }
-            Optional<String> singleExpr = exprRes.getSingleExpr();
+            Optional<String> existsExpr = getExistsExpr(subTraversal);
+            Optional<String> singleExpr =
+                    existsExpr.isPresent() ? existsExpr : exprRes.getSingleExpr();
             if (singleExpr.isPresent()) { // not(select("a").by("name"))
                 String notExpr = getNotExpr(singleExpr.get());
                 SelectOp selectOp = new SelectOp();
@@ -633,5 +643,84 @@ public enum TraversalParentTransformFactory implements TraversalParentTransform
                     ? String.format("!(%s)", expr)
                     : "!" + expr;
         }
+    },
+    // branch(..).option(..) is the union of its options, each of which filters by its pick token
+    // on the value of the branch traversal, e.g.,
+    // branch(values('age')).option(29, out()).option(Pick.none, in()) is
+    // union(where(@.age == 29).out(), where(!(@.age == 29)).in())
+    BRANCH_STEP {
+        @Override
+        public List<InterOpBase> apply(TraversalParent parent) {
+            BranchStep branchStep = (BranchStep) parent;
+            Traversal.Admin branchTraversal =
+                    (Traversal.Admin) branchStep.getLocalChildren().get(0);
+            Optional<String> branchExpr =
+                    getSubTraversalAsExpr(new ExprArg(branchTraversal)).getSingleExpr();
+            if (!branchExpr.isPresent()) {
+                throw new OpArgIllegalException(
+                        OpArgIllegalException.Cause.UNSUPPORTED_TYPE,
+                        "branch(..) is unsupported if the branch traversal is not an expression");
+            }
+            Map<Pick, List<Traversal.Admin>> pickOptions =
+                    Utils.getFieldValue(BranchStep.class, branchStep, "traversalPickOptions");
+            List<Pair<Traversal.Admin, Traversal.Admin>> options =
+                    Utils.getFieldValue(BranchStep.class, branchStep, "traversalOptions");
+            List<InterOpCollection> branches = new ArrayList<>();
+            List<String> tokenExprs = new ArrayList<>();
+            for (Pair<Traversal.Admin, Traversal.Admin> option : options) {
+                String tokenExpr = getPickTokenExpr(branchExpr.get(), option.getValue0());
+                tokenExprs.add(tokenExpr);
+                branches.add(filterOption(tokenExpr, option.getValue1()));
+            }
+            for (Traversal.Admin option : pickOptions.getOrDefault(Pick.any, new ArrayList<>())) {
+                branches.add((new InterOpCollectionBuilder(option)).build());
+            }
+            // the none options are taken by the records matching none of the pick tokens
+            String noneExpr =
+                    tokenExprs.stream()
+                            .map(k -> String.format("!(%s)", k))
+                            .collect(Collectors.joining(" && "));
+            for (Traversal.Admin option : pickOptions.getOrDefault(Pick.none, new ArrayList<>())) {
+                branches.add(
+                        tokenExprs.isEmpty()
+                                ? (new InterOpCollectionBuilder(option)).build()
+                                : filterOption(noneExpr, option));
+            }
+            if (branches.isEmpty()) {
+                throw new OpArgIllegalException(
+                        OpArgIllegalException.Cause.INVALID_TYPE,
+                        "branch(..) should have at least one option");
+            }
+            UnionOp unionOp = new UnionOp();
+            unionOp.setSubOpCollectionList(new OpArg(branches, Function.identity()));
+            return Collections.singletonList(unionOp);
+        }
+
+        // the pick token is a value or a predicate, e.g., option(29, ..) or option(gt(29), ..)
+        private String getPickTokenExpr(String branchExpr, Traversal.Admin tokenTraversal) {
+            if (tokenTraversal instanceof PredicateTraversal) {
+                Object predicate =
+                        Utils.getFieldValue(PredicateTraversal.class, tokenTraversal, "predicate");
+                if (predicate instanceof P) {
+                    return PredicateExprTransformFactory.IS_STEP.flatPredicate(
+                            branchExpr, (P) predicate);
+                }
+            }
+            throw new OpArgIllegalException(
+                    OpArgIllegalException.Cause.UNSUPPORTED_TYPE,
+                    "the pick token of option(..) should be a value or a predicate");
+        }
+
+        private InterOpCollection filterOption(String expr, Traversal.Admin option) {
+            SelectOp selectOp = new SelectOp();
+            selectOp.setPredicate(new OpArg(expr));
+            InterOpCollection opCollection = new InterOpCollection();
+            opCollection.appendInterOp(selectOp);
+            (new InterOpCollectionBuilder(option))
+                    .build()
+                    .unmodifiableCollection()
+                    .forEach(opCollection::appendInterOp);
+            return opCollection;
+        }
     }
 }
@@ -16,6 +16,7 @@
 
 package com.alibaba.graphscope.gremlin;
 
+import com.alibaba.graphscope.common.intermediate.ArgElementMap;
 import com.alibaba.graphscope.common.intermediate.ArgUtils;
 import com.alibaba.graphscope.common.intermediate.operator.ProjectOp;
 import com.alibaba.graphscope.gremlin.transform.StepTransformFactory;
@@ -29,6 +30,8 @@ import org.javatuples.Pair;
 import org.junit.Assert;
 import org.junit.Test;
 
+import java.util.Arrays;
+import java.util.Collections;
 import java.util.List;
 
 public class ValueMapTest {
@@ -57,6 +60,31 @@ public class ValueMapTest {
         Assert.assertEquals(ArgUtils.asNoneAlias(), exprWithAlias.get(0).getValue1());
     }
 
+    @Test
+    public void g_V_elementMap_test() {
+        Traversal traversal = g.V().elementMap();
+        Step elementMapStep = traversal.asAdmin().getEndStep();
+        ProjectOp op = (ProjectOp) StepTransformFactory.VALUES_STEP.apply(elementMapStep);
+
+        List<Pair> exprWithAlias = (List<Pair>) op.getExprWithAlias().get().applyArg();
+        Assert.assertEquals(
+                new ArgElementMap("", Collections.emptyList()), exprWithAlias.get(0).getValue0());
+        Assert.assertEquals(ArgUtils.asNoneAlias(), exprWithAlias.get(0).getValue1());
+    }
+
+    @Test
+    public void g_V_elementMap_strs_test() {
+        Traversal traversal = g.V().elementMap("name", "id");
+        Step elementMapStep = traversal.asAdmin().getEndStep();
+        ProjectOp op = (ProjectOp) StepTransformFactory.VALUES_STEP.apply(elementMapStep);
+
+        List<Pair> exprWithAlias = (List<Pair>) op.getExprWithAlias().get().applyArg();
+        Assert.assertEquals(
+                new ArgElementMap("", Arrays.asList("name", "id")),
+                exprWithAlias.get(0).getValue0());
+        Assert.assertEquals(ArgUtils.asNoneAlias(), exprWithAlias.get(0).getValue1());
+    }
+
     @Test
     public void g_V_values_str_test() {
         Traversal traversal = g.V().values("name");
@@ -0,0 +1,84 @@
+/*
+ * Copyright 2020 Alibaba Group Holding Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+package com.alibaba.graphscope.gremlin.subtask;
+
+import com.alibaba.graphscope.common.intermediate.InterOpCollection;
+import com.alibaba.graphscope.common.intermediate.operator.InterOpBase;
+import com.alibaba.graphscope.common.intermediate.operator.SelectOp;
+import com.alibaba.graphscope.common.intermediate.operator.UnionOp;
+import com.alibaba.graphscope.gremlin.integration.suite.utils.__;
+import com.alibaba.graphscope.gremlin.plugin.processor.IrStandardOpProcessor;
+import com.alibaba.graphscope.gremlin.transform.TraversalParentTransformFactory;
+
+import org.apache.tinkerpop.gremlin.process.traversal.P;
+import org.apache.tinkerpop.gremlin.process.traversal.Pick;
+import org.apache.tinkerpop.gremlin.process.traversal.Traversal;
+import org.apache.tinkerpop.gremlin.process.traversal.dsl.graph.GraphTraversalSource;
+import org.apache.tinkerpop.gremlin.process.traversal.step.TraversalParent;
+import org.apache.tinkerpop.gremlin.structure.Graph;
+import org.apache.tinkerpop.gremlin.tinkergraph.structure.TinkerFactory;
+import org.junit.Assert;
+import org.junit.Test;
+
+import java.util.List;
+
+public class BranchStepTest {
+    private Graph graph = TinkerFactory.createModern();
+    private GraphTraversalSource g = graph.traversal();
+
+    private List<InterOpCollection> getBranches(Traversal traversal) {
+        IrStandardOpProcessor.applyStrategies(traversal);
+        TraversalParent parent = (TraversalParent) traversal.asAdmin().getEndStep();
+        List<InterOpBase> ops = TraversalParentTransformFactory.BRANCH_STEP.apply(parent);
+        Assert.assertEquals(1, ops.size());
+        UnionOp unionOp = (UnionOp) ops.get(0);
+        return (List<InterOpCollection>) unionOp.getSubOpCollectionList().get().applyArg();
+    }
+
+    private String getFilter(InterOpCollection branch) {
+        SelectOp selectOp = (SelectOp) branch.unmodifiableCollection().get(0);
+        return (String) selectOp.getPredicate().get().applyArg();
+    }
+
+    @Test
+    public void g_V_branch_values_option_none() {
+        Traversal traversal =
+                g.V().branch(__.values("age"))
+                        .option(29, __.out())
+                        .option(P.gt(30), __.in())
+                        .option(Pick.none, __.values("name"));
+        List<InterOpCollection> branches = getBranches(traversal);
+
+        Assert.assertEquals(3, branches.size());
+        Assert.assertEquals("@.age == 29", getFilter(branches.get(0)));
+        Assert.assertEquals(2, branches.get(0).unmodifiableCollection().size());
+        Assert.assertEquals("@.age > 30", getFilter(branches.get(1)));
+        Assert.assertEquals("!(@.age == 29) && !(@.age > 30)", getFilter(branches.get(2)));
+    }
+
+    @Test
+    public void g_V_branch_label_option_any() {
+        Traversal traversal =
+                g.V().branch(__.label()).option("person", __.out()).option(Pick.any, __.in());
+        List<InterOpCollection> branches = getBranches(traversal);
+
+        Assert.assertEquals(2, branches.size());
+        Assert.assertEquals("@.~label == \"person\"", getFilter(branches.get(0)));
+        // the any option is taken by all the records
+        Assert.assertEquals(1, branches.get(1).unmodifiableCollection().size());
+    }
+}
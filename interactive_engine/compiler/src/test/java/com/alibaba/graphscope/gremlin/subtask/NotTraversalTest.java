@@ -50,7 +50,15 @@ public class NotTraversalTest {
         Traversal traversal = g.V().not(__.values("name"));
         SelectOp selectOp = (SelectOp) getApplyOrSelect(traversal).get(0);
 
-        Assert.assertEquals("!@.name", selectOp.getPredicate().get().applyArg());
+        Assert.assertEquals("!exists @.name", selectOp.getPredicate().get().applyArg());
+    }
+
+    @Test
+    public void g_V_hasNot_key() {
+        Traversal traversal = g.V().hasNot("name");
+        SelectOp selectOp = (SelectOp) getApplyOrSelect(traversal).get(0);
+
+        Assert.assertEquals("!exists @.name", selectOp.getPredicate().get().applyArg());
     }
 
     @Test
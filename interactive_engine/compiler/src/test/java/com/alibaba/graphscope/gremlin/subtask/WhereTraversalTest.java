@@ -50,7 +50,15 @@ public class WhereTraversalTest {
         Traversal traversal = g.V().where(__.values("name"));
         SelectOp selectOp = (SelectOp) getApplyOrSelect(traversal).get(0);
 
-        Assert.assertEquals("@.name", selectOp.getPredicate().get().applyArg());
+        Assert.assertEquals("exists @.name", selectOp.getPredicate().get().applyArg());
+    }
+
+    @Test
+    public void g_V_has_key() {
+        Traversal traversal = g.V().has("name");
+        SelectOp selectOp = (SelectOp) getApplyOrSelect(traversal).get(0);
+
+        Assert.assertEquals("exists @.name", selectOp.getPredicate().get().applyArg());
     }
 
     @Test
@@ -20,6 +20,7 @@ use std::sync::Arc;
 
 use groot_store::db::api::GraphConfigBuilder;
 use groot_store::db::common::bytes::util::parse_pb;
+use groot_store::db::graph::partition::PartitionRouting;
 use groot_store::db::graph::store::GraphStore;
 use groot_store::db::proto::model::ConfigPb;
 use pegasus_network::config::ServerAddr;
@@ -57,6 +58,89 @@ pub extern "C" fn updatePartitionRouting(engine_handle: EngineHandle, partition_
     engine_ptr.update_partition_routing(partition_id as u32, server_id as u32);
 }
 
+#[no_mangle]
+pub extern "C" fn addFollowerPartition(
+    engine_handle: EngineHandle, partition_id: i32, graph_handle: GraphHandle,
+) {
+    trace!("add follower partition {} to engine", partition_id);
+    let engine_ptr = unsafe { &*(engine_handle as *const GaiaServer) };
+    let graph_ptr = unsafe { Arc::from_raw(&*(graph_handle as *const GraphStore)) };
+    engine_ptr.add_follower_partition(partition_id as u32, graph_ptr);
+}
+
+#[no_mangle]
+pub extern "C" fn removeFollowerPartition(engine_handle: EngineHandle, partition_id: i32) {
+    trace!("remove follower partition {} from engine", partition_id);
+    let engine_ptr = unsafe { &*(engine_handle as *const GaiaServer) };
+    engine_ptr.remove_follower_partition(partition_id as u32);
+}
+
+/// Update the staleness of the follower of the partition on the server, which is removed if
+/// `staleness_ms` is negative.
+#[no_mangle]
+pub extern "C" fn updatePartitionReplica(
+    engine_handle: EngineHandle, partition_id: i32, server_id: i32, staleness_ms: i64,
+) {
+    trace!("update replica of partition {} on server {}", partition_id, server_id);
+    let engine_ptr = unsafe { &*(engine_handle as *const GaiaServer) };
+    let staleness_ms = if staleness_ms < 0 { None } else { Some(staleness_ms as u64) };
+    engine_ptr.update_partition_replica(partition_id as u32, server_id as u32, staleness_ms);
+}
+
+#[no_mangle]
+pub extern "C" fn removePartition(engine_handle: EngineHandle, partition_id: i32) {
+    trace!("remove partition {} from engine", partition_id);
+    let engine_ptr = unsafe { &*(engine_handle as *const GaiaServer) };
+    engine_ptr.remove_partition(partition_id as u32);
+}
+
+/// Switch the partition routing in json, see `PartitionRouting::from_json`; return false if it's
+/// invalid or stale.
+#[no_mangle]
+pub extern "C" fn switchPartitionRouting(engine_handle: EngineHandle, routing_json: *const c_char) -> bool {
+    let slice = unsafe { CStr::from_ptr(routing_json) }.to_bytes();
+    let routing = match std::str::from_utf8(slice)
+        .map_err(|e| format!("{}", e))
+        .and_then(|json| PartitionRouting::from_json(json).map_err(|e| format!("{:?}", e)))
+    {
+        Ok(routing) => routing,
+        Err(e) => {
+            error!("switch partition routing failed: {}", e);
+            return false;
+        }
+    };
+    let engine_ptr = unsafe { &*(engine_handle as *const GaiaServer) };
+    engine_ptr.switch_partition_routing(routing)
+}
+
+/// Replicate the ddl batch written at `snapshot_id` to all the store nodes by the meta raft group, see
+/// `store.raft.peers`; return false if it's not committed, e.g. this node is not the leader.
+#[no_mangle]
+pub extern "C" fn proposeDdl(
+    engine_handle: EngineHandle, snapshot_id: i64, data: *const u8, len: usize,
+) -> bool {
+    let engine_ptr = unsafe { &*(engine_handle as *const GaiaServer) };
+    let batch = unsafe { ::std::slice::from_raw_parts(data, len) }.to_vec();
+    match engine_ptr.propose_ddl(snapshot_id, batch) {
+        Ok(_) => true,
+        Err(e) => {
+            error!("propose ddl at snapshot {} failed: {:?}", snapshot_id, e);
+            false
+        }
+    }
+}
+
+/// The server which the partition should be placed on by consistent hashing, or -1 if partitions are
+/// routed by the routing table, i.e. `gaia.routing.virtual.nodes` is not set.
+#[no_mangle]
+pub extern "C" fn getPartitionServer(engine_handle: EngineHandle, partition_id: i32) -> i32 {
+    let engine_ptr = unsafe { &*(engine_handle as *const GaiaServer) };
+    engine_ptr
+        .get_partition_server(partition_id as u32)
+        .map(|server_id| server_id as i32)
+        .unwrap_or(-1)
+}
+
 #[no_mangle]
 pub extern "C" fn startEngine(engine_handle: EngineHandle) -> Box<EnginePortsResponse> {
     trace!("start gaia engine");
@@ -15,29 +15,81 @@
 //!
 
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "kafka")]
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
 use gaia_pegasus::Configuration as GaiaConfig;
-use global_query::GlobalGraph;
+use global_query::degree::{DegreeReportConfig, DegreeReporter};
+use global_query::export::subgraph::{SubgraphExtractor, SubgraphSelection, MANIFEST_FILE_NAME};
+use global_query::export::ExportFormat;
+use global_query::{GlobalGraph, GlobalGraphQuery};
+use graph_proxy::apis::PurgeHandle;
+use graph_proxy::utils::hash_ring::HashRing;
 use graph_proxy::{apis::PegasusClusterInfo, create_gs_store, GrootMultiPartition};
 use groot_store::api::PartitionId;
-use groot_store::db::api::{GraphConfig, GraphResult};
+use groot_store::db::api::{GraphConfig, GraphError, GraphErrorCode, GraphResult, MAX_SI};
+use groot_store::db::consensus::meta::DdlApplier;
+use groot_store::db::consensus::replica::MetaReplica;
+use groot_store::db::graph::partition::PartitionRouting;
 use groot_store::db::graph::store::GraphStore;
-use pegasus_network::config::{NetworkConfig, ServerAddr};
+#[cfg(feature = "kafka")]
+use groot_store::db::import::kafka::{KafkaIngestConfig, KafkaIngestor, RecordFormat};
+#[cfg(feature = "kafka")]
+use groot_store::db::import::record_mapping::RecordMapping;
+use groot_store::db::service::neighbor_sampling::{NeighborSamplingConfig, NeighborSamplingServer};
+use groot_store::db::service::stream_write::{StreamWriteConfig, StreamWriteServer};
+use pegasus_network::config::{NetworkConfig, ServerAddr, TlsConfig};
 use pegasus_network::SimpleServerDetector;
-use pegasus_server::rpc::{start_all, RPCServerConfig, ServiceStartListener};
+use pegasus_server::admin::ExportQuery;
+use pegasus_server::advisor::AdvisorConfig;
+use pegasus_server::rpc::{start_all, RPCServerConfig, RpcTlsConfig, ServiceStartListener};
+use runtime::extension::{register_extensions, register_standing_queries, start_purge_by, start_triggers};
 use runtime::initialize_job_assembly;
+use runtime::procedure::ProcedureRegistry;
+use runtime::process::operator::dedup_filter::{DedupFilter, DedupSpill};
+use runtime::process::operator::prefetch_expand::{ExpandPrefetch, PrefetchPool};
+use runtime::process::operator::split_expand::ExpandSplit;
+use runtime::session::SessionRegistry;
+use runtime::standing::StandingQueries;
+use runtime::trigger::{RetryHandle, TriggerRegistry};
 use tokio::runtime::Runtime;
 
 use crate::global_query::GraphPartitionManager;
+use crate::store::graph::do_write_batch;
+
+/// The timeout of replicating the routing by the meta raft group.
+const ROUTING_PROPOSE_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct GaiaServer {
     config: Arc<GraphConfig>,
     graph: Arc<GlobalGraph>,
     detector: Arc<SimpleServerDetector>,
+    // route partitions to servers by consistent hashing instead of the routing table if it's set;
+    hash_ring: Option<Arc<HashRing>>,
     rpc_runtime: Runtime,
+    // the replica of the meta raft group if `store.raft.peers` is set, started with the engine
+    meta: Mutex<Option<MetaReplica>>,
+    // the purge of the softly deleted elements if `gaia.soft.delete.property` is set
+    purge: Mutex<Option<PurgeHandle>>,
+    // the standing queries if `gaia.standing.queries` is set, maintained by the mutations of the jobs
+    standing_queries: Mutex<Option<Arc<StandingQueries>>>,
+    // the triggers fired by the mutations of the jobs, with the plans of `gaia.triggers`
+    triggers: Arc<TriggerRegistry>,
+    // the stored procedures, installed by the admins on `/admin/procedures` of the metrics server
+    procedures: Arc<ProcedureRegistry>,
+    // the retries of the pending firings of the triggers, started with the engine
+    trigger_retry: Mutex<Option<RetryHandle>>,
+    // the neighbor sampling service if `store.neighbor.sampling.port` is set, started with the engine
+    neighbor_sampling: Mutex<Option<NeighborSamplingServer>>,
+    // the streaming write service if `store.stream.write.port` is set, started with the engine
+    stream_write: Mutex<Option<StreamWriteServer>>,
+    // the ingestion of `store.kafka.ingest.topic` with its stop flag, started with the engine
+    #[cfg(feature = "kafka")]
+    kafka_ingest: Mutex<Option<(Arc<AtomicBool>, thread::JoinHandle<()>)>>,
 }
 
 impl GaiaServer {
@@ -47,32 +99,176 @@ impl GaiaServer {
             .expect("required config partition.count is missing")
             .parse()
             .expect("parse partition.count failed");
+        let hash_ring = config
+            .get_storage_option("gaia.routing.virtual.nodes")
+            .map(|config_str| {
+                let virtual_nodes = config_str
+                    .parse()
+                    .expect("parse gaia.routing.virtual.nodes failed");
+                Arc::new(HashRing::new(virtual_nodes))
+            });
+        let graph = GlobalGraph::empty(partition_count);
+        if let Some(config_str) = config.get_storage_option("gaia.follower.read.max.staleness.ms") {
+            let max_staleness_ms = config_str
+                .parse()
+                .expect("parse gaia.follower.read.max.staleness.ms failed");
+            graph.set_follower_read_staleness(max_staleness_ms);
+        }
         GaiaServer {
             config,
-            graph: Arc::new(GlobalGraph::empty(partition_count)),
+            graph: Arc::new(graph),
             detector: Arc::new(SimpleServerDetector::new()),
+            hash_ring,
             rpc_runtime: Runtime::new().unwrap(),
+            meta: Mutex::new(None),
+            purge: Mutex::new(None),
+            standing_queries: Mutex::new(None),
+            triggers: Arc::new(TriggerRegistry::default()),
+            procedures: Arc::new(ProcedureRegistry::default()),
+            trigger_retry: Mutex::new(None),
+            neighbor_sampling: Mutex::new(None),
+            stream_write: Mutex::new(None),
+            #[cfg(feature = "kafka")]
+            kafka_ingest: Mutex::new(None),
         }
     }
 
-    pub fn add_partition(&mut self, partition_id: PartitionId, graph_partition: Arc<GraphStore>) {
+    pub fn add_partition(&self, partition_id: PartitionId, graph_partition: Arc<GraphStore>) {
         trace!("add_partition");
-        Arc::get_mut(&mut self.graph)
-            .unwrap()
+        self.graph
             .add_partition(partition_id, graph_partition);
     }
 
-    pub fn update_partition_routing(&mut self, partition_id: PartitionId, worker_id: u32) {
+    pub fn remove_partition(&self, partition_id: PartitionId) {
+        trace!("remove_partition");
+        self.graph.remove_partition(partition_id);
+    }
+
+    pub fn add_follower_partition(&self, partition_id: PartitionId, graph_partition: Arc<GraphStore>) {
+        trace!("add_follower_partition");
+        self.graph
+            .add_follower_partition(partition_id, graph_partition);
+    }
+
+    pub fn remove_follower_partition(&self, partition_id: PartitionId) {
+        trace!("remove_follower_partition");
+        self.graph
+            .remove_follower_partition(partition_id);
+    }
+
+    pub fn update_partition_replica(
+        &self, partition_id: PartitionId, server_id: u32, staleness_ms: Option<u64>,
+    ) {
+        trace!("update_partition_replica");
+        self.graph
+            .update_partition_replica(partition_id, server_id, staleness_ms);
+    }
+
+    pub fn update_partition_routing(&self, partition_id: PartitionId, worker_id: u32) {
         trace!("update_partition_routing");
-        Arc::get_mut(&mut self.graph)
-            .unwrap()
+        self.graph
             .update_partition_routing(partition_id, worker_id);
     }
 
+    /// Switch the routing after partitions are split, merged or moved, see `GlobalGraph::switch_routing`.
+    /// The routing is replicated to all the store nodes by the meta raft group if it's started, and
+    /// switched by each of them once it's committed.
+    pub fn switch_partition_routing(&self, routing: PartitionRouting) -> bool {
+        trace!("switch_partition_routing");
+        if let Some(meta) = self.meta.lock().unwrap().as_ref() {
+            return match meta.propose_routing(&routing, ROUTING_PROPOSE_TIMEOUT) {
+                Ok(index) => {
+                    info!("partition routing of version {} is committed at {}", routing.version(), index);
+                    true
+                }
+                Err(e) => {
+                    error!("replicate partition routing failed: {:?}", e);
+                    false
+                }
+            };
+        }
+        self.graph.switch_routing(routing)
+    }
+
+    /// Replicate the ddl batch to all the store nodes by the meta raft group, where it's written to
+    /// the stores of the partitions once it's committed.
+    pub fn propose_ddl(&self, si: i64, batch: Vec<u8>) -> GraphResult<u64> {
+        trace!("propose_ddl");
+        match self.meta.lock().unwrap().as_ref() {
+            Some(meta) => meta.propose_ddl(si, batch, ROUTING_PROPOSE_TIMEOUT),
+            None => {
+                let msg = "meta raft group is not enabled by store.raft.peers".to_owned();
+                Err(GraphError::new(GraphErrorCode::InvalidOperation, msg))
+            }
+        }
+    }
+
+    /// Start the replica of the meta raft group after the partitions are added, so that the ddl
+    /// batches replayed are written to them.
+    fn start_meta(&self) -> GraphResult<()> {
+        let graph = self.graph.clone();
+        let ddl_applier: DdlApplier = Box::new(move |si, batch| {
+            for store in graph.get_partition_stores() {
+                do_write_batch(store.as_ref(), si, batch)?;
+            }
+            Ok(())
+        });
+        if let Some(meta) = MetaReplica::start(self.config.get_storage_options(), ddl_applier)? {
+            let graph = self.graph.clone();
+            meta.state_machine()
+                .add_routing_listener(Box::new(move |routing| {
+                    graph.switch_routing(routing.clone());
+                }));
+            *self.meta.lock().unwrap() = Some(meta);
+        }
+        Ok(())
+    }
+
     pub fn start(&'static self) -> GraphResult<(u16, u16)> {
+        self.start_meta()?;
         let gaia_config = make_gaia_config(self.config.clone());
         let gaia_rpc_config = make_gaia_rpc_config(self.config.clone());
         info!("Server config {:?}\nRPC config {:?}", gaia_config, gaia_rpc_config);
+        let sessions = SessionRegistry::from_options(self.config.get_storage_options())
+            .map_err(|e| GraphError::new(GraphErrorCode::InvalidOperation, e.to_string()))?;
+        register_extensions(self.config.get_storage_options())
+            .map_err(|e| GraphError::new(GraphErrorCode::InvalidOperation, e.to_string()))?;
+        let workers = match self.config.get_storage_option("worker.num") {
+            Some(worker_num) => worker_num.parse().map_err(|e| {
+                let msg = format!("parse worker.num failed: {}", e);
+                GraphError::new(GraphErrorCode::InvalidOperation, msg)
+            })?,
+            None => 1,
+        };
+        let expand_prefetch = make_expand_prefetch(&self.config)?;
+        let dedup_spill = make_dedup_spill(&self.config)?;
+        *self.purge.lock().unwrap() = start_purge_by(self.config.get_storage_options(), workers)
+            .map_err(|e| GraphError::new(GraphErrorCode::InvalidOperation, e.to_string()))?;
+        let trigger_retry = start_triggers(self.config.get_storage_options(), &self.triggers)
+            .map_err(|e| GraphError::new(GraphErrorCode::InvalidOperation, e.to_string()))?;
+        *self.trigger_retry.lock().unwrap() = Some(trigger_retry);
+        if let Some((port, config)) = make_neighbor_sampling(&self.config) {
+            let server = NeighborSamplingServer::start(port, config, self.graph.clone())?;
+            *self.neighbor_sampling.lock().unwrap() = Some(server);
+        }
+        if let Some((port, config)) = make_stream_write(&self.config) {
+            let server = StreamWriteServer::start(port, config, self.graph.clone())?;
+            *self.stream_write.lock().unwrap() = Some(server);
+        }
+        #[cfg(feature = "kafka")]
+        if let Some(config) = make_kafka_ingest(&self.config) {
+            let mut ingestor = KafkaIngestor::new(config, self.graph.clone())?;
+            let stop = Arc::new(AtomicBool::new(false));
+            let stopped = stop.clone();
+            let handle = thread::Builder::new()
+                .name("kafka-ingest".to_owned())
+                .spawn(move || ingestor.run(&stopped))
+                .map_err(|e| {
+                    let msg = format!("spawn kafka ingestor failed: {}", e);
+                    GraphError::new(GraphErrorCode::InvalidOperation, msg)
+                })?;
+            *self.kafka_ingest.lock().unwrap() = Some((stop, handle));
+        }
         let (server_port, rpc_port) = self.rpc_runtime.block_on(async {
             let column_filter_push_down = false;
             #[cfg(feature = "column_filter_push_down")]
@@ -86,8 +282,54 @@ impl GaiaServer {
                 true,
                 column_filter_push_down,
             );
-            let partition_info = GrootMultiPartition::new(self.graph.clone());
-            let job_compiler = initialize_job_assembly(gs_store, Arc::new(partition_info), cluster_info);
+            let partition_info = match self.hash_ring {
+                Some(ref ring) => GrootMultiPartition::with_hash_ring(self.graph.clone(), ring.clone()),
+                None => GrootMultiPartition::new(self.graph.clone()),
+            };
+            let mut job_compiler =
+                initialize_job_assembly(gs_store, Arc::new(partition_info), cluster_info);
+            if let Some(split) = make_expand_split(&self.config) {
+                job_compiler = job_compiler.with_expand_split(split);
+            }
+            if let Some(prefetch) = expand_prefetch {
+                job_compiler = job_compiler.with_expand_prefetch(prefetch);
+            }
+            if let Some(filter) = make_dedup_filter(&self.config) {
+                job_compiler = job_compiler.with_dedup_filter(filter);
+            }
+            if let Some(spill) = dedup_spill {
+                job_compiler = job_compiler.with_dedup_spill(spill);
+            }
+            if let Some(sessions) = sessions {
+                job_compiler = job_compiler.with_sessions(Arc::new(sessions));
+            }
+            // materialized from the graph registered with the job assembly
+            let standing_queries = register_standing_queries(self.config.get_storage_options())
+                .map_err(|e| GraphError::new(GraphErrorCode::InvalidOperation, e.to_string()))?;
+            if let Some(standing_queries) = standing_queries {
+                *self.standing_queries.lock().unwrap() = Some(standing_queries.clone());
+                job_compiler = job_compiler.with_standing_queries(standing_queries);
+            }
+            job_compiler = job_compiler.with_triggers(self.triggers.clone());
+            pegasus_server::admin::set_procedure_admin(self.procedures.clone());
+            job_compiler = job_compiler.with_procedures(self.procedures.clone());
+            let reporter = DegreeReporter::new(self.graph.clone(), DegreeReportConfig::default());
+            pegasus_server::admin::set_degree_reporter(move |query| {
+                let si = query.snapshot_id.unwrap_or(MAX_SI);
+                reporter.report_json(&query.label, si, query.sample_rate, query.refresh)
+            });
+            if let Some(export_dir) = make_export_dir(&self.config) {
+                let graph = self.graph.clone();
+                pegasus_server::admin::set_subgraph_exporter(move |query| {
+                    export_subgraph(&graph, &export_dir, query)
+                });
+            }
+            let graph = self.graph.clone();
+            pegasus_server::drain::add_drain_hook(move || {
+                if let Err(e) = graph.drain() {
+                    error!("drain graph partitions failed: {:?}", e);
+                }
+            });
             let service_listener = GaiaServiceListener::default();
             let service_listener_clone = service_listener.clone();
             self.rpc_runtime.spawn(async move {
@@ -109,18 +351,65 @@ impl GaiaServer {
                     thread::sleep(Duration::from_millis(10));
                 }
             }
-            (service_listener.get_server_port().unwrap(), service_listener.get_rpc_port().unwrap())
-        });
+            let ports =
+                (service_listener.get_server_port().unwrap(), service_listener.get_rpc_port().unwrap());
+            Ok::<_, GraphError>(ports)
+        })?;
         Ok((server_port, rpc_port))
     }
 
+    /// The standing queries to subscribe, if `gaia.standing.queries` is set.
+    pub fn standing_queries(&self) -> Option<Arc<StandingQueries>> {
+        self.standing_queries.lock().unwrap().clone()
+    }
+
+    /// The triggers to register, fired by the mutations of the jobs.
+    pub fn triggers(&self) -> &Arc<TriggerRegistry> {
+        &self.triggers
+    }
+
+    /// The stored procedures, which may also be installed by the admins on `/admin/procedures`.
+    pub fn procedures(&self) -> &Arc<ProcedureRegistry> {
+        &self.procedures
+    }
+
+    /// The server which the partition should be placed on, if partitions are routed by consistent hashing.
+    pub fn get_partition_server(&self, partition_id: PartitionId) -> Option<u32> {
+        self.hash_ring
+            .as_ref()
+            .and_then(|ring| ring.get_server(partition_id as u64))
+    }
+
     pub fn update_peer_view(&self, peer_view: Vec<(u64, ServerAddr)>) {
         trace!("update_peer_view");
+        if let Some(ref ring) = self.hash_ring {
+            if ring.set_servers(peer_view.iter().map(|(id, _)| *id as u32)) {
+                info!("servers of the hash ring are changed to {:?}", ring.get_servers());
+            }
+        }
         self.detector
             .update_peer_view(peer_view.into_iter());
     }
 
     pub fn stop(&self) {
+        if let Some(mut meta) = self.meta.lock().unwrap().take() {
+            meta.stop();
+        }
+        self.purge.lock().unwrap().take();
+        self.trigger_retry.lock().unwrap().take();
+        if let Some(mut neighbor_sampling) = self.neighbor_sampling.lock().unwrap().take() {
+            neighbor_sampling.stop();
+        }
+        if let Some(mut stream_write) = self.stream_write.lock().unwrap().take() {
+            stream_write.stop();
+        }
+        #[cfg(feature = "kafka")]
+        if let Some((stop, handle)) = self.kafka_ingest.lock().unwrap().take() {
+            stop.store(true, Ordering::Relaxed);
+            if handle.join().is_err() {
+                error!("kafka ingestor panicked");
+            }
+        }
         gaia_pegasus::shutdown_all();
     }
 }
@@ -193,6 +482,27 @@ fn make_gaia_config(graph_config: Arc<GraphConfig>) -> GaiaConfig {
                 .parse()
                 .expect("parse gaia.heartbeat.sec failed")
         });
+    let compression = graph_config
+        .get_storage_option("gaia.shuffle.compression")
+        .map(|config_str| {
+            config_str
+                .parse()
+                .expect("parse gaia.shuffle.compression failed")
+        });
+    let compression_level = graph_config
+        .get_storage_option("gaia.shuffle.compression.level")
+        .map(|config_str| {
+            config_str
+                .parse()
+                .expect("parse gaia.shuffle.compression.level failed")
+        });
+    let batch_bytes = graph_config
+        .get_storage_option("gaia.shuffle.batch.bytes")
+        .map(|config_str| {
+            config_str
+                .parse()
+                .expect("parse gaia.shuffle.batch.bytes failed")
+        });
     let max_pool_size = graph_config
         .get_storage_option("gaia.max.pool.size")
         .map(|config_str| {
@@ -208,7 +518,11 @@ fn make_gaia_config(graph_config: Arc<GraphConfig>) -> GaiaConfig {
         .read_slab_size(read_slab_size)
         .no_delay(no_delay)
         .send_buffer(send_buffer)
-        .heartbeat_sec(heartbeat_sec);
+        .heartbeat_sec(heartbeat_sec)
+        .compression(compression)
+        .compression_level(compression_level)
+        .batch_bytes(batch_bytes)
+        .tls(make_gaia_tls_config(&graph_config));
     let enable_tracing = graph_config
         .get_storage_option("tracing.enabled")
         .map(|config_str| {
@@ -216,7 +530,10 @@ fn make_gaia_config(graph_config: Arc<GraphConfig>) -> GaiaConfig {
                 .parse()
                 .expect("parse tracing.enabled failed")
         });
-    GaiaConfig { network: Some(network_config), max_pool_size, enable_tracing }
+    let tracing_endpoint = graph_config
+        .get_storage_option("tracing.endpoint")
+        .cloned();
+    GaiaConfig { network: Some(network_config), max_pool_size, enable_tracing, tracing_endpoint }
 }
 
 fn make_gaia_rpc_config(graph_config: Arc<GraphConfig>) -> RPCServerConfig {
@@ -226,7 +543,314 @@ fn make_gaia_rpc_config(graph_config: Arc<GraphConfig>) -> RPCServerConfig {
             .parse()
             .expect("parse node.idx failed"),
     };
-    RPCServerConfig::new(Some("0.0.0.0".to_string()), Some(rpc_port))
+    let mut rpc_config = RPCServerConfig::new(Some("0.0.0.0".to_string()), Some(rpc_port));
+    if let Some(tls) = make_gaia_tls_config(&graph_config) {
+        let client_auth = graph_config
+            .get_storage_option("gaia.rpc.tls.client.auth")
+            .map(|config_str| {
+                config_str
+                    .parse()
+                    .expect("parse gaia.rpc.tls.client.auth failed")
+            })
+            .unwrap_or(false);
+        rpc_config.tls = Some(RpcTlsConfig {
+            cert_file: tls.cert_file,
+            key_file: tls.key_file,
+            client_ca_file: if client_auth { Some(tls.ca_file) } else { None },
+        });
+    }
+    rpc_config.metrics_port = graph_config
+        .get_storage_option("gaia.metrics.port")
+        .map(|config_str| {
+            config_str
+                .parse()
+                .expect("parse gaia.metrics.port failed")
+        });
+    // the jobs in json over http, over https by the tls of the rpc server if set
+    rpc_config.http_port = graph_config
+        .get_storage_option("gaia.http.port")
+        .map(|config_str| {
+            config_str
+                .parse()
+                .expect("parse gaia.http.port failed")
+        });
+    rpc_config.runtime_config_file = graph_config
+        .get_storage_option("gaia.runtime.config.file")
+        .cloned();
+    // groot has no secondary indexes to create, the recommendations are served only
+    if graph_config
+        .get_storage_option("gaia.index.advisor.enabled")
+        .map_or(false, |enabled| enabled == "true")
+    {
+        rpc_config.index_advisor = Some(AdvisorConfig::default());
+    }
+    // the results in arrow are served by arrow flight, merged from the servers of `gaia.flight.peers`,
+    // i.e., the rpc uris of all the servers in the order of their ids
+    rpc_config.flight = graph_config
+        .get_storage_option("gaia.flight.enabled")
+        .map(|enabled| enabled == "true");
+    rpc_config.flight_peers = graph_config
+        .get_storage_option("gaia.flight.peers")
+        .map(|peers| {
+            peers
+                .split(',')
+                .map(|uri| uri.trim().to_string())
+                .collect()
+        });
+    // the admin reports are merged from the servers of `gaia.metrics.peers`, i.e., the metrics uris of
+    // all the servers in the order of their ids
+    rpc_config.metrics_peers = graph_config
+        .get_storage_option("gaia.metrics.peers")
+        .map(|peers| {
+            peers
+                .split(',')
+                .map(|uri| uri.trim().to_string())
+                .collect()
+        });
+    rpc_config
+}
+
+/// Split the adjacency of the vertices over `gaia.expand.split.threshold` pending adjacency of a worker
+/// into the tasks of `gaia.expand.split.chunk`, 1024 by default; not split if the threshold is not set.
+fn make_expand_split(graph_config: &GraphConfig) -> Option<ExpandSplit> {
+    let threshold = graph_config
+        .get_storage_option("gaia.expand.split.threshold")?
+        .parse()
+        .expect("parse gaia.expand.split.threshold failed");
+    let chunk = graph_config
+        .get_storage_option("gaia.expand.split.chunk")
+        .map_or(1024, |config_str| {
+            config_str
+                .parse()
+                .expect("parse gaia.expand.split.chunk failed")
+        });
+    Some(ExpandSplit { threshold, chunk })
+}
+
+/// Read the adjacency of up to `gaia.expand.prefetch.depth` records ahead in the edge expand; not read
+/// ahead if not set. The reads are shared by the `gaia.expand.prefetch.threads` threads of the server,
+/// 4 by default, with up to `gaia.expand.prefetch.queue` reads queued, 1024 by default.
+fn make_expand_prefetch(graph_config: &GraphConfig) -> GraphResult<Option<ExpandPrefetch>> {
+    let parse = |key: &str| -> GraphResult<Option<usize>> {
+        match graph_config.get_storage_option(key) {
+            Some(value) => value.parse().map(Some).map_err(|e| {
+                let msg = format!("parse {} failed: {}", key, e);
+                GraphError::new(GraphErrorCode::InvalidOperation, msg)
+            }),
+            None => Ok(None),
+        }
+    };
+    let depth = match parse("gaia.expand.prefetch.depth")? {
+        Some(depth) => depth,
+        None => return Ok(None),
+    };
+    let threads = parse("gaia.expand.prefetch.threads")?.unwrap_or(4);
+    let queue = parse("gaia.expand.prefetch.queue")?.unwrap_or(1024);
+    if threads == 0 {
+        let msg = "gaia.expand.prefetch.threads must be positive".to_owned();
+        return Err(GraphError::new(GraphErrorCode::InvalidOperation, msg));
+    }
+    let pool = PrefetchPool::new(threads, queue).map_err(|e| {
+        let msg = format!("start the prefetch threads failed: {}", e);
+        GraphError::new(GraphErrorCode::InvalidOperation, msg)
+    })?;
+    Ok(Some(ExpandPrefetch { depth, pool: Arc::new(pool) }))
+}
+
+/// Serve the neighbor sampling on `store.neighbor.sampling.port` by `store.neighbor.sampling.threads`
+/// samplers, 4 by default; not served if the port is not set.
+fn make_neighbor_sampling(graph_config: &GraphConfig) -> Option<(u16, NeighborSamplingConfig)> {
+    let port = graph_config
+        .get_storage_option("store.neighbor.sampling.port")?
+        .parse()
+        .expect("parse store.neighbor.sampling.port failed");
+    let mut config = NeighborSamplingConfig::default();
+    if let Some(threads) = graph_config.get_storage_option("store.neighbor.sampling.threads") {
+        config.threads = threads
+            .parse()
+            .expect("parse store.neighbor.sampling.threads failed");
+    }
+    Some((port, config))
+}
+
+fn make_stream_write(graph_config: &GraphConfig) -> Option<(u16, StreamWriteConfig)> {
+    let port = graph_config
+        .get_storage_option("store.stream.write.port")?
+        .parse()
+        .expect("parse store.stream.write.port failed");
+    let mut config = StreamWriteConfig::default();
+    if let Some(lanes) = graph_config.get_storage_option("store.stream.write.lanes") {
+        config.lanes = lanes
+            .parse()
+            .expect("parse store.stream.write.lanes failed");
+    }
+    if let Some(capacity) = graph_config.get_storage_option("store.stream.write.lane.capacity") {
+        config.lane_capacity = capacity
+            .parse()
+            .expect("parse store.stream.write.lane.capacity failed");
+    }
+    if let Some(bytes) = graph_config.get_storage_option("store.stream.write.max.memtable.bytes") {
+        config.max_memtable_bytes = bytes
+            .parse()
+            .expect("parse store.stream.write.max.memtable.bytes failed");
+    }
+    if let Some(ms) = graph_config.get_storage_option("store.stream.write.throttle.interval.ms") {
+        let ms = ms
+            .parse()
+            .expect("parse store.stream.write.throttle.interval.ms failed");
+        config.throttle_interval = Duration::from_millis(ms);
+    }
+    if let Some(secs) = graph_config.get_storage_option("store.stream.write.dedup.retention.secs") {
+        let secs = secs
+            .parse()
+            .expect("parse store.stream.write.dedup.retention.secs failed");
+        config.dedup_retention = Duration::from_secs(secs);
+    }
+    Some((port, config))
+}
+
+/// Ingest the records of `store.kafka.ingest.topic` on `store.kafka.ingest.brokers` by the consumer
+/// group `store.kafka.ingest.group.id`, in json, or in avro of `store.kafka.ingest.avro.schema` if
+/// it's set. The records are mapped by the record mapping `store.kafka.ingest.mapping` if it's set,
+/// or are mutation records otherwise; not ingested if the topic is not set.
+#[cfg(feature = "kafka")]
+fn make_kafka_ingest(graph_config: &GraphConfig) -> Option<KafkaIngestConfig> {
+    let topic = graph_config.get_storage_option("store.kafka.ingest.topic")?;
+    let brokers = graph_config
+        .get_storage_option("store.kafka.ingest.brokers")
+        .expect("required config store.kafka.ingest.brokers is missing");
+    let group_id = graph_config
+        .get_storage_option("store.kafka.ingest.group.id")
+        .map_or("groot", |s| s.as_str());
+    let mut config = KafkaIngestConfig::new(brokers, group_id, topic);
+    if let Some(schema) = graph_config.get_storage_option("store.kafka.ingest.avro.schema") {
+        let confluent_wire_format = graph_config
+            .get_storage_option("store.kafka.ingest.avro.confluent")
+            .map_or(false, |s| {
+                s.parse()
+                    .expect("parse store.kafka.ingest.avro.confluent failed")
+            });
+        config.format = RecordFormat::Avro { schema: schema.clone(), confluent_wire_format };
+    }
+    if let Some(mapping) = graph_config.get_storage_option("store.kafka.ingest.mapping") {
+        let mapping =
+            RecordMapping::from_json_str(mapping).expect("parse store.kafka.ingest.mapping failed");
+        config.mapping = Some(mapping);
+    }
+    if let Some(size) = graph_config.get_storage_option("store.kafka.ingest.batch.size") {
+        config.batch_size = size
+            .parse()
+            .expect("parse store.kafka.ingest.batch.size failed");
+    }
+    if let Some(ms) = graph_config.get_storage_option("store.kafka.ingest.poll.timeout.ms") {
+        let ms = ms
+            .parse()
+            .expect("parse store.kafka.ingest.poll.timeout.ms failed");
+        config.poll_timeout = Duration::from_millis(ms);
+    }
+    Some(config)
+}
+
+/// Extract the subgraphs into `store.export.dir` of the server on `POST /admin/export`, which is not
+/// supported if it's not set.
+fn make_export_dir(graph_config: &GraphConfig) -> Option<PathBuf> {
+    graph_config
+        .get_storage_option("store.export.dir")
+        .map(PathBuf::from)
+}
+
+/// Extract the subgraph of the query from the partitions of the server, returns the manifest in json.
+fn export_subgraph(
+    graph: &Arc<GlobalGraph>, export_dir: &Path, query: &ExportQuery,
+) -> Result<Vec<u8>, String> {
+    let format_name = query.format.as_deref().unwrap_or("csv");
+    let format =
+        ExportFormat::from_name(format_name).ok_or_else(|| format!("unknown format {}", format_name))?;
+    let schema = graph
+        .get_schema(query.snapshot_id)
+        .ok_or_else(|| format!("schema not found at snapshot {}", query.snapshot_id))?;
+    let label_ids = |labels: &Vec<String>| {
+        labels
+            .iter()
+            .map(|label| {
+                schema
+                    .get_label_id(label)
+                    .ok_or_else(|| format!("label {} not found", label))
+            })
+            .collect::<Result<Vec<_>, String>>()
+    };
+    let selection = SubgraphSelection::default()
+        .with_vertex_labels(label_ids(&query.vertex_labels)?)
+        .with_edge_labels(label_ids(&query.edge_labels)?);
+    let output_dir = export_dir.join(&query.name);
+    let extractor = SubgraphExtractor::new(
+        graph.clone(),
+        selection,
+        format,
+        output_dir.clone(),
+        query.snapshot_id,
+        graph.get_process_partition_list(),
+    )
+    .with_partition_manager(graph.clone());
+    extractor
+        .extract()
+        .map_err(|e| format!("extract subgraph failed: {}", e))?;
+    std::fs::read(output_dir.join(MANIFEST_FILE_NAME)).map_err(|e| format!("read manifest failed: {}", e))
+}
+
+/// Drop the duplicates before the exact dedup by a bloom filter of `gaia.dedup.filter.bits` and up to
+/// `gaia.dedup.filter.repeats` repeated keys of a worker, 65536 by default; not dropped if the bits are
+/// not set.
+fn make_dedup_filter(graph_config: &GraphConfig) -> Option<DedupFilter> {
+    let bits = graph_config
+        .get_storage_option("gaia.dedup.filter.bits")?
+        .parse()
+        .expect("parse gaia.dedup.filter.bits failed");
+    let repeats = graph_config
+        .get_storage_option("gaia.dedup.filter.repeats")
+        .map_or(65536, |config_str| {
+            config_str
+                .parse()
+                .expect("parse gaia.dedup.filter.repeats failed")
+        });
+    Some(DedupFilter { bits, repeats })
+}
+
+/// Spill the records of the dedup beyond `gaia.dedup.spill.keys` keys of a worker kept in the memory to
+/// `gaia.dedup.spill.dir`, the temporary directory by default; not spilled if the keys are not set.
+fn make_dedup_spill(graph_config: &GraphConfig) -> GraphResult<Option<DedupSpill>> {
+    let keys = match graph_config.get_storage_option("gaia.dedup.spill.keys") {
+        Some(keys) => keys.parse().map_err(|e| {
+            let msg = format!("parse gaia.dedup.spill.keys failed: {}", e);
+            GraphError::new(GraphErrorCode::InvalidOperation, msg)
+        })?,
+        None => return Ok(None),
+    };
+    let dir = graph_config
+        .get_storage_option("gaia.dedup.spill.dir")
+        .map_or_else(std::env::temp_dir, PathBuf::from);
+    std::fs::create_dir_all(&dir).map_err(|e| {
+        let msg = format!("create the dedup spill dir {:?} failed: {}", dir, e);
+        GraphError::new(GraphErrorCode::InvalidOperation, msg)
+    })?;
+    Ok(Some(DedupSpill { keys, dir }))
+}
+
+/// The certificates shared by the rpc server and the connections between servers, tls is enabled
+/// only if all of `gaia.tls.cert.file`, `gaia.tls.key.file` and `gaia.tls.ca.file` are set.
+fn make_gaia_tls_config(graph_config: &GraphConfig) -> Option<TlsConfig> {
+    let cert_file = graph_config.get_storage_option("gaia.tls.cert.file")?;
+    let key_file = graph_config.get_storage_option("gaia.tls.key.file")?;
+    let ca_file = graph_config.get_storage_option("gaia.tls.ca.file")?;
+    let server_name = graph_config
+        .get_storage_option("gaia.tls.server.name")
+        .cloned();
+    Some(TlsConfig {
+        cert_file: cert_file.clone(),
+        key_file: key_file.clone(),
+        ca_file: ca_file.clone(),
+        server_name,
+    })
 }
 
 #[derive(Default)]
@@ -7,10 +7,13 @@ use std::sync::Once;
 use groot_store::db::api::multi_version_graph::MultiVersionGraph;
 use groot_store::db::api::PropertyMap;
 use groot_store::db::api::{
-    DataLoadTarget, EdgeId, EdgeKind, GraphConfigBuilder, GraphResult, SnapshotId, TypeDef,
+    DataLoadTarget, EdgeId, EdgeKind, GraphConfigBuilder, GraphError, GraphErrorCode, GraphResult,
+    SnapshotId, TypeDef,
 };
 use groot_store::db::common::bytes::util::parse_pb;
+use groot_store::db::graph::replica::decode_entries;
 use groot_store::db::graph::store::GraphStore;
+use groot_store::db::graph::throttle::WriteAdmission;
 use groot_store::db::proto::model::{
     AddEdgeKindPb, ConfigPb, CreateVertexTypePb, DataOperationPb, DdlOperationPb, EdgeIdPb, EdgeLocationPb,
     OpTypePb, OperationBatchPb, OperationPb, VertexIdPb,
@@ -80,10 +83,7 @@ pub extern "C" fn getGraphDefBlob(ptr: GraphHandle) -> Box<JnaResponse> {
                 }
                 response
             }
-            Err(e) => {
-                let msg = format!("{:?}", e);
-                JnaResponse::new_error(&msg)
-            }
+            Err(e) => JnaResponse::new_graph_error(&e),
         }
     }
 }
@@ -97,10 +97,7 @@ pub extern "C" fn ingestData(ptr: GraphHandle, path: *const c_char) -> Box<JnaRe
         let path_str = std::str::from_utf8(slice).unwrap();
         match graph_store_ptr.ingest(path_str) {
             Ok(_) => JnaResponse::new_success(),
-            Err(e) => {
-                let msg = format!("{:?}", e);
-                JnaResponse::new_error(&msg)
-            }
+            Err(e) => JnaResponse::new_graph_error(&e),
         }
     }
 }
@@ -112,22 +109,43 @@ pub extern "C" fn writeBatch(
     trace!("writeBatch");
 
     let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
+    if graph_store_ptr.is_follower() {
+        return JnaResponse::new_error(
+            "graph store is a follower, writes are only replicated from the leader",
+        );
+    }
+    // the writer backs off under the backlog of flushes and compactions, instead of stalling in it, and
+    // waits for the delay by itself rather than on this thread
+    match graph_store_ptr.admit_write() {
+        Ok(WriteAdmission::Admit) => {}
+        Ok(WriteAdmission::Delay(delay)) => {
+            let msg = format!("graph store is behind on compactions, write after {:?}", delay);
+            return JnaResponse::new_retry_after(&msg, delay.as_millis() as u64);
+        }
+        Ok(WriteAdmission::Reject(retry_after)) => {
+            let msg = format!("graph store is overloaded by compactions, retry after {:?}", retry_after);
+            return JnaResponse::new_retry_after(&msg, retry_after.as_millis() as u64);
+        }
+        Err(e) => warn!("get the write backlog of graph store failed: {:?}", e),
+    }
+    let _write = match graph_store_ptr.begin_write() {
+        Some(guard) => guard,
+        None => return JnaResponse::new_error("graph store is draining or paused, writes are rejected"),
+    };
     let buf = unsafe { ::std::slice::from_raw_parts(data, len) };
     let ret = match do_write_batch(graph_store_ptr, snapshot_id, buf) {
         Ok(has_ddl) => {
+            graph_store_ptr.append_replication_log(snapshot_id, buf);
             let mut response = JnaResponse::new_success();
             response.has_ddl(has_ddl);
             response
         }
-        Err(e) => {
-            let err_msg = format!("{:?}", e);
-            JnaResponse::new_error(&err_msg)
-        }
+        Err(e) => JnaResponse::new_graph_error(&e),
     };
     return ret;
 }
 
-fn do_write_batch<G: MultiVersionGraph>(
+pub(crate) fn do_write_batch<G: MultiVersionGraph>(
     graph: &G, snapshot_id: SnapshotId, buf: &[u8],
 ) -> GraphResult<bool> {
     trace!("do_write_batch");
@@ -416,6 +434,85 @@ fn delete_edge<G: MultiVersionGraph>(graph: &G, snapshot_id: i64, op: &Operation
     graph.delete_edge(snapshot_id, edge_id, &edge_kind, edge_location_pb.get_forward())
 }
 
+/// Pull the batches written to the leader after the snapshot `since`, of `max_bytes` at most; the
+/// response data is the latest snapshot id of the leader followed by the batches, in big endian.
+#[no_mangle]
+pub extern "C" fn pullReplicationLog(ptr: GraphHandle, since: i64, max_bytes: i64) -> Box<JnaResponse> {
+    trace!("pullReplicationLog");
+    let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
+    let res = graph_store_ptr
+        .read_replication_log(since, max_bytes.max(0) as usize)
+        .and_then(|(latest_si, entries)| {
+            let mut data = Vec::with_capacity(8 + entries.len());
+            data.extend_from_slice(&latest_si.to_be_bytes());
+            data.extend_from_slice(&entries);
+            let mut response = JnaResponse::new_success();
+            response.data(data)?;
+            Ok(response)
+        });
+    match res {
+        Ok(response) => response,
+        Err(e) => {
+            let msg = format!("{:?}", e);
+            JnaResponse::new_error(&msg)
+        }
+    }
+}
+
+/// Apply the batches pulled from the leader of which the latest snapshot id is `leader_si` to the
+/// follower, the batches applied before are skipped.
+#[no_mangle]
+pub extern "C" fn applyReplicationLog(
+    ptr: GraphHandle, leader_si: i64, data: *const u8, len: usize,
+) -> Box<JnaResponse> {
+    trace!("applyReplicationLog");
+    let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
+    let follower = match graph_store_ptr.get_follower_state() {
+        Some(follower) => follower,
+        None => return JnaResponse::new_error("graph store is not a replication follower"),
+    };
+    let _write = match graph_store_ptr.begin_write() {
+        Some(guard) => guard,
+        None => return JnaResponse::new_error("graph store is draining or paused, writes are rejected"),
+    };
+    let buf = unsafe { ::std::slice::from_raw_parts(data, len) };
+    let res = decode_entries(buf).and_then(|entries| {
+        let mut has_ddl = false;
+        for (si, batch) in entries {
+            if si > follower.applied_si() {
+                has_ddl |= do_write_batch(graph_store_ptr, si, batch)?;
+                follower.on_applied(si, leader_si);
+            }
+        }
+        follower.on_applied(follower.applied_si(), leader_si);
+        Ok(has_ddl)
+    });
+    match res {
+        Ok(has_ddl) => {
+            let mut response = JnaResponse::new_success();
+            response.has_ddl(has_ddl);
+            response
+        }
+        Err(e) => {
+            let msg = format!("{:?}", e);
+            JnaResponse::new_error(&msg)
+        }
+    }
+}
+
+/// The staleness of the follower in milliseconds, see `FollowerState::staleness_ms`, or -1 if it's
+/// not a follower or has never been in sync.
+#[no_mangle]
+pub extern "C" fn getReplicaStaleness(ptr: GraphHandle) -> i64 {
+    let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
+    graph_store_ptr
+        .get_follower_state()
+        .map(|follower| follower.staleness_ms())
+        .filter(|staleness_ms| *staleness_ms <= i64::MAX as u64)
+        .map(|staleness_ms| staleness_ms as i64)
+        .unwrap_or(-1)
+}
+
 #[no_mangle]
 pub extern "C" fn reopenSecondary(ptr: GraphHandle, wait_sec: i64) -> Box<JnaResponse> {
     let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
@@ -482,3 +579,130 @@ pub extern "C" fn compact(ptr: GraphHandle) -> Box<JnaResponse> {
         }
     }
 }
+
+#[no_mangle]
+pub extern "C" fn setBlockCacheCapacity(ptr: GraphHandle, capacity_bytes: i64) -> Box<JnaResponse> {
+    if capacity_bytes < 0 {
+        return JnaResponse::new_error(&format!("invalid block cache capacity {}", capacity_bytes));
+    }
+    let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
+    match graph_store_ptr.set_block_cache_capacity(capacity_bytes as usize) {
+        Ok(_) => JnaResponse::new_success(),
+        Err(e) => {
+            let msg = format!("{:?}", e);
+            JnaResponse::new_error(&msg)
+        }
+    }
+}
+
+/// Map the external ids of the label to vertex ids, the ids are encoded in `data` each by its length
+/// in a big endian i32 followed by the utf8 bytes, and the vertex ids are returned in big endian i64s.
+#[no_mangle]
+pub extern "C" fn mapExternalIds(
+    ptr: GraphHandle, label_id: i32, data: *const u8, len: usize,
+) -> Box<JnaResponse> {
+    let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
+    let buf = unsafe { ::std::slice::from_raw_parts(data, len) };
+    let res = decode_external_ids(buf)
+        .and_then(|external_ids| graph_store_ptr.map_external_ids(label_id, &external_ids))
+        .and_then(|ids| {
+            let mut data = Vec::with_capacity(ids.len() * 8);
+            for id in ids {
+                data.extend_from_slice(&id.to_be_bytes());
+            }
+            let mut response = JnaResponse::new_success();
+            response.data(data)?;
+            Ok(response)
+        });
+    match res {
+        Ok(response) => response,
+        Err(e) => {
+            let msg = format!("{:?}", e);
+            JnaResponse::new_error(&msg)
+        }
+    }
+}
+
+fn decode_external_ids(mut buf: &[u8]) -> GraphResult<Vec<&str>> {
+    let mut external_ids = Vec::new();
+    while !buf.is_empty() {
+        let len = match buf.get(0..4) {
+            Some(bytes) => i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+            None => break,
+        };
+        let bytes = match buf.get(4..4 + len) {
+            Some(bytes) => bytes,
+            None => break,
+        };
+        let external_id = ::std::str::from_utf8(bytes).map_err(|e| {
+            let msg = format!("invalid external id: {}", e);
+            GraphError::new(GraphErrorCode::InvalidData, msg)
+        })?;
+        external_ids.push(external_id);
+        buf = &buf[4 + len..];
+    }
+    if !buf.is_empty() {
+        let msg = "truncated external ids".to_owned();
+        return Err(GraphError::new(GraphErrorCode::InvalidData, msg));
+    }
+    Ok(external_ids)
+}
+
+/// The external id of the vertex in utf8 bytes, or no data if it's not mapped.
+#[no_mangle]
+pub extern "C" fn getExternalId(ptr: GraphHandle, label_id: i32, vertex_id: i64) -> Box<JnaResponse> {
+    let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
+    let res = graph_store_ptr
+        .get_external_id(label_id, vertex_id)
+        .and_then(|external_id| {
+            let mut response = JnaResponse::new_success();
+            if let Some(external_id) = external_id {
+                response.data(external_id.as_bytes().to_vec())?;
+            }
+            Ok(response)
+        });
+    match res {
+        Ok(response) => response,
+        Err(e) => {
+            let msg = format!("{:?}", e);
+            JnaResponse::new_error(&msg)
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn drainGraphStore(ptr: GraphHandle) -> Box<JnaResponse> {
+    let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
+    match graph_store_ptr.drain() {
+        Ok(_) => JnaResponse::new_success(),
+        Err(e) => {
+            let msg = format!("{:?}", e);
+            JnaResponse::new_error(&msg)
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rotateEncryptionKey(ptr: GraphHandle) -> Box<JnaResponse> {
+    let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
+    match graph_store_ptr.rotate_encryption_key() {
+        Ok(_) => JnaResponse::new_success(),
+        Err(e) => {
+            let msg = format!("{:?}", e);
+            JnaResponse::new_error(&msg)
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn sealGraphStore(
+    ptr: GraphHandle, snapshot_id: i64, path: *const c_char,
+) -> Box<JnaResponse> {
+    let graph_store_ptr = unsafe { &*(ptr as *const GraphStore) };
+    let slice = unsafe { CStr::from_ptr(path) }.to_bytes();
+    let path_str = std::str::from_utf8(slice).unwrap();
+    match graph_store_ptr.seal(snapshot_id, path_str) {
+        Ok(_) => JnaResponse::new_success(),
+        Err(e) => JnaResponse::new_graph_error(&e),
+    }
+}
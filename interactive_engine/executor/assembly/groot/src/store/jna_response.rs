@@ -13,11 +13,26 @@ pub struct JnaResponse {
     errMsg: *const c_char,
     data: *const c_void,
     len: i32,
+    // when to retry the rejected request after, in milliseconds, or 0 if it's not to be retried;
+    retryAfterMs: i64,
+    // the code of the error of the store, see `GraphErrorCode::code`, or 0 if it's not of the store;
+    errCode: i32,
+    // whether the failed request may succeed if it's done again;
+    retryable: i32,
 }
 
 impl JnaResponse {
     pub fn default() -> Self {
-        JnaResponse { success: 1, hasDdl: 0, errMsg: ::std::ptr::null(), data: ::std::ptr::null(), len: 0 }
+        JnaResponse {
+            success: 1,
+            hasDdl: 0,
+            errMsg: ::std::ptr::null(),
+            data: ::std::ptr::null(),
+            len: 0,
+            retryAfterMs: 0,
+            errCode: 0,
+            retryable: 0,
+        }
     }
 
     #[inline]
@@ -35,6 +50,23 @@ impl JnaResponse {
         Box::new(resp)
     }
 
+    /// The error of the store, which carries the code and the retryability of it to the clients.
+    #[inline]
+    pub fn new_graph_error(e: &GraphError) -> Box<JnaResponse> {
+        let mut resp = JnaResponse::new_error(&format!("[E{}] {:?}", e.code(), e));
+        resp.errCode = e.code() as i32;
+        resp.retryable = if e.is_retryable() { 1 } else { 0 };
+        resp
+    }
+
+    /// The error of a request rejected for the overload, which is to be retried after the time.
+    #[inline]
+    pub fn new_retry_after(msg: &str, retry_after_ms: u64) -> Box<JnaResponse> {
+        let mut resp = JnaResponse::new_error(msg);
+        resp.retryAfterMs = retry_after_ms.max(1) as i64;
+        resp
+    }
+
     pub fn success(&mut self, success: bool) {
         self.success = if success { 1 } else { 0 };
     }
@@ -1,3 +1,4 @@
 mod backup;
 mod graph;
 mod jna_response;
+mod partition;
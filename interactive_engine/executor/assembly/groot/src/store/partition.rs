@@ -0,0 +1,123 @@
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//!     http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+#![allow(non_snake_case)]
+
+use groot_store::db::api::GraphResult;
+use groot_store::db::common::bytes::util::parse_pb;
+use groot_store::db::graph::partition::{MigrateData, PartitionMigration, VertexRange};
+use groot_store::db::graph::store::GraphStore;
+use groot_store::db::proto::model::ConfigPb;
+
+use crate::store::graph::GraphHandle;
+use crate::store::jna_response::JnaResponse;
+
+pub type MigrationHandle = *const std::os::raw::c_void;
+
+/// Start migrating the data of `source`: the data in the range of `modulus` and `remainder` is
+/// migrated into the existing store `target`, e.g. to merge partitions, if `target` is not null;
+/// otherwise to a new store of the config, the data in the range if `modulus` is positive, e.g. to
+/// split a partition, or all the data, e.g. to move a partition. Return null if it fails.
+#[no_mangle]
+pub extern "C" fn openPartitionMigration(
+    source: GraphHandle, target: GraphHandle, config_bytes: *const u8, len: usize, modulus: i64,
+    remainder: i64, bytes_per_sec: i64,
+) -> MigrationHandle {
+    trace!("openPartitionMigration");
+    // the source and the target should be closed after the migration;
+    let source: &'static GraphStore = unsafe { &*(source as *const GraphStore) };
+    let range = VertexRange { modulus: modulus as u64, remainder: remainder as u64 };
+    if modulus > 0 && (remainder < 0 || remainder >= modulus) {
+        error!("open partition migration failed: invalid range {:?}", range);
+        return std::ptr::null();
+    }
+    let bytes_per_sec = bytes_per_sec.max(0) as u64;
+    let migration = if !target.is_null() {
+        if modulus <= 0 {
+            error!("open partition migration failed: invalid range {:?}", range);
+            return std::ptr::null();
+        }
+        let target = unsafe { &*(target as *const GraphStore) };
+        PartitionMigration::to_store(source, target, range, bytes_per_sec)
+    } else {
+        let buf = unsafe { ::std::slice::from_raw_parts(config_bytes, len) };
+        let proto = parse_pb::<ConfigPb>(buf).expect("parse config pb failed");
+        let data = if modulus > 0 { MigrateData::Range(range) } else { MigrateData::All };
+        match PartitionMigration::to_new_store(source, proto.get_configs(), data, bytes_per_sec) {
+            Ok(migration) => migration,
+            Err(e) => {
+                error!("open partition migration failed: {:?}", e);
+                return std::ptr::null();
+            }
+        }
+    };
+    Box::into_raw(Box::new(migration)) as MigrationHandle
+}
+
+#[no_mangle]
+pub extern "C" fn copyPartition(handle: MigrationHandle) -> Box<JnaResponse> {
+    trace!("copyPartition");
+    let migration = unsafe { &mut *(handle as *mut PartitionMigration) };
+    to_response(migration.copy())
+}
+
+/// Replay the writes since the last copy or catch-up, the response data is the number of writes
+/// replayed in native endian.
+#[no_mangle]
+pub extern "C" fn catchUpPartition(handle: MigrationHandle) -> Box<JnaResponse> {
+    trace!("catchUpPartition");
+    let migration = unsafe { &mut *(handle as *mut PartitionMigration) };
+    to_response(migration.catch_up())
+}
+
+#[no_mangle]
+pub extern "C" fn finishPartitionMigration(handle: MigrationHandle) -> Box<JnaResponse> {
+    trace!("finishPartitionMigration");
+    let migration = unsafe { &mut *(handle as *mut PartitionMigration) };
+    to_response(migration.finish())
+}
+
+/// Complete the migration after the routing is switched, the handle is released.
+#[no_mangle]
+pub extern "C" fn completePartitionMigration(handle: MigrationHandle) -> Box<JnaResponse> {
+    trace!("completePartitionMigration");
+    let migration = unsafe { Box::from_raw(handle as *mut PartitionMigration) };
+    to_response(migration.complete())
+}
+
+/// Abort the migration, the handle is released.
+#[no_mangle]
+pub extern "C" fn abortPartitionMigration(handle: MigrationHandle) {
+    trace!("abortPartitionMigration");
+    let migration = unsafe { Box::from_raw(handle as *mut PartitionMigration) };
+    migration.abort();
+}
+
+fn to_response(res: GraphResult<u64>) -> Box<JnaResponse> {
+    match res {
+        Ok(count) => {
+            let mut response = JnaResponse::new_success();
+            if let Err(e) = response.data(count.to_ne_bytes().to_vec()) {
+                response.success(false);
+                let msg = format!("{:?}", e);
+                response.err_msg(&msg);
+            }
+            response
+        }
+        Err(e) => {
+            let msg = format!("{:?}", e);
+            JnaResponse::new_error(&msg)
+        }
+    }
+}
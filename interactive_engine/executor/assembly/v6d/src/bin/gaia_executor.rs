@@ -15,18 +15,28 @@
 
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use gaia_runtime::error::{StartServerError, StartServerResult};
 use global_query::{FFIGraphStore, GraphPartitionManager};
-use graph_proxy::{apis::PegasusClusterInfo, create_gs_store, VineyardMultiPartition};
+use graph_proxy::apis::graph::PKV;
+use graph_proxy::apis::{register_write_graph, PegasusClusterInfo};
+use graph_proxy::{
+    create_gs_store, VineyardDelta, VineyardDeltaGraph, VineyardDeltaWriter, VineyardMultiPartition,
+};
+use ir_common::generated::schema as schema_pb;
+use ir_common::LabelId;
 use log::info;
 use pegasus::api::Sink;
 use pegasus::{wait_servers_ready, Configuration, JobConf, ServerConf};
 use pegasus_network::config::NetworkConfig;
 use pegasus_network::config::ServerAddr;
 use pegasus_server::rpc::{start_rpc_server, RPCServerConfig, ServiceStartListener};
+use runtime::extension::{register_extensions, register_standing_queries, start_purge_by, start_triggers};
 use runtime::initialize_job_assembly;
+use runtime::procedure::ProcedureRegistry;
+use runtime::session::SessionRegistry;
+use runtime::trigger::TriggerRegistry;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -77,7 +87,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     let network_config = NetworkConfig::with(server_id, server_addrs);
     let server_config = Configuration::with(network_config);
-    let rpc_config = RPCServerConfig::new(Some(String::from("0.0.0.0")), Some(rpc_port));
+    let mut rpc_config = RPCServerConfig::new(Some(String::from("0.0.0.0")), Some(rpc_port));
+    rpc_config.http_port = config_map
+        .get("rpc.http.port")
+        .map(|port| port.parse())
+        .transpose()?;
+    rpc_config.flight = config_map
+        .get("rpc.flight.enabled")
+        .map(|enabled| enabled == "true");
+    rpc_config.flight_peers = config_map.get("rpc.flight.peers").map(|peers| {
+        peers
+            .split(',')
+            .map(|uri| uri.trim().to_string())
+            .collect()
+    });
 
     info!("server config {:?}", server_config);
     info!("rpc config {:?}", rpc_config);
@@ -108,13 +131,48 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let gs_store = create_gs_store(
         Arc::new(ffi_store),
         partition_manager.clone(),
-        computed_process_partition_list,
+        computed_process_partition_list.clone(),
         cluster_info.clone(),
         false,
         false,
     );
     let partition_info = VineyardMultiPartition::new(partition_manager, partition_server_index_map.clone());
-    let job_assembly = initialize_job_assembly(gs_store, Arc::new(partition_info), cluster_info);
+    let mut job_assembly = if let Some(schema_file) = config_map.get("graph.vineyard.delta.schema") {
+        // the vertices and edges written are appended to the delta, read alongside the fragments
+        let schema: schema_pb::Schema = serde_json::from_str(&std::fs::read_to_string(schema_file)?)?;
+        // the vertices of the fragments are looked up by the primary keys on any of the partitions
+        let fragments = gs_store.clone();
+        let delta = Arc::new(VineyardDelta::new(
+            &schema,
+            partition_server_index_map.len() as u32,
+            computed_process_partition_list,
+            Arc::new(move |label: LabelId, pk: &PKV| fragments.get_vertex_id_by_primary_key(label, pk)),
+        )?);
+        register_write_graph(Arc::new(Mutex::new(VineyardDeltaWriter::new(delta.clone()))));
+        info!("Start executor with the delta of vineyard graph of schema {:?}", schema_file);
+        let graph = Arc::new(VineyardDeltaGraph::new(gs_store, delta, cluster_info.clone()));
+        initialize_job_assembly(graph, Arc::new(partition_info), cluster_info)
+    } else {
+        initialize_job_assembly(gs_store, Arc::new(partition_info), cluster_info)
+    };
+    if let Some(sessions) = SessionRegistry::from_options(&config_map)? {
+        job_assembly = job_assembly.with_sessions(Arc::new(sessions));
+    }
+    register_extensions(&config_map)?;
+    // the standing queries are materialized from the graph registered above
+    if let Some(standing_queries) = register_standing_queries(&config_map)? {
+        job_assembly = job_assembly.with_standing_queries(standing_queries);
+    }
+    let triggers = Arc::new(TriggerRegistry::default());
+    // the pending firings of the triggers are retried until the server is stopped
+    let _trigger_retry = start_triggers(&config_map, &triggers)?;
+    job_assembly = job_assembly.with_triggers(triggers);
+    // the stored procedures are installed by the admins on `/admin/procedures` of the metrics server
+    let procedures = Arc::new(ProcedureRegistry::default());
+    pegasus_server::admin::set_procedure_admin(procedures.clone());
+    job_assembly = job_assembly.with_procedures(procedures);
+    // the deleted elements are purged until the server is stopped
+    let _purge = start_purge_by(&config_map, worker_thread_num as u32)?;
     start_rpc_server(server_id, rpc_config, job_assembly, GaiaServiceListener).await?;
     Ok(())
 }
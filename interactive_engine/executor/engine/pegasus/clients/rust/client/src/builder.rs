@@ -550,6 +550,7 @@ impl JobBuilder {
                 }
                 ServerConf::All => Some(pegasus_pb::job_config::Servers::All(pegasus_pb::Empty {})),
             },
+            deterministic: self.conf.deterministic,
         };
 
         let source = pb::Source { resource: self.source };
@@ -344,9 +344,11 @@ impl<T: Decode> Decode for Option<T> {
 
 mod shade;
 mod third_party;
+mod version;
 pub use shade::ShadeCodec;
 #[cfg(feature = "serde")]
 pub use third_party::serde_bin as serde;
+pub use version::{protocol_version, with_protocol_version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
 
 #[cfg(test)]
 mod test {
@@ -0,0 +1,68 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The versions of the protocol of the data shuffled between the servers, which is negotiated for each
+//! connection, so that the servers of adjacent versions can run in one cluster during a rolling upgrade:
+//! * `1`: the legacy protocol, whose servers don't negotiate the version;
+//! * `2`: the data of a message are prefixed by the codec, and may be compressed and batched, see
+//!   `pegasus_network::shuffle`;
+//! * `3`: the records of the IR runtime may carry a sack, which the servers of older versions don't
+//!   decode;
+//! * `4`: the entries of the IR runtime may be the maps nesting the maps, e.g., the trees of `tree()`,
+//!   which are sent to the servers of older versions as the collections of the pairs.
+//!
+//! An `Encode` changing its encoding in a new version must encode as the old one if the protocol
+//! version of the receiver, i.e., `protocol_version()`, is older; the `Decode` must decode both.
+
+use std::cell::Cell;
+
+/// The latest version of the protocol this server speaks;
+pub const PROTOCOL_VERSION: u32 = 4;
+/// The oldest version of the protocol this server speaks;
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+thread_local! {
+    static PEER_PROTOCOL_VERSION: Cell<u32> = Cell::new(PROTOCOL_VERSION);
+}
+
+/// The protocol version negotiated with the server which the data are being encoded for, which is the
+/// latest version if the data are not sent to another server;
+pub fn protocol_version() -> u32 {
+    PEER_PROTOCOL_VERSION.with(|v| v.get())
+}
+
+/// Call `func` encoding the data for the server of the protocol `version`;
+pub fn with_protocol_version<R, F: FnOnce() -> R>(version: u32, func: F) -> R {
+    let prev = PEER_PROTOCOL_VERSION.with(|v| v.replace(version));
+    let result = func();
+    PEER_PROTOCOL_VERSION.with(|v| v.set(prev));
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn protocol_version_test() {
+        assert_eq!(protocol_version(), PROTOCOL_VERSION);
+        let version = with_protocol_version(MIN_PROTOCOL_VERSION, || {
+            assert_eq!(with_protocol_version(PROTOCOL_VERSION, protocol_version), PROTOCOL_VERSION);
+            protocol_version()
+        });
+        assert_eq!(version, MIN_PROTOCOL_VERSION);
+        assert_eq!(protocol_version(), PROTOCOL_VERSION);
+    }
+}
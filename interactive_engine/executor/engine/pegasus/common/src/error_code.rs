@@ -0,0 +1,164 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The stable numeric codes of the errors reported to the clients. The codes are grouped by the layer
+//! where an error occurs:
+//! * `1xxx`: parsing the plan;
+//! * `2xxx`: building the job of the plan;
+//! * `3xxx`: executing the operators of the job;
+//! * `4xxx`: querying or writing the store, where `41xx` are the codes of the groot store;
+//! * `5xxx`: the engine, e.g., the network or the cancellation of a job.
+//!
+//! A code is never reused for another error once released, so that the clients can rely on it.
+
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+
+/// Whether an error is caused by the query itself, which won't succeed until the query is fixed, or by
+/// the system, e.g., the store or the network.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ErrorCategory {
+    User,
+    System,
+}
+
+impl Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCategory::User => write!(f, "user"),
+            ErrorCategory::System => write!(f, "system"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ErrorCode {
+    pub code: u32,
+    pub category: ErrorCategory,
+    /// Whether the same query may succeed if it's submitted again.
+    pub retryable: bool,
+}
+
+impl ErrorCode {
+    pub const fn user(code: u32) -> Self {
+        ErrorCode { code, category: ErrorCategory::User, retryable: false }
+    }
+
+    pub const fn system(code: u32, retryable: bool) -> Self {
+        ErrorCode { code, category: ErrorCategory::System, retryable }
+    }
+
+    pub fn is_user_error(&self) -> bool {
+        self.category == ErrorCategory::User
+    }
+
+    /// The code of an error without any code in its chain.
+    pub const UNKNOWN: ErrorCode = ErrorCode::system(5000, false);
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "E{}", self.code)
+    }
+}
+
+/// An error of its code, which chains to its cause by `source`.
+pub struct CodedError {
+    code: ErrorCode,
+    cause: Box<dyn Error + Send + Sync>,
+}
+
+impl CodedError {
+    pub fn new<E: Into<Box<dyn Error + Send + Sync>>>(code: ErrorCode, cause: E) -> Self {
+        CodedError { code, cause: cause.into() }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    pub fn cause(&self) -> &(dyn Error + Send + Sync + 'static) {
+        self.cause.as_ref()
+    }
+}
+
+impl Debug for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {:?}", self.code, self.cause)
+    }
+}
+
+impl Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.code, self.cause)
+    }
+}
+
+impl Error for CodedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.cause.as_ref())
+    }
+}
+
+/// The code of the outermost `CodedError` in the chain of the error, following `source`.
+pub fn find_code(err: &(dyn Error + 'static)) -> Option<ErrorCode> {
+    let mut next = Some(err);
+    while let Some(err) = next {
+        if let Some(coded) = err.downcast_ref::<CodedError>() {
+            return Some(coded.code);
+        }
+        next = err.source();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Wrapper(Box<dyn Error + Send + Sync>);
+
+    impl Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "wrapped: {}", self.0)
+        }
+    }
+
+    impl Error for Wrapper {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(self.0.as_ref())
+        }
+    }
+
+    #[test]
+    fn test_find_code() {
+        let store = CodedError::new(ErrorCode::system(4001, true), "connection reset");
+        assert_eq!(store.to_string(), "[E4001] connection reset");
+        let err = Wrapper(Box::new(store));
+        assert_eq!(find_code(&err), Some(ErrorCode::system(4001, true)));
+
+        // the outermost code wins
+        let exec = CodedError::new(ErrorCode::user(3001), err);
+        let err = Wrapper(Box::new(exec));
+        let code = find_code(&err).unwrap();
+        assert_eq!(code.code, 3001);
+        assert!(code.is_user_error());
+        assert!(!code.retryable);
+
+        let err = Wrapper("no code".into());
+        assert_eq!(find_code(&err), None);
+    }
+}
@@ -22,6 +22,7 @@ pub mod channel;
 pub mod codec;
 pub mod collections;
 pub mod downcast;
+pub mod error_code;
 pub mod io;
 pub mod logs;
 pub mod queue;
@@ -603,14 +603,20 @@ impl ExecutorRuntime {
 
 static CORE_POOL_SIZE: &'static str = "PEGASUS_CORE_POOL_SIZE";
 
-pub fn init_executor() -> (Mutex<Option<ExecutorRuntime>>, ExecutorProxy) {
-    let core = ::std::env::var(CORE_POOL_SIZE)
+/// The number of threads the [`Executor`] runs tasks in, by `PEGASUS_CORE_POOL_SIZE` or the number of
+/// cpus; `0` runs the tasks directly in the thread spawning them.
+pub fn core_pool_size() -> usize {
+    ::std::env::var(CORE_POOL_SIZE)
         .map(|value| {
             value
                 .parse::<usize>()
                 .unwrap_or_else(|_| num_cpus::get())
         })
-        .unwrap_or_else(|_| num_cpus::get());
+        .unwrap_or_else(|_| num_cpus::get())
+}
+
+pub fn init_executor() -> (Mutex<Option<ExecutorRuntime>>, ExecutorProxy) {
+    let core = core_pool_size();
     if core > 0 {
         let (tx, rx) = crossbeam_channel::unbounded();
         let executor = PooledExecutorRuntime::new(core, rx);
@@ -15,10 +15,13 @@
 
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
 use serde::Deserialize;
 
+pub use crate::shuffle::{Compression, ShuffleParams};
+pub use crate::transport::tls::{TlsConfig, TlsParams};
 use crate::{NetError, Server};
 
 pub const DEFAULT_HEARTBEAT_INTERVAL_SEC: usize = 5;
@@ -74,18 +77,20 @@ impl Default for ReadParams {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct ConnectionParams {
     pub is_nonblocking: bool,
     write: WriteParams,
     read: ReadParams,
+    tls: Option<Arc<TlsParams>>,
+    shuffle: ShuffleParams,
 }
 
 impl ConnectionParams {
     pub fn nonblocking() -> Self {
         let write = WriteParams::default();
         let read = ReadParams::default();
-        ConnectionParams { is_nonblocking: true, write, read }
+        ConnectionParams { is_nonblocking: true, write, read, tls: None, shuffle: ShuffleParams::default() }
     }
 
     pub fn blocking() -> Self {
@@ -93,7 +98,13 @@ impl ConnectionParams {
         write.mode = BlockMode::Blocking(None);
         let mut read = ReadParams::default();
         read.mode = BlockMode::Blocking(None);
-        ConnectionParams { is_nonblocking: false, write, read }
+        ConnectionParams {
+            is_nonblocking: false,
+            write,
+            read,
+            tls: None,
+            shuffle: ShuffleParams::default(),
+        }
     }
 
     pub fn set_read_timeout(&mut self, timeout: Duration) {
@@ -128,6 +139,20 @@ impl ConnectionParams {
         self.write.heartbeat = interval;
     }
 
+    /// Encrypt the connections with mutual tls;
+    pub fn set_tls(&mut self, tls: TlsParams) {
+        self.tls = Some(Arc::new(tls));
+    }
+
+    /// Compress or batch the data sent by the IPC channels;
+    pub fn set_shuffle(&mut self, shuffle: ShuffleParams) {
+        self.shuffle = shuffle;
+    }
+
+    pub fn get_shuffle_params(&self) -> &ShuffleParams {
+        &self.shuffle
+    }
+
     pub fn get_write_params(&self) -> &WriteParams {
         &self.write
     }
@@ -136,6 +161,10 @@ impl ConnectionParams {
         &self.read
     }
 
+    pub fn get_tls_params(&self) -> Option<&TlsParams> {
+        self.tls.as_deref()
+    }
+
     pub(crate) fn get_hb_interval_sec(&self) -> u32 {
         self.write.heartbeat as u32
     }
@@ -153,6 +182,10 @@ pub struct NetworkConfig {
     no_delay: Option<bool>,
     send_buffer: Option<u32>,
     heartbeat_sec: Option<u32>,
+    tls: Option<TlsConfig>,
+    compression: Option<Compression>,
+    compression_level: Option<i32>,
+    batch_bytes: Option<u32>,
     servers: Option<Vec<Option<ServerAddr>>>,
 }
 
@@ -231,6 +264,10 @@ impl NetworkConfig {
             no_delay: None,
             send_buffer: None,
             heartbeat_sec: None,
+            tls: None,
+            compression: None,
+            compression_level: None,
+            batch_bytes: None,
             servers,
         }
     }
@@ -262,6 +299,10 @@ impl NetworkConfig {
             no_delay: None,
             send_buffer: None,
             heartbeat_sec: None,
+            tls: None,
+            compression: None,
+            compression_level: None,
+            batch_bytes: None,
             servers,
         }
     }
@@ -360,6 +401,47 @@ impl NetworkConfig {
         self
     }
 
+    pub fn tls(&mut self, v: Option<TlsConfig>) -> &mut Self {
+        self.tls = v;
+        self
+    }
+
+    pub fn compression(&mut self, v: Option<Compression>) -> &mut Self {
+        if let Some(v) = v {
+            self.set_compression(v);
+        }
+        self
+    }
+
+    pub fn set_compression(&mut self, compression: Compression) -> &mut Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    pub fn compression_level(&mut self, v: Option<i32>) -> &mut Self {
+        if let Some(v) = v {
+            self.set_compression_level(v);
+        }
+        self
+    }
+
+    pub fn set_compression_level(&mut self, level: i32) -> &mut Self {
+        self.compression_level = Some(level);
+        self
+    }
+
+    pub fn batch_bytes(&mut self, v: Option<u32>) -> &mut Self {
+        if let Some(v) = v {
+            self.set_batch_bytes(v);
+        }
+        self
+    }
+
+    pub fn set_batch_bytes(&mut self, bytes: u32) -> &mut Self {
+        self.batch_bytes = Some(bytes);
+        self
+    }
+
     pub fn set_server_addr(&mut self, server_id: u64, addr: ServerAddr) -> Result<&mut Self, NetError> {
         if server_id as usize >= self.servers_size {
             Err(NetError::InvalidConfig(Some(format!(
@@ -396,7 +478,7 @@ impl NetworkConfig {
         }
     }
 
-    pub fn get_connection_param(&self) -> ConnectionParams {
+    pub fn get_connection_param(&self) -> Result<ConnectionParams, NetError> {
         let mut params = if self.nonblocking.unwrap_or(false) {
             ConnectionParams::nonblocking()
         } else {
@@ -434,7 +516,23 @@ impl NetworkConfig {
             }
         }
 
-        params
+        if let Some(ref tls) = self.tls {
+            params.set_tls(tls.build()?);
+        }
+
+        let mut shuffle = ShuffleParams::default();
+        if let Some(compression) = self.compression {
+            shuffle.compression = compression;
+        }
+        if let Some(level) = self.compression_level {
+            shuffle.level = level;
+        }
+        if let Some(batch_bytes) = self.batch_bytes {
+            shuffle.batch_bytes = batch_bytes as usize;
+        }
+        params.set_shuffle(shuffle);
+
+        Ok(params)
     }
 
     pub fn get_servers(&self) -> Result<Option<Vec<Server>>, NetError> {
@@ -518,8 +616,9 @@ mod test {
         println!("get config {:?}", config);
         assert_eq!(config.server_id, 0);
         assert_eq!(config.nonblocking, Some(false));
-        let params = config.get_connection_param();
+        let params = config.get_connection_param().unwrap();
         assert!(!params.is_nonblocking);
+        assert!(params.get_tls_params().is_none());
         let wp = params.get_write_params();
         assert_eq!(wp.mode, BlockMode::Blocking(Some(Duration::from_millis(8))));
         let rp = params.get_read_params();
@@ -534,6 +633,28 @@ mod test {
         assert_eq!(peers[0].addr, "127.0.0.1:8080".parse().unwrap());
         assert_eq!(peers[1].id, 1);
         assert_eq!(peers[1].addr, "127.0.0.1:8081".parse().unwrap());
+        assert_eq!(params.get_shuffle_params(), &ShuffleParams::default());
+    }
+
+    #[test]
+    fn toml_shuffle_config_test() {
+        let content = r#"
+            server_id = 0
+            servers_size = 1
+            compression = "zstd"
+            compression_level = 1
+            batch_bytes = 65536
+        "#;
+
+        let config = NetworkConfig::parse(content).unwrap();
+        let shuffle = *config
+            .get_connection_param()
+            .unwrap()
+            .get_shuffle_params();
+        assert_eq!(shuffle.compression, Compression::Zstd);
+        assert_eq!(shuffle.level, 1);
+        assert_eq!(shuffle.batch_bytes, 65536);
+        assert!(shuffle.is_compressed());
     }
 
     #[test]
@@ -31,6 +31,8 @@ pub enum NetError {
     HostParseError(String),
     HBAbnormal(SocketAddr),
     ChannelRxReset(u128),
+    /// The remote server, the range of the local protocol versions, and the one of the remote;
+    IncompatibleProtocol(u64, (u32, u32), (u32, u32)),
 }
 
 impl Display for NetError {
@@ -76,6 +78,13 @@ impl Display for NetError {
             NetError::ChannelRxReset(id) => {
                 write!(f, "channel {}'s receiver is already in use, multi-receivers is not allowed;", id)
             }
+            NetError::IncompatibleProtocol(id, local, remote) => {
+                write!(
+                    f,
+                    "server {} speaks protocol versions {:?}, incompatible with the local {:?};",
+                    id, remote, local
+                )
+            }
         }
     }
 }
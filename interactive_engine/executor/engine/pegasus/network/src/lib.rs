@@ -41,6 +41,7 @@ pub fn start_up<D: ServerDetect + 'static, A: ToSocketAddrs>(
     server_id: u64, conf: ConnectionParams, addr: A, detect: D,
 ) -> Result<SocketAddr, NetError> {
     info!("start server {} ...", server_id);
+    shuffle::set_shuffle_params(server_id, *conf.get_shuffle_params());
     let mut mgr = manager::ServerManager::new(server_id, conf, detect);
     {
         let mut lock = SHUTDOWN_HOOK
@@ -131,8 +132,10 @@ pub mod config;
 mod error;
 mod manager;
 mod message;
+mod metrics;
 mod receive;
 mod send;
+pub mod shuffle;
 mod state;
 mod transport;
 
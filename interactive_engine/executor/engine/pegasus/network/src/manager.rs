@@ -43,7 +43,7 @@ impl ServerManager {
     }
 
     pub fn bind<A: ToSocketAddrs>(&self, addr: A) -> Result<SocketAddr, NetError> {
-        let addr = crate::transport::block::listen_on(self.server_id, self.conn_params, addr)?;
+        let addr = crate::transport::block::listen_on(self.server_id, self.conn_params.clone(), addr)?;
         Ok(addr)
     }
 
@@ -51,7 +51,7 @@ impl ServerManager {
         for s in self.peer_detect.fetch() {
             if s.id < self.server_id && !crate::state::is_connected(self.server_id, s.id) {
                 if let Err(e) =
-                    crate::transport::block::connect(self.server_id, s.id, self.conn_params, s.addr)
+                    crate::transport::block::connect(self.server_id, s.id, self.conn_params.clone(), s.addr)
                 {
                     error!("fail to connect server[id={},addr={:?}], caused by {}", s.id, s.addr, e);
                 }
@@ -0,0 +1,62 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use prometheus::{
+    exponential_buckets, register_histogram, register_int_counter_vec, Histogram, IntCounterVec,
+};
+
+lazy_static! {
+    /// Bytes written to the connections to remote servers, including heartbeats;
+    pub static ref SENT_BYTES: IntCounterVec = register_int_counter_vec!(
+        "pegasus_network_sent_bytes_total",
+        "Bytes sent to remote servers.",
+        &["remote"]
+    )
+    .unwrap();
+    /// Bytes of the application data received from remote servers;
+    pub static ref RECEIVED_BYTES: IntCounterVec = register_int_counter_vec!(
+        "pegasus_network_received_bytes_total",
+        "Bytes of data received from remote servers.",
+        &["remote"]
+    )
+    .unwrap();
+    /// Bytes of the data before and after compressed, the compression ratio is the latter over the former;
+    pub static ref COMPRESSION_INPUT_BYTES: IntCounterVec = register_int_counter_vec!(
+        "pegasus_network_compression_input_bytes_total",
+        "Bytes of data to compress before sent to remote servers.",
+        &["codec"]
+    )
+    .unwrap();
+    pub static ref COMPRESSION_OUTPUT_BYTES: IntCounterVec = register_int_counter_vec!(
+        "pegasus_network_compression_output_bytes_total",
+        "Bytes of data compressed before sent to remote servers.",
+        &["codec"]
+    )
+    .unwrap();
+    /// Seconds to serialize and compress the data of a message;
+    pub static ref ENCODE_SECONDS: Histogram = register_histogram!(
+        "pegasus_network_encode_seconds",
+        "Seconds to serialize the data sent to remote servers.",
+        exponential_buckets(0.000001, 4.0, 12).unwrap()
+    )
+    .unwrap();
+    /// Seconds to decompress and deserialize the data of a message;
+    pub static ref DECODE_SECONDS: Histogram = register_histogram!(
+        "pegasus_network_decode_seconds",
+        "Seconds to deserialize the data received from remote servers.",
+        exponential_buckets(0.000001, 4.0, 12).unwrap()
+    )
+    .unwrap();
+}
@@ -13,17 +13,18 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io;
-use std::net::TcpStream;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 
 use crossbeam_utils::sync::ShardedLock;
 use pegasus_common::channel::{MPMCReceiver, MPMCSender, MessageReceiver};
 use pegasus_common::codec::Decode;
 
 use crate::message::Payload;
+use crate::transport::ReadHalf;
 use crate::{NetError, Server};
 
 mod decode;
@@ -36,18 +37,30 @@ use crate::config::{BlockMode::Blocking, ConnectionParams};
 /// The receiver for network's applications to receive data from all remote peers;
 pub struct IPCReceiver<T> {
     inbox: MessageReceiver<Payload>,
-    _ph: std::marker::PhantomData<T>,
+    /// The data batched in the messages received but not taken yet;
+    pending: VecDeque<T>,
 }
 
 impl<T: Decode> IPCReceiver<T> {
     pub fn new(inbox: MessageReceiver<Payload>) -> Self {
-        IPCReceiver { inbox, _ph: std::marker::PhantomData }
+        IPCReceiver { inbox, pending: VecDeque::new() }
     }
 
-    pub fn recv(&self) -> io::Result<Option<T>> {
+    pub fn recv(&mut self) -> io::Result<Option<T>> {
+        if let Some(item) = self.pending.pop_front() {
+            return Ok(Some(item));
+        }
         if let Some(payload) = self.inbox.try_recv()? {
+            let start = Instant::now();
+            // decompressed by the receiver of the connection, see `shuffle::decompress`;
             let mut reader = payload.as_ref();
             let item = T::read_from(&mut reader)?;
+            // the rest of the data batched in the message, see `ShuffleParams::batch_bytes`;
+            while !reader.is_empty() {
+                self.pending
+                    .push_back(T::read_from(&mut reader)?);
+            }
+            crate::metrics::DECODE_SECONDS.observe(start.elapsed().as_secs_f64());
             Ok(Some(item))
         } else {
             Ok(None)
@@ -111,17 +124,18 @@ pub fn register_remotes_receiver<T: Decode + 'static>(
 }
 
 pub fn start_net_receiver(
-    local: u64, remote: Server, hb_sec: u32, params: &ConnectionParams, poisoned: Arc<AtomicBool>,
-    conn: TcpStream,
+    local: u64, remote: Server, hb_sec: u32, protocol_version: u32, params: &ConnectionParams,
+    poisoned: Arc<AtomicBool>, conn: ReadHalf,
 ) {
     //    let decoder = DefaultBlockDecoder::new(conn);
     if let Blocking(timeout) = params.get_read_params().mode {
-        conn.set_read_timeout(timeout).ok();
+        conn.socket().set_read_timeout(timeout).ok();
     }
 
     let slab_size = params.get_read_params().slab_size;
     let decoder = self::decode::get_reentrant_decoder(slab_size);
-    let mut net_recv = NetReceiver::new(hb_sec as u64, remote.addr, conn, decoder);
+    let mut net_recv =
+        NetReceiver::new(hb_sec as u64, remote.addr, conn, decoder).with_protocol_version(protocol_version);
     let register = net_recv.get_inbox_register();
     add_remote_register(local, remote.id, register);
     //let disconnected = state.clone();
@@ -24,9 +24,11 @@ use std::time::Instant;
 use crossbeam_queue::SegQueue;
 use crossbeam_utils::sync::ShardedLock;
 use pegasus_common::channel::{MPMCSender, MessageSender};
+use prometheus::IntCounter;
 
 use crate::message::{Message, Payload};
 use crate::receive::MessageDecoder;
+use crate::shuffle::SHUFFLE_PROTOCOL_VERSION;
 use crate::NetError;
 
 /// Inbound mailbox of a data receiver;
@@ -221,6 +223,9 @@ pub(crate) struct NetReceiver<R: Read, D: MessageDecoder> {
     decoder: D,
     last_recv: Instant,
     inbox_table: ReadOptInboxTable,
+    received_bytes: IntCounter,
+    /// Whether the data of the messages are prefixed by the codec, see `shuffle::SHUFFLE_PROTOCOL_VERSION`;
+    is_framed: bool,
 }
 
 impl<R: Read, D: MessageDecoder> NetReceiver<R, D> {
@@ -232,9 +237,17 @@ impl<R: Read, D: MessageDecoder> NetReceiver<R, D> {
             decoder,
             last_recv: Instant::now(),
             inbox_table: ReadOptInboxTable::new(),
+            received_bytes: crate::metrics::RECEIVED_BYTES.with_label_values(&[&addr.to_string()]),
+            is_framed: false,
         }
     }
 
+    /// Set the protocol version negotiated with the remote server, which the data are sent by;
+    pub fn with_protocol_version(mut self, protocol_version: u32) -> Self {
+        self.is_framed = protocol_version >= SHUFFLE_PROTOCOL_VERSION;
+        self
+    }
+
     pub fn recv(&mut self) -> Result<(), NetError> {
         if let Some(msg) = decode_next(&mut self.reader, &mut self.decoder)? {
             let (header, payload) = msg.separate();
@@ -245,6 +258,8 @@ impl<R: Read, D: MessageDecoder> NetReceiver<R, D> {
                 debug!("receive  exhaust signal of channel {} from {:?};", header.channel_id, self.addr);
                 self.inbox_table.close(header.channel_id);
             } else {
+                self.received_bytes.inc_by(payload.len() as u64);
+                let payload = if self.is_framed { crate::shuffle::decompress(payload)? } else { payload };
                 self.inbox_table
                     .dispatch(header.channel_id, payload);
             }
@@ -16,18 +16,20 @@
 use std::collections::HashMap;
 use std::io;
 use std::io::Write;
-use std::net::{SocketAddr, TcpStream};
+use std::net::SocketAddr;
 use std::ops::Deref;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
 use crossbeam_utils::sync::ShardedLock;
-use pegasus_common::codec::Encode;
+use pegasus_common::codec::{with_protocol_version, AsBytes, Encode, PROTOCOL_VERSION};
 
 use crate::config::{BlockMode, ConnectionParams, DEFAULT_SLAB_SIZE};
-use crate::message::MessageHeader;
+use crate::message::{MessageHeader, Payload, MESSAGE_HEAD_SIZE};
+use crate::shuffle::{ShuffleParams, SHUFFLE_PROTOCOL_VERSION};
+use crate::transport::WriteHalf;
 use crate::{NetError, Server};
 
 mod encode;
@@ -41,15 +43,68 @@ pub struct IPCSender<T: Encode> {
     pub channel_id: u128,
     sequence: u64,
     encoder: GeneralEncoder<T>,
+    /// Used only if the target speaks `SHUFFLE_PROTOCOL_VERSION` or later, as an older one can't
+    /// decode the data prefixed by the codec, compressed or batched;
+    shuffle: ShuffleParams,
+    /// The protocol version negotiated with the target, which the data are encoded by;
+    protocol_version: u32,
+    /// The data batched but not sent yet, see `ShuffleParams::batch_bytes`;
+    pending: Vec<u8>,
+    /// The bytes the data are batched up to, adapted to the ratio they are compressed at, see
+    /// `ShuffleParams::adapt_batch_bytes`;
+    batch_threshold: usize,
     outbox_tx: Sender<NetData>,
     close_guard: Arc<AtomicUsize>,
 }
 
 impl<T: Encode> IPCSender<T> {
     pub fn send(&mut self, msg: &T) -> io::Result<()> {
+        let start = Instant::now();
+        let protocol_version = self.protocol_version;
+        if protocol_version >= SHUFFLE_PROTOCOL_VERSION {
+            let pending = &mut self.pending;
+            with_protocol_version(protocol_version, || msg.write_to(pending))?;
+            crate::metrics::ENCODE_SECONDS.observe(start.elapsed().as_secs_f64());
+            if self.pending.len() >= self.batch_threshold {
+                self.send_pending()?;
+            }
+            return Ok(());
+        }
+        let mut header = MessageHeader::new(self.channel_id);
+        header.sequence = self.sequence;
+        let encoder = &mut self.encoder;
+        let payload = with_protocol_version(protocol_version, || encoder.encode(&mut header, msg))?;
+        crate::metrics::ENCODE_SECONDS.observe(start.elapsed().as_secs_f64());
+        self.send_payload(payload)
+    }
+
+    /// Send the data batched, if any;
+    pub fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            self.send_pending()?;
+        }
+        Ok(())
+    }
+
+    fn send_pending(&mut self) -> io::Result<()> {
+        let start = Instant::now();
+        let data = std::mem::replace(&mut self.pending, vec![]);
+        let raw_bytes = data.len();
+        let content = self.shuffle.compress(data)?;
+        self.batch_threshold =
+            self.shuffle
+                .adapt_batch_bytes(self.batch_threshold, raw_bytes, content.len());
         let mut header = MessageHeader::new(self.channel_id);
         header.sequence = self.sequence;
-        let payload = self.encoder.encode(&mut header, msg)?;
+        header.length = content.len() as u64;
+        let mut buffer = Vec::with_capacity(MESSAGE_HEAD_SIZE + content.len());
+        buffer.write_all(header.as_bytes())?;
+        buffer.extend_from_slice(&content);
+        crate::metrics::ENCODE_SECONDS.observe(start.elapsed().as_secs_f64());
+        self.send_payload(buffer.into())
+    }
+
+    fn send_payload(&mut self, payload: Payload) -> io::Result<()> {
         self.outbox_tx
             .send(NetData::AppData(self.channel_id, payload))
             .map_err(|_| {
@@ -61,6 +116,7 @@ impl<T: Encode> IPCSender<T> {
     }
 
     pub fn close(&mut self) -> io::Result<()> {
+        self.flush()?;
         if self.close_guard.fetch_sub(1, Ordering::SeqCst) == 1 {
             let mut header = MessageHeader::new(self.channel_id);
             header.sequence = 0;
@@ -76,12 +132,19 @@ impl<T: Encode> IPCSender<T> {
 }
 
 impl<T: Encode + 'static> IPCSender<T> {
-    fn new(target: SocketAddr, channel_id: u128, outbox_tx: Sender<NetData>) -> Self {
+    fn new(
+        target: SocketAddr, channel_id: u128, outbox_tx: Sender<NetData>, shuffle: ShuffleParams,
+        protocol_version: u32,
+    ) -> Self {
         IPCSender {
             target,
             channel_id,
             sequence: 1,
             encoder: SlabEncoder::new(DEFAULT_SLAB_SIZE).into(),
+            shuffle,
+            protocol_version,
+            pending: vec![],
+            batch_threshold: shuffle.batch_bytes,
             outbox_tx,
             close_guard: Arc::new(AtomicUsize::new(1)),
         }
@@ -104,6 +167,10 @@ impl<T: Encode + 'static> Clone for IPCSender<T> {
             channel_id: self.channel_id,
             sequence: 1,
             encoder: self.encoder.clone(),
+            shuffle: self.shuffle,
+            protocol_version: self.protocol_version,
+            pending: vec![],
+            batch_threshold: self.batch_threshold,
             outbox_tx: self.outbox_tx.clone(),
             close_guard: self.close_guard.clone(),
         }
@@ -172,13 +239,16 @@ pub fn fetch_remote_sender<T: Encode + 'static>(
     let lock = REMOTE_MSG_SENDER
         .read()
         .expect("REMOTE_MSG_SEND read lock poisoned");
+    let shuffle = crate::shuffle::get_shuffle_params(local);
     let mut app_senders = Vec::with_capacity(remotes.len());
     for id in remotes {
         if *id != local {
             if let Some((addr, tx)) = lock.get(&(local, *id)) {
                 if let Some(tx) = tx.upgrade() {
                     let tx = tx.deref().clone();
-                    let sender = IPCSender::<T>::new(*addr, channel_id, tx);
+                    let protocol_version =
+                        crate::state::get_protocol_version(local, *id).unwrap_or(PROTOCOL_VERSION);
+                    let sender = IPCSender::<T>::new(*addr, channel_id, tx, shuffle, protocol_version);
                     app_senders.push(sender);
                 } else {
                     return Err(NetError::NotConnected(*id));
@@ -193,18 +263,18 @@ pub fn fetch_remote_sender<T: Encode + 'static>(
 
 pub(crate) fn start_net_sender(
     local_id: u64, remote: Server, params: &ConnectionParams, state: &Arc<AtomicBool>,
-    recv_poisoned: &Arc<AtomicBool>, conn: TcpStream,
+    recv_poisoned: &Arc<AtomicBool>, conn: WriteHalf,
 ) {
     let mut is_block = !params.is_nonblocking;
     let params = params.get_write_params();
     match params.mode {
         BlockMode::Blocking(timeout) => {
-            conn.set_write_timeout(timeout).ok();
+            conn.socket().set_write_timeout(timeout).ok();
             is_block = false;
         }
         _ => (),
     }
-    conn.set_nodelay(params.nodelay).ok();
+    conn.socket().set_nodelay(params.nodelay).ok();
     let disconnected = state.clone();
     let timeout = params.wait_data as u64;
     let guard = if params.buffer > 0 {
@@ -219,11 +289,9 @@ pub(crate) fn start_net_sender(
                 busy_send(&mut net_tx, is_block, timeout, local_id, remote.id, recv_poisoned);
                 error!("Connection to server {} lost", remote.id);
                 disconnected.store(true, Ordering::SeqCst);
-                net_tx
-                    .take_writer()
-                    .get_ref()
-                    .shutdown(std::net::Shutdown::Write)
-                    .ok();
+                let mut writer = net_tx.take_writer();
+                writer.flush().ok();
+                writer.get_mut().shutdown().ok();
             })
             .expect("start net-sender thread failure;")
     } else {
@@ -237,10 +305,7 @@ pub(crate) fn start_net_sender(
                 busy_send(&mut net_tx, is_block, timeout, local_id, remote.id, recv_poisoned);
                 error!("Connection to server {} lost", remote.id);
                 disconnected.store(true, Ordering::SeqCst);
-                net_tx
-                    .take_writer()
-                    .shutdown(std::net::Shutdown::Write)
-                    .ok();
+                net_tx.take_writer().shutdown().ok();
             })
             .expect("start net-sender thread failure;")
     };
@@ -20,6 +20,7 @@ use std::sync::{Arc, Weak};
 use std::time::Duration;
 
 use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, TryRecvError};
+use prometheus::IntCounter;
 
 use crate::message::{Payload, DEFAULT_MESSAGE_HEADER_BYTES};
 
@@ -35,6 +36,7 @@ pub struct NetSender<W: Write> {
     outbox_tx: (Weak<Sender<NetData>>, Option<Arc<Sender<NetData>>>),
     conn: W,
     next: Option<NetData>,
+    sent_bytes: IntCounter,
 }
 
 impl<W: Write> NetSender<W> {
@@ -47,6 +49,7 @@ impl<W: Write> NetSender<W> {
             outbox_tx: (Arc::downgrade(&outbox_tx), Some(outbox_tx)),
             conn,
             next: None,
+            sent_bytes: crate::metrics::SENT_BYTES.with_label_values(&[&addr.to_string()]),
         }
     }
 
@@ -235,8 +238,12 @@ impl<W: Write> NetSender<W> {
                     super::report_network_error(ch_id, self.addr);
                     return Err(e);
                 }
+                self.sent_bytes.inc_by(data.len() as u64);
+            }
+            NetData::Heartbeat(data) => {
+                self.conn.write_all(data.as_ref())?;
+                self.sent_bytes.inc_by(data.len() as u64);
             }
-            NetData::Heartbeat(data) => self.conn.write_all(data.as_ref())?,
         }
         Ok(())
     }
@@ -247,6 +254,7 @@ impl<W: Write> NetSender<W> {
         loop {
             match self.conn.write(buf.as_ref()) {
                 Ok(size) => {
+                    self.sent_bytes.inc_by(size as u64);
                     let buf_len = buf.len();
                     if size == 0 && buf_len != 0 {
                         return Err(io::Error::from(io::ErrorKind::WriteZero));
@@ -0,0 +1,271 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The compression and the batching of the data sent by the IPC channels, which may be configured
+//! differently on the servers of a cluster:
+//! * `compression`: the data of a message is compressed by lz4 or zstd, where the data less than
+//!   `min_compress_bytes` or not smaller compressed is sent as it is;
+//! * `batch_bytes`: the data sent by a channel are batched into a message until they are over
+//!   `batch_bytes` or flushed, e.g., once an operator finishes a round of firing; so that the small
+//!   batches of the operators are sent in fewer and larger messages, compressed better. If compressed,
+//!   the data are batched up to `batch_bytes` scaled by the ratio the channel compresses its data at,
+//!   up to `MAX_BATCH_SCALE` times, so that the messages sent are of about `batch_bytes`.
+//!
+//! The data of every message sent to a server of `SHUFFLE_PROTOCOL_VERSION` or later is prefixed by a
+//! byte of its codec, whether it's compressed or not, so that the receiver decompresses each message by
+//! its own codec instead of the settings of the receiver; the data sent to an older server are neither
+//! prefixed, compressed nor batched.
+
+use std::collections::HashMap;
+use std::io;
+use std::str::FromStr;
+
+use crossbeam_utils::sync::ShardedLock;
+use serde::Deserialize;
+
+use crate::message::Payload;
+
+/// The protocol version since which the data are prefixed by the codec, see
+/// `pegasus_common::codec::PROTOCOL_VERSION`;
+pub const SHUFFLE_PROTOCOL_VERSION: u32 = 2;
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
+pub const DEFAULT_MIN_COMPRESS_BYTES: usize = 1024;
+/// The most times of `batch_bytes` the data are batched up to before compressed;
+pub const MAX_BATCH_SCALE: usize = 8;
+
+const RAW: u8 = 0;
+const LZ4: u8 = 1;
+const ZSTD: u8 = 2;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl Compression {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Lz4 => "lz4",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Compression::None),
+            "lz4" => Ok(Compression::Lz4),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!("unknown compression `{}`", s)),
+        }
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::None
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ShuffleParams {
+    pub compression: Compression,
+    /// The level of zstd, ignored by lz4;
+    pub level: i32,
+    pub min_compress_bytes: usize,
+    /// Not batched if it's 0;
+    pub batch_bytes: usize,
+}
+
+impl Default for ShuffleParams {
+    fn default() -> Self {
+        ShuffleParams {
+            compression: Compression::None,
+            level: DEFAULT_COMPRESSION_LEVEL,
+            min_compress_bytes: DEFAULT_MIN_COMPRESS_BYTES,
+            batch_bytes: 0,
+        }
+    }
+}
+
+impl ShuffleParams {
+    pub fn is_compressed(&self) -> bool {
+        self.compression != Compression::None
+    }
+
+    /// The bytes the data of a channel are batched up to after a batch of `raw_bytes` is sent in
+    /// `sent_bytes`, i.e. `batch_bytes` scaled by the ratio the batch is compressed at, averaged with
+    /// the `threshold` the batch is batched up to, as the batches are compressed at varied ratios;
+    pub fn adapt_batch_bytes(&self, threshold: usize, raw_bytes: usize, sent_bytes: usize) -> usize {
+        // the ratio of the data not compressed for their size is unknown
+        if self.batch_bytes == 0
+            || !self.is_compressed()
+            || raw_bytes < self.min_compress_bytes
+            || sent_bytes == 0
+        {
+            return threshold;
+        }
+        let scaled = (self.batch_bytes as u128 * raw_bytes as u128 / sent_bytes as u128) as usize;
+        let scaled = scaled.clamp(self.batch_bytes, self.batch_bytes.saturating_mul(MAX_BATCH_SCALE));
+        (threshold + scaled) / 2
+    }
+
+    /// Compress the data into the content of a message, prefixed by the codec;
+    pub fn compress(&self, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        if self.is_compressed() && data.len() >= self.min_compress_bytes {
+            let compressed = match self.compression {
+                Compression::Lz4 => Some((LZ4, lz4_flex::compress_prepend_size(&data))),
+                Compression::Zstd => Some((ZSTD, zstd::bulk::compress(&data, self.level)?)),
+                Compression::None => None,
+            };
+            if let Some((codec, compressed)) = compressed {
+                let codec_name = self.compression.as_str();
+                crate::metrics::COMPRESSION_INPUT_BYTES
+                    .with_label_values(&[codec_name])
+                    .inc_by(data.len() as u64);
+                crate::metrics::COMPRESSION_OUTPUT_BYTES
+                    .with_label_values(&[codec_name])
+                    .inc_by(compressed.len() as u64);
+                if compressed.len() < data.len() {
+                    let mut content = Vec::with_capacity(compressed.len() + 1);
+                    content.push(codec);
+                    content.extend_from_slice(&compressed);
+                    return Ok(content);
+                }
+            }
+        }
+        let mut content = Vec::with_capacity(data.len() + 1);
+        content.push(RAW);
+        content.extend_from_slice(&data);
+        Ok(content)
+    }
+}
+
+/// Decompress the content of a message prefixed by the codec into the data, see `ShuffleParams::compress`;
+pub fn decompress(mut content: Payload) -> io::Result<Payload> {
+    let codec = match content.as_ref().first() {
+        Some(codec) => *codec,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "no compression codec")),
+    };
+    match codec {
+        RAW => {
+            content.advance(1);
+            Ok(content)
+        }
+        LZ4 => lz4_flex::decompress_size_prepended(&content.as_ref()[1..])
+            .map(Payload::from)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+        ZSTD => Ok(zstd::stream::decode_all(&content.as_ref()[1..])?.into()),
+        codec => {
+            Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown compression codec {}", codec)))
+        }
+    }
+}
+
+lazy_static! {
+    static ref SHUFFLE_PARAMS: ShardedLock<HashMap<u64, ShuffleParams>> = ShardedLock::new(HashMap::new());
+}
+
+pub(crate) fn set_shuffle_params(server_id: u64, params: ShuffleParams) {
+    let mut lock = SHUFFLE_PARAMS
+        .write()
+        .expect("SHUFFLE_PARAMS write lock poisoned");
+    lock.insert(server_id, params);
+}
+
+pub(crate) fn get_shuffle_params(server_id: u64) -> ShuffleParams {
+    let lock = SHUFFLE_PARAMS
+        .read()
+        .expect("SHUFFLE_PARAMS read lock poisoned");
+    lock.get(&server_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn params(compression: Compression) -> ShuffleParams {
+        ShuffleParams { compression, min_compress_bytes: 16, ..Default::default() }
+    }
+
+    #[test]
+    fn compress_test() {
+        let data = vec![7u8; 4096];
+        for compression in vec![Compression::Lz4, Compression::Zstd] {
+            let params = params(compression);
+            let content = params.compress(data.clone()).unwrap();
+            assert!(content.len() < data.len());
+            assert_eq!(decompress(content.into()).unwrap().as_ref(), &data[..]);
+        }
+    }
+
+    #[test]
+    fn compress_small_test() {
+        let params = params(Compression::Lz4);
+        let content = params.compress(vec![1, 2, 3]).unwrap();
+        assert_eq!(content, vec![RAW, 1, 2, 3]);
+        assert_eq!(decompress(content.into()).unwrap().as_ref(), &[1, 2, 3]);
+        // uncompressed data are prefixed by the codec as well
+        let params = ShuffleParams::default();
+        let content = params.compress(vec![7u8; 4096]).unwrap();
+        assert_eq!(content[0], RAW);
+        assert_eq!(decompress(content.into()).unwrap().as_ref(), &[7u8; 4096][..]);
+    }
+
+    #[test]
+    fn adapt_batch_bytes_test() {
+        let params = ShuffleParams { batch_bytes: 1000, ..params(Compression::Lz4) };
+        // compressed at 4:1, the data are batched up to 4000 bytes at last
+        let mut threshold = params.batch_bytes;
+        for _ in 0..16 {
+            threshold = params.adapt_batch_bytes(threshold, 4000, 1000);
+        }
+        assert!(threshold > 3990 && threshold <= 4000);
+        // but never more than `MAX_BATCH_SCALE` times
+        assert_eq!(params.adapt_batch_bytes(8000, 100000, 10), 8000);
+        // and never less than `batch_bytes`, even if the data are not smaller compressed
+        assert_eq!(params.adapt_batch_bytes(1000, 1000, 1001), 1000);
+        // the small data not compressed are ignored
+        assert_eq!(params.adapt_batch_bytes(4000, 8, 9), 4000);
+        let params = ShuffleParams { batch_bytes: 1000, ..Default::default() };
+        assert_eq!(params.adapt_batch_bytes(1000, 4000, 1000), 1000);
+    }
+
+    #[test]
+    fn decompress_by_codec_test() {
+        // the data compressed by any settings are decompressed by the codec of the message
+        let data = vec![7u8; 4096];
+        let lz4 = params(Compression::Lz4)
+            .compress(data.clone())
+            .unwrap();
+        let zstd = params(Compression::Zstd)
+            .compress(data.clone())
+            .unwrap();
+        assert_eq!((lz4[0], zstd[0]), (LZ4, ZSTD));
+        assert_eq!(decompress(lz4.into()).unwrap().as_ref(), &data[..]);
+        assert_eq!(decompress(zstd.into()).unwrap().as_ref(), &data[..]);
+        assert!(decompress(vec![].into()).is_err());
+        assert!(decompress(vec![9, 1, 2].into()).is_err());
+    }
+}
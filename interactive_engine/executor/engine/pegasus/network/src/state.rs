@@ -25,6 +25,8 @@ struct ConnectionState {
     pub local_id: u64,
     pub remote_id: u64,
     addr: SocketAddr,
+    /// The protocol version negotiated with the remote;
+    protocol_version: u32,
     disconnected: Arc<AtomicBool>,
 }
 
@@ -40,13 +42,21 @@ lazy_static! {
     static ref ADDR_TO_ID: ShardedLock<HashMap<SocketAddr, u64>> = ShardedLock::new(HashMap::new());
 }
 
-pub fn add_connection(local_id: u64, remote_id: u64, addr: SocketAddr) -> Option<Arc<AtomicBool>> {
+pub fn add_connection(
+    local_id: u64, remote_id: u64, addr: SocketAddr, protocol_version: u32,
+) -> Option<Arc<AtomicBool>> {
     let disconnected = Arc::new(AtomicBool::new(false));
     {
         let mut states = CONNECTION_STATES
             .write()
             .expect("lock poisoned");
-        let st = ConnectionState { local_id, remote_id, addr, disconnected: disconnected.clone() };
+        let st = ConnectionState {
+            local_id,
+            remote_id,
+            addr,
+            protocol_version,
+            disconnected: disconnected.clone(),
+        };
         if let Some(s) = states.get_mut(&(local_id, remote_id)) {
             if !s.is_connected() {
                 *s = st;
@@ -78,6 +88,15 @@ pub fn is_connected(local_id: u64, remote_id: u64) -> bool {
             .unwrap_or(false)
 }
 
+/// The protocol version negotiated with the remote, if it's connected;
+pub fn get_protocol_version(local_id: u64, remote_id: u64) -> Option<u32> {
+    let states = CONNECTION_STATES.read().expect("lock poisoned");
+    states
+        .get(&(local_id, remote_id))
+        .filter(|s| s.is_connected())
+        .map(|s| s.protocol_version)
+}
+
 pub fn check_connect(local: u64, remotes: &[u64]) -> bool {
     let states = CONNECTION_STATES.read().expect("lock poisoned");
     let mut connect_status = true;
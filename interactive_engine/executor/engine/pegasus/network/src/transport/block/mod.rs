@@ -21,7 +21,7 @@ use std::time::Duration;
 
 use crate::receive::start_net_receiver;
 use crate::send::start_net_sender;
-use crate::transport::ConnectionParams;
+use crate::transport::{ConnectionParams, Handshake, ReadHalf, WriteHalf, LEGACY_PROTOCOL_VERSION};
 use crate::{NetError, Server};
 
 pub fn listen_on<A: ToSocketAddrs>(
@@ -37,43 +37,19 @@ pub fn listen_on<A: ToSocketAddrs>(
         .spawn(move || {
             while !crate::is_shutdown(server_id) {
                 match listener.accept() {
-                    Ok((mut stream, addr)) => {
-                        if let Ok(Some((remote_id, hb))) = super::check_connection(&mut stream) {
+                    Ok((stream, addr)) => {
+                        let (mut read_half, write_half) = match super::split(stream, &params, true) {
+                            Ok(halves) => halves,
+                            Err(e) => {
+                                warn!("setup connection from {:?} failure: {}, ignored;", addr, e);
+                                continue;
+                            }
+                        };
+                        if let Ok(Some(handshake)) = super::check_connection(&mut read_half) {
+                            let remote_id = handshake.server_id;
                             info!("accept new connection from server {} on {:?}", remote_id, addr);
                             if !crate::state::is_connected(server_id, remote_id) {
-                                // create network communication_old channel for lib user;
-                                let mut write_half = stream
-                                    .try_clone()
-                                    .expect("clone tcp stream failure;");
-                                if let Err(e) = super::setup_connection(server_id, hb_sec, &mut write_half)
-                                {
-                                    error!("write pass phrase to {:?} failure: {}", addr, e);
-                                } else {
-                                    let hook = crate::state::add_connection(server_id, remote_id, addr)
-                                        // add connection should never fail;
-                                        .expect("add connection failure");
-                                    let remote = Server { id: remote_id, addr };
-                                    if params.is_nonblocking {
-                                        stream.set_nonblocking(true).ok();
-                                    }
-                                    let recv_poisoned = Arc::new(AtomicBool::new(false));
-                                    start_net_sender(
-                                        server_id,
-                                        remote,
-                                        &params,
-                                        &hook,
-                                        &recv_poisoned,
-                                        write_half,
-                                    );
-                                    start_net_receiver(
-                                        server_id,
-                                        remote,
-                                        hb,
-                                        &params,
-                                        recv_poisoned,
-                                        stream,
-                                    );
-                                }
+                                accept(server_id, hb_sec, &params, addr, handshake, read_half, write_half);
                             } else {
                                 warn!("server {} is connected and already in use;", remote_id);
                             }
@@ -96,6 +72,45 @@ pub fn listen_on<A: ToSocketAddrs>(
     Ok(bind_addr)
 }
 
+/// Reply the handshake of the server with the protocol version negotiated, and start the network channels
+/// of it; or refuse it if none of the versions is spoken by both, where the legacy server is refused by
+/// closing the connection, as it can't tell the refusal;
+fn accept(
+    server_id: u64, hb_sec: u32, params: &ConnectionParams, addr: SocketAddr, handshake: Handshake,
+    read_half: ReadHalf, mut write_half: WriteHalf,
+) {
+    let remote_id = handshake.server_id;
+    let versions = super::local_versions();
+    let version = match super::negotiate(versions, handshake.versions) {
+        Some(version) => version,
+        None => {
+            let err = NetError::IncompatibleProtocol(remote_id, versions, handshake.versions);
+            warn!("refuse connection from {:?}: {}", addr, err);
+            if !handshake.is_legacy {
+                super::setup_connection(server_id, hb_sec, Some((0, 0)), &mut write_half).ok();
+            }
+            return;
+        }
+    };
+    // create network communication_old channel for lib user;
+    let reply = if handshake.is_legacy { None } else { Some((version, version)) };
+    if let Err(e) = super::setup_connection(server_id, hb_sec, reply, &mut write_half) {
+        error!("write pass phrase to {:?} failure: {}", addr, e);
+    } else {
+        info!("server {} speaks protocol version {};", remote_id, version);
+        let hook = crate::state::add_connection(server_id, remote_id, addr, version)
+            // add connection should never fail;
+            .expect("add connection failure");
+        let remote = Server { id: remote_id, addr };
+        if params.is_nonblocking {
+            read_half.socket().set_nonblocking(true).ok();
+        }
+        let recv_poisoned = Arc::new(AtomicBool::new(false));
+        start_net_sender(server_id, remote, params, &hook, &recv_poisoned, write_half);
+        start_net_receiver(server_id, remote, handshake.hb_sec, version, params, recv_poisoned, read_half);
+    }
+}
+
 /// 尝试建立新的TCP连接：
 /// - 参数 `addr`为期望建立连接的对端服务监听的 socket 地址；
 /// - 参数`server_id` 是对端服务的序号；
@@ -107,33 +122,67 @@ pub fn listen_on<A: ToSocketAddrs>(
 ///
 /// 如果参数中的`server_id` 大于等于当前服务的id，并不会发起连接，返回`Ok(())`;
 ///
+/// 与对端协商协议版本：先发送带版本范围的握手，旧版本(legacy)的对端会直接关闭连接，此时用旧的握手重新连接；
+///
 pub fn connect(
     local_id: u64, remote_id: u64, params: ConnectionParams, addr: SocketAddr,
+) -> Result<(), NetError> {
+    let versions = super::local_versions();
+    match connect_with(local_id, remote_id, &params, addr, Some(versions)) {
+        Err(NetError::IOError(e))
+            if matches!(e.kind(), io::ErrorKind::UnexpectedEof | io::ErrorKind::ConnectionReset)
+                && versions.0 <= LEGACY_PROTOCOL_VERSION =>
+        {
+            warn!("server {} closes the versioned handshake, connect it as legacy;", remote_id);
+            connect_with(local_id, remote_id, &params, addr, None)
+        }
+        res => res,
+    }
+}
+
+/// Connect with the handshake of the range of the protocol `versions`, or the legacy one if it's `None`;
+fn connect_with(
+    local_id: u64, remote_id: u64, params: &ConnectionParams, addr: SocketAddr,
+    versions: Option<(u32, u32)>,
 ) -> Result<(), NetError> {
     // 连接请求可能会失败， 或许由于对端服务器未启动端口监听，调用方需要根据返回内容确定是否重试;
     info!("Try to connect to server {:?}", addr);
     let timeout = std::time::Duration::from_secs(10);
-    let mut conn = TcpStream::connect_timeout(&addr, timeout)?;
+    let conn = TcpStream::connect_timeout(&addr, timeout)?;
     // let mut conn = TcpStream::connect(addr)?;
     let addr = conn.peer_addr()?;
     info!("connect to server {:?};", addr);
     let hb_sec = params.get_hb_interval_sec();
-    super::setup_connection(local_id, hb_sec, &mut conn)?;
+    let (mut read_half, mut write_half) = super::split(conn, params, false)?;
+    super::setup_connection(local_id, hb_sec, versions, &mut write_half)?;
     info!("setup connection to {:?} success;", addr);
-    if let Some((id, hb_sec)) = super::check_connection(&mut conn)? {
+    if let Some(handshake) = super::check_connection(&mut read_half)? {
+        let id = handshake.server_id;
         if id == remote_id {
-            info!("connect server {} on {:?} success;", remote_id, addr);
-            if let Some(state) = crate::state::add_connection(local_id, remote_id, addr) {
+            // the reply to the legacy handshake is always legacy;
+            let version = match versions {
+                Some(versions) => super::negotiate(versions, handshake.versions)
+                    .filter(|_| !handshake.is_refused())
+                    .ok_or(NetError::IncompatibleProtocol(remote_id, versions, handshake.versions))?,
+                None => LEGACY_PROTOCOL_VERSION,
+            };
+            info!("connect server {} on {:?} success, protocol version {};", remote_id, addr, version);
+            if let Some(state) = crate::state::add_connection(local_id, remote_id, addr, version) {
                 let remote = Server { id: remote_id, addr };
                 if params.is_nonblocking {
-                    conn.set_nonblocking(true).ok();
+                    read_half.socket().set_nonblocking(true).ok();
                 }
-                let read_half = conn
-                    .try_clone()
-                    .expect("clone tcp stream failure;");
                 let recv_poisoned = Arc::new(AtomicBool::new(false));
-                start_net_sender(local_id, remote, &params, &state, &recv_poisoned, conn);
-                start_net_receiver(local_id, remote, hb_sec, &params, recv_poisoned, read_half);
+                start_net_sender(local_id, remote, params, &state, &recv_poisoned, write_half);
+                start_net_receiver(
+                    local_id,
+                    remote,
+                    handshake.hb_sec,
+                    version,
+                    params,
+                    recv_poisoned,
+                    read_half,
+                );
             } else {
                 return Err(NetError::ConflictConnect(remote_id));
             }
@@ -13,45 +13,191 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+
+use pegasus_common::codec::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
 use pegasus_common::io::{ReadExt, WriteExt};
 
 use crate::config::*;
 
 pub(crate) mod block;
 mod nonblock;
+pub(crate) mod tls;
+
+/// The read half of a connection, either plain or over tls;
+pub(crate) enum ReadHalf {
+    Plain(TcpStream),
+    Tls(tls::TlsReader),
+}
+
+impl ReadHalf {
+    pub(crate) fn socket(&self) -> &TcpStream {
+        match self {
+            ReadHalf::Plain(conn) => conn,
+            ReadHalf::Tls(reader) => reader.socket(),
+        }
+    }
+}
+
+impl Read for ReadHalf {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ReadHalf::Plain(conn) => conn.read(buf),
+            ReadHalf::Tls(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl ReadExt for ReadHalf {}
+
+/// The write half of a connection, either plain or over tls;
+pub(crate) enum WriteHalf {
+    Plain(TcpStream),
+    Tls(tls::TlsWriter),
+}
+
+impl WriteHalf {
+    pub(crate) fn socket(&self) -> &TcpStream {
+        match self {
+            WriteHalf::Plain(conn) => conn,
+            WriteHalf::Tls(writer) => writer.socket(),
+        }
+    }
 
+    pub(crate) fn shutdown(&mut self) -> io::Result<()> {
+        match self {
+            WriteHalf::Plain(conn) => conn.shutdown(Shutdown::Write),
+            WriteHalf::Tls(writer) => writer.shutdown(),
+        }
+    }
+}
+
+impl Write for WriteHalf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            WriteHalf::Plain(conn) => conn.write(buf),
+            WriteHalf::Tls(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            WriteHalf::Plain(conn) => conn.flush(),
+            WriteHalf::Tls(writer) => writer.flush(),
+        }
+    }
+}
+
+impl WriteExt for WriteHalf {}
+
+/// Split a connection into halves, over tls if `params` has it, where `accept` tells whether the
+/// connection is accepted by this server, i.e., this server is the tls server of the connection;
+pub(crate) fn split(
+    conn: TcpStream, params: &ConnectionParams, accept: bool,
+) -> io::Result<(ReadHalf, WriteHalf)> {
+    match params.get_tls_params() {
+        Some(tls) => {
+            let (reader, writer) = if accept { tls.accept(&conn)? } else { tls.connect(&conn)? };
+            Ok((ReadHalf::Tls(reader), WriteHalf::Tls(writer)))
+        }
+        None => {
+            let write_half = conn.try_clone()?;
+            Ok((ReadHalf::Plain(conn), WriteHalf::Plain(write_half)))
+        }
+    }
+}
+
+/// The pass phrase of the legacy handshake, i.e., of the protocol version 1, see
+/// `pegasus_common::codec::PROTOCOL_VERSION`;
 pub const PASS_PHRASE: u32 = 9;
+/// The pass phrase of the handshake with the range of the protocol versions in its low 16 bits, i.e.,
+/// the oldest version in the high byte and the latest in the low byte; the range of `0` refuses the
+/// connection;
+const VERSIONED_PASS_PHRASE: u32 = 0x5047_0000;
+/// The protocol version of the servers of the legacy handshake;
+pub(crate) const LEGACY_PROTOCOL_VERSION: u32 = 1;
 
 pub fn get_handshake(server_id: u64, hb: u32) -> u128 {
-    let mut value = (PASS_PHRASE as u128) << 96;
+    handshake_of(PASS_PHRASE, server_id, hb)
+}
+
+fn handshake_of(phrase: u32, server_id: u64, hb: u32) -> u128 {
+    let mut value = (phrase as u128) << 96;
     let server_id = server_id as u128;
     value |= server_id << 32;
     value |= hb as u128;
     value
 }
 
+pub fn get_versioned_handshake(server_id: u64, hb: u32, versions: (u32, u32)) -> u128 {
+    assert!(versions.0 <= 0xff && versions.1 <= 0xff, "invalid protocol versions {:?}", versions);
+    let phrase = VERSIONED_PASS_PHRASE | versions.0 << 8 | versions.1;
+    handshake_of(phrase, server_id, hb)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Handshake {
+    pub server_id: u64,
+    pub hb_sec: u32,
+    /// The oldest and the latest protocol versions of the server, `(1, 1)` if it's legacy;
+    pub versions: (u32, u32),
+    pub is_legacy: bool,
+}
+
+impl Handshake {
+    pub fn is_refused(&self) -> bool {
+        self.versions == (0, 0)
+    }
+}
+
 #[inline]
-fn check_handshake(value: u128) -> Option<(u64, u32)> {
-    if (value >> 96) as u32 == PASS_PHRASE {
-        let mask = (1u128 << 96) - 1;
-        let server_id = ((value & mask) >> 32) as u64;
-        let mask = (1u128 << 32) - 1;
-        let hb = (value & mask) as u32;
-        Some((server_id, hb))
+fn check_handshake(value: u128) -> Option<Handshake> {
+    let phrase = (value >> 96) as u32;
+    let versions = if phrase == PASS_PHRASE {
+        (LEGACY_PROTOCOL_VERSION, LEGACY_PROTOCOL_VERSION)
+    } else if phrase & 0xffff_0000 == VERSIONED_PASS_PHRASE {
+        ((phrase >> 8) & 0xff, phrase & 0xff)
+    } else {
+        return None;
+    };
+    let mask = (1u128 << 96) - 1;
+    let server_id = ((value & mask) >> 32) as u64;
+    let mask = (1u128 << 32) - 1;
+    let hb_sec = (value & mask) as u32;
+    Some(Handshake { server_id, hb_sec, versions, is_legacy: phrase == PASS_PHRASE })
+}
+
+/// The latest protocol version in both the ranges of versions, if any;
+pub(crate) fn negotiate(local: (u32, u32), remote: (u32, u32)) -> Option<u32> {
+    let version = std::cmp::min(local.1, remote.1);
+    if version >= std::cmp::max(local.0, remote.0) {
+        Some(version)
     } else {
         None
     }
 }
 
+/// The range of the protocol versions of the server;
+pub(crate) fn local_versions() -> (u32, u32) {
+    (MIN_PROTOCOL_VERSION, PROTOCOL_VERSION)
+}
+
 #[inline]
-fn check_connection<R: ReadExt>(conn: &mut R) -> std::io::Result<Option<(u64, u32)>> {
+fn check_connection<R: ReadExt>(conn: &mut R) -> std::io::Result<Option<Handshake>> {
     let handshake = conn.read_u128()?;
     Ok(check_handshake(handshake))
 }
 
+/// Write the handshake of the range of the protocol `versions`, or the legacy handshake if it's `None`;
 #[inline]
-fn setup_connection<W: WriteExt>(server_id: u64, hb_sec: u32, conn: &mut W) -> std::io::Result<()> {
-    let handshake = get_handshake(server_id, hb_sec);
+fn setup_connection<W: WriteExt>(
+    server_id: u64, hb_sec: u32, versions: Option<(u32, u32)>, conn: &mut W,
+) -> std::io::Result<()> {
+    let handshake = match versions {
+        Some(versions) => get_versioned_handshake(server_id, hb_sec, versions),
+        None => get_handshake(server_id, hb_sec),
+    };
     conn.write_u128(handshake)
 }
 
@@ -61,7 +207,11 @@ mod test {
 
     fn handshake(server_id: u64) {
         let value = get_handshake(server_id, 5);
-        assert_eq!(Some((server_id, 5)), check_handshake(value), "error handshake on {}", server_id);
+        let expected = Handshake { server_id, hb_sec: 5, versions: (1, 1), is_legacy: true };
+        assert_eq!(Some(expected), check_handshake(value), "error handshake on {}", server_id);
+        let value = get_versioned_handshake(server_id, 5, (1, 2));
+        let expected = Handshake { server_id, hb_sec: 5, versions: (1, 2), is_legacy: false };
+        assert_eq!(Some(expected), check_handshake(value), "error handshake on {}", server_id);
     }
 
     #[test]
@@ -70,5 +220,16 @@ mod test {
             handshake(i);
         }
         handshake(!0);
+        assert_eq!(check_handshake(handshake_of(7, 1, 5)), None);
+        let refused = check_handshake(get_versioned_handshake(1, 5, (0, 0))).unwrap();
+        assert!(refused.is_refused());
+    }
+
+    #[test]
+    fn negotiate_test() {
+        assert_eq!(negotiate((1, 2), (1, 1)), Some(1));
+        assert_eq!(negotiate((1, 2), (1, 3)), Some(2));
+        assert_eq!(negotiate((2, 2), (1, 1)), None);
+        assert_eq!(negotiate((1, 1), (2, 3)), None);
     }
 }
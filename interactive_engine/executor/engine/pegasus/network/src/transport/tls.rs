@@ -0,0 +1,300 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Mutual TLS between servers. Each server is both a tls server (for the connections it accepts) and
+//! a tls client (for the connections it makes), with the same certificate, and the peers are
+//! verified against the same CA.
+//!
+//! A tls session is shared by the reader and the writer of a connection. The reader never writes to
+//! the socket, so that the records written by the writer are never interleaved; the handshake
+//! messages produced while reading (e.g. key updates) are sent by the next write of the writer.
+
+use std::convert::TryFrom;
+use std::fmt::{Debug, Formatter};
+use std::io::{self, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{Certificate, ClientConfig, ClientConnection, Connection, PrivateKey, RootCertStore};
+use rustls::{ServerConfig, ServerConnection, ServerName};
+use serde::Deserialize;
+
+use crate::NetError;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const TLS_READ_BUF_SIZE: usize = 1 << 14;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TlsConfig {
+    /// The certificate chain of this server in PEM;
+    pub cert_file: String,
+    /// The private key of the certificate in PEM, in PKCS#8, PKCS#1 or SEC1;
+    pub key_file: String,
+    /// The CA certificates to verify the peers in PEM;
+    pub ca_file: String,
+    /// The name to verify the certificates of the peers against, the ip address of the peer if
+    /// not set;
+    pub server_name: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn build(&self) -> Result<TlsParams, NetError> {
+        let certs = load_certs(&self.cert_file)?;
+        let key = load_key(&self.key_file)?;
+        let mut roots = RootCertStore::empty();
+        for ca in load_certs(&self.ca_file)? {
+            roots
+                .add(&ca)
+                .map_err(|e| invalid_config(&self.ca_file, e))?;
+        }
+        let server_name =
+            match self.server_name.as_ref() {
+                Some(name) => Some(ServerName::try_from(name.as_str()).map_err(|e| {
+                    NetError::InvalidConfig(Some(format!("tls server name {}: {}", name, e)))
+                })?),
+                None => None,
+            };
+
+        let verifier = AllowAnyAuthenticatedClient::new(roots.clone()).boxed();
+        let server = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs.clone(), key.clone())
+            .map_err(|e| invalid_config(&self.cert_file, e))?;
+        let client = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_client_auth_cert(certs, key)
+            .map_err(|e| invalid_config(&self.cert_file, e))?;
+        Ok(TlsParams { server: Arc::new(server), client: Arc::new(client), server_name })
+    }
+}
+
+fn invalid_config<E: std::fmt::Display>(file: &str, e: E) -> NetError {
+    NetError::InvalidConfig(Some(format!("tls file {}: {}", file, e)))
+}
+
+fn load_certs(file: &str) -> Result<Vec<Certificate>, NetError> {
+    let mut reader = BufReader::new(std::fs::File::open(file)?);
+    let certs = rustls_pemfile::certs(&mut reader)?;
+    if certs.is_empty() {
+        return Err(invalid_config(file, "no certificate found"));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_key(file: &str) -> Result<PrivateKey, NetError> {
+    let mut reader = BufReader::new(std::fs::File::open(file)?);
+    for item in rustls_pemfile::read_all(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => (),
+        }
+    }
+    Err(invalid_config(file, "no private key found"))
+}
+
+pub struct TlsParams {
+    server: Arc<ServerConfig>,
+    client: Arc<ClientConfig>,
+    server_name: Option<ServerName>,
+}
+
+impl Debug for TlsParams {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsParams")
+            .field("server_name", &self.server_name)
+            .finish()
+    }
+}
+
+impl TlsParams {
+    /// Handshake as the tls server on an accepted connection;
+    pub(crate) fn accept(&self, conn: &TcpStream) -> io::Result<(TlsReader, TlsWriter)> {
+        let session = ServerConnection::new(self.server.clone()).map_err(to_io_error)?;
+        handshake(session.into(), conn)
+    }
+
+    /// Handshake as the tls client on a connection made to `conn.peer_addr()`;
+    pub(crate) fn connect(&self, conn: &TcpStream) -> io::Result<(TlsReader, TlsWriter)> {
+        let server_name = match self.server_name.as_ref() {
+            Some(name) => name.clone(),
+            None => ServerName::IpAddress(conn.peer_addr()?.ip()),
+        };
+        let session = ClientConnection::new(self.client.clone(), server_name).map_err(to_io_error)?;
+        handshake(session.into(), conn)
+    }
+}
+
+fn to_io_error(e: rustls::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn handshake(mut session: Connection, conn: &TcpStream) -> io::Result<(TlsReader, TlsWriter)> {
+    conn.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    conn.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut socket = conn.try_clone()?;
+    while session.is_handshaking() {
+        session.complete_io(&mut socket)?;
+    }
+    conn.set_read_timeout(None)?;
+    conn.set_write_timeout(None)?;
+
+    let session = Arc::new(Mutex::new(session));
+    let reader = TlsReader {
+        session: session.clone(),
+        socket: conn.try_clone()?,
+        buf: vec![0; TLS_READ_BUF_SIZE],
+        pos: 0,
+        len: 0,
+    };
+    let writer = TlsWriter { session, socket, pending: Vec::new() };
+    Ok((reader, writer))
+}
+
+fn lock(session: &Mutex<Connection>) -> io::Result<MutexGuard<Connection>> {
+    session
+        .lock()
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "tls session poisoned"))
+}
+
+pub(crate) struct TlsReader {
+    session: Arc<Mutex<Connection>>,
+    socket: TcpStream,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
+}
+
+impl TlsReader {
+    pub(crate) fn socket(&self) -> &TcpStream {
+        &self.socket
+    }
+}
+
+impl Read for TlsReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            {
+                let mut session = lock(&self.session)?;
+                match session.reader().read(buf) {
+                    Ok(size) => return Ok(size),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => (),
+                    Err(e) => return Err(e),
+                }
+                if self.pos < self.len {
+                    let mut records = &self.buf[self.pos..self.len];
+                    self.pos += session.read_tls(&mut records)?;
+                    session
+                        .process_new_packets()
+                        .map_err(to_io_error)?;
+                    continue;
+                }
+            }
+            // read from socket without the session locked, as it may block;
+            let size = self.socket.read(&mut self.buf)?;
+            if size == 0 {
+                return Ok(0);
+            }
+            self.pos = 0;
+            self.len = size;
+        }
+    }
+}
+
+pub(crate) struct TlsWriter {
+    session: Arc<Mutex<Connection>>,
+    socket: TcpStream,
+    /// The records taken from the session but not yet written to the socket;
+    pending: Vec<u8>,
+}
+
+impl TlsWriter {
+    pub(crate) fn socket(&self) -> &TcpStream {
+        &self.socket
+    }
+
+    /// Move the records out of the session, and write them to the socket without the session locked,
+    /// so that the reader won't be blocked by a blocking write;
+    fn write_records(&mut self) -> io::Result<()> {
+        {
+            let mut session = lock(&self.session)?;
+            while session.wants_write() {
+                session.write_tls(&mut self.pending)?;
+            }
+        }
+        while !self.pending.is_empty() {
+            let size = self.socket.write(&self.pending)?;
+            if size == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            self.pending.drain(..size);
+        }
+        Ok(())
+    }
+
+    /// Send close notify to the peer, and shutdown the write side of the socket;
+    pub(crate) fn shutdown(&mut self) -> io::Result<()> {
+        lock(&self.session)?.send_close_notify();
+        let result = self.write_records();
+        self.socket.shutdown(Shutdown::Write)?;
+        result
+    }
+}
+
+impl Write for TlsWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // don't take more data until the records of the previous writes are sent;
+        self.write_records()?;
+        let size = lock(&self.session)?.writer().write(buf)?;
+        match self.write_records() {
+            Ok(()) => Ok(size),
+            // the data is taken and will be sent by the subsequent writes or flush;
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(size),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_records()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tls_config_test() {
+        let conf: TlsConfig = toml::from_str(
+            r#"
+            cert_file = "server.pem"
+            key_file = "server.key"
+            ca_file = "ca.pem"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(conf.cert_file, "server.pem");
+        assert_eq!(conf.server_name, None);
+        match conf.build() {
+            Err(NetError::IOError(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -83,8 +83,8 @@ fn ipc_with_conf(conf: ConnectionParams) {
     servers.push(Server { id: 0, addr: "127.0.0.1:1234".parse().unwrap() });
     servers.push(Server { id: 1, addr: "127.0.0.1:1235".parse().unwrap() });
     servers.push(Server { id: 2, addr: "127.0.0.1:1236".parse().unwrap() });
-    let g1 = mock_process_0(servers.clone(), conf);
-    let g2 = mock_process_1(servers.clone(), conf);
+    let g1 = mock_process_0(servers.clone(), conf.clone());
+    let g2 = mock_process_1(servers.clone(), conf.clone());
     let g3 = mock_process_2(servers, conf);
     g1.join().unwrap();
     g2.join().unwrap();
@@ -38,4 +38,17 @@ pub trait FoldByKey<K: Data + Key, V: Data> {
         I: Clone + Send + Sync + Debug + 'static,
         F: FnMut(I, V) -> FnResult<I> + Send + 'static,
         B: Fn() -> F + Send + 'static;
+
+    /// Analogous to [`fold_partition_by_key()`] but folding the data of each worker without exchanging
+    /// them by the key, e.g., to aggregate them partially before the partial aggregations of a key
+    /// are exchanged and merged.
+    ///
+    /// [`fold_partition_by_key()`]: FoldByKey::fold_partition_by_key()
+    fn fold_local_by_key<I, B, F>(
+        self, init: I, builder: B,
+    ) -> Result<SingleItem<HashMap<K, I>>, BuildJobError>
+    where
+        I: Clone + Send + Sync + Debug + 'static,
+        F: FnMut(I, V) -> FnResult<I> + Send + 'static,
+        B: Fn() -> F + Send + 'static;
 }
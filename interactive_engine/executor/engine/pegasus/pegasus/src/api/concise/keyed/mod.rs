@@ -119,7 +119,7 @@ pub trait KeyBy<D: Data> {
     ///                     input.input_from(vec![])?
     ///                 };
     ///                 src.key_by(|x| Ok((x % 2, x)))?
-    ///                    .partition_by_key()
+    ///                    .partition_by_key()?
     ///                    .filter(move |_| Ok(id == 1))?
     ///                    .map(|d| Ok((d.key, d.value)))?
     ///                    .collect::<Vec<(u32, u32)>>()?
@@ -140,7 +140,7 @@ pub trait KeyBy<D: Data> {
 }
 
 pub trait PartitionByKey<D: Data + HasKey> {
-    fn partition_by_key(self) -> Stream<D>;
+    fn partition_by_key(self) -> Result<Stream<D>, BuildJobError>;
 }
 
 mod apply;
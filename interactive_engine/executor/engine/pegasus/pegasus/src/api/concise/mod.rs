@@ -24,6 +24,7 @@ pub use limit::*;
 pub use map::*;
 pub use merge::*;
 pub use order::*;
+pub use ordered::*;
 pub use reduce::*;
 
 mod any;
@@ -37,6 +38,7 @@ mod limit;
 mod map;
 mod merge;
 mod order;
+mod ordered;
 mod reduce;
 mod switch;
 mod zip;
@@ -0,0 +1,59 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use crate::api::function::FnResult;
+use crate::stream::Stream;
+use crate::{BuildJobError, Data};
+
+/// Exchange the data between the workers in a reproducible order, i.e., the data of a scope received by
+/// a worker are in the order of the workers they're sent from, and then in the order they're sent by
+/// each worker, whatever the order they arrive in, see [`JobConf::deterministic`].
+///
+/// The data of a worker are passed on as soon as the data of all the workers before it have ended,
+/// which are known by the markers sent after the data of each scope, and held until then.
+///
+/// [`JobConf::deterministic`]: crate::JobConf::deterministic
+pub trait OrderedExchange<D: Data> {
+    /// Repartition the data by `route` as [`Stream::repartition`], in the order of the workers.
+    ///
+    /// # Example
+    /// ```
+    /// #     use pegasus::JobConf;
+    /// #     use pegasus::api::{Sink, OrderedExchange, Collect};
+    /// #     let mut conf = JobConf::new("repartition_ordered_example");
+    /// #     conf.set_workers(2);
+    ///       let mut results = pegasus::run(conf, || {
+    ///         let index = pegasus::get_current_worker().index;
+    ///         move |input, output| {
+    ///                 input.input_from(vec![index * 10, index * 10 + 1].into_iter())?
+    ///                      .repartition_ordered(|_| Ok(0))?
+    ///                      .collect::<Vec<u32>>()?
+    ///                      .sink_into(output)
+    ///             }
+    ///         })
+    ///         .expect("run job failure;");
+    ///
+    ///     assert_eq!(results.next().unwrap().unwrap(), [0, 1, 10, 11]);
+    /// ```
+    fn repartition_ordered<F>(self, route: F) -> Result<Stream<D>, BuildJobError>
+    where
+        F: Fn(&D) -> FnResult<u64> + Send + 'static;
+
+    /// Broadcast the data as [`Stream::broadcast`], in the order of the workers.
+    fn broadcast_ordered(self) -> Result<Stream<D>, BuildJobError>;
+
+    /// Aggregate the data as [`Stream::aggregate`], in the order of the workers.
+    fn aggregate_ordered(self) -> Result<Stream<D>, BuildJobError>;
+}
@@ -13,6 +13,8 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use opentelemetry::KeyValue;
+
 use crate::api::function::{BatchRouteFunction, FnResult, RouteFunction};
 use crate::api::scope::{MergedScopeDelta, ScopeDelta};
 use crate::channel_id::{ChannelId, ChannelInfo};
@@ -28,6 +30,7 @@ use crate::data::MicroBatch;
 use crate::data_plane::{GeneralPull, GeneralPush};
 use crate::dataflow::DataflowBuilder;
 use crate::graph::Port;
+use crate::telemetry::{self, ExchangeSpan};
 use crate::BuildJobError;
 use crate::Data;
 
@@ -60,6 +63,16 @@ impl<T: Data> ChannelKind<T> {
             _ => false,
         }
     }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChannelKind::Pipeline => "pipeline",
+            ChannelKind::Shuffle(_) => "shuffle",
+            ChannelKind::BatchShuffle(_) => "batch_shuffle",
+            ChannelKind::Broadcast => "broadcast",
+            ChannelKind::Aggregate => "aggregate",
+        }
+    }
 }
 
 pub struct Channel<T: Data> {
@@ -174,7 +187,7 @@ impl<T: Data> Channel<T> {
     }
 
     fn build_remote(
-        &self, scope_level: u32, target: Port, id: ChannelId, dfb: &DataflowBuilder,
+        &self, kind: &'static str, scope_level: u32, target: Port, id: ChannelId, dfb: &DataflowBuilder,
     ) -> Result<
         (ChannelInfo, Vec<EventEmitPush<T>>, GeneralPull<MicroBatch<T>>, GeneralPush<MicroBatch<T>>),
         BuildJobError,
@@ -183,10 +196,25 @@ impl<T: Data> Channel<T> {
         let worker_index = crate::worker_id::get_current_worker().index as usize;
         let notify = raw.swap_remove(worker_index);
         let ch_info = ChannelInfo::new(id, scope_level, raw.len(), raw.len(), self.source, target);
+        let span = if telemetry::is_recording(&dfb.trace_cx) {
+            let attributes = vec![
+                KeyValue::new("channel.index", id.index as i64),
+                KeyValue::new("channel.kind", kind),
+                KeyValue::new("source", format!("{:?}", self.source)),
+                KeyValue::new("target", format!("{:?}", target)),
+            ];
+            let cx = telemetry::start_span(format!("/exchange-{}", id.index), &dfb.trace_cx, attributes);
+            Some(ExchangeSpan::new(cx, raw.len()))
+        } else {
+            None
+        };
         let mut pushes = Vec::with_capacity(raw.len());
         let source = dfb.worker_id.index;
         for (idx, p) in raw.into_iter().enumerate() {
-            let push = EventEmitPush::new(ch_info, source, idx as u32, p, dfb.event_emitter.clone());
+            let mut push = EventEmitPush::new(ch_info, source, idx as u32, p, dfb.event_emitter.clone());
+            if let Some(span) = span.as_ref() {
+                push = push.with_span(span.clone());
+            }
             pushes.push(push);
         }
         Ok((ch_info, pushes, pull, notify))
@@ -216,10 +244,12 @@ impl<T: Data> Channel<T> {
         }
 
         let kind = std::mem::replace(&mut self.kind, ChannelKind::Pipeline);
+        let kind_name = kind.name();
         match kind {
             ChannelKind::Pipeline => Ok(self.build_pipeline(target, id)),
             ChannelKind::Shuffle(r) => {
-                let (info, pushes, pull, notify) = self.build_remote(scope_level, target, id, dfb)?;
+                let (info, pushes, pull, notify) =
+                    self.build_remote(kind_name, scope_level, target, id, dfb)?;
                 let mut buffers = Vec::with_capacity(pushes.len());
                 for _ in 0..pushes.len() {
                     let b = ScopeBufferPool::new(batch_size, batch_capacity, scope_level);
@@ -231,7 +261,8 @@ impl<T: Data> Channel<T> {
                 Ok(MaterializedChannel { push, pull: pull.into(), notify: Some(notify) })
             }
             ChannelKind::BatchShuffle(route) => {
-                let (info, pushes, pull, notify) = self.build_remote(scope_level, target, id, dfb)?;
+                let (info, pushes, pull, notify) =
+                    self.build_remote(kind_name, scope_level, target, id, dfb)?;
                 let push = ExchangeByBatchPush::new(info, route, pushes);
                 let cancel = push.get_cancel_handle();
                 let push = PerChannelPush::new(
@@ -243,7 +274,8 @@ impl<T: Data> Channel<T> {
                 Ok(MaterializedChannel { push, pull: pull.into(), notify: Some(notify) })
             }
             ChannelKind::Broadcast => {
-                let (info, pushes, pull, notify) = self.build_remote(scope_level, target, id, dfb)?;
+                let (info, pushes, pull, notify) =
+                    self.build_remote(kind_name, scope_level, target, id, dfb)?;
                 let push = BroadcastBatchPush::new(info, pushes);
                 let ch = push.get_cancel_handle();
                 let push = PerChannelPush::new(info, self.scope_delta, MicroBatchPush::Broadcast(push), ch);
@@ -251,7 +283,7 @@ impl<T: Data> Channel<T> {
             }
             ChannelKind::Aggregate => {
                 let (mut ch_info, pushes, pull, notify) =
-                    self.build_remote(scope_level, target, id, dfb)?;
+                    self.build_remote(kind_name, scope_level, target, id, dfb)?;
                 ch_info.target_peers = 1;
                 let push = AggregateBatchPush::new(ch_info, pushes);
                 let cancel = push.get_cancel_handle();
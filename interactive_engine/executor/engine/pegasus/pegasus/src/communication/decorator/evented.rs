@@ -12,6 +12,8 @@
 //! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
+use std::sync::Arc;
+
 use crate::channel_id::ChannelInfo;
 use crate::communication::IOResult;
 use crate::data::MicroBatch;
@@ -21,6 +23,7 @@ use crate::event::emitter::EventEmitter;
 use crate::event::{Event, EventKind};
 use crate::progress::{DynPeers, EndOfScope, EndSyncSignal};
 use crate::tag::tools::map::TidyTagMap;
+use crate::telemetry::ExchangeSpan;
 use crate::PROFILE_COMM_FLAG;
 use crate::{Data, Tag};
 
@@ -33,6 +36,7 @@ pub struct EventEmitPush<T: Data> {
     event_emitter: EventEmitter,
     // scope -> (sequence, counts)
     push_monitor: TidyTagMap<(usize, usize, usize)>,
+    span: Option<Arc<ExchangeSpan>>,
 }
 
 #[allow(dead_code)]
@@ -49,9 +53,16 @@ impl<T: Data> EventEmitPush<T> {
             inner: push,
             event_emitter: emitter,
             push_monitor: push_counts,
+            span: None,
         }
     }
 
+    /// Count the records pushed to the target worker in the span of the exchange channel;
+    pub fn with_span(mut self, span: Arc<ExchangeSpan>) -> Self {
+        self.span = Some(span);
+        self
+    }
+
     pub fn get_push_count(&self, tag: &Tag) -> Option<usize> {
         self.push_monitor.get(tag).map(|(_, _, x)| *x)
     }
@@ -171,6 +182,9 @@ impl<D: Data> Push<MicroBatch<D>> for EventEmitPush<D> {
             batch.set_seq(*seq as u64);
             *seq += 1;
         }
+        if let Some(span) = self.span.as_ref() {
+            span.add_records(self.target_worker as usize, len);
+        }
         if *PROFILE_COMM_FLAG {
             if !self.inner.is_local() {
                 info_worker!(
@@ -55,6 +55,8 @@ pub struct InputHandle<D: Data> {
     // scope skip manager:
     cancel: TidyTagMap<()>,
     parent_cancel: AHashSet<Tag>,
+    // records pulled in total;
+    pulled: u64,
 }
 
 impl<D: Data> InputHandle<D> {
@@ -72,6 +74,7 @@ impl<D: Data> InputHandle<D> {
             event_emitter,
             cancel: TidyTagMap::new(scope_level),
             parent_cancel: AHashSet::new(),
+            pulled: 0,
         }
     }
 
@@ -309,6 +312,7 @@ impl<D: Data> InputHandle<D> {
                                     );
                                 }
                             }
+                            self.pulled += batch.len() as u64;
                             return Ok(Some(batch));
                         }
                     }
@@ -493,6 +497,10 @@ impl<D: Data> InputProxy for RefWrapInput<D> {
     fn cancel_scope(&self, tag: &Tag) -> IOResult<()> {
         self.inbound.borrow_mut().cancel_scope(tag)
     }
+
+    fn pulled_records(&self) -> u64 {
+        self.inbound.borrow().pulled
+    }
 }
 
 struct StashedQueue<D> {
@@ -34,6 +34,9 @@ pub trait InputProxy: AsAny + Send {
     fn is_exhaust(&self) -> bool;
 
     fn cancel_scope(&self, tag: &Tag) -> IOResult<()>;
+
+    /// Records pulled from this input in total;
+    fn pulled_records(&self) -> u64;
 }
 
 mod input;
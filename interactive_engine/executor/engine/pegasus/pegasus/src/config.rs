@@ -22,7 +22,7 @@ use serde::Deserialize;
 
 use crate::errors::StartupError;
 use crate::{get_servers, get_servers_len};
-use crate::{PROFILE_COMM_FLAG, PROFILE_TIME_FLAG};
+use crate::{DETERMINISTIC, PROFILE_COMM_FLAG, PROFILE_TIME_FLAG};
 
 #[macro_export]
 macro_rules! configure_with_default {
@@ -38,6 +38,8 @@ pub struct Configuration {
     pub network: Option<NetworkConfig>,
     pub max_pool_size: Option<u32>,
     pub enable_tracing: Option<bool>,
+    /// The OTLP endpoint the spans are exported to if `enable_tracing`, `http://localhost:4317` by default;
+    pub tracing_endpoint: Option<String>,
 }
 
 impl Configuration {
@@ -46,11 +48,16 @@ impl Configuration {
     }
 
     pub fn singleton() -> Self {
-        Configuration { network: None, max_pool_size: None, enable_tracing: None }
+        Configuration { network: None, max_pool_size: None, enable_tracing: None, tracing_endpoint: None }
     }
 
     pub fn with(network: NetworkConfig) -> Self {
-        Configuration { network: Some(network), max_pool_size: None, enable_tracing: None }
+        Configuration {
+            network: Some(network),
+            max_pool_size: None,
+            enable_tracing: None,
+            tracing_endpoint: None,
+        }
     }
 
     pub fn server_id(&self) -> u64 {
@@ -137,6 +144,8 @@ pub struct JobConf {
     pub trace_enable: bool,
     /// optimization factors of early-stop
     pub debug: bool,
+    /// set to run the job reproducibly, see `DETERMINISTIC`, which is the default of it;
+    pub deterministic: bool,
 }
 
 impl JobConf {
@@ -199,6 +208,7 @@ impl Default for JobConf {
             servers: ServerConf::Local,
             trace_enable: false,
             debug: false,
+            deterministic: *DETERMINISTIC,
         }
     }
 }
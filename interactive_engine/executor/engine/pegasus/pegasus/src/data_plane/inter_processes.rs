@@ -36,7 +36,7 @@ impl<T: Data> Push<T> for RemotePush<T> {
     }
 
     fn flush(&mut self) -> Result<(), IOError> {
-        Ok(())
+        Ok(self.push.flush()?)
     }
 
     fn close(&mut self) -> Result<(), IOError> {
@@ -107,6 +107,9 @@ use intra_thread::{ThreadPull, ThreadPush};
 
 use crate::config::ServerConf;
 
+/// The pushes between the workers in the same process move the data into the channels as they are,
+/// e.g. the `Arc`s shared by the entries are never copied, only the pushes to the workers of the other
+/// processes encode the data;
 #[enum_dispatch(Push<T>)]
 pub enum GeneralPush<T: Data> {
     IntraThread(ThreadPush<T>),
@@ -308,6 +311,7 @@ fn encode_channel_id(id: ChannelId, worker_index: u32) -> u128 {
 
 #[cfg(test)]
 mod test {
+    use pegasus_common::codec::{Decode, Encode, ReadExt, WriteExt};
     use pegasus_network::config::ConnectionParams;
     use pegasus_network::Server;
 
@@ -408,6 +412,43 @@ mod test {
         channel_test(1, 2, 0, &ServerConf::Local);
     }
 
+    /// The data which can't be encoded or decoded, as the entries only shared in the process;
+    #[derive(Clone, Debug)]
+    struct Shared(std::sync::Arc<u64>);
+
+    impl Encode for Shared {
+        fn write_to<W: WriteExt>(&self, _writer: &mut W) -> std::io::Result<()> {
+            panic!("local data encoded")
+        }
+    }
+
+    impl Decode for Shared {
+        fn read_from<R: ReadExt>(_reader: &mut R) -> std::io::Result<Self> {
+            panic!("local data decoded")
+        }
+    }
+
+    #[test]
+    fn intra_process_ch_no_copy() {
+        let mut resources =
+            build_channels::<Shared>(ChannelId::new(1, 4), 2, 0, &ServerConf::Local).unwrap();
+        let (mut pushes_0, _pull_0) = resources.pop_front().unwrap().take();
+        let (_pushes_1, mut pull_1) = resources.pop_front().unwrap().take();
+        assert!(pushes_0.iter().all(|p| p.is_local()));
+        let data = (0..16u64)
+            .map(std::sync::Arc::new)
+            .collect::<Vec<_>>();
+        for d in data.iter() {
+            pushes_0[1].push(Shared(d.clone())).unwrap();
+        }
+        pushes_0[1].flush().unwrap();
+        for d in data.iter() {
+            let received = pull_1.next().unwrap().expect("data lost");
+            assert!(std::sync::Arc::ptr_eq(&received.0, d));
+        }
+        assert!(pull_1.next().unwrap().is_none());
+    }
+
     #[ignore]
     #[test]
     fn test_channel_between_2_servers() {
@@ -20,6 +20,8 @@ use std::fs::File;
 use std::rc::Rc;
 use std::sync::Arc;
 
+use opentelemetry::Context;
+
 use crate::api::meta::OperatorInfo;
 use crate::channel_id::ChannelInfo;
 use crate::communication::output::OutputBuilderImpl;
@@ -41,10 +43,14 @@ pub struct DataflowBuilder {
     operators: Rc<RefCell<Vec<OperatorBuilder>>>,
     edges: Rc<RefCell<Vec<Edge>>>,
     sinks: Rc<RefCell<Vec<usize>>>,
+    /// The trace context of the worker, which the spans of operators and channels are children of;
+    pub(crate) trace_cx: Context,
 }
 
 impl DataflowBuilder {
-    pub(crate) fn new(worker_id: WorkerId, event_emitter: EventEmitter, config: &Arc<JobConf>) -> Self {
+    pub(crate) fn new(
+        worker_id: WorkerId, event_emitter: EventEmitter, config: &Arc<JobConf>, trace_cx: &Context,
+    ) -> Self {
         DataflowBuilder {
             worker_id,
             config: config.clone(),
@@ -53,6 +59,7 @@ impl DataflowBuilder {
             event_emitter,
             ch_index: Rc::new(RefCell::new(1)),
             sinks: Rc::new(RefCell::new(vec![])),
+            trace_cx: trace_cx.clone(),
         }
     }
 
@@ -144,7 +151,7 @@ impl DataflowBuilder {
             let inputs_notify = op_b.take_inputs_notify();
             let outputs_cancel = op_b.build_outputs_cancel();
             sch.add_schedule_op(op_index, op_b.info.scope_level, inputs_notify, outputs_cancel);
-            let op = op_b.build();
+            let op = op_b.build(&self.trace_cx);
             op_names.push(op.info.name.clone());
             if report {
                 writeln!(plan_desc, "\t{}\t{}({})", op.info.index, op.info.name, op.info.index).ok();
@@ -197,6 +204,7 @@ impl Clone for DataflowBuilder {
             ch_index: self.ch_index.clone(),
             edges: self.edges.clone(),
             sinks: self.sinks.clone(),
+            trace_cx: self.trace_cx.clone(),
         }
     }
 }
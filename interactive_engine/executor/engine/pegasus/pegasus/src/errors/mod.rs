@@ -22,6 +22,7 @@ use pegasus_network::NetError;
 mod io_error;
 pub use io_error::IOError;
 pub use io_error::IOErrorKind;
+pub use pegasus_common::error_code::{find_code, CodedError, ErrorCategory, ErrorCode};
 
 use crate::Tag;
 
@@ -99,6 +100,21 @@ impl JobExecError {
         &self.cause
     }
 
+    /// The code of the error, which is the code found in the chain of the cause, or else the code of the
+    /// kind of the error.
+    pub fn code(&self) -> ErrorCode {
+        if let Some(code) = find_code(self.cause.as_ref()) {
+            return code;
+        }
+        match self.kind {
+            ErrorKind::WouldBlock(_) | ErrorKind::Interrupted => ErrorCode::system(5001, true),
+            ErrorKind::IOError => ErrorCode::system(5002, true),
+            ErrorKind::IllegalScopeInput => ErrorCode::system(5003, false),
+            ErrorKind::Canceled => ErrorCode::system(5004, false),
+            ErrorKind::Others => ErrorCode::UNKNOWN,
+        }
+    }
+
     pub fn as_ref<E: Error + 'static>(&self) -> Option<&E> {
         self.cause.downcast_ref::<E>()
     }
@@ -212,6 +228,16 @@ impl Display for BuildJobError {
 impl Error for BuildJobError {}
 
 impl BuildJobError {
+    /// The code of the error, which is the code found in the chain of the cause if any.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            BuildJobError::Unsupported(_) => ErrorCode::user(2000),
+            BuildJobError::InternalError(_) => ErrorCode::system(5005, false),
+            BuildJobError::ServerError(e) => find_code(e.as_ref()).unwrap_or(ErrorCode::system(5006, true)),
+            BuildJobError::UserError(e) => find_code(e.as_ref()).unwrap_or(ErrorCode::user(2001)),
+        }
+    }
+
     pub(crate) fn unsupported<T, S: Into<String>>(msg: S) -> Result<T, Self> {
         Err(BuildJobError::Unsupported(msg.into()))
     }
@@ -287,6 +313,15 @@ impl Display for JobSubmitError {
 
 impl Error for JobSubmitError {}
 
+impl JobSubmitError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            JobSubmitError::Build(err) => err.code(),
+            JobSubmitError::Spawn(_) => ErrorCode::system(5007, true),
+        }
+    }
+}
+
 impl From<BuildJobError> for JobSubmitError {
     fn from(err: BuildJobError) -> Self {
         JobSubmitError::Build(err)
@@ -43,12 +43,15 @@ pub mod communication;
 mod data_plane;
 pub mod dataflow;
 mod event;
+mod metrics;
 mod operator;
+pub mod profile;
 pub(crate) mod progress;
 pub mod resource;
 pub mod result;
 mod schedule;
 pub mod stream;
+mod telemetry;
 pub mod utils;
 mod worker;
 
@@ -81,6 +84,22 @@ lazy_static! {
     static ref JOB_CANCEL_MAP: RwLock<HashMap<u64, Arc<AtomicBool>>> = RwLock::new(HashMap::new());
     pub static ref PROFILE_TIME_FLAG: bool = configure_with_default!(bool, "PROFILE_TIME_FLAG", false);
     pub static ref PROFILE_COMM_FLAG: bool = configure_with_default!(bool, "PROFILE_COMM_FLAG", false);
+    /// set `true` to make the execution reproducible, e.g., for the comparisons of the results and the
+    /// profiles in tests, where the hashers and the samplings are of `DETERMINISTIC_SEED`, and the data
+    /// exchanged between workers are merged in the order of the workers. It's the default of the jobs,
+    /// each of which may set it by `JobConf::deterministic`, see `is_deterministic`;
+    pub static ref DETERMINISTIC: bool = configure_with_default!(bool, "DETERMINISTIC", false);
+}
+
+/// The seed of the hashers and the samplings if `DETERMINISTIC`;
+pub const DETERMINISTIC_SEED: u64 = 0x5eed;
+
+/// Whether the job of the current worker runs reproducibly, or `DETERMINISTIC` out of any worker;
+#[inline]
+pub fn is_deterministic() -> bool {
+    get_current_worker_checked()
+        .map(|worker| worker.deterministic)
+        .unwrap_or(*DETERMINISTIC)
 }
 
 thread_local! {
@@ -160,7 +179,7 @@ pub fn startup(conf: Configuration) -> Result<(), StartupError> {
     if let Some(net_conf) = conf.network_config() {
         if let Some(peers) = net_conf.get_servers()? {
             let addr = net_conf.local_addr()?;
-            let conn_conf = net_conf.get_connection_param();
+            let conn_conf = net_conf.get_connection_param()?;
             for p in peers.iter() {
                 servers.insert(p.id);
             }
@@ -199,7 +218,7 @@ pub fn startup_with<D: ServerDetect + 'static>(
 
     Ok(if let Some(net_conf) = conf.network_config() {
         let addr = net_conf.local_addr()?;
-        let conn_conf = net_conf.get_connection_param();
+        let conn_conf = net_conf.get_connection_param()?;
         let addr = pegasus_network::start_up(server_id, conn_conf, addr, detect)?;
         info!("server {} start on {:?}", server_id, addr);
 
@@ -224,6 +243,11 @@ pub fn shutdown_all() {
     pegasus_executor::await_termination();
 }
 
+/// The number of threads running the workers of all jobs in this server.
+pub fn executor_threads() -> usize {
+    pegasus_executor::core_pool_size()
+}
+
 pub fn run<DI, DO, F, FN>(conf: JobConf, func: F) -> Result<ResultStream<DO>, JobSubmitError>
 where
     DI: Data,
@@ -282,18 +306,19 @@ where
     if workers.is_none() {
         return Ok(());
     }
-    let worker_ids = workers.unwrap();
-    let tracer = global::tracer("executor");
+    let worker_ids = workers
+        .unwrap()
+        .with_deterministic(conf.deterministic);
+    let tracer = global::tracer(telemetry::TRACER_NAME);
+    let running = Arc::new(metrics::RunningJob::new());
 
     let mut workers = Vec::new();
     for worker_id in worker_ids {
         let mut worker = tracer.in_span(format!("/pegasus::run_opt"), |cx| {
             cx.span()
                 .set_attribute(KeyValue::new("worker-id", worker_id.index.to_string()));
-            let span = tracer
-                .span_builder(format!("/worker-{}", worker_id.index))
-                .start_with_context(&tracer, &cx);
-            Worker::new(&conf, worker_id, &peer_guard, sink.clone(), span)
+            let trace_cx = telemetry::start_span(format!("/worker-{}", worker_id.index), &cx, vec![]);
+            Worker::new(&conf, worker_id, &peer_guard, &running, sink.clone(), trace_cx)
         });
         let _g = crate::worker_id::guard(worker.id);
         logic(&mut worker)?;
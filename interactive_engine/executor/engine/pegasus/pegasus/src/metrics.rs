@@ -0,0 +1,80 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use prometheus::{register_int_counter, register_int_counter_vec, register_int_gauge};
+use prometheus::{IntCounter, IntCounterVec, IntGauge};
+
+lazy_static! {
+    static ref JOBS_RUNNING: IntGauge =
+        register_int_gauge!("pegasus_jobs_running", "Jobs running on this server.").unwrap();
+    static ref JOBS_TOTAL: IntCounter =
+        register_int_counter!("pegasus_jobs_total", "Jobs spawned on this server.").unwrap();
+    static ref OPERATOR_RECORDS: IntCounterVec = register_int_counter_vec!(
+        "pegasus_operator_records_total",
+        "Records consumed by operators.",
+        &["operator"]
+    )
+    .unwrap();
+    static ref OPERATOR_FIRES: IntCounterVec = register_int_counter_vec!(
+        "pegasus_operator_fires_total",
+        "Times operators are fired.",
+        &["operator"]
+    )
+    .unwrap();
+}
+
+/// Count a job as running until it's dropped by all the local workers of the job;
+pub(crate) struct RunningJob;
+
+impl RunningJob {
+    pub(crate) fn new() -> Self {
+        JOBS_TOTAL.inc();
+        JOBS_RUNNING.inc();
+        RunningJob
+    }
+}
+
+impl Drop for RunningJob {
+    fn drop(&mut self) {
+        JOBS_RUNNING.dec();
+    }
+}
+
+pub(crate) struct OperatorMetrics {
+    records: IntCounter,
+    fires: IntCounter,
+    /// The records consumed by the operator which are already counted;
+    reported: u64,
+}
+
+impl OperatorMetrics {
+    pub(crate) fn new(name: &str) -> Self {
+        OperatorMetrics {
+            records: OPERATOR_RECORDS.with_label_values(&[name]),
+            fires: OPERATOR_FIRES.with_label_values(&[name]),
+            reported: 0,
+        }
+    }
+
+    /// Report a fire of the operator, with the records consumed by the operator in total;
+    #[inline]
+    pub(crate) fn on_fire(&mut self, consumed: u64) {
+        self.fires.inc();
+        if consumed > self.reported {
+            self.records.inc_by(consumed - self.reported);
+            self.reported = consumed;
+        }
+    }
+}
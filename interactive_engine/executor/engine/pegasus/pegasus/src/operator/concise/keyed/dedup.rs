@@ -7,7 +7,7 @@ use crate::{BuildJobError, Data};
 
 impl<D: Data + HasKey> Dedup<D> for Stream<D> {
     fn dedup(self) -> Result<Stream<D>, BuildJobError> {
-        self.partition_by_key().unary("dedup", |info| {
+        self.partition_by_key()?.unary("dedup", |info| {
             let mut table = TidyTagMap::<AHashSet<D::Target>>::new(info.scope_level);
             move |input, output| {
                 input.for_each_batch(|batch| {
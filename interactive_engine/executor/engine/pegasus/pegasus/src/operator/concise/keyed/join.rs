@@ -181,8 +181,9 @@ fn internal_inner_join<L: Data + HasKey, R: Data + HasKey<Target = L::Target>>(
 where
     L::Target: Clone + Send,
 {
-    this.partition_by_key()
-        .binary("inner_join", other.partition_by_key(), |info| {
+    let other = other.partition_by_key()?;
+    this.partition_by_key()?
+        .binary("inner_join", other, |info| {
             let mut helper = Helper::<L, R>::new(info.scope_level);
             move |left, right, output| {
                 left.for_each_batch(|dataset| {
@@ -235,8 +236,9 @@ where
         JoinType::FullOuter => (true, true),
         _ => return Err(BuildJobError::from("wrong join type".to_string())),
     };
-    this.partition_by_key()
-        .binary(format!("{:?}", join_type).as_str(), other.partition_by_key(), |info| {
+    let other = other.partition_by_key()?;
+    this.partition_by_key()?
+        .binary(format!("{:?}", join_type).as_str(), other, |info| {
             let mut helper = Helper::<L, R>::new(info.scope_level);
             move |left, right, output| {
                 left.for_each_batch(|dataset| {
@@ -307,8 +309,9 @@ where
         JoinType::Anti => true,
         _ => return Err(BuildJobError::from("wrong join type".to_string())),
     };
-    this.partition_by_key()
-        .binary(format!("{:?}", join_type).as_str(), other.partition_by_key(), |info| {
+    let other = other.partition_by_key()?;
+    this.partition_by_key()?
+        .binary(format!("{:?}", join_type).as_str(), other, |info| {
             let mut helper = Helper::<L, R>::new(info.scope_level);
             move |left, right, output| {
                 left.for_each_batch(|dataset| {
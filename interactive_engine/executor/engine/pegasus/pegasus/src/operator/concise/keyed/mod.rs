@@ -16,7 +16,7 @@
 use std::hash::{BuildHasher, Hash, Hasher};
 
 use crate::api::function::{FnResult, RouteFunction};
-use crate::api::{HasKey, Key, KeyBy, Map, Pair, PartitionByKey};
+use crate::api::{HasKey, Key, KeyBy, Map, OrderedExchange, Pair, PartitionByKey};
 use crate::stream::Stream;
 use crate::{BuildJobError, Data};
 
@@ -35,12 +35,19 @@ impl<D: Data> KeyBy<D> for Stream<D> {
 }
 
 impl<D: Data + HasKey> PartitionByKey<D> for Stream<D> {
-    fn partition_by_key(self) -> Stream<D> {
-        let job_id = crate::worker_id::get_current_worker().job_id;
-        let bh = ahash::RandomState::with_seeds(job_id, job_id & 3, job_id & 7, job_id & 15);
+    fn partition_by_key(self) -> Result<Stream<D>, BuildJobError> {
+        // the job id is assigned by the client, which may differ between the runs of the same job
+        let deterministic = self.get_worker_id().deterministic;
+        let seed = if deterministic { crate::DETERMINISTIC_SEED } else { self.get_worker_id().job_id };
+        let bh = ahash::RandomState::with_seeds(seed, seed & 3, seed & 7, seed & 15);
         let router = KeyRouter::new(bh);
 
-        self.repartition(move |item| router.route(item))
+        if deterministic {
+            // the data of a key are in the order of the workers, e.g., for the first of them kept
+            self.repartition_ordered(move |item| router.route(item))
+        } else {
+            Ok(self.repartition(move |item| router.route(item)))
+        }
     }
 }
 
@@ -14,7 +14,7 @@ impl<K: Data + Key, V: Data> ReduceByKey<K, V> for Stream<Pair<K, V>> {
         F: FnMut(V, V) -> FnResult<V> + Send + 'static,
         B: Fn() -> F + Send + 'static,
     {
-        self.partition_by_key()
+        self.partition_by_key()?
             .unary("reduce_by_key", |info| {
                 let mut ttm = TidyTagMap::new(info.scope_level);
                 move |input, output| {
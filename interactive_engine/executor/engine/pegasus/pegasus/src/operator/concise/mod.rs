@@ -33,6 +33,7 @@ mod limit;
 mod map;
 mod merge;
 mod order;
+mod ordered;
 mod reduce;
 
 #[inline]
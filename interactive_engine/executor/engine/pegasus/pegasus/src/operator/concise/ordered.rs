@@ -0,0 +1,186 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::collections::VecDeque;
+
+use crate::api::function::FnResult;
+use crate::api::{OrderedExchange, Unary};
+use crate::codec::{Decode, Encode, ReadExt, WriteExt};
+use crate::stream::Stream;
+use crate::tag::tools::map::TidyTagMap;
+use crate::{BuildJobError, Data};
+
+/// The data exchanged in order, of the worker it's sent from, or the end of the data of a scope sent
+/// from a worker to a target worker.
+#[derive(Clone, Debug)]
+enum Ordered<D> {
+    Item(u32, D),
+    End(u32, u32),
+}
+
+impl<D: Encode> Encode for Ordered<D> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            Ordered::Item(src, data) => {
+                writer.write_u8(0)?;
+                writer.write_u32(*src)?;
+                data.write_to(writer)
+            }
+            Ordered::End(src, target) => {
+                writer.write_u8(1)?;
+                writer.write_u32(*src)?;
+                writer.write_u32(*target)
+            }
+        }
+    }
+}
+
+impl<D: Decode> Decode for Ordered<D> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
+        let kind = reader.read_u8()?;
+        let src = reader.read_u32()?;
+        match kind {
+            0 => Ok(Ordered::Item(src, D::read_from(reader)?)),
+            1 => Ok(Ordered::End(src, reader.read_u32()?)),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "unknown ordered data")),
+        }
+    }
+}
+
+/// The data of a scope received from the workers, where the data of the worker `next` are passed on,
+/// and the data of the workers after it are held until the workers before them have ended.
+struct OrderedMerge<D> {
+    next: usize,
+    ended: Vec<bool>,
+    held: Vec<VecDeque<D>>,
+}
+
+impl<D> OrderedMerge<D> {
+    fn new(peers: usize) -> Self {
+        OrderedMerge {
+            next: 0,
+            ended: vec![false; peers],
+            held: (0..peers).map(|_| VecDeque::new()).collect(),
+        }
+    }
+
+    /// Take the data that can be passed on after `ordered` is received.
+    fn receive(&mut self, ordered: Ordered<D>, ready: &mut Vec<D>) {
+        match ordered {
+            Ordered::Item(src, data) => {
+                let src = src as usize;
+                if src == self.next {
+                    ready.push(data);
+                } else {
+                    self.held[src].push_back(data);
+                }
+            }
+            Ordered::End(src, _) => {
+                self.ended[src as usize] = true;
+                while self.next < self.ended.len() && self.ended[self.next] {
+                    self.next += 1;
+                    if self.next < self.held.len() {
+                        ready.extend(self.held[self.next].drain(..));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Take all the data held at the end of the scope, in the order of the workers, including the
+    /// workers never sending the end of the scope, e.g., without the scope at all.
+    fn finish(mut self, ready: &mut Vec<D>) {
+        for held in self.held.iter_mut().skip(self.next) {
+            ready.extend(held.drain(..));
+        }
+    }
+}
+
+/// Mark the data of each worker with the index of it, followed by the ends of each scope to `targets`.
+fn mark<D: Data>(stream: Stream<D>, targets: u32) -> Result<Stream<Ordered<D>>, BuildJobError> {
+    let src = stream.get_worker_id().index;
+    stream.unary("ordered_mark", |_info| {
+        move |input, output| {
+            input.for_each_batch(|dataset| {
+                let mut session = output.new_session(&dataset.tag)?;
+                for data in dataset.drain() {
+                    session.give(Ordered::Item(src, data))?;
+                }
+                if dataset.is_last() {
+                    for target in 0..targets {
+                        session.give(Ordered::End(src, target))?;
+                    }
+                }
+                Ok(())
+            })
+        }
+    })
+}
+
+/// Merge the data received from the workers in the order of the workers.
+fn merge<D: Data>(stream: Stream<Ordered<D>>) -> Result<Stream<D>, BuildJobError> {
+    let peers = stream.get_worker_id().total_peers() as usize;
+    stream.unary("ordered_merge", |info| {
+        let mut merges = TidyTagMap::new(info.scope_level);
+        move |input, output| {
+            input.for_each_batch(|dataset| {
+                let mut ready = vec![];
+                if !dataset.is_empty() {
+                    let merge = merges.get_mut_or_else(&dataset.tag, || OrderedMerge::new(peers));
+                    for ordered in dataset.drain() {
+                        merge.receive(ordered, &mut ready);
+                    }
+                }
+                if dataset.is_last() {
+                    if let Some(merge) = merges.remove(&dataset.tag) {
+                        merge.finish(&mut ready);
+                    }
+                }
+                if !ready.is_empty() {
+                    let mut session = output.new_session(&dataset.tag)?;
+                    session.give_iterator(ready.into_iter())?;
+                }
+                Ok(())
+            })
+        }
+    })
+}
+
+impl<D: Data> OrderedExchange<D> for Stream<D> {
+    fn repartition_ordered<F>(self, route: F) -> Result<Stream<D>, BuildJobError>
+    where
+        F: Fn(&D) -> FnResult<u64> + Send + 'static,
+    {
+        // the end of a scope is sent to each worker, as the index of it
+        let peers = self.get_worker_id().total_peers();
+        let stream = mark(self, peers)?.repartition(move |ordered| match ordered {
+            Ordered::Item(_, data) => route(data),
+            Ordered::End(_, target) => Ok(*target as u64),
+        });
+        merge(stream)
+    }
+
+    fn broadcast_ordered(self) -> Result<Stream<D>, BuildJobError> {
+        merge(mark(self, 1)?.broadcast())
+    }
+
+    fn aggregate_ordered(self) -> Result<Stream<D>, BuildJobError> {
+        if self.get_partitions() <= 1 {
+            // the data are of a single worker already
+            return Ok(self.aggregate());
+        }
+        merge(mark(self, 1)?.aggregate())
+    }
+}
@@ -17,6 +17,8 @@ use std::cell::Cell;
 use std::time::Instant;
 
 use nohash_hasher::IntSet;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::{Context, KeyValue};
 use pegasus_common::rc::UnsafeRcPtr;
 
 use crate::api::meta::OperatorInfo;
@@ -29,10 +31,13 @@ use crate::data_plane::{GeneralPull, GeneralPush};
 use crate::errors::{IOResult, JobExecError};
 use crate::event::emitter::EventEmitter;
 use crate::graph::Port;
+use crate::metrics::OperatorMetrics;
+use crate::profile::{self, OperatorProfile};
 use crate::progress::EndOfScope;
 use crate::schedule::state::inbound::InputEndNotify;
 use crate::schedule::state::outbound::OutputCancelState;
 use crate::tag::tools::map::TidyTagMap;
+use crate::telemetry;
 use crate::{Data, Tag};
 use crate::{PROFILE_COMM_FLAG, PROFILE_TIME_FLAG};
 
@@ -285,6 +290,9 @@ pub struct Operator {
     core: Box<dyn NotifiableOperator>,
     fire_times: u128,
     exec_st: UnsafeRcPtr<Cell<u128>>,
+    metrics: OperatorMetrics,
+    trace_cx: Context,
+    store_reads: u64,
 }
 
 impl Operator {
@@ -320,10 +328,19 @@ impl Operator {
     #[inline]
     pub fn fire(&mut self) -> Result<(), JobExecError> {
         let _f = Finally::new(self.exec_st.clone());
+        let _trace = telemetry::enter(&self.trace_cx);
         debug_worker!("fire operator {:?}", self.info);
         self.fire_times += 1;
 
+        let store_reads = profile::store_reads();
         let mut result = self.fire_inner();
+        self.store_reads += profile::store_reads() - store_reads;
+        let consumed = self
+            .inputs
+            .iter()
+            .map(|i| i.pulled_records())
+            .sum();
+        self.metrics.on_fire(consumed);
         if let Err(err) = result {
             if !err.can_be_retried() {
                 return Err(err);
@@ -388,6 +405,30 @@ impl Operator {
                 self.exec_st.get() / self.fire_times
             );
         }
+        let records: u64 = self
+            .inputs
+            .iter()
+            .map(|i| i.pulled_records())
+            .sum();
+        if let Some(worker) = crate::worker_id::get_current_worker_checked() {
+            profile::report(worker.job_id, || OperatorProfile {
+                index: self.info.index,
+                name: self.info.name.clone(),
+                worker: worker.index,
+                fire_times: self.fire_times as u64,
+                used_us: self.exec_st.get() as u64,
+                records,
+                store_reads: self.store_reads,
+            });
+        }
+        if telemetry::is_recording(&self.trace_cx) {
+            let span = self.trace_cx.span();
+            span.set_attribute(KeyValue::new("fire_times", self.fire_times as i64));
+            span.set_attribute(KeyValue::new("used_us", self.exec_st.get() as i64));
+            span.set_attribute(KeyValue::new("records", records as i64));
+            span.set_attribute(KeyValue::new("store_reads", self.store_reads as i64));
+            span.end();
+        }
     }
 
     fn fire_inner(&mut self) -> Result<(), JobExecError> {
@@ -474,7 +515,7 @@ impl OperatorBuilder {
         vec
     }
 
-    pub(crate) fn build(self) -> Operator {
+    pub(crate) fn build(self, trace_cx: &Context) -> Operator {
         let op_index = self.index();
         let mut outputs = Vec::new();
         for ob in self.outputs {
@@ -492,6 +533,9 @@ impl OperatorBuilder {
             }
             GeneralOperator::Notifiable(op) => op,
         };
+        let metrics = OperatorMetrics::new(&self.info.name);
+        let attributes = vec![KeyValue::new("operator.index", self.info.index as i64)];
+        let trace_cx = telemetry::start_span(format!("/operator-{}", self.info.name), trace_cx, attributes);
         Operator {
             info: self.info,
             inputs: self.inputs,
@@ -499,6 +543,9 @@ impl OperatorBuilder {
             core,
             fire_times: 0,
             exec_st: UnsafeRcPtr::new(Cell::new(0)),
+            metrics,
+            trace_cx,
+            store_reads: 0,
         }
     }
 }
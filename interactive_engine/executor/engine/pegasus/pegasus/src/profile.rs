@@ -0,0 +1,106 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Profiles of the operators of a job, collected when the operators are closed if the profiling of
+//! the job is enabled, e.g. by the slow query log of the server.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+thread_local! {
+    static STORE_READS: Cell<u64> = Cell::new(0);
+}
+
+/// Count the records read from the store, which are attributed to the operator being fired on the
+/// current thread.
+#[inline]
+pub fn add_store_reads(records: u64) {
+    STORE_READS.with(|r| r.set(r.get() + records));
+}
+
+/// The records read from the store by the current thread in total;
+#[inline]
+pub(crate) fn store_reads() -> u64 {
+    STORE_READS.with(|r| r.get())
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct OperatorProfile {
+    pub index: usize,
+    pub name: String,
+    pub worker: u32,
+    pub fire_times: u64,
+    pub used_us: u64,
+    /// Records consumed by the operator;
+    pub records: u64,
+    /// Records read from the store by the operator;
+    pub store_reads: u64,
+}
+
+lazy_static! {
+    static ref JOB_PROFILES: Mutex<HashMap<u64, Vec<OperatorProfile>>> = Mutex::new(HashMap::new());
+}
+
+/// Collect the profiles of the operators of the job, which should be taken by `take` finally.
+pub fn enable(job_id: u64) {
+    if let Ok(mut profiles) = JOB_PROFILES.lock() {
+        profiles.entry(job_id).or_default();
+    }
+}
+
+/// Take the profiles of the operators of the job closed so far, and stop collecting. The profiles are
+/// in the order of the workers and the operators, rather than the order the operators are closed in.
+pub fn take(job_id: u64) -> Option<Vec<OperatorProfile>> {
+    let mut profiles = JOB_PROFILES
+        .lock()
+        .ok()
+        .and_then(|mut profiles| profiles.remove(&job_id))?;
+    profiles.sort_by_key(|profile| (profile.worker, profile.index));
+    Some(profiles)
+}
+
+pub(crate) fn report<F: FnOnce() -> OperatorProfile>(job_id: u64, profile: F) {
+    if let Ok(mut profiles) = JOB_PROFILES.lock() {
+        if let Some(job) = profiles.get_mut(&job_id) {
+            job.push(profile());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn profile_test() {
+        report(7, || OperatorProfile { index: 1, ..Default::default() });
+        assert_eq!(take(7), None);
+
+        enable(7);
+        report(7, || OperatorProfile { index: 1, ..Default::default() });
+        report(8, || OperatorProfile { index: 2, ..Default::default() });
+        let profiles = take(7).unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].index, 1);
+        assert_eq!(take(7), None);
+
+        let reads = store_reads();
+        add_store_reads(3);
+        assert_eq!(store_reads() - reads, 3);
+    }
+}
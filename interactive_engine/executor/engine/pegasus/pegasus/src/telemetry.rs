@@ -0,0 +1,89 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Spans of jobs exported by opentelemetry. A job has a span per worker, and the span of a worker has
+//! a child span per operator and per cross-worker exchange channel. The context of the operator
+//! span is attached while the operator is fired, so the spans created by user functions, e.g. reads
+//! of the store, are children of the operator. Nothing is recorded if no tracer provider is installed.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use opentelemetry::trace::{Span, TraceContextExt, Tracer};
+use opentelemetry::{global, Context, ContextGuard, KeyValue};
+
+pub(crate) const TRACER_NAME: &str = "executor";
+
+/// Start a span as a child of `parent`, and return the context of the span;
+pub(crate) fn start_span(name: String, parent: &Context, attributes: Vec<KeyValue>) -> Context {
+    let tracer = global::tracer(TRACER_NAME);
+    let span = tracer
+        .span_builder(name)
+        .with_attributes(attributes)
+        .start_with_context(&tracer, parent);
+    parent.with_span(span)
+}
+
+#[inline]
+pub(crate) fn is_recording(cx: &Context) -> bool {
+    cx.has_active_span() && cx.span().is_recording()
+}
+
+/// Attach the context as the current one until the guard is dropped, if its span is recorded;
+#[inline]
+pub(crate) fn enter(cx: &Context) -> Option<ContextGuard> {
+    if is_recording(cx) {
+        Some(cx.clone().attach())
+    } else {
+        None
+    }
+}
+
+/// The span of an exchange channel shared by the pushes to all target workers, which counts the
+/// records pushed to each of them, and ends when all pushes are dropped;
+pub(crate) struct ExchangeSpan {
+    cx: Context,
+    records: Vec<AtomicU64>,
+}
+
+impl ExchangeSpan {
+    pub(crate) fn new(cx: Context, targets: usize) -> Arc<Self> {
+        let records = (0..targets)
+            .map(|_| AtomicU64::new(0))
+            .collect();
+        Arc::new(ExchangeSpan { cx, records })
+    }
+
+    #[inline]
+    pub(crate) fn add_records(&self, target: usize, records: usize) {
+        if let Some(count) = self.records.get(target) {
+            count.fetch_add(records as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for ExchangeSpan {
+    fn drop(&mut self) {
+        let span = self.cx.span();
+        let mut total = 0;
+        for (target, count) in self.records.iter().enumerate() {
+            let count = count.load(Ordering::Relaxed);
+            total += count;
+            span.set_attribute(KeyValue::new(format!("records.to.worker-{}", target), count as i64));
+        }
+        span.set_attribute(KeyValue::new("records", total as i64));
+        span.end();
+    }
+}
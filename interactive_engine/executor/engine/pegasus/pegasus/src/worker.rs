@@ -19,8 +19,8 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
-use opentelemetry::global::BoxedSpan;
-use opentelemetry::{trace, trace::Span, KeyValue};
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::{trace, Context, KeyValue};
 use pegasus_executor::{Task, TaskState};
 
 use crate::api::primitive::source::Source;
@@ -32,11 +32,13 @@ use crate::errors::{BuildJobError, JobExecError};
 use crate::event::emitter::EventEmitter;
 use crate::event::Event;
 use crate::graph::Port;
+use crate::metrics::RunningJob;
 use crate::progress::DynPeers;
 use crate::progress::EndOfScope;
 use crate::resource::{KeyedResources, ResourceMap};
 use crate::result::ResultSink;
 use crate::schedule::Schedule;
+use crate::telemetry;
 use crate::{Data, JobConf, Tag, WorkerId};
 
 pub struct Worker<D: Data, T: Debug + Send + 'static> {
@@ -49,14 +51,15 @@ pub struct Worker<D: Data, T: Debug + Send + 'static> {
     resources: ResourceMap,
     keyed_resources: KeyedResources,
     is_finished: bool,
-    span: BoxedSpan,
+    trace_cx: Context,
+    _running: Arc<RunningJob>,
     _ph: std::marker::PhantomData<D>,
 }
 
 impl<D: Data, T: Debug + Send + 'static> Worker<D, T> {
     pub(crate) fn new(
-        conf: &Arc<JobConf>, id: WorkerId, peer_guard: &Arc<AtomicUsize>, sink: ResultSink<T>,
-        span: BoxedSpan,
+        conf: &Arc<JobConf>, id: WorkerId, peer_guard: &Arc<AtomicUsize>, running: &Arc<RunningJob>,
+        sink: ResultSink<T>, trace_cx: Context,
     ) -> Self {
         if peer_guard.fetch_add(1, Ordering::SeqCst) == 0 {
             pegasus_memory::alloc::new_task(conf.job_id as usize);
@@ -72,7 +75,8 @@ impl<D: Data, T: Debug + Send + 'static> Worker<D, T> {
             resources: ResourceMap::default(),
             keyed_resources: KeyedResources::default(),
             is_finished: false,
-            span: span,
+            trace_cx,
+            _running: running.clone(),
             _ph: std::marker::PhantomData,
         }
     }
@@ -101,7 +105,7 @@ impl<D: Data, T: Debug + Send + 'static> Worker<D, T> {
             abort.close().ok();
         }
         let event_emitter = EventEmitter::new(tx);
-        let dfb = DataflowBuilder::new(self.id, event_emitter.clone(), &self.conf);
+        let dfb = DataflowBuilder::new(self.id, event_emitter.clone(), &self.conf, &self.trace_cx);
         let root_builder = OutputBuilderImpl::new(
             Port::new(0, 0),
             0,
@@ -239,14 +243,16 @@ impl<D: Data, T: Debug + Send + 'static> Task for Worker<D, T> {
     fn execute(&mut self) -> TaskState {
         let _g = crate::worker_id::guard(self.id);
         if self.check_cancel() {
-            self.span
+            self.trace_cx
+                .span()
                 .set_status(trace::Status::error("Job is canceled"));
-            self.span.end();
+            self.trace_cx.span().end();
             self.sink.set_cancel_hook(true);
             return TaskState::Finished;
         }
 
         let _ctx = WorkerContext::new(&mut self.resources, &mut self.keyed_resources);
+        let _trace = telemetry::enter(&self.trace_cx);
 
         match self.task.execute() {
             Ok(state) => {
@@ -259,10 +265,13 @@ impl<D: Data, T: Debug + Send + 'static> Task for Worker<D, T> {
                         elapsed
                     );
                     self.is_finished = true;
-                    self.span
+                    self.trace_cx
+                        .span()
                         .set_attribute(KeyValue::new("used_ms", elapsed.to_string()));
-                    self.span.set_status(trace::Status::Ok);
-                    self.span.end();
+                    self.trace_cx
+                        .span()
+                        .set_status(trace::Status::Ok);
+                    self.trace_cx.span().end();
 
                     // if this is last worker, return Finished
                     if self.peer_guard.fetch_sub(1, Ordering::SeqCst) == 1 {
@@ -277,9 +286,10 @@ impl<D: Data, T: Debug + Send + 'static> Task for Worker<D, T> {
             }
             Err(e) => {
                 error_worker!("job({}) execute error: {}", self.id.job_id, e);
-                self.span
+                self.trace_cx
+                    .span()
                     .set_status(trace::Status::error(format!("Execution error: {}", e)));
-                self.span.end();
+                self.trace_cx.span().end();
                 self.sink.on_error(e);
                 TaskState::Finished
             }
@@ -32,6 +32,8 @@ pub struct WorkerId {
     pub servers: u32,
     /// Indicates that if trace is enabled;
     pub trace_enable: bool,
+    /// Indicates that if the job runs reproducibly, see `JobConf::deterministic`;
+    pub deterministic: bool,
 }
 
 impl WorkerId {
@@ -39,7 +41,16 @@ impl WorkerId {
         job_id: u64, local_peers: u32, index: u32, server_id: u32, server_index: u32, servers: u32,
         trace: bool,
     ) -> Self {
-        WorkerId { job_id, local_peers, index, server_id, server_index, servers, trace_enable: trace }
+        WorkerId {
+            job_id,
+            local_peers,
+            index,
+            server_id,
+            server_index,
+            servers,
+            trace_enable: trace,
+            deterministic: false,
+        }
     }
 
     pub fn total_peers(&self) -> u32 {
@@ -68,6 +79,7 @@ pub struct WorkerIdIter {
     server_index: u32,
     servers: u32,
     trace_enable: bool,
+    deterministic: bool,
     cursor: u32,
     last: u32,
 }
@@ -83,10 +95,16 @@ impl WorkerIdIter {
             server_index,
             servers,
             trace_enable: false,
+            deterministic: false,
             cursor,
             last,
         }
     }
+
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
 }
 
 impl Iterator for WorkerIdIter {
@@ -96,7 +114,7 @@ impl Iterator for WorkerIdIter {
         if self.cursor == self.last {
             None
         } else {
-            let next = WorkerId::new(
+            let mut next = WorkerId::new(
                 self.job_id,
                 self.local_peers,
                 self.cursor,
@@ -105,6 +123,7 @@ impl Iterator for WorkerIdIter {
                 self.servers,
                 self.trace_enable,
             );
+            next.deterministic = self.deterministic;
             self.cursor += 1;
             Some(next)
         }
@@ -296,6 +296,34 @@ fn fold_partition_by_key_test() {
     }
 }
 
+#[test]
+fn fold_local_by_key_test() {
+    let mut conf = JobConf::new("fold_local_by_key");
+    conf.set_workers(2);
+    let num = 1000u32;
+    let mut result = pegasus::run(conf, || {
+        let index = pegasus::get_current_worker().index;
+        let src = index * num..(index + 1) * num;
+        move |input, output| {
+            input
+                .input_from(src)?
+                .key_by(|x| Ok((x % 4, x)))?
+                .fold_local_by_key(0u32, || |a, _| Ok(a + 1))?
+                .sink_into(output)
+        }
+    })
+    .expect("submit job failure:");
+    // the data of each worker are folded by the worker, with all the keys
+    for _ in 0..2 {
+        let groups = result.next().unwrap().unwrap();
+        assert_eq!(groups.len(), 4);
+        for key in 0..4 {
+            assert_eq!(groups.get(&key), Some(&(num / 4)));
+        }
+    }
+    assert!(result.next().is_none());
+}
+
 #[test]
 fn fold_partition_test() {
     let mut conf = JobConf::new("fold_partition_test");
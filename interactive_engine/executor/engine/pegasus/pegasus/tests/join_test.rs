@@ -128,10 +128,10 @@ fn join_test_key_by_with_keyed() {
         let id = pegasus::get_current_worker().index;
         move |input, output| {
             let (src1, src2) = create_src(id, input)?;
-            let src1 = src1.key_by(|x| Ok((x, x)))?.partition_by_key();
+            let src1 = src1.key_by(|x| Ok((x, x)))?.partition_by_key()?;
             let new_src2 = src2
                 .map(|x| Ok(I32 { item: x }))?
-                .partition_by_key();
+                .partition_by_key()?;
 
             src1.inner_join(new_src2)?
                 .map(|(d1, d2)| Ok(((d1.key, d1.value), d2)))?
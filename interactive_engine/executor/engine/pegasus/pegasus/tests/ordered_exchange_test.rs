@@ -0,0 +1,116 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//
+
+use pegasus::api::{Collect, Filter, KeyBy, Map, OrderedExchange, PartitionByKey, Sink};
+use pegasus::JobConf;
+
+fn worker_data(index: u32) -> Vec<u32> {
+    (0..100).map(|i| index * 1000 + i).collect()
+}
+
+#[test]
+fn repartition_ordered_test() {
+    let mut conf = JobConf::new("repartition_ordered_test");
+    conf.set_workers(4);
+    let mut results = pegasus::run(conf, || {
+        let index = pegasus::get_current_worker().index;
+        move |input, output| {
+            input
+                .input_from(worker_data(index))?
+                .repartition_ordered(|_| Ok(1))?
+                .collect::<Vec<u32>>()?
+                .sink_into(output)
+        }
+    })
+    .expect("submit job failure");
+
+    let expected: Vec<u32> = (0..4).flat_map(worker_data).collect();
+    assert_eq!(results.next().unwrap().unwrap(), expected);
+}
+
+#[test]
+fn broadcast_ordered_test() {
+    let mut conf = JobConf::new("broadcast_ordered_test");
+    conf.set_workers(4);
+    let mut results = pegasus::run(conf, || {
+        let index = pegasus::get_current_worker().index;
+        move |input, output| {
+            input
+                .input_from(worker_data(index))?
+                .broadcast_ordered()?
+                .filter(move |_| Ok(index == 2))?
+                .collect::<Vec<u32>>()?
+                .sink_into(output)
+        }
+    })
+    .expect("submit job failure");
+
+    let expected: Vec<u32> = (0..4).flat_map(worker_data).collect();
+    assert_eq!(results.next().unwrap().unwrap(), expected);
+}
+
+#[test]
+fn aggregate_ordered_test() {
+    let mut conf = JobConf::new("aggregate_ordered_test");
+    conf.set_workers(4);
+    let results = pegasus::run(conf, || {
+        let index = pegasus::get_current_worker().index;
+        move |input, output| {
+            input
+                .input_from(worker_data(index))?
+                .aggregate_ordered()?
+                .sink_into(output)
+        }
+    })
+    .expect("submit job failure");
+
+    let expected: Vec<u32> = (0..4).flat_map(worker_data).collect();
+    assert_eq!(
+        results
+            .map(|x| x.unwrap())
+            .collect::<Vec<u32>>(),
+        expected
+    );
+}
+
+#[test]
+fn deterministic_partition_by_key_test() {
+    for _ in 0..3 {
+        let mut conf = JobConf::new("deterministic_partition_by_key_test");
+        conf.set_workers(4);
+        conf.deterministic = true;
+        let mut results = pegasus::run(conf, || {
+            let index = pegasus::get_current_worker().index;
+            move |input, output| {
+                input
+                    .input_from(worker_data(index))?
+                    .key_by(|x| Ok((x % 2, x)))?
+                    .partition_by_key()?
+                    .filter(|pair| Ok(pair.key == 0))?
+                    .map(|pair| Ok(pair.value))?
+                    .collect::<Vec<u32>>()?
+                    .sink_into(output)
+            }
+        })
+        .expect("submit job failure");
+
+        let expected: Vec<u32> = (0..4)
+            .flat_map(worker_data)
+            .filter(|x| x % 2 == 0)
+            .collect();
+        assert_eq!(results.next().unwrap().unwrap(), expected);
+    }
+}
@@ -0,0 +1,339 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Admin reports of the stored graph, which are computed by the store and served on the metrics server:
+//! the degree distributions, the hottest vertices and the partition skew of an edge label on
+//! `GET /admin/degrees?label=knows&sample_rate=0.01&snapshot_id=42&refresh=true`, where only the label
+//! is required; the report is exact without a sample rate, and of the latest snapshot without a
+//! snapshot id. The store sets its reporter by `set_degree_reporter`. The report of a server is of its
+//! own partitions, so the partitions of the peers are merged into it by `merge_degree_reports`, where
+//! the edge count and the skew are of the cluster, while the degree distributions are of the server;
+//! a peer is asked for its own report by `local=true`.
+//!
+//! The subgraph at a snapshot is extracted into the export directory of each server on
+//! `POST /admin/export?name=train&snapshot_id=42&format=parquet&vertex_labels=person&edge_labels=knows`,
+//! where the name and the snapshot id are required, and all the labels are extracted by default. The
+//! store sets its exporter by `set_subgraph_exporter`.
+//!
+//! The stored procedures of the runtime are installed on `POST /admin/procedures?name=by_name`, with
+//! the physical plan encoded in protobuf as the body, and uninstalled on
+//! `DELETE /admin/procedures?name=by_name`. The runtime sets its registry by `set_procedure_admin`.
+//!
+//! All the admin endpoints require a caller authenticated with the role `ADMIN_ROLE`, and are refused if
+//! the server has no authenticator.
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+
+/// The role of the callers allowed to the admin endpoints.
+pub const ADMIN_ROLE: &str = "admin";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DegreeQuery {
+    pub label: String,
+    /// The rate in `(0, 1]` the edges are sampled at, exact if not set.
+    pub sample_rate: Option<f64>,
+    pub snapshot_id: Option<i64>,
+    /// Compute the report again rather than take it from the cache.
+    pub refresh: bool,
+    /// Report the partitions of the server only, rather than merge those of the peers.
+    pub local: bool,
+}
+
+impl DegreeQuery {
+    pub fn parse(query: Option<&str>) -> Result<Self, String> {
+        let mut degree_query = DegreeQuery::default();
+        for pair in query
+            .into_iter()
+            .flat_map(|query| query.split('&'))
+        {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "label" => degree_query.label = value.to_owned(),
+                "sample_rate" => {
+                    let rate = value
+                        .parse::<f64>()
+                        .map_err(|e| format!("invalid sample_rate {}: {}", value, e))?;
+                    degree_query.sample_rate = Some(rate);
+                }
+                "snapshot_id" => {
+                    let si = value
+                        .parse::<i64>()
+                        .map_err(|e| format!("invalid snapshot_id {}: {}", value, e))?;
+                    degree_query.snapshot_id = Some(si);
+                }
+                "refresh" => degree_query.refresh = value.is_empty() || value == "true",
+                "local" => degree_query.local = value.is_empty() || value == "true",
+                _ => return Err(format!("unknown parameter {}", key)),
+            }
+        }
+        if degree_query.label.is_empty() {
+            return Err("label is required".to_owned());
+        }
+        Ok(degree_query)
+    }
+}
+
+/// Computes the report of the query in json, which may scan all the edges of the label.
+pub type DegreeReporter = dyn Fn(&DegreeQuery) -> Result<Vec<u8>, String> + Send + Sync;
+
+static DEGREE_REPORTER: Mutex<Option<Arc<DegreeReporter>>> = Mutex::new(None);
+
+pub fn set_degree_reporter<F>(reporter: F)
+where
+    F: Fn(&DegreeQuery) -> Result<Vec<u8>, String> + Send + Sync + 'static,
+{
+    *DEGREE_REPORTER.lock().unwrap() = Some(Arc::new(reporter));
+}
+
+pub fn get_degree_reporter() -> Option<Arc<DegreeReporter>> {
+    DEGREE_REPORTER.lock().unwrap().clone()
+}
+
+/// Merge the partitions of the reports of the peers into the report of the server, where the edge count
+/// and the partition skew are recomputed over all the partitions.
+pub fn merge_degree_reports(report: &[u8], peer_reports: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+    let mut report: Value = serde_json::from_slice(report).map_err(|e| e.to_string())?;
+    let mut partitions = take_partitions(&mut report)?;
+    for peer_report in peer_reports {
+        let mut peer_report: Value = serde_json::from_slice(peer_report).map_err(|e| e.to_string())?;
+        partitions.extend(take_partitions(&mut peer_report)?);
+    }
+    partitions.sort_by_key(|(partition_id, _)| *partition_id);
+    partitions.dedup_by_key(|(partition_id, _)| *partition_id);
+    let edge_count: u64 = partitions.iter().map(|(_, count)| count).sum();
+    let max = partitions
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0);
+    let skew = if edge_count == 0 { 1.0 } else { max as f64 * partitions.len() as f64 / edge_count as f64 };
+    report["edge_count"] = edge_count.into();
+    report["partition_skew"] = skew.into();
+    report["servers"] = (peer_reports.len() + 1).into();
+    report["partitions"] = partitions
+        .into_iter()
+        .map(|(partition_id, edge_count)| {
+            serde_json::json!({ "partition_id": partition_id, "edge_count": edge_count })
+        })
+        .collect::<Vec<_>>()
+        .into();
+    serde_json::to_vec(&report).map_err(|e| e.to_string())
+}
+
+fn take_partitions(report: &mut Value) -> Result<Vec<(u64, u64)>, String> {
+    let partitions = match report.get_mut("partitions").map(Value::take) {
+        Some(Value::Array(partitions)) => partitions,
+        _ => return Err("partitions not found in degree report".to_owned()),
+    };
+    partitions
+        .iter()
+        .map(|partition| {
+            let partition_id = partition
+                .get("partition_id")
+                .and_then(Value::as_u64);
+            let edge_count = partition
+                .get("edge_count")
+                .and_then(Value::as_u64);
+            partition_id
+                .zip(edge_count)
+                .ok_or_else(|| format!("invalid partition {} in degree report", partition))
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExportQuery {
+    /// The directory under the export directory of the server the subgraph is extracted into.
+    pub name: String,
+    pub snapshot_id: i64,
+    /// The name of the format, e.g. `csv` or `parquet`, `csv` if not set.
+    pub format: Option<String>,
+    pub vertex_labels: Vec<String>,
+    pub edge_labels: Vec<String>,
+}
+
+impl ExportQuery {
+    pub fn parse(query: Option<&str>) -> Result<Self, String> {
+        let mut export_query = ExportQuery::default();
+        let mut snapshot_id = None;
+        for pair in query
+            .into_iter()
+            .flat_map(|query| query.split('&'))
+        {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "name" => export_query.name = value.to_owned(),
+                "snapshot_id" => {
+                    let si = value
+                        .parse::<i64>()
+                        .map_err(|e| format!("invalid snapshot_id {}: {}", value, e))?;
+                    snapshot_id = Some(si);
+                }
+                "format" => export_query.format = Some(value.to_owned()),
+                "vertex_labels" => export_query.vertex_labels = split_labels(value),
+                "edge_labels" => export_query.edge_labels = split_labels(value),
+                _ => return Err(format!("unknown parameter {}", key)),
+            }
+        }
+        // the name is a directory under the export directory, which must not escape it
+        if export_query.name.is_empty()
+            || !export_query
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("invalid name `{}`", export_query.name));
+        }
+        export_query.snapshot_id = snapshot_id.ok_or_else(|| "snapshot_id is required".to_owned())?;
+        Ok(export_query)
+    }
+}
+
+fn split_labels(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter(|label| !label.is_empty())
+        .map(|label| label.to_owned())
+        .collect()
+}
+
+/// Extracts the subgraph of the query, and returns the manifest of the files written in json.
+pub type SubgraphExporter = dyn Fn(&ExportQuery) -> Result<Vec<u8>, String> + Send + Sync;
+
+static SUBGRAPH_EXPORTER: Mutex<Option<Arc<SubgraphExporter>>> = Mutex::new(None);
+
+pub fn set_subgraph_exporter<F>(exporter: F)
+where
+    F: Fn(&ExportQuery) -> Result<Vec<u8>, String> + Send + Sync + 'static,
+{
+    *SUBGRAPH_EXPORTER.lock().unwrap() = Some(Arc::new(exporter));
+}
+
+pub fn get_subgraph_exporter() -> Option<Arc<SubgraphExporter>> {
+    SUBGRAPH_EXPORTER.lock().unwrap().clone()
+}
+
+/// Installs and uninstalls the stored procedures, which run with the privileges of the admin.
+pub trait ProcedureAdmin: Send + Sync + 'static {
+    /// Install the physical plan encoded in protobuf as the procedure of the name, replacing the one
+    /// installed before.
+    fn install(&self, name: &str, plan: &[u8]) -> Result<(), String>;
+
+    /// Uninstall the procedure of the name, returning whether it is found.
+    fn uninstall(&self, name: &str) -> Result<bool, String>;
+}
+
+static PROCEDURE_ADMIN: Mutex<Option<Arc<dyn ProcedureAdmin>>> = Mutex::new(None);
+
+pub fn set_procedure_admin(admin: Arc<dyn ProcedureAdmin>) {
+    *PROCEDURE_ADMIN.lock().unwrap() = Some(admin);
+}
+
+pub fn get_procedure_admin() -> Option<Arc<dyn ProcedureAdmin>> {
+    PROCEDURE_ADMIN.lock().unwrap().clone()
+}
+
+/// The name of the procedure on `/admin/procedures?name=by_name`.
+pub fn parse_procedure_name(query: Option<&str>) -> Result<String, String> {
+    let mut name = None;
+    for pair in query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+    {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "name" => name = Some(value.to_owned()),
+            _ => return Err(format!("unknown parameter {}", key)),
+        }
+    }
+    match name {
+        Some(name) if !name.is_empty() => Ok(name),
+        _ => Err("name is required".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_degree_query() {
+        let query = DegreeQuery::parse(Some("label=knows&sample_rate=0.01&refresh")).unwrap();
+        assert_eq!(
+            query,
+            DegreeQuery {
+                label: "knows".to_owned(),
+                sample_rate: Some(0.01),
+                snapshot_id: None,
+                refresh: true,
+                local: false,
+            }
+        );
+        let query = DegreeQuery::parse(Some("snapshot_id=42&label=3&local=true")).unwrap();
+        assert_eq!(query.snapshot_id, Some(42));
+        assert!(!query.refresh);
+        assert!(query.local);
+        assert!(DegreeQuery::parse(None).is_err());
+        assert!(DegreeQuery::parse(Some("label=knows&sample_rate=x")).is_err());
+        assert!(DegreeQuery::parse(Some("label=knows&top=3")).is_err());
+    }
+
+    #[test]
+    fn test_merge_degree_reports() {
+        let report = br#"{"label":"knows","edge_count":30,"partition_skew":1.0,
+            "partitions":[{"partition_id":0,"edge_count":10},{"partition_id":1,"edge_count":20}]}"#;
+        let peer_report = br#"{"label":"knows","edge_count":90,"partition_skew":1.0,
+            "partitions":[{"partition_id":2,"edge_count":90}]}"#
+            .to_vec();
+        let merged = merge_degree_reports(report, &[peer_report]).unwrap();
+        let merged: Value = serde_json::from_slice(&merged).unwrap();
+        assert_eq!(merged["edge_count"], 120);
+        assert_eq!(merged["partition_skew"], 90.0 * 3.0 / 120.0);
+        assert_eq!(merged["servers"], 2);
+        assert_eq!(merged["partitions"].as_array().unwrap().len(), 3);
+        assert_eq!(merged["label"], "knows");
+        assert!(merge_degree_reports(report, &[b"{}".to_vec()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_export_query() {
+        let query =
+            ExportQuery::parse(Some("name=train&snapshot_id=42&format=parquet&edge_labels=knows,created"))
+                .unwrap();
+        assert_eq!(
+            query,
+            ExportQuery {
+                name: "train".to_owned(),
+                snapshot_id: 42,
+                format: Some("parquet".to_owned()),
+                vertex_labels: vec![],
+                edge_labels: vec!["knows".to_owned(), "created".to_owned()],
+            }
+        );
+        assert!(ExportQuery::parse(Some("name=train")).is_err());
+        assert!(ExportQuery::parse(Some("snapshot_id=42")).is_err());
+        assert!(ExportQuery::parse(Some("name=../etc&snapshot_id=42")).is_err());
+    }
+
+    #[test]
+    fn test_parse_procedure_name() {
+        assert_eq!(parse_procedure_name(Some("name=by_name")).unwrap(), "by_name");
+        assert!(parse_procedure_name(None).is_err());
+        assert!(parse_procedure_name(Some("name=")).is_err());
+        assert!(parse_procedure_name(Some("name=by_name&args=1")).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Admission of the jobs whose plans scan all the vertices or edges, of all labels or some, without any
+//! filter or limit, found by `JobAssembly::lint`. By the `[admission]` section of the runtime
+//! configuration, such jobs are:
+//! * `allow`: run as they are, by default;
+//! * `warn`: run as they are, with a warning logged;
+//! * `reject`: not run;
+//! * `limit`: run with their results limited to `safety_limit` in total, see `JobDesc::result_limit`;
+//! * `low_priority`: run in the low priority class, i.e., in the `background` scheduling class, with the
+//!   settings of `[admission.low_priority]` capping their own, e.g. fewer workers and a shorter time
+//!   limit.
+
+use pegasus::JobConf;
+use serde::Deserialize;
+
+use crate::job::{JobAssembly, JobDesc};
+use crate::scheduling::SchedulingClass;
+
+const DEFAULT_SAFETY_LIMIT: u64 = 10000;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnboundedScanAction {
+    Allow,
+    Warn,
+    Reject,
+    Limit,
+    LowPriority,
+}
+
+impl Default for UnboundedScanAction {
+    fn default() -> Self {
+        UnboundedScanAction::Allow
+    }
+}
+
+/// The settings of the low priority class, each capping that of the jobs in the class if set.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct LowPriorityClass {
+    pub workers: Option<u32>,
+    pub time_limit_ms: Option<u64>,
+    pub batch_capacity: Option<u32>,
+    pub memory_limit_mb: Option<u32>,
+}
+
+impl LowPriorityClass {
+    fn apply(&self, conf: &mut JobConf) {
+        if let Some(workers) = self.workers {
+            conf.workers = conf.workers.min(workers.max(1));
+        }
+        if let Some(time_limit) = self.time_limit_ms {
+            conf.time_limit = conf.time_limit.min(time_limit);
+        }
+        if let Some(batch_capacity) = self.batch_capacity {
+            conf.batch_capacity = conf.batch_capacity.min(batch_capacity.max(1));
+        }
+        if let Some(memory_limit) = self.memory_limit_mb {
+            conf.memory_limit = conf.memory_limit.min(memory_limit);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct AdmissionPolicy {
+    #[serde(default)]
+    pub unbounded_scan: UnboundedScanAction,
+    /// The most results of the jobs limited by `limit`, 10000 by default.
+    pub safety_limit: Option<u64>,
+    #[serde(default)]
+    pub low_priority: LowPriorityClass,
+}
+
+impl AdmissionPolicy {
+    /// Admit a job by the policy, which may limit its results or change its configuration; returns the
+    /// reason if it's rejected.
+    pub fn admit<I: pegasus::Data>(
+        &self, assembly: &dyn JobAssembly<I>, job: &mut JobDesc, conf: &mut JobConf,
+    ) -> Result<(), String> {
+        if self.unbounded_scan == UnboundedScanAction::Allow {
+            return Ok(());
+        }
+        let scans = assembly.lint(job);
+        if scans.is_empty() {
+            return Ok(());
+        }
+        let scans = scans.join(", ");
+        match self.unbounded_scan {
+            UnboundedScanAction::Allow => {}
+            UnboundedScanAction::Warn => {
+                warn!("job {} has unbounded scans: {}", conf.job_id, scans);
+            }
+            UnboundedScanAction::Reject => {
+                return Err(format!("unbounded scans without any filter or limit: {}", scans));
+            }
+            UnboundedScanAction::Limit => {
+                let limit = self
+                    .safety_limit
+                    .unwrap_or(DEFAULT_SAFETY_LIMIT);
+                info!("job {} has unbounded scans: {}, limited to {}", conf.job_id, scans, limit);
+                job.result_limit = Some(limit);
+            }
+            UnboundedScanAction::LowPriority => {
+                info!("job {} has unbounded scans: {}, run in low priority", conf.job_id, scans);
+                job.scheduling_class = SchedulingClass::Background;
+                self.low_priority.apply(conf);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pegasus::{BuildJobError, Worker};
+
+    use super::*;
+
+    /// Any plan of `scan` scans unbounded.
+    struct LintAssembly;
+
+    impl JobAssembly<Vec<u8>> for LintAssembly {
+        fn assemble(
+            &self, _job: &JobDesc, _worker: &mut Worker<Vec<u8>, Vec<u8>>,
+        ) -> Result<(), BuildJobError> {
+            Ok(())
+        }
+
+        fn lint(&self, job: &JobDesc) -> Vec<String> {
+            if job.plan == b"scan" {
+                vec!["scan of all labels".to_owned()]
+            } else {
+                vec![]
+            }
+        }
+    }
+
+    fn job(plan: &str) -> JobDesc {
+        JobDesc { plan: plan.as_bytes().to_vec(), ..Default::default() }
+    }
+
+    fn admit(config: &str, job: &mut JobDesc, conf: &mut JobConf) -> Result<(), String> {
+        let policy: AdmissionPolicy = toml::from_str(config).unwrap();
+        let assembly: &dyn JobAssembly<Vec<u8>> = &LintAssembly;
+        policy.admit(assembly, job, conf)
+    }
+
+    #[test]
+    fn admit_test() {
+        let mut conf = JobConf::new("test");
+        conf.workers = 4;
+        let reject = "unbounded_scan = \"reject\"";
+        assert!(admit(reject, &mut job("scan"), &mut conf).is_err());
+        assert!(admit(reject, &mut job("scan.has(name)"), &mut conf).is_ok());
+
+        let mut limited = job("scan");
+        admit("unbounded_scan = \"limit\"\nsafety_limit = 10", &mut limited, &mut conf).unwrap();
+        assert_eq!(limited.plan, b"scan");
+        assert_eq!(limited.result_limit, Some(10));
+
+        let low_priority = r#"
+            unbounded_scan = "low_priority"
+            [low_priority]
+            workers = 1
+            time_limit_ms = 1000
+        "#;
+        let mut demoted = job("scan");
+        admit(low_priority, &mut demoted, &mut conf).unwrap();
+        assert_eq!(demoted.scheduling_class, SchedulingClass::Background);
+        assert_eq!(conf.workers, 1);
+        assert_eq!(conf.time_limit, 1000);
+        assert_eq!(conf.batch_capacity, JobConf::default().batch_capacity);
+    }
+}
@@ -0,0 +1,470 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Index advisor: learn the properties filtered by the jobs from their audit records, and the audit or
+//! slow query logs written before, and recommend the indexes saving the most of the time of the jobs:
+//! * a property index, on a property compared for equality or a range;
+//! * a composite index, on the properties of a label compared for equality together;
+//! * a fulltext index, on a property matched by a prefix, a suffix or a regex.
+//!
+//! The labels having fewer elements than `min_label_size` in the statistics of the stored graph are
+//! not worth indexing. The recommendations are served on `GET /advisor/indexes` of the metrics server,
+//! and created in the maintenance window by the index creator of the store if set, see
+//! `set_index_creator`; the stores without indexes don't set it, their recommendations are served only.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{AuditRecord, AuditSink, FilterSummary};
+
+const DEFAULT_MIN_QUERIES: u64 = 10;
+const DEFAULT_MIN_LABEL_SIZE: u64 = 10000;
+const DEFAULT_MAX_RECOMMENDATIONS: usize = 20;
+const CHECK_WINDOW_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The number of the elements of a label, by the label and whether it's of edges.
+pub type LabelStatistics = dyn Fn(&str, bool) -> Option<u64> + Send + Sync;
+
+type IndexCreator = Box<dyn Fn(&IndexRecommendation) -> Result<(), String> + Send + Sync>;
+
+static ADVISOR: Mutex<Option<Arc<IndexAdvisor>>> = Mutex::new(None);
+static INDEX_CREATOR: Mutex<Option<IndexCreator>> = Mutex::new(None);
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AdvisorConfig {
+    /// Recommend the indexes helping at least the number of jobs, 10 by default.
+    pub min_queries: Option<u64>,
+    /// Don't recommend the indexes of the labels with fewer elements, 10000 by default.
+    pub min_label_size: Option<u64>,
+    /// The max number of the recommendations, 20 by default.
+    pub max_recommendations: Option<usize>,
+    /// The audit or slow query logs to learn from on start.
+    pub history_files: Option<Vec<String>>,
+    /// Create the recommended indexes in the window of `HH:MM-HH:MM` in UTC, e.g. `02:00-04:00`;
+    /// they are not created if not set.
+    pub maintenance_window: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexKind {
+    Property,
+    Composite,
+    Fulltext,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct IndexRecommendation {
+    pub kind: IndexKind,
+    pub label: String,
+    pub edge: bool,
+    pub properties: Vec<String>,
+    /// The number of jobs the index helps.
+    pub queries: u64,
+    /// The time of the jobs the index helps.
+    pub latency_ms: u64,
+    /// The number of elements of the label, if known from the statistics.
+    pub elements: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct IndexKey {
+    kind: IndexKind,
+    label: String,
+    edge: bool,
+    properties: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct IndexUsage {
+    queries: u64,
+    latency_ms: u64,
+}
+
+/// The window of the day in UTC, in minutes, which may wrap around midnight.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaintenanceWindow {
+    start: u32,
+    end: u32,
+}
+
+impl MaintenanceWindow {
+    pub fn parse(window: &str) -> Option<Self> {
+        let minutes = |time: &str| -> Option<u32> {
+            let (hour, minute) = time.trim().split_once(':')?;
+            let (hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+            if hour < 24 && minute < 60 {
+                Some(hour * 60 + minute)
+            } else {
+                None
+            }
+        };
+        let (start, end) = window.split_once('-')?;
+        Some(MaintenanceWindow { start: minutes(start)?, end: minutes(end)? })
+    }
+
+    pub fn contains(&self, minute_of_day: u32) -> bool {
+        if self.start <= self.end {
+            self.start <= minute_of_day && minute_of_day < self.end
+        } else {
+            self.start <= minute_of_day || minute_of_day < self.end
+        }
+    }
+
+    fn contains_now(&self) -> bool {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.contains(((secs % 86400) / 60) as u32)
+    }
+}
+
+/// The part of the audit or slow query records the advisor learns from.
+#[derive(Deserialize)]
+struct LoggedJob {
+    #[serde(default)]
+    latency_ms: u64,
+    #[serde(default)]
+    filters: Vec<FilterSummary>,
+}
+
+pub struct IndexAdvisor {
+    config: AdvisorConfig,
+    statistics: Box<LabelStatistics>,
+    usages: Mutex<HashMap<IndexKey, IndexUsage>>,
+    /// The indexes created, or failed to create, which are not recommended again.
+    created: Mutex<HashSet<IndexKey>>,
+}
+
+impl IndexAdvisor {
+    pub fn new(config: AdvisorConfig, statistics: Box<LabelStatistics>) -> Self {
+        IndexAdvisor {
+            config,
+            statistics,
+            usages: Mutex::new(HashMap::new()),
+            created: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Learn from the filters of a job taking `latency_ms`.
+    pub fn observe(&self, filters: &[FilterSummary], latency_ms: u64) {
+        // a job counts once for an index, however many operators it helps;
+        let mut keys = HashSet::new();
+        for filter in filters.iter().filter(|f| f.label != "*") {
+            let key = |kind, properties: Vec<String>| IndexKey {
+                kind,
+                label: filter.label.clone(),
+                edge: filter.edge,
+                properties,
+            };
+            for property in filter.eq.iter().chain(filter.range.iter()) {
+                keys.insert(key(IndexKind::Property, vec![property.clone()]));
+            }
+            let mut eq = filter.eq.clone();
+            eq.sort();
+            eq.dedup();
+            if eq.len() > 1 {
+                keys.insert(key(IndexKind::Composite, eq));
+            }
+            for property in filter.text.iter() {
+                keys.insert(key(IndexKind::Fulltext, vec![property.clone()]));
+            }
+        }
+        if keys.is_empty() {
+            return;
+        }
+        let mut usages = self.usages.lock().expect("advisor poisoned");
+        for key in keys {
+            let usage = usages.entry(key).or_default();
+            usage.queries += 1;
+            usage.latency_ms += latency_ms;
+        }
+    }
+
+    /// Learn from an audit or slow query log, returns the number of jobs learned.
+    pub fn observe_log(&self, path: &str) -> std::io::Result<usize> {
+        let mut jobs = 0;
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            match serde_json::from_str::<LoggedJob>(&line) {
+                Ok(job) if !job.filters.is_empty() => {
+                    self.observe(&job.filters, job.latency_ms);
+                    jobs += 1;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("skip the record of {} not parsed: {}", path, e),
+            }
+        }
+        Ok(jobs)
+    }
+
+    /// The indexes helping the jobs the most, by the time of the jobs they help.
+    pub fn recommend(&self) -> Vec<IndexRecommendation> {
+        let min_queries = self
+            .config
+            .min_queries
+            .unwrap_or(DEFAULT_MIN_QUERIES);
+        let min_label_size = self
+            .config
+            .min_label_size
+            .unwrap_or(DEFAULT_MIN_LABEL_SIZE);
+        let candidates: Vec<(IndexKey, IndexUsage)> = {
+            let usages = self.usages.lock().expect("advisor poisoned");
+            let created = self.created.lock().expect("advisor poisoned");
+            usages
+                .iter()
+                .filter(|(key, usage)| usage.queries >= min_queries && !created.contains(key))
+                .map(|(key, usage)| (key.clone(), *usage))
+                .collect()
+        };
+        // the statistics may be read from the store, not under the locks;
+        let mut elements = HashMap::new();
+        let mut recommendations: Vec<IndexRecommendation> = candidates
+            .into_iter()
+            .filter_map(|(key, usage)| {
+                let size = *elements
+                    .entry((key.label.clone(), key.edge))
+                    .or_insert_with(|| (self.statistics)(&key.label, key.edge));
+                if size.map_or(false, |size| size < min_label_size) {
+                    return None;
+                }
+                Some(IndexRecommendation {
+                    kind: key.kind,
+                    label: key.label,
+                    edge: key.edge,
+                    properties: key.properties,
+                    queries: usage.queries,
+                    latency_ms: usage.latency_ms,
+                    elements: size,
+                })
+            })
+            .collect();
+        recommendations.sort_by(|a, b| {
+            (b.latency_ms, b.queries)
+                .cmp(&(a.latency_ms, a.queries))
+                .then_with(|| (&a.label, &a.properties).cmp(&(&b.label, &b.properties)))
+        });
+        recommendations.truncate(
+            self.config
+                .max_recommendations
+                .unwrap_or(DEFAULT_MAX_RECOMMENDATIONS),
+        );
+        recommendations
+    }
+
+    /// Create the recommended indexes by the creator, each is tried once.
+    fn create_indexes(&self, creator: &IndexCreator) {
+        for recommendation in self.recommend() {
+            match creator(&recommendation) {
+                Ok(()) => info!("created the recommended index {:?}", recommendation),
+                Err(e) => warn!("create the recommended index {:?} failure: {}", recommendation, e),
+            }
+            let key = IndexKey {
+                kind: recommendation.kind,
+                label: recommendation.label,
+                edge: recommendation.edge,
+                properties: recommendation.properties,
+            };
+            self.created
+                .lock()
+                .expect("advisor poisoned")
+                .insert(key);
+        }
+    }
+}
+
+impl AuditSink for IndexAdvisor {
+    fn write(&self, record: &AuditRecord) {
+        if record.error.is_none() {
+            self.observe(&record.plan.filters, record.latency_ms);
+        }
+    }
+}
+
+/// Build the advisor of the config learning from its history, serve its recommendations, and create
+/// them in the maintenance window if any.
+pub fn start_advisor(
+    config: AdvisorConfig, statistics: Box<LabelStatistics>,
+) -> std::io::Result<Arc<IndexAdvisor>> {
+    let window = match config.maintenance_window.as_ref() {
+        Some(window) => Some(MaintenanceWindow::parse(window).ok_or_else(|| {
+            let msg = format!("invalid maintenance window `{}`", window);
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, msg)
+        })?),
+        None => None,
+    };
+    let advisor = Arc::new(IndexAdvisor::new(config, statistics));
+    for path in advisor.config.history_files.iter().flatten() {
+        let jobs = advisor.observe_log(path)?;
+        info!("index advisor learned {} jobs from {}", jobs, path);
+    }
+    *ADVISOR.lock().unwrap() = Some(advisor.clone());
+    if let Some(window) = window {
+        let advisor = advisor.clone();
+        std::thread::Builder::new()
+            .name("index-advisor".to_owned())
+            .spawn(move || loop {
+                std::thread::sleep(CHECK_WINDOW_INTERVAL);
+                if window.contains_now() {
+                    match INDEX_CREATOR.lock().unwrap().as_ref() {
+                        Some(creator) => advisor.create_indexes(creator),
+                        None => debug!("no index creator set, the recommended indexes are not created"),
+                    }
+                }
+            })?;
+    }
+    Ok(advisor)
+}
+
+/// The recommendations of the started advisor, `None` if it's not started.
+pub fn recommend() -> Option<Vec<IndexRecommendation>> {
+    let advisor = ADVISOR.lock().unwrap().clone();
+    advisor.map(|advisor| advisor.recommend())
+}
+
+/// Set how the store creates the recommended indexes.
+pub fn set_index_creator<F>(creator: F)
+where
+    F: Fn(&IndexRecommendation) -> Result<(), String> + Send + Sync + 'static,
+{
+    *INDEX_CREATOR.lock().unwrap() = Some(Box::new(creator));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn filter(label: &str, eq: &[&str], text: &[&str]) -> FilterSummary {
+        FilterSummary {
+            label: label.to_owned(),
+            edge: false,
+            eq: eq.iter().map(|p| p.to_string()).collect(),
+            range: vec![],
+            text: text.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    fn advisor() -> IndexAdvisor {
+        let config =
+            AdvisorConfig { min_queries: Some(2), min_label_size: Some(100), ..Default::default() };
+        let statistics = |label: &str, _edge: bool| match label {
+            "person" => Some(1000),
+            "tag" => Some(10),
+            _ => None,
+        };
+        IndexAdvisor::new(config, Box::new(statistics))
+    }
+
+    #[test]
+    fn recommend_test() {
+        let advisor = advisor();
+        for _ in 0..3 {
+            advisor.observe(&[filter("person", &["name", "city"], &[])], 100);
+        }
+        advisor.observe(&[filter("person", &["name"], &["bio"])], 50);
+        advisor.observe(&[filter("person", &[], &["bio"])], 10);
+        advisor.observe(&[filter("tag", &["name"], &[])], 1000);
+        advisor.observe(&[filter("tag", &["name"], &[])], 1000);
+        advisor.observe(&[filter("*", &["name"], &[])], 1000);
+        advisor.observe(&[filter("*", &["name"], &[])], 1000);
+
+        let recommendations = advisor.recommend();
+        let indexes: Vec<(IndexKind, Vec<&str>, u64)> = recommendations
+            .iter()
+            .map(|r| {
+                (
+                    r.kind,
+                    r.properties
+                        .iter()
+                        .map(|p| p.as_str())
+                        .collect(),
+                    r.latency_ms,
+                )
+            })
+            .collect();
+        assert_eq!(
+            indexes,
+            vec![
+                (IndexKind::Property, vec!["name"], 350),
+                (IndexKind::Property, vec!["city"], 300),
+                (IndexKind::Composite, vec!["city", "name"], 300),
+                (IndexKind::Fulltext, vec!["bio"], 60),
+            ]
+        );
+        assert_eq!(recommendations[0].elements, Some(1000));
+    }
+
+    #[test]
+    fn create_indexes_test() {
+        let advisor = advisor();
+        advisor.observe(&[filter("person", &[], &["bio"])], 10);
+        advisor.observe(&[filter("person", &[], &["bio"])], 10);
+        let created = Arc::new(Mutex::new(vec![]));
+        let creator: IndexCreator = {
+            let created = created.clone();
+            Box::new(move |r: &IndexRecommendation| {
+                created.lock().unwrap().push(r.clone());
+                Ok(())
+            })
+        };
+        advisor.create_indexes(&creator);
+        assert_eq!(created.lock().unwrap().len(), 1);
+        assert!(advisor.recommend().is_empty());
+    }
+
+    #[test]
+    fn observe_log_test() {
+        let path = std::env::temp_dir().join(format!("advisor_test_{}.log", std::process::id()));
+        let record = AuditRecord {
+            latency_ms: 5,
+            plan: crate::audit::PlanSummary {
+                filters: vec![filter("person", &["name"], &[])],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        std::fs::write(&path, format!("{}\n{}\n{{}}\n", line, line)).unwrap();
+        let advisor = advisor();
+        assert_eq!(
+            advisor
+                .observe_log(path.to_str().unwrap())
+                .unwrap(),
+            2
+        );
+        assert_eq!(advisor.recommend().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn maintenance_window_test() {
+        let window = MaintenanceWindow::parse("02:00-04:30").unwrap();
+        assert!(window.contains(120));
+        assert!(window.contains(269));
+        assert!(!window.contains(270));
+        let window = MaintenanceWindow::parse("23:00-01:00").unwrap();
+        assert!(window.contains(23 * 60 + 30));
+        assert!(window.contains(30));
+        assert!(!window.contains(60));
+        assert!(MaintenanceWindow::parse("25:00-01:00").is_none());
+        assert!(MaintenanceWindow::parse("02:00").is_none());
+    }
+}
@@ -0,0 +1,258 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Audit log of executed jobs: who ran which plan, the labels it touched, how many rows it returned
+//! and how long it took. Records are written as json lines to a pluggable `AuditSink`.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// The summary of a plan given by the job assembly, see `JobAssembly::summarize`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PlanSummary {
+    /// Identify the plans of the same shape, i.e., plans differing only in their constants.
+    pub fingerprint: String,
+    /// The constants stripped from the plan when it is fingerprinted.
+    pub parameters: Vec<String>,
+    pub labels: Vec<String>,
+    /// The properties filtered by the plan, for the index advisor, see `advisor`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<FilterSummary>,
+}
+
+/// The properties of a label filtered by an operator of a plan, by the kinds of their comparisons
+/// with the constants or parameters.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilterSummary {
+    pub label: String,
+    /// Whether the label is of edges, or of vertices.
+    pub edge: bool,
+    /// Compared by `==` or `within`.
+    pub eq: Vec<String>,
+    /// Compared by `<`, `<=`, `>` or `>=`.
+    pub range: Vec<String>,
+    /// Matched by `starts_with`, `ends_with` or a regex.
+    pub text: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct AuditRecord {
+    pub job_id: u64,
+    pub job_name: String,
+    pub user: Option<String>,
+    #[serde(flatten)]
+    pub plan: PlanSummary,
+    pub rows: u64,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+pub trait AuditSink: Send + Sync + 'static {
+    fn write(&self, record: &AuditRecord);
+}
+
+/// Write the records to all of the sinks, e.g. a file and the index advisor.
+pub struct MultiAuditSink(pub Vec<Arc<dyn AuditSink>>);
+
+impl AuditSink for MultiAuditSink {
+    fn write(&self, record: &AuditRecord) {
+        for sink in self.0.iter() {
+            sink.write(record);
+        }
+    }
+}
+
+/// Append the records to a file, one json per line.
+pub struct FileAuditSink {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl FileAuditSink {
+    pub fn open(path: &str) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(FileAuditSink { writer: Mutex::new(BufWriter::new(file)) })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn write(&self, record: &AuditRecord) {
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(e) => return error!("serialize audit record of job {} failure: {}", record.job_id, e),
+        };
+        let mut writer = self
+            .writer
+            .lock()
+            .expect("audit writer poisoned");
+        if let Err(e) = writeln!(writer, "{}", line).and_then(|_| writer.flush()) {
+            error!("write audit record of job {} failure: {}", record.job_id, e);
+        }
+    }
+}
+
+/// Send the records to a kafka topic, keyed by the job id.
+#[cfg(feature = "kafka")]
+pub struct KafkaAuditSink {
+    producer: rdkafka::producer::BaseProducer,
+    topic: String,
+}
+
+#[cfg(feature = "kafka")]
+impl KafkaAuditSink {
+    pub fn new(brokers: &str, topic: &str) -> Result<Self, rdkafka::error::KafkaError> {
+        let producer = rdkafka::config::ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .create()?;
+        Ok(KafkaAuditSink { producer, topic: topic.to_owned() })
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl AuditSink for KafkaAuditSink {
+    fn write(&self, record: &AuditRecord) {
+        use rdkafka::producer::{BaseRecord, Producer};
+
+        let payload = match serde_json::to_string(record) {
+            Ok(payload) => payload,
+            Err(e) => return error!("serialize audit record of job {} failure: {}", record.job_id, e),
+        };
+        let key = record.job_id.to_string();
+        let message = BaseRecord::to(&self.topic)
+            .key(&key)
+            .payload(&payload);
+        if let Err((e, _)) = self.producer.send(message) {
+            error!("send audit record of job {} failure: {}", record.job_id, e);
+        }
+        // serve the delivery callbacks, the message is sent in background
+        self.producer
+            .poll(std::time::Duration::from_millis(0));
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AuditConfig {
+    /// The file to append the records to.
+    pub file: Option<String>,
+    pub kafka_brokers: Option<String>,
+    pub kafka_topic: Option<String>,
+}
+
+impl AuditConfig {
+    pub fn build_sink(&self) -> std::io::Result<Option<Arc<dyn AuditSink>>> {
+        if let Some(path) = self.file.as_ref() {
+            return Ok(Some(Arc::new(FileAuditSink::open(path)?)));
+        }
+        match (self.kafka_brokers.as_ref(), self.kafka_topic.as_ref()) {
+            #[cfg(feature = "kafka")]
+            (Some(brokers), Some(topic)) => {
+                let sink = KafkaAuditSink::new(brokers, topic)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                Ok(Some(Arc::new(sink)))
+            }
+            #[cfg(not(feature = "kafka"))]
+            (Some(_), Some(_)) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "audit to kafka requires the `kafka` feature",
+            )),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Track a running job, and write its record once the tracker is dropped by all of its holders,
+/// i.e., the result sinks of the job and the submitter.
+pub struct AuditTracker {
+    record: Mutex<AuditRecord>,
+    rows: AtomicU64,
+    start: Instant,
+    sink: Arc<dyn AuditSink>,
+}
+
+impl AuditTracker {
+    pub fn new(record: AuditRecord, sink: Arc<dyn AuditSink>) -> Self {
+        AuditTracker { record: Mutex::new(record), rows: AtomicU64::new(0), start: Instant::now(), sink }
+    }
+
+    pub fn add_row(&self) {
+        self.rows.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record the error of the job, only the first one is kept.
+    pub fn set_error(&self, error: String) {
+        let mut record = self
+            .record
+            .lock()
+            .expect("audit record poisoned");
+        if record.error.is_none() {
+            record.error = Some(error);
+        }
+    }
+}
+
+impl Drop for AuditTracker {
+    fn drop(&mut self) {
+        let record = self
+            .record
+            .get_mut()
+            .expect("audit record poisoned");
+        record.rows = self.rows.load(Ordering::Relaxed);
+        record.latency_ms = self.start.elapsed().as_millis() as u64;
+        self.sink.write(record);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<AuditRecord>>);
+
+    impl AuditSink for MemorySink {
+        fn write(&self, record: &AuditRecord) {
+            self.0.lock().unwrap().push(record.clone());
+        }
+    }
+
+    #[test]
+    fn audit_tracker_test() {
+        let sink = Arc::new(MemorySink::default());
+        let record = AuditRecord { job_id: 1, user: Some("marko".to_owned()), ..Default::default() };
+        let tracker = Arc::new(AuditTracker::new(record, sink.clone()));
+        let peer = tracker.clone();
+        peer.add_row();
+        peer.add_row();
+        peer.set_error("interrupted".to_owned());
+        tracker.set_error("canceled".to_owned());
+        drop(peer);
+        assert!(sink.0.lock().unwrap().is_empty());
+        drop(tracker);
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].rows, 2);
+        assert_eq!(records[0].error, Some("interrupted".to_owned()));
+        let json = serde_json::to_string(&records[0]).unwrap();
+        assert!(json.contains("\"fingerprint\":\"\""));
+    }
+}
@@ -0,0 +1,122 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use serde::Deserialize;
+use tonic::metadata::MetadataMap;
+use tonic::Status;
+
+/// The authenticated caller of a job, handed to the job assembly together with the job so that
+/// permissions can be checked when the plan is admitted.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Principal {
+    pub user: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Free-form attributes of the caller, e.g. the tenant it belongs to.
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+}
+
+impl Principal {
+    pub fn new<S: Into<String>>(user: S) -> Self {
+        Principal { user: user.into(), roles: vec![], attributes: HashMap::new() }
+    }
+
+    pub fn with_role<S: Into<String>>(mut self, role: S) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+
+    pub fn with_attribute<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.attributes.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Validate the credentials carried by the metadata of a request.
+pub trait Authenticator: Send + Sync + 'static {
+    fn authenticate(&self, metadata: &MetadataMap) -> Result<Principal, Status>;
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TokenEntry {
+    pub token: String,
+    #[serde(flatten)]
+    pub principal: Principal,
+}
+
+impl Debug for TokenEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenEntry")
+            .field("token", &"***")
+            .field("principal", &self.principal)
+            .finish()
+    }
+}
+
+/// Authenticate requests by a static table of bearer tokens, which are read from the
+/// `authorization: Bearer <token>` metadata.
+pub struct TokenAuthenticator {
+    tokens: HashMap<String, Principal>,
+}
+
+impl TokenAuthenticator {
+    pub fn new(entries: Vec<TokenEntry>) -> Self {
+        let tokens = entries
+            .into_iter()
+            .map(|e| (e.token, e.principal))
+            .collect();
+        TokenAuthenticator { tokens }
+    }
+}
+
+impl Authenticator for TokenAuthenticator {
+    fn authenticate(&self, metadata: &MetadataMap) -> Result<Principal, Status> {
+        let token = metadata
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("bearer token not found"))?;
+        self.tokens
+            .get(token.trim())
+            .cloned()
+            .ok_or_else(|| Status::unauthenticated("invalid token"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn token_authenticate_test() {
+        let entry = TokenEntry {
+            token: "secret".to_owned(),
+            principal: Principal::new("marko").with_role("analyst"),
+        };
+        let authenticator = TokenAuthenticator::new(vec![entry]);
+        let mut metadata = MetadataMap::new();
+        assert!(authenticator.authenticate(&metadata).is_err());
+        metadata.insert("authorization", "Bearer wrong".parse().unwrap());
+        assert!(authenticator.authenticate(&metadata).is_err());
+        metadata.insert("authorization", "Bearer secret".parse().unwrap());
+        let principal = authenticator.authenticate(&metadata).unwrap();
+        assert_eq!(principal.user, "marko");
+        assert_eq!(principal.roles, vec!["analyst".to_owned()]);
+    }
+}
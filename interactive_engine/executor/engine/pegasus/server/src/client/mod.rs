@@ -8,11 +8,13 @@ use std::task::{Context, Poll};
 use futures::stream::{BoxStream, SelectAll};
 use futures::{Stream, StreamExt};
 use pegasus::{JobConf, ServerConf};
+use tonic::metadata::{Ascii, MetadataValue};
 
 use crate::job::JobDesc;
 use crate::pb::job_config::Servers;
 use crate::pb::job_service_client::JobServiceClient;
 use crate::pb::{BinaryResource, Empty, JobConfig, JobRequest, ServerList};
+use crate::scheduling::SchedulingClass;
 
 pub enum JobError {
     InvalidConfig(String),
@@ -148,7 +150,18 @@ impl RPCJobClient {
             return Ok(futures::stream::empty().boxed());
         }
 
-        let JobDesc { input, plan, resource } = job;
+        let JobDesc {
+            input,
+            plan,
+            resource,
+            session,
+            view,
+            include_deleted,
+            consistency,
+            scheduling_class,
+            provenance,
+            ..
+        } = job;
 
         let conf = JobConfig {
             job_id: config.job_id,
@@ -160,11 +173,70 @@ impl RPCJobClient {
             memory_limit: config.memory_limit,
             trace_enable: config.trace_enable,
             servers: Some(servers),
+            deterministic: config.deterministic,
         };
         let req = JobRequest { conf: Some(conf), source: input, plan, resource };
+        let session: Option<MetadataValue<Ascii>> = session
+            .map(|session| {
+                session
+                    .parse()
+                    .map_err(|_| JobError::InvalidConfig(format!("invalid session {};", session)))
+            })
+            .transpose()?;
+        let view: Option<MetadataValue<Ascii>> = view
+            .map(|view| {
+                view.parse()
+                    .map_err(|_| JobError::InvalidConfig(format!("invalid graph view {};", view)))
+            })
+            .transpose()?;
+        let consistency: Option<MetadataValue<Ascii>> = consistency
+            .map(|consistency| {
+                consistency.parse().map_err(|_| {
+                    JobError::InvalidConfig(format!("invalid read consistency {};", consistency))
+                })
+            })
+            .transpose()?;
+        let to_request = |req: JobRequest| {
+            let mut request = tonic::Request::new(req);
+            if let Some(ref session) = session {
+                request
+                    .metadata_mut()
+                    .insert("session-id", session.clone());
+            }
+            if let Some(ref view) = view {
+                request
+                    .metadata_mut()
+                    .insert("graph-view", view.clone());
+            }
+            if include_deleted {
+                request
+                    .metadata_mut()
+                    .insert("include-deleted", MetadataValue::from_static("true"));
+            }
+            if let Some(ref consistency) = consistency {
+                request
+                    .metadata_mut()
+                    .insert("read-consistency", consistency.clone());
+            }
+            if provenance {
+                request
+                    .metadata_mut()
+                    .insert("provenance", MetadataValue::from_static("true"));
+            }
+            if scheduling_class != SchedulingClass::Interactive {
+                request
+                    .metadata_mut()
+                    .insert("scheduling-class", MetadataValue::from_static(scheduling_class.as_str()));
+            }
+            request
+        };
 
         if r_size == 1 {
-            match remotes[0].borrow_mut().submit(req).await {
+            match remotes[0]
+                .borrow_mut()
+                .submit(to_request(req))
+                .await
+            {
                 Ok(resp) => Ok(resp
                     .into_inner()
                     .map(|r| r.map(|jr| jr.resp))
@@ -174,7 +246,7 @@ impl RPCJobClient {
         } else {
             let mut tasks = Vec::with_capacity(r_size);
             for r in remotes {
-                let req = req.clone();
+                let req = to_request(req.clone());
                 tasks.push(async move {
                     let mut conn = r.borrow_mut();
                     conn.submit(req).await
@@ -70,7 +70,7 @@ mod test {
             assert_eq!(servers[1].id, 1);
             assert_eq!(servers[0].addr, "192.168.1.1:8080".parse().unwrap());
             assert_eq!(servers[1].addr, "192.168.1.2:8080".parse().unwrap());
-            let params = net_conf.get_connection_param();
+            let params = net_conf.get_connection_param().unwrap();
             assert!(!params.is_nonblocking);
             assert_eq!(params.get_read_params().slab_size, 65535);
             assert_eq!(
@@ -0,0 +1,131 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Drain the server before it's shut down, e.g. by a rolling update. Once the server is draining, new
+//! jobs are rejected; it's drained after the running jobs are finished and the drain hooks, e.g.
+//! flushing the store, are done.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(60);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+type DrainHook = Box<dyn Fn() + Send + Sync>;
+
+static DRAINING: AtomicBool = AtomicBool::new(false);
+static DRAINED: AtomicBool = AtomicBool::new(false);
+static RUNNING_JOBS: AtomicUsize = AtomicUsize::new(0);
+static HOOKS: Mutex<Vec<DrainHook>> = Mutex::new(Vec::new());
+
+/// A job admitted before the server is draining, the drain waits until it's dropped.
+pub struct JobPermit {
+    _private: (),
+}
+
+impl Drop for JobPermit {
+    fn drop(&mut self) {
+        RUNNING_JOBS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Admit a new job, or `None` if the server is draining.
+pub fn admit() -> Option<JobPermit> {
+    // count the job before the check, so that a drain started meanwhile waits for it;
+    RUNNING_JOBS.fetch_add(1, Ordering::SeqCst);
+    let permit = JobPermit { _private: () };
+    if DRAINING.load(Ordering::SeqCst) {
+        None
+    } else {
+        Some(permit)
+    }
+}
+
+pub fn is_draining() -> bool {
+    DRAINING.load(Ordering::SeqCst)
+}
+
+pub fn is_drained() -> bool {
+    DRAINED.load(Ordering::SeqCst)
+}
+
+pub fn running_jobs() -> usize {
+    RUNNING_JOBS.load(Ordering::SeqCst)
+}
+
+/// Add a hook called once after the running jobs are finished, e.g. to flush the store.
+pub fn add_drain_hook<F: Fn() + Send + Sync + 'static>(hook: F) {
+    HOOKS.lock().unwrap().push(Box::new(hook));
+}
+
+/// Start draining and wait until the server is drained, returns false if the running jobs are not
+/// finished in `timeout`; it can be called again to keep waiting.
+pub async fn drain(timeout: Duration) -> bool {
+    if !DRAINING.swap(true, Ordering::SeqCst) {
+        info!("server is draining, {} jobs running", running_jobs());
+    }
+    let deadline = Instant::now() + timeout;
+    while running_jobs() > 0 {
+        if Instant::now() >= deadline {
+            warn!("server is not drained in {:?}, {} jobs running", timeout, running_jobs());
+            return false;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    // the hooks may block, e.g. on flushing, and the concurrent drains wait on the lock until
+    // they are done;
+    let res = tokio::task::spawn_blocking(|| {
+        let mut hooks = HOOKS.lock().unwrap();
+        for hook in hooks.drain(..) {
+            hook();
+        }
+        DRAINED.store(true, Ordering::SeqCst);
+    })
+    .await;
+    if let Err(e) = res {
+        error!("drain hooks failed: {}", e);
+        return false;
+    }
+    info!("server is drained");
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn drain_test() {
+        let flushed = Arc::new(AtomicBool::new(false));
+        let flushed_clone = flushed.clone();
+        add_drain_hook(move || flushed_clone.store(true, Ordering::SeqCst));
+
+        let permit = admit().unwrap();
+        assert!(!drain(Duration::from_millis(200)).await);
+        assert!(is_draining());
+        assert!(!is_drained());
+        assert!(!flushed.load(Ordering::SeqCst));
+        assert!(admit().is_none());
+        assert_eq!(running_jobs(), 1);
+
+        drop(permit);
+        assert!(drain(Duration::from_millis(200)).await);
+        assert!(is_drained());
+        assert!(flushed.load(Ordering::SeqCst));
+    }
+}
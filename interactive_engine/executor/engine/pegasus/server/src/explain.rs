@@ -0,0 +1,196 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The physical plan a job would be executed by, after the rewrites of the admission and the assembly,
+//! e.g., the row filters and the statistics-based refinements, to be rendered for telling where and why
+//! the records are exchanged between the workers. It's served on `POST /explain?format=dot|json` of the
+//! http server, with the same body and headers as `POST /job`, where the job is planned but never run.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use serde::Serialize;
+use tonic::Status;
+
+use crate::generated::protocol as pb;
+
+/// How the records of an operator are sent to the next one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Routing {
+    /// Kept in the worker producing them.
+    Pipeline,
+    /// Sent to the worker of their key, e.g., the partition of a vertex.
+    Shuffle,
+    /// Sent to all the workers.
+    Broadcast,
+    /// Sent to a single worker.
+    Aggregate,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlanNode {
+    pub id: usize,
+    pub operator: String,
+    /// The arguments of the operator worth showing, e.g., the labels of a scan.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// The number of the workers the records of the operator are processed by.
+    pub parallelism: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlanEdge {
+    pub from: usize,
+    pub to: usize,
+    pub routing: Routing,
+    /// The key the records are shuffled by, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PlanGraph {
+    /// The number of the workers of the job.
+    pub parallelism: usize,
+    pub nodes: Vec<PlanNode>,
+    pub edges: Vec<PlanEdge>,
+}
+
+impl PlanGraph {
+    pub fn new(parallelism: usize) -> Self {
+        PlanGraph { parallelism, nodes: vec![], edges: vec![] }
+    }
+
+    /// Add an operator, returning its id.
+    pub fn add_node(&mut self, operator: &str, detail: Option<String>, parallelism: usize) -> usize {
+        let id = self.nodes.len();
+        self.nodes
+            .push(PlanNode { id, operator: operator.to_owned(), detail, parallelism });
+        id
+    }
+
+    pub fn add_edge(&mut self, from: usize, to: usize, routing: Routing, key: Option<String>) {
+        self.edges
+            .push(PlanEdge { from, to, routing, key });
+    }
+
+    /// The number of the edges exchanging the records between the workers.
+    pub fn exchanges(&self) -> usize {
+        self.edges
+            .iter()
+            .filter(|edge| edge.routing != Routing::Pipeline)
+            .count()
+    }
+
+    /// Render the plan in the DOT language of graphviz, where the exchanges are drawn in bold.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph plan {\n  node [shape=box];\n");
+        for node in &self.nodes {
+            let mut label = format!("{}: {}", node.id, node.operator);
+            if let Some(detail) = node.detail.as_ref() {
+                write!(label, "\\n{}", escape(detail)).unwrap();
+            }
+            write!(label, "\\nparallelism={}", node.parallelism).unwrap();
+            writeln!(dot, "  n{} [label=\"{}\"];", node.id, label).unwrap();
+        }
+        for edge in &self.edges {
+            write!(dot, "  n{} -> n{}", edge.from, edge.to).unwrap();
+            if edge.routing != Routing::Pipeline {
+                let mut label = format!("{:?}", edge.routing).to_lowercase();
+                if let Some(key) = edge.key.as_ref() {
+                    write!(label, " by {}", escape(key)).unwrap();
+                }
+                write!(dot, " [label=\"{}\", style=bold]", label).unwrap();
+            }
+            dot.push_str(";\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExplainFormat {
+    Dot,
+    Json,
+}
+
+impl FromStr for ExplainFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(ExplainFormat::Dot),
+            "json" => Ok(ExplainFormat::Json),
+            _ => Err(format!("unknown format {}, expect dot or json", s)),
+        }
+    }
+}
+
+/// A job service planning the jobs without running them.
+pub trait ExplainService: Send + Sync + 'static {
+    /// The plan a job of the request would be executed by, with the same authentication and admission
+    /// as a submitted job.
+    fn explain(&self, req: tonic::Request<pb::JobRequest>) -> Result<PlanGraph, Status>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_dot_test() {
+        let mut graph = PlanGraph::new(4);
+        let scan = graph.add_node("Scan", Some("labels [\"person\"]".to_owned()), 4);
+        let shuffle = graph.add_node("Repartition", None, 4);
+        let count = graph.add_node("GroupBy", None, 1);
+        graph.add_edge(scan, shuffle, Routing::Pipeline, None);
+        graph.add_edge(shuffle, count, Routing::Aggregate, None);
+        graph.add_edge(scan, count, Routing::Shuffle, Some("@a".to_owned()));
+        assert_eq!(graph.exchanges(), 2);
+        assert_eq!(
+            graph.to_dot(),
+            "digraph plan {\n  node [shape=box];\n\
+             \x20 n0 [label=\"0: Scan\\nlabels [\\\"person\\\"]\\nparallelism=4\"];\n\
+             \x20 n1 [label=\"1: Repartition\\nparallelism=4\"];\n\
+             \x20 n2 [label=\"2: GroupBy\\nparallelism=1\"];\n\
+             \x20 n0 -> n1;\n\
+             \x20 n1 -> n2 [label=\"aggregate\", style=bold];\n\
+             \x20 n0 -> n2 [label=\"shuffle by @a\", style=bold];\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn to_json_test() {
+        let mut graph = PlanGraph::new(2);
+        let scan = graph.add_node("Scan", None, 2);
+        let sink = graph.add_node("Sink", None, 2);
+        graph.add_edge(scan, sink, Routing::Shuffle, Some("head".to_owned()));
+        let json = serde_json::to_value(&graph).unwrap();
+        assert_eq!(json["nodes"][0], serde_json::json!({"id": 0, "operator": "Scan", "parallelism": 2}));
+        assert_eq!(
+            json["edges"][0],
+            serde_json::json!({"from": 0, "to": 1, "routing": "shuffle", "key": "head"})
+        );
+        assert_eq!("dot".parse::<ExplainFormat>(), Ok(ExplainFormat::Dot));
+        assert!("svg".parse::<ExplainFormat>().is_err());
+    }
+}
@@ -0,0 +1,358 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The Arrow Flight service served on the rpc port, so that the analytics stacks, e.g., pyarrow, ingest the
+//! results as Arrow record batches instead of decoding the responses row by row:
+//! * `GetFlightInfo` of a command descriptor, whose command is a `JobRequest` encoded in protobuf, returns
+//!   the single endpoint of the job on this server, whose ticket is the command; the schema is left empty,
+//!   as it is known only once the results are produced;
+//! * the ticket of `DoGet` is a `JobRequest` encoded in protobuf, which is submitted to the job service
+//!   of this server and of the peers, i.e., the other servers of the cluster, with the metadata of the
+//!   `DoGet`, e.g., the token and the session, so that the results of all the servers are merged;
+//! * each response of the job is a record batch in the Arrow IPC stream format, e.g., sunk by the `ARROW`
+//!   format of IR, and the record batches are streamed back as the flight data, up to
+//!   `FLIGHT_BUFFER_CAPACITY` batches ahead of the client.
+
+use std::io::Cursor;
+use std::pin::Pin;
+
+use arrow_array::RecordBatch;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_descriptor::DescriptorType;
+use arrow_flight::flight_service_server::FlightService;
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PutResult, SchemaResult, Ticket,
+};
+use arrow_ipc::reader::StreamReader;
+use futures::Stream;
+use prost::Message;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tonic::{Code, Extensions, Request, Response, Status, Streaming};
+
+use crate::generated::protocol as pb;
+use crate::pb::job_service_client::JobServiceClient;
+
+type BoxedStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + 'static>>;
+
+/// The record batches buffered ahead of the client, beyond which the responses of the job are not read.
+const FLIGHT_BUFFER_CAPACITY: usize = 16;
+
+pub struct FlightJobService<S> {
+    inner: S,
+    /// The job services of the peers, which the jobs are submitted to along with this server.
+    peers: Vec<JobServiceClient<Channel>>,
+}
+
+impl<S> FlightJobService<S> {
+    pub fn new(inner: S) -> Self {
+        FlightJobService { inner, peers: vec![] }
+    }
+
+    /// Submit the jobs to the peers of the rpc uris as well, which are connected lazily, over tls if
+    /// `tls` is given.
+    pub fn with_peers(
+        mut self, uris: &[String], tls: Option<ClientTlsConfig>,
+    ) -> Result<Self, tonic::transport::Error> {
+        for uri in uris {
+            let mut endpoint = Endpoint::from_shared(uri.clone())?;
+            if let Some(tls) = tls.clone() {
+                endpoint = endpoint.tls_config(tls)?;
+            }
+            self.peers
+                .push(JobServiceClient::new(endpoint.connect_lazy()));
+        }
+        Ok(self)
+    }
+}
+
+fn decode_batches(resp: &[u8]) -> Result<Vec<RecordBatch>, FlightError> {
+    let reader = StreamReader::try_new(Cursor::new(resp), None).map_err(FlightError::Arrow)?;
+    reader
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(FlightError::Arrow)
+}
+
+#[tonic::async_trait]
+impl<S> FlightService for FlightJobService<S>
+where
+    S: pb::job_service_server::JobService,
+{
+    type HandshakeStream = BoxedStream<HandshakeResponse>;
+    type ListFlightsStream = BoxedStream<FlightInfo>;
+    type DoGetStream = BoxedStream<FlightData>;
+    type DoPutStream = BoxedStream<PutResult>;
+    type DoActionStream = BoxedStream<arrow_flight::Result>;
+    type ListActionsStream = BoxedStream<ActionType>;
+    type DoExchangeStream = BoxedStream<FlightData>;
+
+    async fn handshake(
+        &self, _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("handshake is not supported, authenticate by the metadata of DoGet"))
+    }
+
+    async fn list_flights(
+        &self, _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("list_flights is not supported"))
+    }
+
+    async fn get_flight_info(
+        &self, request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let descriptor = request.into_inner();
+        if descriptor.r#type != DescriptorType::Cmd as i32 {
+            Err(Status::invalid_argument("the descriptor is not a command of a job request"))?
+        }
+        pb::JobRequest::decode(descriptor.cmd.as_ref())
+            .map_err(|e| Status::invalid_argument(format!("command is not a job request: {}", e)))?;
+        let endpoint =
+            FlightEndpoint { ticket: Some(Ticket { ticket: descriptor.cmd.clone() }), location: vec![] };
+        let info = FlightInfo {
+            schema: Default::default(),
+            flight_descriptor: Some(descriptor),
+            endpoint: vec![endpoint],
+            total_records: -1,
+            total_bytes: -1,
+        };
+        Ok(Response::new(info))
+    }
+
+    async fn get_schema(
+        &self, _request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        Err(Status::unimplemented("get_schema is not supported"))
+    }
+
+    async fn do_get(&self, request: Request<Ticket>) -> Result<Response<Self::DoGetStream>, Status> {
+        let (metadata, extensions, ticket) = request.into_parts();
+        let job_request = pb::JobRequest::decode(ticket.ticket.as_ref())
+            .map_err(|e| Status::invalid_argument(format!("ticket is not a job request: {}", e)))?;
+        let mut responses: Vec<BoxedStream<pb::JobResponse>> = Vec::with_capacity(self.peers.len() + 1);
+        for peer in self.peers.iter() {
+            let request = Request::from_parts(metadata.clone(), Extensions::default(), job_request.clone());
+            let peer_responses = peer.clone().submit(request).await?.into_inner();
+            responses.push(Box::pin(peer_responses));
+        }
+        let local_responses = self
+            .inner
+            .submit(Request::from_parts(metadata, extensions, job_request))
+            .await?
+            .into_inner();
+        responses.push(Box::pin(local_responses));
+        let (tx, rx) = tokio::sync::mpsc::channel(FLIGHT_BUFFER_CAPACITY);
+        tokio::spawn(async move {
+            // the results of the servers are merged as they are produced
+            let mut responses = futures::stream::select_all(responses);
+            while let Some(resp) = responses.next().await {
+                let batches = match resp {
+                    Ok(resp) => decode_batches(&resp.resp),
+                    // the end of the results of a server
+                    Err(status) if status.code() == Code::Ok => continue,
+                    Err(status) => Err(FlightError::Tonic(status)),
+                };
+                match batches {
+                    Ok(batches) => {
+                        for batch in batches {
+                            if tx.send(Ok(batch)).await.is_err() {
+                                // the client is gone
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        tx.send(Err(e)).await.ok();
+                        return;
+                    }
+                }
+            }
+        });
+        let stream = FlightDataEncoderBuilder::new()
+            .build(ReceiverStream::new(rx))
+            .map(|data| {
+                data.map_err(|e| match e {
+                    FlightError::Tonic(status) => status,
+                    e => Status::internal(e.to_string()),
+                })
+            });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn do_put(
+        &self, _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("do_put is not supported"))
+    }
+
+    async fn do_action(&self, _request: Request<Action>) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("do_action is not supported"))
+    }
+
+    async fn list_actions(
+        &self, _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Err(Status::unimplemented("list_actions is not supported"))
+    }
+
+    async fn do_exchange(
+        &self, _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("do_exchange is not supported"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, Int64Array};
+    use arrow_flight::utils::flight_data_to_batches;
+    use arrow_ipc::writer::StreamWriter;
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+
+    use super::*;
+    use crate::pb::{BinaryResource, Name};
+
+    /// A job service responding the given results to every job.
+    struct ResultsService(Vec<Vec<u8>>);
+
+    #[tonic::async_trait]
+    impl pb::job_service_server::JobService for ResultsService {
+        async fn add_library(&self, _req: Request<BinaryResource>) -> Result<Response<pb::Empty>, Status> {
+            Err(Status::unimplemented(""))
+        }
+
+        async fn remove_library(&self, _req: Request<Name>) -> Result<Response<pb::Empty>, Status> {
+            Err(Status::unimplemented(""))
+        }
+
+        type SubmitStream = UnboundedReceiverStream<Result<pb::JobResponse, Status>>;
+
+        async fn cancel(&self, _req: Request<pb::CancelRequest>) -> Result<Response<pb::Empty>, Status> {
+            Err(Status::unimplemented(""))
+        }
+
+        async fn submit(
+            &self, req: Request<pb::JobRequest>,
+        ) -> Result<Response<Self::SubmitStream>, Status> {
+            assert_eq!(req.metadata().get("session-id").unwrap(), "s1");
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            for resp in self.0.clone() {
+                tx.send(Ok(pb::JobResponse { job_id: 1, resp }))
+                    .unwrap();
+            }
+            tx.send(Err(Status::ok("ok"))).unwrap();
+            Ok(Response::new(UnboundedReceiverStream::new(rx)))
+        }
+    }
+
+    fn encode(values: Vec<i64>) -> Vec<u8> {
+        let batch =
+            RecordBatch::try_from_iter(vec![("id", Arc::new(Int64Array::from(values)) as ArrayRef)])
+                .unwrap();
+        let mut buf = vec![];
+        {
+            let mut writer = StreamWriter::try_new(&mut buf, &batch.schema()).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+        }
+        buf
+    }
+
+    async fn do_get_ids<S>(service: &FlightJobService<S>) -> Vec<i64>
+    where
+        S: pb::job_service_server::JobService,
+    {
+        let job_request = pb::JobRequest { conf: None, source: vec![], plan: vec![], resource: vec![] };
+        let mut request = Request::new(Ticket { ticket: job_request.encode_to_vec().into() });
+        request
+            .metadata_mut()
+            .insert("session-id", "s1".parse().unwrap());
+        let mut stream = service
+            .do_get(request)
+            .await
+            .unwrap()
+            .into_inner();
+        let mut flight_data = vec![];
+        while let Some(data) = stream.next().await {
+            flight_data.push(data.unwrap());
+        }
+        let batches = flight_data_to_batches(&flight_data).unwrap();
+        batches
+            .iter()
+            .flat_map(|batch| {
+                batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<Int64Array>()
+                    .unwrap()
+                    .values()
+                    .to_vec()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn do_get_test() {
+        let service = FlightJobService::new(ResultsService(vec![encode(vec![1, 2]), encode(vec![3])]));
+        assert_eq!(do_get_ids(&service).await, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn do_get_peers_test() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let peer = pb::job_service_server::JobServiceServer::new(ResultsService(vec![encode(vec![4])]));
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(peer)
+                .serve(addr),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let service = FlightJobService::new(ResultsService(vec![encode(vec![1, 2])]))
+            .with_peers(&[format!("http://{}", addr)], None)
+            .unwrap();
+        let mut ids = do_get_ids(&service).await;
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 4]);
+    }
+
+    #[tokio::test]
+    async fn get_flight_info_test() {
+        let service = FlightJobService::new(ResultsService(vec![]));
+        let job_request = pb::JobRequest { conf: None, source: vec![], plan: vec![1], resource: vec![] };
+        let descriptor = FlightDescriptor::new_cmd(job_request.encode_to_vec());
+        let info = service
+            .get_flight_info(Request::new(descriptor))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(info.endpoint.len(), 1);
+        let ticket = info.endpoint[0].ticket.as_ref().unwrap();
+        assert_eq!(pb::JobRequest::decode(ticket.ticket.as_ref()).unwrap(), job_request);
+
+        let descriptor = FlightDescriptor::new_path(vec!["job".to_string()]);
+        assert!(service
+            .get_flight_info(Request::new(descriptor))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,760 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Submit the jobs in JSON over HTTP, for the curl-based debugging and the simple integrations:
+//! `POST /job` with the job in json as the body, whose headers are taken as the metadata of `Submit`,
+//! e.g., `authorization` and `session-id`:
+//!
+//! ```text
+//! {"conf": {"job_id": 1, "workers": 2, "servers": "all"}, "plan": {..}}
+//! ```
+//!
+//! where `conf` is the `JobConfig` in json with `servers` of `"local"`, `"all"` or the ids of the
+//! servers, and `plan` is the plan in json read by the assembly, e.g., the physical plan of IR; the plans
+//! opaque to the assembly are given by `plan_hex` instead, along with `source_hex` and `resource_hex`.
+//! A `JobRequest` encoded in protobuf is taken as well by `content-type: application/x-protobuf`, e.g.,
+//! `curl -H 'content-type: application/x-protobuf' --data-binary @job.pb`. The bodies over
+//! `MAX_BODY_BYTES` are rejected, and the jobs are served over https by the tls of the rpc server if set.
+//!
+//! The frames are streamed back in newline-delimited json as the results are produced:
+//! * `{"result": ..}` of a result in json, e.g., sunk in GraphSON by IR, or `{"result_hex": ".."}`
+//!   otherwise;
+//! * `{"error": {"code": .., "message": ..}}` once the job fails, after which no result is streamed;
+//! * `{"stats": {"job_id": .., "results": .., "elapsed_ms": .., "status": ..}}` as the final frame.
+//!
+//! The plan a job would be executed by is returned by `POST /explain?format=dot|json`, in json by
+//! default, with the same body and headers, see `explain`.
+
+use std::convert::Infallible;
+use std::io::{BufReader, Error as IoError, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use hyper::body::{Bytes, HttpBody};
+use hyper::server::conn::Http;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, HeaderMap, Method, Request, Response, Server, StatusCode};
+use prost::Message;
+use rustls_pemfile::Item;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::StreamExt;
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Status};
+
+use crate::explain::{ExplainFormat, ExplainService};
+use crate::generated::protocol as pb;
+use crate::generated::protocol::job_config::Servers;
+use crate::rpc::RpcTlsConfig;
+
+const NDJSON: &str = "application/x-ndjson";
+const PROTOBUF: &str = "application/x-protobuf";
+/// The largest body of a request read, far larger than the plans.
+pub const MAX_BODY_BYTES: usize = 16 << 20;
+
+/// A job service reading the plans of the jobs in json.
+pub trait JsonPlanService: Send + Sync + 'static {
+    /// The plan of a job in json encoded as the plan of a `JobRequest`.
+    fn encode_plan(&self, plan: &Value) -> Result<Vec<u8>, Status>;
+}
+
+/// Start serving the jobs on `addr` in background, over https by `tls` if set; the endpoint is stopped
+/// with the runtime.
+pub fn start_http_server<S>(
+    addr: SocketAddr, service: S, tls: Option<&RpcTlsConfig>,
+) -> std::io::Result<SocketAddr>
+where
+    S: pb::job_service_server::JobService + ExplainService + JsonPlanService,
+{
+    let service = Arc::new(service);
+    let local_addr = match tls {
+        Some(tls) => start_https(addr, service, tls_acceptor(tls)?)?,
+        None => {
+            let make_service = make_service_fn(move |_| {
+                let service = service.clone();
+                async move { Ok::<_, Infallible>(service_fn(move |req| serve(service.clone(), req))) }
+            });
+            let server = Server::try_bind(&addr)
+                .map_err(|e| IoError::new(ErrorKind::Other, e))?
+                .serve(make_service);
+            let local_addr = server.local_addr();
+            tokio::spawn(async move {
+                if let Err(e) = server.await {
+                    error!("http server at {} stopped: {}", local_addr, e);
+                }
+            });
+            local_addr
+        }
+    };
+    info!("http server started at {}, tls: {}", local_addr, tls.is_some());
+    Ok(local_addr)
+}
+
+fn start_https<S>(addr: SocketAddr, service: Arc<S>, acceptor: TlsAcceptor) -> std::io::Result<SocketAddr>
+where
+    S: pb::job_service_server::JobService + ExplainService + JsonPlanService,
+{
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+    let local_addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    // e.g., out of file descriptors, retried after a while rather than spinning
+                    warn!("http server at {} accept failure: {}", local_addr, e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let acceptor = acceptor.clone();
+            let service = service.clone();
+            tokio::spawn(async move {
+                let stream = match acceptor.accept(stream).await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        debug!("tls handshake with {} failure: {}", peer, e);
+                        return;
+                    }
+                };
+                let serve_fn = service_fn(move |req| serve(service.clone(), req));
+                if let Err(e) = Http::new()
+                    .serve_connection(stream, serve_fn)
+                    .await
+                {
+                    debug!("http connection with {} failure: {}", peer, e);
+                }
+            });
+        }
+    });
+    Ok(local_addr)
+}
+
+fn read_pem(path: &str) -> std::io::Result<Vec<Item>> {
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    rustls_pemfile::read_all(&mut reader)
+}
+
+/// The tls of the rpc server, with the same certificate and the clients verified alike.
+fn tls_acceptor(tls: &RpcTlsConfig) -> std::io::Result<TlsAcceptor> {
+    let invalid = |e: String| IoError::new(ErrorKind::InvalidData, e);
+    let certs: Vec<Certificate> = read_pem(&tls.cert_file)?
+        .into_iter()
+        .filter_map(|item| match item {
+            Item::X509Certificate(cert) => Some(Certificate(cert)),
+            _ => None,
+        })
+        .collect();
+    let key = read_pem(&tls.key_file)?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| invalid(format!("no private key found in {}", tls.key_file)))?;
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match tls.client_ca_file.as_ref() {
+        Some(ca_file) => {
+            let mut roots = RootCertStore::empty();
+            for item in read_pem(ca_file)? {
+                if let Item::X509Certificate(cert) = item {
+                    roots
+                        .add(&Certificate(cert))
+                        .map_err(|e| invalid(e.to_string()))?;
+                }
+            }
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots))
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid(e.to_string()))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+async fn serve<S>(service: Arc<S>, req: Request<Body>) -> Result<Response<Body>, Infallible>
+where
+    S: pb::job_service_server::JobService + ExplainService + JsonPlanService,
+{
+    let resp = match (req.method(), req.uri().path()) {
+        (&Method::POST, "/job") => serve_job(service, req).await,
+        (&Method::POST, "/explain") => serve_explain(service, req).await,
+        _ => status_response(StatusCode::NOT_FOUND, String::new()),
+    };
+    Ok(resp)
+}
+
+fn status_response(status: StatusCode, body: String) -> Response<Body> {
+    let mut resp = Response::new(Body::from(body));
+    *resp.status_mut() = status;
+    resp
+}
+
+fn frame(value: Value) -> Bytes {
+    let mut line = value.to_string();
+    line.push('\n');
+    Bytes::from(line)
+}
+
+fn result_frame(resp: &[u8]) -> Bytes {
+    match serde_json::from_slice::<Value>(resp) {
+        Ok(result) => frame(json!({ "result": result })),
+        Err(_) => {
+            let hex: String = resp
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            frame(json!({ "result_hex": hex }))
+        }
+    }
+}
+
+fn error_frame(status: &Status) -> Bytes {
+    frame(json!({ "error": { "code": format!("{:?}", status.code()), "message": status.message() } }))
+}
+
+fn stats_frame(job_id: u64, results: u64, elapsed: Duration, status: &str) -> Bytes {
+    frame(json!({
+        "stats": {
+            "job_id": job_id,
+            "results": results,
+            "elapsed_ms": elapsed.as_millis() as u64,
+            "status": status,
+        }
+    }))
+}
+
+/// The status of the response if the job is rejected before any result.
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::InvalidArgument => StatusCode::BAD_REQUEST,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::FailedPrecondition => StatusCode::PRECONDITION_FAILED,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// A job in json, see the module doc.
+#[derive(Deserialize)]
+struct JsonJobRequest {
+    #[serde(default)]
+    conf: JsonJobConfig,
+    plan: Option<Value>,
+    plan_hex: Option<String>,
+    #[serde(default)]
+    source_hex: String,
+    #[serde(default)]
+    resource_hex: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct JsonJobConfig {
+    job_id: u64,
+    job_name: String,
+    workers: u32,
+    time_limit: u64,
+    batch_size: u32,
+    batch_capacity: u32,
+    memory_limit: u32,
+    trace_enable: bool,
+    deterministic: bool,
+    servers: Option<JsonServers>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonServers {
+    /// `"local"` or `"all"`.
+    Named(String),
+    Part(Vec<u64>),
+}
+
+impl JsonJobConfig {
+    fn into_pb(self) -> Result<pb::JobConfig, String> {
+        let servers = match self.servers {
+            None => None,
+            Some(JsonServers::Named(name)) if name == "local" => Some(Servers::Local(pb::Empty {})),
+            Some(JsonServers::Named(name)) if name == "all" => Some(Servers::All(pb::Empty {})),
+            Some(JsonServers::Named(name)) => return Err(format!("unknown servers `{}`", name)),
+            Some(JsonServers::Part(servers)) => Some(Servers::Part(pb::ServerList { servers })),
+        };
+        Ok(pb::JobConfig {
+            job_id: self.job_id,
+            job_name: self.job_name,
+            workers: self.workers,
+            time_limit: self.time_limit,
+            batch_size: self.batch_size,
+            batch_capacity: self.batch_capacity,
+            memory_limit: self.memory_limit,
+            trace_enable: self.trace_enable,
+            servers,
+            deterministic: self.deterministic,
+        })
+    }
+}
+
+fn from_hex(field: &str, hex: &str) -> Result<Vec<u8>, String> {
+    let invalid = || format!("`{}` is not in hex", field);
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+/// Read the body up to `limit` bytes, whatever its `content-length` claims.
+pub(crate) async fn read_body(mut body: Body, limit: usize) -> Result<Bytes, Response<Body>> {
+    let too_large = || {
+        let msg = format!("body is larger than {} bytes", limit);
+        status_response(StatusCode::PAYLOAD_TOO_LARGE, msg)
+    };
+    if body.size_hint().lower() > limit as u64 {
+        return Err(too_large());
+    }
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| status_response(StatusCode::BAD_REQUEST, e.to_string()))?;
+        if buf.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(buf))
+}
+
+async fn decode_job_request<S>(
+    service: &S, headers: &HeaderMap, body: Body,
+) -> Result<pb::JobRequest, Response<Body>>
+where
+    S: JsonPlanService,
+{
+    let body = read_body(body, MAX_BODY_BYTES).await?;
+    let bad_request = |e: String| status_response(StatusCode::BAD_REQUEST, e);
+    let protobuf = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |value| value.starts_with(PROTOBUF));
+    if protobuf {
+        return pb::JobRequest::decode(body)
+            .map_err(|e| bad_request(format!("body is not a job request: {}", e)));
+    }
+    let job: JsonJobRequest = serde_json::from_slice(&body)
+        .map_err(|e| bad_request(format!("body is not a job in json: {}", e)))?;
+    let plan = match (job.plan, job.plan_hex) {
+        (Some(plan), None) => service
+            .encode_plan(&plan)
+            .map_err(|status| status_response(http_status(status.code()), status.message().to_owned()))?,
+        (None, Some(hex)) => from_hex("plan_hex", &hex).map_err(bad_request)?,
+        _ => return Err(bad_request("either `plan` or `plan_hex` is expected".to_owned())),
+    };
+    Ok(pb::JobRequest {
+        conf: Some(job.conf.into_pb().map_err(bad_request)?),
+        source: from_hex("source_hex", &job.source_hex).map_err(bad_request)?,
+        plan,
+        resource: from_hex("resource_hex", &job.resource_hex).map_err(bad_request)?,
+    })
+}
+
+async fn serve_job<S>(service: Arc<S>, req: Request<Body>) -> Response<Body>
+where
+    S: pb::job_service_server::JobService + JsonPlanService,
+{
+    let start = Instant::now();
+    let (parts, body) = req.into_parts();
+    let job_request = match decode_job_request(service.as_ref(), &parts.headers, body).await {
+        Ok(job_request) => job_request,
+        Err(resp) => return resp,
+    };
+    let mut job_id = job_request
+        .conf
+        .as_ref()
+        .map(|conf| conf.job_id)
+        .unwrap_or_default();
+    let mut request = tonic::Request::new(job_request);
+    *request.metadata_mut() = MetadataMap::from_headers(parts.headers);
+    let responses = match service.submit(request).await {
+        Ok(responses) => responses.into_inner(),
+        Err(status) => {
+            let mut body = error_frame(&status).to_vec();
+            body.extend_from_slice(&stats_frame(job_id, 0, start.elapsed(), "error"));
+            return Response::builder()
+                .status(http_status(status.code()))
+                .header(header::CONTENT_TYPE, NDJSON)
+                .body(Body::from(body))
+                .expect("build job response failure");
+        }
+    };
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let mut responses = Box::pin(responses);
+        let mut results = 0;
+        let mut status = "ok";
+        while let Some(resp) = responses.next().await {
+            let frame = match resp {
+                Ok(resp) => {
+                    job_id = resp.job_id;
+                    results += 1;
+                    result_frame(&resp.resp)
+                }
+                // the end of the results
+                Err(e) if e.code() == Code::Ok => break,
+                Err(e) => {
+                    status = "error";
+                    error_frame(&e)
+                }
+            };
+            if sender.send_data(frame).await.is_err() {
+                // the client is gone
+                return;
+            }
+            if status != "ok" {
+                break;
+            }
+        }
+        let stats = stats_frame(job_id, results, start.elapsed(), status);
+        sender.send_data(stats).await.ok();
+    });
+    Response::builder()
+        .header(header::CONTENT_TYPE, NDJSON)
+        .body(body)
+        .expect("build job response failure")
+}
+
+fn explain_format(query: Option<&str>) -> Result<ExplainFormat, String> {
+    let mut format = ExplainFormat::Json;
+    for pair in query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+    {
+        match pair.split_once('=') {
+            Some(("format", value)) => format = value.parse()?,
+            _ => return Err(format!("unknown parameter {}", pair)),
+        }
+    }
+    Ok(format)
+}
+
+async fn serve_explain<S>(service: Arc<S>, req: Request<Body>) -> Response<Body>
+where
+    S: ExplainService + JsonPlanService,
+{
+    let format = match explain_format(req.uri().query()) {
+        Ok(format) => format,
+        Err(e) => return status_response(StatusCode::BAD_REQUEST, e),
+    };
+    let (parts, body) = req.into_parts();
+    let job_request = match decode_job_request(service.as_ref(), &parts.headers, body).await {
+        Ok(job_request) => job_request,
+        Err(resp) => return resp,
+    };
+    let mut request = tonic::Request::new(job_request);
+    *request.metadata_mut() = MetadataMap::from_headers(parts.headers);
+    // the plan may be refined by the statistics read from the store
+    let graph = match tokio::task::spawn_blocking(move || service.explain(request)).await {
+        Ok(Ok(graph)) => graph,
+        Ok(Err(status)) => return status_response(http_status(status.code()), status.message().to_owned()),
+        Err(e) => return status_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    let (content_type, body) = match format {
+        ExplainFormat::Dot => ("text/vnd.graphviz", graph.to_dot()),
+        ExplainFormat::Json => ("application/json", json!(graph).to_string()),
+    };
+    Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .expect("build explain response failure")
+}
+
+#[cfg(test)]
+mod test {
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+    use tonic::Response as RpcResponse;
+
+    use super::*;
+    use crate::explain::{PlanGraph, Routing};
+    use crate::pb::{BinaryResource, Empty, Name};
+
+    /// A job service responding the given results, and then the error if any, to every job.
+    struct ResultsService(Vec<Vec<u8>>, Option<Status>);
+
+    #[tonic::async_trait]
+    impl pb::job_service_server::JobService for ResultsService {
+        async fn add_library(
+            &self, _req: tonic::Request<BinaryResource>,
+        ) -> Result<RpcResponse<Empty>, Status> {
+            Err(Status::unimplemented(""))
+        }
+
+        async fn remove_library(&self, _req: tonic::Request<Name>) -> Result<RpcResponse<Empty>, Status> {
+            Err(Status::unimplemented(""))
+        }
+
+        type SubmitStream = UnboundedReceiverStream<Result<pb::JobResponse, Status>>;
+
+        async fn cancel(
+            &self, _req: tonic::Request<pb::CancelRequest>,
+        ) -> Result<RpcResponse<Empty>, Status> {
+            Err(Status::unimplemented(""))
+        }
+
+        async fn submit(
+            &self, req: tonic::Request<pb::JobRequest>,
+        ) -> Result<RpcResponse<Self::SubmitStream>, Status> {
+            if req.metadata().get("authorization").is_none() {
+                return Err(Status::unauthenticated("missing token"));
+            }
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            for resp in self.0.clone() {
+                tx.send(Ok(pb::JobResponse { job_id: 7, resp }))
+                    .unwrap();
+            }
+            tx.send(Err(self
+                .1
+                .clone()
+                .unwrap_or_else(|| Status::ok("ok"))))
+                .unwrap();
+            Ok(RpcResponse::new(UnboundedReceiverStream::new(rx)))
+        }
+    }
+
+    impl ExplainService for ResultsService {
+        fn explain(&self, req: tonic::Request<pb::JobRequest>) -> Result<PlanGraph, Status> {
+            if req.metadata().get("authorization").is_none() {
+                return Err(Status::unauthenticated("missing token"));
+            }
+            let mut graph = PlanGraph::new(2);
+            let scan = graph.add_node("Scan", None, 2);
+            let sink = graph.add_node("Sink", None, 1);
+            graph.add_edge(scan, sink, Routing::Aggregate, None);
+            Ok(graph)
+        }
+    }
+
+    impl JsonPlanService for ResultsService {
+        fn encode_plan(&self, plan: &Value) -> Result<Vec<u8>, Status> {
+            match plan {
+                Value::Object(_) => Ok(plan.to_string().into_bytes()),
+                _ => Err(Status::invalid_argument("plan is not an object")),
+            }
+        }
+    }
+
+    fn job_request(token: bool) -> Request<Body> {
+        post("/job", token)
+    }
+
+    fn post(uri: &str, token: bool) -> Request<Body> {
+        let job_request = pb::JobRequest { conf: None, source: vec![], plan: vec![], resource: vec![] };
+        let mut builder = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, PROTOBUF);
+        if token {
+            builder = builder.header("authorization", "Bearer t1");
+        }
+        builder
+            .body(Body::from(job_request.encode_to_vec()))
+            .unwrap()
+    }
+
+    async fn frames(resp: Response<Body>) -> Vec<Value> {
+        let body = hyper::body::to_bytes(resp.into_body())
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn serve_job_test() {
+        let service = ResultsService(vec![b"{\"a\":1}".to_vec(), vec![0x0a, 0x01]], None);
+        let resp = serve(Arc::new(service), job_request(true))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], NDJSON);
+        let frames = frames(resp).await;
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0], json!({ "result": { "a": 1 } }));
+        assert_eq!(frames[1], json!({ "result_hex": "0a01" }));
+        assert_eq!(frames[2]["stats"]["job_id"], 7);
+        assert_eq!(frames[2]["stats"]["results"], 2);
+        assert_eq!(frames[2]["stats"]["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn serve_job_error_test() {
+        let service = ResultsService(vec![b"1".to_vec()], Some(Status::internal("boom")));
+        let resp = serve(Arc::new(service), job_request(true))
+            .await
+            .unwrap();
+        let frames = frames(resp).await;
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0], json!({ "result": 1 }));
+        assert_eq!(frames[1], json!({ "error": { "code": "Internal", "message": "boom" } }));
+        assert_eq!(frames[2]["stats"]["status"], "error");
+    }
+
+    #[tokio::test]
+    async fn serve_job_rejected_test() {
+        let service = ResultsService(vec![], None);
+        let resp = serve(Arc::new(service), job_request(false))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let frames = frames(resp).await;
+        assert_eq!(frames[0]["error"]["code"], "Unauthenticated");
+        assert_eq!(frames[1]["stats"]["results"], 0);
+    }
+
+    #[tokio::test]
+    async fn serve_explain_test() {
+        let service = Arc::new(ResultsService(vec![], None));
+        let resp = serve(service.clone(), post("/explain", true))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+        let body = hyper::body::to_bytes(resp.into_body())
+            .await
+            .unwrap();
+        let graph: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(graph["edges"][0]["routing"], "aggregate");
+
+        let resp = serve(service.clone(), post("/explain?format=dot", true))
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/vnd.graphviz");
+        let body = hyper::body::to_bytes(resp.into_body())
+            .await
+            .unwrap();
+        assert!(String::from_utf8(body.to_vec())
+            .unwrap()
+            .starts_with("digraph plan {"));
+
+        let resp = serve(service.clone(), post("/explain?format=svg", true))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = serve(service, post("/explain", false))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    async fn decode_json(body: Value) -> Result<pb::JobRequest, StatusCode> {
+        let service = ResultsService(vec![], None);
+        decode_job_request(&service, &HeaderMap::new(), Body::from(body.to_string()))
+            .await
+            .map_err(|resp| resp.status())
+    }
+
+    #[tokio::test]
+    async fn decode_json_job_test() {
+        let body = json!({
+            "conf": { "job_id": 3, "workers": 2, "servers": [0, 1] },
+            "plan": { "plan": [] },
+            "resource_hex": "0a01",
+        });
+        let job_request = decode_json(body).await.unwrap();
+        let conf = job_request.conf.unwrap();
+        assert_eq!(conf.job_id, 3);
+        assert_eq!(conf.workers, 2);
+        assert_eq!(conf.servers, Some(Servers::Part(pb::ServerList { servers: vec![0, 1] })));
+        assert_eq!(job_request.plan, b"{\"plan\":[]}".to_vec());
+        assert_eq!(job_request.resource, vec![0x0a, 0x01]);
+        assert!(job_request.source.is_empty());
+
+        let body = json!({ "conf": { "servers": "all" }, "plan_hex": "0a01" });
+        let job_request = decode_json(body).await.unwrap();
+        assert_eq!(job_request.conf.unwrap().servers, Some(Servers::All(pb::Empty {})));
+        assert_eq!(job_request.plan, vec![0x0a, 0x01]);
+
+        let invalid = vec![
+            json!({ "conf": {} }),
+            json!({ "plan": [] }),
+            json!({ "plan": {}, "plan_hex": "" }),
+            json!({ "plan_hex": "0a0" }),
+            json!({ "plan_hex": "zz" }),
+            json!({ "conf": { "servers": "some" }, "plan_hex": "" }),
+            json!({ "conf": { "workers": -1 }, "plan_hex": "" }),
+        ];
+        for body in invalid {
+            assert_eq!(decode_json(body).await.unwrap_err(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn serve_json_job_test() {
+        let service = ResultsService(vec![b"1".to_vec()], None);
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/job")
+            .header("authorization", "Bearer t1")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({ "plan": {} }).to_string()))
+            .unwrap();
+        let resp = serve(Arc::new(service), req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let frames = frames(resp).await;
+        assert_eq!(frames[0], json!({ "result": 1 }));
+        assert_eq!(frames[1]["stats"]["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn read_body_limit_test() {
+        let body = read_body(Body::from(vec![1u8; 8]), 8)
+            .await
+            .unwrap();
+        assert_eq!(body.len(), 8);
+        // rejected by the length
+        let resp = read_body(Body::from(vec![1u8; 9]), 8)
+            .await
+            .unwrap_err();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        // rejected while read, as the length is unknown
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            for _ in 0..3 {
+                if sender
+                    .send_data(Bytes::from(vec![1u8; 4]))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+        let resp = read_body(body, 8).await.unwrap_err();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -1,11 +1,38 @@
 use libloading::{Library, Symbol};
+use pegasus::errors::ErrorCode;
 use pegasus::{BuildJobError, Data, Worker};
 
+use crate::audit::PlanSummary;
+use crate::auth::Principal;
+use crate::explain::PlanGraph;
+use crate::scheduling::SchedulingClass;
+
 #[derive(Default)]
 pub struct JobDesc {
     pub input: Vec<u8>,
     pub plan: Vec<u8>,
+    /// The resource of the job interpreted by the assembly, e.g., the name of the library linked by
+    /// `DynLibraryAssembly`.
     pub resource: Vec<u8>,
+    /// The authenticated submitter of the job, `None` if authentication is disabled.
+    pub principal: Option<Principal>,
+    /// The session of the job, from the `session-id` metadata of the request, sharing the variables
+    /// bound by the jobs of the same session.
+    pub session: Option<String>,
+    /// The graph view the job is executed against, from the `graph-view` metadata of the request.
+    pub view: Option<String>,
+    /// Whether the job reads the softly deleted vertices and edges, from the `include-deleted`
+    /// metadata of the request.
+    pub include_deleted: bool,
+    /// The read consistency of the job, from the `read-consistency` metadata of the request.
+    pub consistency: Option<String>,
+    /// The scheduling class of the job, from the `scheduling-class` metadata of the request.
+    pub scheduling_class: SchedulingClass,
+    /// Whether each result carries the trace of the steps deriving it, for debugging, from the
+    /// `provenance` metadata of the request.
+    pub provenance: bool,
+    /// The most results of the job in total, across all its workers, set by the admission.
+    pub result_limit: Option<u64>,
 }
 
 impl JobDesc {
@@ -23,10 +50,120 @@ impl JobDesc {
         self.resource = resource_bytes;
         self
     }
+
+    pub fn set_principal(&mut self, principal: Principal) -> &mut Self {
+        self.principal = Some(principal);
+        self
+    }
+
+    pub fn set_session(&mut self, session: String) -> &mut Self {
+        self.session = Some(session);
+        self
+    }
+
+    pub fn set_view(&mut self, view: String) -> &mut Self {
+        self.view = Some(view);
+        self
+    }
+
+    pub fn set_include_deleted(&mut self, include_deleted: bool) -> &mut Self {
+        self.include_deleted = include_deleted;
+        self
+    }
+
+    pub fn set_consistency(&mut self, consistency: String) -> &mut Self {
+        self.consistency = Some(consistency);
+        self
+    }
+
+    pub fn set_scheduling_class(&mut self, scheduling_class: SchedulingClass) -> &mut Self {
+        self.scheduling_class = scheduling_class;
+        self
+    }
+
+    pub fn set_provenance(&mut self, provenance: bool) -> &mut Self {
+        self.provenance = provenance;
+        self
+    }
+}
+
+/// The step of the plan of a job which the assembly can't run, found by `JobAssembly::validate` at the
+/// admission, e.g., an operator of an unsupported argument.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InvalidPlan {
+    /// The path of the step in the plan, e.g., `2` of the third operator of the plan, or
+    /// `2.sub_plans[1].0` of the first operator of the second sub-plan of the third operator.
+    pub step: String,
+    /// The name of the operator of the step.
+    pub operator: String,
+    /// The argument of the operator which is invalid, if any.
+    pub argument: Option<String>,
+    pub reason: String,
+}
+
+impl std::fmt::Display for InvalidPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.argument.as_ref() {
+            Some(argument) => {
+                write!(
+                    f,
+                    "step {} `{}`, argument `{}`: {}",
+                    self.step, self.operator, argument, self.reason
+                )
+            }
+            None => write!(f, "step {} `{}`: {}", self.step, self.operator, self.reason),
+        }
+    }
+}
+
+impl std::error::Error for InvalidPlan {}
+
+impl InvalidPlan {
+    pub const CODE: ErrorCode = ErrorCode::user(1005);
 }
 
 pub trait JobAssembly<I: Data>: Send + Sync + 'static {
     fn assemble(&self, job: &JobDesc, worker: &mut Worker<I, Vec<u8>>) -> Result<(), BuildJobError>;
+
+    /// Summarize the plan of a job for the audit log, `None` if the plan is opaque to the assembly.
+    fn summarize(&self, _job: &JobDesc) -> Option<PlanSummary> {
+        None
+    }
+
+    /// Describe the plan of a job in text for the slow query log, `None` if the plan is opaque.
+    fn describe(&self, _job: &JobDesc) -> Option<String> {
+        None
+    }
+
+    /// Count the vertices, or the edges, of a label in the stored graph for the index advisor, `None` if
+    /// unknown to the assembly.
+    fn count_label(&self, _label: &str, _edge: bool) -> Option<u64> {
+        None
+    }
+
+    /// Describe the scans of the plan of a job reading all the vertices or edges of their labels without
+    /// any filter or limit for the admission, see `admission`; none if the plan is opaque.
+    fn lint(&self, _job: &JobDesc) -> Vec<String> {
+        vec![]
+    }
+
+    /// Validate the plan of a job against the capabilities of the assembly at the admission, rather than
+    /// failing while it's assembled; valid if the plan is opaque.
+    fn validate(&self, _job: &JobDesc) -> Result<(), InvalidPlan> {
+        Ok(())
+    }
+
+    /// The plan of a job as it would be executed by `parallelism` workers, after the rewrites of the
+    /// assembly, see `explain`; `None` if the plan is opaque.
+    fn explain(&self, _job: &JobDesc, _parallelism: usize) -> Result<Option<PlanGraph>, BuildJobError> {
+        Ok(None)
+    }
+
+    /// Encode the plan of a job given in json, e.g., posted to the HTTP endpoint, into the plan of
+    /// `JobDesc`; `None` if the plans are opaque to the assembly.
+    fn encode_plan(&self, _plan: &serde_json::Value) -> Option<Result<Vec<u8>, String>> {
+        None
+    }
 }
 
 pub struct DynLibraryAssembly;
@@ -42,12 +42,26 @@ pub use generated::protocol as pb;
 
 pub trait AnyData: Data + Eq {}
 
+pub mod admin;
+pub mod admission;
+pub mod advisor;
+pub mod audit;
+pub mod auth;
 // pub mod client;
 pub mod client;
 pub mod cluster;
 pub mod config;
+pub mod drain;
+pub mod explain;
+#[cfg(feature = "flight")]
+pub mod flight;
+pub mod http;
 pub mod job;
+pub mod metrics;
 pub mod rpc;
+pub mod runtime_config;
+pub mod scheduling;
+pub mod slow_query;
 
 pub use generated::protocol::{JobRequest, JobResponse};
 
@@ -0,0 +1,402 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Serve the metrics of the store and the runtime in Prometheus text format on `GET /metrics`, and
+//! the endpoints to roll the server: the readiness on `GET /ready`, and the drain on `POST /drain`; and
+//! the indexes recommended by the index advisor on `GET /advisor/indexes`; and the degree reports of
+//! the store on `GET /admin/degrees`, the subgraph extraction on `POST /admin/export`, and the stored
+//! procedures on `POST /admin/procedures` and `DELETE /admin/procedures`, see `admin`.
+//! The admin endpoints are served to the callers with the admin role only.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::header::HeaderValue;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Client, HeaderMap, Method, Request, Response, Server, StatusCode};
+use prometheus::{Encoder, TextEncoder};
+use tonic::metadata::MetadataMap;
+
+use crate::admin::{DegreeQuery, ExportQuery, ADMIN_ROLE};
+use crate::auth::Authenticator;
+use crate::http::{read_body, MAX_BODY_BYTES};
+use crate::{admin, advisor, drain};
+
+/// The time to wait for the degree report of a peer, which may scan all the edges of the label.
+const PEER_REPORT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Who may call the admin endpoints, and the peers their reports are merged from.
+#[derive(Clone, Default)]
+pub struct AdminAccess {
+    /// The admin endpoints are refused if not set.
+    pub authenticator: Option<Arc<dyn Authenticator>>,
+    /// The metrics uris of the other servers, e.g. `http://host:port`.
+    pub peers: Vec<String>,
+}
+
+/// Start serving the metrics on `addr` in background, the endpoint is stopped with the runtime.
+pub fn start_metrics_server(addr: SocketAddr, admin: AdminAccess) -> Result<SocketAddr, hyper::Error> {
+    let admin = Arc::new(admin);
+    let make_service = make_service_fn(move |_| {
+        let admin = admin.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| serve(req, admin.clone()))) }
+    });
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    let local_addr = server.local_addr();
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("metrics server at {} stopped: {}", local_addr, e);
+        }
+    });
+    info!("metrics server started at {}", local_addr);
+    Ok(local_addr)
+}
+
+async fn serve(req: Request<Body>, admin: Arc<AdminAccess>) -> Result<Response<Body>, Infallible> {
+    if req.uri().path().starts_with("/admin/") {
+        if let Err(resp) = authorize(&admin, req.headers()) {
+            return Ok(resp);
+        }
+    }
+    let resp = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => serve_metrics(),
+        (&Method::GET, "/ready") => serve_ready(),
+        (&Method::POST, "/drain") => serve_drain(req.uri().query()).await,
+        (&Method::GET, "/advisor/indexes") => serve_index_recommendations(),
+        (&Method::GET, "/admin/degrees") => {
+            let authorization = req
+                .headers()
+                .get(header::AUTHORIZATION)
+                .cloned();
+            serve_degree_report(req.uri().query(), authorization, &admin.peers).await
+        }
+        (&Method::POST, "/admin/export") => serve_subgraph_export(req.uri().query()).await,
+        (&Method::POST, "/admin/procedures") => {
+            let query = req.uri().query().map(str::to_owned);
+            serve_procedure_install(query.as_deref(), req.into_body()).await
+        }
+        (&Method::DELETE, "/admin/procedures") => serve_procedure_uninstall(req.uri().query()),
+        _ => status_response(StatusCode::NOT_FOUND, String::new()),
+    };
+    Ok(resp)
+}
+
+/// Accept the callers authenticated with the admin role only.
+fn authorize(admin: &AdminAccess, headers: &HeaderMap) -> Result<(), Response<Body>> {
+    let authenticator = admin.authenticator.as_ref().ok_or_else(|| {
+        status_response(
+            StatusCode::FORBIDDEN,
+            "admin endpoints require the auth tokens of the server".to_owned(),
+        )
+    })?;
+    let principal = authenticator
+        .authenticate(&MetadataMap::from_headers(headers.clone()))
+        .map_err(|e| status_response(StatusCode::UNAUTHORIZED, e.message().to_owned()))?;
+    if principal
+        .roles
+        .iter()
+        .any(|role| role == ADMIN_ROLE)
+    {
+        Ok(())
+    } else {
+        Err(status_response(StatusCode::FORBIDDEN, format!("{} is not an admin", principal.user)))
+    }
+}
+
+fn status_response(status: StatusCode, body: String) -> Response<Body> {
+    let mut resp = Response::new(Body::from(body));
+    *resp.status_mut() = status;
+    resp
+}
+
+fn serve_metrics() -> Response<Body> {
+    let encoder = TextEncoder::new();
+    let mut buf = Vec::new();
+    match encoder.encode(&prometheus::gather(), &mut buf) {
+        Ok(()) => Response::builder()
+            .header(header::CONTENT_TYPE, encoder.format_type())
+            .body(Body::from(buf))
+            .expect("build metrics response failure"),
+        Err(e) => status_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// The indexes recommended by the advisor in json, ordered by the time of the jobs they help.
+fn serve_index_recommendations() -> Response<Body> {
+    let recommendations = match advisor::recommend() {
+        Some(recommendations) => recommendations,
+        None => return status_response(StatusCode::NOT_FOUND, "index advisor not started".to_owned()),
+    };
+    match serde_json::to_vec(&recommendations) {
+        Ok(json) => Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json))
+            .expect("build recommendations response failure"),
+        Err(e) => status_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// The degree report of an edge label in json, which is computed in a blocking thread as it may scan
+/// all the edges of the label, and is merged with the reports of the peers unless it's local.
+async fn serve_degree_report(
+    raw_query: Option<&str>, authorization: Option<HeaderValue>, peers: &[String],
+) -> Response<Body> {
+    let reporter = match admin::get_degree_reporter() {
+        Some(reporter) => reporter,
+        None => return status_response(StatusCode::NOT_FOUND, "degree report not supported".to_owned()),
+    };
+    let query = match DegreeQuery::parse(raw_query) {
+        Ok(query) => query,
+        Err(e) => return status_response(StatusCode::BAD_REQUEST, e),
+    };
+    let peers: &[String] = if query.local { &[] } else { peers };
+    let peer_query = format!("{}&local=true", raw_query.unwrap_or_default());
+    let (report, peer_reports) = tokio::join!(
+        tokio::task::spawn_blocking(move || reporter(&query)),
+        fetch_peer_reports(peers, &peer_query, authorization)
+    );
+    let report = match report {
+        Ok(Ok(json)) => json,
+        Ok(Err(e)) => return status_response(StatusCode::BAD_REQUEST, e),
+        Err(e) => return status_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    let json = match peer_reports {
+        Ok(peer_reports) if peer_reports.is_empty() => report,
+        Ok(peer_reports) => match admin::merge_degree_reports(&report, &peer_reports) {
+            Ok(json) => json,
+            Err(e) => return status_response(StatusCode::BAD_GATEWAY, e),
+        },
+        Err(e) => return status_response(StatusCode::BAD_GATEWAY, e),
+    };
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json))
+        .expect("build degree report response failure")
+}
+
+/// The local reports of the peers, which are queried concurrently with the caller's credentials.
+async fn fetch_peer_reports(
+    peers: &[String], query: &str, authorization: Option<HeaderValue>,
+) -> Result<Vec<Vec<u8>>, String> {
+    let client = Client::new();
+    let handles = peers
+        .iter()
+        .map(|peer| {
+            let uri = format!("{}/admin/degrees?{}", peer.trim_end_matches('/'), query);
+            let mut request = Request::get(&uri);
+            if let Some(authorization) = authorization.clone() {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            let request = request
+                .body(Body::empty())
+                .map_err(|e| format!("invalid peer uri {}: {}", uri, e));
+            let client = client.clone();
+            tokio::spawn(async move {
+                let resp = tokio::time::timeout(PEER_REPORT_TIMEOUT, client.request(request?))
+                    .await
+                    .map_err(|_| format!("query {} timeout", uri))?
+                    .map_err(|e| format!("query {} failed: {}", uri, e))?;
+                let status = resp.status();
+                let body = hyper::body::to_bytes(resp.into_body())
+                    .await
+                    .map_err(|e| format!("read {} failed: {}", uri, e))?;
+                if status.is_success() {
+                    Ok(body.to_vec())
+                } else {
+                    Err(format!("query {} failed: {} {}", uri, status, String::from_utf8_lossy(&body)))
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut reports = Vec::with_capacity(handles.len());
+    for handle in handles {
+        reports.push(handle.await.map_err(|e| e.to_string())??);
+    }
+    Ok(reports)
+}
+
+/// Extract the subgraph of the query, responds with the manifest in json after all the files are
+/// written, which is done in a blocking thread as it scans the partitions of the server.
+async fn serve_subgraph_export(query: Option<&str>) -> Response<Body> {
+    let exporter = match admin::get_subgraph_exporter() {
+        Some(exporter) => exporter,
+        None => return status_response(StatusCode::NOT_FOUND, "subgraph export not supported".to_owned()),
+    };
+    let query = match ExportQuery::parse(query) {
+        Ok(query) => query,
+        Err(e) => return status_response(StatusCode::BAD_REQUEST, e),
+    };
+    match tokio::task::spawn_blocking(move || exporter(&query)).await {
+        Ok(Ok(json)) => Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json))
+            .expect("build subgraph export response failure"),
+        Ok(Err(e)) => status_response(StatusCode::BAD_REQUEST, e),
+        Err(e) => status_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Install the procedure of the plan in the body, which is rejected if it sinks or calls procedures.
+async fn serve_procedure_install(query: Option<&str>, body: Body) -> Response<Body> {
+    let procedures = match admin::get_procedure_admin() {
+        Some(procedures) => procedures,
+        None => {
+            return status_response(StatusCode::NOT_FOUND, "stored procedures not supported".to_owned())
+        }
+    };
+    let name = match admin::parse_procedure_name(query) {
+        Ok(name) => name,
+        Err(e) => return status_response(StatusCode::BAD_REQUEST, e),
+    };
+    let plan = match read_body(body, MAX_BODY_BYTES).await {
+        Ok(plan) => plan,
+        Err(resp) => return resp,
+    };
+    match procedures.install(&name, &plan) {
+        Ok(()) => status_response(StatusCode::OK, format!("procedure `{}` installed", name)),
+        Err(e) => status_response(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+fn serve_procedure_uninstall(query: Option<&str>) -> Response<Body> {
+    let procedures = match admin::get_procedure_admin() {
+        Some(procedures) => procedures,
+        None => {
+            return status_response(StatusCode::NOT_FOUND, "stored procedures not supported".to_owned())
+        }
+    };
+    let name = match admin::parse_procedure_name(query) {
+        Ok(name) => name,
+        Err(e) => return status_response(StatusCode::BAD_REQUEST, e),
+    };
+    match procedures.uninstall(&name) {
+        Ok(true) => status_response(StatusCode::OK, format!("procedure `{}` uninstalled", name)),
+        Ok(false) => status_response(StatusCode::NOT_FOUND, format!("procedure `{}` is not found", name)),
+        Err(e) => status_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// The readiness probe, which fails once the server is draining so that no new jobs are routed to it.
+fn serve_ready() -> Response<Body> {
+    if drain::is_draining() {
+        status_response(StatusCode::SERVICE_UNAVAILABLE, "draining".to_owned())
+    } else {
+        status_response(StatusCode::OK, "ready".to_owned())
+    }
+}
+
+/// Drain the server, e.g. in a pre-stop hook, responds after the server is drained or
+/// `timeout_ms` in the query is elapsed.
+async fn serve_drain(query: Option<&str>) -> Response<Body> {
+    let timeout = query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("timeout_ms="))
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(drain::DEFAULT_DRAIN_TIMEOUT);
+    if drain::drain(timeout).await {
+        status_response(StatusCode::OK, "drained".to_owned())
+    } else {
+        let msg = format!("draining, {} jobs running", drain::running_jobs());
+        status_response(StatusCode::SERVICE_UNAVAILABLE, msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::admin::ProcedureAdmin;
+    use crate::auth::{Principal, TokenAuthenticator, TokenEntry};
+
+    #[derive(Default)]
+    struct TestProcedures(Mutex<HashMap<String, Vec<u8>>>);
+
+    impl ProcedureAdmin for TestProcedures {
+        fn install(&self, name: &str, plan: &[u8]) -> Result<(), String> {
+            if plan.is_empty() {
+                return Err("empty plan".to_owned());
+            }
+            self.0
+                .lock()
+                .unwrap()
+                .insert(name.to_owned(), plan.to_vec());
+            Ok(())
+        }
+
+        fn uninstall(&self, name: &str) -> Result<bool, String> {
+            Ok(self.0.lock().unwrap().remove(name).is_some())
+        }
+    }
+
+    fn admin_access() -> Arc<AdminAccess> {
+        let entries = vec![
+            TokenEntry { token: "t1".to_owned(), principal: Principal::new("root").with_role(ADMIN_ROLE) },
+            TokenEntry { token: "t2".to_owned(), principal: Principal::new("marko").with_role("analyst") },
+        ];
+        let authenticator: Arc<dyn Authenticator> = Arc::new(TokenAuthenticator::new(entries));
+        Arc::new(AdminAccess { authenticator: Some(authenticator), peers: vec![] })
+    }
+
+    fn procedure_request(method: Method, token: Option<&str>, body: &[u8]) -> Request<Body> {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri("/admin/procedures?name=by_name");
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        builder.body(Body::from(body.to_vec())).unwrap()
+    }
+
+    async fn status_of(req: Request<Body>, admin: Arc<AdminAccess>) -> StatusCode {
+        serve(req, admin).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn serve_procedures_test() {
+        let procedures = Arc::new(TestProcedures::default());
+        admin::set_procedure_admin(procedures.clone());
+        let access = admin_access();
+
+        // only the admins may install the procedures
+        let req = procedure_request(Method::POST, None, b"plan");
+        assert_eq!(status_of(req, access.clone()).await, StatusCode::UNAUTHORIZED);
+        let req = procedure_request(Method::POST, Some("t2"), b"plan");
+        assert_eq!(status_of(req, access.clone()).await, StatusCode::FORBIDDEN);
+        let req = procedure_request(Method::POST, Some("t1"), b"plan");
+        assert_eq!(status_of(req, Arc::new(AdminAccess::default())).await, StatusCode::FORBIDDEN);
+        assert!(procedures.0.lock().unwrap().is_empty());
+
+        let req = procedure_request(Method::POST, Some("t1"), b"");
+        assert_eq!(status_of(req, access.clone()).await, StatusCode::BAD_REQUEST);
+        let req = procedure_request(Method::POST, Some("t1"), b"plan");
+        assert_eq!(status_of(req, access.clone()).await, StatusCode::OK);
+        assert_eq!(procedures.0.lock().unwrap()["by_name"], b"plan".to_vec());
+
+        // nor uninstall them
+        let req = procedure_request(Method::DELETE, Some("t2"), b"");
+        assert_eq!(status_of(req, access.clone()).await, StatusCode::FORBIDDEN);
+        let req = procedure_request(Method::DELETE, Some("t1"), b"");
+        assert_eq!(status_of(req, access.clone()).await, StatusCode::OK);
+        let req = procedure_request(Method::DELETE, Some("t1"), b"");
+        assert_eq!(status_of(req, access).await, StatusCode::NOT_FOUND);
+        assert!(procedures.0.lock().unwrap().is_empty());
+    }
+}
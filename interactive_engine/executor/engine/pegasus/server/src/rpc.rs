@@ -38,7 +38,7 @@ use opentelemetry_otlp::WithExportConfig;
 use opentelemetry_sdk::propagation::TraceContextPropagator;
 use pegasus::api::function::FnResult;
 use pegasus::api::FromStream;
-use pegasus::errors::{ErrorKind, JobExecError};
+use pegasus::errors::{find_code, ErrorCode, ErrorKind, JobExecError};
 use pegasus::result::{FromStreamExt, ResultSink};
 use pegasus::{Configuration, Data, JobConf, ServerConf};
 use pegasus_network::config::ServerAddr;
@@ -46,19 +46,31 @@ use pegasus_network::ServerDetect;
 use serde::Deserialize;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio_stream::wrappers::UnboundedReceiverStream;
-use tonic::transport::Server;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
 use tonic::{Code, Request, Response, Status};
 
+use crate::advisor::{self, AdvisorConfig};
+use crate::audit::{AuditConfig, AuditRecord, AuditSink, AuditTracker, MultiAuditSink};
+use crate::auth::{Authenticator, Principal, TokenAuthenticator, TokenEntry};
+use crate::drain::JobPermit;
+use crate::explain::{ExplainService, PlanGraph};
 use crate::generated::protocol as pb;
 use crate::generated::protocol::job_config::Servers;
-use crate::job::{JobAssembly, JobDesc};
+use crate::http::JsonPlanService;
+use crate::job::{InvalidPlan, JobAssembly, JobDesc};
 use crate::pb::{BinaryResource, Empty, Name};
+use crate::scheduling::{self, ClassPermit, SchedulingClass};
+use crate::slow_query::{SlowQueryConfig, SlowQueryLog, SlowQueryRecord, SlowQueryTracker};
 
 pub struct RpcSink {
     pub job_id: u64,
     had_error: Arc<AtomicBool>,
     peers: Arc<AtomicUsize>,
     tx: UnboundedSender<Result<pb::JobResponse, Status>>,
+    audit: Option<Arc<AuditTracker>>,
+    slow_query: Option<Arc<SlowQueryTracker>>,
+    permit: Option<Arc<JobPermit>>,
+    class_permit: Option<Arc<ClassPermit>>,
 }
 
 impl RpcSink {
@@ -68,8 +80,37 @@ impl RpcSink {
             had_error: Arc::new(AtomicBool::new(false)),
             peers: Arc::new(AtomicUsize::new(1)),
             job_id,
+            audit: None,
+            slow_query: None,
+            permit: None,
+            class_permit: None,
         }
     }
+
+    /// Count the results and errors of the job in the audit record.
+    pub fn with_audit(mut self, tracker: Arc<AuditTracker>) -> Self {
+        self.audit = Some(tracker);
+        self
+    }
+
+    /// Log the job if it's slow once all the sinks of the job are dropped.
+    pub fn with_slow_query(mut self, tracker: Arc<SlowQueryTracker>) -> Self {
+        self.slow_query = Some(tracker);
+        self
+    }
+
+    /// Hold the permit of the job until all the sinks of the job are dropped, so that a drain waits
+    /// for the job.
+    pub fn with_permit(mut self, permit: JobPermit) -> Self {
+        self.permit = Some(Arc::new(permit));
+        self
+    }
+
+    /// Hold the job in its scheduling class until all the sinks of the job are dropped.
+    pub fn with_class_permit(mut self, permit: ClassPermit) -> Self {
+        self.class_permit = Some(Arc::new(permit));
+        self
+    }
 }
 
 impl FromStream<Vec<u8>> for RpcSink {
@@ -77,6 +118,9 @@ impl FromStream<Vec<u8>> for RpcSink {
         // todo: use bytes to alleviate copy & allocate cost;
         let res = pb::JobResponse { job_id: self.job_id, resp };
         self.tx.send(Ok(res)).ok();
+        if let Some(audit) = self.audit.as_ref() {
+            audit.add_row();
+        }
         Ok(())
     }
 }
@@ -89,6 +133,10 @@ impl Clone for RpcSink {
             had_error: self.had_error.clone(),
             peers: self.peers.clone(),
             tx: self.tx.clone(),
+            audit: self.audit.clone(),
+            slow_query: self.slow_query.clone(),
+            permit: self.permit.clone(),
+            class_permit: self.class_permit.clone(),
         }
     }
 }
@@ -96,8 +144,8 @@ impl Clone for RpcSink {
 impl FromStreamExt<Vec<u8>> for RpcSink {
     fn on_error(&mut self, error: Box<dyn Error + Send>) {
         self.had_error.store(true, Ordering::SeqCst);
-        let status = if let Some(e) = error.downcast_ref::<JobExecError>() {
-            match e.kind {
+        let (status, code) = if let Some(e) = error.downcast_ref::<JobExecError>() {
+            let status = match e.kind {
                 ErrorKind::WouldBlock(_) => {
                     Status::internal(format!("[Execution Error] WouldBlock: {}", error))
                 }
@@ -112,10 +160,16 @@ impl FromStreamExt<Vec<u8>> for RpcSink {
                     Status::deadline_exceeded(format!("[Execution Error] Canceled: {}", error))
                 }
                 _ => Status::unknown(format!("[Execution Error]: {}", error)),
-            }
+            };
+            (status, e.code())
         } else {
-            Status::unknown(format!("[Unknown Error]: {}", error))
+            let code = find_code(error.as_ref()).unwrap_or(ErrorCode::UNKNOWN);
+            (Status::unknown(format!("[Unknown Error]: {}", error)), code)
         };
+        let status = with_error_code(status, code);
+        if let Some(audit) = self.audit.as_ref() {
+            audit.set_error(status.message().to_owned());
+        }
 
         self.tx.send(Err(status)).ok();
     }
@@ -137,6 +191,81 @@ impl Drop for RpcSink {
 pub struct JobServiceImpl<I> {
     inner: Arc<dyn JobAssembly<I>>,
     report: bool,
+    authenticator: Option<Arc<dyn Authenticator>>,
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    slow_query_log: Option<Arc<SlowQueryLog>>,
+}
+
+impl<I> JobServiceImpl<I> {
+    pub fn new(inner: Arc<dyn JobAssembly<I>>, authenticator: Option<Arc<dyn Authenticator>>) -> Self {
+        JobServiceImpl { inner, report: true, authenticator, audit_sink: None, slow_query_log: None }
+    }
+
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit_sink = Some(sink);
+        self
+    }
+
+    pub fn with_slow_query_log(mut self, log: Arc<SlowQueryLog>) -> Self {
+        self.slow_query_log = Some(log);
+        self
+    }
+
+    /// Authenticate the caller of a request, every caller is accepted as `None` if no authenticator
+    /// is configured.
+    fn authenticate(&self, metadata: &tonic::metadata::MetadataMap) -> Result<Option<Principal>, Status> {
+        match self.authenticator {
+            Some(ref authenticator) => authenticator.authenticate(metadata).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The job of the metadata of a request, e.g., `session-id` and `scheduling-class`, whose plan is
+    /// left empty.
+    fn job_desc(&self, metadata: &tonic::metadata::MetadataMap) -> Result<JobDesc, Status> {
+        let principal = self.authenticate(metadata)?;
+        let session = metadata
+            .get("session-id")
+            .and_then(|session| session.to_str().ok())
+            .map(|session| session.to_string());
+        let view = metadata
+            .get("graph-view")
+            .and_then(|view| view.to_str().ok())
+            .map(|view| view.to_string());
+        let include_deleted = metadata
+            .get("include-deleted")
+            .and_then(|include_deleted| include_deleted.to_str().ok())
+            .map_or(false, |include_deleted| include_deleted == "true");
+        let consistency = metadata
+            .get("read-consistency")
+            .and_then(|consistency| consistency.to_str().ok())
+            .map(|consistency| consistency.to_string());
+        let scheduling_class = metadata
+            .get("scheduling-class")
+            .map(|class| {
+                class
+                    .to_str()
+                    .map_err(|e| e.to_string())
+                    .and_then(|class| class.parse::<SchedulingClass>())
+                    .map_err(Status::invalid_argument)
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let provenance = metadata
+            .get("provenance")
+            .and_then(|provenance| provenance.to_str().ok())
+            .map_or(false, |provenance| provenance == "true");
+        Ok(JobDesc {
+            principal,
+            session,
+            view,
+            include_deleted,
+            consistency,
+            scheduling_class,
+            provenance,
+            ..Default::default()
+        })
+    }
 }
 
 #[tonic::async_trait]
@@ -145,6 +274,7 @@ where
     I: Data,
 {
     async fn add_library(&self, request: Request<BinaryResource>) -> Result<Response<Empty>, Status> {
+        self.authenticate(request.metadata())?;
         let BinaryResource { name, resource } = request.into_inner();
         let mut path = PathBuf::from("./lib");
         path.push(&name);
@@ -180,6 +310,7 @@ where
     }
 
     async fn remove_library(&self, request: Request<Name>) -> Result<Response<Empty>, Status> {
+        self.authenticate(request.metadata())?;
         let name = request.into_inner().name;
         pegasus::resource::remove_global_resource(&name);
         Ok(Response::new(Empty {}))
@@ -188,6 +319,7 @@ where
     type SubmitStream = UnboundedReceiverStream<Result<pb::JobResponse, Status>>;
 
     async fn cancel(&self, req: Request<pb::CancelRequest>) -> Result<Response<Empty>, Status> {
+        self.authenticate(req.metadata())?;
         let parent_ctx = global::get_text_map_propagator(|prop| prop.extract(&MetadataMap(req.metadata())));
         let tracer = global::tracer("executor");
         let _span = tracer
@@ -203,21 +335,64 @@ where
         debug!("accept new request from {:?};", req.remote_addr());
         let parent_ctx = global::get_text_map_propagator(|prop| prop.extract(&MetadataMap(req.metadata())));
         let tracer = global::tracer("executor");
+        let headers = self.job_desc(req.metadata())?;
+        let permit = crate::drain::admit().ok_or_else(|| Status::unavailable("server is draining"))?;
 
         let pb::JobRequest { conf, source, plan, resource } = req.into_inner();
         if conf.is_none() {
             return Err(Status::new(Code::InvalidArgument, "job configuration not found"));
         }
 
-        let conf = parse_conf_req(conf.unwrap());
-        info!("job conf {:?}", conf);
+        let mut conf = parse_conf_req(conf.unwrap());
         pegasus::wait_servers_ready(conf.servers());
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-        let rpc_sink = RpcSink::new(conf.job_id, tx);
-        let sink = ResultSink::<Vec<u8>>::with(rpc_sink);
         let job_id = conf.job_id;
         let service = &self.inner;
-        let job = JobDesc { input: source, plan, resource };
+        let mut job = JobDesc { input: source, plan, resource, ..headers };
+        service
+            .validate(&job)
+            .map_err(|e| invalid_plan_status(job_id, e))?;
+        let runtime_config = crate::runtime_config::current();
+        runtime_config
+            .admission
+            .admit(service.as_ref(), &mut job, &mut conf)
+            .map_err(|e| Status::failed_precondition(format!("job {} rejected: {}", job_id, e)))?;
+        let class = job.scheduling_class;
+        let class_permit = scheduling::acquire(class, runtime_config.scheduling.limits(class), &mut conf)
+            .await
+            .map_err(|e| Status::resource_exhausted(format!("job {} rejected: {}", job_id, e)))?;
+        info!("job conf {:?}", conf);
+        let audit = self.audit_sink.as_ref().map(|audit_sink| {
+            let record = AuditRecord {
+                job_id,
+                job_name: conf.job_name.clone(),
+                user: job.principal.as_ref().map(|p| p.user.clone()),
+                plan: service.summarize(&job).unwrap_or_default(),
+                ..Default::default()
+            };
+            Arc::new(AuditTracker::new(record, audit_sink.clone()))
+        });
+        let slow_query = self.slow_query_log.as_ref().map(|log| {
+            let record = SlowQueryRecord {
+                job_id,
+                job_name: conf.job_name.clone(),
+                user: job.principal.as_ref().map(|p| p.user.clone()),
+                summary: service.summarize(&job).unwrap_or_default(),
+                plan: service.describe(&job),
+                ..Default::default()
+            };
+            Arc::new(log.track(record))
+        });
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut rpc_sink = RpcSink::new(job_id, tx)
+            .with_permit(permit)
+            .with_class_permit(class_permit);
+        if let Some(audit) = audit.as_ref() {
+            rpc_sink = rpc_sink.with_audit(audit.clone());
+        }
+        if let Some(slow_query) = slow_query {
+            rpc_sink = rpc_sink.with_slow_query(slow_query);
+        }
+        let sink = ResultSink::<Vec<u8>>::with(rpc_sink);
 
         let mut span = tracer
             .span_builder("JobService/submit")
@@ -233,13 +408,61 @@ where
 
         if let Err(e) = ret {
             error!("submit job {} failure: {:?}", job_id, e);
-            Err(Status::unknown(format!("submit job error {}", e)))
+            if let Some(audit) = audit {
+                audit.set_error(format!("submit job error {}", e));
+            }
+            Err(with_error_code(Status::unknown(format!("submit job error {}", e)), e.code()))
         } else {
             Ok(Response::new(UnboundedReceiverStream::new(rx)))
         }
     }
 }
 
+impl<I> JsonPlanService for JobServiceImpl<I>
+where
+    I: Data,
+{
+    fn encode_plan(&self, plan: &serde_json::Value) -> Result<Vec<u8>, Status> {
+        match self.inner.encode_plan(plan) {
+            Some(Ok(plan)) => Ok(plan),
+            Some(Err(e)) => Err(Status::invalid_argument(format!("invalid plan: {}", e))),
+            None => {
+                Err(Status::invalid_argument("the plan in json is opaque to the server, post `plan_hex`"))
+            }
+        }
+    }
+}
+
+impl<I> ExplainService for JobServiceImpl<I>
+where
+    I: Data,
+{
+    fn explain(&self, req: Request<pb::JobRequest>) -> Result<PlanGraph, Status> {
+        let headers = self.job_desc(req.metadata())?;
+        let pb::JobRequest { conf, source, plan, resource } = req.into_inner();
+        let conf = conf.ok_or_else(|| Status::invalid_argument("job configuration not found"))?;
+        let mut conf = parse_conf_req(conf);
+        let job_id = conf.job_id;
+        let service = &self.inner;
+        let mut job = JobDesc { input: source, plan, resource, ..headers };
+        service
+            .validate(&job)
+            .map_err(|e| invalid_plan_status(job_id, e))?;
+        crate::runtime_config::current()
+            .admission
+            .admit(service.as_ref(), &mut job, &mut conf)
+            .map_err(|e| Status::failed_precondition(format!("job {} rejected: {}", job_id, e)))?;
+        match service.explain(&job, conf.total_workers()) {
+            Ok(Some(graph)) => Ok(graph),
+            Ok(None) => Err(Status::unimplemented("the plan of the job is opaque to the server")),
+            Err(e) => {
+                let status = Status::invalid_argument(format!("explain job {} failure: {}", job_id, e));
+                Err(with_error_code(status, e.code()))
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct RPCServerConfig {
     pub rpc_host: Option<String>,
@@ -253,6 +476,72 @@ pub struct RPCServerConfig {
     pub rpc_keep_alive_timeout_ms: Option<u64>,
     pub tcp_keep_alive_ms: Option<u64>,
     pub tcp_nodelay: Option<bool>,
+    /// Bearer tokens accepted by the job service, authentication is disabled if not set.
+    pub auth_tokens: Option<Vec<TokenEntry>>,
+    /// Where to write the audit records of jobs, audit is disabled if not set.
+    pub audit: Option<AuditConfig>,
+    /// Serve over tls, plaintext is served if not set.
+    pub tls: Option<RpcTlsConfig>,
+    /// The file of the runtime configuration reloaded on changes, see `runtime_config`.
+    pub runtime_config_file: Option<String>,
+    /// Log the jobs running longer than a threshold, not logged if not set.
+    pub slow_query: Option<SlowQueryConfig>,
+    /// The port to serve metrics in Prometheus format on `rpc_host`, not served if not set.
+    pub metrics_port: Option<u16>,
+    /// Recommend indexes by the filters of the jobs, not recommended if not set, see `advisor`.
+    pub index_advisor: Option<AdvisorConfig>,
+    /// Serve the results as Arrow record batches by Arrow Flight on the rpc port, not served if not set;
+    /// requires the `flight` feature.
+    pub flight: Option<bool>,
+    /// The rpc uris of all the servers of the cluster in the order of their ids, which the jobs of Arrow
+    /// Flight are submitted to along with this server, skipped at the id of this server; the jobs are
+    /// submitted to this server only if not set.
+    pub flight_peers: Option<Vec<String>>,
+    /// The metrics uris of all the servers of the cluster in the order of their ids, e.g.
+    /// `http://host:port`, which the admin reports are merged from along with this server, skipped at the
+    /// id of this server; the reports are of this server only if not set.
+    pub metrics_peers: Option<Vec<String>>,
+    /// The port to serve the jobs in JSON over HTTP on `rpc_host`, over https by `tls` if set; not served
+    /// if not set, see `http`.
+    pub http_port: Option<u16>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RpcTlsConfig {
+    /// The certificate chain of the server in PEM.
+    pub cert_file: String,
+    /// The private key of the certificate in PEM.
+    pub key_file: String,
+    /// The CA certificates in PEM to verify the clients against; clients are required to present
+    /// certificates if set.
+    pub client_ca_file: Option<String>,
+}
+
+impl RpcTlsConfig {
+    fn build(&self) -> std::io::Result<ServerTlsConfig> {
+        let cert = std::fs::read(&self.cert_file)?;
+        let key = std::fs::read(&self.key_file)?;
+        let mut tls = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+        if let Some(ca_file) = self.client_ca_file.as_ref() {
+            let ca = std::fs::read(ca_file)?;
+            tls = tls.client_ca_root(Certificate::from_pem(ca));
+        }
+        Ok(tls)
+    }
+
+    /// The tls to connect the other servers by, which present the certificate of this server, and are
+    /// verified against `client_ca_file` if set.
+    #[cfg(feature = "flight")]
+    fn build_client(&self) -> std::io::Result<tonic::transport::ClientTlsConfig> {
+        let cert = std::fs::read(&self.cert_file)?;
+        let key = std::fs::read(&self.key_file)?;
+        let mut tls = tonic::transport::ClientTlsConfig::new().identity(Identity::from_pem(cert, key));
+        if let Some(ca_file) = self.client_ca_file.as_ref() {
+            let ca = std::fs::read(ca_file)?;
+            tls = tls.ca_certificate(Certificate::from_pem(ca));
+        }
+        Ok(tls)
+    }
 }
 
 impl RPCServerConfig {
@@ -269,6 +558,17 @@ impl RPCServerConfig {
             rpc_keep_alive_timeout_ms: None,
             tcp_keep_alive_ms: None,
             tcp_nodelay: None,
+            auth_tokens: None,
+            audit: None,
+            tls: None,
+            runtime_config_file: None,
+            slow_query: None,
+            metrics_port: None,
+            index_advisor: None,
+            flight: None,
+            flight_peers: None,
+            metrics_peers: None,
+            http_port: None,
         }
     }
 
@@ -280,6 +580,8 @@ impl RPCServerConfig {
 pub struct RPCJobServer<S: pb::job_service_server::JobService> {
     service: S,
     rpc_config: RPCServerConfig,
+    #[cfg(feature = "flight")]
+    flight: Option<crate::flight::FlightJobService<S>>,
 }
 
 /// start both rpc server and pegasus server
@@ -293,7 +595,11 @@ where
     E: ServiceStartListener,
 {
     if server_config.enable_tracing.unwrap_or(false) {
-        let _tracer = init_tracer().expect("Failed to initialize tracer.");
+        let endpoint = server_config
+            .tracing_endpoint
+            .as_deref()
+            .unwrap_or(DEFAULT_TRACING_ENDPOINT);
+        let _tracer = init_tracer(endpoint).expect("Failed to initialize tracer.");
     }
     let server_id = server_config.server_id();
     if let Some(server_addr) = pegasus::startup_with(server_config, server_detector)? {
@@ -312,21 +618,123 @@ where
     P: JobAssembly<I>,
     E: ServiceStartListener,
 {
-    let service = JobServiceImpl { inner: Arc::new(assemble), report: true };
-    let server = RPCJobServer::new(rpc_config, service);
+    let authenticator = rpc_config
+        .auth_tokens
+        .clone()
+        .map(|tokens| Arc::new(TokenAuthenticator::new(tokens)) as Arc<dyn Authenticator>);
+    if let Some(port) = rpc_config.metrics_port {
+        let host = rpc_config
+            .rpc_host
+            .clone()
+            .unwrap_or_else(|| "0.0.0.0".to_owned());
+        let addr = format!("{}:{}", host, port).parse::<SocketAddr>()?;
+        let peers = rpc_config
+            .metrics_peers
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|(id, _)| *id as u64 != server_id)
+            .map(|(_, uri)| uri.clone())
+            .collect();
+        let admin = crate::metrics::AdminAccess { authenticator: authenticator.clone(), peers };
+        crate::metrics::start_metrics_server(addr, admin)?;
+    }
+    let assemble = Arc::new(assemble);
+    let mut audit_sink = rpc_config
+        .audit
+        .as_ref()
+        .map(|audit| audit.build_sink())
+        .transpose()?
+        .flatten();
+    if let Some(config) = rpc_config.index_advisor.clone() {
+        let statistics = {
+            let assemble = assemble.clone();
+            move |label: &str, edge: bool| assemble.count_label(label, edge)
+        };
+        let advisor: Arc<dyn AuditSink> = advisor::start_advisor(config, Box::new(statistics))?;
+        audit_sink = match audit_sink {
+            Some(sink) => Some(Arc::new(MultiAuditSink(vec![sink, advisor]))),
+            None => Some(advisor),
+        };
+    }
+    let mut service = JobServiceImpl::new(assemble, authenticator);
+    if let Some(audit_sink) = audit_sink {
+        service = service.with_audit_sink(audit_sink);
+    }
+    if let Some(path) = rpc_config.runtime_config_file.as_ref() {
+        crate::runtime_config::watch(path)?;
+    }
+    if let Some(slow_query) = rpc_config.slow_query.as_ref() {
+        service = service.with_slow_query_log(Arc::new(SlowQueryLog::open(slow_query)?));
+    }
+    if let Some(port) = rpc_config.http_port {
+        let host = rpc_config
+            .rpc_host
+            .clone()
+            .unwrap_or_else(|| "0.0.0.0".to_owned());
+        let addr = format!("{}:{}", host, port).parse::<SocketAddr>()?;
+        crate::http::start_http_server(addr, service.clone(), rpc_config.tls.as_ref())?;
+    }
+    #[cfg(feature = "flight")]
+    let flight = if rpc_config.flight.unwrap_or(false) {
+        let tls = rpc_config
+            .tls
+            .as_ref()
+            .map(|tls| tls.build_client())
+            .transpose()?;
+        let peers: Vec<String> = rpc_config
+            .flight_peers
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|(id, _)| *id as u64 != server_id)
+            .map(|(_, uri)| uri.clone())
+            .collect();
+        Some(crate::flight::FlightJobService::new(service.clone()).with_peers(&peers, tls)?)
+    } else {
+        None
+    };
+    #[cfg(not(feature = "flight"))]
+    if rpc_config.flight.unwrap_or(false) {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "arrow flight requires the `flight` feature",
+        ))?
+    }
+    #[allow(unused_mut)]
+    let mut server = RPCJobServer::new(rpc_config, service);
+    #[cfg(feature = "flight")]
+    if let Some(flight) = flight {
+        server = server.with_flight(flight);
+    }
     server.run(server_id, listener).await?;
     Ok(())
 }
 
 impl<S: pb::job_service_server::JobService> RPCJobServer<S> {
     pub fn new(rpc_config: RPCServerConfig, service: S) -> Self {
-        RPCJobServer { service, rpc_config }
+        RPCJobServer {
+            service,
+            rpc_config,
+            #[cfg(feature = "flight")]
+            flight: None,
+        }
+    }
+
+    /// Serve the Arrow Flight service along with the job service.
+    #[cfg(feature = "flight")]
+    pub fn with_flight(mut self, flight: crate::flight::FlightJobService<S>) -> Self {
+        self.flight = Some(flight);
+        self
     }
 
     pub async fn run<E>(self, server_id: u64, mut listener: E) -> Result<(), Box<dyn std::error::Error>>
     where
         E: ServiceStartListener,
     {
+        #[cfg(feature = "flight")]
+        let RPCJobServer { service, mut rpc_config, flight } = self;
+        #[cfg(not(feature = "flight"))]
         let RPCJobServer { service, mut rpc_config } = self;
         let mut builder = Server::builder();
         if let Some(limit) = rpc_config.rpc_concurrency_limit_per_connection {
@@ -357,7 +765,15 @@ impl<S: pb::job_service_server::JobService> RPCJobServer<S> {
             builder = builder.http2_keepalive_timeout(Some(Duration::from_millis(dur)));
         }
 
+        if let Some(tls) = rpc_config.tls.as_ref() {
+            builder = builder.tls_config(tls.build()?)?;
+        }
+
         let service = builder.add_service(pb::job_service_server::JobServiceServer::new(service));
+        #[cfg(feature = "flight")]
+        let service = service.add_optional_service(
+            flight.map(arrow_flight::flight_service_server::FlightServiceServer::new),
+        );
 
         let rpc_host = rpc_config
             .rpc_host
@@ -378,14 +794,18 @@ impl<S: pb::job_service_server::JobService> RPCJobServer<S> {
     }
 }
 
-fn init_tracer() -> Result<opentelemetry_sdk::trace::Tracer, opentelemetry::trace::TraceError> {
+const DEFAULT_TRACING_ENDPOINT: &str = "http://localhost:4317";
+
+fn init_tracer(
+    endpoint: &str,
+) -> Result<opentelemetry_sdk::trace::Tracer, opentelemetry::trace::TraceError> {
     global::set_text_map_propagator(TraceContextPropagator::new());
     opentelemetry_otlp::new_pipeline()
         .tracing()
         .with_exporter(
             opentelemetry_otlp::new_exporter()
                 .tonic()
-                .with_endpoint("http://localhost:4317"),
+                .with_endpoint(endpoint),
         )
         .with_trace_config(opentelemetry_sdk::trace::config().with_resource(
             opentelemetry_sdk::Resource::new(vec![KeyValue::new("service.name", "pegasus")]),
@@ -440,6 +860,44 @@ impl Stream for TcpIncoming {
     }
 }
 
+/// Attach the code of the error to the status in the metadata of `error-code`, `error-category` and
+/// `error-retryable`, so that the clients can tell the errors of the queries from the failures of the
+/// system without parsing the message.
+fn with_error_code(mut status: Status, code: ErrorCode) -> Status {
+    let metadata = status.metadata_mut();
+    let mut insert = |key: &'static str, value: String| {
+        if let Ok(value) = value.parse() {
+            metadata.insert(key, value);
+        }
+    };
+    insert("error-code", code.code.to_string());
+    insert("error-category", code.category.to_string());
+    insert("error-retryable", code.retryable.to_string());
+    status
+}
+
+/// The status of an invalid plan, of which the step, the operator and the argument are also given in the
+/// metadata of `invalid-step`, `invalid-operator` and `invalid-argument` for the clients.
+fn invalid_plan_status(job_id: u64, invalid: InvalidPlan) -> Status {
+    let mut metadata = tonic::metadata::MetadataMap::new();
+    let mut insert = |key: &'static str, value: &str| {
+        if let Ok(value) = value.parse() {
+            metadata.insert(key, value);
+        }
+    };
+    insert("invalid-step", &invalid.step);
+    insert("invalid-operator", &invalid.operator);
+    if let Some(argument) = invalid.argument.as_ref() {
+        insert("invalid-argument", argument);
+    }
+    let status = Status::with_metadata(
+        Code::InvalidArgument,
+        format!("job {} has an invalid plan: {}", job_id, invalid),
+        metadata,
+    );
+    with_error_code(status, InvalidPlan::CODE)
+}
+
 fn parse_conf_req(mut req: pb::JobConfig) -> JobConf {
     let mut conf = JobConf::new(req.job_name);
     if req.job_id != 0 {
@@ -462,11 +920,19 @@ fn parse_conf_req(mut req: pb::JobConfig) -> JobConf {
         conf.batch_capacity = req.batch_capacity;
     }
 
+    crate::runtime_config::current()
+        .job
+        .apply(&req, &mut conf);
+
     if req.trace_enable {
         conf.trace_enable = true;
         conf.plan_print = true;
     }
 
+    if req.deterministic {
+        conf.deterministic = true;
+    }
+
     if let Some(servers) = req.servers.take() {
         match servers {
             Servers::Local(_) => conf.reset_servers(ServerConf::Local),
@@ -0,0 +1,217 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Runtime configuration reloaded from a file without restarting the server, which is applied to the
+//! jobs submitted after it's reloaded. Components with their own settings, e.g. the sizes of caches,
+//! read their sections of the file by `RuntimeConfig::section`, and are notified of the changes by
+//! listeners registered with `add_listener`.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use pegasus::JobConf;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::admission::AdmissionPolicy;
+use crate::scheduling::SchedulingConfig;
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The defaults of the jobs which don't specify them in their requests.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct JobDefaults {
+    /// Workers per server.
+    pub workers: Option<u32>,
+    /// The most milliseconds a job can run.
+    pub time_limit_ms: Option<u64>,
+    pub batch_size: Option<u32>,
+    pub batch_capacity: Option<u32>,
+    /// The most memory(MB) a job can use in each server.
+    pub memory_limit_mb: Option<u32>,
+}
+
+impl JobDefaults {
+    /// Fill the defaults into the configuration of a job whose request leaves them unset, i.e. `0`.
+    pub fn apply(&self, req: &crate::pb::JobConfig, conf: &mut JobConf) {
+        if req.workers == 0 {
+            if let Some(workers) = self.workers {
+                conf.workers = workers;
+            }
+        }
+        if req.time_limit == 0 {
+            if let Some(time_limit) = self.time_limit_ms {
+                conf.time_limit = time_limit;
+            }
+        }
+        if req.batch_size == 0 {
+            if let Some(batch_size) = self.batch_size {
+                conf.batch_size = batch_size;
+            }
+        }
+        if req.batch_capacity == 0 {
+            if let Some(batch_capacity) = self.batch_capacity {
+                conf.batch_capacity = batch_capacity;
+            }
+        }
+        if let Some(memory_limit) = self.memory_limit_mb {
+            conf.memory_limit = memory_limit;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct RuntimeConfig {
+    #[serde(default)]
+    pub job: JobDefaults,
+    /// How the jobs with unbounded scans are admitted.
+    #[serde(default)]
+    pub admission: AdmissionPolicy,
+    /// The limits of the scheduling classes.
+    #[serde(default)]
+    pub scheduling: SchedulingConfig,
+    /// The sections of other components.
+    #[serde(flatten)]
+    pub sections: HashMap<String, toml::Value>,
+}
+
+impl RuntimeConfig {
+    pub fn parse(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// Deserialize a section of other components, `None` if the section is absent.
+    pub fn section<T: DeserializeOwned>(&self, name: &str) -> Option<Result<T, toml::de::Error>> {
+        self.sections
+            .get(name)
+            .map(|value| value.clone().try_into())
+    }
+}
+
+type Listener = Box<dyn Fn(&RuntimeConfig) + Send + Sync>;
+
+static CURRENT: RwLock<Option<Arc<RuntimeConfig>>> = RwLock::new(None);
+static LISTENERS: RwLock<Vec<Listener>> = RwLock::new(Vec::new());
+
+/// The runtime configuration in effect.
+pub fn current() -> Arc<RuntimeConfig> {
+    CURRENT
+        .read()
+        .expect("runtime config poisoned")
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Be notified of the runtime configuration once it's updated, and the one in effect right now.
+pub fn add_listener<F: Fn(&RuntimeConfig) + Send + Sync + 'static>(listener: F) {
+    listener(&current());
+    LISTENERS
+        .write()
+        .expect("runtime config listeners poisoned")
+        .push(Box::new(listener));
+}
+
+pub fn update(config: RuntimeConfig) {
+    let config = Arc::new(config);
+    *CURRENT
+        .write()
+        .expect("runtime config poisoned") = Some(config.clone());
+    for listener in LISTENERS
+        .read()
+        .expect("runtime config listeners poisoned")
+        .iter()
+    {
+        listener(&config);
+    }
+}
+
+/// Load the runtime configuration from the file, and reload it in background whenever the file is
+/// modified; a file failing to parse is ignored, with the configuration in effect kept.
+pub fn watch(path: &str) -> std::io::Result<()> {
+    let path = PathBuf::from(path);
+    let mut modified = reload(&path)?;
+    std::thread::Builder::new()
+        .name("runtime-config-watcher".to_owned())
+        .spawn(move || loop {
+            std::thread::sleep(WATCH_INTERVAL);
+            match std::fs::metadata(&path).and_then(|m| m.modified()) {
+                Ok(time) if time != modified => match reload(&path) {
+                    Ok(time) => modified = time,
+                    Err(e) => {
+                        error!("reload runtime config from {:?} failure: {}", path, e);
+                        modified = time;
+                    }
+                },
+                Ok(_) => (),
+                Err(e) => warn!("check runtime config {:?} failure: {}", path, e),
+            }
+        })?;
+    Ok(())
+}
+
+fn reload(path: &PathBuf) -> std::io::Result<SystemTime> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    let content = std::fs::read_to_string(path)?;
+    let config = RuntimeConfig::parse(&content)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    if *current() != config {
+        info!("runtime config reloaded from {:?}: {:?}", path, config);
+        update(config);
+    }
+    Ok(modified)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct CacheConfig {
+        capacity: usize,
+    }
+
+    #[test]
+    fn runtime_config_test() {
+        let config = RuntimeConfig::parse(
+            r#"
+            [job]
+            workers = 4
+            time_limit_ms = 60000
+
+            [cache]
+            capacity = 1024
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.job.workers, Some(4));
+        assert_eq!(config.job.batch_size, None);
+        let cache: CacheConfig = config.section("cache").unwrap().unwrap();
+        assert_eq!(cache, CacheConfig { capacity: 1024 });
+        assert!(config
+            .section::<CacheConfig>("absent")
+            .is_none());
+
+        let req = crate::pb::JobConfig { workers: 2, ..Default::default() };
+        let mut conf = JobConf::new("test");
+        conf.workers = req.workers;
+        config.job.apply(&req, &mut conf);
+        // the workers specified by the request are kept;
+        assert_eq!(conf.workers, 2);
+        assert_eq!(conf.time_limit, 60000);
+        assert_eq!(conf.batch_size, 1024);
+    }
+}
@@ -0,0 +1,248 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Scheduling classes of the jobs, from the `scheduling-class` metadata of the requests: `interactive`
+//! by default, `batch` or `background`. Each class is limited by the `[scheduling.<class>]` section of
+//! the runtime configuration in:
+//! * `max_concurrent_jobs`, the jobs of the class running at the same time in this server;
+//! * `worker_share`, the share of the executor threads the workers of its running jobs may occupy,
+//!   e.g. `0.25` of 16 threads for at most 4 workers, with a job of more workers capped to the share;
+//!   the servers of a cluster have to run the same number of threads to cap the jobs alike.
+//!
+//! A job over the limits of its class waits until the jobs of the class before it finish, and is
+//! rejected if it waits longer than `max_wait_ms`; so that the long analytics in the `batch` or
+//! `background` classes can't occupy all of the threads the `interactive` lookups need.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use pegasus::JobConf;
+use serde::Deserialize;
+
+const DEFAULT_MAX_WAIT_MS: u64 = 30000;
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchedulingClass {
+    Interactive,
+    Batch,
+    Background,
+}
+
+impl SchedulingClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SchedulingClass::Interactive => "interactive",
+            SchedulingClass::Batch => "batch",
+            SchedulingClass::Background => "background",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl Default for SchedulingClass {
+    fn default() -> Self {
+        SchedulingClass::Interactive
+    }
+}
+
+impl fmt::Display for SchedulingClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SchedulingClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "interactive" => Ok(SchedulingClass::Interactive),
+            "batch" => Ok(SchedulingClass::Batch),
+            "background" => Ok(SchedulingClass::Background),
+            _ => Err(format!("unknown scheduling class `{}`", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct ClassLimits {
+    /// Unlimited if not set.
+    pub max_concurrent_jobs: Option<usize>,
+    /// In `(0, 1]`, unlimited if not set.
+    pub worker_share: Option<f64>,
+    /// The most milliseconds a job waits for the limits, 30000 by default.
+    pub max_wait_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct SchedulingConfig {
+    #[serde(default)]
+    pub interactive: ClassLimits,
+    #[serde(default)]
+    pub batch: ClassLimits,
+    #[serde(default)]
+    pub background: ClassLimits,
+}
+
+impl SchedulingConfig {
+    pub fn limits(&self, class: SchedulingClass) -> &ClassLimits {
+        match class {
+            SchedulingClass::Interactive => &self.interactive,
+            SchedulingClass::Batch => &self.batch,
+            SchedulingClass::Background => &self.background,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ClassUsage {
+    jobs: usize,
+    workers: usize,
+}
+
+const IDLE: ClassUsage = ClassUsage { jobs: 0, workers: 0 };
+
+/// The usages of the classes by their running jobs, indexed by the classes.
+static USAGES: Mutex<[ClassUsage; 3]> = Mutex::new([IDLE; 3]);
+
+/// A job running in its class, the class is used by the job until it's dropped.
+pub struct ClassPermit {
+    class: SchedulingClass,
+    workers: usize,
+}
+
+impl Drop for ClassPermit {
+    fn drop(&mut self) {
+        let mut usages = USAGES.lock().unwrap();
+        let usage = &mut usages[self.class.index()];
+        usage.jobs -= 1;
+        usage.workers -= self.workers;
+    }
+}
+
+/// Try to run a job of `workers` in the class, or `None` if it's over the limits.
+fn try_acquire(
+    class: SchedulingClass, workers: usize, max_jobs: usize, max_workers: usize,
+) -> Option<ClassPermit> {
+    let mut usages = USAGES.lock().unwrap();
+    let usage = &mut usages[class.index()];
+    if usage.jobs < max_jobs && usage.workers + workers <= max_workers {
+        usage.jobs += 1;
+        usage.workers += workers;
+        Some(ClassPermit { class, workers })
+    } else {
+        None
+    }
+}
+
+/// Wait until a job can run in its class by the limits, where the workers of the job may be capped to
+/// the share of the class; returns the reason if it's rejected.
+pub async fn acquire(
+    class: SchedulingClass, limits: &ClassLimits, conf: &mut JobConf,
+) -> Result<ClassPermit, String> {
+    let max_jobs = limits.max_concurrent_jobs.unwrap_or(usize::MAX);
+    if max_jobs == 0 {
+        return Err(format!("no job is allowed in the {} class", class));
+    }
+    let max_workers = match limits.worker_share {
+        Some(share) => {
+            let threads = pegasus::executor_threads().max(1);
+            let max_workers = ((threads as f64 * share.min(1.0)) as usize).max(1);
+            if conf.workers as usize > max_workers {
+                info!("job {} capped to {} workers of the {} class", conf.job_id, max_workers, class);
+                conf.workers = max_workers as u32;
+            }
+            max_workers
+        }
+        None => usize::MAX,
+    };
+    let workers = conf.workers as usize;
+    let max_wait = Duration::from_millis(
+        limits
+            .max_wait_ms
+            .unwrap_or(DEFAULT_MAX_WAIT_MS),
+    );
+    let start = Instant::now();
+    loop {
+        if let Some(permit) = try_acquire(class, workers, max_jobs, max_workers) {
+            return Ok(permit);
+        }
+        if start.elapsed() >= max_wait {
+            return Err(format!("the {} class is busy after waiting {:?}", class, max_wait));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_class_test() {
+        assert_eq!("batch".parse::<SchedulingClass>(), Ok(SchedulingClass::Batch));
+        assert!("realtime".parse::<SchedulingClass>().is_err());
+        let config: SchedulingConfig = toml::from_str(
+            r#"
+            [background]
+            max_concurrent_jobs = 1
+            worker_share = 0.25
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config
+                .limits(SchedulingClass::Background)
+                .max_concurrent_jobs,
+            Some(1)
+        );
+        assert_eq!(config.limits(SchedulingClass::Interactive), &ClassLimits::default());
+    }
+
+    #[tokio::test]
+    async fn acquire_test() {
+        let limits =
+            ClassLimits { max_concurrent_jobs: Some(1), worker_share: None, max_wait_ms: Some(50) };
+        let mut conf = JobConf::new("first");
+        let first = acquire(SchedulingClass::Batch, &limits, &mut conf)
+            .await
+            .unwrap();
+        // over the limit of the batch class, but not of the others
+        assert!(acquire(SchedulingClass::Batch, &limits, &mut conf)
+            .await
+            .is_err());
+        let other = acquire(SchedulingClass::Background, &limits, &mut conf)
+            .await
+            .unwrap();
+        drop(first);
+        assert!(acquire(SchedulingClass::Batch, &limits, &mut conf)
+            .await
+            .is_ok());
+        drop(other);
+
+        let limits = ClassLimits { worker_share: Some(0.000001), ..Default::default() };
+        conf.workers = 4;
+        let _capped = acquire(SchedulingClass::Interactive, &limits, &mut conf)
+            .await
+            .unwrap();
+        assert_eq!(conf.workers, 1);
+    }
+}
@@ -0,0 +1,272 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Slow query log: the jobs running longer than a threshold are logged with their plans and the
+//! profiles of their operators on this server, i.e. timings, records consumed, records read from the
+//! store and the skew of records among the workers. Records are written as json lines to a file
+//! rotated by size.
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use pegasus::profile::OperatorProfile;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::PlanSummary;
+
+const DEFAULT_MAX_FILE_MB: u64 = 64;
+const DEFAULT_MAX_FILES: usize = 5;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SlowQueryConfig {
+    /// Jobs running for at least `threshold_ms` are logged.
+    pub threshold_ms: u64,
+    /// The file to append the records to.
+    pub file: String,
+    /// Rotate the file once it grows beyond the size, 64MB by default.
+    pub max_file_mb: Option<u64>,
+    /// The number of rotated files kept as `file.1`, `file.2`..., 5 by default.
+    pub max_files: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct OperatorSummary {
+    pub index: usize,
+    pub name: String,
+    pub workers: usize,
+    pub fire_times: u64,
+    /// Time used by the operator on all workers.
+    pub used_us: u64,
+    /// Time used by the operator on the slowest worker.
+    pub max_used_us: u64,
+    pub records: u64,
+    pub store_reads: u64,
+    /// The records consumed by the busiest worker over the average, 1.0 if the records are even.
+    pub skew: f64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SlowQueryRecord {
+    pub job_id: u64,
+    pub job_name: String,
+    pub user: Option<String>,
+    pub latency_ms: u64,
+    #[serde(flatten)]
+    pub summary: PlanSummary,
+    pub plan: Option<String>,
+    pub operators: Vec<OperatorSummary>,
+}
+
+/// Merge the profiles of an operator on all workers.
+pub fn summarize(profiles: Vec<OperatorProfile>) -> Vec<OperatorSummary> {
+    let mut operators: BTreeMap<usize, (OperatorSummary, u64)> = BTreeMap::new();
+    for profile in profiles {
+        let (op, max_records) = operators
+            .entry(profile.index)
+            .or_insert_with(|| (OperatorSummary { index: profile.index, ..Default::default() }, 0));
+        op.name = profile.name;
+        op.workers += 1;
+        op.fire_times += profile.fire_times;
+        op.used_us += profile.used_us;
+        op.max_used_us = op.max_used_us.max(profile.used_us);
+        op.records += profile.records;
+        op.store_reads += profile.store_reads;
+        *max_records = (*max_records).max(profile.records);
+    }
+    operators
+        .into_iter()
+        .map(|(_, (mut op, max_records))| {
+            op.skew = if op.records == 0 {
+                1.0
+            } else {
+                max_records as f64 * op.workers as f64 / op.records as f64
+            };
+            op
+        })
+        .collect()
+}
+
+/// A file rotated once it grows beyond `max_bytes`, the rotated files are renamed to `path.1`,
+/// `path.2`... with the oldest one removed.
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    writer: BufWriter<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &str, max_bytes: u64, max_files: usize) -> std::io::Result<Self> {
+        let path = PathBuf::from(path);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile { path, max_bytes, max_files, writer: BufWriter::new(file), size })
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.writer.flush()?;
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated(index);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated(1))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.writer = BufWriter::new(file);
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 + 1 > self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+}
+
+pub struct SlowQueryLog {
+    threshold: Duration,
+    file: Mutex<RotatingFile>,
+}
+
+impl SlowQueryLog {
+    pub fn open(config: &SlowQueryConfig) -> std::io::Result<Self> {
+        let max_bytes = config
+            .max_file_mb
+            .unwrap_or(DEFAULT_MAX_FILE_MB)
+            << 20;
+        let max_files = config.max_files.unwrap_or(DEFAULT_MAX_FILES);
+        let file = RotatingFile::open(&config.file, max_bytes, max_files)?;
+        Ok(SlowQueryLog { threshold: Duration::from_millis(config.threshold_ms), file: Mutex::new(file) })
+    }
+
+    pub fn write(&self, record: &SlowQueryRecord) {
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(e) => return error!("serialize slow query of job {} failure: {}", record.job_id, e),
+        };
+        let mut file = self
+            .file
+            .lock()
+            .expect("slow query log poisoned");
+        if let Err(e) = file.write_line(&line) {
+            error!("write slow query of job {} failure: {}", record.job_id, e);
+        }
+    }
+
+    /// Track a job to be started, the profiles of its operators are collected since then.
+    pub fn track(self: &Arc<Self>, record: SlowQueryRecord) -> SlowQueryTracker {
+        pegasus::profile::enable(record.job_id);
+        SlowQueryTracker { log: self.clone(), record, start: Instant::now() }
+    }
+}
+
+/// Track a running job, and log it once the tracker is dropped by all of its holders if it runs
+/// longer than the threshold.
+pub struct SlowQueryTracker {
+    log: Arc<SlowQueryLog>,
+    record: SlowQueryRecord,
+    start: Instant,
+}
+
+impl Drop for SlowQueryTracker {
+    fn drop(&mut self) {
+        let profiles = pegasus::profile::take(self.record.job_id).unwrap_or_default();
+        let elapsed = self.start.elapsed();
+        if elapsed >= self.log.threshold {
+            self.record.latency_ms = elapsed.as_millis() as u64;
+            self.record.operators = summarize(profiles);
+            self.log.write(&self.record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn profile(index: usize, worker: u32, used_us: u64, records: u64) -> OperatorProfile {
+        OperatorProfile {
+            index,
+            name: format!("op_{}", index),
+            worker,
+            used_us,
+            records,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn summarize_test() {
+        let operators = summarize(vec![
+            profile(2, 0, 10, 30),
+            profile(1, 0, 5, 0),
+            profile(2, 1, 20, 10),
+            profile(1, 1, 5, 0),
+        ]);
+        assert_eq!(operators.len(), 2);
+        assert_eq!(operators[0].index, 1);
+        assert_eq!(operators[0].skew, 1.0);
+        assert_eq!(operators[1].name, "op_2");
+        assert_eq!(operators[1].workers, 2);
+        assert_eq!(operators[1].used_us, 30);
+        assert_eq!(operators[1].max_used_us, 20);
+        assert_eq!(operators[1].records, 40);
+        assert_eq!(operators[1].skew, 1.5);
+    }
+
+    #[test]
+    fn rotating_file_test() {
+        let dir = std::env::temp_dir().join(format!("slow_query_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("slow.log");
+        let mut file = RotatingFile::open(path.to_str().unwrap(), 16, 2).unwrap();
+        for i in 0..4 {
+            file.write_line(&format!("record-{:05}", i))
+                .unwrap();
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "record-00003\n");
+        assert_eq!(std::fs::read_to_string(file.rotated(1)).unwrap(), "record-00002\n");
+        assert_eq!(std::fs::read_to_string(file.rotated(2)).unwrap(), "record-00001\n");
+        assert!(!file.rotated(3).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
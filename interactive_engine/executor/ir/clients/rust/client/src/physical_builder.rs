@@ -244,12 +244,20 @@ impl PlanBuilder {
         self.join(join_kind, left_plan, right_plan, left_keys, right_keys)
     }
 
-    pub fn union(&mut self, mut plans: Vec<PlanBuilder>) -> &mut Self {
+    pub fn union(&mut self, plans: Vec<PlanBuilder>) -> &mut Self {
+        self.union_with_branch(plans, None)
+    }
+
+    /// Union the sub-plans, tagging each output record with the index of its sub-plan if `branch_alias`.
+    pub fn union_with_branch(
+        &mut self, mut plans: Vec<PlanBuilder>, branch_alias: Option<common_pb::NameOrId>,
+    ) -> &mut Self {
+        let branch_alias = branch_alias.map(|alias| alias.try_into().unwrap());
         let mut sub_plans = vec![];
         for plan in plans.drain(..) {
             sub_plans.push(pb::PhysicalPlan { plan: plan.take(), plan_id: DEFAULT_PLAN_ID });
         }
-        let union = pb::Union { sub_plans };
+        let union = pb::Union { sub_plans, branch_alias };
         let op = pb::physical_opr::operator::OpKind::Union(union);
         self.plan.push(op.into());
         self
@@ -312,11 +320,102 @@ impl PlanBuilder {
         self
     }
 
+    pub fn k_hop(&mut self, k_hop: algebra_pb::KHop) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::KHop(k_hop);
+        self.plan.push(op.into());
+        self
+    }
+
+    pub fn merge(&mut self, merge: algebra_pb::Merge) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::Merge(merge);
+        self.plan.push(op.into());
+        self
+    }
+
+    pub fn mutate(&mut self, mutate: algebra_pb::Mutate) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::Mutate(mutate);
+        self.plan.push(op.into());
+        self
+    }
+
+    pub fn store_var(&mut self, store_var: algebra_pb::StoreVar) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::StoreVar(store_var);
+        self.plan.push(op.into());
+        self
+    }
+
+    pub fn load_var(&mut self, load_var: algebra_pb::LoadVar) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::LoadVar(load_var);
+        self.plan.push(op.into());
+        self
+    }
+
+    pub fn call(&mut self, call: algebra_pb::Call) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::Call(call);
+        self.plan.push(op.into());
+        self
+    }
+
+    pub fn side_effect(&mut self, side_effect: algebra_pb::SideEffect) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::SideEffect(side_effect);
+        self.plan.push(op.into());
+        self
+    }
+
+    pub fn cap(&mut self, cap: algebra_pb::Cap) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::Cap(cap);
+        self.plan.push(op.into());
+        self
+    }
+
+    pub fn sack(&mut self, sack: algebra_pb::Sack) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::Sack(sack);
+        self.plan.push(op.into());
+        self
+    }
+
+    pub fn tree(&mut self, tree: algebra_pb::Tree) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::Tree(tree);
+        self.plan.push(op.into());
+        self
+    }
+
+    /// Match the pattern, whose triples are matched in the order chosen when the plan is assembled.
+    pub fn pattern_match(&mut self, pattern: pb::PatternMatch) -> &mut Self {
+        let op = pb::physical_opr::operator::OpKind::PatternMatch(pattern);
+        self.plan.push(op.into());
+        self
+    }
+
+    /// Repeat the body, where the body of `repeat` is replaced by the given one.
+    pub fn repeat(&mut self, body: PlanBuilder, mut repeat: pb::Repeat) -> &mut Self {
+        repeat.body = Some(pb::PhysicalPlan { plan: body.take(), plan_id: DEFAULT_PLAN_ID });
+        let op = pb::physical_opr::operator::OpKind::Repeat(repeat);
+        self.plan.push(op.into());
+        self
+    }
+
+    pub fn coalesce(&mut self, mut plans: Vec<PlanBuilder>) -> &mut Self {
+        let mut sub_plans = vec![];
+        for plan in plans.drain(..) {
+            sub_plans.push(pb::PhysicalPlan { plan: plan.take(), plan_id: DEFAULT_PLAN_ID });
+        }
+        let coalesce = pb::Coalesce { sub_plans };
+        let op = pb::physical_opr::operator::OpKind::Coalesce(coalesce);
+        self.plan.push(op.into());
+        self
+    }
+
     pub fn sample(&mut self, sample: algebra_pb::Sample) {
         let op = pb::physical_opr::operator::OpKind::Sample(sample);
         self.plan.push(op.into());
     }
 
+    pub fn algorithm(&mut self, algorithm: algebra_pb::GraphAlgorithm) {
+        let op = pb::physical_opr::operator::OpKind::Algorithm(algorithm);
+        self.plan.push(op.into());
+    }
+
     pub fn sink(&mut self, sink: algebra_pb::Sink) {
         let op = pb::physical_opr::operator::OpKind::Sink(sink.into());
         self.plan.push(op.into());
@@ -495,6 +594,13 @@ impl JobBuilder {
         self
     }
 
+    pub fn union_with_branch(
+        &mut self, plans: Vec<PlanBuilder>, branch_alias: Option<common_pb::NameOrId>,
+    ) -> &mut Self {
+        self.plan.union_with_branch(plans, branch_alias);
+        self
+    }
+
     pub fn intersect(&mut self, plans: Vec<PlanBuilder>, key: common_pb::NameOrId) -> &mut Self {
         self.plan.intersect(plans, key);
         self
@@ -515,10 +621,79 @@ impl JobBuilder {
         self
     }
 
+    pub fn k_hop(&mut self, k_hop: algebra_pb::KHop) -> &mut Self {
+        self.plan.k_hop(k_hop);
+        self
+    }
+
+    pub fn merge(&mut self, merge: algebra_pb::Merge) -> &mut Self {
+        self.plan.merge(merge);
+        self
+    }
+
+    pub fn mutate(&mut self, mutate: algebra_pb::Mutate) -> &mut Self {
+        self.plan.mutate(mutate);
+        self
+    }
+
+    pub fn store_var(&mut self, store_var: algebra_pb::StoreVar) -> &mut Self {
+        self.plan.store_var(store_var);
+        self
+    }
+
+    pub fn load_var(&mut self, load_var: algebra_pb::LoadVar) -> &mut Self {
+        self.plan.load_var(load_var);
+        self
+    }
+
+    pub fn call(&mut self, call: algebra_pb::Call) -> &mut Self {
+        self.plan.call(call);
+        self
+    }
+
+    pub fn side_effect(&mut self, side_effect: algebra_pb::SideEffect) -> &mut Self {
+        self.plan.side_effect(side_effect);
+        self
+    }
+
+    pub fn cap(&mut self, cap: algebra_pb::Cap) -> &mut Self {
+        self.plan.cap(cap);
+        self
+    }
+
+    pub fn sack(&mut self, sack: algebra_pb::Sack) -> &mut Self {
+        self.plan.sack(sack);
+        self
+    }
+
+    pub fn tree(&mut self, tree: algebra_pb::Tree) -> &mut Self {
+        self.plan.tree(tree);
+        self
+    }
+
+    pub fn pattern_match(&mut self, pattern: pb::PatternMatch) -> &mut Self {
+        self.plan.pattern_match(pattern);
+        self
+    }
+
+    pub fn repeat(&mut self, body: PlanBuilder, repeat: pb::Repeat) -> &mut Self {
+        self.plan.repeat(body, repeat);
+        self
+    }
+
+    pub fn coalesce(&mut self, plans: Vec<PlanBuilder>) -> &mut Self {
+        self.plan.coalesce(plans);
+        self
+    }
+
     pub fn sample(&mut self, sample: algebra_pb::Sample) {
         self.plan.sample(sample);
     }
 
+    pub fn algorithm(&mut self, algorithm: algebra_pb::GraphAlgorithm) {
+        self.plan.algorithm(algorithm);
+    }
+
     pub fn sink(&mut self, sink: algebra_pb::Sink) {
         self.plan.sink(sink);
     }
@@ -546,6 +721,7 @@ impl JobBuilder {
                 }
                 ServerConf::All => Some(pegasus_pb::job_config::Servers::All(pegasus_pb::Empty {})),
             },
+            deterministic: self.conf.deterministic,
         };
 
         let plan = self.plan.build();
@@ -579,7 +755,12 @@ mod test {
             .add_scan_source(source_pb.clone())
             .select(algebra_pb::Select { predicate: None })
             .repartition(pb::Repartition { strategy: None })
-            .project(algebra_pb::Project { mappings: vec![], is_append: false, meta_data: vec![] })
+            .project(algebra_pb::Project {
+                mappings: vec![],
+                is_append: false,
+                meta_data: vec![],
+                select_tags: false,
+            })
             .limit(algebra_pb::Limit { range: None })
             .sink(sink_pb.clone());
         let plan_len = builder.plan.len();
@@ -599,7 +780,12 @@ mod test {
             meta_data: None,
         };
         let scan2_pb = scan1_pb.clone();
-        let project_pb = algebra_pb::Project { mappings: vec![], is_append: false, meta_data: vec![] };
+        let project_pb = algebra_pb::Project {
+            mappings: vec![],
+            is_append: false,
+            meta_data: vec![],
+            select_tags: false,
+        };
         let sink_pb = algebra_pb::Sink { tags: vec![], sink_target: None };
 
         builder
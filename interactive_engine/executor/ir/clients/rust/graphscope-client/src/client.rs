@@ -0,0 +1,169 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::convert::TryFrom;
+
+use futures::stream::BoxStream;
+use futures::{future, StreamExt};
+use ir_common::generated::algebra as pb;
+use ir_core::error::IrError;
+#[cfg(feature = "graphql")]
+use ir_core::plan::graphql::GraphQLSchema;
+use ir_core::plan::logical::LogicalPlan;
+#[cfg(feature = "graphql")]
+use ir_core::plan::meta::Schema;
+use ir_core::plan::physical::AsPhysical;
+use ir_physical_client::physical_builder::PlanBuilder;
+use pegasus::{JobConf, ServerConf};
+use pegasus_server::client::RPCJobClient;
+use pegasus_server::job::JobDesc;
+use prost::Message;
+use tonic::Code;
+
+use crate::error::ClientResult;
+use crate::result::Row;
+
+/// The rows of a query, streamed as they are produced by the service.
+pub type RowStream = BoxStream<'static, ClientResult<Row>>;
+
+/// A client submitting the plans of IR to the service and decoding the results into the typed rows.
+pub struct Client {
+    inner: RPCJobClient,
+    num_servers: usize,
+    conf: JobConf,
+    provenance: bool,
+    #[cfg(feature = "graphql")]
+    schema: Option<GraphQLSchema>,
+}
+
+impl Client {
+    /// Connect to a single server.
+    pub async fn connect(url: &str) -> ClientResult<Self> {
+        Client::connect_all(&[url]).await
+    }
+
+    /// Connect to all the servers of a cluster, where the `i`-th url is the address of server `i`.
+    pub async fn connect_all(urls: &[&str]) -> ClientResult<Self> {
+        let mut inner = RPCJobClient::new();
+        for (server_id, url) in urls.iter().enumerate() {
+            inner
+                .connect(server_id as u64, url.to_string())
+                .await?;
+        }
+        let mut conf = JobConf::new("graphscope_client");
+        if urls.len() > 1 {
+            conf.reset_servers(ServerConf::All);
+        }
+        Ok(Client {
+            inner,
+            num_servers: urls.len(),
+            conf,
+            provenance: false,
+            #[cfg(feature = "graphql")]
+            schema: None,
+        })
+    }
+
+    /// The config of the jobs submitted by the client, e.g., the number of workers per server.
+    pub fn with_conf(mut self, conf: JobConf) -> Self {
+        self.conf = conf;
+        self
+    }
+
+    /// Trace the steps deriving each row of the jobs submitted, which is carried by
+    /// [`Row::provenance`](crate::Row::provenance), for debugging the wrong results.
+    pub fn with_provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Submit a plan, which is sunk to the client as the `Results` if it does not end with a sink.
+    pub async fn submit(&mut self, mut plan: LogicalPlan) -> ClientResult<RowStream> {
+        let last_node = plan
+            .get_last_node()
+            .map(|node| node.borrow().get_id());
+        if let Some(last_node) = last_node {
+            let is_sink = matches!(
+                plan.get_opr(last_node).and_then(|opr| opr.opr),
+                Some(pb::logical_plan::operator::Opr::Sink(_))
+            );
+            if !is_sink {
+                let sink = pb::Sink {
+                    tags: vec![],
+                    sink_target: Some(pb::sink::SinkTarget {
+                        inner: Some(pb::sink::sink_target::Inner::SinkDefault(pb::SinkDefault {
+                            id_name_mappings: vec![],
+                            format: 0,
+                            columns: vec![],
+                        })),
+                    }),
+                };
+                plan.append_operator_as_node(sink.into(), vec![last_node])?;
+            }
+        }
+        let mut plan_meta = plan.get_plan_meta();
+        if self.conf.workers > 1 || self.num_servers > 1 {
+            plan_meta = plan_meta.with_partition();
+        }
+        let mut builder = PlanBuilder::new(0);
+        plan.add_job_builder(&mut builder, &mut plan_meta)?;
+        let job = JobDesc {
+            plan: builder.build().encode_to_vec(),
+            provenance: self.provenance,
+            ..Default::default()
+        };
+        let results = self
+            .inner
+            .submit(self.conf.clone(), job)
+            .await?;
+        let rows = results
+            // the end of the results
+            .take_while(|result| future::ready(!matches!(result, Err(status) if status.code() == Code::Ok)))
+            .map(|result| Row::decode(&result?));
+        Ok(rows.boxed())
+    }
+
+    /// Submit a plan in protobuf, e.g., built by the other languages.
+    pub async fn submit_pb(&mut self, plan: pb::LogicalPlan) -> ClientResult<RowStream> {
+        let plan = LogicalPlan::try_from(plan).map_err(IrError::from)?;
+        self.submit(plan).await
+    }
+
+    /// Set the schema of the graph, against which the GraphQL queries are resolved.
+    #[cfg(feature = "graphql")]
+    pub fn with_schema(mut self, schema: &Schema) -> Self {
+        self.schema = Some(GraphQLSchema::from(schema));
+        self
+    }
+
+    /// Run a GraphQL query, and get the rows of each root field of the query, in the order of the fields.
+    ///
+    /// Gremlin and Cypher are compiled by the frontend, whose plans are submitted by [`Client::submit_pb`].
+    #[cfg(feature = "graphql")]
+    pub async fn query(
+        &mut self, query: &str, variables: &serde_json::Map<String, serde_json::Value>,
+    ) -> ClientResult<Vec<(String, RowStream)>> {
+        let plans = self
+            .schema
+            .as_ref()
+            .ok_or_else(|| IrError::InvalidQuery("the schema of the graph is not set".to_string()))?
+            .resolve(query, variables)?;
+        let mut results = Vec::with_capacity(plans.len());
+        for plan in plans {
+            results.push((plan.field, self.submit(plan.plan).await?));
+        }
+        Ok(results)
+    }
+}
@@ -0,0 +1,84 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::fmt;
+
+use ir_core::error::IrError;
+use pegasus_server::client::JobError;
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// Fail to connect to the service
+    ConnectError(tonic::transport::Error),
+    /// Fail to build the physical plan of the query
+    PlanError(IrError),
+    /// The job is rejected before submitted, e.g., of an invalid config
+    JobError(JobError),
+    /// The job fails while running, e.g., rejected by the service or fails to execute
+    RpcError(tonic::Status),
+    /// Fail to decode the results
+    DecodeError(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::ConnectError(e) => write!(f, "connect error: {}", e),
+            ClientError::PlanError(e) => write!(f, "plan error: {}", e),
+            ClientError::JobError(e) => write!(f, "job error: {}", e),
+            ClientError::RpcError(status) => {
+                write!(f, "rpc error: {:?}, {}", status.code(), status.message())
+            }
+            ClientError::DecodeError(msg) => write!(f, "decode error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<tonic::transport::Error> for ClientError {
+    fn from(e: tonic::transport::Error) -> Self {
+        ClientError::ConnectError(e)
+    }
+}
+
+impl From<IrError> for ClientError {
+    fn from(e: IrError) -> Self {
+        ClientError::PlanError(e)
+    }
+}
+
+impl From<JobError> for ClientError {
+    fn from(e: JobError) -> Self {
+        match e {
+            JobError::RPCError(status) => ClientError::RpcError(status),
+            e => ClientError::JobError(e),
+        }
+    }
+}
+
+impl From<tonic::Status> for ClientError {
+    fn from(status: tonic::Status) -> Self {
+        ClientError::RpcError(status)
+    }
+}
+
+impl From<prost::DecodeError> for ClientError {
+    fn from(e: prost::DecodeError) -> Self {
+        ClientError::DecodeError(e.to_string())
+    }
+}
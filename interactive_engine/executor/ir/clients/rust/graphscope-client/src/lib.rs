@@ -0,0 +1,32 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The Rust SDK of GraphScope, for the services embedding the engine, e.g.:
+//! * connect to the service and submit a `LogicalPlan` of IR, or one in protobuf built by the other
+//!   languages, which is sunk to the client in the `Results` by default;
+//! * get the results streamed back as the typed `Row`s of vertices, edges, paths and values, which are
+//!   serializable by serde with the `serde` feature;
+//! * run a GraphQL query resolved against the schema of the graph with the `graphql` feature. Gremlin
+//!   and Cypher are compiled into the plans by the frontend instead.
+
+pub mod client;
+pub mod error;
+pub mod result;
+
+pub use client::{Client, RowStream};
+pub use error::{ClientError, ClientResult};
+pub use result::{
+    Edge, Element, Entry, NameOrId, PathStep, Provenance, ProvenanceStep, Row, Value, Vertex,
+};
@@ -0,0 +1,567 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The results of the queries decoded from the `Results` of the result.proto.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use ir_common::generated::common as common_pb;
+use ir_common::generated::results as result_pb;
+use prost::Message;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ClientError, ClientResult};
+
+/// The name of a label, a property or a column, or its id if it is not mapped to a name.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(untagged))]
+pub enum NameOrId {
+    Name(String),
+    Id(i32),
+}
+
+impl From<common_pb::NameOrId> for NameOrId {
+    fn from(name_or_id: common_pb::NameOrId) -> Self {
+        match name_or_id.item {
+            Some(common_pb::name_or_id::Item::Name(name)) => NameOrId::Name(name),
+            Some(common_pb::name_or_id::Item::Id(id)) => NameOrId::Id(id),
+            None => NameOrId::Name(String::new()),
+        }
+    }
+}
+
+/// A primitive value or a collection of values.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Int32(i32),
+    Int64(i64),
+    Double(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    /// The days since 1970-01-01.
+    Date(i32),
+    /// The milliseconds since the midnight.
+    Time(i32),
+    /// The milliseconds since 1970-01-01 00:00:00.000 in UTC.
+    Timestamp(i64),
+    List(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+}
+
+impl From<common_pb::Value> for Value {
+    fn from(value: common_pb::Value) -> Self {
+        use common_pb::value::Item;
+
+        match value.item {
+            Some(Item::Boolean(b)) => Value::Boolean(b),
+            Some(Item::I32(i)) => Value::Int32(i),
+            Some(Item::I64(i)) => Value::Int64(i),
+            Some(Item::F64(f)) => Value::Double(f),
+            Some(Item::Str(s)) => Value::String(s),
+            Some(Item::Blob(b)) => Value::Bytes(b),
+            Some(Item::I32Array(array)) => Value::List(
+                array
+                    .item
+                    .into_iter()
+                    .map(Value::Int32)
+                    .collect(),
+            ),
+            Some(Item::I64Array(array)) => Value::List(
+                array
+                    .item
+                    .into_iter()
+                    .map(Value::Int64)
+                    .collect(),
+            ),
+            Some(Item::F64Array(array)) => Value::List(
+                array
+                    .item
+                    .into_iter()
+                    .map(Value::Double)
+                    .collect(),
+            ),
+            Some(Item::StrArray(array)) => Value::List(
+                array
+                    .item
+                    .into_iter()
+                    .map(Value::String)
+                    .collect(),
+            ),
+            Some(Item::PairArray(array)) => Value::Map(
+                array
+                    .item
+                    .into_iter()
+                    .map(|pair| (pair.key.map(Value::from), pair.val.map(Value::from)))
+                    .map(|(key, val)| (key.unwrap_or(Value::Null), val.unwrap_or(Value::Null)))
+                    .collect(),
+            ),
+            Some(Item::Date(date)) => Value::Date(date.item),
+            Some(Item::Time(time)) => Value::Time(time.item),
+            Some(Item::Timestamp(timestamp)) => Value::Timestamp(timestamp.item),
+            Some(Item::None(_)) | None => Value::Null,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Vertex {
+    pub id: i64,
+    pub label: Option<NameOrId>,
+    pub properties: BTreeMap<NameOrId, Value>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Edge {
+    pub id: i64,
+    pub label: Option<NameOrId>,
+    pub src_id: i64,
+    pub src_label: Option<NameOrId>,
+    pub dst_id: i64,
+    pub dst_label: Option<NameOrId>,
+    pub properties: BTreeMap<NameOrId, Value>,
+}
+
+fn properties_from(properties: Vec<result_pb::Property>) -> BTreeMap<NameOrId, Value> {
+    properties
+        .into_iter()
+        .filter_map(|property| {
+            property.key.map(|key| {
+                (
+                    key.into(),
+                    property
+                        .value
+                        .map(Value::from)
+                        .unwrap_or(Value::Null),
+                )
+            })
+        })
+        .collect()
+}
+
+impl From<result_pb::Vertex> for Vertex {
+    fn from(vertex: result_pb::Vertex) -> Self {
+        Vertex {
+            id: vertex.id,
+            label: vertex.label.map(NameOrId::from),
+            properties: properties_from(vertex.properties),
+        }
+    }
+}
+
+impl From<result_pb::Edge> for Edge {
+    fn from(edge: result_pb::Edge) -> Self {
+        Edge {
+            id: edge.id,
+            label: edge.label.map(NameOrId::from),
+            src_id: edge.src_id,
+            src_label: edge.src_label.map(NameOrId::from),
+            dst_id: edge.dst_id,
+            dst_label: edge.dst_label.map(NameOrId::from),
+            properties: properties_from(edge.properties),
+        }
+    }
+}
+
+/// A vertex or an edge of a path.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PathStep {
+    Vertex(Vertex),
+    Edge(Edge),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Element {
+    Vertex(Vertex),
+    Edge(Edge),
+    Path(Vec<PathStep>),
+    Value(Value),
+}
+
+impl TryFrom<result_pb::Element> for Element {
+    type Error = ClientError;
+
+    fn try_from(element: result_pb::Element) -> ClientResult<Self> {
+        use result_pb::graph_path::vertex_or_edge::Inner as StepInner;
+
+        match element.inner {
+            Some(result_pb::element::Inner::Vertex(vertex)) => Ok(Element::Vertex(vertex.into())),
+            Some(result_pb::element::Inner::Edge(edge)) => Ok(Element::Edge(edge.into())),
+            Some(result_pb::element::Inner::GraphPath(path)) => {
+                let steps = path
+                    .path
+                    .into_iter()
+                    .map(|step| match step.inner {
+                        Some(StepInner::Vertex(vertex)) => Ok(PathStep::Vertex(vertex.into())),
+                        Some(StepInner::Edge(edge)) => Ok(PathStep::Edge(edge.into())),
+                        None => Err(ClientError::DecodeError("empty step of a path".to_string())),
+                    })
+                    .collect::<ClientResult<Vec<_>>>()?;
+                Ok(Element::Path(steps))
+            }
+            Some(result_pb::element::Inner::Object(value)) => Ok(Element::Value(value.into())),
+            None => Ok(Element::Value(Value::Null)),
+        }
+    }
+}
+
+/// The value of a column of a result.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Entry {
+    Element(Element),
+    Collection(Vec<Element>),
+    Map(Vec<(Value, Element)>),
+    /// The map keyed by the elements or nesting the maps, e.g., the trees of `tree()`
+    NestedMap(Vec<(Element, Entry)>),
+}
+
+fn nested_map_from(map: result_pb::KeyValues) -> ClientResult<Vec<(Element, Entry)>> {
+    let mut key_values = Vec::with_capacity(map.key_values.len());
+    for key_value in map.key_values {
+        let key = match (key_value.element_key, key_value.key) {
+            (Some(element), _) => Element::try_from(element)?,
+            (None, key) => Element::Value(key.map(Value::from).unwrap_or(Value::Null)),
+        };
+        let value = match (key_value.nested_value, key_value.value) {
+            (Some(nested), _) => Entry::NestedMap(nested_map_from(nested)?),
+            (None, Some(value)) => Entry::Element(Element::try_from(value)?),
+            (None, None) => Entry::Element(Element::Value(Value::Null)),
+        };
+        key_values.push((key, value));
+    }
+    Ok(key_values)
+}
+
+impl TryFrom<result_pb::Entry> for Entry {
+    type Error = ClientError;
+
+    fn try_from(entry: result_pb::Entry) -> ClientResult<Self> {
+        match entry.inner {
+            Some(result_pb::entry::Inner::Element(element)) => {
+                Ok(Entry::Element(Element::try_from(element)?))
+            }
+            Some(result_pb::entry::Inner::Collection(collection)) => {
+                let elements = collection
+                    .collection
+                    .into_iter()
+                    .map(Element::try_from)
+                    .collect::<ClientResult<Vec<_>>>()?;
+                Ok(Entry::Collection(elements))
+            }
+            Some(result_pb::entry::Inner::Map(map))
+                if map.key_values.iter().any(|key_value| {
+                    key_value.element_key.is_some() || key_value.nested_value.is_some()
+                }) =>
+            {
+                Ok(Entry::NestedMap(nested_map_from(map)?))
+            }
+            Some(result_pb::entry::Inner::Map(map)) => {
+                let mut key_values = Vec::with_capacity(map.key_values.len());
+                for key_value in map.key_values {
+                    let key = key_value
+                        .key
+                        .map(Value::from)
+                        .unwrap_or(Value::Null);
+                    let value = match key_value.value {
+                        Some(value) => Element::try_from(value)?,
+                        None => Element::Value(Value::Null),
+                    };
+                    key_values.push((key, value));
+                }
+                Ok(Entry::Map(key_values))
+            }
+            None => Ok(Entry::Element(Element::Value(Value::Null))),
+        }
+    }
+}
+
+/// A step of the trace of a row, see [`Provenance`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProvenanceStep {
+    /// The operator of the step, e.g., `Project` or `Join`.
+    pub operator: String,
+    /// The tags read by the operator, other than the head.
+    pub tags: Vec<NameOrId>,
+    pub reads_head: bool,
+    /// The ids of the vertices of the tags read.
+    pub vertices: Vec<i64>,
+}
+
+/// The trace of the steps deriving a row, in the provenance tracking mode of the client.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Provenance {
+    pub steps: Vec<ProvenanceStep>,
+    /// Whether the earlier steps are dropped for the limit of the trace.
+    pub truncated: bool,
+}
+
+impl From<result_pb::Provenance> for Provenance {
+    fn from(provenance: result_pb::Provenance) -> Self {
+        let steps = provenance
+            .steps
+            .into_iter()
+            .map(|step| ProvenanceStep {
+                operator: step.operator,
+                tags: step
+                    .tags
+                    .into_iter()
+                    .map(NameOrId::from)
+                    .collect(),
+                reads_head: step.reads_head,
+                vertices: step.vertices,
+            })
+            .collect();
+        Provenance { steps, truncated: provenance.truncated }
+    }
+}
+
+/// A result of a query, i.e., the columns of a record, which are named by the tags if any.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Row {
+    pub columns: Vec<(Option<NameOrId>, Entry)>,
+    /// Only present in the provenance tracking mode.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub provenance: Option<Provenance>,
+}
+
+impl Row {
+    /// Get the entry of the column of the given name.
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.columns
+            .iter()
+            .find(|(column, _)| matches!(column, Some(NameOrId::Name(column)) if column == name))
+            .map(|(_, entry)| entry)
+    }
+
+    /// Decode a result sent by the service, i.e., `Results` encoded in protobuf.
+    pub fn decode(bytes: &[u8]) -> ClientResult<Self> {
+        let results = result_pb::Results::decode(bytes)?;
+        Row::try_from(results)
+    }
+}
+
+impl TryFrom<result_pb::Results> for Row {
+    type Error = ClientError;
+
+    fn try_from(results: result_pb::Results) -> ClientResult<Self> {
+        let record = match results.inner {
+            Some(result_pb::results::Inner::Record(record)) => record,
+            // a side effect returned after the results is a row of the column named by the side effect
+            Some(result_pb::results::Inner::SideEffect(side_effect)) => {
+                let entry = match side_effect.entry {
+                    Some(entry) => Entry::try_from(entry)?,
+                    None => Entry::Element(Element::Value(Value::Null)),
+                };
+                let columns = vec![(Some(NameOrId::Name(side_effect.name)), entry)];
+                return Ok(Row { columns, provenance: None });
+            }
+            None => return Ok(Row::default()),
+        };
+        let columns = record
+            .columns
+            .into_iter()
+            .map(|column| {
+                let entry = match column.entry {
+                    Some(entry) => Entry::try_from(entry)?,
+                    None => Entry::Element(Element::Value(Value::Null)),
+                };
+                Ok((column.name_or_id.map(NameOrId::from), entry))
+            })
+            .collect::<ClientResult<Vec<_>>>()?;
+        Ok(Row { columns, provenance: record.provenance.map(Provenance::from) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn vertex_pb(id: i64, name: &str) -> result_pb::Vertex {
+        result_pb::Vertex {
+            id,
+            label: Some("person".into()),
+            properties: vec![result_pb::Property {
+                key: Some("name".into()),
+                value: Some(common_pb::Value { item: Some(common_pb::value::Item::Str(name.to_string())) }),
+            }],
+        }
+    }
+
+    fn column_pb(name: &str, inner: result_pb::entry::Inner) -> result_pb::Column {
+        result_pb::Column {
+            name_or_id: Some(name.into()),
+            entry: Some(result_pb::Entry { inner: Some(inner) }),
+        }
+    }
+
+    #[test]
+    fn decode_row() {
+        let record = result_pb::Record {
+            columns: vec![
+                column_pb(
+                    "a",
+                    result_pb::entry::Inner::Element(result_pb::Element {
+                        inner: Some(result_pb::element::Inner::Vertex(vertex_pb(1, "marko"))),
+                    }),
+                ),
+                column_pb(
+                    "b",
+                    result_pb::entry::Inner::Collection(result_pb::Collection {
+                        collection: vec![result_pb::Element {
+                            inner: Some(result_pb::element::Inner::Object(common_pb::Value {
+                                item: Some(common_pb::value::Item::None(common_pb::None {})),
+                            })),
+                        }],
+                    }),
+                ),
+            ],
+            provenance: None,
+        };
+        let results = result_pb::Results { inner: Some(result_pb::results::Inner::Record(record)) };
+        let row = Row::decode(&results.encode_to_vec()).unwrap();
+        assert_eq!(row.columns.len(), 2);
+        let mut properties = BTreeMap::new();
+        properties.insert(NameOrId::Name("name".to_string()), Value::String("marko".to_string()));
+        assert_eq!(
+            row.get("a"),
+            Some(&Entry::Element(Element::Vertex(Vertex {
+                id: 1,
+                label: Some(NameOrId::Name("person".to_string())),
+                properties
+            })))
+        );
+        assert_eq!(row.get("b"), Some(&Entry::Collection(vec![Element::Value(Value::Null)])));
+        assert_eq!(row.get("c"), None);
+        assert_eq!(row.provenance, None);
+    }
+
+    #[test]
+    fn decode_provenance_row() {
+        let provenance = result_pb::Provenance {
+            steps: vec![result_pb::provenance::Step {
+                operator: "Project".to_string(),
+                tags: vec!["a".into()],
+                reads_head: true,
+                vertices: vec![1],
+            }],
+            truncated: false,
+        };
+        let record = result_pb::Record { columns: vec![], provenance: Some(provenance) };
+        let results = result_pb::Results { inner: Some(result_pb::results::Inner::Record(record)) };
+        let row = Row::decode(&results.encode_to_vec()).unwrap();
+        assert_eq!(
+            row.provenance,
+            Some(Provenance {
+                steps: vec![ProvenanceStep {
+                    operator: "Project".to_string(),
+                    tags: vec![NameOrId::Name("a".to_string())],
+                    reads_head: true,
+                    vertices: vec![1],
+                }],
+                truncated: false,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_side_effect_row() {
+        let side_effect = result_pb::SideEffect {
+            name: "x".to_string(),
+            entry: Some(result_pb::Entry {
+                inner: Some(result_pb::entry::Inner::Element(result_pb::Element {
+                    inner: Some(result_pb::element::Inner::Object(common_pb::Value {
+                        item: Some(common_pb::value::Item::I64(3)),
+                    })),
+                })),
+            }),
+        };
+        let results =
+            result_pb::Results { inner: Some(result_pb::results::Inner::SideEffect(side_effect)) };
+        let row = Row::decode(&results.encode_to_vec()).unwrap();
+        assert_eq!(row.columns.len(), 1);
+        assert_eq!(row.get("x"), Some(&Entry::Element(Element::Value(Value::Int64(3)))));
+    }
+
+    #[test]
+    fn decode_tree_row() {
+        // the tree of `marko` with the leaf of `vadas`
+        let leaf = result_pb::key_values::KeyValue {
+            element_key: Some(result_pb::Element {
+                inner: Some(result_pb::element::Inner::Vertex(vertex_pb(2, "vadas"))),
+            }),
+            nested_value: Some(result_pb::KeyValues { key_values: vec![] }),
+            ..Default::default()
+        };
+        let root = result_pb::key_values::KeyValue {
+            element_key: Some(result_pb::Element {
+                inner: Some(result_pb::element::Inner::Vertex(vertex_pb(1, "marko"))),
+            }),
+            nested_value: Some(result_pb::KeyValues { key_values: vec![leaf] }),
+            ..Default::default()
+        };
+        let record = result_pb::Record {
+            columns: vec![column_pb(
+                "t",
+                result_pb::entry::Inner::Map(result_pb::KeyValues { key_values: vec![root] }),
+            )],
+            provenance: None,
+        };
+        let results = result_pb::Results { inner: Some(result_pb::results::Inner::Record(record)) };
+        let row = Row::decode(&results.encode_to_vec()).unwrap();
+        let vertex = |id: i64, name: &str| {
+            let mut properties = BTreeMap::new();
+            properties.insert(NameOrId::Name("name".to_string()), Value::String(name.to_string()));
+            Element::Vertex(Vertex { id, label: Some(NameOrId::Name("person".to_string())), properties })
+        };
+        assert_eq!(
+            row.get("t"),
+            Some(&Entry::NestedMap(vec![(
+                vertex(1, "marko"),
+                Entry::NestedMap(vec![(vertex(2, "vadas"), Entry::NestedMap(vec![]))])
+            )]))
+        );
+    }
+
+    #[test]
+    fn decode_invalid_row() {
+        assert!(matches!(Row::decode(&[0xff, 0xff]), Err(ClientError::DecodeError(_))));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_row() {
+        let row = Row {
+            columns: vec![(
+                Some(NameOrId::Name("a".to_string())),
+                Entry::Element(Element::Value(Value::Int64(1))),
+            )],
+            provenance: None,
+        };
+        let json = serde_json::to_string(&row).unwrap();
+        assert_eq!(serde_json::from_str::<Row>(&json).unwrap(), row);
+    }
+}
@@ -13,6 +13,8 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use pegasus_common::error_code::ErrorCode;
+
 pub type ParsePbResult<T> = Result<T, ParsePbError>;
 
 /// Errors that occur when parse a pb struct
@@ -49,6 +51,18 @@ impl std::fmt::Display for ParsePbError {
 
 impl std::error::Error for ParsePbError {}
 
+impl ParsePbError {
+    /// The code of the error, in `1xxx` of the errors of parsing the plan.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ParsePbError::ParseError(_) => ErrorCode::user(1001),
+            ParsePbError::SerdeError(_) => ErrorCode::user(1002),
+            ParsePbError::EmptyFieldError(_) => ErrorCode::user(1003),
+            ParsePbError::Unsupported(_) => ErrorCode::user(1004),
+        }
+    }
+}
+
 impl From<String> for ParsePbError {
     fn from(desc: String) -> Self {
         ParsePbError::ParseError(desc)
@@ -91,6 +91,8 @@ impl TryFrom<Token> for pb::ExprOpr {
             Token::IdentArray(idents) => Ok((idents_to_vars(idents)?, false).into()),
             Token::IdentMap(idents) => Ok((idents_to_vars(idents)?, true).into()),
             Token::IsNull => Ok(pb::Logical::Isnull.into()),
+            Token::Exists => Ok(pb::Logical::Exists.into()),
+            Token::IsType => Ok(pb::Logical::Istype.into()),
         }
     }
 }
@@ -112,6 +114,7 @@ impl ExprToken for pb::ExprOpr {
         match self.item {
             Some(pb::expr_opr::Item::Const(_)) => true,
             Some(pb::expr_opr::Item::Var(_)) => true,
+            Some(pb::expr_opr::Item::Udf(_)) => true,
             _ => false,
         }
     }
@@ -158,17 +161,18 @@ impl ExprToken for pb::ExprOpr {
                         | pb::Logical::Without
                         | pb::Logical::Startswith
                         | pb::Logical::Endswith
-                        | pb::Logical::Regex => 90, // 4.
+                        | pb::Logical::Regex
+                        | pb::Logical::Istype => 90, // 4.
                         pb::Logical::Eq
                         | pb::Logical::Ne
                         | pb::Logical::Lt
                         | pb::Logical::Le
                         | pb::Logical::Gt
                         | pb::Logical::Ge => 80, // 5.
-                        pb::Logical::Isnull => 70, // 6.
-                        pb::Logical::Not => 60,    // 7
-                        pb::Logical::And => 50,    // 8.
-                        pb::Logical::Or => 40,     // 9.
+                        pb::Logical::Isnull | pb::Logical::Exists => 70, // 6.
+                        pb::Logical::Not => 60,                          // 7
+                        pb::Logical::And => 50,                          // 8.
+                        pb::Logical::Or => 40,                           // 9.
                     }
                 }
                 &Brace(_) => 0, // 10.
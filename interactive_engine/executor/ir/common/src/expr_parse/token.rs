@@ -47,6 +47,8 @@ pub enum Token {
     StartsWith, // String StartsWith
     EndsWith,   // String EndsWith
     IsNull,     // IsNull
+    Exists,     // Exists
+    IsType,     // IsType
     // Precedence
     LBrace, // (
     RBrace, // )
@@ -92,9 +94,9 @@ impl ExprToken for Token {
             Power => 120,                                                          // 1.
             Star | Slash | Percent => 110,                                         // 2.
             Plus | Minus | BitLShift | BitRShift | BitAnd | BitOr | BitXor => 100, // 3.
-            Within | Without | StartsWith | EndsWith => 90,                        // 4.
+            Within | Without | StartsWith | EndsWith | IsType => 90,               // 4.
             Eq | Ne | Gt | Lt | Ge | Le => 80,                                     // 5.
-            IsNull => 70,                                                          // 6
+            IsNull | Exists => 70,                                                 // 6
             Not => 60,                                                             // 7
             And => 50,                                                             // 8.
             Or => 40,                                                              // 9.
@@ -361,6 +363,10 @@ fn partial_tokens_to_tokens(mut tokens: &[PartialToken]) -> ExprResult<Vec<Token
                     Some(Token::EndsWith)
                 } else if literal.to_lowercase().as_str() == "isnull" {
                     Some(Token::IsNull)
+                } else if literal.to_lowercase().as_str() == "exists" {
+                    Some(Token::Exists)
+                } else if literal.to_lowercase().as_str() == "istype" {
+                    Some(Token::IsType)
                 } else {
                     // To parse the float of the form `<coefficient>e{+,-}<exponent>`,
                     // for example [Literal("10e"), Minus, Literal("3")] => "1e-3".parse().
@@ -587,6 +593,18 @@ mod tests {
         let case6 = tokenize("isNull @.age");
         let expected_case6 = vec![Token::IsNull, Token::Identifier("@.age".to_string())];
         assert_eq!(case6.unwrap(), expected_case6);
+
+        let case7 = tokenize("!exists @.age && @.name isType \"string\"");
+        let expected_case7 = vec![
+            Token::Not,
+            Token::Exists,
+            Token::Identifier("@.age".to_string()),
+            Token::And,
+            Token::Identifier("@.name".to_string()),
+            Token::IsType,
+            Token::String("string".to_string()),
+        ];
+        assert_eq!(case7.unwrap(), expected_case7);
     }
 
     #[test]
@@ -212,6 +212,8 @@ pub const ID_KEY: &'static str = "~id";
 pub const LABEL_KEY: &'static str = "~label";
 pub const LENGTH_KEY: &'static str = "~len";
 pub const ALL_KEY: &'static str = "~all";
+pub const SRC_ID_KEY: &'static str = "~src_id";
+pub const DST_ID_KEY: &'static str = "~dst_id";
 
 impl From<String> for common_pb::Property {
     fn from(str: String) -> Self {
@@ -664,6 +666,90 @@ impl From<pb::Sample> for pb::logical_plan::Operator {
     }
 }
 
+impl From<pb::GraphAlgorithm> for pb::logical_plan::Operator {
+    fn from(opr: pb::GraphAlgorithm) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::Algorithm(opr)) }
+    }
+}
+
+impl From<pb::KHop> for pb::logical_plan::Operator {
+    fn from(opr: pb::KHop) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::KHop(opr)) }
+    }
+}
+
+impl From<pb::Merge> for pb::logical_plan::Operator {
+    fn from(opr: pb::Merge) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::Merge(opr)) }
+    }
+}
+
+impl From<pb::Mutate> for pb::logical_plan::Operator {
+    fn from(opr: pb::Mutate) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::Mutate(opr)) }
+    }
+}
+
+impl From<pb::StoreVar> for pb::logical_plan::Operator {
+    fn from(opr: pb::StoreVar) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::StoreVar(opr)) }
+    }
+}
+
+impl From<pb::LoadVar> for pb::logical_plan::Operator {
+    fn from(opr: pb::LoadVar) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::LoadVar(opr)) }
+    }
+}
+
+impl From<pb::Call> for pb::logical_plan::Operator {
+    fn from(opr: pb::Call) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::Call(opr)) }
+    }
+}
+
+impl From<pb::SideEffect> for pb::logical_plan::Operator {
+    fn from(opr: pb::SideEffect) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::SideEffect(opr)) }
+    }
+}
+
+impl From<pb::Cap> for pb::logical_plan::Operator {
+    fn from(opr: pb::Cap) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::Cap(opr)) }
+    }
+}
+
+impl From<pb::Sack> for pb::logical_plan::Operator {
+    fn from(opr: pb::Sack) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::Sack(opr)) }
+    }
+}
+
+impl From<pb::Tree> for pb::logical_plan::Operator {
+    fn from(opr: pb::Tree) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::Tree(opr)) }
+    }
+}
+
+impl From<pb::PatternMatch> for pb::logical_plan::Operator {
+    fn from(opr: pb::PatternMatch) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::PatternMatch(opr)) }
+    }
+}
+
+impl From<pb::Repeat> for pb::logical_plan::Operator {
+    fn from(opr: pb::Repeat) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::Repeat(opr)) }
+    }
+}
+
+impl From<pb::Coalesce> for pb::logical_plan::Operator {
+    fn from(opr: pb::Coalesce) -> Self {
+        pb::logical_plan::Operator { opr: Some(pb::logical_plan::operator::Opr::Coalesce(opr)) }
+    }
+}
+
 impl From<Object> for common_pb::Value {
     fn from(value: Object) -> Self {
         let item = match value {
@@ -764,6 +850,8 @@ impl pb::QueryParams {
             predicate: None,
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         })
     }
 }
@@ -858,7 +946,7 @@ impl From<pb::Project> for physical_pb::Project {
                 alias: expr.alias.map(|tag| tag.try_into().unwrap()),
             })
             .collect();
-        physical_pb::Project { mappings, is_append: project.is_append }
+        physical_pb::Project { mappings, is_append: project.is_append, select_tags: project.select_tags }
     }
 }
 
@@ -883,6 +971,7 @@ impl From<pb::GroupBy> for physical_pb::GroupBy {
                 alias: agg_func
                     .alias
                     .map(|tag| tag.try_into().unwrap()),
+                udf: agg_func.udf,
             })
             .collect();
         physical_pb::GroupBy { mappings, functions }
@@ -994,7 +1083,7 @@ impl TryFrom<physical_pb::PhysicalOpr> for physical_pb::physical_opr::operator::
 impl common_pb::Logical {
     pub fn is_unary(&self) -> bool {
         match self {
-            common_pb::Logical::Not | common_pb::Logical::Isnull => true,
+            common_pb::Logical::Not | common_pb::Logical::Isnull | common_pb::Logical::Exists => true,
             _ => false,
         }
     }
@@ -1013,7 +1102,8 @@ impl common_pb::Logical {
             | common_pb::Logical::Endswith
             | common_pb::Logical::And
             | common_pb::Logical::Or
-            | common_pb::Logical::Regex => true,
+            | common_pb::Logical::Regex
+            | common_pb::Logical::Istype => true,
             _ => false,
         }
     }
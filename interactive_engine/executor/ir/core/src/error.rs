@@ -39,6 +39,7 @@ pub enum IrError {
     ParseExprError(ExprError),
     InvalidPattern(String),
     InvalidExtendPattern(IrPatternError),
+    InvalidQuery(String),
 
     // Physical Errors
     MissingData(String),
@@ -69,6 +70,7 @@ impl fmt::Display for IrError {
             IrError::InvalidExtendPattern(err) => {
                 write!(f, "invalid pattern with ExtendStrategy: {:?}", err)
             }
+            IrError::InvalidQuery(s) => write!(f, "invalid query: {:?}", s),
             IrError::PbEncodeError(err) => write!(f, "encoding protobuf error: {:?}", err),
             IrError::PbDecodeError(err) => write!(f, "decoding protobuf error: {:?}", err),
             IrError::MissingData(s) => write!(f, "missing required data: {:?}", s),
@@ -44,6 +44,8 @@ pub(crate) fn query_params(
         predicate,
         sample_ratio: 1.0,
         extra: HashMap::new(),
+        valid_time: None,
+        excluded_tables: vec![],
     }
 }
 
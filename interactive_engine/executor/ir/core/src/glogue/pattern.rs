@@ -700,7 +700,7 @@ fn generate_project_operator(pattern: &Pattern) -> IrPatternResult<Option<pb::lo
             mappings.push(mapping);
         }
         // TODO: the meta_data of project is identical with the meta_data of "Pattern"
-        Ok(Some(pb::Project { mappings, is_append: false, meta_data: vec![] }.into()))
+        Ok(Some(pb::Project { mappings, is_append: false, meta_data: vec![], select_tags: false }.into()))
     } else {
         Ok(None)
     }
@@ -152,6 +152,7 @@ impl From<IrError> for FfiResult {
             IrError::ParseExprError(err) => FfiResult::new(ResultCode::ParseExprError, err.to_string()),
             IrError::InvalidPattern(s) => FfiResult::new(ResultCode::Others, s),
             IrError::InvalidExtendPattern(err) => FfiResult::new(ResultCode::Others, err.to_string()),
+            IrError::InvalidQuery(s) => FfiResult::new(ResultCode::Others, s),
             IrError::PbEncodeError(err) => FfiResult::new(ResultCode::ParsePbError, err.to_string()),
             IrError::PbDecodeError(err) => FfiResult::new(ResultCode::ParsePbError, err.to_string()),
             IrError::MissingData(d) => {
@@ -490,6 +491,10 @@ pub enum FfiLogicalOpt {
     ISNULL = 13,
     // A binary operator to verify whether a string matches a regular expression
     REGEX = 14,
+    // A unary logical operator to verify whether a variable exists, e.g., the property of a key
+    EXISTS = 15,
+    // A binary operator to verify whether a value is of a type given by its name
+    ISTYPE = 16,
 }
 
 impl Default for FfiLogicalOpt {
@@ -727,6 +732,84 @@ pub extern "C" fn print_plan_as_json(ptr_plan: *const c_void) -> FfiResult {
     result
 }
 
+#[cfg(feature = "graphql")]
+fn graphql_schema() -> Result<super::graphql::GraphQLSchema, FfiResult> {
+    use super::meta::STORE_META;
+    let meta = STORE_META
+        .read()
+        .map_err(|_| FfiResult::new(ResultCode::Others, "error reading store meta".to_string()))?;
+    meta.schema
+        .as_ref()
+        .map(super::graphql::GraphQLSchema::from)
+        .ok_or_else(|| {
+            FfiResult::new(ResultCode::Others, "error getting schema from store meta".to_string())
+        })
+}
+
+/// The GraphQL schema derived from the schema of `set_schema`, in the schema definition language.
+#[cfg(feature = "graphql")]
+#[no_mangle]
+pub extern "C" fn get_graphql_schema() -> FfiResult {
+    match graphql_schema().and_then(|schema| string_to_cstr(schema.to_sdl())) {
+        Ok(msg) => FfiResult { code: ResultCode::Success, msg },
+        Err(e) => e,
+    }
+}
+
+/// Resolve the `index`-th root field of a GraphQL query, with the variables in json if not empty, into
+/// the logical plan of `ptr_plan` given by `init_logical_plan`, sunk by the objects of the field in
+/// GraphSON, whose nested lists the protobuf results cannot carry; the message is the response key of
+/// the field, or null if `index` is beyond the root fields.
+#[cfg(feature = "graphql")]
+#[no_mangle]
+pub extern "C" fn resolve_graphql(
+    cstr_query: *const c_char, cstr_variables: *const c_char, index: i32, ptr_plan: *const c_void,
+) -> FfiResult {
+    use super::graphql::GraphQLPlan;
+
+    let resolve = || -> Result<Option<(String, LogicalPlan)>, FfiResult> {
+        let query = cstr_to_string(cstr_query)?;
+        let variables = cstr_to_string(cstr_variables)?;
+        let variables = if variables.is_empty() {
+            serde_json::Map::new()
+        } else {
+            serde_json::from_str(&variables)
+                .map_err(|e| FfiResult::new(ResultCode::Others, format!("invalid variables: {}", e)))?
+        };
+        let mut plans = graphql_schema()?.resolve(&query, &variables)?;
+        if index < 0 || index as usize >= plans.len() {
+            return Ok(None);
+        }
+        let GraphQLPlan { field, mut plan } = plans.swap_remove(index as usize);
+        let last = plan
+            .get_last_node()
+            .map(|node| node.borrow().id)
+            .unwrap_or_default();
+        let sink = pb::Sink {
+            tags: vec![common_pb::NameOrIdKey { key: Some(field.clone().into()) }],
+            sink_target: Some(pb::sink::SinkTarget {
+                inner: Some(pb::sink::sink_target::Inner::SinkDefault(pb::SinkDefault {
+                    id_name_mappings: vec![],
+                    format: pb::sink_default::Format::GraphsonV3 as i32,
+                    columns: vec![],
+                })),
+            }),
+        };
+        plan.append_operator_as_node(sink.into(), vec![last])?;
+        Ok(Some((field, plan)))
+    };
+    match resolve() {
+        Ok(Some((field, plan))) => {
+            let mut ptr_plan = unsafe { Box::from_raw(ptr_plan as *mut LogicalPlan) };
+            *ptr_plan = plan;
+            std::mem::forget(ptr_plan);
+            FfiResult::new(ResultCode::Success, field)
+        }
+        Ok(None) => FfiResult::success(),
+        Err(e) => e,
+    }
+}
+
 /// Internal options for some private functions
 #[allow(dead_code)]
 #[derive(PartialEq, Copy, Clone)]
@@ -744,6 +827,16 @@ enum InnerOpt {
     Sink = 9,
     Params = 10,
     Unfold = 11,
+    Algorithm = 12,
+    KHop = 13,
+    AlgorithmWeight = 14,
+    AlgorithmSource = 15,
+    Union = 16,
+    Merge = 17,
+    Repeat = 18,
+    SideEffect = 19,
+    Cap = 20,
+    PatternMatch = 21,
 }
 
 /// Set the size range limitation for certain operators
@@ -817,6 +910,36 @@ fn set_alias(ptr: *const c_void, alias: FfiAlias, opt: InnerOpt) -> FfiResult {
                     as_opr.alias = pb;
                     std::mem::forget(as_opr);
                 }
+                InnerOpt::Algorithm => {
+                    let mut algorithm = unsafe { Box::from_raw(ptr as *mut pb::GraphAlgorithm) };
+                    algorithm.alias = pb;
+                    std::mem::forget(algorithm);
+                }
+                InnerOpt::KHop => {
+                    let mut k_hop = unsafe { Box::from_raw(ptr as *mut pb::KHop) };
+                    k_hop.alias = pb;
+                    std::mem::forget(k_hop);
+                }
+                InnerOpt::Union => {
+                    let mut union = unsafe { Box::from_raw(ptr as *mut pb::Union) };
+                    union.branch_alias = pb;
+                    std::mem::forget(union);
+                }
+                InnerOpt::Merge => {
+                    let mut merge = unsafe { Box::from_raw(ptr as *mut pb::Merge) };
+                    merge.alias = pb;
+                    std::mem::forget(merge);
+                }
+                InnerOpt::Cap => {
+                    let mut cap = unsafe { Box::from_raw(ptr as *mut pb::Cap) };
+                    cap.alias = pb;
+                    std::mem::forget(cap);
+                }
+                InnerOpt::PatternMatch => {
+                    let mut pattern = unsafe { Box::from_raw(ptr as *mut pb::PatternMatch) };
+                    pattern.alias = pb;
+                    std::mem::forget(pattern);
+                }
                 _ => unreachable!(),
             }
             FfiResult::success()
@@ -879,6 +1002,21 @@ fn set_predicate(ptr: *const c_void, cstr_predicate: *const c_char, opt: InnerOp
                 path.condition = predicate_pb.ok();
                 std::mem::forget(path);
             }
+            InnerOpt::AlgorithmWeight => {
+                let mut algorithm = unsafe { Box::from_raw(ptr as *mut pb::GraphAlgorithm) };
+                algorithm.weight = predicate_pb.ok();
+                std::mem::forget(algorithm);
+            }
+            InnerOpt::AlgorithmSource => {
+                let mut algorithm = unsafe { Box::from_raw(ptr as *mut pb::GraphAlgorithm) };
+                algorithm.source = predicate_pb.ok();
+                std::mem::forget(algorithm);
+            }
+            InnerOpt::Repeat => {
+                let mut repeat = unsafe { Box::from_raw(ptr as *mut pb::Repeat) };
+                repeat.until = predicate_pb.ok();
+                std::mem::forget(repeat);
+            }
             _ => unreachable!(),
         }
         FfiResult::success()
@@ -904,6 +1042,11 @@ fn set_predicate_pb(ptr: *const c_void, ptr_predicate_pb: FfiPbPointer, opt: Inn
                 params.predicate = predicate_pb.ok();
                 std::mem::forget(params);
             }
+            InnerOpt::Repeat => {
+                let mut repeat = unsafe { Box::from_raw(ptr as *mut pb::Repeat) };
+                repeat.until = predicate_pb.ok();
+                std::mem::forget(repeat);
+            }
             _ => unreachable!(),
         }
         FfiResult::success()
@@ -932,6 +1075,16 @@ fn set_tag(ptr: *const c_void, tag: FfiNameOrId, opt: InnerOpt) -> FfiResult {
                     pathxpd.start_tag = pb;
                     std::mem::forget(pathxpd);
                 }
+                InnerOpt::KHop => {
+                    let mut k_hop = unsafe { Box::from_raw(ptr as *mut pb::KHop) };
+                    k_hop.start_tag = pb;
+                    std::mem::forget(k_hop);
+                }
+                InnerOpt::SideEffect => {
+                    let mut side_effect = unsafe { Box::from_raw(ptr as *mut pb::SideEffect) };
+                    side_effect.tag = pb;
+                    std::mem::forget(side_effect);
+                }
                 _ => unreachable!(),
             }
             FfiResult::success()
@@ -956,6 +1109,8 @@ mod params {
             predicate: None,
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         });
 
         Box::into_raw(query_params) as *const c_void
@@ -978,6 +1133,28 @@ mod params {
         }
     }
 
+    /// Add a table to exclude, e.g., `any edge label except knows`, which is resolved against the schema
+    /// into the tables when the plan is built
+    #[no_mangle]
+    pub extern "C" fn add_params_excluded_table(
+        ptr_params: *const c_void, table: FfiNameOrId,
+    ) -> FfiResult {
+        let mut params = unsafe { Box::from_raw(ptr_params as *mut pb::QueryParams) };
+        let pb_result = table.try_into();
+        let result = match pb_result {
+            Ok(pb) => {
+                if let Some(table) = pb {
+                    params.excluded_tables.push(table)
+                }
+                FfiResult::success()
+            }
+            Err(e) => e,
+        };
+        std::mem::forget(params);
+
+        result
+    }
+
     #[no_mangle]
     pub extern "C" fn add_params_column(ptr_params: *const c_void, col: FfiNameOrId) -> FfiResult {
         let mut params = unsafe { Box::from_raw(ptr_params as *mut pb::QueryParams) };
@@ -1033,6 +1210,23 @@ mod params {
         FfiResult::success()
     }
 
+    /// Set the time range the edges must be valid in, which is `as of t` if `start == end == t`,
+    /// and `between start and end` otherwise
+    #[no_mangle]
+    pub extern "C" fn set_params_valid_time(ptr_params: *const c_void, start: i64, end: i64) -> FfiResult {
+        if start > end {
+            return FfiResult::new(
+                ResultCode::InvalidRangeError,
+                format!("the time range [{:?}, {:?}] is invalid", start, end),
+            );
+        }
+        let mut params = unsafe { Box::from_raw(ptr_params as *mut pb::QueryParams) };
+        params.valid_time = Some(pb::TimeRange { start, end });
+        std::mem::forget(params);
+
+        FfiResult::success()
+    }
+
     /// Add extra parameters
     #[no_mangle]
     pub extern "C" fn add_params_extra(
@@ -1071,6 +1265,7 @@ mod project {
             mappings: vec![],
             is_append: if is_append == 0 { false } else { true },
             meta_data: vec![],
+            select_tags: false,
         });
         Box::into_raw(project) as *const c_void
     }
@@ -1123,6 +1318,73 @@ mod project {
         result
     }
 
+    /// To add a mapping for the project operator, which maps the element of a tag into its map, e.g.,
+    /// `elementMap()`, with the id, the label and all the properties, unless any is added by
+    /// `add_project_element_map_key()`, and a `NameOrId` parameter that represents an alias.
+    #[no_mangle]
+    pub extern "C" fn add_project_element_map(
+        ptr_project: *const c_void, tag: FfiNameOrId, alias: FfiAlias,
+    ) -> FfiResult {
+        let mut result = FfiResult::success();
+        let mut project = unsafe { Box::from_raw(ptr_project as *mut pb::Project) };
+        let tag_pb = Option::<common_pb::NameOrId>::try_from(tag);
+        let alias_pb = Option::<common_pb::NameOrId>::try_from(alias);
+
+        if !tag_pb.is_ok() {
+            result = tag_pb.err().unwrap();
+        } else if !alias_pb.is_ok() {
+            result = alias_pb.err().unwrap();
+        } else {
+            let element_map = common_pb::ElementMap { tag: tag_pb.unwrap(), keys: vec![] };
+            let expr = common_pb::Expression {
+                operators: vec![common_pb::ExprOpr {
+                    node_type: None,
+                    item: Some(common_pb::expr_opr::Item::ElementMap(element_map)),
+                }],
+            };
+            let attribute = pb::project::ExprAlias { expr: Some(expr), alias: alias_pb.unwrap() };
+            project.mappings.push(attribute);
+        }
+        std::mem::forget(project);
+
+        result
+    }
+
+    /// To add the key of a property to the element map added last by `add_project_element_map()`
+    #[no_mangle]
+    pub extern "C" fn add_project_element_map_key(
+        ptr_project: *const c_void, cstr_key: *const c_char,
+    ) -> FfiResult {
+        let mut result = FfiResult::success();
+        let mut project = unsafe { Box::from_raw(ptr_project as *mut pb::Project) };
+        let key = cstr_to_string(cstr_key);
+        let element_map = project
+            .mappings
+            .last_mut()
+            .and_then(|mapping| mapping.expr.as_mut())
+            .and_then(|expr| expr.operators.first_mut())
+            .and_then(|opr| match opr.item.as_mut() {
+                Some(common_pb::expr_opr::Item::ElementMap(element_map)) => Some(element_map),
+                _ => None,
+            });
+
+        if !key.is_ok() {
+            result = key.err().unwrap();
+        } else if let Some(element_map) = element_map {
+            element_map
+                .keys
+                .push(common_pb::element_map::Key { name: key.unwrap(), key: None });
+        } else {
+            result = FfiResult::new(
+                ResultCode::MissingDataError,
+                "the element map to add the key to is not added".to_string(),
+            );
+        }
+        std::mem::forget(project);
+
+        result
+    }
+
     /// To add the column's meta for the project operator
     #[no_mangle]
     pub extern "C" fn add_project_meta(ptr_project: *const c_void, ptr_meta: FfiPbPointer) -> FfiResult {
@@ -1139,6 +1401,16 @@ mod project {
         result
     }
 
+    /// Set to project the tags as in Gremlin's `select('a', 'b')`, where a list of the tags is projected
+    /// as a map keyed by the tags, and the records missing any of the tags are filtered
+    #[no_mangle]
+    pub extern "C" fn set_project_select_tags(ptr_project: *const c_void, select_tags: bool) -> FfiResult {
+        let mut project = unsafe { Box::from_raw(ptr_project as *mut pb::Project) };
+        project.select_tags = select_tags;
+        std::mem::forget(project);
+        FfiResult::success()
+    }
+
     /// Append a project operator to the logical plan. To do so, one specifies the following arguments:
     /// * `ptr_plan`: A rust-owned pointer created by `init_logical_plan()`.
     /// * `ptr_project`: A rust-owned pointer created by `init_project_operator()`.
@@ -1319,7 +1591,7 @@ mod union {
     /// To initialize a union operator
     #[no_mangle]
     pub extern "C" fn init_union_operator() -> *const c_void {
-        let union = Box::new(pb::Union { parents: vec![] });
+        let union = Box::new(pb::Union { parents: vec![], branch_alias: None });
         Box::into_raw(union) as *const c_void
     }
 
@@ -1333,6 +1605,12 @@ mod union {
         FfiResult::success()
     }
 
+    /// Set the tag of the index of the branch each record of the Union comes from
+    #[no_mangle]
+    pub extern "C" fn set_union_branch_alias(ptr_union: *const c_void, alias: FfiAlias) -> FfiResult {
+        set_alias(ptr_union, alias, InnerOpt::Union)
+    }
+
     /// Append a Union operator to the logical plan
     #[no_mangle]
     pub extern "C" fn append_union_operator(
@@ -1371,6 +1649,8 @@ mod groupby {
         ToSet = 6,
         Avg = 7,
         First = 8,
+        ApproxCountDistinct = 9,
+        HeavyHitters = 10,
     }
 
     /*
@@ -1389,6 +1669,7 @@ mod groupby {
                 vars: vec![],
                 aggregate: unsafe { std::mem::transmute::<FfiAggOpt, i32>(value.aggregate) },
                 alias: None,
+                udf: String::new(),
             };
             let (vars, alias) = (value.vars as *mut Vec<FfiVariable>, value.alias);
             let vars: Box<Vec<FfiVariable>> = unsafe { Box::from_raw(vars) };
@@ -1481,6 +1762,7 @@ mod groupby {
                 vars: vec![val_pb.unwrap()],
                 aggregate,
                 alias: alias_pb.unwrap(),
+                udf: String::new(),
             });
         } else if val_pb.is_err() {
             result = val_pb.err().unwrap();
@@ -1772,6 +2054,8 @@ mod scan {
                 predicate: None,
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             idx_predicate: None,
             is_count_only: false,
@@ -2005,7 +2289,7 @@ mod sample {
     #[no_mangle]
     pub extern "C" fn init_sample_operator() -> *const c_void {
         let sample: Box<pb::Sample> =
-            Box::new(pb::Sample { sample_type: None, seed: None, sample_weight: None });
+            Box::new(pb::Sample { sample_type: None, seed: None, sample_weight: None, sample_key: None });
         Box::into_raw(sample) as *const c_void
     }
 
@@ -2051,6 +2335,23 @@ mod sample {
         result
     }
 
+    /// Set the key to sample by for the sample operator
+    #[no_mangle]
+    pub extern "C" fn set_sample_key_variable(
+        ptr_sample: *const c_void, ptr_key_var_pb: FfiPbPointer,
+    ) -> FfiResult {
+        let mut result = FfiResult::success();
+        let mut sample = unsafe { Box::from_raw(ptr_sample as *mut pb::Sample) };
+        let sample_key_result = ptr_to_pb::<common_pb::Variable>(ptr_key_var_pb);
+        if let Ok(sample_key) = sample_key_result {
+            sample.sample_key = Some(sample_key);
+        } else {
+            result = sample_key_result.err().unwrap();
+        }
+        std::mem::forget(sample);
+        result
+    }
+
     /// Append a Sample  operator to the logical plan
     #[no_mangle]
     pub extern "C" fn append_sample_operator(
@@ -2066,6 +2367,434 @@ mod sample {
     }
 }
 
+mod algorithm {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[allow(dead_code)]
+    #[repr(i32)]
+    #[derive(Clone, Copy)]
+    pub enum FfiAlgorithmKind {
+        PageRank = 0,
+        Wcc = 1,
+        Lpa = 2,
+        TriangleCount = 3,
+        ClusteringCoefficient = 4,
+        ShortestPath = 5,
+    }
+
+    /// To initialize a graph algorithm operator
+    #[no_mangle]
+    pub extern "C" fn init_algorithm_operator(kind: FfiAlgorithmKind) -> *const c_void {
+        let algorithm = Box::new(pb::GraphAlgorithm {
+            kind: unsafe { std::mem::transmute::<FfiAlgorithmKind, i32>(kind) },
+            params: Some(pb::QueryParams {
+                tables: vec![],
+                columns: vec![],
+                is_all_columns: false,
+                limit: None,
+                predicate: None,
+                sample_ratio: 1.0,
+                extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
+            }),
+            max_iterations: 0,
+            damping: 0.0,
+            alias: None,
+            global: false,
+            weight: None,
+            source: None,
+        });
+        Box::into_raw(algorithm) as *const c_void
+    }
+
+    /// Set the parameters of the edges to traverse, e.g., the edge labels
+    #[no_mangle]
+    pub extern "C" fn set_algorithm_params(
+        ptr_algorithm: *const c_void, ptr_params: *const c_void,
+    ) -> FfiResult {
+        let mut result = FfiResult::success();
+        let mut algorithm = unsafe { Box::from_raw(ptr_algorithm as *mut pb::GraphAlgorithm) };
+        let mut new_params = unsafe { Box::from_raw(ptr_params as *mut pb::QueryParams) };
+        if let Some(old_params) = algorithm.params.as_mut() {
+            std::mem::swap(old_params, new_params.as_mut());
+        } else {
+            result = FfiResult::new(ResultCode::MissingDataError, "pb::GraphAlgorithm::params".to_string());
+        }
+        std::mem::forget(algorithm);
+
+        result
+    }
+
+    /// Set the number of the iterations at most
+    #[no_mangle]
+    pub extern "C" fn set_algorithm_max_iterations(
+        ptr_algorithm: *const c_void, max_iterations: i32,
+    ) -> FfiResult {
+        let mut algorithm = unsafe { Box::from_raw(ptr_algorithm as *mut pb::GraphAlgorithm) };
+        algorithm.max_iterations = max_iterations;
+        std::mem::forget(algorithm);
+        FfiResult::success()
+    }
+
+    /// Set the damping factor of PageRank
+    #[no_mangle]
+    pub extern "C" fn set_algorithm_damping(ptr_algorithm: *const c_void, damping: f64) -> FfiResult {
+        let mut algorithm = unsafe { Box::from_raw(ptr_algorithm as *mut pb::GraphAlgorithm) };
+        algorithm.damping = damping;
+        std::mem::forget(algorithm);
+        FfiResult::success()
+    }
+
+    /// Set to output a single value of all the vertices, for triangle counting and clustering coefficient
+    #[no_mangle]
+    pub extern "C" fn set_algorithm_global(ptr_algorithm: *const c_void, global: bool) -> FfiResult {
+        let mut algorithm = unsafe { Box::from_raw(ptr_algorithm as *mut pb::GraphAlgorithm) };
+        algorithm.global = global;
+        std::mem::forget(algorithm);
+        FfiResult::success()
+    }
+
+    /// Set the weight of the edges traversed, e.g., `1.0 / @.strength`, evaluated per edge
+    #[no_mangle]
+    pub extern "C" fn set_algorithm_weight(
+        ptr_algorithm: *const c_void, cstr_weight: *const c_char,
+    ) -> FfiResult {
+        set_predicate(ptr_algorithm, cstr_weight, InnerOpt::AlgorithmWeight)
+    }
+
+    /// Set the predicate selecting the source vertices of the shortest paths
+    #[no_mangle]
+    pub extern "C" fn set_algorithm_source(
+        ptr_algorithm: *const c_void, cstr_source: *const c_char,
+    ) -> FfiResult {
+        set_predicate(ptr_algorithm, cstr_source, InnerOpt::AlgorithmSource)
+    }
+
+    /// Set the alias of the values of the vertices
+    #[no_mangle]
+    pub extern "C" fn set_algorithm_alias(ptr_algorithm: *const c_void, alias: FfiAlias) -> FfiResult {
+        set_alias(ptr_algorithm, alias, InnerOpt::Algorithm)
+    }
+
+    /// Append a graph algorithm operator to the logical plan
+    #[no_mangle]
+    pub extern "C" fn append_algorithm_operator(
+        ptr_plan: *const c_void, ptr_algorithm: *const c_void, parent: i32, id: *mut i32,
+    ) -> FfiResult {
+        let algorithm = unsafe { Box::from_raw(ptr_algorithm as *mut pb::GraphAlgorithm) };
+        append_operator(ptr_plan, algorithm.as_ref().clone().into(), vec![parent], id)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn destroy_algorithm_operator(ptr: *const c_void) {
+        destroy_ptr::<pb::GraphAlgorithm>(ptr)
+    }
+}
+
+mod k_hop {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::plan::ffi::graph::FfiDirection;
+    use crate::plan::ffi::groupby::FfiAggOpt;
+
+    /// To initialize a k-hop operator, which reaches the vertices within `hops` hops
+    #[no_mangle]
+    pub extern "C" fn init_k_hop_operator(dir: FfiDirection, hops: i32, within: bool) -> *const c_void {
+        let k_hop = Box::new(pb::KHop {
+            start_tag: None,
+            direction: unsafe { std::mem::transmute::<FfiDirection, i32>(dir) },
+            params: Some(pb::QueryParams {
+                tables: vec![],
+                columns: vec![],
+                is_all_columns: false,
+                limit: None,
+                predicate: None,
+                sample_ratio: 1.0,
+                extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
+            }),
+            hops,
+            within,
+            alias: None,
+            aggregates: vec![],
+        });
+        Box::into_raw(k_hop) as *const c_void
+    }
+
+    /// Set the start-vertex's tag of the k-hop
+    #[no_mangle]
+    pub extern "C" fn set_k_hop_start_tag(ptr_k_hop: *const c_void, start_tag: FfiNameOrId) -> FfiResult {
+        set_tag(ptr_k_hop, start_tag, InnerOpt::KHop)
+    }
+
+    /// Set the parameters of the edges to traverse, e.g., the edge labels
+    #[no_mangle]
+    pub extern "C" fn set_k_hop_params(ptr_k_hop: *const c_void, ptr_params: *const c_void) -> FfiResult {
+        let mut result = FfiResult::success();
+        let mut k_hop = unsafe { Box::from_raw(ptr_k_hop as *mut pb::KHop) };
+        let mut new_params = unsafe { Box::from_raw(ptr_params as *mut pb::QueryParams) };
+        if let Some(old_params) = k_hop.params.as_mut() {
+            std::mem::swap(old_params, new_params.as_mut());
+        } else {
+            result = FfiResult::new(ResultCode::MissingDataError, "pb::KHop::params".to_string());
+        }
+        std::mem::forget(k_hop);
+
+        result
+    }
+
+    /// Set the alias of the reached vertices
+    #[no_mangle]
+    pub extern "C" fn set_k_hop_alias(ptr_k_hop: *const c_void, alias: FfiAlias) -> FfiResult {
+        set_alias(ptr_k_hop, alias, InnerOpt::KHop)
+    }
+
+    /// Add the aggregate function of the reached vertices per start vertex
+    #[no_mangle]
+    pub extern "C" fn add_k_hop_agg_fn(
+        ptr_k_hop: *const c_void, agg_val: FfiVariable, agg_opt: FfiAggOpt, alias: FfiAlias,
+    ) -> FfiResult {
+        let mut result = FfiResult::success();
+        let mut k_hop = unsafe { Box::from_raw(ptr_k_hop as *mut pb::KHop) };
+        let val_pb = agg_val.try_into();
+        let aggregate = unsafe { std::mem::transmute::<FfiAggOpt, i32>(agg_opt) };
+        let alias_pb = alias.try_into();
+        if val_pb.is_ok() && alias_pb.is_ok() {
+            k_hop.aggregates.push(pb::group_by::AggFunc {
+                vars: vec![val_pb.unwrap()],
+                aggregate,
+                alias: alias_pb.unwrap(),
+                udf: String::new(),
+            });
+        } else if val_pb.is_err() {
+            result = val_pb.err().unwrap();
+        } else {
+            result = alias_pb.err().unwrap();
+        }
+        std::mem::forget(k_hop);
+
+        result
+    }
+
+    /// Append a k-hop operator to the logical plan
+    #[no_mangle]
+    pub extern "C" fn append_k_hop_operator(
+        ptr_plan: *const c_void, ptr_k_hop: *const c_void, parent: i32, id: *mut i32,
+    ) -> FfiResult {
+        let k_hop = unsafe { Box::from_raw(ptr_k_hop as *mut pb::KHop) };
+        append_operator(ptr_plan, k_hop.as_ref().clone().into(), vec![parent], id)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn destroy_k_hop_operator(ptr: *const c_void) {
+        destroy_ptr::<pb::KHop>(ptr)
+    }
+}
+
+mod side_effect {
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(Copy, Clone)]
+    #[repr(i32)]
+    pub enum FfiSideEffectKind {
+        List = 0,
+        Set = 1,
+        Count = 2,
+        Map = 3,
+    }
+
+    /// To initialize a side effect operator accumulating into the side effect of the name, e.g.,
+    /// `aggregate('x')` or `groupCount('x')` of Gremlin
+    #[no_mangle]
+    pub extern "C" fn init_side_effect_operator(
+        cstr_name: *const c_char, kind: FfiSideEffectKind,
+    ) -> *const c_void {
+        let name = cstr_to_string(cstr_name).expect("C String to Rust String error!");
+        let side_effect = Box::new(pb::SideEffect {
+            name,
+            kind: unsafe { std::mem::transmute::<FfiSideEffectKind, i32>(kind) },
+            tag: None,
+        });
+        Box::into_raw(side_effect) as *const c_void
+    }
+
+    /// Set the tag of the entries to accumulate, or else the head
+    #[no_mangle]
+    pub extern "C" fn set_side_effect_tag(ptr_side_effect: *const c_void, tag: FfiNameOrId) -> FfiResult {
+        set_tag(ptr_side_effect, tag, InnerOpt::SideEffect)
+    }
+
+    /// Append a side effect operator to the logical plan
+    #[no_mangle]
+    pub extern "C" fn append_side_effect_operator(
+        ptr_plan: *const c_void, ptr_side_effect: *const c_void, parent: i32, id: *mut i32,
+    ) -> FfiResult {
+        let side_effect = unsafe { Box::from_raw(ptr_side_effect as *mut pb::SideEffect) };
+        append_operator(ptr_plan, side_effect.as_ref().clone().into(), vec![parent], id)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn destroy_side_effect_operator(ptr: *const c_void) {
+        destroy_ptr::<pb::SideEffect>(ptr)
+    }
+
+    /// To initialize a cap operator reading the side effect of the name, e.g., `cap('x')` of Gremlin
+    #[no_mangle]
+    pub extern "C" fn init_cap_operator(cstr_name: *const c_char) -> *const c_void {
+        let name = cstr_to_string(cstr_name).expect("C String to Rust String error!");
+        let cap = Box::new(pb::Cap { name, alias: None });
+        Box::into_raw(cap) as *const c_void
+    }
+
+    /// Set the alias of the side effect read
+    #[no_mangle]
+    pub extern "C" fn set_cap_alias(ptr_cap: *const c_void, alias: FfiAlias) -> FfiResult {
+        set_alias(ptr_cap, alias, InnerOpt::Cap)
+    }
+
+    /// Append a cap operator to the logical plan
+    #[no_mangle]
+    pub extern "C" fn append_cap_operator(
+        ptr_plan: *const c_void, ptr_cap: *const c_void, parent: i32, id: *mut i32,
+    ) -> FfiResult {
+        let cap = unsafe { Box::from_raw(ptr_cap as *mut pb::Cap) };
+        append_operator(ptr_plan, cap.as_ref().clone().into(), vec![parent], id)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn destroy_cap_operator(ptr: *const c_void) {
+        destroy_ptr::<pb::Cap>(ptr)
+    }
+}
+
+mod merge {
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(Copy, Clone)]
+    #[repr(i32)]
+    pub enum FfiMergeKind {
+        Vertex = 0,
+        Edge = 1,
+    }
+
+    /// The properties of the merge to add to, i.e., the ones to match by, or to set on creation or
+    /// on match.
+    #[allow(dead_code)]
+    #[derive(Copy, Clone)]
+    #[repr(i32)]
+    pub enum FfiMergeSet {
+        Match = 0,
+        OnCreate = 1,
+        OnMatch = 2,
+    }
+
+    /// To initialize a merge operator of the vertex or the edge
+    #[no_mangle]
+    pub extern "C" fn init_merge_operator(kind: FfiMergeKind) -> *const c_void {
+        let merge = Box::new(pb::Merge {
+            kind: unsafe { std::mem::transmute::<FfiMergeKind, i32>(kind) },
+            label: None,
+            src_tag: None,
+            dst_tag: None,
+            properties: vec![],
+            on_create: vec![],
+            on_match: vec![],
+            alias: None,
+        });
+        Box::into_raw(merge) as *const c_void
+    }
+
+    /// Set the label of the vertex or the edge to merge
+    #[no_mangle]
+    pub extern "C" fn set_merge_label(ptr_merge: *const c_void, label: FfiNameOrId) -> FfiResult {
+        let mut result = FfiResult::success();
+        let mut merge = unsafe { Box::from_raw(ptr_merge as *mut pb::Merge) };
+        match label.try_into() {
+            Ok(label_pb) => merge.label = label_pb,
+            Err(e) => result = e,
+        }
+        std::mem::forget(merge);
+
+        result
+    }
+
+    /// Set the tags of the source and the destination vertices of the edge to merge
+    #[no_mangle]
+    pub extern "C" fn set_merge_endpoints(
+        ptr_merge: *const c_void, src_tag: FfiNameOrId, dst_tag: FfiNameOrId,
+    ) -> FfiResult {
+        let mut result = FfiResult::success();
+        let mut merge = unsafe { Box::from_raw(ptr_merge as *mut pb::Merge) };
+        let src_pb = src_tag.try_into();
+        let dst_pb = dst_tag.try_into();
+        if src_pb.is_ok() && dst_pb.is_ok() {
+            merge.src_tag = src_pb.unwrap();
+            merge.dst_tag = dst_pb.unwrap();
+        } else if src_pb.is_err() {
+            result = src_pb.err().unwrap();
+        } else {
+            result = dst_pb.err().unwrap();
+        }
+        std::mem::forget(merge);
+
+        result
+    }
+
+    /// Add a property of the key and the value, a c-like string of an expression, to the properties
+    /// to match by, or to set on creation or on match
+    #[no_mangle]
+    pub extern "C" fn add_merge_property(
+        ptr_merge: *const c_void, set: FfiMergeSet, key: FfiNameOrId, cstr_value: *const c_char,
+    ) -> FfiResult {
+        let mut result = FfiResult::success();
+        let mut merge = unsafe { Box::from_raw(ptr_merge as *mut pb::Merge) };
+        let key_pb = key.try_into();
+        let value_pb = cstr_to_expr_pb(cstr_value);
+        if key_pb.is_ok() && value_pb.is_ok() {
+            let property = pb::merge::PropertySet { key: key_pb.unwrap(), value: value_pb.ok() };
+            match set {
+                FfiMergeSet::Match => merge.properties.push(property),
+                FfiMergeSet::OnCreate => merge.on_create.push(property),
+                FfiMergeSet::OnMatch => merge.on_match.push(property),
+            }
+        } else if key_pb.is_err() {
+            result = key_pb.err().unwrap();
+        } else {
+            result = value_pb.err().unwrap();
+        }
+        std::mem::forget(merge);
+
+        result
+    }
+
+    /// Set the alias of the matched or created vertex or edge
+    #[no_mangle]
+    pub extern "C" fn set_merge_alias(ptr_merge: *const c_void, alias: FfiAlias) -> FfiResult {
+        set_alias(ptr_merge, alias, InnerOpt::Merge)
+    }
+
+    /// Append a merge operator to the logical plan
+    #[no_mangle]
+    pub extern "C" fn append_merge_operator(
+        ptr_plan: *const c_void, ptr_merge: *const c_void, parent: i32, id: *mut i32,
+    ) -> FfiResult {
+        let merge = unsafe { Box::from_raw(ptr_merge as *mut pb::Merge) };
+        append_operator(ptr_plan, merge.as_ref().clone().into(), vec![parent], id)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn destroy_merge_operator(ptr: *const c_void) {
+        destroy_ptr::<pb::Merge>(ptr)
+    }
+}
+
 mod sink {
     use super::*;
 
@@ -2077,6 +2806,8 @@ mod sink {
             sink_target: Some(pb::sink::SinkTarget {
                 inner: Some(pb::sink::sink_target::Inner::SinkDefault(pb::SinkDefault {
                     id_name_mappings: vec![],
+                    format: 0,
+                    columns: vec![],
                 })),
             }),
         });
@@ -2171,6 +2902,8 @@ mod graph {
                 predicate: None,
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             alias: None,
             expand_opt: unsafe { std::mem::transmute::<FfiExpandOpt, i32>(expand_opt) },
@@ -2253,6 +2986,8 @@ mod graph {
                 predicate: None,
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             alias: None,
             meta_data: None,
@@ -2312,6 +3047,7 @@ mod graph {
     pub enum PathOpt {
         Arbitrary = 0,
         Simple = 1,
+        Trail = 2,
     }
 
     #[allow(dead_code)]
@@ -2547,6 +3283,124 @@ mod graph {
     pub extern "C" fn destroy_pattern_operator(ptr: *const c_void) {
         destroy_ptr::<pb::Pattern>(ptr)
     }
+
+    fn required_tag(tag: FfiNameOrId, field: &str) -> Result<common_pb::NameOrId, FfiResult> {
+        let tag_pb: Option<common_pb::NameOrId> = tag.try_into()?;
+        tag_pb.ok_or_else(|| {
+            FfiResult::new(ResultCode::MissingDataError, format!("pb::PatternMatch::{}", field))
+        })
+    }
+
+    /// Take the query parameters of the pointer if not null, which are moved into the pattern.
+    fn take_params(ptr_params: *const c_void) -> Option<pb::QueryParams> {
+        if ptr_params.is_null() {
+            None
+        } else {
+            let params = unsafe { Box::from_raw(ptr_params as *mut pb::QueryParams) };
+            Some(*params)
+        }
+    }
+
+    /// To initialize a pattern-match operator, whose triples are matched in the order chosen by the
+    /// runtime, e.g., `match(...)` of Gremlin
+    #[no_mangle]
+    pub extern "C" fn init_pattern_match_operator() -> *const c_void {
+        let pattern = Box::new(pb::PatternMatch {
+            vertices: vec![],
+            triples: vec![],
+            bound_tags: vec![],
+            alias: None,
+        });
+
+        Box::into_raw(pattern) as *const c_void
+    }
+
+    /// Add a vertex of the tag to the pattern, with the parameters of the vertices to bind if not null
+    #[no_mangle]
+    pub extern "C" fn add_pattern_match_vertex(
+        ptr_pattern: *const c_void, tag: FfiNameOrId, ptr_params: *const c_void,
+    ) -> FfiResult {
+        let params = take_params(ptr_params);
+        match required_tag(tag, "Vertex::tag") {
+            Ok(tag) => {
+                let mut pattern = unsafe { Box::from_raw(ptr_pattern as *mut pb::PatternMatch) };
+                pattern
+                    .vertices
+                    .push(pb::pattern_match::Vertex { tag: Some(tag), params });
+                std::mem::forget(pattern);
+                FfiResult::success()
+            }
+            Err(e) => e,
+        }
+    }
+
+    /// Add a triple of an edge between the vertices of the tags `src` and `dst` to the pattern, with the
+    /// parameters of the edges if not null, and the alias to bind the edge to if given
+    #[no_mangle]
+    pub extern "C" fn add_pattern_match_triple(
+        ptr_pattern: *const c_void, src: FfiNameOrId, dst: FfiNameOrId, dir: FfiDirection,
+        ptr_params: *const c_void, alias: FfiAlias,
+    ) -> FfiResult {
+        let params = take_params(ptr_params);
+        match triple_of(src, dst, dir, params, alias) {
+            Ok(triple) => {
+                let mut pattern = unsafe { Box::from_raw(ptr_pattern as *mut pb::PatternMatch) };
+                pattern.triples.push(triple);
+                std::mem::forget(pattern);
+                FfiResult::success()
+            }
+            Err(e) => e,
+        }
+    }
+
+    fn triple_of(
+        src: FfiNameOrId, dst: FfiNameOrId, dir: FfiDirection, params: Option<pb::QueryParams>,
+        alias: FfiAlias,
+    ) -> Result<pb::pattern_match::Triple, FfiResult> {
+        Ok(pb::pattern_match::Triple {
+            src: Some(required_tag(src, "Triple::src")?),
+            dst: Some(required_tag(dst, "Triple::dst")?),
+            direction: unsafe { std::mem::transmute::<FfiDirection, i32>(dir) },
+            params,
+            alias: alias.try_into()?,
+        })
+    }
+
+    /// Add a tag bound by the input tuples, from which the pattern is matched
+    #[no_mangle]
+    pub extern "C" fn add_pattern_match_bound_tag(
+        ptr_pattern: *const c_void, tag: FfiNameOrId,
+    ) -> FfiResult {
+        match required_tag(tag, "bound_tags") {
+            Ok(tag) => {
+                let mut pattern = unsafe { Box::from_raw(ptr_pattern as *mut pb::PatternMatch) };
+                pattern.bound_tags.push(tag);
+                std::mem::forget(pattern);
+                FfiResult::success()
+            }
+            Err(e) => e,
+        }
+    }
+
+    /// Set the alias of the binding maps
+    #[no_mangle]
+    pub extern "C" fn set_pattern_match_alias(ptr_pattern: *const c_void, alias: FfiAlias) -> FfiResult {
+        set_alias(ptr_pattern, alias, InnerOpt::PatternMatch)
+    }
+
+    /// Append a pattern-match operator to the logical plan
+    #[no_mangle]
+    pub extern "C" fn append_pattern_match_operator(
+        ptr_plan: *const c_void, ptr_pattern: *const c_void, parent: i32, id: *mut i32,
+    ) -> FfiResult {
+        let pattern = unsafe { Box::from_raw(ptr_pattern as *mut pb::PatternMatch) };
+        append_operator(ptr_plan, pattern.as_ref().clone().into(), vec![parent], id)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn destroy_pattern_match_operator(ptr: *const c_void) {
+        destroy_ptr::<pb::PatternMatch>(ptr)
+    }
 }
 
 mod subtask {
@@ -2652,4 +3506,77 @@ mod subtask {
     pub extern "C" fn destroy_segapply_operator(ptr: *const c_void) {
         destroy_ptr::<pb::SegmentApply>(ptr)
     }
+
+    /// To initialize a repeat operator from a root node (id) of the subtask of an iteration, which,
+    /// as of an apply operator, must be appended to the logical plan beforehand. The iterations are
+    /// unbounded if `max_iterations` is not positive, in which case the condition must be set.
+    #[no_mangle]
+    pub extern "C" fn init_repeat_operator(
+        subtask_root: i32, max_iterations: i32, until_first: bool,
+    ) -> *const c_void {
+        let repeat =
+            Box::new(pb::Repeat { subtask: subtask_root, until: None, max_iterations, until_first });
+
+        Box::into_raw(repeat) as *const c_void
+    }
+
+    /// To set the condition of the records to leave the iterations, e.g., `until(...)` of Gremlin.
+    #[no_mangle]
+    pub extern "C" fn set_repeat_until(ptr_repeat: *const c_void, cstr_until: *const c_char) -> FfiResult {
+        set_predicate(ptr_repeat, cstr_until, InnerOpt::Repeat)
+    }
+
+    /// To set the condition of the records to leave the iterations, as a pb pointer.
+    #[no_mangle]
+    pub extern "C" fn set_repeat_until_pb(
+        ptr_repeat: *const c_void, ptr_until_pb: FfiPbPointer,
+    ) -> FfiResult {
+        set_predicate_pb(ptr_repeat, ptr_until_pb, InnerOpt::Repeat)
+    }
+
+    /// Append a repeat operator to the logical plan, whose parent node must present in the plan.
+    #[no_mangle]
+    pub extern "C" fn append_repeat_operator(
+        ptr_plan: *const c_void, ptr_repeat: *const c_void, parent: i32, id: *mut i32,
+    ) -> FfiResult {
+        let repeat = unsafe { Box::from_raw(ptr_repeat as *mut pb::Repeat) };
+        append_operator(ptr_plan, repeat.as_ref().clone().into(), vec![parent], id)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn destroy_repeat_operator(ptr: *const c_void) {
+        destroy_ptr::<pb::Repeat>(ptr)
+    }
+
+    /// To initialize a coalesce operator
+    #[no_mangle]
+    pub extern "C" fn init_coalesce_operator() -> *const c_void {
+        let coalesce = Box::new(pb::Coalesce { subtasks: vec![] });
+        Box::into_raw(coalesce) as *const c_void
+    }
+
+    /// Add the root node (id) of a subtask to the coalesce, in the order of the subtasks to perform,
+    /// which, as of an apply operator, must be appended to the logical plan beforehand.
+    #[no_mangle]
+    pub extern "C" fn add_coalesce_subtask(ptr_coalesce: *const c_void, subtask_root: i32) -> FfiResult {
+        let mut coalesce = unsafe { Box::from_raw(ptr_coalesce as *mut pb::Coalesce) };
+        coalesce.subtasks.push(subtask_root);
+        std::mem::forget(coalesce);
+
+        FfiResult::success()
+    }
+
+    /// Append a coalesce operator to the logical plan, whose parent node must present in the plan.
+    #[no_mangle]
+    pub extern "C" fn append_coalesce_operator(
+        ptr_plan: *const c_void, ptr_coalesce: *const c_void, parent: i32, id: *mut i32,
+    ) -> FfiResult {
+        let coalesce = unsafe { Box::from_raw(ptr_coalesce as *mut pb::Coalesce) };
+        append_operator(ptr_plan, coalesce.as_ref().clone().into(), vec![parent], id)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn destroy_coalesce_operator(ptr: *const c_void) {
+        destroy_ptr::<pb::Coalesce>(ptr)
+    }
 }
@@ -0,0 +1,690 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! A GraphQL read API derived from the schema of the graph, so that the app developers query the graph
+//! by a typed API instead of writing Gremlin:
+//! * each entity (vertex label) is an object type with the fields of `_id`, `_label` and its columns,
+//!   and is queried by a field of `Query` of the same name;
+//! * each relation (edge label) bound to an entity is a field of the list of the entities at the other
+//!   side, named `out_{relation}` or `in_{relation}` as the direction, and suffixed by `_{entity}` if the
+//!   relation binds more than one entity at the other side;
+//! * the arguments of a field are the equality filters of the `_id` and the scalar columns of the entity,
+//!   and `limit` of the root fields.
+//!
+//! A query is resolved into a logical plan for each root field, that scans the entities, limited in
+//! total by `limit`, and projects each of them as an object of its selected fields, tagged by the
+//! response key of the root field. A selected relation is the list of the objects of the related
+//! entities, collected by a left outer `Apply` of each entity, so that the entities without any related
+//! entity are returned as well, and the sibling relations are collected apart, as the GraphQL response
+//! nests them. The query is served by the frontend by `resolve_graphql` of the ffi.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use graphql_parser::query::{
+    parse_query, Definition, Field, OperationDefinition, Selection, SelectionSet, Value,
+};
+use ir_common::expr_parse::str_to_expr_pb;
+use ir_common::generated::algebra as pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::schema as schema_pb;
+use serde_json::Value as JsonValue;
+
+use crate::error::{IrError, IrResult};
+use crate::plan::logical::{LogicalPlan, NodeId};
+use crate::plan::meta::Schema;
+
+const ID_FIELD: &str = "_id";
+const LABEL_FIELD: &str = "_label";
+const LIMIT_ARG: &str = "limit";
+
+/// The relation of an entity that is selected as a field.
+#[derive(Clone, Debug)]
+struct RelationField {
+    relation: String,
+    direction: pb::edge_expand::Direction,
+    entity: String,
+}
+
+/// The object type of an entity.
+#[derive(Clone, Debug, Default)]
+struct EntityType {
+    /// The columns and their GraphQL types.
+    columns: BTreeMap<String, &'static str>,
+    /// The relations by the names of their fields.
+    relations: BTreeMap<String, RelationField>,
+}
+
+/// A logical plan resolved from a root field of a GraphQL query.
+#[derive(Debug)]
+pub struct GraphQLPlan {
+    /// The response key of the root field, i.e., its alias or name.
+    pub field: String,
+    /// The plan projecting the objects of the root field tagged by `field`, without a sink.
+    pub plan: LogicalPlan,
+}
+
+/// The GraphQL schema derived from the schema of the graph, by which the GraphQL queries are resolved.
+pub struct GraphQLSchema {
+    entities: BTreeMap<String, EntityType>,
+}
+
+fn graphql_type(data_type: i32) -> &'static str {
+    use common_pb::DataType;
+    match DataType::from_i32(data_type) {
+        Some(DataType::Boolean) => "Boolean",
+        Some(DataType::Int32) => "Int",
+        Some(DataType::Int64) => "Long",
+        Some(DataType::Double) => "Float",
+        Some(DataType::Int32Array) => "[Int]",
+        Some(DataType::Int64Array) => "[Long]",
+        Some(DataType::DoubleArray) => "[Float]",
+        Some(DataType::StringArray) => "[String]",
+        Some(DataType::Date32) => "Date",
+        Some(DataType::Time32) => "Time",
+        Some(DataType::Timestamp) => "DateTime",
+        _ => "String",
+    }
+}
+
+fn columns_of(columns: &[schema_pb::ColumnMeta]) -> BTreeMap<String, &'static str> {
+    columns
+        .iter()
+        .filter_map(|column| {
+            column
+                .key
+                .as_ref()
+                .map(|key| (key.name.clone(), graphql_type(column.data_type)))
+        })
+        .collect()
+}
+
+impl From<&Schema> for GraphQLSchema {
+    fn from(schema: &Schema) -> Self {
+        let mut entities: BTreeMap<String, EntityType> = schema
+            .get_entities()
+            .iter()
+            .filter_map(|entity| {
+                entity
+                    .label
+                    .as_ref()
+                    .map(|label| (label, entity))
+            })
+            .map(|(label, entity)| {
+                (
+                    label.name.clone(),
+                    EntityType { columns: columns_of(&entity.columns), ..Default::default() },
+                )
+            })
+            .collect();
+        for relation in schema.get_relations() {
+            let label = match &relation.label {
+                Some(label) => &label.name,
+                None => continue,
+            };
+            // the entities at the other side of each entity, by the directions
+            let mut bound: BTreeMap<(&String, pb::edge_expand::Direction), Vec<&String>> = BTreeMap::new();
+            for pair in &relation.entity_pairs {
+                if let (Some(src), Some(dst)) = (&pair.src, &pair.dst) {
+                    bound
+                        .entry((&src.name, pb::edge_expand::Direction::Out))
+                        .or_default()
+                        .push(&dst.name);
+                    bound
+                        .entry((&dst.name, pb::edge_expand::Direction::In))
+                        .or_default()
+                        .push(&src.name);
+                }
+            }
+            for ((entity, direction), mut others) in bound {
+                others.sort();
+                others.dedup();
+                let prefix = if direction == pb::edge_expand::Direction::Out { "out" } else { "in" };
+                if let Some(entity_type) = entities.get_mut(entity) {
+                    for other in &others {
+                        let name = if others.len() > 1 {
+                            format!("{}_{}_{}", prefix, label, other)
+                        } else {
+                            format!("{}_{}", prefix, label)
+                        };
+                        let field =
+                            RelationField { relation: label.clone(), direction, entity: (*other).clone() };
+                        entity_type.relations.insert(name, field);
+                    }
+                }
+            }
+        }
+
+        GraphQLSchema { entities }
+    }
+}
+
+/// Write the arguments of filtering the given entity, i.e., `_id` and the scalar columns.
+fn write_filter_args(sdl: &mut String, entity_type: &EntityType) {
+    write!(sdl, "{}: Long", ID_FIELD).unwrap();
+    for (name, ty) in &entity_type.columns {
+        if !ty.starts_with('[') {
+            write!(sdl, ", {}: {}", name, ty).unwrap();
+        }
+    }
+}
+
+/// Turn a GraphQL value into the literal of an expression.
+fn value_to_literal(
+    value: &Value<'_, String>, variables: &serde_json::Map<String, JsonValue>,
+) -> IrResult<String> {
+    match value {
+        Value::Int(i) => i
+            .as_i64()
+            .map(|i| i.to_string())
+            .ok_or_else(|| IrError::InvalidQuery(format!("invalid integer {:?}", i))),
+        Value::Float(f) => Ok(format!("{:?}", f)),
+        Value::String(s) => Ok(string_literal(s)),
+        Value::Boolean(b) => Ok(b.to_string()),
+        Value::Variable(name) => {
+            let value = variables
+                .get(name)
+                .ok_or_else(|| IrError::MissingData(format!("variable ${}", name)))?;
+            json_to_literal(name, value)
+        }
+        _ => Err(IrError::Unsupported(format!("argument value {}", value))),
+    }
+}
+
+fn string_literal(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn json_to_literal(name: &str, value: &JsonValue) -> IrResult<String> {
+    match value {
+        JsonValue::Bool(b) => Ok(b.to_string()),
+        JsonValue::Number(n) => Ok(n.to_string()),
+        JsonValue::String(s) => Ok(string_literal(s)),
+        _ => Err(IrError::Unsupported(format!("value {} of variable ${}", value, name))),
+    }
+}
+
+/// Turn the default value of a variable into json, as if it is given by the variables.
+fn value_to_json(name: &str, value: &Value<'_, String>) -> IrResult<JsonValue> {
+    match value {
+        Value::Int(i) => Ok(i.as_i64().into()),
+        Value::Float(f) => Ok((*f).into()),
+        Value::String(s) => Ok(s.clone().into()),
+        Value::Boolean(b) => Ok((*b).into()),
+        _ => Err(IrError::Unsupported(format!("default {} of variable ${}", value, name))),
+    }
+}
+
+fn response_key(field: &Field<'_, String>) -> &String {
+    field.alias.as_ref().unwrap_or(&field.name)
+}
+
+fn query_params(table: &str, predicate: Option<String>, limit: Option<i32>) -> IrResult<pb::QueryParams> {
+    Ok(pb::QueryParams {
+        tables: vec![table.into()],
+        columns: vec![],
+        is_all_columns: false,
+        limit: limit.map(|upper| pb::Range { lower: 0, upper }),
+        predicate: predicate.map(str_to_expr_pb).transpose()?,
+        sample_ratio: 1.0,
+        extra: Default::default(),
+        valid_time: None,
+        excluded_tables: vec![],
+    })
+}
+
+/// The object of the selected fields, each of which is a key of the response and the variable of its
+/// value.
+fn object_expr(fields: Vec<(String, String)>) -> common_pb::Expression {
+    let key_vals = fields
+        .into_iter()
+        .map(|(key, var)| common_pb::VariableKeyValue {
+            key: Some(key.into()),
+            value: Some(common_pb::Variable::from(var)),
+        })
+        .collect();
+    common_pb::Expression { operators: vec![common_pb::VariableKeyValues { key_vals }.into()] }
+}
+
+/// The state of resolving a root field into a logical plan.
+struct PlanBuilder<'a> {
+    schema: &'a GraphQLSchema,
+    variables: &'a serde_json::Map<String, JsonValue>,
+    plan: LogicalPlan,
+    num_tags: usize,
+}
+
+impl<'a> PlanBuilder<'a> {
+    /// Append an operator to the `parent`, or as the root of a subtask if `None`.
+    fn append(&mut self, opr: pb::logical_plan::Operator, parent: Option<NodeId>) -> IrResult<NodeId> {
+        self.plan
+            .append_operator_as_node(opr, parent.into_iter().collect())
+    }
+
+    /// The tags are prefixed by `~`, which never conflicts with the response keys of GraphQL.
+    fn next_tag(&mut self) -> String {
+        let tag = format!("~t{}", self.num_tags);
+        self.num_tags += 1;
+        tag
+    }
+
+    /// The predicate of the arguments on an entity, and the limit if it is allowed.
+    fn filter(
+        &self, entity: &str, field: &Field<'_, String>, allow_limit: bool,
+    ) -> IrResult<(Option<String>, Option<i32>)> {
+        let entity_type = &self.schema.entities[entity];
+        let mut conditions = vec![];
+        let mut limit = None;
+        for (arg, value) in &field.arguments {
+            let literal = value_to_literal(value, self.variables)?;
+            if arg == LIMIT_ARG && allow_limit {
+                let upper = literal
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|upper| *upper > 0)
+                    .ok_or_else(|| IrError::InvalidQuery(format!("invalid limit {}", literal)))?;
+                limit = Some(upper);
+            } else if arg == ID_FIELD {
+                conditions.push(format!("@.~id == {}", literal));
+            } else if entity_type.columns.contains_key(arg) {
+                conditions.push(format!("@.{} == {}", arg, literal));
+            } else {
+                return Err(IrError::InvalidQuery(format!("unknown argument {} of {}", arg, field.name)));
+            }
+        }
+        let predicate = if conditions.is_empty() { None } else { Some(conditions.join(" && ")) };
+        Ok((predicate, limit))
+    }
+
+    /// The selected fields of the entity tagged by `tag` at the node `head`, where each selected relation
+    /// is collected by an `Apply` appended to `head`; returns the last node and the fields.
+    fn select(
+        &mut self, entity: &str, tag: &str, mut head: NodeId, selection_set: &SelectionSet<'_, String>,
+    ) -> IrResult<(NodeId, Vec<(String, String)>)> {
+        let schema = self.schema;
+        let entity_type = &schema.entities[entity];
+        let mut fields = vec![];
+        for selection in &selection_set.items {
+            let field = match selection {
+                Selection::Field(field) => field,
+                _ => Err(IrError::Unsupported("fragments in GraphQL queries".to_string()))?,
+            };
+            let var = if field.name == ID_FIELD {
+                format!("@{}.~id", tag)
+            } else if field.name == LABEL_FIELD {
+                format!("@{}.~label", tag)
+            } else if entity_type.columns.contains_key(&field.name) {
+                format!("@{}.{}", tag, field.name)
+            } else if let Some(relation) = entity_type.relations.get(&field.name) {
+                let subtask = self.relation(tag, relation, field)?;
+                let list_tag = self.next_tag();
+                let apply = pb::Apply {
+                    join_kind: pb::join::JoinKind::LeftOuter as i32,
+                    tags: vec![],
+                    subtask: subtask as i32,
+                    alias: Some(list_tag.clone().into()),
+                };
+                head = self.append(apply.into(), Some(head))?;
+                format!("@{}", list_tag)
+            } else {
+                return Err(IrError::ColumnNotExist(field.name.clone().into()));
+            };
+            fields.push((response_key(field).clone(), var));
+        }
+        if fields.is_empty() {
+            return Err(IrError::InvalidQuery(format!("no field is selected in {}", entity)));
+        }
+        Ok((head, fields))
+    }
+
+    /// The subtask collecting the objects of the entities related to the one tagged by `tag` into a list;
+    /// returns the root of the subtask.
+    fn relation(
+        &mut self, tag: &str, relation: &RelationField, field: &Field<'_, String>,
+    ) -> IrResult<NodeId> {
+        let expand = pb::EdgeExpand {
+            v_tag: Some(tag.into()),
+            direction: relation.direction as i32,
+            params: Some(query_params(&relation.relation, None, None)?),
+            alias: None,
+            expand_opt: pb::edge_expand::ExpandOpt::Edge as i32,
+            meta_data: None,
+            is_optional: false,
+        };
+        let root = self.append(expand.into(), None)?;
+        let (predicate, _) = self.filter(&relation.entity, field, false)?;
+        let opt = if relation.direction == pb::edge_expand::Direction::Out {
+            pb::get_v::VOpt::End
+        } else {
+            pb::get_v::VOpt::Start
+        };
+        let other_tag = self.next_tag();
+        let get_v = pb::GetV {
+            tag: None,
+            opt: opt as i32,
+            params: Some(query_params(&relation.entity, predicate, None)?),
+            alias: Some(other_tag.clone().into()),
+            meta_data: None,
+        };
+        let head = self.append(get_v.into(), Some(root))?;
+        let (head, fields) = self.select(&relation.entity, &other_tag, head, &field.selection_set)?;
+        let object_tag = self.next_tag();
+        let project = pb::Project {
+            mappings: vec![pb::project::ExprAlias {
+                expr: Some(object_expr(fields)),
+                alias: Some(object_tag.clone().into()),
+            }],
+            is_append: false,
+            meta_data: vec![],
+            select_tags: false,
+        };
+        let head = self.append(project.into(), Some(head))?;
+        let fold = pb::GroupBy {
+            mappings: vec![],
+            functions: vec![pb::group_by::AggFunc {
+                vars: vec![common_pb::Variable::from(format!("@{}", object_tag))],
+                aggregate: pb::group_by::agg_func::Aggregate::ToList as i32,
+                alias: Some(self.next_tag().into()),
+                udf: String::new(),
+            }],
+            meta_data: vec![],
+        };
+        self.append(fold.into(), Some(head))?;
+        Ok(root)
+    }
+}
+
+impl GraphQLSchema {
+    /// Print the schema in the GraphQL schema definition language.
+    pub fn to_sdl(&self) -> String {
+        let mut sdl = String::new();
+        for scalar in &["Long", "Date", "Time", "DateTime"] {
+            writeln!(sdl, "scalar {}", scalar).unwrap();
+        }
+        sdl.push_str("\ntype Query {\n");
+        for (name, entity_type) in &self.entities {
+            write!(sdl, "  {}(", name).unwrap();
+            write_filter_args(&mut sdl, entity_type);
+            writeln!(sdl, ", {}: Int): [{}!]!", LIMIT_ARG, name).unwrap();
+        }
+        sdl.push_str("}\n");
+        for (name, entity_type) in &self.entities {
+            writeln!(sdl, "\ntype {} {{", name).unwrap();
+            writeln!(sdl, "  {}: Long!", ID_FIELD).unwrap();
+            writeln!(sdl, "  {}: String!", LABEL_FIELD).unwrap();
+            for (column, ty) in &entity_type.columns {
+                writeln!(sdl, "  {}: {}", column, ty).unwrap();
+            }
+            for (field, relation) in &entity_type.relations {
+                write!(sdl, "  {}(", field).unwrap();
+                write_filter_args(&mut sdl, &self.entities[&relation.entity]);
+                writeln!(sdl, "): [{}!]!", relation.entity).unwrap();
+            }
+            sdl.push_str("}\n");
+        }
+        sdl
+    }
+
+    /// Resolve a GraphQL query into a logical plan for each of its root fields, where the `variables`
+    /// are the values of the variables referred by the query.
+    pub fn resolve(
+        &self, query: &str, variables: &serde_json::Map<String, JsonValue>,
+    ) -> IrResult<Vec<GraphQLPlan>> {
+        let document = parse_query::<String>(query).map_err(|e| IrError::InvalidQuery(e.to_string()))?;
+        let mut operations = document
+            .definitions
+            .into_iter()
+            .map(|definition| match definition {
+                Definition::Operation(operation) => Ok(operation),
+                Definition::Fragment(_) => {
+                    Err(IrError::Unsupported("fragments in GraphQL queries".to_string()))
+                }
+            })
+            .collect::<IrResult<Vec<_>>>()?;
+        if operations.len() != 1 {
+            return Err(IrError::InvalidQuery(format!("expect one operation, got {}", operations.len())));
+        }
+        let (selection_set, defaults) = match operations.pop().unwrap() {
+            OperationDefinition::SelectionSet(selection_set) => (selection_set, vec![]),
+            OperationDefinition::Query(query) => (query.selection_set, query.variable_definitions),
+            _ => Err(IrError::Unsupported("GraphQL operations other than query".to_string()))?,
+        };
+        let mut variables = variables.clone();
+        for definition in defaults {
+            if let Some(default) = definition.default_value {
+                if !variables.contains_key(&definition.name) {
+                    let default = value_to_json(&definition.name, &default)?;
+                    variables.insert(definition.name, default);
+                }
+            }
+        }
+
+        let mut plans = vec![];
+        for selection in &selection_set.items {
+            let field = match selection {
+                Selection::Field(field) => field,
+                _ => Err(IrError::Unsupported("fragments in GraphQL queries".to_string()))?,
+            };
+            plans.push(self.resolve_root(field, &variables)?);
+        }
+        Ok(plans)
+    }
+
+    fn resolve_root(
+        &self, field: &Field<'_, String>, variables: &serde_json::Map<String, JsonValue>,
+    ) -> IrResult<GraphQLPlan> {
+        if !self.entities.contains_key(&field.name) {
+            return Err(IrError::TableNotExist(field.name.clone().into()));
+        }
+        let mut builder =
+            PlanBuilder { schema: self, variables, plan: LogicalPlan::with_root(), num_tags: 0 };
+        let (predicate, limit) = builder.filter(&field.name, field, true)?;
+        let tag = builder.next_tag();
+        let scan = pb::Scan {
+            scan_opt: 0,
+            alias: Some(tag.clone().into()),
+            // limited by each partition, ahead of the limit in total
+            params: Some(query_params(&field.name, predicate, limit)?),
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        };
+        let mut head = builder.append(scan.into(), Some(0))?;
+        if let Some(upper) = limit {
+            let limit = pb::Limit { range: Some(pb::Range { lower: 0, upper }) };
+            head = builder.append(limit.into(), Some(head))?;
+        }
+        let (head, fields) = builder.select(&field.name, &tag, head, &field.selection_set)?;
+        let key = response_key(field).clone();
+        let project = pb::Project {
+            mappings: vec![pb::project::ExprAlias {
+                expr: Some(object_expr(fields)),
+                alias: Some(key.clone().into()),
+            }],
+            is_append: false,
+            meta_data: vec![],
+            select_tags: false,
+        };
+        builder.append(project.into(), Some(head))?;
+
+        Ok(GraphQLPlan { field: key, plan: builder.plan })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ir_common::generated::algebra::logical_plan::operator::Opr;
+
+    use super::*;
+    use crate::JsonIO;
+
+    fn modern_schema() -> Schema {
+        let json = r#"{
+            "entities": [
+                {"label": {"id": 0, "name": "person"}, "columns": [
+                    {"key": {"id": 0, "name": "name"}, "data_type": 4, "is_primary_key": false},
+                    {"key": {"id": 1, "name": "age"}, "data_type": 1, "is_primary_key": false}
+                ]},
+                {"label": {"id": 1, "name": "software"}, "columns": [
+                    {"key": {"id": 0, "name": "name"}, "data_type": 4, "is_primary_key": false},
+                    {"key": {"id": 2, "name": "lang"}, "data_type": 4, "is_primary_key": false}
+                ]}
+            ],
+            "relations": [
+                {"label": {"id": 0, "name": "knows"}, "entity_pairs": [
+                    {"src": {"id": 0, "name": "person"}, "dst": {"id": 0, "name": "person"}}
+                ], "columns": []},
+                {"label": {"id": 1, "name": "created"}, "entity_pairs": [
+                    {"src": {"id": 0, "name": "person"}, "dst": {"id": 1, "name": "software"}}
+                ], "columns": []}
+            ],
+            "is_table_id": false,
+            "is_column_id": false
+        }"#;
+        Schema::from_json(json.as_bytes()).unwrap()
+    }
+
+    fn oprs(plan: &LogicalPlan) -> Vec<Opr> {
+        let plan_pb: pb::LogicalPlan = plan.clone().into();
+        plan_pb
+            .nodes
+            .into_iter()
+            .map(|node| node.opr.unwrap().opr.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn schema_to_sdl() {
+        let schema = GraphQLSchema::from(&modern_schema());
+        let sdl = schema.to_sdl();
+        assert!(sdl.contains("  person(_id: Long, age: Int, name: String, limit: Int): [person!]!\n"));
+        assert!(sdl.contains("  lang: String\n"));
+        assert!(sdl.contains("  out_knows(_id: Long, age: Int, name: String): [person!]!\n"));
+        assert!(sdl.contains("  in_knows(_id: Long, age: Int, name: String): [person!]!\n"));
+        assert!(sdl.contains("  out_created(_id: Long, lang: String, name: String): [software!]!\n"));
+        assert!(sdl.contains("  in_created(_id: Long, age: Int, name: String): [person!]!\n"));
+    }
+
+    #[test]
+    fn resolve_query() {
+        let schema = GraphQLSchema::from(&modern_schema());
+        let query = r#"query($name: String) {
+            person(name: $name, limit: 10) { _id name friends: out_knows(age: 29) { name } }
+        }"#;
+        let variables = serde_json::json!({"name": "marko"});
+        let plans = schema
+            .resolve(query, variables.as_object().unwrap())
+            .unwrap();
+        assert_eq!(plans.len(), 1);
+        assert_eq!(plans[0].field, "person");
+        let oprs = oprs(&plans[0].plan);
+        // root, scan, limit, the subtask of `friends`, its apply, and the project of the objects
+        assert_eq!(oprs.len(), 9);
+        match &oprs[1] {
+            Opr::Scan(scan) => {
+                let params = scan.params.as_ref().unwrap();
+                assert_eq!(params.limit, Some(pb::Range { lower: 0, upper: 10 }));
+                assert_eq!(
+                    params.predicate,
+                    Some(str_to_expr_pb("@.name == \"marko\"".to_string()).unwrap())
+                );
+            }
+            _ => panic!("should be scan"),
+        }
+        // limited in total rather than by each partition
+        match &oprs[2] {
+            Opr::Limit(limit) => assert_eq!(limit.range, Some(pb::Range { lower: 0, upper: 10 })),
+            _ => panic!("should be limit"),
+        }
+        assert!(matches!(&oprs[3], Opr::Edge(_)));
+        assert!(matches!(&oprs[4], Opr::Vertex(_)));
+        assert!(matches!(&oprs[5], Opr::Project(_)));
+        match &oprs[6] {
+            Opr::GroupBy(group) => {
+                assert!(group.mappings.is_empty());
+                assert_eq!(group.functions[0].aggregate, pb::group_by::agg_func::Aggregate::ToList as i32);
+            }
+            _ => panic!("should be group"),
+        }
+        // the persons without any friend are returned as well
+        match &oprs[7] {
+            Opr::Apply(apply) => {
+                assert_eq!(apply.join_kind, pb::join::JoinKind::LeftOuter as i32);
+                assert_eq!(apply.subtask, 3);
+            }
+            _ => panic!("should be apply"),
+        }
+        match &oprs[8] {
+            Opr::Project(project) => {
+                assert_eq!(project.mappings[0].alias, Some("person".into()));
+                let expr = project.mappings[0].expr.as_ref().unwrap();
+                match &expr.operators[0].item {
+                    Some(common_pb::expr_opr::Item::Map(map)) => {
+                        let keys: Vec<_> = map
+                            .key_vals
+                            .iter()
+                            .map(|key_val| key_val.key.clone().unwrap())
+                            .collect();
+                        let expected: Vec<common_pb::Value> = vec![
+                            "_id".to_string().into(),
+                            "name".to_string().into(),
+                            "friends".to_string().into(),
+                        ];
+                        assert_eq!(keys, expected);
+                    }
+                    _ => panic!("should be map"),
+                }
+            }
+            _ => panic!("should be project"),
+        }
+    }
+
+    #[test]
+    fn resolve_sibling_relations() {
+        let schema = GraphQLSchema::from(&modern_schema());
+        let query = "{ person { name out_knows { name } out_created { lang } } }";
+        let plans = schema
+            .resolve(query, &serde_json::Map::new())
+            .unwrap();
+        let applies: Vec<_> = oprs(&plans[0].plan)
+            .into_iter()
+            .filter_map(|opr| match opr {
+                Opr::Apply(apply) => Some(apply),
+                _ => None,
+            })
+            .collect();
+        // each relation is collected by its own subtask, rather than the product of both
+        assert_eq!(applies.len(), 2);
+        assert_ne!(applies[0].subtask, applies[1].subtask);
+        assert_ne!(applies[0].alias, applies[1].alias);
+    }
+
+    #[test]
+    fn resolve_invalid_query() {
+        let schema = GraphQLSchema::from(&modern_schema());
+        let empty = serde_json::Map::new();
+        assert!(matches!(schema.resolve("{ movie { _id } }", &empty), Err(IrError::TableNotExist(_))));
+        assert!(matches!(schema.resolve("{ person { lang } }", &empty), Err(IrError::ColumnNotExist(_))));
+        assert!(matches!(
+            schema.resolve("{ person(name: $name) { _id } }", &empty),
+            Err(IrError::MissingData(_))
+        ));
+        assert!(matches!(schema.resolve("{ person { ", &empty), Err(IrError::InvalidQuery(_))));
+        assert!(matches!(
+            schema.resolve("{ person(limit: 0) { _id } }", &empty),
+            Err(IrError::InvalidQuery(_))
+        ));
+    }
+}
@@ -14,7 +14,7 @@
 //! limitations under the License.
 
 use std::cell::RefCell;
-use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
 use std::iter::FromIterator;
@@ -55,6 +55,10 @@ impl Node {
         Node { id, opr, parents: BTreeSet::new(), children: BTreeSet::new() }
     }
 
+    pub fn get_id(&self) -> NodeId {
+        self.id
+    }
+
     pub fn add_child(&mut self, child_id: NodeId) {
         self.children.insert(child_id);
     }
@@ -154,6 +158,27 @@ impl TryFrom<pb::LogicalPlan> for LogicalPlan {
                     Some(pb::logical_plan::operator::Opr::Apply(apply)) => {
                         apply.subtask = id_map[&(apply.subtask as NodeId)] as PbNodeId;
                     }
+                    Some(pb::logical_plan::operator::Opr::Repeat(repeat)) => {
+                        repeat.subtask = id_map[&(repeat.subtask as NodeId)] as PbNodeId;
+                    }
+                    Some(pb::logical_plan::operator::Opr::Coalesce(coalesce)) => {
+                        for subtask in coalesce.subtasks.iter_mut() {
+                            *subtask = id_map[&(*subtask as NodeId)] as PbNodeId;
+                        }
+                    }
+                    Some(pb::logical_plan::operator::Opr::Union(union)) => {
+                        // the parents are kept in order, as the indices of the branches
+                        for parent in union.parents.iter_mut() {
+                            *parent = *id_map
+                                .get(&(*parent as NodeId))
+                                .ok_or_else(|| {
+                                    ParsePbError::ParseError(format!(
+                                        "the parent node's id {:?} of the union is not in the plan",
+                                        parent
+                                    ))
+                                })? as PbNodeId;
+                        }
+                    }
                     _ => {}
                 }
                 let mut parent_ids = parents
@@ -202,6 +227,22 @@ impl From<LogicalPlan> for pb::LogicalPlan {
                 Some(pb::logical_plan::operator::Opr::Apply(apply)) => {
                     apply.subtask = id_map[&(apply.subtask as NodeId)] as PbNodeId;
                 }
+                Some(pb::logical_plan::operator::Opr::Repeat(repeat)) => {
+                    repeat.subtask = id_map[&(repeat.subtask as NodeId)] as PbNodeId;
+                }
+                Some(pb::logical_plan::operator::Opr::Coalesce(coalesce)) => {
+                    for subtask in coalesce.subtasks.iter_mut() {
+                        *subtask = id_map[&(*subtask as NodeId)] as PbNodeId;
+                    }
+                }
+                Some(pb::logical_plan::operator::Opr::Union(union)) => {
+                    // the parents that have been removed from the plan are dropped
+                    union.parents = union
+                        .parents
+                        .iter()
+                        .filter_map(|parent| id_map.get(&(*parent as NodeId)).cloned())
+                        .collect();
+                }
                 _ => {}
             }
             node_pb.opr = Some(operator);
@@ -766,29 +807,34 @@ impl LogicalPlan {
         }
     }
 
-    /// Given a node that contains a subtask, which is typically an  `Apply` operator,
+    /// Given a node that contains a subtask, which is typically an  `Apply` or a `Repeat` operator,
     /// try to extract the subtask as a logical plan.
     ///
     pub fn extract_subplan(&self, node: NodeType) -> Option<LogicalPlan> {
         let node_borrow = node.borrow();
-        match &node_borrow.opr.opr {
-            Some(pb::logical_plan::operator::Opr::Apply(apply_opr)) => {
-                if let Some(from_node) = self.get_node(apply_opr.subtask as NodeId) {
-                    let mut curr_node = from_node.clone();
-                    while let Some(to_node) = curr_node
-                        .clone()
-                        .borrow()
-                        .get_first_child()
-                        .and_then(|node_id| self.get_node(node_id))
-                    {
-                        curr_node = to_node.clone();
-                    }
-                    self.subplan(from_node, curr_node, true)
-                } else {
-                    None
-                }
-            }
+        let subtask = match &node_borrow.opr.opr {
+            Some(pb::logical_plan::operator::Opr::Apply(apply_opr)) => Some(apply_opr.subtask),
+            Some(pb::logical_plan::operator::Opr::Repeat(repeat_opr)) => Some(repeat_opr.subtask),
             _ => None,
+        };
+        subtask.and_then(|subtask| self.extract_subplan_from(subtask as NodeId))
+    }
+
+    /// Extract the subtask from its root node (id) as a logical plan, e.g., the ones of a `Coalesce`.
+    pub fn extract_subplan_from(&self, subtask: NodeId) -> Option<LogicalPlan> {
+        if let Some(from_node) = self.get_node(subtask) {
+            let mut curr_node = from_node.clone();
+            while let Some(to_node) = curr_node
+                .clone()
+                .borrow()
+                .get_first_child()
+                .and_then(|node_id| self.get_node(node_id))
+            {
+                curr_node = to_node.clone();
+            }
+            self.subplan(from_node, curr_node, true)
+        } else {
+            None
         }
     }
 }
@@ -980,6 +1026,86 @@ fn get_table_id_from_pb(schema: &Schema, name: &common_pb::NameOrId) -> Option<K
     })
 }
 
+/// Resolve the excluded tables against the schema, into the tables of all the entities (`is_entity`) or
+/// relations, or of the given tables, but the excluded ones, e.g., `any edge label except knows`. The
+/// tables are resolved as ids if the schema maps the tables as ids, and otherwise as the names.
+fn preprocess_excluded_tables(
+    params: &mut pb::QueryParams, meta: &StoreMeta, is_entity: bool,
+) -> IrResult<()> {
+    if params.excluded_tables.is_empty() {
+        return Ok(());
+    }
+    let schema = meta
+        .schema
+        .as_ref()
+        .ok_or_else(|| IrError::Unsupported("excluding the labels without the schema".to_string()))?;
+    // the labels of the tables named if the schema doesn't map the tables as ids
+    let labels: BTreeMap<i32, String> = if is_entity {
+        schema
+            .get_entities()
+            .iter()
+            .filter_map(|entity| entity.label.as_ref())
+            .map(|label| (label.id, label.name.clone()))
+            .collect()
+    } else {
+        schema
+            .get_relations()
+            .iter()
+            .filter_map(|relation| relation.label.as_ref())
+            .map(|label| (label.id, label.name.clone()))
+            .collect()
+    };
+    let all_tables: Vec<common_pb::NameOrId> = if schema.is_table_id() {
+        let table_ids = if is_entity { schema.get_entity_ids() } else { schema.get_relation_ids() };
+        table_ids
+            .into_iter()
+            .map(|table_id| table_id.into())
+            .collect()
+    } else {
+        let names: BTreeSet<&String> = labels.values().collect();
+        names
+            .into_iter()
+            .map(|name| name.clone().into())
+            .collect()
+    };
+    let resolve = |table: &common_pb::NameOrId| -> IrResult<common_pb::NameOrId> {
+        let resolved: Option<common_pb::NameOrId> = if schema.is_table_id() {
+            get_table_id_from_pb(schema, table).map(|table_id| table_id.into())
+        } else {
+            match table.item.as_ref() {
+                Some(common_pb::name_or_id::Item::Id(id)) => labels.get(id).map(|name| name.clone().into()),
+                _ => Some(table.clone()),
+            }
+        };
+        match resolved {
+            Some(resolved) if all_tables.contains(&resolved) => Ok(resolved),
+            _ => Err(IrError::TableNotExist(table.clone().try_into()?)),
+        }
+    };
+    let mut excluded = vec![];
+    for table in params.excluded_tables.iter() {
+        excluded.push(resolve(table)?);
+    }
+    let mut tables = vec![];
+    if params.tables.is_empty() {
+        tables = all_tables.clone();
+    } else {
+        for table in params.tables.iter() {
+            tables.push(resolve(table)?);
+        }
+    }
+    tables.retain(|table| !excluded.contains(table));
+    if tables.is_empty() {
+        // as no tables means any of the tables
+        Err(IrError::InvalidQuery(format!("all the labels are excluded by {:?}", params.excluded_tables)))?
+    }
+    debug!("tables: {:?} excluding {:?} -> {:?}", params.tables, params.excluded_tables, tables);
+    params.tables = tables;
+    params.excluded_tables.clear();
+
+    Ok(())
+}
+
 fn get_column_id_from_pb(schema: &Schema, name: &common_pb::NameOrId) -> Option<KeyId> {
     name.item.as_ref().and_then(|item| match item {
         common_pb::name_or_id::Item::Name(name) => schema.get_column_id(name),
@@ -1065,6 +1191,38 @@ fn preprocess_label(
     Ok(())
 }
 
+/// The projection of multiple tags of Gremlin's `select('a', 'b').by('name')`, as `[@a.name, @b.name]` with
+/// `select_tags` set, is a map keyed by the tags, as their names are lost once the tags are turned into ids.
+fn select_tags_as_map(expr: &mut common_pb::Expression) {
+    use common_pb::expr_opr::Item;
+    let map = match expr.operators.as_mut_slice() {
+        [common_pb::ExprOpr { item: Some(Item::Vars(vars)), .. }]
+            if vars.keys.len() > 1 && vars.keys.iter().all(|var| var.tag.is_some()) =>
+        {
+            let key_vals = std::mem::take(&mut vars.keys)
+                .into_iter()
+                .map(|var| {
+                    let key = match var
+                        .tag
+                        .as_ref()
+                        .and_then(|tag| tag.item.as_ref())
+                    {
+                        Some(common_pb::name_or_id::Item::Name(name)) => name.clone().into(),
+                        Some(common_pb::name_or_id::Item::Id(id)) => (*id).into(),
+                        None => common_pb::Value::default(),
+                    };
+                    common_pb::VariableKeyValue { key: Some(key), value: Some(var) }
+                })
+                .collect();
+            Some(common_pb::VariableKeyValues { key_vals })
+        }
+        _ => None,
+    };
+    if let Some(map) = map {
+        expr.operators[0].item = Some(Item::Map(map));
+    }
+}
+
 fn preprocess_expression(
     expr: &mut common_pb::Expression, meta: &StoreMeta, plan_meta: &mut PlanMeta, is_predicate: bool,
 ) -> IrResult<()> {
@@ -1115,6 +1273,17 @@ fn preprocess_expression(
                     }
                     count = 0;
                 }
+                common_pb::expr_opr::Item::Udf(udf) => {
+                    // the arguments are operands, which are preprocessed as an expression
+                    let mut args = common_pb::Expression { operators: std::mem::take(&mut udf.args) };
+                    preprocess_expression(&mut args, meta, plan_meta, false)?;
+                    udf.args = args.operators;
+                    count = 0;
+                }
+                common_pb::expr_opr::Item::ElementMap(element_map) => {
+                    preprocess_element_map(element_map, meta, plan_meta)?;
+                    count = 0;
+                }
                 _ => count = 0,
             }
         }
@@ -1123,6 +1292,55 @@ fn preprocess_expression(
     Ok(())
 }
 
+/// The keys of an element map are the columns of its tag, where a key is its name if not given, or all the
+/// columns of the schema if none is given and the schema maps the columns as ids.
+fn preprocess_element_map(
+    element_map: &mut common_pb::ElementMap, meta: &StoreMeta, plan_meta: &mut PlanMeta,
+) -> IrResult<()> {
+    let tag = element_map.tag.clone();
+    let mut to_var = |property: common_pb::property::Item| -> IrResult<common_pb::Variable> {
+        let mut var = common_pb::Variable {
+            tag: tag.clone(),
+            property: Some(common_pb::Property { item: Some(property) }),
+            node_type: None,
+        };
+        preprocess_var(&mut var, meta, plan_meta, false)?;
+        Ok(var)
+    };
+    if element_map.keys.is_empty() {
+        let var = to_var(common_pb::property::Item::All(common_pb::AllKey {}))?;
+        element_map.tag = var.tag;
+        // keyed by the names, rather than the ids of the properties got, if the schema maps the columns
+        if let Some(schema) = meta
+            .schema
+            .as_ref()
+            .filter(|schema| schema.is_column_id())
+        {
+            element_map.keys = schema
+                .get_columns()
+                .map(|(name, column_id)| common_pb::element_map::Key {
+                    name: name.clone(),
+                    key: Some((*column_id).into()),
+                })
+                .collect();
+        }
+    } else {
+        for key in element_map.keys.iter_mut() {
+            let name = key
+                .key
+                .take()
+                .unwrap_or_else(|| key.name.as_str().into());
+            let var = to_var(common_pb::property::Item::Key(name))?;
+            if let Some(common_pb::property::Item::Key(new_key)) = var.property.and_then(|p| p.item) {
+                key.key = Some(new_key);
+            }
+            element_map.tag = var.tag;
+        }
+    }
+
+    Ok(())
+}
+
 fn preprocess_params(
     params: &mut pb::QueryParams, meta: &StoreMeta, plan_meta: &mut PlanMeta,
 ) -> IrResult<()> {
@@ -1225,6 +1443,9 @@ impl AsLogical for pb::Project {
             if let Some(expr) = &mut mapping.expr {
                 let mut is_project_as_head = false;
                 let curr_node = plan_meta.get_curr_node();
+                if self.select_tags {
+                    select_tags_as_map(expr);
+                }
                 preprocess_expression(expr, meta, plan_meta, false)?;
                 if expr.operators.len() == 1 {
                     if let common_pb::ExprOpr { item: Some(Item::Var(var)), .. } =
@@ -1293,6 +1514,8 @@ impl AsLogical for pb::Scan {
             plan_meta.set_tag_nodes(tag_id, vec![plan_meta.get_curr_node()]);
         }
         if let Some(params) = self.params.as_mut() {
+            // the relations are excluded by a scan of the edges, and otherwise the entities
+            preprocess_excluded_tables(params, meta, self.scan_opt != 1)?;
             if self.idx_predicate.is_none() {
                 if let Some(expr) = params.predicate.as_mut() {
                     let idx_pred =
@@ -1323,6 +1546,7 @@ impl AsLogical for pb::EdgeExpand {
         let curr_node = plan_meta.get_curr_node();
         plan_meta.refer_to_nodes(curr_node, vec![curr_node]);
         if let Some(params) = self.params.as_mut() {
+            preprocess_excluded_tables(params, meta, false)?;
             preprocess_params(params, meta, plan_meta)?;
         }
         if let Some(alias) = self.alias.as_mut() {
@@ -1368,6 +1592,7 @@ impl AsLogical for pb::GetV {
         let curr_node = plan_meta.get_curr_node();
         plan_meta.refer_to_nodes(curr_node, vec![curr_node]);
         if let Some(params) = self.params.as_mut() {
+            preprocess_excluded_tables(params, meta, true)?;
             preprocess_params(params, meta, plan_meta)?;
         }
         if let Some(alias) = self.alias.as_mut() {
@@ -1561,6 +1786,16 @@ impl AsLogical for pb::As {
     }
 }
 
+impl AsLogical for pb::Union {
+    fn preprocess(&mut self, _meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if let Some(alias) = self.branch_alias.as_mut() {
+            let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+            plan_meta.set_tag_nodes(tag_id, vec![plan_meta.get_curr_node()]);
+        }
+        Ok(())
+    }
+}
+
 impl AsLogical for pb::Join {
     fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
         for left_key in self.left_keys.iter_mut() {
@@ -1582,17 +1817,297 @@ impl AsLogical for pb::Sample {
             preprocess_var(weight_var, meta, plan_meta, false)?;
             process_columns_meta(plan_meta, false)?;
         }
+        if let Some(key_var) = &mut self.sample_key {
+            preprocess_var(key_var, meta, plan_meta, false)?;
+            process_columns_meta(plan_meta, false)?;
+        }
         Ok(())
     }
 }
 
-impl AsLogical for pb::Sink {
+impl AsLogical for pb::GraphAlgorithm {
+    fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if let Some(params) = self.params.as_mut() {
+            preprocess_params(params, meta, plan_meta)?;
+        }
+        if let Some(weight) = self.weight.as_mut() {
+            preprocess_expression(weight, meta, plan_meta, true)?;
+        }
+        if let Some(source) = self.source.as_mut() {
+            preprocess_expression(source, meta, plan_meta, true)?;
+        }
+        if let Some(alias) = self.alias.as_mut() {
+            let alias_id = get_or_set_tag_id(alias, plan_meta)?;
+            plan_meta.set_tag_nodes(alias_id, vec![plan_meta.get_curr_node()]);
+        }
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::KHop {
+    fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        let curr_node = plan_meta.get_curr_node();
+        plan_meta.refer_to_nodes(curr_node, vec![curr_node]);
+        if let Some(start_tag) = self.start_tag.as_mut() {
+            get_or_set_tag_id(start_tag, plan_meta)?;
+        }
+        if let Some(params) = self.params.as_mut() {
+            preprocess_params(params, meta, plan_meta)?;
+        }
+        if let Some(alias) = self.alias.as_mut() {
+            let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+            plan_meta.set_tag_nodes(tag_id, vec![plan_meta.get_curr_node()]);
+        }
+        for agg_fn in self.aggregates.iter_mut() {
+            for var in agg_fn.vars.iter_mut() {
+                preprocess_var(var, meta, plan_meta, false)?;
+            }
+            if let Some(alias) = agg_fn.alias.as_mut() {
+                let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+                plan_meta.set_tag_nodes(tag_id, vec![plan_meta.get_curr_node()]);
+            }
+        }
+        process_columns_meta(plan_meta, false)?;
+
+        Ok(())
+    }
+}
+
+/// Preprocess the label of the vertex or the edge to write.
+fn preprocess_write_label(label: &mut common_pb::NameOrId, meta: &StoreMeta) -> IrResult<()> {
+    if let Some(schema) = &meta.schema {
+        if schema.is_table_id() {
+            if let Some(new_label) = get_table_id_from_pb(schema, label) {
+                *label = new_label.into();
+            } else {
+                return Err(IrError::TableNotExist(label.clone().try_into()?));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Preprocess the keys and the values of the properties to write.
+fn preprocess_property_sets(
+    property_sets: &mut [pb::merge::PropertySet], meta: &StoreMeta, plan_meta: &mut PlanMeta,
+) -> IrResult<()> {
+    for property in property_sets.iter_mut() {
+        if let Some(schema) = &meta.schema {
+            if schema.is_column_id() {
+                if let Some(key) = property.key.as_mut() {
+                    *key = get_column_id_from_pb(schema, key)
+                        .unwrap_or(INVALID_META_ID)
+                        .into();
+                }
+            }
+        }
+        if let Some(value) = property.value.as_mut() {
+            preprocess_expression(value, meta, plan_meta, false)?;
+        }
+    }
+    Ok(())
+}
+
+impl AsLogical for pb::Merge {
+    fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if let Some(label) = self.label.as_mut() {
+            preprocess_write_label(label, meta)?;
+        }
+        if let Some(src_tag) = self.src_tag.as_mut() {
+            get_or_set_tag_id(src_tag, plan_meta)?;
+        }
+        if let Some(dst_tag) = self.dst_tag.as_mut() {
+            get_or_set_tag_id(dst_tag, plan_meta)?;
+        }
+        preprocess_property_sets(&mut self.properties, meta, plan_meta)?;
+        preprocess_property_sets(&mut self.on_create, meta, plan_meta)?;
+        preprocess_property_sets(&mut self.on_match, meta, plan_meta)?;
+        if let Some(alias) = self.alias.as_mut() {
+            let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+            plan_meta.set_tag_nodes(tag_id, vec![plan_meta.get_curr_node()]);
+        }
+
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::Mutate {
+    fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        use pb::mutate::Kind;
+        match self.kind.as_mut() {
+            Some(Kind::AddV(add_v)) => {
+                if let Some(label) = add_v.label.as_mut() {
+                    preprocess_write_label(label, meta)?;
+                }
+                preprocess_property_sets(&mut add_v.properties, meta, plan_meta)?;
+            }
+            Some(Kind::AddE(add_e)) => {
+                if let Some(label) = add_e.label.as_mut() {
+                    preprocess_write_label(label, meta)?;
+                }
+                if let Some(src_tag) = add_e.src_tag.as_mut() {
+                    get_or_set_tag_id(src_tag, plan_meta)?;
+                }
+                if let Some(dst_tag) = add_e.dst_tag.as_mut() {
+                    get_or_set_tag_id(dst_tag, plan_meta)?;
+                }
+                preprocess_property_sets(&mut add_e.properties, meta, plan_meta)?;
+            }
+            Some(Kind::Property(property)) => {
+                if let Some(tag) = property.tag.as_mut() {
+                    get_or_set_tag_id(tag, plan_meta)?;
+                }
+                preprocess_property_sets(&mut property.properties, meta, plan_meta)?;
+            }
+            Some(Kind::Drop(drop)) => {
+                if let Some(tag) = drop.tag.as_mut() {
+                    get_or_set_tag_id(tag, plan_meta)?;
+                }
+            }
+            None => {}
+        }
+        if let Some(alias) = self.alias.as_mut() {
+            let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+            plan_meta.set_tag_nodes(tag_id, vec![plan_meta.get_curr_node()]);
+        }
+
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::StoreVar {
+    fn preprocess(&mut self, _meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if let Some(tag) = self.tag.as_mut() {
+            get_or_set_tag_id(tag, plan_meta)?;
+        }
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::LoadVar {
+    fn preprocess(&mut self, _meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if let Some(alias) = self.alias.as_mut() {
+            let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+            plan_meta.set_tag_nodes(tag_id, vec![plan_meta.get_curr_node()]);
+        }
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::SideEffect {
+    fn preprocess(&mut self, _meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if let Some(tag) = self.tag.as_mut() {
+            get_or_set_tag_id(tag, plan_meta)?;
+        }
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::Cap {
     fn preprocess(&mut self, _meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if let Some(alias) = self.alias.as_mut() {
+            let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+            plan_meta.set_tag_nodes(tag_id, vec![plan_meta.get_curr_node()]);
+        }
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::Sack {
+    fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        match self.inner.as_mut() {
+            Some(pb::sack::Inner::Init(pb::sack::Init { initial: Some(expr) }))
+            | Some(pb::sack::Inner::Update(pb::sack::Update { by: Some(expr), .. })) => {
+                // the sacks are updated in place of the records, as the columns are filtered on
+                preprocess_expression(expr, meta, plan_meta, false)?;
+                process_columns_meta(plan_meta, true)?;
+            }
+            Some(pb::sack::Inner::Get(pb::sack::Get { alias: Some(alias) })) => {
+                let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+                plan_meta.set_tag_nodes(tag_id, vec![plan_meta.get_curr_node()]);
+            }
+            Some(_) => {}
+            None => Err(IrError::MissingData("Sack::inner".to_string()))?,
+        }
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::Tree {
+    fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        for level in self.levels.iter_mut() {
+            preprocess_var(level, meta, plan_meta, false)?;
+        }
+        process_columns_meta(plan_meta, false)?;
+        if let Some(alias) = self.alias.as_mut() {
+            let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+            plan_meta.set_tag_nodes(tag_id, vec![plan_meta.get_curr_node()]);
+        }
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::PatternMatch {
+    fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        let curr_node = plan_meta.get_curr_node();
+        plan_meta.refer_to_nodes(curr_node, vec![curr_node]);
+        for tag in self.bound_tags.iter_mut() {
+            get_or_set_tag_id(tag, plan_meta)?;
+        }
+        for vertex in self.vertices.iter_mut() {
+            let tag = vertex
+                .tag
+                .as_mut()
+                .ok_or_else(|| IrError::MissingData("PatternMatch::Vertex::tag".to_string()))?;
+            let tag_id = get_or_set_tag_id(tag, plan_meta)?;
+            plan_meta.set_tag_nodes(tag_id, vec![curr_node]);
+            if let Some(params) = vertex.params.as_mut() {
+                preprocess_excluded_tables(params, meta, true)?;
+                preprocess_params(params, meta, plan_meta)?;
+            }
+        }
+        for triple in self.triples.iter_mut() {
+            for tag in [triple.src.as_mut(), triple.dst.as_mut()] {
+                let tag =
+                    tag.ok_or_else(|| IrError::MissingData("PatternMatch::Triple::src/dst".to_string()))?;
+                get_or_set_tag_id(tag, plan_meta)?;
+            }
+            if let Some(params) = triple.params.as_mut() {
+                preprocess_excluded_tables(params, meta, false)?;
+                preprocess_params(params, meta, plan_meta)?;
+            }
+            if let Some(alias) = triple.alias.as_mut() {
+                let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+                plan_meta.set_tag_nodes(tag_id, vec![curr_node]);
+            }
+        }
+        process_columns_meta(plan_meta, false)?;
+        if let Some(alias) = self.alias.as_mut() {
+            let tag_id = get_or_set_tag_id(alias, plan_meta)?;
+            plan_meta.set_tag_nodes(tag_id, vec![curr_node]);
+        }
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::Sink {
+    fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
         for tag_key in self.tags.iter_mut() {
             if let Some(tag) = tag_key.key.as_mut() {
                 get_or_set_tag_id(tag, plan_meta)?;
             }
         }
+        if let Some(pb::sink::SinkTarget {
+            inner: Some(pb::sink::sink_target::Inner::SinkDefault(sink_default)),
+        }) = self.sink_target.as_mut()
+        {
+            // the columns of the tabular results must be materialized as the variables of `Project`
+            for column in sink_default.columns.iter_mut() {
+                if let Some(var) = column.var.as_mut() {
+                    preprocess_var(var, meta, plan_meta, false)?;
+                }
+            }
+        }
         Ok(())
     }
 }
@@ -1613,6 +2128,33 @@ impl AsLogical for pb::Apply {
     }
 }
 
+impl AsLogical for pb::Repeat {
+    fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        // the head is the output of the subtask rather than of the parent
+        let curr_node = plan_meta.get_curr_node();
+        plan_meta.refer_to_nodes(curr_node, vec![curr_node]);
+        if let Some(until) = self.until.as_mut() {
+            preprocess_expression(until, meta, plan_meta, false)?;
+            process_columns_meta(plan_meta, true)?;
+        } else if self.max_iterations <= 0 {
+            Err(IrError::MissingData("`pb::Repeat::until` or `pb::Repeat::max_iterations`".to_string()))?
+        }
+        Ok(())
+    }
+}
+
+impl AsLogical for pb::Coalesce {
+    fn preprocess(&mut self, _meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if self.subtasks.is_empty() {
+            Err(IrError::MissingData("`pb::Coalesce::subtasks`".to_string()))?
+        }
+        // the head is the output of the subtasks rather than of the parent
+        let curr_node = plan_meta.get_curr_node();
+        plan_meta.refer_to_nodes(curr_node, vec![curr_node]);
+        Ok(())
+    }
+}
+
 impl AsLogical for pb::Pattern {
     fn preprocess(&mut self, meta: &StoreMeta, plan_meta: &mut PlanMeta) -> IrResult<()> {
         for sentence in self.sentences.iter_mut() {
@@ -1685,11 +2227,25 @@ impl AsLogical for pb::logical_plan::Operator {
                 Opr::Limit(opr) => opr.preprocess(meta, plan_meta)?,
                 Opr::As(opr) => opr.preprocess(meta, plan_meta)?,
                 Opr::Join(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::Union(opr) => opr.preprocess(meta, plan_meta)?,
                 Opr::Sink(opr) => opr.preprocess(meta, plan_meta)?,
                 Opr::Apply(opr) => opr.preprocess(meta, plan_meta)?,
                 Opr::Pattern(opr) => opr.preprocess(meta, plan_meta)?,
                 Opr::Unfold(opr) => opr.preprocess(meta, plan_meta)?,
                 Opr::Sample(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::Algorithm(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::KHop(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::Merge(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::Mutate(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::StoreVar(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::LoadVar(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::SideEffect(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::Cap(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::Sack(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::Tree(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::PatternMatch(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::Repeat(opr) => opr.preprocess(meta, plan_meta)?,
+                Opr::Coalesce(opr) => opr.preprocess(meta, plan_meta)?,
                 _ => {}
             }
         }
@@ -1702,6 +2258,7 @@ mod test {
     use ir_common::expr_parse::str_to_expr_pb;
     use ir_common::generated::algebra::logical_plan::operator::Opr;
     use ir_common::generated::common::property::Item;
+    use ir_common::generated::schema as schema_pb;
 
     use super::*;
     use crate::plan::meta::Schema;
@@ -1719,6 +2276,8 @@ mod test {
             predicate: None,
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         }
     }
 
@@ -2145,6 +2704,8 @@ mod test {
                 ),
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             idx_predicate: Some(vec!["software".to_string()].into()),
             is_count_only: false,
@@ -2203,6 +2764,142 @@ mod test {
             .is_empty());
     }
 
+    #[test]
+    fn preprocess_expand_excluded_tables() {
+        let meta = StoreMeta {
+            schema: Some(Schema::new(
+                vec![("person".to_string(), 0), ("software".to_string(), 1)],
+                vec![("knows".to_string(), 0), ("creates".to_string(), 1), ("likes".to_string(), 2)],
+                vec![("id".to_string(), 0), ("name".to_string(), 1), ("age".to_string(), 2)],
+            )),
+        };
+        let expand =
+            |tables: Vec<common_pb::NameOrId>, excluded_tables: Vec<common_pb::NameOrId>| pb::EdgeExpand {
+                v_tag: None,
+                direction: 0,
+                params: Some(pb::QueryParams { tables, excluded_tables, ..Default::default() }),
+                expand_opt: 0,
+                alias: None,
+                meta_data: None,
+                is_optional: false,
+            };
+        let tables_of = |expand: pb::EdgeExpand| {
+            let params = expand.params.unwrap();
+            assert!(params.excluded_tables.is_empty());
+            params.tables
+        };
+
+        // any edge label except `knows`
+        let mut any_but_knows = expand(vec![], vec!["knows".into()]);
+        any_but_knows
+            .preprocess(&meta, &mut PlanMeta::default())
+            .unwrap();
+        assert_eq!(tables_of(any_but_knows), vec![1.into(), 2.into()]);
+
+        // the given labels except `likes`
+        let mut given_but_likes = expand(vec!["knows".into(), "likes".into()], vec!["likes".into()]);
+        given_but_likes
+            .preprocess(&meta, &mut PlanMeta::default())
+            .unwrap();
+        assert_eq!(tables_of(given_but_likes), vec![0.into()]);
+
+        // all the labels are excluded, or the excluded label doesn't exist
+        let mut none = expand(vec!["knows".into()], vec!["knows".into()]);
+        assert!(none
+            .preprocess(&meta, &mut PlanMeta::default())
+            .is_err());
+        let mut missing = expand(vec![], vec!["hates".into()]);
+        assert!(missing
+            .preprocess(&meta, &mut PlanMeta::default())
+            .is_err());
+        // the labels can't be resolved without the schema
+        let mut without_schema = expand(vec![], vec!["knows".into()]);
+        assert!(without_schema
+            .preprocess(&StoreMeta::default(), &mut PlanMeta::default())
+            .is_err());
+
+        // any vertex label except `person`
+        let mut get_v = pb::GetV {
+            tag: None,
+            opt: 0,
+            params: Some(pb::QueryParams { excluded_tables: vec!["person".into()], ..Default::default() }),
+            alias: None,
+            meta_data: None,
+        };
+        get_v
+            .preprocess(&meta, &mut PlanMeta::default())
+            .unwrap();
+        assert_eq!(get_v.params.unwrap().tables, vec![1.into()]);
+
+        // the labels are resolved as the names if the schema doesn't map the tables as ids
+        let label = |name: &str, id: i32| Some(schema_pb::LabelMeta { id, name: name.to_string() });
+        let relation = |name: &str, id: i32| schema_pb::RelationMeta {
+            label: label(name, id),
+            entity_pairs: vec![],
+            columns: vec![],
+        };
+        let named_meta = StoreMeta {
+            schema: Some(
+                schema_pb::Schema {
+                    entities: vec![],
+                    relations: vec![relation("knows", 0), relation("creates", 1), relation("likes", 2)],
+                    is_table_id: false,
+                    is_column_id: false,
+                }
+                .into(),
+            ),
+        };
+        let mut named = expand(vec![], vec!["knows".into(), 2.into()]);
+        named
+            .preprocess(&named_meta, &mut PlanMeta::default())
+            .unwrap();
+        assert_eq!(tables_of(named), vec!["creates".into()]);
+    }
+
+    #[test]
+    fn preprocess_element_map_keys() {
+        let meta = StoreMeta {
+            schema: Some(Schema::new(
+                vec![("person".to_string(), 0)],
+                vec![],
+                vec![("name".to_string(), 1), ("age".to_string(), 2)],
+            )),
+        };
+        let element_map = |keys: Vec<&str>| common_pb::Expression {
+            operators: vec![common_pb::ExprOpr {
+                node_type: None,
+                item: Some(common_pb::expr_opr::Item::ElementMap(common_pb::ElementMap {
+                    tag: None,
+                    keys: keys
+                        .into_iter()
+                        .map(|name| common_pb::element_map::Key { name: name.to_string(), key: None })
+                        .collect(),
+                })),
+            }],
+        };
+        let keys_of = |expr: common_pb::Expression| match expr.operators[0].item.clone() {
+            Some(common_pb::expr_opr::Item::ElementMap(element_map)) => element_map
+                .keys
+                .into_iter()
+                .map(|key| (key.name, key.key.unwrap()))
+                .collect::<Vec<(String, common_pb::NameOrId)>>(),
+            _ => unreachable!(),
+        };
+        let mut plan_meta = PlanMeta::default();
+        plan_meta.set_curr_node(0);
+        plan_meta.curr_node_meta_mut();
+
+        // the keys given are got by the ids of the columns
+        let mut given = element_map(vec!["name"]);
+        preprocess_expression(&mut given, &meta, &mut plan_meta, false).unwrap();
+        assert_eq!(keys_of(given), vec![("name".to_string(), 1.into())]);
+
+        // all the columns are keyed by their names as well
+        let mut all = element_map(vec![]);
+        preprocess_expression(&mut all, &meta, &mut plan_meta, false).unwrap();
+        assert_eq!(keys_of(all), vec![("age".to_string(), 2.into()), ("name".to_string(), 1.into())]);
+    }
+
     #[test]
     fn scan_pred_to_idx_pred() {
         let mut plan_meta = PlanMeta::default();
@@ -2225,6 +2922,8 @@ mod test {
                 predicate: Some(str_to_expr_pb("@.name == \"John\"".to_string()).unwrap()),
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             idx_predicate: None,
             is_count_only: false,
@@ -2272,6 +2971,8 @@ mod test {
                 predicate: Some(str_to_expr_pb("@.name == \"John\"".to_string()).unwrap()),
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             idx_predicate: None,
             is_count_only: false,
@@ -2328,6 +3029,8 @@ mod test {
                 predicate: Some(predicate),
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             idx_predicate: None,
             is_count_only: false,
@@ -2374,6 +3077,8 @@ mod test {
                 predicate: Some(str_to_expr_pb("@.name within [\"John\", \"Josh\"]".to_string()).unwrap()),
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             idx_predicate: None,
             is_count_only: false,
@@ -2427,6 +3132,8 @@ mod test {
                 predicate: Some(str_to_expr_pb("@.age == 27 && (@.name == \"John\")".to_string()).unwrap()),
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             idx_predicate: None,
             is_count_only: false,
@@ -2442,6 +3149,8 @@ mod test {
             predicate: Some(str_to_expr_pb("@.age == 27 && (true)".to_string()).unwrap()),
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         };
         assert_eq!(
             scan.idx_predicate.unwrap(),
@@ -2485,6 +3194,8 @@ mod test {
                 ),
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             idx_predicate: None,
             is_count_only: false,
@@ -2500,6 +3211,8 @@ mod test {
             predicate: Some(str_to_expr_pb("true && @.age == 27".to_string()).unwrap()),
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         };
         assert_eq!(
             scan.idx_predicate.unwrap(),
@@ -2570,6 +3283,7 @@ mod test {
             }],
             is_append: false,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![3])
             .unwrap();
@@ -2634,6 +3348,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![3])
             .unwrap();
@@ -2647,6 +3362,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![4])
             .unwrap();
@@ -2716,6 +3432,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![3])
             .unwrap();
@@ -2754,6 +3471,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![5])
             .unwrap();
@@ -2767,6 +3485,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![6])
             .unwrap();
@@ -2879,6 +3598,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         opr_id = plan
             .append_operator_as_node(project.into(), vec![opr_id as NodeId])
@@ -2900,6 +3620,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         opr_id = plan
             .append_operator_as_node(project.into(), vec![opr_id as NodeId])
@@ -2932,6 +3653,8 @@ mod test {
                 predicate: None,
                 sample_ratio: 1.0,
                 extra: Default::default(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             idx_predicate: None,
             is_count_only: false,
@@ -2961,6 +3684,8 @@ mod test {
                 predicate: None,
                 sample_ratio: 1.0,
                 extra: Default::default(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             idx_predicate: None,
             is_count_only: false,
@@ -2978,6 +3703,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
 
         plan.append_operator_as_node(project.into(), vec![opr_id])
@@ -3044,6 +3770,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![3])
             .unwrap();
@@ -3057,6 +3784,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![4])
             .unwrap();
@@ -3122,6 +3850,7 @@ mod test {
             }],
             is_append: false,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![oprid])
             .unwrap();
@@ -3162,6 +3891,7 @@ mod test {
                 vars: vec![],
                 aggregate: 3,
                 alias: Some("~values_2_0".into()),
+                udf: String::new(),
             }],
             meta_data: vec![],
         };
@@ -3221,6 +3951,7 @@ mod test {
                 vars: vec![],
                 aggregate: 3,
                 alias: Some("~values_2_0".into()),
+                udf: String::new(),
             }],
             meta_data: vec![],
         };
@@ -3237,6 +3968,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![2])
             .unwrap();
@@ -3275,6 +4007,7 @@ mod test {
                 vars: vec![],
                 aggregate: 5,
                 alias: Some("~values_0_1".into()),
+                udf: String::new(),
             }],
             meta_data: vec![],
         };
@@ -3298,6 +4031,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![2])
             .unwrap();
@@ -3341,6 +4075,7 @@ mod test {
                 vars: vec![],
                 aggregate: 3,
                 alias: Some("~values_0_1".into()),
+                udf: String::new(),
             }],
             meta_data: vec![],
         };
@@ -3381,6 +4116,7 @@ mod test {
                 }],
                 aggregate: 5,
                 alias: Some("~values_0_1".into()),
+                udf: String::new(),
             }],
             meta_data: vec![],
         };
@@ -3420,6 +4156,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![1])
             .unwrap();
@@ -3485,7 +4222,7 @@ mod test {
         let id2 = plan
             .append_operator_as_node(expand3.into(), vec![opr_id])
             .unwrap();
-        let union = pb::Union { parents: vec![id1_f as PbNodeId, id2 as PbNodeId] };
+        let union = pb::Union { parents: vec![id1_f as PbNodeId, id2 as PbNodeId], branch_alias: None };
         plan.append_operator_as_node(union.into(), vec![id1_f, id2])
             .unwrap();
         assert_eq!(plan.meta.get_curr_referred_nodes(), &vec![id1, id2]);
@@ -3504,6 +4241,7 @@ mod test {
             }],
             is_append: false,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![opr_id])
             .unwrap();
@@ -3523,6 +4261,114 @@ mod test {
         );
     }
 
+    #[test]
+    fn preprocess_union_branch_alias() {
+        let mut plan = LogicalPlan::with_root();
+        let expand = pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: Some(query_params(vec![], vec![])),
+            expand_opt: 0,
+            alias: None,
+            meta_data: None,
+            is_optional: false,
+        };
+        let id1 = plan
+            .append_operator_as_node(expand.clone().into(), vec![0])
+            .unwrap();
+        let id2 = plan
+            .append_operator_as_node(expand.into(), vec![0])
+            .unwrap();
+        let union =
+            pb::Union { parents: vec![id1 as PbNodeId, id2 as PbNodeId], branch_alias: Some("b".into()) };
+        let union_id = plan
+            .append_operator_as_node(union.into(), vec![id1, id2])
+            .unwrap();
+        let b_id = plan.meta.get_tag_id("b").unwrap();
+        assert_eq!(plan.meta.get_tag_nodes(b_id), &vec![union_id]);
+        match plan.get_opr(union_id).unwrap().opr {
+            Some(Opr::Union(union)) => assert_eq!(union.branch_alias, Some((b_id as i32).into())),
+            _ => panic!("should be union"),
+        }
+    }
+
+    #[test]
+    fn preprocess_select_tags_as_map() {
+        let mut plan = LogicalPlan::with_root();
+        let expand = pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: Some(query_params(vec![], vec![])),
+            expand_opt: 0,
+            alias: Some("a".into()),
+            meta_data: None,
+            is_optional: false,
+        };
+        let mut expand2 = expand.clone();
+        expand2.alias = Some("b".into());
+        let id1 = plan
+            .append_operator_as_node(expand.into(), vec![0])
+            .unwrap();
+        let id2 = plan
+            .append_operator_as_node(expand2.into(), vec![id1])
+            .unwrap();
+        // g.V().out().as('a').out().as('b').select('a', 'b').by('name')
+        let mut project = pb::Project {
+            mappings: vec![pb::project::ExprAlias {
+                expr: str_to_expr_pb("[@a.name, @b.name]".to_string()).ok(),
+                alias: None,
+            }],
+            is_append: false,
+            meta_data: vec![],
+            select_tags: false,
+        };
+        // a list of the tags, e.g., of Cypher's `RETURN [a.name, b.name]`, is still a list
+        let list_id = plan
+            .append_operator_as_node(project.clone().into(), vec![id2])
+            .unwrap();
+        match plan.get_opr(list_id).unwrap().opr {
+            Some(Opr::Project(project)) => match project.mappings[0]
+                .expr
+                .as_ref()
+                .unwrap()
+                .operators[0]
+                .item
+            {
+                Some(common_pb::expr_opr::Item::Vars(_)) => {}
+                ref item => panic!("should be a list, but {:?}", item),
+            },
+            _ => panic!("should be project"),
+        }
+        project.select_tags = true;
+        let project_id = plan
+            .append_operator_as_node(project.into(), vec![id2])
+            .unwrap();
+        let a_id = plan.meta.get_tag_id("a").unwrap();
+        let b_id = plan.meta.get_tag_id("b").unwrap();
+        let project = match plan.get_opr(project_id).unwrap().opr {
+            Some(Opr::Project(project)) => project,
+            _ => panic!("should be project"),
+        };
+        let expr = project.mappings[0].expr.clone().unwrap();
+        match expr.operators[0].item.as_ref() {
+            Some(common_pb::expr_opr::Item::Map(map)) => {
+                let key_tags: Vec<(common_pb::Value, Option<common_pb::NameOrId>)> = map
+                    .key_vals
+                    .iter()
+                    .map(|kv| (kv.key.clone().unwrap(), kv.value.as_ref().unwrap().tag.clone()))
+                    .collect();
+                assert_eq!(
+                    key_tags,
+                    vec![
+                        ("a".to_string().into(), Some((a_id as i32).into())),
+                        ("b".to_string().into(), Some((b_id as i32).into()))
+                    ]
+                );
+            }
+            item => panic!("should be a map, but {:?}", item),
+        }
+    }
+
     #[test]
     fn tag_projection_not_exist() {
         let mut plan = LogicalPlan::with_root();
@@ -3533,6 +4379,7 @@ mod test {
             }],
             is_append: false,
             meta_data: vec![],
+            select_tags: false,
         };
         // visiting a non-existing tag does not return error
         let result = plan.append_operator_as_node(project.into(), vec![0]);
@@ -3591,6 +4438,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![opr_id])
             .unwrap();
@@ -3663,6 +4511,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.into(), vec![3])
             .unwrap();
@@ -3672,6 +4521,8 @@ mod test {
             sink_target: Some(pb::sink::SinkTarget {
                 inner: Some(pb::sink::sink_target::Inner::SinkDefault(pb::SinkDefault {
                     id_name_mappings: vec![],
+                    format: 0,
+                    columns: vec![],
                 })),
             }),
         };
@@ -179,10 +179,35 @@ impl Schema {
             .or_else(|| self.relation_name_to_id.get(name).map(|id| *id))
     }
 
+    /// The ids of all the entities, in the ascending order
+    pub fn get_entity_ids(&self) -> Vec<LabelId> {
+        let ids: BTreeSet<LabelId> = self
+            .entity_name_to_id
+            .values()
+            .cloned()
+            .collect();
+        ids.into_iter().collect()
+    }
+
+    /// The ids of all the relations, in the ascending order
+    pub fn get_relation_ids(&self) -> Vec<LabelId> {
+        let ids: BTreeSet<LabelId> = self
+            .relation_name_to_id
+            .values()
+            .cloned()
+            .collect();
+        ids.into_iter().collect()
+    }
+
     pub fn get_column_id(&self, name: &str) -> Option<KeyId> {
         self.column_name_to_id.get(name).cloned()
     }
 
+    /// The names of all the columns with their ids, in the order of the names
+    pub fn get_columns(&self) -> impl Iterator<Item = (&String, &KeyId)> {
+        self.column_name_to_id.iter()
+    }
+
     pub fn get_entity_name(&self, id: KeyId) -> Option<&String> {
         self.id_to_name.get(&(0, id))
     }
@@ -204,6 +229,14 @@ impl Schema {
         self.is_column_id
     }
 
+    pub fn get_entities(&self) -> &[schema_pb::EntityMeta] {
+        &self.entities
+    }
+
+    pub fn get_relations(&self) -> &[schema_pb::RelationMeta] {
+        &self.relations
+    }
+
     pub fn is_table_id(&self) -> bool {
         self.is_table_id
     }
@@ -14,6 +14,8 @@
 //! limitations under the License.
 
 pub mod ffi;
+#[cfg(feature = "graphql")]
+pub mod graphql;
 pub mod logical;
 pub mod meta;
 pub mod patmat;
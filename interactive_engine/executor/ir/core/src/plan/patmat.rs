@@ -676,6 +676,7 @@ impl MatchingStrategy for CompoSentence {
                             }],
                             is_append: true,
                             meta_data: vec![],
+                            select_tags: false,
                         })),
                     }),
                     children: vec![],
@@ -1227,6 +1228,8 @@ mod test {
             predicate: None,
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         }
     }
 
@@ -30,7 +30,7 @@ use ir_common::KeyId;
 use ir_physical_client::physical_builder::PlanBuilder;
 
 use crate::error::{IrError, IrResult};
-use crate::plan::logical::{LogicalPlan, NodeType};
+use crate::plan::logical::{LogicalPlan, NodeId, NodeType};
 use crate::plan::meta::PlanMeta;
 
 /// A trait for building physical plan (pegasus) from the logical plan
@@ -101,6 +101,8 @@ fn post_process_vars(
                             predicate: None,
                             sample_ratio: 1.0,
                             extra: Default::default(),
+                            valid_time: None,
+                            excluded_tables: vec![],
                         };
                         // opt = 4 denotes that to get vertex itself. The same as the followings.
                         let auxilia = pb::GetV {
@@ -159,6 +161,8 @@ impl AsPhysical for pb::Select {
                     predicate: self.predicate.clone(),
                     sample_ratio: 1.0,
                     extra: Default::default(),
+                    valid_time: None,
+                    excluded_tables: vec![],
                 };
                 let auxilia = pb::GetV {
                     tag: tag_pb.clone(),
@@ -316,6 +320,8 @@ impl AsPhysical for pb::PathExpand {
                         predicate: None,
                         sample_ratio: 1.0,
                         extra: Default::default(),
+                        valid_time: None,
+                        excluded_tables: vec![],
                     };
                     // Notice that, when properties of a `Path` is needed, we need to cache the properties of the vertices/edges in the path.
                     // For example, `g.V().out("1..3").with("RESULT_OPT, "ALL_V").values("name")`, we need to cache the property of "name" in all the vertices in the path.
@@ -541,6 +547,7 @@ impl AsPhysical for pb::As {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         builder.project(project_new_alias);
         Ok(())
@@ -631,6 +638,9 @@ impl AsPhysical for pb::Sample {
                     if ratio.ratio < 0.0 || ratio.ratio > 1.0 {
                         Err(IrError::ParsePbError("SampleByRatio ratio should be in [0, 1]".into()))?
                     }
+                    if self.sample_key.is_some() {
+                        Err(IrError::Unsupported("SampleByRatio with sample_key".into()))?
+                    }
                 }
             }
         }
@@ -645,6 +655,225 @@ impl AsPhysical for pb::Sample {
     }
 }
 
+impl AsPhysical for pb::GraphAlgorithm {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, _plan_meta: &mut PlanMeta) -> IrResult<()> {
+        builder.algorithm(self.clone());
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::KHop {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if self.hops <= 0 {
+            Err(IrError::InvalidRange(1, self.hops))?
+        }
+        let mut k_hop = self.clone();
+        let aggregates = std::mem::take(&mut k_hop.aggregates);
+        builder.k_hop(k_hop);
+        // the aggregation of the reached vertices is grouped by the start vertex, and as the reached
+        // vertices are on the workers owning them, they are shuffled by the start vertex to aggregate
+        if !aggregates.is_empty() {
+            let start_tag = self
+                .start_tag
+                .clone()
+                .ok_or_else(|| IrError::MissingData("KHop::start_tag for aggregates".to_string()))?;
+            builder.shuffle(Some(start_tag.clone()));
+            let group = pb::GroupBy {
+                mappings: vec![pb::group_by::KeyAlias {
+                    key: Some(common_pb::Variable {
+                        tag: Some(start_tag.clone()),
+                        property: None,
+                        node_type: None,
+                    }),
+                    alias: Some(start_tag),
+                }],
+                functions: aggregates,
+                meta_data: vec![],
+            };
+            group.add_job_builder(builder, plan_meta)?;
+        }
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::Merge {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, _plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if self.kind == pb::merge::Kind::Edge as i32 {
+            if self.src_tag.is_none() {
+                Err(IrError::MissingData("Merge::src_tag of edge".to_string()))?
+            }
+            if self.dst_tag.is_none() {
+                Err(IrError::MissingData("Merge::dst_tag of edge".to_string()))?
+            }
+        }
+        builder.merge(self.clone());
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::Mutate {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, _plan_meta: &mut PlanMeta) -> IrResult<()> {
+        match self.kind.as_ref() {
+            Some(pb::mutate::Kind::AddE(add_e)) => {
+                if add_e.src_tag.is_none() {
+                    Err(IrError::MissingData("Mutate::AddE::src_tag".to_string()))?
+                }
+                if add_e.dst_tag.is_none() {
+                    Err(IrError::MissingData("Mutate::AddE::dst_tag".to_string()))?
+                }
+            }
+            Some(_) => {}
+            None => Err(IrError::MissingData("Mutate::kind".to_string()))?,
+        }
+        builder.mutate(self.clone());
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::StoreVar {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, _plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if self.name.is_empty() {
+            Err(IrError::MissingData("StoreVar::name".to_string()))?
+        }
+        builder.store_var(self.clone());
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::LoadVar {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, _plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if self.name.is_empty() {
+            Err(IrError::MissingData("LoadVar::name".to_string()))?
+        }
+        builder.load_var(self.clone());
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::Call {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, _plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if self.name.is_empty() {
+            Err(IrError::MissingData("Call::name".to_string()))?
+        }
+        builder.call(self.clone());
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::SideEffect {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, _plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if self.name.is_empty() {
+            Err(IrError::MissingData("SideEffect::name".to_string()))?
+        }
+        builder.side_effect(self.clone());
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::Cap {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, _plan_meta: &mut PlanMeta) -> IrResult<()> {
+        if self.name.is_empty() {
+            Err(IrError::MissingData("Cap::name".to_string()))?
+        }
+        builder.cap(self.clone());
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::Sack {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        let mut sack = self.clone();
+        sack.post_process(builder, plan_meta)?;
+        builder.sack(sack);
+        Ok(())
+    }
+
+    fn post_process(&mut self, builder: &mut PlanBuilder, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        match self.inner.as_ref() {
+            Some(pb::sack::Inner::Init(init)) if init.initial.is_none() => {
+                Err(IrError::MissingData("Sack::Init::initial".to_string()))?
+            }
+            Some(pb::sack::Inner::Update(update)) if update.by.is_none() => {
+                Err(IrError::MissingData("Sack::Update::by".to_string()))?
+            }
+            Some(pb::sack::Inner::Init(_)) | Some(pb::sack::Inner::Update(_)) => {
+                post_process_vars(builder, plan_meta, false)?
+            }
+            Some(_) => {}
+            None => Err(IrError::MissingData("Sack::inner".to_string()))?,
+        }
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::Tree {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        let mut tree = self.clone();
+        tree.post_process(builder, plan_meta)?;
+        builder.tree(tree);
+        Ok(())
+    }
+
+    fn post_process(&mut self, builder: &mut PlanBuilder, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        post_process_vars(builder, plan_meta, false)?;
+        Ok(())
+    }
+}
+
+impl AsPhysical for pb::PatternMatch {
+    fn add_job_builder(&self, builder: &mut PlanBuilder, plan_meta: &mut PlanMeta) -> IrResult<()> {
+        let tag_of = |tag: Option<&common_pb::NameOrId>, field: &str| -> IrResult<KeyId> {
+            let tag = tag
+                .cloned()
+                .ok_or_else(|| IrError::MissingData(format!("PatternMatch::{}", field)))?;
+            Ok(tag.try_into()?)
+        };
+        // the vertices and the edges are keyed by the names of their tags in the binding maps
+        let name_of = |tag: KeyId| {
+            plan_meta
+                .get_tag_id_mappings()
+                .iter()
+                .find(|(_, tag_id)| **tag_id as KeyId == tag)
+                .map(|(name, _)| name.clone())
+                .unwrap_or_default()
+        };
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        for vertex in self.vertices.iter() {
+            let tag = tag_of(vertex.tag.as_ref(), "Vertex::tag")?;
+            vertices.push(physical_pb::pattern_match::Vertex {
+                tag,
+                name: name_of(tag),
+                params: vertex.params.clone(),
+            });
+        }
+        let mut triples = Vec::with_capacity(self.triples.len());
+        for triple in self.triples.iter() {
+            let alias = match triple.alias.as_ref() {
+                Some(alias) => Some(tag_of(Some(alias), "Triple::alias")?),
+                None => None,
+            };
+            triples.push(physical_pb::pattern_match::Triple {
+                src: tag_of(triple.src.as_ref(), "Triple::src")?,
+                dst: tag_of(triple.dst.as_ref(), "Triple::dst")?,
+                direction: triple.direction,
+                params: triple.params.clone(),
+                alias,
+                name: alias.map(name_of).unwrap_or_default(),
+            });
+        }
+        let mut bound_tags = Vec::with_capacity(self.bound_tags.len());
+        for tag in self.bound_tags.iter() {
+            bound_tags.push(tag_of(Some(tag), "bound_tags")?);
+        }
+        let alias = match self.alias.as_ref() {
+            Some(alias) => Some(tag_of(Some(alias), "alias")?),
+            None => None,
+        };
+        builder.pattern_match(physical_pb::PatternMatch { vertices, triples, bound_tags, alias });
+        Ok(())
+    }
+}
+
 impl AsPhysical for pb::Sink {
     fn add_job_builder(&self, builder: &mut PlanBuilder, plan_meta: &mut PlanMeta) -> IrResult<()> {
         let mut sink_opr = self.clone();
@@ -657,7 +886,7 @@ impl AsPhysical for pb::Sink {
             .as_ref()
             .ok_or_else(|| IrError::MissingData("Sink::sink_target::Inner".to_string()))?
         {
-            pb::sink::sink_target::Inner::SinkDefault(_) => {
+            pb::sink::sink_target::Inner::SinkDefault(sink_default) => {
                 let tag_id_mapping = plan_meta
                     .get_tag_id_mappings()
                     .iter()
@@ -670,6 +899,8 @@ impl AsPhysical for pb::Sink {
                 let sink_target = pb::sink::SinkTarget {
                     inner: Some(pb::sink::sink_target::Inner::SinkDefault(pb::SinkDefault {
                         id_name_mappings: tag_id_mapping,
+                        format: sink_default.format,
+                        columns: sink_default.columns.clone(),
                     })),
                 };
                 sink_opr.sink_target = Some(sink_target);
@@ -723,6 +954,18 @@ impl AsPhysical for pb::logical_plan::Operator {
                 }
                 Branch(_) => Ok(()),
                 Sample(sample) => sample.add_job_builder(builder, plan_meta),
+                Algorithm(algorithm) => algorithm.add_job_builder(builder, plan_meta),
+                KHop(k_hop) => k_hop.add_job_builder(builder, plan_meta),
+                Merge(merge) => merge.add_job_builder(builder, plan_meta),
+                Mutate(mutate) => mutate.add_job_builder(builder, plan_meta),
+                StoreVar(store_var) => store_var.add_job_builder(builder, plan_meta),
+                LoadVar(load_var) => load_var.add_job_builder(builder, plan_meta),
+                Call(call) => call.add_job_builder(builder, plan_meta),
+                SideEffect(side_effect) => side_effect.add_job_builder(builder, plan_meta),
+                Cap(cap) => cap.add_job_builder(builder, plan_meta),
+                Sack(sack) => sack.add_job_builder(builder, plan_meta),
+                Tree(tree) => tree.add_job_builder(builder, plan_meta),
+                PatternMatch(pattern) => pattern.add_job_builder(builder, plan_meta),
                 _ => Err(IrError::Unsupported(format!("the operator {:?}", self))),
             }
         } else {
@@ -842,6 +1085,7 @@ impl AsPhysical for LogicalPlan {
                             }],
                             is_append: true,
                             meta_data: vec![],
+                            select_tags: false,
                         });
                         builder.edge_expand(expand_degree);
                         builder.project(pb::Project {
@@ -851,6 +1095,7 @@ impl AsPhysical for LogicalPlan {
                             }],
                             is_append: true,
                             meta_data: vec![],
+                            select_tags: false,
                         });
                     } else {
                         subplan.add_job_builder(&mut sub_bldr, plan_meta)?;
@@ -863,6 +1108,50 @@ impl AsPhysical for LogicalPlan {
                 } else {
                     return Err(IrError::MissingData("Apply::subplan".to_string()));
                 }
+            } else if let Some(Repeat(repeat_opr)) = curr_node.borrow().opr.opr.as_ref() {
+                let subplan = self
+                    .extract_subplan(curr_node.clone())
+                    .ok_or_else(|| IrError::MissingData("Repeat::subplan".to_string()))?;
+                let mut sub_bldr = PlanBuilder::default();
+                subplan.add_job_builder(&mut sub_bldr, plan_meta)?;
+                // the columns of the condition are fetched at the end of each iteration, and before
+                // the first one if the condition is evaluated before it as well
+                plan_meta.set_curr_node(curr_node_id);
+                if repeat_opr.until_first {
+                    post_process_vars(builder, plan_meta, false)?;
+                }
+                post_process_vars(&mut sub_bldr, plan_meta, false)?;
+                builder.repeat(
+                    sub_bldr,
+                    physical_pb::Repeat {
+                        body: None,
+                        until: repeat_opr.until.clone(),
+                        max_iterations: repeat_opr.max_iterations,
+                        until_first: repeat_opr.until_first,
+                    },
+                );
+            } else if let Some(Coalesce(coalesce_opr)) = curr_node.borrow().opr.opr.as_ref() {
+                let mut plans = vec![];
+                for subtask in &coalesce_opr.subtasks {
+                    let subplan = self
+                        .extract_subplan_from(*subtask as NodeId)
+                        .ok_or_else(|| IrError::MissingData("Coalesce::subplan".to_string()))?;
+                    let mut sub_bldr = PlanBuilder::default();
+                    subplan.add_job_builder(&mut sub_bldr, plan_meta)?;
+                    plans.push(sub_bldr);
+                }
+                plan_meta.set_curr_node(curr_node_id);
+                builder.coalesce(plans);
+            } else if let Some(Union(union)) = curr_node.borrow().opr.opr.as_ref() {
+                // A union of a single parent, whose branches are all the identity of the parent, e.g.,
+                // `union(identity(), identity())`, which is a branch per occurrence of the parent.
+                let branches = union.parents.len().max(1);
+                if branches > 1 || union.branch_alias.is_some() {
+                    builder.union_with_branch(
+                        vec![PlanBuilder::default(); branches],
+                        union.branch_alias.clone(),
+                    );
+                }
             } else {
                 curr_node.add_job_builder(builder, plan_meta)?;
             }
@@ -886,8 +1175,9 @@ impl AsPhysical for LogicalPlan {
                 }
 
                 match &merge_node.borrow().opr.opr {
-                    Some(Union(_)) => {
-                        builder.union(plans);
+                    Some(Union(union)) => {
+                        let plans = order_union_branches(union, &merge_node, plans);
+                        builder.union_with_branch(plans, union.branch_alias.clone());
                     }
                     Some(Intersect(intersect)) => {
                         add_intersect_job_builder(builder, plan_meta, intersect, &subplans)?;
@@ -938,6 +1228,33 @@ impl AsPhysical for LogicalPlan {
     }
 }
 
+// The sub-plans of a union are given as of the parents of the merge node, in the order of their ids,
+// which are ordered as of `Union::parents` instead, such that the index of a branch is the index of
+// its parent there. A parent given more than once, e.g., of `union(out(), identity(), identity())`,
+// is a branch per occurrence, and the parents missing from `Union::parents` are the last branches.
+fn order_union_branches(
+    union: &pb::Union, merge_node: &NodeType, plans: Vec<PlanBuilder>,
+) -> Vec<PlanBuilder> {
+    let parents: Vec<_> = merge_node
+        .borrow()
+        .parents
+        .iter()
+        .map(|parent| *parent as i32)
+        .collect();
+    let mut ordered = Vec::with_capacity(union.parents.len().max(plans.len()));
+    for parent in union.parents.iter() {
+        if let Some(idx) = parents.iter().position(|p| p == parent) {
+            ordered.push(plans[idx].clone());
+        }
+    }
+    for (idx, parent) in parents.iter().enumerate() {
+        if !union.parents.contains(parent) {
+            ordered.push(plans[idx].clone());
+        }
+    }
+    ordered
+}
+
 // Given a->b, we support intersecting their neighbors, e.g., Intersect{{a->c, b->c}, key=c}
 // Currently, subplans in Intersect could be like:
 // 1. vec![ExpandE, GetV] for edge expand to intersect;
@@ -1001,6 +1318,8 @@ mod test {
             predicate: None,
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         }
     }
 
@@ -1078,6 +1397,7 @@ mod test {
             }],
             is_append: false,
             meta_data: vec![],
+            select_tags: false,
         }
     }
 
@@ -1088,6 +1408,8 @@ mod test {
             sink_target: Some(pb::sink::SinkTarget {
                 inner: Some(pb::sink::sink_target::Inner::SinkDefault(pb::SinkDefault {
                     id_name_mappings: vec![],
+                    format: 0,
+                    columns: vec![],
                 })),
             }),
         }
@@ -1485,6 +1807,8 @@ mod test {
                     predicate: str_to_expr_pb("@.age > 10".to_string()).ok(),
                     sample_ratio: 1.0,
                     extra: Default::default(),
+                    valid_time: None,
+                    excluded_tables: vec![],
                 }),
                 alias: None,
                 meta_data: None,
@@ -1592,6 +1916,7 @@ mod test {
             ],
             is_append: false,
             meta_data: vec![],
+            select_tags: false,
         };
 
         let mut logical_plan = LogicalPlan::with_node(Node::new(0, source_opr.clone().into()));
@@ -1802,6 +2127,8 @@ mod test {
                 predicate: str_to_expr_pb("@.age > 10".to_string()).ok(),
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             alias: None,
             meta_data: None,
@@ -1838,6 +2165,8 @@ mod test {
                 predicate: str_to_expr_pb("@.age > 10".to_string()).ok(),
                 sample_ratio: 1.0,
                 extra: HashMap::new(),
+                valid_time: None,
+                excluded_tables: vec![],
             }),
             alias: None,
             meta_data: None,
@@ -1967,6 +2296,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         plan.append_operator_as_node(project.clone().into(), vec![opr_id])
             .unwrap();
@@ -2055,6 +2385,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         });
 
         expand.alias = Some(1.into()); // must carry `Apply`'s alias
@@ -2066,6 +2397,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         });
 
         assert_eq!(expected_builder, builder);
@@ -2100,6 +2432,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
         let mut expand = build_edgexpd(2, vec![], None);
         let subplan_id = plan
@@ -2132,6 +2465,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         });
         expand.v_tag = Some(0.into());
         expand.alias = Some(1.into()); // must carry `Apply`'s alias
@@ -2143,6 +2477,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         });
 
         assert_eq!(expected_builder, builder);
@@ -2462,7 +2797,7 @@ mod test {
         let opr = pb::logical_plan::Operator {
             opr: Some(pb::logical_plan::operator::Opr::Edge(pb::EdgeExpand::default())),
         };
-        let union_opr = pb::Union { parents: vec![4, 5] };
+        let union_opr = pb::Union { parents: vec![4, 5], branch_alias: None };
         let join = pb::Join::default();
         let mut plan = LogicalPlan::with_root();
         plan.append_operator_as_node(opr.clone(), vec![0])
@@ -2502,7 +2837,7 @@ mod test {
         let opr = pb::logical_plan::Operator {
             opr: Some(pb::logical_plan::operator::Opr::Edge(pb::EdgeExpand::default())),
         };
-        let union_opr = pb::Union { parents: vec![4, 5] };
+        let union_opr = pb::Union { parents: vec![4, 5], branch_alias: None };
         let join = pb::Join::default();
         let mut plan = LogicalPlan::with_root();
         plan.append_operator_as_node(opr.clone(), vec![0])
@@ -2539,8 +2874,8 @@ mod test {
         let opr = pb::logical_plan::Operator {
             opr: Some(pb::logical_plan::operator::Opr::Edge(pb::EdgeExpand::default())),
         };
-        let union1 = pb::Union { parents: vec![2, 3] };
-        let union2 = pb::Union { parents: vec![3, 4] };
+        let union1 = pb::Union { parents: vec![2, 3], branch_alias: None };
+        let union2 = pb::Union { parents: vec![3, 4], branch_alias: None };
         let join = pb::Join::default();
         let mut plan = LogicalPlan::with_root();
         plan.append_operator_as_node(opr.clone(), vec![0])
@@ -2581,7 +2916,7 @@ mod test {
         let opr = pb::logical_plan::Operator {
             opr: Some(pb::logical_plan::operator::Opr::Edge(pb::EdgeExpand::default())),
         };
-        let union_opr = pb::Union { parents: vec![6, 7] };
+        let union_opr = pb::Union { parents: vec![6, 7], branch_alias: None };
         let join1 = pb::Join::default();
         let join2 = pb::Join::default();
         let mut plan = LogicalPlan::with_root();
@@ -2628,8 +2963,8 @@ mod test {
         let opr = pb::logical_plan::Operator {
             opr: Some(pb::logical_plan::operator::Opr::Edge(pb::EdgeExpand::default())),
         };
-        let union1 = pb::Union { parents: vec![4, 5] };
-        let union2 = pb::Union { parents: vec![8, 9] };
+        let union1 = pb::Union { parents: vec![4, 5], branch_alias: None };
+        let union2 = pb::Union { parents: vec![8, 9], branch_alias: None };
         let join = pb::Join::default();
         let mut plan = LogicalPlan::with_root();
 
@@ -2677,7 +3012,7 @@ mod test {
         let opr = pb::logical_plan::Operator {
             opr: Some(pb::logical_plan::operator::Opr::Edge(pb::EdgeExpand::default())),
         };
-        let union_opr = pb::Union { parents: vec![4, 5] };
+        let union_opr = pb::Union { parents: vec![4, 5], branch_alias: None };
         let join1 = pb::Join::default();
         let join2 = pb::Join::default();
         let mut plan = LogicalPlan::with_root();
@@ -2992,6 +3327,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
 
         let mut logical_plan = LogicalPlan::with_node(Node::new(0, source_opr.clone().into()));
@@ -3040,4 +3376,356 @@ mod test {
         expected_builder.project(project_opr);
         assert_eq!(builder, expected_builder);
     }
+
+    #[test]
+    fn union_branches_as_physical() {
+        // g.V().union(in(), out(), identity(), identity())
+        let scan = build_scan(vec![]);
+        let out = build_edgexpd(0, vec![], None);
+        let mut in_ = out.clone();
+        in_.direction = 1;
+        let mut plan = LogicalPlan::with_root();
+        let scan_id = plan
+            .append_operator_as_node(scan.clone().into(), vec![0])
+            .unwrap();
+        // the out branch is added before the in branch, which comes first in the union
+        let out_id = plan
+            .append_operator_as_node(out.clone().into(), vec![scan_id])
+            .unwrap();
+        let in_id = plan
+            .append_operator_as_node(in_.clone().into(), vec![scan_id])
+            .unwrap();
+        let union = pb::Union {
+            parents: vec![in_id as i32, out_id as i32, scan_id as i32, scan_id as i32],
+            branch_alias: None,
+        };
+        plan.append_operator_as_node(union.into(), vec![in_id, out_id, scan_id])
+            .unwrap();
+
+        plan.clean_redundant_nodes();
+
+        let mut builder = PlanBuilder::default();
+        let mut plan_meta = plan.meta.clone();
+        plan.add_job_builder(&mut builder, &mut plan_meta)
+            .unwrap();
+
+        let mut expected_builder = PlanBuilder::default();
+        expected_builder.add_scan_source(scan);
+        let mut in_plan = PlanBuilder::default();
+        in_plan.edge_expand(in_);
+        let mut out_plan = PlanBuilder::default();
+        out_plan.edge_expand(out);
+        expected_builder.union(vec![in_plan, out_plan, PlanBuilder::default(), PlanBuilder::default()]);
+
+        assert_eq!(builder, expected_builder);
+    }
+
+    #[test]
+    fn union_identities_as_physical() {
+        // g.V().union(identity(), identity())
+        let scan = build_scan(vec![]);
+        let mut plan = LogicalPlan::with_root();
+        let scan_id = plan
+            .append_operator_as_node(scan.clone().into(), vec![0])
+            .unwrap();
+        let union = pb::Union { parents: vec![scan_id as i32, scan_id as i32], branch_alias: None };
+        plan.append_operator_as_node(union.into(), vec![scan_id, scan_id])
+            .unwrap();
+
+        plan.clean_redundant_nodes();
+
+        let mut builder = PlanBuilder::default();
+        let mut plan_meta = plan.meta.clone();
+        plan.add_job_builder(&mut builder, &mut plan_meta)
+            .unwrap();
+
+        let mut expected_builder = PlanBuilder::default();
+        expected_builder.add_scan_source(scan);
+        expected_builder.union(vec![PlanBuilder::default(), PlanBuilder::default()]);
+
+        assert_eq!(builder, expected_builder);
+    }
+
+    #[test]
+    fn repeat_as_physical() {
+        // g.V().repeat(out()).until(has("name", "marko"))
+        let scan = build_scan(vec![]);
+        let expand = build_edgexpd(0, vec![], None);
+        let mut plan = LogicalPlan::with_root();
+        let opr_id = plan
+            .append_operator_as_node(scan.clone().into(), vec![0])
+            .unwrap();
+        let root_id = plan
+            .append_operator_as_node(expand.clone().into(), vec![])
+            .unwrap();
+        let until = str_to_expr_pb("@.name == \"marko\"".to_string()).ok();
+        let repeat = pb::Repeat {
+            subtask: root_id as i32,
+            until: until.clone(),
+            max_iterations: 0,
+            until_first: true,
+        };
+        let opr_id = plan
+            .append_operator_as_node(repeat.into(), vec![opr_id])
+            .unwrap();
+        plan.append_operator_as_node(build_sink().into(), vec![opr_id])
+            .unwrap();
+
+        plan.clean_redundant_nodes();
+
+        let mut builder = PlanBuilder::default();
+        let mut plan_meta = plan.meta.clone();
+        plan.add_job_builder(&mut builder, &mut plan_meta)
+            .unwrap();
+
+        let mut expected_builder = PlanBuilder::default();
+        expected_builder.add_scan_source(scan);
+        let mut body = PlanBuilder::default();
+        body.edge_expand(expand);
+        expected_builder
+            .repeat(body, physical_pb::Repeat { body: None, until, max_iterations: 0, until_first: true });
+        expected_builder.sink(build_sink());
+
+        assert_eq!(builder, expected_builder);
+    }
+
+    #[test]
+    fn repeat_unbounded() {
+        let mut plan = LogicalPlan::with_root();
+        let root_id = plan
+            .append_operator_as_node(build_edgexpd(0, vec![], None).into(), vec![])
+            .unwrap();
+        // neither the condition nor the max iterations
+        let repeat =
+            pb::Repeat { subtask: root_id as i32, until: None, max_iterations: 0, until_first: false };
+        assert!(plan
+            .append_operator_as_node(repeat.into(), vec![0])
+            .is_err());
+    }
+
+    #[test]
+    fn coalesce_as_physical() {
+        // g.V().coalesce(out(), outE())
+        let scan = build_scan(vec![]);
+        let expand0 = build_edgexpd(0, vec![], None);
+        let expand1 = build_edgexpd(1, vec![], None);
+        let mut plan = LogicalPlan::with_root();
+        let opr_id = plan
+            .append_operator_as_node(scan.clone().into(), vec![0])
+            .unwrap();
+        let root_id0 = plan
+            .append_operator_as_node(expand0.clone().into(), vec![])
+            .unwrap();
+        let root_id1 = plan
+            .append_operator_as_node(expand1.clone().into(), vec![])
+            .unwrap();
+        let coalesce = pb::Coalesce { subtasks: vec![root_id0 as i32, root_id1 as i32] };
+        let opr_id = plan
+            .append_operator_as_node(coalesce.into(), vec![opr_id])
+            .unwrap();
+        plan.append_operator_as_node(build_sink().into(), vec![opr_id])
+            .unwrap();
+
+        plan.clean_redundant_nodes();
+
+        let mut builder = PlanBuilder::default();
+        let mut plan_meta = plan.meta.clone();
+        plan.add_job_builder(&mut builder, &mut plan_meta)
+            .unwrap();
+
+        let mut expected_builder = PlanBuilder::default();
+        expected_builder.add_scan_source(scan);
+        let mut plan0 = PlanBuilder::default();
+        plan0.edge_expand(expand0);
+        let mut plan1 = PlanBuilder::default();
+        plan1.edge_expand(expand1);
+        expected_builder.coalesce(vec![plan0, plan1]);
+        expected_builder.sink(build_sink());
+
+        assert_eq!(builder, expected_builder);
+    }
+
+    #[test]
+    fn pattern_match_as_physical() {
+        // g.V().as("a").match(as("a").outE().as("e").inV().as("b"))
+        let mut scan = build_scan(vec![]);
+        scan.alias = Some("a".into());
+        let pattern = pb::PatternMatch {
+            vertices: vec![
+                pb::pattern_match::Vertex { tag: Some("a".into()), params: None },
+                pb::pattern_match::Vertex { tag: Some("b".into()), params: None },
+            ],
+            triples: vec![pb::pattern_match::Triple {
+                src: Some("a".into()),
+                dst: Some("b".into()),
+                direction: 0,
+                params: Some(query_params(vec![], vec![])),
+                alias: Some("e".into()),
+            }],
+            bound_tags: vec!["a".into()],
+            alias: None,
+        };
+        let mut plan = LogicalPlan::with_root();
+        let opr_id = plan
+            .append_operator_as_node(scan.clone().into(), vec![0])
+            .unwrap();
+        plan.append_operator_as_node(pattern.into(), vec![opr_id])
+            .unwrap();
+
+        let mut builder = PlanBuilder::default();
+        let mut plan_meta = plan.meta.clone();
+        plan.add_job_builder(&mut builder, &mut plan_meta)
+            .unwrap();
+
+        let mut expected_builder = PlanBuilder::default();
+        scan.alias = Some(0.into());
+        expected_builder.add_scan_source(scan);
+        expected_builder.pattern_match(physical_pb::PatternMatch {
+            vertices: vec![
+                physical_pb::pattern_match::Vertex { tag: 0, name: "a".to_string(), params: None },
+                physical_pb::pattern_match::Vertex { tag: 1, name: "b".to_string(), params: None },
+            ],
+            triples: vec![physical_pb::pattern_match::Triple {
+                src: 0,
+                dst: 1,
+                direction: 0,
+                params: Some(query_params(vec![], vec![])),
+                alias: Some(2),
+                name: "e".to_string(),
+            }],
+            bound_tags: vec![0],
+            alias: None,
+        });
+
+        assert_eq!(builder, expected_builder);
+    }
+
+    #[test]
+    fn k_hop_aggregates_as_group() {
+        // g.V().as(0).k_hop(2).as(1), and count the reached vertices per start
+        let mut scan = build_scan(vec![]);
+        scan.alias = Some(0.into());
+        let count = pb::group_by::AggFunc {
+            vars: vec![common_pb::Variable { tag: Some(1.into()), property: None, node_type: None }],
+            aggregate: 3,
+            alias: Some(2.into()),
+            udf: String::new(),
+        };
+        let k_hop = pb::KHop {
+            start_tag: Some(0.into()),
+            direction: 0,
+            params: Some(query_params(vec![], vec![])),
+            hops: 2,
+            within: true,
+            alias: Some(1.into()),
+            aggregates: vec![count.clone()],
+        };
+        let mut plan = LogicalPlan::with_root();
+        plan.append_operator_as_node(scan.clone().into(), vec![0])
+            .unwrap();
+        plan.append_operator_as_node(k_hop.clone().into(), vec![1])
+            .unwrap();
+        plan.append_operator_as_node(build_sink().into(), vec![2])
+            .unwrap();
+
+        plan.clean_redundant_nodes();
+
+        let mut job_builder = PlanBuilder::default();
+        let mut plan_meta = plan.meta.clone();
+        plan.add_job_builder(&mut job_builder, &mut plan_meta)
+            .unwrap();
+
+        let mut expected_builder = PlanBuilder::default();
+        expected_builder.add_scan_source(scan);
+        expected_builder.k_hop(pb::KHop { aggregates: vec![], ..k_hop });
+        expected_builder.shuffle(Some(0.into()));
+        expected_builder.group(pb::GroupBy {
+            mappings: vec![pb::group_by::KeyAlias {
+                key: Some(common_pb::Variable { tag: Some(0.into()), property: None, node_type: None }),
+                alias: Some(0.into()),
+            }],
+            functions: vec![count],
+            meta_data: vec![],
+        });
+        expected_builder.sink(build_sink());
+
+        assert_eq!(job_builder, expected_builder);
+    }
+
+    #[test]
+    fn k_hop_invalid_hops() {
+        let k_hop = pb::KHop {
+            start_tag: None,
+            direction: 0,
+            params: Some(query_params(vec![], vec![])),
+            hops: 0,
+            within: false,
+            alias: None,
+            aggregates: vec![],
+        };
+        let mut builder = PlanBuilder::default();
+        let mut plan_meta = PlanMeta::default();
+        assert!(k_hop
+            .add_job_builder(&mut builder, &mut plan_meta)
+            .is_err());
+    }
+
+    #[test]
+    fn merge_edge_as_physical() {
+        let mut merge = pb::Merge {
+            kind: pb::merge::Kind::Edge as i32,
+            label: Some(1.into()),
+            src_tag: Some(0.into()),
+            dst_tag: None,
+            properties: vec![],
+            on_create: vec![],
+            on_match: vec![],
+            alias: Some(2.into()),
+        };
+        let mut builder = PlanBuilder::default();
+        let mut plan_meta = PlanMeta::default();
+        // the destination vertex of the edge is missing
+        assert!(merge
+            .add_job_builder(&mut builder, &mut plan_meta)
+            .is_err());
+
+        merge.dst_tag = Some(1.into());
+        let mut builder = PlanBuilder::default();
+        merge
+            .add_job_builder(&mut builder, &mut plan_meta)
+            .unwrap();
+        let mut expected_builder = PlanBuilder::default();
+        expected_builder.merge(merge);
+        assert_eq!(builder, expected_builder);
+    }
+
+    #[test]
+    fn mutate_add_edge_as_physical() {
+        let add_e = pb::mutate::AddE {
+            label: Some(1.into()),
+            src_tag: Some(0.into()),
+            dst_tag: None,
+            properties: vec![],
+        };
+        let mutate =
+            pb::Mutate { kind: Some(pb::mutate::Kind::AddE(add_e)), batch_size: 0, alias: Some(2.into()) };
+        let mut builder = PlanBuilder::default();
+        let mut plan_meta = PlanMeta::default();
+        // the destination vertex of the edge is missing
+        assert!(mutate
+            .add_job_builder(&mut builder, &mut plan_meta)
+            .is_err());
+
+        let drop = pb::Mutate {
+            kind: Some(pb::mutate::Kind::Drop(pb::mutate::Drop { tag: None })),
+            batch_size: 0,
+            alias: None,
+        };
+        let mut builder = PlanBuilder::default();
+        drop.add_job_builder(&mut builder, &mut plan_meta)
+            .unwrap();
+        let mut expected_builder = PlanBuilder::default();
+        expected_builder.mutate(drop);
+        assert_eq!(builder, expected_builder);
+    }
 }
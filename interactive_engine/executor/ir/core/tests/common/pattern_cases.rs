@@ -70,6 +70,8 @@ pub fn query_params(
         predicate,
         sample_ratio: 1.0,
         extra: HashMap::new(),
+        valid_time: None,
+        excluded_tables: vec![],
     }
 }
 
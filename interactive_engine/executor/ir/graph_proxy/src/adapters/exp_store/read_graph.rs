@@ -33,8 +33,8 @@ use pegasus_common::impl_as_any;
 
 use crate::apis::graph::PKV;
 use crate::apis::{
-    from_fn, ClusterInfo, Details, Direction, DynDetails, Edge, PropertyValue, QueryParams, ReadGraph,
-    Statement, Vertex, ID,
+    from_fn, ClusterInfo, DegreeHistogram, Details, Direction, DynDetails, Edge, GraphStatistics,
+    PropertyValue, QueryParams, ReadGraph, Statement, Vertex, ID,
 };
 use crate::errors::GraphProxyResult;
 use crate::{filter_limit, filter_sample_limit, limit_n, sample_limit, GraphProxyError};
@@ -386,6 +386,63 @@ impl ReadGraph for ExpStore {
         Ok(Some((EXP_STORE_PK.into(), pk_val).into()))
     }
 
+    /// Collect the statistics by a scan of the graph of the current partition, which are estimations
+    /// of the whole graph as the partitions are alike.
+    fn collect_statistics(&self) -> GraphProxyResult<Option<GraphStatistics>> {
+        let mut statistics = GraphStatistics::new();
+        let mut ndvs = NdvCounter::default();
+        let mut vertex_counts: HashMap<LabelId, u64> = HashMap::default();
+        // the vertices of each degree by the power of two no less than it, i.e., a bucket per power
+        let mut degrees: HashMap<(LabelId, Direction), HashMap<u64, u64>> = HashMap::default();
+        for v in self.store.get_all_vertices(None) {
+            let label = encode_runtime_v_label(&v);
+            *vertex_counts.entry(label).or_default() += 1;
+            ndvs.add(label, v.clone_all_properties());
+            for (direction, edges) in [
+                (Direction::Out, self.store.get_out_edges(v.get_id(), None)),
+                (Direction::In, self.store.get_in_edges(v.get_id(), None)),
+            ] {
+                let mut degree_of_labels: HashMap<LabelId, u64> = HashMap::default();
+                for e in edges {
+                    *degree_of_labels
+                        .entry(encode_runtime_e_label(&e))
+                        .or_default() += 1;
+                }
+                for (edge_label, degree) in degree_of_labels {
+                    *degrees
+                        .entry((edge_label, direction))
+                        .or_default()
+                        .entry(degree.next_power_of_two())
+                        .or_default() += 1;
+                }
+            }
+        }
+        for (label, count) in vertex_counts {
+            statistics.add_vertex_count(label, count);
+        }
+        for ((label, key), ndv) in ndvs.finish() {
+            statistics.add_vertex_ndv(label, key, ndv);
+        }
+        for ((label, direction), buckets) in degrees {
+            statistics.add_degrees(label, direction, DegreeHistogram::new(buckets.into_iter().collect()));
+        }
+
+        let mut ndvs = NdvCounter::default();
+        let mut edge_counts: HashMap<LabelId, u64> = HashMap::default();
+        for e in self.store.get_all_edges(None) {
+            let label = encode_runtime_e_label(&e);
+            *edge_counts.entry(label).or_default() += 1;
+            ndvs.add(label, e.clone_all_properties());
+        }
+        for (label, count) in edge_counts {
+            statistics.add_edge_count(label, count);
+        }
+        for ((label, key), ndv) in ndvs.finish() {
+            statistics.add_edge_ndv(label, key, ndv);
+        }
+        Ok(Some(statistics))
+    }
+
     fn count_vertex(&self, params: &QueryParams) -> GraphProxyResult<u64> {
         if params.filter.is_some() {
             // the filter cannot be pushed down to exp_store,
@@ -464,6 +521,46 @@ fn to_runtime_edge(e: LocalEdge<'static, DefaultId, InternalId>, prop_keys: Opti
     e
 }
 
+/// The distinct values of a property of a label to count at most, beyond which the values are not
+/// collected any more, to bound the memory of the counter.
+const MAX_NDV: usize = 1 << 16;
+
+/// Count the numbers of the distinct values of the properties of the labels.
+#[derive(Default)]
+struct NdvCounter {
+    values: HashMap<(LabelId, String), ahash::HashSet<Object>>,
+    saturated: ahash::HashSet<(LabelId, String)>,
+}
+
+impl NdvCounter {
+    fn add(&mut self, label: LabelId, properties: Option<std::collections::HashMap<String, Object>>) {
+        for (key, value) in properties.into_iter().flatten() {
+            let key = (label, key);
+            if self.saturated.contains(&key) {
+                continue;
+            }
+            let values = self.values.entry(key.clone()).or_default();
+            values.insert(value);
+            if values.len() >= MAX_NDV {
+                self.values.remove(&key);
+                self.saturated.insert(key);
+            }
+        }
+    }
+
+    fn finish(self) -> Vec<((LabelId, NameOrId), u64)> {
+        let saturated = self
+            .saturated
+            .into_iter()
+            .map(|(label, key)| ((label, key.into()), MAX_NDV as u64));
+        self.values
+            .into_iter()
+            .map(|((label, key), values)| ((label, key.into()), values.len() as u64))
+            .chain(saturated)
+            .collect()
+    }
+}
+
 /// LazyVertexDetails is used for local property fetching optimization.
 /// That is, the required properties will not be materialized until LazyVertexDetails need to be shuffled.
 #[allow(dead_code)]
@@ -19,17 +19,28 @@ use std::sync::Arc;
 use global_query::store_api::{PartitionId, VertexId};
 use global_query::GraphPartitionManager;
 
-use crate::apis::partitioner::{PartitionInfo, PartitionedData, ServerId};
+use crate::apis::partitioner::{PartitionInfo, PartitionKeyId, PartitionedData, ServerId};
+use crate::apis::{get_current_consistency, ReadConsistency};
+use crate::utils::hash_ring::HashRing;
 use crate::{GraphProxyError, GraphProxyResult};
 
 /// A partition utility that one server contains multiple graph partitions for Groot Store
+/// The partitions are routed to servers by the routing table of the store, or by a consistent-hashing
+/// ring of the servers if it's given, with which the partitions are placed on servers without the table.
 pub struct GrootMultiPartition {
     graph_partition_manager: Arc<dyn GraphPartitionManager>,
+    hash_ring: Option<Arc<HashRing>>,
 }
 
 impl GrootMultiPartition {
     pub fn new(graph_partition_manager: Arc<dyn GraphPartitionManager>) -> Self {
-        GrootMultiPartition { graph_partition_manager }
+        GrootMultiPartition { graph_partition_manager, hash_ring: None }
+    }
+
+    pub fn with_hash_ring(
+        graph_partition_manager: Arc<dyn GraphPartitionManager>, hash_ring: Arc<HashRing>,
+    ) -> Self {
+        GrootMultiPartition { graph_partition_manager, hash_ring: Some(hash_ring) }
     }
 }
 
@@ -40,14 +51,27 @@ impl PartitionInfo for GrootMultiPartition {
             .get_partition_id(data.get_partition_key_id() as VertexId) as PartitionId)
     }
     fn get_server_id(&self, partition_id: PartitionId) -> GraphProxyResult<ServerId> {
-        self.graph_partition_manager
-            .get_server_id(partition_id)
-            .ok_or_else(|| {
-                GraphProxyError::query_store_error(&format!(
-                    "get server id failed on Groot with partition_id of {:?}",
-                    partition_id
-                ))
-            })
+        let server_id = match self.hash_ring {
+            Some(ref ring) => ring.get_server(partition_id as PartitionKeyId),
+            None => match get_current_consistency() {
+                // the leaders have the latest committed snapshot, while the followers may be behind
+                Some(ReadConsistency::LatestCommitted) | Some(ReadConsistency::Snapshot(_)) => self
+                    .graph_partition_manager
+                    .get_server_id_within_staleness(partition_id, 0),
+                Some(ReadConsistency::BoundedStaleness(max_staleness_ms)) => self
+                    .graph_partition_manager
+                    .get_server_id_within_staleness(partition_id, max_staleness_ms),
+                None => self
+                    .graph_partition_manager
+                    .get_server_id(partition_id),
+            },
+        };
+        server_id.ok_or_else(|| {
+            GraphProxyError::cluster_info_missing(&format!(
+                "get server id failed on Groot with partition_id of {:?}",
+                partition_id
+            ))
+        })
     }
 }
 
@@ -32,8 +32,11 @@ use ir_common::{KeyId, LabelId, NameOrId, OneOrMany};
 use crate::adapters::gs_store::details::{LazyEdgeDetails, LazyVertexDetails};
 use crate::apis::graph::PKV;
 use crate::apis::ClusterInfo;
-use crate::apis::{from_fn, Direction, DynDetails, Edge, QueryParams, ReadGraph, Statement, Vertex, ID};
-use crate::utils::expr::eval_pred::PEvaluator;
+use crate::apis::{
+    from_fn, get_current_snapshot, Direction, DynDetails, Edge, EdgeRef, QueryParams, ReadGraph, Statement,
+    Vertex, ID,
+};
+use crate::utils::expr::eval_pred::{EvalPred, PEvaluator};
 use crate::{filter_limit, filter_sample_limit, limit_n, sample_limit};
 use crate::{GraphProxyError, GraphProxyResult};
 
@@ -82,6 +85,80 @@ where
     Arc::new(graph)
 }
 
+impl<V, VI, E, EI> GraphScopeStore<V, VI, E, EI>
+where
+    V: StoreVertex + 'static,
+    VI: Iterator<Item = V> + Send + 'static,
+    E: StoreEdge + 'static,
+    EI: Iterator<Item = E> + Send + 'static,
+{
+    /// The id of the vertex of the label and the primary key, looked up on any partition, local or not.
+    pub fn get_vertex_id_by_primary_key(
+        &self, label_id: LabelId, primary_key: &PKV,
+    ) -> GraphProxyResult<Option<ID>> {
+        let store_label_id = encode_storage_label(label_id)?;
+        let store_indexed_values = match primary_key {
+            OneOrMany::One(pkv) => {
+                vec![encode_store_prop_val(pkv[0].1.clone())]
+            }
+            OneOrMany::Many(pkvs) => {
+                // the composite primary key is hashed in the order of the keys in the schema, rather
+                // than the order of the predicates in the query;
+                let pk_ids = self
+                    .partition_manager
+                    .get_primary_key_ids(store_label_id);
+                match pk_ids {
+                    Some(pk_ids) if pk_ids.len() == pkvs.len() => pk_ids
+                        .iter()
+                        .map(|pk_id| {
+                            pkvs.iter()
+                                .find(|(pk, _)| *pk == NameOrId::Id(*pk_id as KeyId))
+                                .map(|(_, value)| encode_store_prop_val(value.clone()))
+                                .ok_or_else(|| {
+                                    GraphProxyError::invalid_query_error(&format!(
+                                        "primary key {} of label {} is not given in {:?}",
+                                        pk_id, label_id, pkvs
+                                    ))
+                                })
+                        })
+                        .collect::<GraphProxyResult<Vec<_>>>()?,
+                    Some(pk_ids) => {
+                        return Err(GraphProxyError::invalid_query_error(&format!(
+                            "{} primary keys of label {} are required, but {:?} are given",
+                            pk_ids.len(),
+                            label_id,
+                            pkvs
+                        )))
+                    }
+                    None => pkvs
+                        .iter()
+                        .map(|(_pk, value)| encode_store_prop_val(value.clone()))
+                        .collect(),
+                }
+            }
+        };
+        debug!("get_vertex_id_by_primary_key store_indexed_values {:?}", store_indexed_values);
+        if let Some(vid) = self
+            .partition_manager
+            .get_vertex_id_by_primary_keys(store_label_id, store_indexed_values.as_ref())
+        {
+            debug!("get_vertex_id_by_primary_key vid {:?}", vid);
+            // the vertices of the external string ids are stored by the ids mapped, which are
+            // owned by the same partition as the hashed ones;
+            let vid = match store_indexed_values.as_slice() {
+                [Property::String(external_id)] => self
+                    .partition_manager
+                    .lookup_by_external_id(store_label_id, external_id)
+                    .unwrap_or(vid),
+                _ => vid,
+            };
+            Ok(Some(vid as ID))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
 impl<V, VI, E, EI> ReadGraph for GraphScopeStore<V, VI, E, EI>
 where
     V: StoreVertex + 'static,
@@ -96,7 +173,7 @@ where
         debug!("scan_vertex worker_partitions: {:?}", worker_partitions);
         if !worker_partitions.is_empty() {
             let store = self.store.clone();
-            let si = get_snapshot_id(params);
+            let si = get_snapshot_id(params)?;
             let label_ids = encode_storage_labels(params.labels.as_ref())?;
             let row_filter = params.filter.clone();
 
@@ -152,33 +229,18 @@ where
     fn index_scan_vertex(
         &self, label_id: LabelId, primary_key: &PKV, _params: &QueryParams,
     ) -> GraphProxyResult<Option<Vertex>> {
-        // get_vertex_id_by_primary_keys() is a global query function, that is,
+        // get_vertex_id_by_primary_key() is a global query function, that is,
         // you can query vertices (with only vertex id) by pks on any graph partitions (not matter locally or remotely).
         // To guarantee the correctness,
         // 1. all workers are going to search for gid, and compute  which partition this vertex belongs;
         // 2. the worker assigned for this partition will further confirm the result by calling get_vertex() to see if this vertex exists
-        let store_label_id = encode_storage_label(label_id)?;
-        let store_indexed_values = match primary_key {
-            OneOrMany::One(pkv) => {
-                vec![encode_store_prop_val(pkv[0].1.clone())]
-            }
-            OneOrMany::Many(pkvs) => pkvs
-                .iter()
-                .map(|(_pk, value)| encode_store_prop_val(value.clone()))
-                .collect(),
-        };
-        debug!("index_scan_vertex store_indexed_values {:?}", store_indexed_values);
-        if let Some(vid) = self
-            .partition_manager
-            .get_vertex_id_by_primary_keys(store_label_id, store_indexed_values.as_ref())
-        {
-            debug!("index_scan_vertex vid {:?}", vid);
+        if let Some(vid) = self.get_vertex_id_by_primary_key(label_id, primary_key)? {
             let partition_id = self
                 .partition_manager
                 .get_partition_id(vid as VertexId) as PartitionId;
             let worker_partitions = assign_worker_partitions(&self.server_partitions, &self.cluster_info)?;
             if worker_partitions.contains(&partition_id) {
-                Ok(self.get_vertex(&[vid as ID], _params)?.next())
+                Ok(self.get_vertex(&[vid], _params)?.next())
             } else {
                 Ok(None)
             }
@@ -191,7 +253,7 @@ where
         let worker_partitions = assign_worker_partitions(&self.server_partitions, &self.cluster_info)?;
         if !worker_partitions.is_empty() {
             let store = self.store.clone();
-            let si = get_snapshot_id(params);
+            let si = get_snapshot_id(params)?;
             let label_ids = encode_storage_labels(params.labels.as_ref())?;
             let row_filter = params.filter.clone();
 
@@ -236,7 +298,7 @@ where
         &self, ids: &[ID], params: &QueryParams,
     ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
         let store = self.store.clone();
-        let si = get_snapshot_id(params);
+        let si = get_snapshot_id(params)?;
 
         let column_filter_pushdown = self.column_filter_pushdown;
         // also need props in filter, because `filter_limit!`
@@ -265,7 +327,43 @@ where
         &self, _ids: &[ID], _params: &QueryParams,
     ) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
         // TODO(bingqing): adapt get_edge when graphscope support this
-        Err(GraphProxyError::query_store_error("GraphScope storage does not support get_edge for now"))?
+        Err(GraphProxyError::unsupported_error("GraphScope storage does not support get_edge for now"))?
+    }
+
+    fn get_edge_by_id(&self, edge_ref: &EdgeRef, params: &QueryParams) -> GraphProxyResult<Option<Edge>> {
+        // the edge is looked up by the worker assigned for the partition of its source vertex, as
+        // `index_scan_vertex()` does;
+        let partition_id = self
+            .partition_manager
+            .get_partition_id(edge_ref.src_id as VertexId) as PartitionId;
+        let worker_partitions = assign_worker_partitions(&self.server_partitions, &self.cluster_info)?;
+        if !worker_partitions.contains(&partition_id) {
+            return Ok(None);
+        }
+        let si = get_snapshot_id(params)?;
+        let prop_ids = if self.column_filter_pushdown {
+            let cache_prop_ids = encode_storage_prop_keys(params.columns.as_ref())?;
+            extract_needed_columns(params.filter.as_ref(), cache_prop_ids.as_ref())?
+        } else {
+            get_all_storage_props()
+        };
+        let edge = self
+            .store
+            .get_edge_by_id(
+                si,
+                partition_id,
+                encode_storage_label(edge_ref.label)?,
+                edge_ref.src_id as VertexId,
+                edge_ref.dst_id as VertexId,
+                edge_ref.id,
+                prop_ids.as_ref(),
+            )
+            .map(|e| to_runtime_edge(e, params.columns.clone(), true))
+            .filter(|e| match params.filter {
+                Some(ref f) => f.eval_bool(Some(e)).unwrap_or(false),
+                None => true,
+            });
+        Ok(edge)
     }
 
     fn prepare_explore_vertex(
@@ -279,7 +377,7 @@ where
         let limit = params.limit.clone();
         let store = self.store.clone();
         let partition_manager = self.partition_manager.clone();
-        let si = get_snapshot_id(params);
+        let si = get_snapshot_id(params)?;
         let edge_label_ids = encode_storage_labels(params.labels.as_ref())?;
 
         let stmt = from_fn(move |v: ID| {
@@ -340,7 +438,7 @@ where
         &self, direction: Direction, params: &QueryParams,
     ) -> GraphProxyResult<Box<dyn Statement<ID, Edge>>> {
         let store = self.store.clone();
-        let si = get_snapshot_id(params);
+        let si = get_snapshot_id(params)?;
 
         let partition_manager = self.partition_manager.clone();
         let row_filter = params.filter.clone();
@@ -459,7 +557,7 @@ where
             let worker_partitions = assign_worker_partitions(&self.server_partitions, &self.cluster_info)?;
             if !worker_partitions.is_empty() {
                 let store = self.store.clone();
-                let si = get_snapshot_id(params);
+                let si = get_snapshot_id(params)?;
                 let label_ids = encode_storage_labels(params.labels.as_ref())?;
                 let count =
                     store.count_all_vertices(si, label_ids.as_ref(), None, worker_partitions.as_ref());
@@ -477,7 +575,7 @@ where
             let worker_partitions = assign_worker_partitions(&self.server_partitions, &self.cluster_info)?;
             if !worker_partitions.is_empty() {
                 let store = self.store.clone();
-                let si = get_snapshot_id(params);
+                let si = get_snapshot_id(params)?;
                 let label_ids = encode_storage_labels(params.labels.as_ref())?;
                 let count = store.count_all_edges(si, label_ids.as_ref(), None, worker_partitions.as_ref());
                 Ok(count)
@@ -488,15 +586,15 @@ where
     }
 }
 
-fn get_snapshot_id(params: &QueryParams) -> SnapshotId {
-    let si = params
+/// The snapshot to read, which is that of the job if it reads a specific snapshot no later than the
+/// latest committed one given by the compiler, see `ReadConsistency`, or the latest committed one
+/// otherwise.
+fn get_snapshot_id(params: &QueryParams) -> GraphProxyResult<SnapshotId> {
+    let latest = params
         .get_extra_param(SNAPSHOT_ID)
-        .map(|s| {
-            s.parse::<SnapshotId>()
-                .unwrap_or(DEFAULT_SNAPSHOT_ID)
-        })
-        .unwrap_or(DEFAULT_SNAPSHOT_ID);
-    si
+        .and_then(|s| s.parse::<SnapshotId>().ok());
+    let si = get_current_snapshot(latest)?.unwrap_or(DEFAULT_SNAPSHOT_ID);
+    Ok(si)
 }
 
 #[inline]
@@ -172,6 +172,18 @@ impl TryFrom<&Predicates> for Option<Condition> {
                 builder.and(Condition::new(pred));
                 Ok(builder.build())
             }
+            // the existence of a property is checked by the store, as is its absence, e.g., `hasNot(key)`
+            // lowered as `ISNULL`, since a property stored never has a null value
+            Predicates::Unary(upred)
+                if upred.cmp == common_pb::Logical::Exists || upred.cmp == common_pb::Logical::Isnull =>
+            {
+                let key = upred.operand.get_var_prop_id()?;
+                builder.and(Condition::new(StorePredCondition::new_has_prop(key)));
+                if upred.cmp == common_pb::Logical::Isnull {
+                    builder.not();
+                }
+                Ok(builder.build())
+            }
             Predicates::Unary(upred) => Err(GraphProxyError::FilterPushDownError(format!(
                 "Haven't support Unary(Evaluator) yet {:?}",
                 upred
@@ -265,6 +277,31 @@ mod test {
         assert_eq!(cond, target);
     }
 
+    #[test]
+    fn test_exists_predicates_to_condition() {
+        let operand = Operand::Var { tag: None, prop_key: Some(PropKey::Key(NameOrId::Id(1))) };
+        let exists = Predicates::Unary(UnaryPredicate { operand, cmp: common_pb::Logical::Exists });
+        let pred = &Predicates::Not(Box::new(exists));
+        let target = ConditionBuilder::new()
+            .and(Condition::Pred(StorePredCondition::new_has_prop(1)))
+            .not()
+            .build();
+        let cond: Result<Option<Condition>, GraphProxyError> = pred.try_into();
+        assert_eq!(cond.unwrap(), target);
+
+        // the null check is the absence of the property as well
+        let operand = Operand::Var { tag: None, prop_key: Some(PropKey::Key(NameOrId::Id(1))) };
+        let pred = &Predicates::Unary(UnaryPredicate { operand, cmp: common_pb::Logical::Isnull });
+        let cond: Result<Option<Condition>, GraphProxyError> = pred.try_into();
+        assert_eq!(cond.unwrap(), target);
+
+        // the null check of a value other than a property is left to the filter operator
+        let operand = Operand::Var { tag: None, prop_key: None };
+        let pred = &Predicates::Unary(UnaryPredicate { operand, cmp: common_pb::Logical::Isnull });
+        let cond: Result<Option<Condition>, GraphProxyError> = pred.try_into();
+        assert!(cond.is_err());
+    }
+
     #[test]
     fn test_single_op_predicates_to_condition() {
         let left = Operand::Var { tag: None, prop_key: Some(PropKey::Key(NameOrId::Id(1))) };
@@ -25,4 +25,6 @@ pub use exp_store::{create_exp_store, SimplePartition};
 #[cfg(feature = "with_global_query")]
 pub use gs_store::{create_gs_store, GraphScopeStore, GrootMultiPartition, VineyardMultiPartition};
 #[cfg(feature = "with_global_query")]
-pub use vineyard_store::VineyardGraphWriter;
+pub use vineyard_store::{
+    VineyardDelta, VineyardDeltaGraph, VineyardDeltaWriter, VineyardGraphWriter, VineyardIdParser,
+};
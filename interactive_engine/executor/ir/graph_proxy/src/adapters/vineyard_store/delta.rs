@@ -0,0 +1,1167 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The fragments of vineyard are immutable, thus the vertices and the edges written into a vineyard
+//! graph are appended to the delta of the server, which is read alongside the fragments through
+//! `VineyardDeltaGraph`, and written through `VineyardDeltaWriter`.
+//!
+//! The vertices of the delta are assigned to the partitions by the hash of their primary keys, with
+//! the ids in the layout of the global ids of vineyard, taking the offsets from the highest of the
+//! partition and the label downwards, so that they are routed and scanned by the workers as those
+//! of the partitions. A vertex is only added on the server of its partition, where its primary key
+//! is checked against both the delta and the fragments. An edge is only added between the vertices
+//! of the partitions of the server, thus it is kept along with both of its endpoints.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use ahash::{HashMap, HashMapExt};
+use dyn_type::Object;
+use ir_common::generated::schema as schema_pb;
+use ir_common::{LabelId, NameOrId, OneOrMany};
+
+use crate::apis::graph::PKV;
+use crate::apis::partitioner::PartitionId;
+use crate::apis::{
+    ClusterInfo, Details, Direction, DynDetails, Edge, EdgeRef, Element, GraphElement, Mutated, Mutation,
+    QueryParams, ReadGraph, Statement, Vertex, WriteGraphProxy, ID,
+};
+use crate::utils::expr::eval::Context;
+use crate::utils::expr::eval_pred::EvalPred;
+use crate::{limit_n, GraphProxyError, GraphProxyResult};
+
+/// The layout of the global ids of vineyard, i.e., the partition (fragment), the label and the offset,
+/// from the highest bits to the lowest, see `IdParser` of vineyard.
+#[derive(Clone, Copy, Debug)]
+pub struct VineyardIdParser {
+    fid_offset: u32,
+    label_offset: u32,
+}
+
+/// The number of bits to encode the numbers below `num`, which is at least one, as vineyard does.
+fn bit_width(num: u32) -> u32 {
+    if num <= 2 {
+        1
+    } else {
+        32 - (num - 1).leading_zeros()
+    }
+}
+
+impl VineyardIdParser {
+    /// The layout of the ids of the graph of `fnum` partitions and `label_num` vertex labels.
+    pub fn new(fnum: u32, label_num: u32) -> Self {
+        let fid_offset = 64 - bit_width(fnum);
+        let label_offset = fid_offset - bit_width(label_num);
+        VineyardIdParser { fid_offset, label_offset }
+    }
+
+    pub fn get_partition(&self, id: ID) -> PartitionId {
+        ((id as u64) >> self.fid_offset) as PartitionId
+    }
+
+    pub fn get_label(&self, id: ID) -> LabelId {
+        let label_mask = (1_u64 << (self.fid_offset - self.label_offset)) - 1;
+        (((id as u64) >> self.label_offset) & label_mask) as LabelId
+    }
+
+    /// The largest offset of the vertices of a partition and a label.
+    pub fn max_offset(&self) -> u64 {
+        (1_u64 << self.label_offset) - 1
+    }
+
+    pub fn generate_id(&self, partition: PartitionId, label: LabelId, offset: u64) -> ID {
+        (((partition as u64) << self.fid_offset) | ((label as u64) << self.label_offset) | offset) as ID
+    }
+}
+
+#[derive(Default)]
+struct DeltaData {
+    vertices: Vec<Vertex>,
+    vertex_indices: HashMap<ID, usize>,
+    /// The vertices by their labels and the values of their primary keys, in the order of the schema
+    primary_keys: HashMap<(LabelId, Vec<Object>), ID>,
+    /// The number of the vertices of each partition and label, to allocate their offsets
+    offsets: HashMap<(PartitionId, LabelId), u64>,
+    /// The number of the edges of each partition and label, to allocate their offsets
+    edge_offsets: HashMap<(PartitionId, LabelId), u64>,
+    edges: Vec<Edge>,
+    edge_indices: HashMap<ID, usize>,
+    out_edges: HashMap<ID, Vec<usize>>,
+    in_edges: HashMap<ID, Vec<usize>>,
+}
+
+/// The id of the vertex of the fragments of the label and the primary key, on any of the partitions.
+pub type FragmentIndex = Arc<dyn Fn(LabelId, &PKV) -> GraphProxyResult<Option<ID>> + Send + Sync>;
+
+/// The vertices and the edges appended to the immutable fragments of vineyard on a server.
+pub struct VineyardDelta {
+    parser: VineyardIdParser,
+    /// The number of the partitions of the graph
+    fnum: u32,
+    /// The partitions of the server
+    partitions: Vec<PartitionId>,
+    fragments: FragmentIndex,
+    /// The primary keys of each vertex label
+    primary_keys: HashMap<LabelId, Vec<NameOrId>>,
+    data: RwLock<DeltaData>,
+}
+
+impl VineyardDelta {
+    /// The delta of the server of the partitions, out of the `fnum` partitions of the graph, where the
+    /// properties are keyed by the ids of the schema, as written into vineyard, and the vertices of
+    /// the fragments are looked up by their primary keys through `fragments`.
+    pub fn new(
+        schema: &schema_pb::Schema, fnum: u32, partitions: Vec<PartitionId>, fragments: FragmentIndex,
+    ) -> GraphProxyResult<Self> {
+        if partitions.is_empty() {
+            Err(GraphProxyError::write_graph_error("no partition for the delta of vineyard"))?
+        }
+        let mut primary_keys = HashMap::new();
+        let mut label_num = 0;
+        for entity in schema.entities.iter() {
+            let label = entity.label.as_ref().ok_or_else(|| {
+                GraphProxyError::write_graph_error(&format!("label of the entity {:?}", entity))
+            })?;
+            let keys = entity
+                .columns
+                .iter()
+                .filter(|column| column.is_primary_key)
+                .filter_map(|column| column.key.as_ref())
+                .map(|key| NameOrId::Id(key.id))
+                .collect();
+            primary_keys.insert(label.id, keys);
+            label_num = label_num.max(label.id as u32 + 1);
+        }
+        Ok(VineyardDelta {
+            parser: VineyardIdParser::new(fnum, label_num),
+            fnum,
+            partitions,
+            fragments,
+            primary_keys,
+            data: RwLock::new(DeltaData::default()),
+        })
+    }
+
+    pub fn get_parser(&self) -> &VineyardIdParser {
+        &self.parser
+    }
+
+    fn read(&self) -> RwLockReadGuard<DeltaData> {
+        self.data
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<DeltaData> {
+        self.data
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The values of the primary keys of the label, in the order of the schema, where a single value
+    /// is taken as the value of the only primary key.
+    fn get_pk_values(&self, label: LabelId, pk: &PKV) -> GraphProxyResult<Vec<Object>> {
+        let keys = self.primary_keys.get(&label).ok_or_else(|| {
+            GraphProxyError::write_graph_error(&format!("vertex label {} is not in the schema", label))
+        })?;
+        match pk {
+            OneOrMany::One(pkv) if keys.len() <= 1 => Ok(vec![pkv[0].1.clone()]),
+            _ => keys
+                .iter()
+                .map(|key| {
+                    pk.iter()
+                        .find(|(pk_key, _)| pk_key == key)
+                        .map(|(_, value)| value.clone())
+                        .ok_or_else(|| {
+                            GraphProxyError::write_graph_error(&format!(
+                                "primary key {:?} of label {} is not given in {:?}",
+                                key, label, pk
+                            ))
+                        })
+                })
+                .collect(),
+        }
+    }
+
+    /// The primary key of the vertex of the label, which is found in its properties.
+    fn get_pk(&self, label: LabelId, properties: &DynDetails) -> GraphProxyResult<PKV> {
+        let keys = self.primary_keys.get(&label).ok_or_else(|| {
+            GraphProxyError::write_graph_error(&format!("vertex label {} is not in the schema", label))
+        })?;
+        let mut pk = Vec::with_capacity(keys.len());
+        for key in keys {
+            let value = properties
+                .get_property(key)
+                .and_then(|value| value.try_to_owned())
+                .ok_or_else(|| {
+                    GraphProxyError::write_graph_error(&format!(
+                        "primary key {:?} of label {} is not given in {:?}",
+                        key, label, properties
+                    ))
+                })?;
+            pk.push((key.clone(), value));
+        }
+        Ok(pk.into())
+    }
+
+    /// The partition of the vertex by the hash of the values of its primary key, which is the same
+    /// on all the servers, and must be of this server to add the vertex.
+    fn get_pk_partition(&self, label: LabelId, pk_values: &[Object]) -> GraphProxyResult<PartitionId> {
+        let mut hasher = DefaultHasher::new();
+        pk_values.hash(&mut hasher);
+        let partition = (hasher.finish() % self.fnum as u64) as PartitionId;
+        if self.partitions.contains(&partition) {
+            Ok(partition)
+        } else {
+            Err(GraphProxyError::write_graph_error(&format!(
+                "vertex of label {} and primary key {:?} is of partition {} of another server",
+                label, pk_values, partition
+            )))
+        }
+    }
+
+    /// Add the vertex of the properties, which contain its primary key, to the partition of the primary
+    /// key, and return the vertex added.
+    pub fn add_vertex(&self, label: LabelId, properties: DynDetails) -> GraphProxyResult<Vertex> {
+        let pk = self.get_pk(label, &properties)?;
+        let pk_values = self.get_pk_values(label, &pk)?;
+        let partition = self.get_pk_partition(label, &pk_values)?;
+        let mut data = self.write();
+        if data
+            .primary_keys
+            .contains_key(&(label, pk_values.clone()))
+            || (self.fragments)(label, &pk)?.is_some()
+        {
+            Err(GraphProxyError::write_graph_error(&format!(
+                "vertex of label {} and primary key {:?} already exists",
+                label, pk
+            )))?
+        }
+        self.insert_vertex(&mut data, partition, label, pk_values, properties)
+    }
+
+    /// Match the vertex of the delta by the primary key in the properties, or add it with the properties
+    /// and `on_create` if not matched, where the `on_match` properties are set to the matched one, under
+    /// the same lock. Return the vertex after the update, and whether it is added. A vertex of the
+    /// fragments is matched as well, but can't be updated.
+    pub fn merge_vertex(
+        &self, label: LabelId, properties: DynDetails, on_create: DynDetails, on_match: DynDetails,
+    ) -> GraphProxyResult<(Vertex, bool)> {
+        let pk = self.get_pk(label, &properties)?;
+        let pk_values = self.get_pk_values(label, &pk)?;
+        let partition = self.get_pk_partition(label, &pk_values)?;
+        let mut data = self.write();
+        if let Some(id) = data
+            .primary_keys
+            .get(&(label, pk_values.clone()))
+            .cloned()
+        {
+            let index = data.vertex_indices[&id];
+            let details = set_properties(data.vertices[index].get_details(), &on_match);
+            let vertex = Vertex::new(id, Some(label), details);
+            data.vertices[index] = vertex.clone();
+            Ok((vertex, false))
+        } else if let Some(id) = (self.fragments)(label, &pk)? {
+            if on_match
+                .get_all_properties()
+                .map_or(false, |props| !props.is_empty())
+            {
+                Err(GraphProxyError::unsupported_error(&format!(
+                    "set properties of vertex {} on the immutable fragments of vineyard",
+                    id
+                )))?
+            }
+            Ok((Vertex::new(id, Some(label), properties), false))
+        } else {
+            let properties = set_properties(&properties, &on_create);
+            let vertex = self.insert_vertex(&mut data, partition, label, pk_values, properties)?;
+            Ok((vertex, true))
+        }
+    }
+
+    fn insert_vertex(
+        &self, data: &mut DeltaData, partition: PartitionId, label: LabelId, pk_values: Vec<Object>,
+        properties: DynDetails,
+    ) -> GraphProxyResult<Vertex> {
+        let count = data
+            .offsets
+            .entry((partition, label))
+            .or_insert(0);
+        if *count > self.parser.max_offset() {
+            Err(GraphProxyError::write_graph_error(&format!(
+                "no more vertices of label {} can be added to partition {}",
+                label, partition
+            )))?
+        }
+        let id = self
+            .parser
+            .generate_id(partition, label, self.parser.max_offset() - *count);
+        *count += 1;
+        let vertex = Vertex::new(id, Some(label), properties);
+        let index = data.vertices.len();
+        data.vertices.push(vertex.clone());
+        data.vertex_indices.insert(id, index);
+        data.primary_keys.insert((label, pk_values), id);
+        Ok(vertex)
+    }
+
+    /// Add the edge between the vertices, of the delta or the fragments, which are both of the partitions
+    /// of the server, and return the edge added.
+    pub fn add_edge(
+        &self, label: LabelId, src_id: ID, dst_id: ID, properties: DynDetails,
+    ) -> GraphProxyResult<Edge> {
+        self.insert_edge(&mut self.write(), label, src_id, dst_id, properties)
+    }
+
+    /// Match the edge of the delta of the label and the properties between the vertices, or add it with
+    /// the properties and `on_create` if not matched, where the `on_match` properties are set to the
+    /// matched one, under the same lock. Return the edge after the update, and whether it is added.
+    pub fn merge_edge(
+        &self, label: LabelId, src_id: ID, dst_id: ID, properties: DynDetails, on_create: DynDetails,
+        on_match: DynDetails,
+    ) -> GraphProxyResult<(Edge, bool)> {
+        let expected = properties
+            .get_all_properties()
+            .unwrap_or_default();
+        let mut data = self.write();
+        let matched = data
+            .out_edges
+            .get(&src_id)
+            .into_iter()
+            .flatten()
+            .cloned()
+            .find(|index| {
+                let edge = &data.edges[*index];
+                edge.dst_id == dst_id
+                    && edge.label() == Some(label)
+                    && expected.iter().all(|(key, value)| {
+                        edge.get_details()
+                            .get_property(key)
+                            .and_then(|v| v.try_to_owned())
+                            .map_or(false, |v| &v == value)
+                    })
+            });
+        if let Some(index) = matched {
+            let edge = &data.edges[index];
+            let details = set_properties(edge.get_details(), &on_match);
+            let mut updated = Edge::new(edge.id(), Some(label), src_id, dst_id, details);
+            updated.src_label = edge.src_label;
+            updated.dst_label = edge.dst_label;
+            data.edges[index] = updated.clone();
+            Ok((updated, false))
+        } else {
+            let properties = set_properties(&properties, &on_create);
+            let edge = self.insert_edge(&mut data, label, src_id, dst_id, properties)?;
+            Ok((edge, true))
+        }
+    }
+
+    fn insert_edge(
+        &self, data: &mut DeltaData, label: LabelId, src_id: ID, dst_id: ID, properties: DynDetails,
+    ) -> GraphProxyResult<Edge> {
+        // the edges between the servers are rejected, as they are not found by the other servers
+        for id in [src_id, dst_id] {
+            let partition = self.parser.get_partition(id);
+            if !self.partitions.contains(&partition) {
+                Err(GraphProxyError::write_graph_error(&format!(
+                    "edge of label {} from {} to {} has vertex {} of partition {} of another server",
+                    label, src_id, dst_id, id, partition
+                )))?
+            }
+        }
+        // the ids of the edges are allocated as those of the vertices, to be distinct from the fragments
+        let partition = self.parser.get_partition(src_id);
+        let count = data
+            .edge_offsets
+            .entry((partition, label))
+            .or_insert(0);
+        if *count > self.parser.max_offset() {
+            Err(GraphProxyError::write_graph_error(&format!(
+                "no more edges of label {} can be added to partition {}",
+                label, partition
+            )))?
+        }
+        let id = self
+            .parser
+            .generate_id(partition, label, self.parser.max_offset() - *count);
+        *count += 1;
+        let mut edge = Edge::new(id, Some(label), src_id, dst_id, properties);
+        edge.set_src_label(self.parser.get_label(src_id));
+        edge.set_dst_label(self.parser.get_label(dst_id));
+        let index = data.edges.len();
+        data.edges.push(edge.clone());
+        data.edge_indices.insert(id, index);
+        data.out_edges
+            .entry(src_id)
+            .or_insert_with(Vec::new)
+            .push(index);
+        data.in_edges
+            .entry(dst_id)
+            .or_insert_with(Vec::new)
+            .push(index);
+        Ok(edge)
+    }
+
+    pub fn contains_vertex(&self, id: ID) -> bool {
+        self.read().vertex_indices.contains_key(&id)
+    }
+
+    pub fn get_vertex_id(&self, label: LabelId, pk: &PKV) -> GraphProxyResult<Option<ID>> {
+        let pk_values = self.get_pk_values(label, pk)?;
+        Ok(self
+            .read()
+            .primary_keys
+            .get(&(label, pk_values))
+            .cloned())
+    }
+
+    fn get_vertices(&self, ids: &[ID]) -> Vec<Vertex> {
+        let data = self.read();
+        ids.iter()
+            .filter_map(|id| data.vertex_indices.get(id))
+            .map(|index| data.vertices[*index].clone())
+            .collect()
+    }
+
+    fn get_edges(&self, ids: &[ID]) -> Vec<Edge> {
+        let data = self.read();
+        ids.iter()
+            .filter_map(|id| data.edge_indices.get(id))
+            .map(|index| data.edges[*index].clone())
+            .collect()
+    }
+
+    /// The vertices of the partitions and the labels, or all the labels if empty.
+    fn scan_vertices(&self, partitions: &[PartitionId], labels: &[LabelId]) -> Vec<Vertex> {
+        self.read()
+            .vertices
+            .iter()
+            .filter(|v| partitions.contains(&self.parser.get_partition(v.id())))
+            .filter(|v| labels.is_empty() || v.label().map_or(false, |l| labels.contains(&l)))
+            .cloned()
+            .collect()
+    }
+
+    /// The edges of the source vertices of the partitions and the labels, or all the labels if empty.
+    fn scan_edges(&self, partitions: &[PartitionId], labels: &[LabelId]) -> Vec<Edge> {
+        self.read()
+            .edges
+            .iter()
+            .filter(|e| partitions.contains(&self.parser.get_partition(e.src_id)))
+            .filter(|e| labels.is_empty() || e.label().map_or(false, |l| labels.contains(&l)))
+            .cloned()
+            .collect()
+    }
+
+    /// The edges of the vertex in the direction, of the labels, or all the labels if empty.
+    fn adjacent_edges(&self, id: ID, direction: Direction, labels: &[LabelId]) -> Vec<Edge> {
+        let data = self.read();
+        let mut edges = vec![];
+        if let Direction::Out | Direction::Both = direction {
+            for index in data.out_edges.get(&id).into_iter().flatten() {
+                edges.push(data.edges[*index].clone());
+            }
+        }
+        if let Direction::In | Direction::Both = direction {
+            for index in data.in_edges.get(&id).into_iter().flatten() {
+                let e = &data.edges[*index];
+                let mut edge = Edge::with_from_src(
+                    e.id(),
+                    e.label(),
+                    e.src_id,
+                    e.dst_id,
+                    false,
+                    e.get_details().clone(),
+                );
+                edge.src_label = e.src_label;
+                edge.dst_label = e.dst_label;
+                edges.push(edge);
+            }
+        }
+        edges.retain(|e| labels.is_empty() || e.label().map_or(false, |l| labels.contains(&l)));
+        edges
+    }
+}
+
+/// The properties of the details with those of the updates set, which overwrite the existing ones.
+fn set_properties(details: &DynDetails, updates: &DynDetails) -> DynDetails {
+    let mut properties = details.get_all_properties().unwrap_or_default();
+    if let Some(updates) = updates.get_all_properties() {
+        properties.extend(updates);
+    }
+    DynDetails::new(properties)
+}
+
+/// The elements of the delta which satisfy the filter of the params.
+fn filter_delta<E: Element + Context<E>>(elements: Vec<E>, params: &QueryParams) -> Vec<E> {
+    match params.filter.as_ref() {
+        Some(filter) => elements
+            .into_iter()
+            .filter(|e| {
+                filter
+                    .eval_bool::<E, E>(Some(e))
+                    .unwrap_or(false)
+            })
+            .collect(),
+        None => elements,
+    }
+}
+
+/// The vineyard graph, with the delta read alongside the fragments.
+pub struct VineyardDeltaGraph {
+    inner: Arc<dyn ReadGraph>,
+    delta: Arc<VineyardDelta>,
+    cluster_info: Arc<dyn ClusterInfo>,
+}
+
+impl VineyardDeltaGraph {
+    pub fn new(
+        inner: Arc<dyn ReadGraph>, delta: Arc<VineyardDelta>, cluster_info: Arc<dyn ClusterInfo>,
+    ) -> Self {
+        VineyardDeltaGraph { inner, delta, cluster_info }
+    }
+
+    /// The partitions of the server scanned by the current worker, as the fragments are.
+    fn worker_partitions(&self) -> GraphProxyResult<Vec<PartitionId>> {
+        let workers_num = self.cluster_info.get_local_worker_num()?;
+        let worker_idx = self.cluster_info.get_worker_index()?;
+        Ok(self
+            .delta
+            .partitions
+            .iter()
+            .filter(|partition| **partition % workers_num == worker_idx % workers_num)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Chain the edges of the delta adjacent to the vertices to those of the fragments.
+struct DeltaEdgeStatement {
+    inner: Box<dyn Statement<ID, Edge>>,
+    delta: Arc<VineyardDelta>,
+    direction: Direction,
+    params: QueryParams,
+}
+
+impl Statement<ID, Edge> for DeltaEdgeStatement {
+    fn exec(&self, next: ID) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        let edges = self
+            .delta
+            .adjacent_edges(next, self.direction, &self.params.labels);
+        let edges = filter_delta(edges, &self.params);
+        if self.delta.contains_vertex(next) {
+            // the vertices of the delta are absent in the fragments
+            Ok(limit_n!(edges.into_iter(), self.params.limit))
+        } else {
+            let iter = self.inner.exec(next)?.chain(edges);
+            Ok(limit_n!(iter, self.params.limit))
+        }
+    }
+}
+
+/// Chain the vertices adjacent along the edges of the delta to those of the fragments, where the
+/// vertices without the edges of the delta are expanded by the fragments only.
+struct DeltaAdjacentStatement {
+    inner: Box<dyn Statement<ID, Vertex>>,
+    fragments: Arc<dyn ReadGraph>,
+    delta: Arc<VineyardDelta>,
+    direction: Direction,
+    params: QueryParams,
+    /// The params without the labels, which are those of the edges
+    vertex_params: QueryParams,
+}
+
+impl Statement<ID, Vertex> for DeltaAdjacentStatement {
+    fn exec(&self, next: ID) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let ids: Vec<ID> = self
+            .delta
+            .adjacent_edges(next, self.direction, &self.params.labels)
+            .into_iter()
+            .map(|e| e.get_other_id())
+            .collect();
+        let is_delta = self.delta.contains_vertex(next);
+        if ids.is_empty() && !is_delta {
+            return self.inner.exec(next);
+        }
+        // the vertices of the delta are absent in the fragments
+        let mut iter: Box<dyn Iterator<Item = Vertex> + Send> =
+            if is_delta { Box::new(std::iter::empty()) } else { self.inner.exec(next)? };
+        // the adjacent vertices of the edges of the delta are of the partitions of the server
+        let (delta_ids, ids): (Vec<ID>, Vec<ID>) = ids
+            .into_iter()
+            .partition(|id| self.delta.contains_vertex(*id));
+        if !ids.is_empty() {
+            iter = Box::new(
+                iter.chain(
+                    self.fragments
+                        .get_vertex(&ids, &self.vertex_params)?,
+                ),
+            );
+        }
+        let vertices = filter_delta(self.delta.get_vertices(&delta_ids), &self.vertex_params);
+        Ok(limit_n!(iter.chain(vertices), self.params.limit))
+    }
+}
+
+impl ReadGraph for VineyardDeltaGraph {
+    fn scan_vertex(
+        &self, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let vertices = self
+            .delta
+            .scan_vertices(&self.worker_partitions()?, &params.labels);
+        let iter = self
+            .inner
+            .scan_vertex(params)?
+            .chain(filter_delta(vertices, params));
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn index_scan_vertex(
+        &self, label: LabelId, primary_key: &PKV, params: &QueryParams,
+    ) -> GraphProxyResult<Option<Vertex>> {
+        if let Some(vertex) = self
+            .inner
+            .index_scan_vertex(label, primary_key, params)?
+        {
+            return Ok(Some(vertex));
+        }
+        // the vertex of the delta is returned by the worker scanning its partition, as the fragments
+        let id = match self.delta.get_vertex_id(label, primary_key)? {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let partition = self.delta.parser.get_partition(id);
+        if self.worker_partitions()?.contains(&partition) {
+            Ok(filter_delta(self.delta.get_vertices(&[id]), params).pop())
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn scan_edge(&self, params: &QueryParams) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        let edges = self
+            .delta
+            .scan_edges(&self.worker_partitions()?, &params.labels);
+        let iter = self
+            .inner
+            .scan_edge(params)?
+            .chain(filter_delta(edges, params));
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn get_vertex(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let (delta_ids, ids): (Vec<ID>, Vec<ID>) = ids
+            .iter()
+            .partition(|id| self.delta.contains_vertex(**id));
+        let vertices = filter_delta(self.delta.get_vertices(&delta_ids), params);
+        if ids.is_empty() {
+            return Ok(limit_n!(vertices.into_iter(), params.limit));
+        }
+        let iter = self
+            .inner
+            .get_vertex(&ids, params)?
+            .chain(vertices);
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn get_edge(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        let (delta_ids, ids): (Vec<ID>, Vec<ID>) = ids
+            .iter()
+            .partition(|id| self.delta.read().edge_indices.contains_key(*id));
+        let edges = filter_delta(self.delta.get_edges(&delta_ids), params);
+        if ids.is_empty() {
+            return Ok(limit_n!(edges.into_iter(), params.limit));
+        }
+        let iter = self.inner.get_edge(&ids, params)?.chain(edges);
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn get_edge_by_id(&self, edge_ref: &EdgeRef, params: &QueryParams) -> GraphProxyResult<Option<Edge>> {
+        match self.delta.get_edges(&[edge_ref.id]).pop() {
+            Some(edge) => Ok(filter_delta(vec![edge], params).pop()),
+            None => self.inner.get_edge_by_id(edge_ref, params),
+        }
+    }
+
+    fn prepare_explore_vertex(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Vertex>>> {
+        let inner = self
+            .inner
+            .prepare_explore_vertex(direction, params)?;
+        // the labels of the params are those of the edges, while the others apply to the vertices
+        let mut vertex_params = params.clone();
+        vertex_params.labels = vec![];
+        vertex_params.limit = None;
+        Ok(Box::new(DeltaAdjacentStatement {
+            inner,
+            fragments: self.inner.clone(),
+            delta: self.delta.clone(),
+            direction,
+            params: params.clone(),
+            vertex_params,
+        }))
+    }
+
+    fn prepare_explore_edge(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Edge>>> {
+        let inner = self
+            .inner
+            .prepare_explore_edge(direction, params)?;
+        Ok(Box::new(DeltaEdgeStatement {
+            inner,
+            delta: self.delta.clone(),
+            direction,
+            params: params.clone(),
+        }))
+    }
+
+    fn count_vertex(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        let vertices = self
+            .delta
+            .scan_vertices(&self.worker_partitions()?, &params.labels);
+        Ok(self.inner.count_vertex(params)? + filter_delta(vertices, params).len() as u64)
+    }
+
+    fn count_edge(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        let edges = self
+            .delta
+            .scan_edges(&self.worker_partitions()?, &params.labels);
+        Ok(self.inner.count_edge(params)? + filter_delta(edges, params).len() as u64)
+    }
+
+    fn get_primary_key(&self, id: &ID) -> GraphProxyResult<Option<PKV>> {
+        match self.delta.get_vertices(&[*id]).pop() {
+            Some(vertex) => {
+                let label = vertex.label().unwrap_or_default();
+                Ok(Some(self.delta.get_pk(label, vertex.get_details())?))
+            }
+            None => self.inner.get_primary_key(id),
+        }
+    }
+}
+
+/// Write the vertices and the edges through the delta, where they're visible once written.
+pub struct VineyardDeltaWriter {
+    delta: Arc<VineyardDelta>,
+}
+
+impl VineyardDeltaWriter {
+    pub fn new(delta: Arc<VineyardDelta>) -> Self {
+        VineyardDeltaWriter { delta }
+    }
+
+    /// The id of the vertex of the delta, or of the fragments on any of the partitions.
+    fn get_vertex_id(&self, label: LabelId, pk: &PKV) -> GraphProxyResult<ID> {
+        let id = match self.delta.get_vertex_id(label, pk)? {
+            Some(id) => Some(id),
+            None => (self.delta.fragments)(label, pk)?,
+        };
+        id.ok_or_else(|| {
+            GraphProxyError::write_graph_error(&format!(
+                "vertex of label {} and primary key {:?} is not found in vineyard",
+                label, pk
+            ))
+        })
+    }
+}
+
+impl WriteGraphProxy for VineyardDeltaWriter {
+    fn add_vertex(
+        &mut self, label: LabelId, vertex_pk: PKV, properties: DynDetails,
+    ) -> GraphProxyResult<()> {
+        let mut props = properties
+            .get_all_properties()
+            .unwrap_or_default();
+        for (key, value) in vertex_pk.iter() {
+            props.insert(key.clone(), value.clone());
+        }
+        self.delta
+            .add_vertex(label, DynDetails::new(props))
+            .map(|_| ())
+    }
+
+    fn add_edge(
+        &mut self, label: LabelId, src_vertex_label: LabelId, src_vertex_pk: PKV,
+        dst_vertex_label: LabelId, dst_vertex_pk: PKV, properties: DynDetails,
+    ) -> GraphProxyResult<()> {
+        let src_id = self.get_vertex_id(src_vertex_label, &src_vertex_pk)?;
+        let dst_id = self.get_vertex_id(dst_vertex_label, &dst_vertex_pk)?;
+        self.delta
+            .add_edge(label, src_id, dst_id, properties)
+            .map(|_| ())
+    }
+
+    fn finish(&mut self) -> GraphProxyResult<()> {
+        Ok(())
+    }
+
+    fn merge_vertex(
+        &mut self, label: LabelId, properties: DynDetails, on_create: DynDetails, on_match: DynDetails,
+    ) -> GraphProxyResult<(Vertex, bool)> {
+        self.delta
+            .merge_vertex(label, properties, on_create, on_match)
+    }
+
+    fn merge_edge(
+        &mut self, label: LabelId, src_id: ID, dst_id: ID, properties: DynDetails, on_create: DynDetails,
+        on_match: DynDetails,
+    ) -> GraphProxyResult<(Edge, bool)> {
+        self.delta
+            .merge_edge(label, src_id, dst_id, properties, on_create, on_match)
+    }
+
+    fn mutate(&mut self, mutations: Vec<Mutation>) -> GraphProxyResult<Vec<GraphProxyResult<Mutated>>> {
+        let results = mutations
+            .into_iter()
+            .map(|mutation| match mutation {
+                Mutation::AddVertex(label, properties) => self
+                    .delta
+                    .add_vertex(label, properties)
+                    .map(Mutated::Vertex),
+                Mutation::AddEdge(label, src_id, dst_id, properties) => self
+                    .delta
+                    .add_edge(label, src_id, dst_id, properties)
+                    .map(Mutated::Edge),
+                mutation => Err(GraphProxyError::unsupported_error(&format!(
+                    "{:?} on the immutable fragments of vineyard",
+                    mutation
+                ))),
+            })
+            .collect();
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dyn_type::object;
+
+    use super::*;
+    use crate::apis::test_graph::TestGraph;
+
+    const PERSON: LabelId = 0;
+    const SOFTWARE: LabelId = 1;
+    const KNOWS: LabelId = 0;
+    const NAME: i32 = 0;
+
+    /// A single worker of a single server.
+    struct TestClusterInfo;
+
+    impl ClusterInfo for TestClusterInfo {
+        fn get_server_num(&self) -> GraphProxyResult<u32> {
+            Ok(1)
+        }
+
+        fn get_server_index(&self) -> GraphProxyResult<u32> {
+            Ok(0)
+        }
+
+        fn get_local_worker_num(&self) -> GraphProxyResult<u32> {
+            Ok(1)
+        }
+
+        fn get_worker_index(&self) -> GraphProxyResult<u32> {
+            Ok(0)
+        }
+    }
+
+    fn name(name: &str) -> DynDetails {
+        let mut props = HashMap::new();
+        props.insert(NameOrId::Id(NAME), object!(name));
+        DynDetails::new(props)
+    }
+
+    // the schema of person and software, keyed by the name
+    fn test_schema() -> schema_pb::Schema {
+        let entity = |id: LabelId, name: &str| schema_pb::EntityMeta {
+            label: Some(schema_pb::LabelMeta { id, name: name.to_string() }),
+            columns: vec![schema_pb::ColumnMeta {
+                key: Some(schema_pb::LabelMeta { id: NAME, name: "name".to_string() }),
+                data_type: 0,
+                is_primary_key: true,
+            }],
+        };
+        schema_pb::Schema {
+            entities: vec![entity(PERSON, "person"), entity(SOFTWARE, "software")],
+            ..schema_pb::Schema::default()
+        }
+    }
+
+    // the graph of 2 partitions, where the server holds the given ones
+    fn test_graph(partitions: Vec<PartitionId>) -> (VineyardDeltaGraph, VineyardDeltaWriter) {
+        let parser = VineyardIdParser::new(2, 2);
+        let (v1, v2) = (parser.generate_id(0, PERSON, 0), parser.generate_id(0, PERSON, 1));
+        // the fragments of person 1 and 2 in partition 0, knows 10 from 1 to 2
+        let vertices = vec![
+            Vertex::new(v1, Some(PERSON), name("marko")),
+            Vertex::new(v2, Some(PERSON), name("vadas")),
+        ];
+        let indexed = vertices.clone();
+        let index: FragmentIndex = Arc::new(move |label: LabelId, pk: &PKV| {
+            let value = pk.iter().next().map(|(_, value)| value.clone());
+            Ok(indexed
+                .iter()
+                .find(|v| {
+                    v.label() == Some(label)
+                        && v.get_details()
+                            .get_property(&NameOrId::Id(NAME))
+                            .and_then(|p| p.try_to_owned())
+                            == value
+                })
+                .map(|v| v.id()))
+        });
+        let delta = Arc::new(VineyardDelta::new(&test_schema(), 2, partitions, index).unwrap());
+        let fragments =
+            TestGraph::new(vertices, vec![Edge::new(10, Some(KNOWS), v1, v2, DynDetails::Empty)]);
+        let graph = VineyardDeltaGraph::new(Arc::new(fragments), delta.clone(), Arc::new(TestClusterInfo));
+        (graph, VineyardDeltaWriter::new(delta))
+    }
+
+    fn ids<I: Iterator<Item = E>, E: GraphElement>(iter: I) -> Vec<ID> {
+        iter.map(|e| e.id()).collect()
+    }
+
+    #[test]
+    fn vineyard_id_parser_test() {
+        // 4 partitions of 2 bits, and 3 labels of 2 bits
+        let parser = VineyardIdParser::new(4, 3);
+        let id = parser.generate_id(2, 1, 5);
+        assert_eq!(id as u64, (2_u64 << 62) | (1_u64 << 60) | 5);
+        assert_eq!(parser.get_partition(id), 2);
+        assert_eq!(parser.get_label(id), 1);
+        assert_eq!(parser.max_offset(), (1_u64 << 60) - 1);
+        // a single partition or label is of a single bit
+        let parser = VineyardIdParser::new(1, 1);
+        assert_eq!(parser.max_offset(), (1_u64 << 62) - 1);
+    }
+
+    #[test]
+    fn delta_write_through_test() {
+        let (graph, mut writer) = test_graph(vec![0, 1]);
+        let parser = *graph.delta.get_parser();
+        let v1 = parser.generate_id(0, PERSON, 0);
+        let results = writer
+            .mutate(vec![
+                Mutation::AddVertex(PERSON, name("josh")),
+                Mutation::AddVertex(SOFTWARE, name("lop")),
+                Mutation::DropVertex(Vertex::new(v1, Some(PERSON), DynDetails::Empty)),
+            ])
+            .unwrap();
+        let (josh, lop) = match (&results[0], &results[1]) {
+            (Ok(Mutated::Vertex(josh)), Ok(Mutated::Vertex(lop))) => (josh.id(), lop.id()),
+            _ => panic!("unexpected results {:?}", results),
+        };
+        // the fragments are immutable
+        assert!(results[2].is_err());
+        // the vertices are assigned to the partitions of the primary keys, with the offsets from the highest
+        let partition_of = |name: &str| {
+            graph
+                .delta
+                .get_pk_partition(PERSON, &[object!(name)])
+                .unwrap()
+        };
+        assert_eq!(josh, parser.generate_id(partition_of("josh"), PERSON, parser.max_offset()));
+        assert_eq!(lop, parser.generate_id(partition_of("lop"), SOFTWARE, parser.max_offset()));
+        // the primary keys are unique in both the delta and the fragments
+        let results = writer
+            .mutate(vec![
+                Mutation::AddVertex(PERSON, name("josh")),
+                Mutation::AddVertex(PERSON, name("marko")),
+            ])
+            .unwrap();
+        assert!(results[0].is_err() && results[1].is_err());
+
+        // the edges from a vertex of the fragments, and between the vertices of the delta
+        let results = writer
+            .mutate(vec![Mutation::AddEdge(KNOWS, v1, josh, DynDetails::Empty)])
+            .unwrap();
+        // the offsets of the edges are allocated apart from those of the vertices
+        match &results[0] {
+            Ok(Mutated::Edge(edge)) => {
+                assert_eq!(edge.id(), parser.generate_id(0, KNOWS, parser.max_offset()))
+            }
+            _ => panic!("unexpected results {:?}", results),
+        }
+        writer
+            .add_edge(
+                KNOWS,
+                PERSON,
+                vec![(NameOrId::Id(NAME), object!("josh"))].into(),
+                SOFTWARE,
+                (NameOrId::Id(NAME), object!("lop")).into(),
+                DynDetails::Empty,
+            )
+            .unwrap();
+
+        let params = QueryParams::default();
+        let v2 = parser.generate_id(0, PERSON, 1);
+        assert_eq!(ids(graph.scan_vertex(&params).unwrap()), vec![v1, v2, josh, lop]);
+        assert_eq!(graph.count_vertex(&params).unwrap(), 4);
+        let params = QueryParams { labels: vec![SOFTWARE], ..QueryParams::default() };
+        assert_eq!(ids(graph.scan_vertex(&params).unwrap()), vec![lop]);
+        let params = QueryParams::default();
+        assert_eq!(ids(graph.get_vertex(&[lop, v2], &params).unwrap()), vec![v2, lop]);
+        let pk = (NameOrId::Id(NAME), object!("lop")).into();
+        let vertex = graph
+            .index_scan_vertex(SOFTWARE, &pk, &params)
+            .unwrap();
+        assert_eq!(vertex.map(|v| v.id()), Some(lop));
+
+        // the adjacent vertices of the fragments and the delta
+        let stmt = graph
+            .prepare_explore_vertex(Direction::Out, &params)
+            .unwrap();
+        assert_eq!(ids(stmt.exec(v1).unwrap()), vec![v2, josh]);
+        assert_eq!(ids(stmt.exec(josh).unwrap()), vec![lop]);
+        assert_eq!(ids(stmt.exec(v2).unwrap()), Vec::<ID>::new());
+        let stmt = graph
+            .prepare_explore_vertex(Direction::In, &params)
+            .unwrap();
+        assert_eq!(ids(stmt.exec(josh).unwrap()), vec![v1]);
+        assert_eq!(ids(stmt.exec(v2).unwrap()), vec![v1]);
+        let stmt = graph
+            .prepare_explore_edge(Direction::In, &params)
+            .unwrap();
+        let edges: Vec<Edge> = stmt.exec(lop).unwrap().collect();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].get_other_id(), josh);
+        assert_eq!(edges[0].get_other_label(), Some(&PERSON));
+        assert_eq!(graph.count_edge(&params).unwrap(), 3);
+    }
+
+    #[test]
+    fn delta_cross_server_test() {
+        // the server of partition 0 only
+        let (graph, mut writer) = test_graph(vec![0]);
+        let parser = *graph.delta.get_parser();
+        let v1 = parser.generate_id(0, PERSON, 0);
+        let (all, _) = test_graph(vec![0, 1]);
+        let names: Vec<String> = (0..16)
+            .map(|i| format!("person{}", i))
+            .collect();
+        let partition_of = |name: &String| {
+            all.delta
+                .get_pk_partition(PERSON, &[object!(name.as_str())])
+                .unwrap()
+        };
+        let local = names
+            .iter()
+            .find(|n| partition_of(n) == 0)
+            .unwrap();
+        let remote = names
+            .iter()
+            .find(|n| partition_of(n) == 1)
+            .unwrap();
+        // the vertices are only added on the server of the partitions of their primary keys
+        let results = writer
+            .mutate(vec![
+                Mutation::AddVertex(PERSON, name(local)),
+                Mutation::AddVertex(PERSON, name(remote)),
+            ])
+            .unwrap();
+        let local_id = match &results[0] {
+            Ok(Mutated::Vertex(vertex)) => vertex.id(),
+            _ => panic!("unexpected results {:?}", results),
+        };
+        assert!(results[1].is_err());
+        // the edges are only added between the vertices of the server, of either the delta or the fragments
+        writer
+            .add_edge(
+                KNOWS,
+                PERSON,
+                (NameOrId::Id(NAME), object!("marko")).into(),
+                PERSON,
+                (NameOrId::Id(NAME), object!(local.as_str())).into(),
+                DynDetails::Empty,
+            )
+            .unwrap();
+        let remote_id = parser.generate_id(1, PERSON, 0);
+        let results = writer
+            .mutate(vec![
+                Mutation::AddEdge(KNOWS, v1, remote_id, DynDetails::Empty),
+                Mutation::AddEdge(KNOWS, remote_id, local_id, DynDetails::Empty),
+            ])
+            .unwrap();
+        assert!(results[0].is_err() && results[1].is_err());
+        let params = QueryParams::default();
+        let stmt = graph
+            .prepare_explore_vertex(Direction::In, &params)
+            .unwrap();
+        assert_eq!(ids(stmt.exec(local_id).unwrap()), vec![v1]);
+        assert_eq!(graph.count_edge(&params).unwrap(), 2);
+    }
+
+    #[test]
+    fn delta_merge_test() {
+        let (graph, mut writer) = test_graph(vec![0, 1]);
+        let parser = *graph.delta.get_parser();
+        let v1 = parser.generate_id(0, PERSON, 0);
+        let age = |age: i32| {
+            let mut props = HashMap::new();
+            props.insert(NameOrId::Id(1), object!(age));
+            DynDetails::new(props)
+        };
+        let age_of = |details: &DynDetails| {
+            details
+                .get_property(&NameOrId::Id(1))
+                .and_then(|v| v.try_to_owned())
+        };
+
+        // created with the properties of `on_create`, and matched by the primary key afterwards
+        let (josh, created) = writer
+            .merge_vertex(PERSON, name("josh"), age(32), age(33))
+            .unwrap();
+        assert!(created);
+        assert_eq!(age_of(josh.get_details()), Some(object!(32)));
+        let (matched, created) = writer
+            .merge_vertex(PERSON, name("josh"), age(32), age(33))
+            .unwrap();
+        assert!(!created);
+        assert_eq!(matched.id(), josh.id());
+        assert_eq!(age_of(matched.get_details()), Some(object!(33)));
+        let params = QueryParams::default();
+        let vertex = graph
+            .get_vertex(&[josh.id()], &params)
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(age_of(vertex.get_details()), Some(object!(33)));
+
+        // the edges are matched by the label, the endpoints and the properties
+        let (edge, created) = writer
+            .merge_edge(KNOWS, v1, josh.id(), age(1), DynDetails::Empty, DynDetails::Empty)
+            .unwrap();
+        assert!(created);
+        let (matched, created) = writer
+            .merge_edge(KNOWS, v1, josh.id(), age(1), DynDetails::Empty, age(2))
+            .unwrap();
+        assert!(!created);
+        assert_eq!(matched.id(), edge.id());
+        assert_eq!(age_of(matched.get_details()), Some(object!(2)));
+        let (_, created) = writer
+            .merge_edge(KNOWS, v1, josh.id(), age(3), DynDetails::Empty, DynDetails::Empty)
+            .unwrap();
+        assert!(created);
+        assert_eq!(graph.count_edge(&params).unwrap(), 3);
+    }
+}
@@ -13,6 +13,8 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+mod delta;
 mod write_graph;
 
+pub use delta::{VineyardDelta, VineyardDeltaGraph, VineyardDeltaWriter, VineyardIdParser};
 pub use write_graph::VineyardGraphWriter;
@@ -0,0 +1,187 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The read consistency of a job, from the `read-consistency` metadata of the request, which trades
+//! the freshness of the data read for the latency. The stores supporting it read the data of the job
+//! as follows, while the others ignore it:
+//! * `latest`: the latest committed snapshot, from the leaders of the partitions only;
+//! * `bounded-staleness=<ms>`: from the followers of the partitions as well, if they are no staler
+//!   than the bound, whose latest snapshots may be behind the committed one;
+//! * `snapshot=<id>`: the snapshot of the id, which is rejected if it's beyond the latest committed
+//!   snapshot, as it may be partially written.
+//!
+//! Without it, the jobs read as the store is configured, e.g., with the follower reads enabled for all.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use crate::{GraphProxyError, GraphProxyResult};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadConsistency {
+    /// Read the latest committed snapshot
+    LatestCommitted,
+    /// Read from the replicas no staler than the milliseconds
+    BoundedStaleness(u64),
+    /// Read the snapshot of the id
+    Snapshot(i64),
+}
+
+impl FromStr for ReadConsistency {
+    type Err = GraphProxyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || GraphProxyError::unsupported_error(&format!("read consistency `{}`", s));
+        let (level, arg) = match s.split_once('=') {
+            Some((level, arg)) => (level.trim(), Some(arg.trim())),
+            None => (s.trim(), None),
+        };
+        match (level, arg) {
+            ("latest", None) => Ok(ReadConsistency::LatestCommitted),
+            ("bounded-staleness", Some(ms)) => ms
+                .parse()
+                .map(ReadConsistency::BoundedStaleness)
+                .map_err(|_| invalid()),
+            ("snapshot", Some(id)) => match id.parse() {
+                Ok(id) if id >= 0 => Ok(ReadConsistency::Snapshot(id)),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+lazy_static! {
+    /// The read consistency of the jobs, with the number of local workers binding it.
+    static ref JOB_CONSISTENCY: RwLock<HashMap<u64, (ReadConsistency, usize)>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Read the data of the job with the consistency, until all the returned bindings of the job are
+/// dropped, e.g., with the workers of the job as their resources.
+pub fn bind_consistency(job_id: u64, consistency: ReadConsistency) -> ConsistencyBinding {
+    let mut job_consistency = JOB_CONSISTENCY
+        .write()
+        .unwrap_or_else(|e| e.into_inner());
+    let binding = job_consistency
+        .entry(job_id)
+        .or_insert((consistency, 0));
+    binding.0 = consistency;
+    binding.1 += 1;
+    ConsistencyBinding { job_id }
+}
+
+/// The read consistency of the job of the current worker, if any.
+pub fn get_current_consistency() -> Option<ReadConsistency> {
+    let job_consistency = JOB_CONSISTENCY
+        .read()
+        .unwrap_or_else(|e| e.into_inner());
+    if job_consistency.is_empty() {
+        return None;
+    }
+    pegasus::get_current_worker_checked()
+        .and_then(|worker| job_consistency.get(&worker.job_id))
+        .map(|(consistency, _)| *consistency)
+}
+
+/// The snapshot read by the job of the current worker, i.e. the snapshot of its `snapshot=<id>`
+/// consistency, or the latest committed one given by the compiler otherwise, if any.
+pub fn get_current_snapshot(latest: Option<i64>) -> GraphProxyResult<Option<i64>> {
+    match get_current_consistency() {
+        Some(ReadConsistency::Snapshot(si)) => check_snapshot(si, latest).map(Some),
+        _ => Ok(latest),
+    }
+}
+
+/// The snapshot of the id must have been committed, i.e. no later than the latest committed one.
+fn check_snapshot(si: i64, latest: Option<i64>) -> GraphProxyResult<i64> {
+    match latest {
+        Some(latest) if si > latest => Err(GraphProxyError::uncommitted_snapshot(si, latest)),
+        _ => Ok(si),
+    }
+}
+
+pub struct ConsistencyBinding {
+    job_id: u64,
+}
+
+impl Drop for ConsistencyBinding {
+    fn drop(&mut self) {
+        let mut job_consistency = JOB_CONSISTENCY
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(binding) = job_consistency.get_mut(&self.job_id) {
+            binding.1 -= 1;
+            if binding.1 == 0 {
+                job_consistency.remove(&self.job_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pegasus::errors::ErrorCode;
+
+    use super::*;
+
+    #[test]
+    fn parse_consistency_test() {
+        assert_eq!("latest".parse::<ReadConsistency>().unwrap(), ReadConsistency::LatestCommitted);
+        assert_eq!(
+            "bounded-staleness=500"
+                .parse::<ReadConsistency>()
+                .unwrap(),
+            ReadConsistency::BoundedStaleness(500)
+        );
+        assert_eq!(
+            "snapshot=42"
+                .parse::<ReadConsistency>()
+                .unwrap(),
+            ReadConsistency::Snapshot(42)
+        );
+        assert!("latest=1".parse::<ReadConsistency>().is_err());
+        assert!("bounded-staleness"
+            .parse::<ReadConsistency>()
+            .is_err());
+        assert!("snapshot=-1"
+            .parse::<ReadConsistency>()
+            .is_err());
+        assert!("eventual".parse::<ReadConsistency>().is_err());
+    }
+
+    #[test]
+    fn check_snapshot_test() {
+        assert_eq!(check_snapshot(42, Some(42)).unwrap(), 42);
+        assert_eq!(check_snapshot(41, Some(42)).unwrap(), 41);
+        assert_eq!(check_snapshot(43, None).unwrap(), 43);
+        let err = check_snapshot(43, Some(42)).unwrap_err();
+        assert!(matches!(err, GraphProxyError::UncommittedSnapshot { snapshot: 43, latest: 42 }));
+        assert_eq!(err.code(), ErrorCode::user(4007));
+        // without the consistency of a job, the latest committed one is read
+        assert_eq!(get_current_snapshot(Some(42)).unwrap(), Some(42));
+    }
+
+    #[test]
+    fn bind_consistency_test() {
+        let first = bind_consistency(1, ReadConsistency::Snapshot(1));
+        let second = bind_consistency(1, ReadConsistency::Snapshot(1));
+        drop(first);
+        assert!(JOB_CONSISTENCY.read().unwrap().contains_key(&1));
+        drop(second);
+        assert!(!JOB_CONSISTENCY.read().unwrap().contains_key(&1));
+    }
+}
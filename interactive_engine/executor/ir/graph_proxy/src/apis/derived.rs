@@ -0,0 +1,640 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Derived properties: virtual properties of the vertices and edges of a label, each of which is
+//! defined by an expression over the stored properties of the element, e.g.,
+//! `age = 2024 - @.birth_year`, and is computed when it is read. Queries refer to them as to the
+//! stored properties, in projections as well as in predicates.
+//!
+//! The derived properties registered in the `DerivedSchema` are read through `get_graph()`, which
+//! wraps the details of the elements read from the storage, and:
+//!   1. requests the stored properties the required derived properties depend on instead of them;
+//!   2. evaluates the predicates of the queries after the properties are derived, rather than
+//!      pushing them down into the storage, as they may refer to the derived properties.
+//! If the planner maps the properties to ids, the derived properties are registered by the ids
+//! of them in the schema of the planner.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use ahash::HashMap as AHashMap;
+use dyn_type::Object;
+use ir_common::error::ParsePbResult;
+use ir_common::generated::common as common_pb;
+use ir_common::{LabelId, NameOrId};
+use pegasus_common::downcast::*;
+use pegasus_common::impl_as_any;
+
+use crate::apis::graph::PKV;
+use crate::apis::read_graph::FilterMapStatement;
+use crate::apis::{
+    Details, Direction, DynDetails, Edge, EdgeRef, GraphElement, PropertyValue, QueryParams, ReadGraph,
+    Statement, Vertex, ID,
+};
+use crate::limit_n;
+use crate::utils::expr::eval::{Evaluate, Evaluator};
+use crate::utils::expr::eval_pred::{EvalPred, PEvaluator};
+use crate::GraphProxyResult;
+
+static NEXT_PROPERTY_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The evaluators of the derived properties, which are built once per thread, as an evaluator
+    /// can't be shared among threads.
+    static EVALUATORS: RefCell<HashMap<u64, Option<Rc<Evaluator>>>> = RefCell::new(HashMap::new());
+}
+
+#[derive(Clone, Debug)]
+pub struct DerivedProperty {
+    id: u64,
+    name: NameOrId,
+    expr: common_pb::Expression,
+    /// The stored properties the expression refers to, `None` if they are not known
+    dependencies: Option<Vec<NameOrId>>,
+}
+
+impl DerivedProperty {
+    pub fn new(name: NameOrId, expr: common_pb::Expression) -> ParsePbResult<Self> {
+        // validate the expression
+        Evaluator::try_from(expr.clone())?;
+        let dependencies = dependencies_of(&expr)?;
+        let id = NEXT_PROPERTY_ID.fetch_add(1, Ordering::Relaxed);
+        Ok(DerivedProperty { id, name, expr, dependencies })
+    }
+
+    /// Evaluate the property over the stored properties, `None` if it can't be evaluated, e.g.,
+    /// when the stored properties are absent.
+    fn eval(&self, details: &DynDetails) -> Option<Object> {
+        let evaluator = EVALUATORS.with(|evaluators| {
+            evaluators
+                .borrow_mut()
+                .entry(self.id)
+                .or_insert_with(|| {
+                    Evaluator::try_from(self.expr.clone())
+                        .ok()
+                        .map(Rc::new)
+                })
+                .clone()
+        })?;
+        let element = Vertex::new(0, None, details.clone());
+        match evaluator.eval::<Vertex, Vertex>(Some(&element)) {
+            Ok(Object::None) | Err(_) => None,
+            Ok(value) => Some(value),
+        }
+    }
+}
+
+/// The keys of the properties of the head the expression refers to, `None` if the expression
+/// refers to all the properties, or to the properties in the items not analyzed, e.g., `Case`.
+fn dependencies_of(expr: &common_pb::Expression) -> ParsePbResult<Option<Vec<NameOrId>>> {
+    use common_pb::expr_opr::Item;
+    let mut keys: Vec<NameOrId> = vec![];
+    let mut vars = vec![];
+    for opr in expr.operators.iter() {
+        match opr.item.as_ref() {
+            Some(Item::Var(var)) => vars.push(var),
+            Some(Item::Vars(vs)) | Some(Item::VarMap(vs)) => vars.extend(vs.keys.iter()),
+            Some(Item::Map(kvs)) => vars.extend(
+                kvs.key_vals
+                    .iter()
+                    .filter_map(|kv| kv.value.as_ref()),
+            ),
+            Some(Item::Logical(_)) | Some(Item::Arith(_)) => {}
+            Some(Item::Const(_)) | Some(Item::Brace(_)) => {}
+            _ => return Ok(None),
+        }
+    }
+    for var in vars {
+        match var
+            .property
+            .as_ref()
+            .and_then(|p| p.item.as_ref())
+        {
+            Some(common_pb::property::Item::Key(key)) => {
+                let key: NameOrId = key.clone().try_into()?;
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+            Some(common_pb::property::Item::All(_)) => return Ok(None),
+            _ => {}
+        }
+    }
+    Ok(Some(keys))
+}
+
+/// The derived properties of the vertex and edge labels.
+#[derive(Debug, Default)]
+pub struct DerivedSchema {
+    vertices: HashMap<LabelId, Arc<Vec<DerivedProperty>>>,
+    edges: HashMap<LabelId, Arc<Vec<DerivedProperty>>>,
+}
+
+impl DerivedSchema {
+    pub fn new() -> Self {
+        DerivedSchema::default()
+    }
+
+    /// Derive the property of the vertices of the label, replacing the one of the same name.
+    pub fn add_vertex_property(
+        &mut self, label: LabelId, name: NameOrId, expr: common_pb::Expression,
+    ) -> ParsePbResult<()> {
+        add_property(&mut self.vertices, label, DerivedProperty::new(name, expr)?);
+        Ok(())
+    }
+
+    /// Derive the property of the edges of the label, replacing the one of the same name.
+    pub fn add_edge_property(
+        &mut self, label: LabelId, name: NameOrId, expr: common_pb::Expression,
+    ) -> ParsePbResult<()> {
+        add_property(&mut self.edges, label, DerivedProperty::new(name, expr)?);
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty() && self.edges.is_empty()
+    }
+}
+
+fn add_property(
+    properties: &mut HashMap<LabelId, Arc<Vec<DerivedProperty>>>, label: LabelId, property: DerivedProperty,
+) {
+    let properties = Arc::make_mut(properties.entry(label).or_default());
+    properties.retain(|p| p.name != property.name);
+    properties.push(property);
+}
+
+lazy_static! {
+    static ref DERIVED_SCHEMA: RwLock<Option<Arc<DerivedSchema>>> = RwLock::new(None);
+}
+
+/// Register the derived properties, replacing all the ones registered before.
+pub fn register_derived_schema(schema: DerivedSchema) {
+    let schema = if schema.is_empty() { None } else { Some(Arc::new(schema)) };
+    *DERIVED_SCHEMA
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = schema;
+}
+
+pub(crate) fn get_derived_schema() -> Option<Arc<DerivedSchema>> {
+    DERIVED_SCHEMA
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// DerivedDetails wraps the details of a graph element, and computes its derived properties when
+/// they are read.
+#[derive(Debug)]
+struct DerivedDetails {
+    inner: DynDetails,
+    properties: Arc<Vec<DerivedProperty>>,
+    /// The properties required by the query, which are those to be output
+    columns: Option<Arc<Vec<NameOrId>>>,
+}
+
+impl_as_any!(DerivedDetails);
+
+impl Details for DerivedDetails {
+    fn get_property(&self, key: &NameOrId) -> Option<PropertyValue> {
+        if let Some(property) = self.properties.iter().find(|p| &p.name == key) {
+            property
+                .eval(&self.inner)
+                .map(PropertyValue::Owned)
+        } else {
+            self.inner.get_property(key)
+        }
+    }
+
+    fn get_all_properties(&self) -> Option<AHashMap<NameOrId, Object>> {
+        self.inner
+            .get_all_properties()
+            .map(|mut props| {
+                for property in self.properties.iter() {
+                    if let Some(value) = property.eval(&self.inner) {
+                        props.insert(property.name.clone(), value);
+                    }
+                }
+                props
+            })
+    }
+
+    fn get_property_keys(&self) -> Option<Vec<NameOrId>> {
+        if let Some(columns) = self.columns.as_ref().filter(|c| !c.is_empty()) {
+            return Some(columns.to_vec());
+        }
+        let keys = match &self.inner {
+            // `get_property_keys()` is not supported by default details, so take its keys directly
+            DynDetails::Default(props) => Some(props.keys().cloned().collect()),
+            _ => self.inner.get_property_keys(),
+        };
+        // an empty vector stands for all properties, which are derived in `get_all_properties()`
+        keys.map(|mut keys: Vec<NameOrId>| {
+            if !keys.is_empty() {
+                keys.extend(self.properties.iter().map(|p| p.name.clone()));
+            }
+            keys
+        })
+    }
+}
+
+/// Derive the properties of the elements read from the inner graph, and then filter them by the
+/// predicate of the query, if it is not pushed down.
+#[derive(Clone)]
+struct Derive {
+    properties: HashMap<LabelId, Arc<Vec<DerivedProperty>>>,
+    columns: Option<Arc<Vec<NameOrId>>>,
+    filter: Option<Arc<PEvaluator>>,
+}
+
+impl Derive {
+    fn derive(&self, label: Option<LabelId>, details: &mut DynDetails) {
+        if let DynDetails::Empty = details {
+            return;
+        }
+        if let Some(properties) = label.and_then(|label| self.properties.get(&label)) {
+            let inner = std::mem::take(details);
+            *details = DynDetails::lazy(DerivedDetails {
+                inner,
+                properties: properties.clone(),
+                columns: self.columns.clone(),
+            });
+        }
+    }
+
+    fn vertex(&self, mut vertex: Vertex) -> Option<Vertex> {
+        self.derive(vertex.label(), vertex.get_details_mut());
+        if let Some(filter) = self.filter.as_ref() {
+            if !filter.eval_bool(Some(&vertex)).unwrap_or(false) {
+                return None;
+            }
+        }
+        Some(vertex)
+    }
+
+    fn edge(&self, mut edge: Edge) -> Option<Edge> {
+        self.derive(edge.label(), edge.get_details_mut());
+        if let Some(filter) = self.filter.as_ref() {
+            if !filter.eval_bool(Some(&edge)).unwrap_or(false) {
+                return None;
+            }
+        }
+        Some(edge)
+    }
+}
+
+/// The graph with the derived properties.
+pub struct DerivedGraph {
+    inner: Arc<dyn ReadGraph>,
+    schema: Arc<DerivedSchema>,
+}
+
+impl DerivedGraph {
+    pub fn new(inner: Arc<dyn ReadGraph>, schema: Arc<DerivedSchema>) -> Self {
+        DerivedGraph { inner, schema }
+    }
+
+    fn derive_vertices(&self, params: &QueryParams) -> (QueryParams, Derive) {
+        split_params(params, &self.schema.vertices)
+    }
+
+    fn derive_edges(&self, params: &QueryParams) -> (QueryParams, Derive) {
+        split_params(params, &self.schema.edges)
+    }
+}
+
+/// Split the params into those to read the elements from the inner graph, and how to derive the
+/// properties of the elements read.
+fn split_params(
+    params: &QueryParams, properties: &HashMap<LabelId, Arc<Vec<DerivedProperty>>>,
+) -> (QueryParams, Derive) {
+    let mut inner = params.clone();
+    let mut derive = Derive {
+        properties: properties.clone(),
+        columns: params.columns.clone().map(Arc::new),
+        filter: None,
+    };
+    if properties.is_empty() {
+        return (inner, derive);
+    }
+    if params.filter.is_some() {
+        // the predicate may refer to the derived properties, so the limit is applied after it
+        derive.filter = inner.filter.take();
+        inner.limit = None;
+        inner.columns = Some(vec![]);
+    } else {
+        inner.columns = required_columns(params.columns.as_ref(), properties);
+    }
+    (inner, derive)
+}
+
+/// Replace the derived properties in the columns required by the stored ones they depend on.
+fn required_columns(
+    columns: Option<&Vec<NameOrId>>, properties: &HashMap<LabelId, Arc<Vec<DerivedProperty>>>,
+) -> Option<Vec<NameOrId>> {
+    match columns {
+        Some(columns) if !columns.is_empty() => {
+            let mut required = vec![];
+            for column in columns {
+                let mut derived = properties
+                    .values()
+                    .flat_map(|ps| ps.iter())
+                    .filter(|p| &p.name == column)
+                    .peekable();
+                if derived.peek().is_none() {
+                    if !required.contains(column) {
+                        required.push(column.clone());
+                    }
+                    continue;
+                }
+                for property in derived {
+                    match property.dependencies.as_ref() {
+                        Some(dependencies) => {
+                            for key in dependencies {
+                                if !required.contains(key) {
+                                    required.push(key.clone());
+                                }
+                            }
+                        }
+                        None => return Some(vec![]),
+                    }
+                }
+            }
+            // the derived properties of constant expressions still require the details
+            Some(required)
+        }
+        _ => columns.cloned(),
+    }
+}
+
+/// Get the adjacent vertices of the inner graph, and fetch them from the graph with the params.
+struct FetchVertexStatement {
+    vertices: Box<dyn Statement<ID, Vertex>>,
+    graph: DerivedGraph,
+    params: QueryParams,
+}
+
+impl Statement<ID, Vertex> for FetchVertexStatement {
+    fn exec(&self, next: ID) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let ids: Vec<ID> = self
+            .vertices
+            .exec(next)?
+            .map(|v| v.id())
+            .collect();
+        self.graph.get_vertex(&ids, &self.params)
+    }
+}
+
+impl ReadGraph for DerivedGraph {
+    fn scan_vertex(
+        &self, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let (inner_params, derive) = self.derive_vertices(params);
+        let iter = self
+            .inner
+            .scan_vertex(&inner_params)?
+            .filter_map(move |v| derive.vertex(v));
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn index_scan_vertex(
+        &self, label: LabelId, primary_key: &PKV, params: &QueryParams,
+    ) -> GraphProxyResult<Option<Vertex>> {
+        let (inner_params, derive) = self.derive_vertices(params);
+        let vertex = self
+            .inner
+            .index_scan_vertex(label, primary_key, &inner_params)?;
+        Ok(vertex.and_then(|v| derive.vertex(v)))
+    }
+
+    fn scan_edge(&self, params: &QueryParams) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        let (inner_params, derive) = self.derive_edges(params);
+        let iter = self
+            .inner
+            .scan_edge(&inner_params)?
+            .filter_map(move |e| derive.edge(e));
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn get_vertex(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let (inner_params, derive) = self.derive_vertices(params);
+        let iter = self
+            .inner
+            .get_vertex(ids, &inner_params)?
+            .filter_map(move |v| derive.vertex(v));
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn get_edge(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        let (inner_params, derive) = self.derive_edges(params);
+        let iter = self
+            .inner
+            .get_edge(ids, &inner_params)?
+            .filter_map(move |e| derive.edge(e));
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn get_edge_by_id(&self, edge_ref: &EdgeRef, params: &QueryParams) -> GraphProxyResult<Option<Edge>> {
+        let (inner_params, derive) = self.derive_edges(params);
+        let edge = self
+            .inner
+            .get_edge_by_id(edge_ref, &inner_params)?;
+        Ok(edge.and_then(|e| derive.edge(e)))
+    }
+
+    fn prepare_explore_vertex(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Vertex>>> {
+        if params.filter.is_some() && !self.schema.vertices.is_empty() {
+            // the adjacent vertices are fetched with their properties to be filtered, where the
+            // labels of the params are those of the edges
+            let edge_params = QueryParams { labels: params.labels.clone(), ..QueryParams::default() };
+            let vertices = self
+                .inner
+                .prepare_explore_vertex(direction, &edge_params)?;
+            let mut vertex_params = params.clone();
+            vertex_params.labels = vec![];
+            let graph = DerivedGraph { inner: self.inner.clone(), schema: self.schema.clone() };
+            Ok(Box::new(FetchVertexStatement { vertices, graph, params: vertex_params }))
+        } else {
+            let (inner_params, derive) = self.derive_vertices(params);
+            let inner = self
+                .inner
+                .prepare_explore_vertex(direction, &inner_params)?;
+            Ok(Box::new(FilterMapStatement {
+                inner,
+                func: Arc::new(move |v| derive.vertex(v)),
+                limit: params.limit,
+            }))
+        }
+    }
+
+    fn prepare_explore_edge(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Edge>>> {
+        let (inner_params, derive) = self.derive_edges(params);
+        let inner = self
+            .inner
+            .prepare_explore_edge(direction, &inner_params)?;
+        Ok(Box::new(FilterMapStatement {
+            inner,
+            func: Arc::new(move |e| derive.edge(e)),
+            limit: params.limit,
+        }))
+    }
+
+    fn count_vertex(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        if params.filter.is_some() && !self.schema.vertices.is_empty() {
+            Ok(self.scan_vertex(params)?.count() as u64)
+        } else {
+            self.inner.count_vertex(params)
+        }
+    }
+
+    fn count_edge(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        if params.filter.is_some() && !self.schema.edges.is_empty() {
+            Ok(self.scan_edge(params)?.count() as u64)
+        } else {
+            self.inner.count_edge(params)
+        }
+    }
+
+    fn get_primary_key(&self, id: &ID) -> GraphProxyResult<Option<PKV>> {
+        self.inner.get_primary_key(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::HashMapExt;
+    use dyn_type::object;
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+    use crate::apis::test_graph::TestGraph;
+
+    const PERSON: LabelId = 0;
+    const KNOWS: LabelId = 0;
+
+    fn person(id: ID, birth_year: i32) -> Vertex {
+        let mut props = AHashMap::new();
+        props.insert("birth_year".into(), birth_year.into());
+        props.insert("name".into(), format!("p{}", id).into());
+        Vertex::new(id, Some(PERSON), DynDetails::new(props))
+    }
+
+    // age = 2024 - @.birth_year, where person 1 knows person 2 and 3
+    fn derived_graph() -> DerivedGraph {
+        let knows = |id: ID, dst: ID| Edge::new(id, Some(KNOWS), 1, dst, DynDetails::Empty);
+        let graph = TestGraph::new(
+            vec![person(1, 1990), person(2, 2000), person(3, 1980)],
+            vec![knows(12, 2), knows(13, 3)],
+        );
+        let mut schema = DerivedSchema::new();
+        schema
+            .add_vertex_property(
+                PERSON,
+                "age".into(),
+                str_to_expr_pb("2024 - @.birth_year".to_string()).unwrap(),
+            )
+            .unwrap();
+        DerivedGraph::new(Arc::new(graph), Arc::new(schema))
+    }
+
+    fn ages<I: Iterator<Item = Vertex>>(iter: I) -> Vec<Option<Object>> {
+        iter.map(|v| {
+            v.get_property(&"age".into())
+                .and_then(|age| age.try_to_owned())
+        })
+        .collect()
+    }
+
+    #[test]
+    fn derived_property_test() {
+        let graph = derived_graph();
+        // the derived property is computed from the stored one it depends on
+        let mut params = QueryParams::default();
+        params.columns = Some(vec!["age".into()]);
+        let vertices: Vec<Vertex> = graph.scan_vertex(&params).unwrap().collect();
+        assert_eq!(
+            ages(vertices.clone().into_iter()),
+            vec![Some(object!(34)), Some(object!(24)), Some(object!(44))]
+        );
+        assert!(vertices[0]
+            .get_property(&"name".into())
+            .is_none());
+        assert_eq!(vertices[0].get_details().get_property_keys(), Some(vec!["age".into()]));
+
+        // all the properties, including the derived ones
+        let mut params = QueryParams::default();
+        params.columns = Some(vec![]);
+        let vertex = graph
+            .scan_vertex(&params)
+            .unwrap()
+            .next()
+            .unwrap();
+        let props = vertex.get_all_properties().unwrap();
+        assert_eq!(props.get(&"age".into()), Some(&object!(34)));
+        assert_eq!(props.len(), 3);
+    }
+
+    #[test]
+    fn derived_property_filter_test() {
+        let graph = derived_graph();
+        let mut params = QueryParams::default();
+        params.filter = Some(Arc::new(
+            str_to_expr_pb("@.age < 30".to_string())
+                .unwrap()
+                .try_into()
+                .unwrap(),
+        ));
+        let ids: Vec<ID> = graph
+            .scan_vertex(&params)
+            .unwrap()
+            .map(|v| v.id())
+            .collect();
+        assert_eq!(ids, vec![2]);
+        assert_eq!(graph.count_vertex(&params).unwrap(), 1);
+
+        // the adjacent vertices are fetched to be filtered
+        let stmt = graph
+            .prepare_explore_vertex(Direction::Out, &params)
+            .unwrap();
+        let ids: Vec<ID> = stmt.exec(1).unwrap().map(|v| v.id()).collect();
+        assert_eq!(ids, vec![2]);
+    }
+
+    #[test]
+    fn dependencies_test() {
+        let expr = str_to_expr_pb("@.a + @.b * @.a".to_string()).unwrap();
+        assert_eq!(dependencies_of(&expr).unwrap(), Some(vec!["a".into(), "b".into()]));
+        let expr = str_to_expr_pb("@.a + @.b".to_string()).unwrap();
+        let mut properties = HashMap::new();
+        properties.insert(PERSON, Arc::new(vec![DerivedProperty::new("c".into(), expr).unwrap()]));
+        assert_eq!(
+            required_columns(Some(&vec!["c".into(), "a".into(), "d".into()]), &properties),
+            Some(vec!["a".into(), "b".into(), "d".into()])
+        );
+        assert_eq!(required_columns(None, &properties), None);
+    }
+}
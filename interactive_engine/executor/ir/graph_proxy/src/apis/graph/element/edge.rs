@@ -23,7 +23,7 @@ use ahash::HashMap;
 use dyn_type::{BorrowObject, Object};
 use ir_common::error::ParsePbError;
 use ir_common::generated::results as result_pb;
-use ir_common::{LabelId, NameOrId};
+use ir_common::{LabelId, NameOrId, DST_ID_KEY, ID_KEY, SRC_ID_KEY};
 use pegasus_common::codec::{Decode, Encode, ReadExt, WriteExt};
 use pegasus_common::downcast::*;
 use pegasus_common::impl_as_any;
@@ -134,6 +134,39 @@ impl Edge {
     pub fn get_details(&self) -> &DynDetails {
         &self.details
     }
+
+    /// The address to get the edge again by `ReadGraph::get_edge_by_id`, if its label is known.
+    pub fn get_edge_ref(&self) -> Option<EdgeRef> {
+        self.label
+            .map(|label| EdgeRef { label, src_id: self.src_id, dst_id: self.dst_id, id: self.id })
+    }
+}
+
+/// The address of an edge, by its label, source and destination vertices, and its id discriminating
+/// the parallel edges between them, which is stable as long as the edge exists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EdgeRef {
+    pub label: LabelId,
+    pub src_id: ID,
+    pub dst_id: ID,
+    pub id: ID,
+}
+
+impl EdgeRef {
+    /// Parse the edge address of the label from the primary key values of an index scan of edges,
+    /// which are given by the keys `~src_id`, `~dst_id` and `~id`.
+    pub fn from_pkv(label: LabelId, pkv: &[(NameOrId, Object)]) -> Result<Self, ParsePbError> {
+        let get_id = |key: &str| -> Result<ID, ParsePbError> {
+            pkv.iter()
+                .find(|(k, _)| *k == NameOrId::Str(key.to_string()))
+                .ok_or_else(|| ParsePbError::from(format!("`{}` of the edge is missing in {:?}", key, pkv)))
+                .and_then(|(_, v)| {
+                    v.as_i64()
+                        .map_err(|e| ParsePbError::from(format!("invalid `{}` of the edge: {:?}", key, e)))
+                })
+        };
+        Ok(EdgeRef { label, src_id: get_id(SRC_ID_KEY)?, dst_id: get_id(DST_ID_KEY)?, id: get_id(ID_KEY)? })
+    }
 }
 
 impl Encode for Edge {
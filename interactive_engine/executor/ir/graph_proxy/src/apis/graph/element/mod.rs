@@ -15,10 +15,10 @@
 
 use ahash::HashMap;
 use dyn_type::{BorrowObject, Object};
-pub use edge::Edge;
+pub use edge::{Edge, EdgeRef};
 use ir_common::{LabelId, NameOrId};
 pub use path::{GraphPath, VertexOrEdge};
-pub use property::{Details, DynDetails, PropKey, PropertyValue};
+pub use property::{Details, DynDetails, MaskAction, MaskedDetails, PropKey, PropertyValue};
 pub use vertex::Vertex;
 
 use crate::apis::ID;
@@ -89,6 +89,11 @@ pub enum GraphPath {
     EndV((VertexOrEdge, usize)),
     /// Simple path with only end vertex preserved, which may contains both vertices and edges, or only vertices.
     SimpleEndV((VertexOrEdge, Vec<ID>, usize)),
+    /// Trail path, i.e., path without edge duplications, with the ids of the edges visited,
+    /// and the flag of whether the edges are preserved, or only vertices (as for `ResultOpt::AllV`).
+    TrailAllPath((Vec<VertexOrEdge>, Vec<ID>, bool)),
+    /// Trail path with only end vertex preserved, with the ids of the edges visited.
+    TrailEndV((VertexOrEdge, Vec<ID>, usize)),
 }
 
 impl GraphPath {
@@ -103,15 +108,21 @@ impl GraphPath {
                     let id = entry.id();
                     GraphPath::SimpleEndV((entry, vec![id], 1))
                 }
+                pb::path_expand::PathOpt::Trail => GraphPath::TrailEndV((entry.into(), vec![], 1)),
             },
             pb::path_expand::ResultOpt::AllV | pb::path_expand::ResultOpt::AllVE => match path_opt {
                 pb::path_expand::PathOpt::Arbitrary => GraphPath::AllPath(vec![entry.into()]),
                 pb::path_expand::PathOpt::Simple => GraphPath::SimpleAllPath(vec![entry.into()]),
+                pb::path_expand::PathOpt::Trail => {
+                    let with_edges = pb::path_expand::ResultOpt::AllVE == result_opt;
+                    GraphPath::TrailAllPath((vec![entry.into()], vec![], with_edges))
+                }
             },
         }
     }
 
-    // append an entry and return the flag of whether the entry has been appended or not.
+    // append an entry and return the flag of whether the entry has been appended or not,
+    // where a simple path rejects the visited vertices, and a trail path rejects the visited edges.
     pub fn append<E: Into<VertexOrEdge>>(&mut self, entry: E) -> bool {
         match self {
             GraphPath::AllPath(ref mut path) => {
@@ -120,7 +131,7 @@ impl GraphPath {
             }
             GraphPath::SimpleAllPath(ref mut path) => {
                 let entry = entry.into();
-                if path.contains(&entry) {
+                if entry.is_vertex() && path.contains(&entry) {
                     false
                 } else {
                     path.push(entry);
@@ -137,39 +148,81 @@ impl GraphPath {
             }
             GraphPath::SimpleEndV((ref mut e, ref mut path, ref mut weight)) => {
                 let entry = entry.into();
-                if path.contains(&entry.id()) {
-                    false
-                } else {
-                    path.push(entry.id());
-                    *e = entry;
-                    // we only increase the weight when the entry is a vertex.
-                    if e.is_vertex() {
-                        *weight += 1;
+                // the edges are not visited vertices, whose ids may be the same as those of vertices.
+                if entry.is_vertex() {
+                    if path.contains(&entry.id()) {
+                        return false;
                     }
-                    true
+                    path.push(entry.id());
+                    *weight += 1;
+                }
+                *e = entry;
+                true
+            }
+            GraphPath::TrailAllPath((ref mut path, ref mut edges, with_edges)) => {
+                let entry = entry.into();
+                if !Self::visit_edge(edges, &entry) {
+                    return false;
+                }
+                // the edges are expanded to reject the visited ones even if the result is `AllV`,
+                // in which case the edge is replaced by its end vertex, and only its id is kept.
+                if !*with_edges && entry.is_vertex() && path.last().map_or(false, |e| e.is_edge()) {
+                    path.pop();
+                }
+                path.push(entry);
+                true
+            }
+            GraphPath::TrailEndV((ref mut e, ref mut edges, ref mut weight)) => {
+                let entry = entry.into();
+                if !Self::visit_edge(edges, &entry) {
+                    return false;
                 }
+                *e = entry;
+                if e.is_vertex() {
+                    *weight += 1;
+                }
+                true
             }
         }
     }
 
+    // add the edge to the visited ones of a trail path, or return false if it has been visited.
+    fn visit_edge(edges: &mut Vec<ID>, entry: &VertexOrEdge) -> bool {
+        if entry.is_edge() {
+            if edges.contains(&entry.id()) {
+                return false;
+            }
+            edges.push(entry.id());
+        }
+        true
+    }
+
     pub fn get_path_end(&self) -> &VertexOrEdge {
         match self {
-            GraphPath::AllPath(ref p) | GraphPath::SimpleAllPath(ref p) => p.last().unwrap(),
-            GraphPath::EndV((ref e, _)) | GraphPath::SimpleEndV((ref e, _, _)) => e,
+            GraphPath::AllPath(ref p)
+            | GraphPath::SimpleAllPath(ref p)
+            | GraphPath::TrailAllPath((ref p, _, _)) => p.last().unwrap(),
+            GraphPath::EndV((ref e, _))
+            | GraphPath::SimpleEndV((ref e, _, _))
+            | GraphPath::TrailEndV((ref e, _, _)) => e,
         }
     }
 
     pub fn get_path(&self) -> Option<&Vec<VertexOrEdge>> {
         match self {
-            GraphPath::AllPath(p) | GraphPath::SimpleAllPath(p) => Some(p),
-            GraphPath::EndV(_) | GraphPath::SimpleEndV(_) => None,
+            GraphPath::AllPath(p) | GraphPath::SimpleAllPath(p) | GraphPath::TrailAllPath((p, _, _)) => {
+                Some(p)
+            }
+            GraphPath::EndV(_) | GraphPath::SimpleEndV(_) | GraphPath::TrailEndV(_) => None,
         }
     }
 
     pub fn take_path(self) -> Option<Vec<VertexOrEdge>> {
         match self {
-            GraphPath::AllPath(p) | GraphPath::SimpleAllPath(p) => Some(p),
-            GraphPath::EndV(_) | GraphPath::SimpleEndV(_) => None,
+            GraphPath::AllPath(p) | GraphPath::SimpleAllPath(p) | GraphPath::TrailAllPath((p, _, _)) => {
+                Some(p)
+            }
+            GraphPath::EndV(_) | GraphPath::SimpleEndV(_) | GraphPath::TrailEndV(_) => None,
         }
     }
 }
@@ -241,14 +294,14 @@ impl Element for GraphPath {
     // the path len is the number of edges in the path;
     fn len(&self) -> usize {
         match self {
-            GraphPath::AllPath(p) | GraphPath::SimpleAllPath(p) => {
+            GraphPath::AllPath(p) | GraphPath::SimpleAllPath(p) | GraphPath::TrailAllPath((p, _, _)) => {
                 p.iter()
                     .filter(|v_or_e| v_or_e.is_vertex())
                     .count()
                     - 1
             }
             GraphPath::EndV((_, weight)) => *weight - 1,
-            GraphPath::SimpleEndV((_, _, weight)) => *weight - 1,
+            GraphPath::SimpleEndV((_, _, weight)) | GraphPath::TrailEndV((_, _, weight)) => *weight - 1,
         }
     }
 
@@ -271,7 +324,9 @@ impl GraphElement for GraphPath {
 
     fn get_property(&self, key: &NameOrId) -> Option<PropertyValue> {
         match self {
-            GraphPath::AllPath(path) | GraphPath::SimpleAllPath(path) => {
+            GraphPath::AllPath(path)
+            | GraphPath::SimpleAllPath(path)
+            | GraphPath::TrailAllPath((path, _, _)) => {
                 let mut properties = vec![];
                 for v_or_e in path {
                     if let Some(p) = v_or_e.get_property(key) {
@@ -281,38 +336,32 @@ impl GraphElement for GraphPath {
                 Some(PropertyValue::Owned(Object::Vector(properties)))
             }
 
-            GraphPath::EndV((v_or_e, _)) | GraphPath::SimpleEndV((v_or_e, _, _)) => {
-                v_or_e.get_property(key)
-            }
+            GraphPath::EndV((v_or_e, _))
+            | GraphPath::SimpleEndV((v_or_e, _, _))
+            | GraphPath::TrailEndV((v_or_e, _, _)) => v_or_e.get_property(key),
         }
     }
 
     fn get_all_properties(&self) -> Option<HashMap<NameOrId, Object>> {
         match self {
-            GraphPath::AllPath(_) | GraphPath::SimpleAllPath(_) => {
+            GraphPath::AllPath(_) | GraphPath::SimpleAllPath(_) | GraphPath::TrailAllPath(_) => {
                 // not supported yet.
                 None
             }
 
-            GraphPath::EndV((v_or_e, _)) | GraphPath::SimpleEndV((v_or_e, _, _)) => {
-                v_or_e.get_all_properties()
-            }
+            GraphPath::EndV((v_or_e, _))
+            | GraphPath::SimpleEndV((v_or_e, _, _))
+            | GraphPath::TrailEndV((v_or_e, _, _)) => v_or_e.get_all_properties(),
         }
     }
 }
 
 impl PartialEq for GraphPath {
     fn eq(&self, other: &Self) -> bool {
-        // We define eq by structure, ignoring path weight
-        match (self, other) {
-            (GraphPath::AllPath(p1), GraphPath::AllPath(p2))
-            | (GraphPath::AllPath(p1), GraphPath::SimpleAllPath(p2))
-            | (GraphPath::SimpleAllPath(p1), GraphPath::AllPath(p2))
-            | (GraphPath::SimpleAllPath(p1), GraphPath::SimpleAllPath(p2)) => p1.eq(p2),
-            (GraphPath::EndV((p1, _)), GraphPath::EndV((p2, _)))
-            | (GraphPath::EndV((p1, _)), GraphPath::SimpleEndV((p2, _, _)))
-            | (GraphPath::SimpleEndV((p1, _, _)), GraphPath::EndV((p2, _)))
-            | (GraphPath::SimpleEndV((p1, _, _)), GraphPath::SimpleEndV((p2, _, _))) => p1.eq(p2),
+        // We define eq by structure, ignoring path weight and the visited vertices or edges
+        match (self.get_path(), other.get_path()) {
+            (Some(p1), Some(p2)) => p1.eq(p2),
+            (None, None) => self.get_path_end().eq(other.get_path_end()),
             _ => false,
         }
     }
@@ -320,15 +369,11 @@ impl PartialEq for GraphPath {
 impl PartialOrd for GraphPath {
     // We define partial_cmp by structure, ignoring path weight
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (GraphPath::AllPath(p1), GraphPath::AllPath(p2))
-            | (GraphPath::AllPath(p1), GraphPath::SimpleAllPath(p2))
-            | (GraphPath::SimpleAllPath(p1), GraphPath::AllPath(p2))
-            | (GraphPath::SimpleAllPath(p1), GraphPath::SimpleAllPath(p2)) => p1.partial_cmp(p2),
-            (GraphPath::EndV((p1, _)), GraphPath::EndV((p2, _)))
-            | (GraphPath::EndV((p1, _)), GraphPath::SimpleEndV((p2, _, _)))
-            | (GraphPath::SimpleEndV((p1, _, _)), GraphPath::EndV((p2, _)))
-            | (GraphPath::SimpleEndV((p1, _, _)), GraphPath::SimpleEndV((p2, _, _))) => p1.partial_cmp(p2),
+        match (self.get_path(), other.get_path()) {
+            (Some(p1), Some(p2)) => p1.partial_cmp(p2),
+            (None, None) => self
+                .get_path_end()
+                .partial_cmp(other.get_path_end()),
             _ => None,
         }
     }
@@ -391,6 +436,18 @@ impl Encode for GraphPath {
                 path.write_to(writer)?;
                 writer.write_u64(*weight as u64)?;
             }
+            GraphPath::TrailAllPath((path, edges, with_edges)) => {
+                writer.write_u8(4)?;
+                path.write_to(writer)?;
+                edges.write_to(writer)?;
+                writer.write_u8(*with_edges as u8)?;
+            }
+            GraphPath::TrailEndV((path_end, edges, weight)) => {
+                writer.write_u8(5)?;
+                path_end.write_to(writer)?;
+                edges.write_to(writer)?;
+                writer.write_u64(*weight as u64)?;
+            }
         }
         Ok(())
     }
@@ -419,6 +476,18 @@ impl Decode for GraphPath {
                 let weight = <u64>::read_from(reader)? as usize;
                 Ok(GraphPath::SimpleEndV((vertex_or_edge, path, weight)))
             }
+            4 => {
+                let path = <Vec<VertexOrEdge>>::read_from(reader)?;
+                let edges = <Vec<ID>>::read_from(reader)?;
+                let with_edges = reader.read_u8()? != 0;
+                Ok(GraphPath::TrailAllPath((path, edges, with_edges)))
+            }
+            5 => {
+                let vertex_or_edge = <VertexOrEdge>::read_from(reader)?;
+                let edges = <Vec<ID>>::read_from(reader)?;
+                let weight = <u64>::read_from(reader)? as usize;
+                Ok(GraphPath::TrailEndV((vertex_or_edge, edges, weight)))
+            }
             _ => Err(std::io::Error::new(std::io::ErrorKind::Other, "unreachable")),
         }
     }
@@ -458,10 +527,131 @@ impl TryFrom<result_pb::GraphPath> for GraphPath {
 impl Hash for GraphPath {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
-            GraphPath::AllPath(p) | GraphPath::SimpleAllPath(p) => p.hash(state),
-            GraphPath::EndV((e, _)) | GraphPath::SimpleEndV((e, _, _)) => e.hash(state),
+            GraphPath::AllPath(p) | GraphPath::SimpleAllPath(p) | GraphPath::TrailAllPath((p, _, _)) => {
+                p.hash(state)
+            }
+            GraphPath::EndV((e, _))
+            | GraphPath::SimpleEndV((e, _, _))
+            | GraphPath::TrailEndV((e, _, _)) => e.hash(state),
         }
     }
 }
 
 impl_as_any!(GraphPath);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::DynDetails;
+
+    fn vertex(id: ID) -> Vertex {
+        Vertex::new(id, Some(1), DynDetails::default())
+    }
+
+    fn edge(id: ID, src: ID, dst: ID) -> Edge {
+        Edge::new(id, Some(0), src, dst, DynDetails::default())
+    }
+
+    // walk 1 -> 2 by the edge 1, back to 1 by the edge 2, and to 2 by the edge 1 again
+    fn walk(path_opt: pb::path_expand::PathOpt, result_opt: pb::path_expand::ResultOpt) -> Vec<bool> {
+        let mut path = GraphPath::new(vertex(1), path_opt, result_opt);
+        let steps: Vec<VertexOrEdge> = vec![
+            edge(1, 1, 2).into(),
+            vertex(2).into(),
+            edge(2, 2, 1).into(),
+            vertex(1).into(),
+            edge(1, 1, 2).into(),
+        ];
+        steps
+            .into_iter()
+            .map(|entry| path.append(entry))
+            .collect()
+    }
+
+    #[test]
+    fn path_opt_test() {
+        for result_opt in vec![pb::path_expand::ResultOpt::EndV, pb::path_expand::ResultOpt::AllVE] {
+            assert_eq!(walk(pb::path_expand::PathOpt::Arbitrary, result_opt), vec![true; 5]);
+            // the vertex 1 is visited again
+            assert_eq!(
+                walk(pb::path_expand::PathOpt::Simple, result_opt),
+                vec![true, true, true, false, true]
+            );
+            // the edge 1 is visited again
+            assert_eq!(
+                walk(pb::path_expand::PathOpt::Trail, result_opt),
+                vec![true, true, true, true, false]
+            );
+        }
+    }
+
+    #[test]
+    fn trail_path_test() {
+        let mut path =
+            GraphPath::new(vertex(1), pb::path_expand::PathOpt::Trail, pb::path_expand::ResultOpt::EndV);
+        // the edges of the same ends are different edges of a trail
+        assert!(path.append(edge(1, 1, 2)) && path.append(vertex(2)));
+        assert!(path.append(edge(3, 2, 1)) && path.append(vertex(1)));
+        assert!(path.append(edge(4, 1, 2)) && path.append(vertex(2)));
+        assert_eq!(path.len(), 3);
+        assert_eq!(path.get_path_end(), &VertexOrEdge::from(vertex(2)));
+
+        let mut bytes = vec![];
+        path.write_to(&mut bytes).unwrap();
+        let mut decoded = GraphPath::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded, path);
+        assert_eq!(decoded.len(), 3);
+        // the visited edges are kept as the path is shuffled
+        assert!(!decoded.append(edge(3, 2, 1)));
+    }
+
+    #[test]
+    fn trail_path_all_v_test() {
+        let mut path =
+            GraphPath::new(vertex(1), pb::path_expand::PathOpt::Trail, pb::path_expand::ResultOpt::AllV);
+        assert!(path.append(edge(1, 1, 2)));
+        // the edge is the path end until its end vertex is appended
+        assert_eq!(path.get_path_end(), &VertexOrEdge::from(edge(1, 1, 2)));
+        assert!(path.append(vertex(2)));
+        assert!(path.append(edge(2, 2, 1)) && path.append(vertex(1)));
+        assert_eq!(path.get_path(), Some(&vec![vertex(1).into(), vertex(2).into(), vertex(1).into()]));
+        assert_eq!(path.len(), 2);
+
+        let mut bytes = vec![];
+        path.write_to(&mut bytes).unwrap();
+        let mut decoded = GraphPath::read_from(&mut &bytes[..]).unwrap();
+        // the ids of the stripped edges are still visited
+        assert!(!decoded.append(edge(1, 1, 2)));
+        assert!(decoded.append(edge(3, 1, 2)) && decoded.append(vertex(2)));
+        assert_eq!(decoded.take_path().unwrap().len(), 4);
+
+        let mut path =
+            GraphPath::new(vertex(1), pb::path_expand::PathOpt::Trail, pb::path_expand::ResultOpt::AllVE);
+        assert!(path.append(edge(1, 1, 2)) && path.append(vertex(2)));
+        assert_eq!(path.get_path(), Some(&vec![vertex(1).into(), edge(1, 1, 2).into(), vertex(2).into()]));
+    }
+
+    #[test]
+    fn simple_path_test() {
+        // the edge 1 is not the visited vertex 1, though of the same id
+        let mut path =
+            GraphPath::new(vertex(1), pb::path_expand::PathOpt::Simple, pb::path_expand::ResultOpt::EndV);
+        assert!(path.append(edge(1, 1, 2)));
+        assert_eq!(path.get_path_end(), &VertexOrEdge::from(edge(1, 1, 2)));
+        assert!(path.append(vertex(2)));
+        // only the vertices are weighted
+        assert_eq!(path.len(), 1);
+        assert!(path.append(edge(2, 2, 1)) && !path.append(vertex(1)));
+        assert_eq!(path.len(), 1);
+
+        let mut path =
+            GraphPath::new(vertex(1), pb::path_expand::PathOpt::Simple, pb::path_expand::ResultOpt::AllVE);
+        assert!(path.append(edge(1, 1, 2)) && path.append(vertex(2)));
+        assert!(path.append(edge(2, 2, 1)) && !path.append(vertex(1)));
+        assert_eq!(
+            path.get_path(),
+            Some(&vec![vertex(1).into(), edge(1, 1, 2).into(), vertex(2).into(), edge(2, 2, 1).into()])
+        );
+        assert_eq!(path.len(), 1);
+    }
+}
@@ -198,6 +198,85 @@ impl Details for DynDetails {
     }
 }
 
+/// How a restricted property is presented to a caller who is not allowed to see it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MaskAction {
+    /// Replace the value with the given one, e.g., `"***"`.
+    Mask(Object),
+    /// Hide the property as if it does not exist.
+    Omit,
+}
+
+/// MaskedDetails wraps the details of a graph element, and hides the restricted properties.
+#[derive(Debug)]
+pub struct MaskedDetails {
+    inner: DynDetails,
+    rules: Arc<HashMap<NameOrId, MaskAction>>,
+}
+
+impl MaskedDetails {
+    pub fn new(inner: DynDetails, rules: Arc<HashMap<NameOrId, MaskAction>>) -> Self {
+        MaskedDetails { inner, rules }
+    }
+
+    fn is_omitted(&self, key: &NameOrId) -> bool {
+        matches!(self.rules.get(key), Some(MaskAction::Omit))
+    }
+}
+
+impl_as_any!(MaskedDetails);
+
+impl Details for MaskedDetails {
+    fn get_property(&self, key: &NameOrId) -> Option<PropertyValue> {
+        match self.rules.get(key) {
+            Some(MaskAction::Omit) => None,
+            Some(MaskAction::Mask(masked)) => self
+                .inner
+                .get_property(key)
+                .map(|_| PropertyValue::Owned(masked.clone())),
+            None => self.inner.get_property(key),
+        }
+    }
+
+    fn get_all_properties(&self) -> Option<HashMap<NameOrId, Object>> {
+        self.inner
+            .get_all_properties()
+            .map(|mut props| {
+                for (key, action) in self.rules.iter() {
+                    match action {
+                        MaskAction::Omit => {
+                            props.remove(key);
+                        }
+                        MaskAction::Mask(masked) => {
+                            if let Some(value) = props.get_mut(key) {
+                                *value = masked.clone();
+                            }
+                        }
+                    }
+                }
+                props
+            })
+    }
+
+    fn get_property_keys(&self) -> Option<Vec<NameOrId>> {
+        let keys = match &self.inner {
+            // `get_property_keys()` is not supported by default details, so take its keys directly
+            DynDetails::Default(props) => Some(props.keys().cloned().collect()),
+            _ => self.inner.get_property_keys(),
+        };
+        // an empty vector stands for all properties, which are filtered in `get_all_properties()`
+        keys.map(|keys| {
+            if keys.is_empty() {
+                keys
+            } else {
+                keys.into_iter()
+                    .filter(|k| !self.is_omitted(k))
+                    .collect()
+            }
+        })
+    }
+}
+
 impl Encode for DynDetails {
     fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
         match self {
@@ -0,0 +1,121 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The lookups of a scan of vertices by the ids of `@.~id within [...]` or `@.~id == id` of the top-level
+//! conjuncts of the predicate of the scan, which are fetched by `ReadGraph::get_vertex`, rather than
+//! scanning the labels.
+//!
+//! The predicate is still evaluated on the vertices looked up, and so are the labels of the scan.
+
+use dyn_type::Object;
+use ir_common::generated::common as common_pb;
+
+use crate::apis::{PropKey, ID};
+use crate::utils::expr::eval::Operand;
+use crate::utils::expr::eval_compiled::{as_integer, flip};
+use crate::utils::expr::eval_pred::{PEvaluator, Predicate, Predicates};
+
+/// Collect the binary predicates of the top-level conjuncts.
+fn conjuncts<'a>(predicates: &'a Predicates, binaries: &mut Vec<&'a Predicate>) {
+    match predicates {
+        Predicates::Binary(pred) => binaries.push(pred),
+        Predicates::And((left, right)) => {
+            conjuncts(left, binaries);
+            conjuncts(right, binaries);
+        }
+        _ => {}
+    }
+}
+
+/// The predicates of `head_key cmp constant` of the scanned element, i.e., the head, where a constant
+/// on the left is flipped to the right.
+fn head_comparisons(filter: &PEvaluator) -> Vec<(&PropKey, common_pb::Logical, &Object)> {
+    let mut binaries = vec![];
+    if let PEvaluator::Predicates(predicates) = filter {
+        conjuncts(predicates, &mut binaries);
+    }
+    binaries
+        .into_iter()
+        .filter_map(|pred| {
+            let (var, cmp, constant) = match (&pred.left, &pred.right) {
+                (var @ Operand::Var { .. }, Operand::Const(constant)) => (var, pred.cmp, constant),
+                (Operand::Const(constant), var @ Operand::Var { .. }) => (var, flip(pred.cmp)?, constant),
+                _ => return None,
+            };
+            match var {
+                Operand::Var { tag: None, prop_key: Some(key) } => Some((key, cmp, constant)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Extract the ids from the conjuncts of `@.~id within [...]` or `@.~id == id`, which are intersected
+/// if more than one. Return `None` if there is no such conjunct.
+pub fn extract_ids(filter: &PEvaluator) -> Option<Vec<ID>> {
+    let mut ids: Option<Vec<ID>> = None;
+    for (key, cmp, constant) in head_comparisons(filter) {
+        let candidates = match (key, cmp, constant) {
+            (PropKey::Id, common_pb::Logical::Eq, id) => vec![as_integer(&id.as_borrow())?],
+            (PropKey::Id, common_pb::Logical::Within, Object::Vector(vec)) => vec
+                .iter()
+                .map(|id| as_integer(&id.as_borrow()))
+                .collect::<Option<Vec<ID>>>()?,
+            _ => continue,
+        };
+        ids = Some(match ids {
+            Some(ids) => ids
+                .into_iter()
+                .filter(|id| candidates.contains(id))
+                .collect(),
+            None => candidates,
+        });
+    }
+    ids.map(|mut ids| {
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+
+    fn filter(expr: &str) -> PEvaluator {
+        PEvaluator::try_from(str_to_expr_pb(expr.to_string()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_extract_ids() {
+        assert_eq!(extract_ids(&filter("@.~id within [1, 2, 3]")), Some(vec![1, 2, 3]));
+        assert_eq!(extract_ids(&filter("@.age > 10 && 2 == @.~id")), Some(vec![2]));
+        assert_eq!(
+            extract_ids(&filter("@.~id within [1, 2, 3] && @.~id within [2, 3, 4]")),
+            Some(vec![2, 3])
+        );
+        assert_eq!(extract_ids(&filter("@.~id within [1, 2] && @.~id == 3")), Some(vec![]));
+        // not a conjunct, or not of the head
+        assert_eq!(extract_ids(&filter("@.~id within [1, 2] || @.age > 10")), None);
+        assert_eq!(extract_ids(&filter("@a.~id within [1, 2]")), None);
+        assert_eq!(extract_ids(&filter("@.~id != 1")), None);
+        assert_eq!(extract_ids(&filter("@.~id == 1.5")), None);
+        assert_eq!(extract_ids(&filter("@.age > 10")), None);
+    }
+}
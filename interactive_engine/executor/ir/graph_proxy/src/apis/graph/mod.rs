@@ -29,6 +29,7 @@ use pegasus::codec::{ReadExt, WriteExt};
 use crate::utils::expr::eval_pred::PEvaluator;
 
 pub mod element;
+pub mod index;
 pub type ID = i64;
 
 pub fn read_id<R: ReadExt>(reader: &mut R) -> io::Result<ID> {
@@ -45,7 +46,7 @@ pub const ID_BITS: usize = std::mem::size_of::<ID>() * 8;
 /// Primary key in storage, including single column pk and multi column pks.
 pub type PKV = OneOrMany<(NameOrId, Object)>;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Direction {
     Out = 0,
     In = 1,
@@ -65,6 +66,19 @@ impl From<pb::edge_expand::Direction> for Direction {
     }
 }
 
+impl From<algebra_pb::edge_expand::Direction> for Direction {
+    fn from(direction: algebra_pb::edge_expand::Direction) -> Self
+    where
+        Self: Sized,
+    {
+        match direction {
+            algebra_pb::edge_expand::Direction::Out => Direction::Out,
+            algebra_pb::edge_expand::Direction::In => Direction::In,
+            algebra_pb::edge_expand::Direction::Both => Direction::Both,
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct QueryParams {
     pub labels: Vec<LabelId>,
@@ -73,6 +87,30 @@ pub struct QueryParams {
     pub filter: Option<Arc<PEvaluator>>,
     pub sample_ratio: Option<f64>,
     pub extra_params: Option<HashMap<String, String>>,
+    pub valid_time: Option<TimeRange>,
+}
+
+/// The time range of `[start, end]` which the edges of the temporal labels must be valid in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl TimeRange {
+    pub fn as_of(time: i64) -> Self {
+        TimeRange { start: time, end: time }
+    }
+
+    pub fn between(start: i64, end: i64) -> Self {
+        TimeRange { start, end }
+    }
+
+    /// Whether the validity interval of `[valid_from, valid_to)` overlaps with the range, where a
+    /// missing bound is unbounded.
+    pub fn overlaps(&self, valid_from: Option<i64>, valid_to: Option<i64>) -> bool {
+        valid_from.map_or(true, |from| from <= self.end) && valid_to.map_or(true, |to| to > self.start)
+    }
 }
 
 impl TryFrom<Option<algebra_pb::QueryParams>> for QueryParams {
@@ -85,7 +123,8 @@ impl TryFrom<Option<algebra_pb::QueryParams>> for QueryParams {
                 .with_filter(query_params_pb.predicate)?
                 .with_limit(query_params_pb.limit)?
                 .with_sample_ratio(query_params_pb.sample_ratio)?
-                .with_extra_params(query_params_pb.extra)?;
+                .with_extra_params(query_params_pb.extra)?
+                .with_valid_time(query_params_pb.valid_time)?;
             if query_params_pb.is_all_columns {
                 query_param.with_all_columns()
             } else {
@@ -167,6 +206,18 @@ impl QueryParams {
         Ok(self)
     }
 
+    fn with_valid_time(
+        mut self, valid_time_pb: Option<algebra_pb::TimeRange>,
+    ) -> Result<Self, ParsePbError> {
+        if let Some(range) = valid_time_pb {
+            if range.start > range.end {
+                Err(ParsePbError::from(format!("Not a legal time range [{}, {}]", range.start, range.end)))?
+            }
+            self.valid_time = Some(TimeRange::between(range.start, range.end));
+        }
+        Ok(self)
+    }
+
     pub fn get_extra_param(&self, key: &str) -> Option<&String> {
         if let Some(ref extra_params) = self.extra_params {
             extra_params.get(key)
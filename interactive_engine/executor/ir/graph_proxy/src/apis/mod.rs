@@ -14,16 +14,35 @@
 //! limitations under the License.
 
 pub mod cluster_info;
+pub mod consistency;
+pub mod derived;
 pub mod graph;
 pub mod partitioner;
 pub mod read_graph;
+pub mod statistics;
+pub mod temporal;
+#[cfg(any(test, feature = "test_utils"))]
+pub mod test_graph;
+pub mod tombstone;
+pub mod view;
 pub mod write_graph;
 
 pub use cluster_info::*;
+pub use consistency::{
+    bind_consistency, get_current_consistency, get_current_snapshot, ConsistencyBinding, ReadConsistency,
+};
+pub use derived::{register_derived_schema, DerivedGraph, DerivedProperty, DerivedSchema};
 pub use graph::element::{
-    Details, DynDetails, Edge, Element, GraphElement, GraphPath, PropKey, PropertyValue, Vertex,
-    VertexOrEdge,
+    Details, DynDetails, Edge, EdgeRef, Element, GraphElement, GraphPath, MaskAction, MaskedDetails,
+    PropKey, PropertyValue, Vertex, VertexOrEdge,
 };
-pub use graph::{read_id, write_id, Direction, QueryParams, ID};
+pub use graph::{read_id, write_id, Direction, QueryParams, TimeRange, ID};
 pub use read_graph::{from_fn, get_graph, register_graph, ReadGraph, Statement};
-pub use write_graph::WriteGraphProxy;
+pub use statistics::{get_statistics, register_statistics, DegreeHistogram, GraphStatistics};
+pub use temporal::{register_temporal_schema, TemporalGraph, TemporalSchema};
+pub use tombstone::{
+    bind_include_deleted, enable_soft_delete, get_soft_delete, get_undeleted_graph, now_millis,
+    purge_deleted, start_purge, IncludeDeletedBinding, PurgeHandle, SoftDelete, TombstoneGraph,
+};
+pub use view::{bind_view, get_view, register_view, unregister_view, GraphView, ViewBinding, ViewGraph};
+pub use write_graph::{get_write_graph, register_write_graph, Mutated, Mutation, WriteGraphProxy};
@@ -18,9 +18,13 @@ use std::sync::Arc;
 
 use ir_common::LabelId;
 
+use crate::apis::derived::{get_derived_schema, DerivedGraph};
 use crate::apis::graph::PKV;
-use crate::apis::{Direction, Edge, QueryParams, Vertex, ID};
-use crate::GraphProxyResult;
+use crate::apis::temporal::{get_temporal_schema, TemporalGraph};
+use crate::apis::tombstone::{get_hiding_soft_delete, TombstoneGraph};
+use crate::apis::view::{get_current_view, ViewGraph};
+use crate::apis::{Direction, Edge, EdgeRef, GraphElement, GraphStatistics, QueryParams, Vertex, ID};
+use crate::{limit_n, GraphProxyResult};
 
 /// The function for graph query
 pub trait Statement<I, O>: Send + 'static {
@@ -36,6 +40,24 @@ where
     }
 }
 
+/// Filter and map the outputs of the inner statement by the function, with the limit applied after.
+pub(crate) struct FilterMapStatement<O> {
+    pub(crate) inner: Box<dyn Statement<ID, O>>,
+    pub(crate) func: Arc<dyn Fn(O) -> Option<O> + Send + Sync>,
+    pub(crate) limit: Option<usize>,
+}
+
+impl<O: Send + 'static> Statement<ID, O> for FilterMapStatement<O> {
+    fn exec(&self, next: ID) -> GraphProxyResult<Box<dyn Iterator<Item = O> + Send>> {
+        let func = self.func.clone();
+        let iter = self
+            .inner
+            .exec(next)?
+            .filter_map(move |o| func(o));
+        Ok(limit_n!(iter, self.limit))
+    }
+}
+
 pub fn from_fn<I, O, F>(func: F) -> Box<dyn Statement<I, O>>
 where
     F: Fn(I) -> GraphProxyResult<Box<dyn Iterator<Item = O> + Send>> + Send + Sync + 'static,
@@ -69,6 +91,19 @@ pub trait ReadGraph: Send + Sync {
         &self, ids: &[ID], params: &QueryParams,
     ) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>>;
 
+    /// Get the edge with the given address and parameters, and return the edge if exists.
+    /// By default, the edge is found among the outgoing edges of its source vertex, which should be
+    /// overridden by a point lookup if the storage supports it.
+    fn get_edge_by_id(&self, edge_ref: &EdgeRef, params: &QueryParams) -> GraphProxyResult<Option<Edge>> {
+        let mut params = params.clone();
+        params.labels = vec![edge_ref.label];
+        let edge = self
+            .prepare_explore_edge(Direction::Out, &params)?
+            .exec(edge_ref.src_id)?
+            .find(|e| e.dst_id == edge_ref.dst_id && e.id() == edge_ref.id);
+        Ok(edge)
+    }
+
     /// Get adjacent vertices of the given direction with parameters, and return the closure of Statement.
     /// We could further call the returned closure with input vertex and get its adjacent vertices.
     fn prepare_explore_vertex(
@@ -90,6 +125,12 @@ pub trait ReadGraph: Send + Sync {
     /// Get primary key value(s) with the given global_id,
     /// and return the primary key value(s) if exists
     fn get_primary_key(&self, id: &ID) -> GraphProxyResult<Option<PKV>>;
+
+    /// Collect the statistics of the graph to refine the plans, which is called once the graph is
+    /// given to the job assembly, see `register_statistics`. By default, return `None` as unknown.
+    fn collect_statistics(&self) -> GraphProxyResult<Option<GraphStatistics>> {
+        Ok(None)
+    }
 }
 
 lazy_static! {
@@ -98,11 +139,13 @@ lazy_static! {
 }
 
 pub fn register_graph(graph: Arc<dyn ReadGraph>) {
+    let graph = Arc::new(ProfiledGraph { inner: graph }) as Arc<dyn ReadGraph>;
     let ptr = Box::into_raw(Box::new(graph));
     GRAPH_PROXY.store(ptr, Ordering::SeqCst);
 }
 
-pub fn get_graph() -> Option<Arc<dyn ReadGraph>> {
+/// The graph registered, with the deleted elements, the stored properties only, and no view.
+pub(crate) fn get_stored_graph() -> Option<Arc<dyn ReadGraph>> {
     let ptr = GRAPH_PROXY.load(Ordering::SeqCst);
     if ptr.is_null() {
         None
@@ -110,3 +153,125 @@ pub fn get_graph() -> Option<Arc<dyn ReadGraph>> {
         Some(unsafe { (*ptr).clone() })
     }
 }
+
+pub fn get_graph() -> Option<Arc<dyn ReadGraph>> {
+    get_stored_graph().map(|mut graph| {
+        if let Some(soft_delete) = get_hiding_soft_delete() {
+            graph = Arc::new(TombstoneGraph::new(graph, soft_delete));
+        }
+        if let Some(schema) = get_temporal_schema() {
+            graph = Arc::new(TemporalGraph::new(graph, schema));
+        }
+        if let Some(schema) = get_derived_schema() {
+            graph = Arc::new(DerivedGraph::new(graph, schema));
+        }
+        // read through the view of the job of the current worker, if any
+        if let Some(view) = get_current_view() {
+            graph = Arc::new(ViewGraph::new(graph, view));
+        }
+        graph
+    })
+}
+
+/// Count the vertices and edges read from the graph for the profile of the operator reading them,
+/// see `pegasus::profile`.
+struct ProfiledGraph {
+    inner: Arc<dyn ReadGraph>,
+}
+
+#[inline]
+fn count_reads<T: 'static>(iter: Box<dyn Iterator<Item = T> + Send>) -> Box<dyn Iterator<Item = T> + Send> {
+    Box::new(iter.inspect(|_| pegasus::profile::add_store_reads(1)))
+}
+
+struct ProfiledStatement<I, O> {
+    inner: Box<dyn Statement<I, O>>,
+}
+
+impl<I: 'static, O: 'static> Statement<I, O> for ProfiledStatement<I, O> {
+    fn exec(&self, next: I) -> GraphProxyResult<Box<dyn Iterator<Item = O> + Send>> {
+        self.inner.exec(next).map(count_reads)
+    }
+}
+
+impl ReadGraph for ProfiledGraph {
+    fn scan_vertex(
+        &self, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        self.inner.scan_vertex(params).map(count_reads)
+    }
+
+    fn index_scan_vertex(
+        &self, label: LabelId, primary_key: &PKV, params: &QueryParams,
+    ) -> GraphProxyResult<Option<Vertex>> {
+        let vertex = self
+            .inner
+            .index_scan_vertex(label, primary_key, params)?;
+        if vertex.is_some() {
+            pegasus::profile::add_store_reads(1);
+        }
+        Ok(vertex)
+    }
+
+    fn scan_edge(&self, params: &QueryParams) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        self.inner.scan_edge(params).map(count_reads)
+    }
+
+    fn get_vertex(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        self.inner
+            .get_vertex(ids, params)
+            .map(count_reads)
+    }
+
+    fn get_edge(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        self.inner
+            .get_edge(ids, params)
+            .map(count_reads)
+    }
+
+    fn get_edge_by_id(&self, edge_ref: &EdgeRef, params: &QueryParams) -> GraphProxyResult<Option<Edge>> {
+        let edge = self.inner.get_edge_by_id(edge_ref, params)?;
+        if edge.is_some() {
+            pegasus::profile::add_store_reads(1);
+        }
+        Ok(edge)
+    }
+
+    fn prepare_explore_vertex(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Vertex>>> {
+        let stmt = self
+            .inner
+            .prepare_explore_vertex(direction, params)?;
+        Ok(Box::new(ProfiledStatement { inner: stmt }))
+    }
+
+    fn prepare_explore_edge(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Edge>>> {
+        let stmt = self
+            .inner
+            .prepare_explore_edge(direction, params)?;
+        Ok(Box::new(ProfiledStatement { inner: stmt }))
+    }
+
+    fn count_vertex(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        self.inner.count_vertex(params)
+    }
+
+    fn count_edge(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        self.inner.count_edge(params)
+    }
+
+    fn get_primary_key(&self, id: &ID) -> GraphProxyResult<Option<PKV>> {
+        self.inner.get_primary_key(id)
+    }
+
+    fn collect_statistics(&self) -> GraphProxyResult<Option<GraphStatistics>> {
+        self.inner.collect_statistics()
+    }
+}
@@ -0,0 +1,228 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The statistics of the graph given by the store, i.e., the counts of the labels, the numbers of the
+//! distinct values (NDV) of the properties, and the histograms of the degrees, which are consulted by
+//! the runtime to refine the plans, e.g., to reorder the conjuncts of the filters by their
+//! selectivities. The statistics are estimations, which never change the results of the plans.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use ir_common::{LabelId, NameOrId};
+
+use crate::apis::Direction;
+
+/// The histogram of the degrees of the vertices, as the buckets of `(max_degree, vertex_count)` in the
+/// ascending order of `max_degree`, where a bucket counts the vertices of the degrees greater than
+/// `max_degree` of the previous bucket, and no greater than its own `max_degree`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DegreeHistogram {
+    buckets: Vec<(u64, u64)>,
+}
+
+impl DegreeHistogram {
+    pub fn new(mut buckets: Vec<(u64, u64)>) -> Self {
+        buckets.sort_by_key(|(max_degree, _)| *max_degree);
+        DegreeHistogram { buckets }
+    }
+
+    /// The average degree, estimated by the `max_degree` of the buckets, or `None` if empty.
+    pub fn avg_degree(&self) -> Option<f64> {
+        let vertices: u64 = self
+            .buckets
+            .iter()
+            .map(|(_, count)| count)
+            .sum();
+        if vertices == 0 {
+            None
+        } else {
+            let degrees: f64 = self
+                .buckets
+                .iter()
+                .map(|(max_degree, count)| (*max_degree as f64) * (*count as f64))
+                .sum();
+            Some(degrees / vertices as f64)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GraphStatistics {
+    vertex_counts: HashMap<LabelId, u64>,
+    edge_counts: HashMap<LabelId, u64>,
+    vertex_ndvs: HashMap<(LabelId, NameOrId), u64>,
+    edge_ndvs: HashMap<(LabelId, NameOrId), u64>,
+    degrees: HashMap<(LabelId, Direction), DegreeHistogram>,
+}
+
+impl GraphStatistics {
+    pub fn new() -> Self {
+        GraphStatistics::default()
+    }
+
+    pub fn add_vertex_count(&mut self, label: LabelId, count: u64) {
+        self.vertex_counts.insert(label, count);
+    }
+
+    pub fn add_edge_count(&mut self, label: LabelId, count: u64) {
+        self.edge_counts.insert(label, count);
+    }
+
+    /// Add the number of the distinct values of the property of the vertices of the label.
+    pub fn add_vertex_ndv(&mut self, label: LabelId, key: NameOrId, ndv: u64) {
+        self.vertex_ndvs.insert((label, key), ndv);
+    }
+
+    /// Add the number of the distinct values of the property of the edges of the label.
+    pub fn add_edge_ndv(&mut self, label: LabelId, key: NameOrId, ndv: u64) {
+        self.edge_ndvs.insert((label, key), ndv);
+    }
+
+    /// Add the histogram of the degrees of the vertices along the edges of the label in the direction.
+    pub fn add_degrees(&mut self, label: LabelId, direction: Direction, histogram: DegreeHistogram) {
+        self.degrees
+            .insert((label, direction), histogram);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertex_counts.is_empty()
+            && self.edge_counts.is_empty()
+            && self.vertex_ndvs.is_empty()
+            && self.edge_ndvs.is_empty()
+            && self.degrees.is_empty()
+    }
+
+    /// The number of the vertices of the labels, or of all the labels if empty.
+    pub fn vertex_count(&self, labels: &[LabelId]) -> Option<u64> {
+        sum_of(&self.vertex_counts, labels)
+    }
+
+    /// The number of the edges of the labels, or of all the labels if empty.
+    pub fn edge_count(&self, labels: &[LabelId]) -> Option<u64> {
+        sum_of(&self.edge_counts, labels)
+    }
+
+    /// The number of the distinct values of the property of the vertices of the labels, or of all the
+    /// labels if empty, which is the largest of the labels, i.e., the least selective.
+    pub fn vertex_ndv(&self, labels: &[LabelId], key: &NameOrId) -> Option<u64> {
+        max_of(&self.vertex_ndvs, labels, key)
+    }
+
+    /// The number of the distinct values of the property of the edges of the labels, or of all the
+    /// labels if empty, which is the largest of the labels, i.e., the least selective.
+    pub fn edge_ndv(&self, labels: &[LabelId], key: &NameOrId) -> Option<u64> {
+        max_of(&self.edge_ndvs, labels, key)
+    }
+
+    /// The average degree of the vertices along the edges of the labels, or of all the labels if empty,
+    /// in the direction, where both directions are summed up for `Direction::Both`.
+    pub fn avg_degree(&self, labels: &[LabelId], direction: Direction) -> Option<f64> {
+        if direction == Direction::Both {
+            let out_degree = self.avg_degree(labels, Direction::Out);
+            let in_degree = self.avg_degree(labels, Direction::In);
+            return match (out_degree, in_degree) {
+                (Some(out_degree), Some(in_degree)) => Some(out_degree + in_degree),
+                _ => out_degree.or(in_degree),
+            };
+        }
+        let degrees: Vec<f64> = self
+            .degrees
+            .iter()
+            .filter(|((label, dir), _)| *dir == direction && (labels.is_empty() || labels.contains(label)))
+            .filter_map(|(_, histogram)| histogram.avg_degree())
+            .collect();
+        if degrees.is_empty() {
+            None
+        } else {
+            Some(degrees.iter().sum())
+        }
+    }
+}
+
+/// The sum of the counts of the labels, or of all the labels if empty, which is `None` if any of the
+/// labels is missing.
+fn sum_of(counts: &HashMap<LabelId, u64>, labels: &[LabelId]) -> Option<u64> {
+    if labels.is_empty() {
+        if counts.is_empty() {
+            None
+        } else {
+            Some(counts.values().sum())
+        }
+    } else {
+        labels
+            .iter()
+            .map(|label| counts.get(label).copied())
+            .sum()
+    }
+}
+
+fn max_of(ndvs: &HashMap<(LabelId, NameOrId), u64>, labels: &[LabelId], key: &NameOrId) -> Option<u64> {
+    ndvs.iter()
+        .filter(|((label, k), _)| k == key && (labels.is_empty() || labels.contains(label)))
+        .map(|(_, ndv)| *ndv)
+        .max()
+}
+
+lazy_static! {
+    static ref GRAPH_STATISTICS: RwLock<Option<Arc<GraphStatistics>>> = RwLock::new(None);
+}
+
+/// Register the statistics of the graph given by the store, replacing the ones registered before.
+pub fn register_statistics(statistics: GraphStatistics) {
+    let statistics = if statistics.is_empty() { None } else { Some(Arc::new(statistics)) };
+    *GRAPH_STATISTICS
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = statistics;
+}
+
+pub fn get_statistics() -> Option<Arc<GraphStatistics>> {
+    GRAPH_STATISTICS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_statistics() {
+        let mut statistics = GraphStatistics::new();
+        assert!(statistics.is_empty());
+        statistics.add_vertex_count(0, 100);
+        statistics.add_vertex_count(1, 20);
+        statistics.add_edge_count(0, 500);
+        statistics.add_vertex_ndv(0, "name".into(), 90);
+        statistics.add_vertex_ndv(1, "name".into(), 20);
+        statistics.add_edge_ndv(0, "weight".into(), 10);
+        statistics.add_degrees(0, Direction::Out, DegreeHistogram::new(vec![(4, 25), (2, 75)]));
+        statistics.add_degrees(0, Direction::In, DegreeHistogram::new(vec![(5, 100)]));
+
+        assert_eq!(statistics.vertex_count(&[]), Some(120));
+        assert_eq!(statistics.vertex_count(&[0]), Some(100));
+        assert_eq!(statistics.vertex_count(&[0, 2]), None);
+        assert_eq!(statistics.edge_count(&[0]), Some(500));
+        assert_eq!(statistics.vertex_ndv(&[], &"name".into()), Some(90));
+        assert_eq!(statistics.vertex_ndv(&[1], &"name".into()), Some(20));
+        assert_eq!(statistics.vertex_ndv(&[1], &"age".into()), None);
+        assert_eq!(statistics.vertex_ndv(&[0], &"weight".into()), None);
+        assert_eq!(statistics.edge_ndv(&[0], &"weight".into()), Some(10));
+        assert_eq!(statistics.avg_degree(&[0], Direction::Out), Some(2.5));
+        assert_eq!(statistics.avg_degree(&[0], Direction::Both), Some(7.5));
+        assert_eq!(statistics.avg_degree(&[1], Direction::Out), None);
+    }
+}
@@ -0,0 +1,417 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Temporal edges: the edges of a temporal label are valid in the interval of
+//! `[valid_from, valid_to)`, given by two of their properties registered in the `TemporalSchema`,
+//! where a missing bound is unbounded, e.g., a relationship which still holds has no `valid_to`.
+//!
+//! A query reads the graph at a time, i.e., `as of t`, or in a period, i.e., `between t1 and t2`,
+//! by the `valid_time` of its `QueryParams`. The graph read through `get_graph()` then outputs the
+//! edges of the temporal labels valid in the time range only, which applies to the scans of the
+//! edges as well as to the expands, including the adjacent vertices reached by the valid edges.
+//! The edges of the other labels are always valid.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use ir_common::{LabelId, NameOrId};
+
+use crate::apis::graph::{TimeRange, PKV};
+use crate::apis::read_graph::FilterMapStatement;
+use crate::apis::{
+    Direction, DynDetails, Edge, EdgeRef, GraphElement, QueryParams, ReadGraph, Statement, Vertex, ID,
+};
+use crate::limit_n;
+use crate::GraphProxyResult;
+
+/// The properties of the edges holding the bounds of their validity intervals.
+#[derive(Clone, Debug, PartialEq)]
+struct ValidColumns {
+    valid_from: NameOrId,
+    valid_to: NameOrId,
+}
+
+/// The temporal labels of the edges.
+#[derive(Debug, Default)]
+pub struct TemporalSchema {
+    edges: HashMap<LabelId, ValidColumns>,
+}
+
+impl TemporalSchema {
+    pub fn new() -> Self {
+        TemporalSchema::default()
+    }
+
+    /// Make the edges of the label valid in `[valid_from, valid_to)` of the given properties.
+    pub fn add_edge_label(&mut self, label: LabelId, valid_from: NameOrId, valid_to: NameOrId) {
+        self.edges
+            .insert(label, ValidColumns { valid_from, valid_to });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    /// The properties of the bounds of the labels, or of all the temporal labels if empty.
+    fn columns_of(&self, labels: &[LabelId]) -> Vec<NameOrId> {
+        let mut columns = vec![];
+        for (label, valid) in self.edges.iter() {
+            if labels.is_empty() || labels.contains(label) {
+                for column in vec![&valid.valid_from, &valid.valid_to] {
+                    if !columns.contains(column) {
+                        columns.push(column.clone());
+                    }
+                }
+            }
+        }
+        columns
+    }
+}
+
+lazy_static! {
+    static ref TEMPORAL_SCHEMA: RwLock<Option<Arc<TemporalSchema>>> = RwLock::new(None);
+}
+
+/// Register the temporal labels, replacing all the ones registered before.
+pub fn register_temporal_schema(schema: TemporalSchema) {
+    let schema = if schema.is_empty() { None } else { Some(Arc::new(schema)) };
+    *TEMPORAL_SCHEMA
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = schema;
+}
+
+pub(crate) fn get_temporal_schema() -> Option<Arc<TemporalSchema>> {
+    TEMPORAL_SCHEMA
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Admit the edges valid in the time range.
+#[derive(Clone)]
+struct Validate {
+    schema: Arc<TemporalSchema>,
+    range: TimeRange,
+}
+
+impl Validate {
+    fn edge(&self, edge: Edge) -> Option<Edge> {
+        if let Some(valid) = edge
+            .label()
+            .and_then(|label| self.schema.edges.get(&label))
+        {
+            let valid_from = time_of(&edge, &valid.valid_from);
+            let valid_to = time_of(&edge, &valid.valid_to);
+            if !self.range.overlaps(valid_from, valid_to) {
+                return None;
+            }
+        }
+        Some(edge)
+    }
+}
+
+fn time_of(edge: &Edge, key: &NameOrId) -> Option<i64> {
+    edge.get_property(key)
+        .and_then(|value| value.try_to_owned())
+        .and_then(|value| value.as_i64().ok())
+}
+
+/// The graph with the temporal edges.
+pub struct TemporalGraph {
+    inner: Arc<dyn ReadGraph>,
+    schema: Arc<TemporalSchema>,
+}
+
+impl TemporalGraph {
+    pub fn new(inner: Arc<dyn ReadGraph>, schema: Arc<TemporalSchema>) -> Self {
+        TemporalGraph { inner, schema }
+    }
+
+    /// The params to read the edges from the inner graph, with the bounds of the edges required and
+    /// the limit applied after the validation, and how to validate the edges read, if the params
+    /// have a time range.
+    fn edge_params(&self, params: &QueryParams) -> Option<(QueryParams, Validate)> {
+        let range = params.valid_time?;
+        let mut inner = params.clone();
+        inner.valid_time = None;
+        inner.limit = None;
+        let columns = self.schema.columns_of(&params.labels);
+        match inner.columns.as_mut() {
+            // all the properties, including the bounds
+            Some(required) if required.is_empty() => {}
+            Some(required) => {
+                for column in columns {
+                    if !required.contains(&column) {
+                        required.push(column);
+                    }
+                }
+            }
+            None => inner.columns = Some(columns),
+        }
+        Some((inner, Validate { schema: self.schema.clone(), range }))
+    }
+}
+
+/// Get the adjacent vertices along the valid edges, which are fetched from the inner graph with the
+/// params if any is required on the vertices.
+struct AdjacentStatement {
+    edges: Box<dyn Statement<ID, Edge>>,
+    fetch: Option<(Arc<dyn ReadGraph>, QueryParams)>,
+    limit: Option<usize>,
+}
+
+impl Statement<ID, Vertex> for AdjacentStatement {
+    fn exec(&self, next: ID) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let edges = self.edges.exec(next)?;
+        if let Some((graph, params)) = self.fetch.as_ref() {
+            let ids: Vec<ID> = edges.map(|e| e.get_other_id()).collect();
+            graph.get_vertex(&ids, params)
+        } else {
+            let iter = edges
+                .map(|e| Vertex::new(e.get_other_id(), e.get_other_label().cloned(), DynDetails::Empty));
+            Ok(limit_n!(iter, self.limit))
+        }
+    }
+}
+
+impl ReadGraph for TemporalGraph {
+    fn scan_vertex(
+        &self, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        self.inner.scan_vertex(params)
+    }
+
+    fn index_scan_vertex(
+        &self, label: LabelId, primary_key: &PKV, params: &QueryParams,
+    ) -> GraphProxyResult<Option<Vertex>> {
+        self.inner
+            .index_scan_vertex(label, primary_key, params)
+    }
+
+    fn scan_edge(&self, params: &QueryParams) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        if let Some((inner_params, validate)) = self.edge_params(params) {
+            let iter = self
+                .inner
+                .scan_edge(&inner_params)?
+                .filter_map(move |e| validate.edge(e));
+            Ok(limit_n!(iter, params.limit))
+        } else {
+            self.inner.scan_edge(params)
+        }
+    }
+
+    fn get_vertex(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        self.inner.get_vertex(ids, params)
+    }
+
+    fn get_edge(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        if let Some((inner_params, validate)) = self.edge_params(params) {
+            let iter = self
+                .inner
+                .get_edge(ids, &inner_params)?
+                .filter_map(move |e| validate.edge(e));
+            Ok(limit_n!(iter, params.limit))
+        } else {
+            self.inner.get_edge(ids, params)
+        }
+    }
+
+    fn get_edge_by_id(&self, edge_ref: &EdgeRef, params: &QueryParams) -> GraphProxyResult<Option<Edge>> {
+        if let Some((inner_params, validate)) = self.edge_params(params) {
+            let edge = self
+                .inner
+                .get_edge_by_id(edge_ref, &inner_params)?;
+            Ok(edge.and_then(|e| validate.edge(e)))
+        } else {
+            self.inner.get_edge_by_id(edge_ref, params)
+        }
+    }
+
+    fn prepare_explore_vertex(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Vertex>>> {
+        if params.valid_time.is_none() {
+            return self
+                .inner
+                .prepare_explore_vertex(direction, params);
+        }
+        // the labels of the params are those of the edges, while the others apply to the vertices
+        let edge_params = QueryParams {
+            labels: params.labels.clone(),
+            valid_time: params.valid_time,
+            ..QueryParams::default()
+        };
+        let edges = self.prepare_explore_edge(direction, &edge_params)?;
+        let fetch = if params.filter.is_some() || params.columns.is_some() {
+            let mut vertex_params = params.clone();
+            vertex_params.labels = vec![];
+            vertex_params.valid_time = None;
+            Some((self.inner.clone(), vertex_params))
+        } else {
+            None
+        };
+        Ok(Box::new(AdjacentStatement { edges, fetch, limit: params.limit }))
+    }
+
+    fn prepare_explore_edge(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Edge>>> {
+        if let Some((inner_params, validate)) = self.edge_params(params) {
+            let inner = self
+                .inner
+                .prepare_explore_edge(direction, &inner_params)?;
+            Ok(Box::new(FilterMapStatement {
+                inner,
+                func: Arc::new(move |e| validate.edge(e)),
+                limit: params.limit,
+            }))
+        } else {
+            self.inner
+                .prepare_explore_edge(direction, params)
+        }
+    }
+
+    fn count_vertex(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        self.inner.count_vertex(params)
+    }
+
+    fn count_edge(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        if params.valid_time.is_some() {
+            Ok(self.scan_edge(params)?.count() as u64)
+        } else {
+            self.inner.count_edge(params)
+        }
+    }
+
+    fn get_primary_key(&self, id: &ID) -> GraphProxyResult<Option<PKV>> {
+        self.inner.get_primary_key(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::{HashMap as AHashMap, HashMapExt};
+
+    use super::*;
+    use crate::apis::test_graph::TestGraph;
+
+    const KNOWS: LabelId = 0;
+    const CREATED: LabelId = 1;
+
+    fn edge(id: ID, label: LabelId, dst: ID, valid_from: Option<i64>, valid_to: Option<i64>) -> Edge {
+        let mut props = AHashMap::new();
+        if let Some(valid_from) = valid_from {
+            props.insert("valid_from".into(), valid_from.into());
+        }
+        if let Some(valid_to) = valid_to {
+            props.insert("valid_to".into(), valid_to.into());
+        }
+        Edge::new(id, Some(label), 0, dst, DynDetails::new(props))
+    }
+
+    // knows 1 in [10, 20), knows 2 since 15, knows 3 till 10, created 4 without the bounds, all from
+    // vertex 0
+    fn temporal_graph() -> TemporalGraph {
+        let vertices = [0, 11, 12, 13, 14]
+            .iter()
+            .map(|id| Vertex::new(*id, None, DynDetails::new(AHashMap::new())))
+            .collect();
+        let edges = vec![
+            edge(1, KNOWS, 11, Some(10), Some(20)),
+            edge(2, KNOWS, 12, Some(15), None),
+            edge(3, KNOWS, 13, None, Some(10)),
+            edge(4, CREATED, 14, None, None),
+        ];
+        let graph = TestGraph::new(vertices, edges);
+        let mut schema = TemporalSchema::new();
+        schema.add_edge_label(KNOWS, "valid_from".into(), "valid_to".into());
+        TemporalGraph::new(Arc::new(graph), Arc::new(schema))
+    }
+
+    fn params(valid_time: Option<TimeRange>) -> QueryParams {
+        QueryParams { valid_time, ..QueryParams::default() }
+    }
+
+    fn ids<I: Iterator<Item = E>, E: GraphElement>(iter: I) -> Vec<ID> {
+        iter.map(|e| e.id()).collect()
+    }
+
+    #[test]
+    fn time_range_test() {
+        assert!(TimeRange::as_of(10).overlaps(Some(10), Some(20)));
+        assert!(!TimeRange::as_of(20).overlaps(Some(10), Some(20)));
+        assert!(TimeRange::between(0, 10).overlaps(Some(10), None));
+        assert!(!TimeRange::between(0, 9).overlaps(Some(10), None));
+        assert!(TimeRange::as_of(i64::MIN).overlaps(None, None));
+    }
+
+    #[test]
+    fn scan_temporal_edge_test() {
+        let graph = temporal_graph();
+        assert_eq!(ids(graph.scan_edge(&params(None)).unwrap()), vec![1, 2, 3, 4]);
+        assert_eq!(
+            ids(graph
+                .scan_edge(&params(Some(TimeRange::as_of(5))))
+                .unwrap()),
+            vec![3, 4]
+        );
+        assert_eq!(
+            ids(graph
+                .scan_edge(&params(Some(TimeRange::as_of(15))))
+                .unwrap()),
+            vec![1, 2, 4]
+        );
+        assert_eq!(
+            ids(graph
+                .scan_edge(&params(Some(TimeRange::between(5, 12))))
+                .unwrap()),
+            vec![1, 3, 4]
+        );
+        assert_eq!(
+            graph
+                .count_edge(&params(Some(TimeRange::as_of(25))))
+                .unwrap(),
+            2
+        );
+        // the limit applies to the valid edges
+        let mut limited = params(Some(TimeRange::as_of(5)));
+        limited.limit = Some(1);
+        assert_eq!(ids(graph.scan_edge(&limited).unwrap()), vec![3]);
+    }
+
+    #[test]
+    fn explore_temporal_edge_test() {
+        let graph = temporal_graph();
+        let stmt = graph
+            .prepare_explore_edge(Direction::Out, &params(Some(TimeRange::as_of(15))))
+            .unwrap();
+        assert_eq!(ids(stmt.exec(0).unwrap()), vec![1, 2, 4]);
+        // the vertices adjacent along the valid edges
+        let stmt = graph
+            .prepare_explore_vertex(Direction::Out, &params(Some(TimeRange::as_of(5))))
+            .unwrap();
+        assert_eq!(ids(stmt.exec(0).unwrap()), vec![13, 14]);
+        let mut fetched = params(Some(TimeRange::as_of(5)));
+        fetched.columns = Some(vec![]);
+        let stmt = graph
+            .prepare_explore_vertex(Direction::Out, &fetched)
+            .unwrap();
+        assert_eq!(ids(stmt.exec(0).unwrap()), vec![13, 14]);
+    }
+}
@@ -0,0 +1,211 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! An in-memory graph of the given vertices and edges for the tests of the graph wrappers and the
+//! operators, which honors the labels, the predicate, the columns and the limit of the params, while
+//! none of its vertices has a primary key.
+
+use ahash::{HashMap, HashMapExt};
+use ir_common::LabelId;
+
+use crate::apis::graph::PKV;
+use crate::apis::{
+    from_fn, Details, Direction, DynDetails, Edge, Element, GraphElement, QueryParams, ReadGraph,
+    Statement, Vertex, ID,
+};
+use crate::utils::expr::eval::Context;
+use crate::utils::expr::eval_pred::EvalPred;
+use crate::{limit_n, GraphProxyResult};
+
+#[derive(Clone, Default)]
+pub struct TestGraph {
+    pub vertices: Vec<Vertex>,
+    pub edges: Vec<Edge>,
+}
+
+impl TestGraph {
+    pub fn new(vertices: Vec<Vertex>, edges: Vec<Edge>) -> Self {
+        TestGraph { vertices, edges }
+    }
+}
+
+/// The properties of the columns only if given, otherwise all of them.
+fn project(details: &DynDetails, params: &QueryParams) -> DynDetails {
+    match params.columns.as_ref() {
+        Some(columns) if !columns.is_empty() => {
+            let mut props = HashMap::new();
+            for column in columns {
+                if let Some(value) = details
+                    .get_property(column)
+                    .and_then(|v| v.try_to_owned())
+                {
+                    props.insert(column.clone(), value);
+                }
+            }
+            DynDetails::new(props)
+        }
+        _ => details.clone(),
+    }
+}
+
+fn project_vertex(vertex: &Vertex, params: &QueryParams) -> Vertex {
+    Vertex::new(vertex.id(), vertex.label(), project(vertex.get_details(), params))
+}
+
+fn project_edge(edge: &Edge, from_src: bool, params: &QueryParams) -> Edge {
+    let details = project(edge.get_details(), params);
+    let mut projected =
+        Edge::with_from_src(edge.id(), edge.label(), edge.src_id, edge.dst_id, from_src, details);
+    projected.src_label = edge.src_label;
+    projected.dst_label = edge.dst_label;
+    projected
+}
+
+/// Whether the element is of the labels, or any label if empty, and satisfies the predicate.
+fn is_matched<E: Element + GraphElement + Context<E>>(
+    e: &E, labels: &[LabelId], params: &QueryParams,
+) -> bool {
+    (labels.is_empty() || e.label().map_or(false, |l| labels.contains(&l)))
+        && params.filter.as_ref().map_or(true, |filter| {
+            filter
+                .eval_bool::<E, E>(Some(e))
+                .unwrap_or(false)
+        })
+}
+
+/// The edges adjacent to the vertex in the direction, of the labels of the params.
+fn adjacent_edges(edges: &[Edge], id: ID, direction: Direction, params: &QueryParams) -> Vec<Edge> {
+    let mut adjacent = vec![];
+    if let Direction::Out | Direction::Both = direction {
+        for edge in edges.iter().filter(|e| e.src_id == id) {
+            adjacent.push(project_edge(edge, true, params));
+        }
+    }
+    if let Direction::In | Direction::Both = direction {
+        for edge in edges.iter().filter(|e| e.dst_id == id) {
+            adjacent.push(project_edge(edge, false, params));
+        }
+    }
+    adjacent.retain(|e| is_matched(e, &params.labels, params));
+    adjacent
+}
+
+impl ReadGraph for TestGraph {
+    fn scan_vertex(
+        &self, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let vertices: Vec<Vertex> = self
+            .vertices
+            .iter()
+            .map(|v| project_vertex(v, params))
+            .filter(|v| is_matched(v, &params.labels, params))
+            .collect();
+        Ok(limit_n!(vertices.into_iter(), params.limit))
+    }
+
+    fn index_scan_vertex(&self, _: LabelId, _: &PKV, _: &QueryParams) -> GraphProxyResult<Option<Vertex>> {
+        // none of the vertices is indexed by a primary key
+        Ok(None)
+    }
+
+    fn scan_edge(&self, params: &QueryParams) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        let edges: Vec<Edge> = self
+            .edges
+            .iter()
+            .map(|e| project_edge(e, true, params))
+            .filter(|e| is_matched(e, &params.labels, params))
+            .collect();
+        Ok(limit_n!(edges.into_iter(), params.limit))
+    }
+
+    fn get_vertex(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let vertices: Vec<Vertex> = ids
+            .iter()
+            .filter_map(|id| self.vertices.iter().find(|v| v.id() == *id))
+            .map(|v| project_vertex(v, params))
+            .filter(|v| is_matched(v, &params.labels, params))
+            .collect();
+        Ok(limit_n!(vertices.into_iter(), params.limit))
+    }
+
+    fn get_edge(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        let edges: Vec<Edge> = ids
+            .iter()
+            .filter_map(|id| self.edges.iter().find(|e| e.id() == *id))
+            .map(|e| project_edge(e, true, params))
+            .filter(|e| is_matched(e, &params.labels, params))
+            .collect();
+        Ok(limit_n!(edges.into_iter(), params.limit))
+    }
+
+    fn prepare_explore_vertex(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Vertex>>> {
+        // the labels of the params are those of the edges, while the others apply to the vertices
+        let edge_params = QueryParams { labels: params.labels.clone(), ..QueryParams::default() };
+        let graph = self.clone();
+        let params = params.clone();
+        Ok(from_fn(move |id: ID| {
+            let vertices: Vec<Vertex> = adjacent_edges(&graph.edges, id, direction, &edge_params)
+                .into_iter()
+                .map(|e| {
+                    match graph
+                        .vertices
+                        .iter()
+                        .find(|v| v.id() == e.get_other_id())
+                    {
+                        Some(vertex) => project_vertex(vertex, &params),
+                        None => {
+                            Vertex::new(e.get_other_id(), e.get_other_label().cloned(), DynDetails::Empty)
+                        }
+                    }
+                })
+                .filter(|v| is_matched(v, &[], &params))
+                .collect();
+            let iter: Box<dyn Iterator<Item = Vertex> + Send> =
+                limit_n!(vertices.into_iter(), params.limit);
+            Ok(iter)
+        }))
+    }
+
+    fn prepare_explore_edge(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Edge>>> {
+        let edges = self.edges.clone();
+        let params = params.clone();
+        Ok(from_fn(move |id: ID| {
+            let edges = adjacent_edges(&edges, id, direction, &params);
+            let iter: Box<dyn Iterator<Item = Edge> + Send> = limit_n!(edges.into_iter(), params.limit);
+            Ok(iter)
+        }))
+    }
+
+    fn count_vertex(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        Ok(self.scan_vertex(params)?.count() as u64)
+    }
+
+    fn count_edge(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        Ok(self.scan_edge(params)?.count() as u64)
+    }
+
+    fn get_primary_key(&self, _: &ID) -> GraphProxyResult<Option<PKV>> {
+        // none of the vertices has a primary key
+        Ok(None)
+    }
+}
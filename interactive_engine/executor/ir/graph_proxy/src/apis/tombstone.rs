@@ -0,0 +1,582 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Soft deletes: with `SoftDelete` enabled, dropping a vertex or an edge writes a tombstone, i.e., the
+//! time of the deletion in milliseconds as the tombstone property of the element, instead of removing
+//! it from the storage. Dropping a vertex writes the tombstones of its edges as well.
+//!
+//! The graph read through `get_graph()` hides the elements with the tombstones, including the edges
+//! and the adjacent vertices of the expands, while the jobs executed with the deleted elements
+//! visible, i.e., with the `include-deleted` metadata of the request, read them as the others.
+//!
+//! Only the users granted to read the deleted elements can submit the jobs including them, see
+//! `AccessPolicy` of the runtime.
+//!
+//! The elements are removed from the storage once their tombstones are older than the retention, by
+//! `purge_deleted()`, which is scheduled by `start_purge()` on every server, where a job of the local
+//! workers purges the elements of the partitions of the server.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ahash::{HashMap as AHashMap, HashMapExt};
+use ir_common::generated::common as common_pb;
+use ir_common::{LabelId, NameOrId};
+use pegasus::api::{Map, Sink};
+use pegasus::{JobConf, ServerConf};
+
+use crate::apis::graph::PKV;
+use crate::apis::read_graph::{get_stored_graph, FilterMapStatement};
+use crate::apis::{
+    get_write_graph, Direction, DynDetails, Edge, EdgeRef, GraphElement, Mutation, PropKey, QueryParams,
+    ReadGraph, Statement, Vertex, WriteGraphProxy, ID,
+};
+use crate::utils::expr::eval::Operand;
+use crate::utils::expr::eval_pred::{PEvaluator, Predicates, UnaryPredicate};
+use crate::{limit_n, GraphProxyError, GraphProxyResult};
+
+/// The number of the elements to remove in a batch when purging.
+const PURGE_BATCH_SIZE: usize = 1024;
+
+/// The configuration of the soft deletes.
+#[derive(Clone, Debug)]
+pub struct SoftDelete {
+    /// The property of the tombstones
+    pub property: NameOrId,
+    /// How long the deleted elements are kept before purged
+    pub retention: Duration,
+}
+
+impl SoftDelete {
+    pub fn new(property: NameOrId, retention: Duration) -> Self {
+        SoftDelete { property, retention }
+    }
+
+    /// The tombstone to write into the element deleted at the time.
+    pub fn tombstone(&self, deleted_at: i64) -> DynDetails {
+        let mut props = AHashMap::new();
+        props.insert(self.property.clone(), deleted_at.into());
+        DynDetails::new(props)
+    }
+
+    /// Whether the element is deleted, i.e., has the tombstone.
+    pub fn is_deleted<E: GraphElement>(&self, element: &E) -> bool {
+        deleted_at(element, &self.property).is_some()
+    }
+}
+
+fn deleted_at<E: GraphElement>(element: &E, property: &NameOrId) -> Option<i64> {
+    element
+        .get_property(property)
+        .and_then(|value| value.try_to_owned())
+        .and_then(|value| value.as_i64().ok())
+}
+
+/// The current time in milliseconds, as the time of the deletion.
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+lazy_static! {
+    static ref SOFT_DELETE: RwLock<Option<Arc<SoftDelete>>> = RwLock::new(None);
+    /// The jobs reading the deleted elements, with the number of local workers binding them.
+    static ref JOBS_INCLUDING_DELETED: RwLock<HashMap<u64, usize>> = RwLock::new(HashMap::new());
+}
+
+/// Enable the soft deletes with the configuration, or disable them if `None`, where the elements
+/// already deleted softly remain in the storage until they are dropped again.
+pub fn enable_soft_delete(soft_delete: Option<SoftDelete>) {
+    *SOFT_DELETE
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = soft_delete.map(Arc::new);
+}
+
+pub fn get_soft_delete() -> Option<Arc<SoftDelete>> {
+    SOFT_DELETE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Make the deleted elements visible to the job, until all the returned bindings of the job are
+/// dropped, e.g., with the workers of the job as their resources.
+pub fn bind_include_deleted(job_id: u64) -> IncludeDeletedBinding {
+    *JOBS_INCLUDING_DELETED
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(job_id)
+        .or_insert(0) += 1;
+    IncludeDeletedBinding { job_id }
+}
+
+/// Whether the job of the current worker reads the deleted elements.
+fn is_including_deleted() -> bool {
+    let jobs = JOBS_INCLUDING_DELETED
+        .read()
+        .unwrap_or_else(|e| e.into_inner());
+    if jobs.is_empty() {
+        return false;
+    }
+    pegasus::get_current_worker_checked()
+        .map(|worker| jobs.contains_key(&worker.job_id))
+        .unwrap_or(false)
+}
+
+/// The graph registered with the deleted elements hidden, regardless of the view and the visibility of
+/// the deleted elements to the job of the current worker, e.g., to find the edges of the vertices to
+/// delete.
+pub fn get_undeleted_graph() -> Option<Arc<dyn ReadGraph>> {
+    get_stored_graph().map(|graph| match get_soft_delete() {
+        Some(soft_delete) => Arc::new(TombstoneGraph::new(graph, soft_delete)) as Arc<dyn ReadGraph>,
+        None => graph,
+    })
+}
+
+/// The soft deletes hiding the deleted elements from the job of the current worker, if any.
+pub(crate) fn get_hiding_soft_delete() -> Option<Arc<SoftDelete>> {
+    get_soft_delete().filter(|_| !is_including_deleted())
+}
+
+pub struct IncludeDeletedBinding {
+    job_id: u64,
+}
+
+impl Drop for IncludeDeletedBinding {
+    fn drop(&mut self) {
+        let mut jobs = JOBS_INCLUDING_DELETED
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = jobs.get_mut(&self.job_id) {
+            *count -= 1;
+            if *count == 0 {
+                jobs.remove(&self.job_id);
+            }
+        }
+    }
+}
+
+/// The graph hiding the deleted elements.
+pub struct TombstoneGraph {
+    inner: Arc<dyn ReadGraph>,
+    soft_delete: Arc<SoftDelete>,
+}
+
+impl TombstoneGraph {
+    pub fn new(inner: Arc<dyn ReadGraph>, soft_delete: Arc<SoftDelete>) -> Self {
+        TombstoneGraph { inner, soft_delete }
+    }
+
+    /// The params to read the elements from the inner graph, with their tombstones required, and the
+    /// limit applied after the deleted ones are hidden.
+    fn inner_params(&self, params: &QueryParams) -> QueryParams {
+        let mut inner = params.clone();
+        inner.limit = None;
+        let property = &self.soft_delete.property;
+        match inner.columns.as_mut() {
+            // all the properties, including the tombstone
+            Some(columns) if columns.is_empty() => {}
+            Some(columns) => {
+                if !columns.contains(property) {
+                    columns.push(property.clone());
+                }
+            }
+            None => inner.columns = Some(vec![property.clone()]),
+        }
+        inner
+    }
+
+    /// The params to count the elements not deleted by the inner graph, with the absence of the
+    /// tombstone conjoined to the predicate, which the storage may evaluate instead of scanning the
+    /// elements into the wrapper; or `None` if the predicate cannot be conjoined.
+    fn count_params(&self, params: &QueryParams) -> Option<QueryParams> {
+        let undeleted = Predicates::Unary(UnaryPredicate {
+            operand: Operand::Var {
+                tag: None,
+                prop_key: Some(PropKey::Key(self.soft_delete.property.clone())),
+            },
+            cmp: common_pb::Logical::Isnull,
+        });
+        let filter = match params.filter.as_deref() {
+            None => undeleted,
+            Some(PEvaluator::Predicates(predicates)) => predicates.clone().and(undeleted),
+            Some(PEvaluator::General(_)) => return None,
+        };
+        let mut inner = self.inner_params(params);
+        inner.filter = Some(Arc::new(PEvaluator::Predicates(filter)));
+        Some(inner)
+    }
+
+    fn admit<E: GraphElement + 'static>(&self) -> impl Fn(E) -> Option<E> + Send + Sync + 'static {
+        let soft_delete = self.soft_delete.clone();
+        move |e| if soft_delete.is_deleted(&e) { None } else { Some(e) }
+    }
+}
+
+/// The adjacent vertices explored by the inner graph, e.g., including the ones in the partitions of
+/// the other servers, along the edges not deleted only, which hides the deleted vertices as well, as
+/// their edges are deleted with them.
+struct AdjacentStatement {
+    edges: Box<dyn Statement<ID, Edge>>,
+    vertices: Box<dyn Statement<ID, Vertex>>,
+    soft_delete: Arc<SoftDelete>,
+    limit: Option<usize>,
+}
+
+impl Statement<ID, Vertex> for AdjacentStatement {
+    fn exec(&self, next: ID) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        // the number of the edges not deleted to each of the adjacent vertices
+        let mut undeleted = AHashMap::new();
+        for edge in self.edges.exec(next)? {
+            *undeleted
+                .entry(edge.get_other_id())
+                .or_insert(0_usize) += 1;
+        }
+        let soft_delete = self.soft_delete.clone();
+        let iter = self
+            .vertices
+            .exec(next)?
+            .filter(move |v| match undeleted.get_mut(&v.id()) {
+                Some(count) if *count > 0 && !soft_delete.is_deleted(v) => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            });
+        Ok(limit_n!(iter, self.limit))
+    }
+}
+
+impl ReadGraph for TombstoneGraph {
+    fn scan_vertex(
+        &self, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let iter = self
+            .inner
+            .scan_vertex(&self.inner_params(params))?
+            .filter_map(self.admit());
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn index_scan_vertex(
+        &self, label: LabelId, primary_key: &PKV, params: &QueryParams,
+    ) -> GraphProxyResult<Option<Vertex>> {
+        let vertex = self
+            .inner
+            .index_scan_vertex(label, primary_key, &self.inner_params(params))?;
+        Ok(vertex.and_then(self.admit()))
+    }
+
+    fn scan_edge(&self, params: &QueryParams) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        let iter = self
+            .inner
+            .scan_edge(&self.inner_params(params))?
+            .filter_map(self.admit());
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn get_vertex(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let iter = self
+            .inner
+            .get_vertex(ids, &self.inner_params(params))?
+            .filter_map(self.admit());
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn get_edge(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        let iter = self
+            .inner
+            .get_edge(ids, &self.inner_params(params))?
+            .filter_map(self.admit());
+        Ok(limit_n!(iter, params.limit))
+    }
+
+    fn get_edge_by_id(&self, edge_ref: &EdgeRef, params: &QueryParams) -> GraphProxyResult<Option<Edge>> {
+        let edge = self
+            .inner
+            .get_edge_by_id(edge_ref, &self.inner_params(params))?;
+        Ok(edge.and_then(self.admit()))
+    }
+
+    fn prepare_explore_vertex(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Vertex>>> {
+        // the labels of the params are those of the edges, while the others apply to the vertices
+        let edge_params = QueryParams { labels: params.labels.clone(), ..QueryParams::default() };
+        let edges = self.prepare_explore_edge(direction, &edge_params)?;
+        let vertices = self
+            .inner
+            .prepare_explore_vertex(direction, &self.inner_params(params))?;
+        Ok(Box::new(AdjacentStatement {
+            edges,
+            vertices,
+            soft_delete: self.soft_delete.clone(),
+            limit: params.limit,
+        }))
+    }
+
+    fn prepare_explore_edge(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Edge>>> {
+        let inner = self
+            .inner
+            .prepare_explore_edge(direction, &self.inner_params(params))?;
+        Ok(Box::new(FilterMapStatement { inner, func: Arc::new(self.admit()), limit: params.limit }))
+    }
+
+    fn count_vertex(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        match self.count_params(params) {
+            Some(inner) => self.inner.count_vertex(&inner),
+            None => Ok(self.scan_vertex(params)?.count() as u64),
+        }
+    }
+
+    fn count_edge(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        match self.count_params(params) {
+            Some(inner) => self.inner.count_edge(&inner),
+            None => Ok(self.scan_edge(params)?.count() as u64),
+        }
+    }
+
+    fn get_primary_key(&self, id: &ID) -> GraphProxyResult<Option<PKV>> {
+        self.inner.get_primary_key(id)
+    }
+}
+
+/// Remove the elements deleted before `now - retention` from the storage, the edges before the
+/// vertices, in the batches as they are scanned, and return the number of the elements removed.
+pub fn purge_deleted(
+    graph: &dyn ReadGraph, write_graph: &Mutex<dyn WriteGraphProxy>, soft_delete: &SoftDelete, now: i64,
+) -> GraphProxyResult<usize> {
+    let expired_at = now.saturating_sub(soft_delete.retention.as_millis() as i64);
+    let is_expired =
+        |deleted_at: Option<i64>| deleted_at.map_or(false, |deleted_at| deleted_at <= expired_at);
+    let params =
+        QueryParams { columns: Some(vec![soft_delete.property.clone()]), ..QueryParams::default() };
+    let edges = graph
+        .scan_edge(&params)?
+        .filter(|e| is_expired(deleted_at(e, &soft_delete.property)))
+        .map(Mutation::DropEdge);
+    let mut purged = drop_in_batches(write_graph, edges)?;
+    let vertices = graph
+        .scan_vertex(&params)?
+        .filter(|v| is_expired(deleted_at(v, &soft_delete.property)))
+        .map(Mutation::DropVertex);
+    purged += drop_in_batches(write_graph, vertices)?;
+    Ok(purged)
+}
+
+fn drop_in_batches<I: Iterator<Item = Mutation>>(
+    write_graph: &Mutex<dyn WriteGraphProxy>, mut mutations: I,
+) -> GraphProxyResult<usize> {
+    let mut dropped = 0;
+    loop {
+        let batch: Vec<Mutation> = mutations
+            .by_ref()
+            .take(PURGE_BATCH_SIZE)
+            .collect();
+        if batch.is_empty() {
+            return Ok(dropped);
+        }
+        let results = write_graph
+            .lock()
+            .map_err(|e| GraphProxyError::write_graph_error(&format!("{:?}", e)))?
+            .mutate(batch)?;
+        for result in results {
+            match result {
+                Ok(_) => dropped += 1,
+                Err(e) => warn!("failed to purge the deleted element: {}", e),
+            }
+        }
+    }
+}
+
+/// Purge the deleted elements of the partitions of this server by a job of the local workers, each of
+/// which scans the partitions assigned to it, and return the number of the elements removed.
+fn purge_local(workers: u32) -> GraphProxyResult<usize> {
+    let mut conf = JobConf::new("purge_deleted");
+    conf.set_workers(workers);
+    conf.reset_servers(ServerConf::Local);
+    let now = now_millis();
+    let results = pegasus::run(conf, || {
+        move |input, output| {
+            input
+                .input_from(Some(now))?
+                .map(|now| match (get_stored_graph(), get_write_graph(), get_soft_delete()) {
+                    (Some(graph), Some(write_graph), Some(soft_delete)) => {
+                        Ok(purge_deleted(graph.as_ref(), write_graph.as_ref(), &soft_delete, now)? as u64)
+                    }
+                    _ => Ok(0_u64),
+                })?
+                .sink_into(output)
+        }
+    })
+    .map_err(|e| GraphProxyError::write_graph_error(&format!("submit purge job failed: {:?}", e)))?;
+    let mut purged = 0;
+    for result in results {
+        purged += result.map_err(|e| GraphProxyError::write_graph_error(&e.to_string()))? as usize;
+    }
+    Ok(purged)
+}
+
+/// The handle of the scheduled purge, which stops the purge when dropped.
+pub struct PurgeHandle {
+    _stop: Sender<()>,
+}
+
+/// Purge the deleted elements of the partitions of this server by a job of the local workers in every
+/// interval, while the soft deletes are enabled, which is started on every server.
+pub fn start_purge(interval: Duration, workers: u32) -> PurgeHandle {
+    let (stop, stopped) = channel::<()>();
+    std::thread::spawn(move || loop {
+        match stopped.recv_timeout(interval) {
+            Err(RecvTimeoutError::Timeout) => {}
+            _ => break,
+        }
+        if get_soft_delete().is_none() {
+            continue;
+        }
+        match purge_local(workers) {
+            Ok(purged) => info!("purged {} deleted elements", purged),
+            Err(e) => error!("failed to purge the deleted elements: {}", e),
+        }
+    });
+    PurgeHandle { _stop: stop }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::test_graph::TestGraph;
+    use crate::apis::Mutated;
+
+    const PERSON: LabelId = 0;
+    const KNOWS: LabelId = 1;
+    const DELETED_AT: &str = "deleted_at";
+
+    /// A write graph which records the mutations written.
+    #[derive(Default)]
+    struct TestWriteGraph {
+        mutations: Vec<Mutation>,
+    }
+
+    impl WriteGraphProxy for TestWriteGraph {
+        fn add_vertex(&mut self, _: LabelId, _: PKV, _: DynDetails) -> GraphProxyResult<()> {
+            Err(GraphProxyError::unsupported_error("add_vertex() of the test graph"))
+        }
+
+        fn add_edge(
+            &mut self, _: LabelId, _: LabelId, _: PKV, _: LabelId, _: PKV, _: DynDetails,
+        ) -> GraphProxyResult<()> {
+            Err(GraphProxyError::unsupported_error("add_edge() of the test graph"))
+        }
+
+        fn finish(&mut self) -> GraphProxyResult<()> {
+            Ok(())
+        }
+
+        fn mutate(&mut self, mutations: Vec<Mutation>) -> GraphProxyResult<Vec<GraphProxyResult<Mutated>>> {
+            let results = mutations
+                .iter()
+                .map(|_| Ok(Mutated::Dropped))
+                .collect();
+            self.mutations.extend(mutations);
+            Ok(results)
+        }
+    }
+
+    fn tombstone(deleted_at: Option<i64>) -> DynDetails {
+        match deleted_at {
+            Some(deleted_at) => soft_delete().tombstone(deleted_at),
+            None => DynDetails::new(AHashMap::new()),
+        }
+    }
+
+    fn soft_delete() -> SoftDelete {
+        SoftDelete::new(DELETED_AT.into(), Duration::from_millis(100))
+    }
+
+    // person 1, person 2 deleted at 10, person 3 deleted at 200, knows 4 to 1, knows 5 to 2 deleted at 10,
+    // knows 6 to 3
+    fn test_graph() -> TestGraph {
+        TestGraph::new(
+            vec![
+                Vertex::new(1, Some(PERSON), tombstone(None)),
+                Vertex::new(2, Some(PERSON), tombstone(Some(10))),
+                Vertex::new(3, Some(PERSON), tombstone(Some(200))),
+            ],
+            vec![
+                Edge::new(4, Some(KNOWS), 0, 1, tombstone(None)),
+                Edge::new(5, Some(KNOWS), 0, 2, tombstone(Some(10))),
+                Edge::new(6, Some(KNOWS), 0, 3, tombstone(None)),
+            ],
+        )
+    }
+
+    fn ids<I: Iterator<Item = E>, E: GraphElement>(iter: I) -> Vec<ID> {
+        iter.map(|e| e.id()).collect()
+    }
+
+    #[test]
+    fn hide_deleted_test() {
+        let graph = TombstoneGraph::new(Arc::new(test_graph()), Arc::new(soft_delete()));
+        let params = QueryParams::default();
+        assert_eq!(ids(graph.scan_vertex(&params).unwrap()), vec![1]);
+        assert_eq!(ids(graph.scan_edge(&params).unwrap()), vec![4, 6]);
+        assert_eq!(graph.count_vertex(&params).unwrap(), 1);
+        assert_eq!(graph.count_edge(&params).unwrap(), 2);
+        assert_eq!(ids(graph.get_vertex(&[1, 2], &params).unwrap()), vec![1]);
+        // the vertices adjacent along the edges not deleted, which are not deleted either
+        let stmt = graph
+            .prepare_explore_vertex(Direction::Out, &params)
+            .unwrap();
+        assert_eq!(ids(stmt.exec(0).unwrap()), vec![1]);
+    }
+
+    #[test]
+    fn purge_deleted_test() {
+        let graph = test_graph();
+        let write_graph = Mutex::new(TestWriteGraph::default());
+        let purged = purge_deleted(&graph, &write_graph, &soft_delete(), 150).unwrap();
+        assert_eq!(purged, 2);
+        let mutations = write_graph.into_inner().unwrap().mutations;
+        assert!(matches!(&mutations[0], Mutation::DropEdge(e) if e.id() == 5));
+        assert!(matches!(&mutations[1], Mutation::DropVertex(v) if v.id() == 2));
+    }
+
+    #[test]
+    fn include_deleted_test() {
+        let first = bind_include_deleted(1);
+        let second = bind_include_deleted(1);
+        drop(first);
+        assert!(JOBS_INCLUDING_DELETED
+            .read()
+            .unwrap()
+            .contains_key(&1));
+        drop(second);
+        assert!(!JOBS_INCLUDING_DELETED
+            .read()
+            .unwrap()
+            .contains_key(&1));
+    }
+}
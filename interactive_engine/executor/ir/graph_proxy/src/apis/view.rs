@@ -0,0 +1,734 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Graph views: named subgraphs of the graph, each of which is a subset of the vertex and edge
+//! labels, with predicates on the vertices and edges, and optionally subsets of their properties.
+//!
+//! A job executed against a view, i.e., with the `graph-view` metadata of the request, has the view
+//! bound to its id, and `get_graph()` returns the graph read through the view of the job of the
+//! current worker, so that the scans and expands of any query see the elements in the view only.
+//!
+//! The predicates of a query are evaluated by the view on the visible properties, rather than
+//! pushed down into the storage, so that a query can't probe the hidden properties. The edges are
+//! constrained by their labels, the labels of their end vertices if known, and the predicate on
+//! the edges, while the predicate on the vertices applies to the vertices read, including the
+//! adjacent vertices of an expand, which are fetched from the storage to be checked.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::{Arc, RwLock};
+
+use ahash::HashMap as AHashMap;
+use dyn_type::Object;
+use ir_common::error::ParsePbResult;
+use ir_common::generated::common as common_pb;
+use ir_common::{LabelId, NameOrId};
+use pegasus_common::downcast::*;
+use pegasus_common::impl_as_any;
+
+use crate::apis::graph::PKV;
+use crate::apis::read_graph::FilterMapStatement;
+use crate::apis::{
+    from_fn, Details, Direction, DynDetails, Edge, EdgeRef, GraphElement, PropertyValue, QueryParams,
+    ReadGraph, Statement, Vertex, ID,
+};
+use crate::limit_n;
+use crate::utils::expr::eval_pred::{EvalPred, PEvaluator};
+use crate::GraphProxyResult;
+
+/// A subgraph of the graph, where empty labels stand for all the labels, and `None` columns stand
+/// for all the properties.
+#[derive(Debug, Default)]
+pub struct GraphView {
+    vertex_labels: Vec<LabelId>,
+    edge_labels: Vec<LabelId>,
+    vertex_filter: Option<Arc<PEvaluator>>,
+    edge_filter: Option<Arc<PEvaluator>>,
+    vertex_columns: Option<Arc<Vec<NameOrId>>>,
+    edge_columns: Option<Arc<Vec<NameOrId>>>,
+}
+
+impl GraphView {
+    pub fn new() -> Self {
+        GraphView::default()
+    }
+
+    pub fn with_vertex_labels(mut self, labels: Vec<LabelId>) -> Self {
+        self.vertex_labels = labels;
+        self
+    }
+
+    pub fn with_edge_labels(mut self, labels: Vec<LabelId>) -> Self {
+        self.edge_labels = labels;
+        self
+    }
+
+    pub fn with_vertex_filter(mut self, filter: common_pb::Expression) -> ParsePbResult<Self> {
+        self.vertex_filter = Some(Arc::new(filter.try_into()?));
+        Ok(self)
+    }
+
+    pub fn with_edge_filter(mut self, filter: common_pb::Expression) -> ParsePbResult<Self> {
+        self.edge_filter = Some(Arc::new(filter.try_into()?));
+        Ok(self)
+    }
+
+    /// Only the given properties of the vertices are visible.
+    pub fn with_vertex_columns(mut self, columns: Vec<NameOrId>) -> Self {
+        self.vertex_columns = Some(Arc::new(columns));
+        self
+    }
+
+    /// Only the given properties of the edges are visible.
+    pub fn with_edge_columns(mut self, columns: Vec<NameOrId>) -> Self {
+        self.edge_columns = Some(Arc::new(columns));
+        self
+    }
+
+    /// Whether a vertex of the graph may be out of the view other than by its label.
+    fn filters_vertices(&self) -> bool {
+        self.vertex_filter.is_some()
+    }
+
+    /// Whether an edge of the graph may be out of the view other than by its label.
+    fn filters_edges(&self) -> bool {
+        self.edge_filter.is_some() || !self.vertex_labels.is_empty()
+    }
+}
+
+lazy_static! {
+    static ref VIEWS: RwLock<HashMap<String, Arc<GraphView>>> = RwLock::new(HashMap::new());
+    /// The views bound to the running jobs, with the number of local workers binding them.
+    static ref JOB_VIEWS: RwLock<HashMap<u64, (Arc<GraphView>, usize)>> = RwLock::new(HashMap::new());
+}
+
+/// Register the view of the name, replacing the one registered before.
+pub fn register_view(name: &str, view: GraphView) {
+    VIEWS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_string(), Arc::new(view));
+}
+
+/// Unregister the view of the name, returning whether it is found. The running jobs keep reading
+/// through the view.
+pub fn unregister_view(name: &str) -> bool {
+    VIEWS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(name)
+        .is_some()
+}
+
+pub fn get_view(name: &str) -> Option<Arc<GraphView>> {
+    VIEWS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+}
+
+/// Bind the view to the job, until all the returned bindings of the job are dropped, e.g., with the
+/// workers of the job as their resources.
+pub fn bind_view(job_id: u64, view: Arc<GraphView>) -> ViewBinding {
+    let mut job_views = JOB_VIEWS
+        .write()
+        .unwrap_or_else(|e| e.into_inner());
+    let binding = job_views
+        .entry(job_id)
+        .or_insert((view.clone(), 0));
+    binding.0 = view;
+    binding.1 += 1;
+    ViewBinding { job_id }
+}
+
+/// The view of the job of the current worker, if any.
+pub(crate) fn get_current_view() -> Option<Arc<GraphView>> {
+    let job_views = JOB_VIEWS
+        .read()
+        .unwrap_or_else(|e| e.into_inner());
+    if job_views.is_empty() {
+        return None;
+    }
+    pegasus::get_current_worker_checked()
+        .and_then(|worker| job_views.get(&worker.job_id))
+        .map(|(view, _)| view.clone())
+}
+
+pub struct ViewBinding {
+    job_id: u64,
+}
+
+impl Drop for ViewBinding {
+    fn drop(&mut self) {
+        let mut job_views = JOB_VIEWS
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(binding) = job_views.get_mut(&self.job_id) {
+            binding.1 -= 1;
+            if binding.1 == 0 {
+                job_views.remove(&self.job_id);
+            }
+        }
+    }
+}
+
+/// The graph read through a view.
+pub struct ViewGraph {
+    inner: Arc<dyn ReadGraph>,
+    view: Arc<GraphView>,
+}
+
+impl ViewGraph {
+    pub fn new(inner: Arc<dyn ReadGraph>, view: Arc<GraphView>) -> Self {
+        ViewGraph { inner, view }
+    }
+
+    /// The params to read the vertices from the inner graph, or `None` if no label of the query is
+    /// in the view.
+    fn vertex_params(&self, params: &QueryParams) -> Option<QueryParams> {
+        let filtered = self.view.filters_vertices() || params.filter.is_some();
+        constrain_params(params, &self.view.vertex_labels, self.view.vertex_columns.as_ref(), filtered)
+    }
+
+    /// The params to read the edges from the inner graph, or `None` if no label of the query is in
+    /// the view.
+    fn edge_params(&self, params: &QueryParams) -> Option<QueryParams> {
+        let filtered = self.view.edge_filter.is_some() || params.filter.is_some();
+        constrain_params(params, &self.view.edge_labels, self.view.edge_columns.as_ref(), filtered)
+    }
+
+    fn admit(&self, params: &QueryParams) -> Admit {
+        Admit { view: self.view.clone(), filter: params.filter.clone() }
+    }
+}
+
+/// Constrain the labels of the query to those of the view, and restrict the columns of the query to
+/// those visible. The limit is applied by the view after the elements are admitted, and so are the
+/// predicates of the query if there are any predicates, which require all the properties.
+fn constrain_params(
+    params: &QueryParams, labels: &[LabelId], columns: Option<&Arc<Vec<NameOrId>>>, filtered: bool,
+) -> Option<QueryParams> {
+    let mut inner = params.clone();
+    inner.labels = constrain_labels(&params.labels, labels)?;
+    inner.limit = None;
+    if filtered {
+        inner.filter = None;
+        inner.columns = Some(vec![]);
+    } else if let Some(visible) = columns {
+        inner.columns = match params.columns.as_ref() {
+            // all the visible properties, where no visible property stands for none of them
+            Some(required) if required.is_empty() => Some(visible.to_vec()).filter(|c| !c.is_empty()),
+            Some(required) => Some(
+                required
+                    .iter()
+                    .filter(|column| visible.contains(column))
+                    .cloned()
+                    .collect(),
+            )
+            .filter(|c: &Vec<NameOrId>| !c.is_empty()),
+            None => None,
+        };
+    }
+    Some(inner)
+}
+
+/// The labels in both the query and the view, or `None` if there is no such label.
+fn constrain_labels(query: &[LabelId], view: &[LabelId]) -> Option<Vec<LabelId>> {
+    if view.is_empty() {
+        Some(query.to_vec())
+    } else if query.is_empty() {
+        Some(view.to_vec())
+    } else {
+        let labels: Vec<LabelId> = query
+            .iter()
+            .filter(|label| view.contains(label))
+            .cloned()
+            .collect();
+        if labels.is_empty() {
+            None
+        } else {
+            Some(labels)
+        }
+    }
+}
+
+#[inline]
+fn has_label(labels: &[LabelId], label: Option<LabelId>) -> bool {
+    labels.is_empty() || label.map_or(false, |label| labels.contains(&label))
+}
+
+/// Admit the elements in the view, hiding their invisible properties, and then filter them by the
+/// predicate of the query.
+#[derive(Clone)]
+struct Admit {
+    view: Arc<GraphView>,
+    filter: Option<Arc<PEvaluator>>,
+}
+
+impl Admit {
+    fn vertex(&self, mut vertex: Vertex) -> Option<Vertex> {
+        if !has_label(&self.view.vertex_labels, vertex.label()) {
+            return None;
+        }
+        if let Some(filter) = self.view.vertex_filter.as_ref() {
+            if !filter.eval_bool(Some(&vertex)).unwrap_or(false) {
+                return None;
+            }
+        }
+        if let Some(columns) = self.view.vertex_columns.as_ref() {
+            hide_properties(vertex.get_details_mut(), columns);
+        }
+        if let Some(filter) = self.filter.as_ref() {
+            if !filter.eval_bool(Some(&vertex)).unwrap_or(false) {
+                return None;
+            }
+        }
+        Some(vertex)
+    }
+
+    fn edge(&self, mut edge: Edge) -> Option<Edge> {
+        if !has_label(&self.view.edge_labels, edge.label()) {
+            return None;
+        }
+        // the end vertices of unknown labels are admitted
+        let vertex_labels = &self.view.vertex_labels;
+        if !vertex_labels.is_empty()
+            && [edge.get_src_label(), edge.get_dst_label()]
+                .iter()
+                .any(|label| label.map_or(false, |label| !vertex_labels.contains(label)))
+        {
+            return None;
+        }
+        if let Some(filter) = self.view.edge_filter.as_ref() {
+            if !filter.eval_bool(Some(&edge)).unwrap_or(false) {
+                return None;
+            }
+        }
+        if let Some(columns) = self.view.edge_columns.as_ref() {
+            hide_properties(edge.get_details_mut(), columns);
+        }
+        if let Some(filter) = self.filter.as_ref() {
+            if !filter.eval_bool(Some(&edge)).unwrap_or(false) {
+                return None;
+            }
+        }
+        Some(edge)
+    }
+}
+
+fn hide_properties(details: &mut DynDetails, columns: &Arc<Vec<NameOrId>>) {
+    if let DynDetails::Empty = details {
+        return;
+    }
+    let inner = std::mem::take(details);
+    *details = DynDetails::lazy(ViewDetails { inner, columns: columns.clone() });
+}
+
+/// ViewDetails wraps the details of a graph element, and shows the visible properties only.
+#[derive(Debug)]
+struct ViewDetails {
+    inner: DynDetails,
+    columns: Arc<Vec<NameOrId>>,
+}
+
+impl_as_any!(ViewDetails);
+
+impl Details for ViewDetails {
+    fn get_property(&self, key: &NameOrId) -> Option<PropertyValue> {
+        if self.columns.contains(key) {
+            self.inner.get_property(key)
+        } else {
+            None
+        }
+    }
+
+    fn get_all_properties(&self) -> Option<AHashMap<NameOrId, Object>> {
+        self.inner
+            .get_all_properties()
+            .map(|mut props| {
+                props.retain(|key, _| self.columns.contains(key));
+                props
+            })
+    }
+
+    fn get_property_keys(&self) -> Option<Vec<NameOrId>> {
+        let keys = match &self.inner {
+            // `get_property_keys()` is not supported by default details, so take its keys directly
+            DynDetails::Default(props) => Some(props.keys().cloned().collect()),
+            _ => self.inner.get_property_keys(),
+        };
+        keys.map(|keys: Vec<NameOrId>| {
+            if keys.is_empty() {
+                // all the properties, of which the visible ones
+                self.columns.to_vec()
+            } else {
+                keys.into_iter()
+                    .filter(|key| self.columns.contains(key))
+                    .collect()
+            }
+        })
+    }
+}
+
+/// Get the adjacent vertices through the admitted edges, and fetch them to be admitted.
+struct FetchVertexStatement {
+    edges: Box<dyn Statement<ID, Edge>>,
+    graph: Arc<dyn ReadGraph>,
+    params: QueryParams,
+    admit: Admit,
+    limit: Option<usize>,
+}
+
+impl Statement<ID, Vertex> for FetchVertexStatement {
+    fn exec(&self, next: ID) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        let ids: Vec<ID> = self
+            .edges
+            .exec(next)?
+            .map(|edge| edge.get_other_id())
+            .collect();
+        let admit = self.admit.clone();
+        let iter = self
+            .graph
+            .get_vertex(&ids, &self.params)?
+            .filter_map(move |v| admit.vertex(v));
+        Ok(limit_n!(iter, self.limit))
+    }
+}
+
+fn empty_statement<O: Send + 'static>() -> Box<dyn Statement<ID, O>> {
+    from_fn(|_: ID| Ok(Box::new(std::iter::empty()) as Box<dyn Iterator<Item = O> + Send>))
+}
+
+impl ReadGraph for ViewGraph {
+    fn scan_vertex(
+        &self, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        if let Some(inner_params) = self.vertex_params(params) {
+            let admit = self.admit(params);
+            let iter = self
+                .inner
+                .scan_vertex(&inner_params)?
+                .filter_map(move |v| admit.vertex(v));
+            Ok(limit_n!(iter, params.limit))
+        } else {
+            Ok(Box::new(std::iter::empty()))
+        }
+    }
+
+    fn index_scan_vertex(
+        &self, label: LabelId, primary_key: &PKV, params: &QueryParams,
+    ) -> GraphProxyResult<Option<Vertex>> {
+        if !has_label(&self.view.vertex_labels, Some(label)) {
+            return Ok(None);
+        }
+        if let Some(inner_params) = self.vertex_params(params) {
+            let vertex = self
+                .inner
+                .index_scan_vertex(label, primary_key, &inner_params)?;
+            Ok(vertex.and_then(|v| self.admit(params).vertex(v)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn scan_edge(&self, params: &QueryParams) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        if let Some(inner_params) = self.edge_params(params) {
+            let admit = self.admit(params);
+            let iter = self
+                .inner
+                .scan_edge(&inner_params)?
+                .filter_map(move |e| admit.edge(e));
+            Ok(limit_n!(iter, params.limit))
+        } else {
+            Ok(Box::new(std::iter::empty()))
+        }
+    }
+
+    fn get_vertex(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Vertex> + Send>> {
+        if let Some(inner_params) = self.vertex_params(params) {
+            let admit = self.admit(params);
+            let iter = self
+                .inner
+                .get_vertex(ids, &inner_params)?
+                .filter_map(move |v| admit.vertex(v));
+            Ok(limit_n!(iter, params.limit))
+        } else {
+            Ok(Box::new(std::iter::empty()))
+        }
+    }
+
+    fn get_edge(
+        &self, ids: &[ID], params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Iterator<Item = Edge> + Send>> {
+        if let Some(inner_params) = self.edge_params(params) {
+            let admit = self.admit(params);
+            let iter = self
+                .inner
+                .get_edge(ids, &inner_params)?
+                .filter_map(move |e| admit.edge(e));
+            Ok(limit_n!(iter, params.limit))
+        } else {
+            Ok(Box::new(std::iter::empty()))
+        }
+    }
+
+    fn get_edge_by_id(&self, edge_ref: &EdgeRef, params: &QueryParams) -> GraphProxyResult<Option<Edge>> {
+        if !has_label(&self.view.edge_labels, Some(edge_ref.label)) {
+            return Ok(None);
+        }
+        if let Some(inner_params) = self.edge_params(params) {
+            let edge = self
+                .inner
+                .get_edge_by_id(edge_ref, &inner_params)?;
+            Ok(edge.and_then(|e| self.admit(params).edge(e)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn prepare_explore_vertex(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Vertex>>> {
+        // the labels of the params are those of the edges, while the others are on the vertices
+        let edge_labels = match constrain_labels(&params.labels, &self.view.edge_labels) {
+            Some(labels) => labels,
+            None => return Ok(empty_statement()),
+        };
+        if self.view.filters_edges() || self.view.filters_vertices() || params.filter.is_some() {
+            let edge_params = QueryParams { labels: edge_labels, ..QueryParams::default() };
+            let edges = self.prepare_explore_edge(direction, &edge_params)?;
+            let mut vertex_params = params.clone();
+            vertex_params.labels = vec![];
+            let vertex_params = self
+                .vertex_params(&vertex_params)
+                .unwrap_or_default();
+            Ok(Box::new(FetchVertexStatement {
+                edges,
+                graph: self.inner.clone(),
+                params: vertex_params,
+                admit: self.admit(params),
+                limit: params.limit,
+            }))
+        } else {
+            let mut inner_params = params.clone();
+            inner_params.labels = edge_labels;
+            let inner_params =
+                constrain_params(&inner_params, &[], self.view.vertex_columns.as_ref(), false)
+                    .unwrap_or_default();
+            let inner = self
+                .inner
+                .prepare_explore_vertex(direction, &inner_params)?;
+            let admit = self.admit(params);
+            Ok(Box::new(FilterMapStatement {
+                inner,
+                func: Arc::new(move |v| admit.vertex(v)),
+                limit: params.limit,
+            }))
+        }
+    }
+
+    fn prepare_explore_edge(
+        &self, direction: Direction, params: &QueryParams,
+    ) -> GraphProxyResult<Box<dyn Statement<ID, Edge>>> {
+        if let Some(inner_params) = self.edge_params(params) {
+            let inner = self
+                .inner
+                .prepare_explore_edge(direction, &inner_params)?;
+            let admit = self.admit(params);
+            Ok(Box::new(FilterMapStatement {
+                inner,
+                func: Arc::new(move |e| admit.edge(e)),
+                limit: params.limit,
+            }))
+        } else {
+            Ok(empty_statement())
+        }
+    }
+
+    fn count_vertex(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        if self.view.filters_vertices() || params.filter.is_some() {
+            Ok(self.scan_vertex(params)?.count() as u64)
+        } else if let Some(inner_params) = self.vertex_params(params) {
+            self.inner.count_vertex(&inner_params)
+        } else {
+            Ok(0)
+        }
+    }
+
+    fn count_edge(&self, params: &QueryParams) -> GraphProxyResult<u64> {
+        if self.view.filters_edges() || params.filter.is_some() {
+            Ok(self.scan_edge(params)?.count() as u64)
+        } else if let Some(inner_params) = self.edge_params(params) {
+            self.inner.count_edge(&inner_params)
+        } else {
+            Ok(0)
+        }
+    }
+
+    fn get_primary_key(&self, id: &ID) -> GraphProxyResult<Option<PKV>> {
+        self.inner.get_primary_key(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::HashMapExt;
+    use dyn_type::object;
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+    use crate::apis::test_graph::TestGraph;
+
+    const PERSON: LabelId = 0;
+    const SOFTWARE: LabelId = 1;
+    const KNOWS: LabelId = 0;
+    const CREATED: LabelId = 1;
+
+    fn vertex(id: ID, label: LabelId, tenant: i32) -> Vertex {
+        let mut props = AHashMap::new();
+        props.insert("tenant".into(), tenant.into());
+        props.insert("name".into(), format!("v{}", id).into());
+        Vertex::new(id, Some(label), DynDetails::new(props))
+    }
+
+    fn edge(id: ID, label: LabelId, src: (ID, LabelId), dst: (ID, LabelId), weight: f64) -> Edge {
+        let mut props = AHashMap::new();
+        props.insert("weight".into(), weight.into());
+        let mut edge = Edge::new(id, Some(label), src.0, dst.0, DynDetails::new(props));
+        edge.set_src_label(src.1);
+        edge.set_dst_label(dst.1);
+        edge
+    }
+
+    // person 1, 2 of tenant 1, person 3 of tenant 2, software 4 of tenant 1
+    fn view_graph(view: GraphView) -> ViewGraph {
+        let graph = TestGraph::new(
+            vec![vertex(1, PERSON, 1), vertex(2, PERSON, 1), vertex(3, PERSON, 2), vertex(4, SOFTWARE, 1)],
+            vec![
+                edge(12, KNOWS, (1, PERSON), (2, PERSON), 0.5),
+                edge(13, KNOWS, (1, PERSON), (3, PERSON), 1.0),
+                edge(14, CREATED, (1, PERSON), (4, SOFTWARE), 0.4),
+            ],
+        );
+        ViewGraph::new(Arc::new(graph), Arc::new(view))
+    }
+
+    fn filter(expr: &str) -> common_pb::Expression {
+        str_to_expr_pb(expr.to_string()).unwrap()
+    }
+
+    fn ids<I: Iterator<Item = E>, E: GraphElement>(iter: I) -> Vec<ID> {
+        iter.map(|e| e.id()).collect()
+    }
+
+    #[test]
+    fn view_labels_test() {
+        let graph = view_graph(
+            GraphView::new()
+                .with_vertex_labels(vec![PERSON])
+                .with_edge_labels(vec![KNOWS, CREATED]),
+        );
+        let all = QueryParams::default();
+        assert_eq!(ids(graph.scan_vertex(&all).unwrap()), vec![1, 2, 3]);
+        let mut software = QueryParams::default();
+        software.labels = vec![SOFTWARE];
+        assert!(ids(graph.scan_vertex(&software).unwrap()).is_empty());
+        // the edge to the software is out of the view
+        assert_eq!(ids(graph.scan_edge(&all).unwrap()), vec![12, 13]);
+        let out = graph
+            .prepare_explore_vertex(Direction::Out, &all)
+            .unwrap();
+        assert_eq!(ids(out.exec(1).unwrap()), vec![2, 3]);
+        assert_eq!(graph.count_edge(&all).unwrap(), 2);
+    }
+
+    #[test]
+    fn view_filter_test() {
+        let graph = view_graph(
+            GraphView::new()
+                .with_vertex_filter(filter("@.tenant == 1"))
+                .unwrap()
+                .with_edge_filter(filter("@.weight < 0.8"))
+                .unwrap(),
+        );
+        let all = QueryParams::default();
+        assert_eq!(ids(graph.scan_vertex(&all).unwrap()), vec![1, 2, 4]);
+        assert_eq!(graph.count_vertex(&all).unwrap(), 3);
+        let out = graph
+            .prepare_explore_vertex(Direction::Out, &all)
+            .unwrap();
+        assert_eq!(ids(out.exec(1).unwrap()), vec![2, 4]);
+        let out = graph
+            .prepare_explore_edge(Direction::Out, &all)
+            .unwrap();
+        assert_eq!(ids(out.exec(1).unwrap()), vec![12, 14]);
+
+        // the predicates of the query are conjoined, and the limit is applied after them
+        let mut params = QueryParams::default();
+        params.filter = Some(Arc::new(filter("@.name != \"v1\"").try_into().unwrap()));
+        params.limit = Some(1);
+        assert_eq!(ids(graph.scan_vertex(&params).unwrap()), vec![2]);
+    }
+
+    #[test]
+    fn view_columns_test() {
+        let graph = view_graph(
+            GraphView::new()
+                .with_vertex_filter(filter("@.tenant == 2"))
+                .unwrap()
+                .with_vertex_columns(vec!["name".into()]),
+        );
+        let all = QueryParams::default();
+        let vertices: Vec<Vertex> = graph.scan_vertex(&all).unwrap().collect();
+        assert_eq!(ids(vertices.clone().into_iter()), vec![3]);
+        // the hidden property is still filtered by the view
+        assert!(vertices[0]
+            .get_property(&"tenant".into())
+            .is_none());
+        assert_eq!(
+            vertices[0]
+                .get_property(&"name".into())
+                .unwrap()
+                .try_to_owned(),
+            Some(object!("v3"))
+        );
+        let props = vertices[0].get_all_properties().unwrap();
+        assert_eq!(props.len(), 1);
+
+        // the query can't probe the hidden property
+        let mut params = QueryParams::default();
+        params.filter = Some(Arc::new(filter("@.tenant == 2").try_into().unwrap()));
+        assert!(ids(graph.scan_vertex(&params).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn job_view_test() {
+        let view = Arc::new(GraphView::new().with_vertex_labels(vec![PERSON]));
+        let first = bind_view(1, view.clone());
+        let second = bind_view(1, view);
+        drop(first);
+        assert!(JOB_VIEWS.read().unwrap().contains_key(&1));
+        drop(second);
+        assert!(!JOB_VIEWS.read().unwrap().contains_key(&1));
+
+        register_view("tenant", GraphView::new());
+        assert!(get_view("tenant").is_some());
+        assert!(unregister_view("tenant"));
+        assert!(get_view("tenant").is_none());
+    }
+}
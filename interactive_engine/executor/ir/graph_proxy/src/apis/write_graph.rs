@@ -13,11 +13,39 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{Arc, Mutex};
+
 use ir_common::LabelId;
 
 use crate::apis::graph::PKV;
-use crate::apis::DynDetails;
-use crate::GraphProxyResult;
+use crate::apis::{DynDetails, Edge, Vertex, ID};
+use crate::{GraphProxyError, GraphProxyResult};
+
+/// A mutation of the graph, see `WriteGraphProxy::mutate()`.
+#[derive(Clone, Debug)]
+pub enum Mutation {
+    /// Add a vertex of the label and the properties, which contain its primary key
+    AddVertex(LabelId, DynDetails),
+    /// Add an edge of the label and the properties, from the source vertex to the destination vertex
+    AddEdge(LabelId, ID, ID, DynDetails),
+    /// Set the properties of the vertex
+    SetVertexProperties(Vertex, DynDetails),
+    /// Set the properties of the edge
+    SetEdgeProperties(Edge, DynDetails),
+    /// Drop the vertex, together with its edges
+    DropVertex(Vertex),
+    /// Drop the edge
+    DropEdge(Edge),
+}
+
+/// The vertex or the edge after a mutation.
+#[derive(Clone, Debug)]
+pub enum Mutated {
+    Vertex(Vertex),
+    Edge(Edge),
+    Dropped,
+}
 
 /// The interfaces of writing data (vertices, edges and their properties) into a graph.
 pub trait WriteGraphProxy: Send + Sync {
@@ -34,4 +62,51 @@ pub trait WriteGraphProxy: Send + Sync {
 
     /// A hint of all vertices/edges are added.
     fn finish(&mut self) -> GraphProxyResult<()>;
+
+    /// Match the vertex of the given properties, which contain its primary key, or add it with the
+    /// properties if not matched, atomically. The `on_create` properties are further set to the added
+    /// vertex, and the `on_match` properties to the matched one.
+    /// Return the vertex after the update, and whether it is added.
+    fn merge_vertex(
+        &mut self, _label: LabelId, _properties: DynDetails, _on_create: DynDetails, _on_match: DynDetails,
+    ) -> GraphProxyResult<(Vertex, bool)> {
+        Err(GraphProxyError::unsupported_error("merge_vertex() of the graph"))
+    }
+
+    /// Match the edge of the given properties between the source and the destination vertices, or add
+    /// it with the properties if not matched, atomically. The `on_create` properties are further set to
+    /// the added edge, and the `on_match` properties to the matched one.
+    /// Return the edge after the update, and whether it is added.
+    fn merge_edge(
+        &mut self, _label: LabelId, _src_id: ID, _dst_id: ID, _properties: DynDetails,
+        _on_create: DynDetails, _on_match: DynDetails,
+    ) -> GraphProxyResult<(Edge, bool)> {
+        Err(GraphProxyError::unsupported_error("merge_edge() of the graph"))
+    }
+
+    /// Write the mutations in a batch, and return the result of each of them in order, where the
+    /// failure of one mutation does not fail the others.
+    fn mutate(&mut self, _mutations: Vec<Mutation>) -> GraphProxyResult<Vec<GraphProxyResult<Mutated>>> {
+        Err(GraphProxyError::unsupported_error("mutate() of the graph"))
+    }
+}
+
+lazy_static! {
+    /// WRITE_GRAPH_PROXY is a raw pointer which can be safely shared between threads.
+    pub static ref WRITE_GRAPH_PROXY: AtomicPtr<Arc<Mutex<dyn WriteGraphProxy>>> = AtomicPtr::default();
+}
+
+/// Register the graph to write into, which enables the write operators of the runtime, e.g., Merge.
+pub fn register_write_graph(graph: Arc<Mutex<dyn WriteGraphProxy>>) {
+    let ptr = Box::into_raw(Box::new(graph));
+    WRITE_GRAPH_PROXY.store(ptr, Ordering::SeqCst);
+}
+
+pub fn get_write_graph() -> Option<Arc<Mutex<dyn WriteGraphProxy>>> {
+    let ptr = WRITE_GRAPH_PROXY.load(Ordering::SeqCst);
+    if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { (*ptr).clone() })
+    }
 }
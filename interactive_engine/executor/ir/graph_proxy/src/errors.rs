@@ -14,6 +14,7 @@
 //! limitations under the License.
 
 use pegasus::api::function::DynError;
+use pegasus::errors::{CodedError, ErrorCode};
 
 pub type GraphProxyResult<T> = Result<T, GraphProxyError>;
 
@@ -30,6 +31,10 @@ pub enum GraphProxyError {
     ClusterInfoMissing(String),
     /// Not supported error
     UnSupported(String),
+    /// Invalid query error, e.g., the primary keys given don't match the schema of the store
+    InvalidQueryError(String),
+    /// The snapshot read is beyond the latest committed one
+    UncommittedSnapshot { snapshot: i64, latest: i64 },
 }
 
 impl GraphProxyError {
@@ -51,6 +56,29 @@ impl GraphProxyError {
     pub fn unsupported_error(e: &str) -> Self {
         GraphProxyError::UnSupported(e.to_string())
     }
+
+    pub fn invalid_query_error(e: &str) -> Self {
+        GraphProxyError::InvalidQueryError(e.to_string())
+    }
+
+    pub fn uncommitted_snapshot(snapshot: i64, latest: i64) -> Self {
+        GraphProxyError::UncommittedSnapshot { snapshot, latest }
+    }
+
+    /// The code of the error, in `4xxx` of the errors of the store. A failure of the store is not known
+    /// to be transient, and only the missing info of the cluster, e.g., the routing of the partitions not
+    /// synchronized yet, is retryable.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            GraphProxyError::QueryStoreError(_) => ErrorCode::system(4001, false),
+            GraphProxyError::WriteGraphError(_) => ErrorCode::system(4002, false),
+            GraphProxyError::FilterPushDownError(_) => ErrorCode::system(4003, false),
+            GraphProxyError::ClusterInfoMissing(_) => ErrorCode::system(4004, true),
+            GraphProxyError::UnSupported(_) => ErrorCode::user(4005),
+            GraphProxyError::InvalidQueryError(_) => ErrorCode::user(4006),
+            GraphProxyError::UncommittedSnapshot { .. } => ErrorCode::user(4007),
+        }
+    }
 }
 
 impl std::fmt::Display for GraphProxyError {
@@ -65,6 +93,12 @@ impl std::fmt::Display for GraphProxyError {
             GraphProxyError::ClusterInfoMissing(e) => {
                 write!(f, "Cluster info missing error in graph_proxy {}", e)
             }
+            GraphProxyError::InvalidQueryError(e) => write!(f, "Invalid query error in graph_proxy {}", e),
+            GraphProxyError::UncommittedSnapshot { snapshot, latest } => write!(
+                f,
+                "Uncommitted snapshot error in graph_proxy: snapshot {} is beyond the latest committed {}",
+                snapshot, latest
+            ),
         }
     }
 }
@@ -73,7 +107,6 @@ impl std::error::Error for GraphProxyError {}
 
 impl From<GraphProxyError> for DynError {
     fn from(e: GraphProxyError) -> Self {
-        let err: Box<dyn std::error::Error + Send + Sync> = e.into();
-        err
+        Box::new(CodedError::new(e.code(), e))
     }
 }
@@ -20,7 +20,8 @@ extern crate lazy_static;
 pub use adapters::{create_csr_store, create_exp_store, SimplePartition};
 #[cfg(feature = "with_global_query")]
 pub use adapters::{
-    create_gs_store, GraphScopeStore, GrootMultiPartition, VineyardGraphWriter, VineyardMultiPartition,
+    create_gs_store, GraphScopeStore, GrootMultiPartition, VineyardDelta, VineyardDeltaGraph,
+    VineyardDeltaWriter, VineyardGraphWriter, VineyardIdParser, VineyardMultiPartition,
 };
 pub use errors::{GraphProxyError, GraphProxyResult};
 
@@ -62,7 +63,11 @@ macro_rules! sample_limit {
         if let Some(ratio) = $s {
             use rand::prelude::StdRng;
             use rand::{Rng, SeedableRng};
-            let mut rng: StdRng = SeedableRng::from_entropy();
+            let mut rng: StdRng = if pegasus::is_deterministic() {
+                SeedableRng::seed_from_u64(pegasus::DETERMINISTIC_SEED)
+            } else {
+                SeedableRng::from_entropy()
+            };
             let r = $iter.filter(move |_| rng.gen_bool(ratio));
             limit_n!(r, $n)
         } else {
@@ -20,6 +20,7 @@ use std::convert::{TryFrom, TryInto};
 
 use dyn_type::arith::{BitOperand, Exp};
 use dyn_type::object;
+use dyn_type::object::RawType;
 use dyn_type::{BorrowObject, Object};
 use ir_common::error::{ParsePbError, ParsePbResult};
 use ir_common::expr_parse::to_suffix_expr;
@@ -29,6 +30,7 @@ use ir_common::{NameOrId, ALL_KEY, ID_KEY, LABEL_KEY, LENGTH_KEY};
 use crate::apis::{Element, PropKey};
 use crate::utils::expr::eval_pred::EvalPred;
 use crate::utils::expr::{ExprEvalError, ExprEvalResult};
+use crate::utils::udf::{call_udf, get_udf};
 
 /// The trait to define evaluating an expression
 pub trait Evaluate {
@@ -56,6 +58,8 @@ pub enum Operand {
     Map(Vec<(Object, Operand)>),
     // this is to concat multiple fields (refer to paths, or Strings) into one
     Concat(Vec<Operand>),
+    // a call of the user defined function of the name
+    Udf { name: String, args: Vec<Operand> },
 }
 
 #[derive(Debug, Clone)]
@@ -136,6 +140,7 @@ impl TryFrom<common_pb::Expression> for Evaluator {
     where
         Self: Sized,
     {
+        check_type_names(&suffix_tree.operators)?;
         let mut inner_tree: Vec<InnerOpr> = Vec::with_capacity(suffix_tree.operators.len());
         let suffix_oprs = to_suffix_expr(suffix_tree.operators)
             .map_err(|err| ParsePbError::ParseError(format!("{:?}", err)))?;
@@ -222,6 +227,60 @@ pub(crate) fn apply_function<'a>(
     }
 }
 
+/// Whether the operator takes the `None` got from the context as `Object::None`, rather than an error.
+fn takes_none(logical: &common_pb::Logical) -> bool {
+    matches!(logical, common_pb::Logical::Isnull | common_pb::Logical::Exists)
+}
+
+/// Whether the raw type is of the type of the name given to `ISTYPE`, or `None` if the name is unknown.
+/// The names are of `common.DataType` in lower case, e.g., `int32`, or their Java counterparts, e.g.,
+/// `int`, besides `list` and `map`. A boolean is stored as a byte, and a float as a double.
+fn is_of_type_name(raw_type: &RawType, name: &str) -> Option<bool> {
+    let is_of_type = match name.to_lowercase().as_str() {
+        "boolean" => matches!(raw_type, RawType::Byte),
+        "int32" | "int" => matches!(raw_type, RawType::Integer),
+        "int64" | "long" => matches!(raw_type, RawType::Long | RawType::ULLong),
+        "double" | "float" => matches!(raw_type, RawType::Float),
+        "string" => matches!(raw_type, RawType::String),
+        "bytes" => matches!(raw_type, RawType::Blob(_)),
+        "date32" | "date" => matches!(raw_type, RawType::Date),
+        "time32" | "time" => matches!(raw_type, RawType::Time),
+        "timestamp" | "datetime" => matches!(raw_type, RawType::DateTime | RawType::DateTimeWithTz),
+        "list" => matches!(raw_type, RawType::Vector),
+        "map" => matches!(raw_type, RawType::KV),
+        _ => return None,
+    };
+    Some(is_of_type)
+}
+
+fn is_of_type(value: &BorrowObject, name: &str) -> ExprEvalResult<bool> {
+    is_of_type_name(&value.raw_type(), name)
+        .ok_or_else(|| ExprEvalError::Unsupported(format!("type `{}` to check", name)))
+}
+
+/// Check the types given to `ISTYPE`, which must be the constant names of the known types, so that an
+/// unknown name is rejected as the expression is parsed, rather than as a record is evaluated.
+pub(crate) fn check_type_names(oprs: &[common_pb::ExprOpr]) -> ParsePbResult<()> {
+    use common_pb::expr_opr::Item;
+    for (i, opr) in oprs.iter().enumerate() {
+        if opr.item != Some(Item::Logical(common_pb::Logical::Istype as i32)) {
+            continue;
+        }
+        match oprs
+            .get(i + 1)
+            .and_then(|opr| opr.item.as_ref())
+        {
+            Some(Item::Const(common_pb::Value { item: Some(common_pb::value::Item::Str(name)) })) => {
+                if is_of_type_name(&RawType::None, name).is_none() {
+                    Err(ParsePbError::ParseError(format!("unknown type `{}` of `ISTYPE`", name)))?
+                }
+            }
+            _ => Err(ParsePbError::ParseError("the type of `ISTYPE` must be a constant name".to_string()))?,
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn apply_logical<'a>(
     logical: &common_pb::Logical, a: BorrowObject<'a>, b_opt: Option<BorrowObject<'a>>,
 ) -> ExprEvalResult<Object> {
@@ -230,6 +289,8 @@ pub(crate) fn apply_logical<'a>(
         return Ok((!a.eval_bool::<(), NoneContext>(None)?).into());
     } else if logical == &Isnull {
         return Ok(a.eq(&BorrowObject::None).into());
+    } else if logical == &Exists {
+        return Ok((!a.eq(&BorrowObject::None)).into());
     } else {
         if b_opt.is_some() {
             let b = b_opt.unwrap();
@@ -260,8 +321,10 @@ pub(crate) fn apply_logical<'a>(
                     let regex = regex::Regex::new(b.as_str()?.as_ref())?;
                     Ok(regex.is_match(a.as_str()?.as_ref()).into())
                 }
+                Istype => Ok(is_of_type(&a, b.as_str()?.as_ref())?.into()),
                 Not => unreachable!(),
                 Isnull => unreachable!(),
+                Exists => unreachable!(),
             }
         } else {
             Err(ExprEvalError::MissingOperands(InnerOpr::Logical(*logical).into()))
@@ -287,7 +350,7 @@ impl Evaluator {
             let second = _second.unwrap();
             if let InnerOpr::Logical(logical) = second {
                 let mut first = first.eval(context);
-                if common_pb::Logical::Isnull.eq(logical) {
+                if takes_none(logical) {
                     match first {
                         Err(ExprEvalError::GetNoneFromContext) => first = Ok(Object::None),
                         _ => {}
@@ -313,14 +376,14 @@ impl Evaluator {
                     // to deal with two unary operators cases, e.g., !(!true), !(isNull(a)),isNull(extract(a)) etc.
                     if let InnerOpr::Logical(inner_logical) = second {
                         let mut inner_first = first.eval(context);
-                        if common_pb::Logical::Isnull.eq(inner_logical) {
+                        if takes_none(inner_logical) {
                             match inner_first {
                                 Err(ExprEvalError::GetNoneFromContext) => inner_first = Ok(Object::None),
                                 _ => {}
                             }
                         }
                         let mut first = Ok(apply_logical(inner_logical, inner_first?.as_borrow(), None)?);
-                        if common_pb::Logical::Isnull.eq(logical) {
+                        if takes_none(logical) {
                             match first {
                                 Err(ExprEvalError::GetNoneFromContext) => first = Ok(Object::None),
                                 _ => {}
@@ -418,7 +481,7 @@ impl Evaluate for Evaluator {
                         InnerOpr::Logical(logical) => {
                             if logical == &common_pb::Logical::Not {
                                 apply_logical(logical, first?.as_borrow(), None)
-                            } else if logical == &common_pb::Logical::Isnull {
+                            } else if takes_none(logical) {
                                 let first_obj = match first {
                                     Ok(obj) => obj,
                                     Err(err) => match err {
@@ -479,6 +542,17 @@ fn get_object(obj_result: ExprEvalResult<Object>) -> ExprEvalResult<Object> {
     }
 }
 
+/// Evaluate the arguments of a user defined function, where an argument absent in the context is `None`.
+pub fn eval_udf_args<E: Element, C: Context<E>>(
+    args: &[Operand], context: Option<&C>,
+) -> ExprEvalResult<Vec<Object>> {
+    let mut objs = Vec::with_capacity(args.len());
+    for arg in args {
+        objs.push(get_object(arg.eval(context))?);
+    }
+    Ok(objs)
+}
+
 impl EvalPred for Evaluator {
     fn eval_bool<E: Element, C: Context<E>>(&self, context: Option<&C>) -> ExprEvalResult<bool> {
         get_object(self.eval(context))?.eval_bool(context)
@@ -564,6 +638,19 @@ impl TryFrom<common_pb::ExprOpr> for Operand {
                     }
                     Ok(Self::Map(vec))
                 }
+                Udf(udf) => {
+                    if get_udf(&udf.name).is_none() {
+                        return Err(ParsePbError::ParseError(format!(
+                            "user defined function {} is not registered",
+                            udf.name
+                        )));
+                    }
+                    let mut args = Vec::with_capacity(udf.args.len());
+                    for arg in udf.args {
+                        args.push(Operand::try_from(arg)?);
+                    }
+                    Ok(Self::Udf { name: udf.name, args })
+                }
                 _ => Err(ParsePbError::ParseError("invalid operators for an Operand".to_string())),
             }
         } else {
@@ -717,6 +804,7 @@ impl Evaluate for Operand {
             Operand::Concat(_) => {
                 Err(ExprEvalError::Unsupported("evaluating `Concat` is not supported.".to_string()))
             }
+            Operand::Udf { name, args } => call_udf(name, eval_udf_args(args, context)?),
         }
     }
 }
@@ -741,7 +829,7 @@ impl InnerOpr {
     pub fn is_unary(&self) -> bool {
         match self {
             InnerOpr::Logical(logical) => match logical {
-                common_pb::Logical::Not | common_pb::Logical::Isnull => true,
+                common_pb::Logical::Not | common_pb::Logical::Isnull | common_pb::Logical::Exists => true,
                 _ => false,
             },
             InnerOpr::Function(function) => match function {
@@ -1135,6 +1223,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_eval_exists_and_is_type() {
+        // [v0: id = 1, label = 9, age = 31, name = John, birthday = 19900416, hobbies = [football, guitar]]
+        // [v1: id = 2, label = 11, age = 26, name = Jimmy, birthday = 19950816]
+        let ctxt = prepare_context();
+        let cases: Vec<&str> = vec![
+            "exists @0.hobbies",                      // true
+            "exists @1.hobbies",                      // false
+            "!(exists @1.hobbies)",                   // true
+            "exists @1.age && @1.age == 26",          // true
+            "@0.age isType \"int\"",                  // true
+            "@0.age isType \"long\"",                 // false
+            "@0.hobbies isType \"list\"",             // true
+            "@0.name isType \"String\"",              // true
+            "exists @0.age && @0.age isType \"int\"", // true
+            "@0.age isType \"int32\"",                // true
+            "true isType \"boolean\"",                // true
+            "1.5 isType \"float\"",                   // true
+            "1.5 isType \"int64\"",                   // false
+        ];
+        let expected: Vec<Object> = vec![
+            object!(true),
+            object!(false),
+            object!(true),
+            object!(true),
+            object!(true),
+            object!(false),
+            object!(true),
+            object!(true),
+            object!(true),
+            object!(true),
+            object!(true),
+            object!(true),
+            object!(false),
+        ];
+
+        for (case, expected) in cases.into_iter().zip(expected.into_iter()) {
+            let eval = Evaluator::try_from(str_to_expr_pb(case.to_string()).unwrap()).unwrap();
+            assert_eq!(eval.eval::<_, Vertices>(Some(&ctxt)).unwrap(), expected);
+        }
+        // the unknown type is rejected as parsed, even if no record is evaluated
+        assert!(
+            Evaluator::try_from(str_to_expr_pb("@0.age isType \"number\"".to_string()).unwrap()).is_err()
+        );
+        assert!(Evaluator::try_from(str_to_expr_pb("@0.age isType @0.name".to_string()).unwrap()).is_err());
+    }
+
     fn prepare_context_with_date() -> Vertices {
         let map1: HashMap<NameOrId, Object> = vec![
             (
@@ -1344,4 +1479,58 @@ mod tests {
             assert_eq!(eval.eval::<(), NoneContext>(None).unwrap(), expected);
         }
     }
+
+    #[test]
+    fn test_eval_udf() {
+        crate::utils::udf::register_udf(
+            "test_eval_udf_suffix",
+            std::sync::Arc::new(|args: Vec<Object>| -> ExprEvalResult<Object> {
+                Ok(object!(format!("{}{}", args[0].as_str()?, args[1].as_str()?)))
+            }),
+        );
+        let udf = |args: Vec<common_pb::ExprOpr>| common_pb::ExprOpr {
+            node_type: None,
+            item: Some(common_pb::expr_opr::Item::Udf(common_pb::UdfCall {
+                name: "test_eval_udf_suffix".to_string(),
+                args,
+            })),
+        };
+        let ctxt = prepare_context();
+        // UDF(@0.name, '!') == 'John!'
+        let expr = common_pb::Expression {
+            operators: vec![
+                udf(vec![
+                    common_pb::Variable::from("@0.name".to_string()).into(),
+                    common_pb::Value::from("!".to_string()).into(),
+                ]),
+                common_pb::Logical::Eq.into(),
+                common_pb::Value::from("John!".to_string()).into(),
+            ],
+        };
+        let eval = Evaluator::try_from(expr).unwrap();
+        assert_eq!(eval.eval::<_, Vertices>(Some(&ctxt)).unwrap(), object!(true));
+        // UDF(UDF(@1.name, '?'), '!')
+        let expr = common_pb::Expression {
+            operators: vec![udf(vec![
+                udf(vec![
+                    common_pb::Variable::from("@1.name".to_string()).into(),
+                    common_pb::Value::from("?".to_string()).into(),
+                ]),
+                common_pb::Value::from("!".to_string()).into(),
+            ])],
+        };
+        let eval = Evaluator::try_from(expr).unwrap();
+        assert_eq!(eval.eval::<_, Vertices>(Some(&ctxt)).unwrap(), object!("Nancy?!"));
+
+        let unregistered = common_pb::Expression {
+            operators: vec![common_pb::ExprOpr {
+                node_type: None,
+                item: Some(common_pb::expr_opr::Item::Udf(common_pb::UdfCall {
+                    name: "test_eval_udf_absent".to_string(),
+                    args: vec![],
+                })),
+            }],
+        };
+        assert!(Evaluator::try_from(unregistered).is_err());
+    }
 }
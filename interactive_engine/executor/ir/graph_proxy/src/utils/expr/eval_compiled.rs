@@ -0,0 +1,426 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Specialize the hot shapes of the predicates into the native closures while building the plan, which
+//! are evaluated without interpreting the predicates over `Object`s. A predicate comparing a property of
+//! a graph element with a constant, e.g., `@a.age > 27` or `@.name StartsWith "Jo"`, is specialized into
+//! a closure on the borrowed property of the type of the constant, without cloning the property or
+//! dispatching the operator per record; and the conjunctions, disjunctions and negations of them are
+//! specialized recursively. The other predicates, or a property of another type than the constant, fall
+//! back to the interpreter, and the results are always the same as interpreted.
+//!
+//! An IN-list, i.e., `@a.age within [26, 31]`, of the integers or the strings is specialized into a probe
+//! of a hash set, instead of scanning the list per record.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+
+use dyn_type::{BorrowObject, Object, Primitives};
+use ir_common::generated::common as common_pb;
+use ir_common::NameOrId;
+
+use crate::apis::{Element, GraphElement, PropKey, PropertyValue};
+use crate::utils::expr::eval::{Context, Operand};
+use crate::utils::expr::eval_pred::{EvalPred, PEvaluator, Predicate, Predicates};
+use crate::utils::expr::{ExprEvalError, ExprEvalResult};
+
+/// Test a property, or `None` if it is not of the type specialized for.
+type PropertyTest = Box<dyn Fn(&BorrowObject) -> Option<bool> + Send + Sync>;
+
+fn test_ordering(cmp: common_pb::Logical, ordering: Option<Ordering>) -> bool {
+    use common_pb::Logical::*;
+    match cmp {
+        Eq => ordering == Some(Ordering::Equal),
+        Ne => ordering != Some(Ordering::Equal),
+        Lt => ordering == Some(Ordering::Less),
+        Le => matches!(ordering, Some(Ordering::Less) | Some(Ordering::Equal)),
+        Gt => ordering == Some(Ordering::Greater),
+        Ge => matches!(ordering, Some(Ordering::Greater) | Some(Ordering::Equal)),
+        _ => unreachable!(),
+    }
+}
+
+/// The comparison of `right cmp left` as `left flip(cmp) right`.
+pub(crate) fn flip(cmp: common_pb::Logical) -> Option<common_pb::Logical> {
+    use common_pb::Logical::*;
+    match cmp {
+        Eq | Ne => Some(cmp),
+        Lt => Some(Gt),
+        Le => Some(Ge),
+        Gt => Some(Lt),
+        Ge => Some(Le),
+        _ => None,
+    }
+}
+
+/// Specialize `property cmp constant`, only for the property of the same type as the constant, as the
+/// comparisons of the different types, e.g., of `Integer` and `Long`, follow the casting of `dyn_type`.
+fn specialize(cmp: common_pb::Logical, constant: &Object) -> Option<PropertyTest> {
+    use common_pb::Logical::*;
+    let test: PropertyTest = match (cmp, constant) {
+        (Eq | Ne | Lt | Le | Gt | Ge, Object::Primitive(Primitives::Integer(c))) => {
+            let c = *c;
+            Box::new(move |left| match left {
+                BorrowObject::Primitive(Primitives::Integer(v)) => {
+                    Some(test_ordering(cmp, v.partial_cmp(&c)))
+                }
+                _ => None,
+            })
+        }
+        (Eq | Ne | Lt | Le | Gt | Ge, Object::Primitive(Primitives::Long(c))) => {
+            let c = *c;
+            Box::new(move |left| match left {
+                BorrowObject::Primitive(Primitives::Long(v)) => Some(test_ordering(cmp, v.partial_cmp(&c))),
+                _ => None,
+            })
+        }
+        (Eq | Ne | Lt | Le | Gt | Ge, Object::Primitive(Primitives::Float(c))) => {
+            let c = *c;
+            Box::new(move |left| match left {
+                BorrowObject::Primitive(Primitives::Float(v)) => {
+                    Some(test_ordering(cmp, v.partial_cmp(&c)))
+                }
+                _ => None,
+            })
+        }
+        (Eq | Ne | Lt | Le | Gt | Ge, Object::String(c)) => {
+            let c = c.clone();
+            Box::new(move |left| match left {
+                BorrowObject::String(v) => Some(test_ordering(cmp, (*v).partial_cmp(c.as_str()))),
+                _ => None,
+            })
+        }
+        (Startswith, Object::String(c)) => {
+            let c = c.clone();
+            Box::new(move |left| match left {
+                BorrowObject::String(v) => Some(v.starts_with(c.as_str())),
+                _ => None,
+            })
+        }
+        (Endswith, Object::String(c)) => {
+            let c = c.clone();
+            Box::new(move |left| match left {
+                BorrowObject::String(v) => Some(v.ends_with(c.as_str())),
+                _ => None,
+            })
+        }
+        (Regex, Object::String(c)) => {
+            // compiled once, instead of once per record; an invalid pattern is left to the interpreter
+            let regex = regex::Regex::new(c).ok()?;
+            Box::new(move |left| match left {
+                BorrowObject::String(v) => Some(regex.is_match(v)),
+                _ => None,
+            })
+        }
+        (Within | Without, Object::Vector(list)) => {
+            let within = cmp == Within;
+            if list
+                .iter()
+                .all(|obj| as_integer(&obj.as_borrow()).is_some())
+            {
+                let set: HashSet<i64> = list
+                    .iter()
+                    .filter_map(|obj| as_integer(&obj.as_borrow()))
+                    .collect();
+                Box::new(move |left| as_integer(left).map(|v| set.contains(&v) == within))
+            } else if list
+                .iter()
+                .all(|obj| matches!(obj, Object::String(_)))
+            {
+                let set: HashSet<String> = list
+                    .iter()
+                    .filter_map(|obj| obj.as_str().ok().map(|s| s.into_owned()))
+                    .collect();
+                Box::new(move |left| match left {
+                    BorrowObject::String(v) => Some(set.contains(*v) == within),
+                    _ => None,
+                })
+            } else {
+                return None;
+            }
+        }
+        _ => return None,
+    };
+    Some(test)
+}
+
+/// The integers of `Byte`, `Integer` and `Long`, which are equal to each other as `i64`.
+pub(crate) fn as_integer(obj: &BorrowObject) -> Option<i64> {
+    match obj {
+        BorrowObject::Primitive(Primitives::Byte(v)) => Some(*v as i64),
+        BorrowObject::Primitive(Primitives::Integer(v)) => Some(*v as i64),
+        BorrowObject::Primitive(Primitives::Long(v)) => Some(*v),
+        _ => None,
+    }
+}
+
+/// `@tag.key cmp constant` specialized on the type of the constant.
+struct PropertyPredicate {
+    tag: Option<NameOrId>,
+    key: NameOrId,
+    test: PropertyTest,
+    /// The predicate interpreted in case of not specialized
+    pred: Predicate,
+}
+
+impl PropertyPredicate {
+    fn from_predicate(pred: &Predicate) -> Option<Self> {
+        let (var, cmp, constant) = match (&pred.left, &pred.right) {
+            (var @ Operand::Var { .. }, Operand::Const(constant)) => (var, pred.cmp, constant),
+            (Operand::Const(constant), var @ Operand::Var { .. }) => (var, flip(pred.cmp)?, constant),
+            _ => return None,
+        };
+        if let Operand::Var { tag, prop_key: Some(PropKey::Key(key)) } = var {
+            Some(PropertyPredicate {
+                tag: tag.clone(),
+                key: key.clone(),
+                test: specialize(cmp, constant)?,
+                pred: pred.clone(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl EvalPred for PropertyPredicate {
+    fn eval_bool<E: Element, C: Context<E>>(&self, context: Option<&C>) -> ExprEvalResult<bool> {
+        if let Some(element) = context.and_then(|ctxt| ctxt.get(self.tag.as_ref())) {
+            if let Some(graph_element) = element.as_graph_element() {
+                let result = match graph_element.get_property(&self.key) {
+                    Some(PropertyValue::Borrowed(left)) => (self.test)(&left),
+                    Some(PropertyValue::Owned(left)) => (self.test)(&left.as_borrow()),
+                    None => return Err(ExprEvalError::GetNoneFromContext),
+                };
+                if let Some(result) = result {
+                    return Ok(result);
+                }
+            }
+        }
+        self.pred.eval_bool(context)
+    }
+}
+
+enum Compiled {
+    Property(PropertyPredicate),
+    Interpreted(Predicates),
+    Not(Box<Compiled>),
+    And(Box<Compiled>, Box<Compiled>),
+    Or(Box<Compiled>, Box<Compiled>),
+}
+
+impl Compiled {
+    fn compile(pred: &Predicates) -> Self {
+        match pred {
+            Predicates::Binary(binary) => PropertyPredicate::from_predicate(binary)
+                .map(Compiled::Property)
+                .unwrap_or_else(|| Compiled::Interpreted(pred.clone())),
+            Predicates::Not(inner) => Compiled::Not(Box::new(Compiled::compile(inner))),
+            Predicates::And((left, right)) => {
+                Compiled::And(Box::new(Compiled::compile(left)), Box::new(Compiled::compile(right)))
+            }
+            Predicates::Or((left, right)) => {
+                Compiled::Or(Box::new(Compiled::compile(left)), Box::new(Compiled::compile(right)))
+            }
+            _ => Compiled::Interpreted(pred.clone()),
+        }
+    }
+
+    fn is_specialized(&self) -> bool {
+        match self {
+            Compiled::Property(_) => true,
+            Compiled::Interpreted(_) => false,
+            Compiled::Not(inner) => inner.is_specialized(),
+            Compiled::And(left, right) | Compiled::Or(left, right) => {
+                left.is_specialized() || right.is_specialized()
+            }
+        }
+    }
+}
+
+impl EvalPred for Compiled {
+    fn eval_bool<E: Element, C: Context<E>>(&self, context: Option<&C>) -> ExprEvalResult<bool> {
+        match self {
+            Compiled::Property(pred) => pred.eval_bool(context),
+            Compiled::Interpreted(pred) => pred.eval_bool(context),
+            Compiled::Not(pred) => Ok(!pred.eval_bool(context)?),
+            Compiled::And(left, right) => Ok(left.eval_bool(context)? && right.eval_bool(context)?),
+            Compiled::Or(left, right) => Ok(left.eval_bool(context)? || right.eval_bool(context)?),
+        }
+    }
+}
+
+/// The predicates specialized from a `PEvaluator`, which is evaluated the same as the `PEvaluator`.
+pub struct CompiledPredicates {
+    source: Predicates,
+    compiled: Compiled,
+}
+
+impl CompiledPredicates {
+    /// Specialize the predicates, or `None` if none of the predicates is specialized, e.g., of a
+    /// `PEvaluator::General`, which had better be interpreted as is.
+    pub fn compile(p_eval: &PEvaluator) -> Option<Self> {
+        if let PEvaluator::Predicates(source) = p_eval {
+            let compiled = Compiled::compile(source);
+            if compiled.is_specialized() {
+                return Some(CompiledPredicates { source: source.clone(), compiled });
+            }
+        }
+        None
+    }
+
+    pub fn get_source(&self) -> &Predicates {
+        &self.source
+    }
+}
+
+impl fmt::Debug for CompiledPredicates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CompiledPredicates")
+            .field(&self.source)
+            .finish()
+    }
+}
+
+impl EvalPred for CompiledPredicates {
+    fn eval_bool<E: Element, C: Context<E>>(&self, context: Option<&C>) -> ExprEvalResult<bool> {
+        match self.compiled.eval_bool(context) {
+            Err(ExprEvalError::GetNoneFromContext) => Ok(false),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use ahash::HashMap;
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+    use crate::apis::{DynDetails, Vertex};
+
+    struct Vertices {
+        vec: Vec<Vertex>,
+    }
+
+    impl Context<Vertex> for Vertices {
+        fn get(&self, key: Option<&NameOrId>) -> Option<&Vertex> {
+            match key {
+                Some(NameOrId::Id(i)) => self.vec.get(*i as usize),
+                None => self.vec.get(0),
+                _ => None,
+            }
+        }
+    }
+
+    fn prepare_context() -> Vertices {
+        let map1: HashMap<NameOrId, Object> = vec![
+            (NameOrId::from("age".to_string()), 31.into()),
+            (NameOrId::from("weight".to_string()), 65.5.into()),
+            (NameOrId::from("birthday".to_string()), 19900416_i64.into()),
+            (NameOrId::from("name".to_string()), "John".to_string().into()),
+        ]
+        .into_iter()
+        .collect();
+        let map2: HashMap<NameOrId, Object> = vec![
+            (NameOrId::from("age".to_string()), 26_i64.into()),
+            (NameOrId::from("name".to_string()), "Nancy".to_string().into()),
+        ]
+        .into_iter()
+        .collect();
+        Vertices {
+            vec: vec![
+                Vertex::new(1, Some(9.into()), DynDetails::new(map1)),
+                Vertex::new(2, Some(11.into()), DynDetails::new(map2)),
+            ],
+        }
+    }
+
+    fn compile(expr: &str) -> (PEvaluator, Option<CompiledPredicates>) {
+        let p_eval = PEvaluator::try_from(str_to_expr_pb(expr.to_string()).unwrap()).unwrap();
+        let compiled = CompiledPredicates::compile(&p_eval);
+        (p_eval, compiled)
+    }
+
+    #[test]
+    fn test_compile_predicates() {
+        assert!(compile("@0.age > 27").1.is_some());
+        assert!(compile("27 < @0.age").1.is_some());
+        assert!(compile("@0.name == \"John\" && @0.~id == 1")
+            .1
+            .is_some());
+        assert!(compile("@0.name Regex \"^J.*\"").1.is_some());
+        // not any comparison of a property with a constant
+        assert!(compile("@0.~id == 1").1.is_none());
+        assert!(compile("@0.age > @1.age").1.is_none());
+        assert!(compile("@0.age within [26, 31]").1.is_some());
+        assert!(compile("@0.name without [\"John\", \"Tom\"]")
+            .1
+            .is_some());
+        assert!(compile("(@0.age > 27) || (@1.age > 27)")
+            .1
+            .is_none());
+    }
+
+    #[test]
+    fn test_eval_compiled_predicates() {
+        let context = prepare_context();
+        let exprs = vec![
+            "@0.age > 27",
+            "@0.age <= 31",
+            "27 >= @0.age",
+            "@0.age == 31 && @1.age != 26",
+            "@0.age > 30 || @1.age > 30",
+            // a `Long` against an `Integer`, and an `Integer` against a `Long`
+            "@0.birthday > 19900000",
+            "@1.age == 26",
+            // a `Float` against an `Integer`, and an `Integer` against a `Float`
+            "@0.weight > 65",
+            "@0.age < 31.5",
+            "@0.weight >= 65.5",
+            "@0.name == \"John\"",
+            "@0.name < \"Nancy\"",
+            "@0.name StartsWith \"Jo\" && @1.name EndsWith \"cy\"",
+            "@0.name Regex \"^J.*n$\"",
+            "@1.name Regex \"^J.*\"",
+            "@0.age within [26, 31] && @1.age without [26, 31]",
+            "@0.birthday within [19900416]",
+            "@0.name within [\"John\", \"Tom\"] && @1.name without [\"John\"]",
+            "@0.weight within [65, 66]",
+            // a property absent, or of another type than the constant
+            "@0.hobbies == \"football\" || @0.age > 27",
+            "@0.age == \"31\"",
+            "@0.name > 1 || @0.age > 27",
+            "@.age > 27 && @.name == \"John\"",
+        ];
+        for expr in exprs {
+            let (p_eval, compiled) = compile(expr);
+            let compiled = compiled.expect(expr);
+            assert_eq!(
+                compiled
+                    .eval_bool::<_, Vertices>(Some(&context))
+                    .ok(),
+                p_eval
+                    .eval_bool::<_, Vertices>(Some(&context))
+                    .ok(),
+                "{}",
+                expr
+            );
+        }
+    }
+}
@@ -23,7 +23,7 @@ use ir_common::generated::algebra as pb;
 use ir_common::generated::common as common_pb;
 
 use crate::apis::{Element, PropKey};
-use crate::utils::expr::eval::{apply_logical, Context, Evaluate, Evaluator, Operand};
+use crate::utils::expr::eval::{apply_logical, check_type_names, Context, Evaluate, Evaluator, Operand};
 use crate::utils::expr::{ExprEvalError, ExprEvalResult};
 
 /// The trait to define evaluating a predicate, which return `bool` value.
@@ -343,6 +343,7 @@ impl EvalPred for Operand {
                 Ok(true)
             }
             Operand::Concat(_) => Err(ExprEvalError::Unsupported("Concat".to_string())),
+            Operand::Udf { .. } => self.eval(_context)?.eval_bool(_context),
         }
     }
 }
@@ -351,7 +352,7 @@ impl EvalPred for UnaryPredicate {
     fn eval_bool<E: Element, C: Context<E>>(&self, context: Option<&C>) -> ExprEvalResult<bool> {
         use common_pb::Logical;
         match self.cmp {
-            Logical::Isnull => {
+            Logical::Isnull | Logical::Exists => {
                 let left = match self.operand.eval(context) {
                     Ok(left) => Ok(left),
                     Err(err) => match err {
@@ -385,7 +386,8 @@ impl EvalPred for Predicate {
             | Logical::Without
             | Logical::Startswith
             | Logical::Endswith
-            | Logical::Regex => Ok(apply_logical(
+            | Logical::Regex
+            | Logical::Istype => Ok(apply_logical(
                 &self.cmp,
                 self.left.eval(context)?.as_borrow_object(),
                 Some(self.right.eval(context)?.as_borrow_object()),
@@ -486,7 +488,9 @@ fn process_predicates(
                             | Logical::Startswith
                             | Logical::Endswith
                             | Logical::Regex
-                            | Logical::Isnull => partial.cmp(logical)?,
+                            | Logical::Istype
+                            | Logical::Isnull
+                            | Logical::Exists => partial.cmp(logical)?,
                             Logical::Not => is_not = true,
                             Logical::And | Logical::Or => {
                                 predicates = predicates.merge_partial(curr_cmp, partial, is_not)?;
@@ -533,7 +537,7 @@ fn process_predicates(
                         }
                     }
                 }
-                Item::Arith(_) => return Ok(None),
+                Item::Arith(_) | Item::Udf(_) => return Ok(None),
                 Item::Param(param) => {
                     return Err(ExprError::unsupported(format!("Dynamic Param {:?}", param)))
                 }
@@ -579,6 +583,7 @@ impl TryFrom<common_pb::Expression> for PEvaluator {
     type Error = ParsePbError;
 
     fn try_from(expr: common_pb::Expression) -> Result<Self, Self::Error> {
+        check_type_names(&expr.operators)?;
         let mut iter = expr.operators.iter();
         if let Some(pred) =
             process_predicates(&mut iter).map_err(|err| ParsePbError::ParseError(format!("{:?}", err)))?
@@ -1044,6 +1049,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_eval_predicates_exists() {
+        // [v0: id = 1, label = 9, age = 31, name = John, birthday = 19900416, hobbies = [football, guitar]]
+        // [v1: id = 2, label = 11, age = 26, name = Jimmy, birthday = 19950816]
+        let ctxt = prepare_context();
+        let cases: Vec<&str> = vec![
+            "exists @0.hobbies",                      // true
+            "!(exists @1.hobbies)",                   // true
+            "exists @1.hobbies || @1.age == 26",      // true
+            "@0.hobbies isType \"list\"",             // true
+            "@1.age isType \"string\"",               // false
+            "exists @1.age && @1.age isType \"int\"", // true
+        ];
+        let expected: Vec<bool> = vec![true, true, true, true, false, true];
+
+        for (case, expected) in cases.into_iter().zip(expected.into_iter()) {
+            let eval = PEvaluator::try_from(str_to_expr_pb(case.to_string()).unwrap()).unwrap();
+            assert!(matches!(eval, PEvaluator::Predicates(_)));
+            assert_eq!(
+                eval.eval_bool::<_, Vertices>(Some(&ctxt))
+                    .unwrap(),
+                expected
+            );
+        }
+        let unknown = str_to_expr_pb("exists @1.age && @1.age isType \"number\"".to_string()).unwrap();
+        assert!(PEvaluator::try_from(unknown).is_err());
+    }
+
     fn gen_regex_expression(to_match: &str, pattern: &str) -> common_pb::Expression {
         let mut regex_expr = str_to_expr_pb(to_match.to_string()).unwrap();
         let regex_opr = common_pb::ExprOpr {
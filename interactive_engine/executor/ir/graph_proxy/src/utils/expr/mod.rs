@@ -20,6 +20,7 @@ use dyn_type::CastError;
 use crate::utils::expr::eval::OperatorDesc;
 
 pub mod eval;
+pub mod eval_compiled;
 pub mod eval_pred;
 
 pub type ExprEvalResult<T> = Result<T, ExprEvalError>;
@@ -48,6 +49,8 @@ pub enum ExprEvalError {
     RegexError(regex::Error),
     /// Unsupported
     Unsupported(String),
+    /// The error of calling a user defined function, e.g., not registered, or trapped in the sandbox
+    UdfError(String),
     /// Other unknown errors that is converted from a error description
     OtherErr(String),
 }
@@ -69,6 +72,7 @@ impl Display for ExprEvalError {
             }
             GetNoneFromContext => write!(f, "get `None` from `Context`"),
             Unsupported(e) => write!(f, "unsupported: {}", e),
+            UdfError(e) => write!(f, "udf error: {}", e),
             OtherErr(e) => write!(f, "parse error {}", e),
             RegexError(e) => write!(f, "regex error {}", e),
         }
@@ -0,0 +1,212 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! A consistent-hashing ring of servers with virtual nodes.
+//!
+//! Each server is placed on the ring at `virtual_nodes` points, and a key is owned by the server of
+//! the first point clockwise from the hash of the key. When a server joins or leaves, only the keys
+//! between its points and their predecessors change owners, i.e. about `1 / n` of the keys, and the
+//! owner of a key is computed from the members only, so every process routes the same without a
+//! routing table. The hash is deterministic across processes and versions.
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+
+use crate::apis::partitioner::{PartitionKeyId, ServerId};
+
+pub const DEFAULT_VIRTUAL_NODES: usize = 128;
+
+/// The members and the points of the ring, which is immutable and swapped as a whole on changes.
+#[derive(Debug, Default)]
+struct Ring {
+    servers: BTreeSet<ServerId>,
+    // points sorted by hash, with the owner of each;
+    points: Vec<(u64, ServerId)>,
+}
+
+impl Ring {
+    fn build(servers: BTreeSet<ServerId>, virtual_nodes: usize) -> Self {
+        let mut points = Vec::with_capacity(servers.len() * virtual_nodes);
+        for server in servers.iter() {
+            for i in 0..virtual_nodes {
+                points.push((mix64(((*server as u64) << 32) | i as u64), *server));
+            }
+        }
+        // the ties are broken by the server id, so the ring is the same regardless of join order;
+        points.sort_unstable();
+        Ring { servers, points }
+    }
+
+    fn locate(&self, key: PartitionKeyId) -> Option<ServerId> {
+        if self.points.is_empty() {
+            return None;
+        }
+        let hash = mix64(key);
+        let idx = self
+            .points
+            .partition_point(|(point, _)| *point < hash);
+        let (_, server) = self.points[idx % self.points.len()];
+        Some(server)
+    }
+}
+
+pub struct HashRing {
+    virtual_nodes: usize,
+    ring: RwLock<Arc<Ring>>,
+}
+
+impl HashRing {
+    pub fn new(virtual_nodes: usize) -> Self {
+        HashRing { virtual_nodes: virtual_nodes.max(1), ring: RwLock::new(Arc::new(Ring::default())) }
+    }
+
+    pub fn with_servers<I: IntoIterator<Item = ServerId>>(virtual_nodes: usize, servers: I) -> Self {
+        let ring = HashRing::new(virtual_nodes);
+        ring.set_servers(servers);
+        ring
+    }
+
+    pub fn virtual_nodes(&self) -> usize {
+        self.virtual_nodes
+    }
+
+    /// The server owning the key, or `None` if the ring is empty.
+    pub fn get_server(&self, key: PartitionKeyId) -> Option<ServerId> {
+        self.current().locate(key)
+    }
+
+    pub fn get_servers(&self) -> Vec<ServerId> {
+        self.current().servers.iter().copied().collect()
+    }
+
+    /// Join a server; returns false if it's already a member.
+    pub fn add_server(&self, server: ServerId) -> bool {
+        self.update(|servers| servers.insert(server))
+    }
+
+    /// Leave a server; returns false if it's not a member.
+    pub fn remove_server(&self, server: ServerId) -> bool {
+        self.update(|servers| servers.remove(&server))
+    }
+
+    /// Replace the members, e.g. on the change of the peer view; the ring is rebuilt only if they changed.
+    pub fn set_servers<I: IntoIterator<Item = ServerId>>(&self, servers: I) -> bool {
+        let servers: BTreeSet<ServerId> = servers.into_iter().collect();
+        self.update(move |current| {
+            if *current == servers {
+                false
+            } else {
+                *current = servers;
+                true
+            }
+        })
+    }
+
+    /// The keys of `keys` owned by different servers on `self` and `other`, with their owners on
+    /// `self` and on `other`, i.e. the data to be moved when the ring changes from `self` to `other`.
+    pub fn moved_keys<I: IntoIterator<Item = PartitionKeyId>>(
+        &self, other: &HashRing, keys: I,
+    ) -> Vec<(PartitionKeyId, Option<ServerId>, Option<ServerId>)> {
+        let (from, to) = (self.current(), other.current());
+        keys.into_iter()
+            .filter_map(|key| {
+                let (src, dst) = (from.locate(key), to.locate(key));
+                if src != dst {
+                    Some((key, src, dst))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn current(&self) -> Arc<Ring> {
+        self.ring.read().unwrap().clone()
+    }
+
+    fn update<F: FnOnce(&mut BTreeSet<ServerId>) -> bool>(&self, f: F) -> bool {
+        let mut ring = self.ring.write().unwrap();
+        let mut servers = ring.servers.clone();
+        if f(&mut servers) {
+            *ring = Arc::new(Ring::build(servers, self.virtual_nodes));
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl std::fmt::Debug for HashRing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HashRing")
+            .field("virtual_nodes", &self.virtual_nodes)
+            .field("servers", &self.get_servers())
+            .finish()
+    }
+}
+
+/// The finalizer of MurmurHash3, which spreads the sequential ids evenly.
+#[inline]
+fn mix64(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^= h >> 33;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_ring() {
+        let ring = HashRing::new(DEFAULT_VIRTUAL_NODES);
+        assert_eq!(ring.get_server(1), None);
+        assert!(ring.set_servers(0..4));
+        assert!(!ring.set_servers(vec![3, 2, 1, 0]));
+        assert!(!ring.add_server(2));
+
+        let keys = 0..10000u64;
+        let mut counts = [0usize; 4];
+        for key in keys.clone() {
+            counts[ring.get_server(key).unwrap() as usize] += 1;
+        }
+        // every server owns a fair share of the keys;
+        assert!(counts.iter().all(|c| *c > 1500 && *c < 3500), "{:?}", counts);
+
+        // a joining server only takes keys from the others;
+        let grown = HashRing::with_servers(DEFAULT_VIRTUAL_NODES, 0..5);
+        let moved = ring.moved_keys(&grown, keys.clone());
+        assert!(moved.iter().all(|(_, _, to)| *to == Some(4)));
+        assert!(moved.len() > 1000 && moved.len() < 3000, "{}", moved.len());
+
+        // a leaving server only gives away its own keys;
+        assert!(ring.remove_server(1));
+        let shrunk = HashRing::with_servers(DEFAULT_VIRTUAL_NODES, vec![0, 2, 3]);
+        assert!(grown
+            .moved_keys(&grown, keys.clone())
+            .is_empty());
+        assert!(ring
+            .moved_keys(&shrunk, keys.clone())
+            .is_empty());
+        let moved = HashRing::with_servers(DEFAULT_VIRTUAL_NODES, 0..4).moved_keys(&ring, keys);
+        assert_eq!(moved.len(), counts[1]);
+        assert!(moved
+            .iter()
+            .all(|(_, from, _)| *from == Some(1)));
+    }
+}
@@ -14,3 +14,5 @@
 //! limitations under the License.
 
 pub mod expr;
+pub mod hash_ring;
+pub mod udf;
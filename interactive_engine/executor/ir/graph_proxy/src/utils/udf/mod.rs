@@ -0,0 +1,155 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The user defined functions registered in the engine by name, which are called by the `UdfCall` of the
+//! expressions as the scalar functions, and by the `USER_DEFINED` aggregate of the group as the aggregate
+//! functions, which are called on a single argument, i.e., the `Object::Vector` of the values of a group.
+//!
+//! A function of a high overhead per call, e.g., of the Python interpreter, is batched, which is called
+//! once on the arguments of the records of a batch when it is projected.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use dyn_type::Object;
+
+use crate::utils::expr::{ExprEvalError, ExprEvalResult};
+
+#[cfg(feature = "python_udf")]
+pub mod python;
+#[cfg(feature = "wasm_udf")]
+pub mod wasm;
+
+pub trait Udf: Send + Sync {
+    fn call(&self, args: Vec<Object>) -> ExprEvalResult<Object>;
+
+    /// Call the function on the arguments of each row, and return the results in the order of the rows.
+    fn call_batch(&self, rows: Vec<Vec<Object>>) -> ExprEvalResult<Vec<Object>> {
+        rows.into_iter()
+            .map(|args| self.call(args))
+            .collect()
+    }
+
+    /// Whether the function prefers to be called by `call_batch` on the rows of a batch.
+    fn is_batched(&self) -> bool {
+        false
+    }
+}
+
+impl<F> Udf for F
+where
+    F: Fn(Vec<Object>) -> ExprEvalResult<Object> + Send + Sync,
+{
+    fn call(&self, args: Vec<Object>) -> ExprEvalResult<Object> {
+        (self)(args)
+    }
+}
+
+lazy_static! {
+    static ref UDF_REGISTRY: RwLock<HashMap<String, Arc<dyn Udf>>> = RwLock::new(HashMap::new());
+}
+
+/// Register a function by the name, which replaces the function of the same name if any.
+pub fn register_udf<S: Into<String>>(name: S, udf: Arc<dyn Udf>) {
+    let name = name.into();
+    info!("register user defined function {}", name);
+    UDF_REGISTRY
+        .write()
+        .expect("udf registry poisoned")
+        .insert(name, udf);
+}
+
+/// Remove the function of the name, and return whether it was registered.
+pub fn unregister_udf(name: &str) -> bool {
+    UDF_REGISTRY
+        .write()
+        .expect("udf registry poisoned")
+        .remove(name)
+        .is_some()
+}
+
+pub fn get_udf(name: &str) -> Option<Arc<dyn Udf>> {
+    UDF_REGISTRY
+        .read()
+        .expect("udf registry poisoned")
+        .get(name)
+        .cloned()
+}
+
+pub fn call_udf(name: &str, args: Vec<Object>) -> ExprEvalResult<Object> {
+    let udf = get_udf(name)
+        .ok_or_else(|| ExprEvalError::UdfError(format!("function {} is not registered", name)))?;
+    udf.call(args)
+}
+
+pub fn call_udf_batch(name: &str, rows: Vec<Vec<Object>>) -> ExprEvalResult<Vec<Object>> {
+    let udf = get_udf(name)
+        .ok_or_else(|| ExprEvalError::UdfError(format!("function {} is not registered", name)))?;
+    let len = rows.len();
+    let results = udf.call_batch(rows)?;
+    if results.len() != len {
+        Err(ExprEvalError::UdfError(format!(
+            "function {} returns {} results of {} rows",
+            name,
+            results.len(),
+            len
+        )))
+    } else {
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dyn_type::object;
+
+    use super::*;
+
+    #[test]
+    fn test_register_udf() {
+        register_udf(
+            "test_register_udf_add",
+            Arc::new(|args: Vec<Object>| -> ExprEvalResult<Object> {
+                let mut sum = 0;
+                for arg in args {
+                    sum += arg.as_i64()?;
+                }
+                Ok(object!(sum))
+            }),
+        );
+        assert_eq!(
+            call_udf("test_register_udf_add", vec![object!(1), object!(2)]).unwrap(),
+            object!(3_i64)
+        );
+        assert!(unregister_udf("test_register_udf_add"));
+        assert!(matches!(
+            call_udf("test_register_udf_add", vec![object!(1)]),
+            Err(ExprEvalError::UdfError(_))
+        ));
+    }
+
+    #[test]
+    fn test_call_udf_batch() {
+        register_udf(
+            "test_call_udf_batch_neg",
+            Arc::new(|args: Vec<Object>| -> ExprEvalResult<Object> { Ok(object!(-args[0].as_i64()?)) }),
+        );
+        assert_eq!(
+            call_udf_batch("test_call_udf_batch_neg", vec![vec![object!(1)], vec![object!(2)]]).unwrap(),
+            vec![object!(-1_i64), object!(-2_i64)]
+        );
+        assert!(unregister_udf("test_call_udf_batch_neg"));
+    }
+}
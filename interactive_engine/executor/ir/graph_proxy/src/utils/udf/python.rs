@@ -0,0 +1,291 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Call the Python functions in the interpreter embedded by pyo3, which are batched, i.e., the GIL is
+//! acquired once for the rows of a batch. A function is either:
+//! * scalar, i.e., `f(*args)`, which is called on the arguments of each row;
+//! * vectorized, i.e., of the attribute `f.vectorized = True`, which is called once on the list of the
+//!   argument tuples of the rows, and returns the list of the results.
+//!
+//! The arguments and the results are converted between `Object` and Python, where `None`, `bool`, `int`,
+//! `float`, `str`, `bytes`, `list`, `tuple` and `dict` are supported.
+
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::Arc;
+
+use dyn_type::{Object, Primitives};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
+
+use crate::utils::expr::{ExprEvalError, ExprEvalResult};
+use crate::utils::udf::{register_udf, Udf};
+
+fn udf_error<E: Display>(func: &str, e: E) -> ExprEvalError {
+    ExprEvalError::UdfError(format!("python function {}: {}", func, e))
+}
+
+fn to_py(py: Python<'_>, obj: &Object) -> PyObject {
+    match obj {
+        Object::Primitive(Primitives::Byte(v)) => v.to_object(py),
+        Object::Primitive(Primitives::Integer(v)) => v.to_object(py),
+        Object::Primitive(Primitives::Long(v)) => v.to_object(py),
+        Object::Primitive(Primitives::ULLong(v)) => v.to_object(py),
+        Object::Primitive(Primitives::Float(v)) => v.to_object(py),
+        Object::String(s) => s.to_object(py),
+        Object::Vector(vec) => PyList::new(py, vec.iter().map(|obj| to_py(py, obj))).to_object(py),
+        Object::KV(kv) => {
+            let dict = PyDict::new(py);
+            for (key, value) in kv {
+                // a key of the types above, e.g., `int` or `str`, is hashable
+                let _ = dict.set_item(to_py(py, key), to_py(py, value));
+            }
+            dict.to_object(py)
+        }
+        Object::Blob(blob) => PyBytes::new(py, blob).to_object(py),
+        Object::DateFormat(_) | Object::DynOwned(_) => obj.to_string().to_object(py),
+        Object::None => py.None(),
+    }
+}
+
+fn from_py(obj: &PyAny) -> PyResult<Object> {
+    // `bool` is a subclass of `int`, which is checked first
+    if obj.is_none() {
+        Ok(Object::None)
+    } else if let Ok(b) = obj.downcast::<PyBool>() {
+        Ok(b.is_true().into())
+    } else if obj.downcast::<PyLong>().is_ok() {
+        Ok(obj.extract::<i64>()?.into())
+    } else if obj.downcast::<PyFloat>().is_ok() {
+        Ok(obj.extract::<f64>()?.into())
+    } else if let Ok(s) = obj.downcast::<PyString>() {
+        Ok(s.to_str()?.into())
+    } else if let Ok(bytes) = obj.downcast::<PyBytes>() {
+        Ok(Object::Blob(bytes.as_bytes().into()))
+    } else if let Ok(list) = obj.downcast::<PyList>() {
+        Ok(Object::Vector(
+            list.iter()
+                .map(from_py)
+                .collect::<PyResult<_>>()?,
+        ))
+    } else if let Ok(tuple) = obj.downcast::<PyTuple>() {
+        Ok(Object::Vector(
+            tuple
+                .iter()
+                .map(from_py)
+                .collect::<PyResult<_>>()?,
+        ))
+    } else if let Ok(dict) = obj.downcast::<PyDict>() {
+        let mut kv = std::collections::BTreeMap::new();
+        for (key, value) in dict.iter() {
+            kv.insert(from_py(key)?, from_py(value)?);
+        }
+        Ok(Object::KV(kv))
+    } else {
+        Err(pyo3::exceptions::PyTypeError::new_err(format!(
+            "unsupported result type {}",
+            obj.get_type().name()?
+        )))
+    }
+}
+
+pub struct PythonUdf {
+    func_name: String,
+    func: PyObject,
+    vectorized: bool,
+}
+
+impl PythonUdf {
+    /// Load the module of the source code, calling its function `func`.
+    pub fn from_code(code: &str, module: &str, func: &str) -> ExprEvalResult<Self> {
+        Python::with_gil(|py| {
+            let module = PyModule::from_code(py, code, &format!("{}.py", module), module)
+                .map_err(|e| udf_error(func, e))?;
+            let py_func = module
+                .getattr(func)
+                .map_err(|e| udf_error(func, e))?;
+            if !py_func.is_callable() {
+                return Err(udf_error(func, "not callable"));
+            }
+            let vectorized = py_func
+                .getattr("vectorized")
+                .and_then(|v| v.extract::<bool>())
+                .unwrap_or(false);
+            Ok(PythonUdf { func_name: func.to_string(), func: py_func.into_py(py), vectorized })
+        })
+    }
+
+    fn call_scalar(&self, py: Python<'_>, args: Vec<Object>) -> ExprEvalResult<Object> {
+        let args = PyTuple::new(py, args.iter().map(|arg| to_py(py, arg)));
+        self.func
+            .as_ref(py)
+            .call1(args)
+            .and_then(from_py)
+            .map_err(|e| udf_error(&self.func_name, e))
+    }
+}
+
+impl Udf for PythonUdf {
+    fn call(&self, args: Vec<Object>) -> ExprEvalResult<Object> {
+        let mut results = self.call_batch(vec![args])?;
+        if results.len() != 1 {
+            Err(udf_error(&self.func_name, format!("returns {} results of a row", results.len())))
+        } else {
+            Ok(results.pop().unwrap())
+        }
+    }
+
+    fn call_batch(&self, rows: Vec<Vec<Object>>) -> ExprEvalResult<Vec<Object>> {
+        Python::with_gil(|py| {
+            if self.vectorized {
+                let rows = PyList::new(
+                    py,
+                    rows.iter()
+                        .map(|args| PyTuple::new(py, args.iter().map(|arg| to_py(py, arg)))),
+                );
+                let results = self
+                    .func
+                    .as_ref(py)
+                    .call1((rows,))
+                    .map_err(|e| udf_error(&self.func_name, e))?;
+                match from_py(results).map_err(|e| udf_error(&self.func_name, e))? {
+                    Object::Vector(results) => Ok(results),
+                    _ => Err(udf_error(&self.func_name, "a vectorized function must return a list")),
+                }
+            } else {
+                rows.into_iter()
+                    .map(|args| self.call_scalar(py, args))
+                    .collect()
+            }
+        })
+    }
+
+    fn is_batched(&self) -> bool {
+        true
+    }
+}
+
+/// Register the function of each `<name>.py` in the directory, which is the function `<name>` of the
+/// module, and return the names of the functions.
+pub fn register_python_udfs<P: AsRef<Path>>(dir: P) -> ExprEvalResult<Vec<String>> {
+    let mut names = vec![];
+    let entries = std::fs::read_dir(dir).map_err(|e| ExprEvalError::UdfError(e.to_string()))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| ExprEvalError::UdfError(e.to_string()))?
+            .path();
+        if path.extension().map(|ext| ext == "py") != Some(true) {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+            let code = std::fs::read_to_string(&path).map_err(|e| udf_error(name, e))?;
+            register_udf(name, Arc::new(PythonUdf::from_code(&code, name, name)?));
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use dyn_type::object;
+
+    use super::*;
+
+    const FEATURES: &str = r#"
+def concat(a, b):
+    return "%s_%s" % (a, b)
+
+def scale(rows):
+    return [x * 2.0 for (x,) in rows]
+scale.vectorized = True
+
+def broken(rows):
+    return 1
+broken.vectorized = True
+"#;
+
+    #[test]
+    fn test_python_udf_scalar() {
+        let concat = PythonUdf::from_code(FEATURES, "features", "concat").unwrap();
+        assert!(!concat.vectorized);
+        assert_eq!(
+            concat
+                .call(vec![object!("a"), object!(1)])
+                .unwrap(),
+            object!("a_1")
+        );
+        assert_eq!(
+            concat
+                .call_batch(vec![vec![object!("a"), object!(1)], vec![object!("b"), object!(2)]])
+                .unwrap(),
+            vec![object!("a_1"), object!("b_2")]
+        );
+    }
+
+    #[test]
+    fn test_python_udf_vectorized() {
+        let scale = PythonUdf::from_code(FEATURES, "features", "scale").unwrap();
+        assert!(scale.vectorized);
+        assert_eq!(
+            scale
+                .call_batch(vec![vec![object!(1)], vec![object!(1.5)]])
+                .unwrap(),
+            vec![object!(2.0), object!(3.0)]
+        );
+    }
+
+    #[test]
+    fn test_python_udf_invalid() {
+        assert!(PythonUdf::from_code(FEATURES, "features", "absent").is_err());
+        assert!(PythonUdf::from_code("def f(:", "invalid", "f").is_err());
+        let broken = PythonUdf::from_code(FEATURES, "features", "broken").unwrap();
+        assert!(matches!(broken.call(vec![object!(1)]), Err(ExprEvalError::UdfError(_))));
+        let concat = PythonUdf::from_code(FEATURES, "features", "concat").unwrap();
+        assert!(matches!(concat.call(vec![object!(1)]), Err(ExprEvalError::UdfError(_))));
+    }
+
+    #[test]
+    fn test_python_object_conversion() {
+        let obj = Object::Vector(vec![
+            object!(1),
+            object!("a"),
+            Object::None,
+            Object::Blob(vec![1_u8, 2].into()),
+            Object::KV(
+                vec![(object!("k"), object!(1.5))]
+                    .into_iter()
+                    .collect(),
+            ),
+        ]);
+        Python::with_gil(|py| {
+            let py_obj = to_py(py, &obj);
+            assert_eq!(
+                from_py(py_obj.as_ref(py)).unwrap(),
+                Object::Vector(vec![
+                    object!(1_i64),
+                    object!("a"),
+                    Object::None,
+                    Object::Blob(vec![1_u8, 2].into()),
+                    Object::KV(
+                        vec![(object!("k"), object!(1.5))]
+                            .into_iter()
+                            .collect()
+                    ),
+                ])
+            );
+        });
+    }
+}
@@ -0,0 +1,346 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Run the functions exported by the WASM modules in the sandbox of wasmtime, so that the untrusted
+//! functions of the tenants are safe to run in the engine:
+//! * a module can not import anything, i.e., it reaches nothing of the host but its own memory;
+//! * each call runs in a fresh instance, metered by the fuel and bounded by the memory limit, and a
+//!   trap, e.g., of the fuel exhausted, fails the call instead of the engine.
+//!
+//! The arguments, as an `Object::Vector`, and the result are `Object`s encoded by the codec of `dyn_type`,
+//! which are passed through the memory of the instance, where the module exports:
+//! * `memory`: the linear memory;
+//! * `alloc(len: i32) -> i32`: allocate `len` bytes for the encoded arguments;
+//! * `<func>(ptr: i32, len: i32) -> i64`: call the function on the encoded arguments, and return the
+//!   encoded result in the memory as `ptr << 32 | len`.
+//!
+//! The result is decoded by `read_result` rather than `Object::read_from`, which trusts the lengths
+//! and the nesting of the bytes written by the guest.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use dyn_type::{DateTimeFormats, Object, Primitives};
+use pegasus_common::codec::{Decode, Encode, ReadExt};
+use wasmtime::{
+    Config, Engine, ExternType, InstancePre, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+    ValType,
+};
+
+use crate::utils::expr::{ExprEvalError, ExprEvalResult};
+use crate::utils::udf::{register_udf, Udf};
+
+lazy_static! {
+    static ref WASM_ENGINE: Engine = {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        Engine::new(&config).expect("create wasm engine failure")
+    };
+}
+
+/// The limits of a call of the function.
+#[derive(Debug, Clone, Copy)]
+pub struct WasmLimits {
+    /// The fuel of a call, roughly the number of the wasm instructions executed
+    pub fuel: u64,
+    /// The maximum bytes of the memory of a call
+    pub max_memory_bytes: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        WasmLimits { fuel: 10_000_000, max_memory_bytes: 64 << 20 }
+    }
+}
+
+fn udf_error<E: Display>(func: &str, e: E) -> ExprEvalError {
+    ExprEvalError::UdfError(format!("wasm function {}: {}", func, e))
+}
+
+/// The maximum nesting of the vectors and the maps of a result.
+const MAX_RESULT_DEPTH: usize = 64;
+
+fn invalid_result<E: Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid result: {}", e))
+}
+
+/// Read the length of `min_size` bytes at least per item, which never exceeds the bytes remaining.
+fn read_len(reader: &mut &[u8], len: u64, min_size: usize) -> io::Result<usize> {
+    match usize::try_from(len) {
+        Ok(len) if len.saturating_mul(min_size) <= reader.len() => Ok(len),
+        _ => Err(invalid_result(format!("length {} beyond the {} bytes remaining", len, reader.len()))),
+    }
+}
+
+/// Decode the result of a call as `Object::read_from` does, except that the lengths are checked
+/// against the bytes remaining before anything is allocated, the nesting is bounded, and the dynamic
+/// objects of the host, which no guest can encode, are rejected.
+fn read_result(reader: &mut &[u8], depth: usize) -> io::Result<Object> {
+    if depth > MAX_RESULT_DEPTH {
+        return Err(invalid_result(format!("nested deeper than {}", MAX_RESULT_DEPTH)));
+    }
+    match reader.read_u8()? {
+        0 => Ok(Object::Primitive(Primitives::read_from(reader)?)),
+        1 => {
+            let len = reader.read_u32()?;
+            let len = read_len(reader, len as u64, 1)?;
+            let (bytes, rest) = reader.split_at(len);
+            let str = String::from_utf8(bytes.to_vec()).map_err(invalid_result)?;
+            *reader = rest;
+            Ok(Object::String(str))
+        }
+        2 => {
+            let len = reader.read_u32()?;
+            // an object is encoded in a byte at least
+            let len = read_len(reader, len as u64, 1)?;
+            let mut vec = Vec::with_capacity(len);
+            for _ in 0..len {
+                vec.push(read_result(reader, depth + 1)?);
+            }
+            Ok(Object::Vector(vec))
+        }
+        3 => {
+            let len = reader.read_u64()?;
+            let len = read_len(reader, len, 2)?;
+            let mut map = BTreeMap::new();
+            for _ in 0..len {
+                let key = read_result(reader, depth + 1)?;
+                let value = read_result(reader, depth + 1)?;
+                map.insert(key, value);
+            }
+            Ok(Object::KV(map))
+        }
+        4 => {
+            let len = reader.read_u64()?;
+            let len = read_len(reader, len, 1)?;
+            let (bytes, rest) = reader.split_at(len);
+            let blob = bytes.to_vec().into_boxed_slice();
+            *reader = rest;
+            Ok(Object::Blob(blob))
+        }
+        6 => Ok(Object::None),
+        7 => Ok(Object::DateFormat(DateTimeFormats::read_from(reader)?)),
+        e => Err(invalid_result(format!("unsupported object type {}", e))),
+    }
+}
+
+pub struct WasmUdf {
+    func: String,
+    instance_pre: InstancePre<StoreLimits>,
+    limits: WasmLimits,
+}
+
+impl WasmUdf {
+    /// Compile the module, in binary or text, calling its export `func`.
+    pub fn new<B: AsRef<[u8]>>(wasm: B, func: &str, limits: WasmLimits) -> ExprEvalResult<Self> {
+        let module = Module::new(&WASM_ENGINE, wasm).map_err(|e| udf_error(func, e))?;
+        let expected = [
+            ("memory", None),
+            ("alloc", Some((vec![ValType::I32], vec![ValType::I32]))),
+            (func, Some((vec![ValType::I32, ValType::I32], vec![ValType::I64]))),
+        ];
+        for (name, signature) in expected {
+            let valid = match (module.get_export(name), signature) {
+                (Some(ExternType::Memory(_)), None) => true,
+                (Some(ExternType::Func(ty)), Some((params, results))) => {
+                    ty.params().eq(params) && ty.results().eq(results)
+                }
+                _ => false,
+            };
+            if !valid {
+                return Err(udf_error(func, format!("missing or invalid export {}", name)));
+            }
+        }
+        // no host function is linked, and a module importing anything is rejected
+        let instance_pre = Linker::new(&WASM_ENGINE)
+            .instantiate_pre(&module)
+            .map_err(|e| udf_error(func, e))?;
+        Ok(WasmUdf { func: func.to_string(), instance_pre, limits })
+    }
+}
+
+impl Udf for WasmUdf {
+    fn call(&self, args: Vec<Object>) -> ExprEvalResult<Object> {
+        let func = self.func.as_str();
+        let mut bytes = vec![];
+        Object::Vector(args)
+            .write_to(&mut bytes)
+            .map_err(|e| udf_error(func, e))?;
+        let len = i32::try_from(bytes.len()).map_err(|e| udf_error(func, e))?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.max_memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&WASM_ENGINE, limits);
+        store.limiter(|limits| limits);
+        store
+            .add_fuel(self.limits.fuel)
+            .map_err(|e| udf_error(func, e))?;
+        let instance = self
+            .instance_pre
+            .instantiate(&mut store)
+            .map_err(|e| udf_error(func, e))?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| udf_error(func, "missing memory"))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(|e| udf_error(func, e))?;
+        let call = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, func)
+            .map_err(|e| udf_error(func, e))?;
+
+        let ptr = alloc
+            .call(&mut store, len)
+            .map_err(|e| udf_error(func, e))?;
+        memory
+            .write(&mut store, ptr as u32 as usize, &bytes)
+            .map_err(|e| udf_error(func, e))?;
+        let packed = call
+            .call(&mut store, (ptr, len))
+            .map_err(|e| udf_error(func, e))?;
+        let (ptr, len) = ((packed >> 32) as u32 as usize, packed as u32 as usize);
+        let mut result = memory
+            .data(&store)
+            .get(ptr..ptr + len)
+            .ok_or_else(|| udf_error(func, "result out of the memory"))?;
+        read_result(&mut result, 0).map_err(|e| udf_error(func, e))
+    }
+}
+
+/// Register the function of each `<name>.wasm` in the directory, which is the export `<name>` of the
+/// module, and return the names of the functions.
+pub fn register_wasm_udfs<P: AsRef<Path>>(dir: P, limits: WasmLimits) -> ExprEvalResult<Vec<String>> {
+    let mut names = vec![];
+    let entries = std::fs::read_dir(dir).map_err(|e| ExprEvalError::UdfError(e.to_string()))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| ExprEvalError::UdfError(e.to_string()))?
+            .path();
+        if path.extension().map(|ext| ext == "wasm") != Some(true) {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+            let wasm = std::fs::read(&path).map_err(|e| udf_error(name, e))?;
+            register_udf(name, Arc::new(WasmUdf::new(wasm, name, limits)?));
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use dyn_type::object;
+
+    use super::*;
+
+    /// Return the encoded arguments as the result, i.e., the `Object::Vector` of the arguments.
+    const ECHO: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "echo") (param $ptr i32) (param $len i32) (result i64)
+                (i64.or
+                    (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                    (i64.extend_i32_u (local.get $len))))
+            (func (export "spin") (param i32 i32) (result i64)
+                (loop $l (br $l))
+                (i64.const 0))
+            (func (export "grow") (param i32 i32) (result i64)
+                (if (i32.eq (memory.grow (i32.const 2048)) (i32.const -1)) (then unreachable))
+                (i64.const 0)))
+    "#;
+
+    #[test]
+    fn test_wasm_udf_call() {
+        let echo = WasmUdf::new(ECHO, "echo", WasmLimits::default()).unwrap();
+        let args = vec![object!(1), object!("a")];
+        assert_eq!(echo.call(args.clone()).unwrap(), Object::Vector(args));
+    }
+
+    #[test]
+    fn test_wasm_udf_fuel() {
+        let spin = WasmUdf::new(ECHO, "spin", WasmLimits::default()).unwrap();
+        assert!(matches!(spin.call(vec![]), Err(ExprEvalError::UdfError(_))));
+    }
+
+    #[test]
+    fn test_wasm_udf_memory() {
+        // 2048 pages of 64KB exceed the 64MB by default
+        let grow = WasmUdf::new(ECHO, "grow", WasmLimits::default()).unwrap();
+        assert!(matches!(grow.call(vec![]), Err(ExprEvalError::UdfError(_))));
+    }
+
+    /// Return the bytes of the data segment as the result.
+    fn result_module(data: &str, len: usize) -> String {
+        format!(
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{}")
+                (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                (func (export "f") (param i32 i32) (result i64) (i64.const {})))
+            "#,
+            data, len
+        )
+    }
+
+    #[test]
+    fn test_wasm_udf_invalid_result() {
+        // a vector of u32::MAX objects in 5 bytes
+        let huge =
+            WasmUdf::new(result_module("\\02\\ff\\ff\\ff\\ff", 5), "f", WasmLimits::default()).unwrap();
+        assert!(matches!(huge.call(vec![]), Err(ExprEvalError::UdfError(_))));
+        // a string longer than the result
+        let long =
+            WasmUdf::new(result_module("\\01\\10\\00\\00\\00a", 6), "f", WasmLimits::default()).unwrap();
+        assert!(matches!(long.call(vec![]), Err(ExprEvalError::UdfError(_))));
+        // vectors of a single vector nested deeper than the limit
+        let nested = "\\02\\01\\00\\00\\00".repeat(MAX_RESULT_DEPTH + 1) + "\\06";
+        let deep = WasmUdf::new(
+            result_module(&nested, 5 * (MAX_RESULT_DEPTH + 1) + 1),
+            "f",
+            WasmLimits::default(),
+        )
+        .unwrap();
+        assert!(matches!(deep.call(vec![]), Err(ExprEvalError::UdfError(_))));
+        // nested within the limit
+        let nested = "\\02\\01\\00\\00\\00".repeat(MAX_RESULT_DEPTH) + "\\06";
+        let shallow =
+            WasmUdf::new(result_module(&nested, 5 * MAX_RESULT_DEPTH + 1), "f", WasmLimits::default())
+                .unwrap();
+        assert!(shallow.call(vec![]).is_ok());
+    }
+
+    #[test]
+    fn test_wasm_udf_invalid() {
+        assert!(WasmUdf::new(ECHO, "absent", WasmLimits::default()).is_err());
+        // importing a host function to escape the sandbox
+        let import = r#"
+            (module
+                (import "env" "host" (func))
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "f") (param i32 i32) (result i64) (call 0) (i64.const 0)))
+        "#;
+        assert!(WasmUdf::new(import, "f", WasmLimits::default()).is_err());
+    }
+}
@@ -36,6 +36,7 @@ pub mod benchmark {
     use pegasus::{run_opt, Configuration, JobConf, StartupError};
     use pegasus_server::job::{JobAssembly, JobDesc};
     use pegasus_server::rpc::RpcSink;
+    use pegasus_server::scheduling::SchedulingClass;
     use pegasus_server::JobRequest;
     use prost::Message;
     use runtime::process::entry::DynEntry;
@@ -103,7 +104,19 @@ pub mod benchmark {
         let cancel_hook = sink.get_cancel_hook().clone();
         let results = ResultStream::new(conf.job_id, cancel_hook, rx);
         let service = &FACTORY;
-        let job = JobDesc { input: job_req.source, plan: job_req.plan, resource: job_req.resource };
+        let job = JobDesc {
+            input: job_req.source,
+            plan: job_req.plan,
+            resource: job_req.resource,
+            principal: None,
+            session: None,
+            view: None,
+            include_deleted: false,
+            consistency: None,
+            scheduling_class: SchedulingClass::Interactive,
+            provenance: false,
+            result_limit: None,
+        };
         run_opt(conf, sink, move |worker| service.assemble(&job, worker)).expect("submit job failure;");
         results
     }
@@ -151,6 +164,8 @@ pub mod benchmark {
             predicate,
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         }
     }
 
@@ -166,6 +181,8 @@ pub mod benchmark {
             predicate,
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         }
     }
 
@@ -282,6 +299,7 @@ pub mod benchmark {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         }
     }
 
@@ -307,6 +325,7 @@ pub mod benchmark {
                 vars: vec![],
                 aggregate: 3, // count
                 alias: None,
+                udf: String::new(),
             }],
             meta_data: vec![],
         }
@@ -320,6 +339,8 @@ pub mod benchmark {
         Some(pb::sink::SinkTarget {
             inner: Some(pb::sink::sink_target::Inner::SinkDefault(pb::SinkDefault {
                 id_name_mappings: vec![],
+                format: 0,
+                columns: vec![],
             })),
         })
     }
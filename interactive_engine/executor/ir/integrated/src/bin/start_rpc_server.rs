@@ -25,6 +25,14 @@ use structopt::StructOpt;
 struct Config {
     #[structopt(long = "config", parse(from_os_str))]
     config_dir: PathBuf,
+    /// The directory of the WASM modules of the udfs, each `<name>.wasm` exporting `<name>`
+    #[cfg(feature = "wasm_udf")]
+    #[structopt(long = "udf_dir", parse(from_os_str))]
+    udf_dir: Option<PathBuf>,
+    /// The directory of the Python udfs, each `<name>.py` defining the function `<name>`
+    #[cfg(feature = "python_udf")]
+    #[structopt(long = "python_udf_dir", parse(from_os_str))]
+    python_udf_dir: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -32,6 +40,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     pegasus_common::logs::init_log();
     let config: Config = Config::from_args();
     let (server_config, rpc_config) = pegasus_server::config::load_configs(config.config_dir).unwrap();
+    #[cfg(feature = "wasm_udf")]
+    if let Some(udf_dir) = config.udf_dir {
+        use graph_proxy::utils::udf::wasm::{register_wasm_udfs, WasmLimits};
+        let names = register_wasm_udfs(udf_dir, WasmLimits::default())?;
+        info!("registered user defined functions {:?}", names);
+    }
+    #[cfg(feature = "python_udf")]
+    if let Some(python_udf_dir) = config.python_udf_dir {
+        use graph_proxy::utils::udf::python::register_python_udfs;
+        let names = register_python_udfs(python_udf_dir)?;
+        info!("registered python user defined functions {:?}", names);
+    }
 
     let num_servers = server_config.servers_size();
     let cluster_info = Arc::new(PegasusClusterInfo::default());
@@ -0,0 +1,335 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use dyn_type::Object;
+    use graph_proxy::apis::GraphElement;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::DefaultId;
+    use ir_common::expr_parse::str_to_expr_pb;
+    use ir_common::generated::algebra as pb;
+    use ir_physical_client::physical_builder::*;
+    use pegasus_server::JobRequest;
+    use runtime::process::entry::Entry;
+
+    use crate::common::test::*;
+
+    fn gen_algorithm_opr(kind: pb::graph_algorithm::Kind) -> pb::GraphAlgorithm {
+        pb::GraphAlgorithm {
+            kind: kind as i32,
+            params: Some(query_params(vec![], vec![], None)),
+            max_iterations: 0,
+            damping: 0.0,
+            alias: Some(TAG_A.into()),
+            global: false,
+            weight: None,
+            source: None,
+        }
+    }
+
+    // g.V().<algorithm>().as('a')
+    fn init_scan_algorithm_request(algorithm: pb::GraphAlgorithm) -> JobRequest {
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: Some(query_params(vec![], vec![], None)),
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        };
+
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.algorithm(algorithm);
+        job_builder.sink(default_sink_pb());
+
+        job_builder.build().unwrap()
+    }
+
+    fn collect_values(request: JobRequest, worker_num: u32) -> HashMap<i64, Object> {
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = HashMap::new();
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let vertex = record.get(None).unwrap().as_vertex().unwrap();
+                    let value = record
+                        .get(Some(TAG_A))
+                        .unwrap()
+                        .as_object()
+                        .unwrap()
+                        .clone();
+                    result_collection.insert(vertex.id(), value);
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        result_collection
+    }
+
+    fn collect_global_value(request: JobRequest, worker_num: u32) -> Object {
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let value = record
+                        .get(Some(TAG_A))
+                        .unwrap()
+                        .as_object()
+                        .unwrap()
+                        .clone();
+                    result_collection.push(value);
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        assert_eq!(result_collection.len(), 1);
+        result_collection.pop().unwrap()
+    }
+
+    fn to_global_id(id: usize, label: u8) -> i64 {
+        let global_id: DefaultId = LDBCVertexParser::to_global_id(id, label);
+        global_id as i64
+    }
+
+    fn all_vertices() -> Vec<i64> {
+        vec![
+            to_global_id(1, 0),
+            to_global_id(2, 0),
+            to_global_id(3, 1),
+            to_global_id(4, 0),
+            to_global_id(5, 1),
+            to_global_id(6, 0),
+        ]
+    }
+
+    fn scan_wcc(worker_num: u32) {
+        initialize();
+        let request = init_scan_algorithm_request(gen_algorithm_opr(pb::graph_algorithm::Kind::Wcc));
+        let values = collect_values(request, worker_num);
+        // the modern graph is connected, so all the vertices are labeled by the smallest id
+        let min_id = all_vertices().into_iter().min().unwrap();
+        assert_eq!(values.len(), 6);
+        for id in all_vertices() {
+            assert_eq!(values.get(&id).unwrap().as_i64().unwrap(), min_id);
+        }
+    }
+
+    fn scan_lpa(worker_num: u32) {
+        initialize();
+        let request = init_scan_algorithm_request(gen_algorithm_opr(pb::graph_algorithm::Kind::Lpa));
+        let values = collect_values(request, worker_num);
+        let vertices = all_vertices();
+        assert_eq!(values.len(), 6);
+        for id in vertices.iter() {
+            let label = values.get(id).unwrap().as_i64().unwrap();
+            assert!(vertices.contains(&label));
+        }
+    }
+
+    fn scan_page_rank(worker_num: u32) {
+        initialize();
+        let request = init_scan_algorithm_request(gen_algorithm_opr(pb::graph_algorithm::Kind::PageRank));
+        let values = collect_values(request, worker_num);
+        let ranks: HashMap<i64, f64> = values
+            .into_iter()
+            .map(|(id, value)| (id, value.as_f64().unwrap()))
+            .collect();
+        assert_eq!(ranks.len(), 6);
+        // v1 and v6 have no incoming edges, so their ranks are (1 - damping)
+        assert!((ranks[&to_global_id(1, 0)] - 0.15).abs() < 1e-6);
+        assert!((ranks[&to_global_id(6, 0)] - 0.15).abs() < 1e-6);
+        // v3 is pointed to by v1, v4 and v6
+        let v3_rank = ranks[&to_global_id(3, 1)];
+        assert!(ranks.values().all(|rank| *rank <= v3_rank));
+    }
+
+    fn scan_weighted_page_rank(worker_num: u32) {
+        initialize();
+        let mut page_rank = gen_algorithm_opr(pb::graph_algorithm::Kind::PageRank);
+        page_rank.weight = str_to_expr_pb("@.weight".to_string()).ok();
+        let values = collect_values(init_scan_algorithm_request(page_rank), worker_num);
+        let ranks: HashMap<i64, f64> = values
+            .into_iter()
+            .map(|(id, value)| (id, value.as_f64().unwrap()))
+            .collect();
+        assert_eq!(ranks.len(), 6);
+        // v2 and v4 are both pointed to by v1 only, along the edges weighted by 0.5 and 1.0
+        assert!(ranks[&to_global_id(2, 0)] < ranks[&to_global_id(4, 0)]);
+    }
+
+    // the shortest paths from v1 along the out edges, weighted by the reciprocal of `weight`
+    fn scan_shortest_path(worker_num: u32) {
+        initialize();
+        let mut shortest_path = gen_algorithm_opr(pb::graph_algorithm::Kind::ShortestPath);
+        shortest_path.weight = str_to_expr_pb("1.0 / @.weight".to_string()).ok();
+        shortest_path.source = str_to_expr_pb("@.name == \"marko\"".to_string()).ok();
+        let values = collect_values(init_scan_algorithm_request(shortest_path), worker_num);
+        let distances: HashMap<i64, f64> = values
+            .into_iter()
+            .map(|(id, value)| (id, value.as_f64().unwrap()))
+            .collect();
+        assert_eq!(distances.len(), 6);
+        let expected = vec![
+            (to_global_id(1, 0), 0.0),
+            (to_global_id(2, 0), 2.0),
+            (to_global_id(3, 1), 2.5),
+            (to_global_id(4, 0), 1.0),
+            (to_global_id(5, 1), 2.0),
+        ];
+        for (id, distance) in expected {
+            assert!((distances[&id] - distance).abs() < 1e-6);
+        }
+        // v6 is not reachable from v1
+        assert!(distances[&to_global_id(6, 0)].is_infinite());
+    }
+
+    // the only triangle of the modern graph is (v1, v3, v4), ignoring the directions of the edges
+    fn scan_triangle_count(worker_num: u32) {
+        initialize();
+        let request =
+            init_scan_algorithm_request(gen_algorithm_opr(pb::graph_algorithm::Kind::TriangleCount));
+        let values = collect_values(request, worker_num);
+        assert_eq!(values.len(), 6);
+        let in_triangle = vec![to_global_id(1, 0), to_global_id(3, 1), to_global_id(4, 0)];
+        for id in all_vertices() {
+            let expected = if in_triangle.contains(&id) { 1 } else { 0 };
+            assert_eq!(values.get(&id).unwrap().as_u64().unwrap(), expected);
+        }
+    }
+
+    fn scan_clustering_coefficient(worker_num: u32) {
+        initialize();
+        let request = init_scan_algorithm_request(gen_algorithm_opr(
+            pb::graph_algorithm::Kind::ClusteringCoefficient,
+        ));
+        let values = collect_values(request, worker_num);
+        assert_eq!(values.len(), 6);
+        // v1, v3 and v4 have three neighbors each, and v2, v5 and v6 have only one
+        let in_triangle = vec![to_global_id(1, 0), to_global_id(3, 1), to_global_id(4, 0)];
+        for id in all_vertices() {
+            let expected = if in_triangle.contains(&id) { 1.0 / 3.0 } else { 0.0 };
+            assert!((values.get(&id).unwrap().as_f64().unwrap() - expected).abs() < 1e-6);
+        }
+    }
+
+    fn scan_global_triangle(worker_num: u32) {
+        initialize();
+        let mut triangle_count = gen_algorithm_opr(pb::graph_algorithm::Kind::TriangleCount);
+        triangle_count.global = true;
+        let value = collect_global_value(init_scan_algorithm_request(triangle_count), worker_num);
+        assert_eq!(value.as_u64().unwrap(), 1);
+
+        let mut clustering_coefficient =
+            gen_algorithm_opr(pb::graph_algorithm::Kind::ClusteringCoefficient);
+        clustering_coefficient.global = true;
+        let value = collect_global_value(init_scan_algorithm_request(clustering_coefficient), worker_num);
+        assert!((value.as_f64().unwrap() - 1.0 / 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn scan_wcc_test() {
+        scan_wcc(1)
+    }
+
+    #[test]
+    fn scan_wcc_w2_test() {
+        scan_wcc(2)
+    }
+
+    #[test]
+    fn scan_lpa_test() {
+        scan_lpa(1)
+    }
+
+    #[test]
+    fn scan_lpa_w2_test() {
+        scan_lpa(2)
+    }
+
+    #[test]
+    fn scan_page_rank_test() {
+        scan_page_rank(1)
+    }
+
+    #[test]
+    fn scan_page_rank_w2_test() {
+        scan_page_rank(2)
+    }
+
+    #[test]
+    fn scan_weighted_page_rank_test() {
+        scan_weighted_page_rank(1)
+    }
+
+    #[test]
+    fn scan_weighted_page_rank_w2_test() {
+        scan_weighted_page_rank(2)
+    }
+
+    #[test]
+    fn scan_shortest_path_test() {
+        scan_shortest_path(1)
+    }
+
+    #[test]
+    fn scan_shortest_path_w2_test() {
+        scan_shortest_path(2)
+    }
+
+    #[test]
+    fn scan_triangle_count_test() {
+        scan_triangle_count(1)
+    }
+
+    #[test]
+    fn scan_triangle_count_w2_test() {
+        scan_triangle_count(2)
+    }
+
+    #[test]
+    fn scan_clustering_coefficient_test() {
+        scan_clustering_coefficient(1)
+    }
+
+    #[test]
+    fn scan_clustering_coefficient_w2_test() {
+        scan_clustering_coefficient(2)
+    }
+
+    #[test]
+    fn scan_global_triangle_test() {
+        scan_global_triangle(1)
+    }
+
+    #[test]
+    fn scan_global_triangle_w2_test() {
+        scan_global_triangle(2)
+    }
+}
@@ -170,6 +170,7 @@ mod test {
                 vars: vec![common_pb::Variable::from("@".to_string())],
                 aggregate: 3, // count
                 alias: None,
+                udf: String::new(),
             }],
             meta_data: vec![],
         };
@@ -491,6 +491,7 @@ mod test {
                 alias: Some(TAG_B.into()),
             }],
             is_append: false,
+            select_tags: false,
         };
 
         let conf = JobConf::new("project_to_remove_some_tag_test");
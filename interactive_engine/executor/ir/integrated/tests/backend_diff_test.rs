@@ -0,0 +1,114 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use graph_proxy::apis::{GraphView, PegasusClusterInfo, ViewGraph};
+    use graph_proxy::create_exp_store;
+    use ir_common::generated::algebra as pb;
+    use ir_common::generated::common as common_pb;
+    use ir_common::generated::results as result_pb;
+    use ir_physical_client::physical_builder::*;
+    use pegasus_server::JobRequest;
+    use prost::Message;
+
+    use crate::common::diff::*;
+    use crate::common::test::*;
+
+    fn exp_store() -> Backend {
+        Backend::new(create_exp_store(Arc::new(PegasusClusterInfo::default())))
+    }
+
+    // g.V()
+    fn init_scan_request() -> JobRequest {
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: Some(query_params_all_columns(vec![], vec![], None)),
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        };
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.sink(default_sink_pb());
+        job_builder.build().unwrap()
+    }
+
+    fn vertex_result(id: i64, properties: &[(&str, i64)]) -> Vec<u8> {
+        let properties = properties
+            .iter()
+            .map(|(key, value)| result_pb::Property {
+                key: Some((*key).into()),
+                value: Some(common_pb::Value { item: Some(common_pb::value::Item::I64(*value)) }),
+            })
+            .collect();
+        let vertex = result_pb::Vertex { id, label: None, properties };
+        let column = result_pb::Column {
+            name_or_id: None,
+            entry: Some(result_pb::Entry {
+                inner: Some(result_pb::entry::Inner::Element(result_pb::Element {
+                    inner: Some(result_pb::element::Inner::Vertex(vertex)),
+                })),
+            }),
+        };
+        let record = result_pb::Record { columns: vec![column], provenance: None };
+        result_pb::Results { inner: Some(result_pb::results::Inner::Record(record)) }.encode_to_vec()
+    }
+
+    #[test]
+    fn result_diff_test() {
+        let left =
+            vec![vertex_result(1, &[("a", 1), ("b", 2)]), vertex_result(2, &[]), vertex_result(2, &[])];
+        let right =
+            vec![vertex_result(1, &[("b", 2), ("a", 1)]), vertex_result(2, &[]), vertex_result(3, &[])];
+        let diff = ResultDiff::new(&left, &right, DiffOptions::default());
+        assert_eq!(diff.matched, 2);
+        assert_eq!(diff.left_only(), 1);
+        assert_eq!(diff.right_only(), 1);
+        assert_eq!(diff.entries.len(), 2);
+        assert_eq!((diff.entries[0].left, diff.entries[0].right), (2, 1));
+
+        let diff = ResultDiff::new(&left[1..], &right[1..], DiffOptions { ignore_ids: true });
+        assert!(diff.is_empty());
+        assert_eq!(diff.matched, 2);
+    }
+
+    #[test]
+    fn diff_same_backend_test() {
+        initialize();
+        let diff = diff_backends(init_scan_request(), 2, exp_store(), exp_store(), DiffOptions::default());
+        assert!(diff.is_empty(), "{}", diff);
+        assert_eq!(diff.matched, 6);
+    }
+
+    #[test]
+    fn diff_drifted_backend_test() {
+        initialize();
+        // a backend missing the software vertices
+        let view = GraphView::new().with_vertex_labels(vec![PERSON_LABEL]);
+        let drifted = Backend::new(Arc::new(ViewGraph::new(exp_store().graph, Arc::new(view))));
+        let diff = diff_backends(init_scan_request(), 2, exp_store(), drifted, DiffOptions::default());
+        assert_eq!(diff.matched, 4);
+        assert_eq!(diff.left_only(), 2);
+        assert_eq!(diff.right_only(), 0);
+    }
+}
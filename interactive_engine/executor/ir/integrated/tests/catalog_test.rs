@@ -1673,6 +1673,8 @@ mod test {
             sink_target: Some(pb::sink::SinkTarget {
                 inner: Some(pb::sink::sink_target::Inner::SinkDefault(pb::SinkDefault {
                     id_name_mappings: vec![],
+                    format: 0,
+                    columns: vec![],
                 })),
             }),
         }
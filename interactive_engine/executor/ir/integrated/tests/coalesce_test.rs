@@ -0,0 +1,168 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use graph_proxy::apis::GraphElement;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::DefaultId;
+    use ir_common::generated::algebra as pb;
+    use ir_physical_client::physical_builder::*;
+    use pegasus_server::JobRequest;
+    use runtime::process::entry::Entry;
+
+    use crate::common::test::*;
+
+    fn expand_opr(label: i32) -> pb::EdgeExpand {
+        pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: Some(query_params(vec![label.into()], vec![], None)),
+            expand_opt: 0,
+            alias: None,
+            meta_data: None,
+            is_optional: false,
+        }
+    }
+
+    fn expand_plan(label: i32) -> PlanBuilder {
+        let mut plan = PlanBuilder::default();
+        plan.shuffle(None);
+        plan.edge_expand(expand_opr(label));
+        plan
+    }
+
+    // g.V().coalesce(out('knows'), out('created'))
+    fn init_coalesce_request() -> JobRequest {
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: None,
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        };
+
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.coalesce(vec![expand_plan(KNOWS_LABEL as i32), expand_plan(CREATED_LABEL as i32)]);
+        job_builder.sink(default_sink_pb());
+
+        job_builder.build().unwrap()
+    }
+
+    // g.V().coalesce(out('knows'), out('created').limit(1))
+    fn init_coalesce_limit_request() -> JobRequest {
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: None,
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        };
+
+        let mut limit_plan = expand_plan(CREATED_LABEL as i32);
+        limit_plan.limit(pb::Limit { range: Some(pb::Range { lower: 0, upper: 1 }) });
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.coalesce(vec![expand_plan(KNOWS_LABEL as i32), limit_plan]);
+        job_builder.sink(default_sink_pb());
+
+        job_builder.build().unwrap()
+    }
+
+    fn collect_ids(request: JobRequest, worker_num: u32) -> Vec<i64> {
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let vertex = record
+                        .get(None)
+                        .unwrap()
+                        .as_vertex()
+                        .unwrap()
+                        .id();
+                    result_collection.push(vertex);
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        result_collection.sort();
+        result_collection
+    }
+
+    fn to_global_id(id: usize, label: u8) -> i64 {
+        let global_id: DefaultId = LDBCVertexParser::to_global_id(id, label);
+        global_id as i64
+    }
+
+    // v1 knows v2 and v4, so its created v3 is not expanded, while v4 and v6 know no one, so their
+    // created v3, v5 and v3 are expanded instead, and v2, v3 and v5 have neither
+    fn coalesce(worker_num: u32) {
+        initialize();
+        let request = init_coalesce_request();
+        let mut expected = vec![
+            to_global_id(2, 0),
+            to_global_id(4, 0),
+            to_global_id(3, 1),
+            to_global_id(5, 1),
+            to_global_id(3, 1),
+        ];
+        expected.sort();
+        assert_eq!(collect_ids(request, worker_num), expected);
+    }
+
+    #[test]
+    fn coalesce_test() {
+        coalesce(1)
+    }
+
+    #[test]
+    fn coalesce_w2_test() {
+        coalesce(2)
+    }
+
+    // the limit of the last sub-plan bounds the results of each record, so both v4 and v6 derive one
+    // created software, rather than one of them in total
+    fn coalesce_limit(worker_num: u32) {
+        initialize();
+        let request = init_coalesce_limit_request();
+        let ids = collect_ids(request, worker_num);
+        assert_eq!(ids.len(), 4);
+        assert_eq!(&ids[..2], &[to_global_id(2, 0), to_global_id(4, 0)][..]);
+        assert!(ids[2..]
+            .iter()
+            .all(|id| *id == to_global_id(3, 1) || *id == to_global_id(5, 1)));
+    }
+
+    #[test]
+    fn coalesce_limit_test() {
+        coalesce_limit(1)
+    }
+
+    #[test]
+    fn coalesce_limit_w2_test() {
+        coalesce_limit(2)
+    }
+}
@@ -0,0 +1,228 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! A harness running the same plan against two graph proxy backends, e.g., groot, vineyard or the
+//! experimental store, and diffing the multisets of their results, to catch the semantics drifting
+//! between the store integrations.
+//!
+//! The results are compared in a canonical form, where the properties of the graph elements are
+//! sorted by their keys, as the backends may return the properties in any order. The order of the
+//! results, and of the collections in a result, is kept by the backends that are expected to agree on
+//! it, e.g., by an order by, and is only ignored across the results.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use graph_proxy::apis::{ReadGraph, WriteGraphProxy};
+use ir_common::generated::results as result_pb;
+use pegasus_server::JobRequest;
+use prost::Message;
+
+use crate::common::test::run_query_on;
+
+/// A storage backend the plans are run against, with the graph to write into if the plans write.
+#[derive(Clone)]
+pub struct Backend {
+    pub graph: Arc<dyn ReadGraph>,
+    pub write_graph: Option<Arc<Mutex<dyn WriteGraphProxy>>>,
+}
+
+impl Backend {
+    pub fn new(graph: Arc<dyn ReadGraph>) -> Self {
+        Backend { graph, write_graph: None }
+    }
+
+    pub fn with_write_graph(mut self, write_graph: Arc<Mutex<dyn WriteGraphProxy>>) -> Self {
+        self.write_graph = Some(write_graph);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DiffOptions {
+    /// Compare the graph elements without their ids, for the backends encoding the ids differently.
+    pub ignore_ids: bool,
+}
+
+/// A result returned by the backends different times.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffEntry {
+    /// The canonical form of the result.
+    pub result: String,
+    pub left: usize,
+    pub right: usize,
+}
+
+/// The diff of the multisets of the results of two backends.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResultDiff {
+    /// The number of the results returned by both the backends, with multiplicities.
+    pub matched: usize,
+    /// The results returned different times, in the order of their canonical forms.
+    pub entries: Vec<DiffEntry>,
+}
+
+impl ResultDiff {
+    pub fn new(left: &[Vec<u8>], right: &[Vec<u8>], options: DiffOptions) -> Self {
+        let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for result in left {
+            counts
+                .entry(canonical_form(result, options))
+                .or_default()
+                .0 += 1;
+        }
+        for result in right {
+            counts
+                .entry(canonical_form(result, options))
+                .or_default()
+                .1 += 1;
+        }
+        let mut diff = ResultDiff::default();
+        for (result, (left, right)) in counts {
+            diff.matched += left.min(right);
+            if left != right {
+                diff.entries
+                    .push(DiffEntry { result, left, right });
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The number of the results returned by the left backend more times than by the right.
+    pub fn left_only(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|entry| entry.left.saturating_sub(entry.right))
+            .sum()
+    }
+
+    /// The number of the results returned by the right backend more times than by the left.
+    pub fn right_only(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|entry| entry.right.saturating_sub(entry.left))
+            .sum()
+    }
+}
+
+impl fmt::Display for ResultDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} matched, {} only in left, {} only in right",
+            self.matched,
+            self.left_only(),
+            self.right_only()
+        )?;
+        for entry in &self.entries {
+            if entry.left > entry.right {
+                writeln!(f, "- {} x{}", entry.result, entry.left - entry.right)?;
+            } else {
+                writeln!(f, "+ {} x{}", entry.result, entry.right - entry.left)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run the plan against both the backends one after another, and diff their results; all the plans of
+/// the test binary must run by this, as the backends are registered in place of the graph of the
+/// process while a plan runs.
+pub fn diff_backends(
+    job_req: JobRequest, num_workers: u32, left: Backend, right: Backend, options: DiffOptions,
+) -> ResultDiff {
+    let left_results = run_query_on(job_req.clone(), num_workers, left.graph, left.write_graph);
+    let right_results = run_query_on(job_req, num_workers, right.graph, right.write_graph);
+    ResultDiff::new(&left_results, &right_results, options)
+}
+
+fn canonical_form(result: &[u8], options: DiffOptions) -> String {
+    let mut results = result_pb::Results::decode(result).expect("decode results failure;");
+    if let Some(result_pb::results::Inner::Record(record)) = results.inner.as_mut() {
+        for column in record.columns.iter_mut() {
+            if let Some(entry) = column.entry.as_mut() {
+                normalize_entry(entry, options);
+            }
+        }
+        // the provenance is of the plan rather than the backend
+        record.provenance = None;
+    }
+    format!("{:?}", results)
+}
+
+fn normalize_entry(entry: &mut result_pb::Entry, options: DiffOptions) {
+    match entry.inner.as_mut() {
+        Some(result_pb::entry::Inner::Element(element)) => normalize_element(element, options),
+        Some(result_pb::entry::Inner::Collection(collection)) => {
+            for element in collection.collection.iter_mut() {
+                normalize_element(element, options);
+            }
+        }
+        Some(result_pb::entry::Inner::Map(key_values)) => {
+            for key_value in key_values.key_values.iter_mut() {
+                if let Some(element) = key_value.value.as_mut() {
+                    normalize_element(element, options);
+                }
+            }
+        }
+        None => {}
+    }
+}
+
+fn normalize_element(element: &mut result_pb::Element, options: DiffOptions) {
+    match element.inner.as_mut() {
+        Some(result_pb::element::Inner::Vertex(vertex)) => normalize_vertex(vertex, options),
+        Some(result_pb::element::Inner::Edge(edge)) => normalize_edge(edge, options),
+        Some(result_pb::element::Inner::GraphPath(path)) => {
+            for vertex_or_edge in path.path.iter_mut() {
+                match vertex_or_edge.inner.as_mut() {
+                    Some(result_pb::graph_path::vertex_or_edge::Inner::Vertex(vertex)) => {
+                        normalize_vertex(vertex, options)
+                    }
+                    Some(result_pb::graph_path::vertex_or_edge::Inner::Edge(edge)) => {
+                        normalize_edge(edge, options)
+                    }
+                    None => {}
+                }
+            }
+        }
+        Some(result_pb::element::Inner::Object(_)) | None => {}
+    }
+}
+
+fn normalize_vertex(vertex: &mut result_pb::Vertex, options: DiffOptions) {
+    if options.ignore_ids {
+        vertex.id = 0;
+    }
+    sort_properties(&mut vertex.properties);
+}
+
+fn normalize_edge(edge: &mut result_pb::Edge, options: DiffOptions) {
+    if options.ignore_ids {
+        edge.id = 0;
+        edge.src_id = 0;
+        edge.dst_id = 0;
+    }
+    sort_properties(&mut edge.properties);
+}
+
+fn sort_properties(properties: &mut Vec<result_pb::Property>) {
+    properties.sort_by_cached_key(|property| format!("{:?}", property.key));
+}
@@ -15,17 +15,25 @@
 //!
 //!
 
+#[cfg(test)]
+#[allow(dead_code)]
+pub mod diff;
+
 #[cfg(test)]
 #[allow(dead_code)]
 #[allow(unused_imports)]
 pub mod test {
     use std::collections::HashMap;
     use std::convert::{TryFrom, TryInto};
-    use std::sync::{Arc, Once};
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex, Once};
 
     use graph_proxy::apis::partitioner::PartitionKeyId;
+    use graph_proxy::apis::read_graph::GRAPH_PROXY;
+    use graph_proxy::apis::write_graph::WRITE_GRAPH_PROXY;
     use graph_proxy::apis::{
-        register_graph, ClusterInfo, DynDetails, Edge, PegasusClusterInfo, Vertex, ID,
+        register_graph, register_write_graph, ClusterInfo, DynDetails, Edge, PegasusClusterInfo, ReadGraph,
+        Vertex, WriteGraphProxy, ID,
     };
     use graph_proxy::{create_exp_store, GraphProxyResult, SimplePartition};
     use ir_common::expr_parse::str_to_expr_pb;
@@ -38,8 +46,10 @@ pub mod test {
     use pegasus::{run_opt, Configuration, JobConf, StartupError};
     use pegasus_server::job::{JobAssembly, JobDesc};
     use pegasus_server::rpc::RpcSink;
+    use pegasus_server::scheduling::SchedulingClass;
     use pegasus_server::JobRequest;
     use prost::Message;
+    use runtime::procedure::ProcedureRegistry;
     use runtime::process::entry::DynEntry;
     use runtime::process::record::Record;
     use runtime::router::Router;
@@ -60,9 +70,12 @@ pub mod test {
     pub const CREATED_LABEL: LabelId = 1;
 
     static INIT: Once = Once::new();
+    /// Held by the query run against other graphs than the registered ones, see `run_query_on()`.
+    static GRAPHS_REPLACED: Mutex<()> = Mutex::new(());
 
     lazy_static! {
         static ref FACTORY: IRJobAssembly<SimplePartition, PegasusClusterInfo> = initialize_job_assembly();
+        pub static ref PROCEDURES: Arc<ProcedureRegistry> = Arc::new(ProcedureRegistry::default());
     }
 
     pub fn initialize() {
@@ -88,7 +101,7 @@ pub mod test {
         let exp_store = create_exp_store(cluster_info.clone());
         register_graph(exp_store);
         let partition_info = Arc::new(SimplePartition { num_servers: 1 });
-        IRJobAssembly::with(partition_info, cluster_info)
+        IRJobAssembly::with(partition_info, cluster_info).with_procedures(PROCEDURES.clone())
     }
 
     pub fn submit_query(job_req: JobRequest, num_workers: u32) -> ResultStream<Vec<u8>> {
@@ -99,11 +112,62 @@ pub mod test {
         let cancel_hook = sink.get_cancel_hook().clone();
         let results = ResultStream::new(conf.job_id, cancel_hook, rx);
         let service = &FACTORY;
-        let job = JobDesc { input: job_req.source, plan: job_req.plan, resource: job_req.resource };
+        let job = JobDesc {
+            input: job_req.source,
+            plan: job_req.plan,
+            resource: job_req.resource,
+            principal: None,
+            session: None,
+            view: None,
+            include_deleted: false,
+            consistency: None,
+            scheduling_class: SchedulingClass::Interactive,
+            provenance: false,
+            result_limit: None,
+        };
         run_opt(conf, sink, move |worker| service.assemble(&job, worker)).expect("submit job failure;");
         results
     }
 
+    /// The graphs registered before they're replaced, which are registered again when dropped.
+    struct ReplacedGraphs {
+        graph: *mut Arc<dyn ReadGraph>,
+        write_graph: *mut Arc<Mutex<dyn WriteGraphProxy>>,
+    }
+
+    impl Drop for ReplacedGraphs {
+        fn drop(&mut self) {
+            GRAPH_PROXY.store(self.graph, Ordering::SeqCst);
+            WRITE_GRAPH_PROXY.store(self.write_graph, Ordering::SeqCst);
+        }
+    }
+
+    /// Run the query against the graph, and write into the write graph if any, in place of the graphs
+    /// registered, e.g., of another storage backend, and collect its results. The graphs are registered
+    /// until the query completes, so the other queries of the process must not run meanwhile, e.g., they
+    /// are of a test binary of their own, whose queries all run by this.
+    pub fn run_query_on(
+        job_req: JobRequest, num_workers: u32, graph: Arc<dyn ReadGraph>,
+        write_graph: Option<Arc<Mutex<dyn WriteGraphProxy>>>,
+    ) -> Vec<Vec<u8>> {
+        // the graph registered along with the job assembly is not to replace the graph of the query
+        lazy_static::initialize(&FACTORY);
+        let _replaced = GRAPHS_REPLACED
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let _restore = ReplacedGraphs {
+            graph: GRAPH_PROXY.load(Ordering::SeqCst),
+            write_graph: WRITE_GRAPH_PROXY.swap(std::ptr::null_mut(), Ordering::SeqCst),
+        };
+        register_graph(graph);
+        if let Some(write_graph) = write_graph {
+            register_write_graph(write_graph);
+        }
+        submit_query(job_req, num_workers)
+            .map(|result| result.unwrap_or_else(|e| panic!("err result {:?}", e)))
+            .collect()
+    }
+
     pub fn parse_result(result: Vec<u8>) -> Option<Record> {
         let result: result_pb::Results = result_pb::Results::decode(result.as_slice()).unwrap();
         if let Some(result_pb::results::Inner::Record(record_pb)) = result.inner {
@@ -147,6 +211,8 @@ pub mod test {
             predicate,
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         }
     }
 
@@ -162,6 +228,8 @@ pub mod test {
             predicate,
             sample_ratio: 1.0,
             extra: HashMap::new(),
+            valid_time: None,
+            excluded_tables: vec![],
         }
     }
 
@@ -223,6 +291,8 @@ pub mod test {
             sink_target: Some(pb::sink::SinkTarget {
                 inner: Some(pb::sink::sink_target::Inner::SinkDefault(pb::SinkDefault {
                     id_name_mappings: vec![],
+                    format: 0,
+                    columns: vec![],
                 })),
             }),
         }
@@ -232,6 +302,8 @@ pub mod test {
         Some(pb::sink::SinkTarget {
             inner: Some(pb::sink::sink_target::Inner::SinkDefault(pb::SinkDefault {
                 id_name_mappings: vec![],
+                format: 0,
+                columns: vec![],
             })),
         })
     }
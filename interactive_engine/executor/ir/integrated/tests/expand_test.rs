@@ -19,6 +19,7 @@ mod common;
 
 #[cfg(test)]
 mod test {
+    use std::fs::File;
     use std::sync::Arc;
 
     use dyn_type::object;
@@ -29,6 +30,9 @@ mod test {
     use ir_common::expr_parse::str_to_expr_pb;
     use ir_common::generated::{algebra as algebra_pb, common as common_pb, physical as pb};
     use ir_common::KeyId;
+    use ir_core::plan::logical::AsLogical;
+    use ir_core::plan::meta::{PlanMeta, Schema, StoreMeta};
+    use ir_core::JsonIO;
     use ir_physical_client::physical_builder::{JobBuilder, PlanBuilder};
     use pegasus::api::{Map, Sink};
     use pegasus::result::ResultStream;
@@ -86,6 +90,7 @@ mod test {
                 alias: None,
             }],
             is_append: true,
+            select_tags: false,
         };
 
         let result = pegasus::run(conf, || {
@@ -210,6 +215,56 @@ mod test {
         assert_eq!(result_edges, expected_edges)
     }
 
+    // g.V().outE() of any label except 'knows', which is resolved against the schema into the labels of
+    // an expand of many labels
+    #[test]
+    fn expand_oute_with_excluded_label_test() {
+        let modern_schema_file = File::open("../core/resource/modern_schema.json").unwrap();
+        let meta = StoreMeta { schema: Some(Schema::from_json(modern_schema_file).unwrap()) };
+        let mut logical_expand = algebra_pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: Some(algebra_pb::QueryParams {
+                excluded_tables: vec!["knows".into()],
+                ..query_params(vec![], vec![], None)
+            }),
+            expand_opt: 1,
+            alias: None,
+            meta_data: None,
+            is_optional: false,
+        };
+        logical_expand
+            .preprocess(&meta, &mut PlanMeta::default())
+            .unwrap();
+        let query_param = logical_expand.params.unwrap();
+        let created: common_pb::NameOrId = CREATED_LABEL.into();
+        assert_eq!(query_param.tables, vec![created]);
+        let expand_opr_pb = pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: Some(query_param),
+            expand_opt: 1,
+            alias: None,
+            is_optional: false,
+        };
+        let mut result = expand_test(expand_opr_pb);
+        let mut result_edges = vec![];
+        let v1: DefaultId = LDBCVertexParser::to_global_id(1, 0);
+        let v3: DefaultId = LDBCVertexParser::to_global_id(3, 1);
+        let v4: DefaultId = LDBCVertexParser::to_global_id(4, 0);
+        let v5: DefaultId = LDBCVertexParser::to_global_id(5, 1);
+        let v6: DefaultId = LDBCVertexParser::to_global_id(6, 0);
+        let mut expected_edges = vec![(v1, v3), (v4, v3), (v4, v5), (v6, v3)];
+        expected_edges.sort();
+        while let Some(Ok(record)) = result.next() {
+            if let Some(e) = record.get(None).unwrap().as_edge() {
+                result_edges.push((e.src_id as usize, e.dst_id as usize));
+            }
+        }
+        result_edges.sort();
+        assert_eq!(result_edges, expected_edges)
+    }
+
     // g.V().inE('knows') with required properties
     #[test]
     fn expand_ine_with_label_property_test() {
@@ -300,6 +355,7 @@ mod test {
                 alias: None,
             }],
             is_append: false,
+            select_tags: false,
         };
         let expand = pb::EdgeExpand {
             v_tag: None,
@@ -113,6 +113,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
 
         let mut job_builder = JobBuilder::default();
@@ -150,6 +151,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
 
         let mut job_builder = JobBuilder::default();
@@ -0,0 +1,133 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use graph_proxy::apis::GraphElement;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::DefaultId;
+    use ir_common::generated::algebra as pb;
+    use ir_common::generated::algebra::group_by::agg_func::Aggregate;
+    use ir_common::generated::common as common_pb;
+    use ir_common::KeyId;
+    use ir_physical_client::physical_builder::*;
+    use runtime::process::entry::Entry;
+
+    use crate::common::test::*;
+
+    fn to_global_id(id: usize, label: u8) -> i64 {
+        let global_id: DefaultId = LDBCVertexParser::to_global_id(id, label);
+        global_id as i64
+    }
+
+    fn agg_func(aggregate: Aggregate, alias: KeyId) -> pb::group_by::AggFunc {
+        pb::group_by::AggFunc {
+            vars: vec![common_pb::Variable::from("@1".to_string())],
+            aggregate: aggregate as i32,
+            alias: Some(alias.into()),
+            udf: String::new(),
+        }
+    }
+
+    // g.V().as('a').out().as('b').group().by('a').by(count(), dedup().count()), where the out vertices
+    // of a vertex are expanded by the workers of its partition, and the groups are aggregated partially
+    // by the workers before the partial aggregations are exchanged
+    fn group_count(worker_num: u32, shuffle: bool) {
+        initialize();
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: Some(TAG_A.into()),
+            params: None,
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        };
+        let expand_opr = pb::EdgeExpand {
+            v_tag: Some(TAG_A.into()),
+            direction: 0,
+            params: Some(query_params(vec![], vec![], None)),
+            expand_opt: 0,
+            alias: Some(TAG_B.into()),
+            meta_data: None,
+            is_optional: false,
+        };
+        let group_opr = pb::GroupBy {
+            mappings: vec![pb::group_by::KeyAlias {
+                key: Some(common_pb::Variable::from("@0".to_string())),
+                alias: Some(TAG_A.into()),
+            }],
+            functions: vec![agg_func(Aggregate::Count, TAG_C), agg_func(Aggregate::CountDistinct, TAG_D)],
+            meta_data: vec![],
+        };
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.edge_expand(expand_opr);
+        if shuffle {
+            job_builder.shuffle(Some(TAG_A.into()));
+        }
+        job_builder.group(group_opr);
+        job_builder.sink(default_sink_pb());
+        let request = job_builder.build().unwrap();
+
+        let mut results = submit_query(request, worker_num);
+        let mut counts = vec![];
+        while let Some(result) = results.next() {
+            let record = parse_result(result.unwrap()).unwrap();
+            let start = record
+                .get(Some(TAG_A))
+                .unwrap()
+                .as_vertex()
+                .unwrap()
+                .id();
+            let count = |tag| {
+                record
+                    .get(Some(tag))
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .as_u64()
+                    .unwrap()
+            };
+            counts.push((start, count(TAG_C), count(TAG_D)));
+        }
+        counts.sort();
+        let (v1, v4, v6) = (to_global_id(1, 0), to_global_id(4, 0), to_global_id(6, 0));
+        assert_eq!(counts, vec![(v1, 3, 3), (v4, 2, 2), (v6, 1, 1)]);
+    }
+
+    #[test]
+    fn group_count_test() {
+        group_count(1, false)
+    }
+
+    #[test]
+    fn group_count_w2_test() {
+        group_count(2, false)
+    }
+
+    #[test]
+    fn group_count_shuffled_test() {
+        group_count(1, true)
+    }
+
+    #[test]
+    fn group_count_shuffled_w2_test() {
+        group_count(2, true)
+    }
+}
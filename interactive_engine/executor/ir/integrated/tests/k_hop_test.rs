@@ -0,0 +1,242 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use graph_proxy::apis::GraphElement;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::DefaultId;
+    use ir_common::generated::algebra as pb;
+    use ir_common::generated::common as common_pb;
+    use ir_physical_client::physical_builder::*;
+    use pegasus_server::JobRequest;
+    use runtime::process::entry::Entry;
+
+    use crate::common::test::*;
+
+    // g.V().as('a').k_hop(hops).as('b')
+    fn init_k_hop_request(start_ids: Vec<i64>, direction: i32, hops: i32, within: bool) -> JobRequest {
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: Some(TAG_A.into()),
+            params: None,
+            idx_predicate: Some(start_ids.into()),
+            is_count_only: false,
+            meta_data: None,
+        };
+        let k_hop_opr = pb::KHop {
+            start_tag: Some(TAG_A.into()),
+            direction,
+            params: Some(query_params(vec![], vec![], None)),
+            hops,
+            within,
+            alias: Some(TAG_B.into()),
+            aggregates: vec![],
+        };
+
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.k_hop(k_hop_opr);
+        job_builder.sink(default_sink_pb());
+
+        job_builder.build().unwrap()
+    }
+
+    fn collect_reached(request: JobRequest, worker_num: u32) -> Vec<(i64, i64)> {
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let start = record
+                        .get(Some(TAG_A))
+                        .unwrap()
+                        .as_vertex()
+                        .unwrap()
+                        .id();
+                    let reached = record
+                        .get(Some(TAG_B))
+                        .unwrap()
+                        .as_vertex()
+                        .unwrap()
+                        .id();
+                    result_collection.push((start, reached));
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        result_collection.sort();
+        result_collection
+    }
+
+    fn to_global_id(id: usize, label: u8) -> i64 {
+        let global_id: DefaultId = LDBCVertexParser::to_global_id(id, label);
+        global_id as i64
+    }
+
+    // v1 -> v2, v3, v4 in the first hop, and v4 -> v3, v5 in the second hop, where v3 is reached
+    // twice but only in its first hop
+    fn k_hop_frontier(worker_num: u32) {
+        initialize();
+        let v1 = to_global_id(1, 0);
+        let request = init_k_hop_request(vec![v1], 0, 2, false);
+        let reached = collect_reached(request, worker_num);
+        assert_eq!(reached, vec![(v1, to_global_id(5, 1))]);
+    }
+
+    fn k_hop_within(worker_num: u32) {
+        initialize();
+        let v1 = to_global_id(1, 0);
+        let request = init_k_hop_request(vec![v1], 0, 2, true);
+        let reached = collect_reached(request, worker_num);
+        let mut expected = vec![
+            (v1, to_global_id(2, 0)),
+            (v1, to_global_id(3, 1)),
+            (v1, to_global_id(4, 0)),
+            (v1, to_global_id(5, 1)),
+        ];
+        expected.sort();
+        assert_eq!(reached, expected);
+    }
+
+    // v2 - v1 in the first hop, and v1 - v3, v4 in the second hop, where v2 itself is not reached again;
+    // v4 - v1, v3, v5 in the first hop, and v1 - v2, v3 - v6 in the second hop
+    fn k_hop_both_frontier(worker_num: u32) {
+        initialize();
+        let (v2, v4) = (to_global_id(2, 0), to_global_id(4, 0));
+        let request = init_k_hop_request(vec![v2, v4], 2, 2, false);
+        let reached = collect_reached(request, worker_num);
+        let mut expected = vec![
+            (v2, to_global_id(3, 1)),
+            (v2, to_global_id(4, 0)),
+            (v4, to_global_id(2, 0)),
+            (v4, to_global_id(6, 0)),
+        ];
+        expected.sort();
+        assert_eq!(reached, expected);
+    }
+
+    // g.V(v1, v4).as('a').k_hop(2, within).as('b').group().by('a').by(count()), where the vertices
+    // reached on different workers are counted together
+    fn k_hop_count(worker_num: u32) {
+        initialize();
+        let (v1, v4) = (to_global_id(1, 0), to_global_id(4, 0));
+        let count_opr = pb::GroupBy {
+            mappings: vec![pb::group_by::KeyAlias {
+                key: Some(common_pb::Variable::from("@0".to_string())),
+                alias: Some(TAG_A.into()),
+            }],
+            functions: vec![pb::group_by::AggFunc {
+                vars: vec![common_pb::Variable::from("@1".to_string())],
+                aggregate: 3, // count
+                alias: Some(TAG_C.into()),
+                udf: String::new(),
+            }],
+            meta_data: vec![],
+        };
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: Some(TAG_A.into()),
+            params: None,
+            idx_predicate: Some(vec![v1, v4].into()),
+            is_count_only: false,
+            meta_data: None,
+        };
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.k_hop(pb::KHop {
+            start_tag: Some(TAG_A.into()),
+            direction: 0,
+            params: Some(query_params(vec![], vec![], None)),
+            hops: 2,
+            within: true,
+            alias: Some(TAG_B.into()),
+            aggregates: vec![],
+        });
+        job_builder.shuffle(Some(TAG_A.into()));
+        job_builder.group(count_opr);
+        job_builder.sink(default_sink_pb());
+        let request = job_builder.build().unwrap();
+
+        let mut results = submit_query(request, worker_num);
+        let mut counts = vec![];
+        while let Some(result) = results.next() {
+            let record = parse_result(result.unwrap()).unwrap();
+            let start = record
+                .get(Some(TAG_A))
+                .unwrap()
+                .as_vertex()
+                .unwrap()
+                .id();
+            let count = record
+                .get(Some(TAG_C))
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .as_u64()
+                .unwrap();
+            counts.push((start, count));
+        }
+        counts.sort();
+        assert_eq!(counts, vec![(v1, 4), (v4, 2)]);
+    }
+
+    #[test]
+    fn k_hop_frontier_test() {
+        k_hop_frontier(1)
+    }
+
+    #[test]
+    fn k_hop_frontier_w2_test() {
+        k_hop_frontier(2)
+    }
+
+    #[test]
+    fn k_hop_within_test() {
+        k_hop_within(1)
+    }
+
+    #[test]
+    fn k_hop_within_w2_test() {
+        k_hop_within(2)
+    }
+
+    #[test]
+    fn k_hop_both_frontier_test() {
+        k_hop_both_frontier(1)
+    }
+
+    #[test]
+    fn k_hop_both_frontier_w2_test() {
+        k_hop_both_frontier(2)
+    }
+
+    #[test]
+    fn k_hop_count_test() {
+        k_hop_count(1)
+    }
+
+    #[test]
+    fn k_hop_count_w2_test() {
+        k_hop_count(2)
+    }
+}
@@ -156,7 +156,7 @@ mod test {
     }
 
     fn get_union(parents: Vec<i32>) -> pb::Union {
-        pb::Union { parents }
+        pb::Union { parents, branch_alias: None }
     }
 
     fn get_inner_join(tags: Vec<KeyId>) -> pb::Join {
@@ -923,6 +923,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
 
         let mut job_builder = JobBuilder::default();
@@ -1137,6 +1138,7 @@ mod test {
             }],
             is_append: true,
             meta_data: vec![],
+            select_tags: false,
         };
 
         let mut job_builder = JobBuilder::default();
@@ -0,0 +1,171 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use dyn_type::Object;
+    use graph_proxy::apis::GraphElement;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::DefaultId;
+    use ir_common::expr_parse::str_to_expr_pb;
+    use ir_common::generated::algebra as pb;
+    use ir_common::generated::common as common_pb;
+    use ir_physical_client::physical_builder::*;
+    use pegasus::api::Map;
+    use pegasus::stream::Stream;
+    use pegasus::BuildJobError;
+    use pegasus_server::JobRequest;
+    use runtime::procedure::Procedure;
+    use runtime::process::entry::Entry;
+    use runtime::process::record::Record;
+
+    use crate::common::test::*;
+
+    // g.V().hasLabel('person').has('name', $0)
+    fn install_person_by_name() {
+        let mut predicate = str_to_expr_pb("@.name == ".to_string()).unwrap();
+        let param = common_pb::DynamicParam { name: "name".to_string(), index: 0, data_type: None };
+        predicate.operators.push(common_pb::ExprOpr {
+            node_type: None,
+            item: Some(common_pb::expr_opr::Item::Param(param)),
+        });
+        let scan = pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: Some(query_params(vec![PERSON_LABEL.into()], vec![], Some(predicate))),
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        };
+        let mut plan_builder = PlanBuilder::default();
+        plan_builder.add_scan_source(scan);
+        PROCEDURES
+            .install_plan("person_by_name", plan_builder.build())
+            .unwrap();
+    }
+
+    /// Output each record with each argument appended.
+    struct RepeatArgs;
+
+    impl Procedure for RepeatArgs {
+        fn install(
+            &self, input: Stream<Record>, args: Vec<Object>,
+        ) -> Result<Stream<Record>, BuildJobError> {
+            input.flat_map(move |record| {
+                let records = args.clone().into_iter().map(move |arg| {
+                    let mut record = record.clone();
+                    record.append(arg, None);
+                    record
+                });
+                Ok(records)
+            })
+        }
+    }
+
+    fn init_call_request(name: &str, args: Vec<common_pb::Value>) -> JobRequest {
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_dummy_source();
+        job_builder.call(pb::Call { name: name.to_string(), args });
+        job_builder.sink(default_sink_pb());
+        job_builder.build().unwrap()
+    }
+
+    fn to_global_id(id: usize, label: u8) -> i64 {
+        let global_id: DefaultId = LDBCVertexParser::to_global_id(id, label);
+        global_id as i64
+    }
+
+    fn call_plan_procedure(worker_num: u32) {
+        initialize();
+        install_person_by_name();
+        let request = init_call_request("person_by_name", vec!["vadas".to_string().into()]);
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let vertex = record.get(None).unwrap().as_vertex().unwrap();
+                    result_collection.push(vertex.id());
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        assert_eq!(result_collection, vec![to_global_id(2, 0)]);
+    }
+
+    fn call_native_procedure(worker_num: u32) {
+        initialize();
+        PROCEDURES
+            .install_native("repeat_args", Arc::new(RepeatArgs))
+            .unwrap();
+        let request = init_call_request("repeat_args", vec![1.into(), 2.into()]);
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let arg = record
+                        .get(None)
+                        .unwrap()
+                        .as_object()
+                        .unwrap()
+                        .as_i32()
+                        .unwrap();
+                    result_collection.push(arg);
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        result_collection.sort();
+        let mut expected = vec![];
+        for _ in 0..worker_num {
+            expected.extend(vec![1, 2]);
+        }
+        expected.sort();
+        assert_eq!(result_collection, expected);
+    }
+
+    #[test]
+    fn call_plan_procedure_test() {
+        call_plan_procedure(1)
+    }
+
+    #[test]
+    fn call_plan_procedure_w2_test() {
+        call_plan_procedure(2)
+    }
+
+    #[test]
+    fn call_native_procedure_test() {
+        call_native_procedure(1)
+    }
+
+    #[test]
+    fn call_native_procedure_w2_test() {
+        call_native_procedure(2)
+    }
+}
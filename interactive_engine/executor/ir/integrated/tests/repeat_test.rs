@@ -0,0 +1,167 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use graph_proxy::apis::GraphElement;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::DefaultId;
+    use ir_common::expr_parse::str_to_expr_pb;
+    use ir_common::generated::algebra as pb;
+    use ir_common::generated::physical as physical_pb;
+    use ir_physical_client::physical_builder::*;
+    use pegasus_server::JobRequest;
+    use runtime::process::entry::Entry;
+
+    use crate::common::test::*;
+
+    // g.V(1).as('a').repeat(where(select('a').values('name').is(name)).out()).until(until)
+    fn init_repeat_request(name: &str, until: &str, until_first: bool) -> JobRequest {
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: Some(TAG_A.into()),
+            params: None,
+            idx_predicate: Some(vec![to_global_id(1, 0)].into()),
+            is_count_only: false,
+            meta_data: None,
+        };
+        let invariant = pb::Select {
+            predicate: Some(str_to_expr_pb(format!("@{}.name == \"{}\"", TAG_A, name)).unwrap()),
+        };
+        let expand_opr = pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: Some(query_params(vec![], vec![], None)),
+            expand_opt: 0,
+            alias: None,
+            meta_data: None,
+            is_optional: false,
+        };
+        let mut body = PlanBuilder::default();
+        body.select(invariant);
+        body.shuffle(None);
+        body.edge_expand(expand_opr);
+        let repeat = physical_pb::Repeat {
+            body: None,
+            until: Some(str_to_expr_pb(until.to_string()).unwrap()),
+            max_iterations: 0,
+            until_first,
+        };
+
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.repeat(body, repeat);
+        job_builder.sink(default_sink_pb());
+
+        job_builder.build().unwrap()
+    }
+
+    fn collect_ids(request: JobRequest, worker_num: u32) -> Vec<i64> {
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let vertex = record
+                        .get(None)
+                        .unwrap()
+                        .as_vertex()
+                        .unwrap()
+                        .id();
+                    result_collection.push(vertex);
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        result_collection.sort();
+        result_collection
+    }
+
+    fn to_global_id(id: usize, label: u8) -> i64 {
+        let global_id: DefaultId = LDBCVertexParser::to_global_id(id, label);
+        global_id as i64
+    }
+
+    // v1 -> v2, v3, v4 in the first iteration, where v3 leaves the loop, and v4 -> v3, v5 in the second
+    // iteration, which both leave the loop, while v2 has no out edges
+    // until(hasLabel('software'))
+    fn repeat_until(worker_num: u32, until_first: bool) {
+        initialize();
+        let request = init_repeat_request("marko", "@.~label == 1", until_first);
+        let mut expected = vec![to_global_id(3, 1), to_global_id(3, 1), to_global_id(5, 1)];
+        expected.sort();
+        assert_eq!(collect_ids(request, worker_num), expected);
+    }
+
+    // the invariant filter drops all the records
+    fn repeat_invariant_filtered(worker_num: u32) {
+        initialize();
+        let request = init_repeat_request("vadas", "@.~label == 1", false);
+        assert!(collect_ids(request, worker_num).is_empty());
+    }
+
+    #[test]
+    fn repeat_until_test() {
+        repeat_until(1, false)
+    }
+
+    #[test]
+    fn repeat_until_w2_test() {
+        repeat_until(2, false)
+    }
+
+    // until(hasLabel('person')), where v1 leaves the loop before the first iteration if the condition
+    // is evaluated before it, or else v2 and v4 leave the loop after the first iteration, while v3 has
+    // no out edges
+    fn repeat_until_person(worker_num: u32, until_first: bool) {
+        initialize();
+        let request = init_repeat_request("marko", "@.~label == 0", until_first);
+        let expected = if until_first {
+            vec![to_global_id(1, 0)]
+        } else {
+            let mut expected = vec![to_global_id(2, 0), to_global_id(4, 0)];
+            expected.sort();
+            expected
+        };
+        assert_eq!(collect_ids(request, worker_num), expected);
+    }
+
+    #[test]
+    fn repeat_until_first_test() {
+        repeat_until(2, true)
+    }
+
+    #[test]
+    fn repeat_until_person_test() {
+        repeat_until_person(2, false)
+    }
+
+    #[test]
+    fn repeat_until_person_first_test() {
+        repeat_until_person(2, true)
+    }
+
+    #[test]
+    fn repeat_invariant_filtered_test() {
+        repeat_invariant_filtered(2)
+    }
+}
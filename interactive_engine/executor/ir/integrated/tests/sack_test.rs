@@ -0,0 +1,133 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use graph_proxy::apis::GraphElement;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::DefaultId;
+    use ir_common::expr_parse::str_to_expr_pb;
+    use ir_common::generated::algebra as pb;
+    use ir_common::generated::common as common_pb;
+    use ir_physical_client::physical_builder::*;
+    use pegasus_server::JobRequest;
+    use runtime::process::entry::Entry;
+
+    use crate::common::test::*;
+
+    fn sack_opr(inner: pb::sack::Inner) -> pb::Sack {
+        pb::Sack { inner: Some(inner) }
+    }
+
+    // g.withSack(1, sum).V().out().as('b').barrier().sack(), where the sacks count the in-edges
+    fn init_sack_request() -> JobRequest {
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: None,
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        };
+        let expand_opr = pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: Some(query_params(vec![], vec![], None)),
+            expand_opt: 0,
+            alias: Some(TAG_B.into()),
+            meta_data: None,
+            is_optional: false,
+        };
+        let init = pb::sack::Init { initial: str_to_expr_pb("1".to_string()).ok() };
+        let merge = pb::sack::Merge { operator: pb::sack::Operator::Sum as i32 };
+        let sink_opr = pb::Sink {
+            tags: vec![
+                common_pb::NameOrIdKey { key: Some(TAG_B.into()) },
+                common_pb::NameOrIdKey { key: None },
+            ],
+            sink_target: default_sink_target(),
+        };
+
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.sack(sack_opr(pb::sack::Inner::Init(init)));
+        job_builder.shuffle(None);
+        job_builder.edge_expand(expand_opr);
+        job_builder.sack(sack_opr(pb::sack::Inner::Merge(merge)));
+        job_builder.sack(sack_opr(pb::sack::Inner::Get(pb::sack::Get { alias: None })));
+        job_builder.sink(sink_opr);
+
+        job_builder.build().unwrap()
+    }
+
+    fn to_global_id(id: usize, label: u8) -> i64 {
+        let global_id: DefaultId = LDBCVertexParser::to_global_id(id, label);
+        global_id as i64
+    }
+
+    fn sack_merge(worker_num: u32) {
+        initialize();
+        let request = init_sack_request();
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let vertex = record
+                        .get(Some(TAG_B))
+                        .unwrap()
+                        .as_vertex()
+                        .unwrap()
+                        .id();
+                    let sack = record
+                        .get(None)
+                        .unwrap()
+                        .as_object()
+                        .unwrap()
+                        .as_i64()
+                        .unwrap();
+                    result_collection.push((vertex, sack));
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        result_collection.sort();
+        let mut expected = vec![
+            (to_global_id(2, 0), 1),
+            (to_global_id(4, 0), 1),
+            (to_global_id(3, 1), 3),
+            (to_global_id(5, 1), 1),
+        ];
+        expected.sort();
+        assert_eq!(result_collection, expected);
+    }
+
+    #[test]
+    fn sack_merge_test() {
+        sack_merge(1)
+    }
+
+    #[test]
+    fn sack_merge_w2_test() {
+        sack_merge(2)
+    }
+}
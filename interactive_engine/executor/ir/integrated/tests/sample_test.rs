@@ -20,7 +20,10 @@ mod common;
 #[cfg(test)]
 mod test {
     use graph_proxy::apis::GraphElement;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::DefaultId;
     use ir_common::generated::algebra as pb;
+    use ir_common::generated::common as common_pb;
     use ir_physical_client::physical_builder::*;
     use pegasus_server::JobRequest;
     use runtime::process::entry::Entry;
@@ -35,6 +38,7 @@ mod test {
             }),
             seed,
             sample_weight: None,
+            sample_key: None,
         }
     }
 
@@ -46,9 +50,20 @@ mod test {
             }),
             seed,
             sample_weight: None,
+            sample_key: None,
         }
     }
 
+    fn gen_sample_by_num_with_opr(
+        sample_num: i32, sample_weight: Option<common_pb::Variable>,
+        sample_key: Option<common_pb::Variable>,
+    ) -> pb::Sample {
+        let mut sample = gen_sample_by_num_opr(sample_num, None);
+        sample.sample_weight = sample_weight;
+        sample.sample_key = sample_key;
+        sample
+    }
+
     // g.V().sample()
     fn init_scan_sample_request(sample: pb::Sample) -> JobRequest {
         let source_opr = pb::Scan {
@@ -98,6 +113,63 @@ mod test {
         job_builder.build().unwrap()
     }
 
+    // g.V().as('a').out().sample().by('a')
+    fn init_scan_out_sample_by_key_request(sample: pb::Sample) -> JobRequest {
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: Some(TAG_A.into()),
+            params: Some(query_params(vec![], vec![], None)),
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        };
+
+        let expand_opr = pb::EdgeExpand {
+            v_tag: Some(TAG_A.into()),
+            direction: 0,
+            params: Some(query_params(vec![], vec![], None)),
+            expand_opt: 0,
+            alias: None,
+            meta_data: None,
+            is_optional: false,
+        };
+
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.edge_expand(expand_opr);
+        job_builder.sample(sample);
+        job_builder.sink(default_sink_pb());
+
+        job_builder.build().unwrap()
+    }
+
+    fn collect_vertices(request: JobRequest, worker_num: u32) -> Vec<(i64, Option<i64>)> {
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let vertex = record.get(None).unwrap().as_vertex().unwrap();
+                    let start = record
+                        .get(Some(TAG_A))
+                        .and_then(|entry| entry.as_vertex())
+                        .map(|vertex| vertex.id());
+                    result_collection.push((vertex.id(), start));
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        result_collection
+    }
+
+    fn to_global_id(id: usize, label: u8) -> i64 {
+        let global_id: DefaultId = LDBCVertexParser::to_global_id(id, label);
+        global_id as i64
+    }
+
     fn scan_sample_by_num(worker_num: u32, sample_num: i32) -> usize {
         initialize();
         let sample_by_num = gen_sample_by_num_opr(sample_num, None);
@@ -346,4 +418,88 @@ mod test {
             assert_eq!(first_sample, try_sample);
         }
     }
+
+    // g.V().as('a').out().sample(1).by('a'), i.e., 1 neighbor per vertex
+    #[test]
+    fn scan_out_sample_by_key_test() {
+        initialize();
+        let sample = gen_sample_by_num_with_opr(1, None, Some(to_var_pb(Some(TAG_A.into()), None)));
+        let request = init_scan_out_sample_by_key_request(sample);
+        let mut starts: Vec<i64> = collect_vertices(request, 2)
+            .into_iter()
+            .map(|(_, start)| start.unwrap())
+            .collect();
+        starts.sort();
+        let mut expected = vec![to_global_id(1, 0), to_global_id(4, 0), to_global_id(6, 0)];
+        expected.sort();
+        assert_eq!(starts, expected);
+    }
+
+    // g.V().as('a').out().sample(2).by('a'), i.e., 2 neighbors per vertex at most
+    #[test]
+    fn scan_out_sample_by_key_02_test() {
+        initialize();
+        let sample = gen_sample_by_num_with_opr(2, None, Some(to_var_pb(Some(TAG_A.into()), None)));
+        let request = init_scan_out_sample_by_key_request(sample);
+        let mut starts: Vec<i64> = collect_vertices(request, 2)
+            .into_iter()
+            .map(|(_, start)| start.unwrap())
+            .collect();
+        starts.sort();
+        let (v1, v4, v6) = (to_global_id(1, 0), to_global_id(4, 0), to_global_id(6, 0));
+        let mut expected = vec![v1, v1, v4, v4, v6];
+        expected.sort();
+        assert_eq!(starts, expected);
+    }
+
+    // g.V().sample(2).by('age'), where the vertices without ages are never sampled
+    #[test]
+    fn scan_sample_by_weight_test() {
+        initialize();
+        let sample = gen_sample_by_num_with_opr(2, Some(to_var_pb(None, Some("age".into()))), None);
+        let request = init_scan_sample_request(sample);
+        let result_collection = collect_vertices(request, 2);
+        assert_eq!(result_collection.len(), 2);
+        let persons: Vec<i64> = vec![1, 2, 4, 6]
+            .into_iter()
+            .map(|id| to_global_id(id, 0))
+            .collect();
+        for (id, _) in result_collection {
+            assert!(persons.contains(&id));
+        }
+    }
+
+    // g.V().sample(10).by('age')
+    #[test]
+    fn scan_sample_by_weight_02_test() {
+        initialize();
+        let sample = gen_sample_by_num_with_opr(10, Some(to_var_pb(None, Some("age".into()))), None);
+        let request = init_scan_sample_request(sample);
+        let mut ids: Vec<i64> = collect_vertices(request, 2)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort();
+        let mut persons: Vec<i64> = vec![1, 2, 4, 6]
+            .into_iter()
+            .map(|id| to_global_id(id, 0))
+            .collect();
+        persons.sort();
+        assert_eq!(ids, persons);
+    }
+
+    // g.V().out().coin(0.5).with("REPEATABLE", 97)
+    #[test]
+    fn scan_out_sample_by_coin_with_seed_test() {
+        initialize();
+        let request = init_scan_out_sample_request(gen_sample_by_ratio_opr(0.5, Some(97)));
+        let mut first_sample = collect_vertices(request, 2);
+        first_sample.sort();
+        for _i in 0..3 {
+            let request = init_scan_out_sample_request(gen_sample_by_ratio_opr(0.5, Some(97)));
+            let mut try_sample = collect_vertices(request, 2);
+            try_sample.sort();
+            assert_eq!(first_sample, try_sample);
+        }
+    }
 }
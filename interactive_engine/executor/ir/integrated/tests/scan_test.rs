@@ -21,7 +21,7 @@ mod common;
 mod test {
     use std::sync::Arc;
 
-    use graph_proxy::apis::{register_graph, GraphElement};
+    use graph_proxy::apis::{register_graph, Direction, GraphElement, ReadGraph};
     use graph_proxy::create_exp_store;
     use graph_store::common::DefaultId;
     use graph_store::ldbc::LDBCVertexParser;
@@ -456,4 +456,19 @@ mod test {
         expected_ids.sort();
         assert_eq!(result_ids, expected_ids)
     }
+
+    // the statistics of the modern graph
+    #[test]
+    fn collect_statistics_test() {
+        let graph = create_exp_store(Arc::new(TestCluster {}));
+        let statistics = graph.collect_statistics().unwrap().unwrap();
+        assert_eq!(statistics.vertex_count(&[PERSON_LABEL]), Some(4));
+        assert_eq!(statistics.vertex_count(&[SOFTWARE_LABEL]), Some(2));
+        assert_eq!(statistics.edge_count(&[KNOWS_LABEL]), Some(2));
+        assert_eq!(statistics.edge_count(&[CREATED_LABEL]), Some(4));
+        assert_eq!(statistics.vertex_ndv(&[PERSON_LABEL], &"name".into()), Some(4));
+        // marko knows 2 persons, and each of them is known by marko
+        assert_eq!(statistics.avg_degree(&[KNOWS_LABEL], Direction::Out), Some(2.0));
+        assert_eq!(statistics.avg_degree(&[KNOWS_LABEL], Direction::In), Some(1.0));
+    }
 }
@@ -0,0 +1,204 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use ir_common::generated::algebra as pb;
+    use ir_common::generated::physical as physical_pb;
+    use ir_common::generated::results as result_pb;
+    use ir_physical_client::physical_builder::*;
+    use pegasus_server::JobRequest;
+    use prost::Message;
+    use runtime::process::entry::Entry;
+
+    use crate::common::test::*;
+
+    fn source_opr() -> pb::Scan {
+        pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: None,
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        }
+    }
+
+    fn side_effect_opr(kind: pb::side_effect::Kind) -> pb::SideEffect {
+        pb::SideEffect { name: "x".to_string(), kind: kind as i32, tag: None }
+    }
+
+    // g.V().aggregate('x').cap('x'), where the side effect of `x` counts the vertices
+    fn init_cap_request() -> JobRequest {
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr());
+        job_builder.shuffle(None);
+        job_builder.side_effect(side_effect_opr(pb::side_effect::Kind::Count));
+        job_builder.cap(pb::Cap { name: "x".to_string(), alias: None });
+        job_builder.sink(default_sink_pb());
+
+        job_builder.build().unwrap()
+    }
+
+    fn expand_opr() -> pb::EdgeExpand {
+        pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: Some(query_params(vec![], vec![], None)),
+            expand_opt: 0,
+            alias: None,
+            meta_data: None,
+            is_optional: false,
+        }
+    }
+
+    // g.V().repeat(out().aggregate('x')).times(2).cap('x'), where the side effect of `x` counts the
+    // vertices of both iterations
+    fn init_repeat_cap_request() -> JobRequest {
+        let mut body = PlanBuilder::default();
+        body.shuffle(None);
+        body.edge_expand(expand_opr());
+        body.side_effect(side_effect_opr(pb::side_effect::Kind::Count));
+        let repeat = physical_pb::Repeat { body: None, until: None, max_iterations: 2, until_first: false };
+
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr());
+        job_builder.repeat(body, repeat);
+        job_builder.cap(pb::Cap { name: "x".to_string(), alias: None });
+        job_builder.sink(default_sink_pb());
+
+        job_builder.build().unwrap()
+    }
+
+    // g.V().aggregate('x').out(), where the side effect is returned after the results
+    fn init_footer_request() -> JobRequest {
+        let expand_opr = expand_opr();
+
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr());
+        job_builder.side_effect(side_effect_opr(pb::side_effect::Kind::Set));
+        job_builder.shuffle(None);
+        job_builder.edge_expand(expand_opr);
+        job_builder.sink(default_sink_pb());
+
+        job_builder.build().unwrap()
+    }
+
+    fn collect_counts(request: JobRequest, worker_num: u32) -> Vec<u64> {
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let count = record
+                        .get(None)
+                        .unwrap()
+                        .as_object()
+                        .unwrap()
+                        .as_u64()
+                        .unwrap();
+                    result_collection.push(count);
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        result_collection
+    }
+
+    fn cap(worker_num: u32) {
+        initialize();
+        let request = init_cap_request();
+        assert_eq!(collect_counts(request, worker_num), vec![6]);
+    }
+
+    // the first iteration expands to v2, v4, v3, v5, v3 and v3, and the second one from v4 to v5 and v3
+    fn repeat_cap(worker_num: u32) {
+        initialize();
+        let request = init_repeat_cap_request();
+        assert_eq!(collect_counts(request, worker_num), vec![8]);
+    }
+
+    fn footer(worker_num: u32) {
+        initialize();
+        let request = init_footer_request();
+        let mut results = submit_query(request, worker_num);
+        let mut records = 0;
+        let mut side_effects = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => match result_pb::Results::decode(res.as_slice())
+                    .unwrap()
+                    .inner
+                {
+                    Some(result_pb::results::Inner::Record(_)) => {
+                        // the side effect is sent after the results
+                        assert!(side_effects.is_empty());
+                        records += 1;
+                    }
+                    Some(result_pb::results::Inner::SideEffect(side_effect)) => {
+                        let collection = match side_effect.entry.and_then(|entry| entry.inner) {
+                            Some(result_pb::entry::Inner::Collection(collection)) => collection.collection,
+                            entry => panic!("unexpected side effect {:?}", entry),
+                        };
+                        side_effects.push((side_effect.name, collection.len()));
+                    }
+                    None => panic!("empty result"),
+                },
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        assert_eq!(records, 6);
+        assert_eq!(side_effects, vec![("x".to_string(), 6)]);
+    }
+
+    #[test]
+    fn cap_test() {
+        cap(1)
+    }
+
+    #[test]
+    fn cap_w2_test() {
+        cap(2)
+    }
+
+    #[test]
+    fn repeat_cap_test() {
+        repeat_cap(1)
+    }
+
+    #[test]
+    fn repeat_cap_w2_test() {
+        repeat_cap(2)
+    }
+
+    #[test]
+    fn side_effect_footer_test() {
+        footer(1)
+    }
+
+    #[test]
+    fn side_effect_footer_w2_test() {
+        footer(2)
+    }
+}
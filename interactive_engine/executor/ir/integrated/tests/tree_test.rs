@@ -0,0 +1,162 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use graph_proxy::apis::GraphElement;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::DefaultId;
+    use ir_common::generated::algebra as pb;
+    use ir_common::generated::common as common_pb;
+    use ir_physical_client::physical_builder::*;
+    use pegasus_common::downcast::AsAny;
+    use pegasus_server::JobRequest;
+    use runtime::process::entry::{DynEntry, Entry, MapEntry};
+
+    use crate::common::test::*;
+
+    // g.V().as('a').out().as('b').select('a', 'b').tree(), where each vertex with the out-edges is a root
+    fn init_tree_request(max_size: i32) -> JobRequest {
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: Some(TAG_A.into()),
+            params: None,
+            idx_predicate: None,
+            is_count_only: false,
+            meta_data: None,
+        };
+        let expand_opr = pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: Some(query_params(vec![], vec![], None)),
+            expand_opt: 0,
+            alias: Some(TAG_B.into()),
+            meta_data: None,
+            is_optional: false,
+        };
+        let level =
+            |tag: i32| common_pb::Variable { tag: Some(tag.into()), property: None, node_type: None };
+        let tree_opr = pb::Tree { levels: vec![level(TAG_A), level(TAG_B)], max_size, alias: None };
+
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.shuffle(None);
+        job_builder.edge_expand(expand_opr);
+        job_builder.tree(tree_opr);
+        job_builder.sink(default_sink_pb());
+
+        job_builder.build().unwrap()
+    }
+
+    fn to_global_id(id: usize, label: u8) -> i64 {
+        let global_id: DefaultId = LDBCVertexParser::to_global_id(id, label);
+        global_id as i64
+    }
+
+    // the pairs of the elements and their subtrees of the tree
+    fn pairs_of(tree: &DynEntry) -> Vec<(i64, DynEntry)> {
+        tree.as_any_ref()
+            .downcast_ref::<MapEntry>()
+            .unwrap()
+            .inner
+            .iter()
+            .map(|pair| (pair.get_left().as_vertex().unwrap().id(), pair.get_right().clone()))
+            .collect()
+    }
+
+    // the roots with the numbers of their children, and all the leaves
+    fn tree(worker_num: u32, max_size: i32) -> (Vec<(i64, usize)>, Vec<i64>) {
+        initialize();
+        let request = init_tree_request(max_size);
+        let mut results = submit_query(request, worker_num);
+        let mut roots = vec![];
+        let mut leaves = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    // a tree per root
+                    let mut tree = pairs_of(record.get(None).unwrap());
+                    assert_eq!(tree.len(), 1);
+                    let (root, children) = tree.remove(0);
+                    let children = pairs_of(&children);
+                    roots.push((root, children.len()));
+                    for (leaf, subtree) in children {
+                        assert!(pairs_of(&subtree).is_empty());
+                        leaves.push(leaf);
+                    }
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        roots.sort();
+        leaves.sort();
+        (roots, leaves)
+    }
+
+    fn tree_all(worker_num: u32) {
+        let (roots, leaves) = tree(worker_num, 0);
+        let mut expected_roots =
+            vec![(to_global_id(1, 0), 3), (to_global_id(4, 0), 2), (to_global_id(6, 0), 1)];
+        expected_roots.sort();
+        let mut expected_leaves = vec![
+            to_global_id(2, 0),
+            to_global_id(3, 1),
+            to_global_id(4, 0),
+            to_global_id(3, 1),
+            to_global_id(5, 1),
+            to_global_id(3, 1),
+        ];
+        expected_leaves.sort();
+        assert_eq!(roots, expected_roots);
+        assert_eq!(leaves, expected_leaves);
+    }
+
+    // the nodes of each tree beyond the size of 2 are dropped
+    fn tree_limited(worker_num: u32) {
+        let (roots, leaves) = tree(worker_num, 2);
+        let mut expected_roots =
+            vec![(to_global_id(1, 0), 1), (to_global_id(4, 0), 1), (to_global_id(6, 0), 1)];
+        expected_roots.sort();
+        assert_eq!(roots, expected_roots);
+        assert_eq!(leaves.len(), 3);
+    }
+
+    #[test]
+    fn tree_test() {
+        tree_all(1)
+    }
+
+    #[test]
+    fn tree_w2_test() {
+        tree_all(2)
+    }
+
+    #[test]
+    fn tree_max_size_test() {
+        tree_limited(1)
+    }
+
+    #[test]
+    fn tree_max_size_w2_test() {
+        tree_limited(2)
+    }
+}
@@ -0,0 +1,153 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+//!
+//!
+
+mod common;
+
+#[cfg(test)]
+mod test {
+    use graph_proxy::apis::GraphElement;
+    use graph_store::ldbc::LDBCVertexParser;
+    use graph_store::prelude::DefaultId;
+    use ir_common::generated::algebra as pb;
+    use ir_common::generated::common as common_pb;
+    use ir_physical_client::physical_builder::*;
+    use pegasus_server::JobRequest;
+    use runtime::process::entry::Entry;
+
+    use crate::common::test::*;
+
+    fn expand_opr(label: i32) -> pb::EdgeExpand {
+        pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: Some(query_params(vec![label.into()], vec![], None)),
+            expand_opt: 0,
+            alias: None,
+            meta_data: None,
+            is_optional: false,
+        }
+    }
+
+    // g.V(1).union(out('knows'), out('created'), identity()), with the branches tagged by `TAG_B`
+    fn init_union_request(branch_alias: Option<common_pb::NameOrId>) -> JobRequest {
+        let source_opr = pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: None,
+            idx_predicate: Some(vec![to_global_id(1, 0)].into()),
+            is_count_only: false,
+            meta_data: None,
+        };
+        let mut knows = PlanBuilder::default();
+        knows.shuffle(None);
+        knows.edge_expand(expand_opr(KNOWS_LABEL as i32));
+        let mut created = PlanBuilder::default();
+        created.shuffle(None);
+        created.edge_expand(expand_opr(CREATED_LABEL as i32));
+        let identity = PlanBuilder::default();
+
+        let sink_opr = pb::Sink {
+            tags: vec![
+                common_pb::NameOrIdKey { key: None },
+                common_pb::NameOrIdKey { key: Some(TAG_B.into()) },
+            ],
+            sink_target: default_sink_target(),
+        };
+
+        let mut job_builder = JobBuilder::default();
+        job_builder.add_scan_source(source_opr);
+        job_builder.union_with_branch(vec![knows, created, identity], branch_alias);
+        job_builder.sink(sink_opr);
+
+        job_builder.build().unwrap()
+    }
+
+    fn collect_branches(request: JobRequest, worker_num: u32) -> Vec<(i64, Option<i32>)> {
+        let mut results = submit_query(request, worker_num);
+        let mut result_collection = vec![];
+        while let Some(result) = results.next() {
+            match result {
+                Ok(res) => {
+                    let record = parse_result(res).unwrap();
+                    let vertex = record
+                        .get(None)
+                        .unwrap()
+                        .as_vertex()
+                        .unwrap()
+                        .id();
+                    let branch = record
+                        .get(Some(TAG_B))
+                        .and_then(|entry| entry.as_object())
+                        .map(|branch| branch.as_i32().unwrap());
+                    result_collection.push((vertex, branch));
+                }
+                Err(e) => {
+                    panic!("err result {:?}", e);
+                }
+            }
+        }
+        result_collection.sort();
+        result_collection
+    }
+
+    fn to_global_id(id: usize, label: u8) -> i64 {
+        let global_id: DefaultId = LDBCVertexParser::to_global_id(id, label);
+        global_id as i64
+    }
+
+    fn union_with_branch(worker_num: u32) {
+        initialize();
+        let request = init_union_request(Some(TAG_B.into()));
+        let mut expected = vec![
+            (to_global_id(2, 0), Some(0)),
+            (to_global_id(4, 0), Some(0)),
+            (to_global_id(3, 1), Some(1)),
+            (to_global_id(1, 0), Some(2)),
+        ];
+        expected.sort();
+        assert_eq!(collect_branches(request, worker_num), expected);
+    }
+
+    // the records are not tagged without the branch alias
+    fn union_without_branch(worker_num: u32) {
+        initialize();
+        let request = init_union_request(None);
+        let mut expected = vec![
+            (to_global_id(2, 0), None),
+            (to_global_id(4, 0), None),
+            (to_global_id(3, 1), None),
+            (to_global_id(1, 0), None),
+        ];
+        expected.sort();
+        assert_eq!(collect_branches(request, worker_num), expected);
+    }
+
+    #[test]
+    fn union_with_branch_test() {
+        union_with_branch(1)
+    }
+
+    #[test]
+    fn union_with_branch_w2_test() {
+        union_with_branch(2)
+    }
+
+    #[test]
+    fn union_without_branch_test() {
+        union_without_branch(2)
+    }
+}
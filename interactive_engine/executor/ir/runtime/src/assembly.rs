@@ -17,38 +17,84 @@ use std::convert::TryInto;
 use std::sync::Arc;
 use std::vec;
 
+use dyn_type::Object;
 use graph_proxy::apis::cluster_info::ClusterInfo;
-use graph_proxy::apis::partitioner::PartitionInfo;
+use graph_proxy::apis::partitioner::{PartitionInfo, PartitionedData};
+use graph_proxy::apis::{
+    bind_consistency, bind_include_deleted, bind_view, get_graph, get_statistics, get_view, QueryParams,
+    ReadConsistency,
+};
 use ir_common::error::ParsePbError;
 use ir_common::generated::algebra as algebra_pb;
 use ir_common::generated::algebra::join::JoinKind;
 use ir_common::generated::physical as pb;
 use ir_common::generated::physical::physical_opr::operator::OpKind;
+use ir_common::{KeyId, LabelId};
 use pegasus::api::function::*;
 use pegasus::api::{
     Collect, CorrelatedSubTask, Count, Dedup, Filter, Fold, FoldByKey, HasAny, IterCondition, Iteration,
-    Join, KeyBy, Limit, Map, Merge, Sink, SortBy, SortLimitBy,
+    Join, KeyBy, Limit, Map, Merge, OrderedExchange, ReduceByKey, Sink, SortBy, SortLimitBy, Unary,
 };
+use pegasus::codec::Encode;
 use pegasus::stream::Stream;
 use pegasus::{BuildJobError, Worker};
-use pegasus_server::job::{JobAssembly, JobDesc};
+use pegasus_server::audit::PlanSummary;
+use pegasus_server::explain::PlanGraph;
+use pegasus_server::job::{InvalidPlan, JobAssembly, JobDesc};
 use pegasus_server::job_pb as server_pb;
 use prost::Message;
 
+use crate::audit::summarize_plan;
+use crate::auth::{principal_key, AccessPolicy, PropertyMask};
 use crate::error::{FnExecError, FnGenError, FnGenResult};
+use crate::explain::explain_plan;
+use crate::lint::lint_plan;
+use crate::matching::plan_matches;
+use crate::procedure::{bind_named_params, bind_params, ProcedureRegistry, StoredProcedure};
+use crate::process::entry::DynEntry;
 use crate::process::functions::{ApplyGen, CompareFunction, FoldGen, GroupGen, JoinKeyGen, KeyFunction};
 use crate::process::operator::accum::accumulator::Accumulator;
-use crate::process::operator::accum::{SampleAccum, SampleAccumFactoryGen};
+use crate::process::operator::accum::{RecordAccumulator, SampleAccum, SampleAccumFactoryGen};
+use crate::process::operator::algorithm::adjacency::bind_read_scope;
+use crate::process::operator::algorithm::{
+    AlgorithmFuncGen, AlgorithmOperator, TriangleMessage, TriangleOperator, TriangleSummary,
+};
+use crate::process::operator::coalesce::split_coalesced;
+use crate::process::operator::dedup_filter::{dedup_spilled, filter_duplicates, DedupFilter, DedupSpill};
 use crate::process::operator::filter::FilterFuncGen;
 use crate::process::operator::flatmap::FlatMapFuncGen;
+use crate::process::operator::k_hop::{KHopFuncGen, KHopOperator};
 use crate::process::operator::keyed::KeyFunctionGen;
-use crate::process::operator::map::{FilterMapFuncGen, MapFuncGen};
+use crate::process::operator::map::{FilterMapFuncGen, MapFuncGen, ProjectFuncGen, ProjectOperator};
+use crate::process::operator::prefetch_expand::{
+    ExpandPrefetch, PrefetchExpandFuncGen, PrefetchExpandOperator,
+};
+use crate::process::operator::repeat::{count_iteration, RepeatFuncGen, RepeatOperator};
+use crate::process::operator::sack::{SackFuncGen, SackOperator};
 use crate::process::operator::shuffle::RecordRouter;
+use crate::process::operator::side_effect::{
+    add_local_side_effect, add_side_effect, send_local_side_effects, take_side_effect,
+    InstallingSideEffects, SideEffectFuncGen, SideEffectOperator,
+};
+use crate::process::operator::sink::graphbinary::RequestMessage;
 use crate::process::operator::sink::{SinkGen, Sinker};
 use crate::process::operator::sort::CompareFunctionGen;
 use crate::process::operator::source::SourceOperator;
+use crate::process::operator::split_expand::{
+    bind_worker_loads, ExpandSplit, SplitExpand, SplitExpandFuncGen,
+};
+use crate::process::operator::tree::{assemble_trees, TreeFuncGen, TreeOperator};
+use crate::process::operator::variable::{LoadVarFuncGen, StoreVarFuncGen, StoreVarOperator};
+use crate::process::operator::write::{MutateAccum, MutateFuncGen};
 use crate::process::record::{Record, RecordKey};
+use crate::provenance::{bind_provenance, is_tracking_provenance, trace, var_tags, TracedOperator};
+use crate::refine::refine_plan;
 use crate::router::{DefaultRouter, Router};
+use crate::session::{bind_session, SessionRegistry};
+use crate::simplify::simplify_plan;
+use crate::standing::StandingQueries;
+use crate::trigger::{InstallingTrigger, TriggerEvent, TriggerOn, TriggerRegistry};
+use crate::validate::validate_plan;
 
 type RecordMap = Box<dyn MapFunction<Record, Record>>;
 type RecordFilterMap = Box<dyn FilterMapFunction<Record, Record>>;
@@ -64,6 +110,28 @@ type RecordFold = Box<dyn FoldGen<u64, Record>>;
 
 pub struct IRJobAssembly<P: PartitionInfo, C: ClusterInfo> {
     udf_gen: FnGenerator<P, C>,
+    /// The graph served and the policy admitting plans on it, every plan is admitted if not set.
+    access: Option<(String, Arc<AccessPolicy>)>,
+    /// The variables of the sessions, which are not supported if not set.
+    sessions: Option<Arc<SessionRegistry>>,
+    /// The stored procedures, which are not supported if not set.
+    procedures: Option<Arc<ProcedureRegistry>>,
+    /// Split the adjacency of the high-degree vertices among the local workers in the edge expand, which
+    /// is not split if not set.
+    expand_split: Option<ExpandSplit>,
+    /// Read the adjacency of up to the depth of records ahead of the one expanded in the edge expand by
+    /// the shared pool, which is not read ahead if not set.
+    expand_prefetch: Option<ExpandPrefetch>,
+    /// Drop the duplicates by the pre-pass of each worker before the exact dedup, which are all shuffled
+    /// to the exact dedup if not set.
+    dedup_filter: Option<DedupFilter>,
+    /// Spill the records of the keys beyond those kept in the memory by the exact dedup, which keeps all
+    /// the keys in the memory if not set.
+    dedup_spill: Option<DedupSpill>,
+    /// The standing queries maintained by the mutations written, which are not maintained if not set.
+    standing_queries: Option<Arc<StandingQueries>>,
+    /// The triggers fired by the mutations written, which are not fired if not set.
+    triggers: Option<Arc<TriggerRegistry>>,
 }
 
 struct FnGenerator<P: PartitionInfo, C: ClusterInfo> {
@@ -100,8 +168,8 @@ impl<P: PartitionInfo, C: ClusterInfo> FnGenerator<P, C> {
         Ok(Box::new(record_router))
     }
 
-    fn gen_project(&self, opr: pb::Project) -> FnGenResult<RecordFilterMap> {
-        Ok(opr.gen_filter_map()?)
+    fn gen_project(&self, opr: pb::Project) -> FnGenResult<ProjectOperator> {
+        Ok(opr.gen_project()?)
     }
 
     fn gen_unfold(&self, opr: pb::Unfold) -> FnGenResult<RecordFlatMap> {
@@ -148,6 +216,16 @@ impl<P: PartitionInfo, C: ClusterInfo> FnGenerator<P, C> {
         Ok(opr.gen_flat_map()?)
     }
 
+    fn gen_split_expand(&self, opr: pb::EdgeExpand, split: ExpandSplit) -> FnGenResult<SplitExpand> {
+        Ok(opr.gen_split_expand(split)?)
+    }
+
+    fn gen_prefetch_expand(
+        &self, opr: pb::EdgeExpand, prefetch: ExpandPrefetch,
+    ) -> FnGenResult<PrefetchExpandOperator> {
+        Ok(opr.gen_prefetch_expand(prefetch)?)
+    }
+
     fn gen_edge_expand_collection(&self, opr: pb::EdgeExpand) -> FnGenResult<RecordFilterMap> {
         Ok(opr.gen_filter_map()?)
     }
@@ -178,6 +256,58 @@ impl<P: PartitionInfo, C: ClusterInfo> FnGenerator<P, C> {
         Ok(opr.gen_accum()?)
     }
 
+    fn gen_sample_key(&self, opr: algebra_pb::Sample) -> FnGenResult<RecordKeySelector> {
+        Ok(opr.gen_key()?)
+    }
+
+    fn gen_algorithm(&self, opr: algebra_pb::GraphAlgorithm) -> FnGenResult<AlgorithmOperator> {
+        Ok(opr.gen_algorithm()?)
+    }
+
+    fn gen_triangle(&self, opr: algebra_pb::GraphAlgorithm) -> FnGenResult<TriangleOperator> {
+        Ok(opr.gen_triangle()?)
+    }
+
+    fn gen_k_hop(&self, opr: algebra_pb::KHop) -> FnGenResult<KHopOperator> {
+        Ok(opr.gen_k_hop()?)
+    }
+
+    fn gen_repeat(&self, opr: pb::Repeat) -> FnGenResult<RepeatOperator> {
+        Ok(opr.gen_repeat()?)
+    }
+
+    fn gen_merge(&self, opr: algebra_pb::Merge) -> FnGenResult<RecordMap> {
+        Ok(opr.gen_map()?)
+    }
+
+    fn gen_mutate(&self, opr: algebra_pb::Mutate) -> FnGenResult<MutateAccum> {
+        Ok(opr.gen_mutate()?)
+    }
+
+    fn gen_store_var(
+        &self, opr: algebra_pb::StoreVar, sessions: Option<Arc<SessionRegistry>>,
+    ) -> FnGenResult<StoreVarOperator> {
+        Ok(opr.gen_store_var(sessions)?)
+    }
+
+    fn gen_load_var(
+        &self, opr: algebra_pb::LoadVar, sessions: Option<Arc<SessionRegistry>>,
+    ) -> FnGenResult<RecordMap> {
+        Ok(opr.gen_load_var(sessions)?)
+    }
+
+    fn gen_side_effect(&self, opr: algebra_pb::SideEffect) -> FnGenResult<SideEffectOperator> {
+        Ok(opr.gen_side_effect()?)
+    }
+
+    fn gen_sack(&self, opr: algebra_pb::Sack) -> FnGenResult<SackOperator> {
+        Ok(opr.gen_sack()?)
+    }
+
+    fn gen_tree(&self, opr: algebra_pb::Tree) -> FnGenResult<TreeOperator> {
+        Ok(opr.gen_tree()?)
+    }
+
     fn gen_sink(&self, opr: pb::PhysicalOpr) -> FnGenResult<Sinker> {
         Ok(opr.gen_sink()?)
     }
@@ -186,21 +316,289 @@ impl<P: PartitionInfo, C: ClusterInfo> FnGenerator<P, C> {
 impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
     pub fn new(router: Arc<dyn Router<P = P, C = C>>) -> Self {
         let udf_gen = FnGenerator::new(router);
-        IRJobAssembly { udf_gen }
+        IRJobAssembly {
+            udf_gen,
+            access: None,
+            sessions: None,
+            procedures: None,
+            expand_split: None,
+            expand_prefetch: None,
+            dedup_filter: None,
+            dedup_spill: None,
+            standing_queries: None,
+            triggers: None,
+        }
     }
 
     pub fn with(partition_info: Arc<P>, cluster_info: Arc<C>) -> Self {
         let udf_gen = FnGenerator::with(partition_info, cluster_info);
-        IRJobAssembly { udf_gen }
+        IRJobAssembly {
+            udf_gen,
+            access: None,
+            sessions: None,
+            procedures: None,
+            expand_split: None,
+            expand_prefetch: None,
+            dedup_filter: None,
+            dedup_spill: None,
+            standing_queries: None,
+            triggers: None,
+        }
+    }
+
+    pub fn with_access_policy(mut self, graph: &str, policy: Arc<AccessPolicy>) -> Self {
+        self.access = Some((graph.to_string(), policy));
+        self
+    }
+
+    pub fn with_sessions(mut self, sessions: Arc<SessionRegistry>) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    pub fn with_procedures(mut self, procedures: Arc<ProcedureRegistry>) -> Self {
+        self.procedures = Some(procedures);
+        self
+    }
+
+    pub fn with_expand_split(mut self, split: ExpandSplit) -> Self {
+        self.expand_split = Some(split);
+        self
+    }
+
+    pub fn with_expand_prefetch(mut self, prefetch: ExpandPrefetch) -> Self {
+        self.expand_prefetch = Some(prefetch);
+        self
+    }
+
+    pub fn with_dedup_filter(mut self, filter: DedupFilter) -> Self {
+        self.dedup_filter = Some(filter);
+        self
+    }
+
+    pub fn with_dedup_spill(mut self, spill: DedupSpill) -> Self {
+        self.dedup_spill = Some(spill);
+        self
+    }
+
+    pub fn with_standing_queries(mut self, standing_queries: Arc<StandingQueries>) -> Self {
+        self.standing_queries = Some(standing_queries);
+        self
+    }
+
+    pub fn with_triggers(mut self, triggers: Arc<TriggerRegistry>) -> Self {
+        self.triggers = Some(triggers);
+        self
+    }
+
+    /// The plan of the job as it's installed, with the session and the params of the request bound, the
+    /// access policy applied and the plan simplified and refined, and the mask of the properties visible
+    /// to the submitter if any.
+    fn rewrite_plan(
+        &self, job: &JobDesc, request: Option<&RequestMessage>,
+    ) -> FnGenResult<(pb::PhysicalPlan, Option<PropertyMask>)> {
+        let mut physical_plan = decode::<pb::PhysicalPlan>(&job.plan)?;
+        bind_session(&mut physical_plan, job.session.as_deref().unwrap_or_default())?;
+        if let Some(request) = request {
+            let bindings = request
+                .bindings()
+                .into_iter()
+                .map(|(name, value)| (name, value.into()))
+                .collect();
+            bind_named_params(&mut physical_plan, &bindings)?;
+        }
+        // the patterns are planned ahead of the policy, which then admits the scans and expands planned
+        let statistics = get_statistics();
+        plan_matches(&mut physical_plan, statistics.as_deref(), true)?;
+        let mut mask = None;
+        if let Some((graph, policy)) = self.access.as_ref() {
+            policy.admit(graph, job.principal.as_ref(), &physical_plan)?;
+            if job.include_deleted {
+                policy.admit_include_deleted(graph, job.principal.as_ref())?;
+            }
+            mask = policy.property_mask(graph, job.principal.as_ref());
+            // checked ahead of the row filters, which are defined by the policy and pushed down anyway
+            if let Some(mask) = mask.as_ref() {
+                mask.check_pushdown(&physical_plan)?;
+            }
+            policy.apply_row_filters(graph, job.principal.as_ref(), &mut physical_plan)?;
+        }
+        simplify_plan(&mut physical_plan)?;
+        if let Some(statistics) = statistics {
+            refine_plan(&mut physical_plan, &statistics)?;
+        }
+        Ok((physical_plan, mask))
+    }
+
+    /// The scope of the data read by the job besides its plan, i.e. the view, the deleted elements
+    /// included, and the caller with the restrictions of the policy applied to it, so that the adjacency
+    /// cached is shared only by the jobs of the same scope, see `adjacency`.
+    fn read_scope(&self, job: &JobDesc) -> FnGenResult<String> {
+        let restrictions = match self.access.as_ref() {
+            Some((graph, policy)) => policy.restrictions_key(graph, job.principal.as_ref())?,
+            None => principal_key(job.principal.as_ref()),
+        };
+        Ok(format!("{:?}|{}|{}", job.view, job.include_deleted, restrictions))
+    }
+
+    /// Install the trigger plans over the records of the elements written by the event, whose outputs
+    /// are discarded, while the records pass through.
+    fn install_trigger_plans(
+        &self, mut stream: Stream<Record>, plans: Vec<(TriggerOn, pb::PhysicalPlan)>, event: TriggerEvent,
+    ) -> Result<Stream<Record>, BuildJobError> {
+        let _installing = InstallingTrigger::new();
+        for (on, plan) in plans {
+            let (main, copied) = stream.copied()?;
+            let fired = copied.filter(move |record| Ok(on.matches_record(record, event)))?;
+            let discarded = self
+                .install(fired, &plan.plan, None)?
+                .filter(|_| Ok(false))?;
+            stream = main.merge(discarded)?;
+        }
+        Ok(stream)
+    }
+
+    /// Install the procedure called in place of the call.
+    fn install_call(
+        &self, stream: Stream<Record>, call: algebra_pb::Call, mask: Option<&PropertyMask>,
+    ) -> Result<Stream<Record>, BuildJobError> {
+        let procedure = self
+            .procedures
+            .as_ref()
+            .ok_or_else(|| FnGenError::unsupported_error("stored procedures are not enabled"))?
+            .get(&call.name)?
+            .ok_or_else(|| {
+                FnGenError::procedure_error(&format!("procedure `{}` is not found", call.name))
+            })?;
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!("Runtime call operator with procedure {:?}, args {:?}", call.name, call.args);
+        }
+        match procedure {
+            StoredProcedure::Plan(mut plan) => {
+                let statistics = get_statistics();
+                plan_matches(&mut plan, statistics.as_deref(), false)?;
+                bind_params(&mut plan, &call.args)?;
+                if let Some(mask) = mask {
+                    mask.check_pushdown(&plan)?;
+                }
+                simplify_plan(&mut plan)?;
+                if let Some(statistics) = statistics {
+                    refine_plan(&mut plan, &statistics)?;
+                }
+                self.install(stream, &plan.plan, mask)
+            }
+            StoredProcedure::Native(procedure) => {
+                let args = call
+                    .args
+                    .into_iter()
+                    .map(|arg| arg.try_into())
+                    .collect::<Result<Vec<Object>, _>>()
+                    .map_err(FnGenError::from)?;
+                procedure.install(stream, args)
+            }
+        }
+    }
+
+    /// Install the loop of the repeat, where the invariant filters hoisted out of the body are
+    /// evaluated in the first iteration only, which is unrolled unless the condition is evaluated
+    /// before it, where they're evaluated along with the condition.
+    fn install_repeat(
+        &self, mut stream: Stream<Record>, repeat: pb::Repeat, mask: Option<&PropertyMask>,
+    ) -> Result<Stream<Record>, BuildJobError> {
+        let RepeatOperator { invariants, body, until, max_iterations, until_first } =
+            self.udf_gen.gen_repeat(repeat)?;
+        let invariants = invariants
+            .into_iter()
+            .map(|select| self.udf_gen.gen_filter(select))
+            .collect::<FnGenResult<Vec<_>>>()?;
+        let mut iterations = max_iterations;
+        if until_first {
+            if !invariants.is_empty() {
+                // the records leaving the loop before the first iteration are not filtered by the body
+                let leave = until
+                    .clone()
+                    .map(|until| self.udf_gen.gen_filter(until))
+                    .transpose()?;
+                stream = stream.filter(move |record| {
+                    if let Some(leave) = leave.as_ref() {
+                        if leave.test(record)? {
+                            return Ok(true);
+                        }
+                    }
+                    for invariant in invariants.iter() {
+                        if !invariant.test(record)? {
+                            return Ok(false);
+                        }
+                    }
+                    Ok(true)
+                })?;
+            }
+        } else {
+            for invariant in invariants {
+                stream = stream.filter(move |record| invariant.test(record))?;
+            }
+            stream = count_iteration(stream, |_| 0)?;
+            stream = self.install(stream, &body, mask)?;
+            iterations -= 1;
+            if iterations == 0 {
+                return Ok(stream);
+            }
+        }
+        // the iterations unrolled before the loop
+        let unrolled = max_iterations - iterations;
+        let mut cond = IterCondition::max_iters(iterations);
+        if let Some(until) = until {
+            cond.set_until(self.udf_gen.gen_filter(until)?);
+        }
+        stream.iterate_until(cond, move |start| {
+            let start = count_iteration(start, move |tag| tag.current_uncheck().saturating_add(unrolled))?;
+            self.install(start, &body, mask)
+        })
+    }
+
+    /// Install the sub-plans of the coalesce in order, each applied to the records the sub-plans before
+    /// derive nothing from as a sub-task, and merge the records derived by them. The last sub-plan is
+    /// applied as a sub-task as well, so that, e.g., a `limit` in it bounds the results of each record.
+    fn install_coalesce(
+        &self, mut stream: Stream<Record>, coalesce: pb::Coalesce, mask: Option<&PropertyMask>,
+    ) -> Result<Stream<Record>, BuildJobError> {
+        let (last, sub_plans) = coalesce.sub_plans.split_last().ok_or_else(|| {
+            FnGenError::from(ParsePbError::EmptyFieldError("empty sub_plans of Coalesce".to_string()))
+        })?;
+        let mut derived: Option<Stream<Record>> = None;
+        for sub_plan in sub_plans {
+            let (sub_derived, left) = split_coalesced(stream.apply(|sub_start| {
+                self.install(sub_start, &sub_plan.plan[..], mask)?
+                    .collect::<Vec<Record>>()
+            })?)?;
+            derived = Some(match derived {
+                Some(derived) => derived.merge(sub_derived)?,
+                None => sub_derived,
+            });
+            stream = left;
+        }
+        let last_derived = stream
+            .apply(|sub_start| {
+                self.install(sub_start, &last.plan[..], mask)?
+                    .collect::<Vec<Record>>()
+            })?
+            .flat_map(|(_parent, sub)| Ok(sub.into_iter()))?;
+        match derived {
+            Some(derived) => derived.merge(last_derived),
+            None => Ok(last_derived),
+        }
     }
 
     fn install(
-        &self, mut stream: Stream<Record>, plan: &[pb::PhysicalOpr],
+        &self, mut stream: Stream<Record>, plan: &[pb::PhysicalOpr], mask: Option<&PropertyMask>,
     ) -> Result<Stream<Record>, BuildJobError> {
         let mut prev_op_kind = pb::physical_opr::operator::OpKind::Root(pb::Root {});
-        for op in &plan[..] {
+        for (index, op) in plan.iter().enumerate() {
             let op_kind = to_op_kind(op)?;
             match op_kind {
+                OpKind::Repartition(_) if is_two_phase_group(plan.get(index + 1))? => {
+                    // the partial aggregations of the group are shuffled instead of the records
+                }
                 OpKind::Repartition(repartition) => {
                     let repartition_strategy = repartition.strategy.as_ref().ok_or_else(|| {
                         FnGenError::from(ParsePbError::EmptyFieldError(
@@ -210,14 +608,44 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                     match repartition_strategy {
                         pb::repartition::Strategy::ToAnother(shuffle) => {
                             let router = self.udf_gen.gen_shuffle(shuffle)?;
-                            stream = stream.repartition(move |t| router.route(t));
+                            stream = if pegasus::is_deterministic() {
+                                stream.repartition_ordered(move |t| router.route(t))?
+                            } else {
+                                stream.repartition(move |t| router.route(t))
+                            };
+                        }
+                        pb::repartition::Strategy::ToOthers(_) => {
+                            stream = if pegasus::is_deterministic() {
+                                stream.broadcast_ordered()?
+                            } else {
+                                stream.broadcast()
+                            };
                         }
-                        pb::repartition::Strategy::ToOthers(_) => stream = stream.broadcast(),
                     }
                 }
                 OpKind::Project(project) => {
                     let func = self.udf_gen.gen_project(project)?;
-                    stream = stream.filter_map_with_name("Project", move |input| func.exec(input))?;
+                    if func.is_batched() {
+                        // amortize the overhead of calling the batched udfs over the records of a batch
+                        stream = stream.unary("Project", |_info| {
+                            move |input, output| {
+                                input.for_each_batch(|batch| {
+                                    if !batch.is_empty() {
+                                        let records: Vec<Record> = batch.drain().collect();
+                                        let projected = func
+                                            .exec_batch(records)
+                                            .map_err(DynError::from)?;
+                                        output
+                                            .new_session(&batch.tag)?
+                                            .give_iterator(projected.into_iter())?;
+                                    }
+                                    Ok(())
+                                })
+                            }
+                        })?;
+                    } else {
+                        stream = stream.filter_map_with_name("Project", move |input| func.exec(input))?;
+                    }
                 }
                 OpKind::Select(select) => {
                     let func = self.udf_gen.gen_filter(select)?;
@@ -283,42 +711,74 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                         let group_key = group.gen_group_key()?;
                         let group_accum = group.gen_group_accum()?;
                         let group_map = group.gen_group_map()?;
-                        stream = stream
-                            .key_by(move |record| group_key.get_kv(record))?
-                            .fold_partition_by_key(group_accum, || {
-                                |mut accumulator, next| {
-                                    accumulator.accum(next)?;
-                                    Ok(accumulator)
-                                }
-                            })?
-                            .unfold(|kv_map| {
-                                Ok(kv_map
-                                    .into_iter()
-                                    .map(|(key, mut accumulator)| {
-                                        accumulator.finalize().map(|value| (key, value))
-                                    })
-                                    .collect::<Result<Vec<_>, _>>()?
-                                    .into_iter())
-                            })?
-                            .map(move |key_value| group_map.exec(key_value))?;
+                        stream = if pegasus::is_deterministic() {
+                            // the records of a key are accumulated in the order of the workers they're from
+                            stream
+                                .key_by(move |record| group_key.get_kv(record))?
+                                .fold_partition_by_key(group_accum, || {
+                                    |mut accumulator, next| {
+                                        accumulator.accum(next)?;
+                                        Ok(accumulator)
+                                    }
+                                })?
+                                .unfold(|groups| finalize_groups(groups.into_iter()))?
+                        } else {
+                            // the records are accumulated partially by the worker they're in, and only the
+                            // partial accumulations are exchanged by the keys to be merged
+                            stream
+                                .key_by(move |record| group_key.get_kv(record))?
+                                .fold_local_by_key(group_accum, || {
+                                    |mut accumulator, next| {
+                                        accumulator.accum(next)?;
+                                        Ok(accumulator)
+                                    }
+                                })?
+                                .unfold(|partials| Ok(partials.into_iter()))?
+                                .key_by(|partial| Ok(partial))?
+                                .fold_partition_by_key(None, || {
+                                    |merged: Option<RecordAccumulator>, next| match merged {
+                                        Some(mut merged) => {
+                                            merged.merge(next)?;
+                                            Ok(Some(merged))
+                                        }
+                                        None => Ok(Some(next)),
+                                    }
+                                })?
+                                .unfold(|merged| {
+                                    finalize_groups(
+                                        merged
+                                            .into_iter()
+                                            .filter_map(|(key, merged)| merged.map(|m| (key, m))),
+                                    )
+                                })?
+                        }
+                        .map(move |key_value| group_map.exec(key_value))?;
                     }
                 }
                 OpKind::Dedup(dedup) => {
                     let selector = self.udf_gen.gen_dedup(dedup)?;
-                    stream = stream
-                        .key_by(move |record| selector.get_kv(record))?
-                        .dedup()?
-                        .map(|pair| Ok(pair.value))?;
+                    let mut keyed = stream.key_by(move |record| selector.get_kv(record))?;
+                    if let Some(filter) = self.dedup_filter {
+                        keyed = filter_duplicates(keyed, filter)?;
+                    }
+                    keyed = match self.dedup_spill.clone() {
+                        Some(spill) => dedup_spilled(keyed, spill)?,
+                        None => keyed.dedup()?,
+                    };
+                    stream = keyed.map(|pair| Ok(pair.value))?;
                 }
                 OpKind::Union(union) => {
+                    let branch_alias = union
+                        .branch_alias
+                        .map(|alias| alias.value as KeyId);
                     let (mut ori_stream, sub_stream) = stream.copied()?;
-                    stream = self.install(sub_stream, &union.sub_plans[0].plan[..])?;
-                    for subtask in &union.sub_plans[1..] {
+                    stream = self.install(sub_stream, &union.sub_plans[0].plan[..], mask)?;
+                    stream = tag_branch(stream, 0, branch_alias)?;
+                    for (branch, subtask) in union.sub_plans.iter().enumerate().skip(1) {
                         let copied = ori_stream.copied()?;
                         ori_stream = copied.0;
-                        stream = self
-                            .install(copied.1, &subtask.plan[..])?
-                            .merge(stream)?;
+                        let sub_stream = self.install(copied.1, &subtask.plan[..], mask)?;
+                        stream = tag_branch(sub_stream, branch, branch_alias)?.merge(stream)?;
                     }
                 }
                 OpKind::Apply(apply) => {
@@ -334,7 +794,7 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                             JoinKind::Semi => stream
                                 .apply(|sub_start| {
                                     let has_sub = self
-                                        .install(sub_start, &sub_task.plan[..])?
+                                        .install(sub_start, &sub_task.plan[..], mask)?
                                         .any()?;
                                     Ok(has_sub)
                                 })?
@@ -350,7 +810,7 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                             JoinKind::Anti => stream
                                 .apply(|sub_start| {
                                     let has_sub = self
-                                        .install(sub_start, &sub_task.plan[..])?
+                                        .install(sub_start, &sub_task.plan[..], mask)?
                                         .any()?;
                                     Ok(has_sub)
                                 })?
@@ -366,7 +826,7 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                             JoinKind::Inner | JoinKind::LeftOuter => stream
                                 .apply(|sub_start| {
                                     let sub_end = self
-                                        .install(sub_start, &sub_task.plan[..])?
+                                        .install(sub_start, &sub_task.plan[..], mask)?
                                         .collect::<Vec<Record>>()?;
                                     Ok(sub_end)
                                 })?
@@ -396,10 +856,10 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                         .ok_or_else(|| FnGenError::ParseError("right_task is missing in merge".into()))?;
                     let (left_stream, right_stream) = stream.copied()?;
                     let left_stream = self
-                        .install(left_stream, &left_task.plan[..])?
+                        .install(left_stream, &left_task.plan[..], mask)?
                         .key_by(move |record| left_key_selector.get_kv(record))?;
                     let right_stream = self
-                        .install(right_stream, &right_task.plan[..])?
+                        .install(right_stream, &right_task.plan[..], mask)?
                         .key_by(move |record| right_key_selector.get_kv(record))?;
                     stream = match join_kind {
                         JoinKind::Inner => left_stream
@@ -455,6 +915,17 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                         JoinKind::Times => Err(BuildJobError::Unsupported(
                             "JoinKind of Times is not supported yet".to_string(),
                         ))?,
+                    };
+                    if is_tracking_provenance() {
+                        let tags = var_tags(
+                            join.left_keys
+                                .iter()
+                                .chain(join.right_keys.iter()),
+                        );
+                        stream = stream.map(move |mut record| {
+                            trace(&mut record, TracedOperator::Join, &tags);
+                            Ok(record)
+                        })?;
                     }
                 }
                 OpKind::Intersect(intersect) => {
@@ -700,7 +1171,7 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
 
                     // pre-expanding for the path_expand case
                     if !pre_expands.is_empty() {
-                        stream = self.install(stream, &pre_expands)?;
+                        stream = self.install(stream, &pre_expands, mask)?;
                     }
                     // process intersect of edge_expands
                     let is_optimized = intersected_expands
@@ -720,7 +1191,7 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                     // intersect of edge_expands
                     for (repartition, expand_intersect_func) in intersect_expand_funcs {
                         if let Some(repartition) = repartition {
-                            stream = self.install(stream, &vec![repartition])?;
+                            stream = self.install(stream, &vec![repartition], mask)?;
                         }
                         stream = stream.filter_map_with_name("ExpandIntersect", move |input| {
                             expand_intersect_func.exec(input)
@@ -729,15 +1200,16 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                     // unfold the intersection
                     let unfold =
                         pb::Unfold { tag: Some(intersect.key.into()), alias: Some(intersect.key.into()) };
-                    stream = self.install(stream, &vec![unfold.into()])?;
+                    stream = self.install(stream, &vec![unfold.into()], mask)?;
 
                     // add vertex filters
                     if let Some(mut auxilia) = auxilia {
                         auxilia.tag = Some(intersect.key.into());
                         if let Some(auxilia_repartition) = auxilia_repartition {
-                            stream = self.install(stream, &vec![auxilia_repartition, auxilia.into()])?;
+                            stream =
+                                self.install(stream, &vec![auxilia_repartition, auxilia.into()], mask)?;
                         } else {
-                            stream = self.install(stream, &vec![auxilia.into()])?;
+                            stream = self.install(stream, &vec![auxilia.into()], mask)?;
                         }
                     }
                 }
@@ -754,10 +1226,46 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                         }
                     }
                 }
-                OpKind::Edge(edge) => {
-                    let func = self.udf_gen.gen_edge_expand(edge)?;
-                    stream = stream.flat_map_with_name("EdgeExpand", move |input| func.exec(input))?;
-                }
+                OpKind::Edge(edge) => match (self.expand_split, self.expand_prefetch.as_ref()) {
+                    (Some(split), _) if pegasus::get_current_worker().local_peers > 1 => {
+                        // the records split are consumed by the local workers they are sent to
+                        stream = self
+                            .udf_gen
+                            .gen_split_expand(edge, split)?
+                            .install(stream)?;
+                    }
+                    (_, Some(prefetch)) if prefetch.depth > 0 && !edge.is_optional => {
+                        let prefetch = self
+                            .udf_gen
+                            .gen_prefetch_expand(edge, prefetch.clone())?;
+                        // a record is taken out of the batch only as its adjacency is given, so the batch
+                        // interrupted by the output blocked is resumed from the record after
+                        stream = stream.unary("PrefetchExpand", |_info| {
+                            move |input, output| {
+                                input.for_each_batch(|batch| {
+                                    if !batch.is_empty() {
+                                        let mut session = output.new_session(&batch.tag)?;
+                                        let mut reads = prefetch.reads();
+                                        loop {
+                                            reads.request(batch.iter());
+                                            let record = match batch.drain().next() {
+                                                Some(record) => record,
+                                                None => break,
+                                            };
+                                            let adjacency = reads.next(record)?;
+                                            session.give_iterator(adjacency.into_iter())?;
+                                        }
+                                    }
+                                    Ok(())
+                                })
+                            }
+                        })?;
+                    }
+                    _ => {
+                        let func = self.udf_gen.gen_edge_expand(edge)?;
+                        stream = stream.flat_map_with_name("EdgeExpand", move |input| func.exec(input))?;
+                    }
+                },
                 OpKind::Path(path) => {
                     let mut base = path.base.clone().ok_or_else(|| {
                         FnGenError::from(ParsePbError::EmptyFieldError("pb::PathExpand::base".to_string()))
@@ -787,12 +1295,16 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                         )))
                     })?;
 
-                    if pb::path_expand::ResultOpt::AllVE == unsafe { std::mem::transmute(path.result_opt) }
+                    if (pb::path_expand::ResultOpt::AllVE
+                        == unsafe { std::mem::transmute(path.result_opt) }
+                        || (pb::path_expand::PathOpt::Trail as i32) == path.path_opt)
                         && pb::edge_expand::ExpandOpt::Vertex
                             == unsafe { std::mem::transmute(edge_expand.expand_opt) }
                     {
                         // the case when base expand is expand vertex, but needs to expand edges + vertices since the result opt is ALLVE
                         // TODO: in the new compilation stack, this case will not happen.
+                        // Or, the path is a trail, whose visited edges are rejected as they're appended,
+                        // and are stripped from the path if the result opt is ALLV.
                         let mut edge_expand_e = edge_expand.clone();
                         edge_expand_e.expand_opt = pb::edge_expand::ExpandOpt::Edge as i32;
                         let alias = edge_expand_e.alias.take();
@@ -839,7 +1351,7 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                     }
 
                     for _ in 0..range.lower {
-                        stream = self.install(stream, &base_expand_plan)?;
+                        stream = self.install(stream, &base_expand_plan, mask)?;
                     }
                     let times = range.upper - range.lower - 1;
                     if times > 0 {
@@ -848,13 +1360,14 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                             let func = self.udf_gen.gen_path_condition(path.clone())?;
                             until.set_until(func);
                             // Notice that if UNTIL condition set, we expand path without `Emit`
-                            stream = stream
-                                .iterate_until(until, |start| self.install(start, &base_expand_plan[..]))?;
+                            stream = stream.iterate_until(until, |start| {
+                                self.install(start, &base_expand_plan[..], mask)
+                            })?;
                         } else {
                             let (mut hop_stream, copied_stream) = stream.copied()?;
                             stream = copied_stream;
                             for _ in 0..times {
-                                hop_stream = self.install(hop_stream, &base_expand_plan[..])?;
+                                hop_stream = self.install(hop_stream, &base_expand_plan[..], mask)?;
                                 let copied = hop_stream.copied()?;
                                 hop_stream = copied.0;
                                 stream = stream.merge(copied.1)?;
@@ -875,39 +1388,76 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                     })?;
                 }
                 OpKind::Sample(sample) => {
-                    if let Some(sample_weight) = &sample.sample_weight {
-                        if sample_weight.tag.is_some() || sample_weight.property.is_some() {
-                            return Err(FnGenError::from(ParsePbError::ParseError(
-                                "sample_weight is not supported yet".to_string(),
-                            )))?;
-                        }
-                    }
                     if let Some(sample_type) = &sample.sample_type {
                         match &sample_type.inner {
                             // the case of Coin
                             Some(algebra_pb::sample::sample_type::Inner::SampleByRatio(_)) => {
+                                if let Some(sample_weight) = &sample.sample_weight {
+                                    if sample_weight.tag.is_some() || sample_weight.property.is_some() {
+                                        return Err(FnGenError::from(ParsePbError::ParseError(
+                                            "sample_weight of SampleByRatio is not supported yet"
+                                                .to_string(),
+                                        )))?;
+                                    }
+                                }
                                 let func = self.udf_gen.gen_coin(sample)?;
                                 stream = stream.filter(move |input| func.test(input))?;
                             }
-                            // the case of Sample
-                            Some(algebra_pb::sample::sample_type::Inner::SampleByNum(_)) => {
-                                let partial_sample_accum = self.udf_gen.gen_sample(sample)?;
-                                let sample_accum = partial_sample_accum.clone();
+                            // the case of Sample per key, e.g., K neighbors per vertex
+                            Some(algebra_pb::sample::sample_type::Inner::SampleByNum(_))
+                                if sample.sample_key.is_some() =>
+                            {
+                                let sample_key = self.udf_gen.gen_sample_key(sample.clone())?;
+                                let sample_accum = self.udf_gen.gen_sample(sample)?;
                                 stream = stream
-                                    .fold_partition(partial_sample_accum, move || {
-                                        move |mut sample_accum, next| {
-                                            sample_accum.accum(next)?;
-                                            Ok(sample_accum)
-                                        }
-                                    })?
-                                    .unfold(move |mut sample_accum| Ok(sample_accum.finalize()?))?
-                                    .fold(sample_accum, move || {
-                                        move |mut sample_accum, next| {
+                                    .key_by(move |record| sample_key.get_kv(record))?
+                                    .fold_partition_by_key(sample_accum, || {
+                                        |mut sample_accum, next| {
                                             sample_accum.accum(next)?;
                                             Ok(sample_accum)
                                         }
                                     })?
-                                    .unfold(move |mut sample_accum| Ok(sample_accum.finalize()?))?
+                                    .unfold(|kv_map| {
+                                        Ok(kv_map
+                                            .into_iter()
+                                            .map(|(_, mut sample_accum)| sample_accum.finalize())
+                                            .collect::<Result<Vec<_>, _>>()?
+                                            .into_iter()
+                                            .flatten())
+                                    })?;
+                            }
+                            // the case of Sample
+                            Some(algebra_pb::sample::sample_type::Inner::SampleByNum(_)) => {
+                                let partial_sample_accum = self.udf_gen.gen_sample(sample)?;
+                                if partial_sample_accum.is_weighted() {
+                                    // the records sampled by weight in the partitions can't be sampled
+                                    // again by their weights, so they are sampled at once.
+                                    stream = stream
+                                        .fold(partial_sample_accum, move || {
+                                            move |mut sample_accum, next| {
+                                                sample_accum.accum(next)?;
+                                                Ok(sample_accum)
+                                            }
+                                        })?
+                                        .unfold(move |mut sample_accum| Ok(sample_accum.finalize()?))?;
+                                } else {
+                                    let sample_accum = partial_sample_accum.clone();
+                                    stream = stream
+                                        .fold_partition(partial_sample_accum, move || {
+                                            move |mut sample_accum, next| {
+                                                sample_accum.accum(next)?;
+                                                Ok(sample_accum)
+                                            }
+                                        })?
+                                        .unfold(move |mut sample_accum| Ok(sample_accum.finalize()?))?
+                                        .fold(sample_accum, move || {
+                                            move |mut sample_accum, next| {
+                                                sample_accum.accum(next)?;
+                                                Ok(sample_accum)
+                                            }
+                                        })?
+                                        .unfold(move |mut sample_accum| Ok(sample_accum.finalize()?))?
+                                }
                             }
                             None => Err(FnGenError::from(ParsePbError::EmptyFieldError(
                                 "pb::Sample::sample_type.inner".to_string(),
@@ -919,16 +1469,280 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
                         )))?;
                     }
                 }
+                OpKind::Algorithm(algorithm) => {
+                    if algorithm.is_triangle() {
+                        let TriangleOperator { kind, global, alias, probe, intersect, accum } =
+                            self.udf_gen.gen_triangle(algorithm)?;
+                        let (router, probe_router, common_router) = (
+                            self.udf_gen.router.clone(),
+                            self.udf_gen.router.clone(),
+                            self.udf_gen.router.clone(),
+                        );
+                        // the vertices probe their neighbors on the workers owning the neighbors, and the
+                        // numbers of the common neighbors are sent back to the workers owning the vertices.
+                        let counted = stream
+                            .map(TriangleMessage::init)?
+                            .repartition(move |message| {
+                                Ok(router.route(message.get_id().get_partition_key_id())?)
+                            })
+                            .flat_map(move |message| probe.exec(message))?
+                            .repartition(move |message| {
+                                Ok(probe_router.route(message.get_id().get_partition_key_id())?)
+                            })
+                            .flat_map(move |message| intersect.exec(message))?
+                            .repartition(move |message| {
+                                Ok(common_router.route(message.get_id().get_partition_key_id())?)
+                            })
+                            .fold_partition(accum, || {
+                                |mut accum, next| {
+                                    accum.accum(next)?;
+                                    Ok(accum)
+                                }
+                            })?
+                            .unfold(|mut accum| Ok(accum.finalize()?))?;
+                        stream = if global {
+                            counted
+                                .fold(TriangleSummary::default(), || |summary, next| summary.add(next))?
+                                .unfold(move |summary| Ok(std::iter::once(summary.finish(kind, alias))))?
+                        } else {
+                            counted.filter_map(move |message| message.finish(kind, alias))?
+                        };
+                    } else {
+                        let AlgorithmOperator { max_iterations, alias, init, scatter, accum, .. } =
+                            self.udf_gen.gen_algorithm(algorithm)?;
+                        let router = self.udf_gen.router.clone();
+                        let iter_router = self.udf_gen.router.clone();
+                        // the vertices are sent to the workers owning them, where their neighbors are
+                        // explored, and the messages to them are routed in the same way in each iteration.
+                        stream = stream
+                            .map(move |record| init.exec(record))?
+                            .repartition(move |message| {
+                                Ok(router.route(message.get_id().get_partition_key_id())?)
+                            })
+                            .iterate(max_iterations, move |start| {
+                                start
+                                    .flat_map(move |message| scatter.exec(message))?
+                                    .repartition(move |message| {
+                                        Ok(iter_router.route(message.get_id().get_partition_key_id())?)
+                                    })
+                                    .fold_partition(accum, || {
+                                        |mut accum, next| {
+                                            accum.accum(next)?;
+                                            Ok(accum)
+                                        }
+                                    })?
+                                    .unfold(|mut accum| Ok(accum.finalize()?))
+                            })?
+                            .filter_map(move |message| message.finish(alias))?;
+                    }
+                }
+                OpKind::KHop(k_hop) => {
+                    let KHopOperator { start_tag, within, alias, start, expands, accum } =
+                        self.udf_gen.gen_k_hop(k_hop)?;
+                    // the records are sent to the workers owning the start vertices, and the vertices
+                    // reached in each hop are sent to the workers owning them, to be deduplicated there.
+                    let shuffle = self
+                        .udf_gen
+                        .gen_shuffle(&pb::repartition::Shuffle { shuffle_key: start_tag })?;
+                    let mut hops = stream
+                        .repartition(move |record| shuffle.route(record))
+                        .map(move |record| start.exec(record))?;
+                    for expand in expands {
+                        let router = self.udf_gen.router.clone();
+                        hops = hops
+                            .flat_map(move |message| expand.exec(message))?
+                            .repartition(move |message| {
+                                Ok(router.route(message.get_id().get_partition_key_id())?)
+                            })
+                            .fold_partition(accum.clone(), || {
+                                |mut accum, next| {
+                                    accum.accum(next)?;
+                                    Ok(accum)
+                                }
+                            })?
+                            .unfold(|mut accum| Ok(accum.finalize()?))?;
+                    }
+                    stream = hops.filter_map(move |message| message.finish(within, alias))?;
+                }
+                OpKind::Merge(merge) => {
+                    let func = self.udf_gen.gen_merge(merge)?;
+                    stream = stream.map_with_name("Merge", move |input| func.exec(input))?;
+                }
+                OpKind::Mutate(mutate) => {
+                    // the records output are of the elements inserted or updated
+                    let event = match mutate.kind.as_ref() {
+                        Some(algebra_pb::mutate::Kind::AddV(_))
+                        | Some(algebra_pb::mutate::Kind::AddE(_)) => Some(TriggerEvent::Insert),
+                        Some(algebra_pb::mutate::Kind::Property(_)) => Some(TriggerEvent::Update),
+                        _ => None,
+                    };
+                    let mut accum = self.udf_gen.gen_mutate(mutate)?;
+                    if let Some(standing_queries) = self.standing_queries.as_ref() {
+                        accum = accum.with_standing_queries(standing_queries.clone());
+                    }
+                    if let Some(triggers) = self.triggers.as_ref() {
+                        accum = accum.with_triggers(triggers.clone());
+                    }
+                    stream = stream
+                        .fold_partition(accum, || {
+                            |mut accum, next| {
+                                accum.accum(next)?;
+                                Ok(accum)
+                            }
+                        })?
+                        .unfold(|mut accum| Ok(accum.finalize()?))?;
+                    if let (Some(triggers), Some(event)) = (self.triggers.as_ref(), event) {
+                        stream = self.install_trigger_plans(stream, triggers.plans_of(event)?, event)?;
+                    }
+                }
+                OpKind::StoreVar(store_var) => {
+                    let store = Arc::new(
+                        self.udf_gen
+                            .gen_store_var(store_var, self.sessions.clone())?,
+                    );
+                    let accum = store.gen_accum();
+                    let entry_store = store.clone();
+                    // the records pass through, while their entries are collected globally, and the
+                    // collection is broadcast to be bound in the registry of each server.
+                    let (main, copied) = stream.copied()?;
+                    let bound = copied
+                        .map(move |record| entry_store.get_entry(&record))?
+                        .fold(accum, || {
+                            |mut accum, next| {
+                                accum.accum(next)?;
+                                Ok(accum)
+                            }
+                        })?
+                        .map(|mut accum| Ok(accum.finalize()?))?
+                        .into_stream()?
+                        .broadcast()
+                        .filter_map(move |collection| store.bind(collection))?;
+                    stream = main.merge(bound)?;
+                }
+                OpKind::LoadVar(load_var) => {
+                    let func = self
+                        .udf_gen
+                        .gen_load_var(load_var, self.sessions.clone())?;
+                    stream = stream.map_with_name("LoadVar", move |input| func.exec(input))?;
+                }
+                OpKind::SideEffect(side_effect) => {
+                    let side_effect = self.udf_gen.gen_side_effect(side_effect)?;
+                    let accum = side_effect.gen_accum();
+                    let name = side_effect.name.clone();
+                    if stream.get_scope_level() != 0 {
+                        // in a sub-task, the entries are accumulated by the worker locally, which are
+                        // sent once the records of the root plan end after the sub-task
+                        let local = add_local_side_effect(name, accum)?;
+                        stream = stream.map_with_name("SideEffect", move |record| {
+                            let entry = side_effect.get_entry(&record)?;
+                            local
+                                .lock()
+                                .map_err(|e| FnExecError::unexpected_data_error(&e.to_string()))?
+                                .as_mut()
+                                .ok_or_else(|| {
+                                    FnExecError::unexpected_data_error("the local side effect is taken")
+                                })?
+                                .accum(entry)?;
+                            Ok(record)
+                        })?;
+                    } else {
+                        // the records pass through, while their entries are accumulated by each worker,
+                        // and the partial side effects are merged as they all end.
+                        let (main, copied) = stream.copied()?;
+                        let partial = copied
+                            .map(move |record| side_effect.get_entry(&record))?
+                            .fold_partition(accum, || {
+                                |mut accum, next| {
+                                    accum.accum(next)?;
+                                    Ok(accum)
+                                }
+                            })?
+                            .into_stream()?;
+                        add_side_effect(name, partial)?;
+                        stream = main;
+                    }
+                }
+                OpKind::Cap(cap) => {
+                    let alias: Option<KeyId> = cap
+                        .alias
+                        .map(|alias| alias.try_into())
+                        .transpose()?;
+                    // the records are replaced by the side effect merged
+                    let side_effect = take_side_effect(&cap.name)?.map(move |entry| {
+                        let mut record = Record::default();
+                        record.append_arc_entry(entry, alias);
+                        Ok(record)
+                    })?;
+                    stream = stream
+                        .filter_map(|_| Ok(None))?
+                        .merge(side_effect)?;
+                }
+                OpKind::Sack(sack) => match self.udf_gen.gen_sack(sack)? {
+                    SackOperator::Map(func) => {
+                        stream = stream.map_with_name("Sack", move |input| func.exec(input))?;
+                    }
+                    SackOperator::Merge(merge) => {
+                        // the records of the same head are merged by the worker they're shuffled to
+                        stream = stream
+                            .key_by(move |record| merge.get_kv(record))?
+                            .reduce_by_key(move || move |merged, next| merge.merge(merged, next))?
+                            .unfold(|merged| Ok(merged.into_iter().map(|(_, record)| record)))?;
+                    }
+                },
+                OpKind::Tree(tree) => {
+                    let tree = self.udf_gen.gen_tree(tree)?;
+                    let (max_size, alias) = (tree.get_max_size(), tree.get_alias());
+                    // the paths are assembled by the worker their roots are shuffled to
+                    let paths = stream
+                        .filter_map(move |record| tree.get_kv(record))?
+                        .key_by(Ok)?;
+                    stream = assemble_trees(paths, max_size, alias)?;
+                }
+                OpKind::Call(call) => {
+                    stream = self.install_call(stream, call, mask)?;
+                }
+                OpKind::Repeat(repeat) => {
+                    stream = self.install_repeat(stream, repeat, mask)?;
+                }
+                OpKind::Coalesce(coalesce) => {
+                    stream = self.install_coalesce(stream, coalesce, mask)?;
+                }
                 OpKind::Root(_) => {
                     // do nothing, as it is a dummy node
                 }
+                OpKind::PatternMatch(_) => {
+                    // this would be planned in rewriting the plan, and cannot be reached when install.
+                    Err(FnGenError::unsupported_error("unplanned pattern match in install"))?
+                }
                 OpKind::Sink(_) => {
                     // this would be processed in assemble, and cannot be reached when install.
                     Err(FnGenError::unsupported_error("unreachable sink in install"))?
                 }
             }
 
-            prev_op_kind = to_op_kind(op)?;
+            let installed_kind = to_op_kind(op)?;
+            if let Some(mask) = mask {
+                // mask the elements right after they are read from the graph
+                if matches!(
+                    installed_kind,
+                    OpKind::Scan(_)
+                        | OpKind::Edge(_)
+                        | OpKind::Vertex(_)
+                        | OpKind::Path(_)
+                        | OpKind::Intersect(_)
+                        | OpKind::KHop(_)
+                        | OpKind::Merge(_)
+                        | OpKind::Mutate(_)
+                        | OpKind::Call(_)
+                ) {
+                    let mask = mask.clone();
+                    stream =
+                        stream.map_with_name("MaskProperties", move |input| Ok(mask.mask_record(input)))?;
+                }
+            }
+            // the side effects accumulated in the sub-tasks of the operator are sent once its records end
+            stream = send_local_side_effects(stream)?;
+            prev_op_kind = installed_kind;
         }
         Ok(stream)
     }
@@ -936,23 +1750,117 @@ impl<P: PartitionInfo, C: ClusterInfo> IRJobAssembly<P, C> {
 
 impl<P: PartitionInfo, C: ClusterInfo> JobAssembly<Record> for IRJobAssembly<P, C> {
     fn assemble(&self, plan: &JobDesc, worker: &mut Worker<Record, Vec<u8>>) -> Result<(), BuildJobError> {
+        if let Some(name) = plan.view.as_ref() {
+            let view = get_view(name).ok_or_else(|| {
+                FnGenError::unsupported_error(&format!("graph view `{}` is not found", name))
+            })?;
+            // the graph is read through the view until the worker of the job is dropped
+            worker.add_resource(bind_view(worker.id.job_id, view));
+        }
+        if plan.include_deleted {
+            worker.add_resource(bind_include_deleted(worker.id.job_id));
+        }
+        if let Some(consistency) = plan.consistency.as_ref() {
+            let consistency: ReadConsistency = consistency.parse().map_err(FnGenError::from)?;
+            worker.add_resource(bind_consistency(worker.id.job_id, consistency));
+        }
+        if self.expand_split.is_some() {
+            worker.add_resource(bind_worker_loads(worker.id.job_id, worker.id.local_peers as usize));
+        }
+        if plan.provenance {
+            worker.add_resource(bind_provenance(worker.id.job_id));
+        }
+        let scope = self.read_scope(plan)?;
+        worker.add_resource(bind_read_scope(worker.id.job_id, scope));
+        let request = decode_request(plan)?;
         worker.dataflow(move |input, output| {
-            let physical_plan = decode::<pb::PhysicalPlan>(&plan.plan)?;
+            let (physical_plan, mask) = self.rewrite_plan(plan, request.as_ref())?;
             if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
                 debug!("{:#?}", PhysicalPlanPrinter(&physical_plan));
             }
             // input from a dummy record to trigger the computation
             let source = input.input_from(vec![Record::default()])?;
             let plan_len = physical_plan.plan.len();
-            let stream = self.install(source, &physical_plan.plan[0..plan_len - 1])?;
+            let side_effects = InstallingSideEffects::new();
+            let mut stream = self.install(source, &physical_plan.plan[0..plan_len - 1], mask.as_ref())?;
+            let deterministic = pegasus::is_deterministic();
+            if let Some(limit) = plan.result_limit {
+                // limited by each worker, then in total by the one the results are aggregated to, whatever
+                // the partitions the stream is tracked with
+                let limit = limit.min(u32::MAX as u64) as u32;
+                stream = stream.limit_partition(limit)?;
+                stream = if deterministic { stream.aggregate_ordered()? } else { stream.aggregate() };
+                stream = stream.limit_partition(limit)?;
+            }
+            // the side effects not read by `Cap` are returned after the results
+            let unread = side_effects.take_unread()?;
+            if deterministic {
+                // the results of the workers are sent in the order of the workers
+                stream = stream.aggregate_ordered()?;
+            }
             let sink_opr = physical_plan.plan.last().ok_or_else(|| {
                 FnGenError::from(ParsePbError::EmptyFieldError("empty job plan".to_string()))
             })?;
-            let ec = self.udf_gen.gen_sink(sink_opr.clone())?;
+            let mut ec = self.udf_gen.gen_sink(sink_opr.clone())?;
+            if let (Some(request), Sinker::DefaultSinker(default_sinker)) = (request.as_ref(), &mut ec) {
+                default_sinker.respond_to(request.request_id);
+            }
+            if let Sinker::DefaultSinker(default_sinker) = &ec {
+                // rejected before any results are computed, rather than failing after them all
+                if !unread.is_empty() && !default_sinker.returns_side_effects() {
+                    let names: Vec<&String> = unread.iter().map(|(name, _)| name).collect();
+                    Err(FnGenError::unsupported_error(&format!(
+                        "side effects {:?} returned in the format of the sink, other than protobuf",
+                        names
+                    )))?
+                }
+            }
             match ec {
-                Sinker::DefaultSinker(default_sinker) => stream
+                Sinker::DefaultSinker(default_sinker) if default_sinker.is_batched() => stream
+                    .unary("sink_batch", |_info| {
+                        move |input, output| {
+                            input.for_each_batch(|batch| {
+                                if !batch.is_empty() {
+                                    let records: Vec<Record> = batch.drain().collect();
+                                    let encoded = default_sinker
+                                        .encode_batch(records)
+                                        .map_err(DynError::from)?;
+                                    output.new_session(&batch.tag)?.give(encoded)?;
+                                }
+                                Ok(())
+                            })
+                        }
+                    })?
+                    .sink_into(output),
+                Sinker::DefaultSinker(default_sinker) if unread.is_empty() => stream
                     .map(move |record| default_sinker.exec(record))?
                     .sink_into(output),
+                Sinker::DefaultSinker(default_sinker) => {
+                    let default_sinker = Arc::new(default_sinker);
+                    let (main, ended) = stream.copied()?;
+                    let record_sinker = default_sinker.clone();
+                    let results = main.map(move |record| record_sinker.exec(record))?;
+                    // the side effects are sent in the order of their names, once the results all end
+                    let mut footers = ended.filter_map(|_| Ok(None))?;
+                    for (index, (name, side_effect)) in unread.into_iter().enumerate() {
+                        let sinker = default_sinker.clone();
+                        footers = footers.merge(side_effect.map(move |entry| {
+                            Ok((index as u32, sinker.encode_side_effect(&name, &entry)?))
+                        })?)?;
+                    }
+                    let footers = footers
+                        .fold(vec![], || {
+                            |mut footers, next| {
+                                footers.push(next);
+                                Ok(footers)
+                            }
+                        })?
+                        .unfold(|mut footers: Vec<(u32, Vec<u8>)>| {
+                            footers.sort_by_key(|(index, _)| *index);
+                            Ok(footers.into_iter().map(|(_, footer)| footer))
+                        })?;
+                    results.merge(footers)?.sink_into(output)
+                }
                 #[cfg(feature = "with_v6d")]
                 Sinker::GraphSinker(graph_sinker) => {
                     return stream
@@ -970,6 +1878,93 @@ impl<P: PartitionInfo, C: ClusterInfo> JobAssembly<Record> for IRJobAssembly<P,
             }
         })
     }
+
+    fn summarize(&self, job: &JobDesc) -> Option<PlanSummary> {
+        match decode::<pb::PhysicalPlan>(&job.plan).and_then(|plan| summarize_plan(&plan)) {
+            Ok(summary) => Some(summary),
+            Err(e) => {
+                warn!("summarize plan failure: {}", e);
+                None
+            }
+        }
+    }
+
+    fn describe(&self, job: &JobDesc) -> Option<String> {
+        match decode::<pb::PhysicalPlan>(&job.plan) {
+            Ok(plan) => Some(format!("{:?}", PhysicalPlanPrinter(&plan))),
+            Err(e) => {
+                warn!("describe plan failure: {}", e);
+                None
+            }
+        }
+    }
+
+    fn count_label(&self, label: &str, edge: bool) -> Option<u64> {
+        // the labels of the physical plans are resolved to ids
+        let label = label.parse::<LabelId>().ok()?;
+        let graph = get_graph()?;
+        let params = QueryParams { labels: vec![label], ..QueryParams::default() };
+        let count = if edge { graph.count_edge(&params) } else { graph.count_vertex(&params) };
+        count
+            .map_err(|e| warn!("count label for index advisor failure: {}", e))
+            .ok()
+    }
+
+    fn lint(&self, job: &JobDesc) -> Vec<String> {
+        match decode::<pb::PhysicalPlan>(&job.plan) {
+            Ok(plan) => lint_plan(&plan),
+            Err(e) => {
+                warn!("lint plan failure: {}", e);
+                vec![]
+            }
+        }
+    }
+
+    fn validate(&self, job: &JobDesc) -> Result<(), InvalidPlan> {
+        match decode::<pb::PhysicalPlan>(&job.plan) {
+            Ok(plan) => validate_plan(&plan),
+            Err(e) => {
+                // reported while the plan is assembled
+                warn!("validate plan failure: {}", e);
+                Ok(())
+            }
+        }
+    }
+
+    fn explain(&self, job: &JobDesc, parallelism: usize) -> Result<Option<PlanGraph>, BuildJobError> {
+        let (plan, _) = self.rewrite_plan(job, decode_request(job)?.as_ref())?;
+        Ok(Some(explain_plan(&plan, parallelism, pegasus::is_deterministic())))
+    }
+
+    fn encode_plan(&self, plan: &serde_json::Value) -> Option<Result<Vec<u8>, String>> {
+        let plan = serde_json::from_value::<pb::PhysicalPlan>(plan.clone()).map_err(|e| e.to_string());
+        Some(plan.map(|plan| plan.encode_to_vec()))
+    }
+}
+
+/// The request of a Gremlin driver in GraphBinary, which the job carries as its resource if any.
+fn decode_request(job: &JobDesc) -> FnGenResult<Option<RequestMessage>> {
+    if job.resource.is_empty() {
+        return Ok(None);
+    }
+    RequestMessage::decode(&job.resource)
+        .map(Some)
+        .map_err(|e| FnGenError::unsupported_error(&format!("decode the request of the job: {}", e)))
+}
+
+/// Tag the records of the branch of a union with the index of the branch, keeping their heads.
+fn tag_branch(
+    stream: Stream<Record>, branch: usize, branch_alias: Option<KeyId>,
+) -> Result<Stream<Record>, BuildJobError> {
+    match branch_alias {
+        Some(alias) => stream.map(move |mut record| {
+            record
+                .get_columns_mut()
+                .insert(alias as usize, DynEntry::new(Object::from(branch as i32)));
+            Ok(record)
+        }),
+        None => Ok(stream),
+    }
 }
 
 #[inline]
@@ -978,6 +1973,40 @@ fn decode<T: Message + Default>(binary: &[u8]) -> FnGenResult<T> {
 }
 
 #[inline]
+/// Whether the records shuffled to the operator are grouped in two phases, i.e., aggregated partially
+/// by the worker they're in and finally after the partial aggregations are exchanged by the keys, so
+/// the shuffle before the group is skipped. They're grouped as shuffled in the deterministic mode,
+/// keeping the order of records.
+fn is_two_phase_group(next: Option<&pb::PhysicalOpr>) -> FnGenResult<bool> {
+    if pegasus::is_deterministic() {
+        return Ok(false);
+    }
+    match next.map(to_op_kind).transpose()? {
+        Some(OpKind::GroupBy(group)) => Ok(!group.mappings.is_empty()),
+        _ => Ok(false),
+    }
+}
+
+/// The groups of the keys and the aggregated values, in the order of the encoded keys in the deterministic
+/// mode, which is total even for the keys not comparable, e.g., of floats and of different types, or else
+/// in the order of the hash map.
+fn finalize_groups<I: Iterator<Item = (RecordKey, RecordAccumulator)>>(
+    groups: I,
+) -> FnResult<vec::IntoIter<(RecordKey, Record)>> {
+    let mut groups = groups
+        .map(|(key, mut accumulator)| accumulator.finalize().map(|value| (key, value)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if pegasus::is_deterministic() {
+        groups.sort_by_cached_key(|(key, _)| {
+            let mut encoded = vec![];
+            // a key is encoded into the memory, which never fails
+            let _ = key.write_to(&mut encoded);
+            encoded
+        });
+    }
+    Ok(groups.into_iter())
+}
+
 fn to_op_kind(opr: &pb::PhysicalOpr) -> FnGenResult<OpKind> {
     Ok(opr.try_into()?)
 }
@@ -1060,6 +2089,54 @@ impl<'a> std::fmt::Debug for PhysicalOprPrinter<'a> {
                                 .collect::<Vec<_>>(),
                         )
                         .finish(),
+                    OpKind::Repeat(repeat) => f
+                        .debug_struct("Repeat")
+                        .field(
+                            "body",
+                            &repeat
+                                .body
+                                .as_ref()
+                                .map(|plan| PhysicalPlanPrinter(plan)),
+                        )
+                        .field("until", &repeat.until)
+                        .field("max_iterations", &repeat.max_iterations)
+                        .field("until_first", &repeat.until_first)
+                        .finish(),
+                    OpKind::SideEffect(side_effect) => f
+                        .debug_struct("SideEffect")
+                        .field("name", &side_effect.name)
+                        .field("kind", &side_effect.kind)
+                        .field("tag", &side_effect.tag)
+                        .finish(),
+                    OpKind::Cap(cap) => f
+                        .debug_struct("Cap")
+                        .field("name", &cap.name)
+                        .field("alias", &cap.alias)
+                        .finish(),
+                    OpKind::Tree(tree) => f
+                        .debug_struct("Tree")
+                        .field("levels", &tree.levels)
+                        .field("max_size", &tree.max_size)
+                        .field("alias", &tree.alias)
+                        .finish(),
+                    OpKind::PatternMatch(pattern) => f
+                        .debug_struct("PatternMatch")
+                        .field("vertices", &pattern.vertices)
+                        .field("triples", &pattern.triples)
+                        .field("bound_tags", &pattern.bound_tags)
+                        .field("alias", &pattern.alias)
+                        .finish(),
+                    OpKind::Coalesce(coalesce) => f
+                        .debug_struct("Coalesce")
+                        .field(
+                            "sub_plans",
+                            &coalesce
+                                .sub_plans
+                                .iter()
+                                .map(|plan| PhysicalPlanPrinter(plan))
+                                .collect::<Vec<_>>(),
+                        )
+                        .finish(),
                     _ => f
                         .debug_struct("PhysicalOpr")
                         .field("opr", op_kind)
@@ -0,0 +1,396 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+use ir_common::NameOrId;
+use pegasus_server::audit::{FilterSummary, PlanSummary};
+use prost::Message;
+
+use crate::auth::PlanAccess;
+use crate::error::FnGenResult;
+
+/// Summarize a plan for the audit log.
+///
+/// The fingerprint is the FNV-1a hash of the plan whose constants are stripped, including the
+/// constants in the top-level operators of expressions, the values of index predicates and the
+/// ranges of limits; the stripped constants are the parameters in the order of their appearance.
+/// A plan touching all labels of a scan or an expand has the label `*`.
+///
+/// The filters are the properties compared with constants or parameters in the predicates of the
+/// scans and the `GetV`s, and in the index predicates of the scans, for the index advisor.
+pub fn summarize_plan(plan: &pb::PhysicalPlan) -> FnGenResult<PlanSummary> {
+    let mut access = PlanAccess::default();
+    access.collect(plan)?;
+    let mut labels: Vec<String> = access
+        .labels
+        .iter()
+        .map(|label| match label {
+            NameOrId::Str(name) => name.clone(),
+            NameOrId::Id(id) => id.to_string(),
+        })
+        .collect();
+    labels.sort();
+    if access.all_labels {
+        labels.insert(0, "*".to_owned());
+    }
+
+    let mut filters = vec![];
+    collect_filters(plan, &mut filters);
+
+    let mut shape = plan.clone();
+    let mut parameters = vec![];
+    strip_plan(&mut shape, &mut parameters);
+    let fingerprint = format!("{:016x}", fnv1a(&shape.encode_to_vec()));
+    Ok(PlanSummary { fingerprint, parameters, labels, filters })
+}
+
+fn collect_filters(plan: &pb::PhysicalPlan, filters: &mut Vec<FilterSummary>) {
+    for opr in plan.plan.iter() {
+        let op_kind = match opr
+            .opr
+            .as_ref()
+            .and_then(|o| o.op_kind.as_ref())
+        {
+            Some(op_kind) => op_kind,
+            None => continue,
+        };
+        match op_kind {
+            OpKind::Scan(scan) => {
+                let edge = scan.scan_opt == pb::scan::ScanOpt::Edge as i32;
+                let mut filter = FilterSummary { edge, ..Default::default() };
+                if let Some(params) = scan.params.as_ref() {
+                    add_predicate(params.predicate.as_ref(), &mut filter);
+                }
+                if let Some(idx_predicate) = scan.idx_predicate.as_ref() {
+                    for and_predicate in idx_predicate.or_predicates.iter() {
+                        for triplet in and_predicate.predicates.iter() {
+                            if let Some(property) = triplet.key.as_ref().and_then(to_property) {
+                                filter.eq.push(property);
+                            }
+                        }
+                    }
+                }
+                add_filter(scan.params.as_ref(), filter, filters);
+            }
+            OpKind::Vertex(get_v) => {
+                let mut filter = FilterSummary::default();
+                if let Some(params) = get_v.params.as_ref() {
+                    add_predicate(params.predicate.as_ref(), &mut filter);
+                }
+                add_filter(get_v.params.as_ref(), filter, filters);
+            }
+            OpKind::Apply(apply) => {
+                if let Some(sub_plan) = apply.sub_plan.as_ref() {
+                    collect_filters(sub_plan, filters);
+                }
+            }
+            OpKind::Join(join) => {
+                for sub_plan in join
+                    .left_plan
+                    .iter()
+                    .chain(join.right_plan.iter())
+                {
+                    collect_filters(sub_plan, filters);
+                }
+            }
+            OpKind::Union(union) => {
+                for sub_plan in union.sub_plans.iter() {
+                    collect_filters(sub_plan, filters);
+                }
+            }
+            OpKind::Intersect(intersect) => {
+                for sub_plan in intersect.sub_plans.iter() {
+                    collect_filters(sub_plan, filters);
+                }
+            }
+            OpKind::Repeat(repeat) => {
+                if let Some(body) = repeat.body.as_ref() {
+                    collect_filters(body, filters);
+                }
+            }
+            OpKind::Coalesce(coalesce) => {
+                for sub_plan in coalesce.sub_plans.iter() {
+                    collect_filters(sub_plan, filters);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Add the filter for each of the labels of the operator, or for `*` if it has no labels.
+fn add_filter(
+    params: Option<&algebra_pb::QueryParams>, filter: FilterSummary, filters: &mut Vec<FilterSummary>,
+) {
+    if filter.eq.is_empty() && filter.range.is_empty() && filter.text.is_empty() {
+        return;
+    }
+    let tables = params
+        .map(|p| p.tables.as_slice())
+        .unwrap_or_default();
+    if tables.is_empty() {
+        filters.push(FilterSummary { label: "*".to_owned(), ..filter });
+    }
+    for table in tables {
+        let label = match table.item.as_ref() {
+            Some(common_pb::name_or_id::Item::Name(name)) => name.clone(),
+            Some(common_pb::name_or_id::Item::Id(id)) => id.to_string(),
+            None => continue,
+        };
+        filters.push(FilterSummary { label, ..filter.clone() });
+    }
+}
+
+/// Add the properties of the current element compared with a constant or a parameter, i.e.
+/// `@.key <op> value` or `value <op> @.key` in the infix expression.
+fn add_predicate(expr: Option<&common_pb::Expression>, filter: &mut FilterSummary) {
+    use common_pb::expr_opr::Item;
+    use common_pb::Logical;
+
+    let operators = match expr {
+        Some(expr) => &expr.operators,
+        None => return,
+    };
+    for (i, opr) in operators.iter().enumerate() {
+        let logical = match opr.item.as_ref() {
+            Some(Item::Logical(logical)) if i > 0 && i + 1 < operators.len() => *logical,
+            _ => continue,
+        };
+        let property = match (operators[i - 1].item.as_ref(), operators[i + 1].item.as_ref()) {
+            (Some(Item::Var(var)), Some(Item::Const(_) | Item::Param(_)))
+            | (Some(Item::Const(_) | Item::Param(_)), Some(Item::Var(var)))
+                if var.tag.is_none() =>
+            {
+                match var.property.as_ref().and_then(to_property) {
+                    Some(property) => property,
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+        let properties = match Logical::from_i32(logical) {
+            Some(Logical::Eq) | Some(Logical::Within) => &mut filter.eq,
+            Some(Logical::Lt) | Some(Logical::Le) | Some(Logical::Gt) | Some(Logical::Ge) => {
+                &mut filter.range
+            }
+            Some(Logical::Startswith) | Some(Logical::Endswith) | Some(Logical::Regex) => &mut filter.text,
+            _ => continue,
+        };
+        properties.push(property);
+    }
+}
+
+fn to_property(property: &common_pb::Property) -> Option<String> {
+    match property.item.as_ref() {
+        Some(common_pb::property::Item::Key(key)) => match key.item.as_ref() {
+            Some(common_pb::name_or_id::Item::Name(name)) => Some(name.clone()),
+            Some(common_pb::name_or_id::Item::Id(id)) => Some(id.to_string()),
+            None => None,
+        },
+        _ => None,
+    }
+}
+
+fn strip_plan(plan: &mut pb::PhysicalPlan, parameters: &mut Vec<String>) {
+    plan.plan_id = 0;
+    for opr in plan.plan.iter_mut() {
+        let op_kind = match opr
+            .opr
+            .as_mut()
+            .and_then(|o| o.op_kind.as_mut())
+        {
+            Some(op_kind) => op_kind,
+            None => continue,
+        };
+        match op_kind {
+            OpKind::Scan(scan) => {
+                strip_params(scan.params.as_mut(), parameters);
+                if let Some(idx_predicate) = scan.idx_predicate.as_mut() {
+                    for and_predicate in idx_predicate.or_predicates.iter_mut() {
+                        for triplet in and_predicate.predicates.iter_mut() {
+                            if let Some(algebra_pb::index_predicate::triplet::Value::Const(value)) =
+                                triplet.value.as_mut()
+                            {
+                                parameters.push(to_parameter(std::mem::take(value)));
+                            }
+                        }
+                    }
+                }
+            }
+            OpKind::Edge(expand) => strip_params(expand.params.as_mut(), parameters),
+            OpKind::Vertex(get_v) => strip_params(get_v.params.as_mut(), parameters),
+            OpKind::Path(path) => {
+                if let Some(base) = path.base.as_mut() {
+                    if let Some(expand) = base.edge_expand.as_mut() {
+                        strip_params(expand.params.as_mut(), parameters);
+                    }
+                    if let Some(get_v) = base.get_v.as_mut() {
+                        strip_params(get_v.params.as_mut(), parameters);
+                    }
+                }
+                strip_expr(path.condition.as_mut(), parameters);
+            }
+            OpKind::Select(select) => strip_expr(select.predicate.as_mut(), parameters),
+            OpKind::Project(project) => {
+                for mapping in project.mappings.iter_mut() {
+                    strip_expr(mapping.expr.as_mut(), parameters);
+                }
+            }
+            OpKind::Limit(limit) => {
+                if let Some(range) = limit.range.as_mut() {
+                    parameters.push(format!("{}..{}", range.lower, range.upper));
+                    *range = algebra_pb::Range::default();
+                }
+            }
+            OpKind::Apply(apply) => {
+                if let Some(sub_plan) = apply.sub_plan.as_mut() {
+                    strip_plan(sub_plan, parameters);
+                }
+            }
+            OpKind::Join(join) => {
+                for sub_plan in join
+                    .left_plan
+                    .iter_mut()
+                    .chain(join.right_plan.iter_mut())
+                {
+                    strip_plan(sub_plan, parameters);
+                }
+            }
+            OpKind::Union(union) => {
+                for sub_plan in union.sub_plans.iter_mut() {
+                    strip_plan(sub_plan, parameters);
+                }
+            }
+            OpKind::Intersect(intersect) => {
+                for sub_plan in intersect.sub_plans.iter_mut() {
+                    strip_plan(sub_plan, parameters);
+                }
+            }
+            OpKind::Repeat(repeat) => {
+                strip_expr(repeat.until.as_mut(), parameters);
+                if let Some(body) = repeat.body.as_mut() {
+                    strip_plan(body, parameters);
+                }
+            }
+            OpKind::Coalesce(coalesce) => {
+                for sub_plan in coalesce.sub_plans.iter_mut() {
+                    strip_plan(sub_plan, parameters);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn strip_params(params: Option<&mut algebra_pb::QueryParams>, parameters: &mut Vec<String>) {
+    if let Some(params) = params {
+        strip_expr(params.predicate.as_mut(), parameters);
+    }
+}
+
+fn strip_expr(expr: Option<&mut common_pb::Expression>, parameters: &mut Vec<String>) {
+    if let Some(expr) = expr {
+        for opr in expr.operators.iter_mut() {
+            if let Some(common_pb::expr_opr::Item::Const(value)) = opr.item.as_mut() {
+                parameters.push(to_parameter(std::mem::take(value)));
+            }
+        }
+    }
+}
+
+fn to_parameter(value: common_pb::Value) -> String {
+    use common_pb::value::Item;
+    match value.item {
+        Some(Item::Boolean(b)) => b.to_string(),
+        Some(Item::I32(i)) => i.to_string(),
+        Some(Item::I64(i)) => i.to_string(),
+        Some(Item::F64(f)) => f.to_string(),
+        Some(Item::Str(s)) => format!("{:?}", s),
+        Some(item) => format!("{:?}", item),
+        None => "null".to_owned(),
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+
+    fn plan(predicate: &str, labels: Vec<i32>) -> pb::PhysicalPlan {
+        let scan = pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: Some(algebra_pb::QueryParams {
+                tables: labels.into_iter().map(|l| l.into()).collect(),
+                predicate: str_to_expr_pb(predicate.to_string()).ok(),
+                sample_ratio: 1.0,
+                ..Default::default()
+            }),
+            idx_predicate: None,
+            is_count_only: false,
+        };
+        pb::PhysicalPlan { plan_id: 1, plan: vec![scan.into()] }
+    }
+
+    #[test]
+    fn summarize_plan_test() {
+        let summary1 = summarize_plan(&plan("@.name == \"marko\" && @.age > 10", vec![1, 0])).unwrap();
+        assert_eq!(summary1.parameters, vec!["\"marko\"".to_owned(), "10".to_owned()]);
+        assert_eq!(summary1.labels, vec!["0".to_owned(), "1".to_owned()]);
+
+        let summary2 = summarize_plan(&plan("@.name == \"josh\" && @.age > 20", vec![1, 0])).unwrap();
+        assert_eq!(summary1.fingerprint, summary2.fingerprint);
+        assert_ne!(summary1.parameters, summary2.parameters);
+
+        let summary3 = summarize_plan(&plan("@.name == \"marko\" || @.age > 10", vec![])).unwrap();
+        assert_ne!(summary1.fingerprint, summary3.fingerprint);
+        assert_eq!(summary3.labels, vec!["*".to_owned()]);
+    }
+
+    #[test]
+    fn summarize_filters_test() {
+        let summary =
+            summarize_plan(&plan("@.name == \"marko\" && 10 < @.age && @.bio StartsWith \"m\"", vec![0]))
+                .unwrap();
+        assert_eq!(
+            summary.filters,
+            vec![FilterSummary {
+                label: "0".to_owned(),
+                edge: false,
+                eq: vec!["name".to_owned()],
+                range: vec!["age".to_owned()],
+                text: vec!["bio".to_owned()],
+            }]
+        );
+        let summary = summarize_plan(&plan("@.name == @.nick", vec![0])).unwrap();
+        assert!(summary.filters.is_empty());
+        let summary = summarize_plan(&plan("@.age >= 10", vec![])).unwrap();
+        assert_eq!(summary.filters[0].label, "*");
+        assert_eq!(summary.filters[0].range, vec!["age".to_owned()]);
+    }
+}
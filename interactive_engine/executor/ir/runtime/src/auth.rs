@@ -0,0 +1,749 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use graph_proxy::apis::{DynDetails, MaskAction, MaskedDetails};
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+use ir_common::NameOrId;
+use pegasus_common::downcast::AsAny;
+use pegasus_server::auth::Principal;
+
+use crate::error::{FnGenError, FnGenResult};
+use crate::process::entry::{DynEntry, Entry};
+use crate::process::record::Record;
+use crate::row_filter::RowFilters;
+
+/// The permissions granted to a role on a graph.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphGrant {
+    /// The labels allowed to read, `None` means all the labels.
+    read: Option<HashSet<NameOrId>>,
+    write: bool,
+    /// Whether the softly deleted elements can be read, i.e., by the jobs including the deleted.
+    read_deleted: bool,
+}
+
+impl GraphGrant {
+    pub fn read_all() -> Self {
+        GraphGrant { read: None, write: false, read_deleted: false }
+    }
+
+    pub fn read_labels<I: IntoIterator<Item = NameOrId>>(labels: I) -> Self {
+        GraphGrant { read: Some(labels.into_iter().collect()), write: false, read_deleted: false }
+    }
+
+    pub fn with_write(mut self) -> Self {
+        self.write = true;
+        self
+    }
+
+    pub fn with_read_deleted(mut self) -> Self {
+        self.read_deleted = true;
+        self
+    }
+
+    fn merge(&mut self, other: &GraphGrant) {
+        self.read = match (self.read.take(), other.read.as_ref()) {
+            (Some(mut labels), Some(others)) => {
+                labels.extend(others.iter().cloned());
+                Some(labels)
+            }
+            _ => None,
+        };
+        self.write |= other.write;
+        self.read_deleted |= other.read_deleted;
+    }
+
+    fn can_read(&self, label: &NameOrId) -> bool {
+        self.read
+            .as_ref()
+            .map(|labels| labels.contains(label))
+            .unwrap_or(true)
+    }
+}
+
+/// Per-graph and per-label permissions of roles, which are checked when a plan is admitted.
+///
+/// A plan reads a label if it scans it or expands along it, and a scan or expand without labels
+/// reads all the labels. Labels in `GetV` are checked only if given, as the vertices are reached
+/// through an admitted expand. A plan writes the graph if it sinks into a graph.
+///
+/// Besides, properties can be restricted to some roles, and are masked or omitted in the
+/// elements read by the other callers, see `PropertyMask`; and rows can be restricted by
+/// per-label predicates conjoined to the plan, see `RowFilters`.
+#[derive(Clone, Debug, Default)]
+pub struct AccessPolicy {
+    grants: HashMap<String, HashMap<String, GraphGrant>>,
+    // graph -> property -> (allowed roles, action for the others)
+    restrictions: HashMap<String, HashMap<NameOrId, (HashSet<String>, MaskAction)>>,
+    row_filters: HashMap<String, RowFilters>,
+}
+
+impl AccessPolicy {
+    pub fn new() -> Self {
+        AccessPolicy::default()
+    }
+
+    pub fn grant<G: Into<String>, R: Into<String>>(mut self, graph: G, role: R, grant: GraphGrant) -> Self {
+        self.grants
+            .entry(graph.into())
+            .or_default()
+            .insert(role.into(), grant);
+        self
+    }
+
+    pub fn restrict_property<G: Into<String>>(
+        mut self, graph: G, property: NameOrId, allowed_roles: Vec<String>, action: MaskAction,
+    ) -> Self {
+        self.restrictions
+            .entry(graph.into())
+            .or_default()
+            .insert(property, (allowed_roles.into_iter().collect(), action));
+        self
+    }
+
+    /// Filter the vertices of `label` by `template`, e.g., `@.tenant_id == $current_tenant`, where
+    /// `$current_tenant` is the attribute of the caller.
+    pub fn filter_vertices<G: Into<String>, T: Into<String>>(
+        mut self, graph: G, label: NameOrId, template: T,
+    ) -> Self {
+        self.row_filters
+            .entry(graph.into())
+            .or_default()
+            .add_vertex_filter(label, template.into());
+        self
+    }
+
+    pub fn filter_edges<G: Into<String>, T: Into<String>>(
+        mut self, graph: G, label: NameOrId, template: T,
+    ) -> Self {
+        self.row_filters
+            .entry(graph.into())
+            .or_default()
+            .add_edge_filter(label, template.into());
+        self
+    }
+
+    /// Conjoin the row filters of `graph`, instantiated for `principal`, to the plan.
+    pub fn apply_row_filters(
+        &self, graph: &str, principal: Option<&Principal>, plan: &mut pb::PhysicalPlan,
+    ) -> FnGenResult<()> {
+        match self.row_filters.get(graph) {
+            Some(filters) => filters.apply(principal, plan),
+            None => Ok(()),
+        }
+    }
+
+    /// The mask of the properties on `graph` that `principal` is not allowed to see, if any.
+    pub fn property_mask(&self, graph: &str, principal: Option<&Principal>) -> Option<PropertyMask> {
+        let roles: &[String] = principal
+            .map(|p| p.roles.as_slice())
+            .unwrap_or(&[]);
+        let rules: ahash::HashMap<NameOrId, MaskAction> = self
+            .restrictions
+            .get(graph)?
+            .iter()
+            .filter(|(_, (allowed, _))| !roles.iter().any(|r| allowed.contains(r)))
+            .map(|(property, (_, action))| (property.clone(), action.clone()))
+            .collect();
+        if rules.is_empty() {
+            None
+        } else {
+            Some(PropertyMask { rules: Arc::new(rules) })
+        }
+    }
+
+    /// The key of the restrictions of `graph` applied to `principal`, i.e., the caller itself, the
+    /// properties masked and the row filters instantiated for it, so that the data read by a caller is
+    /// shared only with the callers of the same restrictions, e.g., by the adjacency cached.
+    pub fn restrictions_key(&self, graph: &str, principal: Option<&Principal>) -> FnGenResult<String> {
+        let mut masked: Vec<String> = self
+            .property_mask(graph, principal)
+            .map(|mask| {
+                mask.rules
+                    .iter()
+                    .map(|(property, action)| format!("{:?}:{:?}", property, action))
+                    .collect()
+            })
+            .unwrap_or_default();
+        masked.sort();
+        let row_filters = match self.row_filters.get(graph) {
+            Some(filters) => filters.instantiated_key(principal)?,
+            None => String::new(),
+        };
+        Ok(format!("{}|{}|{:?}|{}", graph, principal_key(principal), masked, row_filters))
+    }
+
+    /// Check the plan submitted by `principal` against the grants of its roles on `graph`.
+    pub fn admit(
+        &self, graph: &str, principal: Option<&Principal>, plan: &pb::PhysicalPlan,
+    ) -> FnGenResult<()> {
+        let (principal, grant) = self.grant_of(graph, principal)?;
+        let mut access = PlanAccess::default();
+        access.collect(plan)?;
+        if access.write && !grant.write {
+            Err(FnGenError::unauthorized_error(&format!(
+                "{} is not allowed to write graph {}",
+                principal.user, graph
+            )))?;
+        }
+        if access.all_labels && grant.read.is_some() {
+            Err(FnGenError::unauthorized_error(&format!(
+                "{} is not allowed to read all labels of graph {}",
+                principal.user, graph
+            )))?;
+        }
+        if let Some(label) = access
+            .labels
+            .iter()
+            .find(|l| !grant.can_read(l))
+        {
+            Err(FnGenError::unauthorized_error(&format!(
+                "{} is not allowed to read label {:?} of graph {}",
+                principal.user, label, graph
+            )))?;
+        }
+        Ok(())
+    }
+
+    /// Check whether `principal` is allowed to read the softly deleted elements of `graph`.
+    pub fn admit_include_deleted(&self, graph: &str, principal: Option<&Principal>) -> FnGenResult<()> {
+        let (principal, grant) = self.grant_of(graph, principal)?;
+        if !grant.read_deleted {
+            Err(FnGenError::unauthorized_error(&format!(
+                "{} is not allowed to read the deleted elements of graph {}",
+                principal.user, graph
+            )))?;
+        }
+        Ok(())
+    }
+
+    /// The grants of the roles of `principal` on `graph` merged.
+    fn grant_of<'a>(
+        &self, graph: &str, principal: Option<&'a Principal>,
+    ) -> FnGenResult<(&'a Principal, GraphGrant)> {
+        let principal =
+            principal.ok_or_else(|| FnGenError::unauthorized_error("anonymous job is not allowed"))?;
+        let mut grant: Option<GraphGrant> = None;
+        if let Some(roles) = self.grants.get(graph) {
+            for role_grant in principal
+                .roles
+                .iter()
+                .filter_map(|r| roles.get(r))
+            {
+                match grant {
+                    Some(ref mut grant) => grant.merge(role_grant),
+                    None => grant = Some(role_grant.clone()),
+                }
+            }
+        }
+        let grant = grant.ok_or_else(|| {
+            FnGenError::unauthorized_error(&format!("{} has no access to graph {}", principal.user, graph))
+        })?;
+        Ok((principal, grant))
+    }
+}
+
+/// The key of the caller, of its roles and attributes in order.
+pub fn principal_key(principal: Option<&Principal>) -> String {
+    match principal {
+        Some(p) => {
+            let mut roles = p.roles.clone();
+            roles.sort();
+            let mut attributes: Vec<_> = p.attributes.iter().collect();
+            attributes.sort();
+            format!("{}:{:?}:{:?}", p.user, roles, attributes)
+        }
+        None => String::new(),
+    }
+}
+
+/// Mask the restricted properties of the vertices and edges in records, which is applied right
+/// after the operators reading elements from the graph, so that the following operators, e.g.,
+/// project or valueMap, see the masked properties only. As predicates pushed down into the storage
+/// see the original values, plans pushing down predicates on masked properties are rejected, see
+/// `check_pushdown()`.
+#[derive(Clone, Debug)]
+pub struct PropertyMask {
+    rules: Arc<ahash::HashMap<NameOrId, MaskAction>>,
+}
+
+impl PropertyMask {
+    /// Reject the plan if any predicate pushed down into the storage, i.e., the predicates and the
+    /// index predicates of the scans and expands, reads a masked property, or all the properties,
+    /// as the elements selected by the predicate would reveal the original values.
+    pub fn check_pushdown(&self, plan: &pb::PhysicalPlan) -> FnGenResult<()> {
+        let mut access = PlanAccess::default();
+        access.collect(plan)?;
+        if access.all_pushed_keys {
+            Err(FnGenError::unauthorized_error(
+                "predicate on all the properties cannot be pushed down, as some of them are masked",
+            ))?;
+        }
+        if let Some(key) = access
+            .pushed_keys
+            .iter()
+            .find(|k| self.rules.contains_key(*k))
+        {
+            Err(FnGenError::unauthorized_error(&format!(
+                "predicate on the masked property {:?} cannot be pushed down",
+                key
+            )))?;
+        }
+        Ok(())
+    }
+
+    pub fn mask_record(&self, mut record: Record) -> Record {
+        if let Some(entry) = record
+            .get(None)
+            .and_then(|e| self.mask_entry(e))
+        {
+            record.set_curr_entry(Some(entry));
+        }
+        for (_, entry) in record.get_columns_mut().iter_mut() {
+            if let Some(masked) = self.mask_entry(entry) {
+                *entry = masked;
+            }
+        }
+        record
+    }
+
+    /// Return the masked entry, or `None` if it needs no mask.
+    fn mask_entry(&self, entry: &DynEntry) -> Option<DynEntry> {
+        if let Some(vertex) = entry.as_vertex() {
+            if needs_mask(vertex.get_details()) {
+                let mut vertex = vertex.clone();
+                self.mask_details(vertex.get_details_mut());
+                return Some(DynEntry::new(vertex));
+            }
+        } else if let Some(edge) = entry.as_edge() {
+            if needs_mask(edge.get_details()) {
+                let mut edge = edge.clone();
+                self.mask_details(edge.get_details_mut());
+                return Some(DynEntry::new(edge));
+            }
+        }
+        None
+    }
+
+    fn mask_details(&self, details: &mut DynDetails) {
+        let inner = std::mem::take(details);
+        *details = DynDetails::lazy(MaskedDetails::new(inner, self.rules.clone()));
+    }
+}
+
+/// Elements without details carry no property, and masked ones are not masked again, e.g., when
+/// they are read from a tagged column after another expand.
+fn needs_mask(details: &DynDetails) -> bool {
+    match details {
+        DynDetails::Empty => false,
+        DynDetails::Lazy(lazy) => lazy
+            .as_any_ref()
+            .downcast_ref::<MaskedDetails>()
+            .is_none(),
+        DynDetails::Default(_) => true,
+    }
+}
+
+/// The labels read and whether the graph is written by a plan, including its sub-plans, and the
+/// properties read by the predicates pushed down into the storage.
+#[derive(Default)]
+pub(crate) struct PlanAccess {
+    pub(crate) labels: HashSet<NameOrId>,
+    pub(crate) all_labels: bool,
+    pub(crate) write: bool,
+    pub(crate) pushed_keys: HashSet<NameOrId>,
+    pub(crate) all_pushed_keys: bool,
+}
+
+impl PlanAccess {
+    pub(crate) fn collect(&mut self, plan: &pb::PhysicalPlan) -> FnGenResult<()> {
+        for opr in plan.plan.iter() {
+            let op_kind: OpKind = opr.try_into()?;
+            match op_kind {
+                OpKind::Scan(scan) => {
+                    self.add_tables(scan.params.as_ref(), true)?;
+                    if let Some(idx_predicate) = scan.idx_predicate.as_ref() {
+                        for and_predicate in idx_predicate.or_predicates.iter() {
+                            for triplet in and_predicate.predicates.iter() {
+                                self.add_pushed_property(triplet.key.as_ref())?;
+                            }
+                        }
+                    }
+                }
+                OpKind::Edge(edge) => self.add_tables(edge.params.as_ref(), true)?,
+                OpKind::Vertex(get_v) => self.add_tables(get_v.params.as_ref(), false)?,
+                OpKind::Path(path) => {
+                    if let Some(base) = path.base.as_ref() {
+                        if let Some(edge) = base.edge_expand.as_ref() {
+                            self.add_tables(edge.params.as_ref(), true)?;
+                        }
+                        if let Some(get_v) = base.get_v.as_ref() {
+                            self.add_tables(get_v.params.as_ref(), false)?;
+                        }
+                    }
+                }
+                OpKind::Apply(apply) => {
+                    if let Some(sub_plan) = apply.sub_plan.as_ref() {
+                        self.collect(sub_plan)?;
+                    }
+                }
+                OpKind::Join(join) => {
+                    for sub_plan in join
+                        .left_plan
+                        .iter()
+                        .chain(join.right_plan.iter())
+                    {
+                        self.collect(sub_plan)?;
+                    }
+                }
+                OpKind::Union(union) => {
+                    for sub_plan in union.sub_plans.iter() {
+                        self.collect(sub_plan)?;
+                    }
+                }
+                OpKind::Intersect(intersect) => {
+                    for sub_plan in intersect.sub_plans.iter() {
+                        self.collect(sub_plan)?;
+                    }
+                }
+                OpKind::Repeat(repeat) => {
+                    if let Some(body) = repeat.body.as_ref() {
+                        self.collect(body)?;
+                    }
+                }
+                OpKind::Coalesce(coalesce) => {
+                    for sub_plan in coalesce.sub_plans.iter() {
+                        self.collect(sub_plan)?;
+                    }
+                }
+                OpKind::Sink(sink) => {
+                    if let Some(algebra_pb::sink::sink_target::Inner::SinkVineyard(_)) = sink
+                        .sink_target
+                        .as_ref()
+                        .and_then(|t| t.inner.as_ref())
+                    {
+                        self.write = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn add_tables(
+        &mut self, params: Option<&algebra_pb::QueryParams>, empty_as_all: bool,
+    ) -> FnGenResult<()> {
+        let tables = params
+            .map(|p| p.tables.as_slice())
+            .unwrap_or(&[]);
+        if tables.is_empty() {
+            self.all_labels |= empty_as_all;
+        }
+        for table in tables {
+            self.labels.insert(table.clone().try_into()?);
+        }
+        if let Some(predicate) = params.and_then(|p| p.predicate.as_ref()) {
+            self.add_pushed_expr(predicate)?;
+        }
+        Ok(())
+    }
+
+    fn add_pushed_expr(&mut self, expr: &common_pb::Expression) -> FnGenResult<()> {
+        for opr in expr.operators.iter() {
+            self.add_pushed_opr(opr)?;
+        }
+        Ok(())
+    }
+
+    fn add_pushed_opr(&mut self, opr: &common_pb::ExprOpr) -> FnGenResult<()> {
+        use common_pb::expr_opr::Item;
+        match opr.item.as_ref() {
+            Some(Item::Var(var)) => self.add_pushed_property(var.property.as_ref())?,
+            Some(Item::Vars(vars)) | Some(Item::VarMap(vars)) => {
+                for var in vars.keys.iter() {
+                    self.add_pushed_property(var.property.as_ref())?;
+                }
+            }
+            Some(Item::Map(map)) => {
+                for var in map
+                    .key_vals
+                    .iter()
+                    .filter_map(|kv| kv.value.as_ref())
+                {
+                    self.add_pushed_property(var.property.as_ref())?;
+                }
+            }
+            Some(Item::Concat(concat)) => {
+                for var in concat.vars.iter() {
+                    self.add_pushed_property(var.property.as_ref())?;
+                }
+            }
+            Some(Item::Case(case)) => {
+                for when_then in case.when_then_expressions.iter() {
+                    for expr in when_then
+                        .when_expression
+                        .iter()
+                        .chain(when_then.then_result_expression.iter())
+                    {
+                        self.add_pushed_expr(expr)?;
+                    }
+                }
+                if let Some(expr) = case.else_result_expression.as_ref() {
+                    self.add_pushed_expr(expr)?;
+                }
+            }
+            Some(Item::Udf(udf)) => {
+                for arg in udf.args.iter() {
+                    self.add_pushed_opr(arg)?;
+                }
+            }
+            Some(Item::ElementMap(element_map)) => {
+                if element_map.keys.is_empty() {
+                    self.all_pushed_keys = true;
+                }
+                for key in element_map.keys.iter() {
+                    match key.key.as_ref() {
+                        Some(key) => self.pushed_keys.insert(key.clone().try_into()?),
+                        None => self.pushed_keys.insert(key.name.clone().into()),
+                    };
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn add_pushed_property(&mut self, property: Option<&common_pb::Property>) -> FnGenResult<()> {
+        use common_pb::property::Item;
+        match property.and_then(|p| p.item.as_ref()) {
+            Some(Item::Key(key)) => {
+                self.pushed_keys.insert(key.clone().try_into()?);
+            }
+            Some(Item::All(_)) => self.all_pushed_keys = true,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::{Details, Vertex};
+
+    use super::*;
+
+    fn scan_plan(labels: Vec<i32>) -> pb::PhysicalPlan {
+        let scan = pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: Some(algebra_pb::QueryParams {
+                tables: labels.into_iter().map(|l| l.into()).collect(),
+                ..Default::default()
+            }),
+            idx_predicate: None,
+            is_count_only: false,
+        };
+        let opr = pb::PhysicalOpr {
+            opr: Some(pb::physical_opr::Operator { op_kind: Some(OpKind::Scan(scan)) }),
+            meta_data: vec![],
+        };
+        pb::PhysicalPlan { plan_id: 0, plan: vec![opr] }
+    }
+
+    #[test]
+    fn admit_plan_test() {
+        let policy = AccessPolicy::new()
+            .grant("g", "admin", GraphGrant::read_all().with_write())
+            .grant("g", "analyst", GraphGrant::read_labels(vec![NameOrId::Id(1)]));
+        let admin = Principal::new("root").with_role("admin");
+        let analyst = Principal::new("marko").with_role("analyst");
+        let guest = Principal::new("josh").with_role("guest");
+
+        assert!(policy
+            .admit("g", Some(&admin), &scan_plan(vec![]))
+            .is_ok());
+        assert!(policy
+            .admit("g", Some(&analyst), &scan_plan(vec![1]))
+            .is_ok());
+        assert!(policy
+            .admit("g", Some(&analyst), &scan_plan(vec![1, 2]))
+            .is_err());
+        assert!(policy
+            .admit("g", Some(&analyst), &scan_plan(vec![]))
+            .is_err());
+        assert!(policy
+            .admit("g", Some(&guest), &scan_plan(vec![1]))
+            .is_err());
+        assert!(policy
+            .admit("g2", Some(&admin), &scan_plan(vec![1]))
+            .is_err());
+        assert!(policy
+            .admit("g", None, &scan_plan(vec![1]))
+            .is_err());
+    }
+
+    #[test]
+    fn admit_include_deleted_test() {
+        let policy = AccessPolicy::new()
+            .grant("g", "admin", GraphGrant::read_all().with_read_deleted())
+            .grant("g", "analyst", GraphGrant::read_all());
+        let admin = Principal::new("root").with_role("admin");
+        let analyst = Principal::new("marko").with_role("analyst");
+        assert!(policy
+            .admit_include_deleted("g", Some(&admin))
+            .is_ok());
+        assert!(policy
+            .admit_include_deleted("g", Some(&analyst))
+            .is_err());
+        assert!(policy
+            .admit_include_deleted("g2", Some(&admin))
+            .is_err());
+        assert!(policy.admit_include_deleted("g", None).is_err());
+    }
+
+    #[test]
+    fn restrictions_key_test() {
+        let policy = AccessPolicy::new()
+            .restrict_property("g", "ssn".into(), vec!["admin".to_string()], MaskAction::Omit)
+            .filter_vertices("g", "person".into(), "@.tenant == $tenant");
+        let admin = Principal::new("root")
+            .with_role("admin")
+            .with_attribute("tenant", "1");
+        let analyst = Principal::new("marko")
+            .with_role("analyst")
+            .with_attribute("tenant", "1");
+        let key = |principal: &Principal| {
+            policy
+                .restrictions_key("g", Some(principal))
+                .unwrap()
+        };
+        assert_eq!(key(&admin), key(&admin.clone()));
+        assert_ne!(key(&admin), key(&analyst));
+        // the same caller of another tenant
+        assert_ne!(key(&admin), key(&admin.clone().with_attribute("tenant", "2")));
+        assert!(key(&admin).contains("@.tenant == 1"));
+        assert!(key(&analyst).contains("ssn"));
+        assert!(!key(&admin).contains("ssn"));
+        // the attribute of the row filter is required
+        assert!(policy.restrictions_key("g", None).is_err());
+    }
+
+    fn person() -> Vertex {
+        let mut props = ahash::HashMap::default();
+        props.insert("name".into(), object!("marko"));
+        props.insert("ssn".into(), object!("123"));
+        props.insert("email".into(), object!("marko@x.com"));
+        Vertex::new(1, Some(0), DynDetails::new(props))
+    }
+
+    #[test]
+    fn property_mask_test() {
+        let policy = AccessPolicy::new()
+            .restrict_property("g", "ssn".into(), vec!["admin".to_string()], MaskAction::Omit)
+            .restrict_property("g", "email".into(), vec![], MaskAction::Mask(object!("***")));
+        let admin = Principal::new("root").with_role("admin");
+        let analyst = Principal::new("marko").with_role("analyst");
+        assert!(policy
+            .property_mask("g2", Some(&analyst))
+            .is_none());
+
+        let record = Record::new(person(), Some(0));
+
+        let mask = policy
+            .property_mask("g", Some(&analyst))
+            .unwrap();
+        let record = mask.mask_record(mask.mask_record(record));
+        for tag in vec![None, Some(0)] {
+            let details = record
+                .get(tag)
+                .unwrap()
+                .as_vertex()
+                .unwrap()
+                .get_details();
+            let get = |key: &str| {
+                details
+                    .get_property(&key.into())
+                    .and_then(|v| v.try_to_owned())
+            };
+            assert_eq!(get("name"), Some(object!("marko")));
+            assert_eq!(get("ssn"), None);
+            assert_eq!(get("email"), Some(object!("***")));
+            assert_eq!(details.get_all_properties().unwrap().len(), 2);
+        }
+
+        // admin can read `ssn`, but not `email`, which is allowed to no one
+        let mask = policy.property_mask("g", Some(&admin)).unwrap();
+        let props = mask
+            .mask_record(Record::new(person(), None))
+            .get(None)
+            .unwrap()
+            .as_vertex()
+            .unwrap()
+            .get_details()
+            .get_all_properties()
+            .unwrap();
+        assert_eq!(props.get(&"ssn".into()), Some(&object!("123")));
+        assert_eq!(props.get(&"email".into()), Some(&object!("***")));
+    }
+
+    fn scan_with_predicate(predicate: &str) -> pb::PhysicalPlan {
+        let mut plan = scan_plan(vec![1]);
+        if let Some(OpKind::Scan(scan)) = plan.plan[0]
+            .opr
+            .as_mut()
+            .and_then(|o| o.op_kind.as_mut())
+        {
+            scan.params.as_mut().unwrap().predicate =
+                Some(ir_common::expr_parse::str_to_expr_pb(predicate.to_string()).unwrap());
+        }
+        plan
+    }
+
+    #[test]
+    fn masked_pushdown_test() {
+        let policy = AccessPolicy::new().restrict_property(
+            "g",
+            "ssn".into(),
+            vec!["admin".to_string()],
+            MaskAction::Omit,
+        );
+        let admin = Principal::new("root").with_role("admin");
+        let analyst = Principal::new("marko").with_role("analyst");
+        let mask = policy
+            .property_mask("g", Some(&analyst))
+            .unwrap();
+        assert!(mask
+            .check_pushdown(&scan_with_predicate("@.name == \"marko\""))
+            .is_ok());
+        assert!(mask
+            .check_pushdown(&scan_with_predicate("@.name == \"marko\" && @.ssn == \"123\""))
+            .is_err());
+        assert!(mask
+            .check_pushdown(&scan_with_predicate("@.~all"))
+            .is_err());
+        // nothing is masked for admin
+        assert!(policy
+            .property_mask("g", Some(&admin))
+            .is_none());
+    }
+}
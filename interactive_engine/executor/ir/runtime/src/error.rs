@@ -17,6 +17,7 @@ use graph_proxy::utils::expr::ExprEvalError;
 use graph_proxy::GraphProxyError;
 use ir_common::error::ParsePbError;
 use pegasus::api::function::DynError;
+use pegasus::errors::{CodedError, ErrorCode};
 use pegasus::BuildJobError;
 use prost::DecodeError;
 
@@ -35,12 +36,52 @@ pub enum FnGenError {
     StoreError(GraphProxyError),
     /// Not supported error
     UnSupported(String),
+    /// Access denied error
+    Unauthorized(String),
+    /// Session variable error
+    SessionError(String),
+    /// Stored procedure error
+    ProcedureError(String),
+    /// Trigger error
+    TriggerError(String),
 }
 
 impl FnGenError {
     pub fn unsupported_error(e: &str) -> Self {
         FnGenError::UnSupported(e.to_string())
     }
+
+    pub fn unauthorized_error(e: &str) -> Self {
+        FnGenError::Unauthorized(e.to_string())
+    }
+
+    pub fn session_error(e: &str) -> Self {
+        FnGenError::SessionError(e.to_string())
+    }
+
+    pub fn procedure_error(e: &str) -> Self {
+        FnGenError::ProcedureError(e.to_string())
+    }
+
+    pub fn trigger_error(e: &str) -> Self {
+        FnGenError::TriggerError(e.to_string())
+    }
+
+    /// The code of the error, in `2xxx` of the errors of building the job, or else the code of the
+    /// error of the plan or the store causing it.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            FnGenError::DecodeOpError(_) => ErrorCode::user(1000),
+            FnGenError::ParseError(e) => e.code(),
+            FnGenError::NullGraphError => ErrorCode::system(2001, false),
+            FnGenError::StoreError(e) => e.code(),
+            FnGenError::UnSupported(_) => ErrorCode::user(2002),
+            FnGenError::Unauthorized(_) => ErrorCode::user(2003),
+            FnGenError::SessionError(_) => ErrorCode::user(2004),
+            FnGenError::ProcedureError(_) => ErrorCode::user(2005),
+            FnGenError::TriggerError(_) => ErrorCode::user(2006),
+        }
+    }
 }
 
 impl std::fmt::Display for FnGenError {
@@ -51,11 +92,24 @@ impl std::fmt::Display for FnGenError {
             FnGenError::NullGraphError => write!(f, "Null graph store error in fn gen",),
             FnGenError::StoreError(e) => write!(f, "Query store error in fn gen {}", e),
             FnGenError::UnSupported(e) => write!(f, "Unsupported error in fn gen  {}", e),
+            FnGenError::Unauthorized(e) => write!(f, "Unauthorized error in fn gen {}", e),
+            FnGenError::SessionError(e) => write!(f, "Session error in fn gen {}", e),
+            FnGenError::ProcedureError(e) => write!(f, "Stored procedure error in fn gen {}", e),
+            FnGenError::TriggerError(e) => write!(f, "Trigger error in fn gen {}", e),
         }
     }
 }
 
-impl std::error::Error for FnGenError {}
+impl std::error::Error for FnGenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FnGenError::DecodeOpError(e) => Some(e),
+            FnGenError::ParseError(e) => Some(e),
+            FnGenError::StoreError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl From<ParsePbError> for FnGenError {
     fn from(e: ParsePbError) -> Self {
@@ -77,34 +131,18 @@ impl From<GraphProxyError> for FnGenError {
 
 impl From<FnGenError> for DynError {
     fn from(e: FnGenError) -> Self {
-        let err: Box<dyn std::error::Error + Send + Sync> = e.into();
-        err
+        Box::new(CodedError::new(e.code(), e))
     }
 }
 
 impl From<FnGenError> for BuildJobError {
     fn from(e: FnGenError) -> Self {
-        match e {
-            FnGenError::DecodeOpError(e) => {
-                let err: Box<dyn std::error::Error + Send + Sync> = e.into();
-                BuildJobError::UserError(err)
-            }
-            FnGenError::ParseError(e) => {
-                let err: Box<dyn std::error::Error + Send + Sync> = e.into();
-                BuildJobError::UserError(err)
-            }
-            FnGenError::NullGraphError => {
-                let err: Box<dyn std::error::Error + Send + Sync> = "Null graph error".into();
-                BuildJobError::UserError(err)
-            }
-            FnGenError::StoreError(e) => {
-                let err: Box<dyn std::error::Error + Send + Sync> = e.into();
-                BuildJobError::UserError(err)
-            }
-            FnGenError::UnSupported(e) => {
-                let err: Box<dyn std::error::Error + Send + Sync> = e.into();
-                BuildJobError::UserError(err)
-            }
+        let code = e.code();
+        let err: Box<dyn std::error::Error + Send + Sync> = Box::new(CodedError::new(code, e));
+        if code.is_user_error() {
+            BuildJobError::UserError(err)
+        } else {
+            BuildJobError::ServerError(err)
         }
     }
 }
@@ -126,6 +164,14 @@ pub enum FnExecError {
     UnExpectedData(String),
     /// Accumulate error
     AccumError(String),
+    /// Write graph error
+    WriteError(String),
+    /// Session variable error
+    SessionError(String),
+    /// Standing query error
+    StandingQueryError(String),
+    /// Trigger error
+    TriggerError(String),
     /// Not supported error
     UnSupported(String),
     /// Unreachable error
@@ -145,9 +191,44 @@ impl FnExecError {
         FnExecError::AccumError(e.to_string())
     }
 
+    pub fn write_error(e: &str) -> Self {
+        FnExecError::WriteError(e.to_string())
+    }
+
+    pub fn session_error(e: &str) -> Self {
+        FnExecError::SessionError(e.to_string())
+    }
+
+    pub fn standing_query_error(e: &str) -> Self {
+        FnExecError::StandingQueryError(e.to_string())
+    }
+
+    pub fn trigger_error(e: &str) -> Self {
+        FnExecError::TriggerError(e.to_string())
+    }
+
     pub fn unsupported_error(e: &str) -> Self {
         FnExecError::UnSupported(e.to_string())
     }
+
+    /// The code of the error, in `3xxx` of the errors of executing the operators, or else the code of
+    /// the error of the store causing it.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            FnExecError::NullGraphError => ErrorCode::system(3001, false),
+            FnExecError::StoreError(e) => e.code(),
+            FnExecError::GetTagError(_) => ErrorCode::user(3002),
+            FnExecError::ExprEvalError(_) => ErrorCode::user(3003),
+            FnExecError::UnExpectedData(_) => ErrorCode::user(3004),
+            FnExecError::AccumError(_) => ErrorCode::user(3005),
+            FnExecError::WriteError(_) => ErrorCode::system(3006, false),
+            FnExecError::SessionError(_) => ErrorCode::user(3007),
+            FnExecError::StandingQueryError(_) => ErrorCode::system(3008, false),
+            FnExecError::TriggerError(_) => ErrorCode::user(3009),
+            FnExecError::UnSupported(_) => ErrorCode::user(3010),
+            FnExecError::Unreachable => ErrorCode::system(3011, false),
+        }
+    }
 }
 
 impl std::fmt::Display for FnExecError {
@@ -159,13 +240,25 @@ impl std::fmt::Display for FnExecError {
             FnExecError::ExprEvalError(e) => write!(f, "Eval expression error in exec {}", e),
             FnExecError::UnExpectedData(e) => write!(f, "Unexpected data type in exec {}", e),
             FnExecError::AccumError(e) => write!(f, "Accum error in exec {}", e),
+            FnExecError::WriteError(e) => write!(f, "Write graph error in exec {}", e),
+            FnExecError::SessionError(e) => write!(f, "Session error in exec {}", e),
+            FnExecError::StandingQueryError(e) => write!(f, "Standing query error in exec {}", e),
+            FnExecError::TriggerError(e) => write!(f, "Trigger error in exec {}", e),
             FnExecError::UnSupported(e) => write!(f, "Op not supported error in exec {}", e),
             FnExecError::Unreachable => write!(f, "Unreachable error in exec"),
         }
     }
 }
 
-impl std::error::Error for FnExecError {}
+impl std::error::Error for FnExecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FnExecError::StoreError(e) => Some(e),
+            FnExecError::ExprEvalError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl From<ExprEvalError> for FnExecError {
     fn from(e: ExprEvalError) -> Self {
@@ -181,7 +274,45 @@ impl From<GraphProxyError> for FnExecError {
 
 impl From<FnExecError> for DynError {
     fn from(e: FnExecError) -> Self {
-        let err: Box<dyn std::error::Error + Send + Sync> = e.into();
-        err
+        Box::new(CodedError::new(e.code(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use pegasus::errors::{ErrorCategory, JobExecError};
+
+    use super::*;
+
+    #[test]
+    fn error_code_test() {
+        let err: DynError = FnExecError::get_tag_error("a").into();
+        let err = JobExecError::from(err);
+        assert_eq!(err.code(), ErrorCode::user(3002));
+
+        // the code of the store is reported through the chain
+        let err: DynError = FnExecError::from(GraphProxyError::query_store_error("timeout")).into();
+        let store = err
+            .source()
+            .and_then(|e| e.source())
+            .and_then(|e| e.downcast_ref::<GraphProxyError>());
+        assert!(matches!(store, Some(GraphProxyError::QueryStoreError(_))));
+        let code = JobExecError::from(err).code();
+        assert_eq!(code.code, 4001);
+        assert_eq!(code.category, ErrorCategory::System);
+        assert!(!code.retryable);
+        let err: DynError = FnExecError::from(GraphProxyError::cluster_info_missing("routing")).into();
+        assert!(JobExecError::from(err).code().retryable);
+        let err: DynError = FnExecError::from(GraphProxyError::invalid_query_error("pk")).into();
+        assert_eq!(JobExecError::from(err).code(), ErrorCode::user(4006));
+
+        let err = BuildJobError::from(FnGenError::from(ParsePbError::EmptyFieldError("a".to_string())));
+        assert!(matches!(err, BuildJobError::UserError(_)));
+        assert_eq!(err.code(), ErrorCode::user(1003));
+        let err = BuildJobError::from(FnGenError::NullGraphError);
+        assert!(matches!(err, BuildJobError::ServerError(_)));
+        assert_eq!(err.code(), ErrorCode::system(2001, false));
     }
 }
@@ -0,0 +1,435 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Explain the plans as they're installed on the workers, see `pegasus_server::explain`.
+
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+use pegasus_server::explain::{PlanGraph, Routing};
+
+use crate::validate::op_name;
+
+/// Explain the plan executed by `parallelism` workers.
+///
+/// The records are exchanged by the repartitions of the plan, and by the operators exchanging them
+/// internally, e.g., a dedup shuffles the records by its keys, and a limit, an order or a fold
+/// aggregates them to a single worker, by which the records are processed until they're shuffled or
+/// broadcast again. The results are also aggregated before the sink in the `deterministic` mode.
+///
+/// A group aggregates the records partially in their workers before the partial aggregations are
+/// shuffled by its keys, which is explained as a `PartialGroupBy` before the group, with the
+/// repartition right before the group skipped, except in the `deterministic` mode.
+///
+/// The operators of the sub-plans, e.g., of a join, are connected from the operator before the
+/// enclosing operator, and to the enclosing operator.
+pub fn explain_plan(plan: &pb::PhysicalPlan, parallelism: usize, deterministic: bool) -> PlanGraph {
+    let mut explainer = Explainer { graph: PlanGraph::new(parallelism), deterministic };
+    explainer.explain(&plan.plan, None);
+    explainer.graph
+}
+
+/// The last operator of a plan explained, and the number of the workers processing its records.
+type Tail = Option<(usize, usize)>;
+
+struct Explainer {
+    graph: PlanGraph,
+    deterministic: bool,
+}
+
+impl Explainer {
+    fn explain(&mut self, plan: &[pb::PhysicalOpr], mut tail: Tail) -> Tail {
+        for (index, opr) in plan.iter().enumerate() {
+            if let Some(op_kind) = op_kind_of(opr) {
+                let next = plan.get(index + 1).and_then(op_kind_of);
+                if matches!(op_kind, OpKind::Repartition(_))
+                    && next.map_or(false, |next| self.is_two_phase(next))
+                {
+                    // the partial aggregations of the group are exchanged instead
+                    continue;
+                }
+                tail = self.explain_op(op_kind, tail);
+            }
+        }
+        tail
+    }
+
+    /// Whether the operator is a group aggregating the records partially before they're exchanged, as
+    /// in all but the deterministic mode.
+    fn is_two_phase(&self, op_kind: &OpKind) -> bool {
+        matches!(op_kind, OpKind::GroupBy(group) if !group.mappings.is_empty()) && !self.deterministic
+    }
+
+    fn explain_op(&mut self, op_kind: &OpKind, tail: Tail) -> Tail {
+        let inputs = match op_kind {
+            OpKind::Join(join) => {
+                let left = join
+                    .left_plan
+                    .as_ref()
+                    .map_or(tail, |plan| self.explain(&plan.plan, tail));
+                let right = join
+                    .right_plan
+                    .as_ref()
+                    .map_or(tail, |plan| self.explain(&plan.plan, tail));
+                vec![
+                    (left, Routing::Shuffle, describe_keys(&join.left_keys)),
+                    (right, Routing::Shuffle, describe_keys(&join.right_keys)),
+                ]
+            }
+            OpKind::Union(union) => self.explain_sub_plans(&union.sub_plans, tail),
+            OpKind::Intersect(intersect) => self.explain_sub_plans(&intersect.sub_plans, tail),
+            OpKind::Coalesce(coalesce) => self.explain_sub_plans(&coalesce.sub_plans, tail),
+            OpKind::Apply(apply) => {
+                // the sub-plan is applied to each record in the worker of the record
+                let mut inputs = vec![(tail, Routing::Pipeline, None)];
+                if let Some(sub_plan) = apply.sub_plan.as_ref() {
+                    let sub_tail = self.explain(&sub_plan.plan, tail);
+                    inputs.push((sub_tail, Routing::Pipeline, None));
+                }
+                inputs
+            }
+            OpKind::GroupBy(_) if self.is_two_phase(op_kind) => {
+                // the records are aggregated partially in the worker they're in
+                let parallelism = tail.map_or(self.graph.parallelism, |(_, parallelism)| parallelism);
+                let partial = self
+                    .graph
+                    .add_node("PartialGroupBy", None, parallelism);
+                if let Some((from, _)) = tail {
+                    self.graph
+                        .add_edge(from, partial, Routing::Pipeline, None);
+                }
+                let (routing, key) = self.routing_of(op_kind);
+                vec![(Some((partial, parallelism)), routing, key)]
+            }
+            OpKind::Repeat(repeat) => {
+                // the records are fed back to the body until they leave the loop
+                let body = repeat
+                    .body
+                    .as_ref()
+                    .map_or(tail, |body| self.explain(&body.plan, tail));
+                vec![(body, Routing::Pipeline, None)]
+            }
+            _ => {
+                let (routing, key) = self.routing_of(op_kind);
+                vec![(tail, routing, key)]
+            }
+        };
+        let parallelism = inputs
+            .iter()
+            .map(|(input, routing, _)| match routing {
+                Routing::Pipeline => input.map_or(self.graph.parallelism, |(_, parallelism)| parallelism),
+                Routing::Aggregate => 1,
+                Routing::Shuffle | Routing::Broadcast => self.graph.parallelism,
+            })
+            .max()
+            .unwrap_or(self.graph.parallelism);
+        let id = self
+            .graph
+            .add_node(op_name(op_kind), describe_op(op_kind), parallelism);
+        for (input, routing, key) in inputs {
+            if let Some((from, _)) = input {
+                self.graph.add_edge(from, id, routing, key);
+            }
+        }
+        Some((id, parallelism))
+    }
+
+    fn explain_sub_plans(
+        &mut self, sub_plans: &[pb::PhysicalPlan], tail: Tail,
+    ) -> Vec<(Tail, Routing, Option<String>)> {
+        sub_plans
+            .iter()
+            .map(|sub_plan| (self.explain(&sub_plan.plan, tail), Routing::Pipeline, None))
+            .collect()
+    }
+
+    /// How the records are sent to the operator, and the key if they're shuffled.
+    fn routing_of(&self, op_kind: &OpKind) -> (Routing, Option<String>) {
+        match op_kind {
+            OpKind::Repartition(repartition) => match repartition.strategy.as_ref() {
+                Some(pb::repartition::Strategy::ToAnother(shuffle)) => {
+                    let key = shuffle
+                        .shuffle_key
+                        .map_or_else(|| "head".to_owned(), |tag| format!("@{}", tag));
+                    (Routing::Shuffle, Some(key))
+                }
+                Some(pb::repartition::Strategy::ToOthers(_)) => (Routing::Broadcast, None),
+                None => (Routing::Pipeline, None),
+            },
+            OpKind::GroupBy(group) if !group.mappings.is_empty() => {
+                let keys: Vec<common_pb::Variable> = group
+                    .mappings
+                    .iter()
+                    .filter_map(|mapping| mapping.key.clone())
+                    .collect();
+                (Routing::Shuffle, describe_keys(&keys))
+            }
+            OpKind::Dedup(dedup) => (Routing::Shuffle, describe_keys(&dedup.keys)),
+            OpKind::Sample(sample) => match sample.sample_key.as_ref() {
+                Some(key) if is_sample_by_num(sample) => (Routing::Shuffle, Some(describe_var(key))),
+                _ if is_sample_by_num(sample) => (Routing::Aggregate, None),
+                _ => (Routing::Pipeline, None),
+            },
+            OpKind::KHop(k_hop) => {
+                let key = k_hop
+                    .start_tag
+                    .as_ref()
+                    .map_or_else(|| "head".to_owned(), |tag| format!("@{}", describe_name(tag)));
+                (Routing::Shuffle, Some(key))
+            }
+            // the messages of the algorithms are sent to the partitions of their vertices
+            OpKind::Algorithm(_) => (Routing::Shuffle, Some("vertex".to_owned())),
+            // the variables are collected, and bound on each server
+            OpKind::StoreVar(_) => (Routing::Broadcast, None),
+            // the side effects are merged in a single worker
+            OpKind::SideEffect(_) | OpKind::Cap(_) => (Routing::Aggregate, None),
+            // the records of the same head are merged
+            OpKind::Sack(algebra_pb::Sack { inner: Some(algebra_pb::sack::Inner::Merge(_)) }) => {
+                (Routing::Shuffle, Some("head".to_owned()))
+            }
+            // the paths of the same root are assembled into a tree
+            OpKind::Tree(_) => (Routing::Shuffle, Some("root".to_owned())),
+            OpKind::GroupBy(_) | OpKind::Limit(_) | OpKind::OrderBy(_) => (Routing::Aggregate, None),
+            OpKind::Sink(_) if self.deterministic => (Routing::Aggregate, None),
+            _ => (Routing::Pipeline, None),
+        }
+    }
+}
+
+fn op_kind_of(opr: &pb::PhysicalOpr) -> Option<&OpKind> {
+    opr.opr
+        .as_ref()
+        .and_then(|o| o.op_kind.as_ref())
+}
+
+fn is_sample_by_num(sample: &algebra_pb::Sample) -> bool {
+    matches!(
+        sample
+            .sample_type
+            .as_ref()
+            .and_then(|sample_type| sample_type.inner.as_ref()),
+        Some(algebra_pb::sample::sample_type::Inner::SampleByNum(_))
+    )
+}
+
+fn describe_op(op_kind: &OpKind) -> Option<String> {
+    match op_kind {
+        OpKind::Scan(scan) => describe_labels(scan.params.as_ref()),
+        OpKind::Edge(edge) => describe_labels(edge.params.as_ref()),
+        OpKind::Vertex(get_v) => describe_labels(get_v.params.as_ref()),
+        OpKind::Limit(limit) => limit
+            .range
+            .as_ref()
+            .map(|range| format!("limit {}", range.upper)),
+        OpKind::OrderBy(order) => order
+            .limit
+            .as_ref()
+            .map(|range| format!("limit {}", range.upper)),
+        OpKind::Join(join) => {
+            pb::join::JoinKind::from_i32(join.join_kind).map(|kind| format!("{:?}", kind))
+        }
+        OpKind::Call(call) => Some(call.name.clone()),
+        OpKind::SideEffect(side_effect) => Some(side_effect.name.clone()),
+        OpKind::Cap(cap) => Some(cap.name.clone()),
+        OpKind::Tree(tree) if tree.max_size > 0 => Some(format!("max size {}", tree.max_size)),
+        OpKind::PatternMatch(pattern) => Some(format!("{} triples", pattern.triples.len())),
+        OpKind::Repeat(repeat) if repeat.max_iterations > 0 => {
+            Some(format!("max {} iterations", repeat.max_iterations))
+        }
+        _ => None,
+    }
+}
+
+fn describe_labels(params: Option<&algebra_pb::QueryParams>) -> Option<String> {
+    let labels: Vec<String> = params?
+        .tables
+        .iter()
+        .map(describe_name)
+        .collect();
+    if labels.is_empty() {
+        None
+    } else {
+        Some(format!("labels [{}]", labels.join(", ")))
+    }
+}
+
+fn describe_keys(keys: &[common_pb::Variable]) -> Option<String> {
+    if keys.is_empty() {
+        None
+    } else {
+        let keys: Vec<String> = keys.iter().map(describe_var).collect();
+        Some(keys.join(", "))
+    }
+}
+
+/// Describe a variable as `@a.name`, or `head.~id` without a tag.
+fn describe_var(var: &common_pb::Variable) -> String {
+    let tag = var
+        .tag
+        .as_ref()
+        .map_or_else(|| "head".to_owned(), |tag| format!("@{}", describe_name(tag)));
+    let property = match var
+        .property
+        .as_ref()
+        .and_then(|p| p.item.as_ref())
+    {
+        Some(common_pb::property::Item::Id(_)) => ".~id".to_owned(),
+        Some(common_pb::property::Item::Label(_)) => ".~label".to_owned(),
+        Some(common_pb::property::Item::Len(_)) => ".~len".to_owned(),
+        Some(common_pb::property::Item::All(_)) => ".~all".to_owned(),
+        Some(common_pb::property::Item::Key(key)) => format!(".{}", describe_name(key)),
+        None => String::new(),
+    };
+    tag + &property
+}
+
+fn describe_name(name: &common_pb::NameOrId) -> String {
+    match name.item.as_ref() {
+        Some(common_pb::name_or_id::Item::Name(name)) => name.clone(),
+        Some(common_pb::name_or_id::Item::Id(id)) => id.to_string(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opr(op_kind: OpKind) -> pb::PhysicalOpr {
+        pb::PhysicalOpr::from(op_kind)
+    }
+
+    fn scan(label: &str) -> pb::PhysicalOpr {
+        let params = algebra_pb::QueryParams { tables: vec![label.into()], ..Default::default() };
+        opr(OpKind::Scan(pb::Scan { params: Some(params), ..Default::default() }))
+    }
+
+    fn shuffle(key: Option<i32>) -> pb::PhysicalOpr {
+        let shuffle = pb::repartition::Shuffle { shuffle_key: key };
+        opr(OpKind::Repartition(pb::Repartition {
+            strategy: Some(pb::repartition::Strategy::ToAnother(shuffle)),
+        }))
+    }
+
+    fn plan(plan: Vec<pb::PhysicalOpr>) -> pb::PhysicalPlan {
+        pb::PhysicalPlan { plan_id: 0, plan }
+    }
+
+    fn sink() -> pb::PhysicalOpr {
+        opr(OpKind::Sink(pb::Sink::default()))
+    }
+
+    #[test]
+    fn explain_plan_test() {
+        let limit = opr(OpKind::Limit(algebra_pb::Limit {
+            range: Some(algebra_pb::Range { lower: 0, upper: 10 }),
+        }));
+        let plan = plan(vec![scan("person"), shuffle(None), limit, shuffle(Some(1)), sink()]);
+        let graph = explain_plan(&plan, 4, false);
+        let operators: Vec<(&str, usize)> = graph
+            .nodes
+            .iter()
+            .map(|node| (node.operator.as_str(), node.parallelism))
+            .collect();
+        assert_eq!(
+            operators,
+            vec![("Scan", 4), ("Repartition", 4), ("Limit", 1), ("Repartition", 4), ("Sink", 4)]
+        );
+        assert_eq!(graph.nodes[0].detail.as_deref(), Some("labels [person]"));
+        assert_eq!(graph.nodes[2].detail.as_deref(), Some("limit 10"));
+        let routings: Vec<(Routing, Option<&str>)> = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.routing, edge.key.as_deref()))
+            .collect();
+        assert_eq!(
+            routings,
+            vec![
+                (Routing::Shuffle, Some("head")),
+                (Routing::Aggregate, None),
+                (Routing::Shuffle, Some("@1")),
+                (Routing::Pipeline, None)
+            ]
+        );
+        assert_eq!(graph.exchanges(), 3);
+
+        let graph = explain_plan(&plan, 4, true);
+        assert_eq!(graph.edges[3].routing, Routing::Aggregate);
+        assert_eq!(graph.nodes[4].parallelism, 1);
+    }
+
+    #[test]
+    fn explain_join_test() {
+        let key = |tag: &str| common_pb::Variable { tag: Some(tag.into()), ..Default::default() };
+        let join = pb::Join {
+            left_keys: vec![key("a")],
+            right_keys: vec![key("b")],
+            left_plan: Some(plan(vec![scan("person")])),
+            right_plan: Some(plan(vec![scan("software"), shuffle(None)])),
+            ..Default::default()
+        };
+        let plan = plan(vec![opr(OpKind::Join(join)), sink()]);
+        let graph = explain_plan(&plan, 2, false);
+        let operators: Vec<&str> = graph
+            .nodes
+            .iter()
+            .map(|node| node.operator.as_str())
+            .collect();
+        assert_eq!(operators, vec!["Scan", "Scan", "Repartition", "Join", "Sink"]);
+        let edges: Vec<(usize, usize, Option<&str>)> = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.from, edge.to, edge.key.as_deref()))
+            .collect();
+        assert_eq!(edges, vec![(1, 2, Some("head")), (0, 3, Some("@a")), (2, 3, Some("@b")), (3, 4, None)]);
+    }
+
+    #[test]
+    fn explain_group_test() {
+        let key = common_pb::Variable { tag: Some("a".into()), ..Default::default() };
+        let group = pb::GroupBy {
+            mappings: vec![pb::group_by::KeyAlias { key: Some(key), alias: None }],
+            ..Default::default()
+        };
+        let plan = plan(vec![scan("person"), shuffle(Some(0)), opr(OpKind::GroupBy(group)), sink()]);
+        let graph = explain_plan(&plan, 4, false);
+        let operators: Vec<(&str, usize)> = graph
+            .nodes
+            .iter()
+            .map(|node| (node.operator.as_str(), node.parallelism))
+            .collect();
+        assert_eq!(operators, vec![("Scan", 4), ("PartialGroupBy", 4), ("GroupBy", 4), ("Sink", 4)]);
+        let routings: Vec<(Routing, Option<&str>)> = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.routing, edge.key.as_deref()))
+            .collect();
+        assert_eq!(
+            routings,
+            vec![(Routing::Pipeline, None), (Routing::Shuffle, Some("@a")), (Routing::Pipeline, None)]
+        );
+        assert_eq!(graph.exchanges(), 1);
+
+        // the records are shuffled to the group in the deterministic mode
+        let graph = explain_plan(&plan, 4, true);
+        let operators: Vec<&str> = graph
+            .nodes
+            .iter()
+            .map(|node| node.operator.as_str())
+            .collect();
+        assert_eq!(operators, vec!["Scan", "Repartition", "GroupBy", "Sink"]);
+    }
+}
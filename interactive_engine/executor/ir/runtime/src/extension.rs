@@ -0,0 +1,487 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The extensions of the graph read by the jobs, which are configured by the files of json given in
+//! the options of the server, and registered to the graph proxy when the server starts:
+//!
+//! * `gaia.graph.views`: the named views, e.g., `{"tenant": {"vertex_labels": [0], "vertex_filter":
+//!   "@.tenant == 1", "vertex_columns": ["name"]}}`, with `edge_labels`, `edge_filter` and
+//!   `edge_columns` of the edges alike, all of which are optional.
+//! * `gaia.graph.derived`: the derived properties of the labels, e.g., `{"vertices": {"0": {"age":
+//!   "2024 - @.birth_year"}}, "edges": {"1": {"years": "2024 - @.since"}}}`.
+//! * `gaia.graph.temporal`: the temporal labels of the edges with the properties of the bounds of
+//!   their validity, e.g., `{"1": {"valid_from": "start_date", "valid_to": "end_date"}}`.
+//!
+//! The labels are given by their ids, and the properties by their names or ids, as the graph stores.
+//!
+//! Besides, the soft deletes are enabled by `gaia.soft.delete.property`, the property of the
+//! tombstones, with the deleted elements kept for `gaia.soft.delete.retention.ms`, 7 days by default,
+//! and purged in every `gaia.soft.delete.purge.interval.ms`, 1 hour by default, see `start_purge_by()`.
+//!
+//! The standing queries are configured by `gaia.standing.queries` alike, e.g., `{"adults": {"vertices":
+//! [0], "filter": "@.age > 27", "columns": ["~id", "age"]}}`, with `edges` in place of `vertices`, an
+//! `expand` of `{"direction": "out", "labels": [1]}`, and an `aggregate` of `{"keys": 1, "function":
+//! "sum", "column": 1}` or of the function `count`, see `register_standing_queries()`.
+//!
+//! The trigger plans are configured by `gaia.triggers`, e.g., `{"audit": {"vertices": [0], "events":
+//! ["insert"], "plan": "audit.pb"}}`, with `edges` in place of `vertices`, where the plan is the file
+//! of the encoded physical plan, see `start_triggers()`.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use graph_proxy::apis::{
+    enable_soft_delete, register_derived_schema, register_temporal_schema, register_view, start_purge,
+    DerivedSchema, Direction, GraphView, PurgeHandle, SoftDelete, TemporalSchema,
+};
+use graph_proxy::utils::expr::eval_pred::PEvaluator;
+use ir_common::error::ParsePbError;
+use ir_common::expr_parse::str_to_expr_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::physical as pb;
+use ir_common::{LabelId, NameOrId};
+use prost::Message;
+use serde_json::{Map, Value};
+
+use crate::error::{FnGenError, FnGenResult};
+use crate::standing::{StandingAggregate, StandingColumn, StandingQueries, StandingQuery};
+use crate::trigger::{RetryHandle, TriggerEvent, TriggerOn, TriggerRegistry};
+
+const DEFAULT_SOFT_DELETE_RETENTION_MS: u64 = 7 * 24 * 3600 * 1000;
+const DEFAULT_PURGE_INTERVAL_MS: u64 = 3600 * 1000;
+const DEFAULT_TRIGGER_RETRY_INTERVAL_MS: u64 = 10 * 1000;
+
+/// Register the extensions of the graph configured by the options of the server.
+pub fn register_extensions(options: &HashMap<String, String>) -> FnGenResult<()> {
+    if let Some(path) = options.get("gaia.graph.views") {
+        for (name, view) in read_json(path)?.iter() {
+            register_view(name, parse_view(view)?);
+            info!("register graph view {} of {}", name, path);
+        }
+    }
+    if let Some(path) = options.get("gaia.graph.derived") {
+        register_derived_schema(parse_derived(&read_json(path)?)?);
+        info!("register derived properties of {}", path);
+    }
+    if let Some(path) = options.get("gaia.graph.temporal") {
+        register_temporal_schema(parse_temporal(&read_json(path)?)?);
+        info!("register temporal edges of {}", path);
+    }
+    if let Some(soft_delete) = parse_soft_delete(options)? {
+        info!("enable soft deletes {:?}", soft_delete);
+        enable_soft_delete(Some(soft_delete));
+    }
+    Ok(())
+}
+
+/// Start purging the deleted elements of the partitions of the server by a job of the `workers` local
+/// workers if the soft deletes are enabled, until the returned handle is dropped.
+pub fn start_purge_by(options: &HashMap<String, String>, workers: u32) -> FnGenResult<Option<PurgeHandle>> {
+    if !options.contains_key("gaia.soft.delete.property") {
+        return Ok(None);
+    }
+    let interval_ms =
+        parse_option(options, "gaia.soft.delete.purge.interval.ms")?.unwrap_or(DEFAULT_PURGE_INTERVAL_MS);
+    Ok(Some(start_purge(Duration::from_millis(interval_ms), workers)))
+}
+
+/// The standing queries of `gaia.standing.queries` materialized from the registered graph, with the
+/// deltas of a subscription buffered up to `gaia.standing.subscription.capacity` batches; none if not
+/// configured.
+pub fn register_standing_queries(
+    options: &HashMap<String, String>,
+) -> FnGenResult<Option<Arc<StandingQueries>>> {
+    let path = match options.get("gaia.standing.queries") {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let queries = match parse_option(options, "gaia.standing.subscription.capacity")? {
+        Some(capacity) => StandingQueries::with_capacity(capacity),
+        None => StandingQueries::default(),
+    };
+    for (name, query) in read_json(path)?.iter() {
+        queries
+            .register(name, parse_standing(query)?)
+            .map_err(|e| {
+                FnGenError::unsupported_error(&format!("register standing query {} failed: {}", name, e))
+            })?;
+        info!("register standing query {} of {}", name, path);
+    }
+    Ok(Some(Arc::new(queries)))
+}
+
+/// Register the trigger plans of `gaia.triggers` to the registry, and retry the pending firings of its
+/// triggers in every `gaia.trigger.retry.interval.ms`, 10 seconds by default, until the returned handle
+/// is dropped.
+pub fn start_triggers(
+    options: &HashMap<String, String>, registry: &Arc<TriggerRegistry>,
+) -> FnGenResult<RetryHandle> {
+    if let Some(path) = options.get("gaia.triggers") {
+        for (name, trigger) in read_json(path)?.iter() {
+            let (on, plan) = parse_trigger(trigger)?;
+            registry.register_plan(name, on, plan)?;
+            info!("register trigger plan {} of {}", name, path);
+        }
+    }
+    let interval_ms = parse_option(options, "gaia.trigger.retry.interval.ms")?
+        .unwrap_or(DEFAULT_TRIGGER_RETRY_INTERVAL_MS);
+    Ok(registry.start_retry(Duration::from_millis(interval_ms)))
+}
+
+fn parse_trigger(trigger: &Value) -> FnGenResult<(TriggerOn, pb::PhysicalPlan)> {
+    let on = match (trigger.get("vertices"), trigger.get("edges")) {
+        (Some(labels), None) => TriggerOn::vertices(parse_labels(labels)?),
+        (None, Some(labels)) => TriggerOn::edges(parse_labels(labels)?),
+        _ => Err(parse_error(format!("either vertices or edges of trigger {}", trigger)))?,
+    };
+    let events = match trigger.get("events") {
+        Some(events) => as_array(events)?
+            .iter()
+            .map(|event| match event.as_str() {
+                Some("insert") => Ok(TriggerEvent::Insert),
+                Some("update") => Ok(TriggerEvent::Update),
+                Some("delete") => Ok(TriggerEvent::Delete),
+                _ => Err(parse_error(format!("invalid event {} of trigger", event))),
+            })
+            .collect::<FnGenResult<Vec<_>>>()?,
+        None => vec![],
+    };
+    let path = trigger
+        .get("plan")
+        .and_then(|plan| plan.as_str())
+        .ok_or_else(|| parse_error(format!("plan of trigger {} is missing", trigger)))?;
+    let bytes = std::fs::read(path).map_err(|e| parse_error(format!("read {} failed: {}", path, e)))?;
+    Ok((on.with_events(events), pb::PhysicalPlan::decode(bytes.as_slice())?))
+}
+
+fn parse_standing(query: &Value) -> FnGenResult<StandingQuery> {
+    let mut standing = match (query.get("vertices"), query.get("edges")) {
+        (Some(labels), None) => StandingQuery::vertices(parse_labels(labels)?),
+        (None, Some(labels)) => StandingQuery::edges(parse_labels(labels)?),
+        _ => Err(parse_error(format!("either vertices or edges of standing query {}", query)))?,
+    };
+    if let Some(filter) = query.get("filter") {
+        standing = standing.with_filter(PEvaluator::try_from(parse_expr(filter)?)?);
+    }
+    if let Some(expand) = query.get("expand") {
+        let direction = match expand.get("direction").and_then(|d| d.as_str()) {
+            Some("out") => Direction::Out,
+            Some("in") => Direction::In,
+            Some("both") => Direction::Both,
+            _ => Err(parse_error(format!("invalid direction of expand {}", expand)))?,
+        };
+        let labels = match expand.get("labels") {
+            Some(labels) => parse_labels(labels)?,
+            None => vec![],
+        };
+        standing = standing.with_expand(direction, labels);
+    }
+    if let Some(columns) = query.get("columns") {
+        let columns = as_array(columns)?
+            .iter()
+            .map(parse_column)
+            .collect::<FnGenResult<Vec<_>>>()?;
+        standing = standing.with_columns(columns);
+    }
+    if let Some(aggregate) = query.get("aggregate") {
+        let keys = aggregate
+            .get("keys")
+            .and_then(|keys| keys.as_u64())
+            .unwrap_or(0) as usize;
+        let function = match aggregate
+            .get("function")
+            .and_then(|f| f.as_str())
+        {
+            Some("count") => StandingAggregate::Count,
+            Some("sum") => match aggregate.get("column").and_then(|c| c.as_u64()) {
+                Some(column) => StandingAggregate::Sum(column as usize),
+                None => Err(parse_error(format!("sum without the column of aggregate {}", aggregate)))?,
+            },
+            _ => Err(parse_error(format!("invalid function of aggregate {}", aggregate)))?,
+        };
+        standing = standing.with_aggregate(keys, function);
+    }
+    Ok(standing)
+}
+
+/// The column of the id by `~id`, the label by `~label`, or the property of the name or id otherwise.
+fn parse_column(column: &Value) -> FnGenResult<StandingColumn> {
+    match column.as_str() {
+        Some("~id") => Ok(StandingColumn::Id),
+        Some("~label") => Ok(StandingColumn::Label),
+        _ => Ok(StandingColumn::Property(parse_key(column)?)),
+    }
+}
+
+fn parse_soft_delete(options: &HashMap<String, String>) -> FnGenResult<Option<SoftDelete>> {
+    let property = match options.get("gaia.soft.delete.property") {
+        Some(property) => NameOrId::Str(property.clone()),
+        None => return Ok(None),
+    };
+    let retention_ms =
+        parse_option(options, "gaia.soft.delete.retention.ms")?.unwrap_or(DEFAULT_SOFT_DELETE_RETENTION_MS);
+    Ok(Some(SoftDelete::new(property, Duration::from_millis(retention_ms))))
+}
+
+fn parse_option<T: FromStr>(options: &HashMap<String, String>, key: &str) -> FnGenResult<Option<T>> {
+    options
+        .get(key)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| parse_error(format!("invalid {} {}", key, value)))
+        })
+        .transpose()
+}
+
+fn parse_error(msg: String) -> FnGenError {
+    ParsePbError::ParseError(msg).into()
+}
+
+fn read_json(path: &str) -> FnGenResult<Map<String, Value>> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| parse_error(format!("read {} failed: {}", path, e)))?;
+    match serde_json::from_str(&content) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => Err(parse_error(format!("{} is not a json object", path))),
+        Err(e) => Err(ParsePbError::SerdeError(format!("parse {} failed: {}", path, e)).into()),
+    }
+}
+
+fn parse_view(view: &Value) -> FnGenResult<GraphView> {
+    let mut graph_view = GraphView::new();
+    if let Some(labels) = view.get("vertex_labels") {
+        graph_view = graph_view.with_vertex_labels(parse_labels(labels)?);
+    }
+    if let Some(labels) = view.get("edge_labels") {
+        graph_view = graph_view.with_edge_labels(parse_labels(labels)?);
+    }
+    if let Some(filter) = view.get("vertex_filter") {
+        graph_view = graph_view.with_vertex_filter(parse_expr(filter)?)?;
+    }
+    if let Some(filter) = view.get("edge_filter") {
+        graph_view = graph_view.with_edge_filter(parse_expr(filter)?)?;
+    }
+    if let Some(columns) = view.get("vertex_columns") {
+        graph_view = graph_view.with_vertex_columns(parse_keys(columns)?);
+    }
+    if let Some(columns) = view.get("edge_columns") {
+        graph_view = graph_view.with_edge_columns(parse_keys(columns)?);
+    }
+    Ok(graph_view)
+}
+
+fn parse_derived(derived: &Map<String, Value>) -> FnGenResult<DerivedSchema> {
+    let mut schema = DerivedSchema::new();
+    if let Some(vertices) = derived.get("vertices") {
+        for (label, name, expr) in parse_properties(vertices)? {
+            schema.add_vertex_property(label, name, expr)?;
+        }
+    }
+    if let Some(edges) = derived.get("edges") {
+        for (label, name, expr) in parse_properties(edges)? {
+            schema.add_edge_property(label, name, expr)?;
+        }
+    }
+    Ok(schema)
+}
+
+/// The expressions of the properties of the labels, i.e., `{"label": {"name": "expression"}}`.
+fn parse_properties(properties: &Value) -> FnGenResult<Vec<(LabelId, NameOrId, common_pb::Expression)>> {
+    let mut res = vec![];
+    for (label, props) in as_object(properties)? {
+        let label = parse_label_key(label)?;
+        for (name, expr) in as_object(props)? {
+            res.push((label, NameOrId::Str(name.clone()), parse_expr(expr)?));
+        }
+    }
+    Ok(res)
+}
+
+fn parse_temporal(temporal: &Map<String, Value>) -> FnGenResult<TemporalSchema> {
+    let mut schema = TemporalSchema::new();
+    for (label, bounds) in temporal {
+        let label = parse_label_key(label)?;
+        let bound = |key: &str| {
+            bounds
+                .get(key)
+                .ok_or_else(|| parse_error(format!("{} of temporal label {} is missing", key, label)))
+                .and_then(parse_key)
+        };
+        schema.add_edge_label(label, bound("valid_from")?, bound("valid_to")?);
+    }
+    Ok(schema)
+}
+
+/// The label id as the key of a json object.
+fn parse_label_key(label: &str) -> FnGenResult<LabelId> {
+    label
+        .parse()
+        .map_err(|_| parse_error(format!("invalid label id {}", label)))
+}
+
+fn parse_labels(labels: &Value) -> FnGenResult<Vec<LabelId>> {
+    as_array(labels)?
+        .iter()
+        .map(|label| {
+            label
+                .as_i64()
+                .map(|id| id as LabelId)
+                .ok_or_else(|| parse_error(format!("invalid label id {}", label)))
+        })
+        .collect()
+}
+
+fn parse_keys(keys: &Value) -> FnGenResult<Vec<NameOrId>> {
+    as_array(keys)?.iter().map(parse_key).collect()
+}
+
+/// The property of the name, or of the id.
+fn parse_key(key: &Value) -> FnGenResult<NameOrId> {
+    match key {
+        Value::String(name) => Ok(NameOrId::Str(name.clone())),
+        Value::Number(id) if id.is_i64() => Ok(NameOrId::Id(id.as_i64().unwrap() as i32)),
+        _ => Err(parse_error(format!("invalid property key {}", key))),
+    }
+}
+
+fn parse_expr(expr: &Value) -> FnGenResult<common_pb::Expression> {
+    let expr = expr
+        .as_str()
+        .ok_or_else(|| parse_error(format!("invalid expression {}", expr)))?;
+    str_to_expr_pb(expr.to_string())
+        .map_err(|e| parse_error(format!("parse expression {} failed: {:?}", expr, e)))
+}
+
+fn as_array(value: &Value) -> FnGenResult<&Vec<Value>> {
+    value
+        .as_array()
+        .ok_or_else(|| parse_error(format!("{} is not a json array", value)))
+}
+
+fn as_object(value: &Value) -> FnGenResult<&Map<String, Value>> {
+    value
+        .as_object()
+        .ok_or_else(|| parse_error(format!("{} is not a json object", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::get_view;
+
+    use super::*;
+
+    #[test]
+    fn register_views_test() {
+        let path = std::env::temp_dir().join("gaia_graph_views_test.json");
+        let views = r#"{
+            "tenant": {
+                "vertex_labels": [0], "vertex_filter": "@.tenant == 1", "vertex_columns": ["name", 1]
+            },
+            "all": {}
+        }"#;
+        std::fs::write(&path, views).unwrap();
+        let mut options = HashMap::new();
+        options.insert("gaia.graph.views".to_string(), path.to_str().unwrap().to_string());
+        register_extensions(&options).unwrap();
+        assert!(get_view("tenant").is_some());
+        assert!(get_view("all").is_some());
+
+        std::fs::write(&path, r#"{"invalid": {"vertex_filter": "@.tenant =="}}"#).unwrap();
+        assert!(register_extensions(&options).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parse_derived_test() {
+        let derived = r#"{
+            "vertices": {"0": {"age": "2024 - @.birth_year", "adult": "2024 - @.birth_year >= 18"}},
+            "edges": {"1": {"years": "2024 - @.since"}}
+        }"#;
+        let derived: Map<String, Value> = serde_json::from_str(derived).unwrap();
+        assert!(!parse_derived(&derived).unwrap().is_empty());
+
+        let invalid: Map<String, Value> =
+            serde_json::from_str(r#"{"vertices": {"person": {"age": "2024 - @.birth_year"}}}"#).unwrap();
+        assert!(parse_derived(&invalid).is_err());
+        let invalid: Map<String, Value> =
+            serde_json::from_str(r#"{"vertices": {"0": {"age": "2024 -"}}}"#).unwrap();
+        assert!(parse_derived(&invalid).is_err());
+    }
+
+    #[test]
+    fn parse_soft_delete_test() {
+        let mut options = HashMap::new();
+        assert!(parse_soft_delete(&options).unwrap().is_none());
+        options.insert("gaia.soft.delete.property".to_string(), "deleted_at".to_string());
+        let soft_delete = parse_soft_delete(&options).unwrap().unwrap();
+        assert_eq!(soft_delete.property, NameOrId::Str("deleted_at".to_string()));
+        assert_eq!(soft_delete.retention, Duration::from_millis(DEFAULT_SOFT_DELETE_RETENTION_MS));
+        options.insert("gaia.soft.delete.retention.ms".to_string(), "1000".to_string());
+        let soft_delete = parse_soft_delete(&options).unwrap().unwrap();
+        assert_eq!(soft_delete.retention, Duration::from_millis(1000));
+        options.insert("gaia.soft.delete.retention.ms".to_string(), "1s".to_string());
+        assert!(parse_soft_delete(&options).is_err());
+    }
+
+    #[test]
+    fn parse_standing_test() {
+        let query = r#"{
+            "vertices": [0], "filter": "@.age > 27", "expand": {"direction": "out", "labels": [1]},
+            "columns": ["~label", "weight"], "aggregate": {"keys": 1, "function": "sum", "column": 1}
+        }"#;
+        assert!(parse_standing(&serde_json::from_str(query).unwrap()).is_ok());
+        let query = r#"{"edges": [1], "columns": ["~id"], "aggregate": {"function": "count"}}"#;
+        assert!(parse_standing(&serde_json::from_str(query).unwrap()).is_ok());
+
+        let invalid = r#"{"vertices": [0], "edges": [1]}"#;
+        assert!(parse_standing(&serde_json::from_str(invalid).unwrap()).is_err());
+        let invalid = r#"{"vertices": [0], "expand": {"direction": "up"}}"#;
+        assert!(parse_standing(&serde_json::from_str(invalid).unwrap()).is_err());
+        let invalid = r#"{"vertices": [0], "aggregate": {"function": "sum"}}"#;
+        assert!(parse_standing(&serde_json::from_str(invalid).unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_trigger_test() {
+        let path = std::env::temp_dir().join("gaia_trigger_plan_test.pb");
+        std::fs::write(&path, pb::PhysicalPlan::default().encode_to_vec()).unwrap();
+        let trigger = format!(r#"{{"vertices": [0], "events": ["insert"], "plan": {:?}}}"#, path);
+        assert!(parse_trigger(&serde_json::from_str(&trigger).unwrap()).is_ok());
+
+        let invalid = format!(r#"{{"vertices": [0], "events": ["read"], "plan": {:?}}}"#, path);
+        assert!(parse_trigger(&serde_json::from_str(&invalid).unwrap()).is_err());
+        let invalid = r#"{"edges": [1], "events": ["update"]}"#;
+        assert!(parse_trigger(&serde_json::from_str(invalid).unwrap()).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parse_temporal_test() {
+        let temporal = r#"{
+            "1": {"valid_from": "start_date", "valid_to": "end_date"},
+            "2": {"valid_from": 0, "valid_to": 1}
+        }"#;
+        let temporal: Map<String, Value> = serde_json::from_str(temporal).unwrap();
+        assert!(!parse_temporal(&temporal).unwrap().is_empty());
+
+        let invalid: Map<String, Value> =
+            serde_json::from_str(r#"{"1": {"valid_from": "start_date"}}"#).unwrap();
+        assert!(parse_temporal(&invalid).is_err());
+    }
+}
@@ -17,9 +17,24 @@ pub use assembly::IRJobAssembly;
 use router::Router;
 
 pub mod assembly;
+pub mod audit;
+pub mod auth;
 pub mod error;
+pub mod explain;
+pub mod extension;
+pub mod lint;
+pub mod matching;
+pub mod procedure;
 pub mod process;
+pub mod provenance;
+pub mod refine;
 pub mod router;
+pub mod row_filter;
+pub mod session;
+pub mod simplify;
+pub mod standing;
+pub mod trigger;
+pub mod validate;
 
 #[macro_use]
 extern crate dyn_type;
@@ -30,13 +45,14 @@ use std::sync::Arc;
 
 use graph_proxy::apis::cluster_info::ClusterInfo;
 use graph_proxy::apis::partitioner::PartitionInfo;
-use graph_proxy::apis::{register_graph, ReadGraph};
+use graph_proxy::apis::{register_graph, register_statistics, ReadGraph};
 
 /// Initialize a job assembly with the given graph, partition info and cluster info.
 /// IRJobAssembly provides a `DefaultRouter`, which is a default implementation of `Router` that can be used in most distributed environment.
 pub fn initialize_job_assembly<G: ReadGraph + 'static, P: PartitionInfo, C: ClusterInfo>(
     graph: Arc<G>, partition_info: Arc<P>, cluster_info: Arc<C>,
 ) -> IRJobAssembly<P, C> {
+    register_graph_statistics(graph.as_ref());
     register_graph(graph);
     let job_assembly = IRJobAssembly::with(partition_info, cluster_info);
     job_assembly
@@ -46,7 +62,17 @@ pub fn initialize_job_assembly<G: ReadGraph + 'static, P: PartitionInfo, C: Clus
 pub fn initialize_job_assembly_with_router<G: ReadGraph + 'static, P: PartitionInfo, C: ClusterInfo>(
     graph: Arc<G>, router: Arc<dyn Router<P = P, C = C>>,
 ) -> IRJobAssembly<P, C> {
+    register_graph_statistics(graph.as_ref());
     register_graph(graph);
     let job_assembly = IRJobAssembly::new(router);
     job_assembly
 }
+
+/// Register the statistics collected from the graph, if any, by which the plans are refined.
+fn register_graph_statistics<G: ReadGraph>(graph: &G) {
+    match graph.collect_statistics() {
+        Ok(Some(statistics)) => register_statistics(statistics),
+        Ok(None) => {}
+        Err(e) => warn!("collect the statistics of the graph error: {:?}", e),
+    }
+}
@@ -0,0 +1,178 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Lint the plans for the admission of the server, see `pegasus_server::admission`.
+
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+
+/// Describe the scans reading all the vertices or edges of their labels, i.e., the scans without any
+/// predicate, index predicate or limit, which are neither counting only nor followed by a limit or an
+/// aggregation, e.g., `count()` or `group()`, in their plans or the plans enclosing them.
+pub fn lint_plan(plan: &pb::PhysicalPlan) -> Vec<String> {
+    let mut scans = vec![];
+    lint_scans(plan, false, &mut scans);
+    scans
+}
+
+fn lint_scans(plan: &pb::PhysicalPlan, bounded: bool, scans: &mut Vec<String>) {
+    let op_kinds: Vec<Option<&OpKind>> = plan
+        .plan
+        .iter()
+        .map(|opr| {
+            opr.opr
+                .as_ref()
+                .and_then(|o| o.op_kind.as_ref())
+        })
+        .collect();
+    for (i, op_kind) in op_kinds.iter().enumerate() {
+        let bounded = bounded
+            || op_kinds[i + 1..]
+                .iter()
+                .any(|op| is_limit(*op) || is_aggregation(*op));
+        match op_kind {
+            Some(OpKind::Scan(scan)) if !bounded => {
+                let params = scan.params.as_ref();
+                let filtered = params.map_or(false, |p| p.predicate.is_some() || p.limit.is_some())
+                    || scan
+                        .idx_predicate
+                        .as_ref()
+                        .map_or(false, |p| !p.or_predicates.is_empty());
+                if !filtered && !scan.is_count_only {
+                    scans.push(describe_scan(params));
+                }
+            }
+            Some(OpKind::Apply(apply)) => {
+                if let Some(sub_plan) = apply.sub_plan.as_ref() {
+                    lint_scans(sub_plan, bounded, scans);
+                }
+            }
+            Some(OpKind::Join(join)) => {
+                for sub_plan in join
+                    .left_plan
+                    .iter()
+                    .chain(join.right_plan.iter())
+                {
+                    lint_scans(sub_plan, bounded, scans);
+                }
+            }
+            Some(OpKind::Union(union)) => {
+                for sub_plan in union.sub_plans.iter() {
+                    lint_scans(sub_plan, bounded, scans);
+                }
+            }
+            Some(OpKind::Intersect(intersect)) => {
+                for sub_plan in intersect.sub_plans.iter() {
+                    lint_scans(sub_plan, bounded, scans);
+                }
+            }
+            Some(OpKind::Repeat(repeat)) => {
+                if let Some(body) = repeat.body.as_ref() {
+                    lint_scans(body, bounded, scans);
+                }
+            }
+            Some(OpKind::Coalesce(coalesce)) => {
+                for sub_plan in coalesce.sub_plans.iter() {
+                    lint_scans(sub_plan, bounded, scans);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_limit(op_kind: Option<&OpKind>) -> bool {
+    match op_kind {
+        Some(OpKind::Limit(_)) => true,
+        Some(OpKind::OrderBy(order)) => order.limit.is_some(),
+        _ => false,
+    }
+}
+
+/// Whether the operator aggregates its inputs, whose results are then as many as the groups at most.
+fn is_aggregation(op_kind: Option<&OpKind>) -> bool {
+    match op_kind {
+        Some(OpKind::GroupBy(_)) => true,
+        _ => false,
+    }
+}
+
+fn describe_scan(params: Option<&algebra_pb::QueryParams>) -> String {
+    let labels: Vec<String> = params
+        .map(|p| p.tables.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|table| match table.item.as_ref() {
+            Some(common_pb::name_or_id::Item::Name(name)) => Some(name.clone()),
+            Some(common_pb::name_or_id::Item::Id(id)) => Some(id.to_string()),
+            None => None,
+        })
+        .collect();
+    if labels.is_empty() {
+        "scan of all labels".to_owned()
+    } else {
+        format!("scan of labels [{}]", labels.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+
+    fn scan(predicate: Option<&str>, labels: Vec<i32>) -> pb::PhysicalOpr {
+        pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: Some(algebra_pb::QueryParams {
+                tables: labels.into_iter().map(|l| l.into()).collect(),
+                predicate: predicate.and_then(|p| str_to_expr_pb(p.to_string()).ok()),
+                sample_ratio: 1.0,
+                ..Default::default()
+            }),
+            idx_predicate: None,
+            is_count_only: false,
+        }
+        .into()
+    }
+
+    fn sink() -> pb::PhysicalOpr {
+        pb::PhysicalOpr::from(OpKind::Sink(pb::Sink::default()))
+    }
+
+    #[test]
+    fn lint_plan_test() {
+        let plan = pb::PhysicalPlan { plan_id: 1, plan: vec![scan(None, vec![]), sink()] };
+        assert_eq!(lint_plan(&plan), vec!["scan of all labels".to_owned()]);
+        let plan = pb::PhysicalPlan { plan_id: 1, plan: vec![scan(None, vec![0, 1]), sink()] };
+        assert_eq!(lint_plan(&plan), vec!["scan of labels [0, 1]".to_owned()]);
+        let plan = pb::PhysicalPlan { plan_id: 1, plan: vec![scan(Some("@.age > 10"), vec![0]), sink()] };
+        assert!(lint_plan(&plan).is_empty());
+    }
+
+    #[test]
+    fn lint_aggregation_test() {
+        let count = pb::PhysicalOpr::from(OpKind::GroupBy(pb::GroupBy::default()));
+        let plan = pb::PhysicalPlan { plan_id: 1, plan: vec![scan(None, vec![0]), count, sink()] };
+        assert!(lint_plan(&plan).is_empty());
+        let limit = algebra_pb::Limit { range: Some(algebra_pb::Range { lower: 0, upper: 10 }) };
+        let limit = pb::PhysicalOpr::from(OpKind::Limit(limit));
+        let plan = pb::PhysicalPlan { plan_id: 1, plan: vec![scan(None, vec![0]), limit, sink()] };
+        assert!(lint_plan(&plan).is_empty());
+    }
+}
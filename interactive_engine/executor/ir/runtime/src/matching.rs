@@ -0,0 +1,436 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Plan the matches of the patterns declared as the triples, see `pb::PatternMatch`, while the plan is
+//! assembled, into the scans and the expands of the triples in the order chosen by the statistics of the
+//! graph, see `GraphStatistics`:
+//!   1. the pattern is matched from the vertices bound by the input records, or, as the source of the
+//!      plan, from the scan of the vertices of the least estimated count;
+//!   2. the triples of both the vertices bound are matched first, as they only filter the records, and
+//!      then the triples of a vertex bound in the ascending order of the estimated degrees of the edges
+//!      expanded from the vertex.
+//!
+//! The vertices and the triples are matched in the order they're declared if the statistics are missing.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::convert::TryInto;
+
+use graph_proxy::apis::{Direction, GraphStatistics};
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+
+use crate::error::{FnGenError, FnGenResult};
+use crate::procedure::sub_plans_mut;
+use crate::refine::labels_of;
+
+/// Plan the matches of the plan and its sub-plans, each of which is replaced by its planned operators.
+/// A pattern without the bound vertices is only planned as the source of the root plan, i.e., after the
+/// dummy roots only, as the scan in the middle of a plan would re-scan the graph per input record.
+pub fn plan_matches(
+    plan: &mut pb::PhysicalPlan, statistics: Option<&GraphStatistics>, is_root: bool,
+) -> FnGenResult<()> {
+    let mut planned = Vec::with_capacity(plan.plan.len());
+    let mut is_source = is_root;
+    for mut opr in plan.plan.drain(..) {
+        let mut op_kind: OpKind = (&opr).try_into()?;
+        if let OpKind::PatternMatch(pattern) = op_kind {
+            planned.extend(plan_match(pattern, statistics, is_source)?);
+            is_source = false;
+            continue;
+        }
+        is_source &= matches!(op_kind, OpKind::Root(_));
+        for sub_plan in sub_plans_mut(&mut op_kind) {
+            plan_matches(sub_plan, statistics, false)?;
+        }
+        opr.opr = Some(pb::physical_opr::Operator { op_kind: Some(op_kind) });
+        planned.push(opr);
+    }
+    plan.plan = planned;
+    Ok(())
+}
+
+fn plan_match(
+    pattern: pb::PatternMatch, statistics: Option<&GraphStatistics>, is_source: bool,
+) -> FnGenResult<Vec<pb::PhysicalOpr>> {
+    let params_of = |tag: i32| {
+        pattern
+            .vertices
+            .iter()
+            .find(|vertex| vertex.tag == tag)
+            .and_then(|vertex| vertex.params.clone())
+    };
+    let mut planned = vec![];
+    let mut bound = HashSet::new();
+    if pattern.bound_tags.is_empty() {
+        if !is_source {
+            Err(FnGenError::unsupported_error(
+                "the bound vertices are missing of the pattern not matched as the source",
+            ))?
+        }
+        let start = pattern
+            .vertices
+            .iter()
+            .min_by(|v1, v2| compare_estimated(vertex_count(v1, statistics), vertex_count(v2, statistics)))
+            .ok_or_else(|| FnGenError::unsupported_error("the vertices of the pattern are missing"))?;
+        planned.push(
+            pb::Scan {
+                scan_opt: pb::scan::ScanOpt::Vertex as i32,
+                alias: Some(start.tag),
+                params: start.params.clone(),
+                idx_predicate: None,
+                is_count_only: false,
+            }
+            .into(),
+        );
+        bound.insert(start.tag);
+    } else {
+        for tag in pattern.bound_tags.iter() {
+            if let Some(params) = params_of(*tag) {
+                planned.push(shuffle(Some(*tag)));
+                planned.push(filter_vertex(Some(*tag), params));
+            }
+            bound.insert(*tag);
+        }
+    }
+
+    let mut triples = pattern.triples.clone();
+    while !triples.is_empty() {
+        let index = next_triple(&triples, &bound, statistics).ok_or_else(|| {
+            FnGenError::unsupported_error(&format!(
+                "the triples {:?} are disconnected from the bound",
+                triples
+            ))
+        })?;
+        let triple = triples.remove(index);
+        let (from, to, direction) = if bound.contains(&triple.src) {
+            (triple.src, triple.dst, triple.direction)
+        } else {
+            (triple.dst, triple.src, reverse(triple.direction))
+        };
+        planned.push(shuffle(Some(from)));
+        planned.push(
+            pb::EdgeExpand {
+                v_tag: Some(from),
+                direction,
+                params: triple.params,
+                alias: triple.alias,
+                expand_opt: pb::edge_expand::ExpandOpt::Edge as i32,
+                is_optional: false,
+            }
+            .into(),
+        );
+        if bound.contains(&to) {
+            // the triple closes a cycle of the pattern, where the vertex is bound already
+            planned.push(get_other(None));
+            let select = algebra_pb::Select { predicate: Some(is_vertex_of(to)) };
+            planned.push(OpKind::Select(select).into());
+        } else {
+            planned.push(get_other(Some(to)));
+            if let Some(params) = params_of(to) {
+                planned.push(shuffle(None));
+                planned.push(filter_vertex(None, params));
+            }
+            bound.insert(to);
+        }
+    }
+    if let Some(vertex) = pattern
+        .vertices
+        .iter()
+        .find(|vertex| !bound.contains(&vertex.tag))
+    {
+        Err(FnGenError::unsupported_error(&format!(
+            "the vertex {:?} is disconnected from the bound",
+            vertex
+        )))?
+    }
+
+    let binding = |tag: i32, name: &str| {
+        let key = if name.is_empty() { tag.into() } else { name.to_string().into() };
+        common_pb::VariableKeyValue { key: Some(key), value: Some(var(Some(tag), None)) }
+    };
+    let key_vals = pattern
+        .vertices
+        .iter()
+        .map(|vertex| binding(vertex.tag, &vertex.name))
+        .chain(pattern.triples.iter().filter_map(|triple| {
+            triple
+                .alias
+                .map(|alias| binding(alias, &triple.name))
+        }))
+        .collect();
+    let project = pb::Project {
+        mappings: vec![pb::project::ExprAlias {
+            expr: Some(common_pb::Expression {
+                operators: vec![common_pb::ExprOpr {
+                    node_type: None,
+                    item: Some(common_pb::expr_opr::Item::Map(common_pb::VariableKeyValues { key_vals })),
+                }],
+            }),
+            alias: pattern.alias,
+        }],
+        is_append: true,
+        select_tags: false,
+    };
+    planned.push(OpKind::Project(project).into());
+    Ok(planned)
+}
+
+/// The triple to match next, which is of a vertex bound at least, or `None` if no such triple.
+fn next_triple(
+    triples: &[pb::pattern_match::Triple], bound: &HashSet<i32>, statistics: Option<&GraphStatistics>,
+) -> Option<usize> {
+    if let Some(index) = triples
+        .iter()
+        .position(|triple| bound.contains(&triple.src) && bound.contains(&triple.dst))
+    {
+        return Some(index);
+    }
+    triples
+        .iter()
+        .enumerate()
+        .filter(|(_, triple)| bound.contains(&triple.src) || bound.contains(&triple.dst))
+        .min_by(|(_, t1), (_, t2)| {
+            compare_estimated(expand_degree(t1, bound, statistics), expand_degree(t2, bound, statistics))
+        })
+        .map(|(index, _)| index)
+}
+
+/// Compare the estimations, where the unknown ones are the largest, and the equal ones are kept in the
+/// order they're declared, as `min_by` returns the first of the minimums.
+fn compare_estimated(e1: Option<f64>, e2: Option<f64>) -> Ordering {
+    let e1 = e1.unwrap_or(f64::INFINITY);
+    let e2 = e2.unwrap_or(f64::INFINITY);
+    e1.partial_cmp(&e2).unwrap_or(Ordering::Equal)
+}
+
+fn vertex_count(vertex: &pb::pattern_match::Vertex, statistics: Option<&GraphStatistics>) -> Option<f64> {
+    let labels = labels_of(vertex.params.as_ref())?;
+    statistics?
+        .vertex_count(&labels)
+        .map(|count| count as f64)
+}
+
+/// The average degree of the edges of the triple expanded from the vertex bound.
+fn expand_degree(
+    triple: &pb::pattern_match::Triple, bound: &HashSet<i32>, statistics: Option<&GraphStatistics>,
+) -> Option<f64> {
+    let labels = labels_of(triple.params.as_ref())?;
+    let direction = if bound.contains(&triple.src) { triple.direction } else { reverse(triple.direction) };
+    let direction = pb::edge_expand::Direction::from_i32(direction)?;
+    statistics?.avg_degree(&labels, Direction::from(direction))
+}
+
+fn reverse(direction: i32) -> i32 {
+    match pb::edge_expand::Direction::from_i32(direction) {
+        Some(pb::edge_expand::Direction::Out) => pb::edge_expand::Direction::In as i32,
+        Some(pb::edge_expand::Direction::In) => pb::edge_expand::Direction::Out as i32,
+        _ => direction,
+    }
+}
+
+fn shuffle(shuffle_key: Option<i32>) -> pb::PhysicalOpr {
+    pb::Repartition {
+        strategy: Some(pb::repartition::Strategy::ToAnother(pb::repartition::Shuffle { shuffle_key })),
+    }
+    .into()
+}
+
+/// Get the other vertex of the edge expanded.
+fn get_other(alias: Option<i32>) -> pb::PhysicalOpr {
+    pb::GetV { tag: None, opt: pb::get_v::VOpt::Other as i32, params: None, alias }.into()
+}
+
+/// Filter the vertex of the tag by the labels and the predicate.
+fn filter_vertex(tag: Option<i32>, params: algebra_pb::QueryParams) -> pb::PhysicalOpr {
+    pb::GetV { tag, opt: pb::get_v::VOpt::Itself as i32, params: Some(params), alias: tag }.into()
+}
+
+fn var(tag: Option<i32>, property: Option<common_pb::property::Item>) -> common_pb::Variable {
+    common_pb::Variable {
+        tag: tag.map(|tag| tag.into()),
+        property: property.map(|item| common_pb::Property { item: Some(item) }),
+        node_type: None,
+    }
+}
+
+/// The predicate that the head is the vertex of the tag, i.e., `@.~id == @tag.~id`.
+fn is_vertex_of(tag: i32) -> common_pb::Expression {
+    let id = || Some(common_pb::property::Item::Id(common_pb::IdKey {}));
+    let opr = |item| common_pb::ExprOpr { node_type: None, item: Some(item) };
+    common_pb::Expression {
+        operators: vec![
+            opr(common_pb::expr_opr::Item::Var(var(None, id()))),
+            opr(common_pb::expr_opr::Item::Logical(common_pb::Logical::Eq as i32)),
+            opr(common_pb::expr_opr::Item::Var(var(Some(tag), id()))),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use graph_proxy::apis::DegreeHistogram;
+
+    use super::*;
+
+    const PERSON: i32 = 0;
+    const SOFTWARE: i32 = 1;
+    const KNOWS: i32 = 0;
+    const CREATED: i32 = 1;
+
+    fn labels(label: i32) -> Option<algebra_pb::QueryParams> {
+        Some(algebra_pb::QueryParams { tables: vec![label.into()], ..Default::default() })
+    }
+
+    fn vertex(tag: i32, name: &str, label: i32) -> pb::pattern_match::Vertex {
+        pb::pattern_match::Vertex { tag, name: name.to_string(), params: labels(label) }
+    }
+
+    fn triple(src: i32, dst: i32, label: i32) -> pb::pattern_match::Triple {
+        pb::pattern_match::Triple {
+            src,
+            dst,
+            direction: pb::edge_expand::Direction::Out as i32,
+            params: labels(label),
+            alias: None,
+            name: String::new(),
+        }
+    }
+
+    // match(as('a').out('knows').as('b'), as('a').out('created').as('c'), as('b').out('created').as('c'))
+    fn pattern() -> pb::PatternMatch {
+        pb::PatternMatch {
+            vertices: vec![vertex(0, "a", PERSON), vertex(1, "b", PERSON), vertex(2, "c", SOFTWARE)],
+            triples: vec![triple(0, 1, KNOWS), triple(0, 2, CREATED), triple(1, 2, CREATED)],
+            bound_tags: vec![],
+            alias: None,
+        }
+    }
+
+    // the scans and expands of the planned, as the tags scanned and the pairs of the tags expanded
+    fn describe(planned: &[pb::PhysicalOpr]) -> (Vec<i32>, Vec<(i32, i32)>) {
+        let (mut scanned, mut expanded) = (vec![], vec![]);
+        let mut from = None;
+        for opr in planned {
+            match OpKind::try_from(opr).unwrap() {
+                OpKind::Scan(scan) => scanned.push(scan.alias.unwrap()),
+                OpKind::Edge(expand) => from = expand.v_tag,
+                OpKind::Vertex(get_v) if get_v.opt == pb::get_v::VOpt::Other as i32 => {
+                    expanded.push((from.unwrap(), get_v.alias.unwrap_or(-1)))
+                }
+                _ => {}
+            }
+        }
+        (scanned, expanded)
+    }
+
+    #[test]
+    fn plan_match_declared_order_test() {
+        let planned = plan_match(pattern(), None, true).unwrap();
+        // the closing triple of `b` and `c` is matched by the filter
+        assert_eq!(describe(&planned), (vec![0], vec![(0, 1), (0, 2), (1, -1)]));
+        assert!(planned
+            .iter()
+            .any(|opr| matches!(OpKind::try_from(opr).unwrap(), OpKind::Select(_))));
+        match OpKind::try_from(planned.last().unwrap()).unwrap() {
+            OpKind::Project(project) => assert!(project.is_append),
+            op_kind => panic!("should project the binding maps, but {:?}", op_kind),
+        }
+    }
+
+    #[test]
+    fn plan_match_statistics_test() {
+        let mut statistics = GraphStatistics::new();
+        statistics.add_vertex_count(PERSON as _, 1000);
+        statistics.add_vertex_count(SOFTWARE as _, 10);
+        statistics.add_degrees(KNOWS as _, Direction::Out, DegreeHistogram::new(vec![(100, 10)]));
+        statistics.add_degrees(CREATED as _, Direction::In, DegreeHistogram::new(vec![(5, 10)]));
+        let planned = plan_match(pattern(), Some(&statistics), true).unwrap();
+        // from the fewest software `c`, along the created edges of the fewer degrees to `a` and `b`, and
+        // then the knows edges close the pattern
+        assert_eq!(describe(&planned), (vec![2], vec![(2, 0), (2, 1), (0, -1)]));
+    }
+
+    #[test]
+    fn plan_match_bound_test() {
+        let mut pattern = pattern();
+        pattern.bound_tags = vec![1];
+        let planned = plan_match(pattern, None, true).unwrap();
+        assert_eq!(describe(&planned), (vec![], vec![(1, 0), (0, 2), (1, -1)]));
+
+        let mut pattern = self::pattern();
+        pattern.vertices.push(vertex(3, "d", PERSON));
+        assert!(plan_match(pattern, None, true).is_err());
+    }
+
+    #[test]
+    fn plan_matches_source_test() {
+        let plan = |mut oprs: Vec<pb::PhysicalOpr>| {
+            oprs.push(OpKind::PatternMatch(pattern()).into());
+            pb::PhysicalPlan { plan: oprs, plan_id: 0 }
+        };
+        let mut source = plan(vec![OpKind::Root(pb::Root {}).into()]);
+        assert!(plan_matches(&mut source, None, true).is_ok());
+        assert!(source
+            .plan
+            .iter()
+            .any(|opr| matches!(OpKind::try_from(opr).unwrap(), OpKind::Scan(_))));
+        // the pattern without the bound vertices would re-scan the graph per input record
+        let scan = pb::Scan { scan_opt: pb::scan::ScanOpt::Vertex as i32, ..Default::default() };
+        assert!(plan_matches(&mut plan(vec![scan.into()]), None, true).is_err());
+        assert!(plan_matches(&mut plan(vec![]), None, false).is_err());
+    }
+
+    #[test]
+    fn plan_match_edge_alias_test() {
+        let mut pattern = pattern();
+        pattern.triples[0].alias = Some(3);
+        pattern.triples[0].name = "e".to_string();
+        pattern.triples[1].alias = Some(4);
+        let planned = plan_match(pattern, None, true).unwrap();
+        let aliases: Vec<Option<i32>> = planned
+            .iter()
+            .filter_map(|opr| match OpKind::try_from(opr).unwrap() {
+                OpKind::Edge(expand) => Some(expand.alias),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(aliases, vec![Some(3), Some(4), None]);
+        let key_vals = match OpKind::try_from(planned.last().unwrap()).unwrap() {
+            OpKind::Project(project) => match project.mappings[0]
+                .expr
+                .as_ref()
+                .unwrap()
+                .operators[0]
+                .item
+                .clone()
+            {
+                Some(common_pb::expr_opr::Item::Map(map)) => map.key_vals,
+                item => panic!("should project the binding maps, but {:?}", item),
+            },
+            op_kind => panic!("should project the binding maps, but {:?}", op_kind),
+        };
+        let keys: Vec<common_pb::Value> = key_vals
+            .into_iter()
+            .map(|key_val| key_val.key.unwrap())
+            .collect();
+        let name = |name: &str| common_pb::Value::from(name.to_string());
+        assert_eq!(keys, vec![name("a"), name("b"), name("c"), name("e"), 4.into()]);
+    }
+}
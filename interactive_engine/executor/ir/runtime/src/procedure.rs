@@ -0,0 +1,382 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Stored procedures: the procedures installed by the admins in the registry of the process, which
+//! are called by name, e.g., `CALL proc(args)`, as the `Call` operator of a plan. A procedure is
+//! either:
+//!   1. a physical plan, whose dynamic params are bound to the arguments of the call by their
+//!      indices, and which is installed in place of the `Call`;
+//!   2. a native procedure, which installs its own operators over the record stream.
+//! The records output by the procedure flow to the following operators of the calling plan.
+//!
+//! A procedure runs with the privileges of the admin installing it, i.e., the access policy admits
+//! the calling plan only, while the properties of the records are still masked.
+
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::sync::{Arc, RwLock};
+
+use dyn_type::Object;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+use pegasus::stream::Stream;
+use pegasus::BuildJobError;
+use pegasus_server::admin::ProcedureAdmin;
+use prost::Message;
+
+use crate::error::{FnGenError, FnGenResult};
+use crate::process::record::Record;
+
+/// A procedure implemented in Rust.
+pub trait Procedure: Send + Sync + 'static {
+    /// Install the operators of the procedure over the input stream, with the arguments of the call.
+    fn install(&self, input: Stream<Record>, args: Vec<Object>) -> Result<Stream<Record>, BuildJobError>;
+}
+
+#[derive(Clone)]
+pub enum StoredProcedure {
+    /// A physical plan with dynamic params, which ends without a `Sink`
+    Plan(pb::PhysicalPlan),
+    Native(Arc<dyn Procedure>),
+}
+
+/// The stored procedures in the process.
+#[derive(Default)]
+pub struct ProcedureRegistry {
+    procedures: RwLock<HashMap<String, StoredProcedure>>,
+}
+
+impl ProcedureRegistry {
+    /// Install the plan as the procedure of the name, replacing the one installed before.
+    pub fn install_plan(&self, name: &str, plan: pb::PhysicalPlan) -> FnGenResult<()> {
+        validate_plan(name, plan.clone())?;
+        self.install(name, StoredProcedure::Plan(plan))
+    }
+
+    /// Install the native procedure of the name, replacing the one installed before.
+    pub fn install_native(&self, name: &str, procedure: Arc<dyn Procedure>) -> FnGenResult<()> {
+        self.install(name, StoredProcedure::Native(procedure))
+    }
+
+    /// Uninstall the procedure of the name, returning whether it is found.
+    pub fn uninstall(&self, name: &str) -> FnGenResult<bool> {
+        let mut procedures = self
+            .procedures
+            .write()
+            .map_err(|e| FnGenError::procedure_error(&format!("{:?}", e)))?;
+        Ok(procedures.remove(name).is_some())
+    }
+
+    pub fn get(&self, name: &str) -> FnGenResult<Option<StoredProcedure>> {
+        let procedures = self
+            .procedures
+            .read()
+            .map_err(|e| FnGenError::procedure_error(&format!("{:?}", e)))?;
+        Ok(procedures.get(name).cloned())
+    }
+
+    fn install(&self, name: &str, procedure: StoredProcedure) -> FnGenResult<()> {
+        if name.is_empty() {
+            Err(FnGenError::procedure_error("empty name of procedure"))?
+        }
+        let mut procedures = self
+            .procedures
+            .write()
+            .map_err(|e| FnGenError::procedure_error(&format!("{:?}", e)))?;
+        procedures.insert(name.to_string(), procedure);
+        Ok(())
+    }
+}
+
+/// The procedures installed and uninstalled by the admins on `/admin/procedures` of the metrics server.
+impl ProcedureAdmin for ProcedureRegistry {
+    fn install(&self, name: &str, plan: &[u8]) -> Result<(), String> {
+        let plan = pb::PhysicalPlan::decode(plan).map_err(|e| format!("invalid plan: {}", e))?;
+        self.install_plan(name, plan)
+            .map_err(|e| e.to_string())
+    }
+
+    fn uninstall(&self, name: &str) -> Result<bool, String> {
+        ProcedureRegistry::uninstall(self, name).map_err(|e| e.to_string())
+    }
+}
+
+/// A plan procedure is installed in place of the calls, so it should neither sink the records, nor
+/// call any procedure, which may call itself back.
+fn validate_plan(name: &str, plan: pb::PhysicalPlan) -> FnGenResult<()> {
+    for opr in plan.plan.iter() {
+        let mut op_kind: OpKind = opr.try_into()?;
+        match op_kind {
+            OpKind::Sink(_) => {
+                Err(FnGenError::procedure_error(&format!("procedure `{}` has a sink", name)))?
+            }
+            OpKind::Call(_) => {
+                Err(FnGenError::procedure_error(&format!("procedure `{}` calls a procedure", name)))?
+            }
+            _ => {
+                for sub_plan in sub_plans_mut(&mut op_kind) {
+                    validate_plan(name, std::mem::take(sub_plan))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bind the dynamic params in the predicates, the projections and the path conditions of the plan,
+/// including those in the subplans, to the arguments of their indices.
+pub fn bind_params(plan: &mut pb::PhysicalPlan, args: &[common_pb::Value]) -> FnGenResult<()> {
+    bind_params_with(plan, &|param| get_arg(param, args))
+}
+
+/// Bind the dynamic params of the plan to the values of their names, e.g., the `bindings` of a
+/// request of the Gremlin drivers.
+pub fn bind_named_params(
+    plan: &mut pb::PhysicalPlan, bindings: &HashMap<String, common_pb::Value>,
+) -> FnGenResult<()> {
+    bind_params_with(plan, &|param| {
+        bindings
+            .get(&param.name)
+            .cloned()
+            .ok_or_else(|| {
+                FnGenError::procedure_error(&format!("param `{}` is not bound in the request", param.name))
+            })
+    })
+}
+
+/// The value bound to a dynamic param.
+type ArgOf<'a> = &'a dyn Fn(&common_pb::DynamicParam) -> FnGenResult<common_pb::Value>;
+
+fn bind_params_with(plan: &mut pb::PhysicalPlan, arg: ArgOf) -> FnGenResult<()> {
+    for opr in plan.plan.iter_mut() {
+        let mut op_kind: OpKind = (&*opr).try_into()?;
+        match &mut op_kind {
+            OpKind::Scan(scan) => {
+                bind_query_params(scan.params.as_mut(), arg)?;
+                if let Some(idx_predicate) = scan.idx_predicate.as_mut() {
+                    for triplet in idx_predicate
+                        .or_predicates
+                        .iter_mut()
+                        .flat_map(|and_predicate| and_predicate.predicates.iter_mut())
+                    {
+                        if let Some(algebra_pb::index_predicate::triplet::Value::Param(param)) =
+                            triplet.value.as_ref()
+                        {
+                            let value = arg(param)?;
+                            triplet.value = Some(algebra_pb::index_predicate::triplet::Value::Const(value));
+                        }
+                    }
+                }
+            }
+            OpKind::Edge(expand) => bind_query_params(expand.params.as_mut(), arg)?,
+            OpKind::Vertex(get_v) => bind_query_params(get_v.params.as_mut(), arg)?,
+            OpKind::Path(path) => {
+                if let Some(base) = path.base.as_mut() {
+                    if let Some(expand) = base.edge_expand.as_mut() {
+                        bind_query_params(expand.params.as_mut(), arg)?;
+                    }
+                    if let Some(get_v) = base.get_v.as_mut() {
+                        bind_query_params(get_v.params.as_mut(), arg)?;
+                    }
+                }
+                if let Some(condition) = path.condition.as_mut() {
+                    bind_expr(condition, arg)?;
+                }
+            }
+            OpKind::Repeat(repeat) => {
+                if let Some(until) = repeat.until.as_mut() {
+                    bind_expr(until, arg)?;
+                }
+                if let Some(body) = repeat.body.as_mut() {
+                    bind_params_with(body, arg)?;
+                }
+            }
+            OpKind::Select(select) => {
+                if let Some(predicate) = select.predicate.as_mut() {
+                    bind_expr(predicate, arg)?;
+                }
+            }
+            OpKind::Project(project) => {
+                for mapping in project.mappings.iter_mut() {
+                    if let Some(expr) = mapping.expr.as_mut() {
+                        bind_expr(expr, arg)?;
+                    }
+                }
+            }
+            _ => {
+                for sub_plan in sub_plans_mut(&mut op_kind) {
+                    bind_params_with(sub_plan, arg)?;
+                }
+            }
+        }
+        opr.opr = Some(pb::physical_opr::Operator { op_kind: Some(op_kind) });
+    }
+    Ok(())
+}
+
+pub(crate) fn sub_plans_mut(op_kind: &mut OpKind) -> Vec<&mut pb::PhysicalPlan> {
+    match op_kind {
+        OpKind::Apply(apply) => apply.sub_plan.iter_mut().collect(),
+        OpKind::Join(join) => join
+            .left_plan
+            .iter_mut()
+            .chain(join.right_plan.iter_mut())
+            .collect(),
+        OpKind::Union(union) => union.sub_plans.iter_mut().collect(),
+        OpKind::Intersect(intersect) => intersect.sub_plans.iter_mut().collect(),
+        OpKind::Repeat(repeat) => repeat.body.iter_mut().collect(),
+        OpKind::Coalesce(coalesce) => coalesce.sub_plans.iter_mut().collect(),
+        _ => vec![],
+    }
+}
+
+fn bind_query_params(params: Option<&mut algebra_pb::QueryParams>, arg: ArgOf) -> FnGenResult<()> {
+    if let Some(predicate) = params.and_then(|params| params.predicate.as_mut()) {
+        bind_expr(predicate, arg)?;
+    }
+    Ok(())
+}
+
+fn bind_expr(expr: &mut common_pb::Expression, arg: ArgOf) -> FnGenResult<()> {
+    for opr in expr.operators.iter_mut() {
+        if let Some(common_pb::expr_opr::Item::Param(param)) = opr.item.as_ref() {
+            let value = arg(param)?;
+            opr.item = Some(common_pb::expr_opr::Item::Const(value));
+        }
+    }
+    Ok(())
+}
+
+fn get_arg(param: &common_pb::DynamicParam, args: &[common_pb::Value]) -> FnGenResult<common_pb::Value> {
+    usize::try_from(param.index)
+        .ok()
+        .and_then(|index| args.get(index))
+        .cloned()
+        .ok_or_else(|| {
+            FnGenError::procedure_error(&format!(
+                "argument {} of param `{}` is not given in {} arguments",
+                param.index,
+                param.name,
+                args.len()
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+
+    fn param(index: i32) -> common_pb::ExprOpr {
+        let param = common_pb::DynamicParam { name: format!("p{}", index), index, data_type: None };
+        common_pb::ExprOpr { node_type: None, item: Some(common_pb::expr_opr::Item::Param(param)) }
+    }
+
+    // @.name == $0
+    fn select_by_param() -> pb::PhysicalOpr {
+        let mut predicate = str_to_expr_pb("@.name == ".to_string()).unwrap();
+        predicate.operators.push(param(0));
+        OpKind::Select(algebra_pb::Select { predicate: Some(predicate) }).into()
+    }
+
+    fn bound_arg(opr: &pb::PhysicalOpr) -> Option<common_pb::expr_opr::Item> {
+        match opr.try_into().unwrap() {
+            OpKind::Select(select) => {
+                select
+                    .predicate
+                    .unwrap()
+                    .operators
+                    .pop()
+                    .unwrap()
+                    .item
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn bind_params_test() {
+        let sub_plan = pb::PhysicalPlan { plan_id: 0, plan: vec![select_by_param()] };
+        let apply = pb::Apply { join_kind: 0, keys: vec![], sub_plan: Some(sub_plan), alias: None };
+        let mut plan =
+            pb::PhysicalPlan { plan_id: 0, plan: vec![select_by_param(), OpKind::Apply(apply).into()] };
+        let arg: common_pb::Value = "marko".to_string().into();
+        bind_params(&mut plan, &[arg.clone()]).unwrap();
+
+        let expected = Some(common_pb::expr_opr::Item::Const(arg));
+        assert_eq!(bound_arg(&plan.plan[0]), expected);
+        match (&plan.plan[1]).try_into().unwrap() {
+            OpKind::Apply(apply) => assert_eq!(bound_arg(&apply.sub_plan.unwrap().plan[0]), expected),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn bind_params_missing_arg_test() {
+        let mut plan = pb::PhysicalPlan { plan_id: 0, plan: vec![select_by_param()] };
+        assert!(bind_params(&mut plan, &[]).is_err());
+    }
+
+    #[test]
+    fn bind_named_params_test() {
+        let mut plan = pb::PhysicalPlan { plan_id: 0, plan: vec![select_by_param()] };
+        let arg: common_pb::Value = "marko".to_string().into();
+        let mut bindings = HashMap::new();
+        bindings.insert("p1".to_string(), arg.clone());
+        // bound by the name `p0` rather than the index
+        assert!(bind_named_params(&mut plan, &bindings).is_err());
+        bindings.insert("p0".to_string(), arg.clone());
+        bind_named_params(&mut plan, &bindings).unwrap();
+        assert_eq!(bound_arg(&plan.plan[0]), Some(common_pb::expr_opr::Item::Const(arg)));
+    }
+
+    #[test]
+    fn install_plan_test() {
+        let registry = ProcedureRegistry::default();
+        let plan = pb::PhysicalPlan { plan_id: 0, plan: vec![select_by_param()] };
+        registry
+            .install_plan("by_name", plan.clone())
+            .unwrap();
+        assert!(matches!(registry.get("by_name").unwrap(), Some(StoredProcedure::Plan(p)) if p == plan));
+        assert!(registry.get("by_id").unwrap().is_none());
+
+        // procedures calling procedures are rejected
+        let call = algebra_pb::Call { name: "by_name".to_string(), args: vec![] };
+        let recursive = pb::PhysicalPlan { plan_id: 0, plan: vec![OpKind::Call(call).into()] };
+        assert!(registry
+            .install_plan("recursive", recursive)
+            .is_err());
+
+        assert!(registry.uninstall("by_name").unwrap());
+        assert!(registry.get("by_name").unwrap().is_none());
+    }
+
+    #[test]
+    fn procedure_admin_test() {
+        let registry = ProcedureRegistry::default();
+        let admin: &dyn ProcedureAdmin = &registry;
+        let plan = pb::PhysicalPlan { plan_id: 0, plan: vec![select_by_param()] };
+        admin
+            .install("by_name", &plan.encode_to_vec())
+            .unwrap();
+        assert!(matches!(registry.get("by_name").unwrap(), Some(StoredProcedure::Plan(p)) if p == plan));
+        assert!(admin.install("invalid", &[0xff]).is_err());
+        assert!(admin.uninstall("by_name").unwrap());
+        assert!(!admin.uninstall("by_name").unwrap());
+    }
+}
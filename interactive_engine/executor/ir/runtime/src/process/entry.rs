@@ -29,12 +29,16 @@ use graph_proxy::apis::{Edge, Element, GraphElement, GraphPath, PropertyValue, V
 use ir_common::error::ParsePbError;
 use ir_common::generated::results as result_pb;
 use ir_common::NameOrId;
-use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+use pegasus::codec::{protocol_version, Decode, Encode, ReadExt, WriteExt};
 use pegasus_common::downcast::*;
 use pegasus_common::impl_as_any;
 
 use crate::process::operator::map::{GeneralIntersectionEntry, IntersectionEntry};
 
+/// The protocol version of the servers decoding a `MapEntry`, which is sent to the servers of older
+/// versions as the collection of its pairs.
+const MAP_PROTOCOL_VERSION: u32 = 4;
+
 #[derive(Debug, PartialEq)]
 pub enum EntryType {
     /// Graph Vertex
@@ -51,6 +55,9 @@ pub enum EntryType {
     Intersection,
     /// Type of collection consisting of entries
     Collection,
+    /// Type of map consisting of the pairs of entries, which is kept as a map when nested in another,
+    /// e.g., the subtrees of the trees of `tree()`
+    Map,
 }
 
 pub trait Entry: Debug + Send + Sync + AsAny + Element {
@@ -131,6 +138,9 @@ impl Entry for DynEntry {
     }
 }
 
+// The encoding is shuffled between the executors, which may be of different versions during a rolling
+// upgrade. Any change of it must keep the decoding of the old fixtures in `resource/entry`, and encode
+// as the old one for the executors of an older protocol, see `pegasus::codec::protocol_version`.
 impl Encode for DynEntry {
     fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
         let entry_type = self.get_type();
@@ -184,6 +194,25 @@ impl Encode for DynEntry {
                     .unwrap()
                     .write_to(writer)?;
             }
+            EntryType::Map => {
+                let map = self
+                    .inner
+                    .as_any_ref()
+                    .downcast_ref::<MapEntry>()
+                    .unwrap();
+                if protocol_version() < MAP_PROTOCOL_VERSION {
+                    // encoded as the collection of the pairs
+                    writer.write_u8(6)?;
+                    writer.write_u32(map.inner.len() as u32)?;
+                    for pair in map.inner.iter() {
+                        writer.write_u8(7)?;
+                        pair.write_to(writer)?;
+                    }
+                } else {
+                    writer.write_u8(9)?;
+                    map.write_to(writer)?;
+                }
+            }
         }
         Ok(())
     }
@@ -225,6 +254,10 @@ impl Decode for DynEntry {
                 let general_intersect = GeneralIntersectionEntry::read_from(reader)?;
                 Ok(DynEntry::new(general_intersect))
             }
+            9 => {
+                let map = MapEntry::read_from(reader)?;
+                Ok(DynEntry::new(map))
+            }
             _ => unreachable!(),
         }
     }
@@ -306,6 +339,10 @@ impl Hash for DynEntry {
                 .as_any_ref()
                 .downcast_ref::<PairEntry>()
                 .hash(state),
+            EntryType::Map => self
+                .as_any_ref()
+                .downcast_ref::<MapEntry>()
+                .hash(state),
         }
     }
 }
@@ -335,6 +372,10 @@ impl PartialEq for DynEntry {
                     .as_any_ref()
                     .downcast_ref::<PairEntry>()
                     .eq(&other.as_any_ref().downcast_ref::<PairEntry>()),
+                EntryType::Map => self
+                    .as_any_ref()
+                    .downcast_ref::<MapEntry>()
+                    .eq(&other.as_any_ref().downcast_ref::<MapEntry>()),
             }
         } else {
             false
@@ -373,6 +414,10 @@ impl PartialOrd for DynEntry {
                     .as_any_ref()
                     .downcast_ref::<PairEntry>()
                     .partial_cmp(&other.as_any_ref().downcast_ref::<PairEntry>()),
+                EntryType::Map => self
+                    .as_any_ref()
+                    .downcast_ref::<MapEntry>()
+                    .partial_cmp(&other.as_any_ref().downcast_ref::<MapEntry>()),
             }
         } else {
             None
@@ -548,6 +593,48 @@ impl Decode for CollectionEntry {
     }
 }
 
+/// The map of the pairs of entries in the order they're added, which is sent to the client as a map,
+/// with the maps nested as its values, e.g., the trees of `tree()`.
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd, Eq, Hash)]
+pub struct MapEntry {
+    pub inner: Vec<PairEntry>,
+}
+
+impl_as_any!(MapEntry);
+
+impl Entry for MapEntry {
+    fn get_type(&self) -> EntryType {
+        EntryType::Map
+    }
+}
+
+impl Element for MapEntry {
+    fn as_graph_element(&self) -> Option<&dyn GraphElement> {
+        None
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn as_borrow_object(&self) -> BorrowObject {
+        BorrowObject::None
+    }
+}
+
+impl Encode for MapEntry {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.inner.write_to(writer)
+    }
+}
+
+impl Decode for MapEntry {
+    fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
+        let inner = <Vec<PairEntry>>::read_from(reader)?;
+        Ok(MapEntry { inner })
+    }
+}
+
 impl TryFrom<result_pb::Element> for DynEntry {
     type Error = ParsePbError;
     fn try_from(e: result_pb::Element) -> Result<Self, Self::Error> {
@@ -593,6 +680,15 @@ impl TryFrom<result_pb::Entry> for DynEntry {
                     };
                     Ok(DynEntry::new(collection))
                 }
+                result_pb::entry::Inner::Map(kv)
+                    if kv
+                        .key_values
+                        .iter()
+                        .any(|key_val| key_val.element_key.is_some() || key_val.nested_value.is_some()) =>
+                {
+                    // e.g., the trees
+                    Ok(DynEntry::new(key_values_to_map(kv)?))
+                }
                 result_pb::entry::Inner::Map(kv) => {
                     let mut map = BTreeMap::new();
                     for key_val in kv.key_values {
@@ -618,6 +714,24 @@ impl TryFrom<result_pb::Entry> for DynEntry {
     }
 }
 
+fn key_values_to_map(kv: result_pb::KeyValues) -> Result<MapEntry, ParsePbError> {
+    let mut inner = Vec::with_capacity(kv.key_values.len());
+    for key_val in kv.key_values {
+        let key = match (key_val.element_key, key_val.key) {
+            (Some(element), _) => element.try_into()?,
+            (None, Some(key)) => DynEntry::new(Object::try_from(key)?),
+            (None, None) => Err(ParsePbError::EmptyFieldError("key of the map is empty".to_string()))?,
+        };
+        let value = match (key_val.nested_value, key_val.value) {
+            (Some(nested), _) => DynEntry::new(key_values_to_map(nested)?),
+            (None, Some(value)) => value.try_into()?,
+            (None, None) => Err(ParsePbError::EmptyFieldError("value of the map is empty".to_string()))?,
+        };
+        inner.push(PairEntry::new(key, value));
+    }
+    Ok(MapEntry { inner })
+}
+
 impl From<Vertex> for DynEntry {
     fn from(v: Vertex) -> Self {
         DynEntry::new(v)
@@ -678,3 +792,140 @@ impl From<PairEntry> for DynEntry {
         DynEntry::new(p)
     }
 }
+
+impl From<MapEntry> for DynEntry {
+    fn from(m: MapEntry) -> Self {
+        DynEntry::new(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use dyn_type::object;
+    use graph_proxy::apis::DynDetails;
+
+    use super::*;
+
+    /// The version of the fixtures of the current encoding. Once the encoding of any entry changes, the
+    /// version is bumped with the new fixtures in a new directory, while the old ones are kept.
+    const FIXTURE_VERSION: u32 = 2;
+    /// The version of the fixtures which the entries are encoded as for the executors of each protocol
+    /// version, see `pegasus::codec::PROTOCOL_VERSION`; an older protocol maps to the old fixtures.
+    const PROTOCOL_FIXTURE_VERSIONS: [(u32, u32); 4] = [(1, 1), (2, 1), (3, 1), (4, 2)];
+
+    fn fixture_dir(version: u32) -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("resource");
+        path.push("entry");
+        path.push(format!("v{}", version));
+        path
+    }
+
+    fn vertex(id: ID) -> Vertex {
+        if id == 1 {
+            let details = vec![(NameOrId::from("name"), object!("marko"))];
+            Vertex::new(id, Some(1), DynDetails::new(details.into_iter().collect::<HashMap<_, _>>()))
+        } else {
+            Vertex::new(id, Some(1), DynDetails::default())
+        }
+    }
+
+    fn edge() -> Edge {
+        let details = vec![(NameOrId::from(3), object!(0.5))];
+        Edge::new(7, Some(0), 1, 2, DynDetails::new(details.into_iter().collect::<HashMap<_, _>>()))
+    }
+
+    // the details are of at most one property, as the order of the properties is not stable
+    fn fixtures() -> Vec<(&'static str, DynEntry)> {
+        let kv = vec![(object!("k"), object!(1.5))];
+        vec![
+            ("vertex", vertex(1).into()),
+            ("edge", edge().into()),
+            ("path", GraphPath::AllPath(vec![vertex(1).into(), edge().into(), vertex(2).into()]).into()),
+            ("path_end", GraphPath::SimpleEndV((vertex(2).into(), vec![1, 2], 2)).into()),
+            ("object", Object::Vector(vec![object!(1), object!(2_i64), object!("a"), Object::None]).into()),
+            ("kv", Object::KV(kv.into_iter().collect()).into()),
+            ("intersection", IntersectionEntry::from_iter(vec![3, 1, 3].into_iter()).into()),
+            ("collection", vec![DynEntry::from(object!(1)), vertex(2).into()].into()),
+            ("pair", PairEntry::new(vertex(1).into(), object!("b").into()).into()),
+            ("general_intersection", GeneralIntersectionEntry::from_iter(vec![2, 2].into_iter()).into()),
+            ("map", tree()),
+        ]
+    }
+
+    // the tree of a vertex without any child
+    fn tree() -> DynEntry {
+        MapEntry { inner: vec![PairEntry::new(vertex(1).into(), MapEntry::default().into())] }.into()
+    }
+
+    fn encode(entry: &DynEntry) -> Vec<u8> {
+        let mut bytes = vec![];
+        entry.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn entry_encode_stability_test() {
+        let dir = fixture_dir(FIXTURE_VERSION);
+        for (name, entry) in fixtures() {
+            let path = dir.join(format!("{}.bin", name));
+            let expected =
+                std::fs::read(&path).unwrap_or_else(|e| panic!("read fixture {:?} error: {}", path, e));
+            assert_eq!(encode(&entry), expected, "the encoding of {} is changed", name);
+        }
+    }
+
+    #[test]
+    fn entry_encode_for_protocol_test() {
+        let latest = PROTOCOL_FIXTURE_VERSIONS.last().unwrap();
+        assert_eq!(*latest, (pegasus::codec::PROTOCOL_VERSION, FIXTURE_VERSION));
+        for (protocol_version, version) in PROTOCOL_FIXTURE_VERSIONS.iter() {
+            if *protocol_version < pegasus::codec::MIN_PROTOCOL_VERSION {
+                continue;
+            }
+            let dir = fixture_dir(*version);
+            for (name, entry) in fixtures() {
+                let path = dir.join(format!("{}.bin", name));
+                if let Ok(expected) = std::fs::read(&path) {
+                    let bytes = pegasus::codec::with_protocol_version(*protocol_version, || encode(&entry));
+                    assert_eq!(bytes, expected, "{} is not encoded as {:?}", name, path);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn map_encode_for_old_protocol_test() {
+        // the servers of older versions take the maps as the collections of the pairs
+        let bytes = pegasus::codec::with_protocol_version(MAP_PROTOCOL_VERSION - 1, || encode(&tree()));
+        let pairs =
+            vec![DynEntry::from(PairEntry::new(vertex(1).into(), CollectionEntry::default().into()))];
+        let collection: DynEntry = CollectionEntry { inner: pairs }.into();
+        assert_eq!(bytes, encode(&collection));
+        assert_eq!(DynEntry::read_from(&mut &bytes[..]).unwrap(), collection);
+    }
+
+    #[test]
+    fn entry_decode_fixtures_test() {
+        let expected = fixtures();
+        for version in 1..=FIXTURE_VERSION {
+            let dir = fixture_dir(version);
+            for (name, entry) in expected.iter() {
+                let path = dir.join(format!("{}.bin", name));
+                // the entry may be absent in the older versions
+                if let Ok(bytes) = std::fs::read(&path) {
+                    let mut reader = &bytes[..];
+                    let decoded = DynEntry::read_from(&mut reader)
+                        .unwrap_or_else(|e| panic!("decode fixture {:?} error: {}", path, e));
+                    assert!(reader.is_empty(), "fixture {:?} is not fully decoded", path);
+                    assert_eq!(decoded.get_type(), entry.get_type());
+                    assert_eq!(&decoded, entry, "fixture {:?}", path);
+                    // the decoded is sent to the executors of the current version again
+                    assert_eq!(encode(&decoded), encode(entry), "fixture {:?}", path);
+                }
+            }
+        }
+    }
+}
@@ -18,6 +18,8 @@ use std::convert::TryFrom;
 use std::ops::Div;
 
 use dyn_type::{Object, Primitives};
+use graph_proxy::apis::Element;
+use graph_proxy::utils::udf::{call_udf, get_udf};
 use ir_common::error::ParsePbError;
 use ir_common::generated::physical as pb;
 use ir_common::generated::physical::group_by::agg_func::Aggregate;
@@ -25,9 +27,10 @@ use ir_common::KeyId;
 use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
 
 use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
-use crate::process::entry::{CollectionEntry, DynEntry, Entry};
+use crate::process::entry::{CollectionEntry, DynEntry, Entry, PairEntry};
 use crate::process::operator::accum::accumulator::{
-    Accumulator, Count, DistinctCount, First, Maximum, Minimum, Sum, ToList, ToSet,
+    Accumulator, ApproxDistinctCount, Count, DistinctCount, First, HeavyHitters, Maximum, Minimum, Sum,
+    ToList, ToSet,
 };
 use crate::process::operator::accum::AccumFactoryGen;
 use crate::process::operator::TagKey;
@@ -44,8 +47,18 @@ pub enum EntryAccumulator {
     ToSum(Sum<Primitives>),
     ToAvg(Sum<Primitives>, Count<()>),
     ToFirst(First<DynEntry>),
+    ToApproxDistinctCount(ApproxDistinctCount<DynEntry>),
+    ToHeavyHitters(HeavyHitters<DynEntry>),
+    // the values of a group, and the name of the user defined function applied on them
+    ToUdf(ToList<DynEntry>, String),
 }
 
+/// The precision of `approx_count_distinct`, i.e., 2^14 registers with a standard error of 0.81%.
+const APPROX_COUNT_DISTINCT_PRECISION: u8 = 14;
+/// The number of the heavy hitters output, and the counters kept to find them.
+const HEAVY_HITTERS_TOP: usize = 10;
+const HEAVY_HITTERS_CAPACITY: usize = 1000;
+
 /// Accumulator for Record, including multiple accumulators for entries(columns) in Record.
 /// Notice that if the entry is a None-Entry (i.e., Object::None), it won't be accumulated.
 // TODO: if the none-entry counts, we may further need a flag to identify.
@@ -73,6 +86,95 @@ impl Accumulator<Record, Record> for RecordAccumulator {
     }
 }
 
+impl RecordAccumulator {
+    /// Merge the partial accumulation of the same aggregates, e.g., by another worker before the
+    /// exchange of a two-phase group, see `EntryAccumulator::merge`.
+    pub fn merge(&mut self, other: RecordAccumulator) -> FnExecResult<()> {
+        if self.accum_ops.len() != other.accum_ops.len() {
+            Err(FnExecError::accum_error(&format!(
+                "merge {} accumulators into {}",
+                other.accum_ops.len(),
+                self.accum_ops.len()
+            )))?
+        }
+        for ((accumulator, _, _), (other, _, _)) in self
+            .accum_ops
+            .iter_mut()
+            .zip(other.accum_ops.into_iter())
+        {
+            accumulator.merge(other)?;
+        }
+        Ok(())
+    }
+}
+
+impl EntryAccumulator {
+    /// Merge the partial accumulation of the same kind, as if the entries accumulated by it were
+    /// accumulated by this one, which every kind must support to be accumulated in two phases.
+    pub fn merge(&mut self, other: EntryAccumulator) -> FnExecResult<()> {
+        match (self, other) {
+            (EntryAccumulator::ToCount(count), EntryAccumulator::ToCount(other)) => {
+                count.value += other.value;
+            }
+            (EntryAccumulator::ToList(list), EntryAccumulator::ToList(other)) => {
+                list.inner.extend(other.inner);
+            }
+            (EntryAccumulator::ToMin(min), EntryAccumulator::ToMin(other)) => {
+                if let Some(other) = other.min {
+                    min.accum(other)?;
+                }
+            }
+            (EntryAccumulator::ToMax(max), EntryAccumulator::ToMax(other)) => {
+                if let Some(other) = other.max {
+                    max.accum(other)?;
+                }
+            }
+            (EntryAccumulator::ToSet(set), EntryAccumulator::ToSet(other)) => {
+                set.inner.extend(other.inner);
+            }
+            (
+                EntryAccumulator::ToDistinctCount(distinct_count),
+                EntryAccumulator::ToDistinctCount(other),
+            ) => {
+                distinct_count.inner.extend(other.inner);
+            }
+            (EntryAccumulator::ToSum(sum), EntryAccumulator::ToSum(other)) => {
+                if let Some(other) = other.seed {
+                    sum.accum(other)?;
+                }
+            }
+            (EntryAccumulator::ToAvg(sum, count), EntryAccumulator::ToAvg(other_sum, other_count)) => {
+                if let Some(other) = other_sum.seed {
+                    sum.accum(other)?;
+                }
+                count.value += other_count.value;
+            }
+            (EntryAccumulator::ToFirst(first), EntryAccumulator::ToFirst(other)) => {
+                if first.first.is_none() {
+                    first.first = other.first;
+                }
+            }
+            (
+                EntryAccumulator::ToApproxDistinctCount(count),
+                EntryAccumulator::ToApproxDistinctCount(other),
+            ) => {
+                count.merge(&other)?;
+            }
+            (EntryAccumulator::ToHeavyHitters(heavy_hitters), EntryAccumulator::ToHeavyHitters(other)) => {
+                heavy_hitters.merge(other);
+            }
+            (EntryAccumulator::ToUdf(list, _), EntryAccumulator::ToUdf(other, _)) => {
+                list.inner.extend(other.inner);
+            }
+            (accumulator, other) => Err(FnExecError::accum_error(&format!(
+                "merge accumulator {:?} into {:?}",
+                other, accumulator
+            )))?,
+        }
+        Ok(())
+    }
+}
+
 impl Accumulator<DynEntry, DynEntry> for EntryAccumulator {
     fn accum(&mut self, next: DynEntry) -> FnExecResult<()> {
         // ignore non-exist tag/label/property values;
@@ -116,6 +218,9 @@ impl Accumulator<DynEntry, DynEntry> for EntryAccumulator {
                     count.accum(())
                 }
                 EntryAccumulator::ToFirst(first) => first.accum(next),
+                EntryAccumulator::ToApproxDistinctCount(count) => count.accum(next),
+                EntryAccumulator::ToHeavyHitters(heavy_hitters) => heavy_hitters.accum(next),
+                EntryAccumulator::ToUdf(list, _) => list.accum(next),
             }
         } else {
             Ok(())
@@ -173,6 +278,36 @@ impl Accumulator<DynEntry, DynEntry> for EntryAccumulator {
             EntryAccumulator::ToFirst(first) => Ok(first
                 .finalize()?
                 .unwrap_or(DynEntry::new(Object::None))),
+            EntryAccumulator::ToApproxDistinctCount(count) => {
+                let cnt = count.finalize()?;
+                Ok(DynEntry::new(object!(cnt)))
+            }
+            EntryAccumulator::ToHeavyHitters(heavy_hitters) => {
+                // the pairs of the values and their counts, in the descending order of the counts
+                let pairs = heavy_hitters
+                    .finalize()?
+                    .into_iter()
+                    .map(|(entry, cnt)| DynEntry::new(PairEntry::new(entry, DynEntry::new(object!(cnt)))))
+                    .collect();
+                Ok(DynEntry::new(CollectionEntry { inner: pairs }))
+            }
+            EntryAccumulator::ToUdf(list, udf) => {
+                let mut values = vec![];
+                for entry in list.finalize()? {
+                    let value = if let Some(obj) = entry.as_object() {
+                        obj.clone()
+                    } else if let Some(element) = entry.as_graph_element() {
+                        object!(element.id())
+                    } else {
+                        Err(FnExecError::unsupported_error(&format!(
+                            "entry {:?} in the user defined aggregate {}",
+                            entry, udf
+                        )))?
+                    };
+                    values.push(value);
+                }
+                Ok(DynEntry::new(call_udf(udf, vec![Object::Vector(values)])?))
+            }
         }
     }
 }
@@ -215,6 +350,22 @@ impl AccumFactoryGen for pb::GroupBy {
                 Aggregate::Avg => {
                     EntryAccumulator::ToAvg(Sum { seed: None }, Count { value: 0, _ph: Default::default() })
                 }
+                Aggregate::ApproxCountDistinct => EntryAccumulator::ToApproxDistinctCount(
+                    ApproxDistinctCount::new(APPROX_COUNT_DISTINCT_PRECISION),
+                ),
+                Aggregate::HeavyHitters => EntryAccumulator::ToHeavyHitters(HeavyHitters::new(
+                    HEAVY_HITTERS_TOP,
+                    HEAVY_HITTERS_CAPACITY,
+                )),
+                Aggregate::UserDefined => {
+                    if get_udf(&agg_func.udf).is_none() {
+                        Err(FnGenError::unsupported_error(&format!(
+                            "user defined aggregate {} is not registered",
+                            agg_func.udf
+                        )))?
+                    }
+                    EntryAccumulator::ToUdf(ToList { inner: vec![] }, agg_func.udf.clone())
+                }
             };
             accum_ops.push((entry_accumulator, tag_key, agg_func.alias));
         }
@@ -265,6 +416,19 @@ impl Encode for EntryAccumulator {
                 writer.write_u8(8)?;
                 first.write_to(writer)?;
             }
+            EntryAccumulator::ToApproxDistinctCount(count) => {
+                writer.write_u8(9)?;
+                count.write_to(writer)?;
+            }
+            EntryAccumulator::ToHeavyHitters(heavy_hitters) => {
+                writer.write_u8(10)?;
+                heavy_hitters.write_to(writer)?;
+            }
+            EntryAccumulator::ToUdf(list, udf) => {
+                writer.write_u8(11)?;
+                list.write_to(writer)?;
+                udf.write_to(writer)?;
+            }
         }
         Ok(())
     }
@@ -311,6 +475,19 @@ impl Decode for EntryAccumulator {
                 let first = <First<DynEntry>>::read_from(reader)?;
                 Ok(EntryAccumulator::ToFirst(first))
             }
+            9 => {
+                let count = <ApproxDistinctCount<DynEntry>>::read_from(reader)?;
+                Ok(EntryAccumulator::ToApproxDistinctCount(count))
+            }
+            10 => {
+                let heavy_hitters = <HeavyHitters<DynEntry>>::read_from(reader)?;
+                Ok(EntryAccumulator::ToHeavyHitters(heavy_hitters))
+            }
+            11 => {
+                let list = <ToList<DynEntry>>::read_from(reader)?;
+                let udf = <String>::read_from(reader)?;
+                Ok(EntryAccumulator::ToUdf(list, udf))
+            }
             _ => Err(std::io::Error::new(std::io::ErrorKind::Other, "unreachable")),
         }
     }
@@ -348,16 +525,20 @@ mod tests {
     use std::cmp::Ordering;
 
     use dyn_type::Object;
+    use graph_proxy::utils::expr::ExprEvalResult;
     use ir_common::generated::common as common_pb;
     use ir_common::generated::physical as pb;
+    use ir_common::generated::physical::group_by::agg_func::Aggregate;
+    use ir_common::KeyId;
     use pegasus::api::{Fold, Sink};
+    use pegasus::codec::{Decode, Encode};
     use pegasus::result::ResultStream;
     use pegasus::JobConf;
     use pegasus_common::downcast::AsAny;
 
-    use crate::process::entry::{CollectionEntry, DynEntry, Entry};
+    use crate::process::entry::{CollectionEntry, DynEntry, Entry, PairEntry};
     use crate::process::operator::accum::accumulator::Accumulator;
-    use crate::process::operator::accum::AccumFactoryGen;
+    use crate::process::operator::accum::{AccumFactoryGen, RecordAccumulator};
     use crate::process::operator::tests::{init_source, init_vertex1, init_vertex2, TAG_A, TAG_B};
     use crate::process::record::Record;
 
@@ -393,6 +574,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 5, // to_list
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(init_source(), fold_opr_pb);
@@ -414,6 +596,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 5, // to_list
             alias: None,
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(init_source(), fold_opr_pb);
@@ -435,6 +618,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 3, // count
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(init_source(), fold_opr_pb);
@@ -454,11 +638,13 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 5, // to_list
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let function_2 = pb::group_by::AggFunc {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 3, // Count
             alias: Some(TAG_B.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function_1, function_2] };
         let mut result = fold_test(init_source(), fold_opr_pb);
@@ -484,6 +670,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 1, // min
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(vec![r1, r2], fold_opr_pb);
@@ -505,6 +692,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 2, // max
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(vec![r1, r2], fold_opr_pb);
@@ -531,6 +719,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 4, // distinct_count
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(vec![r1, r2, r3, r4], fold_opr_pb);
@@ -554,6 +743,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 6, // to_set
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(source, fold_opr_pb);
@@ -586,6 +776,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 0, // sum
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(vec![r1, r2, r3], fold_opr_pb);
@@ -608,6 +799,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 7, // avg
             alias: None,
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(vec![r1, r2, r3], fold_opr_pb);
@@ -626,6 +818,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate,
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(vec![r], fold_opr_pb);
@@ -648,6 +841,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@.addr".to_string())],
             aggregate,
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(vec![r1, r2], fold_opr_pb);
@@ -692,6 +886,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 8, // first
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = fold_test(init_source(), fold_opr_pb);
@@ -704,4 +899,170 @@ mod tests {
         }
         assert_eq!(fold_result, expected_result);
     }
+
+    // g.V().approx_count_distinct().as("a")
+    #[test]
+    fn approx_count_distinct_test() {
+        let v1 = init_vertex1();
+        let v2 = init_vertex2();
+        let r1 = Record::new(v1.clone(), None);
+        let r2 = Record::new(v2.clone(), None);
+        let r3 = Record::new(v1, None);
+        let r4 = Record::new(v2, None);
+
+        let function = pb::group_by::AggFunc {
+            vars: vec![common_pb::Variable::from("@".to_string())],
+            aggregate: 9, // approx_count_distinct
+            alias: Some(TAG_A.into()),
+            udf: String::new(),
+        };
+        let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
+        let mut result = fold_test(vec![r1, r2, r3, r4], fold_opr_pb);
+        let mut cnt = 0;
+        if let Some(Ok(record)) = result.next() {
+            if let Some(entry) = record.get(Some(TAG_A)) {
+                cnt = entry.as_object().unwrap().as_u64().unwrap();
+            }
+        }
+        assert_eq!(cnt, 2);
+    }
+
+    // g.V().heavy_hitters().as("a")
+    #[test]
+    fn heavy_hitters_test() {
+        let mut source = init_source();
+        source.push(Record::new(init_vertex1(), None));
+        source.push(Record::new(init_vertex1(), None));
+        let function = pb::group_by::AggFunc {
+            vars: vec![common_pb::Variable::from("@".to_string())],
+            aggregate: 10, // heavy_hitters
+            alias: Some(TAG_A.into()),
+            udf: String::new(),
+        };
+        let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
+        let mut result = fold_test(source, fold_opr_pb);
+        let mut fold_result = DynEntry::new(Object::None);
+        let expected_result = CollectionEntry {
+            inner: vec![
+                DynEntry::new(PairEntry::new(init_vertex1().into(), object!(3u64).into())),
+                DynEntry::new(PairEntry::new(init_vertex2().into(), object!(1u64).into())),
+            ],
+        };
+        if let Some(Ok(record)) = result.next() {
+            if let Some(entry) = record.get(Some(TAG_A)) {
+                fold_result = entry.clone();
+            }
+        }
+        assert_eq!(fold_result, DynEntry::new(expected_result));
+    }
+
+    // the median of the values by a user defined aggregate
+    #[test]
+    fn udf_test() {
+        graph_proxy::utils::udf::register_udf(
+            "udf_test_median",
+            std::sync::Arc::new(|args: Vec<Object>| -> ExprEvalResult<Object> {
+                let mut values = match &args[0] {
+                    Object::Vector(values) => values.clone(),
+                    _ => unreachable!(),
+                };
+                values.sort();
+                Ok(values[values.len() / 2].clone())
+            }),
+        );
+        let source = vec![
+            Record::new(object!(30), None),
+            Record::new(object!(10), None),
+            Record::new(object!(20), None),
+        ];
+        let function = pb::group_by::AggFunc {
+            vars: vec![common_pb::Variable::from("@".to_string())],
+            aggregate: 11, // user_defined
+            alias: Some(TAG_A.into()),
+            udf: "udf_test_median".to_string(),
+        };
+        let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
+        let mut result = fold_test(source, fold_opr_pb);
+        let mut res = Object::None;
+        if let Some(Ok(record)) = result.next() {
+            if let Some(entry) = record.get(Some(TAG_A)) {
+                res = entry.as_object().unwrap().clone();
+            }
+        }
+        assert_eq!(res, object!(20));
+
+        let function = pb::group_by::AggFunc {
+            vars: vec![],
+            aggregate: 11, // user_defined
+            alias: None,
+            udf: "udf_test_absent".to_string(),
+        };
+        let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
+        assert!(fold_opr_pb.gen_accum().is_err());
+    }
+
+    fn accum_of(aggregates: &[Aggregate]) -> RecordAccumulator {
+        let functions = aggregates
+            .iter()
+            .enumerate()
+            .map(|(i, aggregate)| pb::group_by::AggFunc {
+                vars: vec![common_pb::Variable::from("@".to_string())],
+                aggregate: *aggregate as i32,
+                alias: Some((i as KeyId).into()),
+                udf: String::new(),
+            })
+            .collect();
+        pb::GroupBy { mappings: vec![], functions }
+            .gen_accum()
+            .unwrap()
+    }
+
+    // the partial accumulations merged are the same as the accumulation of all the entries
+    #[test]
+    fn merge_test() {
+        let aggregates = vec![
+            Aggregate::Count,
+            Aggregate::ToList,
+            Aggregate::Min,
+            Aggregate::Max,
+            Aggregate::CountDistinct,
+            Aggregate::Sum,
+            Aggregate::Avg,
+            Aggregate::First,
+            Aggregate::ApproxCountDistinct,
+            Aggregate::HeavyHitters,
+        ];
+        let values = vec![20, 10, 30, 10, 20, 10];
+        let mut all = accum_of(&aggregates);
+        let mut left = accum_of(&aggregates);
+        let mut right = accum_of(&aggregates);
+        for (i, value) in values.into_iter().enumerate() {
+            all.accum(Record::new(object!(value), None))
+                .unwrap();
+            let partial = if i < 2 { &mut left } else { &mut right };
+            partial
+                .accum(Record::new(object!(value), None))
+                .unwrap();
+        }
+        // the partial accumulation is shuffled in the encoded form
+        let mut bytes = vec![];
+        right.write_to(&mut bytes).unwrap();
+        let right = RecordAccumulator::read_from(&mut &bytes[..]).unwrap();
+        left.merge(right).unwrap();
+        let expected = all.finalize().unwrap();
+        let merged = left.finalize().unwrap();
+        for i in 0..aggregates.len() {
+            let tag = Some(i as KeyId);
+            assert_eq!(merged.get(tag), expected.get(tag), "{:?}", aggregates[i]);
+        }
+
+        // the accumulations of different aggregates are not merged
+        let mut sum = accum_of(&[Aggregate::Sum]);
+        assert!(sum
+            .merge(accum_of(&[Aggregate::Count]))
+            .is_err());
+        assert!(sum
+            .merge(accum_of(&[Aggregate::Sum, Aggregate::Sum]))
+            .is_err());
+    }
 }
@@ -13,15 +13,16 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
 use std::io;
 use std::ops::Add;
 
 use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
 
-use crate::error::FnExecResult;
+use crate::error::{FnExecError, FnExecResult};
 
 pub trait Accumulator<I, O>: Send + Debug {
     fn accum(&mut self, next: I) -> FnExecResult<()>;
@@ -378,3 +379,344 @@ impl<D: Decode> Decode for First<D> {
         Ok(First { first })
     }
 }
+
+/// Approximate distinct count by HyperLogLog, which keeps `2^precision` registers of the maximal ranks
+/// of the hashed values instead of the values. The standard error is about `1.04 / sqrt(2^precision)`,
+/// e.g., 0.81% of the precision 14 in 16KB. The registers of the partial counts are merged by maximum.
+#[derive(Clone, Eq, PartialEq)]
+pub struct ApproxDistinctCount<D> {
+    pub precision: u8,
+    pub registers: Vec<u8>,
+    pub _ph: std::marker::PhantomData<D>,
+}
+
+impl<D> ApproxDistinctCount<D> {
+    pub fn new(precision: u8) -> Self {
+        ApproxDistinctCount { precision, registers: vec![0; 1 << precision], _ph: std::marker::PhantomData }
+    }
+
+    /// Merge the registers of another partial count, which must be of the same precision, as the
+    /// registers are indexed by the leading `precision` bits of the hashes.
+    pub fn merge(&mut self, other: &Self) -> FnExecResult<()> {
+        if self.precision != other.precision || self.registers.len() != other.registers.len() {
+            Err(FnExecError::accum_error(&format!(
+                "merge approximate distinct count of precision {} into precision {}",
+                other.precision, self.precision
+            )))?
+        }
+        for (register, other) in self
+            .registers
+            .iter_mut()
+            .zip(other.registers.iter())
+        {
+            if *register < *other {
+                *register = *other;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let mut sum = 0.0;
+        let mut zeros = 0;
+        for register in self.registers.iter() {
+            sum += 2f64.powi(-(*register as i32));
+            if *register == 0 {
+                zeros += 1;
+            }
+        }
+        let estimate = alpha * m * m / sum;
+        if estimate <= 2.5 * m && zeros > 0 {
+            // linear counting for the small cardinalities
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+impl<D> Debug for ApproxDistinctCount<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "approx_count_distinct={}", self.estimate())
+    }
+}
+
+impl<D: Hash + Send + 'static> Accumulator<D, u64> for ApproxDistinctCount<D> {
+    fn accum(&mut self, next: D) -> FnExecResult<()> {
+        // the hasher of fixed keys, so that the partial counts in any worker are mergeable
+        let mut hasher = DefaultHasher::new();
+        next.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - self.precision)) as usize;
+        // the rank of the first 1-bit of the rest bits, bounded by a sentinel bit
+        let rank = ((hash << self.precision) | (1 << (self.precision - 1))).leading_zeros() as u8 + 1;
+        if self.registers[index] < rank {
+            self.registers[index] = rank;
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> FnExecResult<u64> {
+        Ok(self.estimate())
+    }
+}
+
+impl<D> Encode for ApproxDistinctCount<D> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u8(self.precision)?;
+        self.registers.write_to(writer)?;
+        Ok(())
+    }
+}
+
+impl<D> Decode for ApproxDistinctCount<D> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let precision = reader.read_u8()?;
+        let registers = <Vec<u8>>::read_from(reader)?;
+        Ok(ApproxDistinctCount { precision, registers, _ph: std::marker::PhantomData })
+    }
+}
+
+/// Heavy hitters by the space-saving algorithm, which keeps at most `capacity` counters, and a new
+/// value replaces the value of the minimal count `c`, counted from `c + 1`. Any value more frequent
+/// than `n / capacity` of the `n` values is kept, and its count is overestimated by `n / capacity` at
+/// most. The counters of the partial heavy hitters are merged by sum, where a value missing from a
+/// full side is counted by the minimal count of that side, as it may have been replaced with that
+/// count at most, and the largest are kept.
+#[derive(Clone)]
+pub struct HeavyHitters<D: Eq + Hash> {
+    /// The number of the most frequent values to output.
+    pub top: usize,
+    pub capacity: usize,
+    pub counters: HashMap<D, u64>,
+}
+
+unsafe impl<D: Send + Eq + Hash> Send for HeavyHitters<D> {}
+
+impl<D: Eq + Hash> HeavyHitters<D> {
+    pub fn new(top: usize, capacity: usize) -> Self {
+        HeavyHitters { top, capacity: capacity.max(top), counters: HashMap::new() }
+    }
+
+    pub fn merge(&mut self, other: Self) {
+        let min_count = self.min_count();
+        let other_min_count = other.min_count();
+        for (data, count) in self.counters.iter_mut() {
+            if !other.counters.contains_key(data) {
+                *count += other_min_count;
+            }
+        }
+        for (data, count) in other.counters {
+            match self.counters.get_mut(&data) {
+                Some(counter) => *counter += count,
+                None => {
+                    self.counters.insert(data, count + min_count);
+                }
+            }
+        }
+        if self.counters.len() > self.capacity {
+            let mut counters: Vec<(D, u64)> = std::mem::take(&mut self.counters)
+                .into_iter()
+                .collect();
+            counters.sort_by(|(_, c1), (_, c2)| c2.cmp(c1));
+            counters.truncate(self.capacity);
+            self.counters = counters.into_iter().collect();
+        }
+    }
+
+    /// The count a value missing from the counters may have, i.e., the minimal count if the counters
+    /// are full, as a value is only replaced then, or 0 otherwise.
+    fn min_count(&self) -> u64 {
+        if self.counters.len() < self.capacity {
+            0
+        } else {
+            self.counters
+                .values()
+                .min()
+                .cloned()
+                .unwrap_or(0)
+        }
+    }
+}
+
+impl<D: Debug + Eq + Hash> Debug for HeavyHitters<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "heavy_hitters={:?}", self.counters)
+    }
+}
+
+impl<D: Debug + Clone + Eq + Hash + Send + 'static> Accumulator<D, Vec<(D, u64)>> for HeavyHitters<D> {
+    fn accum(&mut self, next: D) -> FnExecResult<()> {
+        if let Some(count) = self.counters.get_mut(&next) {
+            *count += 1;
+        } else if self.counters.len() < self.capacity {
+            self.counters.insert(next, 1);
+        } else {
+            let min = self
+                .counters
+                .iter()
+                .min_by_key(|(_, count)| **count)
+                .map(|(data, count)| (data.clone(), *count));
+            if let Some((data, count)) = min {
+                self.counters.remove(&data);
+                self.counters.insert(next, count + 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> FnExecResult<Vec<(D, u64)>> {
+        let mut result: Vec<(D, u64)> = std::mem::take(&mut self.counters)
+            .into_iter()
+            .collect();
+        result.sort_by(|(_, c1), (_, c2)| c2.cmp(c1));
+        result.truncate(self.top);
+        Ok(result)
+    }
+}
+
+impl<D: Encode + Eq + Hash> Encode for HeavyHitters<D> {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u64(self.top as u64)?;
+        writer.write_u64(self.capacity as u64)?;
+        writer.write_u32(self.counters.len() as u32)?;
+        for (data, count) in self.counters.iter() {
+            data.write_to(writer)?;
+            writer.write_u64(*count)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: Decode + Eq + Hash> Decode for HeavyHitters<D> {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let top = reader.read_u64()? as usize;
+        let capacity = reader.read_u64()? as usize;
+        let len = reader.read_u32()?;
+        let mut counters = HashMap::with_capacity(len as usize);
+        for _ in 0..len {
+            let data = <D>::read_from(reader)?;
+            let count = reader.read_u64()?;
+            counters.insert(data, count);
+        }
+        Ok(HeavyHitters { top, capacity, counters })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approx_distinct_count_test() {
+        let mut count = ApproxDistinctCount::<u64>::new(14);
+        for i in 0..100000u64 {
+            count.accum(i % 50000).unwrap();
+        }
+        let estimate = count.finalize().unwrap() as f64;
+        assert!((estimate - 50000.0).abs() / 50000.0 < 0.03);
+
+        let mut small = ApproxDistinctCount::<u64>::new(14);
+        for i in 0..10u64 {
+            small.accum(i).unwrap();
+        }
+        // exact in linear counting unless the values collide in a register
+        assert!((9..=10).contains(&small.finalize().unwrap()));
+    }
+
+    #[test]
+    fn approx_distinct_count_merge_test() {
+        let mut left = ApproxDistinctCount::<u64>::new(12);
+        let mut right = ApproxDistinctCount::<u64>::new(12);
+        for i in 0..20000u64 {
+            if i % 2 == 0 {
+                left.accum(i).unwrap();
+            } else {
+                right.accum(i).unwrap();
+            }
+            // the overlapped values
+            left.accum(i % 100).unwrap();
+        }
+        let mut bytes = vec![];
+        right.write_to(&mut bytes).unwrap();
+        let right = ApproxDistinctCount::<u64>::read_from(&mut bytes.as_slice()).unwrap();
+        left.merge(&right).unwrap();
+        let estimate = left.finalize().unwrap() as f64;
+        assert!((estimate - 20000.0).abs() / 20000.0 < 0.06);
+
+        // the registers of different precisions are not mergeable
+        let mut other = ApproxDistinctCount::<u64>::new(14);
+        assert!(other.merge(&right).is_err());
+    }
+
+    #[test]
+    fn heavy_hitters_test() {
+        let mut heavy_hitters = HeavyHitters::<u64>::new(2, 10);
+        // a skewed stream of 2 heavy values among many light ones
+        for i in 0..1000u64 {
+            let data = match i % 4 {
+                0 | 1 => 1,
+                2 => 2,
+                _ => 100 + i,
+            };
+            heavy_hitters.accum(data).unwrap();
+        }
+        let top = heavy_hitters.finalize().unwrap();
+        assert_eq!(
+            top.iter()
+                .map(|(data, _)| *data)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        // overestimated by n / capacity at most
+        assert!(top[0].1 >= 500 && top[0].1 <= 600);
+        assert!(top[1].1 >= 250 && top[1].1 <= 350);
+    }
+
+    #[test]
+    fn heavy_hitters_merge_test() {
+        let mut left = HeavyHitters::<u64>::new(1, 4);
+        let mut right = HeavyHitters::<u64>::new(1, 4);
+        for i in 0..100u64 {
+            left.accum(if i % 2 == 0 { 7 } else { i })
+                .unwrap();
+            right
+                .accum(if i % 3 == 0 { 7 } else { i + 1000 })
+                .unwrap();
+        }
+        let mut bytes = vec![];
+        right.write_to(&mut bytes).unwrap();
+        let right = HeavyHitters::<u64>::read_from(&mut bytes.as_slice()).unwrap();
+        left.merge(right);
+        assert_eq!(left.counters.len(), 4);
+        let top = left.finalize().unwrap();
+        assert_eq!(top[0].0, 7);
+        assert!(top[0].1 >= 84);
+
+        // a value missing from a full side is counted by the minimal count of that side
+        let mut left = HeavyHitters::<u64>::new(1, 3);
+        left.counters = vec![(1, 5), (2, 3), (4, 6)]
+            .into_iter()
+            .collect();
+        let mut right = HeavyHitters::<u64>::new(1, 3);
+        right.counters = vec![(1, 4), (3, 2), (5, 1)]
+            .into_iter()
+            .collect();
+        left.merge(right);
+        assert_eq!(
+            left.counters,
+            vec![(1, 9), (4, 7), (3, 5)]
+                .into_iter()
+                .collect()
+        );
+        // but not by a side which is not full
+        let mut left = HeavyHitters::<u64>::new(1, 2);
+        left.counters = vec![(1, 5), (2, 3)].into_iter().collect();
+        let mut right = HeavyHitters::<u64>::new(1, 2);
+        right.counters = vec![(1, 4)].into_iter().collect();
+        left.merge(right);
+        assert_eq!(left.counters, vec![(1, 9), (2, 3)].into_iter().collect());
+    }
+}
@@ -16,7 +16,7 @@
 mod accum;
 pub mod accumulator;
 pub mod sample;
-pub use accum::RecordAccumulator;
+pub use accum::{EntryAccumulator, RecordAccumulator};
 pub use sample::SampleAccum;
 
 use crate::error::FnGenResult;
@@ -13,6 +13,10 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use std::cmp::Ordering;
+use std::convert::TryInto;
+
+use dyn_type::Object;
 use ir_common::error::ParsePbError;
 use ir_common::generated::algebra as algebra_pb;
 use pegasus::api::function::DynIter;
@@ -20,13 +24,16 @@ use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
 use rand::prelude::StdRng;
 use rand::{Rng, SeedableRng};
 
-use crate::error::{FnExecResult, FnGenError, FnGenResult};
+use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
+use crate::process::entry::Entry;
 use crate::process::operator::accum::accumulator::Accumulator;
 use crate::process::operator::accum::SampleAccumFactoryGen;
+use crate::process::operator::{deterministic_seed, TagKey};
 use crate::process::record::Record;
 
 /// Sample accumulator, which will keep a sampled vector of records, with the specified sample number.
-/// Implemented via Reservoir Sampling.
+/// Implemented via Reservoir Sampling, or via A-Res Weighted Reservoir Sampling if the sample weight
+/// is given, which keeps the records of the largest keys `u^(1/w)` of a uniform random `u`.
 #[derive(Clone, Debug)]
 pub struct SampleAccum {
     accumulator: Vec<Record>,
@@ -34,10 +41,60 @@ pub struct SampleAccum {
     sample_num: usize,
     rng: StdRng,
     seed: Option<u64>,
+    weight: Option<TagKey>,
+    // the keys of the records sampled by weight
+    weight_keys: Vec<f64>,
+}
+
+impl SampleAccum {
+    /// Whether the records are sampled by weight, of which the sampled records of the partitions can't
+    /// be sampled again without their keys.
+    pub fn is_weighted(&self) -> bool {
+        self.weight.is_some()
+    }
+
+    fn get_weight(&self, weight: &TagKey, record: &Record) -> FnExecResult<f64> {
+        let entry = weight.get_arc_entry(record)?;
+        match entry.as_object() {
+            // the records without weights are never sampled
+            Some(Object::None) => Ok(0.0),
+            Some(obj) => obj.as_f64().map_err(|e| {
+                FnExecError::unexpected_data_error(&format!("invalid sample weight {:?}: {}", obj, e))
+            }),
+            None => Err(FnExecError::unexpected_data_error(&format!("invalid sample weight {:?}", entry))),
+        }
+    }
+
+    fn accum_weighted(&mut self, weight: f64, next: Record) {
+        if weight <= 0.0 {
+            return;
+        }
+        let key = self.rng.gen::<f64>().powf(1.0 / weight);
+        if self.accumulator.len() < self.sample_num {
+            self.accumulator.push(next);
+            self.weight_keys.push(key);
+        } else if let Some((index, min)) = self
+            .weight_keys
+            .iter()
+            .enumerate()
+            .min_by(|(_, x), (_, y)| x.partial_cmp(y).unwrap_or(Ordering::Equal))
+        {
+            if key > *min {
+                self.accumulator[index] = next;
+                self.weight_keys[index] = key;
+            }
+        }
+    }
 }
 
 impl Accumulator<Record, DynIter<Record>> for SampleAccum {
     fn accum(&mut self, next: Record) -> FnExecResult<()> {
+        if let Some(weight) = self.weight.as_ref() {
+            let weight = self.get_weight(weight, &next)?;
+            self.accum_weighted(weight, next);
+            self.count = self.count + 1;
+            return Ok(());
+        }
         if self.count < self.sample_num {
             self.accumulator.push(next);
         } else {
@@ -52,6 +109,7 @@ impl Accumulator<Record, DynIter<Record>> for SampleAccum {
 
     fn finalize(&mut self) -> FnExecResult<DynIter<Record>> {
         let collection = std::mem::replace(&mut self.accumulator, vec![]);
+        self.weight_keys.clear();
         self.count = 0;
         Ok(Box::new(collection.into_iter()))
     }
@@ -63,18 +121,24 @@ impl SampleAccumFactoryGen for algebra_pb::Sample {
             let sample_type = sample_type.inner.ok_or_else(|| {
                 FnGenError::ParseError(ParsePbError::EmptyFieldError("sample_type.inner".to_owned()))
             })?;
+            let seed = self.seed.or_else(deterministic_seed);
             match sample_type {
                 algebra_pb::sample::sample_type::Inner::SampleByNum(num) => {
                     let sample = SampleAccum {
                         sample_num: num.num as usize,
                         accumulator: Vec::with_capacity(num.num as usize),
                         count: 0,
-                        rng: if let Some(seed) = self.seed {
+                        rng: if let Some(seed) = seed {
                             StdRng::seed_from_u64(seed as u64)
                         } else {
                             StdRng::from_entropy()
                         },
-                        seed: self.seed.map(|s| s as u64),
+                        seed: seed.map(|s| s as u64),
+                        weight: self
+                            .sample_weight
+                            .map(|weight| weight.try_into())
+                            .transpose()?,
+                        weight_keys: vec![],
                     };
                     if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
                         debug!("Runtime sample operator: {:?}", sample);
@@ -97,6 +161,8 @@ impl Encode for SampleAccum {
         writer.write_u64(self.count as u64)?;
         writer.write_u64(self.sample_num as u64)?;
         self.seed.write_to(writer)?;
+        self.weight.write_to(writer)?;
+        self.weight_keys.write_to(writer)?;
         Ok(())
     }
 }
@@ -107,7 +173,9 @@ impl Decode for SampleAccum {
         let count = reader.read_u64()? as usize;
         let sample_num = reader.read_u64()? as usize;
         let seed = Option::<u64>::read_from(reader)?;
+        let weight = Option::<TagKey>::read_from(reader)?;
+        let weight_keys = <Vec<f64>>::read_from(reader)?;
         let rng = if let Some(seed) = seed { StdRng::seed_from_u64(seed) } else { StdRng::from_entropy() };
-        Ok(SampleAccum { accumulator, count, sample_num, rng, seed })
+        Ok(SampleAccum { accumulator, count, sample_num, rng, seed, weight, weight_keys })
     }
 }
@@ -0,0 +1,427 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The adjacency explored by the iterative graph algorithms, which is cached per store node and shared
+//! by the workers and the jobs of the node reading the same data: the neighbors of a vertex are read
+//! from the graph once, in the first iteration exploring it, and from the cache in the iterations
+//! after, rather than by the lookups of its edges in the store. The neighbors are kept per vertex
+//! explored, as they're read, rather than in a CSR of all the vertices.
+//!
+//! A snapshot of the adjacency is of the labels, the direction, the filter and the weight of the
+//! edges explored, and of the scope of the job reading them, i.e. the graph registered, the view, the
+//! deleted elements included, and the caller with its property mask and row filters, see
+//! `bind_read_scope`. It's at the snapshot of the graph read, see `current_snapshot`, and invalidated
+//! once the graph read advances to a newer snapshot, while the reads of an older snapshot bypass it.
+//! The graph not versioned may change between the jobs, so that its adjacency is of the job reading
+//! it, and dropped with the job.
+//!
+//! The snapshots not read for `IDLE_TIMEOUT` are evicted, and so are the least recently read ones
+//! over `MAX_SNAPSHOTS`. The memory of the snapshots is bounded by the budget of the node, see
+//! `set_adjacency_budget`, over which the neighbors are no longer cached but read from the graph.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use graph_proxy::apis::read_graph::GRAPH_PROXY;
+use graph_proxy::apis::{get_current_snapshot, Direction, QueryParams, ID};
+use graph_proxy::GraphProxyResult;
+use ir_common::generated::common as common_pb;
+use lazy_static::lazy_static;
+
+/// The bytes of the adjacency snapshots of a store node by default.
+pub const DEFAULT_ADJACENCY_BUDGET: usize = 256 << 20;
+
+/// The snapshots not read for the time are evicted.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The snapshots cached at most, over which the least recently read ones are evicted.
+pub const MAX_SNAPSHOTS: usize = 64;
+
+/// The key of the snapshot id of the graph given in the extra parameters by the compiler.
+const SNAPSHOT_ID: &str = "SID";
+
+// the bytes of a vertex in the offsets, and of a neighbor in the adjacency;
+const VERTEX_BYTES: usize = 32;
+const NEIGHBOR_BYTES: usize = 16;
+
+lazy_static! {
+    static ref ADJACENCY_CACHE: AdjacencyCache = AdjacencyCache::new(DEFAULT_ADJACENCY_BUDGET);
+    /// The read scopes of the jobs, with the number of local workers binding them.
+    static ref JOB_READ_SCOPES: RwLock<HashMap<u64, (String, usize)>> = RwLock::new(HashMap::new());
+}
+
+/// Set the bytes of the adjacency snapshots of the store node, where 0 disables them.
+pub fn set_adjacency_budget(bytes: usize) {
+    ADJACENCY_CACHE.set_budget(bytes)
+}
+
+/// The adjacency snapshot of the store node read by the job of the current worker, or `None` if it's
+/// not cached.
+pub fn get_adjacency(key: String, snapshot: Option<i64>) -> Option<Arc<AdjacencySnapshot>> {
+    let job_id = pegasus::get_current_worker_checked()?.job_id;
+    ADJACENCY_CACHE.get(key, snapshot, job_id)
+}
+
+/// The key of the adjacency explored along the direction by the parameters, and weighted by the
+/// expression, in the read scope of the job of the current worker, where the extra parameters, e.g.
+/// the snapshot id, are not of the adjacency.
+pub fn adjacency_key(
+    direction: Direction, params: &QueryParams, weight: Option<&common_pb::Expression>,
+) -> String {
+    let mut params = params.clone();
+    params.extra_params = None;
+    // the graphs registered are never freed, so that the pointer is of a single graph
+    let graph = GRAPH_PROXY.load(Ordering::SeqCst) as usize;
+    let scope = get_current_read_scope().unwrap_or_default();
+    format!("{:?}|{:?}|{:?}|{:x}|{}", direction, params, weight, graph, scope)
+}
+
+/// The snapshot of the graph read, i.e. that of the job reading a specific snapshot, which is rejected
+/// if it's beyond the one given by the compiler, or the one given by the compiler, or `None` if the
+/// graph is not versioned.
+pub fn current_snapshot(params: &QueryParams) -> GraphProxyResult<Option<i64>> {
+    let latest = params
+        .get_extra_param(SNAPSHOT_ID)
+        .and_then(|si| si.parse().ok());
+    get_current_snapshot(latest)
+}
+
+/// Bind the scope of the data read by the job, i.e. what the data read depends on besides the plan,
+/// until all the returned bindings of the job are dropped, e.g., with the workers of the job as their
+/// resources, when the adjacency of the job is dropped as well.
+pub fn bind_read_scope(job_id: u64, scope: String) -> ReadScopeBinding {
+    let mut job_scopes = JOB_READ_SCOPES
+        .write()
+        .unwrap_or_else(|e| e.into_inner());
+    let binding = job_scopes
+        .entry(job_id)
+        .or_insert((String::new(), 0));
+    binding.0 = scope;
+    binding.1 += 1;
+    ReadScopeBinding { job_id }
+}
+
+fn get_current_read_scope() -> Option<String> {
+    let job_scopes = JOB_READ_SCOPES
+        .read()
+        .unwrap_or_else(|e| e.into_inner());
+    if job_scopes.is_empty() {
+        return None;
+    }
+    pegasus::get_current_worker_checked()
+        .and_then(|worker| job_scopes.get(&worker.job_id))
+        .map(|(scope, _)| scope.clone())
+}
+
+pub struct ReadScopeBinding {
+    job_id: u64,
+}
+
+impl Drop for ReadScopeBinding {
+    fn drop(&mut self) {
+        let mut job_scopes = JOB_READ_SCOPES
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(binding) = job_scopes.get_mut(&self.job_id) {
+            binding.1 -= 1;
+            if binding.1 == 0 {
+                job_scopes.remove(&self.job_id);
+                ADJACENCY_CACHE.remove_job(self.job_id);
+            }
+        }
+    }
+}
+
+struct Budget {
+    limit: AtomicUsize,
+    used: AtomicUsize,
+}
+
+impl Budget {
+    fn try_charge(&self, bytes: usize) -> bool {
+        let limit = self.limit.load(Ordering::Relaxed);
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                used.checked_add(bytes)
+                    .filter(|used| *used <= limit)
+            })
+            .is_ok()
+    }
+
+    fn release(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::SeqCst);
+    }
+}
+
+struct CachedAdjacency {
+    adjacency: Arc<AdjacencySnapshot>,
+    // the job of the adjacency of the graph not versioned
+    job_id: Option<u64>,
+    last_read: Instant,
+    // the order of the last read among the snapshots, to evict the least recently read one
+    read_seq: u64,
+}
+
+/// The adjacency snapshots of a store node, by the keys of their adjacency.
+pub struct AdjacencyCache {
+    budget: Arc<Budget>,
+    snapshots: Mutex<HashMap<String, CachedAdjacency>>,
+    reads: AtomicU64,
+}
+
+impl AdjacencyCache {
+    pub fn new(budget: usize) -> Self {
+        let budget = Budget { limit: AtomicUsize::new(budget), used: AtomicUsize::new(0) };
+        AdjacencyCache {
+            budget: Arc::new(budget),
+            snapshots: Mutex::new(HashMap::new()),
+            reads: AtomicU64::new(0),
+        }
+    }
+
+    /// Set the budget, where the snapshots over it are dropped.
+    pub fn set_budget(&self, bytes: usize) {
+        self.budget.limit.store(bytes, Ordering::SeqCst);
+        if self.used_bytes() > bytes {
+            self.snapshots
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clear();
+        }
+    }
+
+    /// The bytes of the snapshots, including those invalidated but still read by the running jobs.
+    pub fn used_bytes(&self) -> usize {
+        self.budget.used.load(Ordering::SeqCst)
+    }
+
+    /// The snapshot of the adjacency at the snapshot of the graph, which replaces the one at an older
+    /// snapshot, or `None` if the graph read is older than the one cached, or the budget is 0. The
+    /// adjacency of the graph not versioned is of the job.
+    pub fn get(&self, key: String, snapshot: Option<i64>, job_id: u64) -> Option<Arc<AdjacencySnapshot>> {
+        if self.budget.limit.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+        let (key, job_id) = match snapshot {
+            Some(_) => (key, None),
+            None => (format!("{}|job#{}", key, job_id), Some(job_id)),
+        };
+        let now = Instant::now();
+        let read_seq = self.reads.fetch_add(1, Ordering::Relaxed);
+        let mut snapshots = self
+            .snapshots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        snapshots.retain(|_, cached| now.duration_since(cached.last_read) < IDLE_TIMEOUT);
+        if let Some(cached) = snapshots.get_mut(&key) {
+            if cached.adjacency.snapshot == snapshot {
+                cached.last_read = now;
+                cached.read_seq = read_seq;
+                return Some(cached.adjacency.clone());
+            }
+            if cached.adjacency.snapshot > snapshot {
+                return None;
+            }
+            debug!(
+                "the adjacency snapshot at {:?} is invalidated by {:?} of {}",
+                cached.adjacency.snapshot, snapshot, key
+            );
+        } else if snapshots.len() >= MAX_SNAPSHOTS {
+            let lru = snapshots
+                .iter()
+                .min_by_key(|(_, cached)| cached.read_seq)
+                .map(|(key, _)| key.clone());
+            if let Some(lru) = lru {
+                snapshots.remove(&lru);
+            }
+        }
+        let adjacency = Arc::new(AdjacencySnapshot {
+            snapshot,
+            budget: self.budget.clone(),
+            adjacency: RwLock::new(Adjacency::default()),
+            charged: AtomicUsize::new(0),
+        });
+        let cached = CachedAdjacency { adjacency: adjacency.clone(), job_id, last_read: now, read_seq };
+        snapshots.insert(key, cached);
+        Some(adjacency)
+    }
+
+    /// Drop the adjacency of the job, i.e. that of the graph not versioned read by it.
+    pub fn remove_job(&self, job_id: u64) {
+        self.snapshots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, cached| cached.job_id != Some(job_id));
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+}
+
+#[derive(Default)]
+struct Adjacency {
+    // the range of the neighbors of each vertex in `neighbors`
+    offsets: HashMap<ID, (usize, usize)>,
+    neighbors: Vec<(ID, f64)>,
+}
+
+/// The weighted neighbors of the vertices explored, which are only added, and never changed.
+pub struct AdjacencySnapshot {
+    snapshot: Option<i64>,
+    budget: Arc<Budget>,
+    adjacency: RwLock<Adjacency>,
+    // the bytes charged to the budget, which are released as it's dropped
+    charged: AtomicUsize,
+}
+
+impl AdjacencySnapshot {
+    pub fn get(&self, id: ID) -> Option<Vec<(ID, f64)>> {
+        let adjacency = self
+            .adjacency
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        adjacency
+            .offsets
+            .get(&id)
+            .map(|(start, end)| adjacency.neighbors[*start..*end].to_vec())
+    }
+
+    /// Add the neighbors of the vertex, or return false if they're over the budget.
+    pub fn insert(&self, id: ID, neighbors: &[(ID, f64)]) -> bool {
+        let mut adjacency = self
+            .adjacency
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        // added by another worker exploring the vertex
+        if adjacency.offsets.contains_key(&id) {
+            return true;
+        }
+        let bytes = VERTEX_BYTES + NEIGHBOR_BYTES * neighbors.len();
+        if !self.budget.try_charge(bytes) {
+            return false;
+        }
+        self.charged.fetch_add(bytes, Ordering::SeqCst);
+        let start = adjacency.neighbors.len();
+        adjacency.neighbors.extend_from_slice(neighbors);
+        let end = adjacency.neighbors.len();
+        adjacency.offsets.insert(id, (start, end));
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.adjacency
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .offsets
+            .len()
+    }
+}
+
+impl Drop for AdjacencySnapshot {
+    fn drop(&mut self) {
+        self.budget
+            .release(self.charged.load(Ordering::SeqCst));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adjacency_snapshot_test() {
+        let cache = AdjacencyCache::new(VERTEX_BYTES * 2 + NEIGHBOR_BYTES * 3);
+        let adjacency = cache.get("a".to_string(), Some(1), 1).unwrap();
+        assert!(adjacency.insert(1, &[(2, 1.0), (3, 0.5)]));
+        assert!(adjacency.insert(2, &[(3, 1.0)]));
+        assert_eq!(adjacency.get(1), Some(vec![(2, 1.0), (3, 0.5)]));
+        assert_eq!(adjacency.get(3), None);
+        // over the budget
+        assert!(!adjacency.insert(3, &[]));
+        assert_eq!(adjacency.len(), 2);
+        assert_eq!(cache.used_bytes(), VERTEX_BYTES * 2 + NEIGHBOR_BYTES * 3);
+
+        // shared at the same snapshot by the jobs, and bypassed by the older ones
+        assert!(Arc::ptr_eq(&cache.get("a".to_string(), Some(1), 2).unwrap(), &adjacency));
+        assert!(cache.get("a".to_string(), Some(0), 1).is_none());
+        // invalidated by the newer one, and released once it's no longer read
+        let newer = cache.get("a".to_string(), Some(2), 1).unwrap();
+        assert_eq!(newer.len(), 0);
+        assert_eq!(cache.used_bytes(), VERTEX_BYTES * 2 + NEIGHBOR_BYTES * 3);
+        drop(adjacency);
+        assert_eq!(cache.used_bytes(), 0);
+        assert!(newer.insert(3, &[]));
+
+        cache.set_budget(0);
+        assert!(cache.get("a".to_string(), Some(2), 1).is_none());
+    }
+
+    #[test]
+    fn adjacency_not_versioned_test() {
+        let cache = AdjacencyCache::new(DEFAULT_ADJACENCY_BUDGET);
+        // of the job, rather than shared by the jobs
+        let adjacency = cache.get("a".to_string(), None, 1).unwrap();
+        assert!(Arc::ptr_eq(&cache.get("a".to_string(), None, 1).unwrap(), &adjacency));
+        assert!(!Arc::ptr_eq(&cache.get("a".to_string(), None, 2).unwrap(), &adjacency));
+        cache.get("a".to_string(), Some(1), 1).unwrap();
+        assert_eq!(cache.len(), 3);
+        cache.remove_job(1);
+        assert_eq!(cache.len(), 2);
+        assert!(!Arc::ptr_eq(&cache.get("a".to_string(), None, 1).unwrap(), &adjacency));
+    }
+
+    #[test]
+    fn adjacency_eviction_test() {
+        let cache = AdjacencyCache::new(DEFAULT_ADJACENCY_BUDGET);
+        let first = cache.get("0".to_string(), Some(1), 1).unwrap();
+        for i in 1..MAX_SNAPSHOTS {
+            cache.get(i.to_string(), Some(1), 1).unwrap();
+        }
+        // read lately, so that the second one is the least recently read
+        cache.get("0".to_string(), Some(1), 1).unwrap();
+        assert_eq!(cache.len(), MAX_SNAPSHOTS);
+        cache
+            .get(MAX_SNAPSHOTS.to_string(), Some(1), 1)
+            .unwrap();
+        assert_eq!(cache.len(), MAX_SNAPSHOTS);
+        assert!(Arc::ptr_eq(&cache.get("0".to_string(), Some(1), 1).unwrap(), &first));
+        let second = cache.get("1".to_string(), Some(1), 1).unwrap();
+        assert_eq!(second.len(), 0);
+        assert_eq!(cache.len(), MAX_SNAPSHOTS);
+    }
+
+    #[test]
+    fn adjacency_key_test() {
+        let mut params = QueryParams::default();
+        params.labels = vec![1];
+        let key = adjacency_key(Direction::Out, &params, None);
+        params.extra_params = Some(
+            vec![(SNAPSHOT_ID.to_string(), "3".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(adjacency_key(Direction::Out, &params, None), key);
+        assert_eq!(current_snapshot(&params).unwrap(), Some(3));
+        assert_ne!(adjacency_key(Direction::Both, &params, None), key);
+        params.labels = vec![2];
+        assert_ne!(adjacency_key(Direction::Out, &params, None), key);
+    }
+}
@@ -0,0 +1,744 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The iterative graph algorithms, which are vertex-centric: in each iteration, the vertices send
+//! their values to their neighbors (`ScatterOperator`), and the messages are routed to the workers of
+//! the neighbors and folded into their new values (`AlgorithmAccum`). The edges along which the
+//! values are sent may be weighted by an expression evaluated per edge. The neighbors explored are
+//! cached in the adjacency snapshots of the store node for the iterations after the first, see
+//! `adjacency`.
+
+pub mod adjacency;
+mod triangle;
+
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::io;
+use std::sync::Arc;
+
+use graph_proxy::apis::{get_graph, Direction, Edge, GraphElement, QueryParams, Statement, Vertex, ID};
+use graph_proxy::utils::expr::eval::{Evaluate, Evaluator};
+use graph_proxy::utils::expr::eval_pred::{EvalPred, PEvaluator};
+use ir_common::error::ParsePbError;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::KeyId;
+use pegasus::api::function::{DynIter, FlatMapFunction, FnResult, MapFunction};
+use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+pub use triangle::{TriangleAccum, TriangleMessage, TriangleOperator, TriangleSummary};
+
+use self::adjacency::AdjacencySnapshot;
+use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
+use crate::process::entry::Entry;
+use crate::process::operator::accum::accumulator::Accumulator;
+use crate::process::record::Record;
+
+const DEFAULT_MAX_ITERATIONS: u32 = 20;
+const DEFAULT_DAMPING: f64 = 0.85;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlgorithmValue {
+    Rank(f64),
+    Label(ID),
+    Distance(f64),
+}
+
+#[derive(Clone, Debug)]
+pub enum AlgorithmMessage {
+    /// The vertex of the record at its head, with its current value
+    Vertex(ID, Record, AlgorithmValue),
+    /// The value sent to the vertex by one of its neighbors
+    Neighbor(ID, AlgorithmValue),
+}
+
+impl AlgorithmMessage {
+    /// The record with its vertex at the head.
+    pub fn init(record: Record, kind: algebra_pb::graph_algorithm::Kind) -> FnResult<Self> {
+        let id = record
+            .get(None)
+            .and_then(|entry| entry.as_vertex())
+            .map(|vertex| vertex.id())
+            .ok_or_else(|| {
+                FnExecError::unexpected_data_error("the head of GraphAlgorithm is not a vertex")
+            })?;
+        let value = match kind {
+            algebra_pb::graph_algorithm::Kind::PageRank => AlgorithmValue::Rank(1.0),
+            algebra_pb::graph_algorithm::Kind::ShortestPath => AlgorithmValue::Distance(f64::INFINITY),
+            _ => AlgorithmValue::Label(id),
+        };
+        Ok(AlgorithmMessage::Vertex(id, record, value))
+    }
+
+    /// The id of the vertex which the message belongs to, to route the message by.
+    pub fn get_id(&self) -> ID {
+        match self {
+            AlgorithmMessage::Vertex(id, _, _) => *id,
+            AlgorithmMessage::Neighbor(id, _) => *id,
+        }
+    }
+
+    /// The record with the value of its vertex appended with the alias.
+    pub fn finish(self, alias: Option<KeyId>) -> FnResult<Option<Record>> {
+        match self {
+            AlgorithmMessage::Vertex(_, mut record, value) => {
+                match value {
+                    AlgorithmValue::Rank(rank) => record.append(object!(rank), alias),
+                    AlgorithmValue::Label(label) => record.append(object!(label), alias),
+                    AlgorithmValue::Distance(distance) => record.append(object!(distance), alias),
+                }
+                Ok(Some(record))
+            }
+            AlgorithmMessage::Neighbor(_, _) => Ok(None),
+        }
+    }
+}
+
+/// Turn the records into the messages of their vertices, where the sources of the shortest paths
+/// start with the distance of 0.
+pub struct InitOperator {
+    kind: algebra_pb::graph_algorithm::Kind,
+    source: Option<PEvaluator>,
+}
+
+impl MapFunction<Record, AlgorithmMessage> for InitOperator {
+    fn exec(&self, input: Record) -> FnResult<AlgorithmMessage> {
+        let is_source = if let Some(source) = self.source.as_ref() {
+            source
+                .eval_bool(Some(&input))
+                .map_err(|e| FnExecError::from(e))?
+        } else {
+            false
+        };
+        match AlgorithmMessage::init(input, self.kind)? {
+            AlgorithmMessage::Vertex(id, record, _) if is_source => {
+                Ok(AlgorithmMessage::Vertex(id, record, AlgorithmValue::Distance(0.0)))
+            }
+            message => Ok(message),
+        }
+    }
+}
+
+/// The neighbors of the vertices, along the edges weighted by 1.0, or by the weight of the edges.
+enum Neighbors {
+    Vertices(Box<dyn Statement<ID, Vertex>>),
+    Edges(Box<dyn Statement<ID, Edge>>, Evaluator),
+}
+
+impl Neighbors {
+    fn get(&self, id: ID) -> FnExecResult<Vec<(ID, f64)>> {
+        match self {
+            Neighbors::Vertices(stmt) => Ok(stmt.exec(id)?.map(|v| (v.id(), 1.0)).collect()),
+            Neighbors::Edges(stmt, weight) => {
+                let mut neighbors = vec![];
+                for edge in stmt.exec(id)? {
+                    let w = weight
+                        .eval::<Edge, Edge>(Some(&edge))?
+                        .as_f64()
+                        .map_err(|e| FnExecError::unexpected_data_error(&format!("{:?}", e)))?;
+                    if !(w >= 0.0 && w.is_finite()) {
+                        Err(FnExecError::unexpected_data_error(&format!(
+                            "invalid weight {} of edge {}",
+                            w,
+                            edge.id()
+                        )))?
+                    }
+                    neighbors.push((edge.get_other_id(), w));
+                }
+                Ok(neighbors)
+            }
+        }
+    }
+}
+
+/// Send the values of the vertices to their neighbors, along with the vertices themselves.
+pub struct ScatterOperator {
+    kind: algebra_pb::graph_algorithm::Kind,
+    neighbors: Neighbors,
+    adjacency: Option<Arc<AdjacencySnapshot>>,
+}
+
+impl ScatterOperator {
+    // the neighbors in the adjacency snapshot, or read from the graph and added to it
+    fn get_neighbors(&self, id: ID) -> FnExecResult<Vec<(ID, f64)>> {
+        if let Some(adjacency) = self.adjacency.as_ref() {
+            if let Some(neighbors) = adjacency.get(id) {
+                return Ok(neighbors);
+            }
+            let neighbors = self.neighbors.get(id)?;
+            adjacency.insert(id, &neighbors);
+            return Ok(neighbors);
+        }
+        self.neighbors.get(id)
+    }
+}
+
+impl FlatMapFunction<AlgorithmMessage, AlgorithmMessage> for ScatterOperator {
+    type Target = DynIter<AlgorithmMessage>;
+
+    fn exec(&self, input: AlgorithmMessage) -> FnResult<Self::Target> {
+        let (id, value) = match &input {
+            AlgorithmMessage::Vertex(id, _, value) => (*id, *value),
+            AlgorithmMessage::Neighbor(_, _) => return Ok(Box::new(std::iter::empty())),
+        };
+        // the vertices not reached yet have no distance to send
+        if let AlgorithmValue::Distance(distance) = value {
+            if distance.is_infinite() {
+                return Ok(Box::new(std::iter::once(input)));
+            }
+        }
+        let neighbors = self.get_neighbors(id)?;
+        let messages = scatter(self.kind, value, neighbors);
+        Ok(Box::new(std::iter::once(input).chain(messages.into_iter())))
+    }
+}
+
+/// The values sent to the weighted neighbors: the rank in proportion to the weights for PageRank, the
+/// distance through the edges for the shortest paths, and the value itself otherwise.
+fn scatter(
+    kind: algebra_pb::graph_algorithm::Kind, value: AlgorithmValue, neighbors: Vec<(ID, f64)>,
+) -> Vec<AlgorithmMessage> {
+    match (kind, value) {
+        (algebra_pb::graph_algorithm::Kind::PageRank, AlgorithmValue::Rank(rank)) => {
+            let total: f64 = neighbors.iter().map(|(_, w)| *w).sum();
+            if total > 0.0 {
+                neighbors
+                    .into_iter()
+                    .map(|(neighbor, w)| {
+                        AlgorithmMessage::Neighbor(neighbor, AlgorithmValue::Rank(rank * w / total))
+                    })
+                    .collect()
+            } else {
+                vec![]
+            }
+        }
+        (algebra_pb::graph_algorithm::Kind::ShortestPath, AlgorithmValue::Distance(distance)) => neighbors
+            .into_iter()
+            .map(|(neighbor, w)| {
+                AlgorithmMessage::Neighbor(neighbor, AlgorithmValue::Distance(distance + w))
+            })
+            .collect(),
+        _ => neighbors
+            .into_iter()
+            .map(|(neighbor, _)| AlgorithmMessage::Neighbor(neighbor, value))
+            .collect(),
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct VertexState {
+    vertex: Option<(Record, AlgorithmValue)>,
+    rank: f64,
+    labels: HashMap<ID, u64>,
+    distance: Option<f64>,
+}
+
+/// Fold the messages of the vertices into their new values. The messages to the vertices which are
+/// not in the input are dropped.
+#[derive(Clone, Debug)]
+pub struct AlgorithmAccum {
+    kind: algebra_pb::graph_algorithm::Kind,
+    damping: f64,
+    states: HashMap<ID, VertexState>,
+}
+
+impl AlgorithmAccum {
+    fn new_value(&self, id: ID, value: AlgorithmValue, state: &VertexState) -> AlgorithmValue {
+        match self.kind {
+            algebra_pb::graph_algorithm::Kind::PageRank => {
+                AlgorithmValue::Rank((1.0 - self.damping) + self.damping * state.rank)
+            }
+            algebra_pb::graph_algorithm::Kind::Wcc => {
+                let min = state.labels.keys().min().cloned().unwrap_or(id);
+                match value {
+                    AlgorithmValue::Label(label) if label <= min => value,
+                    _ => AlgorithmValue::Label(min),
+                }
+            }
+            // the most frequent label of the neighbors, and the smallest one among the ties
+            algebra_pb::graph_algorithm::Kind::Lpa => state
+                .labels
+                .iter()
+                .max_by(|(l1, c1), (l2, c2)| c1.cmp(c2).then(l2.cmp(l1)))
+                .map(|(label, _)| AlgorithmValue::Label(*label))
+                .unwrap_or(value),
+            algebra_pb::graph_algorithm::Kind::ShortestPath => match (value, state.distance) {
+                (AlgorithmValue::Distance(current), Some(distance)) if distance < current => {
+                    AlgorithmValue::Distance(distance)
+                }
+                _ => value,
+            },
+            algebra_pb::graph_algorithm::Kind::TriangleCount
+            | algebra_pb::graph_algorithm::Kind::ClusteringCoefficient => value,
+        }
+    }
+}
+
+impl Accumulator<AlgorithmMessage, DynIter<AlgorithmMessage>> for AlgorithmAccum {
+    fn accum(&mut self, next: AlgorithmMessage) -> FnExecResult<()> {
+        match next {
+            AlgorithmMessage::Vertex(id, record, value) => {
+                self.states.entry(id).or_default().vertex = Some((record, value));
+            }
+            AlgorithmMessage::Neighbor(id, value) => {
+                let state = self.states.entry(id).or_default();
+                match value {
+                    AlgorithmValue::Rank(rank) => state.rank += rank,
+                    AlgorithmValue::Label(label) => *state.labels.entry(label).or_insert(0) += 1,
+                    AlgorithmValue::Distance(distance) => {
+                        state.distance = Some(
+                            state
+                                .distance
+                                .map_or(distance, |min| min.min(distance)),
+                        )
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> FnExecResult<DynIter<AlgorithmMessage>> {
+        let states = std::mem::replace(&mut self.states, HashMap::new());
+        let mut vertices = Vec::with_capacity(states.len());
+        for (id, state) in states.iter() {
+            if let Some((record, value)) = state.vertex.as_ref() {
+                let value = self.new_value(*id, *value, state);
+                vertices.push(AlgorithmMessage::Vertex(*id, record.clone(), value));
+            }
+        }
+        Ok(Box::new(vertices.into_iter()))
+    }
+}
+
+/// The functions of a graph algorithm, which iterates `scatter` and `accum` by `max_iterations`.
+pub struct AlgorithmOperator {
+    pub kind: algebra_pb::graph_algorithm::Kind,
+    pub max_iterations: u32,
+    pub alias: Option<KeyId>,
+    pub init: InitOperator,
+    pub scatter: ScatterOperator,
+    pub accum: AlgorithmAccum,
+}
+
+pub trait AlgorithmFuncGen {
+    fn is_triangle(&self) -> bool;
+
+    fn gen_algorithm(self) -> FnGenResult<AlgorithmOperator>;
+
+    fn gen_triangle(self) -> FnGenResult<TriangleOperator>;
+}
+
+impl AlgorithmFuncGen for algebra_pb::GraphAlgorithm {
+    /// The triangles are counted by intersecting the neighbors, rather than by iterations.
+    fn is_triangle(&self) -> bool {
+        matches!(
+            algebra_pb::graph_algorithm::Kind::from_i32(self.kind),
+            Some(algebra_pb::graph_algorithm::Kind::TriangleCount)
+                | Some(algebra_pb::graph_algorithm::Kind::ClusteringCoefficient)
+        )
+    }
+
+    fn gen_algorithm(self) -> FnGenResult<AlgorithmOperator> {
+        let graph = get_graph().ok_or_else(|| FnGenError::NullGraphError)?;
+        let kind = algebra_pb::graph_algorithm::Kind::from_i32(self.kind)
+            .ok_or_else(|| ParsePbError::from(format!("invalid GraphAlgorithm kind {}", self.kind)))?;
+        let query_params: QueryParams = self.params.try_into()?;
+        // PageRank and the shortest paths are along the out edges, while the components and the
+        // communities are undirected
+        let direction = match kind {
+            algebra_pb::graph_algorithm::Kind::PageRank
+            | algebra_pb::graph_algorithm::Kind::ShortestPath => Direction::Out,
+            algebra_pb::graph_algorithm::Kind::Wcc | algebra_pb::graph_algorithm::Kind::Lpa => {
+                Direction::Both
+            }
+            algebra_pb::graph_algorithm::Kind::TriangleCount
+            | algebra_pb::graph_algorithm::Kind::ClusteringCoefficient => {
+                Err(ParsePbError::from(format!("GraphAlgorithm {:?} is not iterative", kind)))?
+            }
+        };
+        if self.global {
+            Err(ParsePbError::from(format!("global output of GraphAlgorithm {:?} is not supported", kind)))?
+        }
+        let source = match (kind, self.source) {
+            (algebra_pb::graph_algorithm::Kind::ShortestPath, Some(source)) => {
+                Some(PEvaluator::try_from(source)?)
+            }
+            (algebra_pb::graph_algorithm::Kind::ShortestPath, None) => {
+                Err(ParsePbError::EmptyFieldError("source of GraphAlgorithm ShortestPath".to_string()))?
+            }
+            (_, Some(_)) => {
+                Err(ParsePbError::from(format!("source of GraphAlgorithm {:?} is not supported", kind)))?
+            }
+            (_, None) => None,
+        };
+        let adjacency = adjacency::get_adjacency(
+            adjacency::adjacency_key(direction, &query_params, self.weight.as_ref()),
+            adjacency::current_snapshot(&query_params)?,
+        );
+        let neighbors = match self.weight {
+            Some(weight) => {
+                if !matches!(
+                    kind,
+                    algebra_pb::graph_algorithm::Kind::PageRank
+                        | algebra_pb::graph_algorithm::Kind::ShortestPath
+                ) {
+                    Err(ParsePbError::from(format!(
+                        "weight of GraphAlgorithm {:?} is not supported",
+                        kind
+                    )))?
+                }
+                let weight = Evaluator::try_from(weight)?;
+                // the properties of the edges are required to evaluate their weights
+                let mut query_params = query_params.clone();
+                if query_params.columns.is_none() {
+                    query_params.columns = Some(vec![]);
+                }
+                Neighbors::Edges(graph.prepare_explore_edge(direction, &query_params)?, weight)
+            }
+            None => Neighbors::Vertices(graph.prepare_explore_vertex(direction, &query_params)?),
+        };
+        let max_iterations =
+            if self.max_iterations > 0 { self.max_iterations as u32 } else { DEFAULT_MAX_ITERATIONS };
+        let damping = if self.damping > 0.0 && self.damping < 1.0 { self.damping } else { DEFAULT_DAMPING };
+        let alias: Option<KeyId> = self
+            .alias
+            .map(|alias| alias.try_into())
+            .transpose()?;
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!(
+                "Runtime graph algorithm operator {:?}, query_params {:?}, max_iterations {:?}, damping {:?}, alias {:?}",
+                kind, query_params, max_iterations, damping, alias
+            );
+        }
+        Ok(AlgorithmOperator {
+            kind,
+            max_iterations,
+            alias,
+            init: InitOperator { kind, source },
+            scatter: ScatterOperator { kind, neighbors, adjacency },
+            accum: AlgorithmAccum { kind, damping, states: HashMap::new() },
+        })
+    }
+
+    fn gen_triangle(self) -> FnGenResult<TriangleOperator> {
+        let graph = get_graph().ok_or_else(|| FnGenError::NullGraphError)?;
+        let kind = algebra_pb::graph_algorithm::Kind::from_i32(self.kind)
+            .ok_or_else(|| ParsePbError::from(format!("invalid GraphAlgorithm kind {}", self.kind)))?;
+        if !self.is_triangle() {
+            Err(ParsePbError::from(format!("GraphAlgorithm {:?} is not counted by triangles", kind)))?
+        }
+        if self.weight.is_some() || self.source.is_some() {
+            Err(ParsePbError::from(format!(
+                "weight or source of GraphAlgorithm {:?} is not supported",
+                kind
+            )))?
+        }
+        let query_params: QueryParams = self.params.try_into()?;
+        // the vertex explores its neighbors to probe them, and the neighbor explores its own to intersect
+        let probe_stmt = graph.prepare_explore_vertex(Direction::Both, &query_params)?;
+        let intersect_stmt = graph.prepare_explore_vertex(Direction::Both, &query_params)?;
+        let alias: Option<KeyId> = self
+            .alias
+            .map(|alias| alias.try_into())
+            .transpose()?;
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!(
+                "Runtime triangle operator {:?}, query_params {:?}, global {:?}, alias {:?}",
+                kind, query_params, self.global, alias
+            );
+        }
+        Ok(TriangleOperator::new(kind, self.global, alias, probe_stmt, intersect_stmt))
+    }
+}
+
+impl Encode for AlgorithmValue {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            AlgorithmValue::Rank(rank) => {
+                writer.write_u8(0)?;
+                writer.write_f64(*rank)?;
+            }
+            AlgorithmValue::Label(label) => {
+                writer.write_u8(1)?;
+                writer.write_i64(*label)?;
+            }
+            AlgorithmValue::Distance(distance) => {
+                writer.write_u8(2)?;
+                writer.write_f64(*distance)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for AlgorithmValue {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        match reader.read_u8()? {
+            0 => Ok(AlgorithmValue::Rank(reader.read_f64()?)),
+            1 => Ok(AlgorithmValue::Label(reader.read_i64()?)),
+            2 => Ok(AlgorithmValue::Distance(reader.read_f64()?)),
+            _ => Err(io::Error::new(io::ErrorKind::Other, "unreachable")),
+        }
+    }
+}
+
+impl Encode for AlgorithmMessage {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            AlgorithmMessage::Vertex(id, record, value) => {
+                writer.write_u8(0)?;
+                writer.write_i64(*id)?;
+                record.write_to(writer)?;
+                value.write_to(writer)?;
+            }
+            AlgorithmMessage::Neighbor(id, value) => {
+                writer.write_u8(1)?;
+                writer.write_i64(*id)?;
+                value.write_to(writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for AlgorithmMessage {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        match reader.read_u8()? {
+            0 => {
+                let id = reader.read_i64()?;
+                let record = Record::read_from(reader)?;
+                let value = AlgorithmValue::read_from(reader)?;
+                Ok(AlgorithmMessage::Vertex(id, record, value))
+            }
+            1 => {
+                let id = reader.read_i64()?;
+                let value = AlgorithmValue::read_from(reader)?;
+                Ok(AlgorithmMessage::Neighbor(id, value))
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Other, "unreachable")),
+        }
+    }
+}
+
+impl Encode for VertexState {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        self.vertex.write_to(writer)?;
+        writer.write_f64(self.rank)?;
+        writer.write_u32(self.labels.len() as u32)?;
+        for (label, count) in self.labels.iter() {
+            writer.write_i64(*label)?;
+            writer.write_u64(*count)?;
+        }
+        self.distance.write_to(writer)?;
+        Ok(())
+    }
+}
+
+impl Decode for VertexState {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let vertex = <Option<(Record, AlgorithmValue)>>::read_from(reader)?;
+        let rank = reader.read_f64()?;
+        let len = reader.read_u32()?;
+        let mut labels = HashMap::with_capacity(len as usize);
+        for _ in 0..len {
+            let label = reader.read_i64()?;
+            let count = reader.read_u64()?;
+            labels.insert(label, count);
+        }
+        let distance = <Option<f64>>::read_from(reader)?;
+        Ok(VertexState { vertex, rank, labels, distance })
+    }
+}
+
+impl Encode for AlgorithmAccum {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_i32(self.kind as i32)?;
+        writer.write_f64(self.damping)?;
+        writer.write_u32(self.states.len() as u32)?;
+        for (id, state) in self.states.iter() {
+            writer.write_i64(*id)?;
+            state.write_to(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Decode for AlgorithmAccum {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let kind = algebra_pb::graph_algorithm::Kind::from_i32(reader.read_i32()?)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "invalid GraphAlgorithm kind"))?;
+        let damping = reader.read_f64()?;
+        let len = reader.read_u32()?;
+        let mut states = HashMap::with_capacity(len as usize);
+        for _ in 0..len {
+            let id = reader.read_i64()?;
+            let state = VertexState::read_from(reader)?;
+            states.insert(id, state);
+        }
+        Ok(AlgorithmAccum { kind, damping, states })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::{DynDetails, Vertex};
+
+    use super::*;
+    use crate::process::operator::tests::PERSON_LABEL;
+
+    fn vertex_record(id: ID) -> Record {
+        Record::new(Vertex::new(id, Some(PERSON_LABEL), DynDetails::default()), None)
+    }
+
+    fn accum(
+        kind: algebra_pb::graph_algorithm::Kind, messages: Vec<AlgorithmMessage>,
+    ) -> Vec<(ID, AlgorithmValue)> {
+        let mut accum = AlgorithmAccum { kind, damping: DEFAULT_DAMPING, states: HashMap::new() };
+        for message in messages {
+            accum.accum(message).unwrap();
+        }
+        let mut values: Vec<(ID, AlgorithmValue)> = accum
+            .finalize()
+            .unwrap()
+            .map(|message| match message {
+                AlgorithmMessage::Vertex(id, _, value) => (id, value),
+                AlgorithmMessage::Neighbor(_, _) => unreachable!(),
+            })
+            .collect();
+        values.sort_by_key(|(id, _)| *id);
+        values
+    }
+
+    #[test]
+    fn page_rank_accum_test() {
+        let kind = algebra_pb::graph_algorithm::Kind::PageRank;
+        let messages = vec![
+            AlgorithmMessage::init(vertex_record(1), kind).unwrap(),
+            AlgorithmMessage::init(vertex_record(2), kind).unwrap(),
+            AlgorithmMessage::Neighbor(2, AlgorithmValue::Rank(0.5)),
+            AlgorithmMessage::Neighbor(2, AlgorithmValue::Rank(1.0)),
+            // the vertex not in the input
+            AlgorithmMessage::Neighbor(3, AlgorithmValue::Rank(0.5)),
+        ];
+        let values = accum(kind, messages);
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0], (1, AlgorithmValue::Rank(1.0 - DEFAULT_DAMPING)));
+        assert_eq!(values[1], (2, AlgorithmValue::Rank(1.0 - DEFAULT_DAMPING + DEFAULT_DAMPING * 1.5)));
+    }
+
+    #[test]
+    fn wcc_accum_test() {
+        let kind = algebra_pb::graph_algorithm::Kind::Wcc;
+        let messages = vec![
+            AlgorithmMessage::init(vertex_record(1), kind).unwrap(),
+            AlgorithmMessage::init(vertex_record(2), kind).unwrap(),
+            AlgorithmMessage::Neighbor(1, AlgorithmValue::Label(2)),
+            AlgorithmMessage::Neighbor(2, AlgorithmValue::Label(1)),
+        ];
+        let values = accum(kind, messages);
+        assert_eq!(values, vec![(1, AlgorithmValue::Label(1)), (2, AlgorithmValue::Label(1))]);
+    }
+
+    #[test]
+    fn lpa_accum_test() {
+        let kind = algebra_pb::graph_algorithm::Kind::Lpa;
+        let messages = vec![
+            AlgorithmMessage::init(vertex_record(1), kind).unwrap(),
+            AlgorithmMessage::init(vertex_record(2), kind).unwrap(),
+            AlgorithmMessage::Neighbor(1, AlgorithmValue::Label(5)),
+            AlgorithmMessage::Neighbor(1, AlgorithmValue::Label(3)),
+            AlgorithmMessage::Neighbor(1, AlgorithmValue::Label(5)),
+            // the smallest label among the ties
+            AlgorithmMessage::Neighbor(2, AlgorithmValue::Label(4)),
+            AlgorithmMessage::Neighbor(2, AlgorithmValue::Label(3)),
+        ];
+        let values = accum(kind, messages);
+        assert_eq!(values, vec![(1, AlgorithmValue::Label(5)), (2, AlgorithmValue::Label(3))]);
+    }
+
+    #[test]
+    fn shortest_path_accum_test() {
+        let kind = algebra_pb::graph_algorithm::Kind::ShortestPath;
+        let messages = vec![
+            AlgorithmMessage::Vertex(1, vertex_record(1), AlgorithmValue::Distance(0.0)),
+            AlgorithmMessage::init(vertex_record(2), kind).unwrap(),
+            AlgorithmMessage::init(vertex_record(3), kind).unwrap(),
+            // the source keeps its distance of 0
+            AlgorithmMessage::Neighbor(1, AlgorithmValue::Distance(2.0)),
+            AlgorithmMessage::Neighbor(2, AlgorithmValue::Distance(1.5)),
+            AlgorithmMessage::Neighbor(2, AlgorithmValue::Distance(0.5)),
+        ];
+        let values = accum(kind, messages);
+        assert_eq!(
+            values,
+            vec![
+                (1, AlgorithmValue::Distance(0.0)),
+                (2, AlgorithmValue::Distance(0.5)),
+                (3, AlgorithmValue::Distance(f64::INFINITY))
+            ]
+        );
+    }
+
+    #[test]
+    fn weighted_scatter_test() {
+        let neighbors = vec![(2, 1.0), (3, 3.0)];
+        let values: Vec<(ID, AlgorithmValue)> = scatter(
+            algebra_pb::graph_algorithm::Kind::PageRank,
+            AlgorithmValue::Rank(2.0),
+            neighbors.clone(),
+        )
+        .into_iter()
+        .map(|message| (message.get_id(), message_value(message)))
+        .collect();
+        assert_eq!(values, vec![(2, AlgorithmValue::Rank(0.5)), (3, AlgorithmValue::Rank(1.5))]);
+
+        let values: Vec<(ID, AlgorithmValue)> = scatter(
+            algebra_pb::graph_algorithm::Kind::ShortestPath,
+            AlgorithmValue::Distance(1.0),
+            neighbors,
+        )
+        .into_iter()
+        .map(|message| (message.get_id(), message_value(message)))
+        .collect();
+        assert_eq!(values, vec![(2, AlgorithmValue::Distance(2.0)), (3, AlgorithmValue::Distance(4.0))]);
+
+        // the rank is not sent along the edges of no weight
+        assert!(scatter(
+            algebra_pb::graph_algorithm::Kind::PageRank,
+            AlgorithmValue::Rank(1.0),
+            vec![(2, 0.0)]
+        )
+        .is_empty());
+    }
+
+    fn message_value(message: AlgorithmMessage) -> AlgorithmValue {
+        match message {
+            AlgorithmMessage::Neighbor(_, value) => value,
+            AlgorithmMessage::Vertex(_, _, value) => value,
+        }
+    }
+
+    #[test]
+    fn algorithm_message_codec_test() {
+        let message = AlgorithmMessage::Neighbor(7, AlgorithmValue::Rank(0.25));
+        let mut bytes = vec![];
+        message.write_to(&mut bytes).unwrap();
+        match AlgorithmMessage::read_from(&mut bytes.as_slice()).unwrap() {
+            AlgorithmMessage::Neighbor(id, value) => {
+                assert_eq!((id, value), (7, AlgorithmValue::Rank(0.25)));
+            }
+            _ => unreachable!(),
+        }
+    }
+}
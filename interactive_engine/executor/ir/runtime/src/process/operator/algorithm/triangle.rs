@@ -0,0 +1,438 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The triangles of a vertex are counted in three steps: the vertex probes each of its neighbors with
+//! its own neighbors (`ProbeOperator`), the neighbor intersects them with its own neighbors
+//! (`IntersectOperator`), and the common neighbors are summed up for the vertex (`TriangleAccum`).
+//! Each triangle of the vertex is found by both of its neighbors in the triangle.
+
+use std::collections::HashMap;
+use std::io;
+
+use graph_proxy::apis::{GraphElement, Statement, Vertex, ID};
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::KeyId;
+use pegasus::api::function::{DynIter, FlatMapFunction, FnResult};
+use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+
+use crate::error::{FnExecError, FnExecResult};
+use crate::process::entry::Entry;
+use crate::process::operator::accum::accumulator::Accumulator;
+use crate::process::operator::map::IntersectionEntry;
+use crate::process::record::Record;
+
+#[derive(Clone, Debug)]
+pub enum TriangleMessage {
+    /// The vertex of the record at its head
+    Vertex(ID, Record),
+    /// The neighbors of a vertex (the second), sent to one of them (the first) to intersect with
+    /// the neighbors of its own
+    Probe(ID, ID, IntersectionEntry),
+    /// The vertex with the number of its unique neighbors
+    Degree(ID, Record, u64),
+    /// The number of the common neighbors of the vertex and one of its neighbors
+    Common(ID, u64),
+    /// The record of the vertex, with the number of its triangles and its degree
+    Counted(Record, u64, u64),
+}
+
+impl TriangleMessage {
+    /// The record with its vertex at the head.
+    pub fn init(record: Record) -> FnResult<Self> {
+        let id = record
+            .get(None)
+            .and_then(|entry| entry.as_vertex())
+            .map(|vertex| vertex.id())
+            .ok_or_else(|| {
+                FnExecError::unexpected_data_error("the head of GraphAlgorithm is not a vertex")
+            })?;
+        Ok(TriangleMessage::Vertex(id, record))
+    }
+
+    /// The id of the vertex which the message is sent to, to route the message by.
+    pub fn get_id(&self) -> ID {
+        match self {
+            TriangleMessage::Vertex(id, _) => *id,
+            TriangleMessage::Probe(id, _, _) => *id,
+            TriangleMessage::Degree(id, _, _) => *id,
+            TriangleMessage::Common(id, _) => *id,
+            TriangleMessage::Counted(_, _, _) => 0,
+        }
+    }
+
+    /// The record with the number of triangles or the clustering coefficient appended with the alias.
+    pub fn finish(
+        self, kind: algebra_pb::graph_algorithm::Kind, alias: Option<KeyId>,
+    ) -> FnResult<Option<Record>> {
+        match self {
+            TriangleMessage::Counted(mut record, triangles, degree) => {
+                match kind {
+                    algebra_pb::graph_algorithm::Kind::ClusteringCoefficient => {
+                        record.append(object!(clustering_coefficient(triangles, degree)), alias)
+                    }
+                    _ => record.append(object!(triangles), alias),
+                }
+                Ok(Some(record))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The local clustering coefficient, which is 0 for the vertex with less than two neighbors.
+pub fn clustering_coefficient(triangles: u64, degree: u64) -> f64 {
+    if degree < 2 {
+        0.0
+    } else {
+        (2 * triangles) as f64 / (degree * (degree - 1)) as f64
+    }
+}
+
+/// The unique neighbors of the vertex, excluding the vertex itself given self-loops.
+fn explore_neighbors(stmt: &dyn Statement<ID, Vertex>, id: ID) -> FnResult<IntersectionEntry> {
+    let neighbors = stmt
+        .exec(id)?
+        .map(|vertex| vertex.id())
+        .filter(|neighbor| *neighbor != id);
+    Ok(IntersectionEntry::from_iter(neighbors))
+}
+
+fn probe(id: ID, record: Record, neighbors: IntersectionEntry) -> Vec<TriangleMessage> {
+    let mut targets: Vec<ID> = neighbors.iter().cloned().collect();
+    targets.dedup();
+    let mut messages = Vec::with_capacity(targets.len() + 1);
+    messages.push(TriangleMessage::Degree(id, record, targets.len() as u64));
+    for target in targets {
+        messages.push(TriangleMessage::Probe(target, id, neighbors.clone()));
+    }
+    messages
+}
+
+/// Probe the neighbors of the vertices with the neighbors of the vertices.
+pub struct ProbeOperator {
+    stmt: Box<dyn Statement<ID, Vertex>>,
+}
+
+impl FlatMapFunction<TriangleMessage, TriangleMessage> for ProbeOperator {
+    type Target = DynIter<TriangleMessage>;
+
+    fn exec(&self, input: TriangleMessage) -> FnResult<Self::Target> {
+        match input {
+            TriangleMessage::Vertex(id, record) => {
+                let neighbors = explore_neighbors(self.stmt.as_ref(), id)?;
+                Ok(Box::new(probe(id, record, neighbors).into_iter()))
+            }
+            _ => Ok(Box::new(std::iter::once(input))),
+        }
+    }
+}
+
+/// Intersect the neighbors in the probes with the neighbors of the probed vertices, and send the
+/// numbers of the common neighbors back.
+pub struct IntersectOperator {
+    stmt: Box<dyn Statement<ID, Vertex>>,
+}
+
+impl FlatMapFunction<TriangleMessage, TriangleMessage> for IntersectOperator {
+    type Target = DynIter<TriangleMessage>;
+
+    fn exec(&self, input: TriangleMessage) -> FnResult<Self::Target> {
+        match input {
+            TriangleMessage::Probe(target, source, neighbors) => {
+                let common = neighbors.common_len(&explore_neighbors(self.stmt.as_ref(), target)?);
+                Ok(Box::new(std::iter::once(TriangleMessage::Common(source, common as u64))))
+            }
+            _ => Ok(Box::new(std::iter::once(input))),
+        }
+    }
+}
+
+/// Sum up the common neighbors of the vertices. The messages to the vertices which are not in the
+/// input are dropped.
+#[derive(Clone, Debug)]
+pub struct TriangleAccum {
+    states: HashMap<ID, (Option<(Record, u64)>, u64)>,
+}
+
+impl Accumulator<TriangleMessage, DynIter<TriangleMessage>> for TriangleAccum {
+    fn accum(&mut self, next: TriangleMessage) -> FnExecResult<()> {
+        match next {
+            TriangleMessage::Degree(id, record, degree) => {
+                self.states.entry(id).or_default().0 = Some((record, degree));
+            }
+            TriangleMessage::Common(id, common) => {
+                self.states.entry(id).or_default().1 += common;
+            }
+            _ => Err(FnExecError::unexpected_data_error(&format!(
+                "unexpected message {:?} in TriangleAccum",
+                next
+            )))?,
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> FnExecResult<DynIter<TriangleMessage>> {
+        let states = std::mem::replace(&mut self.states, HashMap::new());
+        let counted = states
+            .into_iter()
+            .filter_map(|(_, (vertex, common))| {
+                vertex.map(|(record, degree)| TriangleMessage::Counted(record, common / 2, degree))
+            });
+        Ok(Box::new(counted))
+    }
+}
+
+/// The summary of the triangles of all the vertices, for the global output.
+#[derive(Clone, Debug, Default)]
+pub struct TriangleSummary {
+    triangles: u64,
+    coefficients: f64,
+    vertices: u64,
+}
+
+impl TriangleSummary {
+    pub fn add(mut self, message: TriangleMessage) -> FnResult<Self> {
+        if let TriangleMessage::Counted(_, triangles, degree) = message {
+            self.triangles += triangles;
+            self.coefficients += clustering_coefficient(triangles, degree);
+            self.vertices += 1;
+        }
+        Ok(self)
+    }
+
+    /// The number of the triangles, each of which is counted by its three vertices, or the average
+    /// clustering coefficient of the vertices.
+    pub fn finish(self, kind: algebra_pb::graph_algorithm::Kind, alias: Option<KeyId>) -> Record {
+        match kind {
+            algebra_pb::graph_algorithm::Kind::ClusteringCoefficient => {
+                let average =
+                    if self.vertices == 0 { 0.0 } else { self.coefficients / self.vertices as f64 };
+                Record::new(object!(average), alias)
+            }
+            _ => Record::new(object!(self.triangles / 3), alias),
+        }
+    }
+}
+
+/// The functions of triangle counting, which outputs per vertex, or once for all if `global`.
+pub struct TriangleOperator {
+    pub kind: algebra_pb::graph_algorithm::Kind,
+    pub global: bool,
+    pub alias: Option<KeyId>,
+    pub probe: ProbeOperator,
+    pub intersect: IntersectOperator,
+    pub accum: TriangleAccum,
+}
+
+impl TriangleOperator {
+    pub fn new(
+        kind: algebra_pb::graph_algorithm::Kind, global: bool, alias: Option<KeyId>,
+        probe_stmt: Box<dyn Statement<ID, Vertex>>, intersect_stmt: Box<dyn Statement<ID, Vertex>>,
+    ) -> Self {
+        TriangleOperator {
+            kind,
+            global,
+            alias,
+            probe: ProbeOperator { stmt: probe_stmt },
+            intersect: IntersectOperator { stmt: intersect_stmt },
+            accum: TriangleAccum { states: HashMap::new() },
+        }
+    }
+}
+
+impl Encode for TriangleMessage {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            TriangleMessage::Vertex(id, record) => {
+                writer.write_u8(0)?;
+                writer.write_i64(*id)?;
+                record.write_to(writer)?;
+            }
+            TriangleMessage::Probe(target, source, neighbors) => {
+                writer.write_u8(1)?;
+                writer.write_i64(*target)?;
+                writer.write_i64(*source)?;
+                neighbors.write_to(writer)?;
+            }
+            TriangleMessage::Degree(id, record, degree) => {
+                writer.write_u8(2)?;
+                writer.write_i64(*id)?;
+                record.write_to(writer)?;
+                writer.write_u64(*degree)?;
+            }
+            TriangleMessage::Common(id, common) => {
+                writer.write_u8(3)?;
+                writer.write_i64(*id)?;
+                writer.write_u64(*common)?;
+            }
+            TriangleMessage::Counted(record, triangles, degree) => {
+                writer.write_u8(4)?;
+                record.write_to(writer)?;
+                writer.write_u64(*triangles)?;
+                writer.write_u64(*degree)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for TriangleMessage {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        match reader.read_u8()? {
+            0 => {
+                let id = reader.read_i64()?;
+                let record = Record::read_from(reader)?;
+                Ok(TriangleMessage::Vertex(id, record))
+            }
+            1 => {
+                let target = reader.read_i64()?;
+                let source = reader.read_i64()?;
+                let neighbors = IntersectionEntry::read_from(reader)?;
+                Ok(TriangleMessage::Probe(target, source, neighbors))
+            }
+            2 => {
+                let id = reader.read_i64()?;
+                let record = Record::read_from(reader)?;
+                let degree = reader.read_u64()?;
+                Ok(TriangleMessage::Degree(id, record, degree))
+            }
+            3 => {
+                let id = reader.read_i64()?;
+                let common = reader.read_u64()?;
+                Ok(TriangleMessage::Common(id, common))
+            }
+            4 => {
+                let record = Record::read_from(reader)?;
+                let triangles = reader.read_u64()?;
+                let degree = reader.read_u64()?;
+                Ok(TriangleMessage::Counted(record, triangles, degree))
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Other, "unreachable")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::DynDetails;
+
+    use super::*;
+    use crate::process::operator::tests::PERSON_LABEL;
+
+    fn vertex_record(id: ID) -> Record {
+        Record::new(Vertex::new(id, Some(PERSON_LABEL), DynDetails::default()), None)
+    }
+
+    /// Count the triangles of all the vertices in the undirected graph of the edges, by the probes,
+    /// the intersections and the accumulation as in the operators.
+    fn count_triangles(edges: &[(ID, ID)]) -> HashMap<ID, (u64, u64)> {
+        let mut adjacency: HashMap<ID, Vec<ID>> = HashMap::new();
+        for (src, dst) in edges {
+            adjacency.entry(*src).or_default().push(*dst);
+            adjacency.entry(*dst).or_default().push(*src);
+        }
+        let neighbors_of = |id: ID| {
+            let neighbors = adjacency.get(&id).cloned().unwrap_or_default();
+            IntersectionEntry::from_iter(
+                neighbors
+                    .into_iter()
+                    .filter(move |neighbor| *neighbor != id),
+            )
+        };
+        let mut accum = TriangleAccum { states: HashMap::new() };
+        for id in adjacency.keys() {
+            for message in probe(*id, vertex_record(*id), neighbors_of(*id)) {
+                match message {
+                    TriangleMessage::Probe(target, source, neighbors) => {
+                        let common = neighbors.common_len(&neighbors_of(target));
+                        accum
+                            .accum(TriangleMessage::Common(source, common as u64))
+                            .unwrap();
+                    }
+                    _ => accum.accum(message).unwrap(),
+                }
+            }
+        }
+        accum
+            .finalize()
+            .unwrap()
+            .map(|message| match message {
+                TriangleMessage::Counted(record, triangles, degree) => {
+                    let id = record
+                        .get(None)
+                        .unwrap()
+                        .as_vertex()
+                        .unwrap()
+                        .id();
+                    (id, (triangles, degree))
+                }
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn triangle_count_clique_test() {
+        // the clique of 4 vertices, with a duplicated edge and a self-loop
+        let edges = vec![(1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4), (2, 1), (3, 3)];
+        let counted = count_triangles(&edges);
+        assert_eq!(counted.len(), 4);
+        for (_, (triangles, degree)) in counted {
+            assert_eq!((triangles, degree), (3, 3));
+            assert_eq!(clustering_coefficient(triangles, degree), 1.0);
+        }
+    }
+
+    #[test]
+    fn triangle_count_skewed_test() {
+        // a hub connected to 1000 leaves, with a chain among the first 10 leaves
+        let hub = 0;
+        let mut edges: Vec<(ID, ID)> = (1..=1000).map(|leaf| (hub, leaf)).collect();
+        edges.extend((1..10).map(|leaf| (leaf, leaf + 1)));
+        let counted = count_triangles(&edges);
+        assert_eq!(counted.len(), 1001);
+        assert_eq!(counted[&hub], (9, 1000));
+        assert_eq!(counted[&1], (1, 2));
+        assert_eq!(counted[&5], (2, 3));
+        assert_eq!(counted[&500], (0, 1));
+        let total: u64 = counted
+            .values()
+            .map(|(triangles, _)| *triangles)
+            .sum();
+        assert_eq!(total / 3, 9);
+        assert_eq!(clustering_coefficient(2, 3), 2.0 / 3.0);
+        assert_eq!(clustering_coefficient(0, 1), 0.0);
+    }
+
+    #[test]
+    fn triangle_summary_test() {
+        let summary = TriangleSummary::default()
+            .add(TriangleMessage::Counted(vertex_record(1), 1, 2))
+            .unwrap()
+            .add(TriangleMessage::Counted(vertex_record(2), 1, 2))
+            .unwrap()
+            .add(TriangleMessage::Counted(vertex_record(3), 1, 3))
+            .unwrap()
+            .add(TriangleMessage::Counted(vertex_record(4), 0, 1))
+            .unwrap();
+        let kind = algebra_pb::graph_algorithm::Kind::TriangleCount;
+        let record = summary.clone().finish(kind, None);
+        assert_eq!(record.get(None).unwrap().as_object(), Some(&object!(1u64)));
+        let kind = algebra_pb::graph_algorithm::Kind::ClusteringCoefficient;
+        let record = summary.finish(kind, None);
+        let average = (1.0 + 1.0 + 1.0 / 3.0) / 4.0;
+        assert_eq!(record.get(None).unwrap().as_object(), Some(&object!(average)));
+    }
+}
@@ -0,0 +1,51 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The coalesce operator evaluates its sub-plans on each record in order, each applied to the record
+//! as a sub-task, until one of them derives any records, which are the results of the record. The
+//! records a sub-plan derives nothing from are split from the results and given to the next sub-plan,
+//! so the sub-plans after are never evaluated on the records with the results. The last sub-plan is
+//! applied to the records left as a sub-task as well, as its results are bounded per record, e.g., by
+//! a `limit` or a `dedup` in it, rather than across all the records left.
+
+use pegasus::api::Branch;
+use pegasus::stream::Stream;
+use pegasus::BuildJobError;
+
+use crate::process::record::Record;
+
+/// Split the records applied with a sub-plan into the records derived by the sub-plan, and the
+/// records the sub-plan derives nothing from, which are to be given to the next sub-plan.
+pub fn split_coalesced(
+    stream: Stream<(Record, Vec<Record>)>,
+) -> Result<(Stream<Record>, Stream<Record>), BuildJobError> {
+    stream.branch("Coalesce", |_info| {
+        |input, derived, left| {
+            input.for_each_batch(|batch| {
+                let mut derived_session = derived.new_session(&batch.tag)?;
+                let mut left_session = left.new_session(&batch.tag)?;
+                for (parent, sub) in batch.drain() {
+                    if sub.is_empty() {
+                        left_session.give(parent)?;
+                    }
+                    for record in sub {
+                        derived_session.give(record)?;
+                    }
+                }
+                Ok(())
+            })
+        }
+    })
+}
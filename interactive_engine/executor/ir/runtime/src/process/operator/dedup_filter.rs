@@ -0,0 +1,444 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The pre-pass of the dedup, which drops the duplicates before they're shuffled to the exact dedup,
+//! and the exact dedup spilling the records to the disk.
+//!
+//! Each worker keeps a bloom filter of the keys it has passed, shared by the scopes, i.e., of the
+//! hashes of the keys with their scopes, and the exact keys of a scope passed again as the bloom
+//! filter may have passed them, i.e., the repeated keys, up to `repeats` of them. A record is dropped
+//! only if its key is a repeated one, which is certainly passed before, so no distinct record is
+//! dropped by the false positives of the bloom filter, nor by clearing it once it's filled or all the
+//! scopes are ended. A key is passed by a worker at most twice, or more once the repeated keys are
+//! full, and the exact dedup after the shuffle gets the keys passed rather than all the duplicates,
+//! e.g., of the paths of multi-path traversals.
+//!
+//! The exact dedup of a scope keeps up to `keys` keys in the memory, and the records of the other keys
+//! are spilled to the buckets of their keys in the spill directory, which are deduplicated one after
+//! another as the scope is ended.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use pegasus::api::function::{DynError, FnResult};
+use pegasus::api::{Key, Pair, PartitionByKey, Unary};
+use pegasus::codec::{Decode, Encode};
+use pegasus::stream::Stream;
+use pegasus::tag::tools::map::TidyTagMap;
+use pegasus::tag::Tag;
+use pegasus::{BuildJobError, Data};
+
+/// The bits of a key set in the bloom filter.
+const BLOOM_HASHES: u64 = 4;
+
+/// The buckets of the records spilled by the exact dedup of a scope.
+const SPILL_BUCKETS: usize = 16;
+
+/// The sequence of the spilled scopes, naming their files.
+static SPILL_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DedupFilter {
+    /// The bits of the bloom filter of a worker.
+    pub bits: usize,
+    /// The most repeated keys kept by a worker in a scope.
+    pub repeats: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DedupSpill {
+    /// The most keys kept in the memory by the exact dedup of a worker in a scope.
+    pub keys: usize,
+    /// The directory of the files of the records spilled.
+    pub dir: PathBuf,
+}
+
+/// The bloom filter of the hashes of the keys, whose bits are set by double hashing.
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    inserted: usize,
+}
+
+impl BloomFilter {
+    pub fn new(bits: usize) -> Self {
+        BloomFilter { bits: vec![0; (bits.max(1) + 63) / 64], inserted: 0 }
+    }
+
+    /// Insert the hash, and return whether it may be inserted before.
+    pub fn insert(&mut self, hash: u64) -> bool {
+        let len = self.bits.len() as u64 * 64;
+        let (h1, h2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        let mut inserted = true;
+        for i in 0..BLOOM_HASHES {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % len;
+            let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+            inserted &= self.bits[word] & mask != 0;
+            self.bits[word] |= mask;
+        }
+        self.inserted += 1;
+        inserted
+    }
+
+    /// Whether the hashes inserted are so many that most hashes may be inserted before, i.e., more
+    /// than the bits over the bits set by a hash.
+    fn is_full(&self) -> bool {
+        self.inserted as u64 * BLOOM_HASHES > self.bits.len() as u64 * 64
+    }
+
+    fn clear(&mut self) {
+        if self.inserted > 0 {
+            self.bits.iter_mut().for_each(|word| *word = 0);
+            self.inserted = 0;
+        }
+    }
+}
+
+/// The pre-pass of the keys of a worker in all the scopes.
+pub struct KeyFilter<K> {
+    bloom: BloomFilter,
+    repeats: TidyTagMap<HashSet<K>>,
+    capacity: usize,
+}
+
+impl<K: Hash + Eq + Clone> KeyFilter<K> {
+    pub fn new(filter: DedupFilter, scope_level: u32) -> Self {
+        KeyFilter {
+            bloom: BloomFilter::new(filter.bits),
+            repeats: TidyTagMap::new(scope_level),
+            capacity: filter.repeats,
+        }
+    }
+
+    /// Whether the record of the key in the scope is passed, or dropped as it's certainly passed before.
+    pub fn pass(&mut self, tag: &Tag, key: &K) -> bool {
+        let repeats = self.repeats.get_mut_or_else(tag, HashSet::new);
+        if repeats.contains(key) {
+            return false;
+        }
+        if self.bloom.is_full() {
+            self.bloom.clear();
+        }
+        let mut hasher = DefaultHasher::new();
+        tag.hash(&mut hasher);
+        key.hash(&mut hasher);
+        if self.bloom.insert(hasher.finish()) && repeats.len() < self.capacity {
+            repeats.insert(key.clone());
+        }
+        true
+    }
+
+    /// End the scope, clearing the bloom filter if all the scopes are ended.
+    pub fn end(&mut self, tag: &Tag) {
+        self.repeats.remove(tag);
+        if self.repeats.is_empty() {
+            self.bloom.clear();
+        }
+    }
+}
+
+/// Drop the duplicates of the keyed records by the pre-pass of each worker, before the exact dedup.
+pub fn filter_duplicates<K, V>(
+    stream: Stream<Pair<K, V>>, filter: DedupFilter,
+) -> Result<Stream<Pair<K, V>>, BuildJobError>
+where
+    K: Data + Key,
+    V: Data,
+{
+    stream.unary("DedupFilter", move |info| {
+        let mut key_filter = KeyFilter::new(filter, info.scope_level);
+        move |input, output| {
+            input.for_each_batch(|batch| {
+                if !batch.is_empty() {
+                    let mut session = output.new_session(&batch.tag)?;
+                    for pair in batch.drain() {
+                        if key_filter.pass(&session.tag, &pair.key) {
+                            session.give(pair)?;
+                        }
+                    }
+                }
+                if batch.is_last() {
+                    key_filter.end(&batch.tag);
+                }
+                Ok(())
+            })
+        }
+    })
+}
+
+/// The keys of the exact dedup of a worker in a scope, with the records spilled.
+pub struct SpilledSet<K, V> {
+    keys: HashSet<K>,
+    capacity: usize,
+    dir: PathBuf,
+    seq: u64,
+    buckets: Vec<Option<BufWriter<File>>>,
+    // the next bucket to deduplicate as the scope is ended
+    cursor: usize,
+    _value: std::marker::PhantomData<V>,
+}
+
+impl<K: Data + Key, V: Data> SpilledSet<K, V> {
+    pub fn new(spill: &DedupSpill) -> Self {
+        SpilledSet {
+            keys: HashSet::new(),
+            capacity: spill.keys,
+            dir: spill.dir.clone(),
+            seq: SPILL_SEQ.fetch_add(1, Ordering::Relaxed),
+            buckets: (0..SPILL_BUCKETS).map(|_| None).collect(),
+            cursor: 0,
+            _value: std::marker::PhantomData,
+        }
+    }
+
+    /// The record if its key is not seen before and is kept in the memory, or else it's dropped or
+    /// spilled.
+    pub fn insert(&mut self, pair: Pair<K, V>) -> FnResult<Option<Pair<K, V>>> {
+        if self.keys.contains(&pair.key) {
+            Ok(None)
+        } else if self.keys.len() < self.capacity {
+            self.keys.insert(pair.key.clone());
+            Ok(Some(pair))
+        } else {
+            let bucket = bucket_of(&pair.key);
+            if self.buckets[bucket].is_none() {
+                let file = File::create(spill_path(&self.dir, self.seq, bucket))?;
+                self.buckets[bucket] = Some(BufWriter::new(file));
+            }
+            let mut bytes = vec![];
+            pair.write_to(&mut bytes)?;
+            let writer = self.buckets[bucket].as_mut().unwrap();
+            writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+            writer.write_all(&bytes)?;
+            Ok(None)
+        }
+    }
+
+    /// The distinct records of the next bucket spilled, whose keys are not kept in the memory.
+    pub fn next_bucket(&mut self) -> FnResult<Option<Vec<Pair<K, V>>>> {
+        while self.cursor < SPILL_BUCKETS {
+            let bucket = self.cursor;
+            self.cursor += 1;
+            if let Some(writer) = self.buckets[bucket].take() {
+                let file = writer
+                    .into_inner()
+                    .map_err(|e| Box::new(e.into_error()) as DynError)?;
+                drop(file);
+                let path = spill_path(&self.dir, self.seq, bucket);
+                let records = read_distinct(&path);
+                let _ = std::fs::remove_file(&path);
+                return records.map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<K, V> Drop for SpilledSet<K, V> {
+    fn drop(&mut self) {
+        // the buckets not deduplicated, e.g., of the job cancelled
+        for (bucket, writer) in self.buckets.iter_mut().enumerate() {
+            if writer.take().is_some() {
+                let _ = std::fs::remove_file(spill_path(&self.dir, self.seq, bucket));
+            }
+        }
+    }
+}
+
+fn spill_path(dir: &Path, seq: u64, bucket: usize) -> PathBuf {
+    dir.join(format!("dedup-{}-{}-{}", std::process::id(), seq, bucket))
+}
+
+fn bucket_of<K: Hash>(key: &K) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % SPILL_BUCKETS as u64) as usize
+}
+
+fn read_distinct<K: Data + Key, V: Data>(path: &Path) -> FnResult<Vec<Pair<K, V>>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut keys = HashSet::new();
+    let mut records = vec![];
+    let mut len = [0u8; 4];
+    loop {
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(records),
+            Err(e) => Err(e)?,
+        }
+        let mut bytes = vec![0; u32::from_le_bytes(len) as usize];
+        reader.read_exact(&mut bytes)?;
+        let pair = Pair::<K, V>::read_from(&mut &bytes[..])?;
+        if keys.insert(pair.key.clone()) {
+            records.push(pair);
+        }
+    }
+}
+
+/// Drop the duplicates of the keyed records exactly, with the records of the keys beyond those kept in
+/// the memory spilled to the disk.
+pub fn dedup_spilled<K, V>(
+    stream: Stream<Pair<K, V>>, spill: DedupSpill,
+) -> Result<Stream<Pair<K, V>>, BuildJobError>
+where
+    K: Data + Key,
+    V: Data,
+{
+    stream
+        .partition_by_key()?
+        .unary("dedup", move |info| {
+            let mut table = TidyTagMap::<SpilledSet<K, V>>::new(info.scope_level);
+            move |input, output| {
+                input.for_each_batch(|batch| {
+                    if !batch.is_empty() {
+                        let mut session = output.new_session(&batch.tag)?;
+                        let set = table.get_mut_or_else(&batch.tag, || SpilledSet::new(&spill));
+                        for pair in batch.drain() {
+                            if let Some(pair) = set.insert(pair)? {
+                                session.give(pair)?;
+                            }
+                        }
+                    }
+                    if batch.is_last() {
+                        // the end interrupted by the output blocked is resumed from the bucket after
+                        if let Some(set) = table.get_mut(&batch.tag) {
+                            let mut session = output.new_session(&batch.tag)?;
+                            while let Some(records) = set.next_bucket()? {
+                                session.give_iterator(records.into_iter())?;
+                            }
+                        }
+                        table.remove(&batch.tag);
+                    }
+                    Ok(())
+                })
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloom_filter_test() {
+        let mut bloom = BloomFilter::new(1 << 12);
+        assert!(!bloom.insert(1));
+        assert!(!bloom.insert(2));
+        assert!(bloom.insert(1));
+        assert!(bloom.insert(2));
+        // the bits are rounded up to a word
+        assert_eq!(BloomFilter::new(1).bits.len(), 1);
+        // a word is full of the hashes setting more bits than it
+        let mut bloom = BloomFilter::new(64);
+        (0..16).for_each(|hash| {
+            bloom.insert(hash);
+        });
+        assert!(!bloom.is_full());
+        bloom.insert(16);
+        assert!(bloom.is_full());
+        bloom.clear();
+        assert!(!bloom.is_full());
+        assert!(!bloom.insert(1));
+    }
+
+    #[test]
+    fn key_filter_test() {
+        // the small bloom filter has many false positives, which never drop a distinct key
+        let root = Tag::Root;
+        for bits in vec![64, 1 << 16] {
+            let mut key_filter = KeyFilter::new(DedupFilter { bits, repeats: 1000 }, 0);
+            let mut passed = vec![0; 100];
+            for _ in 0..5 {
+                for key in 0..100 {
+                    if key_filter.pass(&root, &key) {
+                        passed[key] += 1;
+                    }
+                }
+            }
+            assert!(passed
+                .iter()
+                .all(|times| *times == 1 || *times == 2));
+        }
+
+        // the duplicates are passed once the repeated keys are full
+        let mut key_filter = KeyFilter::new(DedupFilter { bits: 1 << 16, repeats: 1 }, 0);
+        assert!(key_filter.pass(&root, &1) && key_filter.pass(&root, &2));
+        assert!(key_filter.pass(&root, &1) && key_filter.pass(&root, &2));
+        assert!(!key_filter.pass(&root, &1));
+        assert!(key_filter.pass(&root, &2));
+    }
+
+    #[test]
+    fn key_filter_scopes_test() {
+        // the scopes share the bloom filter, and the keys of a scope are never dropped in another
+        let (first, second) = (Tag::One(1), Tag::One(2));
+        let mut key_filter = KeyFilter::new(DedupFilter { bits: 1 << 16, repeats: 100 }, 1);
+        assert!(key_filter.pass(&first, &1) && key_filter.pass(&first, &1));
+        assert!(!key_filter.pass(&first, &1));
+        assert!(key_filter.pass(&second, &1));
+        key_filter.end(&first);
+        assert!(key_filter.bloom.inserted > 0);
+        assert!(key_filter.pass(&first, &1));
+        // the bloom filter is cleared as all the scopes are ended
+        key_filter.end(&first);
+        key_filter.end(&second);
+        assert_eq!(key_filter.bloom.inserted, 0);
+    }
+
+    fn spill_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dedup_spill_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn spilled_set_test() {
+        let dir = spill_dir("set");
+        let mut set = SpilledSet::<u32, u32>::new(&DedupSpill { keys: 4, dir: dir.clone() });
+        let mut passed = vec![];
+        for value in 0..100u32 {
+            if let Some(pair) = set
+                .insert(Pair { key: value % 20, value })
+                .unwrap()
+            {
+                passed.push(pair.key);
+            }
+        }
+        // the keys kept in the memory are passed as they're inserted
+        assert_eq!(passed, vec![0, 1, 2, 3]);
+        assert!(std::fs::read_dir(&dir).unwrap().count() > 0);
+        while let Some(records) = set.next_bucket().unwrap() {
+            passed.extend(records.into_iter().map(|pair| {
+                // the first record of a key spilled is kept
+                assert_eq!(pair.key, pair.value);
+                pair.key
+            }));
+        }
+        passed.sort();
+        assert_eq!(passed, (0..20).collect::<Vec<_>>());
+        // the files are removed once deduplicated
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        let mut set = SpilledSet::<u32, u32>::new(&DedupSpill { keys: 0, dir: dir.clone() });
+        set.insert(Pair { key: 1, value: 1 }).unwrap();
+        drop(set);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
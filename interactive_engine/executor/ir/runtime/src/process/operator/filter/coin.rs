@@ -13,15 +13,18 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
 use ir_common::error::ParsePbError;
 use ir_common::generated::algebra as algebra_pb;
 use pegasus::api::function::FilterFunction;
 use pegasus::api::function::FnResult;
-use rand::prelude::StdRng;
-use rand::{Rng, SeedableRng};
+use rand::{thread_rng, Rng};
 
 use crate::error::FnGenError;
 use crate::error::FnGenResult;
+use crate::process::operator::deterministic_seed;
 use crate::process::operator::filter::FilterFuncGen;
 use crate::process::record::Record;
 
@@ -32,16 +35,17 @@ struct CoinOperator {
 }
 
 impl FilterFunction<Record> for CoinOperator {
-    fn test(&self, _input: &Record) -> FnResult<bool> {
-        let mut rng = if let Some(seed) = self.seed {
-            StdRng::seed_from_u64(seed as u64)
-        } else {
-            StdRng::from_entropy()
-        };
-        if rng.gen_bool(self.ratio) {
-            Ok(true)
+    fn test(&self, input: &Record) -> FnResult<bool> {
+        if let Some(seed) = self.seed {
+            // the coin of a record is decided by the seed and its head, so that the same records are
+            // sampled in every run with the seed, wherever they are processed.
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            input.get(None).hash(&mut hasher);
+            let coin = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+            Ok(coin < self.ratio)
         } else {
-            Ok(false)
+            Ok(thread_rng().gen_bool(self.ratio))
         }
     }
 }
@@ -59,7 +63,8 @@ impl FilterFuncGen for algebra_pb::Sample {
                             "SampleByRatio ratio should be in [0, 1]".into(),
                         ));
                     }
-                    let coin = CoinOperator { seed: self.seed, ratio: ratio.ratio };
+                    let seed = self.seed.or_else(deterministic_seed);
+                    let coin = CoinOperator { seed, ratio: ratio.ratio };
                     if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
                         debug!("Runtime coin operator: {:?}", coin);
                     }
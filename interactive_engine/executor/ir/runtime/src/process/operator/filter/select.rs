@@ -15,6 +15,7 @@
 
 use std::convert::TryInto;
 
+use graph_proxy::utils::expr::eval_compiled::CompiledPredicates;
 use graph_proxy::utils::expr::eval_pred::{EvalPred, PEvaluator};
 use ir_common::error::ParsePbError;
 use ir_common::generated::algebra as pb;
@@ -27,14 +28,18 @@ use crate::process::record::Record;
 #[derive(Debug)]
 struct SelectOperator {
     pub filter: PEvaluator,
+    /// The filter specialized at the time of building the plan, if any, which is evaluated instead
+    pub compiled: Option<CompiledPredicates>,
 }
 
 impl FilterFunction<Record> for SelectOperator {
     fn test(&self, input: &Record) -> FnResult<bool> {
-        let res = self
-            .filter
-            .eval_bool(Some(input))
-            .map_err(|e| FnExecError::from(e))?;
+        let res = if let Some(compiled) = self.compiled.as_ref() {
+            compiled.eval_bool(Some(input))
+        } else {
+            self.filter.eval_bool(Some(input))
+        }
+        .map_err(|e| FnExecError::from(e))?;
         Ok(res)
     }
 }
@@ -42,7 +47,9 @@ impl FilterFunction<Record> for SelectOperator {
 impl FilterFuncGen for pb::Select {
     fn gen_filter(self) -> FnGenResult<Box<dyn FilterFunction<Record>>> {
         if let Some(predicate) = self.predicate {
-            let select_operator = SelectOperator { filter: predicate.try_into()? };
+            let filter: PEvaluator = predicate.try_into()?;
+            let compiled = CompiledPredicates::compile(&filter);
+            let select_operator = SelectOperator { filter, compiled };
             if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
                 debug!("Runtime select operator: {:?}", select_operator);
             }
@@ -35,6 +35,27 @@ pub struct EdgeExpandOperator<E: Entry> {
     expand_opt: ExpandOpt,
 }
 
+/// The edge expand counting the adjacency of the start vertices, so that the records expanded from
+/// the high-degree vertices can be split among the workers, see `split_expand`.
+pub trait CountExpand: FlatMapFunction<Record, Record, Target = DynIter<Record>> {
+    /// Count the adjacency of the start vertex of the record up to `limit`, `None` if it can't be split,
+    /// e.g., to get the degree or to expand a path.
+    fn count(&self, input: &Record, limit: usize) -> FnResult<Option<usize>>;
+}
+
+impl<E: Entry + 'static> CountExpand for EdgeExpandOperator<E> {
+    fn count(&self, input: &Record, limit: usize) -> FnResult<Option<usize>> {
+        match input.get(self.start_v_tag) {
+            Some(entry)
+                if entry.get_type() == EntryType::Vertex && self.expand_opt != ExpandOpt::Degree =>
+            {
+                Ok(Some(self.stmt.exec(entry.id())?.take(limit).count()))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
 impl<E: Entry + 'static> FlatMapFunction<Record, Record> for EdgeExpandOperator<E> {
     type Target = DynIter<Record>;
 
@@ -99,6 +120,16 @@ impl FlatMapFuncGen for pb::EdgeExpand {
     fn gen_flat_map(
         self,
     ) -> FnGenResult<Box<dyn FlatMapFunction<Record, Record, Target = DynIter<Record>>>> {
+        Ok(Box::new(self.gen_count_expand()?))
+    }
+}
+
+pub trait CountExpandGen {
+    fn gen_count_expand(self) -> FnGenResult<Box<dyn CountExpand>>;
+}
+
+impl CountExpandGen for pb::EdgeExpand {
+    fn gen_count_expand(self) -> FnGenResult<Box<dyn CountExpand>> {
         if self.is_optional {
             return Err(FnGenError::unsupported_error("optional edge expand in EdgeExpandOperator"));
         }
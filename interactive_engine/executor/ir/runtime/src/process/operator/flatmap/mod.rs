@@ -17,6 +17,7 @@ mod fused;
 mod get_v;
 mod unfold;
 
+pub use edge_expand::{CountExpand, CountExpandGen};
 use pegasus::api::function::{DynIter, FlatMapFunction};
 
 use crate::error::FnGenResult;
@@ -20,7 +20,7 @@ use pegasus::api::function::{DynIter, FlatMapFunction, FnResult};
 use pegasus_common::downcast::AsAny;
 
 use crate::error::{FnExecError, FnGenResult};
-use crate::process::entry::{CollectionEntry, Entry, EntryType};
+use crate::process::entry::{CollectionEntry, DynEntry, Entry, EntryType, MapEntry};
 use crate::process::operator::flatmap::FlatMapFuncGen;
 use crate::process::operator::map::{GeneralIntersectionEntry, IntersectionEntry};
 use crate::process::record::Record;
@@ -102,6 +102,23 @@ impl FlatMapFunction<Record, Record> for UnfoldOperator {
                 }
                 Ok(Box::new(res.into_iter()))
             }
+            EntryType::Map => {
+                // unfolded as the pairs of the map, e.g., the roots of the trees with their subtrees
+                let entry = input.get(self.tag).unwrap();
+                let map = entry
+                    .as_any_ref()
+                    .downcast_ref::<MapEntry>()
+                    .ok_or_else(|| {
+                        FnExecError::unexpected_data_error("downcast map entry in UnfoldOperator")
+                    })?;
+                let mut res = Vec::with_capacity(map.len());
+                for pair in map.inner.iter().cloned() {
+                    let mut new_entry = input.clone();
+                    new_entry.append(DynEntry::from(pair), self.alias);
+                    res.push(new_entry);
+                }
+                Ok(Box::new(res.into_iter()))
+            }
             EntryType::Path => {
                 let entry = input.get(self.tag).unwrap();
                 let path = entry.as_graph_path().ok_or_else(|| {
@@ -191,6 +208,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 5, // ToList
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let unfold_opr_pb = pb::Unfold { tag: Some(TAG_A.into()), alias: None };
@@ -221,6 +239,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 5, // ToList
             alias: None,
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let unfold_opr_pb = pb::Unfold { tag: None, alias: None };
@@ -245,6 +264,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 5, // ToList
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let unfold_opr_pb = pb::Unfold { tag: None, alias: None };
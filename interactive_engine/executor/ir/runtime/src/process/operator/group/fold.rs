@@ -107,6 +107,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 3, // count
             alias: None,
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = count_test(init_source(), fold_opr_pb);
@@ -126,6 +127,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 3, // count
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let fold_opr_pb = pb::GroupBy { mappings: vec![], functions: vec![function] };
         let mut result = count_test(init_source(), fold_opr_pb);
@@ -151,6 +151,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 5, // ToList
             alias: Some(TAG_B.into()),
+            udf: String::new(),
         };
         let key_alias = pb::group_by::KeyAlias {
             key: Some(common_pb::Variable::from("@".to_string())),
@@ -183,6 +184,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 5, // ToList
             alias: Some(TAG_B.into()),
+            udf: String::new(),
         };
         let key_alias = pb::group_by::KeyAlias {
             key: Some(common_pb::Variable::from("@.name".to_string())),
@@ -224,6 +226,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 5, // ToList
             alias: Some(TAG_C.into()),
+            udf: String::new(),
         };
         let key_alias_1 = pb::group_by::KeyAlias {
             key: Some(common_pb::Variable::from("@.id".to_string())),
@@ -271,11 +274,13 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 5, // ToList
             alias: Some(TAG_A.into()),
+            udf: String::new(),
         };
         let function_2 = pb::group_by::AggFunc {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 3, // Count
             alias: Some(TAG_B.into()),
+            udf: String::new(),
         };
         let key_alias = pb::group_by::KeyAlias {
             key: Some(common_pb::Variable::from("@".to_string())),
@@ -319,6 +324,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 3, // Count
             alias: Some(TAG_B.into()),
+            udf: String::new(),
         };
         let key_alias = pb::group_by::KeyAlias {
             key: Some(common_pb::Variable::from("@".to_string())),
@@ -350,6 +356,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 3, // Count
             alias: Some(TAG_B.into()),
+            udf: String::new(),
         };
         let key_alias = pb::group_by::KeyAlias {
             key: Some(common_pb::Variable::from("@.name".to_string())),
@@ -380,6 +387,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@.age".to_string())],
             aggregate: 1, // min
             alias: Some(TAG_B.into()),
+            udf: String::new(),
         };
         let key_alias = pb::group_by::KeyAlias {
             key: Some(common_pb::Variable::from("@.name".to_string())),
@@ -409,6 +417,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@.age".to_string())],
             aggregate: 2, // max
             alias: Some(TAG_B.into()),
+            udf: String::new(),
         };
         let key_alias = pb::group_by::KeyAlias {
             key: Some(common_pb::Variable::from("@.name".to_string())),
@@ -438,6 +447,7 @@ mod tests {
             vars: vec![common_pb::Variable::from("@".to_string())],
             aggregate: 8, // First
             alias: Some(TAG_B.into()),
+            udf: String::new(),
         };
         let key_alias = pb::group_by::KeyAlias {
             key: Some(common_pb::Variable::from("@.name".to_string())),
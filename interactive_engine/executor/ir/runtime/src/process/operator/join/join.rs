@@ -96,11 +96,11 @@ mod tests {
                 let left_stream = s1
                     .key_by(move |record| left_key_selector.get_kv(record))?
                     // TODO(bingqing): remove this when new keyed-join in gaia-x is ready;
-                    .partition_by_key();
+                    .partition_by_key()?;
                 let right_stream = s2
                     .key_by(move |record| right_key_selector.get_kv(record))?
                     // TODO(bingqing): remove this when new keyed-join in gaia-x is ready;
-                    .partition_by_key();
+                    .partition_by_key()?;
 
                 let stream = match join_kind {
                     JoinKind::Inner => left_stream
@@ -0,0 +1,342 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The k-hop operator is a breadth-first search of k hops. Each hop expands the frontier
+//! (`KHopExpandOperator`), routes the reached vertices to the workers owning them, and deduplicates
+//! them there (`KHopAccum`) against the vertices visited by the same start. The visited markers of a
+//! vertex are kept by the worker owning it, and are only updated after all the vertices of the hop
+//! arrive, so that each vertex is visited in its shortest hops.
+
+use std::collections::HashSet;
+use std::convert::TryInto;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use graph_proxy::apis::{get_graph, Direction, GraphElement, QueryParams, Statement, Vertex, ID};
+use ir_common::error::ParsePbError;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::KeyId;
+use pegasus::api::function::{DynIter, FlatMapFunction, FnResult};
+use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+
+use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
+use crate::process::entry::Entry;
+use crate::process::operator::accum::accumulator::Accumulator;
+use crate::process::record::Record;
+
+#[derive(Clone, Debug)]
+pub enum KHopMessage {
+    /// The vertex reached in the last hop, from the start vertex of the record of the sequence
+    Frontier(u64, Record, Vertex),
+    /// The vertex reached within the hops, to output with the record
+    Reached(Record, Vertex),
+}
+
+impl KHopMessage {
+    /// The id of the vertex reached, to route the message by.
+    pub fn get_id(&self) -> ID {
+        match self {
+            KHopMessage::Frontier(_, _, vertex) => vertex.id(),
+            KHopMessage::Reached(_, vertex) => vertex.id(),
+        }
+    }
+
+    /// The record with the reached vertex appended with the alias, which is either the frontier, or
+    /// any vertex reached `within` the hops.
+    pub fn finish(self, within: bool, alias: Option<KeyId>) -> FnResult<Option<Record>> {
+        match self {
+            KHopMessage::Frontier(_, mut record, vertex) if !within => {
+                record.append(vertex, alias);
+                Ok(Some(record))
+            }
+            KHopMessage::Reached(mut record, vertex) if within => {
+                record.append(vertex, alias);
+                Ok(Some(record))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Start the search from the start vertex of the record, with a sequence unique to the record.
+pub struct KHopStartOperator {
+    start_tag: Option<KeyId>,
+    worker_index: u64,
+    seq: AtomicU64,
+    visited: Arc<Mutex<HashSet<(u64, ID)>>>,
+}
+
+impl KHopStartOperator {
+    /// The record is expected to be routed to the worker owning the start vertex, which is visited at
+    /// first.
+    pub fn exec(&self, input: Record) -> FnResult<KHopMessage> {
+        let start = input
+            .get(self.start_tag)
+            .and_then(|entry| entry.as_vertex())
+            .cloned()
+            .ok_or_else(|| {
+                FnExecError::unexpected_data_error(&format!(
+                    "start_tag {:?} of KHop is not a vertex in {:?}",
+                    self.start_tag, input
+                ))
+            })?;
+        let seq = (self.worker_index << 48) | self.seq.fetch_add(1, Ordering::Relaxed);
+        self.visited
+            .lock()
+            .map_err(|e| FnExecError::unexpected_data_error(&format!("{:?}", e)))?
+            .insert((seq, start.id()));
+        Ok(KHopMessage::Frontier(seq, input, start))
+    }
+}
+
+/// Expand the frontier by one hop.
+pub struct KHopExpandOperator {
+    stmt: Box<dyn Statement<ID, Vertex>>,
+}
+
+impl FlatMapFunction<KHopMessage, KHopMessage> for KHopExpandOperator {
+    type Target = DynIter<KHopMessage>;
+
+    fn exec(&self, input: KHopMessage) -> FnResult<Self::Target> {
+        match input {
+            KHopMessage::Frontier(seq, record, vertex) => {
+                let neighbors = self.stmt.exec(vertex.id())?;
+                Ok(Box::new(
+                    neighbors.map(move |neighbor| KHopMessage::Frontier(seq, record.clone(), neighbor)),
+                ))
+            }
+            KHopMessage::Reached(_, _) => Ok(Box::new(std::iter::once(input))),
+        }
+    }
+}
+
+/// Deduplicate the frontier of a hop against the visited vertices, once all of the hop arrive.
+#[derive(Clone, Debug)]
+pub struct KHopAccum {
+    within: bool,
+    visited: Arc<Mutex<HashSet<(u64, ID)>>>,
+    messages: Vec<KHopMessage>,
+}
+
+impl Accumulator<KHopMessage, DynIter<KHopMessage>> for KHopAccum {
+    fn accum(&mut self, next: KHopMessage) -> FnExecResult<()> {
+        self.messages.push(next);
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> FnExecResult<DynIter<KHopMessage>> {
+        let messages = std::mem::replace(&mut self.messages, vec![]);
+        let mut visited = self
+            .visited
+            .lock()
+            .map_err(|e| FnExecError::unexpected_data_error(&format!("{:?}", e)))?;
+        let mut results = Vec::with_capacity(messages.len());
+        for message in messages {
+            match message {
+                KHopMessage::Frontier(seq, record, vertex) => {
+                    if visited.insert((seq, vertex.id())) {
+                        if self.within {
+                            results.push(KHopMessage::Reached(record.clone(), vertex.clone()));
+                        }
+                        results.push(KHopMessage::Frontier(seq, record, vertex));
+                    }
+                }
+                KHopMessage::Reached(_, _) => results.push(message),
+            }
+        }
+        Ok(Box::new(results.into_iter()))
+    }
+}
+
+/// The functions of the k-hop operator, with one expand function per hop.
+pub struct KHopOperator {
+    pub start_tag: Option<KeyId>,
+    pub within: bool,
+    pub alias: Option<KeyId>,
+    pub start: KHopStartOperator,
+    pub expands: Vec<KHopExpandOperator>,
+    pub accum: KHopAccum,
+}
+
+pub trait KHopFuncGen {
+    fn gen_k_hop(self) -> FnGenResult<KHopOperator>;
+}
+
+impl KHopFuncGen for algebra_pb::KHop {
+    fn gen_k_hop(self) -> FnGenResult<KHopOperator> {
+        let graph = get_graph().ok_or_else(|| FnGenError::NullGraphError)?;
+        if self.hops <= 0 {
+            Err(ParsePbError::from(format!("invalid hops {} of KHop", self.hops)))?
+        }
+        let direction_pb: algebra_pb::edge_expand::Direction =
+            unsafe { ::std::mem::transmute(self.direction) };
+        let direction = Direction::from(direction_pb);
+        let query_params: QueryParams = self.params.try_into()?;
+        let start_tag: Option<KeyId> = self
+            .start_tag
+            .map(|tag| tag.try_into())
+            .transpose()?;
+        let alias: Option<KeyId> = self
+            .alias
+            .map(|alias| alias.try_into())
+            .transpose()?;
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!(
+                "Runtime k-hop operator with start_tag {:?}, direction {:?}, query_params {:?}, hops {:?}, within {:?}, alias {:?}",
+                start_tag, direction, query_params, self.hops, self.within, alias
+            );
+        }
+        let mut expands = Vec::with_capacity(self.hops as usize);
+        for _ in 0..self.hops {
+            let stmt = graph.prepare_explore_vertex(direction, &query_params)?;
+            expands.push(KHopExpandOperator { stmt });
+        }
+        let visited = Arc::new(Mutex::new(HashSet::new()));
+        Ok(KHopOperator {
+            start_tag,
+            within: self.within,
+            alias,
+            start: KHopStartOperator {
+                start_tag,
+                worker_index: pegasus::get_current_worker().index as u64,
+                seq: AtomicU64::new(0),
+                visited: visited.clone(),
+            },
+            expands,
+            accum: KHopAccum { within: self.within, visited, messages: vec![] },
+        })
+    }
+}
+
+impl Encode for KHopMessage {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            KHopMessage::Frontier(seq, record, vertex) => {
+                writer.write_u8(0)?;
+                writer.write_u64(*seq)?;
+                record.write_to(writer)?;
+                vertex.write_to(writer)?;
+            }
+            KHopMessage::Reached(record, vertex) => {
+                writer.write_u8(1)?;
+                record.write_to(writer)?;
+                vertex.write_to(writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for KHopMessage {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        match reader.read_u8()? {
+            0 => {
+                let seq = reader.read_u64()?;
+                let record = Record::read_from(reader)?;
+                let vertex = Vertex::read_from(reader)?;
+                Ok(KHopMessage::Frontier(seq, record, vertex))
+            }
+            1 => {
+                let record = Record::read_from(reader)?;
+                let vertex = Vertex::read_from(reader)?;
+                Ok(KHopMessage::Reached(record, vertex))
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Other, "unreachable")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::DynDetails;
+
+    use super::*;
+    use crate::process::operator::tests::PERSON_LABEL;
+
+    fn vertex(id: ID) -> Vertex {
+        Vertex::new(id, Some(PERSON_LABEL), DynDetails::default())
+    }
+
+    fn reached(accum: &mut KHopAccum, frontier: Vec<(u64, ID)>) -> Vec<(u64, ID)> {
+        for (seq, id) in frontier {
+            let record = Record::new(vertex(0), None);
+            accum
+                .accum(KHopMessage::Frontier(seq, record, vertex(id)))
+                .unwrap();
+        }
+        accum
+            .finalize()
+            .unwrap()
+            .filter_map(|message| match message {
+                KHopMessage::Frontier(seq, _, vertex) => Some((seq, vertex.id())),
+                KHopMessage::Reached(_, _) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn k_hop_accum_dedup_test() {
+        let visited = Arc::new(Mutex::new(HashSet::new()));
+        visited.lock().unwrap().insert((0, 1));
+        let mut accum = KHopAccum { within: false, visited, messages: vec![] };
+        // the start vertex and the duplicates in the same hop are removed
+        let first = reached(&mut accum, vec![(0, 1), (0, 2), (0, 3), (0, 2), (1, 2)]);
+        assert_eq!(first, vec![(0, 2), (0, 3), (1, 2)]);
+        // the vertices visited in the previous hops are removed
+        let second = reached(&mut accum, vec![(0, 2), (0, 4), (1, 3)]);
+        assert_eq!(second, vec![(0, 4), (1, 3)]);
+    }
+
+    #[test]
+    fn k_hop_message_finish_test() {
+        let record = Record::new(vertex(1), None);
+        let frontier = KHopMessage::Frontier(0, record.clone(), vertex(2));
+        let reached = KHopMessage::Reached(record, vertex(3));
+        assert!(frontier
+            .clone()
+            .finish(true, None)
+            .unwrap()
+            .is_none());
+        assert!(reached
+            .clone()
+            .finish(false, None)
+            .unwrap()
+            .is_none());
+        let record = frontier
+            .finish(false, Some(0))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            record
+                .get(Some(0))
+                .unwrap()
+                .as_vertex()
+                .unwrap()
+                .id(),
+            2
+        );
+        let record = reached.finish(true, Some(0)).unwrap().unwrap();
+        assert_eq!(
+            record
+                .get(None)
+                .unwrap()
+                .as_vertex()
+                .unwrap()
+                .id(),
+            3
+        );
+    }
+}
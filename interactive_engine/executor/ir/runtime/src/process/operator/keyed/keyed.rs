@@ -78,6 +78,19 @@ impl KeyFunctionGen for algebra_pb::Dedup {
     }
 }
 
+impl KeyFunctionGen for algebra_pb::Sample {
+    fn gen_key(self) -> FnGenResult<Box<dyn KeyFunction<Record, RecordKey, Record>>> {
+        let sample_key = self
+            .sample_key
+            .ok_or_else(|| ParsePbError::EmptyFieldError("sample_key".to_owned()))?;
+        let key_selector = KeySelector::with(vec![sample_key])?;
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!("Runtime sample operator key_selector: {:?}", key_selector);
+        }
+        Ok(Box::new(key_selector))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use ahash::HashMap;
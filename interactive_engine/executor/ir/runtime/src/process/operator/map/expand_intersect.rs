@@ -107,6 +107,25 @@ impl IntersectionEntry {
         len as usize
     }
 
+    /// The number of the unique vertices in the collection.
+    pub fn distinct_len(&self) -> usize {
+        self.vertex_vec.len()
+    }
+
+    /// The number of the unique vertices shared with the other collection. The smaller collection
+    /// is looked up in the larger one, which is cheap when the two are in skewed sizes.
+    pub fn common_len(&self, other: &IntersectionEntry) -> usize {
+        let (smaller, larger) = if self.vertex_vec.len() <= other.vertex_vec.len() {
+            (&self.vertex_vec, &other.vertex_vec)
+        } else {
+            (&other.vertex_vec, &self.vertex_vec)
+        };
+        smaller
+            .iter()
+            .filter(|vid| larger.binary_search(vid).is_ok())
+            .count()
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &ID> {
         self.vertex_vec
             .iter()
@@ -707,6 +726,15 @@ mod tests {
             .map(|(src, dst)| Edge::new(0, None, src, dst, Default::default()))
     }
 
+    #[test]
+    fn common_len_test() {
+        let hub = IntersectionEntry::from_iter(to_vertex_iter((1..1000).collect()));
+        let leaf = IntersectionEntry::from_iter(to_vertex_iter(vec![0, 2, 2, 500, 1000]));
+        assert_eq!(leaf.distinct_len(), 4);
+        assert_eq!(hub.common_len(&leaf), 2);
+        assert_eq!(leaf.common_len(&hub), 2);
+    }
+
     #[test]
     fn intersect_test_01() {
         let mut intersection = IntersectionEntry::from_iter(to_vertex_iter(vec![1, 2, 3]));
@@ -21,6 +21,7 @@ mod project;
 
 pub use expand_intersect::{GeneralIntersectionEntry, IntersectionEntry};
 use pegasus::api::function::{FilterMapFunction, MapFunction};
+pub use project::{ProjectFuncGen, ProjectOperator};
 
 use crate::error::FnGenResult;
 use crate::process::record::Record;
@@ -16,11 +16,12 @@
 use std::convert::TryFrom;
 
 use dyn_type::Object;
-use graph_proxy::utils::expr::eval::{Evaluate, Evaluator};
+use graph_proxy::utils::expr::eval::{eval_udf_args, Evaluate, Evaluator, Operand};
+use graph_proxy::utils::udf::{call_udf, call_udf_batch, get_udf};
 use ir_common::error::ParsePbError;
 use ir_common::generated::common as common_pb;
 use ir_common::generated::physical as pb;
-use ir_common::KeyId;
+use ir_common::{KeyId, NameOrId, ID_KEY, LABEL_KEY};
 use pegasus::api::function::{FilterMapFunction, FnResult};
 
 use crate::error::FnExecError;
@@ -32,14 +33,22 @@ use crate::process::entry::PairEntry;
 use crate::process::operator::map::FilterMapFuncGen;
 use crate::process::operator::TagKey;
 use crate::process::record::Record;
+use crate::provenance::{expr_tags, is_tracking_provenance, trace, TracedOperator};
 
 /// Project entries with specified tags or further their properties.
 /// Notice that when projecting a single column, if the result is a None-Entry,
 /// Caused by either the given `tag` or the required properties do not exist, the record will be filtered.
+/// If a column is projected by a batched user defined function, e.g., of Python, the records are better
+/// projected by `exec_batch`, which calls the function once on the records of a batch.
 #[derive(Debug)]
-struct ProjectOperator {
+pub struct ProjectOperator {
     is_append: bool,
     projected_columns: Vec<(Projector, Option<KeyId>)>,
+    /// The tags read by the projected columns, to be traced in the provenance tracking mode.
+    traced_tags: Option<Vec<Option<KeyId>>>,
+    /// The tags selected as in Gremlin's `select('a', 'b')`, where the records missing any of them
+    /// are filtered.
+    selected_tags: Vec<KeyId>,
 }
 
 #[derive(Debug)]
@@ -52,6 +61,12 @@ pub enum Projector {
     MultiGraphElementProjector(Vec<(Option<Object>, TagKey)>),
     /// A simple concatenation of multiple entries.
     ConcatProjector(Vec<TagKey>),
+    /// A user defined function on the arguments, which is projected in batch if the function is batched.
+    UdfProjector(String, Vec<Operand>),
+    /// The map of the element of the tag, e.g., `elementMap()`, which is a collection of PairEntry keyed by
+    /// `~id`, `~label` and then the names of the properties, or all the properties sorted by their keys
+    /// if none is given.
+    ElementMapProjector(Option<KeyId>, Vec<(Object, NameOrId)>),
 }
 
 // TODO:
@@ -110,25 +125,130 @@ fn exec_projector(input: &Record, projector: &Projector) -> FnExecResult<DynEntr
                 }
             }
         }
+        Projector::UdfProjector(name, args) => {
+            DynEntry::new(call_udf(name, eval_udf_args::<DynEntry, Record>(args, Some(input))?)?)
+        }
+        Projector::ElementMapProjector(tag, keys) => {
+            let element = match input
+                .get(*tag)
+                .and_then(|entry| entry.as_graph_element())
+            {
+                Some(element) => element,
+                None => return Ok(DynEntry::new(Object::None)),
+            };
+            let label = element
+                .label()
+                .map(|label| label.into())
+                .unwrap_or(Object::None);
+            let mut pairs = vec![(object!(ID_KEY), element.id().into()), (object!(LABEL_KEY), label)];
+            if keys.is_empty() {
+                let mut properties = element
+                    .get_all_properties()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let key: Object = match key {
+                            NameOrId::Str(str) => str.into(),
+                            NameOrId::Id(id) => id.into(),
+                        };
+                        (key, value)
+                    })
+                    .collect::<Vec<(Object, Object)>>();
+                properties.sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+                pairs.extend(properties);
+            } else {
+                for (name, key) in keys.iter() {
+                    // the properties missing in the element are omitted
+                    if let Some(value) = element
+                        .get_property(key)
+                        .and_then(|value| value.try_to_owned())
+                    {
+                        pairs.push((name.clone(), value));
+                    }
+                }
+            }
+            let inner = pairs
+                .into_iter()
+                .map(|(key, value)| PairEntry::new(key.into(), value.into()).into())
+                .collect();
+            DynEntry::new(CollectionEntry { inner })
+        }
     };
     Ok(entry)
 }
 
-impl FilterMapFunction<Record, Record> for ProjectOperator {
-    fn exec(&self, mut input: Record) -> FnResult<Option<Record>> {
+impl ProjectOperator {
+    /// Whether any column is projected by a batched user defined function.
+    pub fn is_batched(&self) -> bool {
+        self.projected_columns
+            .iter()
+            .any(|(projector, _)| match projector {
+                Projector::UdfProjector(name, _) => get_udf(name)
+                    .map(|udf| udf.is_batched())
+                    .unwrap_or(false),
+                _ => false,
+            })
+    }
+
+    /// Project the records of a batch, where each user defined function is called once on the arguments
+    /// of all the records, and the other columns are projected record by record.
+    pub fn exec_batch(&self, inputs: Vec<Record>) -> FnExecResult<Vec<Record>> {
+        let mut columns = Vec::with_capacity(self.projected_columns.len());
+        for (projector, _) in self.projected_columns.iter() {
+            let entries = if let Projector::UdfProjector(name, args) = projector {
+                let mut rows = Vec::with_capacity(inputs.len());
+                for input in inputs.iter() {
+                    rows.push(eval_udf_args::<DynEntry, Record>(args, Some(input))?);
+                }
+                call_udf_batch(name, rows)?
+                    .into_iter()
+                    .map(DynEntry::new)
+                    .collect()
+            } else {
+                inputs
+                    .iter()
+                    .map(|input| exec_projector(input, projector))
+                    .collect::<FnExecResult<Vec<DynEntry>>>()?
+            };
+            columns.push(entries.into_iter());
+        }
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let entries = columns
+                .iter_mut()
+                .map(|column| column.next().unwrap())
+                .collect();
+            if let Some(output) = self.project(input, entries) {
+                outputs.push(output);
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// Put the projected entries, one for each column, into the record.
+    fn project(&self, mut input: Record, mut entries: Vec<DynEntry>) -> Option<Record> {
+        if self
+            .selected_tags
+            .iter()
+            .any(|tag| input.get(Some(*tag)).is_none())
+        {
+            return None;
+        }
+        if let Some(tags) = self.traced_tags.as_ref() {
+            trace(&mut input, TracedOperator::Project, tags);
+        }
         if self.is_append {
             if self.projected_columns.len() == 1 {
-                let (projector, alias) = self.projected_columns.get(0).unwrap();
-                let entry = exec_projector(&input, &projector)?;
+                let (_, alias) = self.projected_columns.get(0).unwrap();
+                let entry = entries.pop().unwrap();
                 if entry.is_none() {
-                    Ok(None)
+                    None
                 } else {
                     input.append_arc_entry(entry, alias.clone());
-                    Ok(Some(input))
+                    Some(input)
                 }
             } else {
-                for (projector, alias) in self.projected_columns.iter() {
-                    let entry = exec_projector(&input, projector)?;
+                for ((_, alias), entry) in self.projected_columns.iter().zip(entries) {
                     // Notice that if multiple columns, alias cannot be None
                     if let Some(alias) = alias {
                         let columns = input.get_columns_mut();
@@ -137,41 +257,77 @@ impl FilterMapFunction<Record, Record> for ProjectOperator {
                 }
                 // set head as None when the last column is appended
                 input.set_curr_entry(None);
-                Ok(Some(input))
+                Some(input)
             }
         } else {
             let mut new_record = Record::default();
+            new_record.set_provenance(input.take_provenance());
             if self.projected_columns.len() == 1 {
-                let (projector, alias) = self.projected_columns.get(0).unwrap();
-                let entry = exec_projector(&input, &projector)?;
+                let (_, alias) = self.projected_columns.get(0).unwrap();
+                let entry = entries.pop().unwrap();
                 if entry.is_none() {
-                    Ok(None)
+                    None
                 } else {
                     new_record.append_arc_entry(entry, alias.clone());
-                    Ok(Some(new_record))
+                    Some(new_record)
                 }
             } else {
-                for (projector, alias) in self.projected_columns.iter() {
-                    let entry = exec_projector(&input, &projector)?;
+                for ((_, alias), entry) in self.projected_columns.iter().zip(entries) {
                     // Notice that if multiple columns, alias cannot be None
                     if let Some(alias) = alias {
                         let columns = new_record.get_columns_mut();
                         columns.insert(*alias as usize, entry);
                     }
                 }
-                Ok(Some(new_record))
+                Some(new_record)
             }
         }
     }
 }
 
+impl FilterMapFunction<Record, Record> for ProjectOperator {
+    fn exec(&self, input: Record) -> FnResult<Option<Record>> {
+        let mut entries = Vec::with_capacity(self.projected_columns.len());
+        for (projector, _) in self.projected_columns.iter() {
+            entries.push(exec_projector(&input, projector)?);
+        }
+        Ok(self.project(input, entries))
+    }
+}
+
+pub trait ProjectFuncGen {
+    fn gen_project(self) -> FnGenResult<ProjectOperator>;
+}
+
 impl FilterMapFuncGen for pb::Project {
     fn gen_filter_map(self) -> FnGenResult<Box<dyn FilterMapFunction<Record, Record>>> {
+        Ok(Box::new(self.gen_project()?))
+    }
+}
+
+impl ProjectFuncGen for pb::Project {
+    fn gen_project(self) -> FnGenResult<ProjectOperator> {
         let mut projected_columns = Vec::with_capacity(self.mappings.len());
+        let mut traced_tags = if is_tracking_provenance() { Some(vec![]) } else { None };
+        let mut selected_tags = vec![];
         for expr_alias in self.mappings.into_iter() {
             let expr = expr_alias
                 .expr
                 .ok_or_else(|| ParsePbError::from("expr eval is missing in project"))?;
+            if let Some(traced_tags) = traced_tags.as_mut() {
+                for tag in expr_tags(&expr) {
+                    if !traced_tags.contains(&tag) {
+                        traced_tags.push(tag);
+                    }
+                }
+            }
+            if self.select_tags {
+                for tag in expr_tags(&expr).into_iter().flatten() {
+                    if !selected_tags.contains(&tag) {
+                        selected_tags.push(tag);
+                    }
+                }
+            }
             let projector = if expr.operators.len() == 1 {
                 match expr.operators.get(0).unwrap() {
                     common_pb::ExprOpr { item: Some(common_pb::expr_opr::Item::Var(var)), .. } => {
@@ -215,6 +371,42 @@ impl FilterMapFuncGen for pb::Project {
                             .collect::<Result<Vec<TagKey>, _>>()?;
                         Projector::ConcatProjector(tag_keys)
                     }
+                    common_pb::ExprOpr { item: Some(common_pb::expr_opr::Item::Udf(udf)), .. } => {
+                        if get_udf(&udf.name).is_none() {
+                            Err(ParsePbError::ParseError(format!(
+                                "user defined function {} is not registered",
+                                udf.name
+                            )))?
+                        }
+                        let args = udf
+                            .args
+                            .iter()
+                            .map(|arg| Operand::try_from(arg.clone()))
+                            .collect::<Result<Vec<Operand>, _>>()?;
+                        Projector::UdfProjector(udf.name.clone(), args)
+                    }
+                    common_pb::ExprOpr {
+                        item: Some(common_pb::expr_opr::Item::ElementMap(element_map)),
+                        ..
+                    } => {
+                        let tag = element_map
+                            .tag
+                            .clone()
+                            .map(KeyId::try_from)
+                            .transpose()?;
+                        let keys = element_map
+                            .keys
+                            .iter()
+                            .map(|key| {
+                                let prop_key = match key.key.clone() {
+                                    Some(prop_key) => NameOrId::try_from(prop_key)?,
+                                    None => key.name.clone().into(),
+                                };
+                                Ok((object!(key.name.clone()), prop_key))
+                            })
+                            .collect::<Result<Vec<(Object, NameOrId)>, ParsePbError>>()?;
+                        Projector::ElementMapProjector(tag, keys)
+                    }
                     _ => {
                         let evaluator = Evaluator::try_from(expr)?;
                         Projector::ExprProjector(evaluator)
@@ -226,35 +418,43 @@ impl FilterMapFuncGen for pb::Project {
             };
             projected_columns.push((projector, expr_alias.alias));
         }
-        let project_operator = ProjectOperator { is_append: self.is_append, projected_columns };
+        let project_operator =
+            ProjectOperator { is_append: self.is_append, projected_columns, traced_tags, selected_tags };
         if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
             debug!("Runtime project operator {:?}", project_operator);
         }
-        Ok(Box::new(project_operator))
+        Ok(project_operator)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
     use ahash::HashMap;
     use dyn_type::Object;
     use graph_proxy::apis::{DynDetails, Edge, GraphElement, GraphPath, Vertex};
+    use graph_proxy::utils::expr::ExprEvalResult;
+    use graph_proxy::utils::udf::{register_udf, unregister_udf, Udf};
     use ir_common::expr_parse::str_to_expr_pb;
     use ir_common::generated::{common as common_pb, physical as pb};
     use ir_common::NameOrId;
+    use pegasus::api::function::FilterMapFunction;
     use pegasus::api::{Map, Sink};
     use pegasus::result::ResultStream;
     use pegasus::JobConf;
     use pegasus_common::downcast::AsAny;
 
     use crate::process::entry::{CollectionEntry, Entry, PairEntry};
-    use crate::process::operator::map::FilterMapFuncGen;
+    use crate::process::operator::map::{FilterMapFuncGen, ProjectFuncGen};
     use crate::process::operator::tests::{
         init_source, init_source_with_multi_tags, init_source_with_tag, init_vertex1, init_vertex2,
         to_expr_map_pb, to_expr_var_pb, to_expr_vars_pb, to_var_pb, PERSON_LABEL, TAG_A, TAG_B, TAG_C,
         TAG_D, TAG_E, TAG_F, TAG_G,
     };
     use crate::process::record::Record;
+    use crate::provenance::{bind_provenance, ProvenanceStep, TracedOperator};
 
     fn project_test(source: Vec<Record>, project_opr_pb: pb::Project) -> ResultStream<Record> {
         let conf = JobConf::new("project_test");
@@ -282,6 +482,7 @@ mod tests {
                 alias: None,
             }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source(), project_opr_pb);
         let mut object_result = vec![];
@@ -307,6 +508,7 @@ mod tests {
                 alias: Some(TAG_B.into()),
             }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source_with_tag(), project_opr_pb);
 
@@ -335,6 +537,7 @@ mod tests {
                 alias: None,
             }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source_with_tag(), project_opr_pb);
         let mut object_result = vec![];
@@ -366,6 +569,7 @@ mod tests {
                 },
             ],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source(), project_opr_pb);
         let mut object_result = vec![];
@@ -403,6 +607,7 @@ mod tests {
                 },
             ],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source_with_tag(), project_opr_pb);
 
@@ -459,6 +664,7 @@ mod tests {
                 },
             ],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(source, project_opr_pb);
 
@@ -497,6 +703,7 @@ mod tests {
                 alias: Some(TAG_B.into()),
             }],
             is_append: true,
+            select_tags: false,
         };
         let mut result = project_test(init_source_with_tag(), project_opr_pb);
         let mut a_results = vec![];
@@ -536,6 +743,7 @@ mod tests {
                 },
             ],
             is_append: true,
+            select_tags: false,
         };
         let mut result = project_test(init_source(), project_opr_pb);
         let mut object_result = vec![];
@@ -562,6 +770,7 @@ mod tests {
         let project_opr_pb = pb::Project {
             mappings: vec![pb::project::ExprAlias { expr: None, alias: None }],
             is_append: false,
+            select_tags: false,
         };
         let project_func = project_opr_pb.gen_filter_map();
         if let Err(_) = project_func {
@@ -578,6 +787,7 @@ mod tests {
                 alias: None,
             }],
             is_append: true,
+            select_tags: false,
         };
         let project_func = project_opr_pb.gen_filter_map();
         if let Err(_) = project_func {
@@ -594,6 +804,7 @@ mod tests {
                 alias: None,
             }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source(), project_opr_pb);
         let mut collection_result: Vec<Vec<Object>> = vec![];
@@ -625,6 +836,7 @@ mod tests {
                 alias: None,
             }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source(), project_opr_pb);
         let mut object_result = vec![];
@@ -673,6 +885,7 @@ mod tests {
         let project_opr_pb = pb::Project {
             mappings: vec![pb::project::ExprAlias { expr: Some(expr), alias: None }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source(), project_opr_pb);
         let mut object_result = vec![];
@@ -697,6 +910,7 @@ mod tests {
                 alias: None,
             }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source(), project_opr_pb);
         let mut object_result = vec![];
@@ -749,6 +963,7 @@ mod tests {
                 alias: None,
             }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source_with_tag(), project_opr_pb);
         let mut object_result = vec![];
@@ -798,6 +1013,7 @@ mod tests {
         let project_opr_pb = pb::Project {
             mappings: vec![pb::project::ExprAlias { expr: Some(expr), alias: None }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source_with_tag(), project_opr_pb);
         let mut object_result = vec![];
@@ -828,6 +1044,7 @@ mod tests {
                 alias: None,
             }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source_with_tag(), project_opr_pb);
 
@@ -880,6 +1097,7 @@ mod tests {
                 },
             ],
             is_append: true,
+            select_tags: false,
         };
         let mut result = project_test(init_source_with_multi_tags(), project_opr_pb);
         let mut results = vec![];
@@ -910,6 +1128,7 @@ mod tests {
                 alias: None,
             }],
             is_append: true,
+            select_tags: false,
         };
         let mut result = project_test(init_source(), project_opr_pb);
         let mut result_cnt = 0;
@@ -928,6 +1147,7 @@ mod tests {
                 alias: None,
             }],
             is_append: true,
+            select_tags: false,
         };
         let mut result = project_test(init_source_with_tag(), project_opr_pb);
         let mut result_cnt = 0;
@@ -952,6 +1172,7 @@ mod tests {
                 },
             ],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source(), project_opr_pb);
         let mut result_cnt = 0;
@@ -981,6 +1202,60 @@ mod tests {
         assert_eq!(result_cnt, 2);
     }
 
+    // g.V().as("a").out().as("b").select("a", "b").by("name"), which is a map keyed by the tags
+    #[test]
+    fn project_multi_tags_as_map_test() {
+        let project_opr_pb = pb::Project {
+            mappings: vec![pb::project::ExprAlias {
+                expr: Some(to_expr_map_pb(vec![
+                    ("a".to_string(), (Some(TAG_A.into()), Some("name".into()))),
+                    ("b".to_string(), (Some(TAG_B.into()), Some("name".into()))),
+                ])),
+                alias: None,
+            }],
+            is_append: false,
+            select_tags: true,
+        };
+        let mut result = project_test(init_source_with_multi_tags(), project_opr_pb.clone());
+        let mut object_result = vec![];
+        while let Some(Ok(res)) = result.next() {
+            let collection = res
+                .get(None)
+                .unwrap()
+                .as_any_ref()
+                .downcast_ref::<CollectionEntry>()
+                .unwrap();
+            for entry in collection.inner.iter() {
+                let pair_entry = entry
+                    .as_any_ref()
+                    .downcast_ref::<PairEntry>()
+                    .unwrap();
+                let key = pair_entry.get_left().as_object().unwrap();
+                let value = pair_entry.get_right().as_object().unwrap();
+                object_result.push((key.clone(), value.clone()));
+            }
+        }
+        assert_eq!(object_result, vec![(object!("a"), object!("marko")), (object!("b"), object!("vadas"))]);
+
+        // the records without the tag `b` are filtered
+        let mut result = project_test(init_source_with_tag(), project_opr_pb.clone());
+        let mut result_cnt = 0;
+        while let Some(Ok(_res)) = result.next() {
+            result_cnt += 1;
+        }
+        assert_eq!(result_cnt, 0);
+
+        // while they are kept with the missing values, e.g., of Cypher's `RETURN {a: a.name, b: b.name}`
+        let mut project_opr_pb = project_opr_pb;
+        project_opr_pb.select_tags = false;
+        let mut result = project_test(init_source_with_tag(), project_opr_pb);
+        let mut result_cnt = 0;
+        while let Some(Ok(_res)) = result.next() {
+            result_cnt += 1;
+        }
+        assert_eq!(result_cnt, 2);
+    }
+
     // g.V().as("a").select("a").by(valueMap("test1", "test2"))
     #[test]
     fn project_multi_none_exist_props_test() {
@@ -996,6 +1271,7 @@ mod tests {
                 },
             ],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(init_source_with_tag(), project_opr_pb);
         let mut result_cnt = 0;
@@ -1039,6 +1315,7 @@ mod tests {
                 alias: None,
             }],
             is_append: true,
+            select_tags: false,
         };
         let mut result = project_test(source, project_opr_pb);
         if let Some(Err(_res)) = result.next() {
@@ -1120,6 +1397,7 @@ mod tests {
                 pb::project::ExprAlias { expr: Some(expr4), alias: Some(TAG_G.into()) },
             ],
             is_append: true,
+            select_tags: false,
         };
 
         let mut result = project_test(source, project_opr_pb);
@@ -1200,6 +1478,7 @@ mod tests {
                 alias: Some(TAG_C.into()),
             }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(source, project_opr_pb);
         let mut results = vec![];
@@ -1265,6 +1544,7 @@ mod tests {
                 alias: Some(TAG_C.into()),
             }],
             is_append: false,
+            select_tags: false,
         };
         let mut result = project_test(source, project_opr_pb);
         let mut results = vec![];
@@ -1279,4 +1559,239 @@ mod tests {
         }
         assert_eq!(results, vec![concat_path]);
     }
+
+    struct BatchedUdf {
+        num_calls: AtomicUsize,
+    }
+
+    impl Udf for BatchedUdf {
+        fn call(&self, args: Vec<Object>) -> ExprEvalResult<Object> {
+            Ok(object!(args[0].as_i64()? * 2))
+        }
+
+        fn call_batch(&self, rows: Vec<Vec<Object>>) -> ExprEvalResult<Vec<Object>> {
+            self.num_calls.fetch_add(1, Ordering::SeqCst);
+            rows.into_iter()
+                .map(|args| self.call(args))
+                .collect()
+        }
+
+        fn is_batched(&self) -> bool {
+            true
+        }
+    }
+
+    // g.V().project(udf(@.age), @.name)
+    #[test]
+    fn project_udf_batch_test() {
+        let udf = Arc::new(BatchedUdf { num_calls: AtomicUsize::new(0) });
+        register_udf("project_udf_batch_test_double", udf.clone());
+        let project_opr_pb = pb::Project {
+            mappings: vec![
+                pb::project::ExprAlias {
+                    expr: Some(common_pb::Expression {
+                        operators: vec![common_pb::ExprOpr {
+                            item: Some(common_pb::expr_opr::Item::Udf(common_pb::UdfCall {
+                                name: "project_udf_batch_test_double".to_string(),
+                                args: vec![common_pb::Variable::from("@.age".to_string()).into()],
+                            })),
+                            node_type: None,
+                        }],
+                    }),
+                    alias: Some(TAG_A.into()),
+                },
+                pb::project::ExprAlias {
+                    expr: Some(str_to_expr_pb("@.name".to_string()).unwrap()),
+                    alias: Some(TAG_B.into()),
+                },
+            ],
+            is_append: false,
+            select_tags: false,
+        };
+        let project = project_opr_pb.gen_project().unwrap();
+        assert!(project.is_batched());
+        let to_objects = |record: &Record| {
+            (
+                record
+                    .get(Some(TAG_A))
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+                record
+                    .get(Some(TAG_B))
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+            )
+        };
+        let results = project.exec_batch(init_source()).unwrap();
+        assert_eq!(udf.num_calls.load(Ordering::SeqCst), 1);
+        let expected = vec![(object!(58_i64), object!("marko")), (object!(54_i64), object!("vadas"))];
+        assert_eq!(
+            results
+                .iter()
+                .map(to_objects)
+                .collect::<Vec<_>>(),
+            expected
+        );
+        // the same as projected record by record
+        let mut results = vec![];
+        for input in init_source() {
+            results.push(to_objects(&project.exec(input).unwrap().unwrap()));
+        }
+        assert_eq!(results, expected);
+        assert!(unregister_udf("project_udf_batch_test_double"));
+    }
+
+    // g.V().as("a").select("a").by("name") in the provenance tracking mode
+    #[test]
+    fn project_provenance_test() {
+        let conf = JobConf::new("project_provenance_test");
+        let _binding = bind_provenance(conf.job_id);
+        let project_opr_pb = pb::Project {
+            mappings: vec![pb::project::ExprAlias {
+                expr: Some(to_expr_var_pb(Some(TAG_A.into()), Some("name".into()))),
+                alias: Some(TAG_B.into()),
+            }],
+            is_append: false,
+            select_tags: false,
+        };
+        let mut result = pegasus::run(conf, || {
+            let project_opr_pb = project_opr_pb.clone();
+            |input, output| {
+                let mut stream = input.input_from(init_source_with_tag().into_iter())?;
+                let project_func = project_opr_pb.gen_filter_map().unwrap();
+                stream = stream.filter_map(move |i| project_func.exec(i))?;
+                stream.sink_into(output)
+            }
+        })
+        .expect("build job failure");
+        let mut vertices = vec![];
+        while let Some(Ok(record)) = result.next() {
+            let steps: Vec<&ProvenanceStep> = record
+                .get_provenance()
+                .unwrap()
+                .steps()
+                .collect();
+            assert_eq!(steps.len(), 1);
+            assert_eq!(steps[0].operator, TracedOperator::Project);
+            assert_eq!(steps[0].tags, vec![Some(TAG_A)]);
+            vertices.extend(steps[0].vertices.clone());
+        }
+        vertices.sort();
+        assert_eq!(vertices, vec![1, 2]);
+    }
+
+    // flatten the element map of the head as the pairs of its keys and values
+    fn element_map_of(record: &Record) -> Vec<(Object, Object)> {
+        let collection = record
+            .get(None)
+            .unwrap()
+            .as_any_ref()
+            .downcast_ref::<CollectionEntry>()
+            .unwrap();
+        collection
+            .inner
+            .iter()
+            .map(|entry| {
+                let pair_entry = entry
+                    .as_any_ref()
+                    .downcast_ref::<PairEntry>()
+                    .unwrap();
+                (
+                    pair_entry
+                        .get_left()
+                        .as_object()
+                        .unwrap()
+                        .clone(),
+                    pair_entry
+                        .get_right()
+                        .as_object()
+                        .unwrap()
+                        .clone(),
+                )
+            })
+            .collect()
+    }
+
+    fn to_element_map_pb(tag: Option<common_pb::NameOrId>, keys: Vec<&str>) -> common_pb::Expression {
+        let keys = keys
+            .into_iter()
+            .map(|name| common_pb::element_map::Key { name: name.to_string(), key: Some(name.into()) })
+            .collect();
+        common_pb::Expression {
+            operators: vec![common_pb::ExprOpr {
+                node_type: None,
+                item: Some(common_pb::expr_opr::Item::ElementMap(common_pb::ElementMap { tag, keys })),
+            }],
+        }
+    }
+
+    // g.V().elementMap('name', 'code') and g.V().as('a').select('a').elementMap()
+    #[test]
+    fn project_element_map_test() {
+        let project_opr_pb = pb::Project {
+            mappings: vec![pb::project::ExprAlias {
+                expr: Some(to_element_map_pb(None, vec!["name", "code"])),
+                alias: None,
+            }],
+            is_append: false,
+            select_tags: false,
+        };
+        let mut result = project_test(init_source(), project_opr_pb);
+        let mut maps = vec![];
+        while let Some(Ok(res)) = result.next() {
+            maps.push(element_map_of(&res));
+        }
+        // the missing property, i.e., the code of v2, is omitted
+        let expected = vec![
+            vec![
+                (object!("~id"), object!(1)),
+                (object!("~label"), object!(PERSON_LABEL)),
+                (object!("name"), object!("marko")),
+                (object!("code"), object!("11051")),
+            ],
+            vec![
+                (object!("~id"), object!(2)),
+                (object!("~label"), object!(PERSON_LABEL)),
+                (object!("name"), object!("vadas")),
+            ],
+        ];
+        assert_eq!(maps, expected);
+
+        let project_opr_pb = pb::Project {
+            mappings: vec![pb::project::ExprAlias {
+                expr: Some(to_element_map_pb(Some(TAG_A.into()), vec![])),
+                alias: None,
+            }],
+            is_append: false,
+            select_tags: false,
+        };
+        let mut result = project_test(init_source_with_tag(), project_opr_pb);
+        let mut maps = vec![];
+        while let Some(Ok(res)) = result.next() {
+            maps.push(element_map_of(&res));
+        }
+        // all the properties are sorted by their keys
+        let expected = vec![
+            vec![
+                (object!("~id"), object!(1)),
+                (object!("~label"), object!(PERSON_LABEL)),
+                (object!("age"), object!(29)),
+                (object!("code"), object!("11051")),
+                (object!("id"), object!(1)),
+                (object!("name"), object!("marko")),
+            ],
+            vec![
+                (object!("~id"), object!(2)),
+                (object!("~label"), object!(PERSON_LABEL)),
+                (object!("age"), object!(27)),
+                (object!("id"), object!(2)),
+                (object!("name"), object!("vadas")),
+            ],
+        ];
+        assert_eq!(maps, expected);
+    }
 }
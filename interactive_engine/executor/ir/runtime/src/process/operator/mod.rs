@@ -14,17 +14,29 @@
 //! limitations under the License.
 
 pub mod accum;
+pub mod algorithm;
+pub mod coalesce;
+pub mod dedup_filter;
 pub mod filter;
 pub mod flatmap;
 pub mod group;
 pub mod join;
+pub mod k_hop;
 pub mod keyed;
 pub mod map;
+pub mod prefetch_expand;
+pub mod repeat;
+pub mod sack;
 pub mod shuffle;
+pub mod side_effect;
 pub mod sink;
 pub mod sort;
 pub mod source;
+pub mod split_expand;
 pub mod subtask;
+pub mod tree;
+pub mod variable;
+pub mod write;
 
 use std::convert::TryFrom;
 
@@ -170,6 +182,15 @@ impl Decode for TagKey {
     }
 }
 
+/// The seed of a sampling without any seed given, which is fixed if the execution is deterministic.
+pub(crate) fn deterministic_seed() -> Option<i32> {
+    if pegasus::is_deterministic() {
+        Some(pegasus::DETERMINISTIC_SEED as i32)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use ahash::HashMap;
@@ -0,0 +1,384 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The prefetch expand overlaps the reads of the adjacency in the edge expand with the expanding of
+//! the records. As the adjacency of a record in a batch is given to the output, the adjacency of up
+//! to `depth` records after it is read ahead by the prefetch pool, i.e., a fixed number of threads
+//! shared by the prefetch expand of all the jobs, whose queue is bounded. The record whose read is
+//! not queued as the pool is full is read by the worker as it's expanded. The reads of the pool are
+//! in the context of the worker, e.g., its consistency and partitions, through an instance of the
+//! expand of the operator other than the one of the worker, as the statements of the graph are not
+//! shared among the threads.
+//!
+//! A record stays in its batch until its adjacency is given, so the batch interrupted by the output
+//! blocked is resumed from the record after the last one given, with the reads ahead of it done
+//! again. The error of a read is raised as the record is expanded, which fails the job.
+//!
+//! The records read ahead are counted in `ir_expand_prefetch_records_total`, the times the expand
+//! waits for the adjacency not read yet in `ir_expand_prefetch_waits_total`, and the records read by
+//! the worker in `ir_expand_prefetch_inline_total`, in total of the server. The counts of each
+//! operator are logged with its job as the operator is dropped.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex};
+
+use ir_common::generated::physical as pb;
+use lazy_static::lazy_static;
+use pegasus::api::function::{DynError, FnResult};
+use pegasus::WorkerId;
+use prometheus::{register_int_counter, IntCounter};
+
+use crate::error::{FnExecError, FnGenResult};
+use crate::process::operator::flatmap::{CountExpand, CountExpandGen};
+use crate::process::record::Record;
+
+lazy_static! {
+    static ref PREFETCH_RECORDS: IntCounter = register_int_counter!(
+        "ir_expand_prefetch_records_total",
+        "Records whose adjacency is read ahead by the prefetch expand."
+    )
+    .unwrap();
+    static ref PREFETCH_WAITS: IntCounter = register_int_counter!(
+        "ir_expand_prefetch_waits_total",
+        "Times the prefetch expand waits for the adjacency not read yet."
+    )
+    .unwrap();
+    static ref PREFETCH_INLINE: IntCounter = register_int_counter!(
+        "ir_expand_prefetch_inline_total",
+        "Records whose adjacency is read by the worker as the prefetch pool is full."
+    )
+    .unwrap();
+}
+
+type Adjacency = FnResult<Vec<Record>>;
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// The threads reading ahead for the prefetch expand of all the jobs.
+pub struct PrefetchPool {
+    tasks: SyncSender<Task>,
+}
+
+impl PrefetchPool {
+    /// Start `threads` threads reading the tasks of a queue of `capacity` tasks at most, which stop
+    /// as the pool is dropped.
+    pub fn new(threads: usize, capacity: usize) -> std::io::Result<Self> {
+        let (tasks, rx) = mpsc::sync_channel::<Task>(capacity);
+        let rx = Arc::new(Mutex::new(rx));
+        for i in 0..threads {
+            let rx = rx.clone();
+            std::thread::Builder::new()
+                .name(format!("prefetch-{}", i))
+                .spawn(move || loop {
+                    let task = match rx.lock() {
+                        Ok(rx) => rx.recv(),
+                        Err(_) => return,
+                    };
+                    match task {
+                        Ok(task) => task(),
+                        Err(_) => return,
+                    }
+                })?;
+        }
+        Ok(PrefetchPool { tasks })
+    }
+
+    /// Queue the task, which is returned if the queue is full.
+    fn try_submit(&self, task: Task) -> Result<(), Task> {
+        self.tasks.try_send(task).map_err(|e| match e {
+            TrySendError::Full(task) | TrySendError::Disconnected(task) => task,
+        })
+    }
+}
+
+/// The prefetch of the edge expand, with the shared pool reading ahead.
+#[derive(Clone)]
+pub struct ExpandPrefetch {
+    /// The records after the one expanded whose adjacency is read ahead.
+    pub depth: usize,
+    pub pool: Arc<PrefetchPool>,
+}
+
+/// Read the adjacency of a record, every read being replied, so the worker never waits for a lost read.
+fn read(expand: &dyn CountExpand, record: Record) -> Adjacency {
+    std::panic::catch_unwind(AssertUnwindSafe(|| expand.exec(record).map(|iter| iter.collect())))
+        .unwrap_or_else(|_| Err(Box::new(FnExecError::Unreachable) as DynError))
+}
+
+/// Expand the records of the batches with their adjacency read ahead.
+pub struct PrefetchExpandOperator {
+    // the expand of the reads of the pool, which are of the operator one after another
+    shared: Arc<Mutex<Box<dyn CountExpand>>>,
+    // the expand of the reads of the worker
+    expand: Box<dyn CountExpand>,
+    pool: Arc<PrefetchPool>,
+    depth: usize,
+    worker: WorkerId,
+    records: Cell<u64>,
+    waits: Cell<u64>,
+    inline: Cell<u64>,
+}
+
+impl PrefetchExpandOperator {
+    fn new(
+        shared: Box<dyn CountExpand>, expand: Box<dyn CountExpand>, prefetch: ExpandPrefetch,
+        worker: WorkerId,
+    ) -> Self {
+        PrefetchExpandOperator {
+            shared: Arc::new(Mutex::new(shared)),
+            expand,
+            pool: prefetch.pool,
+            depth: prefetch.depth,
+            worker,
+            records: Cell::new(0),
+            waits: Cell::new(0),
+            inline: Cell::new(0),
+        }
+    }
+
+    /// The reads of the records of a batch, which are dropped with the batch interrupted.
+    pub fn reads(&self) -> PrefetchReads {
+        PrefetchReads { operator: self, reads: VecDeque::new() }
+    }
+
+    /// Queue the read of a record to the pool, or keep it to be read by the worker if the pool is full.
+    fn submit(&self, record: Record) -> Read {
+        let (reply, replies) = mpsc::sync_channel(1);
+        let shared = self.shared.clone();
+        let worker = self.worker;
+        let task: Task = Box::new(move || {
+            pegasus::set_current_worker(Some(worker));
+            let adjacency = match shared.lock() {
+                Ok(expand) => read(&**expand, record),
+                Err(_) => Err(Box::new(FnExecError::Unreachable) as DynError),
+            };
+            pegasus::set_current_worker(None);
+            // the reads of the batch interrupted are dropped
+            let _ = reply.send(adjacency);
+        });
+        match self.pool.try_submit(task) {
+            Ok(()) => Read::Pool(replies),
+            Err(_) => Read::Worker,
+        }
+    }
+}
+
+impl Drop for PrefetchExpandOperator {
+    fn drop(&mut self) {
+        if log_enabled!(log::Level::Debug) {
+            debug!(
+                "prefetch expand of job {} worker {}: {} read ahead, {} waits, {} read inline",
+                self.worker.job_id,
+                self.worker.index,
+                self.records.get(),
+                self.waits.get(),
+                self.inline.get()
+            );
+        }
+    }
+}
+
+enum Read {
+    /// Read by the pool, replied to the receiver.
+    Pool(Receiver<Adjacency>),
+    /// Read by the worker as the record is expanded.
+    Worker,
+}
+
+/// The reads of the record expanded next and of up to `depth` records after it, in the order of the
+/// records.
+pub struct PrefetchReads<'a> {
+    operator: &'a PrefetchExpandOperator,
+    reads: VecDeque<Read>,
+}
+
+impl<'a> PrefetchReads<'a> {
+    /// Read the adjacency of the records ahead not read yet, with `ahead` from the record expanded next.
+    pub fn request<'b>(&mut self, ahead: impl Iterator<Item = &'b Record>) {
+        let wanted = self.operator.depth + 1;
+        for record in ahead
+            .skip(self.reads.len())
+            .take(wanted.saturating_sub(self.reads.len()))
+        {
+            let read = self.operator.submit(record.clone());
+            let full = matches!(read, Read::Worker);
+            self.reads.push_back(read);
+            if full {
+                return;
+            }
+        }
+    }
+
+    /// The adjacency of the record expanded next, which is the first one of the records requested.
+    pub fn next(&mut self, record: Record) -> Adjacency {
+        let operator = self.operator;
+        match self.reads.pop_front() {
+            Some(Read::Pool(replies)) => {
+                let adjacency = match replies.try_recv() {
+                    Ok(adjacency) => adjacency,
+                    Err(TryRecvError::Empty) => {
+                        PREFETCH_WAITS.inc();
+                        operator.waits.set(operator.waits.get() + 1);
+                        replies
+                            .recv()
+                            .unwrap_or_else(|_| Err(Box::new(FnExecError::Unreachable) as DynError))
+                    }
+                    Err(TryRecvError::Disconnected) => Err(Box::new(FnExecError::Unreachable) as DynError),
+                };
+                PREFETCH_RECORDS.inc();
+                operator.records.set(operator.records.get() + 1);
+                adjacency
+            }
+            Some(Read::Worker) | None => {
+                PREFETCH_INLINE.inc();
+                operator.inline.set(operator.inline.get() + 1);
+                read(operator.expand.as_ref(), record)
+            }
+        }
+    }
+}
+
+pub trait PrefetchExpandFuncGen {
+    fn gen_prefetch_expand(self, prefetch: ExpandPrefetch) -> FnGenResult<PrefetchExpandOperator>;
+}
+
+impl PrefetchExpandFuncGen for pb::EdgeExpand {
+    fn gen_prefetch_expand(self, prefetch: ExpandPrefetch) -> FnGenResult<PrefetchExpandOperator> {
+        let worker = pegasus::get_current_worker();
+        let shared = self.clone().gen_count_expand()?;
+        let expand = self.gen_count_expand()?;
+        if log_enabled!(log::Level::Debug) && worker.index == 0 {
+            debug!("Runtime prefetch expand operator with depth {}", prefetch.depth);
+        }
+        Ok(PrefetchExpandOperator::new(shared, expand, prefetch, worker))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::{DynDetails, GraphElement, Vertex, ID};
+    use pegasus::api::function::{DynIter, FlatMapFunction};
+
+    use super::*;
+    use crate::process::entry::Entry;
+    use crate::process::operator::tests::PERSON_LABEL;
+
+    /// Expand the vertex of id `n` to the vertices of ids `0..n`, failing on the vertex of id 0.
+    struct CountingExpand;
+
+    impl FlatMapFunction<Record, Record> for CountingExpand {
+        type Target = DynIter<Record>;
+
+        fn exec(&self, input: Record) -> FnResult<Self::Target> {
+            let degree = input.get(None).unwrap().id();
+            if degree == 0 {
+                return Err(Box::new(FnExecError::unexpected_data_error("vertex 0")));
+            }
+            Ok(Box::new((0..degree).map(|id| Record::new(vertex(id), None))))
+        }
+    }
+
+    impl CountExpand for CountingExpand {
+        fn count(&self, _input: &Record, _limit: usize) -> FnResult<Option<usize>> {
+            Ok(None)
+        }
+    }
+
+    fn vertex(id: ID) -> Vertex {
+        Vertex::new(id, Some(PERSON_LABEL), DynDetails::default())
+    }
+
+    fn operator(depth: usize, pool: &Arc<PrefetchPool>) -> PrefetchExpandOperator {
+        let worker = WorkerId::new(1, 1, 0, 0, 0, 1, false);
+        let prefetch = ExpandPrefetch { depth, pool: pool.clone() };
+        PrefetchExpandOperator::new(Box::new(CountingExpand), Box::new(CountingExpand), prefetch, worker)
+    }
+
+    fn records(ids: &[ID]) -> VecDeque<Record> {
+        ids.iter()
+            .map(|id| Record::new(vertex(*id), None))
+            .collect()
+    }
+
+    /// Expand the records of the batch as the operator does, stopping after `limit` records as if the
+    /// output is blocked.
+    fn expand(
+        operator: &PrefetchExpandOperator, batch: &mut VecDeque<Record>, limit: usize,
+    ) -> FnResult<Vec<ID>> {
+        let mut expanded = vec![];
+        let mut reads = operator.reads();
+        for _ in 0..limit {
+            reads.request(batch.iter());
+            match batch.pop_front() {
+                Some(record) => expanded.extend(
+                    reads
+                        .next(record)?
+                        .into_iter()
+                        .map(|record| record.get(None).unwrap().id()),
+                ),
+                None => break,
+            }
+        }
+        Ok(expanded)
+    }
+
+    #[test]
+    fn prefetch_expand_test() {
+        let pool = Arc::new(PrefetchPool::new(2, 16).unwrap());
+        for depth in vec![0, 1, 8] {
+            let operator = operator(depth, &pool);
+            let expanded = expand(&operator, &mut records(&[3, 1, 2]), usize::MAX).unwrap();
+            assert_eq!(expanded, vec![0, 1, 2, 0, 0, 1]);
+            assert_eq!(operator.records.get() + operator.inline.get(), 3);
+        }
+    }
+
+    #[test]
+    fn prefetch_expand_interrupted_test() {
+        // the batch interrupted is resumed from the record after the last one given
+        let pool = Arc::new(PrefetchPool::new(2, 16).unwrap());
+        let operator = operator(2, &pool);
+        let mut batch = records(&[2, 3, 1, 2]);
+        assert_eq!(expand(&operator, &mut batch, 1).unwrap(), vec![0, 1]);
+        assert_eq!(batch.len(), 3);
+        assert_eq!(expand(&operator, &mut batch, usize::MAX).unwrap(), vec![0, 1, 2, 0, 0, 1]);
+    }
+
+    #[test]
+    fn prefetch_expand_pool_full_test() {
+        // the pool of no thread never takes a read, which are all read by the worker
+        let pool = Arc::new(PrefetchPool::new(0, 0).unwrap());
+        let operator = operator(4, &pool);
+        let expanded = expand(&operator, &mut records(&[3, 1, 2]), usize::MAX).unwrap();
+        assert_eq!(expanded, vec![0, 1, 2, 0, 0, 1]);
+        assert_eq!(operator.inline.get(), 3);
+        assert_eq!(operator.records.get(), 0);
+    }
+
+    #[test]
+    fn prefetch_expand_error_test() {
+        let pool = Arc::new(PrefetchPool::new(2, 16).unwrap());
+        for depth in vec![0, 1, 8] {
+            let operator = operator(depth, &pool);
+            let mut batch = records(&[2, 0, 3]);
+            assert!(expand(&operator, &mut batch, usize::MAX).is_err());
+            // the record failed is not given, nor the ones after it
+            assert_eq!(batch.len(), 1);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The repeat operator runs its body in a loop of the dataflow, rather than unrolled into a fixed
+//! number of iterations. The records satisfying the condition leave the loop, and the others are fed
+//! back to the body, until no record is left in the loop, which all the workers agree on as the ends
+//! of the iterations are synchronized among them, or until the max iterations.
+//!
+//! The filters at the beginning of the body on the tags bound before the loop, e.g., of
+//! `repeat(where(select('a').has(...)).out())`, are invariant in the iterations, as long as the body
+//! keeps the tags, e.g., with the expands and the filters only. They're hoisted out of the loop, and
+//! evaluated once, as the records enter the loop, rather than in every iteration.
+//!
+//! The records entering each iteration are counted in `ir_repeat_iteration_records_total`.
+
+use std::convert::TryInto;
+
+use ir_common::error::ParsePbError;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+use ir_common::KeyId;
+use lazy_static::lazy_static;
+use pegasus::api::Unary;
+use pegasus::stream::Stream;
+use pegasus::{BuildJobError, Tag};
+use prometheus::{register_int_counter_vec, IntCounterVec};
+
+use crate::error::{FnGenError, FnGenResult};
+use crate::process::record::Record;
+use crate::provenance::expr_tags;
+
+/// The iterations counted separately, after which the records are counted together.
+const MAX_ITERATION_LABEL: u32 = 64;
+
+lazy_static! {
+    static ref ITERATION_RECORDS: IntCounterVec = register_int_counter_vec!(
+        "ir_repeat_iteration_records_total",
+        "Records entering each iteration of the repeat operators.",
+        &["iteration"]
+    )
+    .unwrap();
+}
+
+pub struct RepeatOperator {
+    /// The filters hoisted out of the body, which are invariant in the iterations
+    pub invariants: Vec<algebra_pb::Select>,
+    /// The body after the invariant filters
+    pub body: Vec<pb::PhysicalOpr>,
+    /// The condition of the records to leave the loop
+    pub until: Option<algebra_pb::Select>,
+    /// The iterations at most, which is `u32::MAX` if unbounded
+    pub max_iterations: u32,
+    pub until_first: bool,
+}
+
+pub trait RepeatFuncGen {
+    fn gen_repeat(self) -> FnGenResult<RepeatOperator>;
+}
+
+impl RepeatFuncGen for pb::Repeat {
+    fn gen_repeat(self) -> FnGenResult<RepeatOperator> {
+        let body = self
+            .body
+            .map(|body| body.plan)
+            .unwrap_or_default();
+        if body.is_empty() {
+            Err(ParsePbError::EmptyFieldError("empty body of Repeat".to_string()))?
+        }
+        let until = self
+            .until
+            .map(|predicate| algebra_pb::Select { predicate: Some(predicate) });
+        if until.is_none() && self.max_iterations <= 0 {
+            Err(FnGenError::unsupported_error("repeat without the condition or the max iterations"))?
+        }
+        let max_iterations = if self.max_iterations > 0 { self.max_iterations as u32 } else { u32::MAX };
+        let (invariants, body) = hoist_invariants(body)?;
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!(
+                "Runtime repeat operator with {} invariant filters hoisted, max iterations {}",
+                invariants.len(),
+                max_iterations
+            );
+        }
+        Ok(RepeatOperator { invariants, body, until, max_iterations, until_first: self.until_first })
+    }
+}
+
+/// Split the filters at the beginning of the body which are invariant in the iterations, i.e., on the
+/// tags bound before the loop only, from the body, if the body keeps the tags.
+fn hoist_invariants(
+    body: Vec<pb::PhysicalOpr>,
+) -> FnGenResult<(Vec<algebra_pb::Select>, Vec<pb::PhysicalOpr>)> {
+    let mut bound = vec![];
+    for opr in body.iter() {
+        let op_kind: OpKind = opr.try_into()?;
+        match bound_tags(op_kind) {
+            Some(tags) => bound.extend(tags),
+            None => return Ok((vec![], body)),
+        }
+    }
+    let mut invariants = vec![];
+    let mut rest = body.into_iter().peekable();
+    while let Some(opr) = rest.peek() {
+        let op_kind: OpKind = opr.try_into()?;
+        match op_kind {
+            OpKind::Select(select) if is_invariant(&select, &bound) => {
+                invariants.push(select);
+                rest.next();
+            }
+            _ => break,
+        }
+    }
+    Ok((invariants, rest.collect()))
+}
+
+/// The tags bound by the operator, or `None` if it may drop the tags bound before.
+fn bound_tags(op_kind: OpKind) -> Option<Vec<KeyId>> {
+    match op_kind {
+        OpKind::Select(_) | OpKind::Dedup(_) | OpKind::Repartition(_) => Some(vec![]),
+        OpKind::Edge(expand) => Some(expand.alias.into_iter().collect()),
+        OpKind::Vertex(get_v) => Some(get_v.alias.into_iter().collect()),
+        OpKind::Path(path) => Some(path.alias.into_iter().collect()),
+        OpKind::Project(project) if project.is_append => Some(
+            project
+                .mappings
+                .into_iter()
+                .filter_map(|mapping| mapping.alias)
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Whether the filter is on the tags which are not bound in the body, rather than on the head.
+fn is_invariant(select: &algebra_pb::Select, bound: &[KeyId]) -> bool {
+    select
+        .predicate
+        .as_ref()
+        .map_or(false, |predicate| {
+            expr_tags(predicate)
+                .iter()
+                .all(|tag| tag.map_or(false, |tag| !bound.contains(&tag)))
+        })
+}
+
+fn iteration_label(iteration: u32) -> String {
+    if iteration < MAX_ITERATION_LABEL {
+        iteration.to_string()
+    } else {
+        format!("{}+", MAX_ITERATION_LABEL)
+    }
+}
+
+/// Count the records entering the iteration, which is given by the tag of their batch.
+pub fn count_iteration<F>(stream: Stream<Record>, iteration: F) -> Result<Stream<Record>, BuildJobError>
+where
+    F: Fn(&Tag) -> u32 + Send + 'static,
+{
+    stream.unary("RepeatMetrics", move |_info| {
+        move |input, output| {
+            input.for_each_batch(|batch| {
+                let end = batch.take_end();
+                let res = if !batch.is_empty() {
+                    ITERATION_RECORDS
+                        .with_label_values(&[&iteration_label(iteration(&batch.tag))])
+                        .inc_by(batch.len() as u64);
+                    output.push_batch_mut(batch)
+                } else {
+                    Ok(())
+                };
+                if let Some(end) = end {
+                    batch.set_end(end);
+                }
+                Ok(res?)
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+
+    fn select(expr: &str) -> pb::PhysicalOpr {
+        let predicate = str_to_expr_pb(expr.to_string()).unwrap();
+        OpKind::Select(algebra_pb::Select { predicate: Some(predicate) }).into()
+    }
+
+    fn expand(alias: Option<KeyId>) -> pb::PhysicalOpr {
+        pb::EdgeExpand { alias, ..Default::default() }.into()
+    }
+
+    #[test]
+    fn hoist_invariants_test() {
+        // the filter on the tag bound before the loop is hoisted, while those after are not
+        let body =
+            vec![select("@0.age > 20"), select("@.age > 20"), select("@0.age < 30"), expand(Some(1))];
+        let (invariants, body) = hoist_invariants(body).unwrap();
+        assert_eq!(invariants.len(), 1);
+        assert_eq!(body.len(), 3);
+
+        // the filter on the tag rebound in the body is not
+        let body = vec![select("@1.age > 20"), expand(Some(1))];
+        let (invariants, body) = hoist_invariants(body).unwrap();
+        assert!(invariants.is_empty());
+        assert_eq!(body.len(), 2);
+
+        // nor in the body which may drop the tags
+        let project = pb::Project { mappings: vec![], is_append: false, select_tags: false };
+        let body = vec![select("@0.age > 20"), expand(None), OpKind::Project(project).into()];
+        let (invariants, body) = hoist_invariants(body).unwrap();
+        assert!(invariants.is_empty());
+        assert_eq!(body.len(), 3);
+    }
+
+    #[test]
+    fn iteration_label_test() {
+        assert_eq!(iteration_label(0), "0");
+        assert_eq!(iteration_label(MAX_ITERATION_LABEL - 1), "63");
+        assert_eq!(iteration_label(MAX_ITERATION_LABEL), "64+");
+    }
+}
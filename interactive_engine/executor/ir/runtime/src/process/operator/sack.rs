@@ -0,0 +1,388 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The sack of a record is a state carried along with its columns, across the workers as well, which
+//! is initialized, updated and projected by the `Sack` operators, e.g., the weight of a walk. The
+//! records derived from a record share its sack, until it's updated, as the entries are immutable.
+//! The records of the same head and the same columns, i.e., the equal traversers of TinkerPop, are
+//! merged into one by `Sack::Merge`, with their sacks merged.
+
+use std::convert::TryFrom;
+use std::convert::TryInto;
+
+use dyn_type::{Object, Primitives};
+use graph_proxy::utils::expr::eval::{Evaluate, Evaluator};
+use ir_common::error::ParsePbError;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::KeyId;
+use pegasus::api::function::{FnResult, MapFunction};
+
+use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
+use crate::process::entry::{DynEntry, Entry};
+use crate::process::record::{Record, RecordKey};
+
+pub enum SackOperator {
+    /// Initialize, update or project the sack of each record
+    Map(Box<dyn MapFunction<Record, Record>>),
+    /// Merge the records of the same head and the same columns, which are keyed by them
+    Merge(SackMerge),
+}
+
+/// Initialize the sack of each record as the value of the expression.
+struct InitSack {
+    initial: Evaluator,
+}
+
+impl MapFunction<Record, Record> for InitSack {
+    fn exec(&self, mut input: Record) -> FnResult<Record> {
+        let initial = self
+            .initial
+            .eval::<DynEntry, Record>(Some(&input))
+            .map_err(FnExecError::from)?;
+        input.set_sack(Some(DynEntry::new(initial)));
+        Ok(input)
+    }
+}
+
+/// Update the sack of each record by the operator with the value of the expression.
+struct UpdateSack {
+    operator: algebra_pb::sack::Operator,
+    by: Evaluator,
+}
+
+impl MapFunction<Record, Record> for UpdateSack {
+    fn exec(&self, mut input: Record) -> FnResult<Record> {
+        let value = self
+            .by
+            .eval::<DynEntry, Record>(Some(&input))
+            .map_err(FnExecError::from)?;
+        let sack = apply_operator(self.operator, input.take_sack(), DynEntry::new(value))?;
+        input.set_sack(Some(sack));
+        Ok(input)
+    }
+}
+
+/// Project the sack of each record as the head, which is none without the sack.
+struct GetSack {
+    alias: Option<KeyId>,
+}
+
+impl MapFunction<Record, Record> for GetSack {
+    fn exec(&self, mut input: Record) -> FnResult<Record> {
+        let sack = input
+            .get_sack()
+            .cloned()
+            .unwrap_or_else(|| DynEntry::new(Object::None));
+        input.append_arc_entry(sack, self.alias);
+        Ok(input)
+    }
+}
+
+/// Merge the records of the same head and the same columns into the first of them, whose sack is
+/// merged by the operator, as the records differ in their sacks only.
+#[derive(Clone, Copy, Debug)]
+pub struct SackMerge {
+    operator: algebra_pb::sack::Operator,
+}
+
+impl SackMerge {
+    pub fn get_kv(&self, mut input: Record) -> FnResult<(RecordKey, Record)> {
+        let head = input
+            .get(None)
+            .cloned()
+            .unwrap_or_else(|| DynEntry::new(Object::None));
+        let mut key = vec![head];
+        // the columns are keyed along with their tags, which are in the order of the tags
+        for (tag, entry) in input.get_columns_mut().iter() {
+            key.push(DynEntry::new(Object::from(tag as KeyId)));
+            key.push(entry.clone());
+        }
+        Ok((RecordKey::new(key), input))
+    }
+
+    pub fn merge(&self, mut merged: Record, mut next: Record) -> FnResult<Record> {
+        let sack = match (merged.take_sack(), next.take_sack()) {
+            (Some(sack), Some(other)) => Some(apply_operator(self.operator, Some(sack), other)?),
+            (sack, other) => sack.or(other),
+        };
+        merged.set_sack(sack);
+        Ok(merged)
+    }
+}
+
+/// Apply the operator on the sack with the value, where the sack must be given unless it's assigned.
+fn apply_operator(
+    operator: algebra_pb::sack::Operator, sack: Option<DynEntry>, value: DynEntry,
+) -> FnExecResult<DynEntry> {
+    use algebra_pb::sack::Operator;
+    let sack = match (operator, sack) {
+        (Operator::Assign, _) => return Ok(value),
+        (_, Some(sack)) => sack,
+        (_, None) => Err(FnExecError::unexpected_data_error(&format!(
+            "sack is not initialized before {:?} with {:?}",
+            operator, value
+        )))?,
+    };
+    let merged = match operator {
+        Operator::Min => {
+            if value < sack {
+                value
+            } else {
+                sack
+            }
+        }
+        Operator::Max => {
+            if value > sack {
+                value
+            } else {
+                sack
+            }
+        }
+        _ => {
+            let (a, b) = (as_primitive(&sack)?, as_primitive(&value)?);
+            if operator == Operator::Div
+                && matches!(
+                    b,
+                    Primitives::Byte(0)
+                        | Primitives::Integer(0)
+                        | Primitives::Long(0)
+                        | Primitives::ULLong(0)
+                )
+            {
+                Err(FnExecError::unexpected_data_error(&format!("sack {:?} divided by zero", sack)))?
+            }
+            let result = checked_arith(operator, a, b).ok_or_else(|| {
+                FnExecError::unexpected_data_error(&format!(
+                    "sack {:?} overflows by {:?} with {:?}",
+                    sack, operator, value
+                ))
+            })?;
+            DynEntry::new(Object::Primitive(result))
+        }
+    };
+    Ok(merged)
+}
+
+/// Apply the arithmetic operator on the numbers, of the wider type of both as `dyn_type` does, which is
+/// none if the integers overflow, e.g., `i32::MIN / -1`, rather than panicking or wrapping around.
+fn checked_arith(operator: algebra_pb::sack::Operator, a: Primitives, b: Primitives) -> Option<Primitives> {
+    use algebra_pb::sack::Operator;
+    macro_rules! checked {
+        ($a:expr, $b:expr) => {
+            match operator {
+                Operator::Sum => $a.checked_add($b),
+                Operator::Minus => $a.checked_sub($b),
+                Operator::Mult => $a.checked_mul($b),
+                _ => $a.checked_div($b),
+            }
+        };
+    }
+    match (a, b) {
+        (Primitives::Float(_), _) | (_, Primitives::Float(_)) => Some(match operator {
+            Operator::Sum => a + b,
+            Operator::Minus => a - b,
+            Operator::Mult => a * b,
+            _ => a / b,
+        }),
+        (Primitives::ULLong(_), _) | (_, Primitives::ULLong(_)) => {
+            checked!(a.as_u128().ok()?, b.as_u128().ok()?).map(Primitives::ULLong)
+        }
+        (Primitives::Long(_), _) | (_, Primitives::Long(_)) => {
+            checked!(a.as_i64().ok()?, b.as_i64().ok()?).map(Primitives::Long)
+        }
+        (Primitives::Integer(_), _) | (_, Primitives::Integer(_)) => {
+            checked!(a.as_i32().ok()?, b.as_i32().ok()?).map(Primitives::Integer)
+        }
+        (Primitives::Byte(a), Primitives::Byte(b)) => checked!(a, b).map(Primitives::Byte),
+    }
+}
+
+fn as_primitive(entry: &DynEntry) -> FnExecResult<Primitives> {
+    entry
+        .as_object()
+        .and_then(|object| object.as_primitive().ok())
+        .ok_or_else(|| FnExecError::unexpected_data_error(&format!("sack {:?} is not a number", entry)))
+}
+
+pub trait SackFuncGen {
+    fn gen_sack(self) -> FnGenResult<SackOperator>;
+}
+
+impl SackFuncGen for algebra_pb::Sack {
+    fn gen_sack(self) -> FnGenResult<SackOperator> {
+        let operator_of = |operator: i32| {
+            algebra_pb::sack::Operator::from_i32(operator)
+                .ok_or_else(|| FnGenError::unsupported_error(&format!("sack operator {}", operator)))
+        };
+        let inner = self
+            .inner
+            .ok_or_else(|| ParsePbError::EmptyFieldError("inner of Sack".to_string()))?;
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!("Runtime sack operator {:?}", inner);
+        }
+        let sack = match inner {
+            algebra_pb::sack::Inner::Init(init) => {
+                let initial = init
+                    .initial
+                    .ok_or_else(|| ParsePbError::EmptyFieldError("initial of Sack::Init".to_string()))?;
+                SackOperator::Map(Box::new(InitSack { initial: Evaluator::try_from(initial)? }))
+            }
+            algebra_pb::sack::Inner::Update(update) => {
+                let by = update
+                    .by
+                    .ok_or_else(|| ParsePbError::EmptyFieldError("by of Sack::Update".to_string()))?;
+                SackOperator::Map(Box::new(UpdateSack {
+                    operator: operator_of(update.operator)?,
+                    by: Evaluator::try_from(by)?,
+                }))
+            }
+            algebra_pb::sack::Inner::Merge(merge) => {
+                SackOperator::Merge(SackMerge { operator: operator_of(merge.operator)? })
+            }
+            algebra_pb::sack::Inner::Get(get) => {
+                let alias: Option<KeyId> = get
+                    .alias
+                    .map(|alias| alias.try_into())
+                    .transpose()?;
+                SackOperator::Map(Box::new(GetSack { alias }))
+            }
+        };
+        Ok(sack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+    use crate::process::operator::tests::init_source;
+
+    fn gen_map(inner: algebra_pb::sack::Inner) -> Box<dyn MapFunction<Record, Record>> {
+        match (algebra_pb::Sack { inner: Some(inner) })
+            .gen_sack()
+            .unwrap()
+        {
+            SackOperator::Map(map) => map,
+            SackOperator::Merge(_) => panic!("should be a map of the sacks"),
+        }
+    }
+
+    fn init(expr: &str) -> Box<dyn MapFunction<Record, Record>> {
+        let initial = str_to_expr_pb(expr.to_string()).ok();
+        gen_map(algebra_pb::sack::Inner::Init(algebra_pb::sack::Init { initial }))
+    }
+
+    fn update(operator: algebra_pb::sack::Operator, expr: &str) -> Box<dyn MapFunction<Record, Record>> {
+        let by = str_to_expr_pb(expr.to_string()).ok();
+        gen_map(algebra_pb::sack::Inner::Update(algebra_pb::sack::Update { operator: operator as i32, by }))
+    }
+
+    fn sack_of(record: &Record) -> Object {
+        record
+            .get_sack()
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .clone()
+    }
+
+    // g.withSack(1).V().sack(sum).by('age').sack(mult).by(2).sack()
+    #[test]
+    fn sack_update_test() {
+        let init = init("1");
+        let sum = update(algebra_pb::sack::Operator::Sum, "@.age");
+        let mult = update(algebra_pb::sack::Operator::Mult, "2");
+        let get = gen_map(algebra_pb::sack::Inner::Get(algebra_pb::sack::Get { alias: None }));
+        let mut sacks = vec![];
+        for record in init_source() {
+            let record = mult
+                .exec(sum.exec(init.exec(record).unwrap()).unwrap())
+                .unwrap();
+            // the sack is carried across the workers
+            let mut bytes = vec![];
+            pegasus::codec::Encode::write_to(&record, &mut bytes).unwrap();
+            let record: Record = pegasus::codec::Decode::read_from(&mut bytes.as_slice()).unwrap();
+            let record = get.exec(record).unwrap();
+            assert_eq!(record.get(None).unwrap().as_object(), Some(&sack_of(&record)));
+            sacks.push(sack_of(&record));
+        }
+        assert_eq!(sacks, vec![object!(60), object!(56)]);
+    }
+
+    // the sack is not sent to the servers of the protocol before it, which would drop it
+    #[test]
+    fn sack_protocol_version_test() {
+        let record = init("1").exec(init_source().remove(0)).unwrap();
+        let mut bytes = vec![];
+        assert!(pegasus::codec::with_protocol_version(2, || pegasus::codec::Encode::write_to(
+            &record, &mut bytes
+        ))
+        .is_err());
+        let mut bytes = vec![];
+        assert!(pegasus::codec::Encode::write_to(&record, &mut bytes).is_ok());
+    }
+
+    #[test]
+    fn sack_update_error_test() {
+        // the sack is not initialized
+        let sum = update(algebra_pb::sack::Operator::Sum, "1");
+        assert!(sum.exec(init_source().remove(0)).is_err());
+        // while it can be assigned without being initialized
+        let assign = update(algebra_pb::sack::Operator::Assign, "1");
+        let record = assign.exec(init_source().remove(0)).unwrap();
+        assert_eq!(sack_of(&record), object!(1));
+        let div = update(algebra_pb::sack::Operator::Div, "0");
+        assert!(div.exec(record).is_err());
+    }
+
+    #[test]
+    fn sack_overflow_test() {
+        // the integers in the expressions are of i64
+        let use_sack = |sack: Object, operator: algebra_pb::sack::Operator, by: &str| {
+            let mut record = init_source().remove(0);
+            record.set_sack(Some(DynEntry::new(sack)));
+            update(operator, by).exec(record)
+        };
+        assert!(use_sack(object!(i64::MIN), algebra_pb::sack::Operator::Div, "-1").is_err());
+        assert!(use_sack(object!(i64::MIN), algebra_pb::sack::Operator::Minus, "1").is_err());
+        assert!(use_sack(object!(i64::MAX), algebra_pb::sack::Operator::Mult, "2").is_err());
+        // while the integers are widened as of `dyn_type`
+        let record = use_sack(object!(i32::MIN), algebra_pb::sack::Operator::Minus, "1").unwrap();
+        assert_eq!(sack_of(&record), object!(i32::MIN as i64 - 1));
+    }
+
+    #[test]
+    fn sack_merge_test() {
+        let merge = SackMerge { operator: algebra_pb::sack::Operator::Sum };
+        let init = init("@.age");
+        let mut records = init_source();
+        let vertex1 = init.exec(records.remove(0)).unwrap();
+        let merged = merge
+            .merge(vertex1.clone(), vertex1.clone())
+            .unwrap();
+        assert_eq!(sack_of(&merged), object!(58));
+        // the sack of the other is taken if the record has none
+        let merged = merge.merge(records.remove(0), vertex1).unwrap();
+        assert_eq!(sack_of(&merged), object!(29));
+        let (key, _) = merge.get_kv(merged.clone()).unwrap();
+        assert_eq!(key, RecordKey::new(vec![merged.get(None).unwrap().clone()]));
+        // the records of the same head but different columns are not merged
+        let mut tagged = merged.clone();
+        tagged.append(object!(1), Some(0));
+        let (tagged_key, _) = merge.get_kv(tagged).unwrap();
+        assert_ne!(tagged_key, key);
+    }
+}
@@ -0,0 +1,494 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The side effects of a job are named accumulators of the entries of the records, e.g., of
+//! `aggregate('x')` or `groupCount('x')`. Each worker accumulates the records passing through the
+//! `SideEffect` operator into its partial side effect, which is sent as the records end to be merged
+//! with those of the other workers, and of the other operators of the same name, in a single worker.
+//! The merged side effect replaces the records of `Cap`, or else is returned after the results.
+//!
+//! The partial side effects are kept by the name in the worker installing the plan, until they're
+//! read by `Cap` or returned after the results, as the operators reading them may be installed far
+//! after the operators accumulating them. So `Cap` is in the root plan only, i.e., of the same scope
+//! as the results. The `SideEffect` in a sub-task, e.g., `repeat(aggregate('x'))`, accumulates into
+//! an accumulator local to the worker instead, which is sent as the partial side effect once the
+//! records of the root plan end after the sub-task, as all the records of the sub-task end before.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::{Arc, Mutex};
+
+use dyn_type::Object;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::KeyId;
+use pegasus::api::function::FnResult;
+use pegasus::api::{Fold, Map, Merge};
+use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+use pegasus::stream::Stream;
+use pegasus::BuildJobError;
+
+use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
+use crate::process::entry::{CollectionEntry, DynEntry, PairEntry};
+use crate::process::operator::accum::accumulator::{Accumulator, Count, ToList, ToSet};
+use crate::process::operator::accum::EntryAccumulator;
+use crate::process::record::Record;
+
+/// The side effect accumulated by a worker in a sub-task, which is taken as the partial side effect.
+pub type LocalSideEffect = Arc<Mutex<Option<SideEffectAccum>>>;
+
+#[derive(Default)]
+struct SideEffects {
+    /// The partial side effects by the names
+    partial: HashMap<String, Stream<SideEffectAccum>>,
+    /// The side effects accumulated in the sub-tasks, which are not sent as partial side effects yet
+    local: Vec<(String, LocalSideEffect)>,
+}
+
+thread_local! {
+    /// The side effects of the job installed by the worker.
+    static SIDE_EFFECTS: RefCell<Option<SideEffects>> = RefCell::new(None);
+}
+
+/// Keep the side effects of the job installed by the thread until dropped.
+pub(crate) struct InstallingSideEffects {
+    was_installing: Option<SideEffects>,
+}
+
+impl InstallingSideEffects {
+    pub(crate) fn new() -> Self {
+        let was_installing =
+            SIDE_EFFECTS.with(|side_effects| side_effects.replace(Some(SideEffects::default())));
+        InstallingSideEffects { was_installing }
+    }
+
+    /// Take the side effects not read by `Cap`, in the order of their names, merged respectively.
+    pub(crate) fn take_unread(&self) -> Result<Vec<(String, Stream<DynEntry>)>, BuildJobError> {
+        let side_effects = SIDE_EFFECTS.with(|side_effects| {
+            side_effects
+                .borrow_mut()
+                .as_mut()
+                .map(|side_effects| std::mem::take(&mut side_effects.partial))
+                .unwrap_or_default()
+        });
+        let mut side_effects: Vec<_> = side_effects.into_iter().collect();
+        side_effects.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+        side_effects
+            .into_iter()
+            .map(|(name, partial)| Ok((name, merge_side_effect(partial)?)))
+            .collect()
+    }
+}
+
+impl Drop for InstallingSideEffects {
+    fn drop(&mut self) {
+        let was_installing = self.was_installing.take();
+        SIDE_EFFECTS.with(|side_effects| side_effects.replace(was_installing));
+    }
+}
+
+/// Keep the partial side effects of the name, along with those of the other operators of the name.
+pub fn add_side_effect(name: String, partial: Stream<SideEffectAccum>) -> Result<(), BuildJobError> {
+    if partial.get_scope_level() != 0 {
+        Err(FnGenError::unsupported_error(&format!("side effect `{}` out of the root plan", name)))?
+    }
+    SIDE_EFFECTS.with(|side_effects| {
+        let mut side_effects = side_effects.borrow_mut();
+        let side_effects = side_effects.as_mut().ok_or_else(|| {
+            FnGenError::unsupported_error(&format!("side effect `{}` out of a job", name))
+        })?;
+        let partial = match side_effects.partial.remove(&name) {
+            Some(added) => added.merge(partial)?,
+            None => partial,
+        };
+        side_effects.partial.insert(name, partial);
+        Ok(())
+    })
+}
+
+/// Keep the side effect of the name accumulated by the worker in a sub-task, until it's sent as the
+/// partial side effect by `send_local_side_effects()`.
+pub fn add_local_side_effect(
+    name: String, accum: SideEffectAccum,
+) -> Result<LocalSideEffect, BuildJobError> {
+    SIDE_EFFECTS.with(|side_effects| {
+        let mut side_effects = side_effects.borrow_mut();
+        let side_effects = side_effects.as_mut().ok_or_else(|| {
+            FnGenError::unsupported_error(&format!("side effect `{}` out of a job", name))
+        })?;
+        let local = Arc::new(Mutex::new(Some(accum)));
+        side_effects.local.push((name, local.clone()));
+        Ok(local)
+    })
+}
+
+/// Send the side effects accumulated by the worker in the sub-tasks installed before the stream of the
+/// root plan, as the partial side effects once the records of the stream end.
+pub fn send_local_side_effects(stream: Stream<Record>) -> Result<Stream<Record>, BuildJobError> {
+    let local = SIDE_EFFECTS.with(|side_effects| {
+        side_effects
+            .borrow_mut()
+            .as_mut()
+            .map(|side_effects| std::mem::take(&mut side_effects.local))
+            .unwrap_or_default()
+    });
+    if local.is_empty() || stream.get_scope_level() != 0 {
+        // keep them until the records of the root plan
+        SIDE_EFFECTS.with(|side_effects| {
+            if let Some(side_effects) = side_effects.borrow_mut().as_mut() {
+                side_effects.local.extend(local);
+            }
+        });
+        return Ok(stream);
+    }
+    let mut stream = stream;
+    for (name, local) in local {
+        let (main, copied) = stream.copied()?;
+        let partial = copied
+            .fold_partition(0_u64, || |count, _| Ok(count + 1))?
+            .into_stream()?
+            .map(move |_| {
+                let partial = local
+                    .lock()
+                    .map_err(|e| FnExecError::unexpected_data_error(&e.to_string()))?
+                    .take()
+                    .ok_or_else(|| FnExecError::unexpected_data_error("the local side effect is taken"))?;
+                Ok(partial)
+            })?;
+        add_side_effect(name, partial)?;
+        stream = main;
+    }
+    Ok(stream)
+}
+
+/// Take the side effect of the name merged, which is then not returned after the results.
+pub fn take_side_effect(name: &str) -> Result<Stream<DynEntry>, BuildJobError> {
+    let partial = SIDE_EFFECTS.with(|side_effects| {
+        side_effects
+            .borrow_mut()
+            .as_mut()
+            .and_then(|side_effects| side_effects.partial.remove(name))
+    });
+    let partial = partial.ok_or_else(|| {
+        FnGenError::unsupported_error(&format!("side effect `{}` is not accumulated before", name))
+    })?;
+    if partial.get_scope_level() != 0 {
+        Err(FnGenError::unsupported_error(&format!("side effect `{}` out of the root plan", name)))?
+    }
+    merge_side_effect(partial)
+}
+
+/// Merge the partial side effects in a single worker, once the partial side effects all end.
+fn merge_side_effect(partial: Stream<SideEffectAccum>) -> Result<Stream<DynEntry>, BuildJobError> {
+    partial
+        .fold(None, || {
+            |merged: Option<SideEffectAccum>, next| match merged {
+                Some(mut merged) => {
+                    merged.merge(next)?;
+                    Ok(Some(merged))
+                }
+                None => Ok(Some(next)),
+            }
+        })?
+        .into_stream()?
+        .map(|merged| match merged {
+            Some(mut merged) => Ok(merged.finalize()?),
+            None => Err(FnExecError::unexpected_data_error("no partial side effect is merged").into()),
+        })
+}
+
+/// Accumulate the entries of the tag in the records into the side effect of the name.
+pub struct SideEffectOperator {
+    pub name: String,
+    kind: algebra_pb::side_effect::Kind,
+    tag: Option<KeyId>,
+}
+
+impl SideEffectOperator {
+    pub fn get_entry(&self, input: &Record) -> FnResult<DynEntry> {
+        let entry = input.get(self.tag).ok_or_else(|| {
+            FnExecError::get_tag_error(&format!(
+                "tag {:?} of side effect `{}` is not found in {:?}",
+                self.tag, self.name, input
+            ))
+        })?;
+        Ok(entry.clone())
+    }
+
+    pub fn gen_accum(&self) -> SideEffectAccum {
+        match self.kind {
+            algebra_pb::side_effect::Kind::List => {
+                SideEffectAccum::Entries(EntryAccumulator::ToList(ToList { inner: vec![] }))
+            }
+            algebra_pb::side_effect::Kind::Set => {
+                SideEffectAccum::Entries(EntryAccumulator::ToSet(ToSet { inner: Default::default() }))
+            }
+            algebra_pb::side_effect::Kind::Count => {
+                SideEffectAccum::Entries(EntryAccumulator::ToCount(Count {
+                    value: 0,
+                    _ph: Default::default(),
+                }))
+            }
+            algebra_pb::side_effect::Kind::Map => SideEffectAccum::Map(HashMap::new()),
+        }
+    }
+}
+
+/// The partial side effect accumulated by a worker.
+#[derive(Clone, Debug)]
+pub enum SideEffectAccum {
+    /// The list, the set or the number of the entries
+    Entries(EntryAccumulator),
+    /// The numbers of the distinct entries
+    Map(HashMap<DynEntry, u64>),
+}
+
+impl SideEffectAccum {
+    /// Merge the partial side effect of the same kind.
+    pub fn merge(&mut self, other: SideEffectAccum) -> FnExecResult<()> {
+        match (self, other) {
+            (SideEffectAccum::Entries(entries), SideEffectAccum::Entries(other)) => entries.merge(other),
+            (SideEffectAccum::Map(map), SideEffectAccum::Map(other)) => {
+                for (entry, count) in other {
+                    *map.entry(entry).or_default() += count;
+                }
+                Ok(())
+            }
+            (side_effect, other) => Err(FnExecError::accum_error(&format!(
+                "merge side effect {:?} into {:?}",
+                other, side_effect
+            ))),
+        }
+    }
+}
+
+impl Accumulator<DynEntry, DynEntry> for SideEffectAccum {
+    fn accum(&mut self, next: DynEntry) -> FnExecResult<()> {
+        match self {
+            SideEffectAccum::Entries(entries) => entries.accum(next),
+            SideEffectAccum::Map(map) => {
+                if !next.is_none() {
+                    *map.entry(next).or_default() += 1;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn finalize(&mut self) -> FnExecResult<DynEntry> {
+        match self {
+            SideEffectAccum::Entries(entries) => entries.finalize(),
+            SideEffectAccum::Map(map) => {
+                // the entries may be of any types, which are incomparable, so they're in the order of
+                // their encoded bytes, which is total
+                let mut pairs: Vec<(Vec<u8>, DynEntry, u64)> = std::mem::take(map)
+                    .into_iter()
+                    .map(|(entry, count)| {
+                        let mut bytes = vec![];
+                        entry.write_to(&mut bytes)?;
+                        Ok((bytes, entry, count))
+                    })
+                    .collect::<std::io::Result<_>>()
+                    .map_err(|e| FnExecError::unexpected_data_error(&e.to_string()))?;
+                pairs.sort_by(|(bytes1, _, _), (bytes2, _, _)| bytes1.cmp(bytes2));
+                let inner = pairs
+                    .into_iter()
+                    .map(|(_, entry, count)| {
+                        DynEntry::new(PairEntry::new(entry, DynEntry::new(Object::from(count))))
+                    })
+                    .collect();
+                Ok(DynEntry::new(CollectionEntry { inner }))
+            }
+        }
+    }
+}
+
+impl Encode for SideEffectAccum {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            SideEffectAccum::Entries(entries) => {
+                writer.write_u8(0)?;
+                entries.write_to(writer)?;
+            }
+            SideEffectAccum::Map(map) => {
+                writer.write_u8(1)?;
+                writer.write_u64(map.len() as u64)?;
+                for (entry, count) in map {
+                    entry.write_to(writer)?;
+                    writer.write_u64(*count)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for SideEffectAccum {
+    fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
+        match reader.read_u8()? {
+            0 => Ok(SideEffectAccum::Entries(EntryAccumulator::read_from(reader)?)),
+            1 => {
+                let len = reader.read_u64()? as usize;
+                let mut map = HashMap::with_capacity(len);
+                for _ in 0..len {
+                    let entry = DynEntry::read_from(reader)?;
+                    map.insert(entry, reader.read_u64()?);
+                }
+                Ok(SideEffectAccum::Map(map))
+            }
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Other, "unreachable")),
+        }
+    }
+}
+
+pub trait SideEffectFuncGen {
+    fn gen_side_effect(self) -> FnGenResult<SideEffectOperator>;
+}
+
+impl SideEffectFuncGen for algebra_pb::SideEffect {
+    fn gen_side_effect(self) -> FnGenResult<SideEffectOperator> {
+        let kind = algebra_pb::side_effect::Kind::from_i32(self.kind).ok_or_else(|| {
+            FnGenError::unsupported_error(&format!("side effect of the kind {}", self.kind))
+        })?;
+        let tag: Option<KeyId> = self.tag.map(|tag| tag.try_into()).transpose()?;
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!(
+                "Runtime side effect operator with name {:?}, kind {:?}, tag {:?}",
+                self.name, kind, tag
+            );
+        }
+        Ok(SideEffectOperator { name: self.name, kind, tag })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::GraphElement;
+
+    use super::*;
+    use crate::process::entry::Entry;
+    use crate::process::operator::tests::{init_source, init_vertex1};
+
+    fn side_effect_operator(kind: algebra_pb::side_effect::Kind) -> SideEffectOperator {
+        SideEffectOperator { name: "x".to_string(), kind, tag: None }
+    }
+
+    fn accumulate(kind: algebra_pb::side_effect::Kind, records: Vec<Record>) -> SideEffectAccum {
+        let side_effect = side_effect_operator(kind);
+        let mut accum = side_effect.gen_accum();
+        for record in records {
+            accum
+                .accum(side_effect.get_entry(&record).unwrap())
+                .unwrap();
+        }
+        accum
+    }
+
+    // the partial side effects of two workers, where vertex 1 is accumulated by both
+    fn merged(kind: algebra_pb::side_effect::Kind) -> DynEntry {
+        let mut accum = accumulate(kind, init_source());
+        let other = accumulate(kind, vec![Record::new(init_vertex1(), None)]);
+        // the partial side effects are sent among the workers
+        let mut bytes = vec![];
+        other.write_to(&mut bytes).unwrap();
+        let other = SideEffectAccum::read_from(&mut bytes.as_slice()).unwrap();
+        accum.merge(other).unwrap();
+        accum.finalize().unwrap()
+    }
+
+    fn ids(entry: &DynEntry) -> Vec<i64> {
+        let collection = entry
+            .as_any_ref()
+            .downcast_ref::<CollectionEntry>()
+            .unwrap();
+        let mut ids: Vec<i64> = collection
+            .inner
+            .iter()
+            .map(|entry| entry.as_vertex().unwrap().id())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn side_effect_test() {
+        assert_eq!(ids(&merged(algebra_pb::side_effect::Kind::List)), vec![1, 1, 2]);
+        assert_eq!(ids(&merged(algebra_pb::side_effect::Kind::Set)), vec![1, 2]);
+        assert_eq!(
+            merged(algebra_pb::side_effect::Kind::Count)
+                .as_object()
+                .unwrap()
+                .as_u64()
+                .unwrap(),
+            3
+        );
+        let map = merged(algebra_pb::side_effect::Kind::Map);
+        let counts: Vec<(i64, u64)> = map
+            .as_any_ref()
+            .downcast_ref::<CollectionEntry>()
+            .unwrap()
+            .inner
+            .iter()
+            .map(|entry| {
+                let pair = entry
+                    .as_any_ref()
+                    .downcast_ref::<PairEntry>()
+                    .unwrap();
+                let count = pair
+                    .get_right()
+                    .as_object()
+                    .unwrap()
+                    .as_u64()
+                    .unwrap();
+                (pair.get_left().as_vertex().unwrap().id(), count)
+            })
+            .collect();
+        assert_eq!(counts, vec![(1, 2), (2, 1)]);
+    }
+
+    // the entries of any types are in the same order, whatever the order they're accumulated in
+    #[test]
+    fn side_effect_map_order_test() {
+        let entries = vec![
+            DynEntry::new(Object::from("a")),
+            DynEntry::new(Object::from(1)),
+            DynEntry::new(Object::from(f64::NAN)),
+            DynEntry::new(Object::from(1)),
+        ];
+        let finalize = |entries: Vec<DynEntry>| -> Vec<u8> {
+            let mut accum = SideEffectAccum::Map(HashMap::new());
+            for entry in entries {
+                accum.accum(entry).unwrap();
+            }
+            let mut bytes = vec![];
+            accum
+                .finalize()
+                .unwrap()
+                .write_to(&mut bytes)
+                .unwrap();
+            bytes
+        };
+        let mut reversed = entries.clone();
+        reversed.reverse();
+        assert_eq!(finalize(entries), finalize(reversed));
+    }
+
+    #[test]
+    fn side_effect_kind_mismatch_test() {
+        let mut accum = accumulate(algebra_pb::side_effect::Kind::List, init_source());
+        let other = accumulate(algebra_pb::side_effect::Kind::Map, init_source());
+        assert!(accum.merge(other).is_err());
+    }
+}
@@ -0,0 +1,379 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The results of a batch of records as an Arrow record batch, written in the Arrow IPC stream format and
+//! delivered by the Arrow Flight service of the server, where each sink column is converted into:
+//! * the vertices: `{tag}.id` and `{tag}.label`;
+//! * the edges: `{tag}.id`, `{tag}.label`, `{tag}.src_id` and `{tag}.dst_id`;
+//! * the objects, e.g., the projected properties: `{tag}` typed by its first non-null value.
+//!
+//! The types of the object columns are inferred by each batch, so the schema may change among the batches
+//! if a column is all null in a batch; the collections and paths should be flattened in advance.
+//!
+//! In the provenance tracking mode, the provenance of each record is written in json into the column
+//! `provenance`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BinaryBuilder, Float64Builder, Int32Builder, Int64Builder, StringBuilder,
+    TimestampMillisecondBuilder,
+};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::ipc::writer::StreamWriter;
+use arrow::record_batch::RecordBatch;
+use dyn_type::object::Primitives;
+use dyn_type::Object;
+use graph_proxy::apis::GraphElement;
+use ir_common::generated::algebra::sink_default::MetaType;
+use ir_common::KeyId;
+
+use crate::error::{FnExecError, FnExecResult};
+use crate::process::entry::{DynEntry, Entry, EntryType};
+
+/// The name of the column of the head, which is not tagged
+const HEAD_COLUMN: &str = "head";
+/// The name of the column of the provenance of the records
+const PROVENANCE_COLUMN: &str = "provenance";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColumnKind {
+    Vertex,
+    Edge,
+    Int32,
+    Int64,
+    Float64,
+    Utf8,
+    Binary,
+    Timestamp,
+}
+
+fn arrow_error(e: arrow::error::ArrowError) -> FnExecError {
+    FnExecError::unexpected_data_error(&format!("write arrow error {:?}", e))
+}
+
+fn cast_error<E: std::fmt::Debug>(name: &str, e: E) -> FnExecError {
+    FnExecError::unexpected_data_error(&format!("column `{}` of mixed types in arrow: {:?}", name, e))
+}
+
+pub struct ArrowBatchWriter<'a> {
+    /// A map from id to name, where the unmapped ids are written as they are;
+    schema_map: Option<&'a HashMap<(MetaType, i32), String>>,
+    /// The provenance of each record of the batch in json, in the provenance tracking mode;
+    provenance: Option<Vec<Option<String>>>,
+}
+
+impl<'a> ArrowBatchWriter<'a> {
+    pub fn new(schema_map: Option<&'a HashMap<(MetaType, i32), String>>) -> Self {
+        ArrowBatchWriter { schema_map, provenance: None }
+    }
+
+    pub fn with_provenance(mut self, provenance: Vec<Option<String>>) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Write the sink columns of a batch of records as a record batch, where the entry of a record is
+    /// `None` if the record has no such column.
+    pub fn write_batch(
+        &self, columns: Vec<(Option<KeyId>, Vec<Option<&DynEntry>>)>,
+    ) -> FnExecResult<Vec<u8>> {
+        if columns.is_empty() {
+            Err(FnExecError::unsupported_error("sink no column in arrow"))?
+        }
+        let mut fields = vec![];
+        let mut arrays = vec![];
+        for (tag, entries) in columns {
+            let name = tag
+                .map(|tag| self.get_meta_name(tag, MetaType::Tag))
+                .unwrap_or_else(|| HEAD_COLUMN.to_string());
+            self.write_column(&name, &entries, &mut fields, &mut arrays)?;
+        }
+        if let Some(provenance) = self.provenance.as_ref() {
+            let mut builder = StringBuilder::new();
+            for value in provenance {
+                builder.append_option(value.as_deref());
+            }
+            fields.push(Field::new(PROVENANCE_COLUMN, DataType::Utf8, true));
+            arrays.push(Arc::new(builder.finish()));
+        }
+        let schema = Arc::new(Schema::new(fields));
+        let batch = RecordBatch::try_new(schema.clone(), arrays).map_err(arrow_error)?;
+        let mut buf = vec![];
+        {
+            let mut writer = StreamWriter::try_new(&mut buf, &schema).map_err(arrow_error)?;
+            writer.write(&batch).map_err(arrow_error)?;
+            writer.finish().map_err(arrow_error)?;
+        }
+        Ok(buf)
+    }
+
+    fn get_meta_name(&self, meta_id: KeyId, t: MetaType) -> String {
+        if let Some(meta_name) = self
+            .schema_map
+            .and_then(|schema_map| schema_map.get(&(t, meta_id)))
+        {
+            meta_name.clone()
+        } else {
+            // if we cannot find mapped meta_name, we write meta_id directly.
+            meta_id.to_string()
+        }
+    }
+
+    fn kind_of(&self, name: &str, entries: &[Option<&DynEntry>]) -> FnExecResult<ColumnKind> {
+        for entry in entries.iter().flatten() {
+            let kind =
+                match entry.get_type() {
+                    EntryType::Vertex => ColumnKind::Vertex,
+                    EntryType::Edge => ColumnKind::Edge,
+                    EntryType::Object => match entry.as_object().unwrap() {
+                        Object::None => continue,
+                        Object::Primitive(Primitives::Byte(_))
+                        | Object::Primitive(Primitives::Integer(_)) => ColumnKind::Int32,
+                        Object::Primitive(Primitives::Long(_))
+                        | Object::Primitive(Primitives::ULLong(_)) => ColumnKind::Int64,
+                        Object::Primitive(Primitives::Float(_)) => ColumnKind::Float64,
+                        Object::String(_) => ColumnKind::Utf8,
+                        Object::Blob(_) => ColumnKind::Binary,
+                        Object::DateFormat(date_time) if date_time.timestamp_millis().is_some() => {
+                            ColumnKind::Timestamp
+                        }
+                        Object::DateFormat(_) => ColumnKind::Utf8,
+                        obj => Err(FnExecError::unsupported_error(&format!(
+                            "write object {:?} of column `{}` in arrow",
+                            obj, name
+                        )))?,
+                    },
+                    _ => Err(FnExecError::unsupported_error(&format!(
+                        "write entry {:?} of column `{}` in arrow, which should be flattened in advance",
+                        entry, name
+                    )))?,
+                };
+            return Ok(kind);
+        }
+        // a column of all null values
+        Ok(ColumnKind::Utf8)
+    }
+
+    fn write_column(
+        &self, name: &str, entries: &[Option<&DynEntry>], fields: &mut Vec<Field>,
+        arrays: &mut Vec<ArrayRef>,
+    ) -> FnExecResult<()> {
+        let objects = || {
+            entries.iter().map(|entry| {
+                entry
+                    .and_then(|entry| entry.as_object())
+                    .filter(|obj| **obj != Object::None)
+            })
+        };
+        let kind = self.kind_of(name, entries)?;
+        match kind {
+            ColumnKind::Vertex | ColumnKind::Edge => {
+                let mut ids = Int64Builder::with_capacity(entries.len());
+                let mut labels = StringBuilder::new();
+                let mut src_ids = Int64Builder::with_capacity(entries.len());
+                let mut dst_ids = Int64Builder::with_capacity(entries.len());
+                for entry in entries {
+                    if let Some(v) = entry.and_then(|entry| entry.as_vertex()) {
+                        ids.append_value(v.id() as i64);
+                        labels.append_option(
+                            v.label()
+                                .map(|label| self.get_meta_name(label, MetaType::Entity)),
+                        );
+                        src_ids.append_null();
+                        dst_ids.append_null();
+                    } else if let Some(e) = entry.and_then(|entry| entry.as_edge()) {
+                        ids.append_value(e.id() as i64);
+                        labels.append_option(
+                            e.label()
+                                .map(|label| self.get_meta_name(label, MetaType::Relation)),
+                        );
+                        src_ids.append_value(e.src_id as i64);
+                        dst_ids.append_value(e.dst_id as i64);
+                    } else {
+                        ids.append_null();
+                        labels.append_null();
+                        src_ids.append_null();
+                        dst_ids.append_null();
+                    }
+                }
+                fields.push(Field::new(&format!("{}.id", name), DataType::Int64, true));
+                arrays.push(Arc::new(ids.finish()));
+                fields.push(Field::new(&format!("{}.label", name), DataType::Utf8, true));
+                arrays.push(Arc::new(labels.finish()));
+                if kind == ColumnKind::Edge {
+                    fields.push(Field::new(&format!("{}.src_id", name), DataType::Int64, true));
+                    arrays.push(Arc::new(src_ids.finish()));
+                    fields.push(Field::new(&format!("{}.dst_id", name), DataType::Int64, true));
+                    arrays.push(Arc::new(dst_ids.finish()));
+                }
+            }
+            ColumnKind::Int32 => {
+                let mut builder = Int32Builder::with_capacity(entries.len());
+                for obj in objects() {
+                    let value = obj
+                        .map(|obj| obj.as_i32())
+                        .transpose()
+                        .map_err(|e| cast_error(name, e))?;
+                    builder.append_option(value);
+                }
+                fields.push(Field::new(name, DataType::Int32, true));
+                arrays.push(Arc::new(builder.finish()));
+            }
+            ColumnKind::Int64 => {
+                let mut builder = Int64Builder::with_capacity(entries.len());
+                for obj in objects() {
+                    let value = obj
+                        .map(|obj| obj.as_i64())
+                        .transpose()
+                        .map_err(|e| cast_error(name, e))?;
+                    builder.append_option(value);
+                }
+                fields.push(Field::new(name, DataType::Int64, true));
+                arrays.push(Arc::new(builder.finish()));
+            }
+            ColumnKind::Float64 => {
+                let mut builder = Float64Builder::with_capacity(entries.len());
+                for obj in objects() {
+                    let value = obj
+                        .map(|obj| obj.as_f64())
+                        .transpose()
+                        .map_err(|e| cast_error(name, e))?;
+                    builder.append_option(value);
+                }
+                fields.push(Field::new(name, DataType::Float64, true));
+                arrays.push(Arc::new(builder.finish()));
+            }
+            ColumnKind::Utf8 => {
+                let mut builder = StringBuilder::new();
+                for obj in objects() {
+                    let value = match obj {
+                        Some(Object::DateFormat(date_time)) => Some(format!("{:?}", date_time)),
+                        Some(obj) => Some(
+                            obj.as_str()
+                                .map_err(|e| cast_error(name, e))?
+                                .into_owned(),
+                        ),
+                        None => None,
+                    };
+                    builder.append_option(value);
+                }
+                fields.push(Field::new(name, DataType::Utf8, true));
+                arrays.push(Arc::new(builder.finish()));
+            }
+            ColumnKind::Binary => {
+                let mut builder = BinaryBuilder::new();
+                for obj in objects() {
+                    let value = obj
+                        .map(|obj| obj.as_bytes())
+                        .transpose()
+                        .map_err(|e| cast_error(name, e))?;
+                    builder.append_option(value);
+                }
+                fields.push(Field::new(name, DataType::Binary, true));
+                arrays.push(Arc::new(builder.finish()));
+            }
+            ColumnKind::Timestamp => {
+                let mut builder = TimestampMillisecondBuilder::with_capacity(entries.len());
+                for obj in objects() {
+                    let value = match obj {
+                        Some(obj) => Some(
+                            obj.as_date_format()
+                                .ok()
+                                .and_then(|date_time| date_time.timestamp_millis())
+                                .ok_or_else(|| cast_error(name, obj))?,
+                        ),
+                        None => None,
+                    };
+                    builder.append_option(value);
+                }
+                fields.push(Field::new(name, DataType::Timestamp(TimeUnit::Millisecond, None), true));
+                arrays.push(Arc::new(builder.finish()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    use arrow::array::{Array, Int32Array, Int64Array, StringArray};
+    use arrow::ipc::reader::StreamReader;
+    use dyn_type::Object;
+    use graph_proxy::apis::{DynDetails, Vertex};
+    use ir_common::generated::algebra::sink_default::MetaType;
+
+    use super::ArrowBatchWriter;
+    use crate::process::entry::DynEntry;
+
+    #[test]
+    fn arrow_batch_test() {
+        let mut schema_map = HashMap::new();
+        schema_map.insert((MetaType::Entity, 0), "person".to_string());
+        schema_map.insert((MetaType::Tag, 0), "a".to_string());
+        schema_map.insert((MetaType::Tag, 1), "age".to_string());
+        let writer = ArrowBatchWriter::new(Some(&schema_map));
+        let v1 = DynEntry::new(Vertex::new(1, Some(0), DynDetails::default()));
+        let v2 = DynEntry::new(Vertex::new(2, Some(0), DynDetails::default()));
+        let age1 = DynEntry::new(Object::from(29));
+        let bytes = writer
+            .write_batch(vec![(Some(0), vec![Some(&v1), Some(&v2)]), (Some(1), vec![Some(&age1), None])])
+            .unwrap();
+
+        let mut reader = StreamReader::try_new(Cursor::new(bytes), None).unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert!(reader.next().is_none());
+        let schema = batch.schema();
+        let names: Vec<&str> = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(names, vec!["a.id", "a.label", "age"]);
+        let ids = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(ids.values(), &[1, 2]);
+        let labels = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(labels.value(1), "person");
+        let ages = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        assert_eq!(ages.value(0), 29);
+        assert!(ages.is_null(1));
+    }
+
+    #[test]
+    fn arrow_batch_mixed_types_test() {
+        let writer = ArrowBatchWriter::new(None);
+        let age = DynEntry::new(Object::from(29));
+        let name = DynEntry::new(Object::from("marko"));
+        assert!(writer
+            .write_batch(vec![(None, vec![Some(&age), Some(&name)])])
+            .is_err());
+    }
+}
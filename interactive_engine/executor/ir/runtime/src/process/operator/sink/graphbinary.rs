@@ -0,0 +1,883 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The GraphBinary 1.0 of TinkerPop, which is the default serialization of the modern Gremlin drivers:
+//! * `GraphBinaryWriter` writes the results as the fully qualified values, i.e.,
+//!   `{type_code}{value_flag}{value}`;
+//! * `RequestMessage` reads the request sent by the drivers, whose arguments are the parameters of a query,
+//!   e.g., the `bindings` of a script;
+//! * `ResponseMessage` wraps the results written into the response to the request, which the frontend
+//!   forwards to the drivers as it is.
+//!
+//! A job carrying the request of a driver as its resource binds the dynamic params of its plan to the
+//! `bindings` of the request, and sinks each of its results as a `PARTIAL_CONTENT` response, while the
+//! frontend ends the responses once the job completes.
+
+use std::collections::{BTreeMap, HashMap};
+
+use dyn_type::object::{DateTimeFormats, Primitives};
+use dyn_type::Object;
+use graph_proxy::apis::{Edge, GraphElement, GraphPath, Vertex, VertexOrEdge, ID};
+use ir_common::generated::algebra::sink_default::MetaType;
+use ir_common::{KeyId, NameOrId};
+
+use crate::error::{FnExecError, FnExecResult};
+use crate::process::entry::{CollectionEntry, DynEntry, Entry, EntryType, MapEntry, PairEntry};
+use crate::process::operator::map::{GeneralIntersectionEntry, IntersectionEntry};
+
+const INT: u8 = 0x01;
+const LONG: u8 = 0x02;
+const STRING: u8 = 0x03;
+const DATE: u8 = 0x04;
+const TIMESTAMP: u8 = 0x05;
+const DOUBLE: u8 = 0x07;
+const FLOAT: u8 = 0x08;
+const LIST: u8 = 0x09;
+const MAP: u8 = 0x0a;
+const SET: u8 = 0x0b;
+const UUID: u8 = 0x0c;
+const EDGE: u8 = 0x0d;
+const PATH: u8 = 0x0e;
+const PROPERTY: u8 = 0x0f;
+const VERTEX: u8 = 0x11;
+const VERTEX_PROPERTY: u8 = 0x12;
+const BIG_INTEGER: u8 = 0x23;
+const BYTE: u8 = 0x24;
+const BYTE_BUFFER: u8 = 0x25;
+const BOOLEAN: u8 = 0x27;
+const UNSPECIFIED_NULL: u8 = 0xfe;
+
+const VALUE_FLAG: u8 = 0x00;
+const NULL_FLAG: u8 = 0x01;
+/// The version 1 with the highest bit set, which leads a request message
+const VERSION: u8 = 0x81;
+/// The mime type which the drivers prefix to the requests in GraphBinary
+const MIME_TYPE: &str = "application/vnd.graphbinary-v1.0";
+/// The deepest nesting of the collections read, beyond which the bytes are rejected rather than
+/// overflowing the stack
+const MAX_DEPTH: usize = 64;
+/// The status code of a response carrying a part of the results, which are followed by the others
+pub const PARTIAL_CONTENT: i32 = 206;
+
+const DEFAULT_VERTEX_LABEL: &str = "vertex";
+const DEFAULT_EDGE_LABEL: &str = "edge";
+
+fn write_len(len: usize, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&(len as i32).to_be_bytes());
+}
+
+/// Write a string without its type code and value flag, as the labels and keys of the elements;
+fn write_bare_string(s: &str, buf: &mut Vec<u8>) {
+    write_len(s.len(), buf);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn write_null(buf: &mut Vec<u8>) {
+    buf.push(UNSPECIFIED_NULL);
+    buf.push(NULL_FLAG);
+}
+
+fn write_string(s: &str, buf: &mut Vec<u8>) {
+    buf.push(STRING);
+    buf.push(VALUE_FLAG);
+    write_bare_string(s, buf);
+}
+
+fn write_id(id: ID, buf: &mut Vec<u8>) {
+    buf.push(LONG);
+    buf.push(VALUE_FLAG);
+    buf.extend_from_slice(&(id as i64).to_be_bytes());
+}
+
+/// The minimal two's complement bytes in big endian of a non-negative integer.
+fn big_integer_bytes(u: u128) -> Vec<u8> {
+    let bytes = u.to_be_bytes();
+    let start = bytes
+        .iter()
+        .position(|b| *b != 0)
+        .unwrap_or(bytes.len() - 1);
+    let mut magnitude = bytes[start..].to_vec();
+    if magnitude[0] & 0x80 != 0 {
+        magnitude.insert(0, 0);
+    }
+    magnitude
+}
+
+pub struct GraphBinaryWriter<'a> {
+    /// A map from id to name, where the unmapped ids are written as they are;
+    schema_map: Option<&'a HashMap<(MetaType, i32), String>>,
+}
+
+impl<'a> GraphBinaryWriter<'a> {
+    pub fn new(schema_map: Option<&'a HashMap<(MetaType, i32), String>>) -> Self {
+        GraphBinaryWriter { schema_map }
+    }
+
+    /// Write the sink columns of a record carrying a provenance, in the provenance tracking mode, as a
+    /// map of the `result` and the `provenance` in json;
+    pub fn write_columns_with_provenance(
+        &self, columns: Vec<(Option<KeyId>, &DynEntry)>, provenance: &str, buf: &mut Vec<u8>,
+    ) -> FnExecResult<()> {
+        buf.push(MAP);
+        buf.push(VALUE_FLAG);
+        write_len(2, buf);
+        write_string("result", buf);
+        self.write_columns(columns, buf)?;
+        write_string("provenance", buf);
+        write_string(provenance, buf);
+        Ok(())
+    }
+
+    /// Write the sink columns of a record, where a single column is written as its entry, and the
+    /// columns are written as a map of the tags to the entries otherwise;
+    pub fn write_columns(
+        &self, columns: Vec<(Option<KeyId>, &DynEntry)>, buf: &mut Vec<u8>,
+    ) -> FnExecResult<()> {
+        if columns.len() == 1 {
+            return self.write_entry(columns[0].1, buf);
+        }
+        buf.push(MAP);
+        buf.push(VALUE_FLAG);
+        write_len(columns.len(), buf);
+        for (tag, entry) in columns {
+            if let Some(tag) = tag {
+                write_string(&self.get_meta_name(tag, MetaType::Tag), buf);
+            } else {
+                write_null(buf);
+            }
+            self.write_entry(entry, buf)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_entry(&self, e: &DynEntry, buf: &mut Vec<u8>) -> FnExecResult<()> {
+        match e.get_type() {
+            EntryType::Vertex => self.write_vertex(e.as_vertex().unwrap(), buf),
+            EntryType::Edge => self.write_edge(e.as_edge().unwrap(), buf),
+            EntryType::Path => self.write_path(e.as_graph_path().unwrap(), buf),
+            EntryType::Object => self.write_object(e.as_object().unwrap(), buf)?,
+            EntryType::Collection => {
+                let collection = e
+                    .as_any_ref()
+                    .downcast_ref::<CollectionEntry>()
+                    .unwrap();
+                let is_map = collection
+                    .inner
+                    .first()
+                    .map(|entry| entry.get_type() == EntryType::Pair)
+                    .unwrap_or(false);
+                buf.push(if is_map { MAP } else { LIST });
+                buf.push(VALUE_FLAG);
+                write_len(collection.len(), buf);
+                for entry in &collection.inner {
+                    if is_map {
+                        let pair = entry
+                            .as_any_ref()
+                            .downcast_ref::<PairEntry>()
+                            .unwrap();
+                        self.write_entry(pair.get_left(), buf)?;
+                        self.write_entry(pair.get_right(), buf)?;
+                    } else {
+                        self.write_entry(entry, buf)?;
+                    }
+                }
+            }
+            EntryType::Intersection => {
+                let vertices: Vec<ID> = if let Some(intersection) = e
+                    .as_any_ref()
+                    .downcast_ref::<IntersectionEntry>()
+                {
+                    intersection.iter().cloned().collect()
+                } else if let Some(general_intersection) = e
+                    .as_any_ref()
+                    .downcast_ref::<GeneralIntersectionEntry>()
+                {
+                    general_intersection.iter().cloned().collect()
+                } else {
+                    Err(FnExecError::unsupported_error("unsupported intersection entry type"))?
+                };
+                buf.push(LIST);
+                buf.push(VALUE_FLAG);
+                write_len(vertices.len(), buf);
+                for vid in vertices {
+                    buf.push(VERTEX);
+                    buf.push(VALUE_FLAG);
+                    write_id(vid, buf);
+                    write_bare_string(DEFAULT_VERTEX_LABEL, buf);
+                    write_null(buf);
+                }
+            }
+            EntryType::Map => {
+                let map = e
+                    .as_any_ref()
+                    .downcast_ref::<MapEntry>()
+                    .unwrap();
+                buf.push(MAP);
+                buf.push(VALUE_FLAG);
+                write_len(map.len(), buf);
+                for pair in &map.inner {
+                    self.write_entry(pair.get_left(), buf)?;
+                    self.write_entry(pair.get_right(), buf)?;
+                }
+            }
+            EntryType::Pair => Err(FnExecError::unsupported_error(&format!(
+                "write a pair {:?} out of a collection in GraphBinary",
+                e
+            )))?,
+        }
+        Ok(())
+    }
+
+    pub fn write_object(&self, obj: &Object, buf: &mut Vec<u8>) -> FnExecResult<()> {
+        match obj {
+            Object::Primitive(Primitives::Byte(b)) => {
+                buf.extend_from_slice(&[BYTE, VALUE_FLAG]);
+                buf.extend_from_slice(&b.to_be_bytes());
+            }
+            Object::Primitive(Primitives::Integer(i)) => {
+                buf.extend_from_slice(&[INT, VALUE_FLAG]);
+                buf.extend_from_slice(&i.to_be_bytes());
+            }
+            Object::Primitive(Primitives::Long(l)) => {
+                buf.extend_from_slice(&[LONG, VALUE_FLAG]);
+                buf.extend_from_slice(&l.to_be_bytes());
+            }
+            Object::Primitive(Primitives::ULLong(u)) => {
+                if *u <= i64::MAX as u128 {
+                    buf.extend_from_slice(&[LONG, VALUE_FLAG]);
+                    buf.extend_from_slice(&(*u as i64).to_be_bytes());
+                } else {
+                    let bytes = big_integer_bytes(*u);
+                    buf.extend_from_slice(&[BIG_INTEGER, VALUE_FLAG]);
+                    write_len(bytes.len(), buf);
+                    buf.extend_from_slice(&bytes);
+                }
+            }
+            Object::Primitive(Primitives::Float(f)) => {
+                buf.extend_from_slice(&[DOUBLE, VALUE_FLAG]);
+                buf.extend_from_slice(&f.to_be_bytes());
+            }
+            Object::String(s) => write_string(s, buf),
+            Object::Vector(vec) => {
+                buf.extend_from_slice(&[LIST, VALUE_FLAG]);
+                write_len(vec.len(), buf);
+                for item in vec {
+                    self.write_object(item, buf)?;
+                }
+            }
+            Object::KV(kv) => {
+                buf.extend_from_slice(&[MAP, VALUE_FLAG]);
+                write_len(kv.len(), buf);
+                for (key, val) in kv {
+                    // the key in KV of VarMap.eval() is vec![tag, prop_name], whose tag is mapped to name
+                    match key {
+                        Object::Vector(v) if v.len() == 2 && v[0].as_i32().is_ok() => {
+                            let tag = self.get_meta_name(v[0].as_i32().unwrap(), MetaType::Tag);
+                            buf.extend_from_slice(&[LIST, VALUE_FLAG]);
+                            write_len(2, buf);
+                            write_string(&tag, buf);
+                            self.write_object(&v[1], buf)?;
+                        }
+                        _ => self.write_object(key, buf)?,
+                    }
+                    self.write_object(val, buf)?;
+                }
+            }
+            Object::Blob(b) => {
+                buf.extend_from_slice(&[BYTE_BUFFER, VALUE_FLAG]);
+                write_len(b.len(), buf);
+                buf.extend_from_slice(b);
+            }
+            Object::DateFormat(DateTimeFormats::Time(t)) => write_string(&t.to_string(), buf),
+            Object::DateFormat(date_time) => {
+                buf.extend_from_slice(&[DATE, VALUE_FLAG]);
+                buf.extend_from_slice(
+                    &date_time
+                        .timestamp_millis()
+                        .unwrap_or_default()
+                        .to_be_bytes(),
+                );
+            }
+            Object::None => write_null(buf),
+            Object::DynOwned(_) => {
+                Err(FnExecError::unsupported_error(&format!("write object {:?} in GraphBinary", obj)))?
+            }
+        }
+        Ok(())
+    }
+
+    fn get_meta_name(&self, meta_id: KeyId, t: MetaType) -> String {
+        if let Some(meta_name) = self
+            .schema_map
+            .and_then(|schema_map| schema_map.get(&(t, meta_id)))
+        {
+            meta_name.clone()
+        } else {
+            // if we cannot find mapped meta_name, we write meta_id directly.
+            meta_id.to_string()
+        }
+    }
+
+    fn meta_name(&self, meta: &NameOrId, t: MetaType) -> String {
+        match meta {
+            NameOrId::Str(name) => name.clone(),
+            NameOrId::Id(id) => self.get_meta_name(*id, t),
+        }
+    }
+
+    fn label_name(&self, label: Option<KeyId>, t: MetaType, default: &str) -> String {
+        label
+            .map(|label| self.get_meta_name(label, t))
+            .unwrap_or_else(|| default.to_string())
+    }
+
+    /// The properties of the element sorted by their keys, where those of unsupported types are skipped;
+    fn properties_of<E: GraphElement>(&self, element: &E) -> Vec<(String, Vec<u8>)> {
+        let mut properties = vec![];
+        if let Some(all_properties) = element.get_all_properties() {
+            for (key, val) in all_properties {
+                let mut value = vec![];
+                if self.write_object(&val, &mut value).is_ok() {
+                    properties.push((self.meta_name(&key, MetaType::Column), value));
+                }
+            }
+        }
+        properties.sort_by(|l, r| l.0.cmp(&r.0));
+        properties
+    }
+
+    fn write_vertex(&self, v: &Vertex, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&[VERTEX, VALUE_FLAG]);
+        write_id(v.id(), buf);
+        write_bare_string(&self.label_name(v.label(), MetaType::Entity, DEFAULT_VERTEX_LABEL), buf);
+        let properties = self.properties_of(v);
+        if properties.is_empty() {
+            write_null(buf);
+        } else {
+            buf.extend_from_slice(&[LIST, VALUE_FLAG]);
+            write_len(properties.len(), buf);
+            for (key, value) in properties {
+                buf.extend_from_slice(&[VERTEX_PROPERTY, VALUE_FLAG]);
+                write_string(&format!("{}.{}", v.id(), key), buf);
+                write_bare_string(&key, buf);
+                buf.extend_from_slice(&value);
+                // the parent and the meta-properties
+                write_null(buf);
+                write_null(buf);
+            }
+        }
+    }
+
+    fn write_edge(&self, e: &Edge, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&[EDGE, VALUE_FLAG]);
+        write_id(e.id(), buf);
+        write_bare_string(&self.label_name(e.label(), MetaType::Relation, DEFAULT_EDGE_LABEL), buf);
+        write_id(e.dst_id, buf);
+        write_bare_string(
+            &self.label_name(e.get_dst_label().cloned(), MetaType::Entity, DEFAULT_VERTEX_LABEL),
+            buf,
+        );
+        write_id(e.src_id, buf);
+        write_bare_string(
+            &self.label_name(e.get_src_label().cloned(), MetaType::Entity, DEFAULT_VERTEX_LABEL),
+            buf,
+        );
+        // the parent
+        write_null(buf);
+        let properties = self.properties_of(e);
+        if properties.is_empty() {
+            write_null(buf);
+        } else {
+            buf.extend_from_slice(&[LIST, VALUE_FLAG]);
+            write_len(properties.len(), buf);
+            for (key, value) in properties {
+                buf.extend_from_slice(&[PROPERTY, VALUE_FLAG]);
+                write_bare_string(&key, buf);
+                buf.extend_from_slice(&value);
+                // the parent
+                write_null(buf);
+            }
+        }
+    }
+
+    fn write_vertex_or_edge(&self, vertex_or_edge: &VertexOrEdge, buf: &mut Vec<u8>) {
+        match vertex_or_edge {
+            VertexOrEdge::V(v) => self.write_vertex(v, buf),
+            VertexOrEdge::E(e) => self.write_edge(e, buf),
+        }
+    }
+
+    fn write_path(&self, p: &GraphPath, buf: &mut Vec<u8>) {
+        let objects: Vec<&VertexOrEdge> = match p {
+            GraphPath::AllPath(path)
+            | GraphPath::SimpleAllPath(path)
+            | GraphPath::TrailAllPath((path, _, _)) => path.iter().collect(),
+            GraphPath::EndV((path_end, _))
+            | GraphPath::SimpleEndV((path_end, _, _))
+            | GraphPath::TrailEndV((path_end, _, _)) => vec![path_end],
+        };
+        buf.extend_from_slice(&[PATH, VALUE_FLAG]);
+        // the elements of a path in runtime are not labeled
+        buf.extend_from_slice(&[LIST, VALUE_FLAG]);
+        write_len(objects.len(), buf);
+        for _ in 0..objects.len() {
+            buf.extend_from_slice(&[SET, VALUE_FLAG]);
+            write_len(0, buf);
+        }
+        buf.extend_from_slice(&[LIST, VALUE_FLAG]);
+        write_len(objects.len(), buf);
+        for vertex_or_edge in objects {
+            self.write_vertex_or_edge(vertex_or_edge, buf);
+        }
+    }
+}
+
+/// The request sent by the Gremlin drivers in GraphBinary, i.e.,
+/// `{version}{request_id}{op}{processor}{args}`.
+#[derive(Debug, PartialEq)]
+pub struct RequestMessage {
+    pub request_id: [u8; 16],
+    /// The operation of the request, e.g., `eval` and `bytecode`;
+    pub op: String,
+    pub processor: String,
+    /// The arguments of the request, e.g., the `gremlin` script and its `bindings` as the parameters;
+    pub args: HashMap<String, Object>,
+}
+
+impl RequestMessage {
+    /// Decode the request, which may be prefixed by its mime type, i.e., `{len}{mime_type}`.
+    pub fn decode(bytes: &[u8]) -> FnExecResult<Self> {
+        let mut reader = GraphBinaryReader::new(bytes);
+        let mut version = reader.read_u8()?;
+        if version != VERSION {
+            let mime_type = reader.read_bytes(version as usize)?;
+            if mime_type != MIME_TYPE.as_bytes() {
+                Err(FnExecError::unexpected_data_error(&format!(
+                    "unsupported mime type {} of request",
+                    String::from_utf8_lossy(mime_type)
+                )))?
+            }
+            version = reader.read_u8()?;
+        }
+        if version != VERSION {
+            Err(FnExecError::unexpected_data_error(&format!(
+                "unsupported GraphBinary version {:#x}",
+                version
+            )))?
+        }
+        let mut request_id = [0_u8; 16];
+        request_id.copy_from_slice(reader.read_bytes(16)?);
+        let op = reader.read_bare_string()?;
+        let processor = reader.read_bare_string()?;
+        let len = reader.read_len()?;
+        let mut args = HashMap::with_capacity(reader.capacity_of(len));
+        for _ in 0..len {
+            match reader.read_object()? {
+                Object::String(key) => {
+                    args.insert(key, reader.read_object()?);
+                }
+                key => Err(FnExecError::unexpected_data_error(&format!(
+                    "the key of request args {:?} is not a string",
+                    key
+                )))?,
+            }
+        }
+        Ok(RequestMessage { request_id, op, processor, args })
+    }
+
+    /// The parameters bound to the variables of a script, which are given by the `bindings` argument;
+    pub fn bindings(&self) -> HashMap<String, Object> {
+        let mut bindings = HashMap::new();
+        if let Some(Object::KV(kv)) = self.args.get("bindings") {
+            for (key, val) in kv {
+                if let Object::String(key) = key {
+                    bindings.insert(key.clone(), val.clone());
+                }
+            }
+        }
+        bindings
+    }
+}
+
+/// The response to a request in GraphBinary, i.e.,
+/// `{version}{request_id}{status_code}{status_message}{status_attributes}{result_meta}{result_data}`,
+/// whose data are the results as a list, without any status message, attributes or meta.
+pub struct ResponseMessage {
+    request_id: Option<[u8; 16]>,
+    status_code: i32,
+}
+
+impl ResponseMessage {
+    pub fn new(request_id: Option<[u8; 16]>, status_code: i32) -> Self {
+        ResponseMessage { request_id, status_code }
+    }
+
+    /// Encode the response of the results, each of which is written as a fully qualified value.
+    pub fn encode(&self, results: &[Vec<u8>]) -> Vec<u8> {
+        let mut buf = vec![VERSION];
+        if let Some(request_id) = self.request_id.as_ref() {
+            buf.push(VALUE_FLAG);
+            buf.extend_from_slice(request_id);
+        } else {
+            buf.push(NULL_FLAG);
+        }
+        buf.extend_from_slice(&self.status_code.to_be_bytes());
+        // the status message, attributes and the result meta
+        buf.push(NULL_FLAG);
+        write_len(0, &mut buf);
+        write_len(0, &mut buf);
+        buf.extend_from_slice(&[LIST, VALUE_FLAG]);
+        write_len(results.len(), &mut buf);
+        for result in results {
+            buf.extend_from_slice(result);
+        }
+        buf
+    }
+}
+
+/// Read the fully qualified values into objects, where the graph elements are not expected as parameters.
+pub struct GraphBinaryReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// The nesting of the collections being read
+    depth: usize,
+}
+
+impl<'a> GraphBinaryReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        GraphBinaryReader { bytes, pos: 0, depth: 0 }
+    }
+
+    /// The capacity reserved for the items of a length read, which is bounded by the bytes remaining,
+    /// as an item takes two bytes at least, i.e., its type code and value flag.
+    fn capacity_of(&self, len: usize) -> usize {
+        len.min((self.bytes.len() - self.pos) / 2)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> FnExecResult<&'a [u8]> {
+        if len > self.bytes.len() - self.pos {
+            Err(FnExecError::unexpected_data_error(&format!(
+                "read {} bytes at {} out of {} bytes of GraphBinary",
+                len,
+                self.pos,
+                self.bytes.len()
+            )))?
+        }
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> FnExecResult<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_i32(&mut self) -> FnExecResult<i32> {
+        let mut bytes = [0_u8; 4];
+        bytes.copy_from_slice(self.read_bytes(4)?);
+        Ok(i32::from_be_bytes(bytes))
+    }
+
+    fn read_i64(&mut self) -> FnExecResult<i64> {
+        let mut bytes = [0_u8; 8];
+        bytes.copy_from_slice(self.read_bytes(8)?);
+        Ok(i64::from_be_bytes(bytes))
+    }
+
+    fn read_len(&mut self) -> FnExecResult<usize> {
+        let len = self.read_i32()?;
+        if len < 0 {
+            Err(FnExecError::unexpected_data_error(&format!("negative length {} of GraphBinary", len)))?
+        }
+        Ok(len as usize)
+    }
+
+    fn read_bare_string(&mut self) -> FnExecResult<String> {
+        let len = self.read_len()?;
+        String::from_utf8(self.read_bytes(len)?.to_vec())
+            .map_err(|e| FnExecError::unexpected_data_error(&format!("{:?}", e)))
+    }
+
+    pub fn read_object(&mut self) -> FnExecResult<Object> {
+        if self.depth >= MAX_DEPTH {
+            Err(FnExecError::unexpected_data_error(&format!(
+                "GraphBinary nested deeper than {} at {}",
+                MAX_DEPTH, self.pos
+            )))?
+        }
+        self.depth += 1;
+        let obj = self.read_value();
+        self.depth -= 1;
+        obj
+    }
+
+    fn read_value(&mut self) -> FnExecResult<Object> {
+        let type_code = self.read_u8()?;
+        let value_flag = self.read_u8()?;
+        if value_flag == NULL_FLAG {
+            return Ok(Object::None);
+        }
+        let obj = match type_code {
+            INT => Object::from(self.read_i32()?),
+            LONG => Object::from(self.read_i64()?),
+            STRING => Object::String(self.read_bare_string()?),
+            DATE | TIMESTAMP => {
+                let millis = self.read_i64()?;
+                DateTimeFormats::from_timestamp_millis(millis)
+                    .map(Object::DateFormat)
+                    .map_err(|e| FnExecError::unexpected_data_error(&format!("{:?}", e)))?
+            }
+            DOUBLE => {
+                let mut bytes = [0_u8; 8];
+                bytes.copy_from_slice(self.read_bytes(8)?);
+                Object::from(f64::from_be_bytes(bytes))
+            }
+            FLOAT => {
+                let mut bytes = [0_u8; 4];
+                bytes.copy_from_slice(self.read_bytes(4)?);
+                Object::from(f32::from_be_bytes(bytes) as f64)
+            }
+            LIST | SET => {
+                let len = self.read_len()?;
+                let mut vec = Vec::with_capacity(self.capacity_of(len));
+                for _ in 0..len {
+                    vec.push(self.read_object()?);
+                }
+                Object::Vector(vec)
+            }
+            MAP => {
+                let len = self.read_len()?;
+                let mut kv = BTreeMap::new();
+                for _ in 0..len {
+                    let key = self.read_object()?;
+                    kv.insert(key, self.read_object()?);
+                }
+                Object::KV(kv)
+            }
+            UUID => {
+                let hex: Vec<String> = self
+                    .read_bytes(16)?
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                let hex = hex.concat();
+                Object::String(format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[0..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..32]
+                ))
+            }
+            BIG_INTEGER => {
+                let len = self.read_len()?;
+                let bytes = self.read_bytes(len)?;
+                let negative = bytes
+                    .first()
+                    .map(|b| b & 0x80 != 0)
+                    .unwrap_or(false);
+                let magnitude = bytes
+                    .iter()
+                    .position(|b| *b != 0)
+                    .map(|start| &bytes[start..])
+                    .unwrap_or(&[]);
+                if negative || magnitude.len() > 16 {
+                    Err(FnExecError::unsupported_error(&format!(
+                        "read BigInteger of {:?} in GraphBinary",
+                        bytes
+                    )))?
+                }
+                let mut u = [0_u8; 16];
+                u[16 - magnitude.len()..].copy_from_slice(magnitude);
+                let u = u128::from_be_bytes(u);
+                if u <= i64::MAX as u128 {
+                    Object::from(u as i64)
+                } else {
+                    Object::from(u)
+                }
+            }
+            BYTE => Object::from(self.read_u8()? as i8),
+            BYTE_BUFFER => {
+                let len = self.read_len()?;
+                Object::Blob(self.read_bytes(len)?.into())
+            }
+            BOOLEAN => Object::from(self.read_u8()? != 0),
+            UNSPECIFIED_NULL => Object::None,
+            _ => Err(FnExecError::unsupported_error(&format!(
+                "read GraphBinary of type code {:#x}",
+                type_code
+            )))?,
+        };
+        Ok(obj)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use dyn_type::Object;
+    use graph_proxy::apis::{DynDetails, Vertex};
+    use ir_common::generated::algebra::sink_default::MetaType;
+    use ir_common::NameOrId;
+
+    use super::*;
+    use crate::process::entry::DynEntry;
+
+    fn read(bytes: &[u8]) -> Object {
+        let mut reader = GraphBinaryReader::new(bytes);
+        let obj = reader.read_object().unwrap();
+        assert_eq!(reader.pos, bytes.len());
+        obj
+    }
+
+    #[test]
+    fn object_graphbinary_test() {
+        let writer = GraphBinaryWriter::new(None);
+        let objects = vec![
+            Object::from(1),
+            Object::from(-2_i64),
+            Object::from(0.5),
+            Object::from("marko"),
+            Object::Vector(vec![Object::from(1), Object::from("a")]),
+            Object::from(vec![(Object::from("k"), Object::from(3_i64))]),
+            Object::from(i64::MAX as u128 + 1),
+            Object::from(vec![1_u8, 2, 3]),
+            Object::None,
+        ];
+        for obj in objects {
+            let mut buf = vec![];
+            writer.write_object(&obj, &mut buf).unwrap();
+            assert_eq!(read(&buf), obj);
+        }
+    }
+
+    #[test]
+    fn vertex_graphbinary_test() {
+        let mut schema_map = HashMap::new();
+        schema_map.insert((MetaType::Entity, 0), "person".to_string());
+        schema_map.insert((MetaType::Column, 2), "age".to_string());
+        let writer = GraphBinaryWriter::new(Some(&schema_map));
+        let mut properties = HashMap::new();
+        properties.insert(NameOrId::Id(2), Object::from(29));
+        let v = Vertex::new(1, Some(0), DynDetails::new(properties));
+        let mut buf = vec![];
+        writer
+            .write_entry(&DynEntry::new(v), &mut buf)
+            .unwrap();
+
+        let mut expected = vec![VERTEX, VALUE_FLAG, LONG, VALUE_FLAG, 0, 0, 0, 0, 0, 0, 0, 1];
+        expected.extend_from_slice(&[0, 0, 0, 6]);
+        expected.extend_from_slice(b"person");
+        expected.extend_from_slice(&[LIST, VALUE_FLAG, 0, 0, 0, 1, VERTEX_PROPERTY, VALUE_FLAG]);
+        expected.extend_from_slice(&[STRING, VALUE_FLAG, 0, 0, 0, 5]);
+        expected.extend_from_slice(b"1.age");
+        expected.extend_from_slice(&[0, 0, 0, 3]);
+        expected.extend_from_slice(b"age");
+        expected.extend_from_slice(&[INT, VALUE_FLAG, 0, 0, 0, 29]);
+        expected.extend_from_slice(&[UNSPECIFIED_NULL, NULL_FLAG, UNSPECIFIED_NULL, NULL_FLAG]);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn request_message_test() {
+        let mut bytes = vec![VERSION];
+        bytes.extend_from_slice(&[7; 16]);
+        bytes.extend_from_slice(&[0, 0, 0, 4]);
+        bytes.extend_from_slice(b"eval");
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        // args: {"gremlin": "g.V(x)", "bindings": {"x": 1}}
+        bytes.extend_from_slice(&[0, 0, 0, 2]);
+        bytes.extend_from_slice(&[STRING, VALUE_FLAG, 0, 0, 0, 7]);
+        bytes.extend_from_slice(b"gremlin");
+        bytes.extend_from_slice(&[STRING, VALUE_FLAG, 0, 0, 0, 6]);
+        bytes.extend_from_slice(b"g.V(x)");
+        bytes.extend_from_slice(&[STRING, VALUE_FLAG, 0, 0, 0, 8]);
+        bytes.extend_from_slice(b"bindings");
+        bytes.extend_from_slice(&[MAP, VALUE_FLAG, 0, 0, 0, 1, STRING, VALUE_FLAG, 0, 0, 0, 1]);
+        bytes.extend_from_slice(b"x");
+        bytes.extend_from_slice(&[INT, VALUE_FLAG, 0, 0, 0, 1]);
+
+        let request = RequestMessage::decode(&bytes).unwrap();
+        assert_eq!(request.request_id, [7; 16]);
+        assert_eq!(request.op, "eval");
+        assert_eq!(request.processor, "");
+        assert_eq!(request.args.get("gremlin"), Some(&Object::from("g.V(x)")));
+        let mut bindings = HashMap::new();
+        bindings.insert("x".to_string(), Object::from(1));
+        assert_eq!(request.bindings(), bindings);
+    }
+
+    #[test]
+    fn request_message_truncated_test() {
+        let bytes = vec![VERSION, 0, 0];
+        assert!(RequestMessage::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn request_message_mime_type_test() {
+        let mut bytes = vec![MIME_TYPE.len() as u8];
+        bytes.extend_from_slice(MIME_TYPE.as_bytes());
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&[1; 16]);
+        bytes.extend_from_slice(&[0, 0, 0, 4]);
+        bytes.extend_from_slice(b"eval");
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+        let request = RequestMessage::decode(&bytes).unwrap();
+        assert_eq!(request.request_id, [1; 16]);
+        assert!(request.args.is_empty());
+
+        bytes[1] = b'x';
+        assert!(RequestMessage::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn read_untrusted_len_test() {
+        // a list claiming i32::MAX items reserves no more than the bytes given
+        let bytes = vec![LIST, VALUE_FLAG, 0x7f, 0xff, 0xff, 0xff, INT, VALUE_FLAG, 0, 0, 0, 1];
+        let mut reader = GraphBinaryReader::new(&bytes);
+        assert!(reader.read_object().is_err());
+
+        let mut bytes = vec![];
+        for _ in 0..MAX_DEPTH + 1 {
+            bytes.extend_from_slice(&[LIST, VALUE_FLAG, 0, 0, 0, 1]);
+        }
+        bytes.extend_from_slice(&[INT, VALUE_FLAG, 0, 0, 0, 1]);
+        let mut reader = GraphBinaryReader::new(&bytes);
+        assert!(reader.read_object().is_err());
+        // within the depth, of the lists and the int nested
+        let mut reader = GraphBinaryReader::new(&bytes[12..]);
+        assert!(reader.read_object().is_ok());
+    }
+
+    #[test]
+    fn response_message_test() {
+        let mut result = vec![];
+        GraphBinaryWriter::new(None)
+            .write_object(&Object::from(1), &mut result)
+            .unwrap();
+        let response = ResponseMessage::new(Some([2; 16]), PARTIAL_CONTENT).encode(&[result]);
+
+        let mut expected = vec![VERSION, VALUE_FLAG];
+        expected.extend_from_slice(&[2; 16]);
+        expected.extend_from_slice(&[0, 0, 0, 206, NULL_FLAG, 0, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[LIST, VALUE_FLAG, 0, 0, 0, 1, INT, VALUE_FLAG, 0, 0, 0, 1]);
+        assert_eq!(response, expected);
+
+        let response = ResponseMessage::new(None, PARTIAL_CONTENT).encode(&[]);
+        assert_eq!(&response[0..2], &[VERSION, NULL_FLAG]);
+    }
+}
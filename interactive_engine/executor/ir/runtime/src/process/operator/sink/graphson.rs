@@ -0,0 +1,471 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The serialization of the results into the GraphSON 3.0 of TinkerPop, which is consumed by the standard
+//! Gremlin drivers directly, without translating the `Results` of result.proto in the compiler.
+
+use std::collections::HashMap;
+
+use dyn_type::object::{DateTimeFormats, Primitives};
+use dyn_type::Object;
+use graph_proxy::apis::{Edge, GraphElement, GraphPath, Vertex, VertexOrEdge, ID};
+use ir_common::generated::algebra::sink_default::MetaType;
+use ir_common::{KeyId, NameOrId};
+use serde_json::{json, Map, Value};
+
+use crate::error::{FnExecError, FnExecResult};
+use crate::process::entry::{CollectionEntry, DynEntry, Entry, EntryType, MapEntry, PairEntry};
+use crate::process::operator::map::{GeneralIntersectionEntry, IntersectionEntry};
+
+const DEFAULT_VERTEX_LABEL: &str = "vertex";
+const DEFAULT_EDGE_LABEL: &str = "edge";
+
+fn typed(t: &str, value: Value) -> Value {
+    json!({ "@type": t, "@value": value })
+}
+
+fn id_to_graphson(id: ID) -> Value {
+    typed("g:Int64", Value::from(id as i64))
+}
+
+/// A GraphSON map is a flat list of its keys and values, as its keys are not necessarily strings.
+fn map_to_graphson(pairs: Vec<(Value, Value)>) -> Value {
+    let mut flat = Vec::with_capacity(pairs.len() * 2);
+    for (key, value) in pairs {
+        flat.push(key);
+        flat.push(value);
+    }
+    typed("g:Map", Value::Array(flat))
+}
+
+pub struct GraphSONWriter<'a> {
+    /// A map from id to name, where the unmapped ids are written as they are;
+    schema_map: Option<&'a HashMap<(MetaType, i32), String>>,
+}
+
+impl<'a> GraphSONWriter<'a> {
+    pub fn new(schema_map: Option<&'a HashMap<(MetaType, i32), String>>) -> Self {
+        GraphSONWriter { schema_map }
+    }
+
+    /// Wrap the result of a record carrying a provenance, in the provenance tracking mode, as a map of
+    /// the `result` and the `provenance` in json;
+    pub fn with_provenance(&self, result: Value, provenance: String) -> Value {
+        map_to_graphson(vec![
+            (Value::String("result".to_owned()), result),
+            (Value::String("provenance".to_owned()), Value::String(provenance)),
+        ])
+    }
+
+    /// Write the sink columns of a record, where a single column is written as its entry, and the
+    /// columns are written as a map of the tags to the entries otherwise;
+    pub fn columns_to_graphson(&self, columns: Vec<(Option<KeyId>, &DynEntry)>) -> FnExecResult<Value> {
+        if columns.len() == 1 {
+            return self.entry_to_graphson(columns[0].1);
+        }
+        let mut pairs = Vec::with_capacity(columns.len());
+        for (tag, entry) in columns {
+            let key = tag
+                .map(|tag| Value::String(self.get_meta_name(tag, MetaType::Tag)))
+                .unwrap_or(Value::Null);
+            pairs.push((key, self.entry_to_graphson(entry)?));
+        }
+        Ok(map_to_graphson(pairs))
+    }
+
+    pub fn entry_to_graphson(&self, e: &DynEntry) -> FnExecResult<Value> {
+        match e.get_type() {
+            EntryType::Vertex => Ok(self.vertex_to_graphson(e.as_vertex().unwrap())),
+            EntryType::Edge => Ok(self.edge_to_graphson(e.as_edge().unwrap())),
+            EntryType::Path => Ok(self.path_to_graphson(e.as_graph_path().unwrap())),
+            EntryType::Object => self.object_to_graphson(e.as_object().unwrap()),
+            EntryType::Collection => {
+                let collection = e
+                    .as_any_ref()
+                    .downcast_ref::<CollectionEntry>()
+                    .unwrap();
+                if collection
+                    .inner
+                    .first()
+                    .map(|entry| entry.get_type() == EntryType::Pair)
+                    .unwrap_or(false)
+                {
+                    let mut pairs = Vec::with_capacity(collection.len());
+                    for entry in &collection.inner {
+                        let pair = entry
+                            .as_any_ref()
+                            .downcast_ref::<PairEntry>()
+                            .unwrap();
+                        pairs.push((
+                            self.entry_to_graphson(pair.get_left())?,
+                            self.entry_to_graphson(pair.get_right())?,
+                        ));
+                    }
+                    Ok(map_to_graphson(pairs))
+                } else {
+                    let mut list = Vec::with_capacity(collection.len());
+                    for entry in &collection.inner {
+                        list.push(self.entry_to_graphson(entry)?);
+                    }
+                    Ok(typed("g:List", Value::Array(list)))
+                }
+            }
+            EntryType::Intersection => {
+                let vertices: Vec<Value> = if let Some(intersection) = e
+                    .as_any_ref()
+                    .downcast_ref::<IntersectionEntry>()
+                {
+                    intersection
+                        .iter()
+                        .map(|vid| self.vid_to_graphson(vid))
+                        .collect()
+                } else if let Some(general_intersection) = e
+                    .as_any_ref()
+                    .downcast_ref::<GeneralIntersectionEntry>()
+                {
+                    general_intersection
+                        .iter()
+                        .map(|vid| self.vid_to_graphson(vid))
+                        .collect()
+                } else {
+                    Err(FnExecError::unsupported_error("unsupported intersection entry type"))?
+                };
+                Ok(typed("g:List", Value::Array(vertices)))
+            }
+            EntryType::Map => {
+                let map = e
+                    .as_any_ref()
+                    .downcast_ref::<MapEntry>()
+                    .unwrap();
+                let mut pairs = Vec::with_capacity(map.len());
+                for pair in &map.inner {
+                    pairs.push((
+                        self.entry_to_graphson(pair.get_left())?,
+                        self.entry_to_graphson(pair.get_right())?,
+                    ));
+                }
+                Ok(map_to_graphson(pairs))
+            }
+            EntryType::Pair => Err(FnExecError::unsupported_error(&format!(
+                "write a pair {:?} out of a collection in GraphSON",
+                e
+            ))),
+        }
+    }
+
+    pub fn object_to_graphson(&self, obj: &Object) -> FnExecResult<Value> {
+        let value = match obj {
+            Object::Primitive(Primitives::Byte(b)) => typed("gx:Byte", Value::from(*b)),
+            Object::Primitive(Primitives::Integer(i)) => typed("g:Int32", Value::from(*i)),
+            Object::Primitive(Primitives::Long(l)) => typed("g:Int64", Value::from(*l)),
+            Object::Primitive(Primitives::ULLong(u)) => {
+                if *u <= i64::MAX as u128 {
+                    typed("g:Int64", Value::from(*u as i64))
+                } else {
+                    typed("gx:BigInteger", Value::String(u.to_string()))
+                }
+            }
+            Object::Primitive(Primitives::Float(f)) => {
+                // the json numbers cannot be NaN or Infinity, which are written as strings in GraphSON
+                let value = if f.is_nan() {
+                    Value::from("NaN")
+                } else if f.is_infinite() {
+                    Value::from(if *f > 0.0 { "Infinity" } else { "-Infinity" })
+                } else {
+                    Value::from(*f)
+                };
+                typed("g:Double", value)
+            }
+            Object::String(s) => Value::String(s.clone()),
+            Object::Vector(vec) => {
+                let mut list = Vec::with_capacity(vec.len());
+                for item in vec {
+                    list.push(self.object_to_graphson(item)?);
+                }
+                typed("g:List", Value::Array(list))
+            }
+            Object::KV(kv) => {
+                let mut pairs = Vec::with_capacity(kv.len());
+                for (key, val) in kv {
+                    // the key in KV of VarMap.eval() is vec![tag, prop_name], whose tag is mapped to name
+                    let key = match key {
+                        Object::Vector(v) if v.len() == 2 => match v[0].as_i32() {
+                            Ok(tag_id) => Object::Vector(vec![
+                                Object::from(self.get_meta_name(tag_id, MetaType::Tag)),
+                                v[1].clone(),
+                            ]),
+                            Err(_) => key.clone(),
+                        },
+                        _ => key.clone(),
+                    };
+                    pairs.push((self.object_to_graphson(&key)?, self.object_to_graphson(val)?));
+                }
+                map_to_graphson(pairs)
+            }
+            Object::DateFormat(DateTimeFormats::Time(t)) => Value::String(t.to_string()),
+            Object::DateFormat(date_time) => {
+                typed("g:Date", Value::from(date_time.timestamp_millis().unwrap_or_default()))
+            }
+            Object::None => Value::Null,
+            Object::Blob(_) | Object::DynOwned(_) => {
+                Err(FnExecError::unsupported_error(&format!("write object {:?} in GraphSON", obj)))?
+            }
+        };
+        Ok(value)
+    }
+
+    fn get_meta_name(&self, meta_id: KeyId, t: MetaType) -> String {
+        if let Some(meta_name) = self
+            .schema_map
+            .and_then(|schema_map| schema_map.get(&(t, meta_id)))
+        {
+            meta_name.clone()
+        } else {
+            // if we cannot find mapped meta_name, we write meta_id directly.
+            meta_id.to_string()
+        }
+    }
+
+    fn meta_to_graphson(&self, meta: &NameOrId, t: MetaType) -> String {
+        match meta {
+            NameOrId::Str(name) => name.clone(),
+            NameOrId::Id(id) => self.get_meta_name(*id, t),
+        }
+    }
+
+    fn label_to_graphson(&self, label: Option<KeyId>, t: MetaType, default: &str) -> Value {
+        Value::String(
+            label
+                .map(|label| self.get_meta_name(label, t))
+                .unwrap_or_else(|| default.to_string()),
+        )
+    }
+
+    fn vid_to_graphson(&self, vid: &ID) -> Value {
+        typed("g:Vertex", json!({ "id": id_to_graphson(*vid), "label": DEFAULT_VERTEX_LABEL }))
+    }
+
+    fn vertex_to_graphson(&self, v: &Vertex) -> Value {
+        let mut properties = Map::new();
+        if let Some(all_properties) = v.get_all_properties() {
+            for (key, val) in all_properties {
+                let key = self.meta_to_graphson(&key, MetaType::Column);
+                // properties of unsupported types are skipped, as they are not required by the vertex
+                if let Ok(value) = self.object_to_graphson(&val) {
+                    let vertex_property = typed(
+                        "g:VertexProperty",
+                        json!({
+                            "id": format!("{}.{}", v.id(), key),
+                            "value": value,
+                            "label": key.clone(),
+                        }),
+                    );
+                    properties.insert(key, Value::Array(vec![vertex_property]));
+                }
+            }
+        }
+        let mut vertex = json!({
+            "id": id_to_graphson(v.id()),
+            "label": self.label_to_graphson(v.label(), MetaType::Entity, DEFAULT_VERTEX_LABEL),
+        });
+        if !properties.is_empty() {
+            vertex["properties"] = Value::Object(properties);
+        }
+        typed("g:Vertex", vertex)
+    }
+
+    fn edge_to_graphson(&self, e: &Edge) -> Value {
+        let mut properties = Map::new();
+        if let Some(all_properties) = e.get_all_properties() {
+            for (key, val) in all_properties {
+                let key = self.meta_to_graphson(&key, MetaType::Column);
+                if let Ok(value) = self.object_to_graphson(&val) {
+                    let property = typed("g:Property", json!({ "key": key.clone(), "value": value }));
+                    properties.insert(key, property);
+                }
+            }
+        }
+        let mut edge = json!({
+            "id": id_to_graphson(e.id()),
+            "label": self.label_to_graphson(e.label(), MetaType::Relation, DEFAULT_EDGE_LABEL),
+            "outV": id_to_graphson(e.src_id),
+            "outVLabel": self.label_to_graphson(
+                e.get_src_label().cloned(),
+                MetaType::Entity,
+                DEFAULT_VERTEX_LABEL
+            ),
+            "inV": id_to_graphson(e.dst_id),
+            "inVLabel": self.label_to_graphson(
+                e.get_dst_label().cloned(),
+                MetaType::Entity,
+                DEFAULT_VERTEX_LABEL
+            ),
+        });
+        if !properties.is_empty() {
+            edge["properties"] = Value::Object(properties);
+        }
+        typed("g:Edge", edge)
+    }
+
+    fn vertex_or_edge_to_graphson(&self, vertex_or_edge: &VertexOrEdge) -> Value {
+        match vertex_or_edge {
+            VertexOrEdge::V(v) => self.vertex_to_graphson(v),
+            VertexOrEdge::E(e) => self.edge_to_graphson(e),
+        }
+    }
+
+    fn path_to_graphson(&self, p: &GraphPath) -> Value {
+        let objects: Vec<Value> = match p {
+            GraphPath::AllPath(path)
+            | GraphPath::SimpleAllPath(path)
+            | GraphPath::TrailAllPath((path, _, _)) => path
+                .iter()
+                .map(|vertex_or_edge| self.vertex_or_edge_to_graphson(vertex_or_edge))
+                .collect(),
+            GraphPath::EndV((path_end, _))
+            | GraphPath::SimpleEndV((path_end, _, _))
+            | GraphPath::TrailEndV((path_end, _, _)) => {
+                vec![self.vertex_or_edge_to_graphson(path_end)]
+            }
+        };
+        // the elements of a path in runtime are not labeled
+        let labels = objects
+            .iter()
+            .map(|_| typed("g:Set", Value::Array(vec![])))
+            .collect();
+        typed(
+            "g:Path",
+            json!({
+                "labels": typed("g:List", Value::Array(labels)),
+                "objects": typed("g:List", Value::Array(objects)),
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use dyn_type::Object;
+    use graph_proxy::apis::{DynDetails, Edge, Vertex};
+    use ir_common::generated::algebra::sink_default::MetaType;
+    use ir_common::NameOrId;
+    use serde_json::json;
+
+    use super::GraphSONWriter;
+    use crate::process::entry::{CollectionEntry, DynEntry, PairEntry};
+
+    fn schema_map() -> HashMap<(MetaType, i32), String> {
+        let mut schema_map = HashMap::new();
+        schema_map.insert((MetaType::Entity, 0), "person".to_string());
+        schema_map.insert((MetaType::Relation, 1), "knows".to_string());
+        schema_map.insert((MetaType::Column, 2), "name".to_string());
+        schema_map.insert((MetaType::Tag, 0), "a".to_string());
+        schema_map
+    }
+
+    #[test]
+    fn vertex_graphson_test() {
+        let schema_map = schema_map();
+        let writer = GraphSONWriter::new(Some(&schema_map));
+        let mut properties = HashMap::new();
+        properties.insert(NameOrId::Id(2), Object::from("marko"));
+        let v = Vertex::new(1, Some(0), DynDetails::new(properties));
+        let value = writer
+            .entry_to_graphson(&DynEntry::new(v))
+            .unwrap();
+        let expected = json!({
+            "@type": "g:Vertex",
+            "@value": {
+                "id": { "@type": "g:Int64", "@value": 1 },
+                "label": "person",
+                "properties": {
+                    "name": [{
+                        "@type": "g:VertexProperty",
+                        "@value": { "id": "1.name", "value": "marko", "label": "name" }
+                    }]
+                }
+            }
+        });
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn edge_graphson_test() {
+        let schema_map = schema_map();
+        let writer = GraphSONWriter::new(Some(&schema_map));
+        let mut e = Edge::new(12, Some(1), 1, 2, DynDetails::default());
+        e.set_src_label(0);
+        let value = writer
+            .entry_to_graphson(&DynEntry::new(e))
+            .unwrap();
+        let expected = json!({
+            "@type": "g:Edge",
+            "@value": {
+                "id": { "@type": "g:Int64", "@value": 12 },
+                "label": "knows",
+                "outV": { "@type": "g:Int64", "@value": 1 },
+                "outVLabel": "person",
+                "inV": { "@type": "g:Int64", "@value": 2 },
+                "inVLabel": "vertex"
+            }
+        });
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn map_graphson_test() {
+        let writer = GraphSONWriter::new(None);
+        let collection = CollectionEntry {
+            inner: vec![DynEntry::new(PairEntry::new(
+                DynEntry::new(Object::from("count")),
+                DynEntry::new(Object::from(3_i64)),
+            ))],
+        };
+        let value = writer
+            .entry_to_graphson(&DynEntry::new(collection))
+            .unwrap();
+        let expected = json!({
+            "@type": "g:Map",
+            "@value": ["count", { "@type": "g:Int64", "@value": 3 }]
+        });
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn columns_graphson_test() {
+        let schema_map = schema_map();
+        let writer = GraphSONWriter::new(Some(&schema_map));
+        let a = DynEntry::new(Object::Vector(vec![Object::from(1), Object::from(2)]));
+        let b = DynEntry::new(Object::from(0.5));
+        let value = writer
+            .columns_to_graphson(vec![(Some(0), &a), (Some(1), &b)])
+            .unwrap();
+        let expected = json!({
+            "@type": "g:Map",
+            "@value": [
+                "a",
+                {
+                    "@type": "g:List",
+                    "@value": [{ "@type": "g:Int32", "@value": 1 }, { "@type": "g:Int32", "@value": 2 }]
+                },
+                "1",
+                { "@type": "g:Double", "@value": 0.5 }
+            ]
+        });
+        assert_eq!(value, expected);
+    }
+}
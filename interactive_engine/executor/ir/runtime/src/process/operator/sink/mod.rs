@@ -12,9 +12,14 @@
 //! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
+#[cfg(feature = "arrow")]
+mod arrow;
+pub mod graphbinary;
+mod graphson;
 mod sink;
 #[cfg(feature = "with_v6d")]
 mod sink_vineyard;
+mod tabular;
 
 use std::convert::TryInto;
 
@@ -60,8 +65,12 @@ impl SinkGen for pb::Sink {
                 .collect();
             match inner {
                 algebra_pb::sink::sink_target::Inner::SinkDefault(sink_default) => {
-                    let default_sink_op =
-                        DefaultSinkOp { tags, id_name_mappings: sink_default.id_name_mappings };
+                    let default_sink_op = DefaultSinkOp {
+                        tags,
+                        id_name_mappings: sink_default.id_name_mappings,
+                        format: sink_default.format,
+                        columns: sink_default.columns,
+                    };
                     default_sink_op.gen_sink()
                 }
                 algebra_pb::sink::sink_target::Inner::SinkVineyard(_sink_vineyard) => {
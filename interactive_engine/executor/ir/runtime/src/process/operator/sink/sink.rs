@@ -15,12 +15,14 @@
 
 use std::borrow::BorrowMut;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 
 use dyn_type::Object;
 use graph_proxy::apis::VertexOrEdge;
 use graph_proxy::apis::{Edge, Element, GraphElement, GraphPath, Vertex, ID};
+use ir_common::error::ParsePbError;
 use ir_common::generated::algebra as algebra_pb;
-use ir_common::generated::algebra::sink_default::MetaType;
+use ir_common::generated::algebra::sink_default::{Format, MetaType};
 use ir_common::generated::common as common_pb;
 use ir_common::generated::results as result_pb;
 use ir_common::{KeyId, NameOrId};
@@ -29,10 +31,16 @@ use pegasus_common::downcast::AsAny;
 use prost::Message;
 
 use crate::error::{FnExecError, FnExecResult, FnGenResult};
-use crate::process::entry::{CollectionEntry, DynEntry, Entry, EntryType, PairEntry};
+use crate::process::entry::{CollectionEntry, DynEntry, Entry, EntryType, MapEntry, PairEntry};
 use crate::process::operator::map::{GeneralIntersectionEntry, IntersectionEntry};
+#[cfg(feature = "arrow")]
+use crate::process::operator::sink::arrow::ArrowBatchWriter;
+use crate::process::operator::sink::graphbinary::{GraphBinaryWriter, ResponseMessage, PARTIAL_CONTENT};
+use crate::process::operator::sink::graphson::GraphSONWriter;
+use crate::process::operator::sink::tabular::{record_to_row, TabularColumn};
 use crate::process::operator::sink::{SinkGen, Sinker};
 use crate::process::record::Record;
+use crate::provenance::Provenance;
 
 #[derive(Debug)]
 pub struct RecordSinkEncoder {
@@ -40,6 +48,12 @@ pub struct RecordSinkEncoder {
     sink_keys: Vec<Option<KeyId>>,
     /// A map from id to name; Now we only support to map Tag (Alias) in Runtime.
     schema_map: Option<HashMap<(MetaType, i32), String>>,
+    /// the format of the results sent to the client;
+    format: Format,
+    /// the declared columns of the tabular results;
+    columns: Vec<TabularColumn>,
+    /// the id of the request of a Gremlin driver that the results respond to in GraphBinary;
+    request_id: Option<[u8; 16]>,
 }
 
 impl RecordSinkEncoder {
@@ -112,6 +126,13 @@ impl RecordSinkEncoder {
                     Err(FnExecError::unsupported_error("unsupported intersection entry type"))?
                 }
             }
+            EntryType::Map => {
+                let map = e
+                    .as_any_ref()
+                    .downcast_ref::<MapEntry>()
+                    .unwrap();
+                Some(result_pb::entry::Inner::Map(self.map_entry_to_pb(map)?))
+            }
             _ => {
                 if let Some(map_pb) = self.try_map_to_pb(e) {
                     Some(result_pb::entry::Inner::Map(map_pb))
@@ -159,20 +180,48 @@ impl RecordSinkEncoder {
                 .as_any_ref()
                 .downcast_ref::<PairEntry>()
                 .unwrap();
-            if let Some(key_obj) = pair.get_left().as_object() {
-                let key_pb: common_pb::Value = key_obj.clone().into();
-                let val_pb = self.element_to_pb(pair.get_right());
-                key_values.push(result_pb::key_values::KeyValue { key: Some(key_pb), value: Some(val_pb) })
-            } else {
-                Err(FnExecError::unsupported_error(&format!(
-                    "only support map result with object key, while it is {:?}",
-                    pair.get_left()
-                )))?
-            }
+            key_values.push(self.pair_to_pb(pair)?);
         }
         Ok(result_pb::KeyValues { key_values })
     }
 
+    fn map_entry_to_pb(&self, map: &MapEntry) -> FnExecResult<result_pb::KeyValues> {
+        let mut key_values = Vec::with_capacity(map.len());
+        for pair in map.inner.iter() {
+            key_values.push(self.pair_to_pb(pair)?);
+        }
+        Ok(result_pb::KeyValues { key_values })
+    }
+
+    fn pair_to_pb(&self, pair: &PairEntry) -> FnExecResult<result_pb::key_values::KeyValue> {
+        let mut key_value = result_pb::key_values::KeyValue::default();
+        match pair.get_left().get_type() {
+            EntryType::Object => {
+                let key_obj = pair.get_left().as_object().unwrap();
+                key_value.key = Some(key_obj.clone().into());
+            }
+            // e.g., the vertices of the trees
+            EntryType::Vertex | EntryType::Edge | EntryType::Path => {
+                key_value.element_key = Some(self.element_to_pb(pair.get_left()));
+            }
+            _ => Err(FnExecError::unsupported_error(&format!(
+                "only support map result with object or graph element key, while it is {:?}",
+                pair.get_left()
+            )))?,
+        }
+        if let Some(nested) = pair
+            .get_right()
+            .as_any_ref()
+            .downcast_ref::<MapEntry>()
+        {
+            // e.g., the subtrees of the trees
+            key_value.nested_value = Some(self.map_entry_to_pb(nested)?);
+        } else {
+            key_value.value = Some(self.element_to_pb(pair.get_right()));
+        }
+        Ok(key_value)
+    }
+
     fn element_to_pb(&self, e: &DynEntry) -> result_pb::Element {
         let inner = match e.get_type() {
             EntryType::Vertex => {
@@ -200,6 +249,9 @@ impl RecordSinkEncoder {
             EntryType::Pair => {
                 unreachable!()
             }
+            EntryType::Map => {
+                unreachable!()
+            }
         };
         result_pb::Element { inner }
     }
@@ -238,6 +290,50 @@ impl RecordSinkEncoder {
         mapped_meta.into()
     }
 
+    fn provenance_to_pb(&self, provenance: &Provenance) -> result_pb::Provenance {
+        let steps = provenance
+            .steps()
+            .map(|step| result_pb::provenance::Step {
+                operator: step.operator.as_str().to_owned(),
+                tags: step
+                    .tags
+                    .iter()
+                    .filter_map(|tag| tag.map(|tag| self.meta_to_pb(NameOrId::Id(tag), MetaType::Tag)))
+                    .collect(),
+                reads_head: step.tags.contains(&None),
+                vertices: step.vertices.clone(),
+            })
+            .collect();
+        result_pb::Provenance { steps, truncated: provenance.is_truncated() }
+    }
+
+    /// The provenance in json, for the formats other than protobuf, in the same shape as in protobuf.
+    fn provenance_to_json(&self, provenance: &Provenance) -> String {
+        let provenance_pb = self.provenance_to_pb(provenance);
+        let steps = provenance_pb
+            .steps
+            .into_iter()
+            .map(|step| {
+                let tags = step
+                    .tags
+                    .into_iter()
+                    .map(|tag| match tag.item {
+                        Some(common_pb::name_or_id::Item::Name(name)) => serde_json::Value::from(name),
+                        Some(common_pb::name_or_id::Item::Id(id)) => serde_json::Value::from(id),
+                        None => serde_json::Value::Null,
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::json!({
+                    "operator": step.operator,
+                    "tags": tags,
+                    "reads_head": step.reads_head,
+                    "vertices": step.vertices,
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({ "steps": steps, "truncated": provenance_pb.truncated }).to_string()
+    }
+
     fn get_meta_name(&self, meta_id: KeyId, t: MetaType) -> NameOrId {
         if let Some(schema_map) = self.schema_map.as_ref() {
             if let Some(meta_name) = schema_map.get(&(t, meta_id)) {
@@ -303,23 +399,182 @@ impl RecordSinkEncoder {
     fn path_to_pb(&self, p: &GraphPath) -> result_pb::GraphPath {
         let mut graph_path_pb = vec![];
         match p {
-            GraphPath::AllPath(path) | GraphPath::SimpleAllPath(path) => {
+            GraphPath::AllPath(path)
+            | GraphPath::SimpleAllPath(path)
+            | GraphPath::TrailAllPath((path, _, _)) => {
                 for vertex_or_edge in path {
                     let vertex_or_edge_pb = self.vertex_or_edge_to_pb(vertex_or_edge);
                     graph_path_pb.push(vertex_or_edge_pb);
                 }
             }
-            GraphPath::EndV((path_end, _)) | GraphPath::SimpleEndV((path_end, _, _)) => {
+            GraphPath::EndV((path_end, _))
+            | GraphPath::SimpleEndV((path_end, _, _))
+            | GraphPath::TrailEndV((path_end, _, _)) => {
                 let vertex_or_edge_pb = self.vertex_or_edge_to_pb(path_end);
                 graph_path_pb.push(vertex_or_edge_pb);
             }
         }
         result_pb::GraphPath { path: graph_path_pb }
     }
+
+    fn get_sink_columns<'a>(&self, input: &'a mut Record) -> Vec<(Option<KeyId>, &'a DynEntry)> {
+        if self.sink_keys.is_empty() {
+            // the case of sink all **tagged** columns by default.
+            input
+                .get_columns_mut()
+                .iter()
+                .map(|(sink_key, entry)| (Some(sink_key as KeyId), entry))
+                .collect()
+        } else {
+            let mut sink_columns = Vec::with_capacity(self.sink_keys.len());
+            for sink_key in self.sink_keys.iter() {
+                if let Some(entry) = input.get(sink_key.clone()) {
+                    sink_columns.push((sink_key.clone(), entry));
+                }
+            }
+            sink_columns
+        }
+    }
+
+    /// Respond to the request of a Gremlin driver, where each record is encoded in GraphBinary as a
+    /// response of a part of the results, whatever the format of the sink;
+    pub fn respond_to(&mut self, request_id: [u8; 16]) {
+        self.format = Format::GraphbinaryV1;
+        self.request_id = Some(request_id);
+    }
+
+    /// Whether the records are encoded by batches, i.e., `encode_batch()` instead of `exec()`;
+    pub fn is_batched(&self) -> bool {
+        self.format == Format::Arrow
+    }
+
+    /// Whether the side effects can be returned after the results, i.e., by `encode_side_effect()`;
+    pub fn returns_side_effects(&self) -> bool {
+        matches!(self.format, Format::Protobuf | Format::Tabular)
+    }
+
+    /// Encode a batch of records as an Arrow record batch in the IPC stream format.
+    #[cfg(feature = "arrow")]
+    pub fn encode_batch(&self, mut records: Vec<Record>) -> FnExecResult<Vec<u8>> {
+        let sink_keys: Vec<Option<KeyId>> = if self.sink_keys.is_empty() {
+            // the case of sink all **tagged** columns by default.
+            let mut sink_keys = std::collections::BTreeSet::new();
+            for record in records.iter_mut() {
+                sink_keys.extend(
+                    record
+                        .get_columns_mut()
+                        .keys()
+                        .map(|sink_key| sink_key as KeyId),
+                );
+            }
+            sink_keys.into_iter().map(Some).collect()
+        } else {
+            self.sink_keys.clone()
+        };
+        let provenance: Vec<Option<String>> = records
+            .iter_mut()
+            .map(|record| {
+                record
+                    .take_provenance()
+                    .map(|provenance| self.provenance_to_json(&provenance))
+            })
+            .collect();
+        let columns = sink_keys
+            .into_iter()
+            .map(|sink_key| {
+                let entries = records
+                    .iter()
+                    .map(|record| record.get(sink_key.clone()))
+                    .collect();
+                (sink_key, entries)
+            })
+            .collect();
+        let mut writer = ArrowBatchWriter::new(self.schema_map.as_ref());
+        if provenance.iter().any(Option::is_some) {
+            writer = writer.with_provenance(provenance);
+        }
+        writer.write_batch(columns)
+    }
+
+    #[cfg(not(feature = "arrow"))]
+    pub fn encode_batch(&self, _records: Vec<Record>) -> FnExecResult<Vec<u8>> {
+        Err(FnExecError::unsupported_error(
+            "sink in arrow is not as a feature. Try \'cargo build --features arrow\'",
+        ))
+    }
+
+    /// Encode the side effect of the name, which is returned after the results, in the formats of
+    /// `returns_side_effects()` only.
+    pub fn encode_side_effect(&self, name: &str, entry: &DynEntry) -> FnExecResult<Vec<u8>> {
+        match self.format {
+            Format::Protobuf | Format::Tabular => {
+                let side_effect_pb =
+                    result_pb::SideEffect { name: name.to_string(), entry: Some(self.entry_to_pb(entry)?) };
+                let results = result_pb::Results {
+                    inner: Some(result_pb::results::Inner::SideEffect(side_effect_pb)),
+                };
+                Ok(results.encode_to_vec())
+            }
+            _ => Err(FnExecError::unsupported_error(&format!(
+                "side effect `{}` returned in {:?}",
+                name, self.format
+            ))),
+        }
+    }
+
+    fn record_to_graphbinary(&self, mut input: Record) -> FnExecResult<Vec<u8>> {
+        let writer = GraphBinaryWriter::new(self.schema_map.as_ref());
+        let provenance = input
+            .take_provenance()
+            .map(|provenance| self.provenance_to_json(&provenance));
+        let mut buf = vec![];
+        match provenance {
+            Some(provenance) => writer.write_columns_with_provenance(
+                self.get_sink_columns(&mut input),
+                &provenance,
+                &mut buf,
+            )?,
+            None => writer.write_columns(self.get_sink_columns(&mut input), &mut buf)?,
+        }
+        if self.request_id.is_some() {
+            Ok(ResponseMessage::new(self.request_id, PARTIAL_CONTENT).encode(&[buf]))
+        } else {
+            Ok(buf)
+        }
+    }
+
+    fn record_to_graphson(&self, mut input: Record) -> FnExecResult<Vec<u8>> {
+        let writer = GraphSONWriter::new(self.schema_map.as_ref());
+        let provenance = input.take_provenance();
+        let mut value = writer.columns_to_graphson(self.get_sink_columns(&mut input))?;
+        if let Some(provenance) = provenance {
+            value = writer.with_provenance(value, self.provenance_to_json(&provenance));
+        }
+        debug!("sink record in graphson {}", value);
+        serde_json::to_vec(&value)
+            .map_err(|e| FnExecError::unexpected_data_error(&format!("write graphson error {:?}", e)))
+    }
 }
 
 impl MapFunction<Record, Vec<u8>> for RecordSinkEncoder {
     fn exec(&self, mut input: Record) -> FnResult<Vec<u8>> {
+        match self.format {
+            Format::GraphsonV3 => return Ok(self.record_to_graphson(input)?),
+            Format::GraphbinaryV1 => return Ok(self.record_to_graphbinary(input)?),
+            Format::Arrow => {
+                Err(FnExecError::unsupported_error("encode a record out of a batch in arrow"))?
+            }
+            Format::Tabular => {
+                let mut record_pb = record_to_row(&self.columns, &input)?;
+                record_pb.provenance = input
+                    .take_provenance()
+                    .map(|provenance| self.provenance_to_pb(&provenance));
+                let results =
+                    result_pb::Results { inner: Some(result_pb::results::Inner::Record(record_pb)) };
+                return Ok(results.encode_to_vec());
+            }
+            Format::Protobuf => {}
+        }
         let mut sink_columns = Vec::with_capacity(self.sink_keys.len());
         if self.sink_keys.is_empty() {
             // the case of sink all **tagged** columns by default.
@@ -347,7 +602,10 @@ impl MapFunction<Record, Vec<u8>> for RecordSinkEncoder {
             }
         }
 
-        let record_pb = result_pb::Record { columns: sink_columns };
+        let provenance = input
+            .take_provenance()
+            .map(|provenance| self.provenance_to_pb(&provenance));
+        let record_pb = result_pb::Record { columns: sink_columns, provenance };
         debug!("sink record_pb {:?}", record_pb);
         let results = result_pb::Results { inner: Some(result_pb::results::Inner::Record(record_pb)) };
         Ok(results.encode_to_vec())
@@ -357,6 +615,8 @@ impl MapFunction<Record, Vec<u8>> for RecordSinkEncoder {
 pub struct DefaultSinkOp {
     pub tags: Vec<Option<KeyId>>,
     pub id_name_mappings: Vec<algebra_pb::sink_default::IdNameMapping>,
+    pub format: i32,
+    pub columns: Vec<algebra_pb::sink_default::Column>,
 }
 
 impl SinkGen for DefaultSinkOp {
@@ -366,9 +626,21 @@ impl SinkGen for DefaultSinkOp {
             let meta_type = unsafe { ::std::mem::transmute(id_name_mappings_pb.meta_type) };
             schema_map.insert((meta_type, id_name_mappings_pb.id), id_name_mappings_pb.name);
         }
+        let format = Format::from_i32(self.format).unwrap_or(Format::Protobuf);
+        if format == Format::Tabular && self.columns.is_empty() {
+            Err(ParsePbError::EmptyFieldError("columns of the tabular sink are missing".to_string()))?
+        }
+        let columns = self
+            .columns
+            .into_iter()
+            .map(TabularColumn::try_from)
+            .collect::<FnGenResult<Vec<_>>>()?;
         let record_sinker = RecordSinkEncoder {
             sink_keys: self.tags,
             schema_map: if schema_map.is_empty() { None } else { Some(schema_map) },
+            format,
+            columns,
+            request_id: None,
         };
         if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
             debug!("Runtime sink operator: {:?}", record_sinker);
@@ -0,0 +1,314 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Shape the results into the tabular rows of the declared columns, as SQL/PGQ does, for the BI tools that
+//! only understand tables:
+//! * the value of a column is the property of a tagged entry, or the id of the entry if no property
+//!   is given, e.g., of a vertex;
+//! * the value is NULL if the tag or the property is absent, or the tagged entry is NULL;
+//! * the value is coerced into the declared type, e.g., an integer into a double, or a string of a date
+//!   into a date, and it is an error if the value can not be coerced without losing its meaning.
+
+use std::convert::TryFrom;
+
+use dyn_type::object::{DateTimeFormats, Primitives};
+use dyn_type::Object;
+use graph_proxy::apis::Element;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::results as result_pb;
+use ir_common::KeyId;
+
+use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
+use crate::process::entry::{Entry, EntryType};
+use crate::process::operator::TagKey;
+use crate::process::record::Record;
+
+#[derive(Debug)]
+pub struct TabularColumn {
+    name: String,
+    tag: Option<KeyId>,
+    tag_key: TagKey,
+    data_type: common_pb::DataType,
+}
+
+impl TryFrom<algebra_pb::sink_default::Column> for TabularColumn {
+    type Error = FnGenError;
+
+    fn try_from(column: algebra_pb::sink_default::Column) -> FnGenResult<Self> {
+        use common_pb::DataType;
+
+        let data_type = DataType::from_i32(column.data_type)
+            .filter(|data_type| {
+                matches!(
+                    data_type,
+                    DataType::Boolean
+                        | DataType::Int32
+                        | DataType::Int64
+                        | DataType::Double
+                        | DataType::String
+                        | DataType::Bytes
+                        | DataType::Date32
+                        | DataType::Time32
+                        | DataType::Timestamp
+                )
+            })
+            .ok_or_else(|| {
+                FnGenError::unsupported_error(&format!(
+                    "type {:?} of the tabular column {}",
+                    column.data_type, column.name
+                ))
+            })?;
+        let var = column.var.ok_or_else(|| {
+            FnGenError::unsupported_error(&format!("tabular column {} without var", column.name))
+        })?;
+        let tag = var
+            .tag
+            .clone()
+            .map(KeyId::try_from)
+            .transpose()?;
+        let tag_key = TagKey::try_from(var)?;
+        Ok(TabularColumn { name: column.name, tag, tag_key, data_type })
+    }
+}
+
+impl TabularColumn {
+    /// The value of the column in the given record, which is `Object::None` as NULL.
+    fn value(&self, record: &Record) -> FnExecResult<Object> {
+        let is_null = match record.get(self.tag) {
+            Some(entry) => entry.as_object() == Some(&Object::None),
+            None => true,
+        };
+        if is_null {
+            return Ok(Object::None);
+        }
+        let entry = self.tag_key.get_arc_entry(record)?;
+        let obj = match entry.get_type() {
+            EntryType::Object => entry
+                .as_object()
+                .cloned()
+                .unwrap_or(Object::None),
+            EntryType::Vertex | EntryType::Edge => entry
+                .as_graph_element()
+                .map(|element| Object::from(element.id()))
+                .unwrap_or(Object::None),
+            _ => Err(FnExecError::unsupported_error(&format!(
+                "entry {:?} in the tabular column {}",
+                entry, self.name
+            )))?,
+        };
+        if obj == Object::None {
+            Ok(obj)
+        } else {
+            self.coerce(obj)
+        }
+    }
+
+    fn coerce(&self, obj: Object) -> FnExecResult<Object> {
+        use common_pb::DataType;
+
+        let coerced = match self.data_type {
+            DataType::Boolean => match &obj {
+                Object::String(s) => s.parse::<bool>().ok().map(Object::from),
+                _ => obj.as_bool().ok().map(Object::from),
+            },
+            DataType::Int32 => match &obj {
+                Object::String(s) => s.trim().parse::<i32>().ok().map(Object::from),
+                Object::Primitive(Primitives::Float(f)) => integral(*f)
+                    .and_then(|i| i32::try_from(i).ok())
+                    .map(Object::from),
+                _ => obj.as_i32().ok().map(Object::from),
+            },
+            DataType::Int64 => match &obj {
+                Object::String(s) => s.trim().parse::<i64>().ok().map(Object::from),
+                Object::Primitive(Primitives::Float(f)) => integral(*f).map(Object::from),
+                _ => obj.as_i64().ok().map(Object::from),
+            },
+            DataType::Double => match &obj {
+                Object::String(s) => s.trim().parse::<f64>().ok().map(Object::from),
+                _ => obj.as_f64().ok().map(Object::from),
+            },
+            DataType::String => match &obj {
+                Object::Primitive(_) | Object::String(_) | Object::DateFormat(_) => {
+                    Some(Object::from(obj.to_string()))
+                }
+                Object::Blob(b) => String::from_utf8(b.to_vec())
+                    .ok()
+                    .map(Object::from),
+                _ => None,
+            },
+            DataType::Bytes => match &obj {
+                Object::Blob(_) => Some(obj.clone()),
+                Object::String(s) => Some(Object::from(s.as_bytes().to_vec())),
+                _ => None,
+            },
+            DataType::Date32 => date_format(&obj)
+                .and_then(|d| d.as_date().ok())
+                .map(|d| Object::from(DateTimeFormats::Date(d))),
+            DataType::Time32 => date_format(&obj)
+                .and_then(|d| d.as_time().ok())
+                .map(|t| Object::from(DateTimeFormats::Time(t))),
+            DataType::Timestamp => match &obj {
+                Object::Primitive(Primitives::Integer(_)) | Object::Primitive(Primitives::Long(_)) => obj
+                    .as_i64()
+                    .ok()
+                    .and_then(|millis| DateTimeFormats::from_timestamp_millis(millis).ok())
+                    .map(Object::from),
+                _ => match date_format(&obj) {
+                    Some(DateTimeFormats::Date(d)) => d
+                        .and_hms_opt(0, 0, 0)
+                        .map(|dt| Object::from(DateTimeFormats::DateTime(dt))),
+                    Some(DateTimeFormats::Time(_)) | None => None,
+                    Some(dt) => Some(Object::from(dt)),
+                },
+            },
+            // the other types are rejected while generating the column
+            _ => None,
+        };
+        coerced.ok_or_else(|| {
+            FnExecError::unexpected_data_error(&format!(
+                "cannot coerce {:?} into {:?} of the tabular column {}",
+                obj, self.data_type, self.name
+            ))
+        })
+    }
+}
+
+/// The integer of a float without a fractional part.
+fn integral(f: f64) -> Option<i64> {
+    if f.fract() == 0.0 && f >= i64::MIN as f64 && f <= i64::MAX as f64 {
+        Some(f as i64)
+    } else {
+        None
+    }
+}
+
+/// The date, time or datetime of an object, or parsed from a string in ISO formats.
+fn date_format(obj: &Object) -> Option<DateTimeFormats> {
+    match obj {
+        Object::String(s) => DateTimeFormats::from_str(s).ok(),
+        _ => obj.as_date_format().ok().cloned(),
+    }
+}
+
+/// Write a record as a row of the declared columns.
+pub fn record_to_row(columns: &[TabularColumn], record: &Record) -> FnExecResult<result_pb::Record> {
+    let mut row = Vec::with_capacity(columns.len());
+    for column in columns {
+        let value: common_pb::Value = column.value(record)?.into();
+        row.push(result_pb::Column {
+            name_or_id: Some(column.name.clone().into()),
+            entry: Some(result_pb::Entry {
+                inner: Some(result_pb::entry::Inner::Element(result_pb::Element {
+                    inner: Some(result_pb::element::Inner::Object(value)),
+                })),
+            }),
+        });
+    }
+    Ok(result_pb::Record { columns: row, provenance: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use dyn_type::object;
+    use ir_common::NameOrId;
+
+    use super::*;
+    use crate::process::operator::tests::{init_vertex1, to_var_pb, TAG_A, TAG_B, TAG_C};
+
+    fn column(tag: KeyId, key: Option<&str>, name: &str, data_type: common_pb::DataType) -> TabularColumn {
+        let column_pb = algebra_pb::sink_default::Column {
+            var: Some(to_var_pb(Some(tag.into()), key.map(|key| key.into()))),
+            name: name.to_string(),
+            data_type: data_type as i32,
+        };
+        TabularColumn::try_from(column_pb).unwrap()
+    }
+
+    fn values(row: result_pb::Record) -> Vec<common_pb::value::Item> {
+        row.columns
+            .into_iter()
+            .map(|column| match column.entry.unwrap().inner.unwrap() {
+                result_pb::entry::Inner::Element(result_pb::Element {
+                    inner: Some(result_pb::element::Inner::Object(value)),
+                }) => value.item.unwrap(),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn record_to_row_test() {
+        use common_pb::value::Item;
+        use common_pb::DataType;
+
+        let mut record = Record::new(init_vertex1(), Some(TAG_A));
+        record.append(Object::None, Some(TAG_B));
+        let columns = vec![
+            column(TAG_A, None, "id", DataType::Int64),
+            column(TAG_A, Some("age"), "age", DataType::Double),
+            column(TAG_A, Some("code"), "code", DataType::Int32),
+            column(TAG_A, Some("birthday"), "birthday", DataType::Date32),
+            column(TAG_B, Some("name"), "friend", DataType::String),
+            column(TAG_C, Some("name"), "other", DataType::String),
+        ];
+        let row = record_to_row(&columns, &record).unwrap();
+        assert_eq!(row.columns[0].name_or_id, Some(NameOrId::from("id".to_string()).into()));
+        assert_eq!(
+            values(row),
+            vec![
+                Item::I64(1),
+                Item::F64(29.0),
+                Item::I32(11051),
+                Item::None(common_pb::None {}),
+                Item::None(common_pb::None {}),
+                Item::None(common_pb::None {}),
+            ]
+        );
+    }
+
+    #[test]
+    fn coerce_test() {
+        use common_pb::DataType;
+
+        let date = column(TAG_A, Some("birthday"), "birthday", DataType::Date32);
+        assert_eq!(
+            date.coerce(object!("2020-01-02 10:00:00.000"))
+                .unwrap(),
+            Object::from(DateTimeFormats::from_str("2020-01-02").unwrap())
+        );
+        let timestamp = column(TAG_A, Some("created"), "created", DataType::Timestamp);
+        assert_eq!(
+            timestamp.coerce(object!(0_i64)).unwrap(),
+            Object::from(DateTimeFormats::from_str("1970-01-01 00:00:00.000").unwrap())
+        );
+        let int = column(TAG_A, Some("age"), "age", DataType::Int32);
+        assert_eq!(int.coerce(object!(29.0)).unwrap(), object!(29));
+        assert!(int.coerce(object!(29.5)).is_err());
+        assert!(int.coerce(object!("marko")).is_err());
+        let boolean = column(TAG_A, Some("alive"), "alive", DataType::Boolean);
+        assert_eq!(boolean.coerce(object!("true")).unwrap(), Object::from(true));
+    }
+
+    #[test]
+    fn unsupported_column_test() {
+        let column_pb = algebra_pb::sink_default::Column {
+            var: Some(to_var_pb(Some(TAG_A.into()), Some("tags".into()))),
+            name: "tags".to_string(),
+            data_type: common_pb::DataType::StringArray as i32,
+        };
+        assert!(TabularColumn::try_from(column_pb).is_err());
+    }
+}
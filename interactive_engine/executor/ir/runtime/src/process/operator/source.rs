@@ -18,9 +18,10 @@ use std::convert::{TryFrom, TryInto};
 use std::sync::Arc;
 
 use dyn_type::{object, Object};
+use graph_proxy::apis::graph::index::extract_ids;
 use graph_proxy::apis::graph::PKV;
 use graph_proxy::apis::partitioner::{PartitionInfo, PartitionedData};
-use graph_proxy::apis::{get_graph, ClusterInfo, Edge, QueryParams, Vertex, ID};
+use graph_proxy::apis::{get_graph, ClusterInfo, Edge, EdgeRef, GraphElement, QueryParams, Vertex, ID};
 use ir_common::error::{ParsePbError, ParsePbResult};
 use ir_common::generated::algebra as algebra_pb;
 use ir_common::generated::physical as pb;
@@ -95,7 +96,8 @@ impl SourceOperator {
                     }
                     Ok(source_op)
                 } else {
-                    let source_op = SourceOperator::try_from(scan)?;
+                    let mut source_op = SourceOperator::try_from(scan)?;
+                    source_op.push_down_index(partitioner)?;
                     debug!("Runtime source op of scan {:?}", source_op);
                     Ok(source_op)
                 }
@@ -105,6 +107,28 @@ impl SourceOperator {
         }
     }
 
+    /// Push the predicate of a scan of vertices down to the lookups of the storage, i.e., get the vertices
+    /// of the ids of `@.~id within [...]`, where the predicate and the labels of the scan are still
+    /// tested on the vertices looked up. The edges are always scanned, as an edge id is neither routed
+    /// by the partitions of the vertices nor looked up by every storage.
+    fn push_down_index<P: PartitionInfo, C: ClusterInfo>(
+        &mut self, partitioner: Arc<dyn Router<P = P, C = C>>,
+    ) -> ParsePbResult<()> {
+        // the elements of a sampled scan are not pushed down
+        if self.query_params.sample_ratio.is_some() || !matches!(self.source_type, SourceType::Vertex) {
+            return Ok(());
+        }
+        if let Some(ids) = self
+            .query_params
+            .filter
+            .as_ref()
+            .and_then(|filter| extract_ids(filter))
+        {
+            self.set_src(ids, partitioner)?;
+        }
+        Ok(())
+    }
+
     /// Assign source vertex ids for each worker to call get_vertex
     fn set_src<P: PartitionInfo, C: ClusterInfo>(
         &mut self, ids: Vec<ID>, partitioner: Arc<dyn Router<P = P, C = C>>,
@@ -142,6 +166,13 @@ impl SourceOperator {
                             v_source = graph.get_vertex(src, &self.query_params)?;
                         }
                     }
+                    // the vertices are got by the ids regardless of the labels
+                    if self.query_params.has_labels() {
+                        let labels = self.query_params.labels.clone();
+                        v_source = Box::new(
+                            v_source.filter(move |v| v.label().map_or(false, |l| labels.contains(&l))),
+                        );
+                    }
                     if self.is_count_only {
                         let count = v_source.count() as u64;
                         return Ok(Box::new(
@@ -195,6 +226,29 @@ impl SourceOperator {
                             vec![Record::new(object!(count), self.alias.clone())].into_iter(),
                         ));
                     }
+                } else if let Some(pkvs) = &self.primary_key_values {
+                    // query edges by their addresses, e.g., `g.E(id)`
+                    if !self.query_params.has_labels() {
+                        Err(FnGenError::unsupported_error(
+                            "Empty label in `IndexScan` of edges self.query_params.labels",
+                        ))?
+                    }
+                    let mut source_edges = vec![];
+                    for label in &self.query_params.labels {
+                        for pkv in pkvs {
+                            let edge_ref = EdgeRef::from_pkv(*label, pkv)?;
+                            if let Some(e) = graph.get_edge_by_id(&edge_ref, &self.query_params)? {
+                                source_edges.push(e);
+                            }
+                        }
+                    }
+                    if self.is_count_only {
+                        let count = source_edges.len() as u64;
+                        return Ok(Box::new(
+                            vec![Record::new(object!(count), self.alias.clone())].into_iter(),
+                        ));
+                    }
+                    e_source = Box::new(source_edges.into_iter());
                 } else {
                     if self.is_count_only {
                         let count = graph.count_edge(&self.query_params)?;
@@ -0,0 +1,444 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The split expand mitigates the stragglers of the edge expand from the high-degree vertices. Each
+//! local worker of a job keeps its pending adjacency, i.e., the records expanded by it or sent to it
+//! but not consumed yet. The records of the vertices of at most `chunk` adjacency are expanded by the
+//! worker itself, as the edge expand does. The adjacency of the other vertices is read once; once the
+//! pending adjacency of the worker would exceed the `threshold`, the records expanded are split into
+//! the tasks of `chunk` records, each sent to the local worker of the least pending adjacency
+//! (`SplitExpandOperator`), where the operators following the expand consume them
+//! (`TaskExpandOperator`). Only the tasks are exchanged, and only among the workers of the same
+//! server, so the tasks never leave the server.
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use ir_common::generated::physical as pb;
+use lazy_static::lazy_static;
+use pegasus::api::function::{DynIter, FlatMapFunction, FnResult};
+use pegasus::api::{Branch, Map, Merge};
+use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+use pegasus::stream::Stream;
+use pegasus::BuildJobError;
+
+use crate::error::{FnGenError, FnGenResult};
+use crate::process::operator::flatmap::{CountExpand, CountExpandGen};
+use crate::process::record::Record;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExpandSplit {
+    /// The pending adjacency of a worker over which the adjacency of its vertices is split.
+    pub threshold: usize,
+    /// The most records of a task, only the vertices of more adjacency are split.
+    pub chunk: usize,
+}
+
+/// The pending adjacency of the local workers of a job, indexed by the local indices of the workers.
+pub struct WorkerLoads {
+    loads: Vec<AtomicUsize>,
+}
+
+impl WorkerLoads {
+    pub fn new(local_peers: usize) -> Self {
+        WorkerLoads {
+            loads: (0..local_peers)
+                .map(|_| AtomicUsize::new(0))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> usize {
+        self.loads[index].load(Ordering::Relaxed)
+    }
+
+    fn add(&self, index: usize, load: usize) {
+        self.loads[index].fetch_add(load, Ordering::Relaxed);
+    }
+
+    fn sub(&self, index: usize, load: usize) {
+        self.loads[index].fetch_sub(load, Ordering::Relaxed);
+    }
+
+    /// The local index of the worker of the least pending adjacency.
+    fn idlest(&self) -> usize {
+        (0..self.loads.len())
+            .min_by_key(|index| self.get(*index))
+            .unwrap_or(0)
+    }
+}
+
+lazy_static! {
+    /// The loads of the local workers of the jobs, with the number of local workers binding them.
+    static ref JOB_LOADS: RwLock<HashMap<u64, (Arc<WorkerLoads>, usize)>> = RwLock::new(HashMap::new());
+}
+
+/// Share the loads of the local workers of the job among them, until all the returned bindings of the
+/// job are dropped, e.g., with the workers of the job as their resources.
+pub fn bind_worker_loads(job_id: u64, local_peers: usize) -> WorkerLoadsBinding {
+    JOB_LOADS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(job_id)
+        .or_insert_with(|| (Arc::new(WorkerLoads::new(local_peers)), 0))
+        .1 += 1;
+    WorkerLoadsBinding { job_id }
+}
+
+fn get_worker_loads(job_id: u64) -> Option<Arc<WorkerLoads>> {
+    JOB_LOADS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&job_id)
+        .map(|(loads, _)| loads.clone())
+}
+
+pub struct WorkerLoadsBinding {
+    job_id: u64,
+}
+
+impl Drop for WorkerLoadsBinding {
+    fn drop(&mut self) {
+        let mut jobs = JOB_LOADS
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some((_, count)) = jobs.get_mut(&self.job_id) {
+            *count -= 1;
+            if *count == 0 {
+                jobs.remove(&self.job_id);
+            }
+        }
+    }
+}
+
+/// The records expanded from a vertex, to be consumed by a local worker.
+#[derive(Clone, Debug)]
+pub struct ExpandTask {
+    /// The local index of the worker to consume the records.
+    pub worker: usize,
+    pub records: Vec<Record>,
+}
+
+/// The records expanded from a vertex by the worker itself, or split into the tasks of the workers.
+pub enum Split {
+    Local(DynIter<Record>),
+    Tasks(Vec<ExpandTask>),
+}
+
+/// Expand the start vertices, and split the records expanded from the high-degree vertices into the
+/// tasks of the local workers.
+pub struct SplitExpandOperator {
+    expand: Box<dyn CountExpand>,
+    split: ExpandSplit,
+    loads: Arc<WorkerLoads>,
+    local_index: usize,
+}
+
+impl SplitExpandOperator {
+    fn local(&self, iter: DynIter<Record>, load: usize) -> Split {
+        self.loads.add(self.local_index, load);
+        Split::Local(Box::new(PendingIter {
+            iter,
+            loads: self.loads.clone(),
+            local_index: self.local_index,
+            load,
+        }))
+    }
+
+    pub fn exec(&self, input: Record) -> FnResult<Split> {
+        match self
+            .expand
+            .count(&input, self.split.chunk + 1)?
+        {
+            Some(degree) if degree > self.split.chunk => {}
+            Some(degree) => return Ok(self.local(self.expand.exec(input)?, degree)),
+            None => return Ok(self.local(self.expand.exec(input)?, 1)),
+        }
+        // the adjacency is read once, and the records expanded from it are split
+        let expanded: Vec<Record> = self.expand.exec(input)?.collect();
+        let degree = expanded.len();
+        if self.loads.get(self.local_index) + degree <= self.split.threshold {
+            return Ok(self.local(Box::new(expanded.into_iter()), degree));
+        }
+        let mut tasks = Vec::with_capacity((degree + self.split.chunk - 1) / self.split.chunk);
+        let mut expanded = expanded.into_iter();
+        loop {
+            let records: Vec<Record> = expanded
+                .by_ref()
+                .take(self.split.chunk)
+                .collect();
+            if records.is_empty() {
+                break;
+            }
+            let worker = self.loads.idlest();
+            self.loads.add(worker, records.len());
+            tasks.push(ExpandTask { worker, records });
+        }
+        Ok(Split::Tasks(tasks))
+    }
+}
+
+/// Consume the tasks routed to the worker, whose records are no longer pending once consumed.
+pub struct TaskExpandOperator {
+    loads: Arc<WorkerLoads>,
+    local_index: usize,
+}
+
+impl FlatMapFunction<ExpandTask, Record> for TaskExpandOperator {
+    type Target = DynIter<Record>;
+
+    fn exec(&self, input: ExpandTask) -> FnResult<Self::Target> {
+        let load = input.records.len();
+        Ok(Box::new(PendingIter {
+            iter: Box::new(input.records.into_iter()),
+            loads: self.loads.clone(),
+            local_index: self.local_index,
+            load,
+        }))
+    }
+}
+
+/// The expanded records, whose adjacency is pending until they are all consumed.
+struct PendingIter {
+    iter: DynIter<Record>,
+    loads: Arc<WorkerLoads>,
+    local_index: usize,
+    load: usize,
+}
+
+impl Iterator for PendingIter {
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+impl Drop for PendingIter {
+    fn drop(&mut self) {
+        self.loads.sub(self.local_index, self.load);
+    }
+}
+
+/// The functions of the split expand, with the global index of the first local worker to route the
+/// tasks by.
+pub struct SplitExpand {
+    pub first_index: u64,
+    pub split: SplitExpandOperator,
+    pub expand: TaskExpandOperator,
+}
+
+impl SplitExpand {
+    /// Install the split expand, where only the tasks split are exchanged among the local workers.
+    pub fn install(self, stream: Stream<Record>) -> Result<Stream<Record>, BuildJobError> {
+        let SplitExpand { first_index, split, expand } = self;
+        let (local, tasks) = stream.branch("SplitExpand", |_info| {
+            move |input, local, tasks| {
+                input.for_each_batch(|batch| {
+                    let mut local_session = local.new_session(&batch.tag)?;
+                    let mut tasks_session = tasks.new_session(&batch.tag)?;
+                    for record in batch.drain() {
+                        match split.exec(record)? {
+                            Split::Local(iter) => local_session.give_iterator(iter)?,
+                            Split::Tasks(split_tasks) => {
+                                for task in split_tasks {
+                                    tasks_session.give(task)?;
+                                }
+                            }
+                        }
+                    }
+                    Ok(())
+                })
+            }
+        })?;
+        let split = tasks
+            .repartition(move |task| Ok(first_index + task.worker as u64))
+            .flat_map_with_name("TaskExpand", move |task| expand.exec(task))?;
+        local.merge(split)
+    }
+}
+
+pub trait SplitExpandFuncGen {
+    fn gen_split_expand(self, split: ExpandSplit) -> FnGenResult<SplitExpand>;
+}
+
+impl SplitExpandFuncGen for pb::EdgeExpand {
+    fn gen_split_expand(self, split: ExpandSplit) -> FnGenResult<SplitExpand> {
+        if split.chunk == 0 {
+            return Err(FnGenError::unsupported_error("split expand with the chunk of 0"));
+        }
+        let worker = pegasus::get_current_worker();
+        let loads = get_worker_loads(worker.job_id).ok_or_else(|| {
+            FnGenError::unsupported_error("split expand without the loads of the workers")
+        })?;
+        let local_peers = worker.local_peers.max(1);
+        let local_index = (worker.index % local_peers) as usize;
+        if log_enabled!(log::Level::Debug) && worker.index == 0 {
+            debug!("Runtime split expand operator with {:?}", split);
+        }
+        Ok(SplitExpand {
+            first_index: (worker.index - worker.index % local_peers) as u64,
+            split: SplitExpandOperator {
+                expand: self.gen_count_expand()?,
+                split,
+                loads: loads.clone(),
+                local_index,
+            },
+            expand: TaskExpandOperator { loads, local_index },
+        })
+    }
+}
+
+impl Encode for ExpandTask {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u64(self.worker as u64)?;
+        writer.write_u64(self.records.len() as u64)?;
+        for record in self.records.iter() {
+            record.write_to(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Decode for ExpandTask {
+    fn read_from<R: ReadExt>(reader: &mut R) -> io::Result<Self> {
+        let worker = reader.read_u64()? as usize;
+        let len = reader.read_u64()? as usize;
+        // the length is not trusted to allocate
+        let mut records = Vec::new();
+        for _ in 0..len {
+            records.push(Record::read_from(reader)?);
+        }
+        Ok(ExpandTask { worker, records })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::{DynDetails, Vertex, ID};
+
+    use super::*;
+    use crate::process::entry::Entry;
+    use crate::process::operator::tests::PERSON_LABEL;
+
+    /// Expand the vertex of id `n` to the vertices of ids `0..n`, counting the expansions.
+    #[derive(Default)]
+    struct CountingExpand {
+        expansions: Arc<AtomicUsize>,
+    }
+
+    impl FlatMapFunction<Record, Record> for CountingExpand {
+        type Target = DynIter<Record>;
+
+        fn exec(&self, input: Record) -> FnResult<Self::Target> {
+            self.expansions.fetch_add(1, Ordering::Relaxed);
+            let degree = input.get(None).unwrap().id();
+            Ok(Box::new((0..degree).map(|id| Record::new(vertex(id), None))))
+        }
+    }
+
+    impl CountExpand for CountingExpand {
+        fn count(&self, input: &Record, limit: usize) -> FnResult<Option<usize>> {
+            Ok(Some((input.get(None).unwrap().id() as usize).min(limit)))
+        }
+    }
+
+    fn vertex(id: ID) -> Vertex {
+        Vertex::new(id, Some(PERSON_LABEL), DynDetails::default())
+    }
+
+    fn split(loads: Arc<WorkerLoads>, expansions: Arc<AtomicUsize>) -> SplitExpandOperator {
+        SplitExpandOperator {
+            expand: Box::new(CountingExpand { expansions }),
+            split: ExpandSplit { threshold: 10, chunk: 4 },
+            loads,
+            local_index: 0,
+        }
+    }
+
+    fn ids(records: impl Iterator<Item = Record>) -> Vec<ID> {
+        records
+            .map(|record| record.get(None).unwrap().id())
+            .collect()
+    }
+
+    #[test]
+    fn split_expand_test() {
+        let loads = Arc::new(WorkerLoads::new(3));
+        let expansions = Arc::new(AtomicUsize::new(0));
+        let operator = split(loads.clone(), expansions.clone());
+        // the vertices within the threshold are expanded by the worker itself
+        let first = match operator
+            .exec(Record::new(vertex(3), None))
+            .unwrap()
+        {
+            Split::Local(iter) => iter,
+            Split::Tasks(_) => panic!("vertex 3 is split"),
+        };
+        let second = match operator
+            .exec(Record::new(vertex(6), None))
+            .unwrap()
+        {
+            Split::Local(iter) => iter,
+            Split::Tasks(_) => panic!("vertex 6 is split"),
+        };
+        assert_eq!(loads.get(0), 9);
+        assert_eq!(ids(first), vec![0, 1, 2]);
+        assert_eq!(loads.get(0), 6);
+        // over the threshold, the records are split to the idlest workers, expanded once
+        let tasks = match operator
+            .exec(Record::new(vertex(10), None))
+            .unwrap()
+        {
+            Split::Tasks(tasks) => tasks,
+            Split::Local(_) => panic!("vertex 10 is not split"),
+        };
+        let workers: Vec<usize> = tasks.iter().map(|task| task.worker).collect();
+        assert_eq!(workers, vec![1, 2, 1]);
+        assert_eq!((loads.get(0), loads.get(1), loads.get(2)), (6, 6, 4));
+        assert_eq!(expansions.load(Ordering::Relaxed), 3);
+        drop(second);
+        assert_eq!(loads.get(0), 0);
+    }
+
+    #[test]
+    fn split_tasks_test() {
+        let loads = Arc::new(WorkerLoads::new(2));
+        // the worker itself is busy, so all the tasks are sent to the other
+        loads.add(0, 100);
+        let operator = split(loads.clone(), Arc::default());
+        let expand = TaskExpandOperator { loads: loads.clone(), local_index: 1 };
+        let tasks = match operator
+            .exec(Record::new(vertex(11), None))
+            .unwrap()
+        {
+            Split::Tasks(tasks) => tasks,
+            Split::Local(_) => panic!("vertex 11 is not split"),
+        };
+        let mut expanded = vec![];
+        for task in tasks {
+            assert_eq!(task.worker, 1);
+            // the task is sent to the worker in the encoded form
+            let mut bytes = vec![];
+            task.write_to(&mut bytes).unwrap();
+            let task = ExpandTask::read_from(&mut &bytes[..]).unwrap();
+            expanded.extend(ids(expand.exec(task).unwrap()));
+        }
+        assert_eq!(expanded, (0..11).collect::<Vec<ID>>());
+        assert_eq!(loads.get(1), 0);
+    }
+}
@@ -0,0 +1,348 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The tree operator assembles the paths of the records into trees, one per root, e.g., `tree()`.
+//! The paths are keyed by their roots, so that the paths of a root are assembled by the worker the
+//! root is shuffled to, and each tree is sent as a record of its own, rather than all the trees in
+//! a single one, which is a nested map of the elements and their subtrees.
+//!
+//! A tree is sent as soon as it is full, i.e., of the maximum size, as the later paths of its root
+//! can't add any node to it, and those paths are dropped; the other trees are sent one after
+//! another as the scope is ended, when all the paths of their roots are assembled.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+
+use dyn_type::Object;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::KeyId;
+use pegasus::api::function::FnResult;
+use pegasus::api::{Pair, PartitionByKey, Unary};
+use pegasus::stream::Stream;
+use pegasus::tag::tools::map::TidyTagMap;
+use pegasus::BuildJobError;
+
+use crate::error::{FnExecError, FnGenResult};
+use crate::process::entry::{DynEntry, Entry, MapEntry, PairEntry};
+use crate::process::operator::TagKey;
+use crate::process::record::{Record, RecordKey};
+
+#[derive(Debug)]
+pub struct TreeOperator {
+    /// The levels of the paths from the root, or the elements of the path of the head if empty
+    levels: Vec<TagKey>,
+    max_size: usize,
+    alias: Option<KeyId>,
+}
+
+impl TreeOperator {
+    /// Get the path of the record keyed by its root, which is none without the root. The path ends
+    /// right before the first level the record doesn't have.
+    pub fn get_kv(&self, input: Record) -> FnResult<Option<(RecordKey, Vec<DynEntry>)>> {
+        let path: Vec<DynEntry> = if self.levels.is_empty() {
+            let head = match input.get(None) {
+                Some(head) => head,
+                None => return Ok(None),
+            };
+            match head
+                .as_graph_path()
+                .and_then(|path| path.get_path())
+            {
+                Some(path) => path
+                    .iter()
+                    .cloned()
+                    .map(DynEntry::from)
+                    .collect(),
+                None => Err(FnExecError::unexpected_data_error(&format!(
+                    "the elements of the path are not kept in {:?} for the tree",
+                    head
+                )))?,
+            }
+        } else {
+            let mut path = Vec::with_capacity(self.levels.len());
+            for level in self.levels.iter() {
+                let entry = level.get_arc_entry(&input)?;
+                if entry.as_object() == Some(&Object::None) {
+                    break;
+                }
+                path.push(entry);
+            }
+            path
+        };
+        Ok(path
+            .first()
+            .cloned()
+            .map(|root| (RecordKey::new(vec![root]), path)))
+    }
+
+    pub fn get_max_size(&self) -> usize {
+        self.max_size
+    }
+
+    pub fn get_alias(&self) -> Option<KeyId> {
+        self.alias
+    }
+}
+
+/// A node of the tree, whose children are kept in the order they're added.
+#[derive(Clone, Debug, Default)]
+struct TreeNode {
+    children: Vec<(DynEntry, TreeNode)>,
+    indices: HashMap<DynEntry, usize>,
+}
+
+impl TreeNode {
+    fn into_entry(self) -> DynEntry {
+        let inner = self
+            .children
+            .into_iter()
+            .map(|(element, child)| PairEntry::new(element, child.into_entry()))
+            .collect();
+        DynEntry::new(MapEntry { inner })
+    }
+}
+
+/// The tree assembled from the paths of a root, with the number of its nodes.
+#[derive(Clone, Debug, Default)]
+pub struct PathTree {
+    top: TreeNode,
+    size: usize,
+}
+
+impl PathTree {
+    /// Whether the tree is of the maximum size, so that no more node is added to it.
+    pub fn is_full(&self, max_size: usize) -> bool {
+        self.size >= max_size
+    }
+
+    /// Add the path to the tree, where the nodes of the path beyond the maximum size are dropped.
+    pub fn add_path(&mut self, path: Vec<DynEntry>, max_size: usize) {
+        let mut node = &mut self.top;
+        for element in path {
+            let index = match node.indices.get(&element) {
+                Some(index) => *index,
+                None if self.size < max_size => {
+                    self.size += 1;
+                    node.indices
+                        .insert(element.clone(), node.children.len());
+                    node.children
+                        .push((element, TreeNode::default()));
+                    node.children.len() - 1
+                }
+                None => return,
+            };
+            node = &mut node.children[index].1;
+        }
+    }
+
+    /// The record of the tree, i.e., the map from the root to its subtree.
+    pub fn into_record(self, alias: Option<KeyId>) -> Record {
+        let mut record = Record::default();
+        record.append_arc_entry(self.top.into_entry(), alias);
+        record
+    }
+}
+
+/// The trees being assembled by a worker in a scope, with the roots of the trees already sent.
+#[derive(Default)]
+struct RootTrees {
+    trees: HashMap<RecordKey, PathTree>,
+    sent: HashSet<RecordKey>,
+}
+
+impl RootTrees {
+    /// Add the path to the tree of the root, which is taken once the tree is full.
+    fn add_path(&mut self, root: RecordKey, path: Vec<DynEntry>, max_size: usize) -> Option<PathTree> {
+        if self.sent.contains(&root) {
+            return None;
+        }
+        let tree = self.trees.entry(root.clone()).or_default();
+        tree.add_path(path, max_size);
+        if tree.is_full(max_size) {
+            self.sent.insert(root.clone());
+            self.trees.remove(&root)
+        } else {
+            None
+        }
+    }
+}
+
+/// Assemble the paths keyed by their roots into the trees, which are sent as the records of the
+/// `alias`, each as soon as it is full, or else as the scope is ended.
+pub fn assemble_trees(
+    stream: Stream<Pair<RecordKey, Vec<DynEntry>>>, max_size: usize, alias: Option<KeyId>,
+) -> Result<Stream<Record>, BuildJobError> {
+    stream
+        .partition_by_key()?
+        .unary("tree", move |info| {
+            let mut table = TidyTagMap::<RootTrees>::new(info.scope_level);
+            move |input, output| {
+                input.for_each_batch(|batch| {
+                    if !batch.is_empty() {
+                        let mut session = output.new_session(&batch.tag)?;
+                        let trees = table.get_mut_or_else(&batch.tag, RootTrees::default);
+                        for pair in batch.drain() {
+                            let (root, path) = pair.take();
+                            if let Some(tree) = trees.add_path(root, path, max_size) {
+                                session.give(tree.into_record(alias))?;
+                            }
+                        }
+                    }
+                    if batch.is_last() {
+                        if let Some(trees) = table.remove(&batch.tag) {
+                            let mut session = output.new_session(&batch.tag)?;
+                            let records = trees
+                                .trees
+                                .into_iter()
+                                .map(move |(_, tree)| tree.into_record(alias));
+                            session.give_iterator(records)?;
+                        }
+                    }
+                    Ok(())
+                })
+            }
+        })
+}
+
+pub trait TreeFuncGen {
+    fn gen_tree(self) -> FnGenResult<TreeOperator>;
+}
+
+impl TreeFuncGen for algebra_pb::Tree {
+    fn gen_tree(self) -> FnGenResult<TreeOperator> {
+        let levels = self
+            .levels
+            .into_iter()
+            .map(|level| level.try_into())
+            .collect::<Result<Vec<TagKey>, _>>()?;
+        let max_size = if self.max_size > 0 { self.max_size as usize } else { usize::MAX };
+        let alias: Option<KeyId> = self
+            .alias
+            .map(|alias| alias.try_into())
+            .transpose()?;
+        let tree = TreeOperator { levels, max_size, alias };
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!("Runtime tree operator {:?}", tree);
+        }
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::Element;
+    use ir_common::generated::common as common_pb;
+    use pegasus_common::downcast::AsAny;
+
+    use super::*;
+    use crate::process::operator::tests::{
+        init_source_with_multi_tags, init_vertex1, init_vertex2, to_var_pb, TAG_A, TAG_B, TAG_C,
+    };
+
+    fn gen_tree(levels: Vec<common_pb::Variable>, max_size: i32) -> TreeOperator {
+        algebra_pb::Tree { levels, max_size, alias: None }
+            .gen_tree()
+            .unwrap()
+    }
+
+    // flatten the tree as the pairs of the elements and the numbers of their children, in pre-order
+    fn flatten(entry: &DynEntry, nodes: &mut Vec<(DynEntry, usize)>) {
+        let children = entry
+            .as_any_ref()
+            .downcast_ref::<MapEntry>()
+            .unwrap();
+        for pair in children.inner.iter() {
+            nodes.push((pair.get_left().clone(), pair.get_right().len()));
+            flatten(pair.get_right(), nodes);
+        }
+    }
+
+    // g.V().as('a').out().as('b').select('a', 'b').tree()
+    #[test]
+    fn tree_levels_test() {
+        let tree =
+            gen_tree(vec![to_var_pb(Some(TAG_A.into()), None), to_var_pb(Some(TAG_B.into()), None)], 0);
+        let (key, path) = tree
+            .get_kv(init_source_with_multi_tags().remove(0))
+            .unwrap()
+            .unwrap();
+        let (v1, v2) = (DynEntry::new(init_vertex1()), DynEntry::new(init_vertex2()));
+        assert_eq!(key, RecordKey::new(vec![v1.clone()]));
+        assert_eq!(path, vec![v1.clone(), v2.clone()]);
+
+        // the path ends right before the missing level, and is none without the root
+        let tree =
+            gen_tree(vec![to_var_pb(Some(TAG_A.into()), None), to_var_pb(Some(TAG_C.into()), None)], 0);
+        let (_, path) = tree
+            .get_kv(init_source_with_multi_tags().remove(0))
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, vec![v1]);
+        let tree = gen_tree(vec![to_var_pb(Some(TAG_C.into()), None)], 0);
+        assert!(tree
+            .get_kv(init_source_with_multi_tags().remove(0))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn tree_add_path_test() {
+        let entry = |id: i32| DynEntry::new(object!(id));
+        let mut tree = PathTree::default();
+        tree.add_path(vec![entry(1), entry(2), entry(3)], usize::MAX);
+        tree.add_path(vec![entry(1), entry(2), entry(4)], usize::MAX);
+        tree.add_path(vec![entry(1), entry(5)], usize::MAX);
+        let record = tree.clone().into_record(None);
+        let mut nodes = vec![];
+        flatten(record.get(None).unwrap(), &mut nodes);
+        assert_eq!(nodes, vec![(entry(1), 2), (entry(2), 2), (entry(3), 0), (entry(4), 0), (entry(5), 0)]);
+
+        // the nodes beyond the maximum size are dropped
+        let mut tree = PathTree::default();
+        tree.add_path(vec![entry(1), entry(2), entry(3)], 4);
+        tree.add_path(vec![entry(1), entry(5), entry(6)], 4);
+        tree.add_path(vec![entry(1), entry(2), entry(3)], 4);
+        let record = tree.into_record(None);
+        let mut nodes = vec![];
+        flatten(record.get(None).unwrap(), &mut nodes);
+        assert_eq!(nodes, vec![(entry(1), 2), (entry(2), 1), (entry(3), 0), (entry(5), 0)]);
+    }
+
+    #[test]
+    fn tree_sent_once_full_test() {
+        let entry = |id: i32| DynEntry::new(object!(id));
+        let root = |id: i32| RecordKey::new(vec![entry(id)]);
+        let mut trees = RootTrees::default();
+        assert!(trees
+            .add_path(root(1), vec![entry(1), entry(2)], 3)
+            .is_none());
+        assert!(trees
+            .add_path(root(7), vec![entry(7), entry(8)], 3)
+            .is_none());
+        // the tree of the root 1 is sent once full, before the end of the scope
+        let tree = trees
+            .add_path(root(1), vec![entry(1), entry(3)], 3)
+            .unwrap();
+        let mut nodes = vec![];
+        flatten(tree.into_record(None).get(None).unwrap(), &mut nodes);
+        assert_eq!(nodes, vec![(entry(1), 2), (entry(2), 0), (entry(3), 0)]);
+        // and the later paths of the root are dropped, rather than sent as another tree
+        assert!(trees
+            .add_path(root(1), vec![entry(1), entry(4)], 3)
+            .is_none());
+        assert_eq!(trees.trees.len(), 1);
+        assert!(trees.trees.contains_key(&root(7)));
+    }
+}
@@ -0,0 +1,244 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::KeyId;
+use pegasus::api::function::{FnResult, MapFunction};
+
+use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
+use crate::process::entry::{CollectionEntry, DynEntry};
+use crate::process::operator::accum::accumulator::Accumulator;
+use crate::process::record::Record;
+use crate::session::SessionRegistry;
+
+/// Bind the entries of the tag in all the records to the variable of the session.
+pub struct StoreVarOperator {
+    tag: Option<KeyId>,
+    name: String,
+    session: String,
+    registry: Arc<SessionRegistry>,
+}
+
+impl StoreVarOperator {
+    pub fn get_entry(&self, input: &Record) -> FnResult<DynEntry> {
+        let entry = input.get(self.tag).ok_or_else(|| {
+            FnExecError::get_tag_error(&format!(
+                "tag {:?} of variable `{}` is not found in {:?}",
+                self.tag, self.name, input
+            ))
+        })?;
+        Ok(entry.clone())
+    }
+
+    /// Collect the entries into a collection, which is bounded in size as the variables.
+    pub fn gen_accum(&self) -> StoreVarAccum {
+        StoreVarAccum {
+            name: self.name.clone(),
+            max_entries: self.registry.max_entries(),
+            collection: CollectionEntry::default(),
+        }
+    }
+
+    /// Bind the collection in the registry of the process, which outputs nothing.
+    pub fn bind(&self, collection: CollectionEntry) -> FnResult<Option<Record>> {
+        self.registry
+            .bind(&self.session, &self.name, collection)?;
+        Ok(None)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StoreVarAccum {
+    name: String,
+    max_entries: usize,
+    collection: CollectionEntry,
+}
+
+impl Accumulator<DynEntry, CollectionEntry> for StoreVarAccum {
+    fn accum(&mut self, next: DynEntry) -> FnExecResult<()> {
+        // fail early rather than collecting all the entries
+        if self.collection.inner.len() >= self.max_entries {
+            Err(FnExecError::session_error(&format!(
+                "variable `{}` exceeds the limit {}",
+                self.name, self.max_entries
+            )))?
+        }
+        self.collection.inner.push(next);
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> FnExecResult<CollectionEntry> {
+        Ok(std::mem::take(&mut self.collection))
+    }
+}
+
+/// Append the collection bound to the variable of the session to each record.
+struct LoadVarOperator {
+    collection: CollectionEntry,
+    alias: Option<KeyId>,
+}
+
+impl MapFunction<Record, Record> for LoadVarOperator {
+    fn exec(&self, mut input: Record) -> FnResult<Record> {
+        input.append(self.collection.clone(), self.alias);
+        Ok(input)
+    }
+}
+
+pub trait StoreVarFuncGen {
+    fn gen_store_var(self, registry: Option<Arc<SessionRegistry>>) -> FnGenResult<StoreVarOperator>;
+}
+
+impl StoreVarFuncGen for algebra_pb::StoreVar {
+    fn gen_store_var(self, registry: Option<Arc<SessionRegistry>>) -> FnGenResult<StoreVarOperator> {
+        let registry =
+            registry.ok_or_else(|| FnGenError::unsupported_error("session variables are not enabled"))?;
+        if self.session.is_empty() {
+            Err(FnGenError::session_error(&format!("variable `{}` is stored out of a session", self.name)))?
+        }
+        let tag: Option<KeyId> = self.tag.map(|tag| tag.try_into()).transpose()?;
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!("Runtime store_var operator with name {:?}, tag {:?}", self.name, tag);
+        }
+        Ok(StoreVarOperator { tag, name: self.name, session: self.session, registry })
+    }
+}
+
+pub trait LoadVarFuncGen {
+    fn gen_load_var(
+        self, registry: Option<Arc<SessionRegistry>>,
+    ) -> FnGenResult<Box<dyn MapFunction<Record, Record>>>;
+}
+
+impl LoadVarFuncGen for algebra_pb::LoadVar {
+    fn gen_load_var(
+        self, registry: Option<Arc<SessionRegistry>>,
+    ) -> FnGenResult<Box<dyn MapFunction<Record, Record>>> {
+        let registry =
+            registry.ok_or_else(|| FnGenError::unsupported_error("session variables are not enabled"))?;
+        if self.session.is_empty() {
+            Err(FnGenError::session_error(&format!("variable `{}` is loaded out of a session", self.name)))?
+        }
+        // the variable is bound by the previous jobs of the session, and is loaded once for the job
+        let collection = registry
+            .get(&self.session, &self.name)
+            .map_err(|e| FnGenError::session_error(&e.to_string()))?
+            .ok_or_else(|| FnGenError::session_error(&format!("variable `{}` is not bound", self.name)))?;
+        let alias: Option<KeyId> = self
+            .alias
+            .map(|alias| alias.try_into())
+            .transpose()?;
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!(
+                "Runtime load_var operator with name {:?} of {} entries, alias {:?}",
+                self.name,
+                collection.inner.len(),
+                alias
+            );
+        }
+        Ok(Box::new(LoadVarOperator { collection, alias }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use graph_proxy::apis::GraphElement;
+    use ir_common::generated::algebra as algebra_pb;
+    use pegasus::api::function::MapFunction;
+
+    use super::{LoadVarFuncGen, StoreVarOperator};
+    use crate::process::entry::{CollectionEntry, DynEntry, Entry};
+    use crate::process::operator::accum::accumulator::Accumulator;
+    use crate::process::operator::tests::{init_source, init_vertex1};
+    use crate::process::record::Record;
+    use crate::session::SessionRegistry;
+
+    fn store_var_operator(registry: Arc<SessionRegistry>) -> StoreVarOperator {
+        StoreVarOperator { tag: None, name: "x".to_string(), session: "s1".to_string(), registry }
+    }
+
+    // g.V().store('x')
+    #[test]
+    fn store_var_test() {
+        let registry = Arc::new(SessionRegistry::new(10, Duration::from_secs(60)));
+        let store_var = store_var_operator(registry.clone());
+        let mut accum = store_var.gen_accum();
+        for record in init_source() {
+            accum
+                .accum(store_var.get_entry(&record).unwrap())
+                .unwrap();
+        }
+        let collection = accum.finalize().unwrap();
+        assert_eq!(collection.inner.len(), 2);
+        assert!(store_var.bind(collection).unwrap().is_none());
+        let bound = registry.get("s1", "x").unwrap().unwrap();
+        let ids: Vec<i64> = bound
+            .inner
+            .iter()
+            .map(|entry| entry.as_vertex().unwrap().id())
+            .collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn store_var_exceeding_limit_test() {
+        let registry = Arc::new(SessionRegistry::new(1, Duration::from_secs(60)));
+        let store_var = store_var_operator(registry);
+        let mut accum = store_var.gen_accum();
+        let mut results = init_source()
+            .into_iter()
+            .map(|record| accum.accum(store_var.get_entry(&record).unwrap()));
+        assert!(results.next().unwrap().is_ok());
+        assert!(results.next().unwrap().is_err());
+    }
+
+    // g.V().cap('x')
+    #[test]
+    fn load_var_test() {
+        let registry = Arc::new(SessionRegistry::new(10, Duration::from_secs(60)));
+        let collection =
+            CollectionEntry { inner: vec![DynEntry::new(object!(1)), DynEntry::new(object!(2))] };
+        registry
+            .bind("s1", "x", collection.clone())
+            .unwrap();
+        let load_var =
+            algebra_pb::LoadVar { name: "x".to_string(), alias: Some(0.into()), session: "s1".to_string() };
+        let func = load_var
+            .clone()
+            .gen_load_var(Some(registry.clone()))
+            .unwrap();
+        let record = func
+            .exec(Record::new(init_vertex1(), None))
+            .unwrap();
+        let loaded = record
+            .get(Some(0))
+            .unwrap()
+            .as_any_ref()
+            .downcast_ref::<CollectionEntry>()
+            .unwrap();
+        assert_eq!(loaded, &collection);
+
+        // a variable of another session is not visible
+        let mut other = load_var;
+        other.session = "s2".to_string();
+        assert!(other.gen_load_var(Some(registry)).is_err());
+    }
+}
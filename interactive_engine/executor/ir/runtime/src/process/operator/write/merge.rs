@@ -0,0 +1,237 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::convert::TryInto;
+use std::sync::{Arc, Mutex};
+
+use graph_proxy::apis::{get_write_graph, WriteGraphProxy};
+use graph_proxy::utils::expr::eval::Evaluator;
+use ir_common::error::ParsePbError;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::{KeyId, LabelId, NameOrId};
+use pegasus::api::function::{FnResult, MapFunction};
+
+use crate::error::{FnExecError, FnGenError, FnGenResult};
+use crate::process::operator::map::MapFuncGen;
+use crate::process::operator::write::{eval_properties, get_vertex_id, parse_property_sets};
+use crate::process::record::Record;
+
+/// Match the vertex or the edge of the properties, or create it if not matched, and append it to the
+/// record, as the MERGE clause of Cypher.
+struct MergeOperator {
+    kind: algebra_pb::merge::Kind,
+    label: LabelId,
+    src_tag: Option<KeyId>,
+    dst_tag: Option<KeyId>,
+    properties: Vec<(NameOrId, Evaluator)>,
+    on_create: Vec<(NameOrId, Evaluator)>,
+    on_match: Vec<(NameOrId, Evaluator)>,
+    alias: Option<KeyId>,
+    graph: Arc<Mutex<dyn WriteGraphProxy>>,
+}
+
+impl MapFunction<Record, Record> for MergeOperator {
+    fn exec(&self, mut input: Record) -> FnResult<Record> {
+        let properties = eval_properties(&input, &self.properties)?;
+        let on_create = eval_properties(&input, &self.on_create)?;
+        let on_match = eval_properties(&input, &self.on_match)?;
+        // the match and the creation are done by the graph atomically, per record
+        match self.kind {
+            algebra_pb::merge::Kind::Vertex => {
+                let (vertex, _) = self
+                    .graph
+                    .lock()
+                    .map_err(|e| FnExecError::unexpected_data_error(&format!("{:?}", e)))?
+                    .merge_vertex(self.label, properties, on_create, on_match)?;
+                input.append(vertex, self.alias);
+            }
+            algebra_pb::merge::Kind::Edge => {
+                let src_id = get_vertex_id(&input, self.src_tag)?;
+                let dst_id = get_vertex_id(&input, self.dst_tag)?;
+                let (edge, _) = self
+                    .graph
+                    .lock()
+                    .map_err(|e| FnExecError::unexpected_data_error(&format!("{:?}", e)))?
+                    .merge_edge(self.label, src_id, dst_id, properties, on_create, on_match)?;
+                input.append(edge, self.alias);
+            }
+        }
+        Ok(input)
+    }
+}
+
+impl MapFuncGen for algebra_pb::Merge {
+    fn gen_map(self) -> FnGenResult<Box<dyn MapFunction<Record, Record>>> {
+        let graph = get_write_graph().ok_or_else(|| FnGenError::NullGraphError)?;
+        let kind = algebra_pb::merge::Kind::from_i32(self.kind)
+            .ok_or_else(|| ParsePbError::from(format!("invalid Merge kind {}", self.kind)))?;
+        let label: LabelId = self
+            .label
+            .ok_or_else(|| ParsePbError::EmptyFieldError("label in Merge".to_string()))?
+            .try_into()?;
+        let src_tag: Option<KeyId> = self
+            .src_tag
+            .map(|tag| tag.try_into())
+            .transpose()?;
+        let dst_tag: Option<KeyId> = self
+            .dst_tag
+            .map(|tag| tag.try_into())
+            .transpose()?;
+        let alias: Option<KeyId> = self
+            .alias
+            .map(|alias| alias.try_into())
+            .transpose()?;
+        let merge_operator = MergeOperator {
+            kind,
+            label,
+            src_tag,
+            dst_tag,
+            properties: parse_property_sets(self.properties)?,
+            on_create: parse_property_sets(self.on_create)?,
+            on_match: parse_property_sets(self.on_match)?,
+            alias,
+            graph,
+        };
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!(
+                "Runtime merge operator with kind {:?}, label {:?}, src_tag {:?}, dst_tag {:?}, properties {:?}, on_create {:?}, on_match {:?}, alias {:?}",
+                kind,
+                label,
+                src_tag,
+                dst_tag,
+                merge_operator.properties,
+                merge_operator.on_create,
+                merge_operator.on_match,
+                alias
+            );
+        }
+        Ok(Box::new(merge_operator))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use graph_proxy::apis::GraphElement;
+    use ir_common::generated::algebra as algebra_pb;
+    use pegasus::api::function::MapFunction;
+
+    use super::MergeOperator;
+    use crate::process::entry::Entry;
+    use crate::process::operator::tests::{init_vertex1, init_vertex2, PERSON_LABEL};
+    use crate::process::operator::write::tests::{get_property, property, TestWriteGraph, KNOWS_LABEL};
+    use crate::process::record::Record;
+
+    fn merge_vertex_operator(graph: Arc<Mutex<TestWriteGraph>>) -> MergeOperator {
+        MergeOperator {
+            kind: algebra_pb::merge::Kind::Vertex,
+            label: PERSON_LABEL,
+            src_tag: None,
+            dst_tag: None,
+            properties: vec![property("id", "@.id + 10"), property("name", "@.name")],
+            on_create: vec![property("created", "true")],
+            on_match: vec![property("matched", "true")],
+            alias: Some(0),
+            graph,
+        }
+    }
+
+    // MERGE (b:person {id: a.id + 10, name: a.name}) ON CREATE SET b.created = true
+    // ON MATCH SET b.matched = true
+    #[test]
+    fn merge_vertex_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        let merge = merge_vertex_operator(graph.clone());
+
+        let record = merge
+            .exec(Record::new(init_vertex1(), None))
+            .unwrap();
+        let vertex = record
+            .get(Some(0))
+            .unwrap()
+            .as_vertex()
+            .unwrap();
+        assert_eq!(vertex.id(), 11);
+        assert_eq!(get_property(vertex, "name"), Some(object!("marko")));
+        assert_eq!(get_property(vertex, "created"), Some(object!(true)));
+        assert_eq!(get_property(vertex, "matched"), None);
+
+        let record = merge
+            .exec(Record::new(init_vertex1(), None))
+            .unwrap();
+        let vertex = record
+            .get(Some(0))
+            .unwrap()
+            .as_vertex()
+            .unwrap();
+        assert_eq!(vertex.id(), 11);
+        assert_eq!(get_property(vertex, "matched"), Some(object!(true)));
+
+        let record = merge
+            .exec(Record::new(init_vertex2(), None))
+            .unwrap();
+        let vertex = record
+            .get(Some(0))
+            .unwrap()
+            .as_vertex()
+            .unwrap();
+        assert_eq!(vertex.id(), 12);
+        assert_eq!(get_property(vertex, "matched"), None);
+        assert_eq!(graph.lock().unwrap().vertices.len(), 2);
+    }
+
+    // MERGE (a)-[e:knows {weight: 0.5}]->(b) ON CREATE SET e.created = true ON MATCH SET e.matched = true
+    #[test]
+    fn merge_edge_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        let merge = MergeOperator {
+            kind: algebra_pb::merge::Kind::Edge,
+            label: KNOWS_LABEL,
+            src_tag: Some(0),
+            dst_tag: Some(1),
+            properties: vec![property("weight", "0.5")],
+            on_create: vec![property("created", "true")],
+            on_match: vec![property("matched", "true")],
+            alias: Some(2),
+            graph: graph.clone(),
+        };
+        let mut record = Record::new(init_vertex1(), Some(0));
+        record.append(init_vertex2(), Some(1));
+
+        let merged = merge.exec(record.clone()).unwrap();
+        let edge = merged.get(Some(2)).unwrap().as_edge().unwrap();
+        assert_eq!((edge.src_id, edge.dst_id), (1, 2));
+        assert_eq!(get_property(edge, "weight"), Some(object!(0.5)));
+        assert_eq!(get_property(edge, "created"), Some(object!(true)));
+
+        let merged = merge.exec(record).unwrap();
+        let edge = merged.get(Some(2)).unwrap().as_edge().unwrap();
+        assert_eq!(get_property(edge, "matched"), Some(object!(true)));
+        assert_eq!(graph.lock().unwrap().edges.len(), 1);
+    }
+
+    #[test]
+    fn merge_edge_missing_vertex_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        let mut merge = merge_vertex_operator(graph.clone());
+        merge.kind = algebra_pb::merge::Kind::Edge;
+        merge.src_tag = Some(0);
+        merge.dst_tag = Some(1);
+        let record = Record::new(init_vertex1(), Some(0));
+        assert!(merge.exec(record).is_err());
+        assert!(graph.lock().unwrap().edges.is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The operators writing into the graph, which is registered by `register_write_graph()` of the
+//! graph proxy.
+
+mod merge;
+mod mutate;
+
+use std::convert::TryFrom;
+
+use ahash::{HashMap, HashMapExt};
+use graph_proxy::apis::{DynDetails, GraphElement, ID};
+use graph_proxy::utils::expr::eval::{Evaluate, Evaluator};
+use ir_common::error::ParsePbError;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::{KeyId, NameOrId};
+pub use mutate::{MutateAccum, MutateFuncGen};
+
+use crate::error::{FnExecError, FnExecResult, FnGenResult};
+use crate::process::entry::{DynEntry, Entry};
+use crate::process::record::Record;
+
+/// Parse the properties to write, with their values to evaluate against the records.
+fn parse_property_sets(
+    property_sets: Vec<algebra_pb::merge::PropertySet>,
+) -> FnGenResult<Vec<(NameOrId, Evaluator)>> {
+    let mut properties = Vec::with_capacity(property_sets.len());
+    for property_set in property_sets {
+        let key = property_set
+            .key
+            .ok_or_else(|| ParsePbError::EmptyFieldError("key of PropertySet".to_string()))?;
+        let value = property_set
+            .value
+            .ok_or_else(|| ParsePbError::EmptyFieldError("value of PropertySet".to_string()))?;
+        properties.push((NameOrId::try_from(key)?, Evaluator::try_from(value)?));
+    }
+    Ok(properties)
+}
+
+/// Evaluate the values of the properties to write against the record.
+fn eval_properties(input: &Record, properties: &[(NameOrId, Evaluator)]) -> FnExecResult<DynDetails> {
+    let mut details = HashMap::with_capacity(properties.len());
+    for (key, value) in properties {
+        let value = value.eval::<DynEntry, Record>(Some(input))?;
+        details.insert(key.clone(), value);
+    }
+    Ok(DynDetails::new(details))
+}
+
+/// Get the id of the vertex referred by the tag in the record.
+fn get_vertex_id(input: &Record, tag: Option<KeyId>) -> FnExecResult<ID> {
+    input
+        .get(tag)
+        .and_then(|entry| entry.as_vertex())
+        .map(|vertex| vertex.id())
+        .ok_or_else(|| {
+            FnExecError::unexpected_data_error(&format!("tag {:?} is not a vertex in {:?}", tag, input))
+        })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::convert::TryFrom;
+
+    use ahash::HashMap;
+    use dyn_type::Object;
+    use graph_proxy::apis::graph::PKV;
+    use graph_proxy::apis::{
+        Details, DynDetails, Edge, GraphElement, Mutated, Mutation, Vertex, WriteGraphProxy, ID,
+    };
+    use graph_proxy::utils::expr::eval::Evaluator;
+    use graph_proxy::{GraphProxyError, GraphProxyResult};
+    use ir_common::expr_parse::str_to_expr_pb;
+    use ir_common::{LabelId, NameOrId};
+
+    pub const KNOWS_LABEL: LabelId = 1;
+
+    /// A graph that identifies the vertices by the property `id`, and the edges by their endpoints.
+    #[derive(Default)]
+    pub struct TestWriteGraph {
+        pub vertices: HashMap<ID, HashMap<NameOrId, Object>>,
+        pub edges: HashMap<(ID, ID), HashMap<NameOrId, Object>>,
+    }
+
+    fn update(properties: &mut HashMap<NameOrId, Object>, details: DynDetails) {
+        if let Some(mut new_properties) = details.get_all_properties() {
+            properties.extend(new_properties.drain());
+        }
+    }
+
+    fn get_id(properties: &DynDetails) -> GraphProxyResult<ID> {
+        properties
+            .get_property(&"id".into())
+            .and_then(|id| id.try_to_owned())
+            .and_then(|id| id.as_i64().ok())
+            .ok_or_else(|| GraphProxyError::write_graph_error("the primary key `id` is missing"))
+    }
+
+    fn to_edge(label: LabelId, src_id: ID, dst_id: ID, properties: &HashMap<NameOrId, Object>) -> Edge {
+        let details = DynDetails::new(properties.clone());
+        Edge::new(src_id << 8 | dst_id, Some(label), src_id, dst_id, details)
+    }
+
+    impl TestWriteGraph {
+        fn apply(&mut self, mutation: Mutation) -> GraphProxyResult<Mutated> {
+            match mutation {
+                Mutation::AddVertex(label, properties) => {
+                    let id = get_id(&properties)?;
+                    if self.vertices.contains_key(&id) {
+                        Err(GraphProxyError::write_graph_error(&format!("vertex {} exists", id)))?
+                    }
+                    let vertex_properties = self.vertices.entry(id).or_default();
+                    update(vertex_properties, properties);
+                    let details = DynDetails::new(vertex_properties.clone());
+                    Ok(Mutated::Vertex(Vertex::new(id, Some(label), details)))
+                }
+                Mutation::AddEdge(label, src_id, dst_id, properties) => {
+                    let edge_properties = self.edges.entry((src_id, dst_id)).or_default();
+                    update(edge_properties, properties);
+                    Ok(Mutated::Edge(to_edge(label, src_id, dst_id, edge_properties)))
+                }
+                Mutation::SetVertexProperties(vertex, properties) => {
+                    let vertex_properties = self
+                        .vertices
+                        .get_mut(&vertex.id())
+                        .ok_or_else(|| {
+                            GraphProxyError::write_graph_error(&format!("vertex {} not found", vertex.id()))
+                        })?;
+                    update(vertex_properties, properties);
+                    let details = DynDetails::new(vertex_properties.clone());
+                    Ok(Mutated::Vertex(Vertex::new(vertex.id(), vertex.label(), details)))
+                }
+                Mutation::SetEdgeProperties(edge, properties) => {
+                    let edge_properties = self
+                        .edges
+                        .get_mut(&(edge.src_id, edge.dst_id))
+                        .ok_or_else(|| {
+                            GraphProxyError::write_graph_error(&format!("edge {} not found", edge.id()))
+                        })?;
+                    update(edge_properties, properties);
+                    let label = edge.label().unwrap_or(KNOWS_LABEL);
+                    Ok(Mutated::Edge(to_edge(label, edge.src_id, edge.dst_id, edge_properties)))
+                }
+                Mutation::DropVertex(vertex) => {
+                    let id = vertex.id();
+                    self.vertices.remove(&id);
+                    self.edges
+                        .retain(|(src_id, dst_id), _| *src_id != id && *dst_id != id);
+                    Ok(Mutated::Dropped)
+                }
+                Mutation::DropEdge(edge) => {
+                    self.edges.remove(&(edge.src_id, edge.dst_id));
+                    Ok(Mutated::Dropped)
+                }
+            }
+        }
+    }
+
+    impl WriteGraphProxy for TestWriteGraph {
+        fn add_vertex(&mut self, _: LabelId, _: PKV, _: DynDetails) -> GraphProxyResult<()> {
+            Err(GraphProxyError::unsupported_error("add_vertex() of the test graph"))
+        }
+
+        fn add_edge(
+            &mut self, _: LabelId, _: LabelId, _: PKV, _: LabelId, _: PKV, _: DynDetails,
+        ) -> GraphProxyResult<()> {
+            Err(GraphProxyError::unsupported_error("add_edge() of the test graph"))
+        }
+
+        fn finish(&mut self) -> GraphProxyResult<()> {
+            Ok(())
+        }
+
+        fn merge_vertex(
+            &mut self, label: LabelId, properties: DynDetails, on_create: DynDetails, on_match: DynDetails,
+        ) -> GraphProxyResult<(Vertex, bool)> {
+            let id = get_id(&properties)?;
+            let vertex_properties = self.vertices.entry(id).or_default();
+            let created = vertex_properties.is_empty();
+            update(vertex_properties, properties);
+            update(vertex_properties, if created { on_create } else { on_match });
+            let details = DynDetails::new(vertex_properties.clone());
+            Ok((Vertex::new(id, Some(label), details), created))
+        }
+
+        fn merge_edge(
+            &mut self, label: LabelId, src_id: ID, dst_id: ID, properties: DynDetails,
+            on_create: DynDetails, on_match: DynDetails,
+        ) -> GraphProxyResult<(Edge, bool)> {
+            let edge_properties = self.edges.entry((src_id, dst_id)).or_default();
+            let created = edge_properties.is_empty();
+            update(edge_properties, properties);
+            update(edge_properties, if created { on_create } else { on_match });
+            Ok((to_edge(label, src_id, dst_id, edge_properties), created))
+        }
+
+        fn mutate(&mut self, mutations: Vec<Mutation>) -> GraphProxyResult<Vec<GraphProxyResult<Mutated>>> {
+            Ok(mutations
+                .into_iter()
+                .map(|mutation| self.apply(mutation))
+                .collect())
+        }
+    }
+
+    pub fn property(key: &str, value: &str) -> (NameOrId, Evaluator) {
+        let expr = str_to_expr_pb(value.to_string()).unwrap();
+        (key.into(), Evaluator::try_from(expr).unwrap())
+    }
+
+    pub fn get_property(element: &impl GraphElement, key: &str) -> Option<Object> {
+        element
+            .get_property(&key.into())
+            .map(|value| value.try_to_owned().unwrap())
+    }
+}
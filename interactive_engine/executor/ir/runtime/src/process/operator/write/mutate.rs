@@ -0,0 +1,641 @@
+//
+//! Copyright 2021 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::convert::TryInto;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use graph_proxy::apis::{
+    get_soft_delete, get_undeleted_graph, get_write_graph, now_millis, Direction, GraphElement, Mutated,
+    Mutation, QueryParams, ReadGraph, SoftDelete, WriteGraphProxy,
+};
+use graph_proxy::utils::expr::eval::Evaluator;
+use ir_common::error::ParsePbError;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::{KeyId, LabelId, NameOrId};
+use pegasus::api::function::DynIter;
+
+use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
+use crate::process::entry::Entry;
+use crate::process::operator::accum::accumulator::Accumulator;
+use crate::process::operator::write::{eval_properties, get_vertex_id, parse_property_sets};
+use crate::process::record::Record;
+use crate::standing::{Change, StandingQueries};
+use crate::trigger::TriggerRegistry;
+
+const DEFAULT_BATCH_SIZE: usize = 1024;
+
+/// The mutation of the Gremlin steps to build from each record.
+#[derive(Debug)]
+enum MutateKind {
+    AddV {
+        label: LabelId,
+        properties: Vec<(NameOrId, Evaluator)>,
+    },
+    AddE {
+        label: LabelId,
+        src_tag: Option<KeyId>,
+        dst_tag: Option<KeyId>,
+        properties: Vec<(NameOrId, Evaluator)>,
+    },
+    Property {
+        tag: Option<KeyId>,
+        properties: Vec<(NameOrId, Evaluator)>,
+    },
+    Drop {
+        tag: Option<KeyId>,
+    },
+    /// Drop by writing the tombstones, of the edges of the vertex dropped as well, see `SoftDelete`
+    SoftDrop {
+        tag: Option<KeyId>,
+        soft_delete: Arc<SoftDelete>,
+        graph: UndeletedGraph,
+    },
+}
+
+/// The graph to find the edges of the vertices to drop softly.
+#[derive(Clone)]
+struct UndeletedGraph(Arc<dyn ReadGraph>);
+
+impl Debug for UndeletedGraph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UndeletedGraph")
+    }
+}
+
+impl MutateKind {
+    fn build_mutation(&self, input: &Record) -> FnExecResult<Mutation> {
+        match self {
+            MutateKind::AddV { label, properties } => {
+                Ok(Mutation::AddVertex(*label, eval_properties(input, properties)?))
+            }
+            MutateKind::AddE { label, src_tag, dst_tag, properties } => {
+                let src_id = get_vertex_id(input, *src_tag)?;
+                let dst_id = get_vertex_id(input, *dst_tag)?;
+                Ok(Mutation::AddEdge(*label, src_id, dst_id, eval_properties(input, properties)?))
+            }
+            MutateKind::Property { tag, properties } => {
+                let properties = eval_properties(input, properties)?;
+                let entry = input
+                    .get(*tag)
+                    .ok_or_else(|| FnExecError::get_tag_error(&format!("tag {:?} in {:?}", tag, input)))?;
+                if let Some(vertex) = entry.as_vertex() {
+                    Ok(Mutation::SetVertexProperties(vertex.clone(), properties))
+                } else if let Some(edge) = entry.as_edge() {
+                    Ok(Mutation::SetEdgeProperties(edge.clone(), properties))
+                } else {
+                    Err(FnExecError::unexpected_data_error(&format!(
+                        "neither vertex nor edge to set properties in {:?}",
+                        input
+                    )))
+                }
+            }
+            MutateKind::Drop { tag } => {
+                let entry = input
+                    .get(*tag)
+                    .ok_or_else(|| FnExecError::get_tag_error(&format!("tag {:?} in {:?}", tag, input)))?;
+                if let Some(vertex) = entry.as_vertex() {
+                    Ok(Mutation::DropVertex(vertex.clone()))
+                } else if let Some(edge) = entry.as_edge() {
+                    Ok(Mutation::DropEdge(edge.clone()))
+                } else {
+                    Err(FnExecError::unexpected_data_error(&format!(
+                        "neither vertex nor edge to drop in {:?}",
+                        input
+                    )))
+                }
+            }
+            MutateKind::SoftDrop { tag, soft_delete, .. } => {
+                let entry = input
+                    .get(*tag)
+                    .ok_or_else(|| FnExecError::get_tag_error(&format!("tag {:?} in {:?}", tag, input)))?;
+                let tombstone = soft_delete.tombstone(now_millis());
+                if let Some(vertex) = entry.as_vertex() {
+                    Ok(Mutation::SetVertexProperties(vertex.clone(), tombstone))
+                } else if let Some(edge) = entry.as_edge() {
+                    Ok(Mutation::SetEdgeProperties(edge.clone(), tombstone))
+                } else {
+                    Err(FnExecError::unexpected_data_error(&format!(
+                        "neither vertex nor edge to drop in {:?}",
+                        input
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Build the mutations of the record, which are more than one only if the tombstones of the edges
+    /// are written together with that of the vertex dropped softly.
+    fn build_mutations(&self, input: &Record) -> FnExecResult<Vec<Mutation>> {
+        let mutation = self.build_mutation(input)?;
+        let mut mutations = vec![];
+        if let (MutateKind::SoftDrop { graph, .. }, Mutation::SetVertexProperties(vertex, tombstone)) =
+            (self, &mutation)
+        {
+            let edges = graph
+                .0
+                .prepare_explore_edge(Direction::Both, &QueryParams::default())?
+                .exec(vertex.id())?;
+            for edge in edges {
+                mutations.push(Mutation::SetEdgeProperties(edge, tombstone.clone()));
+            }
+        }
+        mutations.insert(0, mutation);
+        Ok(mutations)
+    }
+
+    fn is_drop(&self) -> bool {
+        matches!(self, MutateKind::Drop { .. } | MutateKind::SoftDrop { .. })
+    }
+}
+
+/// Write the mutations of the records into the graph in batches, and output the records with the
+/// vertices or the edges added or updated once all of them are written, while the dropped ones are not
+/// output. All the records failed in a batch are reported in the error, with the reasons of them, where
+/// a record fails if any of its mutations fails.
+/// The changes of the mutations written are applied to the standing queries, and the triggers are fired
+/// before and after the mutations are written, if any.
+#[derive(Clone)]
+pub struct MutateAccum {
+    kind: Arc<MutateKind>,
+    batch_size: usize,
+    alias: Option<KeyId>,
+    graph: Arc<Mutex<dyn WriteGraphProxy>>,
+    standing_queries: Option<Arc<StandingQueries>>,
+    triggers: Option<Arc<TriggerRegistry>>,
+    batch: Vec<(Record, Vec<Mutation>)>,
+    outputs: Vec<Record>,
+}
+
+impl Debug for MutateAccum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MutateAccum")
+            .field("kind", &self.kind)
+            .field("batch_size", &self.batch_size)
+            .field("alias", &self.alias)
+            .finish()
+    }
+}
+
+impl MutateAccum {
+    pub fn with_standing_queries(mut self, standing_queries: Arc<StandingQueries>) -> Self {
+        self.standing_queries = Some(standing_queries);
+        self
+    }
+
+    pub fn with_triggers(mut self, triggers: Arc<TriggerRegistry>) -> Self {
+        self.triggers = Some(triggers);
+        self
+    }
+
+    fn flush(&mut self) -> FnExecResult<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let (records, mutations): (Vec<Record>, Vec<Vec<Mutation>>) = std::mem::take(&mut self.batch)
+            .into_iter()
+            .unzip();
+        let counts: Vec<usize> = mutations.iter().map(|m| m.len()).collect();
+        let mutations: Vec<Mutation> = mutations.into_iter().flatten().collect();
+        let len = mutations.len();
+        let written = if self.standing_queries.is_some() || self.triggers.is_some() {
+            mutations.clone()
+        } else {
+            vec![]
+        };
+        let results = self
+            .graph
+            .lock()
+            .map_err(|e| FnExecError::unexpected_data_error(&format!("{:?}", e)))?
+            .mutate(mutations)?;
+        if results.len() != len {
+            Err(FnExecError::write_error(&format!(
+                "{} results of {} mutations in a batch",
+                results.len(),
+                len
+            )))?
+        }
+        if let Some(standing_queries) = self.standing_queries.as_ref() {
+            let changes: Vec<Change> = written
+                .iter()
+                .zip(results.iter())
+                .filter_map(|(mutation, result)| {
+                    result
+                        .as_ref()
+                        .ok()
+                        .and_then(|mutated| Change::from_mutation(mutation, mutated))
+                })
+                .collect();
+            standing_queries.apply(&changes)?;
+        }
+        if let Some(triggers) = self.triggers.as_ref() {
+            triggers.fire_after(&written, &results)?;
+        }
+        let mut results = results.into_iter();
+        let mut failures = vec![];
+        for (mut record, count) in records.into_iter().zip(counts) {
+            // the result of the record is that of its first mutation, unless any of them fails
+            let mut record_results: Vec<_> = results.by_ref().take(count).collect();
+            let result = match record_results.iter().position(|r| r.is_err()) {
+                Some(i) => record_results.swap_remove(i),
+                None => record_results.swap_remove(0),
+            };
+            match result {
+                Ok(_) if self.kind.is_drop() => {}
+                Ok(Mutated::Vertex(vertex)) => {
+                    record.append(vertex, self.alias);
+                    self.outputs.push(record);
+                }
+                Ok(Mutated::Edge(edge)) => {
+                    record.append(edge, self.alias);
+                    self.outputs.push(record);
+                }
+                Ok(Mutated::Dropped) => {}
+                Err(e) => failures.push(format!("{:?}: {}", record, e)),
+            }
+        }
+        if !failures.is_empty() {
+            Err(FnExecError::write_error(&format!(
+                "{} records failed to write: [{}]",
+                failures.len(),
+                failures.join(", ")
+            )))?
+        }
+        Ok(())
+    }
+}
+
+impl Accumulator<Record, DynIter<Record>> for MutateAccum {
+    fn accum(&mut self, next: Record) -> FnExecResult<()> {
+        let mut mutations = self.kind.build_mutations(&next)?;
+        if let Some(triggers) = self.triggers.as_ref() {
+            let fired = triggers.fire_before(&mutations)?;
+            mutations.extend(fired);
+        }
+        self.batch.push((next, mutations));
+        if self.batch.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> FnExecResult<DynIter<Record>> {
+        self.flush()?;
+        let outputs = std::mem::take(&mut self.outputs);
+        Ok(Box::new(outputs.into_iter()))
+    }
+}
+
+pub trait MutateFuncGen {
+    fn gen_mutate(self) -> FnGenResult<MutateAccum>;
+}
+
+impl MutateFuncGen for algebra_pb::Mutate {
+    fn gen_mutate(self) -> FnGenResult<MutateAccum> {
+        use algebra_pb::mutate::Kind;
+        let graph = get_write_graph().ok_or_else(|| FnGenError::NullGraphError)?;
+        let kind = match self.kind {
+            Some(Kind::AddV(add_v)) => {
+                let label: LabelId = add_v
+                    .label
+                    .ok_or_else(|| ParsePbError::EmptyFieldError("label of AddV".to_string()))?
+                    .try_into()?;
+                MutateKind::AddV { label, properties: parse_property_sets(add_v.properties)? }
+            }
+            Some(Kind::AddE(add_e)) => {
+                let label: LabelId = add_e
+                    .label
+                    .ok_or_else(|| ParsePbError::EmptyFieldError("label of AddE".to_string()))?
+                    .try_into()?;
+                let src_tag: Option<KeyId> = add_e
+                    .src_tag
+                    .map(|tag| tag.try_into())
+                    .transpose()?;
+                let dst_tag: Option<KeyId> = add_e
+                    .dst_tag
+                    .map(|tag| tag.try_into())
+                    .transpose()?;
+                MutateKind::AddE {
+                    label,
+                    src_tag,
+                    dst_tag,
+                    properties: parse_property_sets(add_e.properties)?,
+                }
+            }
+            Some(Kind::Property(property)) => {
+                let tag: Option<KeyId> = property
+                    .tag
+                    .map(|tag| tag.try_into())
+                    .transpose()?;
+                MutateKind::Property { tag, properties: parse_property_sets(property.properties)? }
+            }
+            Some(Kind::Drop(drop)) => {
+                let tag: Option<KeyId> = drop.tag.map(|tag| tag.try_into()).transpose()?;
+                match get_soft_delete() {
+                    Some(soft_delete) => {
+                        let graph = get_undeleted_graph().ok_or_else(|| FnGenError::NullGraphError)?;
+                        MutateKind::SoftDrop { tag, soft_delete, graph: UndeletedGraph(graph) }
+                    }
+                    None => MutateKind::Drop { tag },
+                }
+            }
+            None => Err(ParsePbError::EmptyFieldError("kind of Mutate".to_string()))?,
+        };
+        let batch_size = if self.batch_size > 0 { self.batch_size as usize } else { DEFAULT_BATCH_SIZE };
+        let alias: Option<KeyId> = self
+            .alias
+            .map(|alias| alias.try_into())
+            .transpose()?;
+        let mutate_accum = MutateAccum {
+            kind: Arc::new(kind),
+            batch_size,
+            alias,
+            graph,
+            standing_queries: None,
+            triggers: None,
+            batch: vec![],
+            outputs: vec![],
+        };
+        if log_enabled!(log::Level::Debug) && pegasus::get_current_worker().index == 0 {
+            debug!("Runtime mutate operator {:?}", mutate_accum);
+        }
+        Ok(mutate_accum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use dyn_type::Object;
+    use graph_proxy::apis::test_graph::TestGraph;
+    use graph_proxy::apis::{Edge, Mutated, Mutation, SoftDelete, ID};
+
+    use super::{MutateAccum, MutateKind, UndeletedGraph};
+    use crate::error::FnExecResult;
+    use crate::process::entry::Entry;
+    use crate::process::operator::accum::accumulator::Accumulator;
+    use crate::process::operator::tests::{init_source, init_vertex1, init_vertex2, PERSON_LABEL};
+    use crate::process::operator::write::tests::{get_property, property, TestWriteGraph, KNOWS_LABEL};
+    use crate::process::record::Record;
+    use crate::standing::{Delta, StandingColumn, StandingQueries, StandingQuery};
+    use crate::trigger::{AfterCommit, TriggerEvent, TriggerOn, TriggerRegistry};
+
+    fn mutate_accum(kind: MutateKind, batch_size: usize, graph: Arc<Mutex<TestWriteGraph>>) -> MutateAccum {
+        MutateAccum {
+            kind: Arc::new(kind),
+            batch_size,
+            alias: Some(0),
+            graph,
+            standing_queries: None,
+            triggers: None,
+            batch: vec![],
+            outputs: vec![],
+        }
+    }
+
+    fn mutate(accum: &mut MutateAccum, source: Vec<Record>) -> Result<Vec<Record>, String> {
+        for record in source {
+            accum.accum(record).map_err(|e| e.to_string())?;
+        }
+        accum
+            .finalize()
+            .map(|outputs| outputs.collect())
+            .map_err(|e| e.to_string())
+    }
+
+    // g.V().addV('person').property('id', @.id + 10).property('name', @.name)
+    #[test]
+    fn add_vertex_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        let kind = MutateKind::AddV {
+            label: PERSON_LABEL,
+            properties: vec![property("id", "@.id + 10"), property("name", "@.name")],
+        };
+        let mut accum = mutate_accum(kind, 1, graph.clone());
+        let outputs = mutate(&mut accum, init_source()).unwrap();
+        let added: Vec<(i64, Object)> = outputs
+            .iter()
+            .map(|record| {
+                let vertex = record
+                    .get(Some(0))
+                    .unwrap()
+                    .as_vertex()
+                    .unwrap();
+                (vertex.id(), get_property(vertex, "name").unwrap())
+            })
+            .collect();
+        assert_eq!(added, vec![(11, object!("marko")), (12, object!("vadas"))]);
+        assert_eq!(graph.lock().unwrap().vertices.len(), 2);
+    }
+
+    // the vertices added are inserted into the results of the standing queries
+    #[test]
+    fn add_vertex_standing_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        let standing_queries = Arc::new(StandingQueries::default());
+        let query = StandingQuery::vertices(vec![PERSON_LABEL])
+            .with_columns(vec![StandingColumn::Property("name".into())]);
+        standing_queries
+            .register_with("names", query, Arc::new(TestGraph::default()))
+            .unwrap();
+        let subscription = standing_queries
+            .subscribe("names")
+            .unwrap()
+            .unwrap();
+        let kind = MutateKind::AddV {
+            label: PERSON_LABEL,
+            properties: vec![property("id", "@.id + 10"), property("name", "@.name")],
+        };
+        let mut accum = mutate_accum(kind, 2, graph).with_standing_queries(standing_queries);
+        mutate(&mut accum, init_source()).unwrap();
+        assert_eq!(
+            subscription.deltas.try_recv().unwrap(),
+            vec![Delta::Insert(vec![object!("marko")]), Delta::Insert(vec![object!("vadas")])]
+        );
+    }
+
+    struct RecordAdded(Mutex<Vec<ID>>);
+
+    impl AfterCommit for RecordAdded {
+        fn fire(&self, _: TriggerEvent, _: &Mutation, mutated: &Mutated) -> FnExecResult<()> {
+            if let Mutated::Vertex(vertex) = mutated {
+                self.0.lock().unwrap().push(vertex.id());
+            }
+            Ok(())
+        }
+    }
+
+    // the triggers are fired by the vertices added
+    #[test]
+    fn add_vertex_trigger_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        let triggers = Arc::new(TriggerRegistry::default());
+        let added = Arc::new(RecordAdded(Mutex::new(vec![])));
+        let on = TriggerOn::vertices(vec![PERSON_LABEL]).with_events(vec![TriggerEvent::Insert]);
+        triggers
+            .register_after("added", on, added.clone())
+            .unwrap();
+        let kind = MutateKind::AddV { label: PERSON_LABEL, properties: vec![property("id", "@.id + 10")] };
+        let mut accum = mutate_accum(kind, 1, graph).with_triggers(triggers);
+        mutate(&mut accum, init_source()).unwrap();
+        assert_eq!(*added.0.lock().unwrap(), vec![11, 12]);
+    }
+
+    // g.V().as('a').out().as('b').addE('knows').from('a').to('b').property('weight', 0.5)
+    #[test]
+    fn add_edge_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        let kind = MutateKind::AddE {
+            label: KNOWS_LABEL,
+            src_tag: Some(1),
+            dst_tag: Some(2),
+            properties: vec![property("weight", "0.5")],
+        };
+        let mut accum = mutate_accum(kind, 2, graph.clone());
+        let mut record = Record::new(init_vertex1(), Some(1));
+        record.append(init_vertex2(), Some(2));
+        let outputs = mutate(&mut accum, vec![record]).unwrap();
+        assert_eq!(outputs.len(), 1);
+        let edge = outputs[0]
+            .get(Some(0))
+            .unwrap()
+            .as_edge()
+            .unwrap();
+        assert_eq!((edge.src_id, edge.dst_id), (1, 2));
+        assert_eq!(get_property(edge, "weight"), Some(object!(0.5)));
+        assert_eq!(graph.lock().unwrap().edges.len(), 1);
+    }
+
+    // g.V().property('age', @.age + 1)
+    #[test]
+    fn set_property_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        graph
+            .lock()
+            .unwrap()
+            .vertices
+            .insert(1, Default::default());
+        let kind = MutateKind::Property { tag: None, properties: vec![property("age", "@.age + 1")] };
+        let mut accum = mutate_accum(kind, 1, graph.clone());
+        let outputs = mutate(&mut accum, vec![Record::new(init_vertex1(), None)]).unwrap();
+        let vertex = outputs[0]
+            .get(Some(0))
+            .unwrap()
+            .as_vertex()
+            .unwrap();
+        let age = get_property(vertex, "age").unwrap();
+        assert_eq!(age.as_i64().unwrap(), 30);
+    }
+
+    // g.V().drop(), where the dropped ones are not output
+    #[test]
+    fn drop_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        {
+            let mut graph = graph.lock().unwrap();
+            graph.vertices.insert(1, Default::default());
+            graph.vertices.insert(2, Default::default());
+            graph.edges.insert((1, 2), Default::default());
+        }
+        let mut accum = mutate_accum(MutateKind::Drop { tag: None }, 1, graph.clone());
+        let outputs = mutate(&mut accum, vec![Record::new(init_vertex1(), None)]).unwrap();
+        assert!(outputs.is_empty());
+        let graph = graph.lock().unwrap();
+        assert_eq!(graph.vertices.len(), 1);
+        assert!(graph.edges.is_empty());
+    }
+
+    // g.V().drop() with the soft deletes, where the tombstones are written into the vertex and its edges
+    #[test]
+    fn soft_drop_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        {
+            let mut graph = graph.lock().unwrap();
+            graph.vertices.insert(1, Default::default());
+            graph.vertices.insert(2, Default::default());
+            graph.edges.insert((1, 2), Default::default());
+            graph.edges.insert((2, 3), Default::default());
+        }
+        let edges = vec![
+            Edge::new(0, Some(KNOWS_LABEL), 1, 2, Default::default()),
+            Edge::new(1, Some(KNOWS_LABEL), 2, 3, Default::default()),
+        ];
+        let kind = MutateKind::SoftDrop {
+            tag: None,
+            soft_delete: Arc::new(SoftDelete::new("deleted_at".into(), Duration::from_secs(60))),
+            graph: UndeletedGraph(Arc::new(TestGraph::new(vec![], edges))),
+        };
+        let mut accum = mutate_accum(kind, 1, graph.clone());
+        let outputs = mutate(&mut accum, vec![Record::new(init_vertex1(), None)]).unwrap();
+        assert!(outputs.is_empty());
+        let graph = graph.lock().unwrap();
+        // kept in the graph with the tombstones
+        assert_eq!(graph.vertices.len(), 2);
+        assert!(graph.vertices[&1].contains_key(&"deleted_at".into()));
+        assert!(!graph.vertices[&2].contains_key(&"deleted_at".into()));
+        assert!(graph.edges[&(1, 2)].contains_key(&"deleted_at".into()));
+        assert!(!graph.edges[&(2, 3)].contains_key(&"deleted_at".into()));
+    }
+
+    #[test]
+    fn mutate_in_batches_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        let kind = MutateKind::AddV { label: PERSON_LABEL, properties: vec![property("id", "@.id")] };
+        let mut accum = mutate_accum(kind, 2, graph.clone());
+        accum
+            .accum(Record::new(init_vertex1(), None))
+            .unwrap();
+        // not written until the batch is full
+        assert!(graph.lock().unwrap().vertices.is_empty());
+        accum
+            .accum(Record::new(init_vertex2(), None))
+            .unwrap();
+        assert_eq!(graph.lock().unwrap().vertices.len(), 2);
+        assert!(accum.batch.is_empty());
+        assert_eq!(accum.finalize().unwrap().count(), 2);
+    }
+
+    // the records failed to write are reported, while the others in the batch are still written
+    #[test]
+    fn mutate_failure_test() {
+        let graph = Arc::new(Mutex::new(TestWriteGraph::default()));
+        graph
+            .lock()
+            .unwrap()
+            .vertices
+            .insert(2, Default::default());
+        let kind = MutateKind::AddV { label: PERSON_LABEL, properties: vec![property("id", "@.id")] };
+        let mut accum = mutate_accum(kind, 4, graph.clone());
+        let error = mutate(&mut accum, init_source()).unwrap_err();
+        assert!(error.contains("1 records failed to write"));
+        assert!(error.contains("vertex 2 exists"));
+        assert_eq!(graph.lock().unwrap().vertices.len(), 2);
+    }
+
+    #[test]
+    fn mutate_mismatched_entry_test() {
+        let edge = Edge::new(0, Some(KNOWS_LABEL), 1, 2, Default::default());
+        let kind =
+            MutateKind::AddE { label: KNOWS_LABEL, src_tag: None, dst_tag: None, properties: vec![] };
+        // the endpoints of the edge to add must be vertices
+        assert!(kind
+            .build_mutation(&Record::new(edge.clone(), None))
+            .is_err());
+        let kind = MutateKind::Drop { tag: None };
+        assert!(matches!(kind.build_mutation(&Record::new(edge, None)), Ok(Mutation::DropEdge(_))));
+    }
+}
@@ -20,15 +20,33 @@ use graph_proxy::apis::{Edge, GraphPath, Vertex};
 use graph_proxy::utils::expr::eval::Context;
 use ir_common::{KeyId, NameOrId};
 use pegasus::api::function::DynIter;
-use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+use pegasus::codec::{protocol_version, Decode, Encode, ReadExt, WriteExt};
 use vec_map::VecMap;
 
 use crate::process::entry::{DynEntry, Entry, EntryType};
+use crate::provenance::Provenance;
+
+/// The bit of the leading byte of an encoded record if it has a head.
+const HAS_CURR: u8 = 1;
+/// The bit of the leading byte of an encoded record if it carries a provenance, followed by its columns.
+const HAS_PROVENANCE: u8 = 2;
+/// The protocol version of the servers decoding the provenance of a record, which is not sent to the
+/// servers of older versions, as they take the leading byte other than 0 for a head.
+const PROVENANCE_PROTOCOL_VERSION: u32 = 2;
+/// The bit of the leading byte of an encoded record if it carries a sack, after its provenance if any.
+const HAS_SACK: u8 = 4;
+/// The protocol version of the servers decoding the sack of a record, which can't be sent to the
+/// servers of older versions, as they don't take the bit of the sack.
+const SACK_PROTOCOL_VERSION: u32 = 3;
 
 #[derive(Debug, Clone, Default)]
 pub struct Record {
     curr: Option<DynEntry>,
     columns: VecMap<DynEntry>,
+    /// The trace of the steps deriving the record, only in the provenance tracking mode.
+    provenance: Option<Box<Provenance>>,
+    /// The state carried by the record, which is initialized and updated by `Sack`.
+    sack: Option<DynEntry>,
 }
 
 unsafe impl Send for Record {}
@@ -41,7 +59,7 @@ impl Record {
         if let Some(tag) = tag {
             columns.insert(tag as usize, entry.clone());
         }
-        Record { curr: Some(entry), columns }
+        Record { curr: Some(entry), columns, provenance: None, sack: None }
     }
 
     /// A handy api to append entry of different types that can be turned into `Entry`
@@ -97,18 +115,58 @@ impl Record {
         }
     }
 
+    pub fn get_provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_deref()
+    }
+
+    /// The trace of the record, which is started if the record carries none.
+    pub fn get_provenance_mut(&mut self) -> &mut Provenance {
+        self.provenance.get_or_insert_with(Box::default)
+    }
+
+    pub fn take_provenance(&mut self) -> Option<Box<Provenance>> {
+        self.provenance.take()
+    }
+
+    pub fn set_provenance(&mut self, provenance: Option<Box<Provenance>>) {
+        self.provenance = provenance;
+    }
+
+    pub fn get_sack(&self) -> Option<&DynEntry> {
+        self.sack.as_ref()
+    }
+
+    pub fn set_sack(&mut self, sack: Option<DynEntry>) {
+        self.sack = sack;
+    }
+
+    pub fn take_sack(&mut self) -> Option<DynEntry> {
+        self.sack.take()
+    }
+
     /// To join this record with `other` record. After the join, the columns
     /// from both sides will be merged (and deduplicated). The `curr` entry of the joined
     /// record will be specified according to `is_left_opt`, namely, if
     /// * `is_left_opt = None` -> set as `None`,
     /// * `is_left_opt = Some(true)` -> set as left record,
     /// * `is_left_opt = Some(false)` -> set as right record.
+    /// The provenance of `other` follows that of this record if any, while the sack of this record is
+    /// kept if any.
     pub fn join(mut self, mut other: Record, is_left_opt: Option<bool>) -> Record {
         for column in other.columns.drain() {
             if !self.columns.contains_key(column.0) {
                 self.columns.insert(column.0, column.1);
             }
         }
+        if let Some(other_provenance) = other.provenance.take() {
+            match self.provenance.as_mut() {
+                Some(provenance) => provenance.merge(*other_provenance),
+                None => self.provenance = Some(other_provenance),
+            }
+        }
+        if self.sack.is_none() {
+            self.sack = other.sack;
+        }
 
         if let Some(is_left) = is_left_opt {
             if !is_left {
@@ -139,6 +197,7 @@ impl Context<DynEntry> for Record {
                 match entry_type {
                     EntryType::Collection => None,
                     EntryType::Intersection => None,
+                    EntryType::Map => None,
                     _ => Some(entry),
                 }
             })
@@ -147,7 +206,7 @@ impl Context<DynEntry> for Record {
 }
 
 /// RecordKey is the key fields of a Record, with each key corresponding to a request column_tag
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
 pub struct RecordKey {
     key_fields: Vec<DynEntry>,
 }
@@ -232,28 +291,50 @@ impl<E: Entry + 'static> Iterator for RecordPathExpandIter<E> {
 
 impl Encode for Record {
     fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
-        match &self.curr {
-            None => {
-                writer.write_u8(0)?;
-            }
-            Some(entry) => {
-                writer.write_u8(1)?;
-                entry.write_to(writer)?;
+        // the provenance is flagged in the leading byte, so the records are encoded as before without it
+        let provenance =
+            if protocol_version() >= PROVENANCE_PROTOCOL_VERSION { self.provenance.as_ref() } else { None };
+        let mut flags = 0;
+        if self.curr.is_some() {
+            flags |= HAS_CURR;
+        }
+        if provenance.is_some() {
+            flags |= HAS_PROVENANCE;
+        }
+        if self.sack.is_some() {
+            // unlike the provenance, the results would be wrong without the sack
+            let version = protocol_version();
+            if version < SACK_PROTOCOL_VERSION {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    format!("sack of a record sent to a server of the protocol version {}", version),
+                ));
             }
+            flags |= HAS_SACK;
+        }
+        writer.write_u8(flags)?;
+        if let Some(entry) = &self.curr {
+            entry.write_to(writer)?;
         }
         writer.write_u64(self.columns.len() as u64)?;
         for (k, v) in self.columns.iter() {
             (k as KeyId).write_to(writer)?;
             v.write_to(writer)?;
         }
+        if let Some(provenance) = provenance {
+            provenance.write_to(writer)?;
+        }
+        if let Some(sack) = &self.sack {
+            sack.write_to(writer)?;
+        }
         Ok(())
     }
 }
 
 impl Decode for Record {
     fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
-        let opt = reader.read_u8()?;
-        let curr = if opt == 0 { None } else { Some(<DynEntry>::read_from(reader)?) };
+        let flags = reader.read_u8()?;
+        let curr = if flags & HAS_CURR == 0 { None } else { Some(<DynEntry>::read_from(reader)?) };
         let size = <u64>::read_from(reader)? as usize;
         let mut columns = VecMap::with_capacity(size);
         for _i in 0..size {
@@ -261,7 +342,10 @@ impl Decode for Record {
             let v = <DynEntry>::read_from(reader)?;
             columns.insert(k, v);
         }
-        Ok(Record { curr, columns })
+        let provenance =
+            if flags & HAS_PROVENANCE == 0 { None } else { Some(Box::new(Provenance::read_from(reader)?)) };
+        let sack = if flags & HAS_SACK == 0 { None } else { Some(<DynEntry>::read_from(reader)?) };
+        Ok(Record { curr, columns, provenance, sack })
     }
 }
 
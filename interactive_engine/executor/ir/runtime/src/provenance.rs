@@ -0,0 +1,369 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! The provenance tracking mode of a job, with the `provenance` metadata of the request, for debugging
+//! the wrong results: each record carries a trace of the steps deriving it, i.e., the tags, and the
+//! vertices of the tags, read by the projects and the joins the record passes through, which is sunk
+//! with the record in the `provenance` of `results.Record` in protobuf. In GraphSON and GraphBinary, a
+//! result is a map of the `result` and the `provenance` in json, and in Arrow the provenance in json is
+//! the column `provenance`.
+//!
+//! The trace keeps the latest `MAX_STEPS` steps, where a record joined carries the steps of both sides.
+//! The trace is shuffled with the record to the servers of the protocol version 2 or later only, see
+//! `Record`.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::sync::RwLock;
+
+use ir_common::generated::common as common_pb;
+use ir_common::KeyId;
+use lazy_static::lazy_static;
+use pegasus::codec::{Decode, Encode, ReadExt, WriteExt};
+
+use crate::process::record::Record;
+
+/// The most steps kept in a trace.
+pub const MAX_STEPS: usize = 64;
+/// The most items allocated in advance for a length decoded, which is not trusted.
+const MAX_PREALLOCATED: usize = 1024;
+
+lazy_static! {
+    /// The jobs tracking the provenance, with the number of workers binding them.
+    static ref JOBS_TRACKING: RwLock<HashMap<u64, usize>> = RwLock::new(HashMap::new());
+}
+
+/// Track the provenance of the records of the job, until all the returned bindings of the job are
+/// dropped, e.g., with the workers of the job as their resources.
+pub fn bind_provenance(job_id: u64) -> ProvenanceBinding {
+    *JOBS_TRACKING
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(job_id)
+        .or_insert(0) += 1;
+    ProvenanceBinding { job_id }
+}
+
+/// Whether the job of the current worker tracks the provenance, which is checked as the operators are
+/// generated rather than for each record.
+pub fn is_tracking_provenance() -> bool {
+    let jobs = JOBS_TRACKING
+        .read()
+        .unwrap_or_else(|e| e.into_inner());
+    if jobs.is_empty() {
+        return false;
+    }
+    pegasus::get_current_worker_checked()
+        .map(|worker| jobs.contains_key(&worker.job_id))
+        .unwrap_or(false)
+}
+
+pub struct ProvenanceBinding {
+    job_id: u64,
+}
+
+impl Drop for ProvenanceBinding {
+    fn drop(&mut self) {
+        let mut jobs = JOBS_TRACKING
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = jobs.get_mut(&self.job_id) {
+            *count -= 1;
+            if *count == 0 {
+                jobs.remove(&self.job_id);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TracedOperator {
+    Project = 0,
+    Join = 1,
+}
+
+impl TracedOperator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TracedOperator::Project => "Project",
+            TracedOperator::Join => "Join",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProvenanceStep {
+    pub operator: TracedOperator,
+    /// The tags read by the operator, where `None` is the head.
+    pub tags: Vec<Option<KeyId>>,
+    /// The ids of the vertices of the tags, if the tags are of vertices.
+    pub vertices: Vec<i64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Provenance {
+    steps: VecDeque<ProvenanceStep>,
+    /// Whether the earlier steps are dropped for the limit of the trace.
+    truncated: bool,
+}
+
+impl Provenance {
+    pub fn steps(&self) -> impl Iterator<Item = &ProvenanceStep> {
+        self.steps.iter()
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn push(&mut self, step: ProvenanceStep) {
+        self.steps.push_back(step);
+        self.truncate();
+    }
+
+    /// Take the steps of `other` after the steps of this trace, e.g., of the right side of a join.
+    pub fn merge(&mut self, other: Provenance) {
+        self.truncated |= other.truncated;
+        self.steps.extend(other.steps);
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        while self.steps.len() > MAX_STEPS {
+            self.steps.pop_front();
+            self.truncated = true;
+        }
+    }
+}
+
+/// Add a step of the operator reading the tags of the record to the trace of the record.
+pub fn trace(record: &mut Record, operator: TracedOperator, tags: &[Option<KeyId>]) {
+    let vertices = tags
+        .iter()
+        .filter_map(|tag| record.get(*tag))
+        .filter_map(|entry| entry.as_vertex().map(|vertex| vertex.id()))
+        .collect();
+    let step = ProvenanceStep { operator, tags: tags.to_vec(), vertices };
+    record.get_provenance_mut().push(step);
+}
+
+/// The tags of the variables of an expression, in the order of their first appearance.
+pub fn expr_tags(expr: &common_pb::Expression) -> Vec<Option<KeyId>> {
+    let mut tags = vec![];
+    collect_tags(&expr.operators, &mut tags);
+    tags
+}
+
+fn collect_tags(operators: &[common_pb::ExprOpr], tags: &mut Vec<Option<KeyId>>) {
+    for opr in operators {
+        match opr.item.as_ref() {
+            Some(common_pb::expr_opr::Item::Var(var)) => add_tag(var, tags),
+            Some(common_pb::expr_opr::Item::Vars(vars)) | Some(common_pb::expr_opr::Item::VarMap(vars)) => {
+                for var in vars.keys.iter() {
+                    add_tag(var, tags);
+                }
+            }
+            Some(common_pb::expr_opr::Item::Map(key_vals)) => {
+                for var in key_vals
+                    .key_vals
+                    .iter()
+                    .filter_map(|key_val| key_val.value.as_ref())
+                {
+                    add_tag(var, tags);
+                }
+            }
+            Some(common_pb::expr_opr::Item::Concat(concat)) => {
+                for var in concat.vars.iter() {
+                    add_tag(var, tags);
+                }
+            }
+            Some(common_pb::expr_opr::Item::Udf(udf)) => collect_tags(&udf.args, tags),
+            Some(common_pb::expr_opr::Item::ElementMap(element_map)) => {
+                add_tag_id(element_map.tag.as_ref(), tags)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The tags of the variables, e.g., the keys of a join, in the order of their first appearance.
+pub fn var_tags<'a>(vars: impl IntoIterator<Item = &'a common_pb::Variable>) -> Vec<Option<KeyId>> {
+    let mut tags = vec![];
+    for var in vars {
+        add_tag(var, &mut tags);
+    }
+    tags
+}
+
+fn add_tag(var: &common_pb::Variable, tags: &mut Vec<Option<KeyId>>) {
+    add_tag_id(var.tag.as_ref(), tags)
+}
+
+fn add_tag_id(tag: Option<&common_pb::NameOrId>, tags: &mut Vec<Option<KeyId>>) {
+    // the tags of the physical plans are resolved to ids
+    if let Ok(tag) = tag.cloned().map(KeyId::try_from).transpose() {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+}
+
+impl Encode for Provenance {
+    fn write_to<W: WriteExt>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_u8(self.truncated as u8)?;
+        writer.write_u32(self.steps.len() as u32)?;
+        for step in self.steps.iter() {
+            writer.write_u8(step.operator as u8)?;
+            writer.write_u32(step.tags.len() as u32)?;
+            for tag in step.tags.iter() {
+                tag.write_to(writer)?;
+            }
+            writer.write_u32(step.vertices.len() as u32)?;
+            for vertex in step.vertices.iter() {
+                writer.write_i64(*vertex)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for Provenance {
+    fn read_from<R: ReadExt>(reader: &mut R) -> std::io::Result<Self> {
+        let truncated = reader.read_u8()? != 0;
+        let len = reader.read_u32()? as usize;
+        if len > MAX_STEPS {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} steps of provenance over the limit {}", len, MAX_STEPS),
+            ));
+        }
+        let mut steps = VecDeque::with_capacity(len);
+        for _ in 0..len {
+            let operator = match reader.read_u8()? {
+                0 => TracedOperator::Project,
+                1 => TracedOperator::Join,
+                _ => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "unknown operator of provenance",
+                    ))
+                }
+            };
+            let tags_len = reader.read_u32()? as usize;
+            let mut tags = Vec::with_capacity(tags_len.min(MAX_PREALLOCATED));
+            for _ in 0..tags_len {
+                tags.push(<Option<KeyId>>::read_from(reader)?);
+            }
+            let vertices_len = reader.read_u32()? as usize;
+            let mut vertices = Vec::with_capacity(vertices_len.min(MAX_PREALLOCATED));
+            for _ in 0..vertices_len {
+                vertices.push(reader.read_i64()?);
+            }
+            steps.push_back(ProvenanceStep { operator, tags, vertices });
+        }
+        Ok(Provenance { steps, truncated })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::{DynDetails, Vertex};
+
+    use super::*;
+
+    fn step(operator: TracedOperator, tag: KeyId) -> ProvenanceStep {
+        ProvenanceStep { operator, tags: vec![Some(tag)], vertices: vec![] }
+    }
+
+    #[test]
+    fn provenance_test() {
+        let mut left = Provenance::default();
+        left.push(step(TracedOperator::Project, 0));
+        let mut right = Provenance::default();
+        for tag in 0..MAX_STEPS as KeyId {
+            right.push(step(TracedOperator::Project, tag));
+        }
+        assert!(!right.is_truncated());
+        left.merge(right);
+        assert!(left.is_truncated());
+        assert_eq!(left.steps().count(), MAX_STEPS);
+        assert_eq!(left.steps().next().unwrap().tags, vec![Some(0)]);
+        assert_eq!(left.steps().last().unwrap().tags, vec![Some(MAX_STEPS as KeyId - 1)]);
+
+        let mut buf = vec![];
+        left.write_to(&mut buf).unwrap();
+        let decoded = Provenance::read_from(&mut buf.as_slice()).unwrap();
+        assert_eq!(decoded, left);
+
+        // a length over the limit is rejected rather than allocated
+        let mut corrupt = vec![0u8];
+        corrupt.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Provenance::read_from(&mut corrupt.as_slice()).is_err());
+    }
+
+    #[test]
+    fn record_provenance_protocol_test() {
+        let vertex = Vertex::new(7, Some(1), DynDetails::default());
+        let mut record = Record::new(vertex, Some(0));
+        trace(&mut record, TracedOperator::Project, &[Some(0)]);
+        let mut buf = vec![];
+        record.write_to(&mut buf).unwrap();
+        let decoded = Record::read_from(&mut buf.as_slice()).unwrap();
+        assert_eq!(decoded.get_provenance(), record.get_provenance());
+
+        // the servers of the legacy protocol take the leading byte for whether a head follows
+        let mut buf = vec![];
+        pegasus::codec::with_protocol_version(1, || record.write_to(&mut buf)).unwrap();
+        assert_eq!(buf[0], 1);
+        let decoded = Record::read_from(&mut buf.as_slice()).unwrap();
+        assert!(decoded.get_provenance().is_none());
+        assert_eq!(
+            decoded
+                .get(Some(0))
+                .unwrap()
+                .as_vertex()
+                .unwrap()
+                .id(),
+            7
+        );
+    }
+
+    #[test]
+    fn trace_test() {
+        let vertex = Vertex::new(7, Some(1), DynDetails::default());
+        let mut record = Record::new(vertex, Some(0));
+        trace(&mut record, TracedOperator::Project, &[Some(0), Some(1)]);
+        trace(&mut record, TracedOperator::Join, &[None]);
+        let steps: Vec<&ProvenanceStep> = record
+            .get_provenance()
+            .unwrap()
+            .steps()
+            .collect();
+        assert_eq!(steps[0].vertices, vec![7]);
+        assert_eq!(steps[0].tags, vec![Some(0), Some(1)]);
+        assert_eq!(steps[1].operator, TracedOperator::Join);
+        assert_eq!(steps[1].vertices, vec![7]);
+    }
+
+    #[test]
+    fn bind_provenance_test() {
+        let first = bind_provenance(1);
+        let second = bind_provenance(1);
+        drop(first);
+        assert!(JOBS_TRACKING.read().unwrap().contains_key(&1));
+        drop(second);
+        assert!(!JOBS_TRACKING.read().unwrap().contains_key(&1));
+    }
+}
@@ -0,0 +1,345 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Refine the plan while it is assembled by the statistics of the graph given by the store, see
+//! `GraphStatistics`, which never changes the results of the plan:
+//!   1. reorder the conjuncts of the predicates in the ascending order of their estimated selectivities,
+//!      so that the most selective ones are evaluated first and short-circuit the others, e.g.,
+//!      `@.age > 10 && @.name == "marko"` into `@.name == "marko" && @.age > 10` if the names are
+//!      mostly distinct;
+//!   2. reorder the sub-plans of an intersection in the ascending order of the estimated degrees of
+//!      their last expands, as the first one expands the candidates, and the others intersect with them.
+//!      The expands of the physical plan are always of their directions specified, and the choice left
+//!      to the runtime is which side of the intersection is to expand.
+//!
+//! The plan is kept as is if the statistics are missing, e.g., of the labels unknown to the store.
+
+use std::cmp::Ordering;
+use std::convert::{TryFrom, TryInto};
+
+use graph_proxy::apis::{Direction, GraphStatistics};
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+use ir_common::{LabelId, NameOrId};
+
+use crate::error::FnGenResult;
+use crate::procedure::sub_plans_mut;
+use crate::simplify::{as_logical, flatten_items, parse_items, Item};
+
+/// The selectivity of an equality of an unknown number of distinct values.
+const EQ_SELECTIVITY: f64 = 0.1;
+const RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+const MATCH_SELECTIVITY: f64 = 0.25;
+const DEFAULT_SELECTIVITY: f64 = 0.5;
+
+/// Refine the operators of the plan and its sub-plans by the statistics.
+pub fn refine_plan(plan: &mut pb::PhysicalPlan, statistics: &GraphStatistics) -> FnGenResult<()> {
+    for opr in plan.plan.iter_mut() {
+        let mut op_kind: OpKind = (&*opr).try_into()?;
+        match &mut op_kind {
+            OpKind::Scan(scan) => {
+                let scope = match pb::scan::ScanOpt::from_i32(scan.scan_opt) {
+                    Some(pb::scan::ScanOpt::Vertex) => Scope::vertices(scan.params.as_ref()),
+                    Some(pb::scan::ScanOpt::Edge) => Scope::edges(scan.params.as_ref()),
+                    _ => Scope::Unknown,
+                };
+                refine_query_params(scan.params.as_mut(), &scope, statistics);
+            }
+            OpKind::Edge(expand) => {
+                // the tables always apply to the edges, while the predicate applies to the end vertices
+                // of the vertices expanded
+                let scope = match pb::edge_expand::ExpandOpt::from_i32(expand.expand_opt) {
+                    Some(pb::edge_expand::ExpandOpt::Edge) => Scope::edges(expand.params.as_ref()),
+                    Some(pb::edge_expand::ExpandOpt::Vertex) => Scope::Vertices(vec![]),
+                    _ => Scope::Unknown,
+                };
+                refine_query_params(expand.params.as_mut(), &scope, statistics);
+            }
+            OpKind::Vertex(get_v) => {
+                let scope = Scope::vertices(get_v.params.as_ref());
+                refine_query_params(get_v.params.as_mut(), &scope, statistics);
+            }
+            OpKind::Select(select) => {
+                if let Some(predicate) = select.predicate.as_mut() {
+                    reorder_conjuncts(predicate, &Scope::Unknown, statistics);
+                }
+            }
+            OpKind::Intersect(intersect) => reorder_intersection(intersect, statistics),
+            _ => {}
+        }
+        for sub_plan in sub_plans_mut(&mut op_kind) {
+            refine_plan(sub_plan, statistics)?;
+        }
+        opr.opr = Some(pb::physical_opr::Operator { op_kind: Some(op_kind) });
+    }
+    Ok(())
+}
+
+/// The elements which a predicate applies to, of the labels if known, or of all the labels if empty.
+enum Scope {
+    Vertices(Vec<LabelId>),
+    Edges(Vec<LabelId>),
+    Unknown,
+}
+
+impl Scope {
+    fn vertices(params: Option<&algebra_pb::QueryParams>) -> Self {
+        labels_of(params).map_or(Scope::Unknown, Scope::Vertices)
+    }
+
+    fn edges(params: Option<&algebra_pb::QueryParams>) -> Self {
+        labels_of(params).map_or(Scope::Unknown, Scope::Edges)
+    }
+
+    fn ndv(&self, statistics: &GraphStatistics, key: &NameOrId) -> Option<u64> {
+        match self {
+            Scope::Vertices(labels) => statistics.vertex_ndv(labels, key),
+            Scope::Edges(labels) => statistics.edge_ndv(labels, key),
+            Scope::Unknown => statistics
+                .vertex_ndv(&[], key)
+                .max(statistics.edge_ndv(&[], key)),
+        }
+    }
+
+    fn count(&self, statistics: &GraphStatistics) -> Option<u64> {
+        match self {
+            Scope::Vertices(labels) => statistics.vertex_count(labels),
+            Scope::Edges(labels) => statistics.edge_count(labels),
+            Scope::Unknown => None,
+        }
+    }
+}
+
+/// The ids of the labels, which is `None` if any label is given by its name.
+pub(crate) fn labels_of(params: Option<&algebra_pb::QueryParams>) -> Option<Vec<LabelId>> {
+    params.map_or(Some(vec![]), |params| {
+        params
+            .tables
+            .iter()
+            .map(|label| LabelId::try_from(label.clone()).ok())
+            .collect()
+    })
+}
+
+fn refine_query_params(
+    params: Option<&mut algebra_pb::QueryParams>, scope: &Scope, statistics: &GraphStatistics,
+) {
+    if let Some(predicate) = params.and_then(|params| params.predicate.as_mut()) {
+        reorder_conjuncts(predicate, scope, statistics);
+    }
+}
+
+/// Reorder the top-level conjuncts of the predicate by their estimated selectivities, which is kept as
+/// is if the predicate is not a conjunction, e.g., of a top-level `||`.
+fn reorder_conjuncts(expr: &mut common_pb::Expression, scope: &Scope, statistics: &GraphStatistics) {
+    let items = match parse_items(&expr.operators) {
+        Some(items) => items,
+        None => return,
+    };
+    // `&&` and `||` are of the lowest precedences, which split the conjuncts
+    let mut conjuncts = vec![vec![]];
+    let mut and = None;
+    for item in items {
+        match as_logical(&item) {
+            Some(common_pb::Logical::And) => {
+                and = Some(item);
+                conjuncts.push(vec![]);
+            }
+            Some(common_pb::Logical::Or) => return,
+            _ => conjuncts.last_mut().unwrap().push(item),
+        }
+    }
+    let and = match and {
+        Some(and) => and,
+        None => return,
+    };
+    let mut estimated: Vec<(f64, Vec<Item>)> = conjuncts
+        .into_iter()
+        .map(|conjunct| (selectivity(&conjunct, scope, statistics), conjunct))
+        .collect();
+    // the sort is stable, which keeps the order of the conjuncts of the same selectivity
+    estimated.sort_by(|(s1, _), (s2, _)| s1.partial_cmp(s2).unwrap_or(Ordering::Equal));
+    let mut items = vec![];
+    for (i, (_, conjunct)) in estimated.into_iter().enumerate() {
+        if i > 0 {
+            items.push(and.clone());
+        }
+        items.extend(conjunct);
+    }
+    let mut operators = Vec::with_capacity(expr.operators.len());
+    flatten_items(items, &mut operators);
+    expr.operators = operators;
+}
+
+/// The comparison of `variable cmp constant`, which is canonicalized from `constant cmp variable` while
+/// the plan is simplified.
+fn as_comparison(
+    conjunct: &[Item],
+) -> Option<(&common_pb::Variable, common_pb::Logical, &common_pb::Value)> {
+    use common_pb::expr_opr::Item::{Const, Var};
+    match conjunct {
+        [Item::Token(left), cmp, Item::Token(right)] => match (left.item.as_ref(), right.item.as_ref()) {
+            (Some(Var(var)), Some(Const(value))) => Some((var, as_logical(cmp)?, value)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The number of the values of a list, or 1 of a single value.
+fn list_len(value: &common_pb::Value) -> usize {
+    use common_pb::value::Item;
+    match value.item.as_ref() {
+        Some(Item::I32Array(array)) => array.item.len(),
+        Some(Item::I64Array(array)) => array.item.len(),
+        Some(Item::F64Array(array)) => array.item.len(),
+        Some(Item::StrArray(array)) => array.item.len(),
+        _ => 1,
+    }
+}
+
+/// Estimate the fraction of the elements satisfying the conjunct, by the number of the distinct values
+/// of a property, or by the number of the elements for their ids.
+fn selectivity(conjunct: &[Item], scope: &Scope, statistics: &GraphStatistics) -> f64 {
+    use common_pb::Logical::*;
+    if let Some((var, cmp, value)) = as_comparison(conjunct) {
+        // the variable of a tag other than the head refers to the elements of the unknown labels
+        let scope = if var.tag.is_some() { &Scope::Unknown } else { scope };
+        let distinct = match var
+            .property
+            .as_ref()
+            .and_then(|property| property.item.as_ref())
+        {
+            Some(common_pb::property::Item::Key(key)) => NameOrId::try_from(key.clone())
+                .ok()
+                .and_then(|key| scope.ndv(statistics, &key)),
+            Some(common_pb::property::Item::Id(_)) => scope.count(statistics),
+            _ => None,
+        };
+        let eq = distinct
+            .filter(|distinct| *distinct > 0)
+            .map_or(EQ_SELECTIVITY, |distinct| 1.0 / distinct as f64);
+        match cmp {
+            Eq => eq,
+            Ne => 1.0 - eq,
+            Lt | Le | Gt | Ge => RANGE_SELECTIVITY,
+            Within => (eq * list_len(value) as f64).min(1.0),
+            Without => 1.0 - (eq * list_len(value) as f64).min(1.0),
+            Startswith | Endswith | Regex => MATCH_SELECTIVITY,
+            _ => DEFAULT_SELECTIVITY,
+        }
+    } else {
+        DEFAULT_SELECTIVITY
+    }
+}
+
+/// Reorder the sub-plans of the intersection by the estimated degrees of their last expands, which are
+/// kept as is if the degree of any sub-plan is unknown.
+fn reorder_intersection(intersect: &mut pb::Intersect, statistics: &GraphStatistics) {
+    let degrees: Option<Vec<f64>> = intersect
+        .sub_plans
+        .iter()
+        .map(|sub_plan| expand_degree(sub_plan, statistics))
+        .collect();
+    if let Some(degrees) = degrees {
+        let mut estimated: Vec<(f64, pb::PhysicalPlan)> = degrees
+            .into_iter()
+            .zip(intersect.sub_plans.drain(..))
+            .collect();
+        estimated.sort_by(|(d1, _), (d2, _)| d1.partial_cmp(d2).unwrap_or(Ordering::Equal));
+        intersect.sub_plans = estimated
+            .into_iter()
+            .map(|(_, sub_plan)| sub_plan)
+            .collect();
+    }
+}
+
+/// The average degree of the last expand of the sub-plan, which is the one to intersect on.
+fn expand_degree(sub_plan: &pb::PhysicalPlan, statistics: &GraphStatistics) -> Option<f64> {
+    for opr in sub_plan.plan.iter().rev() {
+        match OpKind::try_from(opr).ok()? {
+            OpKind::Edge(expand) => {
+                let labels = labels_of(expand.params.as_ref())?;
+                let direction = pb::edge_expand::Direction::from_i32(expand.direction)?;
+                return statistics.avg_degree(&labels, Direction::from(direction));
+            }
+            OpKind::Path(_) => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use graph_proxy::apis::DegreeHistogram;
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+
+    fn prepare_statistics() -> GraphStatistics {
+        let mut statistics = GraphStatistics::new();
+        statistics.add_vertex_count(0, 1000);
+        statistics.add_vertex_ndv(0, "name".into(), 1000);
+        statistics.add_vertex_ndv(0, "gender".into(), 2);
+        statistics.add_degrees(0, Direction::Out, DegreeHistogram::new(vec![(100, 10)]));
+        statistics.add_degrees(1, Direction::Out, DegreeHistogram::new(vec![(5, 10)]));
+        statistics
+    }
+
+    fn assert_reordered(expr: &str, expected: &str) {
+        let statistics = prepare_statistics();
+        let mut expr_pb = str_to_expr_pb(expr.to_string()).unwrap();
+        reorder_conjuncts(&mut expr_pb, &Scope::Vertices(vec![0]), &statistics);
+        assert_eq!(expr_pb, str_to_expr_pb(expected.to_string()).unwrap(), "{}", expr);
+    }
+
+    #[test]
+    fn reorder_conjuncts_test() {
+        assert_reordered("@.age > 10 && @.name == \"marko\"", "@.name == \"marko\" && @.age > 10");
+        assert_reordered(
+            "@.gender == \"F\" && @.age > 10 && @.~id within [1, 2]",
+            "@.~id within [1, 2] && @.age > 10 && @.gender == \"F\"",
+        );
+        // the conjuncts of the unknown selectivities are kept in order
+        assert_reordered("(@.age > 10 || @.age < 5) && @.x == 1", "@.x == 1 && (@.age > 10 || @.age < 5)");
+        assert_reordered("@.a == 1 && @.b == 2", "@.a == 1 && @.b == 2");
+        // not a conjunction
+        assert_reordered("@.age > 10 || @.name == \"marko\"", "@.age > 10 || @.name == \"marko\"");
+        assert_reordered("@.age > 10", "@.age > 10");
+    }
+
+    #[test]
+    fn reorder_intersection_test() {
+        let expand = |label: LabelId| -> pb::PhysicalPlan {
+            let expand = pb::EdgeExpand {
+                direction: pb::edge_expand::Direction::Out as i32,
+                params: Some(algebra_pb::QueryParams { tables: vec![label.into()], ..Default::default() }),
+                ..Default::default()
+            };
+            pb::PhysicalPlan { plan_id: 0, plan: vec![expand.into()] }
+        };
+        let statistics = prepare_statistics();
+        let mut intersect = pb::Intersect { sub_plans: vec![expand(0), expand(1)], key: 2 };
+        reorder_intersection(&mut intersect, &statistics);
+        assert_eq!(intersect.sub_plans, vec![expand(1), expand(0)]);
+        // kept as is of an unknown degree
+        let mut intersect = pb::Intersect { sub_plans: vec![expand(0), expand(2)], key: 2 };
+        reorder_intersection(&mut intersect, &statistics);
+        assert_eq!(intersect.sub_plans, vec![expand(0), expand(2)]);
+    }
+}
@@ -0,0 +1,567 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Row-level security: per-label predicate templates that are conjoined to every operator reading
+//! the vertices or edges of the label when a plan is admitted.
+//!
+//! A template is an expression on the element, e.g., `@.tenant_id == $current_tenant`, where
+//! `$name` is replaced by the attribute `name` of the caller. Numeric attributes are substituted
+//! as they are, and the others as string literals.
+//!
+//! The filters are conjoined to:
+//!   1. `Scan`, and `EdgeExpand` that expands edges, as their predicates;
+//!   2. `GetV(Itself)`, which fetches and filters vertices;
+//!   3. after `EdgeExpand` that expands vertices, or `GetV` that gets adjacent vertices, a
+//!      `Repartition` + `GetV(Itself)` is appended to filter the vertices, unless there is one
+//!      already. If the edges of the expand are filtered too, it is split into expanding edges
+//!      and getting their adjacent vertices.
+//! Expansions that can't be filtered this way, e.g., counting the degrees over filtered edges, or
+//! path expansions that get adjacent vertices while the vertices are filtered, are rejected.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+use ir_common::expr_parse::str_to_expr_pb;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+use ir_common::NameOrId;
+use pegasus_server::auth::Principal;
+
+use crate::error::{FnGenError, FnGenResult};
+
+/// The predicate templates of the vertex and edge labels of a graph.
+#[derive(Clone, Debug, Default)]
+pub struct RowFilters {
+    vertices: HashMap<NameOrId, String>,
+    edges: HashMap<NameOrId, String>,
+}
+
+impl RowFilters {
+    pub fn add_vertex_filter(&mut self, label: NameOrId, template: String) {
+        self.vertices.insert(label, template);
+    }
+
+    pub fn add_edge_filter(&mut self, label: NameOrId, template: String) {
+        self.edges.insert(label, template);
+    }
+
+    /// Conjoin the filters, instantiated with the attributes of `principal`, to the plan.
+    pub fn apply(&self, principal: Option<&Principal>, plan: &mut pb::PhysicalPlan) -> FnGenResult<()> {
+        let rewriter = RowFilterRewriter {
+            vertices: instantiate_all(&self.vertices, principal)?,
+            edges: instantiate_all(&self.edges, principal)?,
+        };
+        rewriter.rewrite(plan)
+    }
+
+    /// The filters instantiated with the attributes of `principal`, in the order of the labels, as
+    /// the key of the rows they admit.
+    pub fn instantiated_key(&self, principal: Option<&Principal>) -> FnGenResult<String> {
+        let mut filters = vec![];
+        for (kind, templates) in [("v", &self.vertices), ("e", &self.edges)].iter() {
+            for (label, filter) in instantiate_all(templates, principal)? {
+                filters.push(format!("{}:{:?}:{}", kind, label, filter));
+            }
+        }
+        filters.sort();
+        Ok(filters.join(","))
+    }
+}
+
+fn instantiate_all(
+    templates: &HashMap<NameOrId, String>, principal: Option<&Principal>,
+) -> FnGenResult<HashMap<NameOrId, String>> {
+    templates
+        .iter()
+        .map(|(label, template)| Ok((label.clone(), instantiate(template, principal)?)))
+        .collect()
+}
+
+fn instantiate(template: &str, principal: Option<&Principal>) -> FnGenResult<String> {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let name = &rest[..len];
+        let value = principal
+            .and_then(|p| p.attributes.get(name))
+            .ok_or_else(|| {
+                FnGenError::unauthorized_error(&format!("attribute `{}` of row filter is not found", name))
+            })?;
+        if value.parse::<i64>().is_ok() {
+            result.push_str(value);
+        } else if value.contains(|c| c == '"' || c == '\\') {
+            Err(FnGenError::unauthorized_error(&format!("invalid value of attribute `{}`", name)))?
+        } else {
+            result.push('"');
+            result.push_str(value);
+            result.push('"');
+        }
+        rest = &rest[len..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+struct RowFilterRewriter {
+    vertices: HashMap<NameOrId, String>,
+    edges: HashMap<NameOrId, String>,
+}
+
+impl RowFilterRewriter {
+    fn rewrite(&self, plan: &mut pb::PhysicalPlan) -> FnGenResult<()> {
+        let oprs = std::mem::take(&mut plan.plan);
+        let mut rewritten = Vec::with_capacity(oprs.len());
+        for (idx, opr) in oprs.iter().enumerate() {
+            let op_kind: OpKind = opr.try_into()?;
+            match op_kind {
+                OpKind::Scan(mut scan) => {
+                    let filters = if scan.scan_opt == pb::scan::ScanOpt::Edge as i32 {
+                        &self.edges
+                    } else {
+                        &self.vertices
+                    };
+                    let params = scan.params.get_or_insert_with(default_params);
+                    if let Some(filter) = filter_of(filters, &params.tables)? {
+                        conjoin(params, filter);
+                    }
+                    rewritten.push(with_op_kind(opr, OpKind::Scan(scan)));
+                }
+                OpKind::Edge(mut expand) => {
+                    let params = expand.params.get_or_insert_with(default_params);
+                    let edge_filter = filter_of(&self.edges, &params.tables)?;
+                    match unsafe {
+                        std::mem::transmute::<i32, pb::edge_expand::ExpandOpt>(expand.expand_opt)
+                    } {
+                        pb::edge_expand::ExpandOpt::Edge => {
+                            if let Some(filter) = edge_filter {
+                                conjoin(params, filter);
+                            }
+                            rewritten.push(with_op_kind(opr, OpKind::Edge(expand)));
+                        }
+                        pb::edge_expand::ExpandOpt::Degree => {
+                            if edge_filter.is_some() {
+                                Err(FnGenError::unauthorized_error(
+                                    "counting degrees over filtered edges is not allowed",
+                                ))?
+                            }
+                            rewritten.push(with_op_kind(opr, OpKind::Edge(expand)));
+                        }
+                        pb::edge_expand::ExpandOpt::Vertex => {
+                            if let Some(filter) = edge_filter {
+                                // expand edges to filter them, then get the adjacent vertices,
+                                // the predicate on the vertices goes to the following auxilia
+                                let vertex_params = algebra_pb::QueryParams {
+                                    columns: std::mem::take(&mut params.columns),
+                                    is_all_columns: std::mem::take(&mut params.is_all_columns),
+                                    predicate: params.predicate.take(),
+                                    sample_ratio: 1.0,
+                                    ..Default::default()
+                                };
+                                conjoin(params, filter);
+                                let alias = expand.alias.take();
+                                let opt = match unsafe {
+                                    std::mem::transmute::<i32, pb::edge_expand::Direction>(expand.direction)
+                                } {
+                                    pb::edge_expand::Direction::Out => pb::get_v::VOpt::End,
+                                    pb::edge_expand::Direction::In => pb::get_v::VOpt::Start,
+                                    pb::edge_expand::Direction::Both => pb::get_v::VOpt::Other,
+                                };
+                                expand.expand_opt = pb::edge_expand::ExpandOpt::Edge as i32;
+                                rewritten.push(with_op_kind(opr, OpKind::Edge(expand)));
+                                let mut auxilia = auxilia(vertex_params, alias);
+                                self.filter_auxilia(&mut auxilia)?;
+                                if auxilia
+                                    .params
+                                    .as_ref()
+                                    .map_or(false, |p| p.has_predicates() || p.has_columns())
+                                {
+                                    let get_v =
+                                        pb::GetV { tag: None, opt: opt as i32, params: None, alias: None };
+                                    rewritten.push(get_v.into());
+                                    rewritten.push(shuffle());
+                                    rewritten.push(auxilia.into());
+                                } else {
+                                    let get_v = pb::GetV {
+                                        tag: None,
+                                        opt: opt as i32,
+                                        params: None,
+                                        alias: auxilia.alias,
+                                    };
+                                    rewritten.push(get_v.into());
+                                }
+                            } else {
+                                let alias = expand.alias.clone();
+                                rewritten.push(with_op_kind(opr, OpKind::Edge(expand)));
+                                self.filter_adjacent_vertices(
+                                    &oprs[idx + 1..],
+                                    &[],
+                                    alias,
+                                    &mut rewritten,
+                                )?;
+                            }
+                        }
+                    }
+                }
+                OpKind::Vertex(mut get_v) => {
+                    if get_v.opt == pb::get_v::VOpt::Itself as i32 {
+                        self.filter_auxilia(&mut get_v)?;
+                        rewritten.push(with_op_kind(opr, OpKind::Vertex(get_v)));
+                    } else {
+                        let tables = get_v
+                            .params
+                            .as_ref()
+                            .map(|p| p.tables.clone())
+                            .unwrap_or_default();
+                        let alias = get_v.alias.clone();
+                        rewritten.push(with_op_kind(opr, OpKind::Vertex(get_v)));
+                        self.filter_adjacent_vertices(&oprs[idx + 1..], &tables, alias, &mut rewritten)?;
+                    }
+                }
+                OpKind::Path(mut path) => {
+                    if let Some(base) = path.base.as_mut() {
+                        self.filter_path_base(base)?;
+                    }
+                    rewritten.push(with_op_kind(opr, OpKind::Path(path)));
+                }
+                OpKind::Apply(mut apply) => {
+                    if let Some(sub_plan) = apply.sub_plan.as_mut() {
+                        self.rewrite(sub_plan)?;
+                    }
+                    rewritten.push(with_op_kind(opr, OpKind::Apply(apply)));
+                }
+                OpKind::Join(mut join) => {
+                    for sub_plan in join
+                        .left_plan
+                        .iter_mut()
+                        .chain(join.right_plan.iter_mut())
+                    {
+                        self.rewrite(sub_plan)?;
+                    }
+                    rewritten.push(with_op_kind(opr, OpKind::Join(join)));
+                }
+                OpKind::Union(mut union) => {
+                    for sub_plan in union.sub_plans.iter_mut() {
+                        self.rewrite(sub_plan)?;
+                    }
+                    rewritten.push(with_op_kind(opr, OpKind::Union(union)));
+                }
+                OpKind::Intersect(mut intersect) => {
+                    for sub_plan in intersect.sub_plans.iter_mut() {
+                        self.rewrite(sub_plan)?;
+                    }
+                    rewritten.push(with_op_kind(opr, OpKind::Intersect(intersect)));
+                }
+                OpKind::Repeat(mut repeat) => {
+                    if let Some(body) = repeat.body.as_mut() {
+                        self.rewrite(body)?;
+                    }
+                    rewritten.push(with_op_kind(opr, OpKind::Repeat(repeat)));
+                }
+                OpKind::Coalesce(mut coalesce) => {
+                    for sub_plan in coalesce.sub_plans.iter_mut() {
+                        self.rewrite(sub_plan)?;
+                    }
+                    rewritten.push(with_op_kind(opr, OpKind::Coalesce(coalesce)));
+                }
+                _ => rewritten.push(opr.clone()),
+            }
+        }
+        plan.plan = rewritten;
+        Ok(())
+    }
+
+    /// Filter the vertices just got, unless they are fetched by an auxilia right after, which is
+    /// filtered when it is rewritten.
+    fn filter_adjacent_vertices(
+        &self, following: &[pb::PhysicalOpr], tables: &[common_pb::NameOrId], alias: Option<i32>,
+        rewritten: &mut Vec<pb::PhysicalOpr>,
+    ) -> FnGenResult<()> {
+        let filter = match filter_of(&self.vertices, tables)? {
+            Some(filter) => filter,
+            None => return Ok(()),
+        };
+        let next = following
+            .iter()
+            .find(|opr| !opr.is_repartition());
+        if let Some(Ok(OpKind::Vertex(get_v))) = next.map(|opr| opr.try_into() as Result<OpKind, _>) {
+            if get_v.opt == pb::get_v::VOpt::Itself as i32 && get_v.tag.is_none() {
+                return Ok(());
+            }
+        }
+        let mut params = default_params();
+        conjoin(&mut params, filter);
+        rewritten.push(shuffle());
+        rewritten.push(auxilia(params, alias).into());
+        Ok(())
+    }
+
+    fn filter_auxilia(&self, auxilia: &mut pb::GetV) -> FnGenResult<()> {
+        let params = auxilia
+            .params
+            .get_or_insert_with(default_params);
+        if let Some(filter) = filter_of(&self.vertices, &params.tables)? {
+            conjoin(params, filter);
+        }
+        Ok(())
+    }
+
+    fn filter_path_base(&self, base: &mut pb::path_expand::ExpandBase) -> FnGenResult<()> {
+        let expand = base
+            .edge_expand
+            .as_mut()
+            .ok_or_else(|| FnGenError::ParseError("empty EdgeExpand of ExpandBase in PathExpand".into()))?;
+        let params = expand.params.get_or_insert_with(default_params);
+        let edge_filter = filter_of(&self.edges, &params.tables)?;
+        if expand.expand_opt == pb::edge_expand::ExpandOpt::Edge as i32 {
+            if let Some(filter) = edge_filter {
+                conjoin(params, filter);
+            }
+            if !self.vertices.is_empty() {
+                Err(FnGenError::unauthorized_error(
+                    "expanding paths by edges is not allowed while vertices are filtered",
+                ))?
+            }
+        } else {
+            if edge_filter.is_some() {
+                Err(FnGenError::unauthorized_error(
+                    "expanding paths by vertices is not allowed while edges are filtered",
+                ))?
+            }
+            if !self.vertices.is_empty() {
+                let get_v = base
+                    .get_v
+                    .get_or_insert_with(|| auxilia(default_params(), None));
+                self.filter_auxilia(get_v)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The conjunction of the filters of the labels in `tables`, or all labels if `tables` is empty.
+/// A filter applies only to the rows of its label unless it is the only label in `tables`.
+fn filter_of(
+    filters: &HashMap<NameOrId, String>, tables: &[common_pb::NameOrId],
+) -> FnGenResult<Option<common_pb::Expression>> {
+    let labels: Vec<NameOrId> = tables
+        .iter()
+        .map(|t| t.clone().try_into())
+        .collect::<Result<_, _>>()?;
+    let mut clauses = vec![];
+    for (label, filter) in filters.iter() {
+        if labels.len() == 1 && labels[0] == *label {
+            clauses.push(format!("({})", filter));
+        } else if labels.is_empty() || labels.contains(label) {
+            let label = match label {
+                NameOrId::Str(name) => format!("\"{}\"", name),
+                NameOrId::Id(id) => id.to_string(),
+            };
+            clauses.push(format!("(@.~label != {} || ({}))", label, filter));
+        }
+    }
+    if clauses.is_empty() {
+        return Ok(None);
+    }
+    clauses.sort();
+    let expr = clauses.join(" && ");
+    let filter = str_to_expr_pb(expr.clone())
+        .map_err(|e| FnGenError::ParseError(format!("invalid row filter {}: {:?}", expr, e).into()))?;
+    Ok(Some(filter))
+}
+
+fn conjoin(params: &mut algebra_pb::QueryParams, filter: common_pb::Expression) {
+    let predicate = match params.predicate.take() {
+        Some(predicate) if !predicate.operators.is_empty() => {
+            let mut operators = Vec::with_capacity(predicate.operators.len() + filter.operators.len() + 5);
+            operators.push(brace(common_pb::expr_opr::Brace::LeftBrace));
+            operators.extend(predicate.operators);
+            operators.push(brace(common_pb::expr_opr::Brace::RightBrace));
+            operators.push(common_pb::Logical::And.into());
+            operators.push(brace(common_pb::expr_opr::Brace::LeftBrace));
+            operators.extend(filter.operators);
+            operators.push(brace(common_pb::expr_opr::Brace::RightBrace));
+            common_pb::Expression { operators }
+        }
+        _ => filter,
+    };
+    params.predicate = Some(predicate);
+}
+
+/// Replace the operator of `opr`, keeping its meta data.
+fn with_op_kind(opr: &pb::PhysicalOpr, op_kind: OpKind) -> pb::PhysicalOpr {
+    let mut opr = opr.clone();
+    opr.opr = Some(pb::physical_opr::Operator { op_kind: Some(op_kind) });
+    opr
+}
+
+fn brace(brace: common_pb::expr_opr::Brace) -> common_pb::ExprOpr {
+    common_pb::ExprOpr { node_type: None, item: Some(common_pb::expr_opr::Item::Brace(brace as i32)) }
+}
+
+fn default_params() -> algebra_pb::QueryParams {
+    algebra_pb::QueryParams { sample_ratio: 1.0, ..Default::default() }
+}
+
+fn auxilia(params: algebra_pb::QueryParams, alias: Option<i32>) -> pb::GetV {
+    pb::GetV { tag: None, opt: pb::get_v::VOpt::Itself as i32, params: Some(params), alias }
+}
+
+fn shuffle() -> pb::PhysicalOpr {
+    pb::Repartition {
+        strategy: Some(pb::repartition::Strategy::ToAnother(pb::repartition::Shuffle {
+            shuffle_key: None,
+        })),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn principal() -> Principal {
+        Principal::new("marko").with_attribute("current_tenant", "t1")
+    }
+
+    fn filters() -> RowFilters {
+        let mut filters = RowFilters::default();
+        filters.add_vertex_filter(NameOrId::Id(1), "@.tenant_id == $current_tenant".to_string());
+        filters
+    }
+
+    fn plan(oprs: Vec<pb::PhysicalOpr>) -> pb::PhysicalPlan {
+        pb::PhysicalPlan { plan_id: 0, plan: oprs }
+    }
+
+    fn scan(tables: Vec<i32>) -> pb::PhysicalOpr {
+        pb::Scan {
+            scan_opt: 0,
+            alias: None,
+            params: Some(algebra_pb::QueryParams {
+                tables: tables.into_iter().map(|l| l.into()).collect(),
+                predicate: str_to_expr_pb("@.age > 10".to_string()).ok(),
+                ..default_params()
+            }),
+            idx_predicate: None,
+            is_count_only: false,
+        }
+        .into()
+    }
+
+    fn predicate_of(opr: &pb::PhysicalOpr) -> Option<common_pb::Expression> {
+        let op_kind: Result<OpKind, _> = opr.try_into();
+        match op_kind {
+            Ok(OpKind::Scan(scan)) => scan.params.unwrap().predicate,
+            Ok(OpKind::Vertex(get_v)) => get_v.params.unwrap().predicate,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn instantiate_test() {
+        let principal = principal().with_attribute("level", "3");
+        assert_eq!(
+            instantiate("@.tenant_id == $current_tenant && @.level <= $level", Some(&principal)).unwrap(),
+            "@.tenant_id == \"t1\" && @.level <= 3"
+        );
+        assert!(instantiate("@.region == $region", Some(&principal)).is_err());
+        assert!(instantiate("@.tenant_id == $current_tenant", None).is_err());
+        let principal = Principal::new("josh").with_attribute("current_tenant", "t1\" || true");
+        assert!(instantiate("@.tenant_id == $current_tenant", Some(&principal)).is_err());
+    }
+
+    #[test]
+    fn filter_scan_test() {
+        let mut plan1 = plan(vec![scan(vec![1])]);
+        filters()
+            .apply(Some(&principal()), &mut plan1)
+            .unwrap();
+        assert_eq!(
+            predicate_of(&plan1.plan[0]),
+            str_to_expr_pb("(@.age > 10) && ((@.tenant_id == \"t1\"))".to_string()).ok()
+        );
+
+        let mut plan2 = plan(vec![scan(vec![1, 2])]);
+        filters()
+            .apply(Some(&principal()), &mut plan2)
+            .unwrap();
+        assert_eq!(
+            predicate_of(&plan2.plan[0]),
+            str_to_expr_pb("(@.age > 10) && ((@.~label != 1 || (@.tenant_id == \"t1\")))".to_string()).ok()
+        );
+
+        // label 2 is not filtered
+        let mut plan3 = plan(vec![scan(vec![2])]);
+        filters()
+            .apply(Some(&principal()), &mut plan3)
+            .unwrap();
+        assert_eq!(predicate_of(&plan3.plan[0]), str_to_expr_pb("@.age > 10".to_string()).ok());
+    }
+
+    #[test]
+    fn filter_expand_test() {
+        let expand = pb::EdgeExpand {
+            v_tag: None,
+            direction: 0,
+            params: None,
+            alias: Some(1),
+            expand_opt: pb::edge_expand::ExpandOpt::Vertex as i32,
+            is_optional: false,
+        };
+        let mut plan1 = plan(vec![scan(vec![1]), expand.clone().into()]);
+        filters()
+            .apply(Some(&principal()), &mut plan1)
+            .unwrap();
+        assert_eq!(plan1.plan.len(), 4);
+        assert!(plan1.plan[2].is_repartition());
+        assert_eq!(
+            predicate_of(&plan1.plan[3]),
+            str_to_expr_pb("(@.~label != 1 || (@.tenant_id == \"t1\"))".to_string()).ok()
+        );
+
+        // the auxilia already there is filtered instead
+        let auxilia: pb::PhysicalOpr = auxilia(default_params(), Some(1)).into();
+        let mut plan2 = plan(vec![scan(vec![1]), expand.clone().into(), shuffle(), auxilia]);
+        filters()
+            .apply(Some(&principal()), &mut plan2)
+            .unwrap();
+        assert_eq!(plan2.plan.len(), 4);
+        assert_eq!(predicate_of(&plan2.plan[3]), predicate_of(&plan1.plan[3]));
+
+        // edges are filtered, so the expand is split
+        let mut filters = filters();
+        filters.add_edge_filter(NameOrId::Id(0), "@.tenant_id == $current_tenant".to_string());
+        let mut plan3 = plan(vec![expand.into()]);
+        filters
+            .apply(Some(&principal()), &mut plan3)
+            .unwrap();
+        assert_eq!(plan3.plan.len(), 4);
+        let op_kind: Result<OpKind, _> = (&plan3.plan[0]).try_into();
+        match op_kind {
+            Ok(OpKind::Edge(expand)) => {
+                assert_eq!(expand.expand_opt, pb::edge_expand::ExpandOpt::Edge as i32);
+                assert!(expand.params.unwrap().predicate.is_some());
+            }
+            _ => panic!("expand edges is expected"),
+        }
+    }
+}
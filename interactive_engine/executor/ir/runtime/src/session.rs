@@ -0,0 +1,310 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Session-scoped variables: a job submitted with a session, i.e., the `session-id` metadata of the
+//! request, binds the results of `StoreVar` to named variables of the session, which are loaded by
+//! `LoadVar` in the following jobs of the same session, e.g., `store('x')` across requests of
+//! Gremlin, or `WITH` across statements of Cypher.
+//!
+//! The variables are kept by each server in the registry of the process, where a variable is
+//! bounded in size, and a session is cleaned up lazily after being idle for its time-to-live. The
+//! sessions are enabled by `gaia.session.max.entries` of the server, with the time-to-live of
+//! `gaia.session.ttl.ms`, one hour by default.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+
+use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
+use crate::process::entry::CollectionEntry;
+
+const DEFAULT_SESSION_TTL_MS: u64 = 3600 * 1000;
+
+struct Session {
+    variables: HashMap<String, CollectionEntry>,
+    last_access: Instant,
+}
+
+/// The variables of the sessions in the process.
+pub struct SessionRegistry {
+    /// The maximum number of entries of a variable
+    max_entries: usize,
+    /// The time-to-live of an idle session
+    ttl: Duration,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl SessionRegistry {
+    pub fn new(max_entries: usize, ttl: Duration) -> Self {
+        SessionRegistry { max_entries, ttl, sessions: Mutex::new(HashMap::new()) }
+    }
+
+    /// The registry configured by the options of the server, or `None` if the sessions are disabled.
+    pub fn from_options(options: &HashMap<String, String>) -> FnGenResult<Option<Self>> {
+        let parse = |key: &str| -> FnGenResult<Option<u64>> {
+            options
+                .get(key)
+                .map(|value| {
+                    value.trim().parse::<u64>().map_err(|e| {
+                        FnGenError::session_error(&format!("parse {} of {} failed: {}", key, value, e))
+                    })
+                })
+                .transpose()
+        };
+        match parse("gaia.session.max.entries")? {
+            Some(max_entries) => {
+                let ttl = parse("gaia.session.ttl.ms")?.unwrap_or(DEFAULT_SESSION_TTL_MS);
+                Ok(Some(SessionRegistry::new(max_entries as usize, Duration::from_millis(ttl))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Bind the collection to the variable of the session, replacing the one bound before.
+    pub fn bind(&self, session: &str, name: &str, value: CollectionEntry) -> FnExecResult<()> {
+        if value.inner.len() > self.max_entries {
+            Err(FnExecError::session_error(&format!(
+                "variable `{}` of {} entries exceeds the limit {}",
+                name,
+                value.inner.len(),
+                self.max_entries
+            )))?
+        }
+        let now = Instant::now();
+        let mut sessions = self.lock()?;
+        self.evict(&mut sessions, now);
+        let session = sessions
+            .entry(session.to_string())
+            .or_insert_with(|| Session { variables: HashMap::new(), last_access: now });
+        session.last_access = now;
+        session
+            .variables
+            .insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Get the collection bound to the variable of the session, which keeps the session alive.
+    pub fn get(&self, session: &str, name: &str) -> FnExecResult<Option<CollectionEntry>> {
+        let now = Instant::now();
+        let mut sessions = self.lock()?;
+        self.evict(&mut sessions, now);
+        Ok(sessions.get_mut(session).and_then(|session| {
+            session.last_access = now;
+            session.variables.get(name).cloned()
+        }))
+    }
+
+    /// Close the session with its variables, returning whether it is found.
+    pub fn close(&self, session: &str) -> FnExecResult<bool> {
+        Ok(self.lock()?.remove(session).is_some())
+    }
+
+    /// Clean up the sessions idle for longer than the time-to-live, returning the number of them.
+    pub fn evict_expired(&self) -> FnExecResult<usize> {
+        let mut sessions = self.lock()?;
+        Ok(self.evict(&mut sessions, Instant::now()))
+    }
+
+    fn evict(&self, sessions: &mut HashMap<String, Session>, now: Instant) -> usize {
+        let len = sessions.len();
+        sessions.retain(|_, session| now.duration_since(session.last_access) <= self.ttl);
+        len - sessions.len()
+    }
+
+    fn lock(&self) -> FnExecResult<std::sync::MutexGuard<HashMap<String, Session>>> {
+        self.sessions
+            .lock()
+            .map_err(|e| FnExecError::session_error(&format!("{:?}", e)))
+    }
+}
+
+/// Set the session of the job to the `StoreVar` and `LoadVar` operators of the plan, including those
+/// in the subplans, which overrides any session given in the plan, so that a job can only access the
+/// variables of its own session.
+pub fn bind_session(plan: &mut pb::PhysicalPlan, session: &str) -> FnGenResult<()> {
+    for opr in plan.plan.iter_mut() {
+        let op_kind: OpKind = (&*opr).try_into()?;
+        let op_kind = match op_kind {
+            OpKind::StoreVar(mut store_var) => {
+                store_var.session = session.to_string();
+                OpKind::StoreVar(store_var)
+            }
+            OpKind::LoadVar(mut load_var) => {
+                load_var.session = session.to_string();
+                OpKind::LoadVar(load_var)
+            }
+            OpKind::Apply(mut apply) => {
+                if let Some(sub_plan) = apply.sub_plan.as_mut() {
+                    bind_session(sub_plan, session)?;
+                }
+                OpKind::Apply(apply)
+            }
+            OpKind::Join(mut join) => {
+                for sub_plan in join
+                    .left_plan
+                    .iter_mut()
+                    .chain(join.right_plan.iter_mut())
+                {
+                    bind_session(sub_plan, session)?;
+                }
+                OpKind::Join(join)
+            }
+            OpKind::Union(mut union) => {
+                for sub_plan in union.sub_plans.iter_mut() {
+                    bind_session(sub_plan, session)?;
+                }
+                OpKind::Union(union)
+            }
+            OpKind::Intersect(mut intersect) => {
+                for sub_plan in intersect.sub_plans.iter_mut() {
+                    bind_session(sub_plan, session)?;
+                }
+                OpKind::Intersect(intersect)
+            }
+            OpKind::Repeat(mut repeat) => {
+                if let Some(body) = repeat.body.as_mut() {
+                    bind_session(body, session)?;
+                }
+                OpKind::Repeat(repeat)
+            }
+            OpKind::Coalesce(mut coalesce) => {
+                for sub_plan in coalesce.sub_plans.iter_mut() {
+                    bind_session(sub_plan, session)?;
+                }
+                OpKind::Coalesce(coalesce)
+            }
+            _ => continue,
+        };
+        opr.opr = Some(pb::physical_opr::Operator { op_kind: Some(op_kind) });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ir_common::generated::algebra as algebra_pb;
+
+    use super::*;
+    use crate::process::entry::DynEntry;
+
+    fn collection(ids: Vec<i64>) -> CollectionEntry {
+        CollectionEntry {
+            inner: ids
+                .into_iter()
+                .map(|id| DynEntry::new(object!(id)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn from_options_test() {
+        let mut options = HashMap::new();
+        assert!(SessionRegistry::from_options(&options)
+            .unwrap()
+            .is_none());
+        options.insert("gaia.session.max.entries".to_string(), "100".to_string());
+        let registry = SessionRegistry::from_options(&options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(registry.max_entries(), 100);
+        assert_eq!(registry.ttl, Duration::from_millis(DEFAULT_SESSION_TTL_MS));
+        options.insert("gaia.session.ttl.ms".to_string(), "1s".to_string());
+        assert!(SessionRegistry::from_options(&options).is_err());
+    }
+
+    #[test]
+    fn bind_and_get_test() {
+        let registry = SessionRegistry::new(3, Duration::from_secs(60));
+        registry
+            .bind("s1", "x", collection(vec![1, 2]))
+            .unwrap();
+        assert_eq!(registry.get("s1", "x").unwrap(), Some(collection(vec![1, 2])));
+        // variables are isolated among the sessions
+        assert_eq!(registry.get("s2", "x").unwrap(), None);
+        assert_eq!(registry.get("s1", "y").unwrap(), None);
+        // rebinding replaces the variable
+        registry
+            .bind("s1", "x", collection(vec![3]))
+            .unwrap();
+        assert_eq!(registry.get("s1", "x").unwrap(), Some(collection(vec![3])));
+        assert!(registry.close("s1").unwrap());
+        assert_eq!(registry.get("s1", "x").unwrap(), None);
+        assert!(!registry.close("s1").unwrap());
+    }
+
+    #[test]
+    fn bind_exceeding_limit_test() {
+        let registry = SessionRegistry::new(3, Duration::from_secs(60));
+        assert!(registry
+            .bind("s1", "x", collection(vec![1, 2, 3, 4]))
+            .is_err());
+        assert_eq!(registry.get("s1", "x").unwrap(), None);
+    }
+
+    #[test]
+    fn evict_expired_test() {
+        let registry = SessionRegistry::new(3, Duration::from_millis(50));
+        registry
+            .bind("s1", "x", collection(vec![1]))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        registry
+            .bind("s2", "x", collection(vec![2]))
+            .unwrap();
+        // s1 is evicted lazily when s2 is bound
+        assert_eq!(registry.evict_expired().unwrap(), 0);
+        assert_eq!(registry.get("s1", "x").unwrap(), None);
+        assert_eq!(registry.get("s2", "x").unwrap(), Some(collection(vec![2])));
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(registry.evict_expired().unwrap(), 1);
+    }
+
+    #[test]
+    fn bind_session_test() {
+        let store_var =
+            algebra_pb::StoreVar { name: "x".to_string(), tag: None, session: "s2".to_string() };
+        let load_var = algebra_pb::LoadVar { name: "x".to_string(), alias: None, session: String::new() };
+        let sub_plan = pb::PhysicalPlan { plan: vec![OpKind::LoadVar(load_var).into()], plan_id: 0 };
+        let apply = pb::Apply { join_kind: 0, keys: vec![], sub_plan: Some(sub_plan), alias: None };
+        let mut plan = pb::PhysicalPlan {
+            plan: vec![OpKind::StoreVar(store_var).into(), OpKind::Apply(apply).into()],
+            plan_id: 0,
+        };
+        bind_session(&mut plan, "s1").unwrap();
+
+        match (&plan.plan[0]).try_into().unwrap() {
+            OpKind::StoreVar(store_var) => assert_eq!(store_var.session, "s1"),
+            _ => unreachable!(),
+        }
+        match (&plan.plan[1]).try_into().unwrap() {
+            OpKind::Apply(apply) => match (&apply.sub_plan.unwrap().plan[0])
+                .try_into()
+                .unwrap()
+            {
+                OpKind::LoadVar(load_var) => assert_eq!(load_var.session, "s1"),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+}
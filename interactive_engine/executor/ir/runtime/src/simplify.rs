@@ -0,0 +1,475 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Simplify the expressions of a plan while it is assembled, to reduce the cost of evaluating them per
+//! record without any change of the compiler frontend:
+//!   1. fold the constants, e.g., `@.age > 10 + 5` into `@.age > 15`, and `(1 < 2)` into `true`;
+//!   2. canonicalize the comparisons of a constant and a variable, e.g., `10 < @.age` into `@.age > 10`;
+//!   3. of the predicates, remove the always-true conjuncts and the always-false disjuncts, e.g.,
+//!      `@.age > 10 && true` into `@.age > 10`, and a select of an always-true predicate is removed.
+//!
+//! A term of a variable is never removed by its always-false conjunct or always-true disjunct, e.g.,
+//! `@.age > 10 || true` is kept, as a property absent fails the whole predicate rather than being a
+//! null, i.e., the predicate is false even if a disjunct is always true; a predicate is only reduced to
+//! `false` as a whole, if each of its disjuncts has an always-false conjunct.
+//!
+//! The IN-lists of the predicates are probed by the hash sets, see `CompiledPredicates` of the select.
+
+use std::convert::{TryFrom, TryInto};
+
+use dyn_type::{Object, Primitives};
+use graph_proxy::utils::expr::eval::{Evaluate, Evaluator, NoneContext};
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::common as common_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+
+use crate::error::FnGenResult;
+use crate::procedure::sub_plans_mut;
+
+/// Simplify the expressions of the operators of the plan and its sub-plans.
+pub fn simplify_plan(plan: &mut pb::PhysicalPlan) -> FnGenResult<()> {
+    let mut simplified = Vec::with_capacity(plan.plan.len());
+    for mut opr in plan.plan.drain(..) {
+        let mut op_kind: OpKind = (&opr).try_into()?;
+        match &mut op_kind {
+            OpKind::Scan(scan) => simplify_query_params(scan.params.as_mut()),
+            OpKind::Edge(expand) => simplify_query_params(expand.params.as_mut()),
+            OpKind::Vertex(get_v) => simplify_query_params(get_v.params.as_mut()),
+            OpKind::Path(path) => {
+                if let Some(base) = path.base.as_mut() {
+                    if let Some(expand) = base.edge_expand.as_mut() {
+                        simplify_query_params(expand.params.as_mut());
+                    }
+                    if let Some(get_v) = base.get_v.as_mut() {
+                        simplify_query_params(get_v.params.as_mut());
+                    }
+                }
+                if let Some(condition) = path.condition.as_mut() {
+                    simplify_expr(condition, true);
+                }
+            }
+            OpKind::Select(select) => {
+                if let Some(predicate) = select.predicate.as_mut() {
+                    simplify_expr(predicate, true);
+                    if const_bool(&predicate.operators) == Some(true) {
+                        continue;
+                    }
+                }
+            }
+            OpKind::Project(project) => {
+                for mapping in project.mappings.iter_mut() {
+                    if let Some(expr) = mapping.expr.as_mut() {
+                        simplify_expr(expr, false);
+                    }
+                }
+            }
+            _ => {
+                for sub_plan in sub_plans_mut(&mut op_kind) {
+                    simplify_plan(sub_plan)?;
+                }
+            }
+        }
+        opr.opr = Some(pb::physical_opr::Operator { op_kind: Some(op_kind) });
+        simplified.push(opr);
+    }
+    plan.plan = simplified;
+    Ok(())
+}
+
+fn simplify_query_params(params: Option<&mut algebra_pb::QueryParams>) {
+    if let Some(params) = params {
+        if let Some(predicate) = params.predicate.as_mut() {
+            simplify_expr(predicate, true);
+            if const_bool(&predicate.operators) == Some(true) {
+                params.predicate = None;
+            }
+        }
+    }
+}
+
+/// Simplify the expression, where a predicate, i.e., evaluated as a `bool`, is further simplified by
+/// its always-true or always-false terms.
+pub fn simplify_expr(expr: &mut common_pb::Expression, is_predicate: bool) {
+    if let Some(items) = parse_items(&expr.operators) {
+        let mut operators = Vec::with_capacity(expr.operators.len());
+        flatten_items(simplify_items(items, is_predicate, true), &mut operators);
+        expr.operators = operators;
+    }
+}
+
+/// A token of the expression, or the tokens enclosed by a pair of braces.
+#[derive(Debug, Clone)]
+pub(crate) enum Item {
+    Token(common_pb::ExprOpr),
+    Group(Vec<Item>),
+}
+
+pub(crate) fn parse_items(operators: &[common_pb::ExprOpr]) -> Option<Vec<Item>> {
+    let mut stack: Vec<Vec<Item>> = vec![vec![]];
+    for opr in operators {
+        match opr.item {
+            Some(common_pb::expr_opr::Item::Brace(0)) => stack.push(vec![]),
+            Some(common_pb::expr_opr::Item::Brace(_)) => {
+                let group = stack.pop()?;
+                stack.last_mut()?.push(Item::Group(group));
+            }
+            _ => stack.last_mut()?.push(Item::Token(opr.clone())),
+        }
+    }
+    if stack.len() == 1 {
+        stack.pop()
+    } else {
+        None
+    }
+}
+
+pub(crate) fn flatten_items(items: Vec<Item>, operators: &mut Vec<common_pb::ExprOpr>) {
+    for item in items {
+        match item {
+            Item::Token(opr) => operators.push(opr),
+            Item::Group(group) => {
+                operators.push(brace(0));
+                flatten_items(group, operators);
+                operators.push(brace(1));
+            }
+        }
+    }
+}
+
+fn brace(brace: i32) -> common_pb::ExprOpr {
+    common_pb::ExprOpr { item: Some(common_pb::expr_opr::Item::Brace(brace)), node_type: None }
+}
+
+pub(crate) fn as_logical(item: &Item) -> Option<common_pb::Logical> {
+    match item {
+        Item::Token(common_pb::ExprOpr { item: Some(common_pb::expr_opr::Item::Logical(l)), .. }) => {
+            common_pb::Logical::from_i32(*l)
+        }
+        _ => None,
+    }
+}
+
+fn is_arith(item: &Item) -> bool {
+    matches!(item, Item::Token(common_pb::ExprOpr { item: Some(common_pb::expr_opr::Item::Arith(_)), .. }))
+}
+
+fn is_const(item: &Item) -> bool {
+    matches!(item, Item::Token(common_pb::ExprOpr { item: Some(common_pb::expr_opr::Item::Const(_)), .. }))
+}
+
+fn is_var(item: &Item) -> bool {
+    matches!(item, Item::Token(common_pb::ExprOpr { item: Some(common_pb::expr_opr::Item::Var(_)), .. }))
+}
+
+fn const_bool(operators: &[common_pb::ExprOpr]) -> Option<bool> {
+    match operators {
+        [common_pb::ExprOpr { item: Some(common_pb::expr_opr::Item::Const(value)), .. }] => {
+            match value.item {
+                Some(common_pb::value::Item::Boolean(b)) => Some(b),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn item_bool(items: &[Item]) -> Option<bool> {
+    match items {
+        [Item::Token(opr)] => const_bool(std::slice::from_ref(opr)),
+        _ => None,
+    }
+}
+
+/// Evaluate the constant tokens, and only fold a primitive or a string, which converts back to the
+/// same `Value` without loss.
+fn eval_const(items: &[Item]) -> Option<Item> {
+    let mut operators = Vec::with_capacity(items.len());
+    flatten_items(items.to_vec(), &mut operators);
+    let evaluator = Evaluator::try_from(common_pb::Expression { operators }).ok()?;
+    match evaluator.eval::<(), NoneContext>(None).ok()? {
+        Object::Primitive(Primitives::ULLong(_)) => None,
+        obj @ Object::Primitive(_) | obj @ Object::String(_) => {
+            Some(Item::Token(common_pb::Value::from(obj).into()))
+        }
+        _ => None,
+    }
+}
+
+/// Simplify the items, which are the whole predicate if `is_top`, rather than a group of it, e.g., a
+/// negated one.
+fn simplify_items(items: Vec<Item>, is_predicate: bool, is_top: bool) -> Vec<Item> {
+    // simplify the groups inside out, where a group of a constant is the constant
+    let items = items.into_iter().map(|item| match item {
+        Item::Group(group) => {
+            let mut group = simplify_items(group, is_predicate, false);
+            if group.len() == 1 && is_const(&group[0]) {
+                group.pop().unwrap()
+            } else {
+                Item::Group(group)
+            }
+        }
+        token => token,
+    });
+    // `&&` and `||` are of the lowest precedences, which split the terms operated before them
+    let mut terms = vec![vec![]];
+    let mut connectives = vec![];
+    for item in items {
+        match as_logical(&item) {
+            Some(logical @ common_pb::Logical::And) | Some(logical @ common_pb::Logical::Or) => {
+                connectives.push(logical);
+                terms.push(vec![]);
+            }
+            _ => terms.last_mut().unwrap().push(item),
+        }
+    }
+    let terms: Vec<Vec<Item>> = terms.into_iter().map(simplify_term).collect();
+    if is_predicate && !connectives.is_empty() && terms.iter().all(|term| !term.is_empty()) {
+        simplify_connectives(terms, connectives, is_top)
+    } else {
+        join_terms(terms, connectives)
+    }
+}
+
+fn simplify_term(mut term: Vec<Item>) -> Vec<Item> {
+    let is_foldable = |item: &Item| is_const(item) || is_arith(item) || as_logical(item).is_some();
+    if term.len() > 1 && term.iter().all(is_foldable) {
+        if let Some(folded) = eval_const(&term) {
+            return vec![folded];
+        }
+    }
+    // fold a run of `const (arith const)+`, which is not operated by another arithmetic before or after
+    let mut i = 0;
+    while i < term.len() {
+        let mut end = i + 1;
+        if is_const(&term[i]) && (i == 0 || !is_arith(&term[i - 1])) {
+            while end + 1 < term.len() && is_arith(&term[end]) && is_const(&term[end + 1]) {
+                end += 2;
+            }
+            if end > i + 1 && (end == term.len() || !is_arith(&term[end])) {
+                if let Some(folded) = eval_const(&term[i..end]) {
+                    term.splice(i..end, std::iter::once(folded));
+                    end = i + 1;
+                }
+            }
+        }
+        i = end;
+    }
+    // `const cmp var` into `var flip(cmp) const`
+    if term.len() == 3 && is_const(&term[0]) && is_var(&term[2]) {
+        if let Some(flipped) = as_logical(&term[1]).and_then(flip) {
+            term.swap(0, 2);
+            term[1] = Item::Token(flipped.into());
+        }
+    }
+    term
+}
+
+fn flip(cmp: common_pb::Logical) -> Option<common_pb::Logical> {
+    use common_pb::Logical::*;
+    match cmp {
+        Eq | Ne => Some(cmp),
+        Lt => Some(Gt),
+        Le => Some(Ge),
+        Gt => Some(Lt),
+        Ge => Some(Le),
+        _ => None,
+    }
+}
+
+/// Simplify the disjunction of the conjunctions of the terms, as `&&` precedes `||`, by the identities
+/// that hold even if a variable is absent, i.e., only the constant terms are removed.
+fn simplify_connectives(
+    terms: Vec<Vec<Item>>, connectives: Vec<common_pb::Logical>, is_top: bool,
+) -> Vec<Item> {
+    let mut terms = terms.into_iter();
+    let mut disjuncts = vec![vec![terms.next().unwrap()]];
+    for (connective, term) in connectives.into_iter().zip(terms) {
+        if connective == common_pb::Logical::Or {
+            disjuncts.push(vec![term]);
+        } else {
+            disjuncts.last_mut().unwrap().push(term);
+        }
+    }
+    // each disjunct of an always-false conjunct is false, or absent or failed otherwise
+    if is_top
+        && disjuncts.iter().all(|conjuncts| {
+            conjuncts
+                .iter()
+                .any(|term| item_bool(term) == Some(false))
+        })
+    {
+        return vec![Item::Token(common_pb::Value::from(false).into())];
+    }
+    let mut simplified: Vec<Vec<Vec<Item>>> = vec![];
+    for conjuncts in disjuncts {
+        // `x && true` is `x`
+        let conjuncts: Vec<Vec<Item>> = conjuncts
+            .into_iter()
+            .filter(|term| item_bool(term) != Some(true))
+            .collect();
+        let conjuncts = if conjuncts.is_empty() {
+            vec![vec![Item::Token(common_pb::Value::from(true).into())]]
+        } else if conjuncts
+            .iter()
+            .all(|term| item_bool(term) == Some(false))
+        {
+            vec![vec![Item::Token(common_pb::Value::from(false).into())]]
+        } else {
+            conjuncts
+        };
+        simplified.push(conjuncts);
+    }
+    let is_const_bool =
+        |conjuncts: &Vec<Vec<Item>>| conjuncts.len() == 1 && item_bool(&conjuncts[0]).is_some();
+    // the disjuncts are all constant, which are folded as a whole
+    if simplified.iter().all(is_const_bool) {
+        let any = simplified
+            .iter()
+            .any(|conjuncts| item_bool(&conjuncts[0]) == Some(true));
+        return vec![Item::Token(common_pb::Value::from(any).into())];
+    }
+    // `x || false` is `x`
+    simplified.retain(|conjuncts| !is_const_bool(conjuncts) || item_bool(&conjuncts[0]) == Some(true));
+    let mut terms = vec![];
+    let mut connectives = vec![];
+    for conjuncts in simplified {
+        if !terms.is_empty() {
+            connectives.push(common_pb::Logical::Or);
+        }
+        for (i, term) in conjuncts.into_iter().enumerate() {
+            if i > 0 {
+                connectives.push(common_pb::Logical::And);
+            }
+            terms.push(term);
+        }
+    }
+    join_terms(terms, connectives)
+}
+
+fn join_terms(terms: Vec<Vec<Item>>, connectives: Vec<common_pb::Logical>) -> Vec<Item> {
+    let mut items = vec![];
+    let mut connectives = connectives.into_iter();
+    for (i, term) in terms.into_iter().enumerate() {
+        if i > 0 {
+            if let Some(connective) = connectives.next() {
+                items.push(Item::Token(connective.into()));
+            }
+        }
+        items.extend(term);
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+
+    fn simplify(expr: &str, is_predicate: bool) -> common_pb::Expression {
+        let mut expr = str_to_expr_pb(expr.to_string()).unwrap();
+        simplify_expr(&mut expr, is_predicate);
+        expr
+    }
+
+    fn assert_simplified(expr: &str, expected: &str) {
+        assert_eq!(simplify(expr, true), str_to_expr_pb(expected.to_string()).unwrap(), "{}", expr);
+    }
+
+    #[test]
+    fn fold_constants_test() {
+        assert_simplified("@.age > 10 + 5", "@.age > 15");
+        assert_simplified("@.age > 2 * 3 + 4", "@.age > 10");
+        assert_simplified("@.age > (1 + 2) * 3", "@.age > 9");
+        assert_simplified("@.name == \"John\" && 1 < 2", "@.name == \"John\"");
+        // not folded across the operands of a variable
+        assert_simplified("@.age + 2 + 3 > 10", "@.age + 2 + 3 > 10");
+        assert_simplified("1 + 2 * @.age > 10", "1 + 2 * @.age > 10");
+        // a projection is only folded
+        assert_eq!(simplify("@.age + (1 + 2)", false), str_to_expr_pb("@.age + 3".to_string()).unwrap());
+        assert_eq!(
+            simplify("@.name && true", false),
+            str_to_expr_pb("@.name && true".to_string()).unwrap()
+        );
+    }
+
+    #[test]
+    fn canonicalize_comparison_test() {
+        assert_simplified("10 < @.age", "@.age > 10");
+        assert_simplified("10 >= @.age && \"John\" == @.name", "@.age <= 10 && @.name == \"John\"");
+        assert_simplified("10 within @.ids", "10 within @.ids");
+    }
+
+    #[test]
+    fn simplify_predicates_test() {
+        assert_simplified("@.age > 10 && true", "@.age > 10");
+        assert_simplified("true && @.age > 10 && @.name == \"John\"", "@.age > 10 && @.name == \"John\"");
+        assert_simplified("@.age > 10 && false", "false");
+        assert_simplified("@.age > 10 && false || 1 > 2", "false");
+        assert_simplified("false || @.age > 10", "@.age > 10");
+        assert_simplified("1 > 2 || true && 2 > 1", "true");
+        assert_simplified(
+            "(@.age > 10 || 1 > 2) && (@.name == \"John\")",
+            "(@.age > 10) && (@.name == \"John\")",
+        );
+        assert_simplified("!(1 > 2) && @.age > 10", "@.age > 10");
+    }
+
+    #[test]
+    fn simplify_predicates_of_absent_test() {
+        // a predicate of `@.age` absent is false, even if a disjunct is always true
+        assert_simplified("@.age > 10 || true", "@.age > 10 || true");
+        assert_simplified(
+            "@.age > 10 && false || @.name == \"John\"",
+            "@.age > 10 && false || @.name == \"John\"",
+        );
+        // a negated group is not false as a whole, which is false if `@.age` is absent
+        assert_simplified("!(@.age > 10 && false)", "!(@.age > 10 && false)");
+        assert_simplified("!(@.age > 10 || 1 > 2)", "!(@.age > 10)");
+    }
+
+    #[test]
+    fn simplify_plan_test() {
+        let select = |predicate: &str| -> pb::PhysicalOpr {
+            OpKind::Select(algebra_pb::Select {
+                predicate: Some(str_to_expr_pb(predicate.to_string()).unwrap()),
+            })
+            .into()
+        };
+        let mut plan = pb::PhysicalPlan {
+            plan_id: 1,
+            plan: vec![
+                select("1 < 2 && (2 > 1 || 3 > 4)"),
+                select("@.age > 10 || 1 < 2"),
+                select("10 < @.age && true"),
+            ],
+        };
+        simplify_plan(&mut plan).unwrap();
+        assert_eq!(plan.plan.len(), 2);
+        assert_eq!(
+            OpKind::try_from(&plan.plan[0]).unwrap(),
+            OpKind::Select(algebra_pb::Select {
+                predicate: Some(str_to_expr_pb("@.age > 10 || true".to_string()).unwrap())
+            })
+        );
+        assert_eq!(
+            OpKind::try_from(&plan.plan[1]).unwrap(),
+            OpKind::Select(algebra_pb::Select {
+                predicate: Some(str_to_expr_pb("@.age > 10".to_string()).unwrap())
+            })
+        );
+    }
+}
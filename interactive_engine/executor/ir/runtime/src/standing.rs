@@ -0,0 +1,859 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Standing queries: the queries registered by name in the registry of the process, whose results are
+//! materialized once registered, and maintained incrementally by the changes of the graph, e.g., the
+//! mutations written by the `Mutate` operators, or those of a CDC stream applied by `apply()`.
+//!
+//! A standing query scans the vertices or the edges of some labels, filtered by a predicate, and
+//! optionally expands the edges of the vertices matched, where each element (the edge if expanded)
+//! is a row of the projected columns. The rows may be further aggregated by `Count` or `Sum`, grouped
+//! by their leading columns.
+//!
+//! The subscribers of a standing query receive the deltas of its results, i.e., the rows inserted and
+//! deleted, in batches of the changes applied, where the update of a row is a deletion of the old row
+//! followed by an insertion of the new one. The deltas of a subscription are buffered up to the
+//! capacity of the registry, 1024 batches by default; a subscriber lagging further is dropped, whose
+//! deltas then end, so that it may subscribe again, rather than the writers being blocked by it.
+//!
+//! A query is materialized while the changes are not applied, so that none of the changes written
+//! during the materialization is missed by it.
+//!
+//! The results of a server are maintained by the changes applied in the process only, so the
+//! mutations of the graph should be written through the server, or applied to it.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, RwLock};
+
+use dyn_type::{Object, Primitives};
+use graph_proxy::apis::{
+    get_graph, get_soft_delete, Direction, Edge, GraphElement, Mutated, Mutation, QueryParams, ReadGraph,
+    Vertex, ID,
+};
+use graph_proxy::utils::expr::eval::Context;
+use graph_proxy::utils::expr::eval_pred::{EvalPred, PEvaluator};
+use ir_common::{LabelId, NameOrId};
+
+use crate::error::{FnExecError, FnExecResult};
+
+const DEFAULT_SUBSCRIPTION_CAPACITY: usize = 1024;
+
+/// A change of the graph, given by the element after the change.
+#[derive(Clone, Debug)]
+pub enum Change {
+    /// A vertex added or updated
+    UpsertVertex(Vertex),
+    /// A vertex deleted, together with its edges
+    DeleteVertex(ID),
+    /// An edge added or updated
+    UpsertEdge(Edge),
+    /// An edge deleted
+    DeleteEdge(Edge),
+}
+
+impl Change {
+    /// The change made by the mutation written with the result, where the elements dropped softly,
+    /// i.e., updated with the tombstones, are deleted.
+    pub fn from_mutation(mutation: &Mutation, mutated: &Mutated) -> Option<Change> {
+        let soft_delete = get_soft_delete();
+        match (mutation, mutated) {
+            (Mutation::DropVertex(vertex), Mutated::Dropped) => Some(Change::DeleteVertex(vertex.id())),
+            (Mutation::DropEdge(edge), Mutated::Dropped) => Some(Change::DeleteEdge(edge.clone())),
+            (_, Mutated::Vertex(vertex)) => {
+                if soft_delete.map_or(false, |s| s.is_deleted(vertex)) {
+                    Some(Change::DeleteVertex(vertex.id()))
+                } else {
+                    Some(Change::UpsertVertex(vertex.clone()))
+                }
+            }
+            (_, Mutated::Edge(edge)) => {
+                if soft_delete.map_or(false, |s| s.is_deleted(edge)) {
+                    Some(Change::DeleteEdge(edge.clone()))
+                } else {
+                    Some(Change::UpsertEdge(edge.clone()))
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A delta of the results of a standing query.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Delta {
+    Insert(Vec<Object>),
+    Delete(Vec<Object>),
+}
+
+/// The elements scanned by a standing query, of any label if the labels are empty.
+#[derive(Clone, Debug)]
+pub enum StandingSource {
+    Vertex(Vec<LabelId>),
+    Edge(Vec<LabelId>),
+}
+
+/// A column of the rows of a standing query, projected from the element of the row.
+#[derive(Clone, Debug)]
+pub enum StandingColumn {
+    Id,
+    Label,
+    Property(NameOrId),
+}
+
+/// The aggregate of the rows of a standing query, which is maintained under the deletions.
+#[derive(Clone, Copy, Debug)]
+pub enum StandingAggregate {
+    Count,
+    /// Sum the column of the index, where the values not numeric are ignored
+    Sum(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct StandingQuery {
+    source: StandingSource,
+    filter: Option<Arc<PEvaluator>>,
+    /// Expand the edges of the labels in the direction from the vertices matched
+    expand: Option<(Direction, Vec<LabelId>)>,
+    columns: Vec<StandingColumn>,
+    /// The aggregate, grouped by the number of the leading columns
+    aggregate: Option<(usize, StandingAggregate)>,
+}
+
+impl StandingQuery {
+    pub fn vertices(labels: Vec<LabelId>) -> Self {
+        StandingQuery::new(StandingSource::Vertex(labels))
+    }
+
+    pub fn edges(labels: Vec<LabelId>) -> Self {
+        StandingQuery::new(StandingSource::Edge(labels))
+    }
+
+    fn new(source: StandingSource) -> Self {
+        StandingQuery { source, filter: None, expand: None, columns: vec![], aggregate: None }
+    }
+
+    /// Filter the scanned elements by the predicate.
+    pub fn with_filter(mut self, filter: PEvaluator) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Expand the edges of the scanned vertices, as the elements of the rows.
+    pub fn with_expand(mut self, direction: Direction, labels: Vec<LabelId>) -> Self {
+        self.expand = Some((direction, labels));
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Vec<StandingColumn>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Aggregate the rows grouped by the first `keys` columns, where a result is the keys followed by
+    /// the aggregated value.
+    pub fn with_aggregate(mut self, keys: usize, aggregate: StandingAggregate) -> Self {
+        self.aggregate = Some((keys, aggregate));
+        self
+    }
+
+    fn validate(&self) -> FnExecResult<()> {
+        if self.expand.is_some() {
+            if let StandingSource::Edge(_) = self.source {
+                Err(FnExecError::standing_query_error("expand the edges of edges"))?
+            }
+        }
+        if let Some((keys, aggregate)) = self.aggregate {
+            if keys > self.columns.len() {
+                Err(FnExecError::standing_query_error(&format!(
+                    "group by {} keys of {} columns",
+                    keys,
+                    self.columns.len()
+                )))?
+            }
+            if let StandingAggregate::Sum(column) = aggregate {
+                if column >= self.columns.len() {
+                    Err(FnExecError::standing_query_error(&format!(
+                        "sum the column {} of {} columns",
+                        column,
+                        self.columns.len()
+                    )))?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_source(&self, element: &dyn GraphElement) -> bool {
+        let labels = match &self.source {
+            StandingSource::Vertex(labels) => labels,
+            StandingSource::Edge(labels) => labels,
+        };
+        has_label(labels, element)
+    }
+
+    fn is_matched<E: GraphElement + Context<E>>(&self, element: &E) -> FnExecResult<bool> {
+        if !self.is_source(element) {
+            return Ok(false);
+        }
+        match &self.filter {
+            Some(filter) => Ok(filter.eval_bool::<E, E>(Some(element))?),
+            None => Ok(true),
+        }
+    }
+
+    fn project(&self, element: &dyn GraphElement) -> Vec<Object> {
+        self.columns
+            .iter()
+            .map(|column| match column {
+                StandingColumn::Id => element.id().into(),
+                StandingColumn::Label => element
+                    .label()
+                    .map(|label| label.into())
+                    .unwrap_or(Object::None),
+                StandingColumn::Property(key) => element
+                    .get_property(key)
+                    .and_then(|value| value.try_to_owned())
+                    .unwrap_or(Object::None),
+            })
+            .collect()
+    }
+}
+
+fn has_label(labels: &[LabelId], element: &dyn GraphElement) -> bool {
+    labels.is_empty()
+        || element
+            .label()
+            .map_or(false, |label| labels.contains(&label))
+}
+
+/// The key of a row, i.e., the vertex expanded from and the element of the row, where the vertex is
+/// the element itself if not expanded.
+type RowKey = (ID, ID);
+
+#[derive(Default)]
+struct Group {
+    count: u64,
+    sum: Option<Primitives>,
+}
+
+/// The materialized results of a standing query.
+struct StandingView {
+    query: StandingQuery,
+    /// The graph to expand the edges of the vertices matched
+    graph: Arc<dyn ReadGraph>,
+    /// The rows with the endpoints of their elements
+    rows: HashMap<RowKey, (Vec<Object>, [ID; 2])>,
+    /// The rows of each vertex, either as the element, or as an endpoint of the edge of the row
+    vertex_rows: HashMap<ID, HashSet<RowKey>>,
+    /// The vertices matched if expanded
+    matched: HashSet<ID>,
+    groups: HashMap<Vec<Object>, Group>,
+    subscribers: Vec<SyncSender<Vec<Delta>>>,
+}
+
+impl StandingView {
+    fn new(query: StandingQuery, graph: Arc<dyn ReadGraph>) -> Self {
+        StandingView {
+            query,
+            graph,
+            rows: HashMap::new(),
+            vertex_rows: HashMap::new(),
+            matched: HashSet::new(),
+            groups: HashMap::new(),
+            subscribers: vec![],
+        }
+    }
+
+    /// Materialize the results by the elements scanned from the graph.
+    fn materialize(&mut self) -> FnExecResult<()> {
+        let mut deltas = vec![];
+        match self.query.source.clone() {
+            StandingSource::Vertex(labels) => {
+                let params = QueryParams { labels, ..Default::default() };
+                for vertex in self.graph.scan_vertex(&params)? {
+                    self.apply(Change::UpsertVertex(vertex), &mut deltas)?;
+                }
+            }
+            StandingSource::Edge(labels) => {
+                let params = QueryParams { labels, ..Default::default() };
+                for edge in self.graph.scan_edge(&params)? {
+                    self.apply(Change::UpsertEdge(edge), &mut deltas)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, change: Change, deltas: &mut Vec<Delta>) -> FnExecResult<()> {
+        match change {
+            Change::UpsertVertex(vertex) => {
+                if let StandingSource::Edge(_) = self.query.source {
+                    return Ok(());
+                }
+                let id = vertex.id();
+                let is_matched = self.query.is_matched(&vertex)?;
+                if let Some((direction, labels)) = self.query.expand.clone() {
+                    if is_matched && self.matched.insert(id) {
+                        let params = QueryParams { labels, ..Default::default() };
+                        let edges = self
+                            .graph
+                            .prepare_explore_edge(direction, &params)?
+                            .exec(id)?;
+                        for edge in edges {
+                            let row = self.query.project(&edge);
+                            self.upsert_row((id, edge.id()), [edge.src_id, edge.dst_id], row, deltas);
+                        }
+                    } else if !is_matched && self.matched.remove(&id) {
+                        self.delete_vertex_rows(id, |key| key.0 == id, deltas);
+                    }
+                } else if is_matched {
+                    let row = self.query.project(&vertex);
+                    self.upsert_row((id, id), [id, id], row, deltas);
+                } else {
+                    self.delete_row(&(id, id), deltas);
+                }
+            }
+            Change::DeleteVertex(id) => {
+                self.matched.remove(&id);
+                self.delete_vertex_rows(id, |_| true, deltas);
+            }
+            Change::UpsertEdge(edge) => {
+                let id = edge.id();
+                let ends = [edge.src_id, edge.dst_id];
+                if let Some((direction, labels)) = self.query.expand.clone() {
+                    if !has_label(&labels, &edge) {
+                        return Ok(());
+                    }
+                    let row = self.query.project(&edge);
+                    for start in expand_starts(direction, &edge) {
+                        if self.matched.contains(&start) {
+                            self.upsert_row((start, id), ends, row.clone(), deltas);
+                        }
+                    }
+                } else if let StandingSource::Edge(_) = self.query.source {
+                    if self.query.is_matched(&edge)? {
+                        let row = self.query.project(&edge);
+                        self.upsert_row((id, id), ends, row, deltas);
+                    } else {
+                        self.delete_row(&(id, id), deltas);
+                    }
+                }
+            }
+            Change::DeleteEdge(edge) => {
+                let id = edge.id();
+                for start in vec![id, edge.src_id, edge.dst_id] {
+                    self.delete_row(&(start, id), deltas);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn upsert_row(&mut self, key: RowKey, ends: [ID; 2], row: Vec<Object>, deltas: &mut Vec<Delta>) {
+        if let Some((old, _)) = self.rows.get(&key) {
+            if *old == row {
+                return;
+            }
+            self.delete_row(&key, deltas);
+        }
+        for end in ends.iter() {
+            self.vertex_rows
+                .entry(*end)
+                .or_default()
+                .insert(key);
+        }
+        self.emit(&row, true, deltas);
+        self.rows.insert(key, (row, ends));
+    }
+
+    fn delete_row(&mut self, key: &RowKey, deltas: &mut Vec<Delta>) {
+        if let Some((row, ends)) = self.rows.remove(key) {
+            for end in ends.iter() {
+                if let Some(keys) = self.vertex_rows.get_mut(end) {
+                    keys.remove(key);
+                    if keys.is_empty() {
+                        self.vertex_rows.remove(end);
+                    }
+                }
+            }
+            self.emit(&row, false, deltas);
+        }
+    }
+
+    fn delete_vertex_rows<F: Fn(&RowKey) -> bool>(&mut self, id: ID, pred: F, deltas: &mut Vec<Delta>) {
+        let keys: Vec<RowKey> = self
+            .vertex_rows
+            .get(&id)
+            .map(|keys| {
+                keys.iter()
+                    .filter(|key| pred(key))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        for key in keys {
+            self.delete_row(&key, deltas);
+        }
+    }
+
+    /// Emit the deltas of the row inserted or deleted, i.e., the row itself if not aggregated, or the
+    /// update of its group otherwise.
+    fn emit(&mut self, row: &[Object], is_insert: bool, deltas: &mut Vec<Delta>) {
+        let (keys, aggregate) = match self.query.aggregate {
+            Some(aggregate) => aggregate,
+            None => {
+                let row = row.to_vec();
+                deltas.push(if is_insert { Delta::Insert(row) } else { Delta::Delete(row) });
+                return;
+            }
+        };
+        let group_keys = row[..keys].to_vec();
+        let group = self
+            .groups
+            .entry(group_keys.clone())
+            .or_default();
+        if group.count > 0 {
+            deltas.push(Delta::Delete(group_row(&group_keys, group, aggregate)));
+        }
+        let value = match aggregate {
+            StandingAggregate::Sum(column) => row[column].as_primitive().ok(),
+            StandingAggregate::Count => None,
+        };
+        if is_insert {
+            group.count += 1;
+            if let Some(value) = value {
+                group.sum = Some(group.sum.map_or(value, |sum| sum + value));
+            }
+        } else {
+            group.count -= 1;
+            if let Some(value) = value {
+                group.sum = group.sum.map(|sum| sum - value);
+            }
+        }
+        if group.count > 0 {
+            deltas.push(Delta::Insert(group_row(&group_keys, group, aggregate)));
+        } else {
+            self.groups.remove(&group_keys);
+        }
+    }
+
+    fn results(&self) -> Vec<Vec<Object>> {
+        match self.query.aggregate {
+            Some((_, aggregate)) => self
+                .groups
+                .iter()
+                .map(|(keys, group)| group_row(keys, group, aggregate))
+                .collect(),
+            None => self
+                .rows
+                .values()
+                .map(|(row, _)| row.clone())
+                .collect(),
+        }
+    }
+
+    fn publish(&mut self, deltas: Vec<Delta>) {
+        if deltas.is_empty() {
+            return;
+        }
+        // the subscribers dropped, or lagging over the capacity, are removed
+        self.subscribers
+            .retain(|subscriber| match subscriber.try_send(deltas.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    warn!("drop the subscriber of standing query lagging over the capacity");
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+}
+
+fn group_row(keys: &[Object], group: &Group, aggregate: StandingAggregate) -> Vec<Object> {
+    let value = match aggregate {
+        StandingAggregate::Count => group.count.into(),
+        StandingAggregate::Sum(_) => group
+            .sum
+            .map(Object::Primitive)
+            .unwrap_or(Object::None),
+    };
+    let mut row = keys.to_vec();
+    row.push(value);
+    row
+}
+
+/// The vertices which the edge is expanded from in the direction.
+fn expand_starts(direction: Direction, edge: &Edge) -> Vec<ID> {
+    match direction {
+        Direction::Out => vec![edge.src_id],
+        Direction::In => vec![edge.dst_id],
+        Direction::Both if edge.src_id == edge.dst_id => vec![edge.src_id],
+        Direction::Both => vec![edge.src_id, edge.dst_id],
+    }
+}
+
+/// The results of a standing query when subscribed, and the deltas of them afterwards, until the
+/// query is unregistered.
+pub struct Subscription {
+    pub results: Vec<Vec<Object>>,
+    pub deltas: Receiver<Vec<Delta>>,
+}
+
+/// The standing queries in the process.
+pub struct StandingQueries {
+    views: RwLock<HashMap<String, Arc<Mutex<StandingView>>>>,
+    /// The most batches of deltas buffered for a subscriber
+    capacity: usize,
+}
+
+impl Default for StandingQueries {
+    fn default() -> Self {
+        StandingQueries::with_capacity(DEFAULT_SUBSCRIPTION_CAPACITY)
+    }
+}
+
+impl StandingQueries {
+    pub fn with_capacity(capacity: usize) -> Self {
+        StandingQueries { views: RwLock::new(HashMap::new()), capacity: capacity.max(1) }
+    }
+
+    /// Register the query of the name, replacing the one registered before, whose results are
+    /// materialized from the registered graph.
+    pub fn register(&self, name: &str, query: StandingQuery) -> FnExecResult<()> {
+        let graph = get_graph().ok_or(FnExecError::NullGraphError)?;
+        self.register_with(name, query, graph)
+    }
+
+    pub(crate) fn register_with(
+        &self, name: &str, query: StandingQuery, graph: Arc<dyn ReadGraph>,
+    ) -> FnExecResult<()> {
+        if name.is_empty() {
+            Err(FnExecError::standing_query_error("empty name of standing query"))?
+        }
+        query.validate()?;
+        // the changes are not applied until the view is materialized and registered, so the changes
+        // written after the scan are applied to it
+        let mut views = self
+            .views
+            .write()
+            .map_err(|e| FnExecError::standing_query_error(&format!("{:?}", e)))?;
+        let mut view = StandingView::new(query, graph);
+        view.materialize()?;
+        views.insert(name.to_string(), Arc::new(Mutex::new(view)));
+        Ok(())
+    }
+
+    /// Unregister the query of the name, returning whether it is found, where its subscriptions end.
+    pub fn unregister(&self, name: &str) -> FnExecResult<bool> {
+        let mut views = self
+            .views
+            .write()
+            .map_err(|e| FnExecError::standing_query_error(&format!("{:?}", e)))?;
+        Ok(views.remove(name).is_some())
+    }
+
+    /// The current results of the query of the name.
+    pub fn results(&self, name: &str) -> FnExecResult<Option<Vec<Vec<Object>>>> {
+        match self.get(name)? {
+            Some(view) => {
+                let view = view
+                    .lock()
+                    .map_err(|e| FnExecError::standing_query_error(&format!("{:?}", e)))?;
+                Ok(Some(view.results()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Subscribe the query of the name, where no delta is missed or repeated after the results.
+    pub fn subscribe(&self, name: &str) -> FnExecResult<Option<Subscription>> {
+        match self.get(name)? {
+            Some(view) => {
+                let mut view = view
+                    .lock()
+                    .map_err(|e| FnExecError::standing_query_error(&format!("{:?}", e)))?;
+                let (tx, rx) = sync_channel(self.capacity);
+                view.subscribers.push(tx);
+                Ok(Some(Subscription { results: view.results(), deltas: rx }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Apply the changes of the graph to the results of all the queries, and publish the deltas.
+    pub fn apply(&self, changes: &[Change]) -> FnExecResult<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        // the views are read locked until the changes are applied, not to be missed by a registering one
+        let views = self
+            .views
+            .read()
+            .map_err(|e| FnExecError::standing_query_error(&format!("{:?}", e)))?;
+        for view in views.values() {
+            let mut view = view
+                .lock()
+                .map_err(|e| FnExecError::standing_query_error(&format!("{:?}", e)))?;
+            let mut deltas = vec![];
+            for change in changes {
+                view.apply(change.clone(), &mut deltas)?;
+            }
+            view.publish(deltas);
+        }
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> FnExecResult<Option<Arc<Mutex<StandingView>>>> {
+        let views = self
+            .views
+            .read()
+            .map_err(|e| FnExecError::standing_query_error(&format!("{:?}", e)))?;
+        Ok(views.get(name).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use graph_proxy::apis::test_graph::TestGraph;
+    use graph_proxy::apis::DynDetails;
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+    use crate::process::operator::tests::{init_vertex1, init_vertex2, PERSON_LABEL};
+    use crate::process::operator::write::tests::KNOWS_LABEL;
+
+    fn test_graph() -> Arc<dyn ReadGraph> {
+        let edges = vec![knows(12, 1, 2, 0.5), knows(13, 1, 3, 0.4)];
+        Arc::new(TestGraph::new(vec![init_vertex1(), init_vertex2()], edges))
+    }
+
+    fn knows(id: ID, src: ID, dst: ID, weight: f64) -> Edge {
+        let details: HashMap<NameOrId, Object> = vec![("weight".into(), object!(weight))]
+            .into_iter()
+            .collect();
+        Edge::new(id, Some(KNOWS_LABEL), src, dst, DynDetails::new(details))
+    }
+
+    fn person(id: ID, age: i32) -> Vertex {
+        let details: HashMap<NameOrId, Object> = vec![("age".into(), object!(age))]
+            .into_iter()
+            .collect();
+        Vertex::new(id, Some(PERSON_LABEL), DynDetails::new(details))
+    }
+
+    fn filter(expr: &str) -> PEvaluator {
+        PEvaluator::try_from(str_to_expr_pb(expr.to_string()).unwrap()).unwrap()
+    }
+
+    fn sorted(mut rows: Vec<Vec<Object>>) -> Vec<Vec<Object>> {
+        rows.sort();
+        rows
+    }
+
+    // g.V().has('age', gt(27)).values('age')
+    #[test]
+    fn standing_filter_test() {
+        let queries = StandingQueries::default();
+        let query = StandingQuery::vertices(vec![PERSON_LABEL])
+            .with_filter(filter("@.age > 27"))
+            .with_columns(vec![StandingColumn::Id, StandingColumn::Property("age".into())]);
+        queries
+            .register_with("adults", query, test_graph())
+            .unwrap();
+        let subscription = queries.subscribe("adults").unwrap().unwrap();
+        assert_eq!(subscription.results, vec![vec![object!(1), object!(29)]]);
+
+        queries
+            .apply(&[
+                Change::UpsertVertex(person(2, 28)),
+                Change::UpsertVertex(person(1, 20)),
+                Change::UpsertVertex(person(3, 26)),
+            ])
+            .unwrap();
+        let deltas = subscription.deltas.try_recv().unwrap();
+        assert_eq!(
+            deltas,
+            vec![
+                Delta::Insert(vec![object!(2), object!(28)]),
+                Delta::Delete(vec![object!(1), object!(29)])
+            ]
+        );
+
+        queries
+            .apply(&[Change::UpsertVertex(person(2, 30)), Change::DeleteVertex(2)])
+            .unwrap();
+        let deltas = subscription.deltas.try_recv().unwrap();
+        assert_eq!(
+            deltas,
+            vec![
+                Delta::Delete(vec![object!(2), object!(28)]),
+                Delta::Insert(vec![object!(2), object!(30)]),
+                Delta::Delete(vec![object!(2), object!(30)])
+            ]
+        );
+        assert!(queries
+            .results("adults")
+            .unwrap()
+            .unwrap()
+            .is_empty());
+
+        assert!(queries.unregister("adults").unwrap());
+        assert!(subscription.deltas.recv().is_err());
+    }
+
+    // g.V().has('age', gt(27)).outE('knows').values('weight')
+    #[test]
+    fn standing_expand_test() {
+        let queries = StandingQueries::default();
+        let query = StandingQuery::vertices(vec![PERSON_LABEL])
+            .with_filter(filter("@.age > 27"))
+            .with_expand(Direction::Out, vec![KNOWS_LABEL])
+            .with_columns(vec![StandingColumn::Property("weight".into())]);
+        queries
+            .register_with("knows", query, test_graph())
+            .unwrap();
+        assert_eq!(
+            sorted(queries.results("knows").unwrap().unwrap()),
+            vec![vec![object!(0.4)], vec![object!(0.5)]]
+        );
+        let subscription = queries.subscribe("knows").unwrap().unwrap();
+
+        // the edges from the vertices not matched are not expanded
+        queries
+            .apply(&[Change::UpsertEdge(knows(14, 1, 4, 0.9)), Change::UpsertEdge(knows(24, 2, 4, 0.8))])
+            .unwrap();
+        assert_eq!(subscription.deltas.try_recv().unwrap(), vec![Delta::Insert(vec![object!(0.9)])]);
+
+        // the edges of the vertex dropped are deleted
+        queries
+            .apply(&[Change::DeleteVertex(3), Change::DeleteEdge(knows(14, 1, 4, 0.9))])
+            .unwrap();
+        assert_eq!(
+            subscription.deltas.try_recv().unwrap(),
+            vec![Delta::Delete(vec![object!(0.4)]), Delta::Delete(vec![object!(0.9)])]
+        );
+
+        // the edges of the vertex no longer matched are deleted
+        queries
+            .apply(&[Change::UpsertVertex(person(1, 20))])
+            .unwrap();
+        assert_eq!(subscription.deltas.try_recv().unwrap(), vec![Delta::Delete(vec![object!(0.5)])]);
+        assert!(queries
+            .results("knows")
+            .unwrap()
+            .unwrap()
+            .is_empty());
+    }
+
+    // g.E().hasLabel('knows').group().by('~label').by(sum('weight'))
+    #[test]
+    fn standing_aggregate_test() {
+        let queries = StandingQueries::default();
+        let query = StandingQuery::edges(vec![KNOWS_LABEL])
+            .with_columns(vec![StandingColumn::Label, StandingColumn::Property("weight".into())])
+            .with_aggregate(1, StandingAggregate::Sum(1));
+        queries
+            .register_with("weights", query, test_graph())
+            .unwrap();
+        let subscription = queries.subscribe("weights").unwrap().unwrap();
+        assert_eq!(subscription.results, vec![vec![object!(KNOWS_LABEL), object!(0.9)]]);
+
+        queries
+            .apply(&[Change::DeleteEdge(knows(12, 1, 2, 0.5))])
+            .unwrap();
+        assert_eq!(
+            subscription.deltas.try_recv().unwrap(),
+            vec![
+                Delta::Delete(vec![object!(KNOWS_LABEL), object!(0.9)]),
+                Delta::Insert(vec![object!(KNOWS_LABEL), object!(0.4)])
+            ]
+        );
+
+        // the last edge of the group is deleted with its endpoint
+        queries
+            .apply(&[Change::DeleteVertex(3)])
+            .unwrap();
+        assert_eq!(
+            subscription.deltas.try_recv().unwrap(),
+            vec![Delta::Delete(vec![object!(KNOWS_LABEL), object!(0.4)])]
+        );
+    }
+
+    #[test]
+    fn standing_count_test() {
+        let queries = StandingQueries::default();
+        let query = StandingQuery::vertices(vec![])
+            .with_columns(vec![StandingColumn::Label])
+            .with_aggregate(1, StandingAggregate::Count);
+        queries
+            .register_with("count", query, test_graph())
+            .unwrap();
+        assert_eq!(
+            queries.results("count").unwrap().unwrap(),
+            vec![vec![object!(PERSON_LABEL), object!(2u64)]]
+        );
+        queries
+            .apply(&[Change::UpsertVertex(person(3, 30)), Change::UpsertVertex(person(1, 30))])
+            .unwrap();
+        assert_eq!(
+            queries.results("count").unwrap().unwrap(),
+            vec![vec![object!(PERSON_LABEL), object!(3u64)]]
+        );
+    }
+
+    #[test]
+    fn standing_lagging_test() {
+        let queries = StandingQueries::with_capacity(1);
+        let query = StandingQuery::vertices(vec![PERSON_LABEL]).with_columns(vec![StandingColumn::Id]);
+        queries
+            .register_with("persons", query, test_graph())
+            .unwrap();
+        let subscription = queries.subscribe("persons").unwrap().unwrap();
+        queries
+            .apply(&[Change::UpsertVertex(person(3, 30))])
+            .unwrap();
+        // the subscriber lagging over the capacity is dropped, rather than blocking the changes
+        queries
+            .apply(&[Change::UpsertVertex(person(4, 30))])
+            .unwrap();
+        assert_eq!(subscription.deltas.try_recv().unwrap(), vec![Delta::Insert(vec![object!(3)])]);
+        assert!(subscription.deltas.recv().is_err());
+        assert_eq!(
+            queries
+                .results("persons")
+                .unwrap()
+                .unwrap()
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn standing_invalid_test() {
+        let queries = StandingQueries::default();
+        let query = StandingQuery::edges(vec![]).with_expand(Direction::Out, vec![]);
+        assert!(queries
+            .register_with("invalid", query, test_graph())
+            .is_err());
+        let query = StandingQuery::vertices(vec![]).with_aggregate(1, StandingAggregate::Count);
+        assert!(queries
+            .register_with("invalid", query, test_graph())
+            .is_err());
+        assert!(queries.results("invalid").unwrap().is_none());
+    }
+}
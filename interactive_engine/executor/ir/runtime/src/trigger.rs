@@ -0,0 +1,592 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Triggers: the hooks registered by name in the registry of the process, which fire on the inserts,
+//! updates and deletes of the vertices or the edges of some labels written by the `Mutate` operators.
+//! A trigger is either:
+//!   1. a before-commit trigger in Rust, which fires before the mutation is written, and whose
+//!      mutations, e.g., of a denormalized counter, are written in the same batch, while the record
+//!      fails if any of them fails, or the trigger returns an error;
+//!   2. an after-commit trigger in Rust, which fires once the mutation is written, e.g., to push a
+//!      notification, and is fired at least once: the firings failed are kept pending, and retried
+//!      before the following ones, and in every interval of the retries started by `start_retry()`,
+//!      until they succeed;
+//!   3. a plan, i.e., a physical plan without a sink, installed after the `Mutate` operators of the
+//!      jobs over the records of the vertices or the edges inserted or updated, i.e., in the head of
+//!      the records, which fails the job if it fails, so it is fired again when the job is retried.
+//!      Like a procedure, the plan runs with the privileges of the admin registering it.
+//!
+//! The mutations of the before-commit triggers do not fire the before-commit triggers again, neither
+//! do the `Mutate` operators of the trigger plans install the trigger plans again.
+
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryInto;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use graph_proxy::apis::{get_soft_delete, Details, DynDetails, GraphElement, Mutated, Mutation};
+use graph_proxy::GraphProxyResult;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+use ir_common::LabelId;
+
+use crate::error::{FnExecError, FnExecResult, FnGenError, FnGenResult};
+use crate::process::entry::Entry;
+use crate::process::record::Record;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TriggerEvent {
+    Insert,
+    Update,
+    /// The drop, or the soft drop writing the tombstone
+    Delete,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TriggerElement {
+    Vertex,
+    Edge,
+}
+
+/// The element, its label, and the event of the mutation.
+fn classify(mutation: &Mutation) -> (TriggerElement, Option<LabelId>, TriggerEvent) {
+    let update_or_delete = |details: &DynDetails| {
+        let is_tombstone = get_soft_delete().map_or(false, |soft_delete| {
+            details
+                .get_property(&soft_delete.property)
+                .is_some()
+        });
+        if is_tombstone {
+            TriggerEvent::Delete
+        } else {
+            TriggerEvent::Update
+        }
+    };
+    match mutation {
+        Mutation::AddVertex(label, _) => (TriggerElement::Vertex, Some(*label), TriggerEvent::Insert),
+        Mutation::AddEdge(label, _, _, _) => (TriggerElement::Edge, Some(*label), TriggerEvent::Insert),
+        Mutation::SetVertexProperties(vertex, details) => {
+            (TriggerElement::Vertex, vertex.label(), update_or_delete(details))
+        }
+        Mutation::SetEdgeProperties(edge, details) => {
+            (TriggerElement::Edge, edge.label(), update_or_delete(details))
+        }
+        Mutation::DropVertex(vertex) => (TriggerElement::Vertex, vertex.label(), TriggerEvent::Delete),
+        Mutation::DropEdge(edge) => (TriggerElement::Edge, edge.label(), TriggerEvent::Delete),
+    }
+}
+
+/// The mutations which a trigger fires on.
+#[derive(Clone, Debug)]
+pub struct TriggerOn {
+    element: TriggerElement,
+    /// Any label if empty
+    labels: Vec<LabelId>,
+    /// Any event if empty
+    events: Vec<TriggerEvent>,
+}
+
+impl TriggerOn {
+    pub fn vertices(labels: Vec<LabelId>) -> Self {
+        TriggerOn { element: TriggerElement::Vertex, labels, events: vec![] }
+    }
+
+    pub fn edges(labels: Vec<LabelId>) -> Self {
+        TriggerOn { element: TriggerElement::Edge, labels, events: vec![] }
+    }
+
+    pub fn with_events(mut self, events: Vec<TriggerEvent>) -> Self {
+        self.events = events;
+        self
+    }
+
+    fn matches(&self, element: TriggerElement, label: Option<LabelId>, event: TriggerEvent) -> bool {
+        self.element == element
+            && (self.labels.is_empty() || label.map_or(false, |label| self.labels.contains(&label)))
+            && (self.events.is_empty() || self.events.contains(&event))
+    }
+
+    fn matches_mutation(&self, mutation: &Mutation) -> Option<TriggerEvent> {
+        let (element, label, event) = classify(mutation);
+        if self.matches(element, label, event) {
+            Some(event)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the head of the record is the element of the trigger written by the event.
+    pub(crate) fn matches_record(&self, record: &Record, event: TriggerEvent) -> bool {
+        match record.get(None) {
+            Some(entry) => {
+                if let Some(vertex) = entry.as_vertex() {
+                    self.matches(TriggerElement::Vertex, vertex.label(), event)
+                } else if let Some(edge) = entry.as_edge() {
+                    self.matches(TriggerElement::Edge, edge.label(), event)
+                } else {
+                    false
+                }
+            }
+            None => false,
+        }
+    }
+}
+
+/// A trigger in Rust firing before the mutation is written.
+pub trait BeforeCommit: Send + Sync + 'static {
+    /// The mutations to write in the same batch as the mutation, or an error to fail the record.
+    fn fire(&self, event: TriggerEvent, mutation: &Mutation) -> FnExecResult<Vec<Mutation>>;
+}
+
+/// A trigger in Rust firing once the mutation is written, which may fire more than once.
+pub trait AfterCommit: Send + Sync + 'static {
+    fn fire(&self, event: TriggerEvent, mutation: &Mutation, mutated: &Mutated) -> FnExecResult<()>;
+}
+
+#[derive(Clone)]
+enum TriggerAction {
+    BeforeCommit(Arc<dyn BeforeCommit>),
+    AfterCommit(Arc<dyn AfterCommit>),
+    Plan(pb::PhysicalPlan),
+}
+
+#[derive(Clone)]
+struct Trigger {
+    on: TriggerOn,
+    action: TriggerAction,
+}
+
+/// A firing of an after-commit trigger failed, to be retried.
+struct PendingFiring {
+    name: String,
+    trigger: Arc<dyn AfterCommit>,
+    event: TriggerEvent,
+    mutation: Mutation,
+    mutated: Mutated,
+    attempts: usize,
+}
+
+/// The triggers in the process.
+#[derive(Default)]
+pub struct TriggerRegistry {
+    triggers: RwLock<HashMap<String, Trigger>>,
+    pending: Mutex<VecDeque<PendingFiring>>,
+}
+
+impl TriggerRegistry {
+    /// Register the before-commit trigger of the name, replacing the one registered before.
+    pub fn register_before(
+        &self, name: &str, on: TriggerOn, trigger: Arc<dyn BeforeCommit>,
+    ) -> FnGenResult<()> {
+        self.register(name, Trigger { on, action: TriggerAction::BeforeCommit(trigger) })
+    }
+
+    /// Register the after-commit trigger of the name, replacing the one registered before.
+    pub fn register_after(
+        &self, name: &str, on: TriggerOn, trigger: Arc<dyn AfterCommit>,
+    ) -> FnGenResult<()> {
+        self.register(name, Trigger { on, action: TriggerAction::AfterCommit(trigger) })
+    }
+
+    /// Register the plan of the name, replacing the one registered before, which is fired by the
+    /// inserts and the updates only, as the elements dropped are not output by the `Mutate`.
+    pub fn register_plan(&self, name: &str, on: TriggerOn, plan: pb::PhysicalPlan) -> FnGenResult<()> {
+        if on.events.is_empty() || on.events.contains(&TriggerEvent::Delete) {
+            Err(FnGenError::trigger_error(&format!("trigger plan `{}` fired by the deletes", name)))?
+        }
+        for opr in plan.plan.iter() {
+            let op_kind: OpKind = opr.try_into()?;
+            if let OpKind::Sink(_) = op_kind {
+                Err(FnGenError::trigger_error(&format!("trigger plan `{}` has a sink", name)))?
+            }
+        }
+        self.register(name, Trigger { on, action: TriggerAction::Plan(plan) })
+    }
+
+    /// Unregister the trigger of the name, returning whether it is found, where its pending firings
+    /// are still retried.
+    pub fn unregister(&self, name: &str) -> FnGenResult<bool> {
+        let mut triggers = self
+            .triggers
+            .write()
+            .map_err(|e| FnGenError::trigger_error(&format!("{:?}", e)))?;
+        Ok(triggers.remove(name).is_some())
+    }
+
+    fn register(&self, name: &str, trigger: Trigger) -> FnGenResult<()> {
+        if name.is_empty() {
+            Err(FnGenError::trigger_error("empty name of trigger"))?
+        }
+        let mut triggers = self
+            .triggers
+            .write()
+            .map_err(|e| FnGenError::trigger_error(&format!("{:?}", e)))?;
+        triggers.insert(name.to_string(), trigger);
+        Ok(())
+    }
+
+    fn triggers(&self) -> FnExecResult<Vec<(String, Trigger)>> {
+        let triggers = self
+            .triggers
+            .read()
+            .map_err(|e| FnExecError::trigger_error(&format!("{:?}", e)))?;
+        let mut triggers: Vec<(String, Trigger)> = triggers
+            .iter()
+            .map(|(name, trigger)| (name.clone(), trigger.clone()))
+            .collect();
+        // fire in the order of the names
+        triggers.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(triggers)
+    }
+
+    /// Fire the before-commit triggers of the mutations, returning the mutations of the triggers.
+    pub fn fire_before(&self, mutations: &[Mutation]) -> FnExecResult<Vec<Mutation>> {
+        let mut fired = vec![];
+        for (name, trigger) in self.triggers()? {
+            if let TriggerAction::BeforeCommit(before) = &trigger.action {
+                for mutation in mutations {
+                    if let Some(event) = trigger.on.matches_mutation(mutation) {
+                        let mutations = before.fire(event, mutation).map_err(|e| {
+                            FnExecError::trigger_error(&format!("trigger `{}` failed: {}", name, e))
+                        })?;
+                        fired.extend(mutations);
+                    }
+                }
+            }
+        }
+        Ok(fired)
+    }
+
+    /// Fire the after-commit triggers of the mutations written successfully, after retrying the
+    /// firings pending, where the firings failed are kept pending.
+    pub fn fire_after(
+        &self, mutations: &[Mutation], results: &[GraphProxyResult<Mutated>],
+    ) -> FnExecResult<()> {
+        self.retry_pending()?;
+        let mut failed = vec![];
+        for (name, trigger) in self.triggers()? {
+            if let TriggerAction::AfterCommit(after) = &trigger.action {
+                for (mutation, result) in mutations.iter().zip(results.iter()) {
+                    if let (Some(event), Ok(mutated)) = (trigger.on.matches_mutation(mutation), result) {
+                        let firing = PendingFiring {
+                            name: name.clone(),
+                            trigger: after.clone(),
+                            event,
+                            mutation: mutation.clone(),
+                            mutated: mutated.clone(),
+                            attempts: 0,
+                        };
+                        if let Some(firing) = fire(firing) {
+                            failed.push(firing);
+                        }
+                    }
+                }
+            }
+        }
+        if !failed.is_empty() {
+            self.pending
+                .lock()
+                .map_err(|e| FnExecError::trigger_error(&format!("{:?}", e)))?
+                .extend(failed);
+        }
+        Ok(())
+    }
+
+    /// Retry the firings pending, returning the number of those still pending.
+    pub fn retry_pending(&self) -> FnExecResult<usize> {
+        let mut pending = self
+            .pending
+            .lock()
+            .map_err(|e| FnExecError::trigger_error(&format!("{:?}", e)))?;
+        let firings: Vec<PendingFiring> = pending.drain(..).collect();
+        for firing in firings {
+            if let Some(firing) = fire(firing) {
+                pending.push_back(firing);
+            }
+        }
+        Ok(pending.len())
+    }
+
+    /// Retry the firings pending in every interval, until the returned handle is dropped.
+    pub fn start_retry(self: &Arc<Self>, interval: Duration) -> RetryHandle {
+        let (stop, stopped) = channel::<()>();
+        let registry = self.clone();
+        std::thread::spawn(move || loop {
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => break,
+            }
+            match registry.retry_pending() {
+                Ok(0) => {}
+                Ok(pending) => warn!("{} firings of the triggers are still pending", pending),
+                Err(e) => error!("failed to retry the pending firings of the triggers: {}", e),
+            }
+        });
+        RetryHandle { _stop: stop }
+    }
+
+    /// The trigger plans fired by the records output by the `Mutate` of the event, which are not
+    /// installed within a trigger plan.
+    pub(crate) fn plans_of(&self, event: TriggerEvent) -> FnGenResult<Vec<(TriggerOn, pb::PhysicalPlan)>> {
+        if is_installing_trigger() {
+            return Ok(vec![]);
+        }
+        let triggers = self
+            .triggers()
+            .map_err(|e| FnGenError::trigger_error(&e.to_string()))?;
+        Ok(triggers
+            .into_iter()
+            .filter_map(|(_, trigger)| match trigger.action {
+                TriggerAction::Plan(plan) if trigger.on.events.contains(&event) => Some((trigger.on, plan)),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+/// The handle of the scheduled retries of the pending firings, which stops the retries when dropped.
+pub struct RetryHandle {
+    _stop: Sender<()>,
+}
+
+/// Fire the pending firing, returning it back if failed.
+fn fire(mut firing: PendingFiring) -> Option<PendingFiring> {
+    firing.attempts += 1;
+    match firing
+        .trigger
+        .fire(firing.event, &firing.mutation, &firing.mutated)
+    {
+        Ok(()) => None,
+        Err(e) => {
+            warn!(
+                "trigger `{}` failed on {:?} of {:?} after {} attempts: {}",
+                firing.name, firing.event, firing.mutation, firing.attempts, e
+            );
+            Some(firing)
+        }
+    }
+}
+
+thread_local! {
+    static INSTALLING_TRIGGER: Cell<bool> = Cell::new(false);
+}
+
+fn is_installing_trigger() -> bool {
+    INSTALLING_TRIGGER.with(|installing| installing.get())
+}
+
+/// Mark the trigger plans installed by the thread until dropped, so that the `Mutate` operators
+/// within them do not install the trigger plans again.
+pub(crate) struct InstallingTrigger {
+    was_installing: bool,
+}
+
+impl InstallingTrigger {
+    pub(crate) fn new() -> Self {
+        let was_installing = INSTALLING_TRIGGER.with(|installing| installing.replace(true));
+        InstallingTrigger { was_installing }
+    }
+}
+
+impl Drop for InstallingTrigger {
+    fn drop(&mut self) {
+        let was_installing = self.was_installing;
+        INSTALLING_TRIGGER.with(|installing| installing.set(was_installing));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use graph_proxy::apis::{DynDetails, GraphProxyError, Vertex};
+
+    use super::*;
+    use crate::process::operator::tests::{init_vertex1, PERSON_LABEL};
+
+    const SOFTWARE_LABEL: LabelId = 1;
+
+    /// Count the vertices added of each label in the vertex of the label.
+    struct CountAdded;
+
+    impl BeforeCommit for CountAdded {
+        fn fire(&self, _event: TriggerEvent, mutation: &Mutation) -> FnExecResult<Vec<Mutation>> {
+            match mutation {
+                Mutation::AddVertex(label, _) => {
+                    let counter = Vertex::new(*label as i64, Some(*label), DynDetails::default());
+                    Ok(vec![Mutation::SetVertexProperties(counter, DynDetails::default())])
+                }
+                _ => Err(FnExecError::unexpected_data_error("not an added vertex")),
+            }
+        }
+    }
+
+    /// Fail the first firings of the number.
+    struct FailFirst {
+        failures: AtomicUsize,
+        fired: AtomicUsize,
+    }
+
+    impl AfterCommit for FailFirst {
+        fn fire(&self, event: TriggerEvent, _: &Mutation, _: &Mutated) -> FnExecResult<()> {
+            assert_eq!(event, TriggerEvent::Delete);
+            self.fired.fetch_add(1, Ordering::SeqCst);
+            let failures = self.failures.load(Ordering::SeqCst);
+            if failures > 0 {
+                self.failures
+                    .store(failures - 1, Ordering::SeqCst);
+                Err(FnExecError::unexpected_data_error("notification failed"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn trigger_before_test() {
+        let registry = TriggerRegistry::default();
+        let on = TriggerOn::vertices(vec![PERSON_LABEL]).with_events(vec![TriggerEvent::Insert]);
+        registry
+            .register_before("count", on, Arc::new(CountAdded))
+            .unwrap();
+        let mutations = vec![
+            Mutation::AddVertex(PERSON_LABEL, DynDetails::default()),
+            Mutation::AddVertex(SOFTWARE_LABEL, DynDetails::default()),
+            Mutation::DropVertex(init_vertex1()),
+        ];
+        let fired = registry.fire_before(&mutations).unwrap();
+        assert_eq!(fired.len(), 1);
+        assert!(matches!(&fired[0], Mutation::SetVertexProperties(v, _) if v.id() == PERSON_LABEL as i64));
+
+        // the trigger fails the mutation it fires on
+        let on = TriggerOn::vertices(vec![PERSON_LABEL]).with_events(vec![TriggerEvent::Delete]);
+        registry
+            .register_before("count", on, Arc::new(CountAdded))
+            .unwrap();
+        assert!(registry.fire_before(&mutations).is_err());
+        assert!(registry.unregister("count").unwrap());
+        assert!(registry
+            .fire_before(&mutations)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn trigger_after_retry_test() {
+        let registry = TriggerRegistry::default();
+        let trigger = Arc::new(FailFirst { failures: AtomicUsize::new(2), fired: AtomicUsize::new(0) });
+        registry
+            .register_after("notify", TriggerOn::vertices(vec![]), trigger.clone())
+            .unwrap();
+        let mutations = vec![
+            Mutation::DropVertex(init_vertex1()),
+            Mutation::DropVertex(init_vertex1()),
+            Mutation::AddEdge(0, 1, 2, DynDetails::default()),
+        ];
+        let results = vec![
+            Ok(Mutated::Dropped),
+            Err(GraphProxyError::write_graph_error("vertex not found")),
+            Ok(Mutated::Dropped),
+        ];
+        // only the first mutation written fires the trigger, which fails
+        registry
+            .fire_after(&mutations, &results)
+            .unwrap();
+        assert_eq!(trigger.fired.load(Ordering::SeqCst), 1);
+        assert_eq!(registry.retry_pending().unwrap(), 1);
+        assert_eq!(registry.retry_pending().unwrap(), 0);
+        assert_eq!(trigger.fired.load(Ordering::SeqCst), 3);
+        assert_eq!(registry.retry_pending().unwrap(), 0);
+    }
+
+    #[test]
+    fn trigger_scheduled_retry_test() {
+        let registry = Arc::new(TriggerRegistry::default());
+        let trigger = Arc::new(FailFirst { failures: AtomicUsize::new(1), fired: AtomicUsize::new(0) });
+        registry
+            .register_after("notify", TriggerOn::vertices(vec![]), trigger.clone())
+            .unwrap();
+        registry
+            .fire_after(&[Mutation::DropVertex(init_vertex1())], &[Ok(Mutated::Dropped)])
+            .unwrap();
+        // retried without any following firing
+        let _retry = registry.start_retry(Duration::from_millis(10));
+        for _ in 0..100 {
+            if trigger.fired.load(Ordering::SeqCst) == 2 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(trigger.fired.load(Ordering::SeqCst), 2);
+        assert_eq!(registry.retry_pending().unwrap(), 0);
+    }
+
+    #[test]
+    fn trigger_plan_test() {
+        let registry = TriggerRegistry::default();
+        let on = TriggerOn::vertices(vec![PERSON_LABEL]);
+        // fired by any event including the deletes
+        assert!(registry
+            .register_plan("plan", on.clone(), pb::PhysicalPlan::default())
+            .is_err());
+        let on = on.with_events(vec![TriggerEvent::Insert]);
+        let sink = pb::PhysicalOpr {
+            opr: Some(pb::physical_opr::Operator { op_kind: Some(OpKind::Sink(pb::Sink::default())) }),
+            ..Default::default()
+        };
+        let plan = pb::PhysicalPlan { plan: vec![sink], ..Default::default() };
+        assert!(registry
+            .register_plan("plan", on.clone(), plan)
+            .is_err());
+        registry
+            .register_plan("plan", on, pb::PhysicalPlan::default())
+            .unwrap();
+        assert_eq!(
+            registry
+                .plans_of(TriggerEvent::Insert)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(registry
+            .plans_of(TriggerEvent::Update)
+            .unwrap()
+            .is_empty());
+        {
+            let _installing = InstallingTrigger::new();
+            assert!(registry
+                .plans_of(TriggerEvent::Insert)
+                .unwrap()
+                .is_empty());
+        }
+        assert_eq!(
+            registry
+                .plans_of(TriggerEvent::Insert)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let record = Record::new(init_vertex1(), None);
+        let (on, _) = registry
+            .plans_of(TriggerEvent::Insert)
+            .unwrap()
+            .remove(0);
+        assert!(on.matches_record(&record, TriggerEvent::Insert));
+        assert!(!on.matches_record(&record, TriggerEvent::Update));
+    }
+}
@@ -0,0 +1,831 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Validate the plans for the admission of the server against the capabilities of the runtime, see
+//! `JobAssembly::validate`, which names the step and the argument of a plan that the runtime can't run,
+//! rather than failing with an unsupported error deep inside an operator while the plan is assembled.
+
+use graph_proxy::utils::udf::get_udf;
+use ir_common::generated::algebra as algebra_pb;
+use ir_common::generated::physical as pb;
+use ir_common::generated::physical::physical_opr::operator::OpKind;
+use pegasus_server::job::InvalidPlan;
+
+/// Validate the plan, which must end with a sink, and its sub-plans.
+pub fn validate_plan(plan: &pb::PhysicalPlan) -> Result<(), InvalidPlan> {
+    match plan.plan.last().and_then(op_kind_of) {
+        Some(OpKind::Sink(_)) => validate_steps(plan, ""),
+        Some(op_kind) => {
+            let step = (plan.plan.len() - 1).to_string();
+            Err(invalid(&step, op_name(op_kind), None, "the plan must end with a sink"))
+        }
+        None => Err(invalid("0", "Sink", None, "the plan must end with a sink")),
+    }
+}
+
+fn invalid(step: &str, operator: &str, argument: Option<&str>, reason: &str) -> InvalidPlan {
+    InvalidPlan {
+        step: step.to_owned(),
+        operator: operator.to_owned(),
+        argument: argument.map(|argument| argument.to_owned()),
+        reason: reason.to_owned(),
+    }
+}
+
+fn op_kind_of(opr: &pb::PhysicalOpr) -> Option<&OpKind> {
+    opr.opr
+        .as_ref()
+        .and_then(|opr| opr.op_kind.as_ref())
+}
+
+pub(crate) fn op_name(op_kind: &OpKind) -> &'static str {
+    match op_kind {
+        OpKind::Project(_) => "Project",
+        OpKind::Select(_) => "Select",
+        OpKind::GroupBy(_) => "GroupBy",
+        OpKind::OrderBy(_) => "OrderBy",
+        OpKind::Dedup(_) => "Dedup",
+        OpKind::Unfold(_) => "Unfold",
+        OpKind::Limit(_) => "Limit",
+        OpKind::Scan(_) => "Scan",
+        OpKind::Sink(_) => "Sink",
+        OpKind::Apply(_) => "Apply",
+        OpKind::Join(_) => "Join",
+        OpKind::Union(_) => "Union",
+        OpKind::Intersect(_) => "Intersect",
+        OpKind::Repartition(_) => "Repartition",
+        OpKind::Root(_) => "Root",
+        OpKind::Sample(_) => "Sample",
+        OpKind::Algorithm(_) => "GraphAlgorithm",
+        OpKind::KHop(_) => "KHop",
+        OpKind::Merge(_) => "Merge",
+        OpKind::Mutate(_) => "Mutate",
+        OpKind::StoreVar(_) => "StoreVar",
+        OpKind::LoadVar(_) => "LoadVar",
+        OpKind::Call(_) => "Call",
+        OpKind::SideEffect(_) => "SideEffect",
+        OpKind::Cap(_) => "Cap",
+        OpKind::Sack(_) => "Sack",
+        OpKind::Tree(_) => "Tree",
+        OpKind::PatternMatch(_) => "PatternMatch",
+        OpKind::Repeat(_) => "Repeat",
+        OpKind::Coalesce(_) => "Coalesce",
+        OpKind::Vertex(_) => "GetV",
+        OpKind::Edge(_) => "EdgeExpand",
+        OpKind::Path(_) => "PathExpand",
+    }
+}
+
+/// The step of the operator of the index in the plan of the path, e.g., `2.sub_plans[1]`.
+fn step_of(path: &str, index: usize) -> String {
+    if path.is_empty() {
+        index.to_string()
+    } else {
+        format!("{}.{}", path, index)
+    }
+}
+
+fn validate_steps(plan: &pb::PhysicalPlan, path: &str) -> Result<(), InvalidPlan> {
+    if plan.plan.is_empty() {
+        return Err(invalid(path, "Plan", None, "the sub-plan is empty"));
+    }
+    let is_root = path.is_empty();
+    for (i, opr) in plan.plan.iter().enumerate() {
+        let step = step_of(path, i);
+        let op_kind =
+            op_kind_of(opr).ok_or_else(|| invalid(&step, "Unknown", None, "the operator is missing"))?;
+        if let OpKind::Sink(_) = op_kind {
+            if !is_root || i + 1 != plan.plan.len() {
+                return Err(invalid(&step, "Sink", None, "a sink must be the last operator of the plan"));
+            }
+        }
+        if matches!(op_kind, OpKind::Cap(_)) && !is_root {
+            let reason = "the side effects are only read in the root plan";
+            return Err(invalid(&step, op_name(op_kind), None, reason));
+        }
+        if let OpKind::PatternMatch(pattern) = op_kind {
+            // the vertices of the pattern are scanned only as the source of the root plan
+            let is_source = is_root
+                && plan.plan[..i]
+                    .iter()
+                    .all(|opr| matches!(op_kind_of(opr), Some(OpKind::Root(_))));
+            if pattern.bound_tags.is_empty() && !is_source {
+                let reason = "the bound vertices are missing of the pattern not matched as the source";
+                return Err(invalid(&step, op_name(op_kind), Some("bound_tags"), reason));
+            }
+        }
+        validate_step(op_kind, &step)?;
+    }
+    Ok(())
+}
+
+fn validate_step(op_kind: &OpKind, step: &str) -> Result<(), InvalidPlan> {
+    let name = op_name(op_kind);
+    match op_kind {
+        OpKind::Scan(scan) => {
+            if scan.scan_opt == pb::scan::ScanOpt::Table as i32 {
+                return Err(invalid(step, name, Some("scan_opt"), "scanning a table is not supported"));
+            }
+            validate_params(scan.params.as_ref(), step, name, "params")?;
+        }
+        OpKind::Limit(limit) => validate_limit(limit.range.as_ref(), step, name, "range")?,
+        OpKind::OrderBy(order) => {
+            if order.limit.is_some() {
+                validate_limit(order.limit.as_ref(), step, name, "limit")?;
+            }
+        }
+        OpKind::GroupBy(group) => {
+            for (i, function) in group.functions.iter().enumerate() {
+                if function.vars.len() > 1 {
+                    let argument = format!("functions[{}].vars", i);
+                    return Err(invalid(
+                        step,
+                        name,
+                        Some(&argument),
+                        "aggregating multiple fields is not supported",
+                    ));
+                }
+                if group.functions.len() > 1 && function.alias.is_none() {
+                    let argument = format!("functions[{}].alias", i);
+                    return Err(invalid(
+                        step,
+                        name,
+                        Some(&argument),
+                        "the alias of one of the aggregates is missing",
+                    ));
+                }
+                if function.aggregate == pb::group_by::agg_func::Aggregate::UserDefined as i32
+                    && get_udf(&function.udf).is_none()
+                {
+                    let argument = format!("functions[{}].udf", i);
+                    let reason = format!("the user defined aggregate `{}` is not registered", function.udf);
+                    return Err(invalid(step, name, Some(&argument), &reason));
+                }
+            }
+        }
+        OpKind::Vertex(get_v) => {
+            if get_v.opt != pb::get_v::VOpt::Itself as i32 {
+                if let Some(params) = get_v.params.as_ref() {
+                    if params.has_predicates() || params.has_columns() {
+                        return Err(invalid(
+                            step,
+                            name,
+                            Some("params"),
+                            "the predicates or columns are only supported of the vertices themselves",
+                        ));
+                    }
+                }
+            }
+        }
+        OpKind::Edge(expand) => validate_params(expand.params.as_ref(), step, name, "params")?,
+        OpKind::Path(path) => {
+            let base = path
+                .base
+                .as_ref()
+                .ok_or_else(|| invalid(step, name, Some("base"), "the expand base is missing"))?;
+            if base.edge_expand.is_none() {
+                return Err(invalid(step, name, Some("base.edge_expand"), "the edge expand is missing"));
+            }
+            let range = path
+                .hop_range
+                .as_ref()
+                .ok_or_else(|| invalid(step, name, Some("hop_range"), "the range of hops is missing"))?;
+            if range.upper <= range.lower || range.lower < 0 {
+                let reason = format!("the range of hops [{}, {}) is empty", range.lower, range.upper);
+                return Err(invalid(step, name, Some("hop_range"), &reason));
+            }
+        }
+        OpKind::Apply(apply) => {
+            if !apply.keys.is_empty() {
+                return Err(invalid(step, name, Some("keys"), "segment apply is not supported"));
+            }
+            match pb::join::JoinKind::from_i32(apply.join_kind) {
+                Some(pb::join::JoinKind::Inner)
+                | Some(pb::join::JoinKind::LeftOuter)
+                | Some(pb::join::JoinKind::Semi)
+                | Some(pb::join::JoinKind::Anti) => {}
+                join_kind => {
+                    let reason = format!("the join kind {:?} is not supported by apply", join_kind);
+                    return Err(invalid(step, name, Some("join_kind"), &reason));
+                }
+            }
+            let sub_plan = apply
+                .sub_plan
+                .as_ref()
+                .ok_or_else(|| invalid(step, name, Some("sub_plan"), "the sub-plan is missing"))?;
+            validate_steps(sub_plan, &format!("{}.sub_plan", step))?;
+        }
+        OpKind::Join(join) => {
+            let left_plan = join
+                .left_plan
+                .as_ref()
+                .ok_or_else(|| invalid(step, name, Some("left_plan"), "the left plan is missing"))?;
+            let right_plan = join
+                .right_plan
+                .as_ref()
+                .ok_or_else(|| invalid(step, name, Some("right_plan"), "the right plan is missing"))?;
+            validate_steps(left_plan, &format!("{}.left_plan", step))?;
+            validate_steps(right_plan, &format!("{}.right_plan", step))?;
+        }
+        OpKind::Union(union) => {
+            for (i, sub_plan) in union.sub_plans.iter().enumerate() {
+                validate_steps(sub_plan, &format!("{}.sub_plans[{}]", step, i))?;
+            }
+        }
+        OpKind::Repeat(repeat) => {
+            if repeat.until.is_none() && repeat.max_iterations <= 0 {
+                return Err(invalid(
+                    step,
+                    name,
+                    Some("max_iterations"),
+                    "the max iterations must be positive without the condition",
+                ));
+            }
+            let body = repeat
+                .body
+                .as_ref()
+                .ok_or_else(|| invalid(step, name, Some("body"), "the body is missing"))?;
+            validate_steps(body, &format!("{}.body", step))?;
+        }
+        OpKind::SideEffect(side_effect) if side_effect.name.is_empty() => {
+            return Err(invalid(step, name, Some("name"), "the name of the side effect is missing"));
+        }
+        OpKind::Cap(cap) if cap.name.is_empty() => {
+            return Err(invalid(step, name, Some("name"), "the name of the side effect is missing"));
+        }
+        OpKind::Sack(sack) => match sack.inner.as_ref() {
+            None => {
+                return Err(invalid(step, name, Some("inner"), "the operation on the sacks is missing"))
+            }
+            Some(algebra_pb::sack::Inner::Init(init)) if init.initial.is_none() => {
+                return Err(invalid(step, name, Some("init.initial"), "the initial value is missing"));
+            }
+            Some(algebra_pb::sack::Inner::Update(update)) if update.by.is_none() => {
+                return Err(invalid(step, name, Some("update.by"), "the value to update by is missing"));
+            }
+            _ => {}
+        },
+        OpKind::Coalesce(coalesce) => {
+            if coalesce.sub_plans.is_empty() {
+                return Err(invalid(step, name, Some("sub_plans"), "the sub-plans are missing"));
+            }
+            for (i, sub_plan) in coalesce.sub_plans.iter().enumerate() {
+                validate_steps(sub_plan, &format!("{}.sub_plans[{}]", step, i))?;
+            }
+        }
+        OpKind::PatternMatch(pattern) => {
+            if pattern.triples.is_empty() {
+                return Err(invalid(step, name, Some("triples"), "the triples are missing"));
+            }
+            let is_declared = |tag: &i32| {
+                pattern
+                    .vertices
+                    .iter()
+                    .any(|vertex| vertex.tag == *tag)
+            };
+            for (i, triple) in pattern.triples.iter().enumerate() {
+                if !is_declared(&triple.src) || !is_declared(&triple.dst) {
+                    let argument = format!("triples[{}]", i);
+                    let reason =
+                        format!("the vertices of {} -> {} are not declared", triple.src, triple.dst);
+                    return Err(invalid(step, name, Some(&argument), &reason));
+                }
+            }
+            if let Some(tag) = pattern
+                .bound_tags
+                .iter()
+                .find(|tag| !is_declared(tag))
+            {
+                let reason = format!("the bound vertex {} is not declared", tag);
+                return Err(invalid(step, name, Some("bound_tags"), &reason));
+            }
+        }
+        OpKind::Intersect(intersect) => {
+            let mut all_expand_vertices = true;
+            for (i, sub_plan) in intersect.sub_plans.iter().enumerate() {
+                let path = format!("{}.sub_plans[{}]", step, i);
+                validate_steps(sub_plan, &path)?;
+                all_expand_vertices &= validate_intersected(sub_plan, &path)?;
+            }
+            // the expands of the vertices only are intersected by the optimized implementation
+            if all_expand_vertices {
+                for (i, sub_plan) in intersect.sub_plans.iter().enumerate() {
+                    if let Some(OpKind::Edge(expand)) = last_intersected(sub_plan).1 {
+                        if expand.is_optional {
+                            let argument = format!("sub_plans[{}].is_optional", i);
+                            return Err(invalid(
+                                step,
+                                name,
+                                Some(&argument),
+                                "an optional expand to intersect is not supported",
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn validate_params(
+    params: Option<&algebra_pb::QueryParams>, step: &str, name: &str, argument: &str,
+) -> Result<(), InvalidPlan> {
+    if let Some(range) = params.and_then(|params| params.limit.as_ref()) {
+        if range.upper < 0 {
+            let argument = format!("{}.limit", argument);
+            return Err(invalid(step, name, Some(&argument), "the limit must not be negative"));
+        }
+    }
+    if params.map_or(false, |params| !params.excluded_tables.is_empty()) {
+        let argument = format!("{}.excluded_tables", argument);
+        let reason = "the excluded labels are not resolved against the schema into the tables";
+        return Err(invalid(step, name, Some(&argument), reason));
+    }
+    Ok(())
+}
+
+fn validate_limit(
+    range: Option<&algebra_pb::Range>, step: &str, name: &str, argument: &str,
+) -> Result<(), InvalidPlan> {
+    let range = range.ok_or_else(|| invalid(step, name, Some(argument), "the range is missing"))?;
+    // e.g., `limit(10)` is translated into the range of `[0, 10)`
+    if range.upper <= range.lower || range.lower != 0 {
+        let reason = format!("the range [{}, {}) must be non-empty from 0", range.lower, range.upper);
+        return Err(invalid(step, name, Some(argument), &reason));
+    }
+    Ok(())
+}
+
+/// The index and the operator of the last expand of a sub-plan to intersect, i.e., before the optional
+/// `GetV(Itself)` filtering the intersected vertices, and its repartition.
+fn last_intersected(sub_plan: &pb::PhysicalPlan) -> (usize, Option<&OpKind>) {
+    let op_kinds: Vec<Option<&OpKind>> = sub_plan.plan.iter().map(op_kind_of).collect();
+    let mut end = op_kinds.len();
+    if let Some(Some(OpKind::Vertex(get_v))) = op_kinds.last() {
+        if get_v.opt == pb::get_v::VOpt::Itself as i32 {
+            end -= 1;
+            if let Some(Some(OpKind::Repartition(_))) = op_kinds[..end].last() {
+                end -= 1;
+            }
+        }
+    }
+    match end {
+        0 => (0, None),
+        _ => (end - 1, op_kinds[end - 1]),
+    }
+}
+
+/// Validate the sub-plan to intersect, which ends with an expand of the vertices, an expand of the edges
+/// and their adjacent vertices, or an expand of the paths and their end vertices, where the expand is
+/// the first operator or after a repartition. Return whether it's an expand of the vertices.
+fn validate_intersected(sub_plan: &pb::PhysicalPlan, path: &str) -> Result<bool, InvalidPlan> {
+    let (index, last) = last_intersected(sub_plan);
+    let step = step_of(path, index);
+    let is_first = |index: usize| {
+        index == 0
+            || matches!(
+                sub_plan
+                    .plan
+                    .get(index - 1)
+                    .and_then(op_kind_of),
+                Some(OpKind::Repartition(_))
+            )
+    };
+    match last {
+        Some(OpKind::Edge(expand)) => {
+            if expand.expand_opt != pb::edge_expand::ExpandOpt::Vertex as i32 {
+                return Err(invalid(
+                    &step,
+                    "EdgeExpand",
+                    Some("expand_opt"),
+                    "the edges to intersect must be followed by getting their adjacent vertices",
+                ));
+            }
+            if !is_first(index) {
+                return Err(invalid(
+                    &step,
+                    "EdgeExpand",
+                    None,
+                    "an expand to intersect must start the sub-plan",
+                ));
+            }
+            Ok(true)
+        }
+        Some(OpKind::Vertex(get_v)) => {
+            let prev = if index > 0 {
+                sub_plan
+                    .plan
+                    .get(index - 1)
+                    .and_then(op_kind_of)
+            } else {
+                None
+            };
+            match prev {
+                Some(OpKind::Edge(_)) => {
+                    if get_v.opt == pb::get_v::VOpt::Itself as i32 {
+                        return Err(invalid(
+                            &step,
+                            "GetV",
+                            Some("opt"),
+                            "the adjacent vertices must be intersected",
+                        ));
+                    }
+                    if let Some(params) = get_v.params.as_ref() {
+                        if params.has_predicates() || params.has_columns() {
+                            return Err(invalid(
+                                &step,
+                                "GetV",
+                                Some("params"),
+                                "the adjacent vertices to intersect must be filtered after intersected",
+                            ));
+                        }
+                    }
+                    if !is_first(index - 1) {
+                        let step = step_of(path, index - 1);
+                        return Err(invalid(
+                            &step,
+                            "EdgeExpand",
+                            None,
+                            "an expand to intersect must start the sub-plan",
+                        ));
+                    }
+                    Ok(false)
+                }
+                Some(OpKind::Path(path_expand)) => {
+                    if get_v.opt != pb::get_v::VOpt::End as i32 {
+                        return Err(invalid(
+                            &step,
+                            "GetV",
+                            Some("opt"),
+                            "the end vertices of the paths must be intersected",
+                        ));
+                    }
+                    let step = step_of(path, index - 1);
+                    if let Some(range) = path_expand.hop_range.as_ref() {
+                        if range.lower < 1 {
+                            let reason = format!(
+                                "the paths to intersect of the hops from {} are not supported",
+                                range.lower
+                            );
+                            return Err(invalid(&step, "PathExpand", Some("hop_range"), &reason));
+                        }
+                    }
+                    let expand_opt = path_expand
+                        .base
+                        .as_ref()
+                        .and_then(|base| base.edge_expand.as_ref())
+                        .map(|expand| expand.expand_opt);
+                    if expand_opt != Some(pb::edge_expand::ExpandOpt::Vertex as i32) {
+                        return Err(invalid(
+                            &step,
+                            "PathExpand",
+                            Some("base.edge_expand.expand_opt"),
+                            "the paths to intersect must expand the vertices",
+                        ));
+                    }
+                    if !is_first(index - 1) {
+                        return Err(invalid(
+                            &step,
+                            "PathExpand",
+                            None,
+                            "an expand to intersect must start the sub-plan",
+                        ));
+                    }
+                    Ok(false)
+                }
+                _ => Err(invalid(&step, "GetV", None, "the sub-plan to intersect must end with an expand")),
+            }
+        }
+        Some(op_kind) => {
+            Err(invalid(&step, op_name(op_kind), None, "the sub-plan to intersect must end with an expand"))
+        }
+        None => Err(invalid(path, "Plan", None, "the sub-plan to intersect must end with an expand")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ir_common::expr_parse::str_to_expr_pb;
+
+    use super::*;
+
+    fn scan(scan_opt: pb::scan::ScanOpt) -> pb::PhysicalOpr {
+        pb::Scan { scan_opt: scan_opt as i32, ..Default::default() }.into()
+    }
+
+    fn limit(lower: i32, upper: i32) -> pb::PhysicalOpr {
+        pb::PhysicalOpr::from(OpKind::Limit(algebra_pb::Limit {
+            range: Some(algebra_pb::Range { lower, upper }),
+        }))
+    }
+
+    fn expand(expand_opt: pb::edge_expand::ExpandOpt) -> pb::PhysicalOpr {
+        pb::EdgeExpand { expand_opt: expand_opt as i32, ..Default::default() }.into()
+    }
+
+    fn get_v(opt: pb::get_v::VOpt) -> pb::PhysicalOpr {
+        pb::GetV { opt: opt as i32, ..Default::default() }.into()
+    }
+
+    fn sink() -> pb::PhysicalOpr {
+        pb::PhysicalOpr::from(OpKind::Sink(pb::Sink::default()))
+    }
+
+    fn plan(plan: Vec<pb::PhysicalOpr>) -> pb::PhysicalPlan {
+        pb::PhysicalPlan { plan_id: 1, plan }
+    }
+
+    #[test]
+    fn validate_plan_test() {
+        let valid = plan(vec![scan(pb::scan::ScanOpt::Vertex), limit(0, 10), sink()]);
+        assert_eq!(validate_plan(&valid), Ok(()));
+        assert_eq!(
+            validate_plan(&plan(vec![scan(pb::scan::ScanOpt::Vertex)])),
+            Err(invalid("0", "Scan", None, "the plan must end with a sink"))
+        );
+        assert_eq!(
+            validate_plan(&plan(vec![scan(pb::scan::ScanOpt::Table), sink()])),
+            Err(invalid("0", "Scan", Some("scan_opt"), "scanning a table is not supported"))
+        );
+        assert_eq!(
+            validate_plan(&plan(vec![scan(pb::scan::ScanOpt::Vertex), limit(1, 10), sink()])),
+            Err(invalid("1", "Limit", Some("range"), "the range [1, 10) must be non-empty from 0"))
+        );
+        let params = algebra_pb::QueryParams { excluded_tables: vec![0.into()], ..Default::default() };
+        let unresolved = pb::EdgeExpand { params: Some(params), ..Default::default() }.into();
+        assert_eq!(
+            validate_plan(&plan(vec![scan(pb::scan::ScanOpt::Vertex), unresolved, sink()])),
+            Err(invalid(
+                "1",
+                "EdgeExpand",
+                Some("params.excluded_tables"),
+                "the excluded labels are not resolved against the schema into the tables"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_sub_plans_test() {
+        let apply = |sub_plan: Vec<pb::PhysicalOpr>| -> pb::PhysicalOpr {
+            pb::PhysicalOpr::from(OpKind::Apply(pb::Apply {
+                join_kind: pb::join::JoinKind::Semi as i32,
+                sub_plan: Some(plan(sub_plan)),
+                ..Default::default()
+            }))
+        };
+        let valid = plan(vec![
+            scan(pb::scan::ScanOpt::Vertex),
+            apply(vec![expand(pb::edge_expand::ExpandOpt::Vertex)]),
+            sink(),
+        ]);
+        assert_eq!(validate_plan(&valid), Ok(()));
+        let invalid_plan = plan(vec![
+            scan(pb::scan::ScanOpt::Vertex),
+            apply(vec![expand(pb::edge_expand::ExpandOpt::Vertex), limit(0, 0)]),
+            sink(),
+        ]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid(
+                "1.sub_plan.1",
+                "Limit",
+                Some("range"),
+                "the range [0, 0) must be non-empty from 0"
+            ))
+        );
+        let invalid_plan = plan(vec![scan(pb::scan::ScanOpt::Vertex), apply(vec![sink()]), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid("1.sub_plan.0", "Sink", None, "a sink must be the last operator of the plan"))
+        );
+
+        let repeat = |body: Vec<pb::PhysicalOpr>, max_iterations: i32| -> pb::PhysicalOpr {
+            pb::PhysicalOpr::from(OpKind::Repeat(pb::Repeat {
+                body: Some(plan(body)),
+                max_iterations,
+                ..Default::default()
+            }))
+        };
+        let valid = plan(vec![
+            scan(pb::scan::ScanOpt::Vertex),
+            repeat(vec![expand(pb::edge_expand::ExpandOpt::Vertex)], 3),
+            sink(),
+        ]);
+        assert_eq!(validate_plan(&valid), Ok(()));
+        // the loop never ends without the condition or the max iterations
+        let invalid_plan = plan(vec![
+            scan(pb::scan::ScanOpt::Vertex),
+            repeat(vec![expand(pb::edge_expand::ExpandOpt::Vertex)], 0),
+            sink(),
+        ]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid(
+                "1",
+                "Repeat",
+                Some("max_iterations"),
+                "the max iterations must be positive without the condition"
+            ))
+        );
+        let invalid_plan =
+            plan(vec![scan(pb::scan::ScanOpt::Vertex), repeat(vec![limit(0, 0)], 3), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid("1.body.0", "Limit", Some("range"), "the range [0, 0) must be non-empty from 0"))
+        );
+
+        let coalesce = |sub_plans: Vec<pb::PhysicalPlan>| -> pb::PhysicalOpr {
+            pb::PhysicalOpr::from(OpKind::Coalesce(pb::Coalesce { sub_plans }))
+        };
+        let valid = plan(vec![
+            scan(pb::scan::ScanOpt::Vertex),
+            coalesce(vec![plan(vec![expand(pb::edge_expand::ExpandOpt::Vertex)]), plan(vec![])]),
+            sink(),
+        ]);
+        assert_eq!(validate_plan(&valid), Ok(()));
+        let invalid_plan = plan(vec![scan(pb::scan::ScanOpt::Vertex), coalesce(vec![]), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid("1", "Coalesce", Some("sub_plans"), "the sub-plans are missing"))
+        );
+    }
+
+    #[test]
+    fn validate_sack_test() {
+        let sack = |inner: Option<algebra_pb::sack::Inner>| -> pb::PhysicalOpr {
+            pb::PhysicalOpr::from(OpKind::Sack(algebra_pb::Sack { inner }))
+        };
+        let init = algebra_pb::sack::Inner::Init(algebra_pb::sack::Init {
+            initial: str_to_expr_pb("1".to_string()).ok(),
+        });
+        let valid = plan(vec![scan(pb::scan::ScanOpt::Vertex), sack(Some(init)), sink()]);
+        assert_eq!(validate_plan(&valid), Ok(()));
+        let invalid_plan = plan(vec![scan(pb::scan::ScanOpt::Vertex), sack(None), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid("1", "Sack", Some("inner"), "the operation on the sacks is missing"))
+        );
+        let update = algebra_pb::sack::Inner::Update(algebra_pb::sack::Update { operator: 1, by: None });
+        let invalid_plan = plan(vec![scan(pb::scan::ScanOpt::Vertex), sack(Some(update)), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid("1", "Sack", Some("update.by"), "the value to update by is missing"))
+        );
+    }
+
+    #[test]
+    fn validate_pattern_match_test() {
+        let vertex = |tag: i32| pb::pattern_match::Vertex { tag, ..Default::default() };
+        let triple = |src: i32, dst: i32| pb::pattern_match::Triple { src, dst, ..Default::default() };
+        let pattern_match = |triples: Vec<pb::pattern_match::Triple>, bound_tags: Vec<i32>| {
+            pb::PhysicalOpr::from(OpKind::PatternMatch(pb::PatternMatch {
+                vertices: vec![vertex(0), vertex(1)],
+                triples,
+                bound_tags,
+                alias: None,
+            }))
+        };
+        let valid = plan(vec![pattern_match(vec![triple(0, 1)], vec![]), sink()]);
+        assert_eq!(validate_plan(&valid), Ok(()));
+        let invalid_plan = plan(vec![pattern_match(vec![], vec![]), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid("0", "PatternMatch", Some("triples"), "the triples are missing"))
+        );
+        let invalid_plan = plan(vec![pattern_match(vec![triple(0, 1), triple(1, 2)], vec![]), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid(
+                "0",
+                "PatternMatch",
+                Some("triples[1]"),
+                "the vertices of 1 -> 2 are not declared"
+            ))
+        );
+        let invalid_plan =
+            plan(vec![scan(pb::scan::ScanOpt::Vertex), pattern_match(vec![triple(0, 1)], vec![2]), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid("1", "PatternMatch", Some("bound_tags"), "the bound vertex 2 is not declared"))
+        );
+        let invalid_plan =
+            plan(vec![scan(pb::scan::ScanOpt::Vertex), pattern_match(vec![triple(0, 1)], vec![]), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid(
+                "1",
+                "PatternMatch",
+                Some("bound_tags"),
+                "the bound vertices are missing of the pattern not matched as the source"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_side_effect_test() {
+        let side_effect = |name: &str| -> pb::PhysicalOpr {
+            pb::PhysicalOpr::from(OpKind::SideEffect(algebra_pb::SideEffect {
+                name: name.to_string(),
+                kind: 0,
+                tag: None,
+            }))
+        };
+        let cap =
+            pb::PhysicalOpr::from(OpKind::Cap(algebra_pb::Cap { name: "x".to_string(), alias: None }));
+        let valid = plan(vec![scan(pb::scan::ScanOpt::Vertex), side_effect("x"), cap, sink()]);
+        assert_eq!(validate_plan(&valid), Ok(()));
+        let invalid_plan = plan(vec![scan(pb::scan::ScanOpt::Vertex), side_effect(""), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid("1", "SideEffect", Some("name"), "the name of the side effect is missing"))
+        );
+        // the side effects are accumulated in the sub-plans, but read in the root plan only
+        let repeat = |first: pb::PhysicalOpr| -> pb::PhysicalOpr {
+            pb::PhysicalOpr::from(OpKind::Repeat(pb::Repeat {
+                body: Some(plan(vec![first, expand(pb::edge_expand::ExpandOpt::Vertex)])),
+                max_iterations: 3,
+                ..Default::default()
+            }))
+        };
+        let valid = plan(vec![scan(pb::scan::ScanOpt::Vertex), repeat(side_effect("x")), sink()]);
+        assert_eq!(validate_plan(&valid), Ok(()));
+        let cap =
+            pb::PhysicalOpr::from(OpKind::Cap(algebra_pb::Cap { name: "x".to_string(), alias: None }));
+        let invalid_plan = plan(vec![scan(pb::scan::ScanOpt::Vertex), repeat(cap), sink()]);
+        assert_eq!(
+            validate_plan(&invalid_plan),
+            Err(invalid("1.body.0", "Cap", None, "the side effects are only read in the root plan"))
+        );
+    }
+
+    #[test]
+    fn validate_intersect_test() {
+        let intersect = |sub_plans: Vec<Vec<pb::PhysicalOpr>>| -> pb::PhysicalPlan {
+            let sub_plans = sub_plans.into_iter().map(plan).collect();
+            plan(vec![
+                scan(pb::scan::ScanOpt::Vertex),
+                pb::PhysicalOpr::from(OpKind::Intersect(pb::Intersect { sub_plans, key: 1 })),
+                sink(),
+            ])
+        };
+        let valid = intersect(vec![
+            vec![expand(pb::edge_expand::ExpandOpt::Vertex)],
+            vec![expand(pb::edge_expand::ExpandOpt::Edge), get_v(pb::get_v::VOpt::Other)],
+            vec![expand(pb::edge_expand::ExpandOpt::Vertex), get_v(pb::get_v::VOpt::Itself)],
+        ]);
+        assert_eq!(validate_plan(&valid), Ok(()));
+        assert_eq!(
+            validate_plan(&intersect(vec![
+                vec![expand(pb::edge_expand::ExpandOpt::Vertex)],
+                vec![expand(pb::edge_expand::ExpandOpt::Edge)],
+            ])),
+            Err(invalid(
+                "1.sub_plans[1].0",
+                "EdgeExpand",
+                Some("expand_opt"),
+                "the edges to intersect must be followed by getting their adjacent vertices"
+            ))
+        );
+        assert_eq!(
+            validate_plan(&intersect(vec![
+                vec![expand(pb::edge_expand::ExpandOpt::Vertex)],
+                vec![limit(0, 10)],
+            ])),
+            Err(invalid(
+                "1.sub_plans[1].0",
+                "Limit",
+                None,
+                "the sub-plan to intersect must end with an expand"
+            ))
+        );
+        let mut optional =
+            pb::EdgeExpand { expand_opt: pb::edge_expand::ExpandOpt::Vertex as i32, ..Default::default() };
+        optional.is_optional = true;
+        assert_eq!(
+            validate_plan(&intersect(vec![
+                vec![expand(pb::edge_expand::ExpandOpt::Vertex)],
+                vec![optional.into()],
+            ])),
+            Err(invalid(
+                "1",
+                "Intersect",
+                Some("sub_plans[1].is_optional"),
+                "an optional expand to intersect is not supported"
+            ))
+        );
+    }
+}
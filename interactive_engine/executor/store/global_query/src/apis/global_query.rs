@@ -15,7 +15,10 @@
 
 use std::sync::Arc;
 
-use groot_store::api::{Condition, Edge, LabelId, PartitionId, PropId, SnapshotId, Vertex, VertexId};
+use groot_store::api::{
+    Condition, Edge, EdgeId, LabelId, PartitionId, PropId, SnapshotId, Vertex, VertexId,
+};
+use groot_store::db::api::EdgeDirection;
 
 use crate::apis::graph_schema::Schema;
 
@@ -61,7 +64,32 @@ pub trait GlobalGraphQuery: Send + Sync {
     fn get_edge_properties(
         &self, si: SnapshotId, ids: Vec<PartitionLabeledVertexIds>, output_prop_ids: Option<&Vec<PropId>>,
     ) -> Self::EI;
+    /// Get the edge of the label from the source vertex in the partition to the destination vertex,
+    /// which is discriminated by its id among the parallel edges.
+    fn get_edge_by_id(
+        &self, si: SnapshotId, partition_id: PartitionId, label: LabelId, src_id: VertexId,
+        dst_id: VertexId, edge_id: EdgeId, output_prop_ids: Option<&Vec<PropId>>,
+    ) -> Option<Self::E>;
+    /// Get the edges of the label from (`Out`) or to (`In`) the vertex in the partition within the
+    /// inclusive bounds of the sort property of the label, in its descending order and at most `limit`
+    /// of them unless it's 0, e.g. the latest k edges by a timestamp.
+    fn get_sorted_edges(
+        &self, si: SnapshotId, partition_id: PartitionId, vertex_id: VertexId, label: LabelId,
+        direction: EdgeDirection, lower: Option<i64>, upper: Option<i64>, limit: usize,
+        output_prop_ids: Option<&Vec<PropId>>,
+    ) -> Self::EI;
+    /// Get a page of at most `page_size` edges of the labels from (`Out`) or to (`In`) the vertex in
+    /// the partition, which is read after the page of `token`, with the token of the next page if the
+    /// edges are truncated by the page size, so that a super vertex is expanded page by page.
+    fn get_edges_page(
+        &self, si: SnapshotId, partition_id: PartitionId, vertex_id: VertexId, edge_labels: &Vec<LabelId>,
+        direction: EdgeDirection, token: Option<&[u8]>, page_size: usize,
+        output_prop_ids: Option<&Vec<PropId>>,
+    ) -> (Self::EI, Option<Vec<u8>>);
 
+    /// Get the vertices of the labels, or of all the labels if they're empty, in the partitions, where
+    /// the labels are fused into one scan of each partition, e.g. skipping the labels of no vertex in
+    /// it, so that `limit` bounds the vertices of all the labels in a partition rather than of each.
     fn get_all_vertices(
         &self, si: SnapshotId, labels: &Vec<LabelId>, condition: Option<&Condition>,
         dedup_prop_ids: Option<&Vec<PropId>>, output_prop_ids: Option<&Vec<PropId>>, limit: usize,
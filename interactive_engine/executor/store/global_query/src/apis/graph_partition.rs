@@ -14,15 +14,30 @@
 //! limitations under the License.
 
 use groot_store::api::prelude::Property;
-use groot_store::api::{LabelId, PartitionId, VertexId};
+use groot_store::api::{LabelId, PartitionId, PropId, VertexId};
 
 // Partition manager for graph query
 pub trait GraphPartitionManager: Send + Sync {
     fn get_partition_id(&self, vid: VertexId) -> i32;
     fn get_server_id(&self, pid: PartitionId) -> Option<u32>;
+    /// The server to read the partition from, which may be a follower no staler than
+    /// `max_staleness_ms`, or the leader only if it's 0, regardless of the follower reads configured.
+    fn get_server_id_within_staleness(&self, pid: PartitionId, _max_staleness_ms: u64) -> Option<u32> {
+        self.get_server_id(pid)
+    }
     fn get_process_partition_list(&self) -> Vec<PartitionId>;
     fn get_vertex_id_by_primary_key(
         &self, label_id: LabelId, key: &String,
     ) -> Option<(PartitionId, VertexId)>;
     fn get_vertex_id_by_primary_keys(&self, label_id: LabelId, pks: &[Property]) -> Option<VertexId>;
+    /// The vertex id mapped from the external string id, if the partition owning the external id is
+    /// in this process and the id is mapped.
+    fn lookup_by_external_id(&self, _label_id: LabelId, _external_id: &str) -> Option<VertexId> {
+        None
+    }
+    /// The ids of the primary key properties of the label, in the order they are hashed into the
+    /// vertex id, which is required to look up the vertex by a composite primary key.
+    fn get_primary_key_ids(&self, _label_id: LabelId) -> Option<Vec<PropId>> {
+        None
+    }
 }
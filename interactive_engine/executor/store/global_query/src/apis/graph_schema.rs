@@ -21,5 +21,10 @@ pub trait Schema: Send + Sync {
     fn get_prop_name(&self, prop_id: PropId) -> Option<String>;
     fn get_label_id(&self, name: &str) -> Option<LabelId>;
     fn get_label_name(&self, label: LabelId) -> Option<String>;
+    /// Get the ids of all properties defined on `label`, ordered by property id.
+    /// Returns an empty vector if the schema cannot enumerate the properties of a label.
+    fn get_label_prop_ids(&self, _label: LabelId) -> Vec<PropId> {
+        vec![]
+    }
     fn to_proto(&self) -> Vec<u8>;
 }
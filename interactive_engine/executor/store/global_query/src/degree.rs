@@ -0,0 +1,431 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Degree distributions, hottest vertices and partition skew of the edges of a label, e.g. for capacity
+//! planning and finding the super vertices. Every partition of the process is scanned by its own thread.
+//!
+//! A report is exact, or approximate by sampling the edges at a rate, where the degrees and the edge
+//! counts are those sampled scaled by the rate. A sampled report keeps the memory of the degrees by
+//! the rate and estimates the high degrees well, so it still finds the hottest vertices, but it misses
+//! many vertices of low degrees. The reports are cached for `cache_ttl` by the label, the snapshot and
+//! the rate.
+//!
+//! As a report scans all the edges of the label, only one report is computed at a time, a refresh of a
+//! report computed less than `min_refresh_interval` ago is answered by the cache, and a report keeping
+//! the degrees of more than `max_vertices` vertices fails, which is to be sampled at a lower rate.
+//!
+//! A report is of the partitions of the process, the edge counts of the partitions of the other
+//! processes are merged by the server, see `pegasus_server::admin`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use groot_store::api::{Edge, LabelId, PartitionId, SnapshotId, VertexId};
+use serde::Serialize;
+
+use crate::apis::global_query::GlobalGraphQuery;
+use crate::apis::graph_partition::GraphPartitionManager;
+
+#[derive(Clone, Debug)]
+pub struct DegreeReportConfig {
+    /// The number of the hottest vertices reported by degree.
+    pub top_k: usize,
+    pub cache_ttl: Duration,
+    /// The least time between the computations of a report, even if it's refreshed.
+    pub min_refresh_interval: Duration,
+    /// The most vertices of which the degrees are kept by a report.
+    pub max_vertices: usize,
+}
+
+impl Default for DegreeReportConfig {
+    fn default() -> Self {
+        DegreeReportConfig {
+            top_k: 20,
+            cache_ttl: Duration::from_secs(600),
+            min_refresh_interval: Duration::from_secs(60),
+            max_vertices: 1 << 24,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HotVertex {
+    pub id: VertexId,
+    pub degree: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DegreeDistribution {
+    /// The number of the vertices with at least one edge.
+    pub vertex_count: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p99: u64,
+    /// The number of the vertices by degree, where the i-th bucket counts the degrees in `[2^i, 2^(i+1))`.
+    pub histogram: Vec<u64>,
+    pub hottest: Vec<HotVertex>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PartitionEdges {
+    pub partition_id: PartitionId,
+    pub edge_count: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DegreeReport {
+    pub label: String,
+    pub snapshot_id: SnapshotId,
+    /// The rate the edges are sampled at, 1.0 for an exact report.
+    pub sample_rate: f64,
+    pub edge_count: u64,
+    pub out_degree: DegreeDistribution,
+    pub in_degree: DegreeDistribution,
+    pub partitions: Vec<PartitionEdges>,
+    /// The most edges of a partition over the mean of the partitions, 1.0 if they're even.
+    pub partition_skew: f64,
+    pub computed_at_ms: u64,
+}
+
+#[derive(Default)]
+struct PartitionDegrees {
+    edge_count: u64,
+    out_degrees: HashMap<VertexId, u64>,
+    in_degrees: HashMap<VertexId, u64>,
+}
+
+type CacheKey = (LabelId, SnapshotId, u64);
+
+pub struct DegreeReporter<G: GlobalGraphQuery + GraphPartitionManager> {
+    graph: Arc<G>,
+    config: DegreeReportConfig,
+    cache: Mutex<HashMap<CacheKey, (Instant, Arc<DegreeReport>)>>,
+    // held by the report being computed
+    computing: Mutex<()>,
+}
+
+impl<G: GlobalGraphQuery + GraphPartitionManager> DegreeReporter<G> {
+    pub fn new(graph: Arc<G>, config: DegreeReportConfig) -> Self {
+        DegreeReporter { graph, config, cache: Mutex::new(HashMap::new()), computing: Mutex::new(()) }
+    }
+
+    /// The report of the edge label, given by its name or id, at the snapshot. It's computed exactly
+    /// if `sample_rate` is none, and is taken from the cache unless `refresh`, or it's computed within
+    /// `min_refresh_interval`.
+    pub fn report(
+        &self, label: &str, si: SnapshotId, sample_rate: Option<f64>, refresh: bool,
+    ) -> Result<Arc<DegreeReport>, String> {
+        let sample_rate = sample_rate.unwrap_or(1.0);
+        if !(sample_rate > 0.0 && sample_rate <= 1.0) {
+            return Err(format!("invalid sample rate {}, which should be in (0, 1]", sample_rate));
+        }
+        let schema = self
+            .graph
+            .get_schema(si)
+            .ok_or_else(|| format!("schema not found at snapshot {}", si))?;
+        let label_id = schema
+            .get_label_id(label)
+            .or_else(|| label.parse::<LabelId>().ok())
+            .ok_or_else(|| format!("edge label {} not found", label))?;
+        let key = (label_id, si, sample_rate.to_bits());
+        let cached = || {
+            let cache = self
+                .cache
+                .lock()
+                .expect("degree report cache poisoned");
+            cache
+                .get(&key)
+                .and_then(|(computed_at, report)| {
+                    let fresh_for =
+                        if refresh { self.config.min_refresh_interval } else { self.config.cache_ttl };
+                    if computed_at.elapsed() < fresh_for {
+                        Some(report.clone())
+                    } else {
+                        None
+                    }
+                })
+        };
+        if let Some(report) = cached() {
+            return Ok(report);
+        }
+        let _computing = match self.computing.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::WouldBlock) => {
+                return Err("another degree report is being computed, retry later".to_owned());
+            }
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        };
+        // computed by the report just finished
+        if let Some(report) = cached() {
+            return Ok(report);
+        }
+        let label_name = schema
+            .get_label_name(label_id)
+            .unwrap_or_else(|| label_id.to_string());
+        let report = Arc::new(self.compute(label_id, label_name, si, sample_rate)?);
+        let mut cache = self
+            .cache
+            .lock()
+            .expect("degree report cache poisoned");
+        cache.retain(|_, (computed_at, _)| computed_at.elapsed() < self.config.cache_ttl);
+        cache.insert(key, (Instant::now(), report.clone()));
+        Ok(report)
+    }
+
+    /// The report in json, see `report()`.
+    pub fn report_json(
+        &self, label: &str, si: SnapshotId, sample_rate: Option<f64>, refresh: bool,
+    ) -> Result<Vec<u8>, String> {
+        let report = self.report(label, si, sample_rate, refresh)?;
+        serde_json::to_vec(report.as_ref()).map_err(|e| e.to_string())
+    }
+
+    fn compute(
+        &self, label_id: LabelId, label: String, si: SnapshotId, sample_rate: f64,
+    ) -> Result<DegreeReport, String> {
+        let partition_ids = self.graph.get_process_partition_list();
+        let results = std::thread::scope(|s| {
+            let handles = partition_ids
+                .iter()
+                .map(|partition_id| {
+                    s.spawn(move || self.scan_partition(*partition_id, label_id, si, sample_rate))
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join())
+                .collect::<Vec<_>>()
+        });
+        let mut partitions = Vec::with_capacity(results.len());
+        let mut out_degrees = HashMap::new();
+        let mut in_degrees = HashMap::new();
+        for (partition_id, result) in partition_ids.iter().zip(results) {
+            let degrees = result.map_err(|_| format!("scan partition {} panicked", partition_id))??;
+            partitions.push(PartitionEdges {
+                partition_id: *partition_id,
+                edge_count: scale(degrees.edge_count, sample_rate),
+            });
+            merge_degrees(&mut out_degrees, degrees.out_degrees);
+            merge_degrees(&mut in_degrees, degrees.in_degrees);
+            self.check_vertices(out_degrees.len() + in_degrees.len(), sample_rate)?;
+        }
+        let edge_count = partitions.iter().map(|p| p.edge_count).sum();
+        let partition_skew = skew(&partitions);
+        let top_k = self.config.top_k;
+        info!(
+            "degrees of edge label {} at snapshot {} computed, {} edges sampled at {}",
+            label, si, edge_count, sample_rate
+        );
+        Ok(DegreeReport {
+            label,
+            snapshot_id: si,
+            sample_rate,
+            edge_count,
+            out_degree: distribution(out_degrees, sample_rate, top_k),
+            in_degree: distribution(in_degrees, sample_rate, top_k),
+            partitions,
+            partition_skew,
+            computed_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+        })
+    }
+
+    fn check_vertices(&self, vertices: usize, sample_rate: f64) -> Result<(), String> {
+        if vertices > self.config.max_vertices {
+            Err(format!(
+                "degrees of more than {} vertices are kept at sample rate {}, sample at a lower rate",
+                self.config.max_vertices, sample_rate
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn scan_partition(
+        &self, partition_id: PartitionId, label_id: LabelId, si: SnapshotId, sample_rate: f64,
+    ) -> Result<PartitionDegrees, String> {
+        let mut degrees = PartitionDegrees::default();
+        // no property of the edges is read
+        let no_props = vec![];
+        let edges = self.graph.get_all_edges(
+            si,
+            &vec![label_id],
+            None,
+            None,
+            Some(&no_props),
+            usize::max_value(),
+            &vec![partition_id],
+        );
+        for e in edges {
+            if sample_rate < 1.0
+                && !is_sampled(e.get_edge_id(), e.get_src_id(), e.get_dst_id(), sample_rate)
+            {
+                continue;
+            }
+            degrees.edge_count += 1;
+            *degrees
+                .out_degrees
+                .entry(e.get_src_id())
+                .or_insert(0) += 1;
+            *degrees
+                .in_degrees
+                .entry(e.get_dst_id())
+                .or_insert(0) += 1;
+            if degrees.edge_count % 4096 == 0 {
+                self.check_vertices(degrees.out_degrees.len() + degrees.in_degrees.len(), sample_rate)?;
+            }
+        }
+        Ok(degrees)
+    }
+}
+
+/// Whether the edge is sampled, which is decided by the hash of the edge so that the same edges are
+/// sampled every time at the same rate.
+fn is_sampled(edge_id: i64, src_id: VertexId, dst_id: VertexId, sample_rate: f64) -> bool {
+    let mut hasher = DefaultHasher::new();
+    (edge_id, src_id, dst_id).hash(&mut hasher);
+    (hasher.finish() as f64) < sample_rate * u64::MAX as f64
+}
+
+fn scale(count: u64, sample_rate: f64) -> u64 {
+    (count as f64 / sample_rate).round() as u64
+}
+
+fn merge_degrees(into: &mut HashMap<VertexId, u64>, from: HashMap<VertexId, u64>) {
+    if into.is_empty() {
+        *into = from;
+        return;
+    }
+    for (id, degree) in from {
+        *into.entry(id).or_insert(0) += degree;
+    }
+}
+
+fn skew(partitions: &[PartitionEdges]) -> f64 {
+    let total: u64 = partitions.iter().map(|p| p.edge_count).sum();
+    let max = partitions
+        .iter()
+        .map(|p| p.edge_count)
+        .max()
+        .unwrap_or(0);
+    if total == 0 {
+        1.0
+    } else {
+        max as f64 * partitions.len() as f64 / total as f64
+    }
+}
+
+fn distribution(degrees: HashMap<VertexId, u64>, sample_rate: f64, top_k: usize) -> DegreeDistribution {
+    let mut degrees = degrees
+        .into_iter()
+        .map(|(id, count)| (id, scale(count, sample_rate)))
+        .collect::<Vec<_>>();
+    if degrees.is_empty() {
+        return DegreeDistribution::default();
+    }
+    // in the descending order of the degrees, and the ascending order of the ids among the same degree
+    degrees.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let n = degrees.len();
+    let percentile = |q: f64| degrees[n - ((q * n as f64).ceil() as usize).clamp(1, n)].1;
+    let mut histogram = vec![];
+    for (_, degree) in degrees.iter() {
+        let bucket = (63 - (*degree).max(1).leading_zeros()) as usize;
+        if histogram.len() <= bucket {
+            histogram.resize(bucket + 1, 0);
+        }
+        histogram[bucket] += 1;
+    }
+    DegreeDistribution {
+        vertex_count: n as u64,
+        max: degrees[0].1,
+        mean: degrees
+            .iter()
+            .map(|(_, d)| *d as f64)
+            .sum::<f64>()
+            / n as f64,
+        p50: percentile(0.5),
+        p99: percentile(0.99),
+        histogram,
+        hottest: degrees
+            .iter()
+            .take(top_k)
+            .map(|(id, degree)| HotVertex { id: *id, degree: *degree })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distribution() {
+        let degrees = (1..=100)
+            .map(|i| (i as VertexId, i as u64))
+            .collect::<HashMap<_, _>>();
+        let dist = distribution(degrees, 1.0, 3);
+        assert_eq!(dist.vertex_count, 100);
+        assert_eq!(dist.max, 100);
+        assert_eq!(dist.mean, 50.5);
+        assert_eq!(dist.p50, 50);
+        assert_eq!(dist.p99, 99);
+        // [1, 2), [2, 4), ..., [64, 128)
+        assert_eq!(dist.histogram, vec![1, 2, 4, 8, 16, 32, 37]);
+        assert_eq!(
+            dist.hottest,
+            vec![
+                HotVertex { id: 100, degree: 100 },
+                HotVertex { id: 99, degree: 99 },
+                HotVertex { id: 98, degree: 98 }
+            ]
+        );
+
+        let degrees = vec![(1, 5), (2, 1)]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let dist = distribution(degrees, 0.1, 1);
+        assert_eq!(dist.max, 50);
+        assert_eq!(dist.hottest, vec![HotVertex { id: 1, degree: 50 }]);
+        assert_eq!(distribution(HashMap::new(), 1.0, 1), DegreeDistribution::default());
+    }
+
+    #[test]
+    fn test_skew() {
+        let partitions = |counts: Vec<u64>| {
+            counts
+                .into_iter()
+                .enumerate()
+                .map(|(i, edge_count)| PartitionEdges { partition_id: i as PartitionId, edge_count })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(skew(&partitions(vec![10, 10, 10, 10])), 1.0);
+        assert_eq!(skew(&partitions(vec![40, 0, 0, 0])), 4.0);
+        assert_eq!(skew(&partitions(vec![0, 0])), 1.0);
+    }
+
+    #[test]
+    fn test_is_sampled() {
+        let sampled = (0..10000)
+            .filter(|i| is_sampled(*i, *i, *i + 1, 0.1))
+            .count();
+        assert!(sampled > 800 && sampled < 1200, "{} of 10000 sampled at 0.1", sampled);
+        assert_eq!(is_sampled(7, 1, 2, 0.5), is_sampled(7, 1, 2, 0.5));
+    }
+}
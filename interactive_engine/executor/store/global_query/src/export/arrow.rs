@@ -0,0 +1,457 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BinaryArray, BinaryBuilder, BooleanArray, Float32Array, Float64Array, Int16Array, Int32Array,
+    Int64Array, ListArray, ListBuilder, StringArray, StringBuilder, UInt8Array,
+};
+use arrow::datatypes::{
+    DataType as ArrowType, Field, Float32Type, Float64Type, Int32Type, Int64Type, Schema as ArrowSchema,
+};
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use groot_store::api::{DataType, LabelId, PartitionId, Property};
+use parquet::arrow::ArrowWriter as ParquetWriter;
+
+use super::{partition_file_name, sanitize_file_name, ElementWriter, ExportRecord};
+use crate::apis::graph_schema::Schema;
+
+/// The number of rows buffered for a label before they are written as a record batch.
+const BATCH_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileKind {
+    Ipc,
+    Parquet,
+}
+
+impl FileKind {
+    fn extension(&self) -> &'static str {
+        match self {
+            FileKind::Ipc => "arrow",
+            FileKind::Parquet => "parquet",
+        }
+    }
+}
+
+enum FileSink {
+    Ipc(FileWriter<BufWriter<File>>),
+    Parquet(ParquetWriter<File>),
+}
+
+impl FileSink {
+    fn write(&mut self, batch: &RecordBatch) -> io::Result<()> {
+        match self {
+            FileSink::Ipc(writer) => writer.write(batch).map_err(arrow_error),
+            FileSink::Parquet(writer) => writer.write(batch).map_err(parquet_error),
+        }
+    }
+
+    fn close(self) -> io::Result<()> {
+        match self {
+            FileSink::Ipc(mut writer) => writer.finish().map_err(arrow_error),
+            FileSink::Parquet(writer) => writer
+                .close()
+                .map(|_| ())
+                .map_err(parquet_error),
+        }
+    }
+}
+
+struct Row {
+    id: i64,
+    /// `(src_id, dst_id, src_label, dst_label)` of an edge
+    endpoints: Option<(i64, i64, String, String)>,
+    /// The values of the property columns, in the order of the columns
+    values: Vec<Property>,
+}
+
+struct LabelFile {
+    path: PathBuf,
+    label: String,
+    schema: Arc<ArrowSchema>,
+    columns: Vec<(String, DataType)>,
+    rows: Vec<Row>,
+    sink: FileSink,
+}
+
+impl LabelFile {
+    fn flush(&mut self) -> io::Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let rows = std::mem::take(&mut self.rows);
+        let mut arrays: Vec<ArrayRef> =
+            vec![Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.id)))];
+        arrays.push(Arc::new(StringArray::from_iter_values(rows.iter().map(|_| self.label.as_str()))));
+        if rows
+            .first()
+            .map_or(false, |r| r.endpoints.is_some())
+        {
+            let endpoints = rows
+                .iter()
+                .map(|r| {
+                    r.endpoints
+                        .as_ref()
+                        .expect("edge record without endpoints")
+                })
+                .collect::<Vec<_>>();
+            arrays.push(Arc::new(Int64Array::from_iter_values(endpoints.iter().map(|e| e.0))));
+            arrays.push(Arc::new(Int64Array::from_iter_values(endpoints.iter().map(|e| e.1))));
+            arrays.push(Arc::new(StringArray::from_iter_values(endpoints.iter().map(|e| e.2.as_str()))));
+            arrays.push(Arc::new(StringArray::from_iter_values(endpoints.iter().map(|e| e.3.as_str()))));
+        }
+        for (i, (_, data_type)) in self.columns.iter().enumerate() {
+            arrays.push(build_column(data_type, rows.iter().map(|r| &r.values[i])));
+        }
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays).map_err(arrow_error)?;
+        self.sink.write(&batch)
+    }
+}
+
+/// Writes one Arrow IPC file or Parquet file per label, with the columns `id` and `label` for vertices,
+/// `id`, `label`, `src_id`, `dst_id`, `src_label` and `dst_label` for edges, followed by the properties
+/// of the label typed as declared by the schema.
+pub(crate) struct ArrowWriter<'a> {
+    output_dir: PathBuf,
+    partition_id: PartitionId,
+    schema: &'a dyn Schema,
+    kind: FileKind,
+    vertex_files: HashMap<LabelId, LabelFile>,
+    edge_files: HashMap<LabelId, LabelFile>,
+}
+
+impl<'a> ArrowWriter<'a> {
+    pub fn new(
+        output_dir: PathBuf, partition_id: PartitionId, schema: &'a dyn Schema, kind: FileKind,
+    ) -> Self {
+        ArrowWriter {
+            output_dir,
+            partition_id,
+            schema,
+            kind,
+            vertex_files: HashMap::new(),
+            edge_files: HashMap::new(),
+        }
+    }
+
+    fn open_label_file(&self, record: &ExportRecord, is_vertex: bool) -> io::Result<LabelFile> {
+        let prefix = if is_vertex { "vertex" } else { "edge" };
+        let path = self.output_dir.join(partition_file_name(
+            &format!("{}_{}", prefix, sanitize_file_name(&record.label)),
+            self.partition_id,
+            self.kind.extension(),
+        ));
+        let prop_ids = self.schema.get_label_prop_ids(record.label_id);
+        let columns = if prop_ids.is_empty() {
+            record
+                .properties
+                .iter()
+                .map(|p| (p.name.clone(), p.data_type))
+                .collect::<Vec<_>>()
+        } else {
+            prop_ids
+                .into_iter()
+                .map(|prop_id| {
+                    let name = self
+                        .schema
+                        .get_prop_name(prop_id)
+                        .unwrap_or_else(|| prop_id.to_string());
+                    let data_type = self
+                        .schema
+                        .get_prop_type(record.label_id, prop_id)
+                        .unwrap_or_default();
+                    (name, data_type)
+                })
+                .collect()
+        };
+        let mut fields =
+            vec![Field::new("id", ArrowType::Int64, false), Field::new("label", ArrowType::Utf8, false)];
+        if !is_vertex {
+            fields.push(Field::new("src_id", ArrowType::Int64, false));
+            fields.push(Field::new("dst_id", ArrowType::Int64, false));
+            fields.push(Field::new("src_label", ArrowType::Utf8, false));
+            fields.push(Field::new("dst_label", ArrowType::Utf8, false));
+        }
+        for (name, data_type) in &columns {
+            fields.push(Field::new(name, arrow_type(data_type), true));
+        }
+        let schema = Arc::new(ArrowSchema::new(fields));
+        let file = File::create(&path)?;
+        let sink = match self.kind {
+            FileKind::Ipc => {
+                FileSink::Ipc(FileWriter::try_new(BufWriter::new(file), &schema).map_err(arrow_error)?)
+            }
+            FileKind::Parquet => FileSink::Parquet(
+                ParquetWriter::try_new(file, schema.clone(), None).map_err(parquet_error)?,
+            ),
+        };
+        Ok(LabelFile { path, label: record.label.clone(), schema, columns, rows: vec![], sink })
+    }
+
+    fn write_record(&mut self, record: &ExportRecord, is_vertex: bool) -> io::Result<()> {
+        let files = if is_vertex { &self.vertex_files } else { &self.edge_files };
+        if !files.contains_key(&record.label_id) {
+            let file = self.open_label_file(record, is_vertex)?;
+            if is_vertex {
+                self.vertex_files.insert(record.label_id, file);
+            } else {
+                self.edge_files.insert(record.label_id, file);
+            }
+        }
+        let files = if is_vertex { &mut self.vertex_files } else { &mut self.edge_files };
+        let file = files.get_mut(&record.label_id).unwrap();
+        let values = file
+            .columns
+            .iter()
+            .map(|(name, _)| {
+                record
+                    .properties
+                    .iter()
+                    .find(|p| &p.name == name)
+                    .map(|p| p.value.clone())
+                    .unwrap_or(Property::Null)
+            })
+            .collect();
+        let endpoints = record
+            .endpoints
+            .as_ref()
+            .map(|e| (e.src_id, e.dst_id, e.src_label.clone(), e.dst_label.clone()));
+        file.rows
+            .push(Row { id: record.id, endpoints, values });
+        if file.rows.len() >= BATCH_SIZE {
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ElementWriter for ArrowWriter<'a> {
+    fn write_vertex(&mut self, vertex: &ExportRecord) -> io::Result<()> {
+        self.write_record(vertex, true)
+    }
+
+    fn write_edge(&mut self, edge: &ExportRecord) -> io::Result<()> {
+        self.write_record(edge, false)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        for (_, mut file) in self
+            .vertex_files
+            .into_iter()
+            .chain(self.edge_files.into_iter())
+        {
+            file.flush()?;
+            file.sink.close()?;
+            files.push(file.path);
+        }
+        Ok(files)
+    }
+}
+
+fn arrow_error(e: arrow::error::ArrowError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("write arrow error {:?}", e))
+}
+
+fn parquet_error(e: parquet::errors::ParquetError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("write parquet error {:?}", e))
+}
+
+fn list_type(item: ArrowType) -> ArrowType {
+    ArrowType::List(Box::new(Field::new("item", item, true)))
+}
+
+fn arrow_type(data_type: &DataType) -> ArrowType {
+    match data_type {
+        DataType::Bool => ArrowType::Boolean,
+        DataType::Char => ArrowType::UInt8,
+        DataType::Short => ArrowType::Int16,
+        DataType::Int => ArrowType::Int32,
+        DataType::Long => ArrowType::Int64,
+        DataType::Float => ArrowType::Float32,
+        DataType::Double => ArrowType::Float64,
+        DataType::Bytes => ArrowType::Binary,
+        DataType::ListInt => list_type(ArrowType::Int32),
+        DataType::ListLong => list_type(ArrowType::Int64),
+        DataType::ListFloat => list_type(ArrowType::Float32),
+        DataType::ListDouble => list_type(ArrowType::Float64),
+        DataType::ListString => list_type(ArrowType::Utf8),
+        DataType::ListBytes => list_type(ArrowType::Binary),
+        _ => ArrowType::Utf8,
+    }
+}
+
+/// Build the column of a property in the type given by `arrow_type()`, where a value of another type
+/// is written as null, except that the narrower integers and floats are widened.
+fn build_column<'b, I: Iterator<Item = &'b Property>>(data_type: &DataType, values: I) -> ArrayRef {
+    match data_type {
+        DataType::Bool => Arc::new(
+            values
+                .map(|v| if let Property::Bool(b) = v { Some(*b) } else { None })
+                .collect::<BooleanArray>(),
+        ),
+        DataType::Char => Arc::new(
+            values
+                .map(|v| if let Property::Char(c) = v { Some(*c) } else { None })
+                .collect::<UInt8Array>(),
+        ),
+        DataType::Short => Arc::new(
+            values
+                .map(|v| if let Property::Short(s) = v { Some(*s) } else { None })
+                .collect::<Int16Array>(),
+        ),
+        DataType::Int => Arc::new(
+            values
+                .map(|v| match v {
+                    Property::Short(s) => Some(*s as i32),
+                    Property::Int(i) => Some(*i),
+                    _ => None,
+                })
+                .collect::<Int32Array>(),
+        ),
+        DataType::Long => Arc::new(
+            values
+                .map(|v| match v {
+                    Property::Short(s) => Some(*s as i64),
+                    Property::Int(i) => Some(*i as i64),
+                    Property::Long(l) => Some(*l),
+                    _ => None,
+                })
+                .collect::<Int64Array>(),
+        ),
+        DataType::Float => Arc::new(
+            values
+                .map(|v| if let Property::Float(f) = v { Some(*f) } else { None })
+                .collect::<Float32Array>(),
+        ),
+        DataType::Double => Arc::new(
+            values
+                .map(|v| match v {
+                    Property::Float(f) => Some(*f as f64),
+                    Property::Double(d) => Some(*d),
+                    _ => None,
+                })
+                .collect::<Float64Array>(),
+        ),
+        DataType::Bytes => Arc::new(
+            values
+                .map(|v| if let Property::Bytes(b) = v { Some(b.as_slice()) } else { None })
+                .collect::<BinaryArray>(),
+        ),
+        DataType::ListInt => Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(values.map(|v| {
+            if let Property::ListInt(l) = v {
+                Some(l.iter().map(|i| Some(*i)).collect::<Vec<_>>())
+            } else {
+                None
+            }
+        }))),
+        DataType::ListLong => {
+            Arc::new(ListArray::from_iter_primitive::<Int64Type, _, _>(values.map(|v| {
+                if let Property::ListLong(l) = v {
+                    Some(l.iter().map(|i| Some(*i)).collect::<Vec<_>>())
+                } else {
+                    None
+                }
+            })))
+        }
+        DataType::ListFloat => {
+            Arc::new(ListArray::from_iter_primitive::<Float32Type, _, _>(values.map(|v| {
+                if let Property::ListFloat(l) = v {
+                    Some(l.iter().map(|f| Some(*f)).collect::<Vec<_>>())
+                } else {
+                    None
+                }
+            })))
+        }
+        DataType::ListDouble => {
+            Arc::new(ListArray::from_iter_primitive::<Float64Type, _, _>(values.map(|v| {
+                if let Property::ListDouble(l) = v {
+                    Some(l.iter().map(|d| Some(*d)).collect::<Vec<_>>())
+                } else {
+                    None
+                }
+            })))
+        }
+        DataType::ListString => {
+            let mut builder = ListBuilder::new(StringBuilder::new());
+            for v in values {
+                if let Property::ListString(l) = v {
+                    for s in l {
+                        builder.values().append_value(s);
+                    }
+                    builder.append(true);
+                } else {
+                    builder.append(false);
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::ListBytes => {
+            let mut builder = ListBuilder::new(BinaryBuilder::new());
+            for v in values {
+                if let Property::ListBytes(l) = v {
+                    for b in l {
+                        builder.values().append_value(b);
+                    }
+                    builder.append(true);
+                } else {
+                    builder.append(false);
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        _ => Arc::new(
+            values
+                .map(|v| match v {
+                    Property::Null | Property::Unknown => None,
+                    _ => Some(v.to_plain_string()),
+                })
+                .collect::<StringArray>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::Array;
+
+    use super::*;
+
+    #[test]
+    fn test_build_column() {
+        let values = vec![Property::Int(1), Property::Long(2), Property::Null];
+        let column = build_column(&DataType::Long, values.iter());
+        assert_eq!(column.data_type(), &arrow_type(&DataType::Long));
+        assert_eq!(column.len(), 3);
+        assert_eq!(column.null_count(), 1);
+
+        let values = vec![Property::ListString(vec!["a".to_owned(), "b".to_owned()]), Property::Int(1)];
+        let column = build_column(&DataType::ListString, values.iter());
+        assert_eq!(column.data_type(), &arrow_type(&DataType::ListString));
+        assert_eq!(column.null_count(), 1);
+
+        let values = vec![Property::Date("20220101".to_owned()), Property::Null];
+        let column = build_column(&DataType::Date, values.iter());
+        assert_eq!(column.data_type(), &ArrowType::Utf8);
+        assert_eq!(column.null_count(), 1);
+    }
+}
@@ -0,0 +1,226 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use groot_store::api::{DataType, LabelId, PartitionId, Property};
+
+use super::{partition_file_name, sanitize_file_name, ElementWriter, ExportRecord};
+use crate::apis::graph_schema::Schema;
+
+const ARRAY_DELIMITER: &str = ";";
+
+struct LabelFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    columns: Vec<String>,
+}
+
+/// Writes one csv file per label, with headers in the format of the neo4j bulk importer,
+/// i.e. `:ID,:LABEL,name:type,...` for vertices and `:START_ID,:END_ID,:TYPE,name:type,...`
+/// for edges.
+pub(crate) struct CsvWriter<'a> {
+    output_dir: PathBuf,
+    partition_id: PartitionId,
+    schema: &'a dyn Schema,
+    vertex_files: HashMap<LabelId, LabelFile>,
+    edge_files: HashMap<LabelId, LabelFile>,
+}
+
+impl<'a> CsvWriter<'a> {
+    pub fn new(output_dir: PathBuf, partition_id: PartitionId, schema: &'a dyn Schema) -> Self {
+        CsvWriter {
+            output_dir,
+            partition_id,
+            schema,
+            vertex_files: HashMap::new(),
+            edge_files: HashMap::new(),
+        }
+    }
+
+    fn open_label_file(&self, record: &ExportRecord, is_vertex: bool) -> io::Result<LabelFile> {
+        let prefix = if is_vertex { "vertex" } else { "edge" };
+        let path = self.output_dir.join(partition_file_name(
+            &format!("{}_{}", prefix, sanitize_file_name(&record.label)),
+            self.partition_id,
+            "csv",
+        ));
+        let mut columns = vec![];
+        let mut headers = if is_vertex {
+            vec![":ID".to_owned(), ":LABEL".to_owned()]
+        } else {
+            vec![":START_ID".to_owned(), ":END_ID".to_owned(), ":TYPE".to_owned()]
+        };
+        let prop_ids = self.schema.get_label_prop_ids(record.label_id);
+        if prop_ids.is_empty() {
+            for prop in &record.properties {
+                headers.push(format!("{}:{}", prop.name, csv_type_name(&prop.data_type)));
+                columns.push(prop.name.clone());
+            }
+        } else {
+            for prop_id in prop_ids {
+                let name = self
+                    .schema
+                    .get_prop_name(prop_id)
+                    .unwrap_or_else(|| prop_id.to_string());
+                let data_type = self
+                    .schema
+                    .get_prop_type(record.label_id, prop_id)
+                    .unwrap_or_default();
+                headers.push(format!("{}:{}", name, csv_type_name(&data_type)));
+                columns.push(name);
+            }
+        }
+        let mut writer = BufWriter::new(File::create(&path)?);
+        write_row(&mut writer, headers.iter().map(|h| h.as_str()))?;
+        Ok(LabelFile { path, writer, columns })
+    }
+}
+
+impl<'a> ElementWriter for CsvWriter<'a> {
+    fn write_vertex(&mut self, vertex: &ExportRecord) -> io::Result<()> {
+        if !self.vertex_files.contains_key(&vertex.label_id) {
+            let file = self.open_label_file(vertex, true)?;
+            self.vertex_files.insert(vertex.label_id, file);
+        }
+        let file = self
+            .vertex_files
+            .get_mut(&vertex.label_id)
+            .unwrap();
+        let mut row = vec![vertex.id.to_string(), vertex.label.clone()];
+        row.extend(property_fields(&file.columns, vertex));
+        write_row(&mut file.writer, row.iter().map(|f| f.as_str()))
+    }
+
+    fn write_edge(&mut self, edge: &ExportRecord) -> io::Result<()> {
+        if !self.edge_files.contains_key(&edge.label_id) {
+            let file = self.open_label_file(edge, false)?;
+            self.edge_files.insert(edge.label_id, file);
+        }
+        let file = self.edge_files.get_mut(&edge.label_id).unwrap();
+        let endpoints = edge
+            .endpoints
+            .as_ref()
+            .expect("edge record without endpoints");
+        let mut row = vec![endpoints.src_id.to_string(), endpoints.dst_id.to_string(), edge.label.clone()];
+        row.extend(property_fields(&file.columns, edge));
+        write_row(&mut file.writer, row.iter().map(|f| f.as_str()))
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        for (_, mut file) in self
+            .vertex_files
+            .into_iter()
+            .chain(self.edge_files.into_iter())
+        {
+            file.writer.flush()?;
+            files.push(file.path);
+        }
+        Ok(files)
+    }
+}
+
+fn property_fields(columns: &Vec<String>, record: &ExportRecord) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| {
+            record
+                .properties
+                .iter()
+                .find(|p| &p.name == column)
+                .map(|p| csv_value(&p.value))
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn csv_value(value: &Property) -> String {
+    match value {
+        Property::ListInt(v) => join_array(v),
+        Property::ListLong(v) => join_array(v),
+        Property::ListFloat(v) => join_array(v),
+        Property::ListDouble(v) => join_array(v),
+        Property::ListString(v) => v.join(ARRAY_DELIMITER),
+        _ => value.to_plain_string(),
+    }
+}
+
+fn join_array<T: ToString>(values: &Vec<T>) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(ARRAY_DELIMITER)
+}
+
+fn csv_type_name(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Bool => "boolean",
+        DataType::Char => "char",
+        DataType::Short => "short",
+        DataType::Int => "int",
+        DataType::Long => "long",
+        DataType::Float => "float",
+        DataType::Double => "double",
+        DataType::Date => "date",
+        DataType::ListInt => "int[]",
+        DataType::ListLong => "long[]",
+        DataType::ListFloat => "float[]",
+        DataType::ListDouble => "double[]",
+        DataType::ListString => "string[]",
+        _ => "string",
+    }
+}
+
+fn escape_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn write_row<'b, W: Write, I: Iterator<Item = &'b str>>(writer: &mut W, fields: I) -> io::Result<()> {
+    let line = fields
+        .map(|f| escape_field(f))
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(writer, "{}", line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_field() {
+        assert_eq!(escape_field("marko"), "marko");
+        assert_eq!(escape_field("a,b"), "\"a,b\"");
+        assert_eq!(escape_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_field("line\nbreak"), "\"line\nbreak\"");
+    }
+
+    #[test]
+    fn test_csv_value() {
+        assert_eq!(csv_value(&Property::ListLong(vec![1, 2, 3])), "1;2;3");
+        assert_eq!(csv_value(&Property::ListString(vec!["a".to_owned(), "b".to_owned()])), "a;b");
+        assert_eq!(csv_value(&Property::Int(29)), "29");
+        assert_eq!(csv_value(&Property::Null), "");
+    }
+}
@@ -0,0 +1,170 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use groot_store::api::{DataType, PartitionId};
+
+use super::{partition_file_name, ElementWriter, ExportRecord};
+
+const VERTEX_LABEL_KEY: &str = "labelV";
+const EDGE_LABEL_KEY: &str = "labelE";
+
+/// Writes a GraphML document per partition. GraphML requires all the `<key>` declarations to
+/// precede the graph, so the elements are streamed into a temporary body file while the keys
+/// are collected, and the document is assembled in `finish()`.
+pub(crate) struct GraphMLWriter {
+    path: PathBuf,
+    body_path: PathBuf,
+    body: BufWriter<File>,
+    // (domain, name) -> attr.type
+    keys: BTreeMap<(&'static str, String), &'static str>,
+}
+
+impl GraphMLWriter {
+    pub fn new(output_dir: PathBuf, partition_id: PartitionId) -> io::Result<Self> {
+        let path = output_dir.join(partition_file_name("graph", partition_id, "graphml"));
+        let body_path = output_dir.join(partition_file_name("graph", partition_id, "graphml.body"));
+        let body = BufWriter::new(File::create(&body_path)?);
+        Ok(GraphMLWriter { path, body_path, body, keys: BTreeMap::new() })
+    }
+
+    fn write_data(&mut self, domain: &'static str, record: &ExportRecord) -> io::Result<()> {
+        for prop in &record.properties {
+            self.keys
+                .entry((domain, prop.name.clone()))
+                .or_insert_with(|| graphml_type_name(&prop.data_type));
+            writeln!(
+                self.body,
+                "      <data key=\"{}\">{}</data>",
+                escape_xml(&key_id(domain, &prop.name)),
+                escape_xml(&prop.value.to_plain_string())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl ElementWriter for GraphMLWriter {
+    fn write_vertex(&mut self, vertex: &ExportRecord) -> io::Result<()> {
+        writeln!(self.body, "    <node id=\"{}\">", vertex.id)?;
+        writeln!(
+            self.body,
+            "      <data key=\"{}\">{}</data>",
+            VERTEX_LABEL_KEY,
+            escape_xml(&vertex.label)
+        )?;
+        self.write_data("node", vertex)?;
+        writeln!(self.body, "    </node>")
+    }
+
+    fn write_edge(&mut self, edge: &ExportRecord) -> io::Result<()> {
+        let endpoints = edge
+            .endpoints
+            .as_ref()
+            .expect("edge record without endpoints");
+        writeln!(
+            self.body,
+            "    <edge id=\"{}\" source=\"{}\" target=\"{}\">",
+            edge.id, endpoints.src_id, endpoints.dst_id
+        )?;
+        writeln!(self.body, "      <data key=\"{}\">{}</data>", EDGE_LABEL_KEY, escape_xml(&edge.label))?;
+        self.write_data("edge", edge)?;
+        writeln!(self.body, "    </edge>")
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<Vec<PathBuf>> {
+        let GraphMLWriter { path, body_path, body, keys } = *self;
+        drop(body.into_inner().map_err(|e| e.into_error())?);
+        let mut writer = BufWriter::new(File::create(&path)?);
+        writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(writer, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+        writeln!(
+            writer,
+            "  <key id=\"{}\" for=\"node\" attr.name=\"{}\" attr.type=\"string\"/>",
+            VERTEX_LABEL_KEY, VERTEX_LABEL_KEY
+        )?;
+        writeln!(
+            writer,
+            "  <key id=\"{}\" for=\"edge\" attr.name=\"{}\" attr.type=\"string\"/>",
+            EDGE_LABEL_KEY, EDGE_LABEL_KEY
+        )?;
+        for ((domain, name), attr_type) in keys.iter() {
+            writeln!(
+                writer,
+                "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+                escape_xml(&key_id(domain, name)),
+                domain,
+                escape_xml(name),
+                attr_type
+            )?;
+        }
+        writeln!(writer, "  <graph id=\"G\" edgedefault=\"directed\">")?;
+        io::copy(&mut File::open(&body_path)?, &mut writer)?;
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")?;
+        writer.flush()?;
+        fs::remove_file(&body_path)?;
+        Ok(vec![path])
+    }
+}
+
+fn key_id(domain: &str, name: &str) -> String {
+    match domain {
+        "node" => format!("v_{}", name),
+        _ => format!("e_{}", name),
+    }
+}
+
+fn graphml_type_name(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Bool => "boolean",
+        DataType::Short | DataType::Int => "int",
+        DataType::Long => "long",
+        DataType::Float => "float",
+        DataType::Double => "double",
+        _ => "string",
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("marko"), "marko");
+        assert_eq!(escape_xml("<a & 'b'>"), "&lt;a &amp; &apos;b&apos;&gt;");
+        assert_eq!(escape_xml("\"q\""), "&quot;q&quot;");
+    }
+}
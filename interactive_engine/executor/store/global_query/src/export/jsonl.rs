@@ -0,0 +1,84 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use groot_store::api::PartitionId;
+use serde_json::{json, Map, Value};
+
+use super::{partition_file_name, ElementWriter, ExportRecord};
+
+/// Writes one json object per line, vertices first and then edges, e.g.
+/// `{"type":"vertex","id":1,"label":"person","properties":{"name":"marko"}}`.
+pub(crate) struct JsonLinesWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl JsonLinesWriter {
+    pub fn new(output_dir: PathBuf, partition_id: PartitionId) -> io::Result<Self> {
+        let path = output_dir.join(partition_file_name("graph", partition_id, "jsonl"));
+        let writer = BufWriter::new(File::create(&path)?);
+        Ok(JsonLinesWriter { path, writer })
+    }
+
+    fn write_line(&mut self, value: Value) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, &value)?;
+        self.writer.write_all(b"\n")
+    }
+}
+
+impl ElementWriter for JsonLinesWriter {
+    fn write_vertex(&mut self, vertex: &ExportRecord) -> io::Result<()> {
+        self.write_line(json!({
+            "type": "vertex",
+            "id": vertex.id,
+            "label": vertex.label,
+            "properties": properties_to_json(vertex),
+        }))
+    }
+
+    fn write_edge(&mut self, edge: &ExportRecord) -> io::Result<()> {
+        let endpoints = edge
+            .endpoints
+            .as_ref()
+            .expect("edge record without endpoints");
+        self.write_line(json!({
+            "type": "edge",
+            "id": edge.id,
+            "label": edge.label,
+            "src": endpoints.src_id,
+            "src_label": endpoints.src_label,
+            "dst": endpoints.dst_id,
+            "dst_label": endpoints.dst_label,
+            "properties": properties_to_json(edge),
+        }))
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<Vec<PathBuf>> {
+        self.writer.flush()?;
+        Ok(vec![self.path])
+    }
+}
+
+fn properties_to_json(record: &ExportRecord) -> Value {
+    let mut properties = Map::new();
+    for prop in &record.properties {
+        properties.insert(prop.name.clone(), prop.value.to_json());
+    }
+    Value::Object(properties)
+}
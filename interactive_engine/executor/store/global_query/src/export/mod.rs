@@ -0,0 +1,455 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Export the graph visible at a snapshot into interchange formats (GraphML, CSV per label, JSON
+//! lines, and Arrow IPC or Parquet per label with the `arrow` feature). Every partition is scanned
+//! and written by its own writer thread, so an export produces one set of files per partition. The
+//! snapshot is pinned until the export finishes, so it's not garbage collected however long it takes.
+//!
+//! An export reads the partitions of its own process only. If it's induced, the edges of which an
+//! endpoint is in the partition of another process can't be checked against the vertices selected
+//! there, and are written to the `boundary` directory instead, to be kept by the loader only if the
+//! endpoint is in the vertices exported by the other process.
+
+#[cfg(feature = "arrow")]
+mod arrow;
+mod csv;
+mod graphml;
+mod jsonl;
+pub mod subgraph;
+
+use std::collections::HashSet;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::{Arc, Barrier, RwLock};
+
+use groot_store::api::{
+    Condition, DataType, Edge, LabelId, PartitionId, Property, SnapshotId, Vertex, VertexId,
+};
+use groot_store::db::graph::pin::SnapshotPin;
+
+use crate::apis::global_query::GlobalGraphQuery;
+use crate::apis::graph_partition::GraphPartitionManager;
+use crate::apis::graph_schema::Schema;
+
+/// The directory of the edges of which an endpoint is in the partition of another process.
+pub const BOUNDARY_DIR_NAME: &str = "boundary";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    GraphML,
+    Csv,
+    JsonLines,
+    /// Arrow IPC files, which requires the `arrow` feature.
+    Arrow,
+    /// Parquet files, which requires the `arrow` feature.
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "graphml" => Some(ExportFormat::GraphML),
+            "csv" => Some(ExportFormat::Csv),
+            "jsonl" | "jsonlines" | "json_lines" => Some(ExportFormat::JsonLines),
+            "arrow" | "ipc" => Some(ExportFormat::Arrow),
+            "parquet" => Some(ExportFormat::Parquet),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportConfig {
+    pub format: ExportFormat,
+    pub output_dir: PathBuf,
+    pub snapshot_id: SnapshotId,
+    /// Labels of vertices to export, empty means all labels.
+    pub vertex_labels: Vec<LabelId>,
+    /// Labels of edges to export, empty means all labels.
+    pub edge_labels: Vec<LabelId>,
+    /// Predicate on the vertices to export, evaluated by the store.
+    pub vertex_condition: Option<Condition>,
+    /// Predicate on the edges to export, evaluated by the store.
+    pub edge_condition: Option<Condition>,
+    /// Export only the edges of which both endpoints are exported, i.e. the subgraph induced by the
+    /// selected vertices, where the endpoints may be in any of the partitions.
+    pub induced: bool,
+    pub partition_ids: Vec<PartitionId>,
+}
+
+impl ExportConfig {
+    pub fn new(
+        format: ExportFormat, output_dir: PathBuf, snapshot_id: SnapshotId, partition_ids: Vec<PartitionId>,
+    ) -> Self {
+        ExportConfig {
+            format,
+            output_dir,
+            snapshot_id,
+            vertex_labels: vec![],
+            edge_labels: vec![],
+            vertex_condition: None,
+            edge_condition: None,
+            induced: false,
+            partition_ids,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+    pub vertex_count: u64,
+    pub edge_count: u64,
+    pub files: Vec<PathBuf>,
+    /// The edges written to the `boundary` directory, which are not counted in `edge_count`.
+    pub boundary_edge_count: u64,
+    pub boundary_files: Vec<PathBuf>,
+}
+
+impl ExportSummary {
+    fn merge(&mut self, other: ExportSummary) {
+        self.vertex_count += other.vertex_count;
+        self.edge_count += other.edge_count;
+        self.files.extend(other.files);
+        self.boundary_edge_count += other.boundary_edge_count;
+        self.boundary_files.extend(other.boundary_files);
+    }
+}
+
+pub(crate) struct ExportProperty {
+    pub name: String,
+    pub data_type: DataType,
+    pub value: Property,
+}
+
+pub(crate) struct EdgeEndpoints {
+    pub src_id: VertexId,
+    pub src_label: String,
+    pub dst_id: VertexId,
+    pub dst_label: String,
+}
+
+/// An element (vertex or edge) with its label and property names resolved by the schema.
+pub(crate) struct ExportRecord {
+    pub id: i64,
+    pub label_id: LabelId,
+    pub label: String,
+    pub endpoints: Option<EdgeEndpoints>,
+    pub properties: Vec<ExportProperty>,
+}
+
+pub(crate) trait ElementWriter {
+    fn write_vertex(&mut self, vertex: &ExportRecord) -> io::Result<()>;
+    fn write_edge(&mut self, edge: &ExportRecord) -> io::Result<()>;
+    /// Flush all the pending data and return the files that have been written.
+    fn finish(self: Box<Self>) -> io::Result<Vec<PathBuf>>;
+}
+
+pub struct GraphExporter<G: GlobalGraphQuery> {
+    graph: Arc<G>,
+    config: ExportConfig,
+    /// Locates the endpoints of the edges if the export is induced, or all the endpoints are taken
+    /// as in the partitions of the process if it's not set.
+    partition_manager: Option<Arc<dyn GraphPartitionManager>>,
+}
+
+impl<G: GlobalGraphQuery> GraphExporter<G> {
+    pub fn new(graph: Arc<G>, config: ExportConfig) -> Self {
+        GraphExporter { graph, config, partition_manager: None }
+    }
+
+    pub fn with_partition_manager(mut self, partition_manager: Arc<dyn GraphPartitionManager>) -> Self {
+        self.partition_manager = Some(partition_manager);
+        self
+    }
+
+    pub fn export(&self) -> io::Result<ExportSummary> {
+        let _pin = SnapshotPin::new(self.config.snapshot_id);
+        let schema = self
+            .graph
+            .get_schema(self.config.snapshot_id)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("schema not found at snapshot {}", self.config.snapshot_id),
+                )
+            })?;
+        std::fs::create_dir_all(&self.config.output_dir)?;
+        let selected = RwLock::new(HashSet::new());
+        let barrier = Barrier::new(self.config.partition_ids.len());
+        let results = std::thread::scope(|s| {
+            let handles = self
+                .config
+                .partition_ids
+                .iter()
+                .map(|partition_id| {
+                    let schema = schema.clone();
+                    let (selected, barrier) = (&selected, &barrier);
+                    s.spawn(move || {
+                        self.export_partition(*partition_id, schema.as_ref(), selected, barrier)
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle.join().unwrap_or_else(|_| {
+                        Err(io::Error::new(io::ErrorKind::Other, "export writer thread panicked"))
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+        let mut summary = ExportSummary::default();
+        for result in results {
+            summary.merge(result?);
+        }
+        info!(
+            "export snapshot {} finished, {} vertices and {} edges written to {} files",
+            self.config.snapshot_id,
+            summary.vertex_count,
+            summary.edge_count,
+            summary.files.len()
+        );
+        Ok(summary)
+    }
+
+    fn create_writer<'a>(
+        &self, output_dir: PathBuf, partition_id: PartitionId, schema: &'a dyn Schema,
+    ) -> io::Result<Box<dyn ElementWriter + 'a>> {
+        let writer: Box<dyn ElementWriter + 'a> = match self.config.format {
+            ExportFormat::GraphML => Box::new(graphml::GraphMLWriter::new(output_dir, partition_id)?),
+            ExportFormat::Csv => Box::new(csv::CsvWriter::new(output_dir, partition_id, schema)),
+            ExportFormat::JsonLines => Box::new(jsonl::JsonLinesWriter::new(output_dir, partition_id)?),
+            #[cfg(feature = "arrow")]
+            ExportFormat::Arrow => {
+                Box::new(arrow::ArrowWriter::new(output_dir, partition_id, schema, arrow::FileKind::Ipc))
+            }
+            #[cfg(feature = "arrow")]
+            ExportFormat::Parquet => Box::new(arrow::ArrowWriter::new(
+                output_dir,
+                partition_id,
+                schema,
+                arrow::FileKind::Parquet,
+            )),
+            #[cfg(not(feature = "arrow"))]
+            ExportFormat::Arrow | ExportFormat::Parquet => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("export format {:?} requires the `arrow` feature", self.config.format),
+                ));
+            }
+        };
+        Ok(writer)
+    }
+
+    fn export_partition(
+        &self, partition_id: PartitionId, schema: &dyn Schema, selected: &RwLock<HashSet<VertexId>>,
+        barrier: &Barrier,
+    ) -> io::Result<ExportSummary> {
+        let mut summary = ExportSummary::default();
+        // a panic is taken as an error, as the other partitions wait for this one at the barrier
+        let vertices = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut writer = self.create_writer(self.config.output_dir.clone(), partition_id, schema)?;
+            let ids = self.export_vertices(partition_id, schema, writer.as_mut(), &mut summary)?;
+            Ok((writer, ids))
+        }))
+        .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "export vertices panicked")));
+        if self.config.induced {
+            // the edges are checked against the vertices selected in all the partitions, so every
+            // partition, even a failed one, must publish its vertices before any edge is exported
+            if let Ok((_, ids)) = &vertices {
+                selected
+                    .write()
+                    .expect("selected vertices poisoned")
+                    .extend(ids.iter().copied());
+            }
+            barrier.wait();
+        }
+        let (mut writer, _) = vertices?;
+        let selected = if self.config.induced {
+            Some(
+                selected
+                    .read()
+                    .expect("selected vertices poisoned"),
+            )
+        } else {
+            None
+        };
+        let local_partitions = self
+            .config
+            .partition_ids
+            .iter()
+            .copied()
+            .collect::<HashSet<_>>();
+        let is_local = |id: VertexId| match self.partition_manager.as_ref() {
+            Some(manager) => local_partitions.contains(&(manager.get_partition_id(id) as PartitionId)),
+            None => true,
+        };
+        let mut boundary_writer = None;
+        let all_props = vec![];
+        let partition_ids = vec![partition_id];
+        let edges = self.graph.get_all_edges(
+            self.config.snapshot_id,
+            &self.config.edge_labels,
+            self.config.edge_condition.as_ref(),
+            None,
+            Some(&all_props),
+            usize::max_value(),
+            &partition_ids,
+        );
+        for e in edges {
+            let mut is_boundary = false;
+            if let Some(selected) = selected.as_ref() {
+                let (src_id, dst_id) = (e.get_src_id(), e.get_dst_id());
+                // an endpoint of another process is checked by the loader instead
+                let is_kept = |id: VertexId| !is_local(id) || selected.contains(&id);
+                if !is_kept(src_id) || !is_kept(dst_id) {
+                    continue;
+                }
+                is_boundary = !is_local(src_id) || !is_local(dst_id);
+            }
+            let label_id = e.get_label_id();
+            let record = ExportRecord {
+                id: e.get_edge_id(),
+                label_id,
+                label: label_name(schema, label_id),
+                endpoints: Some(EdgeEndpoints {
+                    src_id: e.get_src_id(),
+                    src_label: label_name(schema, e.get_src_label_id()),
+                    dst_id: e.get_dst_id(),
+                    dst_label: label_name(schema, e.get_dst_label_id()),
+                }),
+                properties: resolve_properties(schema, label_id, e.get_properties()),
+            };
+            if is_boundary {
+                if boundary_writer.is_none() {
+                    let boundary_dir = self.config.output_dir.join(BOUNDARY_DIR_NAME);
+                    std::fs::create_dir_all(&boundary_dir)?;
+                    boundary_writer = Some(self.create_writer(boundary_dir, partition_id, schema)?);
+                }
+                if let Some(boundary_writer) = boundary_writer.as_mut() {
+                    boundary_writer.write_edge(&record)?;
+                }
+                summary.boundary_edge_count += 1;
+            } else {
+                writer.write_edge(&record)?;
+                summary.edge_count += 1;
+            }
+        }
+        summary.files = writer.finish()?;
+        if let Some(boundary_writer) = boundary_writer {
+            summary.boundary_files = boundary_writer.finish()?;
+        }
+        debug!(
+            "partition {} exported, {} vertices, {} edges",
+            partition_id, summary.vertex_count, summary.edge_count
+        );
+        Ok(summary)
+    }
+
+    /// Write the vertices of the partition, and return their ids if the export is induced.
+    fn export_vertices(
+        &self, partition_id: PartitionId, schema: &dyn Schema, writer: &mut dyn ElementWriter,
+        summary: &mut ExportSummary,
+    ) -> io::Result<Vec<VertexId>> {
+        let partition_ids = vec![partition_id];
+        // an empty output property list means all the properties
+        let all_props = vec![];
+        let mut ids = vec![];
+        let vertices = self.graph.get_all_vertices(
+            self.config.snapshot_id,
+            &self.config.vertex_labels,
+            self.config.vertex_condition.as_ref(),
+            None,
+            Some(&all_props),
+            usize::max_value(),
+            &partition_ids,
+        );
+        for v in vertices {
+            if self.config.induced {
+                ids.push(v.get_id());
+            }
+            let label_id = v.get_label_id();
+            let record = ExportRecord {
+                id: v.get_id(),
+                label_id,
+                label: label_name(schema, label_id),
+                endpoints: None,
+                properties: resolve_properties(schema, label_id, v.get_properties()),
+            };
+            writer.write_vertex(&record)?;
+            summary.vertex_count += 1;
+        }
+        Ok(ids)
+    }
+}
+
+fn label_name(schema: &dyn Schema, label_id: LabelId) -> String {
+    schema
+        .get_label_name(label_id)
+        .unwrap_or_else(|| label_id.to_string())
+}
+
+fn resolve_properties<I: Iterator<Item = (u32, Property)>>(
+    schema: &dyn Schema, label_id: LabelId, props: I,
+) -> Vec<ExportProperty> {
+    let mut properties = props
+        .map(|(prop_id, value)| ExportProperty {
+            name: schema
+                .get_prop_name(prop_id)
+                .unwrap_or_else(|| prop_id.to_string()),
+            data_type: schema
+                .get_prop_type(label_id, prop_id)
+                .unwrap_or_else(|| infer_data_type(&value)),
+            value,
+        })
+        .collect::<Vec<_>>();
+    properties.sort_by(|a, b| a.name.cmp(&b.name));
+    properties
+}
+
+fn infer_data_type(value: &Property) -> DataType {
+    match value {
+        Property::Bool(_) => DataType::Bool,
+        Property::Char(_) => DataType::Char,
+        Property::Short(_) => DataType::Short,
+        Property::Int(_) => DataType::Int,
+        Property::Long(_) => DataType::Long,
+        Property::Float(_) => DataType::Float,
+        Property::Double(_) => DataType::Double,
+        Property::Bytes(_) => DataType::Bytes,
+        Property::String(_) => DataType::String,
+        Property::Date(_) => DataType::Date,
+        Property::ListInt(_) => DataType::ListInt,
+        Property::ListLong(_) => DataType::ListLong,
+        Property::ListFloat(_) => DataType::ListFloat,
+        Property::ListDouble(_) => DataType::ListDouble,
+        Property::ListString(_) => DataType::ListString,
+        Property::ListBytes(_) => DataType::ListBytes,
+        Property::Null | Property::Unknown => DataType::Unknown,
+    }
+}
+
+pub(crate) fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// File name for the data of `partition_id`, e.g. `vertex_person_p3.csv`.
+pub(crate) fn partition_file_name(prefix: &str, partition_id: PartitionId, ext: &str) -> String {
+    format!("{}_p{}.{}", prefix, partition_id, ext)
+}
@@ -0,0 +1,179 @@
+//
+//! Copyright 2022 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Extract the subgraph selected by labels and predicates as it is at a snapshot, e.g. as point-in-time
+//! training graphs. All the partitions are read at the same snapshot id, which the store keeps
+//! readable while new writes go to the later snapshots, so the extraction never pauses the writes.
+//! The edges are those of which both endpoints are selected, in whichever partition the endpoints are;
+//! where the edges with an endpoint in another process are listed as the boundary edges, which the
+//! loader keeps only if the endpoint is in the vertices extracted by that process.
+//!
+//! Besides the data files, a `manifest.json` is written to the output directory, listing the files by
+//! vertex and edge labels, to be loaded by the analytical engine or the training pipelines.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use groot_store::api::{Condition, LabelId, PartitionId, SnapshotId};
+use serde_json::json;
+
+use super::{ExportConfig, ExportFormat, ExportSummary, GraphExporter, BOUNDARY_DIR_NAME};
+use crate::apis::global_query::GlobalGraphQuery;
+use crate::apis::graph_partition::GraphPartitionManager;
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, Default)]
+pub struct SubgraphSelection {
+    /// Labels of the selected vertices, empty means all labels.
+    pub vertex_labels: Vec<LabelId>,
+    pub vertex_condition: Option<Condition>,
+    /// Labels of the selected edges, empty means all labels.
+    pub edge_labels: Vec<LabelId>,
+    pub edge_condition: Option<Condition>,
+}
+
+impl SubgraphSelection {
+    pub fn with_vertex_labels(mut self, labels: Vec<LabelId>) -> Self {
+        self.vertex_labels = labels;
+        self
+    }
+
+    pub fn with_vertex_condition(mut self, condition: Condition) -> Self {
+        self.vertex_condition = Some(condition);
+        self
+    }
+
+    pub fn with_edge_labels(mut self, labels: Vec<LabelId>) -> Self {
+        self.edge_labels = labels;
+        self
+    }
+
+    pub fn with_edge_condition(mut self, condition: Condition) -> Self {
+        self.edge_condition = Some(condition);
+        self
+    }
+}
+
+pub struct SubgraphExtractor<G: GlobalGraphQuery> {
+    exporter: GraphExporter<G>,
+}
+
+impl<G: GlobalGraphQuery> SubgraphExtractor<G> {
+    pub fn new(
+        graph: Arc<G>, selection: SubgraphSelection, format: ExportFormat, output_dir: PathBuf,
+        snapshot_id: SnapshotId, partition_ids: Vec<PartitionId>,
+    ) -> Self {
+        let mut config = ExportConfig::new(format, output_dir, snapshot_id, partition_ids);
+        config.vertex_labels = selection.vertex_labels;
+        config.vertex_condition = selection.vertex_condition;
+        config.edge_labels = selection.edge_labels;
+        config.edge_condition = selection.edge_condition;
+        config.induced = true;
+        SubgraphExtractor { exporter: GraphExporter::new(graph, config) }
+    }
+
+    /// Locate the endpoints of the edges, to tell the boundary edges of the other processes.
+    pub fn with_partition_manager(mut self, partition_manager: Arc<dyn GraphPartitionManager>) -> Self {
+        self.exporter = self
+            .exporter
+            .with_partition_manager(partition_manager);
+        self
+    }
+
+    /// Extract the subgraph of all the partitions, and write the manifest once all the data files are
+    /// written, so a manifest is present only if the extraction succeeds.
+    pub fn extract(&self) -> io::Result<ExportSummary> {
+        let mut summary = self.exporter.export()?;
+        let config = &self.exporter.config;
+        let manifest = config.output_dir.join(MANIFEST_FILE_NAME);
+        write_manifest(&manifest, config.snapshot_id, config.format, &summary)?;
+        summary.files.push(manifest);
+        Ok(summary)
+    }
+}
+
+fn write_manifest(
+    path: &Path, snapshot_id: SnapshotId, format: ExportFormat, summary: &ExportSummary,
+) -> io::Result<()> {
+    let manifest = json!({
+        "snapshot_id": snapshot_id,
+        "format": format!("{:?}", format),
+        "vertex_count": summary.vertex_count,
+        "edge_count": summary.edge_count,
+        "boundary_edge_count": summary.boundary_edge_count,
+        "vertices": files_by_label(&summary.files, "vertex_", ""),
+        "edges": files_by_label(&summary.files, "edge_", ""),
+        "boundary_edges": files_by_label(&summary.boundary_files, "edge_", BOUNDARY_DIR_NAME),
+    });
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, &manifest)?;
+    Ok(())
+}
+
+/// The files of the labels with the `prefix`, named by the path relative to the output directory.
+fn files_by_label(files: &[PathBuf], prefix: &str, dir: &str) -> BTreeMap<String, Vec<String>> {
+    let mut by_label = BTreeMap::new();
+    for file in files {
+        let name = file
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if let Some(label) = file_label(name, prefix) {
+            let path = if dir.is_empty() { name.to_owned() } else { format!("{}/{}", dir, name) };
+            by_label
+                .entry(label.to_owned())
+                .or_insert_with(Vec::new)
+                .push(path);
+        }
+    }
+    for files in by_label.values_mut() {
+        files.sort();
+    }
+    by_label
+}
+
+/// The label of a data file named by `partition_file_name()`, e.g. `person` of `vertex_person_p3.csv`,
+/// or `None` for the files of all labels, e.g. `graph_p3.jsonl`.
+fn file_label<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+    let stem = name.strip_prefix(prefix)?;
+    let end = stem.rfind("_p")?;
+    Some(&stem[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_label() {
+        assert_eq!(file_label("vertex_person_p3.csv", "vertex_"), Some("person"));
+        assert_eq!(file_label("edge_knows_p0.parquet", "edge_"), Some("knows"));
+        assert_eq!(file_label("vertex_software_part_p12.arrow", "vertex_"), Some("software_part"));
+        assert_eq!(file_label("graph_p3.jsonl", "vertex_"), None);
+    }
+
+    #[test]
+    fn test_files_by_label() {
+        let files =
+            vec![PathBuf::from("/out/boundary/edge_knows_p1.csv"), PathBuf::from("edge_knows_p0.csv")];
+        let by_label = files_by_label(&files, "edge_", BOUNDARY_DIR_NAME);
+        assert_eq!(by_label["knows"], vec!["boundary/edge_knows_p0.csv", "boundary/edge_knows_p1.csv"]);
+        assert!(files_by_label(&files, "vertex_", "").is_empty());
+    }
+}
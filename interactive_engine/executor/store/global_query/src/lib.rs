@@ -20,6 +20,8 @@ extern crate itertools;
 extern crate log;
 
 pub mod apis;
+pub mod degree;
+pub mod export;
 pub mod store_impl;
 
 pub use apis::global_query::{GlobalGraphQuery, PartitionLabeledVertexIds, PartitionVertexIds};
@@ -13,20 +13,23 @@
 //! See the License for the specific language governing permissions and
 //! limitations under the License.
 
-use std::cell::RefCell;
 use std::collections::HashMap;
-use std::io::Write;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 
-use byteorder::{BigEndian, WriteBytesExt};
 use groot_store::api::prelude::Property;
-use groot_store::api::{Condition, LabelId, PartitionId, PropId, SnapshotId, VertexId};
+use groot_store::api::{Condition, EdgeId, ElemFilter, LabelId, PartitionId, PropId, SnapshotId, VertexId};
 use groot_store::db::api::multi_version_graph::MultiVersionGraph;
 use groot_store::db::api::types::RocksEdge;
-use groot_store::db::api::{PropertyId, Records};
+use groot_store::db::api::{EdgeDirection, EdgeId as DbEdgeId, GraphResult, PropertyId, Records, MAX_SI};
 use groot_store::db::graph::entity::{RocksEdgeImpl, RocksVertexImpl};
-use groot_store::db::graph::get_vertex_id_by_primary_keys;
+use groot_store::db::graph::id_mapping::external_id_key;
+use groot_store::db::graph::partition::PartitionRouting;
+use groot_store::db::graph::sort_index::SortRange;
 use groot_store::db::graph::store::GraphStore;
+use groot_store::db::graph::{encode_pk_value, get_vertex_id_by_primary_keys};
+use groot_store::db::service::neighbor_sampling::SamplingPartitions;
+use groot_store::db::service::stream_write::WritePartitions;
 use groot_store::db::storage::RawBytes;
 use itertools::Itertools;
 
@@ -37,9 +40,28 @@ use crate::apis::graph_schema::Schema;
 use crate::store_impl::groot::global_graph_schema::GlobalGraphSchema;
 
 pub struct GlobalGraph {
+    // replaced as a whole, so that the partitions and the routing are updated atomically
+    table: RwLock<PartitionTable>,
+    // the followers of each partition, with the staleness reported, to route reads to
+    replicas: RwLock<HashMap<PartitionId, Vec<(u32, u64)>>>,
+    // reads are routed to the followers no staler than it, and only to the leaders if it's 0
+    max_staleness_ms: AtomicU64,
+}
+
+#[derive(Clone)]
+struct PartitionTable {
     graph_partitions: Arc<HashMap<PartitionId, Arc<GraphStore>>>,
-    total_partition: u32,
-    partition_to_server: HashMap<PartitionId, u32>,
+    // the follower replicas in this process, which serve the reads routed but not the scans
+    follower_partitions: Arc<HashMap<PartitionId, Arc<GraphStore>>>,
+    routing: Arc<PartitionRouting>,
+}
+
+impl PartitionTable {
+    fn get(&self, partition_id: PartitionId) -> Option<&Arc<GraphStore>> {
+        self.graph_partitions
+            .get(&partition_id)
+            .or_else(|| self.follower_partitions.get(&partition_id))
+    }
 }
 
 unsafe impl Send for GlobalGraph {}
@@ -49,22 +71,190 @@ unsafe impl Sync for GlobalGraph {}
 #[allow(dead_code)]
 impl GlobalGraph {
     pub fn empty(total_partition: u32) -> Self {
-        GlobalGraph {
+        let table = PartitionTable {
             graph_partitions: Arc::new(HashMap::new()),
-            total_partition,
-            partition_to_server: HashMap::new(),
+            follower_partitions: Arc::new(HashMap::new()),
+            routing: Arc::new(PartitionRouting::new(total_partition)),
+        };
+        GlobalGraph {
+            table: RwLock::new(table),
+            replicas: RwLock::new(HashMap::new()),
+            max_staleness_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub fn add_partition(&self, partition_id: PartitionId, graph_store: Arc<GraphStore>) {
+        let mut table = self.table.write().unwrap();
+        let mut graph_partitions = (*table.graph_partitions).clone();
+        graph_partitions.insert(partition_id, graph_store);
+        table.graph_partitions = Arc::new(graph_partitions);
+    }
+
+    pub fn remove_partition(&self, partition_id: PartitionId) -> Option<Arc<GraphStore>> {
+        let mut table = self.table.write().unwrap();
+        let mut graph_partitions = (*table.graph_partitions).clone();
+        let removed = graph_partitions.remove(&partition_id);
+        table.graph_partitions = Arc::new(graph_partitions);
+        removed
+    }
+
+    pub fn add_follower_partition(&self, partition_id: PartitionId, graph_store: Arc<GraphStore>) {
+        let mut table = self.table.write().unwrap();
+        let mut follower_partitions = (*table.follower_partitions).clone();
+        follower_partitions.insert(partition_id, graph_store);
+        table.follower_partitions = Arc::new(follower_partitions);
+    }
+
+    pub fn remove_follower_partition(&self, partition_id: PartitionId) -> Option<Arc<GraphStore>> {
+        let mut table = self.table.write().unwrap();
+        let mut follower_partitions = (*table.follower_partitions).clone();
+        let removed = follower_partitions.remove(&partition_id);
+        table.follower_partitions = Arc::new(follower_partitions);
+        removed
+    }
+
+    /// Update the staleness of the follower of the partition on the server, or remove the follower
+    /// if `staleness_ms` is `None`.
+    pub fn update_partition_replica(
+        &self, partition_id: PartitionId, server_id: u32, staleness_ms: Option<u64>,
+    ) {
+        let mut replicas = self.replicas.write().unwrap();
+        let followers = replicas.entry(partition_id).or_default();
+        followers.retain(|(server, _)| *server != server_id);
+        if let Some(staleness_ms) = staleness_ms {
+            followers.push((server_id, staleness_ms));
+            followers.sort_unstable();
+        } else if followers.is_empty() {
+            replicas.remove(&partition_id);
         }
     }
 
-    pub fn add_partition(&mut self, partition_id: PartitionId, graph_store: Arc<GraphStore>) {
-        Arc::get_mut(&mut self.graph_partitions)
+    /// Route the reads of partitions to the followers no staler than `max_staleness_ms` as well as
+    /// the leaders, or only to the leaders if it's 0.
+    pub fn set_follower_read_staleness(&self, max_staleness_ms: u64) {
+        self.max_staleness_ms
+            .store(max_staleness_ms, Ordering::Relaxed);
+    }
+
+    pub fn update_partition_routing(&self, partition_id: PartitionId, server_id: u32) {
+        let mut table = self.table.write().unwrap();
+        let mut routing = (*table.routing).clone();
+        routing.set_server(partition_id, server_id);
+        table.routing = Arc::new(routing);
+    }
+
+    /// Switch to the routing of a newer version, e.g. after partitions are split, merged or moved;
+    /// the partitions added to this process should be added before, and those removed from this
+    /// process are removed after. Return false if the routing is stale.
+    pub fn switch_routing(&self, routing: PartitionRouting) -> bool {
+        let mut table = self.table.write().unwrap();
+        if routing.version() <= table.routing.version() {
+            return false;
+        }
+        info!("switch partition routing from version {} to {}", table.routing.version(), routing.version());
+        table.routing = Arc::new(routing);
+        true
+    }
+
+    pub fn get_routing(&self) -> Arc<PartitionRouting> {
+        self.table.read().unwrap().routing.clone()
+    }
+
+    fn partitions(&self) -> Arc<HashMap<PartitionId, Arc<GraphStore>>> {
+        self.table
+            .read()
+            .unwrap()
+            .graph_partitions
+            .clone()
+    }
+
+    /// The partition to read in this process, the leader or a follower, of which the reads are routed
+    /// here, see `get_server_id`.
+    fn get_partition(&self, partition_id: PartitionId) -> Option<Arc<GraphStore>> {
+        self.table
+            .read()
             .unwrap()
-            .insert(partition_id, graph_store);
+            .get(partition_id)
+            .cloned()
+    }
+
+    /// One of the followers of the partition no staler than the bound, if it's picked over the
+    /// leader. The pick depends on the partition only, so that the reads of a vertex are routed to
+    /// the same server by all the processes, and the partitions are spread over the replicas.
+    fn pick_follower(&self, partition_id: PartitionId, max_staleness_ms: u64) -> Option<u32> {
+        if max_staleness_ms == 0 {
+            return None;
+        }
+        let replicas = self.replicas.read().unwrap();
+        let followers = replicas
+            .get(&partition_id)?
+            .iter()
+            .filter(|(_, staleness_ms)| *staleness_ms <= max_staleness_ms)
+            .map(|(server_id, _)| *server_id)
+            .collect_vec();
+        // the leader is picked as the last one;
+        followers
+            .get(partition_id as usize % (followers.len() + 1))
+            .copied()
+    }
+
+    /// The stores of the partitions in this process, excluding the followers.
+    pub fn get_partition_stores(&self) -> Vec<Arc<GraphStore>> {
+        self.partitions().values().cloned().collect()
+    }
+
+    /// Drain all the partitions in this process, see `GraphStore::drain`.
+    pub fn drain(&self) -> GraphResult<()> {
+        for graph in self.partitions().values() {
+            graph.drain()?;
+        }
+        Ok(())
     }
 
-    pub fn update_partition_routing(&mut self, partition_id: PartitionId, server_id: u32) {
-        self.partition_to_server
-            .insert(partition_id, server_id);
+    /// The in edges of the vertex in `store`. The in edges of the labels stored only in the out
+    /// direction are their reverse index entries, so their properties, if required, are read from the
+    /// out direction in the partitions of their sources, which are filtered by the condition then.
+    /// The properties are absent if the partition of the source is not in this process, see
+    /// `reverse_index`.
+    fn get_vertex_in_edges(
+        &self, si: SnapshotId, store: &Arc<GraphStore>, vertex_id: VertexId, edge_label: Option<i32>,
+        condition: Option<&Condition>, property_ids: Option<&Vec<PropertyId>>,
+    ) -> GraphResult<Records<RocksEdgeImpl>> {
+        let out_only = match edge_label {
+            Some(edge_label) => store.is_out_only_edge_label(edge_label),
+            None => !store.get_out_only_edge_labels(si).is_empty(),
+        };
+        if !out_only || (condition.is_none() && property_ids.is_none()) {
+            return store.get_in_edges(si, vertex_id, edge_label, condition, property_ids);
+        }
+        let edges = store.get_in_edges(si, vertex_id, edge_label, None, property_ids)?;
+        let store = store.clone();
+        let routing = self.get_routing();
+        let partitions = self.partitions();
+        let condition = condition.cloned();
+        let property_ids = property_ids.cloned();
+        let edges = edges.filter_map(move |edge| {
+            let mut edge = match edge {
+                Ok(edge) => edge,
+                Err(e) => return Some(Err(e)),
+            };
+            let edge_id = *RocksEdge::get_edge_id(&edge);
+            let edge_kind = RocksEdge::get_edge_relation(&edge).clone();
+            if store.is_out_only_edge_label(edge_kind.edge_label_id) {
+                if let Some(source) = partitions.get(&routing.get_partition_id(edge_id.src_id)) {
+                    match source.get_edge(si, edge_id, Some(&edge_kind), property_ids.as_ref()) {
+                        Ok(Some(source_edge)) => edge = source_edge,
+                        Ok(None) => return None,
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+            }
+            match condition {
+                Some(ref condition) if !condition.filter_edge(&edge).unwrap_or(false) => None,
+                _ => Some(Ok(edge)),
+            }
+        });
+        Ok(Box::new(edges))
     }
 
     fn get_limit(raw_limit: usize) -> usize {
@@ -116,7 +306,7 @@ impl GlobalGraphQuery for GlobalGraph {
         let mut res: Vec<(VertexId, Self::EI)> = Vec::new();
         let property_ids = Self::parse_property_id(output_prop_ids);
         for (partition_id, vertex_ids) in src_ids {
-            if let Some(store) = self.graph_partitions.get(&partition_id) {
+            if let Some(store) = self.get_partition(partition_id) {
                 for vertex_id in vertex_ids {
                     let mut vertex_out_edges: Records<RocksEdgeImpl> = Box::new(::std::iter::empty());
                     if edge_labels.is_empty() {
@@ -183,33 +373,31 @@ impl GlobalGraphQuery for GlobalGraph {
         let mut res: Vec<(VertexId, Self::EI)> = Vec::new();
         let property_ids = Self::parse_property_id(output_prop_ids);
         for (partition_id, vertex_ids) in src_ids {
-            if let Some(store) = self.graph_partitions.get(&partition_id) {
+            if let Some(store) = self.get_partition(partition_id) {
+                let edge_labels: Vec<Option<i32>> = if edge_labels.is_empty() {
+                    vec![None]
+                } else {
+                    edge_labels
+                        .iter()
+                        .map(|edge_label| Some(*edge_label as i32))
+                        .collect()
+                };
                 for vertex_id in vertex_ids {
                     let mut vertex_in_edges: Records<RocksEdgeImpl> = Box::new(::std::iter::empty());
-                    if edge_labels.is_empty() {
+                    for edge_label in edge_labels.iter() {
                         vertex_in_edges = Box::new(
                             vertex_in_edges.chain(
-                                store
-                                    .get_in_edges(si, vertex_id, None, condition, property_ids.as_ref())
-                                    .unwrap(),
+                                self.get_vertex_in_edges(
+                                    si,
+                                    &store,
+                                    vertex_id,
+                                    *edge_label,
+                                    condition,
+                                    property_ids.as_ref(),
+                                )
+                                .unwrap(),
                             ),
                         )
-                    } else {
-                        for edge_label in edge_labels {
-                            vertex_in_edges = Box::new(
-                                vertex_in_edges.chain(
-                                    store
-                                        .get_in_edges(
-                                            si,
-                                            vertex_id,
-                                            Some(*edge_label as i32),
-                                            condition,
-                                            property_ids.as_ref(),
-                                        )
-                                        .unwrap(),
-                                ),
-                            )
-                        }
                     }
                     res.push((
                         vertex_id,
@@ -248,20 +436,20 @@ impl GlobalGraphQuery for GlobalGraph {
     fn get_vertex_properties(
         &self, si: SnapshotId, ids: Vec<PartitionLabeledVertexIds>, output_prop_ids: Option<&Vec<PropId>>,
     ) -> Self::VI {
-        let graph_partitions = self.graph_partitions.clone();
+        let table = self.table.read().unwrap().clone();
         let property_ids = Self::parse_property_id(output_prop_ids);
         Box::new(
             ids.into_iter()
                 .flat_map(move |(partition_id, label_id_vec)| {
-                    let graph_partitions = graph_partitions.clone();
+                    let table = table.clone();
                     let property_ids = property_ids.clone();
                     label_id_vec
                         .into_iter()
                         .flat_map(move |(label_id, vids)| {
-                            let graph_partitions = graph_partitions.clone();
+                            let table = table.clone();
                             let property_ids = property_ids.clone();
-                            vids.into_iter().filter_map(move |vid| {
-                                match graph_partitions.get(&partition_id) {
+                            vids.into_iter()
+                                .filter_map(move |vid| match table.get(partition_id) {
                                     None => None,
                                     Some(partition) => partition
                                         .get_vertex(
@@ -271,8 +459,7 @@ impl GlobalGraphQuery for GlobalGraph {
                                             property_ids.as_ref(),
                                         )
                                         .unwrap(),
-                                }
-                            })
+                                })
                         })
                 }),
         )
@@ -285,6 +472,80 @@ impl GlobalGraphQuery for GlobalGraph {
         unimplemented!()
     }
 
+    fn get_edge_by_id(
+        &self, si: SnapshotId, partition_id: PartitionId, label: LabelId, src_id: VertexId,
+        dst_id: VertexId, edge_id: EdgeId, output_prop_ids: Option<&Vec<PropId>>,
+    ) -> Option<Self::E> {
+        let partition = self.get_partition(partition_id)?;
+        let property_ids = Self::parse_property_id(output_prop_ids);
+        let edge_id = DbEdgeId::new(src_id, dst_id, edge_id);
+        match partition.get_edge_by_label(si, edge_id, label as i32, property_ids.as_ref()) {
+            Ok(edge) => edge,
+            Err(e) => {
+                error!("get edge {:?} of label {} failed: {:?}", edge_id, label, e);
+                None
+            }
+        }
+    }
+
+    fn get_sorted_edges(
+        &self, si: SnapshotId, partition_id: PartitionId, vertex_id: VertexId, label: LabelId,
+        direction: EdgeDirection, lower: Option<i64>, upper: Option<i64>, limit: usize,
+        output_prop_ids: Option<&Vec<PropId>>,
+    ) -> Self::EI {
+        let partition = match self.get_partition(partition_id) {
+            Some(partition) => partition,
+            None => return Box::new(::std::iter::empty()),
+        };
+        let property_ids = Self::parse_property_id(output_prop_ids);
+        let range = SortRange { lower, upper, limit };
+        match partition.get_sorted_edges(
+            si,
+            vertex_id,
+            direction,
+            label as i32,
+            &range,
+            property_ids.as_ref(),
+        ) {
+            Ok(edges) => Box::new(edges.into_iter()),
+            Err(e) => {
+                error!("get sorted edges of label {} of vertex {} failed: {:?}", label, vertex_id, e);
+                Box::new(::std::iter::empty())
+            }
+        }
+    }
+
+    fn get_edges_page(
+        &self, si: SnapshotId, partition_id: PartitionId, vertex_id: VertexId, edge_labels: &Vec<LabelId>,
+        direction: EdgeDirection, token: Option<&[u8]>, page_size: usize,
+        output_prop_ids: Option<&Vec<PropId>>,
+    ) -> (Self::EI, Option<Vec<u8>>) {
+        let partition = match self.get_partition(partition_id) {
+            Some(partition) => partition,
+            None => return (Box::new(::std::iter::empty()), None),
+        };
+        let property_ids = Self::parse_property_id(output_prop_ids);
+        let labels: Vec<i32> = edge_labels
+            .iter()
+            .map(|label| *label as i32)
+            .collect();
+        match partition.get_edges_page(
+            si,
+            vertex_id,
+            direction,
+            &labels,
+            token,
+            page_size,
+            property_ids.as_ref(),
+        ) {
+            Ok(page) => (Box::new(page.edges.into_iter()), page.next_token),
+            Err(e) => {
+                error!("get edges page of vertex {} failed: {:?}", vertex_id, e);
+                (Box::new(::std::iter::empty()), None)
+            }
+        }
+    }
+
     fn get_all_vertices(
         &self, si: SnapshotId, labels: &Vec<LabelId>, condition: Option<&Condition>,
         _dedup_prop_ids: Option<&Vec<PropId>>, output_prop_ids: Option<&Vec<PropId>>, limit: usize,
@@ -292,44 +553,30 @@ impl GlobalGraphQuery for GlobalGraph {
     ) -> Self::VI {
         let output_property_ids = Self::parse_property_id(output_prop_ids);
         let partitions = if partition_ids.is_empty() {
-            self.graph_partitions
+            self.partitions()
                 .keys()
                 .map(|k| *k)
                 .collect_vec()
         } else {
             partition_ids.clone()
         };
+        let labels: Vec<i32> = labels
+            .iter()
+            .map(|label| *label as i32)
+            .collect();
         let mut res: Self::VI = Box::new(::std::iter::empty());
         for pid in partitions {
-            if let Some(partition) = self.graph_partitions.get(&pid) {
-                if labels.is_empty() {
-                    res = Box::new(
-                        res.chain(
-                            partition
-                                .scan_vertex(si, None, condition, output_property_ids.as_ref())
-                                .unwrap()
-                                .take(Self::get_limit(limit))
-                                .map(|v| v.unwrap()),
-                        ),
-                    )
-                } else {
-                    for label in labels {
-                        res = Box::new(
-                            res.chain(
-                                partition
-                                    .scan_vertex(
-                                        si,
-                                        Some(*label as i32),
-                                        condition,
-                                        output_property_ids.as_ref(),
-                                    )
-                                    .unwrap()
-                                    .take(Self::get_limit(limit))
-                                    .map(|v| v.unwrap()),
-                            ),
-                        )
-                    }
-                }
+            if let Some(partition) = self.partitions().get(&pid) {
+                // the labels are scanned in one pass of the partition, with the limit of all of them
+                res = Box::new(
+                    res.chain(
+                        partition
+                            .scan_vertices(si, &labels, condition, output_property_ids.as_ref())
+                            .unwrap()
+                            .take(Self::get_limit(limit))
+                            .map(|v| v.unwrap()),
+                    ),
+                )
             }
         }
         res
@@ -342,7 +589,7 @@ impl GlobalGraphQuery for GlobalGraph {
     ) -> Self::EI {
         let output_property_ids = Self::parse_property_id(output_prop_ids);
         let partitions = if partition_ids.is_empty() {
-            self.graph_partitions
+            self.partitions()
                 .keys()
                 .map(|k| *k)
                 .collect_vec()
@@ -351,7 +598,7 @@ impl GlobalGraphQuery for GlobalGraph {
         };
         let mut res: Self::EI = Box::new(::std::iter::empty());
         for pid in partitions {
-            if let Some(partition) = self.graph_partitions.get(&pid) {
+            if let Some(partition) = self.partitions().get(&pid) {
                 if labels.is_empty() {
                     res = Box::new(
                         res.chain(
@@ -404,157 +651,111 @@ impl GlobalGraphQuery for GlobalGraph {
     }
 
     fn get_schema(&self, _si: i64) -> Option<Arc<dyn Schema>> {
-        let partition = self.graph_partitions.values().nth(0)?;
+        let partitions = self.partitions();
+        let partition = partitions.values().nth(0)?;
         let graph_def = partition.get_graph_def().ok()?;
         Some(Arc::new(GlobalGraphSchema::new(graph_def)))
     }
 }
 
-thread_local! {
-    static FIELD_BUF: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(64 << 10));
+impl SamplingPartitions for GlobalGraph {
+    fn get_vertex_partition(&self, vertex_id: VertexId) -> Option<Arc<GraphStore>> {
+        self.get_partition(self.get_routing().get_partition_id(vertex_id))
+    }
+
+    fn get_readable_si(&self) -> SnapshotId {
+        self.partitions()
+            .values()
+            .map(|store| store.get_readable_si())
+            .min()
+            .unwrap_or(MAX_SI)
+    }
+}
+
+impl WritePartitions for GlobalGraph {
+    fn get_write_partition(&self, vertex_id: VertexId) -> Option<Arc<GraphStore>> {
+        // the followers only accept the writes of their leaders
+        let partition_id = self.get_routing().get_partition_id(vertex_id);
+        self.partitions().get(&partition_id).cloned()
+    }
+
+    fn get_partitions(&self) -> Vec<Arc<GraphStore>> {
+        self.partitions().values().cloned().collect()
+    }
 }
 
 impl GraphPartitionManager for GlobalGraph {
     fn get_partition_id(&self, vid: i64) -> i32 {
-        let partition_count = self.total_partition;
-        floor_mod(vid, partition_count as i64) as i32
+        self.get_routing().get_partition_id(vid) as i32
     }
 
     fn get_server_id(&self, partition_id: u32) -> Option<u32> {
-        self.partition_to_server
-            .get(&partition_id)
-            .map(|x| *x)
+        let max_staleness_ms = self.max_staleness_ms.load(Ordering::Relaxed);
+        self.get_server_id_within_staleness(partition_id, max_staleness_ms)
+    }
+
+    fn get_server_id_within_staleness(&self, partition_id: u32, max_staleness_ms: u64) -> Option<u32> {
+        self.pick_follower(partition_id, max_staleness_ms)
+            .or_else(|| self.get_routing().get_server_id(partition_id))
     }
 
     fn get_process_partition_list(&self) -> Vec<u32> {
-        self.graph_partitions
+        self.partitions()
             .keys()
             .into_iter()
             .map(|x| *x)
             .collect::<Vec<u32>>()
     }
 
+    fn lookup_by_external_id(&self, label_id: LabelId, external_id: &str) -> Option<VertexId> {
+        let key = external_id_key(label_id as i32, external_id);
+        let partition = self.get_partition(self.get_routing().get_partition_id(key))?;
+        match partition.lookup_by_external_id(label_id as i32, external_id) {
+            Ok(id) => id,
+            Err(e) => {
+                error!("lookup by external id {} of label {} failed: {:?}", external_id, label_id, e);
+                None
+            }
+        }
+    }
+
+    fn get_primary_key_ids(&self, label_id: LabelId) -> Option<Vec<PropId>> {
+        // the schema is the same on all the partitions
+        let partitions = self.partitions();
+        let partition = partitions.values().next()?;
+        match partition.get_pk_prop_ids(label_id as i32) {
+            Ok(pk_ids) => pk_ids.map(|ids| ids.into_iter().map(|id| id as PropId).collect()),
+            Err(e) => {
+                error!("get primary keys of label {} failed: {:?}", label_id, e);
+                None
+            }
+        }
+    }
+
     fn get_vertex_id_by_primary_key(&self, _label_id: u32, _key: &String) -> Option<(u32, i64)> {
         // TODO check
         unimplemented!("get vertex id by primary key is not implemented")
     }
 
     fn get_vertex_id_by_primary_keys(&self, label_id: LabelId, pks: &[Property]) -> Option<VertexId> {
-        Some(FIELD_BUF.with(|data| {
-            let pks_bytes = pks.iter().map(|pk| {
-                let mut data = data.borrow_mut();
-                data.clear();
-                match pk {
-                    Property::Bool(v) => {
-                        data.write_u8(*v as u8).unwrap();
-                    }
-                    Property::Char(v) => {
-                        data.write_u8(*v as u8).unwrap();
-                    }
-                    Property::Short(v) => {
-                        data.write_i16::<BigEndian>(*v).unwrap();
-                    }
-                    Property::Int(v) => {
-                        data.write_i32::<BigEndian>(*v).unwrap();
-                    }
-                    Property::Long(v) => {
-                        data.write_i64::<BigEndian>(*v).unwrap();
-                    }
-                    Property::Float(v) => {
-                        data.write_f32::<BigEndian>(*v).unwrap();
-                    }
-                    Property::Double(v) => {
-                        data.write_f64::<BigEndian>(*v).unwrap();
-                    }
-                    Property::Bytes(v) => {
-                        data.extend_from_slice(v);
-                    }
-                    Property::String(v) => {
-                        data.extend(v.as_bytes());
-                    }
-                    Property::Date(v) => {
-                        data.extend(v.as_bytes());
-                    }
-                    Property::ListInt(v) => {
-                        data.write_i32::<BigEndian>(v.len() as i32)
-                            .unwrap();
-                        for i in v {
-                            data.write_i32::<BigEndian>(*i).unwrap();
-                        }
-                    }
-                    Property::ListLong(v) => {
-                        data.write_i32::<BigEndian>(v.len() as i32)
-                            .unwrap();
-                        for i in v {
-                            data.write_i64::<BigEndian>(*i).unwrap();
-                        }
-                    }
-                    Property::ListFloat(v) => {
-                        data.write_i32::<BigEndian>(v.len() as i32)
-                            .unwrap();
-                        for i in v {
-                            data.write_f32::<BigEndian>(*i).unwrap();
-                        }
-                    }
-                    Property::ListDouble(v) => {
-                        data.write_i32::<BigEndian>(v.len() as i32)
-                            .unwrap();
-                        for i in v {
-                            data.write_f64::<BigEndian>(*i).unwrap();
-                        }
-                    }
-                    Property::ListString(v) => {
-                        data.write_i32::<BigEndian>(v.len() as i32)
-                            .unwrap();
-                        let mut offset = 0;
-                        let mut bytes_vec = Vec::with_capacity(v.len());
-                        for i in v {
-                            let b = i.as_bytes();
-                            bytes_vec.push(b);
-                            offset += b.len();
-                            data.write_i32::<BigEndian>(offset as i32)
-                                .unwrap();
-                        }
-                        for b in bytes_vec {
-                            data.write(b).unwrap();
-                        }
-                    }
-                    Property::ListBytes(v) => {
-                        data.write_i32::<BigEndian>(v.len() as i32)
-                            .unwrap();
-                        let mut offset = 0;
-                        for i in v {
-                            offset += i.len();
-                            data.write_i32::<BigEndian>(offset as i32)
-                                .unwrap();
-                        }
-                        for i in v {
-                            data.write(i.as_slice()).unwrap();
-                        }
-                    }
-                    Property::Null => {
-                        unimplemented!()
-                    }
-                    Property::Unknown => {
-                        unimplemented!()
-                    }
-                }
-                data
-            });
-            get_vertex_id_by_primary_keys(label_id as i32, pks_bytes) as VertexId
-        }))
-    }
-}
-
-fn floor_div(x: i64, y: i64) -> i64 {
-    let mut r = x / y;
-    // if the signs are different and modulo not zero, round down
-    if (x ^ y) < 0 && (r * y != x) {
-        r = r - 1;
+        // encode the keys as the declared types of the primary keys, the same as the writer does
+        let partitions = self.partitions();
+        let partition = partitions.values().next()?;
+        let pk_defs = match partition.get_pk_prop_defs(label_id as i32) {
+            Ok(pk_defs) => pk_defs?,
+            Err(e) => {
+                error!("get primary keys of label {} failed: {:?}", label_id, e);
+                return None;
+            }
+        };
+        if pk_defs.len() != pks.len() {
+            return None;
+        }
+        let values = pks
+            .iter()
+            .zip(pk_defs.iter())
+            .map(|(pk, pk_def)| encode_pk_value(pk, pk_def.r#type).map(|v| v.into_vec()))
+            .collect::<Option<Vec<_>>>()?;
+        Some(get_vertex_id_by_primary_keys(label_id as i32, values.iter()) as VertexId)
     }
-    r
-}
-
-fn floor_mod(x: i64, y: i64) -> i64 {
-    x - floor_div(x, y) * y
 }
@@ -81,6 +81,22 @@ impl Schema for GlobalGraphSchema {
         Some(type_def.get_label())
     }
 
+    fn get_label_prop_ids(&self, label: u32) -> Vec<u32> {
+        let mut prop_ids = self
+            .graph_def
+            .label_to_types
+            .get(&(label as i32))
+            .map(|type_def| {
+                type_def
+                    .get_prop_defs()
+                    .map(|prop_def| prop_def.id as u32)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        prop_ids.sort();
+        prop_ids
+    }
+
     fn to_proto(&self) -> Vec<u8> {
         unimplemented!()
     }
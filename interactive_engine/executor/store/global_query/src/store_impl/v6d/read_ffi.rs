@@ -17,6 +17,7 @@ type GetAllEdgesIterator = *const ::libc::c_void;
 
 // these are for path directly to GAIA
 use dyn_type::object::{DateTimeFormats, Object, Primitives, RawType};
+use groot_store::db::api::EdgeDirection;
 use ir_common::generated::common as common_pb;
 use ir_common::KeyId;
 
@@ -1296,6 +1297,75 @@ impl GlobalGraphQuery for FFIGraphStore {
         unimplemented!()
     }
 
+    fn get_edge_by_id(
+        &self, si: i64, partition_id: PartitionId, label: LabelId, src_id: VertexId, dst_id: VertexId,
+        edge_id: i64, output_prop_ids: Option<&Vec<u32>>,
+    ) -> Option<Self::E> {
+        // no point lookup of edges in vineyard, so the edge is found among the out edges of the source
+        self.get_out_edges(
+            si,
+            vec![(partition_id, vec![src_id])],
+            &vec![label],
+            None,
+            None,
+            output_prop_ids,
+            0,
+        )
+        .flat_map(|(_, iter)| iter)
+        .find(|e| e.get_dst_id() == dst_id && e.get_edge_id() == edge_id)
+    }
+
+    fn get_sorted_edges(
+        &self, _si: i64, _partition_id: PartitionId, _vertex_id: VertexId, label: LabelId,
+        _direction: EdgeDirection, _lower: Option<i64>, _upper: Option<i64>, _limit: usize,
+        _output_prop_ids: Option<&Vec<u32>>,
+    ) -> Self::EI {
+        // the edges in vineyard have no sort properties
+        error!("sorted edges of label {} are not supported in vineyard", label);
+        GlobalEdgeIter::Common(Box::new(std::iter::empty()))
+    }
+
+    fn get_edges_page(
+        &self, si: i64, partition_id: PartitionId, vertex_id: VertexId, edge_labels: &Vec<LabelId>,
+        direction: EdgeDirection, token: Option<&[u8]>, page_size: usize,
+        output_prop_ids: Option<&Vec<u32>>,
+    ) -> (Self::EI, Option<Vec<u8>>) {
+        // no seek in the adjacency of vineyard, so the token is the number of the edges read before
+        let offset = match token {
+            Some(token) if token.len() == 8 => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(token);
+                u64::from_be_bytes(bytes) as usize
+            }
+            Some(_) => {
+                error!("invalid page token of vertex {}", vertex_id);
+                return (GlobalEdgeIter::Common(Box::new(std::iter::empty())), None);
+            }
+            None => 0,
+        };
+        let ids = vec![(partition_id, vec![vertex_id])];
+        let iter = match direction {
+            EdgeDirection::In => self.get_in_edges(si, ids, edge_labels, None, None, output_prop_ids, 0),
+            _ => self.get_out_edges(si, ids, edge_labels, None, None, output_prop_ids, 0),
+        };
+        let mut edges: Vec<FFIEdge> = iter
+            .flat_map(|(_, iter)| iter)
+            .skip(offset)
+            .take(page_size + 1)
+            .collect();
+        let next_token = if edges.len() > page_size {
+            edges.truncate(page_size);
+            Some(
+                ((offset + page_size) as u64)
+                    .to_be_bytes()
+                    .to_vec(),
+            )
+        } else {
+            None
+        };
+        (GlobalEdgeIter::Common(Box::new(edges.into_iter())), next_token)
+    }
+
     fn get_all_vertices(
         &self, _si: i64, labels_ref: &Vec<LabelId>, _condition: Option<&Condition>,
         _dedup_prop_ids: Option<&Vec<u32>>, _output_prop_ids: Option<&Vec<u32>>, limit: usize,
@@ -7,6 +7,9 @@ fn main() {
             proto_root.to_owned() + "/groot/sdk/model.proto",
             proto_root.to_owned() + "/groot/sdk/schema.proto",
             proto_root.to_owned() + "/schema_common.proto",
+            proto_root.to_owned() + "/groot/stream_write_service.proto",
+            proto_root.to_owned() + "/groot/neighbor_sampling_service.proto",
+            proto_root.to_owned() + "/groot/raft_service.proto",
         ],
         &[proto_root],
         "./src/db/proto",
@@ -0,0 +1,80 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Encode the properties by `Property::to_vec`, which must be accepted by `Value::from_proto` and be
+//! decoded to the same properties.
+
+#![no_main]
+
+use groot_store::api::prelude::Property;
+use groot_store::db::api::{Value, ValueType};
+use groot_store::db::proto::schema_common::PropertyValuePb;
+use libfuzzer_sys::fuzz_target;
+
+fn decode(r#type: ValueType, data: Vec<u8>) -> Value {
+    let mut pb = PropertyValuePb::new();
+    pb.set_data_type(r#type.to_proto().unwrap());
+    pb.set_val(data);
+    Value::from_proto(&pb).unwrap_or_else(|e| panic!("{:?} rejects the encoded {:?}", r#type, e))
+}
+
+fuzz_target!(|data: &[u8]| {
+    // the strs split by `0`, which are in valid utf8
+    let text = String::from_utf8_lossy(data);
+    let strs: Vec<String> = text.split('\0').map(|s| s.to_owned()).collect();
+    let value = decode(ValueType::StringList, Property::ListString(strs.clone()).to_vec());
+    let array = value.get_str_list().unwrap();
+    assert_eq!(array.iter().collect::<Vec<_>>(), strs);
+
+    let value = decode(ValueType::String, Property::String(text.to_string()).to_vec());
+    assert_eq!(value.get_str().unwrap(), text);
+
+    let longs: Vec<i64> = data
+        .chunks(8)
+        .map(|chunk| {
+            let mut bytes = [0_u8; 8];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            i64::from_be_bytes(bytes)
+        })
+        .collect();
+    let value = decode(ValueType::LongList, Property::ListLong(longs.clone()).to_vec());
+    assert_eq!(
+        value
+            .get_long_list()
+            .unwrap()
+            .iter()
+            .collect::<Vec<_>>(),
+        longs
+    );
+    for l in longs {
+        assert_eq!(
+            decode(ValueType::Long, Property::Long(l).to_vec())
+                .get_long()
+                .unwrap(),
+            l
+        );
+    }
+
+    let ints: Vec<i32> = data.iter().map(|b| *b as i32 - 128).collect();
+    let value = decode(ValueType::IntList, Property::ListInt(ints.clone()).to_vec());
+    assert_eq!(
+        value
+            .get_int_list()
+            .unwrap()
+            .iter()
+            .collect::<Vec<_>>(),
+        ints
+    );
+});
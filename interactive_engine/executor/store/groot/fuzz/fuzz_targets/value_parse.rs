@@ -0,0 +1,67 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Parse the property values from the clients, where the first byte is the data type and the rest are
+//! the bytes of the value. A value accepted by `Value::from_proto` must be read by all the getters
+//! without panic.
+
+#![no_main]
+
+use groot_store::db::api::Value;
+use groot_store::db::proto::schema_common::{DataTypePb, PropertyValuePb};
+use libfuzzer_sys::fuzz_target;
+use protobuf::ProtobufEnum;
+
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    let data_type = match DataTypePb::from_i32(data[0] as i32) {
+        Some(data_type) => data_type,
+        None => return,
+    };
+    let mut pb = PropertyValuePb::new();
+    pb.set_data_type(data_type);
+    pb.set_val(data[1..].to_vec());
+    if let Ok(value) = Value::from_proto(&pb) {
+        let value = value.as_ref();
+        let _ = value.get_bool();
+        let _ = value.get_char();
+        let _ = value.get_short();
+        let _ = value.get_int();
+        let _ = value.get_long();
+        let _ = value.get_float();
+        let _ = value.get_double();
+        let _ = value.get_str();
+        let _ = value.get_bytes();
+        let _ = value.to_long();
+        let _ = value.to_double();
+        if let Ok(array) = value.get_int_list() {
+            array.iter().for_each(drop);
+        }
+        if let Ok(array) = value.get_long_list() {
+            array.iter().for_each(drop);
+        }
+        if let Ok(array) = value.get_float_list() {
+            array.iter().for_each(drop);
+        }
+        if let Ok(array) = value.get_double_list() {
+            array.iter().for_each(drop);
+        }
+        if let Ok(array) = value.get_str_list() {
+            array.iter().for_each(drop);
+        }
+    }
+});
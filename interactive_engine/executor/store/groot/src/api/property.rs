@@ -911,6 +911,55 @@ impl Property {
     }
 }
 
+impl Property {
+    /// Render the property as a plain (unquoted) string, which is used by text based exporters.
+    /// List values are rendered as their json representation so they can be parsed back without
+    /// ambiguity, e.g. `["a","b"]`; `Null` and `Unknown` are rendered as an empty string.
+    pub fn to_plain_string(&self) -> String {
+        match self {
+            Property::Bool(v) => v.to_string(),
+            Property::Char(v) => (*v as char).to_string(),
+            Property::Short(v) => v.to_string(),
+            Property::Int(v) => v.to_string(),
+            Property::Long(v) => v.to_string(),
+            Property::Float(v) => v.to_string(),
+            Property::Double(v) => v.to_string(),
+            Property::String(v) | Property::Date(v) => v.clone(),
+            Property::Bytes(v) => String::from_utf8_lossy(v).into_owned(),
+            Property::ListInt(_)
+            | Property::ListLong(_)
+            | Property::ListFloat(_)
+            | Property::ListDouble(_)
+            | Property::ListString(_)
+            | Property::ListBytes(_) => self.to_json().to_string(),
+            Property::Null | Property::Unknown => String::new(),
+        }
+    }
+
+    /// Convert the property to a json value, keeping numeric values as json numbers.
+    /// Bytes are rendered as an array of unsigned bytes as json has no binary type.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Property::Bool(v) => json!(v),
+            Property::Char(v) => json!((*v as char).to_string()),
+            Property::Short(v) => json!(v),
+            Property::Int(v) => json!(v),
+            Property::Long(v) => json!(v),
+            Property::Float(v) => json!(v),
+            Property::Double(v) => json!(v),
+            Property::String(v) | Property::Date(v) => json!(v),
+            Property::Bytes(v) => json!(v),
+            Property::ListInt(v) => json!(v),
+            Property::ListLong(v) => json!(v),
+            Property::ListFloat(v) => json!(v),
+            Property::ListDouble(v) => json!(v),
+            Property::ListString(v) => json!(v),
+            Property::ListBytes(v) => json!(v),
+            Property::Null | Property::Unknown => serde_json::Value::Null,
+        }
+    }
+}
+
 pub fn parse_proerty_as_string(data: Vec<u8>, data_type: &DataType) -> Option<String> {
     let mut rdr = Cursor::new(data);
     match *data_type {
@@ -970,7 +1019,10 @@ pub fn parse_proerty_as_string(data: Vec<u8>, data_type: &DataType) -> Option<St
 #[cfg(test)]
 mod tests {
 
+    use proptest::prelude::*;
+
     use super::*;
+    use crate::db::api::{ValueRef, ValueType};
 
     #[test]
     fn test_parse_proerty_as_string() {
@@ -1116,4 +1168,94 @@ mod tests {
         let p2 = Property::Float(1.0);
         assert!(p1.contains(&p2).unwrap());
     }
+
+    #[test]
+    fn test_property_to_string_and_json() {
+        assert_eq!(Property::Int(10).to_plain_string(), "10");
+        assert_eq!(Property::Bool(true).to_plain_string(), "true");
+        assert_eq!(Property::Char('x' as u8).to_plain_string(), "x");
+        assert_eq!(Property::String("a,b".to_owned()).to_plain_string(), "a,b");
+        assert_eq!(Property::Null.to_plain_string(), "");
+        assert_eq!(
+            Property::ListString(vec!["a".to_owned(), "b".to_owned()]).to_plain_string(),
+            r#"["a","b"]"#
+        );
+
+        assert_eq!(Property::Long(7).to_json(), json!(7));
+        assert_eq!(Property::Double(1.5).to_json(), json!(1.5));
+        assert_eq!(Property::Date("2000-01-01".to_owned()).to_json(), json!("2000-01-01"));
+        assert_eq!(Property::ListInt(vec![1, 2]).to_json(), json!([1, 2]));
+        assert_eq!(Property::Null.to_json(), serde_json::Value::Null);
+    }
+
+    // the floats are compared by bits, as NaN != NaN
+    proptest! {
+        #[test]
+        fn test_to_vec_round_trip(
+            b in any::<bool>(), c in any::<u8>(), s in any::<i16>(), i in any::<i32>(),
+            l in any::<i64>(), f in any::<f32>(), d in any::<f64>(), text in ".*",
+        ) {
+            let data = Property::Bool(b).to_vec();
+            prop_assert_eq!(ValueRef::new(ValueType::Bool, &data).get_bool().unwrap(), b);
+            let data = Property::Char(c).to_vec();
+            prop_assert_eq!(ValueRef::new(ValueType::Char, &data).get_char().unwrap(), c);
+            let data = Property::Short(s).to_vec();
+            prop_assert_eq!(ValueRef::new(ValueType::Short, &data).get_short().unwrap(), s);
+            let data = Property::Int(i).to_vec();
+            prop_assert_eq!(ValueRef::new(ValueType::Int, &data).get_int().unwrap(), i);
+            let data = Property::Long(l).to_vec();
+            prop_assert_eq!(ValueRef::new(ValueType::Long, &data).get_long().unwrap(), l);
+            let data = Property::Float(f).to_vec();
+            let res = ValueRef::new(ValueType::Float, &data).get_float().unwrap();
+            prop_assert_eq!(res.to_bits(), f.to_bits());
+            let data = Property::Double(d).to_vec();
+            let res = ValueRef::new(ValueType::Double, &data).get_double().unwrap();
+            prop_assert_eq!(res.to_bits(), d.to_bits());
+            let data = Property::String(text.clone()).to_vec();
+            prop_assert_eq!(ValueRef::new(ValueType::String, &data).get_str().unwrap(), text.as_str());
+        }
+
+        #[test]
+        fn test_to_vec_list_round_trip(
+            ints in prop::collection::vec(any::<i32>(), 0..16),
+            longs in prop::collection::vec(any::<i64>(), 0..16),
+            floats in prop::collection::vec(any::<f32>(), 0..16),
+            doubles in prop::collection::vec(any::<f64>(), 0..16),
+            strs in prop::collection::vec(".*", 0..16),
+        ) {
+            let data = Property::ListInt(ints.clone()).to_vec();
+            let value = ValueRef::new(ValueType::IntList, &data);
+            prop_assert!(value.check().is_ok());
+            prop_assert_eq!(value.get_int_list().unwrap().iter().collect::<Vec<_>>(), ints);
+            let data = Property::ListLong(longs.clone()).to_vec();
+            let value = ValueRef::new(ValueType::LongList, &data);
+            prop_assert!(value.check().is_ok());
+            prop_assert_eq!(value.get_long_list().unwrap().iter().collect::<Vec<_>>(), longs);
+            let data = Property::ListFloat(floats.clone()).to_vec();
+            let value = ValueRef::new(ValueType::FloatList, &data);
+            let res: Vec<u32> = value.get_float_list().unwrap().iter().map(|x| x.to_bits()).collect();
+            prop_assert_eq!(res, floats.iter().map(|x| x.to_bits()).collect::<Vec<_>>());
+            let data = Property::ListDouble(doubles.clone()).to_vec();
+            let value = ValueRef::new(ValueType::DoubleList, &data);
+            let res: Vec<u64> = value.get_double_list().unwrap().iter().map(|x| x.to_bits()).collect();
+            prop_assert_eq!(res, doubles.iter().map(|x| x.to_bits()).collect::<Vec<_>>());
+            let data = Property::ListString(strs.clone()).to_vec();
+            let value = ValueRef::new(ValueType::StringList, &data);
+            prop_assert!(value.check().is_ok());
+            let res: Vec<&str> = value.get_str_list().unwrap().iter().collect();
+            prop_assert_eq!(res, strs.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn test_to_bytes_round_trip(i in any::<i32>(), l in any::<i64>(), d in any::<f64>(), text in ".*") {
+            let res = parse_proerty_as_string(Property::Int(i).to_bytes(), &DataType::Int);
+            prop_assert_eq!(res.unwrap(), i.to_string());
+            let res = parse_proerty_as_string(Property::Long(l).to_bytes(), &DataType::Long);
+            prop_assert_eq!(res.unwrap(), l.to_string());
+            let res = parse_proerty_as_string(Property::Double(d).to_bytes(), &DataType::Double);
+            prop_assert_eq!(res.unwrap(), d.to_string());
+            let res = parse_proerty_as_string(Property::String(text.clone()).to_bytes(), &DataType::String);
+            prop_assert_eq!(res.unwrap(), format!("\"{}\"", text));
+        }
+    }
 }
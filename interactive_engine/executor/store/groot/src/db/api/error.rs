@@ -55,6 +55,29 @@ impl GraphError {
             }
         }
     }
+
+    /// The code of the error reported to the clients, see `GraphErrorCode::code`.
+    pub fn code(&self) -> u32 {
+        self.classify().code()
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.classify().is_retryable()
+    }
+
+    pub fn is_user_error(&self) -> bool {
+        self.classify().is_user_error()
+    }
+
+    fn classify(&self) -> GraphErrorCode {
+        match self {
+            GraphError::WithBackTrace(inner) => inner.get_error_code(),
+            GraphError::Internal(_) => GraphErrorCode::GraphStoreBug,
+            GraphError::Rocksdb(_) => GraphErrorCode::ExternalStorageError,
+            GraphError::InvalidArgument(_) => GraphErrorCode::InvalidOperation,
+            GraphError::TooManyVersions(_) => GraphErrorCode::TooManyVersions,
+        }
+    }
 }
 
 impl Debug for GraphError {
@@ -113,6 +136,54 @@ pub enum GraphErrorCode {
     EngineError,
 }
 
+impl GraphErrorCode {
+    /// The stable numeric code reported to the clients, in `41xx` of the errors of the groot store, which
+    /// is never reused for another error code.
+    pub fn code(&self) -> u32 {
+        match self {
+            GraphErrorCode::ValueTypeMismatch => 4101,
+            GraphErrorCode::Utf8Error => 4102,
+            GraphErrorCode::InvalidData => 4103,
+            GraphErrorCode::LockFailed => 4104,
+            GraphErrorCode::TooManyVersions => 4105,
+            GraphErrorCode::GraphStoreBug => 4106,
+            GraphErrorCode::InvalidOperation => 4107,
+            GraphErrorCode::DataNotExists => 4108,
+            GraphErrorCode::TypeNotFound => 4109,
+            GraphErrorCode::TypeAlreadyExists => 4110,
+            GraphErrorCode::ExternalStorageError => 4111,
+            GraphErrorCode::DecodeError => 4112,
+            GraphErrorCode::MetaNotFound => 4113,
+            GraphErrorCode::NotSupported => 4114,
+            GraphErrorCode::EngineError => 4115,
+        }
+    }
+
+    /// Whether the same operation may succeed if it's done again, e.g., a type not visible yet.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            GraphErrorCode::LockFailed
+                | GraphErrorCode::DataNotExists
+                | GraphErrorCode::ExternalStorageError
+                | GraphErrorCode::TooManyVersions
+        )
+    }
+
+    /// Whether the error is caused by the operation of the user rather than the store.
+    pub fn is_user_error(&self) -> bool {
+        matches!(
+            self,
+            GraphErrorCode::ValueTypeMismatch
+                | GraphErrorCode::InvalidOperation
+                | GraphErrorCode::TypeNotFound
+                | GraphErrorCode::TypeAlreadyExists
+                | GraphErrorCode::MetaNotFound
+                | GraphErrorCode::NotSupported
+        )
+    }
+}
+
 macro_rules! func_signature {
     ($func:tt, $($x:tt),*) => {
         {
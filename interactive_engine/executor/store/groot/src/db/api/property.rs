@@ -154,43 +154,57 @@ impl<'a> ValueRef<'a> {
     }
 
     pub fn get_bool(&self) -> GraphResult<bool> {
-        let res = self.check_type_match(ValueType::Bool);
+        let res = self
+            .check_type_match(ValueType::Bool)
+            .and_then(|_| self.check_fixed_len());
         res_unwrap!(res, get_bool)?;
         Ok(get_bool(self.data))
     }
 
     pub fn get_char(&self) -> GraphResult<u8> {
-        let res = self.check_type_match(ValueType::Char);
+        let res = self
+            .check_type_match(ValueType::Char)
+            .and_then(|_| self.check_fixed_len());
         res_unwrap!(res, get_char)?;
         Ok(get_char(self.data))
     }
 
     pub fn get_short(&self) -> GraphResult<i16> {
-        let res = self.check_type_match(ValueType::Short);
+        let res = self
+            .check_type_match(ValueType::Short)
+            .and_then(|_| self.check_fixed_len());
         res_unwrap!(res, get_short)?;
         Ok(get_short(self.data))
     }
 
     pub fn get_int(&self) -> GraphResult<i32> {
-        let res = self.check_type_match(ValueType::Int);
+        let res = self
+            .check_type_match(ValueType::Int)
+            .and_then(|_| self.check_fixed_len());
         res_unwrap!(res, get_int)?;
         Ok(get_int(self.data))
     }
 
     pub fn get_long(&self) -> GraphResult<i64> {
-        let res = self.check_type_match(ValueType::Long);
+        let res = self
+            .check_type_match(ValueType::Long)
+            .and_then(|_| self.check_fixed_len());
         res_unwrap!(res, get_long)?;
         Ok(get_long(self.data))
     }
 
     pub fn get_float(&self) -> GraphResult<f32> {
-        let res = self.check_type_match(ValueType::Float);
+        let res = self
+            .check_type_match(ValueType::Float)
+            .and_then(|_| self.check_fixed_len());
         res_unwrap!(res, get_float)?;
         Ok(get_float(self.data))
     }
 
     pub fn get_double(&self) -> GraphResult<f64> {
-        let res = self.check_type_match(ValueType::Double);
+        let res = self
+            .check_type_match(ValueType::Double)
+            .and_then(|_| self.check_fixed_len());
         res_unwrap!(res, get_double)?;
         Ok(get_double(self.data))
     }
@@ -250,6 +264,7 @@ impl<'a> ValueRef<'a> {
 
     /// transform value to long if it is a number, else return None
     pub fn to_long(&self) -> Option<i64> {
+        self.check_fixed_len().ok()?;
         match self.r#type {
             ValueType::Bool => Some(get_bool(self.data) as i64),
             ValueType::Char => Some(get_char(self.data) as i64),
@@ -264,6 +279,7 @@ impl<'a> ValueRef<'a> {
 
     /// transform value to double if it is a number, else return None
     pub fn to_double(&self) -> Option<f64> {
+        self.check_fixed_len().ok()?;
         match self.r#type {
             ValueType::Bool => {
                 if get_bool(self.data) {
@@ -291,6 +307,28 @@ impl<'a> ValueRef<'a> {
         Ok(())
     }
 
+    /// check the layout of the data, which may be from the clients, so that the value can be extracted
+    /// safely; the strs are not checked to be in valid utf8, see `weak_check_str_list`
+    pub fn check(&self) -> GraphResult<()> {
+        match self.r#type {
+            ValueType::String | ValueType::Bytes => Ok(()),
+            ValueType::IntList | ValueType::LongList | ValueType::FloatList | ValueType::DoubleList => self
+                .check_numeric_array(self.r#type)
+                .map(|_| ()),
+            ValueType::StringList => self.weak_check_str_list().map(|_| ()),
+            _ => self.check_fixed_len(),
+        }
+    }
+
+    fn check_fixed_len(&self) -> GraphResult<()> {
+        if self.r#type.has_fixed_length() && self.r#type.len() == self.data.len() {
+            return Ok(());
+        }
+        let msg = format!("invalid {:?} bytes, data len is {}", self.r#type, self.data.len());
+        let err = gen_graph_err!(GraphErrorCode::InvalidData, msg, check_fixed_len);
+        Err(err)
+    }
+
     fn check_numeric_array(&self, value_type: ValueType) -> GraphResult<UnsafeBytesReader> {
         let reader = UnsafeBytesReader::new(self.data);
         let unit_size = match value_type {
@@ -298,7 +336,9 @@ impl<'a> ValueRef<'a> {
             ValueType::LongList | ValueType::DoubleList => ::std::mem::size_of::<u64>(),
             _ => unreachable!(),
         };
-        if reader.read_u32(0).to_be() as usize * unit_size + LEN_SIZE == self.data.len() {
+        if self.data.len() >= LEN_SIZE
+            && reader.read_u32(0).to_be() as usize * unit_size + LEN_SIZE == self.data.len()
+        {
             return Ok(reader);
         }
         let msg = format!("invalid {:?} bytes, data len is {}", value_type, self.data.len());
@@ -307,15 +347,25 @@ impl<'a> ValueRef<'a> {
     }
 
     /// it's called weak because str content won't be check, so it map happens that the content is
-    /// in valid utf8 and when user extract the str, the process will be panic
+    /// in valid utf8 and when user extract the str, the process will be panic. The offsets are checked
+    /// to be ascending and within the data.
     fn weak_check_str_list(&self) -> GraphResult<UnsafeBytesReader> {
         let reader = UnsafeBytesReader::new(self.data);
-        let len = reader.read_u32(0).to_be() as usize;
-        let total_len = reader
-            .read_u32(LEN_SIZE + LEN_SIZE * (len - 1))
-            .to_be() as usize;
-        if total_len == self.data.len() - (LEN_SIZE + LEN_SIZE * len) {
-            return Ok(reader);
+        if self.data.len() >= LEN_SIZE {
+            let len = reader.read_u32(0).to_be() as usize;
+            let str_start_off = LEN_SIZE + LEN_SIZE * len;
+            if str_start_off <= self.data.len() {
+                let mut end_off = 0;
+                let ascending = (0..len).all(|i| {
+                    let off = reader.read_u32(LEN_SIZE + LEN_SIZE * i).to_be() as usize;
+                    let ascending = off >= end_off;
+                    end_off = off;
+                    ascending
+                });
+                if ascending && end_off == self.data.len() - str_start_off {
+                    return Ok(reader);
+                }
+            }
         }
         let msg = format!("invalid str array bytes");
         let err = gen_graph_err!(GraphErrorCode::InvalidData, msg, weak_check_str_list);
@@ -585,7 +635,9 @@ impl Value {
 
     pub fn from_proto(pb: &PropertyValuePb) -> GraphResult<Self> {
         let val_type = ValueType::from_i32(pb.get_data_type().value())?;
-        Ok(Value::new(val_type, Vec::from(pb.get_val())))
+        let value = Value::new(val_type, Vec::from(pb.get_val()));
+        res_unwrap!(value.as_ref().check(), from_proto)?;
+        Ok(value)
     }
 
     pub fn from_value_ref(value_ref: &ValueRef) -> Self {
@@ -1104,6 +1156,8 @@ impl<'a> std::fmt::Debug for StrArray<'a> {
 mod tests {
     use std::fmt::Debug;
 
+    use proptest::prelude::*;
+
     use super::*;
 
     macro_rules! normal_test {
@@ -1259,6 +1313,63 @@ mod tests {
         assert!(Value::int_list(&[1, 3, 2]) > Value::int_list(&[1, 2, 3, 4]));
     }
 
+    #[test]
+    fn value_check_test() {
+        // an empty str list has no offsets
+        let v = Value::string_list(&[]);
+        assert!(v.as_ref().check().is_ok());
+        assert_eq!(v.get_str_list().unwrap().len(), 0);
+
+        let invalid = vec![
+            (ValueType::Int, vec![0_u8; 3]),
+            (ValueType::Double, vec![0_u8; 9]),
+            (ValueType::IntList, vec![0_u8; 2]),
+            (ValueType::IntList, vec![0, 0, 0, 2, 0, 0, 0, 1]),
+            (ValueType::StringList, vec![]),
+            (ValueType::StringList, vec![0, 0, 0, 1]),
+            (ValueType::StringList, vec![0, 0, 0, 0, b'a']),
+            // the offsets must be ascending
+            (ValueType::StringList, vec![0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1, b'a', b'b']),
+        ];
+        for (r#type, data) in invalid {
+            let v = ValueRef::new(r#type, &data);
+            assert!(v.check().is_err());
+            assert!(v.to_long().is_none());
+        }
+        assert!(ValueRef::new(ValueType::Long, &[0; 7])
+            .get_long()
+            .is_err());
+        assert!(ValueRef::new(ValueType::StringList, &[0, 0, 0, 1])
+            .get_str_list()
+            .is_err());
+    }
+
+    fn value_type_strategy() -> impl Strategy<Value = ValueType> {
+        prop::sample::select(ValueType::all_value_types())
+    }
+
+    proptest! {
+        // the data from the clients is arbitrary, which must be rejected rather than panic
+        #[test]
+        fn value_arbitrary_bytes_test(
+            r#type in value_type_strategy(),
+            data in prop::collection::vec(any::<u8>(), 0..64),
+        ) {
+            let v = ValueRef::new(r#type, &data);
+            if v.check().is_ok() {
+                let _ = v.to_long();
+                let _ = v.to_double();
+                // the iteration stops at a str in invalid utf8
+                if let Ok(array) = v.get_str_list() {
+                    prop_assert!(array.iter().count() <= array.len());
+                }
+                if let Ok(array) = v.get_long_list() {
+                    prop_assert_eq!(array.iter().count(), array.len());
+                }
+            }
+        }
+    }
+
     fn check_numeric_array<T: ToBigEndian + PartialEq + Debug>(array: NumericArray<T>, ans: &[T]) {
         assert_eq!(array.len(), ans.len());
         for i in 0..ans.len() {
@@ -156,6 +156,8 @@ pub struct TypeDef {
     label: String,
     label_id: LabelId,
     properties: HashMap<PropertyId, PropDef>,
+    // the ids of the properties in the order they are declared
+    prop_order: Vec<PropertyId>,
     type_enum: TypeEnumPb,
 }
 
@@ -172,6 +174,16 @@ impl TypeDef {
         self.properties.get(&prop_id)
     }
 
+    /// The ids of the primary key properties in the order they are declared, which is the order they
+    /// are hashed into the vertex id, the same as `TypeDef.getPrimaryKeyList()` in Java.
+    pub fn get_pk_prop_ids(&self) -> Vec<PropertyId> {
+        self.prop_order
+            .iter()
+            .filter(|id| self.properties.get(id).map_or(false, |p| p.pk))
+            .copied()
+            .collect()
+    }
+
     pub fn get_label(&self) -> String {
         self.label.clone()
     }
@@ -185,12 +197,14 @@ impl TypeDef {
         let label = proto.get_label();
         let label_id = proto.get_label_id().get_id();
         let mut properties = HashMap::new();
+        let mut prop_order = Vec::with_capacity(proto.get_props().len());
         for propertydef_pb in proto.get_props() {
             let property_def = PropDef::from_proto(propertydef_pb)?;
+            prop_order.push(property_def.id);
             properties.insert(property_def.id, property_def);
         }
         let type_enum = proto.get_type_enum();
-        Ok(Self::new(version_id, label.to_string(), label_id, properties, type_enum))
+        Ok(Self::new(version_id, label.to_string(), label_id, properties, prop_order, type_enum))
     }
 
     pub fn to_proto(&self) -> GraphResult<TypeDefPb> {
@@ -198,7 +212,11 @@ impl TypeDef {
         typedef_pb.set_version_id(self.version);
         typedef_pb.set_label(self.label.clone());
         typedef_pb.mut_label_id().set_id(self.label_id);
-        for property_def in self.properties.values() {
+        for property_def in self
+            .prop_order
+            .iter()
+            .filter_map(|id| self.properties.get(id))
+        {
             typedef_pb
                 .mut_props()
                 .push(property_def.to_proto()?);
@@ -225,9 +243,9 @@ impl TypeDef {
 
     fn new(
         version: i32, label: String, label_id: LabelId, properties: HashMap<PropertyId, PropDef>,
-        type_enum: TypeEnumPb,
+        prop_order: Vec<PropertyId>, type_enum: TypeEnumPb,
     ) -> Self {
-        TypeDef { version, label, label_id, properties, type_enum }
+        TypeDef { version, label, label_id, properties, prop_order, type_enum }
     }
 
     #[cfg(test)]
@@ -272,9 +290,15 @@ impl TypeDefBuilder {
         &mut self, id: PropertyId, inner_id: PropertyId, name: String, r#type: ValueType,
         default_value: Option<Value>, pk: bool, comment: String,
     ) -> &mut Self {
-        self.type_def
+        let prop_def = PropDef::new(id, inner_id, name, r#type, default_value, pk, comment);
+        if self
+            .type_def
             .properties
-            .insert(id, PropDef::new(id, inner_id, name, r#type, default_value, pk, comment));
+            .insert(id, prop_def)
+            .is_none()
+        {
+            self.type_def.prop_order.push(id);
+        }
         self
     }
 
@@ -288,6 +312,11 @@ impl TypeDefBuilder {
         self
     }
 
+    pub fn set_label(&mut self, label: &str) -> &mut Self {
+        self.type_def.label = label.to_owned();
+        self
+    }
+
     pub fn build(self) -> TypeDef {
         self.type_def
     }
@@ -388,4 +417,17 @@ mod tests {
         let type_def2 = TypeDef::from_bytes(&bytes).unwrap();
         assert_eq!(type_def, type_def2);
     }
+
+    #[test]
+    fn test_pk_declaration_order() {
+        let mut builder = TypeDefBuilder::new();
+        for (id, pk) in [(7, true), (3, false), (5, true), (1, true)] {
+            let name = format!("p{}", id);
+            builder.add_property(id, id, name, ValueType::Long, None, pk, "comment".to_string());
+        }
+        let type_def = builder.build();
+        assert_eq!(type_def.get_pk_prop_ids(), vec![7, 5, 1]);
+        let type_def2 = TypeDef::from_bytes(&type_def.to_bytes().unwrap()).unwrap();
+        assert_eq!(type_def2.get_pk_prop_ids(), vec![7, 5, 1]);
+    }
 }
@@ -0,0 +1,150 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use super::{Consensus, StateMachine};
+use crate::db::api::*;
+use crate::db::graph::partition::PartitionRouting;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MetaCommand {
+    /// A batch of ddl operations written at the snapshot, in the format of the write batches.
+    Ddl { si: SnapshotId, batch: Vec<u8> },
+    /// The partition routing in json, see `PartitionRouting::to_json`.
+    SwitchRouting(String),
+}
+
+impl MetaCommand {
+    pub fn encode(&self) -> GraphResult<Vec<u8>> {
+        serde_json::to_vec(self).map_err(|e| {
+            let msg = format!("encode meta command failed: {}", e);
+            gen_graph_err!(GraphErrorCode::InvalidData, msg, encode)
+        })
+    }
+
+    pub fn decode(bytes: &[u8]) -> GraphResult<Self> {
+        serde_json::from_slice(bytes).map_err(|e| {
+            let msg = format!("decode meta command failed: {}", e);
+            gen_graph_err!(GraphErrorCode::InvalidData, msg, decode)
+        })
+    }
+
+    /// Replicate the command by the consensus, see `Consensus::propose`.
+    pub fn propose(&self, consensus: &dyn Consensus, timeout: Duration) -> GraphResult<u64> {
+        consensus.propose(self.encode()?, timeout)
+    }
+}
+
+pub type DdlApplier = Box<dyn Fn(SnapshotId, &[u8]) -> GraphResult<()> + Send + Sync>;
+
+pub type RoutingListener = Box<dyn Fn(&PartitionRouting) + Send + Sync>;
+
+/// The schema and the partition routing replicated among store nodes: the ddl batches are written to
+/// the local stores by the applier, and the routing is kept and published to the listeners, e.g.
+/// to switch the routing of the engine.
+pub struct MetaStateMachine {
+    ddl_applier: DdlApplier,
+    last_ddl_si: AtomicI64,
+    routing: RwLock<Option<Arc<PartitionRouting>>>,
+    routing_listeners: Mutex<Vec<RoutingListener>>,
+}
+
+impl MetaStateMachine {
+    pub fn new(ddl_applier: DdlApplier) -> Self {
+        MetaStateMachine {
+            ddl_applier,
+            last_ddl_si: AtomicI64::new(-1),
+            routing: RwLock::new(None),
+            routing_listeners: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn add_routing_listener(&self, listener: RoutingListener) {
+        if let Some(routing) = self.get_routing() {
+            listener(&routing);
+        }
+        self.routing_listeners
+            .lock()
+            .unwrap()
+            .push(listener);
+    }
+
+    pub fn get_routing(&self) -> Option<Arc<PartitionRouting>> {
+        self.routing.read().unwrap().clone()
+    }
+
+    /// The snapshot of the last ddl batch applied, the schema is up to date at it.
+    pub fn last_ddl_si(&self) -> SnapshotId {
+        self.last_ddl_si.load(Ordering::Acquire)
+    }
+}
+
+impl StateMachine for MetaStateMachine {
+    fn apply(&self, index: u64, command: &[u8]) -> GraphResult<()> {
+        match MetaCommand::decode(command)? {
+            MetaCommand::Ddl { si, batch } => {
+                // the batches replayed after a restart are already written;
+                if si > self.last_ddl_si() {
+                    (self.ddl_applier)(si, &batch)?;
+                    self.last_ddl_si.store(si, Ordering::Release);
+                }
+            }
+            MetaCommand::SwitchRouting(json) => {
+                let routing = PartitionRouting::from_json(&json)?;
+                let mut current = self.routing.write().unwrap();
+                if current
+                    .as_ref()
+                    .map_or(true, |r| routing.version() > r.version())
+                {
+                    info!(
+                        "switch partition routing to version {} at raft index {}",
+                        routing.version(),
+                        index
+                    );
+                    for listener in self.routing_listeners.lock().unwrap().iter() {
+                        listener(&routing);
+                    }
+                    *current = Some(Arc::new(routing));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    #[test]
+    fn test_meta_state_machine() {
+        let ddl_count = Arc::new(AtomicUsize::new(0));
+        let count = ddl_count.clone();
+        let sm = MetaStateMachine::new(Box::new(move |_, _| {
+            count.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }));
+        let ddl = MetaCommand::Ddl { si: 3, batch: vec![1, 2] };
+        sm.apply(1, &ddl.encode().unwrap()).unwrap();
+        sm.apply(2, &ddl.encode().unwrap()).unwrap();
+        assert_eq!(ddl_count.load(Ordering::SeqCst), 1);
+        assert_eq!(sm.last_ddl_si(), 3);
+
+        let switched = Arc::new(AtomicUsize::new(0));
+        let count = switched.clone();
+        sm.add_routing_listener(Box::new(move |_| {
+            count.fetch_add(1, Ordering::SeqCst);
+        }));
+        let mut routing = PartitionRouting::new(2);
+        routing.split(0, 2, 1).unwrap();
+        let switch = MetaCommand::SwitchRouting(routing.to_json());
+        sm.apply(3, &switch.encode().unwrap()).unwrap();
+        // a stale routing is ignored;
+        let stale = MetaCommand::SwitchRouting(PartitionRouting::new(2).to_json());
+        sm.apply(4, &stale.encode().unwrap()).unwrap();
+        assert_eq!(switched.load(Ordering::SeqCst), 1);
+        assert_eq!(sm.get_routing().unwrap().version(), routing.version());
+    }
+}
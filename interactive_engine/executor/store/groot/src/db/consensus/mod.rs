@@ -0,0 +1,37 @@
+//! Consensus of the metadata among store nodes, i.e. the schema and the partition routing, so that
+//! their updates are replicated and linearizable without an external coordinator.
+//!
+//! `Consensus` is pluggable, the embedded implementation is a raft group of the store nodes, see
+//! `RaftNode`, of which the replicated commands are applied to a `StateMachine` on every node, e.g.
+//! the `MetaStateMachine`.
+
+use std::time::Duration;
+
+use crate::db::api::*;
+
+pub mod meta;
+pub mod node;
+pub mod raft;
+pub mod replica;
+pub mod storage;
+
+/// The state replicated by the commands, which are applied in the same order on every replica.
+pub trait StateMachine: Send + Sync {
+    /// Apply the command committed at `index`, it should be deterministic.
+    fn apply(&self, index: u64, command: &[u8]) -> GraphResult<()>;
+}
+
+pub trait Consensus: Send + Sync {
+    /// Replicate the command, and wait until it's applied to the local state machine; it fails if
+    /// this replica is not the leader, see `leader`.
+    fn propose(&self, command: Vec<u8>, timeout: Duration) -> GraphResult<u64>;
+
+    /// Wait until the commands committed before are applied to the local state machine, so that
+    /// the following reads of the local state are linearizable; only on the leader as `propose`.
+    fn sync(&self, timeout: Duration) -> GraphResult<u64>;
+
+    fn is_leader(&self) -> bool;
+
+    /// The id of the leader known by this replica, to which the proposals should be sent.
+    fn leader(&self) -> Option<u64>;
+}
@@ -0,0 +1,365 @@
+//! The runtime of a raft replica: a ticker thread drives the elections and heartbeats of the
+//! `RaftCore`, the committed entries are applied to the state machine in order, and the messages are
+//! sent to the peers by the `RaftTransport`, e.g. `GrpcTransport`, which are received by the
+//! `RaftService` of the peers.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::FutureExt;
+use grpcio::{ChannelBuilder, Environment, RpcContext, UnarySink};
+
+use super::raft::{Message, NodeId, RaftCore, RaftStorage, Role};
+use super::{Consensus, StateMachine};
+use crate::db::api::*;
+use crate::db::proto::raft_service::{RaftAckPb, RaftMessagePb};
+use crate::db::proto::raft_service_grpc::{Raft, RaftClient};
+
+pub trait RaftTransport: Send + Sync {
+    /// Send the message to the peer without waiting, the lost messages are recovered by raft.
+    fn send(&self, to: NodeId, msg: Message);
+}
+
+#[derive(Clone, Debug)]
+pub struct RaftConfig {
+    pub id: NodeId,
+    /// all the replicas of the group, including this one
+    pub peers: Vec<NodeId>,
+    pub tick: Duration,
+    /// ticks without hearing from the leader before starting an election
+    pub election_ticks: u32,
+    pub heartbeat_ticks: u32,
+}
+
+impl RaftConfig {
+    pub fn new(id: NodeId, peers: Vec<NodeId>) -> Self {
+        RaftConfig { id, peers, tick: Duration::from_millis(100), election_ticks: 10, heartbeat_ticks: 2 }
+    }
+}
+
+struct Inner {
+    core: Mutex<RaftCore>,
+    applied: Mutex<u64>,
+    applied_cond: Condvar,
+    transport: Box<dyn RaftTransport>,
+    state_machine: Arc<dyn StateMachine>,
+    stopped: AtomicBool,
+}
+
+#[derive(Clone)]
+pub struct RaftNode {
+    inner: Arc<Inner>,
+}
+
+impl RaftNode {
+    pub fn new(
+        config: &RaftConfig, storage: Box<dyn RaftStorage>, transport: Box<dyn RaftTransport>,
+        state_machine: Arc<dyn StateMachine>,
+    ) -> GraphResult<Self> {
+        let core = RaftCore::new(
+            config.id,
+            config.peers.clone(),
+            storage,
+            config.election_ticks,
+            config.heartbeat_ticks,
+        )?;
+        let inner = Inner {
+            core: Mutex::new(core),
+            applied: Mutex::new(0),
+            applied_cond: Condvar::new(),
+            transport,
+            state_machine,
+            stopped: AtomicBool::new(false),
+        };
+        Ok(RaftNode { inner: Arc::new(inner) })
+    }
+
+    /// Start ticking the replica every `config.tick` until it's stopped.
+    pub fn start(&self, config: &RaftConfig) {
+        let node = self.clone();
+        let tick = config.tick;
+        thread::Builder::new()
+            .name(format!("raft-tick-{}", config.id))
+            .spawn(move || {
+                while !node.inner.stopped.load(Ordering::Acquire) {
+                    thread::sleep(tick);
+                    if let Err(e) = node.with_core(|core| core.tick()) {
+                        error!("raft tick failed: {:?}", e);
+                    }
+                }
+            })
+            .expect("spawn raft ticker failed");
+    }
+
+    pub fn stop(&self) {
+        self.inner
+            .stopped
+            .store(true, Ordering::Release);
+        self.inner.applied_cond.notify_all();
+    }
+
+    /// Handle the message from a peer.
+    pub fn step(&self, msg: Message) -> GraphResult<()> {
+        self.with_core(|core| core.step(msg))
+    }
+
+    pub fn role(&self) -> Role {
+        self.inner.core.lock().unwrap().role()
+    }
+
+    pub fn applied_index(&self) -> u64 {
+        *self.inner.applied.lock().unwrap()
+    }
+
+    // the committed entries are applied under the core lock so they are applied in order, but the
+    // messages are sent after it's released;
+    fn with_core<T, F>(&self, f: F) -> GraphResult<T>
+    where
+        F: FnOnce(&mut RaftCore) -> GraphResult<T>,
+    {
+        let (res, messages) = {
+            let mut core = self.inner.core.lock().unwrap();
+            let res = f(&mut core);
+            let committed = core.take_committed();
+            if let Some(last) = committed.last() {
+                let last_index = last.index;
+                for entry in committed {
+                    // the no-op entries of new leaders
+                    if entry.data.is_empty() {
+                        continue;
+                    }
+                    if let Err(e) = self
+                        .inner
+                        .state_machine
+                        .apply(entry.index, &entry.data)
+                    {
+                        error!("apply raft entry {} failed: {:?}", entry.index, e);
+                    }
+                }
+                *self.inner.applied.lock().unwrap() = last_index;
+                self.inner.applied_cond.notify_all();
+            }
+            (res, core.take_messages())
+        };
+        for (to, msg) in messages {
+            self.inner.transport.send(to, msg);
+        }
+        res
+    }
+
+    // wait until the entry proposed is applied, and check it's not overwritten by another leader;
+    fn wait_applied(&self, index: u64, term: u64, timeout: Duration) -> GraphResult<u64> {
+        let deadline = Instant::now() + timeout;
+        let mut applied = self.inner.applied.lock().unwrap();
+        while *applied < index {
+            let now = Instant::now();
+            if now >= deadline || self.inner.stopped.load(Ordering::Acquire) {
+                let msg = format!("raft entry {} is not applied in {:?}", index, timeout);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, wait_applied, index));
+            }
+            applied = self
+                .inner
+                .applied_cond
+                .wait_timeout(applied, deadline - now)
+                .unwrap()
+                .0;
+        }
+        drop(applied);
+        if self.inner.core.lock().unwrap().term_at(index) != Some(term) {
+            let msg = format!("raft entry {} of term {} is dropped by a new leader", index, term);
+            return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, wait_applied, index));
+        }
+        Ok(index)
+    }
+}
+
+impl Consensus for RaftNode {
+    fn propose(&self, command: Vec<u8>, timeout: Duration) -> GraphResult<u64> {
+        if command.is_empty() {
+            let msg = "empty command is reserved".to_owned();
+            return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, propose));
+        }
+        let (index, term) = self.with_core(|core| core.propose(command))?;
+        self.wait_applied(index, term, timeout)
+    }
+
+    fn sync(&self, timeout: Duration) -> GraphResult<u64> {
+        // an empty entry is skipped by the state machine, it's committed only if this replica is
+        // still the leader, after all the entries before it;
+        let (index, term) = self.with_core(|core| core.propose(Vec::new()))?;
+        self.wait_applied(index, term, timeout)
+    }
+
+    fn is_leader(&self) -> bool {
+        self.role() == Role::Leader
+    }
+
+    fn leader(&self) -> Option<u64> {
+        self.inner.core.lock().unwrap().leader()
+    }
+}
+
+/// Send the raft messages to the `RaftService` of the peers by grpc.
+pub struct GrpcTransport {
+    from: NodeId,
+    clients: HashMap<NodeId, RaftClient>,
+}
+
+impl GrpcTransport {
+    /// `peers` are the addresses of the raft services by node ids.
+    pub fn new(from: NodeId, peers: &HashMap<NodeId, String>) -> Self {
+        let env = Arc::new(Environment::new(1));
+        let clients = peers
+            .iter()
+            .filter(|(id, _)| **id != from)
+            .map(|(id, addr)| {
+                let channel = ChannelBuilder::new(env.clone()).connect(addr);
+                (*id, RaftClient::new(channel))
+            })
+            .collect();
+        GrpcTransport { from, clients }
+    }
+}
+
+impl RaftTransport for GrpcTransport {
+    fn send(&self, to: NodeId, msg: Message) {
+        let client = match self.clients.get(&to) {
+            Some(client) => client,
+            None => {
+                warn!("unknown raft peer {}", to);
+                return;
+            }
+        };
+        let mut req = RaftMessagePb::new();
+        req.set_from(self.from as i64);
+        req.set_to(to as i64);
+        req.set_payload(serde_json::to_vec(&msg).expect("serialize raft message failed"));
+        match client.step_async(&req) {
+            Ok(receiver) => client.spawn(receiver.map(move |res| {
+                if let Err(e) = res {
+                    debug!("send raft message to {} failed: {:?}", to, e);
+                }
+            })),
+            Err(e) => debug!("send raft message to {} failed: {:?}", to, e),
+        }
+    }
+}
+
+/// The grpc service receiving the raft messages of the peers, see `raft_service.proto`.
+#[derive(Clone)]
+pub struct RaftService {
+    node: RaftNode,
+}
+
+impl RaftService {
+    pub fn new(node: RaftNode) -> Self {
+        RaftService { node }
+    }
+}
+
+impl Raft for RaftService {
+    fn step(&mut self, ctx: RpcContext, req: RaftMessagePb, sink: UnarySink<RaftAckPb>) {
+        let res = serde_json::from_slice::<Message>(req.get_payload())
+            .map_err(|e| format!("decode raft message failed: {}", e))
+            .and_then(|msg| {
+                self.node
+                    .step(msg)
+                    .map_err(|e| format!("{:?}", e))
+            });
+        let mut ack = RaftAckPb::new();
+        match res {
+            Ok(()) => ack.set_success(true),
+            Err(e) => {
+                warn!("handle raft message from {} failed: {}", req.get_from(), e);
+                ack.set_errMsg(e);
+            }
+        }
+        ctx.spawn(sink.success(ack).map(|_| ()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::RwLock;
+
+    use super::super::raft::MemRaftStorage;
+    use super::*;
+
+    // deliver the messages in process, by the nodes registered after they are created;
+    struct LocalTransport {
+        nodes: Arc<RwLock<HashMap<NodeId, RaftNode>>>,
+    }
+
+    impl RaftTransport for LocalTransport {
+        fn send(&self, to: NodeId, msg: Message) {
+            let node = self.nodes.read().unwrap().get(&to).cloned();
+            if let Some(node) = node {
+                thread::spawn(move || {
+                    let _ = node.step(msg);
+                });
+            }
+        }
+    }
+
+    struct Counter(Mutex<Vec<u8>>);
+
+    impl StateMachine for Counter {
+        fn apply(&self, _index: u64, command: &[u8]) -> GraphResult<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .extend_from_slice(command);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_raft_node() {
+        let nodes = Arc::new(RwLock::new(HashMap::new()));
+        let mut machines = HashMap::new();
+        for id in 1..=3 {
+            let mut config = RaftConfig::new(id, vec![1, 2, 3]);
+            config.tick = Duration::from_millis(10);
+            let transport = LocalTransport { nodes: nodes.clone() };
+            let machine = Arc::new(Counter(Mutex::new(Vec::new())));
+            let node = RaftNode::new(
+                &config,
+                Box::new(MemRaftStorage::default()),
+                Box::new(transport),
+                machine.clone(),
+            )
+            .unwrap();
+            node.start(&config);
+            nodes.write().unwrap().insert(id, node);
+            machines.insert(id, machine);
+        }
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let (id, leader) = loop {
+            let leader = nodes
+                .read()
+                .unwrap()
+                .iter()
+                .find(|(_, n)| n.is_leader())
+                .map(|(id, n)| (*id, n.clone()));
+            if let Some(leader) = leader {
+                break leader;
+            }
+            assert!(Instant::now() < deadline, "no leader is elected");
+            thread::sleep(Duration::from_millis(10));
+        };
+        leader
+            .propose(vec![1], Duration::from_secs(5))
+            .unwrap();
+        leader
+            .propose(vec![2], Duration::from_secs(5))
+            .unwrap();
+        leader.sync(Duration::from_secs(5)).unwrap();
+        assert_eq!(*machines[&id].0.lock().unwrap(), vec![1, 2]);
+        for node in nodes.read().unwrap().values() {
+            node.stop();
+        }
+    }
+}
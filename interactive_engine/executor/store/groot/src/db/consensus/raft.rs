@@ -0,0 +1,564 @@
+//! The core of raft: leader election and log replication, driven by `tick` and `step` without any
+//! io, so it's deterministic and can be tested in process. The messages to send are collected in
+//! the outbox and the entries committed are handed out in order, see `RaftNode` for the runtime.
+//!
+//! An empty entry is a no-op, which a new leader appends to commit the entries of the previous
+//! terms, and which is proposed to make a linearizable read, see `RaftNode::sync`. The log is not
+//! compacted, as the metadata replicated is small.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::db::api::*;
+
+pub type NodeId = u64;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub term: u64,
+    pub index: u64,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HardState {
+    pub term: u64,
+    pub voted_for: Option<NodeId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Message {
+    RequestVote { term: u64, candidate: NodeId, last_index: u64, last_term: u64 },
+    Vote { term: u64, from: NodeId, granted: bool },
+    Append { term: u64, leader: NodeId, prev_index: u64, prev_term: u64, entries: Vec<Entry>, commit: u64 },
+    // `match_index` is the last index replicated if it succeeds, or a hint of where to retry
+    AppendAck { term: u64, from: NodeId, success: bool, match_index: u64 },
+}
+
+impl Message {
+    pub fn term(&self) -> u64 {
+        match *self {
+            Message::RequestVote { term, .. } => term,
+            Message::Vote { term, .. } => term,
+            Message::Append { term, .. } => term,
+            Message::AppendAck { term, .. } => term,
+        }
+    }
+}
+
+/// The durable state of a replica, which is persisted before the messages depending on it are sent.
+pub trait RaftStorage: Send {
+    fn load(&self) -> GraphResult<(HardState, Vec<Entry>)>;
+
+    fn save_hard_state(&mut self, state: &HardState) -> GraphResult<()>;
+
+    fn append(&mut self, entries: &[Entry]) -> GraphResult<()>;
+
+    /// Remove the entries from `index` on.
+    fn truncate(&mut self, index: u64) -> GraphResult<()>;
+}
+
+#[derive(Default)]
+pub struct MemRaftStorage {
+    state: HardState,
+    entries: Vec<Entry>,
+}
+
+impl RaftStorage for MemRaftStorage {
+    fn load(&self) -> GraphResult<(HardState, Vec<Entry>)> {
+        Ok((self.state.clone(), self.entries.clone()))
+    }
+
+    fn save_hard_state(&mut self, state: &HardState) -> GraphResult<()> {
+        self.state = state.clone();
+        Ok(())
+    }
+
+    fn append(&mut self, entries: &[Entry]) -> GraphResult<()> {
+        self.entries.extend_from_slice(entries);
+        Ok(())
+    }
+
+    fn truncate(&mut self, index: u64) -> GraphResult<()> {
+        self.entries
+            .truncate(index.saturating_sub(1) as usize);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+pub struct RaftCore {
+    id: NodeId,
+    peers: Vec<NodeId>,
+    storage: Box<dyn RaftStorage>,
+    state: HardState,
+    role: Role,
+    leader: Option<NodeId>,
+    // entry of index `i` is at `log[i - 1]`
+    log: Vec<Entry>,
+    commit: u64,
+    applied: u64,
+    votes: HashSet<NodeId>,
+    next_index: HashMap<NodeId, u64>,
+    match_index: HashMap<NodeId, u64>,
+    election_ticks: u32,
+    heartbeat_ticks: u32,
+    elapsed: u32,
+    // randomized in `[election_ticks, 2 * election_ticks)` on every reset to avoid split votes
+    timeout: u32,
+    seed: u64,
+    outbox: Vec<(NodeId, Message)>,
+}
+
+impl RaftCore {
+    pub fn new(
+        id: NodeId, peers: Vec<NodeId>, storage: Box<dyn RaftStorage>, election_ticks: u32,
+        heartbeat_ticks: u32,
+    ) -> GraphResult<Self> {
+        let (state, log) = storage.load()?;
+        let peers = peers.into_iter().filter(|p| *p != id).collect();
+        let mut core = RaftCore {
+            id,
+            peers,
+            storage,
+            state,
+            role: Role::Follower,
+            leader: None,
+            log,
+            commit: 0,
+            applied: 0,
+            votes: HashSet::new(),
+            next_index: HashMap::new(),
+            match_index: HashMap::new(),
+            election_ticks: election_ticks.max(1),
+            heartbeat_ticks: heartbeat_ticks.max(1),
+            elapsed: 0,
+            timeout: 0,
+            seed: id.wrapping_mul(0x9e3779b97f4a7c15) | 1,
+            outbox: Vec::new(),
+        };
+        core.reset_timeout();
+        Ok(core)
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn term(&self) -> u64 {
+        self.state.term
+    }
+
+    pub fn leader(&self) -> Option<NodeId> {
+        self.leader
+    }
+
+    pub fn commit_index(&self) -> u64 {
+        self.commit
+    }
+
+    pub fn last_index(&self) -> u64 {
+        self.log.len() as u64
+    }
+
+    pub fn term_at(&self, index: u64) -> Option<u64> {
+        if index == 0 {
+            Some(0)
+        } else {
+            self.log.get(index as usize - 1).map(|e| e.term)
+        }
+    }
+
+    fn last_term(&self) -> u64 {
+        self.log.last().map(|e| e.term).unwrap_or(0)
+    }
+
+    fn quorum(&self) -> usize {
+        (self.peers.len() + 1) / 2 + 1
+    }
+
+    fn reset_timeout(&mut self) {
+        // xorshift, good enough to spread the timeouts;
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.elapsed = 0;
+        self.timeout = self.election_ticks + (self.seed % self.election_ticks as u64) as u32;
+    }
+
+    pub fn tick(&mut self) -> GraphResult<()> {
+        self.elapsed += 1;
+        match self.role {
+            Role::Leader => {
+                if self.elapsed >= self.heartbeat_ticks {
+                    self.elapsed = 0;
+                    self.broadcast_append();
+                }
+                Ok(())
+            }
+            _ => {
+                if self.elapsed >= self.timeout {
+                    self.campaign()
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    fn campaign(&mut self) -> GraphResult<()> {
+        self.role = Role::Candidate;
+        self.leader = None;
+        self.state = HardState { term: self.state.term + 1, voted_for: Some(self.id) };
+        self.storage.save_hard_state(&self.state)?;
+        self.reset_timeout();
+        self.votes.clear();
+        self.votes.insert(self.id);
+        info!("raft node {} campaigns at term {}", self.id, self.state.term);
+        if self.votes.len() >= self.quorum() {
+            return self.become_leader();
+        }
+        let msg = Message::RequestVote {
+            term: self.state.term,
+            candidate: self.id,
+            last_index: self.last_index(),
+            last_term: self.last_term(),
+        };
+        for peer in self.peers.clone() {
+            self.outbox.push((peer, msg.clone()));
+        }
+        Ok(())
+    }
+
+    fn become_follower(&mut self, term: u64, leader: Option<NodeId>) -> GraphResult<()> {
+        if term > self.state.term {
+            self.state = HardState { term, voted_for: None };
+            self.storage.save_hard_state(&self.state)?;
+        }
+        self.role = Role::Follower;
+        self.leader = leader;
+        self.reset_timeout();
+        Ok(())
+    }
+
+    fn become_leader(&mut self) -> GraphResult<()> {
+        info!("raft node {} becomes the leader at term {}", self.id, self.state.term);
+        self.role = Role::Leader;
+        self.leader = Some(self.id);
+        self.elapsed = 0;
+        let next = self.last_index() + 1;
+        for peer in self.peers.iter() {
+            self.next_index.insert(*peer, next);
+            self.match_index.insert(*peer, 0);
+        }
+        // commit the entries of the previous terms by an entry of this term;
+        self.append_local(Vec::new())?;
+        self.broadcast_append();
+        Ok(())
+    }
+
+    fn append_local(&mut self, data: Vec<u8>) -> GraphResult<u64> {
+        let entry = Entry { term: self.state.term, index: self.last_index() + 1, data };
+        self.storage
+            .append(std::slice::from_ref(&entry))?;
+        self.log.push(entry);
+        self.maybe_commit();
+        Ok(self.last_index())
+    }
+
+    /// Append the data to the log if it's the leader, return the index and the term of the entry,
+    /// which is committed if the entry of the index has the term once it's applied.
+    pub fn propose(&mut self, data: Vec<u8>) -> GraphResult<(u64, u64)> {
+        if self.role != Role::Leader {
+            let msg = format!("raft node {} is not the leader, the leader is {:?}", self.id, self.leader);
+            return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, propose));
+        }
+        let index = self.append_local(data)?;
+        self.broadcast_append();
+        Ok((index, self.state.term))
+    }
+
+    fn broadcast_append(&mut self) {
+        for peer in self.peers.clone() {
+            self.send_append(peer);
+        }
+    }
+
+    fn send_append(&mut self, peer: NodeId) {
+        let next = *self.next_index.get(&peer).unwrap_or(&1);
+        let prev_index = next - 1;
+        let prev_term = self.term_at(prev_index).unwrap_or(0);
+        let entries = self.log[prev_index as usize..].to_vec();
+        let msg = Message::Append {
+            term: self.state.term,
+            leader: self.id,
+            prev_index,
+            prev_term,
+            entries,
+            commit: self.commit,
+        };
+        self.outbox.push((peer, msg));
+    }
+
+    fn maybe_commit(&mut self) {
+        let quorum = self.quorum();
+        for index in (self.commit + 1..=self.last_index()).rev() {
+            // only the entries of the current term are committed by counting replicas;
+            if self.term_at(index) != Some(self.state.term) {
+                break;
+            }
+            let replicas = 1 + self
+                .match_index
+                .values()
+                .filter(|m| **m >= index)
+                .count();
+            if replicas >= quorum {
+                self.commit = index;
+                break;
+            }
+        }
+    }
+
+    pub fn step(&mut self, msg: Message) -> GraphResult<()> {
+        if msg.term() > self.state.term {
+            let leader = match msg {
+                Message::Append { leader, .. } => Some(leader),
+                _ => None,
+            };
+            self.become_follower(msg.term(), leader)?;
+        }
+        match msg {
+            Message::RequestVote { term, candidate, last_index, last_term } => {
+                let up_to_date = (last_term, last_index) >= (self.last_term(), self.last_index());
+                let granted = term == self.state.term
+                    && self
+                        .state
+                        .voted_for
+                        .map_or(true, |v| v == candidate)
+                    && up_to_date;
+                if granted {
+                    self.state.voted_for = Some(candidate);
+                    self.storage.save_hard_state(&self.state)?;
+                    self.reset_timeout();
+                }
+                let reply = Message::Vote { term: self.state.term, from: self.id, granted };
+                self.outbox.push((candidate, reply));
+            }
+            Message::Vote { term, from, granted } => {
+                if self.role == Role::Candidate && term == self.state.term && granted {
+                    self.votes.insert(from);
+                    if self.votes.len() >= self.quorum() {
+                        self.become_leader()?;
+                    }
+                }
+            }
+            Message::Append { term, leader, prev_index, prev_term, entries, commit } => {
+                if term < self.state.term {
+                    let reply = Message::AppendAck {
+                        term: self.state.term,
+                        from: self.id,
+                        success: false,
+                        match_index: 0,
+                    };
+                    self.outbox.push((leader, reply));
+                    return Ok(());
+                }
+                if self.role != Role::Follower || self.leader != Some(leader) {
+                    self.become_follower(term, Some(leader))?;
+                }
+                self.elapsed = 0;
+                let reply = if self.term_at(prev_index) != Some(prev_term) {
+                    let hint = prev_index
+                        .saturating_sub(1)
+                        .min(self.last_index());
+                    Message::AppendAck { term, from: self.id, success: false, match_index: hint }
+                } else {
+                    let last_new = prev_index + entries.len() as u64;
+                    self.append_entries(entries)?;
+                    self.commit = self.commit.max(commit.min(last_new));
+                    Message::AppendAck { term, from: self.id, success: true, match_index: last_new }
+                };
+                self.outbox.push((leader, reply));
+            }
+            Message::AppendAck { term, from, success, match_index } => {
+                if self.role != Role::Leader || term != self.state.term {
+                    return Ok(());
+                }
+                if success {
+                    let matched = self.match_index.entry(from).or_insert(0);
+                    if match_index > *matched {
+                        *matched = match_index;
+                        self.next_index.insert(from, match_index + 1);
+                        self.maybe_commit();
+                    }
+                } else {
+                    let next = self.next_index.entry(from).or_insert(1);
+                    *next = (*next - 1).min(match_index + 1).max(1);
+                    self.send_append(from);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn append_entries(&mut self, entries: Vec<Entry>) -> GraphResult<()> {
+        for (i, entry) in entries.iter().enumerate() {
+            match self.term_at(entry.index) {
+                Some(term) if term == entry.term => continue,
+                Some(_) => {
+                    // a conflict, the entries from it on are never committed;
+                    assert!(entry.index > self.commit, "committed entry {} is overwritten", entry.index);
+                    self.storage.truncate(entry.index)?;
+                    self.log.truncate(entry.index as usize - 1);
+                }
+                None => {}
+            }
+            self.storage.append(&entries[i..])?;
+            self.log.extend_from_slice(&entries[i..]);
+            break;
+        }
+        Ok(())
+    }
+
+    pub fn take_messages(&mut self) -> Vec<(NodeId, Message)> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    /// The entries committed but not yet taken, in order.
+    pub fn take_committed(&mut self) -> Vec<Entry> {
+        let committed = self.log[self.applied as usize..self.commit as usize].to_vec();
+        self.applied = self.commit;
+        committed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cluster(n: u64) -> HashMap<NodeId, RaftCore> {
+        let ids: Vec<NodeId> = (1..=n).collect();
+        ids.iter()
+            .map(|id| {
+                let core =
+                    RaftCore::new(*id, ids.clone(), Box::new(MemRaftStorage::default()), 10, 3).unwrap();
+                (*id, core)
+            })
+            .collect()
+    }
+
+    /// Deliver the messages among the nodes until there are none, the messages to `down` are dropped.
+    fn deliver(nodes: &mut HashMap<NodeId, RaftCore>, down: &[NodeId]) {
+        loop {
+            let mut msgs = Vec::new();
+            for core in nodes.values_mut() {
+                if !down.contains(&core.id()) {
+                    msgs.extend(core.take_messages());
+                }
+            }
+            if msgs.is_empty() {
+                return;
+            }
+            for (to, msg) in msgs {
+                if !down.contains(&to) {
+                    nodes.get_mut(&to).unwrap().step(msg).unwrap();
+                }
+            }
+        }
+    }
+
+    fn elect(nodes: &mut HashMap<NodeId, RaftCore>, down: &[NodeId]) -> NodeId {
+        for _ in 0..100 {
+            for core in nodes.values_mut() {
+                if !down.contains(&core.id()) {
+                    core.tick().unwrap();
+                }
+            }
+            deliver(nodes, down);
+            let leaders: Vec<NodeId> = nodes
+                .values()
+                .filter(|c| c.role() == Role::Leader && !down.contains(&c.id()))
+                .map(|c| c.id())
+                .collect();
+            if leaders.len() == 1 {
+                return leaders[0];
+            }
+        }
+        panic!("no leader is elected");
+    }
+
+    #[test]
+    fn test_raft_replication() {
+        let mut nodes = cluster(3);
+        let leader = elect(&mut nodes, &[]);
+        let (index, term) = nodes
+            .get_mut(&leader)
+            .unwrap()
+            .propose(b"a".to_vec())
+            .unwrap();
+        deliver(&mut nodes, &[]);
+        // followers learn the commit index by the next heartbeat;
+        for _ in 0..3 {
+            nodes.get_mut(&leader).unwrap().tick().unwrap();
+        }
+        deliver(&mut nodes, &[]);
+        for core in nodes.values_mut() {
+            assert_eq!(core.term_at(index), Some(term));
+            let data: Vec<Vec<u8>> = core
+                .take_committed()
+                .into_iter()
+                .map(|e| e.data)
+                .collect();
+            assert_eq!(data, vec![vec![], b"a".to_vec()]);
+        }
+        let follower = nodes
+            .keys()
+            .copied()
+            .find(|id| *id != leader)
+            .unwrap();
+        assert!(nodes
+            .get_mut(&follower)
+            .unwrap()
+            .propose(b"b".to_vec())
+            .is_err());
+
+        // a new leader is elected without the old one, with all the committed entries;
+        let new_leader = elect(&mut nodes, &[leader]);
+        assert_ne!(new_leader, leader);
+        let core = nodes.get_mut(&new_leader).unwrap();
+        assert!(core.term() > term);
+        assert_eq!(core.term_at(index), Some(term));
+        core.propose(b"c".to_vec()).unwrap();
+        deliver(&mut nodes, &[leader]);
+        assert!(nodes[&new_leader].commit_index() > index);
+
+        // the old leader catches up once it's back, after the entries it has taken;
+        for _ in 0..3 {
+            nodes
+                .get_mut(&new_leader)
+                .unwrap()
+                .tick()
+                .unwrap();
+        }
+        deliver(&mut nodes, &[]);
+        let old = nodes.get_mut(&leader).unwrap();
+        assert_eq!(old.role(), Role::Follower);
+        assert_eq!(old.leader(), Some(new_leader));
+        let data: Vec<Vec<u8>> = old
+            .take_committed()
+            .into_iter()
+            .map(|e| e.data)
+            .filter(|d| !d.is_empty())
+            .collect();
+        assert_eq!(data, vec![b"c".to_vec()]);
+    }
+}
@@ -0,0 +1,148 @@
+//! The replica of the meta raft group on a store node, which serves the raft messages of the peers by
+//! grpc. It's configured by `store.raft.node.id`, `store.raft.peers`, e.g. `1@host1:port1,2@host2:port2`
+//! including this node, and `store.raft.data.path` of the raft log.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use grpcio::{Environment, Server, ServerBuilder};
+
+use super::meta::{DdlApplier, MetaCommand, MetaStateMachine};
+use super::node::{GrpcTransport, RaftConfig, RaftNode, RaftService};
+use super::raft::NodeId;
+use super::storage::RocksRaftStorage;
+use super::Consensus;
+use crate::db::api::*;
+use crate::db::graph::partition::PartitionRouting;
+use crate::db::proto::raft_service_grpc::create_raft;
+
+pub struct MetaReplica {
+    node: RaftNode,
+    state_machine: Arc<MetaStateMachine>,
+    server: Server,
+}
+
+impl MetaReplica {
+    /// Start the replica if `store.raft.peers` is configured, otherwise the metadata is updated by the
+    /// external coordinator and `None` is returned.
+    pub fn start(options: &HashMap<String, String>, ddl_applier: DdlApplier) -> GraphResult<Option<Self>> {
+        let peers = match options.get("store.raft.peers") {
+            Some(peers) if !peers.trim().is_empty() => parse_peers(peers)?,
+            _ => return Ok(None),
+        };
+        let id: NodeId = get_option(options, "store.raft.node.id")?
+            .parse()
+            .map_err(|e| {
+                let msg = format!("parse store.raft.node.id failed: {}", e);
+                gen_graph_err!(GraphErrorCode::InvalidOperation, msg, start)
+            })?;
+        let port = peers
+            .get(&id)
+            .and_then(|addr| addr.rsplit(':').next())
+            .and_then(|port| port.parse::<u16>().ok())
+            .ok_or_else(|| {
+                let msg = format!("raft node {} is not in store.raft.peers", id);
+                gen_graph_err!(GraphErrorCode::InvalidOperation, msg, start)
+            })?;
+        let storage = RocksRaftStorage::open(get_option(options, "store.raft.data.path")?)?;
+
+        let mut node_ids = peers.keys().copied().collect::<Vec<_>>();
+        node_ids.sort();
+        let config = RaftConfig::new(id, node_ids);
+        let state_machine = Arc::new(MetaStateMachine::new(ddl_applier));
+        let node = RaftNode::new(
+            &config,
+            Box::new(storage),
+            Box::new(GrpcTransport::new(id, &peers)),
+            state_machine.clone(),
+        )?;
+        let env = Arc::new(Environment::new(1));
+        let mut server = ServerBuilder::new(env)
+            .register_service(create_raft(RaftService::new(node.clone())))
+            .bind("0.0.0.0", port)
+            .build()
+            .map_err(|e| {
+                let msg = format!("start raft service on port {} failed: {:?}", port, e);
+                gen_graph_err!(GraphErrorCode::InvalidOperation, msg, start)
+            })?;
+        server.start();
+        node.start(&config);
+        info!("raft node {} of the meta group {:?} is started on port {}", id, peers, port);
+        Ok(Some(MetaReplica { node, state_machine, server }))
+    }
+
+    pub fn state_machine(&self) -> &Arc<MetaStateMachine> {
+        &self.state_machine
+    }
+
+    pub fn consensus(&self) -> &dyn Consensus {
+        &self.node
+    }
+
+    /// Replicate the routing to the store nodes, where it's switched by the routing listeners.
+    pub fn propose_routing(&self, routing: &PartitionRouting, timeout: Duration) -> GraphResult<u64> {
+        MetaCommand::SwitchRouting(routing.to_json()).propose(&self.node, timeout)
+    }
+
+    /// Replicate the ddl batch to the store nodes, where it's written by the ddl applier.
+    pub fn propose_ddl(&self, si: SnapshotId, batch: Vec<u8>, timeout: Duration) -> GraphResult<u64> {
+        MetaCommand::Ddl { si, batch }.propose(&self.node, timeout)
+    }
+
+    pub fn stop(&mut self) {
+        self.node.stop();
+        if let Err(e) = futures::executor::block_on(self.server.shutdown()) {
+            warn!("shutdown raft service failed: {:?}", e);
+        }
+    }
+}
+
+fn get_option<'a>(options: &'a HashMap<String, String>, key: &str) -> GraphResult<&'a String> {
+    options.get(key).ok_or_else(|| {
+        let msg = format!("required config {} is missing", key);
+        gen_graph_err!(GraphErrorCode::InvalidOperation, msg, get_option)
+    })
+}
+
+/// Parse the peers in `id@host:port` separated by commas.
+fn parse_peers(peers: &str) -> GraphResult<HashMap<NodeId, String>> {
+    let mut res = HashMap::new();
+    for peer in peers
+        .split(',')
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+    {
+        let (id, addr) = peer
+            .split_once('@')
+            .and_then(|(id, addr)| id.parse::<NodeId>().ok().map(|id| (id, addr)))
+            .ok_or_else(|| {
+                let msg = format!("invalid raft peer {} in store.raft.peers", peer);
+                gen_graph_err!(GraphErrorCode::InvalidOperation, msg, parse_peers)
+            })?;
+        if res.insert(id, addr.to_owned()).is_some() {
+            let msg = format!("duplicated raft node {} in store.raft.peers", id);
+            return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, parse_peers));
+        }
+    }
+    if res.is_empty() {
+        let msg = "store.raft.peers is empty".to_owned();
+        return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, parse_peers));
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_peers() {
+        let peers = parse_peers("1@host1:5001, 2@host2:5002").unwrap();
+        assert_eq!(peers.get(&1), Some(&"host1:5001".to_owned()));
+        assert_eq!(peers.get(&2), Some(&"host2:5002".to_owned()));
+        assert!(parse_peers("1@host1:5001,1@host2:5002").is_err());
+        assert!(parse_peers("host1:5001").is_err());
+        assert!(parse_peers("").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::raft::{Entry, HardState, RaftStorage};
+use crate::db::api::*;
+use crate::db::storage::rocksdb::RocksDB;
+
+const STATE_KEY: &[u8] = b"raft#state";
+const LOG_PREFIX: &[u8] = b"raft#log#";
+
+/// The raft state and log in a dedicated rocksdb, the entries are keyed by their indexes in big
+/// endian so they are scanned in order.
+pub struct RocksRaftStorage {
+    db: Arc<RocksDB>,
+}
+
+impl RocksRaftStorage {
+    pub fn open(path: &str) -> GraphResult<Self> {
+        let mut options = HashMap::new();
+        options.insert("store.data.path".to_owned(), path.to_owned());
+        Ok(RocksRaftStorage { db: Arc::new(RocksDB::open(&options)?) })
+    }
+
+    fn log_key(index: u64) -> Vec<u8> {
+        let mut key = Vec::with_capacity(LOG_PREFIX.len() + 8);
+        key.extend_from_slice(LOG_PREFIX);
+        key.extend_from_slice(&index.to_be_bytes());
+        key
+    }
+}
+
+fn decode<'a, T: serde::Deserialize<'a>>(bytes: &'a [u8]) -> GraphResult<T> {
+    serde_json::from_slice(bytes).map_err(|e| {
+        let msg = format!("decode raft state failed: {}", e);
+        gen_graph_err!(GraphErrorCode::InvalidData, msg, decode)
+    })
+}
+
+fn encode<T: serde::Serialize>(value: &T) -> GraphResult<Vec<u8>> {
+    serde_json::to_vec(value).map_err(|e| {
+        let msg = format!("encode raft state failed: {}", e);
+        gen_graph_err!(GraphErrorCode::InvalidData, msg, encode)
+    })
+}
+
+impl RaftStorage for RocksRaftStorage {
+    fn load(&self) -> GraphResult<(HardState, Vec<Entry>)> {
+        let state = match self.db.get(STATE_KEY)? {
+            Some(res) => decode(res.as_bytes())?,
+            None => HardState::default(),
+        };
+        let mut entries = Vec::new();
+        let mut iter = self.db.scan_prefix(LOG_PREFIX)?;
+        while let Some((_, v)) = iter.next() {
+            let entry: Entry = decode(v)?;
+            if entry.index != entries.len() as u64 + 1 {
+                let msg = format!("raft log is not continuous at {}", entry.index);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, load));
+            }
+            entries.push(entry);
+        }
+        Ok((state, entries))
+    }
+
+    fn save_hard_state(&mut self, state: &HardState) -> GraphResult<()> {
+        self.db.put(STATE_KEY, &encode(state)?)
+    }
+
+    fn append(&mut self, entries: &[Entry]) -> GraphResult<()> {
+        for entry in entries {
+            self.db
+                .put(&Self::log_key(entry.index), &encode(entry)?)?;
+        }
+        Ok(())
+    }
+
+    fn truncate(&mut self, index: u64) -> GraphResult<()> {
+        self.db
+            .delete_range(&Self::log_key(index), &Self::log_key(u64::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::util::fs;
+
+    #[test]
+    fn test_rocks_raft_storage() {
+        let path = "test_rocks_raft_storage";
+        fs::rmr(path).unwrap();
+        {
+            let mut storage = RocksRaftStorage::open(path).unwrap();
+            let state = HardState { term: 2, voted_for: Some(1) };
+            storage.save_hard_state(&state).unwrap();
+            let entries: Vec<Entry> = (1..=3)
+                .map(|index| Entry { term: 1, index, data: vec![index as u8] })
+                .collect();
+            storage.append(&entries).unwrap();
+            storage.truncate(3).unwrap();
+        }
+        let storage = RocksRaftStorage::open(path).unwrap();
+        let (state, entries) = storage.load().unwrap();
+        assert_eq!(state, HardState { term: 2, voted_for: Some(1) });
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| e.index)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        drop(storage);
+        fs::rmr(path).unwrap();
+    }
+}
@@ -0,0 +1,375 @@
+//! Build the sst files of a data load directly, for the initial loads which are too large for the
+//! write queue: the encoded vertices and edges of a partition are buffered and sorted in memory,
+//! spilled as sorted runs when the buffer is full, and merged into one sst file, which is ingested
+//! by `GraphStore::commit_data_load` without going through the memtables and the wal.
+//!
+//! The data load should be prepared first, i.e. `prepare_data_load` assigns the table of the target,
+//! and the entries are written at the start of the table, so they are visible once it's online.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::PathBuf;
+
+use rocksdb::{Options, SstFileWriter};
+
+use super::bin::{edge_key, vertex_key};
+use super::codec::Encoder;
+use crate::api::PartitionId;
+use crate::db::api::*;
+use crate::db::import::mutation::{Mutation, MutationOp};
+use crate::db::import::record_mapping::{CompiledMapping, MappedRecord};
+
+/// The bytes of entries buffered before they are spilled as a sorted run by default.
+pub const DEFAULT_BUFFER_BYTES: usize = 256 << 20;
+
+/// The sst file of a partition, in the layout `commit_data_load` ingests from.
+pub fn sst_file_name(partition_id: PartitionId) -> String {
+    format!("part-r-{:0>5}.sst", partition_id)
+}
+
+/// Build the sst file of a partition in `dir`, the entries can be added in any order, and the last
+/// one of the same key wins.
+pub struct PartitionSstBuilder {
+    dir: PathBuf,
+    partition_id: PartitionId,
+    buffer: Vec<(Vec<u8>, Vec<u8>)>,
+    buffer_bytes: usize,
+    max_buffer_bytes: usize,
+    runs: Vec<PathBuf>,
+    count: u64,
+}
+
+impl PartitionSstBuilder {
+    pub fn new(dir: &str, partition_id: PartitionId, max_buffer_bytes: usize) -> GraphResult<Self> {
+        fs::create_dir_all(dir).map_err(|e| io_err(e, "create dir", dir))?;
+        Ok(PartitionSstBuilder {
+            dir: PathBuf::from(dir),
+            partition_id,
+            buffer: Vec::new(),
+            buffer_bytes: 0,
+            max_buffer_bytes,
+            runs: Vec::new(),
+            count: 0,
+        })
+    }
+
+    /// Add a vertex of the table assigned by `prepare_data_load`, `encoder` is of the label at the
+    /// snapshot of the load, see `GraphStore::get_data_load_encoder`.
+    pub fn add_vertex(
+        &mut self, table_id: i64, encoder: &Encoder, id: VertexId, properties: &dyn PropertyMap,
+    ) -> GraphResult<()> {
+        let mut buf = Vec::new();
+        encoder.encode(properties, &mut buf)?;
+        self.put(vertex_key(table_id, id, 0).to_vec(), buf)
+    }
+
+    /// Add an edge of both directions, so the partition should own both of its endpoints, or the
+    /// edge should be added to the partitions of its source and destination respectively with
+    /// `add_edge_direction`.
+    pub fn add_edge(
+        &mut self, table_id: i64, encoder: &Encoder, id: &EdgeId, properties: &dyn PropertyMap,
+    ) -> GraphResult<()> {
+        let mut buf = Vec::new();
+        encoder.encode(properties, &mut buf)?;
+        self.put(edge_key(table_id, *id, EdgeDirection::Out, 0).to_vec(), buf.clone())?;
+        self.put(edge_key(table_id, *id, EdgeDirection::In, 0).to_vec(), buf)
+    }
+
+    /// Add an edge of one direction, i.e. `Out` in the partition of its source and `In` in that of
+    /// its destination. The `In` of the labels stored only in the out direction is their reverse index,
+    /// which needs no property, see `reverse_index`.
+    pub fn add_edge_direction(
+        &mut self, table_id: i64, encoder: &Encoder, id: &EdgeId, direction: EdgeDirection,
+        properties: &dyn PropertyMap,
+    ) -> GraphResult<()> {
+        let mut buf = Vec::new();
+        encoder.encode(properties, &mut buf)?;
+        self.put(edge_key(table_id, *id, direction, 0).to_vec(), buf)
+    }
+
+    /// Add a record mapped by `mapping`, with the ids derived like those of the streaming ingestion,
+    /// see `CompiledMapping::to_mutation`. An edge is added in both directions, or in `direction`
+    /// only if it's set, like `add_edge` and `add_edge_direction` respectively.
+    pub fn add_mapped_record(
+        &mut self, table_id: i64, encoder: &Encoder, mapping: &CompiledMapping, record: MappedRecord,
+        direction: Option<EdgeDirection>,
+    ) -> GraphResult<()> {
+        match mapping.to_mutation(record, MutationOp::Insert)? {
+            Mutation::Vertex { id, properties, .. } => self.add_vertex(table_id, encoder, id, &properties),
+            Mutation::Edge { id, properties, .. } => match direction {
+                Some(direction) => self.add_edge_direction(table_id, encoder, &id, direction, &properties),
+                None => self.add_edge(table_id, encoder, &id, &properties),
+            },
+        }
+    }
+
+    pub fn put(&mut self, key: Vec<u8>, value: Vec<u8>) -> GraphResult<()> {
+        self.buffer_bytes += key.len() + value.len();
+        self.buffer.push((key, value));
+        self.count += 1;
+        if self.buffer_bytes >= self.max_buffer_bytes {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Write the sst file, return its path, or `None` if nothing is added.
+    pub fn finish(mut self) -> GraphResult<Option<String>> {
+        let path = self.dir.join(sst_file_name(self.partition_id));
+        let path_str = path.to_string_lossy().to_string();
+        let opts = Options::default();
+        let mut writer = SstFileWriter::create(&opts);
+        let mut written = 0;
+        if self.runs.is_empty() {
+            let entries = sort_entries(std::mem::take(&mut self.buffer));
+            if entries.is_empty() {
+                return Ok(None);
+            }
+            writer
+                .open(&path)
+                .map_err(|e| sst_err(e, &path_str))?;
+            for (key, value) in entries {
+                writer
+                    .put(&key, &value)
+                    .map_err(|e| sst_err(e, &path_str))?;
+                written += 1;
+            }
+        } else {
+            self.spill()?;
+            let mut merger = RunMerger::open(&self.runs)?;
+            writer
+                .open(&path)
+                .map_err(|e| sst_err(e, &path_str))?;
+            while let Some((key, value)) = merger.next()? {
+                writer
+                    .put(&key, &value)
+                    .map_err(|e| sst_err(e, &path_str))?;
+                written += 1;
+            }
+        }
+        writer
+            .finish()
+            .map_err(|e| sst_err(e, &path_str))?;
+        info!(
+            "built sst file {} of {} entries from {} added in {} runs",
+            path_str,
+            written,
+            self.count,
+            self.runs.len()
+        );
+        Ok(Some(path_str))
+    }
+
+    // write the buffer sorted as a run of `(key_len: u32, value_len: u32, key, value)*`;
+    fn spill(&mut self) -> GraphResult<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let entries = sort_entries(std::mem::take(&mut self.buffer));
+        self.buffer_bytes = 0;
+        let path = self
+            .dir
+            .join(format!(".part-{}.run-{}", self.partition_id, self.runs.len()));
+        let path_str = path.to_string_lossy().to_string();
+        let file = File::create(&path).map_err(|e| io_err(e, "create run", &path_str))?;
+        let mut writer = BufWriter::new(file);
+        for (key, value) in entries {
+            let res = writer
+                .write_all(&(key.len() as u32).to_be_bytes())
+                .and_then(|_| writer.write_all(&(value.len() as u32).to_be_bytes()))
+                .and_then(|_| writer.write_all(&key))
+                .and_then(|_| writer.write_all(&value));
+            res.map_err(|e| io_err(e, "write run", &path_str))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| io_err(e, "write run", &path_str))?;
+        self.runs.push(path);
+        Ok(())
+    }
+}
+
+impl Drop for PartitionSstBuilder {
+    fn drop(&mut self) {
+        for run in self.runs.iter() {
+            let _ = fs::remove_file(run);
+        }
+    }
+}
+
+// sort by the keys and keep the last entry of the same key, the sort is stable;
+fn sort_entries(mut entries: Vec<(Vec<u8>, Vec<u8>)>) -> Vec<(Vec<u8>, Vec<u8>)> {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut ret: Vec<(Vec<u8>, Vec<u8>)> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        match ret.last_mut() {
+            Some(last) if last.0 == entry.0 => *last = entry,
+            _ => ret.push(entry),
+        }
+    }
+    ret
+}
+
+struct RunReader {
+    path: String,
+    reader: BufReader<File>,
+}
+
+impl RunReader {
+    fn next(&mut self) -> GraphResult<Option<(Vec<u8>, Vec<u8>)>> {
+        let mut header = [0u8; 8];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(io_err(e, "read run", &self.path)),
+        }
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&header[0..4]);
+        let mut key = vec![0; u32::from_be_bytes(len) as usize];
+        len.copy_from_slice(&header[4..8]);
+        let mut value = vec![0; u32::from_be_bytes(len) as usize];
+        self.reader
+            .read_exact(&mut key)
+            .and_then(|_| self.reader.read_exact(&mut value))
+            .map_err(|e| io_err(e, "read run", &self.path))?;
+        Ok(Some((key, value)))
+    }
+}
+
+// the head of a run, ordered by the key, then by the run so the later one wins;
+struct Head {
+    key: Vec<u8>,
+    value: Vec<u8>,
+    run: usize,
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then(other.run.cmp(&self.run))
+    }
+}
+
+struct RunMerger {
+    readers: Vec<RunReader>,
+    heap: BinaryHeap<Reverse<Head>>,
+}
+
+impl RunMerger {
+    fn open(runs: &[PathBuf]) -> GraphResult<Self> {
+        let mut merger = RunMerger { readers: Vec::with_capacity(runs.len()), heap: BinaryHeap::new() };
+        for (run, path) in runs.iter().enumerate() {
+            let path = path.to_string_lossy().to_string();
+            let file = File::open(&path).map_err(|e| io_err(e, "open run", &path))?;
+            merger
+                .readers
+                .push(RunReader { path, reader: BufReader::new(file) });
+            merger.advance(run)?;
+        }
+        Ok(merger)
+    }
+
+    fn advance(&mut self, run: usize) -> GraphResult<()> {
+        if let Some((key, value)) = self.readers[run].next()? {
+            self.heap
+                .push(Reverse(Head { key, value, run }));
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> GraphResult<Option<(Vec<u8>, Vec<u8>)>> {
+        let head = match self.heap.pop() {
+            Some(Reverse(head)) => head,
+            None => return Ok(None),
+        };
+        self.advance(head.run)?;
+        // the same key of the earlier runs are overwritten;
+        while self
+            .heap
+            .peek()
+            .map_or(false, |Reverse(next)| next.key == head.key)
+        {
+            let Reverse(stale) = self.heap.pop().unwrap();
+            self.advance(stale.run)?;
+        }
+        Ok(Some((head.key, head.value)))
+    }
+}
+
+fn io_err(e: std::io::Error, op: &str, path: &str) -> GraphError {
+    let msg = format!("{} {} failed: {}", op, path, e);
+    gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+}
+
+fn sst_err(e: rocksdb::Error, path: &str) -> GraphError {
+    let msg = format!("write sst file {} failed: {}", path, e.into_string());
+    gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::db::storage::rocksdb::RocksDB;
+    use crate::db::util::fs as util_fs;
+
+    #[test]
+    fn test_partition_sst_builder() {
+        let dir = "test_partition_sst_builder";
+        util_fs::rmr(dir).unwrap();
+        {
+            let builder = PartitionSstBuilder::new(dir, 1, 64).unwrap();
+            assert!(builder.finish().unwrap().is_none());
+
+            // spilled every 8 entries, with the later entries overwriting the earlier ones;
+            let mut builder = PartitionSstBuilder::new(dir, 1, 64).unwrap();
+            for round in 0..3u8 {
+                for i in (0..10u8).rev() {
+                    builder
+                        .put(vec![b'k', i], vec![round; 6])
+                        .unwrap();
+                }
+            }
+            builder.put(vec![b'k', 3], vec![9]).unwrap();
+            assert!(builder.runs.len() > 1);
+            let path = builder.finish().unwrap().unwrap();
+            assert!(path.ends_with("part-r-00001.sst"));
+            assert_eq!(fs::read_dir(dir).unwrap().count(), 1);
+
+            let mut config = HashMap::new();
+            config.insert("store.data.path".to_owned(), format!("{}/db", dir));
+            let db = RocksDB::open(&config).unwrap();
+            db.load(&[path.as_str()]).unwrap();
+            let mut iter = db.scan_prefix(b"k").unwrap();
+            for i in 0..10u8 {
+                let (k, v) = iter.next().unwrap();
+                assert_eq!(k, &[b'k', i][..]);
+                if i == 3 {
+                    assert_eq!(v, &[9][..]);
+                } else {
+                    assert_eq!(v, &[2; 6][..]);
+                }
+            }
+            assert!(iter.next().is_none());
+        }
+        util_fs::rmr(dir).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! Map the external string ids of vertices to numeric vertex ids, by a persistent dictionary of each
+//! label kept in the meta of the partition owning the external id, see `external_id_key`.
+//!
+//! The ids are allocated in batches, the end of the last batch of each label is persisted before
+//! any id of it is handed out, so an id is never reused after a restart while the ids left in the
+//! batch are skipped. The ids allocated by the partition `p` of `n` are equal to `p` modulo `n`, so
+//! the vertices are routed to the partition owning their external ids, and the ids allocated by
+//! different partitions never conflict.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::get_vertex_id_by_primary_keys;
+use super::meta::meta_key;
+use crate::db::api::*;
+use crate::db::common::bytes::transform;
+use crate::db::storage::rocksdb::RocksDB;
+
+pub const DEFAULT_BATCH_SIZE: i64 = 1024;
+
+/// The key routing the external id to the partition owning its mapping, which is the vertex id of
+/// the external id as a single string primary key.
+pub fn external_id_key(label: LabelId, external_id: &str) -> VertexId {
+    get_vertex_id_by_primary_keys(label, std::iter::once(&external_id.as_bytes().to_vec()))
+}
+
+pub struct IdMapping {
+    storage: Arc<RocksDB>,
+    partition_id: i64,
+    partition_count: i64,
+    batch_size: i64,
+    // the next sequence and the end of the batch allocated of each label, held by the allocation so
+    // that an external id is never mapped twice;
+    batches: Mutex<HashMap<LabelId, (i64, i64)>>,
+}
+
+impl IdMapping {
+    pub fn new(storage: Arc<RocksDB>, partition_id: u32, partition_count: u32, batch_size: i64) -> Self {
+        assert!(
+            partition_id < partition_count,
+            "invalid partition {} of {}",
+            partition_id,
+            partition_count
+        );
+        IdMapping {
+            storage,
+            partition_id: partition_id as i64,
+            partition_count: partition_count as i64,
+            batch_size: batch_size.max(1),
+            batches: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The vertex ids of the external ids of the label, the ids are allocated for those not mapped.
+    pub fn get_or_allocate(&self, label: LabelId, external_ids: &[&str]) -> GraphResult<Vec<VertexId>> {
+        let mut batches = self.batches.lock().unwrap();
+        let mut ids = Vec::with_capacity(external_ids.len());
+        for external_id in external_ids {
+            if external_id.is_empty() {
+                let msg = format!("empty external id of label {}", label);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, get_or_allocate, label));
+            }
+            let id = match self.lookup(label, external_id)? {
+                Some(id) => id,
+                None => {
+                    let id = self.allocate(&mut batches, label)?;
+                    // the reverse mapping is written first, the external id is mapped once the
+                    // forward one is written;
+                    let reverse = reverse_key(label, id);
+                    res_unwrap!(
+                        self.storage
+                            .put(&reverse, external_id.as_bytes()),
+                        get_or_allocate
+                    )?;
+                    let forward = forward_key(label, external_id);
+                    let val = transform::i64_to_vec(id.to_be());
+                    res_unwrap!(self.storage.put(&forward, &val), get_or_allocate)?;
+                    id
+                }
+            };
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+
+    pub fn lookup(&self, label: LabelId, external_id: &str) -> GraphResult<Option<VertexId>> {
+        let key = forward_key(label, external_id);
+        match res_unwrap!(self.storage.get(&key), lookup, label, external_id)? {
+            Some(v) => {
+                let id = res_unwrap!(transform::bytes_to_i64(v.as_bytes()), lookup, label, external_id)?;
+                Ok(Some(id.to_be()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The external id of the vertex, if its id is allocated by the mapping.
+    pub fn reverse_lookup(&self, label: LabelId, id: VertexId) -> GraphResult<Option<String>> {
+        let key = reverse_key(label, id);
+        match res_unwrap!(self.storage.get(&key), reverse_lookup, label, id)? {
+            Some(v) => {
+                let external_id =
+                    res_unwrap!(transform::bytes_to_str(v.as_bytes()), reverse_lookup, label, id)?;
+                Ok(Some(external_id.to_owned()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn allocate(
+        &self, batches: &mut HashMap<LabelId, (i64, i64)>, label: LabelId,
+    ) -> GraphResult<VertexId> {
+        let (next, end) = batches.entry(label).or_insert((0, 0));
+        if *next >= *end {
+            let key = batch_end_key(label);
+            let start = match res_unwrap!(self.storage.get(&key), allocate, label)? {
+                Some(v) => res_unwrap!(transform::bytes_to_i64(v.as_bytes()), allocate, label)?.to_be(),
+                None => 0,
+            };
+            let max_seq = (i64::MAX - self.partition_id) / self.partition_count;
+            if start >= max_seq {
+                let msg = format!("vertex ids of label {} are used up", label);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, allocate, label));
+            }
+            let batch_end = start
+                .saturating_add(self.batch_size)
+                .min(max_seq);
+            res_unwrap!(
+                self.storage
+                    .put(&key, &transform::i64_to_vec(batch_end.to_be())),
+                allocate,
+                label
+            )?;
+            *next = start;
+            *end = batch_end;
+        }
+        let seq = *next;
+        *next += 1;
+        Ok(seq * self.partition_count + self.partition_id)
+    }
+}
+
+fn forward_key(label: LabelId, external_id: &str) -> Vec<u8> {
+    meta_key(&format!("ExternalId#{}#{}", label, external_id))
+}
+
+fn reverse_key(label: LabelId, id: VertexId) -> Vec<u8> {
+    meta_key(&format!("InternalId#{}#{}", label, id))
+}
+
+fn batch_end_key(label: LabelId) -> Vec<u8> {
+    meta_key(&format!("ExternalIdBatchEnd#{}", label))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::util::fs;
+
+    #[test]
+    fn test_id_mapping() {
+        let path = "test_id_mapping";
+        fs::rmr(path).unwrap();
+        {
+            let mut options = HashMap::new();
+            options.insert("store.data.path".to_owned(), path.to_owned());
+            let storage = Arc::new(RocksDB::open(&options).unwrap());
+            let mapping = IdMapping::new(storage.clone(), 1, 4, 2);
+            let ids = mapping
+                .get_or_allocate(1, &["a", "b", "a", "c"])
+                .unwrap();
+            assert_eq!(ids, vec![1, 5, 1, 9]);
+            // the labels are mapped separately;
+            assert_eq!(mapping.get_or_allocate(2, &["a"]).unwrap(), vec![1]);
+            assert_eq!(mapping.lookup(1, "b").unwrap(), Some(5));
+            assert_eq!(mapping.lookup(1, "d").unwrap(), None);
+            assert_eq!(mapping.reverse_lookup(1, 9).unwrap(), Some("c".to_owned()));
+            assert_eq!(mapping.reverse_lookup(1, 13).unwrap(), None);
+            assert!(mapping.get_or_allocate(1, &[""]).is_err());
+            drop(mapping);
+
+            // the ids left in the batch of label 1, i.e. the sequence 3, are skipped after a restart;
+            let mapping = IdMapping::new(storage, 1, 4, 2);
+            assert_eq!(mapping.get_or_allocate(1, &["c", "d"]).unwrap(), vec![9, 17]);
+        }
+        fs::rmr(path).unwrap();
+    }
+}
@@ -1,11 +1,13 @@
 use std::sync::Arc;
 
-use crate::db::api::{EdgeDirection, EdgeId, GraphResult, Records, SnapshotId, VertexId};
+use crate::db::api::{EdgeDirection, EdgeId, GraphErrorCode, GraphResult, Records, SnapshotId, VertexId};
+use crate::db::common::bytes::util::{UnsafeBytesReader, UnsafeBytesWriter};
 use crate::db::graph::bin::{
     edge_prefix, edge_table_prefix_key, parse_edge_key, parse_vertex_key, vertex_table_prefix_key,
 };
 use crate::db::graph::codec::get_codec_version;
 use crate::db::graph::entity::{RocksEdgeImpl, RocksVertexImpl};
+use crate::db::graph::table_manager::TableId;
 use crate::db::graph::types::{EdgeInfo, EdgeKindInfo, VertexTypeInfo};
 use crate::db::storage::rocksdb::RocksDB;
 
@@ -211,3 +213,42 @@ impl IntoIterator for EdgeKindScan {
         return Box::new(::std::iter::empty());
     }
 }
+
+/// A page of the adjacency of a vertex, see `GraphStore::get_edges_page`.
+pub struct EdgePage {
+    pub edges: Vec<RocksEdgeImpl>,
+    /// The opaque token to read the next page from, or `None` if it's the last page.
+    pub next_token: Option<Vec<u8>>,
+}
+
+impl EdgePage {
+    /// Whether there are more edges after the page, which are truncated by the page size.
+    pub fn is_truncated(&self) -> bool {
+        self.next_token.is_some()
+    }
+}
+
+/// The token of the page ending with the edge in the table, which is read after it, as the edges
+/// are read in the order of the tables and the edge keys.
+pub fn page_token(table_id: TableId, edge_id: EdgeId) -> Vec<u8> {
+    let mut ret = vec![0u8; 32];
+    let mut writer = UnsafeBytesWriter::new(&mut ret);
+    writer.write_i64(0, table_id.to_be());
+    writer.write_i64(8, edge_id.src_id.to_be());
+    writer.write_i64(16, edge_id.dst_id.to_be());
+    writer.write_i64(24, edge_id.inner_id.to_be());
+    ret
+}
+
+/// return (table_id, edge_id)
+pub fn parse_page_token(token: &[u8]) -> GraphResult<(TableId, EdgeId)> {
+    if token.len() != 32 {
+        let msg = format!("invalid page token, token len is {}", token.len());
+        return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, parse_page_token));
+    }
+    let reader = UnsafeBytesReader::new(token);
+    let table_id = reader.read_i64(0).to_be();
+    let edge_id =
+        EdgeId::new(reader.read_i64(8).to_be(), reader.read_i64(16).to_be(), reader.read_i64(24).to_be());
+    Ok((table_id, edge_id))
+}
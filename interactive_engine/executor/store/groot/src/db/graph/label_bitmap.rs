@@ -0,0 +1,77 @@
+//! The bitmap of the vertex tables of the partition holding any vertex, by which a scan of multiple
+//! labels skips the labels of no vertex in the partition without opening their iterators, see
+//! `GraphStore::scan_vertices`.
+//!
+//! A table is marked as a vertex is written to it, and a table not marked is probed by a seek to its
+//! first key once. A mark is never cleared but with its table, as the vertices deleted are still visible
+//! at the older snapshots. Nothing is persisted, as a probe is a single seek; a secondary catching up
+//! with its primary, or a store ingesting the vertices of a bulk load, probes again only the tables
+//! found empty.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use super::bin::vertex_table_prefix_key;
+use super::table_manager::TableId;
+use crate::db::api::*;
+use crate::db::storage::rocksdb::RocksDB;
+
+pub struct LabelBitmap {
+    storage: Arc<RocksDB>,
+    // whether the tables probed or written hold any vertex
+    occupied: RwLock<HashMap<TableId, bool>>,
+    // bumped as the tables found empty are forgotten, so that a probe started before is not kept
+    epoch: AtomicU64,
+}
+
+impl LabelBitmap {
+    pub fn new(storage: Arc<RocksDB>) -> Self {
+        LabelBitmap { storage, occupied: RwLock::new(HashMap::new()), epoch: AtomicU64::new(0) }
+    }
+
+    /// Whether any vertex is written to the table, at any snapshot.
+    pub fn is_occupied(&self, table_id: TableId) -> GraphResult<bool> {
+        if let Some(occupied) = self.occupied.read().unwrap().get(&table_id) {
+            return Ok(*occupied);
+        }
+        // probed out of the lock, which the writes marking the tables wait for
+        let epoch = self.epoch.load(Ordering::Acquire);
+        let found = self
+            .storage
+            .new_scan(&vertex_table_prefix_key(table_id))?
+            .next()
+            .is_some();
+        let mut occupied = self.occupied.write().unwrap();
+        if self.epoch.load(Ordering::Acquire) != epoch {
+            return Ok(found);
+        }
+        // a table marked by a write since is kept marked
+        Ok(*occupied.entry(table_id).or_insert(found))
+    }
+
+    /// Mark the table of the vertex to be written.
+    pub fn mark(&self, table_id: TableId) {
+        if let Some(true) = self.occupied.read().unwrap().get(&table_id) {
+            return;
+        }
+        self.occupied
+            .write()
+            .unwrap()
+            .insert(table_id, true);
+    }
+
+    /// Forget the table garbage collected, or brought online by a bulk load.
+    pub fn remove(&self, table_id: TableId) {
+        let mut occupied = self.occupied.write().unwrap();
+        occupied.remove(&table_id);
+        self.epoch.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Forget the tables found empty, to probe them again, e.g. after catching up with the primary.
+    pub fn forget_empty(&self) {
+        let mut occupied = self.occupied.write().unwrap();
+        occupied.retain(|_, occupied| *occupied);
+        self.epoch.fetch_add(1, Ordering::AcqRel);
+    }
+}
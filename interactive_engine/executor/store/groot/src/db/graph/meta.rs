@@ -359,6 +359,118 @@ impl Meta {
         Ok(())
     }
 
+    /// Persist the source offsets consumed up to `si` by an ingestion source like a kafka topic.
+    pub fn write_ingest_offsets(&self, source: &str, offsets: &IngestOffsets) -> GraphResult<()> {
+        let key = _gen_key(&format!("IngestOffsets#{}", source));
+        let v = serde_json::to_vec(offsets).map_err(|e| {
+            let msg = format!("encode ingest offsets failed: {:?}", e);
+            gen_graph_err!(GraphErrorCode::InvalidData, msg, write_ingest_offsets, source)
+        })?;
+        res_unwrap!(self.store.put(&key, &v), write_ingest_offsets, source)
+    }
+
+    pub fn read_ingest_offsets(&self, source: &str) -> GraphResult<Option<IngestOffsets>> {
+        let key = _gen_key(&format!("IngestOffsets#{}", source));
+        match res_unwrap!(self.store.get(&key), read_ingest_offsets, source)? {
+            Some(v) => {
+                let offsets = serde_json::from_slice(v.as_bytes()).map_err(|e| {
+                    let msg = format!("decode ingest offsets failed: {:?}", e);
+                    gen_graph_err!(GraphErrorCode::InvalidData, msg, read_ingest_offsets, source)
+                })?;
+                Ok(Some(offsets))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Remember that the mutations identified by `dedup_ids` have been written at `si`. Entries are
+    /// indexed by `ts_ms` as well, so that `purge_dedup_ids` doesn't need to scan the whole log.
+    pub fn write_dedup_ids(&self, si: SnapshotId, dedup_ids: &[String], ts_ms: u64) -> GraphResult<()> {
+        let entry = DedupEntry { si, ts_ms };
+        let v = serde_json::to_vec(&entry).map_err(|e| {
+            let msg = format!("encode dedup entry failed: {:?}", e);
+            gen_graph_err!(GraphErrorCode::InvalidData, msg, write_dedup_ids, si)
+        })?;
+        for dedup_id in dedup_ids {
+            let key = _gen_key(&format!("DedupId#{}", dedup_id));
+            res_unwrap!(self.store.put(&key, &v), write_dedup_ids, si, dedup_id)?;
+            let index_key = _gen_key(&format!("DedupTs#{:020}#{}", ts_ms, dedup_id));
+            res_unwrap!(self.store.put(&index_key, &[]), write_dedup_ids, si, dedup_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_dedup_id(&self, dedup_id: &str) -> GraphResult<Option<DedupEntry>> {
+        let key = _gen_key(&format!("DedupId#{}", dedup_id));
+        match res_unwrap!(self.store.get(&key), read_dedup_id, dedup_id)? {
+            Some(v) => {
+                let entry = serde_json::from_slice(v.as_bytes()).map_err(|e| {
+                    let msg = format!("decode dedup entry failed: {:?}", e);
+                    gen_graph_err!(GraphErrorCode::InvalidData, msg, read_dedup_id, dedup_id)
+                })?;
+                Ok(Some(entry))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Drop the dedup entries written before `before_ms`, returns the number of dropped entries.
+    pub fn purge_dedup_ids(&self, before_ms: u64) -> GraphResult<usize> {
+        let prefix = _gen_key("DedupTs#");
+        let mut expired = Vec::new();
+        {
+            let mut iter = res_unwrap!(self.store.scan_prefix(&prefix), purge_dedup_ids, before_ms)?;
+            while let Some((k, _)) = iter.next() {
+                let key = res_unwrap!(transform::bytes_to_str(&k[8..]), purge_dedup_ids, before_ms)?;
+                let items: Vec<&str> = key.splitn(3, '#').collect();
+                let ts_ms = items
+                    .get(1)
+                    .and_then(|ts| ts.parse::<u64>().ok());
+                match (ts_ms, items.get(2)) {
+                    (Some(ts_ms), Some(dedup_id)) if ts_ms < before_ms => {
+                        expired.push((k.to_vec(), dedup_id.to_string()))
+                    }
+                    (Some(_), Some(_)) => break,
+                    _ => {
+                        let msg = format!("invalid dedup index key {}", key);
+                        return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, purge_dedup_ids));
+                    }
+                }
+            }
+        }
+        for (index_key, dedup_id) in expired.iter() {
+            let key = _gen_key(&format!("DedupId#{}", dedup_id));
+            res_unwrap!(self.store.delete(&key), purge_dedup_ids, before_ms)?;
+            res_unwrap!(self.store.delete(index_key), purge_dedup_ids, before_ms)?;
+        }
+        Ok(expired.len())
+    }
+
+    /// Remember that the table is placed in the column family, see `RocksDB::place_table`.
+    pub fn write_table_placement(&self, table_id: TableId, cf: &str) -> GraphResult<()> {
+        let key = _gen_key(&format!("TablePlacement#{}", table_id));
+        res_unwrap!(self.store.put(&key, cf.as_bytes()), write_table_placement, table_id, cf)
+    }
+
+    pub fn read_table_placements(&self) -> GraphResult<Vec<(TableId, String)>> {
+        let prefix = _gen_key("TablePlacement#");
+        let mut placements = Vec::new();
+        let mut iter = res_unwrap!(self.store.scan_prefix(&prefix), read_table_placements)?;
+        while let Some((k, v)) = iter.next() {
+            let table_id =
+                transform::bytes_to_str(&k[prefix.len()..]).and_then(|s| parse_str::<TableId>(s));
+            let cf = transform::bytes_to_str(v);
+            match (table_id, cf) {
+                (Ok(table_id), Ok(cf)) => placements.push((table_id, cf.to_owned())),
+                _ => {
+                    let msg = format!("invalid table placement {:?}", k);
+                    return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, read_table_placements));
+                }
+            }
+        }
+        Ok(placements)
+    }
+
     pub fn _gen_next_table_id(&self) -> GraphResult<TableId> {
         let key = _gen_key("NextTableId");
         let table_id = match res_unwrap!(self.store.get(&key), get_next_table_id)? {
@@ -399,6 +511,21 @@ fn _gen_key(key: &str) -> Vec<u8> {
     buf
 }
 
+/// Offsets of an ingestion source committed together with the snapshot they were applied at.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IngestOffsets {
+    pub si: SnapshotId,
+    /// (partition, next offset to consume)
+    pub offsets: Vec<(i32, i64)>,
+}
+
+/// A mutation identified by a client supplied dedup id, written at `si` at `ts_ms`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DedupEntry {
+    pub si: SnapshotId,
+    pub ts_ms: u64,
+}
+
 trait ItemCommon: Sized {
     fn from_kv(k: &[u8], v: &[u8]) -> GraphResult<Self>;
     fn prefix() -> &'static str;
@@ -448,7 +575,7 @@ fn common_parse_key<'a>(k: &'a [u8], prefix: &str, size: usize) -> GraphResult<V
     Err(err)
 }
 
-fn meta_key(key: &str) -> Vec<u8> {
+pub(super) fn meta_key(key: &str) -> Vec<u8> {
     let bytes = key.as_bytes();
     let mut ret = Vec::with_capacity(8 + key.len());
     let prefix = transform::i64_to_arr(META_TABLE_ID.to_be());
@@ -869,6 +996,53 @@ mod tests {
         fs::rmr(path).unwrap();
     }
 
+    #[test]
+    fn test_ingest_offsets() {
+        let path = "test_meta_ingest_offsets";
+        fs::rmr(path).unwrap();
+        {
+            let mut config = HashMap::new();
+            config.insert("store.data.path".to_owned(), path.to_owned());
+            let db = RocksDB::open(&config).unwrap();
+            let meta = Meta::new(Arc::new(db));
+            assert!(meta
+                .read_ingest_offsets("kafka#g#t")
+                .unwrap()
+                .is_none());
+            let offsets = IngestOffsets { si: 10, offsets: vec![(0, 100), (1, 42)] };
+            meta.write_ingest_offsets("kafka#g#t", &offsets)
+                .unwrap();
+            assert_eq!(meta.read_ingest_offsets("kafka#g#t").unwrap(), Some(offsets));
+            // ingest offsets must not break the recovery of schema items
+            meta.recover().unwrap();
+        }
+        fs::rmr(path).unwrap();
+    }
+
+    #[test]
+    fn test_dedup_ids() {
+        let path = "test_meta_dedup_ids";
+        fs::rmr(path).unwrap();
+        {
+            let mut config = HashMap::new();
+            config.insert("store.data.path".to_owned(), path.to_owned());
+            let db = RocksDB::open(&config).unwrap();
+            let meta = Meta::new(Arc::new(db));
+            meta.write_dedup_ids(10, &["a".to_owned(), "b#1".to_owned()], 1000)
+                .unwrap();
+            meta.write_dedup_ids(11, &["c".to_owned()], 2000)
+                .unwrap();
+            assert_eq!(meta.read_dedup_id("b#1").unwrap(), Some(DedupEntry { si: 10, ts_ms: 1000 }));
+            assert_eq!(meta.purge_dedup_ids(1500).unwrap(), 2);
+            assert!(meta.read_dedup_id("a").unwrap().is_none());
+            assert!(meta.read_dedup_id("b#1").unwrap().is_none());
+            assert_eq!(meta.read_dedup_id("c").unwrap(), Some(DedupEntry { si: 11, ts_ms: 2000 }));
+            assert_eq!(meta.purge_dedup_ids(1500).unwrap(), 0);
+            meta.recover().unwrap();
+        }
+        fs::rmr(path).unwrap();
+    }
+
     fn gen_edge_kinds(label: LabelId) -> HashSet<EdgeKind> {
         let mut ret = HashSet::new();
         for si in 10..=20 {
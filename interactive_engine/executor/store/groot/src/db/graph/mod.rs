@@ -1,26 +1,41 @@
 use std::cell::RefCell;
+use std::convert::TryFrom;
 use std::io::Write;
 use std::ops::Deref;
 
 use byteorder::{BigEndian, WriteBytesExt};
 
-use crate::db::api::{LabelId, VertexId};
+use crate::api::Property;
+use crate::db::api::{LabelId, Value, ValueType, VertexId};
+use crate::db::import::record_mapping::property_to_value;
 
 #[cfg(test)]
 mod bench;
 pub mod bin;
+pub mod bulk_load;
 pub mod codec;
 pub mod entity;
+pub mod id_mapping;
 pub mod iter;
+pub mod label_bitmap;
 mod meta;
+pub mod partition;
+pub mod pin;
 mod property;
+pub mod replica;
+pub mod reverse_index;
+pub mod sealed;
+pub mod sort_index;
 pub mod store;
 mod table_manager;
 #[cfg(test)]
 mod tests;
+pub mod throttle;
 pub mod types;
 mod version;
 
+pub use self::meta::{DedupEntry, IngestOffsets};
+
 thread_local! {
     static BUFFER: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(64 << 10));
 }
@@ -45,6 +60,31 @@ pub fn get_vertex_id_by_primary_keys<'a, T: Deref<Target = Vec<u8>>>(
     })
 }
 
+/// Encode a primary key literal as a value of the declared type of the primary key, so that e.g. a
+/// long literal of an int primary key is hashed as the writer hashes the int. Returns `None` if the
+/// literal can't be represented in the declared type, in which case no vertex has the primary key.
+pub fn encode_pk_value(property: &Property, r#type: ValueType) -> Option<Value> {
+    let integer = match property {
+        Property::Short(v) => Some(*v as i64),
+        Property::Int(v) => Some(*v as i64),
+        Property::Long(v) => Some(*v),
+        _ => None,
+    };
+    let value = match (r#type, integer, property) {
+        (ValueType::Short, Some(v), _) => Value::short(i16::try_from(v).ok()?),
+        (ValueType::Int, Some(v), _) => Value::int(i32::try_from(v).ok()?),
+        (ValueType::Long, Some(v), _) => Value::long(v),
+        (ValueType::Float, _, Property::Double(v)) => Value::float(*v as f32),
+        (ValueType::Double, _, Property::Float(v)) => Value::double(*v as f64),
+        _ => property_to_value(property)?,
+    };
+    if value.is_type(r#type) {
+        Some(value)
+    } else {
+        None
+    }
+}
+
 pub fn hash64(data: &[u8], length: usize) -> i64 {
     let seed = 0xc70f6907;
     hash64_with_seed(data, length, seed)
@@ -95,7 +135,9 @@ mod test {
 
     use byteorder::{BigEndian, WriteBytesExt};
 
-    use crate::db::graph::get_vertex_id_by_primary_keys;
+    use crate::api;
+    use crate::db::api::ValueType;
+    use crate::db::graph::{encode_pk_value, get_vertex_id_by_primary_keys};
 
     thread_local! {
         static FIELD_BUF: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(64 << 10));
@@ -284,6 +326,28 @@ mod test {
     }
 
     #[ignore]
+    #[test]
+    fn test_encode_pk_value() {
+        let encode = |p: api::Property, t: ValueType| encode_pk_value(&p, t).map(|v| v.into_vec());
+        let pks = [
+            encode(api::Property::Long(999999999999_i64), ValueType::Long).unwrap(),
+            encode(api::Property::Int(29), ValueType::Long).unwrap(),
+            encode(api::Property::Long(7), ValueType::Int).unwrap(),
+        ];
+        let expected = [Property::Long(999999999999_i64), Property::Long(29), Property::Int(7)];
+        assert_eq!(
+            get_vertex_id_by_primary_keys(1, pks.iter()),
+            get_vertex_id_by_pk_property(1, &expected)
+        );
+
+        assert!(encode(api::Property::Long(1 << 40), ValueType::Int).is_none());
+        assert!(encode(api::Property::String("1".to_owned()), ValueType::Long).is_none());
+        assert_eq!(
+            encode(api::Property::Double(1.5), ValueType::Float),
+            encode(api::Property::Float(1.5), ValueType::Float)
+        );
+    }
+
     #[test]
     fn bench_hash() {
         let mut i = 0_i64;
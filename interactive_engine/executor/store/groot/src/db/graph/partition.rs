@@ -0,0 +1,511 @@
+//! Online management of partitions: a hot partition is split into two, cold sibling partitions are
+//! merged into one, and the data of a partition is migrated to another store with a throttled copy
+//! and a final catch-up before the routing is switched.
+//!
+//! A partition owns the vertices of which `floor_mod(id, modulus) == remainder`, and the initial
+//! partitions own `(partition_count, partition_id)`. A partition is split into the halves
+//! `(2 * modulus, remainder)` and `(2 * modulus, remainder + modulus)`, and two halves are merged
+//! back. The data of a vertex, i.e. the vertex and its edges in the forward (backward) edge tables,
+//! are keyed by the vertex id following the table prefix, so the data of a range is selected by keys.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::api::PartitionId;
+use crate::db::api::*;
+use crate::db::graph::store::GraphStore;
+use crate::db::storage::rocksdb::RocksDB;
+
+/// The prefix of the meta keys, i.e. the big endian bytes of the meta table id `i64::MIN`.
+const META_PREFIX: [u8; 8] = [0x80, 0, 0, 0, 0, 0, 0, 0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VertexRange {
+    pub modulus: u64,
+    pub remainder: u64,
+}
+
+impl VertexRange {
+    pub fn contains(&self, vertex_id: VertexId) -> bool {
+        vertex_id.rem_euclid(self.modulus as i64) as u64 == self.remainder
+    }
+
+    pub fn split(&self) -> (VertexRange, VertexRange) {
+        let modulus = self.modulus * 2;
+        (
+            VertexRange { modulus, remainder: self.remainder },
+            VertexRange { modulus, remainder: self.remainder + self.modulus },
+        )
+    }
+
+    /// Whether the two ranges are the halves split from the same range.
+    fn is_sibling_of(&self, other: &VertexRange) -> bool {
+        let half = self.modulus / 2;
+        self.modulus == other.modulus
+            && self.remainder != other.remainder
+            && self.remainder % half == other.remainder % half
+    }
+}
+
+/// The vertex which a storage key belongs to, or `None` for the meta keys.
+fn vertex_of_key(key: &[u8]) -> Option<VertexId> {
+    if key.len() < 16 || key[0..8] == META_PREFIX {
+        return None;
+    }
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&key[8..16]);
+    Some(i64::from_be_bytes(id))
+}
+
+/// Route the vertices to partitions, and the partitions to servers. It's never modified in place
+/// when in use, but replaced by a modified copy of a newer version.
+///
+/// The version is the routing's own, bumped only when partitions are split, merged or moved, and
+/// independent of the schema version, i.e. the snapshot of the last ddl, so that a routing is never
+/// taken as stale because of the schema changes or the initial assignment of the servers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartitionRouting {
+    version: u64,
+    partition_count: u32,
+    partitions: HashMap<PartitionId, VertexRange>,
+    ranges: HashMap<VertexRange, PartitionId>,
+    servers: HashMap<PartitionId, u32>,
+    max_modulus: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RoutingEntry {
+    partition: PartitionId,
+    modulus: u64,
+    remainder: u64,
+    server: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RoutingTable {
+    version: u64,
+    partition_count: u32,
+    entries: Vec<RoutingEntry>,
+}
+
+impl PartitionRouting {
+    pub fn new(partition_count: u32) -> Self {
+        let mut routing = PartitionRouting {
+            version: 0,
+            partition_count,
+            partitions: HashMap::new(),
+            ranges: HashMap::new(),
+            servers: HashMap::new(),
+            max_modulus: partition_count as u64,
+        };
+        for partition in 0..partition_count {
+            let range = VertexRange { modulus: partition_count as u64, remainder: partition as u64 };
+            routing.partitions.insert(partition, range);
+            routing.ranges.insert(range, partition);
+        }
+        routing
+    }
+
+    /// Parse the routing in json, e.g. `{"version":1,"partition_count":2,"entries":[{"partition":0,
+    /// "modulus":2,"remainder":0,"server":0},...]}`, the ranges should cover all the vertices.
+    pub fn from_json(json: &str) -> GraphResult<Self> {
+        let table: RoutingTable = serde_json::from_str(json).map_err(|e| {
+            let msg = format!("parse partition routing failed: {}", e);
+            gen_graph_err!(GraphErrorCode::InvalidData, msg, from_json)
+        })?;
+        if table.partition_count == 0 {
+            let msg = "partition count of the routing should be positive".to_string();
+            return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, from_json));
+        }
+        let mut routing = PartitionRouting {
+            version: table.version,
+            partition_count: table.partition_count,
+            partitions: HashMap::new(),
+            ranges: HashMap::new(),
+            servers: HashMap::new(),
+            max_modulus: table.partition_count as u64,
+        };
+        for entry in table.entries {
+            let range = VertexRange { modulus: entry.modulus, remainder: entry.remainder };
+            routing.check_range(entry.partition, &range)?;
+            routing.insert(entry.partition, range);
+            if let Some(server) = entry.server {
+                routing.servers.insert(entry.partition, server);
+            }
+        }
+        Ok(routing)
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut entries = self
+            .partitions
+            .iter()
+            .map(|(partition, range)| RoutingEntry {
+                partition: *partition,
+                modulus: range.modulus,
+                remainder: range.remainder,
+                server: self.servers.get(partition).copied(),
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.partition);
+        let table = RoutingTable { version: self.version, partition_count: self.partition_count, entries };
+        serde_json::to_string(&table).expect("serialize partition routing failed")
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn get_partition_id(&self, vertex_id: VertexId) -> PartitionId {
+        let mut modulus = self.partition_count as u64;
+        while modulus <= self.max_modulus {
+            let range = VertexRange { modulus, remainder: vertex_id.rem_euclid(modulus as i64) as u64 };
+            if let Some(partition) = self.ranges.get(&range) {
+                return *partition;
+            }
+            modulus *= 2;
+        }
+        // not reachable as the ranges cover all the vertices;
+        vertex_id.rem_euclid(self.partition_count as i64) as PartitionId
+    }
+
+    pub fn get_server_id(&self, partition: PartitionId) -> Option<u32> {
+        self.servers.get(&partition).copied()
+    }
+
+    pub fn get_range(&self, partition: PartitionId) -> Option<VertexRange> {
+        self.partitions.get(&partition).copied()
+    }
+
+    /// Assign the server of a partition in the current version, e.g. initially when the store starts.
+    pub fn set_server(&mut self, partition: PartitionId, server: u32) {
+        self.servers.insert(partition, server);
+    }
+
+    /// Move `partition` to `server` after its data is migrated, which is a new version.
+    pub fn move_partition(&mut self, partition: PartitionId, server: u32) -> GraphResult<()> {
+        if !self.partitions.contains_key(&partition) {
+            let msg = format!("partition {} not found", partition);
+            return Err(gen_graph_err!(
+                GraphErrorCode::InvalidOperation,
+                msg,
+                move_partition,
+                partition,
+                server
+            ));
+        }
+        self.servers.insert(partition, server);
+        self.version += 1;
+        Ok(())
+    }
+
+    /// Split `partition` into halves, and the second half is owned by `new_partition` on `server`;
+    /// return the range of the new partition.
+    pub fn split(
+        &mut self, partition: PartitionId, new_partition: PartitionId, server: u32,
+    ) -> GraphResult<VertexRange> {
+        if self.partitions.contains_key(&new_partition) {
+            let msg = format!("partition {} already exists", new_partition);
+            return Err(gen_graph_err!(
+                GraphErrorCode::InvalidOperation,
+                msg,
+                split,
+                partition,
+                new_partition
+            ));
+        }
+        let range = self
+            .partitions
+            .get(&partition)
+            .copied()
+            .ok_or_else(|| {
+                let msg = format!("partition {} not found", partition);
+                gen_graph_err!(GraphErrorCode::InvalidOperation, msg, split, partition, new_partition)
+            })?;
+        let (kept, moved) = range.split();
+        self.ranges.remove(&range);
+        self.insert(partition, kept);
+        self.insert(new_partition, moved);
+        self.servers.insert(new_partition, server);
+        self.version += 1;
+        Ok(moved)
+    }
+
+    /// Merge `sibling` into `partition`, which are the halves split from the same range; return the
+    /// range of the sibling, of which the data should be migrated to the partition.
+    pub fn merge(&mut self, partition: PartitionId, sibling: PartitionId) -> GraphResult<VertexRange> {
+        let range = self.partitions.get(&partition).copied();
+        let sibling_range = self.partitions.get(&sibling).copied();
+        let (range, sibling_range) = match (range, sibling_range) {
+            (Some(range), Some(sibling_range))
+                if range.modulus > self.partition_count as u64 && range.is_sibling_of(&sibling_range) =>
+            {
+                (range, sibling_range)
+            }
+            _ => {
+                let msg =
+                    format!("partition {} and {} are not split from the same range", partition, sibling);
+                return Err(gen_graph_err!(
+                    GraphErrorCode::InvalidOperation,
+                    msg,
+                    merge,
+                    partition,
+                    sibling
+                ));
+            }
+        };
+        let modulus = range.modulus / 2;
+        let merged = VertexRange { modulus, remainder: range.remainder % modulus };
+        self.ranges.remove(&range);
+        self.ranges.remove(&sibling_range);
+        self.partitions.remove(&sibling);
+        self.servers.remove(&sibling);
+        self.insert(partition, merged);
+        self.version += 1;
+        Ok(sibling_range)
+    }
+
+    /// A range is valid if its modulus is the partition count multiplied by a power of 2, as ranges
+    /// are looked up by the moduli, and its remainder is less than the modulus.
+    fn check_range(&self, partition: PartitionId, range: &VertexRange) -> GraphResult<()> {
+        let partition_count = self.partition_count as u64;
+        let valid = partition_count > 0
+            && range.modulus >= partition_count
+            && range.modulus % partition_count == 0
+            && (range.modulus / partition_count).is_power_of_two()
+            && range.remainder < range.modulus;
+        if !valid {
+            let msg = format!(
+                "invalid range {:?} of partition {} with partition count {}",
+                range, partition, partition_count
+            );
+            return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, check_range, partition));
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, partition: PartitionId, range: VertexRange) {
+        self.partitions.insert(partition, range);
+        self.ranges.insert(range, partition);
+        self.max_modulus = self.max_modulus.max(range.modulus);
+    }
+}
+
+/// Limit the rate of copying to `bytes_per_sec`, not limited if it's 0.
+pub struct Throttle {
+    bytes_per_sec: u64,
+    start: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Throttle { bytes_per_sec, start: Instant::now(), bytes: 0 }
+    }
+
+    pub fn acquire(&mut self, bytes: u64) {
+        if self.bytes_per_sec == 0 {
+            return;
+        }
+        self.bytes += bytes;
+        let expected = Duration::from_secs_f64(self.bytes as f64 / self.bytes_per_sec as f64);
+        let elapsed = self.start.elapsed();
+        if expected > elapsed {
+            thread::sleep(expected - elapsed);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum MigrateData {
+    /// All the data and the meta, e.g. to move a partition to another store.
+    All,
+    /// The data in the range and the meta, e.g. to split a partition into a new store.
+    Range(VertexRange),
+    /// The data in the range without the meta, e.g. to merge a partition into an existing store.
+    RangeOnly(VertexRange),
+}
+
+impl MigrateData {
+    fn accepts(&self, key: &[u8]) -> bool {
+        match (self, vertex_of_key(key)) {
+            (MigrateData::All, _) => true,
+            (MigrateData::Range(_), None) => true,
+            (MigrateData::Range(range), Some(id)) | (MigrateData::RangeOnly(range), Some(id)) => {
+                range.contains(id)
+            }
+            (MigrateData::RangeOnly(_), None) => false,
+        }
+    }
+}
+
+/// Migrate the data of the source store to a target rocksdb in phases:
+/// 1. `copy` copies a consistent view of the data throttled, while the source keeps serving;
+/// 2. `catch_up` replays the writes on the source since the copy, repeated until the lag is small;
+///    the wal of the source should be kept by `store.rocksdb.wal.ttl.seconds` in the meantime;
+/// 3. `finish` pauses the writes of the source, replays the remaining writes and flushes the target;
+/// 4. the routing is switched to the target, then `complete` resumes the writes of the source and
+///    deletes the migrated range from the source, or `abort` resumes the writes only.
+pub struct PartitionMigration<'a> {
+    source: &'a GraphStore,
+    target: Arc<RocksDB>,
+    data: MigrateData,
+    throttle: Throttle,
+    since: Option<u64>,
+}
+
+impl<'a> PartitionMigration<'a> {
+    /// Migrate to a new store opened with `target_options`, e.g. `store.data.path`; the store can be
+    /// opened as a `GraphStore` after the migration is completed.
+    pub fn to_new_store(
+        source: &'a GraphStore, target_options: &HashMap<String, String>, data: MigrateData,
+        bytes_per_sec: u64,
+    ) -> GraphResult<Self> {
+        let target = Arc::new(RocksDB::open(target_options)?);
+        Ok(Self::new(source, target, data, bytes_per_sec))
+    }
+
+    /// Migrate the data in `range` to an existing store, e.g. to merge the source into it.
+    pub fn to_store(
+        source: &'a GraphStore, target: &GraphStore, range: VertexRange, bytes_per_sec: u64,
+    ) -> Self {
+        Self::new(source, target.get_storage().clone(), MigrateData::RangeOnly(range), bytes_per_sec)
+    }
+
+    fn new(source: &'a GraphStore, target: Arc<RocksDB>, data: MigrateData, bytes_per_sec: u64) -> Self {
+        PartitionMigration { source, target, data, throttle: Throttle::new(bytes_per_sec), since: None }
+    }
+
+    /// Copy the data to the target, return the number of entries copied.
+    pub fn copy(&mut self) -> GraphResult<u64> {
+        let storage = self.source.get_storage();
+        // the writes since are replayed by the catch-up, including those already in the copy;
+        let since = storage.latest_sequence_number()?;
+        let data = self.data;
+        let throttle = &mut self.throttle;
+        let count =
+            storage.copy_to(&self.target, |key| data.accepts(key), &mut |bytes| throttle.acquire(bytes))?;
+        self.since = Some(since);
+        info!("copied {} entries of {:?} since sequence {}", count, data, since);
+        Ok(count)
+    }
+
+    /// Replay the writes on the source since the copy or the last catch-up, return the number of
+    /// writes replayed, which tells the lag.
+    pub fn catch_up(&mut self) -> GraphResult<u64> {
+        let since = self.since.ok_or_else(|| {
+            let msg = "catch up before the data is copied".to_string();
+            gen_graph_err!(GraphErrorCode::InvalidOperation, msg, catch_up)
+        })?;
+        let data = self.data;
+        let (next, count) = self
+            .source
+            .get_storage()
+            .copy_updates_to(&self.target, since, |key| data.accepts(key))?;
+        self.since = Some(next);
+        debug!("replayed {} writes of {:?} since sequence {}", count, data, since);
+        Ok(count)
+    }
+
+    /// Pause the writes of the source and replay the remaining writes, the writes are resumed if it
+    /// fails; return the number of writes replayed.
+    pub fn finish(&mut self) -> GraphResult<u64> {
+        self.source.pause_writes();
+        let res = self
+            .catch_up()
+            .and_then(|count| self.target.flush().map(|_| count));
+        if res.is_err() {
+            self.source.resume_writes();
+        }
+        res
+    }
+
+    /// Resume the writes of the source after the routing is switched, and delete the migrated range
+    /// from the source; return the number of entries deleted.
+    pub fn complete(self) -> GraphResult<u64> {
+        self.source.resume_writes();
+        match self.data {
+            MigrateData::All => Ok(0),
+            MigrateData::Range(range) | MigrateData::RangeOnly(range) => self
+                .source
+                .get_storage()
+                .delete_if(|key| vertex_of_key(key).map_or(false, |id| range.contains(id))),
+        }
+    }
+
+    /// Give up the migration, the data copied to the target is not deleted.
+    pub fn abort(self) {
+        self.source.resume_writes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_routing() {
+        let mut routing = PartitionRouting::new(4);
+        assert_eq!(routing.get_partition_id(6), 2);
+        assert_eq!(routing.get_partition_id(-1), 3);
+
+        // 2 owns 2, 10, 18.., and 4 owns 6, 14, 22..;
+        let moved = routing.split(2, 4, 1).unwrap();
+        assert_eq!(moved, VertexRange { modulus: 8, remainder: 6 });
+        assert_eq!(routing.get_partition_id(10), 2);
+        assert_eq!(routing.get_partition_id(6), 4);
+        assert_eq!(routing.get_partition_id(-2), 4);
+        assert_eq!(routing.get_server_id(4), Some(1));
+        assert!(routing.split(3, 4, 1).is_err());
+
+        let parsed = PartitionRouting::from_json(&routing.to_json()).unwrap();
+        assert_eq!(parsed, routing);
+
+        assert!(routing.merge(2, 3).is_err());
+        assert!(routing.merge(1, 3).is_err());
+        assert_eq!(routing.merge(2, 4).unwrap(), moved);
+        assert_eq!(routing.get_partition_id(6), 2);
+        assert_eq!(routing.get_range(2), Some(VertexRange { modulus: 4, remainder: 2 }));
+        assert_eq!(routing.version(), 2);
+
+        // assigning the servers keeps the version, while moving a partition is a new version
+        routing.set_server(0, 1);
+        assert_eq!(routing.version(), 2);
+        routing.move_partition(0, 2).unwrap();
+        assert_eq!(routing.version(), 3);
+        assert_eq!(routing.get_server_id(0), Some(2));
+        assert!(routing.move_partition(4, 2).is_err());
+    }
+
+    #[test]
+    fn test_invalid_routing() {
+        let json = |modulus: u64, remainder: u64| {
+            format!(
+                r#"{{"version":1,"partition_count":2,"entries":[{{"partition":0,"modulus":{},"remainder":{}}}]}}"#,
+                modulus, remainder
+            )
+        };
+        assert!(PartitionRouting::from_json(&json(4, 2)).is_ok());
+        assert!(PartitionRouting::from_json(&json(0, 0)).is_err());
+        assert!(PartitionRouting::from_json(&json(6, 0)).is_err());
+        assert!(PartitionRouting::from_json(&json(4, 4)).is_err());
+        let empty = r#"{"version":1,"partition_count":0,"entries":[]}"#;
+        assert!(PartitionRouting::from_json(empty).is_err());
+    }
+
+    #[test]
+    fn test_migrate_data() {
+        let mut key = Vec::new();
+        key.extend_from_slice(&2i64.to_be_bytes());
+        key.extend_from_slice(&6i64.to_be_bytes());
+        key.extend_from_slice(&(!1i64).to_be_bytes());
+        let meta = META_PREFIX.to_vec();
+        let range = VertexRange { modulus: 8, remainder: 6 };
+        assert!(MigrateData::Range(range).accepts(&key));
+        assert!(MigrateData::Range(range).accepts(&meta));
+        assert!(!MigrateData::RangeOnly(range).accepts(&meta));
+        assert!(!MigrateData::Range(VertexRange { modulus: 8, remainder: 2 }).accepts(&key));
+    }
+}
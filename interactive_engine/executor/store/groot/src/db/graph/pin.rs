@@ -0,0 +1,69 @@
+//! The snapshots pinned by the long reads of the process, e.g. the exports of the graph, which are not
+//! garbage collected until they are unpinned, so that a read sees the same data however long it takes.
+//! The pins are shared by all the partitions of the process, as a read of a snapshot spans them all.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::db::api::SnapshotId;
+
+lazy_static! {
+    static ref PINNED_SNAPSHOTS: Mutex<BTreeMap<SnapshotId, usize>> = Mutex::new(BTreeMap::new());
+}
+
+/// Keeps the snapshot readable until it's dropped.
+pub struct SnapshotPin {
+    si: SnapshotId,
+}
+
+impl SnapshotPin {
+    pub fn new(si: SnapshotId) -> Self {
+        *PINNED_SNAPSHOTS
+            .lock()
+            .unwrap()
+            .entry(si)
+            .or_insert(0) += 1;
+        SnapshotPin { si }
+    }
+}
+
+impl Drop for SnapshotPin {
+    fn drop(&mut self) {
+        let mut pinned = PINNED_SNAPSHOTS.lock().unwrap();
+        if let Some(count) = pinned.get_mut(&self.si) {
+            *count -= 1;
+            if *count == 0 {
+                pinned.remove(&self.si);
+            }
+        }
+    }
+}
+
+/// The snapshot which the data are garbage collected before instead of `si`, i.e. the oldest pinned one
+/// if it's older.
+pub fn gc_snapshot(si: SnapshotId) -> SnapshotId {
+    let pinned = PINNED_SNAPSHOTS.lock().unwrap();
+    match pinned.keys().next() {
+        Some(oldest) if *oldest < si => *oldest,
+        _ => si,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_pin() {
+        // the snapshots are far below those of the other tests, which may pin in parallel
+        assert_eq!(gc_snapshot(-90), -90);
+        let pin = SnapshotPin::new(-100);
+        let another = SnapshotPin::new(-100);
+        assert_eq!(gc_snapshot(-90), -100);
+        assert_eq!(gc_snapshot(-110), -110);
+        drop(pin);
+        assert_eq!(gc_snapshot(-90), -100);
+        drop(another);
+        assert_eq!(gc_snapshot(-90), -90);
+    }
+}
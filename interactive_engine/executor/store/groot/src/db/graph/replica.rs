@@ -0,0 +1,199 @@
+//! Read replicas of a partition by shipping the write queue: the leader store keeps the operation
+//! batches it has written, with their snapshot ids, in a bounded in-memory log, and a follower store
+//! of the same partition pulls the batches after the last one it has applied and writes them in the
+//! same order, so the follower has the same data and schema as the leader at the applied snapshot.
+//!
+//! A follower which falls behind the retained log, e.g. after a restart, should be bootstrapped
+//! again by copying the partition, see `PartitionMigration`.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::db::api::*;
+use crate::db::util::time::current_time_millis;
+
+/// The bytes of the entries retained by default, see `store.replication.log.mb`.
+pub const DEFAULT_LOG_BYTES: usize = 256 << 20;
+
+const ENTRY_HEADER_LEN: usize = 12;
+
+struct LogInner {
+    entries: VecDeque<(SnapshotId, Vec<u8>)>,
+    bytes: usize,
+    // the largest snapshot id of the entries dropped, the followers behind it can't catch up;
+    truncated_si: SnapshotId,
+}
+
+/// The operation batches written to the leader store, in ascending order of snapshot ids.
+pub struct ReplicationLog {
+    capacity_bytes: usize,
+    inner: Mutex<LogInner>,
+}
+
+impl ReplicationLog {
+    pub fn new(capacity_bytes: usize) -> Self {
+        let inner = LogInner { entries: VecDeque::new(), bytes: 0, truncated_si: -1 };
+        ReplicationLog { capacity_bytes, inner: Mutex::new(inner) }
+    }
+
+    pub fn append(&self, si: SnapshotId, batch: &[u8]) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.bytes += batch.len();
+        inner.entries.push_back((si, batch.to_vec()));
+        while inner.bytes > self.capacity_bytes && inner.entries.len() > 1 {
+            let (si, batch) = inner.entries.pop_front().unwrap();
+            inner.bytes -= batch.len();
+            inner.truncated_si = si;
+        }
+    }
+
+    /// The snapshot id of the last entry, or -1 if nothing is written.
+    pub fn latest_si(&self) -> SnapshotId {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .entries
+            .back()
+            .map(|(si, _)| *si)
+            .unwrap_or(inner.truncated_si)
+    }
+
+    /// The entries after the snapshot `since`, encoded by `encode_entries`, of `max_bytes` at most
+    /// unless the first entry is larger; fails if some entries after `since` are dropped.
+    pub fn read_since(&self, since: SnapshotId, max_bytes: usize) -> GraphResult<Vec<u8>> {
+        let inner = self.inner.lock().unwrap();
+        if since < inner.truncated_si {
+            let msg = format!(
+                "replication log after snapshot {} is truncated at {}, the follower should be resynced",
+                since, inner.truncated_si
+            );
+            return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, read_since, since));
+        }
+        let start = inner
+            .entries
+            .partition_point(|(si, _)| *si <= since);
+        let mut buf = Vec::new();
+        for (si, batch) in inner.entries.range(start..) {
+            if !buf.is_empty() && buf.len() + ENTRY_HEADER_LEN + batch.len() > max_bytes {
+                break;
+            }
+            encode_entry(&mut buf, *si, batch);
+        }
+        Ok(buf)
+    }
+}
+
+fn encode_entry(buf: &mut Vec<u8>, si: SnapshotId, batch: &[u8]) {
+    buf.extend_from_slice(&si.to_be_bytes());
+    buf.extend_from_slice(&(batch.len() as u32).to_be_bytes());
+    buf.extend_from_slice(batch);
+}
+
+/// Encode the entries as `(si: i64, len: u32, batch)*` in big endian.
+pub fn encode_entries<'a, I: IntoIterator<Item = (SnapshotId, &'a [u8])>>(entries: I) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for (si, batch) in entries {
+        encode_entry(&mut buf, si, batch);
+    }
+    buf
+}
+
+pub fn decode_entries(mut buf: &[u8]) -> GraphResult<Vec<(SnapshotId, &[u8])>> {
+    let mut entries = Vec::new();
+    while !buf.is_empty() {
+        if buf.len() < ENTRY_HEADER_LEN {
+            let msg = format!("invalid replication entry header of {} bytes", buf.len());
+            return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, decode_entries));
+        }
+        let mut si = [0u8; 8];
+        si.copy_from_slice(&buf[0..8]);
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&buf[8..ENTRY_HEADER_LEN]);
+        let len = u32::from_be_bytes(len) as usize;
+        if buf.len() < ENTRY_HEADER_LEN + len {
+            let msg = format!("replication entry of {} bytes is truncated", len);
+            return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, decode_entries));
+        }
+        entries.push((SnapshotId::from_be_bytes(si), &buf[ENTRY_HEADER_LEN..ENTRY_HEADER_LEN + len]));
+        buf = &buf[ENTRY_HEADER_LEN + len..];
+    }
+    Ok(entries)
+}
+
+/// The progress of a follower store against its leader.
+pub struct FollowerState {
+    applied_si: AtomicI64,
+    leader_si: AtomicI64,
+    // when the follower was last known to have applied all the writes of the leader;
+    synced_at_ms: AtomicU64,
+}
+
+impl FollowerState {
+    pub fn new() -> Self {
+        FollowerState {
+            applied_si: AtomicI64::new(-1),
+            leader_si: AtomicI64::new(-1),
+            synced_at_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub fn applied_si(&self) -> SnapshotId {
+        self.applied_si.load(Ordering::Acquire)
+    }
+
+    /// Record the entries applied till `applied_si`, with the latest snapshot of the leader when
+    /// they were pulled.
+    pub fn on_applied(&self, applied_si: SnapshotId, leader_si: SnapshotId) {
+        self.applied_si
+            .fetch_max(applied_si, Ordering::AcqRel);
+        self.leader_si
+            .fetch_max(leader_si, Ordering::AcqRel);
+        if self.applied_si() >= self.leader_si.load(Ordering::Acquire) {
+            self.synced_at_ms
+                .store(current_time_millis(), Ordering::Release);
+        }
+    }
+
+    /// The upper bound of how old the data read from the follower is, i.e. the time since it was
+    /// last in sync with the leader, or `u64::MAX` if it has never been.
+    pub fn staleness_ms(&self) -> u64 {
+        match self.synced_at_ms.load(Ordering::Acquire) {
+            0 => u64::MAX,
+            synced_at => current_time_millis().saturating_sub(synced_at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replication_log() {
+        let log = ReplicationLog::new(10);
+        assert_eq!(log.latest_si(), -1);
+        log.append(1, b"aaaa");
+        log.append(2, b"bbbb");
+        let buf = log.read_since(0, 1024).unwrap();
+        assert_eq!(decode_entries(&buf).unwrap(), vec![(1, &b"aaaa"[..]), (2, &b"bbbb"[..])]);
+        // one entry at least even if it's larger than the max bytes;
+        let buf = log.read_since(0, 1).unwrap();
+        assert_eq!(decode_entries(&buf).unwrap(), vec![(1, &b"aaaa"[..])]);
+        assert!(log.read_since(2, 1024).unwrap().is_empty());
+
+        log.append(3, b"cccc");
+        assert_eq!(log.latest_si(), 3);
+        assert!(log.read_since(0, 1024).is_err());
+        let buf = log.read_since(1, 1024).unwrap();
+        assert_eq!(decode_entries(&buf).unwrap(), vec![(2, &b"bbbb"[..]), (3, &b"cccc"[..])]);
+        assert!(decode_entries(&buf[..buf.len() - 1]).is_err());
+
+        let follower = FollowerState::new();
+        assert_eq!(follower.staleness_ms(), u64::MAX);
+        follower.on_applied(2, 3);
+        assert_eq!(follower.staleness_ms(), u64::MAX);
+        follower.on_applied(3, 3);
+        assert_eq!(follower.applied_si(), 3);
+        assert!(follower.staleness_ms() < 1000);
+    }
+}
@@ -0,0 +1,72 @@
+//! The edges of the labels in `store.edge.out.only.labels` keep their properties only in the out
+//! direction, in the partition of their sources, which saves the storage of the in direction at the
+//! cost of reading the properties of the in edges from the sources.
+//!
+//! The partition of the destination of such an edge keeps the reverse index of the edge instead, i.e.
+//! its in-direction entry without any property, so `in()` is served where it's routed to, like the
+//! edges stored in both directions, and the index is persisted and maintained by the same writes and
+//! deletes as the in direction of the other edges, including those of the bulk loads. The properties
+//! of the in edges are read from the out direction in the partitions of their sources, see
+//! `GlobalGraph::get_in_edges`.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::db::api::*;
+
+pub struct ReverseIndex {
+    out_only_labels: HashSet<String>,
+    // whether the edge labels are stored only in the out direction, resolved by their names
+    modes: RwLock<HashMap<LabelId, bool>>,
+}
+
+impl ReverseIndex {
+    /// `labels` are the names of the edge labels stored only in the out direction, separated by `,`.
+    pub fn new(labels: &str) -> Self {
+        let out_only_labels = labels
+            .split(',')
+            .map(|label| label.trim())
+            .filter(|label| !label.is_empty())
+            .map(|label| label.to_owned())
+            .collect();
+        ReverseIndex { out_only_labels, modes: RwLock::new(HashMap::new()) }
+    }
+
+    /// Whether the edges of the label are stored only in the out direction, `name` resolves the name
+    /// of the label if it's not known yet.
+    pub fn is_out_only<F: FnOnce() -> Option<String>>(&self, label: LabelId, name: F) -> bool {
+        if self.out_only_labels.is_empty() {
+            return false;
+        }
+        if let Some(out_only) = self.modes.read().unwrap().get(&label) {
+            return *out_only;
+        }
+        match name() {
+            Some(name) => {
+                let out_only = self.out_only_labels.contains(&name);
+                self.modes
+                    .write()
+                    .unwrap()
+                    .insert(label, out_only);
+                out_only
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reverse_index() {
+        let index = ReverseIndex::new("knows, created");
+        assert!(index.is_out_only(1, || Some("knows".to_owned())));
+        assert!(!index.is_out_only(2, || Some("likes".to_owned())));
+        // the modes are resolved once
+        assert!(index.is_out_only(1, || None));
+        assert!(!index.is_out_only(3, || None));
+        assert!(!ReverseIndex::new("").is_out_only(1, || panic!("resolved without labels")));
+    }
+}
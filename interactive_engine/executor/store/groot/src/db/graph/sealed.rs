@@ -0,0 +1,924 @@
+//! The read-only serving mode of a sealed graph, e.g. a static graph after its bulk load: the graph of
+//! a partition at a snapshot is laid out by `GraphStore::seal` in flat arrays, which are memory mapped
+//! by the store opened with `store.sealed.path`, so the point reads and the scans binary search and
+//! walk the arrays without going through RocksDB, and the writes are rejected.
+//!
+//! A sealed graph is a directory of the data file, of the sections of 8-byte aligned arrays in little
+//! endian, and the json manifest of the sections:
+//! - the vertices of a label, in the ids sorted in the key order, i.e. as unsigned integers, and the
+//!   `n + 1` offsets of their encoded properties in the data section;
+//! - the adjacency of an edge kind in a direction, in the CSR of the vertices having edges sorted in
+//!   the key order, the `n + 1` starts of their edges, and the other ends and the inner ids of the
+//!   edges sorted by the other ends and the inner ids, with the `m + 1` offsets of their encoded
+//!   properties in the data section. The in adjacency of the labels stored only in the out direction
+//!   is built from the out edges, and shares their data section.
+//!
+//! The ids of the edges, which are most of the bytes of the adjacency, are stored in a block per
+//! vertex, with the `n + 1` byte offsets of the blocks: the other ends delta-encoded in the key order
+//! and the inner ids zigzag-encoded, each followed by the other, all compressed as LEB128 varints. The
+//! blocks are decoded as the edges are iterated, see `SealedEdges`, and a truncated one is an error.
+//! The edges of RocksDB are keyed by their ids, which are prefix-compressed in the blocks of RocksDB,
+//! see `store.rocksdb.cf.<name>.block.restart.interval`.
+//!
+//! The encoded properties are encrypted as the values of RocksDB are if `store.encryption.enabled`,
+//! with their offsets in the data file as the associated data, so that they are decrypted as read,
+//! and a sealed graph encrypted is opened only by a store of the cipher, see `encryption`.
+//!
+//! The schema is still read from RocksDB, and the sealed graph is read at the snapshot it's sealed
+//! at, whatever the snapshot read at. It's sealed by the `sealGraph` of the client service, e.g.
+//! after a bulk load, and the layout is checked as it's opened, so that a corrupt one is rejected.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+use super::bin::{edge_table_prefix_key, parse_edge_key, parse_vertex_key, vertex_table_prefix_key};
+use super::table_manager::Table;
+use crate::db::api::*;
+use crate::db::storage::encryption::ValueCipher;
+use crate::db::storage::rocksdb::RocksDB;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const DATA_FILE: &str = "graph.data";
+
+const MAGIC: &[u8; 8] = b"GSEALED3";
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+struct Section {
+    offset: usize,
+    len: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct VertexLabelManifest {
+    label: LabelId,
+    ids: Section,
+    offsets: Section,
+    data: Section,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct AdjacencyManifest {
+    vertices: Section,
+    starts: Section,
+    blocks: Section,
+    ids: Section,
+    offsets: Section,
+    data: Section,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct EdgeKindManifest {
+    edge_label: LabelId,
+    src_label: LabelId,
+    dst_label: LabelId,
+    out_adjacency: AdjacencyManifest,
+    in_adjacency: AdjacencyManifest,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    snapshot_id: SnapshotId,
+    #[serde(default)]
+    encrypted: bool,
+    vertex_labels: Vec<VertexLabelManifest>,
+    edge_kinds: Vec<EdgeKindManifest>,
+}
+
+/// An edge of an adjacency, where `vertex` is the end of the direction, i.e. the source of the out
+/// edges and the destination of the in edges.
+#[derive(Clone, Copy)]
+struct AdjacencyEntry {
+    vertex: VertexId,
+    other: VertexId,
+    inner_id: i64,
+    span: (u64, u64),
+}
+
+// the ids are encoded in big endian in the keys, see `bin::vertex_key` and `bin::edge_key`
+fn key_order(id: i64) -> u64 {
+    id as u64
+}
+
+/// Write the graph of a partition at a snapshot as a sealed graph.
+pub struct SealedGraphWriter {
+    dir: PathBuf,
+    writer: BufWriter<File>,
+    offset: usize,
+    manifest: Manifest,
+    cipher: Option<Arc<ValueCipher>>,
+}
+
+impl SealedGraphWriter {
+    /// Create the sealed graph in the dir, of the encoded properties encrypted if the cipher is given.
+    pub fn create(dir: &str, si: SnapshotId, cipher: Option<Arc<ValueCipher>>) -> GraphResult<Self> {
+        fs::create_dir_all(dir).map_err(|e| io_err(e, "create dir", dir))?;
+        let dir = PathBuf::from(dir);
+        let path = dir.join(DATA_FILE);
+        let file = File::create(&path).map_err(|e| io_err(e, "create", &path.to_string_lossy()))?;
+        let mut writer = SealedGraphWriter {
+            dir,
+            writer: BufWriter::new(file),
+            offset: 0,
+            manifest: Manifest::default(),
+            cipher,
+        };
+        writer.manifest.snapshot_id = si;
+        writer.manifest.encrypted = writer.cipher.is_some();
+        writer.append(MAGIC)?;
+        Ok(writer)
+    }
+
+    /// Write the vertices of the label visible at `si` in its table.
+    pub fn add_vertex_table(
+        &mut self, storage: &RocksDB, si: SnapshotId, label: LabelId, table: &Table,
+    ) -> GraphResult<()> {
+        let data_ts = si - table.start_si;
+        let mut ids = Vec::new();
+        let mut offsets = vec![0u64];
+        let data_start = self.begin_section()?;
+        let mut prev = None;
+        for (raw_key, raw_val) in storage.new_scan(&vertex_table_prefix_key(table.id))? {
+            let (id, ts) = parse_vertex_key(raw_key.to_slice())?;
+            // the versions of a vertex are from the latest one
+            if ts > data_ts || prev == Some(id) {
+                continue;
+            }
+            prev = Some(id);
+            let val = raw_val.to_slice();
+            // the vertex deleted
+            if val.len() < 4 {
+                continue;
+            }
+            self.append_value(val)?;
+            ids.push(id);
+            offsets.push((self.offset - data_start) as u64);
+        }
+        let data = Section { offset: data_start, len: self.offset - data_start };
+        let ids = self.write_i64s(&ids)?;
+        let offsets = self.write_u64s(&offsets)?;
+        self.manifest
+            .vertex_labels
+            .push(VertexLabelManifest { label, ids, offsets, data });
+        Ok(())
+    }
+
+    /// Write the edges of the kind visible at `si` in its table, the in edges of the labels stored
+    /// only in the out direction are their reverse index entries, see `reverse_index`.
+    pub fn add_edge_table(
+        &mut self, storage: &RocksDB, si: SnapshotId, edge_kind: &EdgeKind, table: &Table,
+    ) -> GraphResult<()> {
+        let (out_entries, out_data) = self.write_edges(storage, si, table, EdgeDirection::Out)?;
+        let out_adjacency = self.write_adjacency(&out_entries, out_data)?;
+        let (in_entries, in_data) = self.write_edges(storage, si, table, EdgeDirection::In)?;
+        let in_adjacency = self.write_adjacency(&in_entries, in_data)?;
+        self.manifest.edge_kinds.push(EdgeKindManifest {
+            edge_label: edge_kind.edge_label_id,
+            src_label: edge_kind.src_vertex_label_id,
+            dst_label: edge_kind.dst_vertex_label_id,
+            out_adjacency,
+            in_adjacency,
+        });
+        Ok(())
+    }
+
+    /// Flush the data file, and write the manifest, which makes the sealed graph complete.
+    pub fn finish(mut self) -> GraphResult<()> {
+        let data_path = self.dir.join(DATA_FILE);
+        let data_path = data_path.to_string_lossy();
+        self.writer
+            .flush()
+            .map_err(|e| io_err(e, "flush", &data_path))?;
+        self.writer
+            .get_ref()
+            .sync_all()
+            .map_err(|e| io_err(e, "sync", &data_path))?;
+        let json = serde_json::to_string(&self.manifest).expect("serialize sealed manifest failed");
+        let path = self.dir.join(MANIFEST_FILE);
+        fs::write(&path, json).map_err(|e| io_err(e, "write", &path.to_string_lossy()))
+    }
+
+    // the entries of the edges sorted in the key order of the direction, with the data section of
+    // their properties;
+    fn write_edges(
+        &mut self, storage: &RocksDB, si: SnapshotId, table: &Table, direction: EdgeDirection,
+    ) -> GraphResult<(Vec<AdjacencyEntry>, Section)> {
+        let data_ts = si - table.start_si;
+        let mut entries = Vec::new();
+        let data_start = self.begin_section()?;
+        let mut prev = None;
+        for (raw_key, raw_val) in storage.new_scan(&edge_table_prefix_key(table.id, direction))? {
+            let (edge_id, ts) = parse_edge_key(raw_key.to_slice());
+            if ts > data_ts || prev == Some(edge_id) {
+                continue;
+            }
+            prev = Some(edge_id);
+            let val = raw_val.to_slice();
+            if val.len() < 4 {
+                continue;
+            }
+            let start = (self.offset - data_start) as u64;
+            self.append_value(val)?;
+            let (vertex, other) = match direction {
+                EdgeDirection::In => (edge_id.dst_id, edge_id.src_id),
+                _ => (edge_id.src_id, edge_id.dst_id),
+            };
+            let span = (start, (self.offset - data_start) as u64);
+            entries.push(AdjacencyEntry { vertex, other, inner_id: edge_id.inner_id, span });
+        }
+        Ok((entries, Section { offset: data_start, len: self.offset - data_start }))
+    }
+
+    fn write_adjacency(
+        &mut self, entries: &[AdjacencyEntry], data: Section,
+    ) -> GraphResult<AdjacencyManifest> {
+        let mut vertices = Vec::new();
+        let mut starts = Vec::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if vertices.last() != Some(&entry.vertex) {
+                vertices.push(entry.vertex);
+                starts.push(i as u64);
+            }
+        }
+        starts.push(entries.len() as u64);
+        let mut blocks = Vec::with_capacity(starts.len());
+        let mut ids = Vec::new();
+        for block in starts.windows(2) {
+            blocks.push(ids.len() as u64);
+            let mut prev = None;
+            for entry in &entries[block[0] as usize..block[1] as usize] {
+                let other = key_order(entry.other);
+                write_varint(&mut ids, prev.map_or(other, |prev| other - prev));
+                write_varint(&mut ids, zigzag(entry.inner_id));
+                prev = Some(other);
+            }
+        }
+        blocks.push(ids.len() as u64);
+        // the properties are written in the order of the edges, so that their spans are adjacent
+        let mut offsets = vec![entries.first().map_or(0, |e| e.span.0)];
+        offsets.extend(entries.iter().map(|e| e.span.1));
+        Ok(AdjacencyManifest {
+            vertices: self.write_i64s(&vertices)?,
+            starts: self.write_u64s(&starts)?,
+            blocks: self.write_u64s(&blocks)?,
+            ids: self.write_bytes(&ids)?,
+            offsets: self.write_u64s(&offsets)?,
+            data,
+        })
+    }
+
+    fn write_i64s(&mut self, values: &[i64]) -> GraphResult<Section> {
+        let offset = self.begin_section()?;
+        for value in values {
+            self.append(&value.to_le_bytes())?;
+        }
+        Ok(Section { offset, len: self.offset - offset })
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> GraphResult<Section> {
+        let offset = self.begin_section()?;
+        self.append(bytes)?;
+        Ok(Section { offset, len: bytes.len() })
+    }
+
+    fn write_u64s(&mut self, values: &[u64]) -> GraphResult<Section> {
+        let offset = self.begin_section()?;
+        for value in values {
+            self.append(&value.to_le_bytes())?;
+        }
+        Ok(Section { offset, len: self.offset - offset })
+    }
+
+    // pad to the alignment of the sections, and return the offset of the section
+    fn begin_section(&mut self) -> GraphResult<usize> {
+        let padding = (8 - self.offset % 8) % 8;
+        self.append(&[0u8; 8][..padding])?;
+        Ok(self.offset)
+    }
+
+    // the value read from RocksDB is decrypted, which is encrypted again at its offset if the cipher is
+    // given, see the module doc;
+    fn append_value(&mut self, value: &[u8]) -> GraphResult<()> {
+        match self.cipher.clone() {
+            Some(cipher) => {
+                let encrypted = cipher.encrypt(&(self.offset as u64).to_le_bytes(), value)?;
+                self.append(&encrypted)
+            }
+            None => self.append(value),
+        }
+    }
+
+    fn append(&mut self, bytes: &[u8]) -> GraphResult<()> {
+        self.writer.write_all(bytes).map_err(|e| {
+            let path = self.dir.join(DATA_FILE);
+            io_err(e, "write", &path.to_string_lossy())
+        })?;
+        self.offset += bytes.len();
+        Ok(())
+    }
+}
+
+struct Adjacency {
+    vertices: Section,
+    starts: Section,
+    blocks: Section,
+    ids: Section,
+    offsets: Section,
+    data: Section,
+}
+
+impl From<&AdjacencyManifest> for Adjacency {
+    fn from(m: &AdjacencyManifest) -> Self {
+        Adjacency {
+            vertices: m.vertices,
+            starts: m.starts,
+            blocks: m.blocks,
+            ids: m.ids,
+            offsets: m.offsets,
+            data: m.data,
+        }
+    }
+}
+
+struct VertexLabel {
+    ids: Section,
+    offsets: Section,
+    data: Section,
+}
+
+/// A sealed graph memory mapped, see the module doc.
+pub struct SealedGraph {
+    mmap: Mmap,
+    snapshot_id: SnapshotId,
+    vertex_labels: HashMap<LabelId, VertexLabel>,
+    edge_kinds: Vec<(EdgeKind, Adjacency, Adjacency)>,
+    // the cipher of the encoded properties if the sealed graph is encrypted
+    cipher: Option<Arc<ValueCipher>>,
+}
+
+impl SealedGraph {
+    /// Open the sealed graph in the dir, the cipher is required if it's encrypted.
+    pub fn open(dir: &str, cipher: Option<Arc<ValueCipher>>) -> GraphResult<Self> {
+        let dir = Path::new(dir);
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let json = fs::read_to_string(&manifest_path)
+            .map_err(|e| io_err(e, "read", &manifest_path.to_string_lossy()))?;
+        let manifest: Manifest = serde_json::from_str(&json).map_err(|e| {
+            let msg = format!("parse sealed manifest {:?} failed: {}", manifest_path, e);
+            gen_graph_err!(GraphErrorCode::InvalidData, msg, open)
+        })?;
+        let data_path = dir.join(DATA_FILE);
+        let data_path_str = data_path.to_string_lossy();
+        let file = File::open(&data_path).map_err(|e| io_err(e, "open", &data_path_str))?;
+        // the file is never modified once sealed
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|e| io_err(e, "mmap", &data_path_str))?;
+        if mmap.len() < MAGIC.len() || &mmap[..MAGIC.len()] != MAGIC {
+            let msg = format!("{} is not a sealed graph of this version", data_path_str);
+            return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, open));
+        }
+
+        let mut sections = vec![];
+        for label in manifest.vertex_labels.iter() {
+            sections.extend_from_slice(&[label.ids, label.offsets, label.data]);
+        }
+        for kind in manifest.edge_kinds.iter() {
+            for adjacency in [&kind.out_adjacency, &kind.in_adjacency].iter() {
+                sections.extend_from_slice(&[
+                    adjacency.vertices,
+                    adjacency.starts,
+                    adjacency.blocks,
+                    adjacency.ids,
+                    adjacency.offsets,
+                    adjacency.data,
+                ]);
+            }
+        }
+        if let Some(section) = sections.iter().find(|s| {
+            s.offset
+                .checked_add(s.len)
+                .map_or(true, |end| end > mmap.len())
+        }) {
+            let msg = format!("section {:?} out of {} of {} bytes", section, data_path_str, mmap.len());
+            return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, open));
+        }
+        let section = |s: Section| &mmap[s.offset..s.offset + s.len];
+        for label in manifest.vertex_labels.iter() {
+            let n = label.ids.len / 8;
+            if label.ids.len % 8 != 0
+                || label.offsets.len != (n + 1) * 8
+                || !check_offsets(section(label.offsets), label.data.len as u64)
+            {
+                let msg = format!("vertex label {} of {} is corrupt", label.label, data_path_str);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, open));
+            }
+        }
+        for kind in manifest.edge_kinds.iter() {
+            for a in [&kind.out_adjacency, &kind.in_adjacency].iter() {
+                // the edges are of the `m + 1` offsets of their properties
+                let (n, m) = (a.vertices.len / 8, (a.offsets.len / 8).saturating_sub(1));
+                if a.vertices.len % 8 != 0
+                    || a.offsets.len % 8 != 0
+                    || a.offsets.len == 0
+                    || a.starts.len != (n + 1) * 8
+                    || a.blocks.len != (n + 1) * 8
+                    || !check_offsets(section(a.starts), m as u64)
+                    || !check_offsets(section(a.blocks), a.ids.len as u64)
+                    || !check_offsets(section(a.offsets), a.data.len as u64)
+                {
+                    let msg = format!("edge label {} of {} is corrupt", kind.edge_label, data_path_str);
+                    return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, open));
+                }
+            }
+        }
+        let cipher = match (manifest.encrypted, cipher) {
+            (false, _) => None,
+            (true, Some(cipher)) => Some(cipher),
+            (true, None) => {
+                let msg =
+                    format!("{} is encrypted, but store.encryption.enabled is not set", data_path_str);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, open));
+            }
+        };
+
+        let vertex_labels = manifest
+            .vertex_labels
+            .iter()
+            .map(|l| (l.label, VertexLabel { ids: l.ids, offsets: l.offsets, data: l.data }))
+            .collect();
+        let edge_kinds = manifest
+            .edge_kinds
+            .iter()
+            .map(|k| {
+                let edge_kind = EdgeKind::new(k.edge_label, k.src_label, k.dst_label);
+                (edge_kind, Adjacency::from(&k.out_adjacency), Adjacency::from(&k.in_adjacency))
+            })
+            .collect();
+        info!("sealed graph at {:?} of si#{} is opened", dir, manifest.snapshot_id);
+        Ok(SealedGraph { mmap, snapshot_id: manifest.snapshot_id, vertex_labels, edge_kinds, cipher })
+    }
+
+    /// The snapshot sealed at, which the sealed graph is read at.
+    pub fn snapshot_id(&self) -> SnapshotId {
+        self.snapshot_id
+    }
+
+    pub fn vertex_count(&self, label: LabelId) -> usize {
+        self.vertex_labels
+            .get(&label)
+            .map_or(0, |l| l.ids.len / 8)
+    }
+
+    /// The id and the encoded properties of the `i`th vertex of the label, in the key order.
+    pub fn vertex_at(&self, label: LabelId, i: usize) -> GraphResult<(VertexId, Cow<[u8]>)> {
+        let l = &self.vertex_labels[&label];
+        let id = read_i64(self.section(l.ids), i);
+        Ok((id, self.vertex_data(l, i)?))
+    }
+
+    pub fn get_vertex(&self, label: LabelId, id: VertexId) -> GraphResult<Option<Cow<[u8]>>> {
+        let l = match self.vertex_labels.get(&label) {
+            Some(l) => l,
+            None => return Ok(None),
+        };
+        match search(self.section(l.ids), id) {
+            Ok(i) => self.vertex_data(l, i).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// The index of the edge kind in the sealed graph, which the edges are read by.
+    pub fn find_edge_kind(&self, edge_kind: &EdgeKind) -> Option<usize> {
+        self.edge_kinds
+            .iter()
+            .position(|(k, _, _)| k == edge_kind)
+    }
+
+    /// The edges of the vertex in the direction, or all the out edges if the vertex is `None`, with
+    /// their positions in the adjacency to read their properties by.
+    pub fn edges(
+        self: &Arc<Self>, kind: usize, direction: EdgeDirection, vertex: Option<VertexId>,
+    ) -> SealedEdges {
+        let adjacency = self.adjacency(kind, direction);
+        let vertices = self.section(adjacency.vertices);
+        let positions = match vertex {
+            Some(vertex) => match search(vertices, vertex) {
+                Ok(j) => j..j + 1,
+                Err(_) => 0..0,
+            },
+            None => 0..vertices.len() / 8,
+        };
+        SealedEdges {
+            graph: self.clone(),
+            kind,
+            direction,
+            positions,
+            vertex: 0,
+            edges: 0..0,
+            decoder: BlockDecoder::default(),
+        }
+    }
+
+    /// The encoded properties of the edge at the position of the adjacency.
+    pub fn edge_data(&self, kind: usize, direction: EdgeDirection, i: usize) -> GraphResult<Cow<[u8]>> {
+        let adjacency = self.adjacency(kind, direction);
+        let offsets = self.section(adjacency.offsets);
+        let start = read_u64(offsets, i) as usize;
+        let end = read_u64(offsets, i + 1) as usize;
+        self.value(adjacency.data, start..end)
+    }
+
+    pub fn get_edge(&self, kind: usize, edge_id: EdgeId) -> GraphResult<Option<Cow<[u8]>>> {
+        let adjacency = self.adjacency(kind, EdgeDirection::Out);
+        let j = match search(self.section(adjacency.vertices), edge_id.src_id) {
+            Ok(j) => j,
+            Err(_) => return Ok(None),
+        };
+        let starts = self.section(adjacency.starts);
+        let ids = self.section(adjacency.ids);
+        let mut decoder = BlockDecoder::at(read_u64(self.section(adjacency.blocks), j) as usize);
+        for i in read_u64(starts, j) as usize..read_u64(starts, j + 1) as usize {
+            let (other, inner_id) = decoder.next(ids)?;
+            // the other ends are sorted in the key order
+            if key_order(other) > key_order(edge_id.dst_id) {
+                break;
+            }
+            if other == edge_id.dst_id && inner_id == edge_id.inner_id {
+                return self
+                    .edge_data(kind, EdgeDirection::Out, i)
+                    .map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn adjacency(&self, kind: usize, direction: EdgeDirection) -> &Adjacency {
+        let (_, out_adjacency, in_adjacency) = &self.edge_kinds[kind];
+        match direction {
+            EdgeDirection::In => in_adjacency,
+            _ => out_adjacency,
+        }
+    }
+
+    fn vertex_data(&self, l: &VertexLabel, i: usize) -> GraphResult<Cow<[u8]>> {
+        let offsets = self.section(l.offsets);
+        let start = read_u64(offsets, i) as usize;
+        let end = read_u64(offsets, i + 1) as usize;
+        self.value(l.data, start..end)
+    }
+
+    // the encoded properties in the range of the data section, decrypted by their offset in the file
+    fn value(&self, data: Section, range: Range<usize>) -> GraphResult<Cow<[u8]>> {
+        let offset = (data.offset + range.start) as u64;
+        let bytes = &self.section(data)[range];
+        match self.cipher.as_ref() {
+            Some(cipher) => Ok(Cow::Owned(cipher.decrypt(&offset.to_le_bytes(), bytes)?)),
+            None => Ok(Cow::Borrowed(bytes)),
+        }
+    }
+
+    fn section(&self, section: Section) -> &[u8] {
+        &self.mmap[section.offset..section.offset + section.len]
+    }
+}
+
+/// The edges of a direction of an edge kind, which decode the block of the ids of the edges of each
+/// vertex, and end at the first error of a block corrupt.
+pub struct SealedEdges {
+    graph: Arc<SealedGraph>,
+    kind: usize,
+    direction: EdgeDirection,
+    // the positions of the vertices of the blocks not decoded yet
+    positions: Range<usize>,
+    // the vertex of the block decoded, and the positions of its edges not decoded yet
+    vertex: VertexId,
+    edges: Range<usize>,
+    decoder: BlockDecoder,
+}
+
+impl Iterator for SealedEdges {
+    type Item = GraphResult<(EdgeId, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let adjacency = self.graph.adjacency(self.kind, self.direction);
+            if let Some(i) = self.edges.next() {
+                let (other, inner_id) = match self
+                    .decoder
+                    .next(self.graph.section(adjacency.ids))
+                {
+                    Ok(ids) => ids,
+                    Err(e) => {
+                        self.edges = 0..0;
+                        self.positions = 0..0;
+                        return Some(Err(e));
+                    }
+                };
+                let edge_id = match self.direction {
+                    EdgeDirection::In => EdgeId::new(other, self.vertex, inner_id),
+                    _ => EdgeId::new(self.vertex, other, inner_id),
+                };
+                return Some(Ok((edge_id, i)));
+            }
+            let j = self.positions.next()?;
+            let starts = self.graph.section(adjacency.starts);
+            self.vertex = read_i64(self.graph.section(adjacency.vertices), j);
+            self.edges = read_u64(starts, j) as usize..read_u64(starts, j + 1) as usize;
+            self.decoder = BlockDecoder::at(read_u64(self.graph.section(adjacency.blocks), j) as usize);
+        }
+    }
+}
+
+/// The decoder of a block of the ids of the edges, of the first other end, and the deltas of the
+/// other ends which follow, each followed by the inner id.
+#[derive(Clone, Copy, Default)]
+struct BlockDecoder {
+    cursor: usize,
+    prev: Option<u64>,
+}
+
+impl BlockDecoder {
+    fn at(cursor: usize) -> Self {
+        BlockDecoder { cursor, prev: None }
+    }
+
+    fn next(&mut self, bytes: &[u8]) -> GraphResult<(VertexId, i64)> {
+        let delta = read_varint(bytes, &mut self.cursor)?;
+        let inner_id = unzigzag(read_varint(bytes, &mut self.cursor)?);
+        let value = self
+            .prev
+            .map_or(delta, |prev| prev.wrapping_add(delta));
+        self.prev = Some(value);
+        Ok((value as VertexId, inner_id))
+    }
+}
+
+// the small inner ids, either positive or negative, in the small varints
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], cursor: &mut usize) -> GraphResult<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = match bytes.get(*cursor) {
+            // the 10th byte of a u64 holds its highest bit only
+            Some(byte) if shift < 63 || *byte <= 1 => *byte,
+            _ => {
+                let msg =
+                    format!("varint at {} of {} bytes is truncated or overflows", cursor, bytes.len());
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, read_varint));
+            }
+        };
+        *cursor += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+fn read_i64(bytes: &[u8], i: usize) -> i64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[i * 8..i * 8 + 8]);
+    i64::from_le_bytes(buf)
+}
+
+fn read_u64(bytes: &[u8], i: usize) -> u64 {
+    read_i64(bytes, i) as u64
+}
+
+// whether the offsets are ascending and within the bound
+fn check_offsets(offsets: &[u8], bound: u64) -> bool {
+    let mut prev = 0;
+    for i in 0..offsets.len() / 8 {
+        let offset = read_u64(offsets, i);
+        if offset < prev || offset > bound {
+            return false;
+        }
+        prev = offset;
+    }
+    true
+}
+
+// binary search the id in the ids sorted in the key order
+fn search(ids: &[u8], id: VertexId) -> Result<usize, usize> {
+    let (mut lo, mut hi) = (0, ids.len() / 8);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        match key_order(read_i64(ids, mid)).cmp(&key_order(id)) {
+            Ordering::Less => lo = mid + 1,
+            Ordering::Equal => return Ok(mid),
+            Ordering::Greater => hi = mid,
+        }
+    }
+    Err(lo)
+}
+
+fn io_err(e: std::io::Error, op: &str, path: &str) -> GraphError {
+    let msg = format!("{} {} failed: {}", op, path, e);
+    gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::storage::encryption::EnvKeyProvider;
+
+    #[test]
+    fn test_search() {
+        let mut ids = vec![];
+        for id in vec![1i64, 3, 10, -5, -1] {
+            ids.extend_from_slice(&id.to_le_bytes());
+        }
+        assert_eq!(search(&ids, 3), Ok(1));
+        assert_eq!(search(&ids, -5), Ok(3));
+        assert_eq!(search(&ids, -1), Ok(4));
+        assert_eq!(search(&ids, 2), Err(1));
+        // the negative ids are after the positive ones in the key order
+        assert_eq!(search(&ids, -6), Err(3));
+        assert_eq!(search(&[], 1), Err(0));
+    }
+
+    #[test]
+    fn test_delta_varint() {
+        let mut buf = vec![];
+        for value in vec![0u64, 1, 127, 128, 300, u64::max_value()] {
+            buf.clear();
+            write_varint(&mut buf, value);
+            let mut cursor = 0;
+            assert_eq!(read_varint(&buf, &mut cursor).unwrap(), value);
+            assert_eq!(cursor, buf.len());
+        }
+        assert_eq!(buf.len(), 10);
+        // truncated, and overflowing a u64
+        assert!(read_varint(&buf[..9], &mut 0).is_err());
+        assert!(read_varint(&[], &mut 0).is_err());
+        buf[9] = 0x02;
+        assert!(read_varint(&buf, &mut 0).is_err());
+        for value in vec![0i64, 1, -1, 63, -64, i64::max_value(), i64::min_value()] {
+            assert_eq!(unzigzag(zigzag(value)), value);
+        }
+        assert_eq!(zigzag(-1), 1);
+
+        // a block of the edges in the key order of the other ends, where the negative ids are the last
+        let edges = vec![(3i64, 1i64), (5, 2), (5, 9), (1000, -3), (-7, 0), (-1, 1 << 40)];
+        buf.clear();
+        let mut prev = None;
+        for (other, inner_id) in edges.iter() {
+            let other = key_order(*other);
+            write_varint(&mut buf, prev.map_or(other, |prev| other - prev));
+            write_varint(&mut buf, zigzag(*inner_id));
+            prev = Some(other);
+        }
+        let mut decoder = BlockDecoder::at(0);
+        let decoded: Vec<(VertexId, i64)> = edges
+            .iter()
+            .map(|_| decoder.next(&buf).unwrap())
+            .collect();
+        assert_eq!(decoded, edges);
+        assert_eq!(decoder.cursor, buf.len());
+        // the inner id of the last edge truncated
+        let mut decoder = BlockDecoder::at(0);
+        for _ in 0..edges.len() - 1 {
+            decoder.next(&buf[..buf.len() - 1]).unwrap();
+        }
+        assert!(decoder.next(&buf[..buf.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_open_invalid() {
+        let dir = "store_test/test_sealed_open_invalid";
+        let _ = fs::remove_dir_all(dir);
+        assert!(SealedGraph::open(dir, None).is_err());
+        let writer = SealedGraphWriter::create(dir, 1, None).unwrap();
+        writer.finish().unwrap();
+        let graph = SealedGraph::open(dir, None).unwrap();
+        assert_eq!(graph.snapshot_id(), 1);
+        assert_eq!(graph.vertex_count(1), 0);
+        assert!(graph.get_vertex(1, 1).unwrap().is_none());
+        fs::write(Path::new(dir).join(DATA_FILE), b"").unwrap();
+        assert!(SealedGraph::open(dir, None).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_corrupt() {
+        let dir = "store_test/test_sealed_open_corrupt";
+        let _ = fs::remove_dir_all(dir);
+        let mut writer = SealedGraphWriter::create(dir, 1, None).unwrap();
+        writer
+            .append_value(b"\0\0\0\x01vertex")
+            .unwrap();
+        let data = Section { offset: MAGIC.len(), len: 10 };
+        let ids = writer.write_i64s(&[7]).unwrap();
+        let offsets = writer.write_u64s(&[0, 10]).unwrap();
+        let label = VertexLabelManifest { label: 1, ids, offsets, data };
+        writer
+            .manifest
+            .vertex_labels
+            .push(label.clone());
+        // an edge kind of no edges, but a start past the edges
+        let mut adjacency = writer.write_adjacency(&[], data).unwrap();
+        adjacency.starts = writer.write_u64s(&[1]).unwrap();
+        writer
+            .manifest
+            .edge_kinds
+            .push(EdgeKindManifest {
+                edge_label: 2,
+                src_label: 1,
+                dst_label: 1,
+                out_adjacency: adjacency.clone(),
+                in_adjacency: adjacency,
+            });
+        let manifest = writer.manifest.clone();
+        writer.finish().unwrap();
+        assert!(SealedGraph::open(dir, None).is_err());
+
+        let write_manifest = |manifest: &Manifest| {
+            let json = serde_json::to_string(manifest).unwrap();
+            fs::write(Path::new(dir).join(MANIFEST_FILE), json).unwrap();
+        };
+        let mut valid = manifest.clone();
+        valid.edge_kinds.clear();
+        write_manifest(&valid);
+        let graph = SealedGraph::open(dir, None).unwrap();
+        assert_eq!(
+            graph
+                .get_vertex(1, 7)
+                .unwrap()
+                .unwrap()
+                .as_ref(),
+            b"\0\0\0\x01vertex"
+        );
+
+        // the offsets past the data, and the ids of no offsets
+        let mut corrupt = valid.clone();
+        corrupt.vertex_labels[0].data.len = 4;
+        write_manifest(&corrupt);
+        assert!(SealedGraph::open(dir, None).is_err());
+        let mut corrupt = valid.clone();
+        corrupt.vertex_labels[0].offsets.len = 0;
+        write_manifest(&corrupt);
+        assert!(SealedGraph::open(dir, None).is_err());
+        let mut corrupt = valid;
+        corrupt.encrypted = true;
+        write_manifest(&corrupt);
+        assert!(SealedGraph::open(dir, None).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted() {
+        let dir = "store_test/test_sealed_encrypted";
+        let _ = fs::remove_dir_all(dir);
+        let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+        let provider = EnvKeyProvider::parse(&format!("1:{}", key)).unwrap();
+        let cipher = Arc::new(ValueCipher::new(Arc::new(provider)).unwrap());
+        let value = b"\0\0\0\x01a secret vertex";
+        let mut writer = SealedGraphWriter::create(dir, 1, Some(cipher.clone())).unwrap();
+        writer.append_value(value).unwrap();
+        let data = Section { offset: MAGIC.len(), len: writer.offset - MAGIC.len() };
+        let ids = writer.write_i64s(&[7]).unwrap();
+        let offsets = writer
+            .write_u64s(&[0, data.len as u64])
+            .unwrap();
+        let label = VertexLabelManifest { label: 1, ids, offsets, data };
+        writer.manifest.vertex_labels.push(label);
+        writer.finish().unwrap();
+
+        let bytes = fs::read(Path::new(dir).join(DATA_FILE)).unwrap();
+        assert!(!bytes.windows(value.len()).any(|w| w == value));
+        assert!(SealedGraph::open(dir, None).is_err());
+        let graph = SealedGraph::open(dir, Some(cipher)).unwrap();
+        assert_eq!(
+            graph
+                .get_vertex(1, 7)
+                .unwrap()
+                .unwrap()
+                .as_ref(),
+            value
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+//! The adjacency of the edge labels in `store.edge.sort.properties`, e.g. `transfer:ts`, is indexed
+//! in the descending order of their sort properties besides their edge tables, so that the edges of
+//! a vertex in a range of the sort property, or the latest k of them, are read without scanning all
+//! its edges. The sort property should be an integral one, e.g. a timestamp in milliseconds.
+//!
+//! An index key is the edge key of `bin::edge_key` with the sort value after the vertex, under the
+//! prefix `!edge_table_prefix` of its edge table, so it's kept with the vertex and garbage collected
+//! with the table. The index is only added to, the entries of the edges deleted or updated are still
+//! visible at the older snapshots, so the entries are verified by the edges read at the snapshot.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use super::bin::edge_table_prefix;
+use super::table_manager::TableId;
+use crate::db::api::*;
+use crate::db::common::bytes::util::{UnsafeBytesReader, UnsafeBytesWriter};
+
+pub struct SortIndex {
+    // the names of the sort properties by the names of the edge labels
+    properties: HashMap<String, String>,
+    // the sort properties of the edge labels resolved by their type defs
+    resolved: RwLock<HashMap<LabelId, Option<PropertyId>>>,
+}
+
+impl SortIndex {
+    /// `conf` is the sort properties of the edge labels, e.g. `transfer:ts,knows:since`.
+    pub fn new(conf: &str) -> Self {
+        let mut properties = HashMap::new();
+        for item in conf
+            .split(',')
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+        {
+            match item.split_once(':') {
+                Some((label, property)) => {
+                    properties.insert(label.trim().to_owned(), property.trim().to_owned());
+                }
+                None => warn!("invalid edge sort property {}, which should be `label:property`", item),
+            }
+        }
+        SortIndex { properties, resolved: RwLock::new(HashMap::new()) }
+    }
+
+    /// The sort property of the edge label, `type_def` resolves the type def of the label if it's
+    /// not known yet.
+    pub fn get_sort_property<F: FnOnce() -> Option<TypeDef>>(
+        &self, label: LabelId, type_def: F,
+    ) -> Option<PropertyId> {
+        if self.properties.is_empty() {
+            return None;
+        }
+        if let Some(prop_id) = self.resolved.read().unwrap().get(&label) {
+            return *prop_id;
+        }
+        let type_def = type_def()?;
+        let prop_id = self
+            .properties
+            .get(&type_def.get_label())
+            .and_then(|name| {
+                let prop_def = type_def
+                    .get_prop_defs()
+                    .find(|prop_def| &prop_def.name == name);
+                match prop_def {
+                    Some(prop_def) if is_integral(&prop_def.r#type) => Some(prop_def.id),
+                    _ => {
+                        warn!("sort property {} of edge#{} is not an integral property", name, label);
+                        None
+                    }
+                }
+            });
+        self.resolved
+            .write()
+            .unwrap()
+            .insert(label, prop_id);
+        prop_id
+    }
+}
+
+/// The bounds and the limit of the edges read in the descending order of the sort property.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SortRange {
+    /// The inclusive lower bound, or unbounded.
+    pub lower: Option<i64>,
+    /// The inclusive upper bound, or unbounded.
+    pub upper: Option<i64>,
+    /// The number of the edges at most, e.g. the latest k edges, or unlimited if it's 0.
+    pub limit: usize,
+}
+
+impl SortRange {
+    pub fn contains(&self, value: i64) -> bool {
+        self.lower.map_or(true, |lower| value >= lower) && self.upper.map_or(true, |upper| value <= upper)
+    }
+}
+
+fn is_integral(r#type: &ValueType) -> bool {
+    matches!(r#type, ValueType::Short | ValueType::Int | ValueType::Long)
+}
+
+/// The sort value of the property, which should be an integral one.
+pub fn sort_value(value: &ValueRef) -> Option<i64> {
+    match value.get_type() {
+        ValueType::Short => value.get_short().ok().map(|v| v as i64),
+        ValueType::Int => value.get_int().ok().map(|v| v as i64),
+        ValueType::Long => value.get_long().ok(),
+        _ => None,
+    }
+}
+
+fn sort_index_table_prefix(table_id: TableId, direction: EdgeDirection) -> i64 {
+    !edge_table_prefix(table_id, direction)
+}
+
+/// The start of the index keys of the edge table, i.e. those of the in direction, the keys of both
+/// directions are within the 2 prefixes from it.
+pub fn sort_index_table_start(table_id: TableId) -> i64 {
+    sort_index_table_prefix(table_id, EdgeDirection::In)
+}
+
+// the sort values are encoded in the descending order of the big endian bytes
+fn encode_sort_value(value: i64) -> i64 {
+    !(value ^ i64::min_value())
+}
+
+pub fn sort_index_key(table_id: TableId, id: EdgeId, direction: EdgeDirection, value: i64) -> [u8; 40] {
+    let mut ret = [0u8; 40];
+    let mut writer = UnsafeBytesWriter::new(&mut ret);
+    let (x, y) = match direction {
+        EdgeDirection::In => (id.dst_id, id.src_id),
+        EdgeDirection::Out => (id.src_id, id.dst_id),
+        _ => unreachable!(),
+    };
+    writer.write_i64(0, sort_index_table_prefix(table_id, direction).to_be());
+    writer.write_i64(8, x.to_be());
+    writer.write_i64(16, encode_sort_value(value).to_be());
+    writer.write_i64(24, y.to_be());
+    writer.write_i64(32, id.inner_id.to_be());
+    ret
+}
+
+/// The prefix of the index keys of the vertex, and the key of them to scan from by the upper bound.
+pub fn sort_index_prefix(
+    table_id: TableId, vertex_id: VertexId, direction: EdgeDirection, upper: Option<i64>,
+) -> ([u8; 16], [u8; 24]) {
+    let mut start = [0u8; 24];
+    let mut writer = UnsafeBytesWriter::new(&mut start);
+    writer.write_i64(0, sort_index_table_prefix(table_id, direction).to_be());
+    writer.write_i64(8, vertex_id.to_be());
+    let upper = upper.unwrap_or(i64::max_value());
+    writer.write_i64(16, encode_sort_value(upper).to_be());
+    let mut prefix = [0u8; 16];
+    prefix.copy_from_slice(&start[0..16]);
+    (prefix, start)
+}
+
+/// return (edge_id, sort value)
+pub fn parse_sort_index_key(key: &[u8]) -> (EdgeId, i64) {
+    let reader = UnsafeBytesReader::new(key);
+    let prefix = reader.read_i64(0).to_be();
+    let id1 = reader.read_i64(8).to_be();
+    let value = !reader.read_i64(16).to_be() ^ i64::min_value();
+    let id2 = reader.read_i64(24).to_be();
+    let id = reader.read_i64(32).to_be();
+    // the prefix of the out direction is the complement of an even one
+    if (prefix & 1) == 1 {
+        (EdgeId::new(id1, id2, id), value)
+    } else {
+        (EdgeId::new(id2, id1, id), value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_index_key() {
+        let out_keys: Vec<[u8; 40]> = vec![i64::max_value(), 100, 0, -1, i64::min_value()]
+            .into_iter()
+            .map(|v| sort_index_key(3, EdgeId::new(10, 20, 1), EdgeDirection::Out, v))
+            .collect();
+        // descending by the sort values
+        assert!(out_keys.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(parse_sort_index_key(&out_keys[1]), (EdgeId::new(10, 20, 1), 100));
+        let in_key = sort_index_key(3, EdgeId::new(10, 20, 1), EdgeDirection::In, -1);
+        assert_eq!(parse_sort_index_key(&in_key), (EdgeId::new(10, 20, 1), -1));
+
+        let (prefix, start) = sort_index_prefix(3, 10, EdgeDirection::Out, Some(100));
+        assert!(out_keys[1].starts_with(&prefix));
+        assert!(out_keys[0][..] < start[..] && start[..] <= out_keys[1][..]);
+        // the keys of both directions are within the 2 prefixes from the table start
+        let table_start = sort_index_table_start(3).to_be_bytes();
+        let table_end = (sort_index_table_start(3) + 2).to_be_bytes();
+        for key in vec![out_keys[0], in_key] {
+            assert!(table_start[..] <= key[..] && key[..] < table_end[..]);
+        }
+    }
+
+    #[test]
+    fn test_sort_property() {
+        let index = SortIndex::new("knows:since, invalid");
+        let mut builder = TypeDefBuilder::new();
+        builder.set_label("knows");
+        builder.add_property(1, 1, "since".to_owned(), ValueType::Long, None, false, "".to_owned());
+        let type_def = builder.build();
+        assert_eq!(index.get_sort_property(1, || Some(type_def.clone())), Some(1));
+        // resolved once
+        assert_eq!(index.get_sort_property(1, || None), Some(1));
+        assert_eq!(index.get_sort_property(2, || None), None);
+
+        let mut builder = TypeDefBuilder::new();
+        builder.set_label("knows");
+        builder.add_property(1, 1, "since".to_owned(), ValueType::String, None, false, "".to_owned());
+        assert_eq!(index.get_sort_property(3, || Some(builder.build())), None);
+    }
+}
@@ -3,19 +3,29 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
-use std::sync::atomic::{AtomicIsize, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::thread;
+use std::time::Duration;
 
 use ::crossbeam_epoch as epoch;
 use protobuf::Message;
 
 use super::bin::*;
+use super::bulk_load::sst_file_name;
 use super::codec::*;
+use super::get_vertex_id_by_primary_keys;
+use super::id_mapping::{IdMapping, DEFAULT_BATCH_SIZE};
+use super::label_bitmap::LabelBitmap;
 use super::meta::*;
+use super::reverse_index::ReverseIndex;
+use super::sealed::{SealedGraph, SealedGraphWriter};
+use super::sort_index::*;
 use super::types::*;
 use crate::api::elem::Edge;
 use crate::api::Condition;
 use crate::api::ElemFilter;
+use crate::api::PartitionId;
 use crate::api::PropId;
 use crate::db::api::multi_version_graph::{GraphBackup, MultiVersionGraph};
 use crate::db::api::types::RocksEdge;
@@ -23,11 +33,15 @@ use crate::db::api::GraphErrorCode::{InvalidData, TypeNotFound};
 use crate::db::api::*;
 use crate::db::common::bytes::transform;
 use crate::db::graph::entity::{RocksEdgeImpl, RocksVertexImpl};
-use crate::db::graph::iter::{EdgeTypeScan, VertexTypeScan};
+use crate::db::graph::iter::{page_token, parse_page_token, EdgePage, EdgeTypeScan, VertexTypeScan};
+use crate::db::graph::replica::{FollowerState, ReplicationLog, DEFAULT_LOG_BYTES};
 use crate::db::graph::table_manager::Table;
+use crate::db::graph::throttle::{WriteAdmission, WriteBacklog, WriteThrottle};
+use crate::db::storage::metrics;
 use crate::db::storage::rocksdb::{RocksDB, RocksDBBackupEngine};
 use crate::db::storage::RawBytes;
 use crate::db::util::lock::GraphMutexLock;
+use crate::db::util::time::current_time_millis;
 
 pub struct GraphStore {
     config: GraphConfig,
@@ -40,6 +54,28 @@ pub struct GraphStore {
     // ensure all modification to graph is in ascending order of snapshot id
     si_guard: AtomicIsize,
     lock: GraphMutexLock<()>,
+    // writes are rejected once the store is drained for shutdown
+    draining: AtomicBool,
+    // writes are rejected while paused, e.g. in the final catch-up of a partition migration
+    writes_paused: AtomicBool,
+    // held by the writes in read mode, see `begin_write`
+    write_gate: RwLock<()>,
+    // the writes to be shipped to the followers if it's the leader of a replicated partition
+    replication_log: Option<ReplicationLog>,
+    // the progress of replication if it's a follower, which only accepts the writes of the leader
+    follower: Option<FollowerState>,
+    // the dictionary of the external string ids of vertices, see `id_mapping`
+    id_mapping: IdMapping,
+    // the labels of the edges stored only in the out direction, see `reverse_index`
+    reverse_index: ReverseIndex,
+    // the adjacency of the labels ordered by their sort properties, see `sort_index`
+    sort_index: SortIndex,
+    // the vertex tables holding any vertex, see `label_bitmap`
+    label_bitmap: LabelBitmap,
+    // throttles the writes by the backlog of flushes and compactions, see `throttle`
+    write_throttle: Option<WriteThrottle>,
+    // the sealed graph served read-only if `store.sealed.path` is set, see `sealed`
+    sealed: Option<Arc<SealedGraph>>,
 }
 
 pub struct GraphBackupEngine {
@@ -78,6 +114,10 @@ impl MultiVersionGraph for GraphStore {
         property_ids: Option<&Vec<PropertyId>>,
     ) -> GraphResult<Option<Self::V>> {
         debug!("get_vertex {:?}, {:?}, {:?}", vertex_id, label_id, property_ids);
+        let si = self.read_si(si);
+        if let Some(ref sealed) = self.sealed {
+            return self.get_sealed_vertex(sealed, si, vertex_id, label_id, property_ids);
+        }
         if let Some(label_id) = label_id {
             self.get_vertex_from_label(si, vertex_id, label_id, property_ids)
         } else {
@@ -101,6 +141,7 @@ impl MultiVersionGraph for GraphStore {
         property_ids: Option<&Vec<PropertyId>>,
     ) -> GraphResult<Option<Self::E>> {
         debug!("get_edge {:?}", edge_id);
+        let si = self.read_si(si);
         if let Some(relation) = edge_relation {
             self.get_edge_from_relation(si, edge_id, relation, property_ids)
         } else {
@@ -133,58 +174,8 @@ impl MultiVersionGraph for GraphStore {
         property_ids: Option<&Vec<PropertyId>>,
     ) -> GraphResult<Records<Self::V>> {
         debug!("scan_vertex {:?}, {:?}, {:?}", label_id, condition, property_ids);
-        let with_prop = property_ids.is_some();
-        let mut iter = match label_id {
-            Some(label_id) => {
-                match self
-                    .vertex_manager
-                    .get_type_info(si as i64, label_id as i32)
-                {
-                    Ok(vertex_type_info) => {
-                        let scan =
-                            VertexTypeScan::new(self.storage.clone(), si, vertex_type_info, with_prop);
-                        scan.into_iter()
-                    }
-                    Err(e) => {
-                        if let TypeNotFound = e.get_error_code() {
-                            Box::new(::std::iter::empty())
-                        } else {
-                            return Err(e);
-                        }
-                    }
-                }
-            }
-            None => {
-                let guard = epoch::pin();
-                let map = self.vertex_manager.get_map(&guard);
-                let map_ref = unsafe { map.deref() };
-                let mut iter = map_ref.values();
-                let mut res: Records<Self::V> = Box::new(::std::iter::empty());
-                while let Some(info) = next_vertex_type_info(si, &mut iter) {
-                    let label_iter =
-                        VertexTypeScan::new(self.storage.clone(), si, info, with_prop).into_iter();
-                    res = Box::new(res.chain(label_iter));
-                }
-                res
-            }
-        };
-
-        if let Some(condition) = condition.cloned() {
-            iter = Box::new(iter.filter(move |v| {
-                v.is_ok()
-                    && condition
-                        .filter_vertex(v.as_ref().unwrap())
-                        .unwrap_or(false)
-            }))
-        }
-        let columns = Self::parse_columns(property_ids);
-        Ok(Box::new(iter.map(move |v| match v {
-            Ok(mut v) => {
-                v.set_columns(columns.clone());
-                Ok(v)
-            }
-            Err(e) => Err(e),
-        })))
+        let labels: Vec<LabelId> = label_id.into_iter().collect();
+        self.scan_vertices(si, &labels, condition, property_ids)
     }
 
     fn scan_edge(
@@ -260,6 +251,7 @@ impl MultiVersionGraph for GraphStore {
             let err = gen_graph_err!(GraphErrorCode::InvalidOperation, msg, create_vertex_type);
             return Err(err);
         }
+        self.place_table(&type_def.get_label(), table_id)?;
         self.meta
             .create_vertex_type(si, schema_version, label_id, type_def, table_id)
             .and_then(|table| {
@@ -312,6 +304,16 @@ impl MultiVersionGraph for GraphStore {
             let err = gen_graph_err!(GraphErrorCode::InvalidOperation, msg, add_edge_kind, si, edge_kind);
             return Err(err);
         }
+        let label = self
+            .meta
+            .get_graph_def()
+            .lock()?
+            .label_to_types
+            .get(&edge_kind.get_edge_label_id())
+            .map(|type_def| type_def.get_label());
+        if let Some(label) = label {
+            self.place_table(&label, table_id)?;
+        }
         self.meta
             .add_edge_kind(si, schema_version, edge_kind, table_id)
             .and_then(|table| {
@@ -377,7 +379,10 @@ impl MultiVersionGraph for GraphStore {
         let res = self
             .vertex_manager
             .get_type(si, label)
-            .and_then(|info| self.do_insert_vertex_data(si, info.as_ref(), id, properties))
+            .and_then(|info| {
+                self.check_pk_conflict(si, info.as_ref(), id, properties)?;
+                self.do_insert_vertex_data(si, info.as_ref(), id, properties)
+            })
             .map(|_| self.update_si_guard(si));
 
         res_unwrap!(res, insert_overwrite_vertex, si, id, label)
@@ -397,14 +402,17 @@ impl MultiVersionGraph for GraphStore {
                 let decoder = info.get_decoder(si, version)?;
                 let mut old = decoder.decode_all(data);
                 merge_updates(&mut old, properties);
+                // the merged primary keys must still hash into the id, i.e. they are not updated;
                 let res = self
-                    .do_insert_vertex_data(si, info.as_ref(), id, &old)
+                    .check_composite_pk(label, id, &old)
+                    .and_then(|_| self.do_insert_vertex_data(si, info.as_ref(), id, &old))
                     .map(|_| self.update_si_guard(si));
                 res_unwrap!(res, insert_update_vertex, si, id, label)
             }
             None => {
                 let res = self
-                    .do_insert_vertex_data(si, info.as_ref(), id, properties)
+                    .check_composite_pk(label, id, properties)
+                    .and_then(|_| self.do_insert_vertex_data(si, info.as_ref(), id, properties))
                     .map(|_| self.update_si_guard(si));
                 res_unwrap!(res, insert_update_vertex, si, id, label)
             }
@@ -450,6 +458,9 @@ impl MultiVersionGraph for GraphStore {
     ) -> GraphResult<()> {
         debug!("insert_overwrite_edge");
         self.check_si_guard(si)?;
+        let no_properties = HashMap::<PropertyId, Value>::new();
+        let properties: &dyn PropertyMap =
+            if self.is_reverse_index_write(edge_kind, forward) { &no_properties } else { properties };
         let direction = if forward { EdgeDirection::Out } else { EdgeDirection::In };
         let res = self
             .edge_manager
@@ -464,6 +475,9 @@ impl MultiVersionGraph for GraphStore {
     ) -> GraphResult<()> {
         debug!("insert_update_edge, {:?}, {:?}, {}", id, edge_kind, forward);
         self.check_si_guard(si)?;
+        if self.is_reverse_index_write(edge_kind, forward) {
+            return self.insert_overwrite_edge(si, id, edge_kind, forward, properties);
+        }
 
         // if edge id is not 0, it may be existed edge id, or next edge id to be created.
 
@@ -504,6 +518,11 @@ impl MultiVersionGraph for GraphStore {
     ) -> GraphResult<()> {
         debug!("clear_edge_properties");
         self.check_si_guard(si)?;
+        // the reverse index entries have no property to clear
+        if self.is_reverse_index_write(edge_kind, forward) {
+            self.update_si_guard(si);
+            return Ok(());
+        }
 
         let mut complete_id = id;
         if id.inner_id == 0 {
@@ -544,6 +563,9 @@ impl MultiVersionGraph for GraphStore {
     fn delete_edge(&self, si: i64, id: EdgeId, edge_kind: &EdgeKind, forward: bool) -> GraphResult<()> {
         trace!("delete_edge {:?}, {:?}, {}", id, edge_kind, forward);
         self.check_si_guard(si)?;
+        if self.skip_in_edge_write(si, edge_kind, forward) {
+            return Ok(());
+        }
         let mut complete_id = id;
         if id.inner_id == 0 {
             let edge_id =
@@ -561,6 +583,8 @@ impl MultiVersionGraph for GraphStore {
     }
 
     fn gc(&self, si: i64) -> GraphResult<()> {
+        // the snapshots pinned by the long reads are kept
+        let si = super::pin::gc_snapshot(si);
         let vertex_tables = self.vertex_manager.gc(si)?;
         if !vertex_tables.is_empty() {
             info!("garbage collect vertex table {:?}", vertex_tables);
@@ -568,6 +592,7 @@ impl MultiVersionGraph for GraphStore {
         for vt in vertex_tables {
             let table_prefix = vertex_table_prefix(vt);
             self.delete_table_by_prefix(table_prefix, true)?;
+            self.label_bitmap.remove(vt);
         }
         let edge_tables = self.edge_manager.gc(si)?;
         if !edge_tables.is_empty() {
@@ -576,6 +601,7 @@ impl MultiVersionGraph for GraphStore {
         for et in edge_tables {
             let out_table_prefix = edge_table_prefix(et, EdgeDirection::Out);
             self.delete_table_by_prefix(out_table_prefix, false)?;
+            self.delete_table_by_prefix(sort_index_table_start(et), false)?;
         }
         Ok(())
     }
@@ -611,8 +637,11 @@ impl MultiVersionGraph for GraphStore {
         }
         self.meta
             .commit_data_load(si, schema_version, target, table_id)?;
-        let data_file_path =
-            format!("{}/{}/part-r-{:0>5}.sst", self.data_download_root, unique_path, partition_id);
+        let data_file_path = format!(
+            "{}/{}",
+            self.get_data_load_dir(unique_path),
+            sst_file_name(partition_id as PartitionId)
+        );
         info!("committing data load from path {}", data_file_path);
 
         if Path::new(data_file_path.as_str()).exists() {
@@ -633,6 +662,8 @@ impl MultiVersionGraph for GraphStore {
             let info = self
                 .vertex_manager
                 .get_type(si, target.label_id)?;
+            // probed again with the vertices loaded
+            self.label_bitmap.remove(table_id);
             info.online_table(Table::new(si, table_id))?;
             info!("online vertex. labelId {}, tableId {}, si {}", target.label_id, table_id, si);
         }
@@ -660,6 +691,7 @@ impl GraphStore {
             "rocksdb" => {
                 let res = RocksDB::open(config.get_storage_options()).and_then(|db| {
                     let storage = Arc::new(db);
+                    metrics::register_rocksdb(path, &storage);
                     Self::init(config, storage, path)
                 });
                 res_unwrap!(res, open, config, path)
@@ -680,7 +712,14 @@ impl GraphStore {
     }
 
     pub fn try_catch_up_with_primary(&self) -> GraphResult<()> {
-        self.storage.try_catch_up_with_primary()
+        self.storage.try_catch_up_with_primary()?;
+        // the tables written by the primary since;
+        self.label_bitmap.forget_empty();
+        // the tables placed by the primary since;
+        for (table_id, cf) in self.meta.read_table_placements()? {
+            self.storage.place_table(table_id, &cf)?;
+        }
+        Ok(())
     }
 
     pub fn compact(&self) -> GraphResult<()> {
@@ -688,12 +727,183 @@ impl GraphStore {
     }
 
     pub fn reopen(&self, wait_sec: u64) -> GraphResult<()> {
-        self.storage.reopen(wait_sec)
+        self.storage.reopen(wait_sec)?;
+        self.label_bitmap.forget_empty();
+        Ok(())
+    }
+
+    /// Seal the graph at `si`, e.g. after its bulk load, into the flat arrays at the path, which are
+    /// served read-only by the store opened with `store.sealed.path`, see `sealed`.
+    pub fn seal(&self, si: SnapshotId, path: &str) -> GraphResult<()> {
+        let mut writer = SealedGraphWriter::create(path, si, self.storage.cipher())?;
+        for info in self.get_vertex_infos(si, &[])? {
+            if let Some(table) = info.get_table(si) {
+                writer.add_vertex_table(&self.storage, si, info.get_label() as LabelId, &table)?;
+            }
+        }
+        for edge_info in self.get_edge_infos(si, &[])? {
+            for kind_info in edge_info.lock().iter_kinds() {
+                if !kind_info.is_alive_at(si) {
+                    continue;
+                }
+                if let Some(table) = kind_info.get_table(si) {
+                    let edge_kind: EdgeKind = kind_info.get_type().into();
+                    writer.add_edge_table(&self.storage, si, &edge_kind, &table)?;
+                }
+            }
+        }
+        writer.finish()?;
+        info!("graph store at {} is sealed at si#{} to {}", self.data_root, si, path);
+        Ok(())
+    }
+
+    /// Whether the store serves a sealed graph read-only.
+    pub fn is_sealed(&self) -> bool {
+        self.sealed.is_some()
+    }
+
+    /// The vertex ids of the external string ids of the label, allocated for those not mapped yet; the
+    /// external ids should be owned by this partition, see `id_mapping::external_id_key`.
+    pub fn map_external_ids(&self, label: LabelId, external_ids: &[&str]) -> GraphResult<Vec<VertexId>> {
+        let _write = self.begin_write().ok_or_else(|| {
+            let msg = "graph store is draining, paused or sealed, writes are rejected".to_owned();
+            gen_graph_err!(GraphErrorCode::InvalidOperation, msg, map_external_ids, label)
+        })?;
+        self.id_mapping
+            .get_or_allocate(label, external_ids)
+    }
+
+    pub fn lookup_by_external_id(
+        &self, label: LabelId, external_id: &str,
+    ) -> GraphResult<Option<VertexId>> {
+        self.id_mapping.lookup(label, external_id)
+    }
+
+    pub fn get_external_id(&self, label: LabelId, id: VertexId) -> GraphResult<Option<String>> {
+        self.id_mapping.reverse_lookup(label, id)
+    }
+
+    /// Resize the block cache shared by the column families, see `store.rocksdb.block.cache.mb`.
+    pub fn set_block_cache_capacity(&self, capacity: usize) -> GraphResult<()> {
+        self.storage.set_block_cache_capacity(capacity)
+    }
+
+    /// Stop accepting writes and flush the memtables, so that the store can be shut down without
+    /// replaying the wal when it's opened again.
+    pub fn drain(&self) -> GraphResult<()> {
+        self.draining.store(true, Ordering::SeqCst);
+        info!("graph store at {} is draining", self.data_root);
+        self.wait_writes();
+        self.storage.flush()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Reject the writes until `resume_writes`, and wait until the running writes are done.
+    pub fn pause_writes(&self) {
+        self.writes_paused.store(true, Ordering::SeqCst);
+        self.wait_writes();
+    }
+
+    pub fn resume_writes(&self) {
+        self.writes_paused
+            .store(false, Ordering::SeqCst);
+    }
+
+    /// Start a write, which holds the returned guard until it's done, or `None` if the writes are
+    /// rejected, i.e. the store is draining, the writes are paused or the store serves a sealed graph.
+    pub fn begin_write(&self) -> Option<RwLockReadGuard<()>> {
+        let guard = self.write_gate.read().unwrap();
+        if self.draining.load(Ordering::SeqCst)
+            || self.writes_paused.load(Ordering::SeqCst)
+            || self.sealed.is_some()
+        {
+            None
+        } else {
+            Some(guard)
+        }
+    }
+
+    /// Whether to write now, later or retry after a while, under the backlog of flushes and compactions,
+    /// see `WriteThrottle`. The writes are always admitted if the throttle is disabled.
+    pub fn admit_write(&self) -> GraphResult<WriteAdmission> {
+        match self.write_throttle {
+            Some(ref throttle) => {
+                let admission = throttle.admit(&WriteBacklog::of(&self.storage)?);
+                match admission {
+                    WriteAdmission::Admit => {}
+                    WriteAdmission::Delay(_) => metrics::STORE_WRITES_DELAYED.inc(),
+                    WriteAdmission::Reject(_) => metrics::STORE_WRITES_REJECTED.inc(),
+                }
+                Ok(admission)
+            }
+            None => Ok(WriteAdmission::Admit),
+        }
+    }
+
+    fn wait_writes(&self) {
+        let _gate = self.write_gate.write().unwrap();
+    }
+
+    /// Keep the batch written at `si` for the followers, if it's a leader.
+    pub fn append_replication_log(&self, si: SnapshotId, batch: &[u8]) {
+        if let Some(ref log) = self.replication_log {
+            log.append(si, batch);
+        }
+    }
+
+    /// The latest snapshot of the leader and the batches written after `since`, see
+    /// `ReplicationLog::read_since`.
+    pub fn read_replication_log(
+        &self, since: SnapshotId, max_bytes: usize,
+    ) -> GraphResult<(SnapshotId, Vec<u8>)> {
+        match self.replication_log {
+            Some(ref log) => {
+                let latest = log.latest_si();
+                Ok((latest, log.read_since(since, max_bytes)?))
+            }
+            None => {
+                let msg = format!("graph store at {} is not a replication leader", self.data_root);
+                Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, read_replication_log, since))
+            }
+        }
+    }
+
+    pub fn is_follower(&self) -> bool {
+        self.follower.is_some()
+    }
+
+    pub fn get_follower_state(&self) -> Option<&FollowerState> {
+        self.follower.as_ref()
+    }
+
+    pub(crate) fn get_storage(&self) -> &Arc<RocksDB> {
+        &self.storage
+    }
+
+    /// Re-encrypt the stored values with the current key of the key provider in background.
+    pub fn rotate_encryption_key(&self) -> GraphResult<()> {
+        let storage = self.storage.clone();
+        let res = thread::Builder::new()
+            .name("encryption-rewrite".to_owned())
+            .spawn(move || match storage.rewrite_encrypted() {
+                Ok(count) => info!("encryption key rotated, {} values rewritten", count),
+                Err(e) => error!("rotate encryption key failed: {:?}", e),
+            });
+        res.map(|_| ()).map_err(|e| {
+            let msg = format!("spawn encryption rewrite thread failed: {}", e);
+            gen_graph_err!(GraphErrorCode::ExternalStorageError, msg, rotate_encryption_key)
+        })
     }
 
     fn init(config: &GraphConfig, storage: Arc<RocksDB>, path: &str) -> GraphResult<Self> {
         let meta = Meta::new(storage.clone());
         let (vertex_manager, edge_manager) = res_unwrap!(meta.recover(), init)?;
+        for (table_id, cf) in res_unwrap!(meta.read_table_placements(), init)? {
+            storage.place_table(table_id, &cf)?;
+        }
         let data_root = path.to_string();
         let mut download_root = "".to_string();
         download_root = config
@@ -703,6 +913,49 @@ impl GraphStore {
         if download_root.is_empty() {
             download_root = format!("{}/../{}", data_root, "download");
         }
+        let (replication_log, follower) = match config.get_storage_option("store.replication.role") {
+            Some(role) if role == "leader" => {
+                let log_bytes = config
+                    .get_storage_option("store.replication.log.mb")
+                    .map(|s| s.parse::<usize>().unwrap() << 20)
+                    .unwrap_or(DEFAULT_LOG_BYTES);
+                (Some(ReplicationLog::new(log_bytes)), None)
+            }
+            Some(role) if role == "follower" => (None, Some(FollowerState::new())),
+            _ => (None, None),
+        };
+        let partition_id = config
+            .get_storage_option("store.partition.id")
+            .map(|s| s.parse::<u32>().unwrap())
+            .unwrap_or(0);
+        let partition_count = config
+            .get_storage_option("partition.count")
+            .map(|s| s.parse::<u32>().unwrap())
+            .unwrap_or(1);
+        let batch_size = config
+            .get_storage_option("store.id.mapping.batch.size")
+            .map(|s| s.parse::<i64>().unwrap())
+            .unwrap_or(DEFAULT_BATCH_SIZE);
+        let id_mapping = IdMapping::new(storage.clone(), partition_id, partition_count, batch_size);
+        let reverse_index = ReverseIndex::new(
+            config
+                .get_storage_option("store.edge.out.only.labels")
+                .map(|s| s.as_str())
+                .unwrap_or(""),
+        );
+        let sort_index = SortIndex::new(
+            config
+                .get_storage_option("store.edge.sort.properties")
+                .map(|s| s.as_str())
+                .unwrap_or(""),
+        );
+
+        let label_bitmap = LabelBitmap::new(storage.clone());
+        let write_throttle = WriteThrottle::from_config(config)?;
+        let sealed = match config.get_storage_option("store.sealed.path") {
+            Some(sealed_path) => Some(Arc::new(SealedGraph::open(sealed_path, storage.cipher())?)),
+            None => None,
+        };
 
         let ret = GraphStore {
             config: config.clone(),
@@ -714,6 +967,17 @@ impl GraphStore {
             data_download_root: download_root,
             si_guard: AtomicIsize::new(0),
             lock: GraphMutexLock::new(()),
+            draining: AtomicBool::new(false),
+            writes_paused: AtomicBool::new(false),
+            write_gate: RwLock::new(()),
+            replication_log,
+            follower,
+            id_mapping,
+            reverse_index,
+            sort_index,
+            label_bitmap,
+            write_throttle,
+            sealed,
         };
         Ok(ret)
     }
@@ -766,6 +1030,7 @@ impl GraphStore {
                 .and_then(|_| {
                     let ts = si - table.start_si;
                     let key = vertex_key(table.id, id, ts);
+                    self.label_bitmap.mark(table.id);
                     self.storage.put(&key, &buf)
                 });
         }
@@ -774,6 +1039,408 @@ impl GraphStore {
         Err(err)
     }
 
+    /// The ids of the primary keys of the vertex label, if it's identified by a composite primary
+    /// key. The labels of a single primary key are not checked, as their vertices may be stored by
+    /// the ids mapped from external ids, see `IdMapping`.
+    fn get_composite_pk_ids(&self, label: LabelId) -> GraphResult<Option<Vec<PropertyId>>> {
+        let graph_def = self.meta.get_graph_def().lock()?;
+        let pk_ids = graph_def
+            .label_to_types
+            .get(&label)
+            .map(|type_def| type_def.get_pk_prop_ids())
+            .filter(|pk_ids| pk_ids.len() > 1);
+        Ok(pk_ids)
+    }
+
+    /// Check that the id is derived from the composite primary key of the vertex, and return the
+    /// values of the primary key.
+    fn check_composite_pk(
+        &self, label: LabelId, id: VertexId, properties: &dyn PropertyMap,
+    ) -> GraphResult<Option<Vec<(PropertyId, Vec<u8>)>>> {
+        let pk_ids = match self.get_composite_pk_ids(label)? {
+            Some(pk_ids) => pk_ids,
+            None => return Ok(None),
+        };
+        let mut pks = Vec::with_capacity(pk_ids.len());
+        for pk_id in pk_ids {
+            match properties.get(pk_id) {
+                Some(v) => pks.push((pk_id, v.as_bytes().to_vec())),
+                None => {
+                    let msg =
+                        format!("primary key #{} of vertex {} of label {} is missing", pk_id, id, label);
+                    return Err(gen_graph_err!(InvalidData, msg, check_composite_pk, label, id));
+                }
+            }
+        }
+        if get_vertex_id_by_primary_keys(label, pks.iter().map(|(_, pk)| pk)) != id {
+            let msg = format!("vertex id {} is not derived from the primary key of label {}", id, label);
+            return Err(gen_graph_err!(InvalidData, msg, check_composite_pk, label, id));
+        }
+        Ok(Some(pks))
+    }
+
+    /// Enforce the uniqueness of composite primary keys, the vertex overwritten must have the same
+    /// primary key as the one stored of the id, which differs only if their hashes collide.
+    fn check_pk_conflict(
+        &self, si: SnapshotId, info: &VertexTypeInfo, id: VertexId, properties: &dyn PropertyMap,
+    ) -> GraphResult<()> {
+        let label = info.get_label();
+        let pks = match self.check_composite_pk(label, id, properties)? {
+            Some(pks) => pks,
+            None => return Ok(()),
+        };
+        if let Some(data) = self.get_vertex_data(si, id, info)? {
+            let data = data.as_slice();
+            let decoder = info.get_decoder(si, get_codec_version(data))?;
+            let old = decoder.decode_all(data);
+            let conflict = pks.iter().any(|(pk_id, pk)| {
+                old.get(pk_id)
+                    .map_or(false, |v| v.as_bytes() != pk.as_slice())
+            });
+            if conflict {
+                let msg = format!("vertex {} of label {} exists with a different primary key", id, label);
+                let err = gen_graph_err!(GraphErrorCode::InvalidOperation, msg, check_pk_conflict, si, id);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the edges of the label are stored only in the out direction, see `ReverseIndex`.
+    pub fn is_out_only_edge_label(&self, label: LabelId) -> bool {
+        self.reverse_index.is_out_only(label, || {
+            let graph_def = self.meta.get_graph_def().lock().ok()?;
+            graph_def
+                .label_to_types
+                .get(&label)
+                .map(|type_def| type_def.get_label())
+        })
+    }
+
+    /// The edge labels alive at `si` which are stored only in the out direction.
+    pub fn get_out_only_edge_labels(&self, si: SnapshotId) -> Vec<LabelId> {
+        let guard = epoch::pin();
+        let inner = self.edge_manager.get_inner(&guard);
+        let edge_mgr = unsafe { inner.deref() };
+        let mut iter = edge_mgr.get_all_edges();
+        let mut labels = Vec::new();
+        while let Some(info) = next_edge_info(si, &mut iter) {
+            if self.is_out_only_edge_label(info.get_label()) {
+                labels.push(info.get_label());
+            }
+        }
+        labels
+    }
+
+    /// The sort property of the edge label, by which its adjacency is ordered, see `SortIndex`.
+    pub fn get_sort_property(&self, label: LabelId) -> Option<PropertyId> {
+        self.sort_index.get_sort_property(label, || {
+            let graph_def = self.meta.get_graph_def().lock().ok()?;
+            graph_def.label_to_types.get(&label).cloned()
+        })
+    }
+
+    /// The edges of the label from (`Out`) or to (`In`) the vertex within the range of the sort
+    /// property of the label, in its descending order, e.g. the latest k edges by a timestamp. The
+    /// in edges of the labels stored only in the out direction have no sort property to sort by.
+    pub fn get_sorted_edges(
+        &self, si: SnapshotId, vertex_id: VertexId, direction: EdgeDirection, label: LabelId,
+        range: &SortRange, property_ids: Option<&Vec<PropertyId>>,
+    ) -> GraphResult<Vec<RocksEdgeImpl>> {
+        let prop_id = match self.get_sort_property(label) {
+            Some(prop_id) => prop_id,
+            None => {
+                let msg = format!("edge#{} has no sort property", label);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, get_sorted_edges, label));
+            }
+        };
+        if direction == EdgeDirection::Both {
+            let msg = "the sorted edges of both directions".to_owned();
+            return Err(gen_graph_err!(GraphErrorCode::NotSupported, msg, get_sorted_edges, label));
+        }
+        if direction == EdgeDirection::In && self.is_out_only_edge_label(label) {
+            let msg = format!("the in edges of edge#{} are stored without the sort property", label);
+            return Err(gen_graph_err!(GraphErrorCode::NotSupported, msg, get_sorted_edges, label));
+        }
+        let columns = Self::parse_columns(property_ids);
+        let info = match self.edge_manager.get_edge_info(si, label) {
+            Ok(info) => info,
+            Err(e) if matches!(e.get_error_code(), TypeNotFound) => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        let mut edges = Vec::new();
+        let edge_kinds = info.lock();
+        let mut edge_kind_iter = edge_kinds.iter_kinds();
+        while let Some(edge_kind_info) = edge_kind_iter.next() {
+            if edge_kind_info.is_alive_at(si) {
+                let edge_kind = edge_kind_info.get_type().into();
+                let info = self
+                    .edge_manager
+                    .get_edge_kind(si, &edge_kind)?;
+                let sorted =
+                    self.scan_sort_index(si, &info, vertex_id, direction, prop_id, range, &columns)?;
+                edges.extend(sorted);
+            }
+        }
+        // the edges of each kind are sorted, but not those of different kinds
+        edges.sort_by(|(x, _), (y, _)| y.cmp(x));
+        if range.limit > 0 {
+            edges.truncate(range.limit);
+        }
+        Ok(edges
+            .into_iter()
+            .map(|(_, edge)| edge)
+            .collect())
+    }
+
+    /// A page of at most `page_size` edges of the labels, or of all the labels if they're empty, from
+    /// (`Out`) or to (`In`) the vertex, which is read after the page of `token`, or from the first
+    /// edge without it. The pages should be read at the same snapshot, they're read in the order of
+    /// the edge tables and the edge keys, and each page seeks to its start rather than skips the
+    /// edges before, so the edges of a super vertex are read in bounded memory and time per page.
+    pub fn get_edges_page(
+        &self, si: SnapshotId, vertex_id: VertexId, direction: EdgeDirection, labels: &[LabelId],
+        token: Option<&[u8]>, page_size: usize, property_ids: Option<&Vec<PropertyId>>,
+    ) -> GraphResult<EdgePage> {
+        if direction == EdgeDirection::Both || page_size == 0 {
+            let msg = format!("invalid page of {} edges of direction {:?}", page_size, direction);
+            return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, get_edges_page, vertex_id));
+        }
+        let after = match token {
+            Some(token) => Some(parse_page_token(token)?),
+            None => None,
+        };
+        let mut tables = Vec::new();
+        for edge_info in self.get_edge_infos(si, labels)? {
+            let edge_kinds = edge_info.lock();
+            for edge_kind_info in edge_kinds.iter_kinds() {
+                if edge_kind_info.is_alive_at(si) {
+                    if let Some(table) = edge_kind_info.get_table(si) {
+                        tables.push((table, edge_kind_info.clone()));
+                    }
+                }
+            }
+        }
+        tables.sort_by_key(|(table, _)| table.id);
+
+        let columns = Self::parse_columns(property_ids);
+        let mut edges = Vec::with_capacity(page_size);
+        let mut last = None;
+        for (table, info) in tables {
+            let prefix = edge_prefix(table.id, vertex_id, direction);
+            let (start, mut prev_id) = match after {
+                Some((table_id, _)) if table.id < table_id => continue,
+                // the versions of the last edge of the previous page are after its key of ts 0
+                Some((table_id, edge_id)) if table.id == table_id => {
+                    (edge_key(table.id, edge_id, direction, 0).to_vec(), Some(edge_id))
+                }
+                _ => (prefix.to_vec(), None),
+            };
+            let data_ts = si - table.start_si;
+            let mut iter = self.storage.scan_prefix_from(&prefix, &start)?;
+            while let Some((key, val)) = iter.next() {
+                if !key.starts_with(&prefix) {
+                    break;
+                }
+                let (edge_id, ts) = parse_edge_key(key);
+                if ts > data_ts || prev_id == Some(edge_id) {
+                    continue;
+                }
+                prev_id = Some(edge_id);
+                if val.len() < 4 {
+                    continue;
+                }
+                if edges.len() == page_size {
+                    let next_token = last.map(|(table_id, edge_id)| page_token(table_id, edge_id));
+                    return Ok(EdgePage { edges, next_token });
+                }
+                let decoder = info.get_decoder(si, get_codec_version(val))?;
+                let edge_kind = info.get_type().into();
+                edges.push(RocksEdgeImpl::with_columns(
+                    edge_id,
+                    edge_kind,
+                    Some(decoder),
+                    RawBytes::new(val),
+                    columns.clone(),
+                ));
+                last = Some((table.id, edge_id));
+            }
+        }
+        Ok(EdgePage { edges, next_token: None })
+    }
+
+    /// Scan the vertices of the labels, or of all the labels if they're empty, in one pass, where the
+    /// labels of no vertex in the partition are skipped, see `label_bitmap`.
+    pub fn scan_vertices(
+        &self, si: SnapshotId, labels: &[LabelId], condition: Option<&Condition>,
+        property_ids: Option<&Vec<PropertyId>>,
+    ) -> GraphResult<Records<RocksVertexImpl>> {
+        let si = self.read_si(si);
+        let with_prop = property_ids.is_some();
+        let mut iter: Records<RocksVertexImpl> = Box::new(::std::iter::empty());
+        for info in self.get_vertex_infos(si, labels)? {
+            if let Some(ref sealed) = self.sealed {
+                let label = info.get_label() as LabelId;
+                iter =
+                    Box::new(iter.chain(scan_sealed_vertices(sealed.clone(), si, info, label, with_prop)));
+                continue;
+            }
+            let table = match info.get_table(si) {
+                Some(table) => table,
+                None => continue,
+            };
+            if self.label_bitmap.is_occupied(table.id)? {
+                let scan = VertexTypeScan::new(self.storage.clone(), si, info, with_prop);
+                iter = Box::new(iter.chain(scan.into_iter()));
+            }
+        }
+
+        if let Some(condition) = condition.cloned() {
+            iter = Box::new(iter.filter(move |v| {
+                v.is_ok()
+                    && condition
+                        .filter_vertex(v.as_ref().unwrap())
+                        .unwrap_or(false)
+            }))
+        }
+        let columns = Self::parse_columns(property_ids);
+        Ok(Box::new(iter.map(move |v| match v {
+            Ok(mut v) => {
+                v.set_columns(columns.clone());
+                Ok(v)
+            }
+            Err(e) => Err(e),
+        })))
+    }
+
+    // the vertex type infos of the labels alive at `si`, or of all the labels if they're empty;
+    fn get_vertex_infos(
+        &self, si: SnapshotId, labels: &[LabelId],
+    ) -> GraphResult<Vec<Arc<VertexTypeInfo>>> {
+        let mut infos = Vec::new();
+        if labels.is_empty() {
+            let guard = epoch::pin();
+            let map = self.vertex_manager.get_map(&guard);
+            let map_ref = unsafe { map.deref() };
+            let mut iter = map_ref.values();
+            while let Some(info) = next_vertex_type_info(si, &mut iter) {
+                infos.push(info);
+            }
+        } else {
+            for label in labels {
+                match self.vertex_manager.get_type_info(si, *label) {
+                    Ok(info) => infos.push(info),
+                    Err(e) if matches!(e.get_error_code(), TypeNotFound) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(infos)
+    }
+
+    // the edge infos of the labels alive at `si`, or of all the labels if they're empty;
+    fn get_edge_infos(&self, si: SnapshotId, labels: &[LabelId]) -> GraphResult<Vec<Arc<EdgeInfo>>> {
+        let mut infos = Vec::new();
+        if labels.is_empty() {
+            let guard = epoch::pin();
+            let inner = self.edge_manager.get_inner(&guard);
+            let edge_mgr = unsafe { inner.deref() };
+            let mut iter = edge_mgr.get_all_edges();
+            while let Some(info) = next_edge_info(si, &mut iter) {
+                infos.push(info);
+            }
+        } else {
+            for label in labels {
+                match self.edge_manager.get_edge_info(si, *label) {
+                    Ok(info) => infos.push(info),
+                    Err(e) if matches!(e.get_error_code(), TypeNotFound) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(infos)
+    }
+
+    fn scan_sort_index(
+        &self, si: SnapshotId, info: &EdgeKindInfo, vertex_id: VertexId, direction: EdgeDirection,
+        prop_id: PropertyId, range: &SortRange, columns: &Option<HashSet<PropId>>,
+    ) -> GraphResult<Vec<(i64, RocksEdgeImpl)>> {
+        let mut edges = Vec::new();
+        let table = match info.get_table(si) {
+            Some(table) => table,
+            None => return Ok(edges),
+        };
+        let (prefix, start) = sort_index_prefix(table.id, vertex_id, direction, range.upper);
+        let mut iter = self.storage.scan_from(&start)?;
+        while let Some((key, _)) = iter.next() {
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            let (edge_id, value) = parse_sort_index_key(key);
+            if range.lower.map_or(false, |lower| value < lower) {
+                break;
+            }
+            // the entry is stale if the edge is deleted or its sort property is updated at `si`
+            if let Some((current, edge)) =
+                self.read_sorted_edge(si, info, edge_id, direction, prop_id, columns)?
+            {
+                if current == value {
+                    edges.push((value, edge));
+                    if edges.len() == range.limit {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(edges)
+    }
+
+    // the edge at `si` with the value of its sort property;
+    fn read_sorted_edge(
+        &self, si: SnapshotId, info: &EdgeKindInfo, edge_id: EdgeId, direction: EdgeDirection,
+        prop_id: PropertyId, columns: &Option<HashSet<PropId>>,
+    ) -> GraphResult<Option<(i64, RocksEdgeImpl)>> {
+        if let Some(data) = self.get_edge_data(si, edge_id, info, direction)? {
+            let decoder = info.get_decoder(si, get_codec_version(&data))?;
+            if let Some(value) = decoder
+                .decode_property(&data, prop_id)
+                .and_then(|v| sort_value(&v))
+            {
+                let edge_kind = info.get_type().into();
+                let edge = RocksEdgeImpl::with_columns(
+                    edge_id,
+                    edge_kind,
+                    Some(decoder),
+                    RawBytes::new(&data),
+                    columns.clone(),
+                );
+                return Ok(Some((value, edge)));
+            }
+        }
+        Ok(None)
+    }
+
+    // the in-direction writes of the edges stored only in the out direction write their reverse index
+    // entries, which have no property;
+    fn is_reverse_index_write(&self, edge_kind: &EdgeKind, forward: bool) -> bool {
+        !forward && self.is_out_only_edge_label(edge_kind.edge_label_id)
+    }
+
+    // the in edges of the labels stored only in the out direction are their reverse index entries,
+    // which are read without properties;
+    fn scan_edge_label(
+        &self, si: SnapshotId, edge_info: Arc<EdgeInfo>, vertex_id: Option<VertexId>,
+        direction: EdgeDirection, with_prop: bool,
+    ) -> GraphResult<Records<RocksEdgeImpl>> {
+        let with_prop = with_prop
+            && !(direction == EdgeDirection::In && self.is_out_only_edge_label(edge_info.get_label()));
+        if let Some(ref sealed) = self.sealed {
+            return Ok(scan_sealed_edges(sealed, si, &edge_info, vertex_id, direction, with_prop));
+        }
+        let scan = EdgeTypeScan::new(self.storage.clone(), si, edge_info, vertex_id, direction, with_prop);
+        Ok(scan.into_iter())
+    }
+
     fn do_insert_edge_data(
         &self, si: SnapshotId, edge_id: EdgeId, info: &EdgeKindInfo, direction: EdgeDirection,
         properties: &dyn PropertyMap,
@@ -788,6 +1455,20 @@ impl GraphStore {
                     let ts = si - table.start_si;
                     let key = edge_key(table.id, edge_id, direction, ts);
                     self.storage.put(&key, &buf)
+                })
+                .and_then(|_| {
+                    let label = info.get_type().edge_label_id;
+                    let value = self
+                        .get_sort_property(label)
+                        .and_then(|prop_id| properties.get(prop_id))
+                        .and_then(|v| sort_value(&v));
+                    match value {
+                        Some(value) => {
+                            let key = sort_index_key(table.id, edge_id, direction, value);
+                            self.storage.put(&key, &[])
+                        }
+                        None => Ok(()),
+                    }
                 });
         }
         let msg = format!("table not found at {} of {:?}", si, info.get_type());
@@ -795,7 +1476,24 @@ impl GraphStore {
         Err(err)
     }
 
+    // place the new table of the label in the column family configured for it, which is remembered
+    // before the table is created, so it's never written elsewhere;
+    fn place_table(&self, label: &str, table_id: i64) -> GraphResult<()> {
+        if let Some(cf) = self.storage.column_family_of_label(label) {
+            self.meta.write_table_placement(table_id, cf)?;
+            self.storage.place_table(table_id, cf)?;
+            info!("table {} of label {} is placed in column family {}", table_id, label, cf);
+        }
+        Ok(())
+    }
+
     fn check_si_guard(&self, si: SnapshotId) -> GraphResult<()> {
+        if self.sealed.is_some() {
+            let msg =
+                format!("graph store at {} serves a sealed graph, writes are rejected", self.data_root);
+            let err = gen_graph_err!(GraphErrorCode::InvalidOperation, msg);
+            return Err(err);
+        }
         let guard = self.si_guard.load(Ordering::Relaxed) as SnapshotId;
         if si < guard {
             let msg = format!("si#{} is less than current si_guard#{}", si, guard);
@@ -810,9 +1508,46 @@ impl GraphStore {
             .store(si as isize, Ordering::Relaxed);
     }
 
+    /// The latest snapshot written, which a write can still be applied at without being rejected.
+    pub fn get_write_si(&self) -> SnapshotId {
+        self.si_guard.load(Ordering::Relaxed) as SnapshotId
+    }
+
+    /// The latest snapshot of which all the writes are applied, i.e. the one before the latest snapshot
+    /// written, as more writes of it may come; or `MAX_SI` if nothing is written since it's opened.
+    pub fn get_readable_si(&self) -> SnapshotId {
+        match self.si_guard.load(Ordering::Relaxed) as SnapshotId {
+            0 => MAX_SI,
+            guard => guard - 1,
+        }
+    }
+
     pub fn ingest(&self, data_path: &str) -> GraphResult<()> {
         let p = [data_path];
-        self.storage.load(&p)
+        self.storage.load(&p)?;
+        // the vertices ingested are not marked
+        self.label_bitmap.forget_empty();
+        Ok(())
+    }
+
+    /// The dir `commit_data_load` ingests the sst files of the load from, where the files built by
+    /// `PartitionSstBuilder` should be.
+    pub fn get_data_load_dir(&self, unique_path: &str) -> String {
+        format!("{}/{}", self.data_download_root, unique_path)
+    }
+
+    /// The encoder of the properties of the target at the snapshot of the data load.
+    pub fn get_data_load_encoder(&self, si: SnapshotId, target: &DataLoadTarget) -> GraphResult<Encoder> {
+        if target.src_label_id > 0 {
+            let edge_kind = EdgeKind::new(target.label_id, target.src_label_id, target.dst_label_id);
+            self.edge_manager
+                .get_edge_kind(si, &edge_kind)?
+                .get_encoder(si)
+        } else {
+            self.vertex_manager
+                .get_type_info(si, target.label_id)?
+                .get_encoder(si)
+        }
     }
 
     pub fn get_graph_def(&self) -> GraphResult<GraphDef> {
@@ -820,6 +1555,108 @@ impl GraphStore {
         Ok((&*graph_def).clone())
     }
 
+    /// The ids of the primary key properties of the label, in the order they are hashed into the
+    /// vertex id, or `None` if the label doesn't exist.
+    pub fn get_pk_prop_ids(&self, label: LabelId) -> GraphResult<Option<Vec<PropertyId>>> {
+        let graph_def = self.meta.get_graph_def().lock()?;
+        Ok(graph_def
+            .label_to_types
+            .get(&label)
+            .map(|type_def| type_def.get_pk_prop_ids()))
+    }
+
+    /// The definitions of the primary key properties of the label, in the order they are hashed into
+    /// the vertex id, or `None` if the label doesn't exist.
+    pub fn get_pk_prop_defs(&self, label: LabelId) -> GraphResult<Option<Vec<PropDef>>> {
+        let graph_def = self.meta.get_graph_def().lock()?;
+        Ok(graph_def
+            .label_to_types
+            .get(&label)
+            .map(|type_def| {
+                type_def
+                    .get_pk_prop_ids()
+                    .into_iter()
+                    .filter_map(|id| type_def.get_prop_def(id).cloned())
+                    .collect()
+            }))
+    }
+
+    /// Get the edge of the label by its id, which is a point lookup in each kind of the label rather
+    /// than in each kind of all the labels as `get_edge` does without the edge kind.
+    pub fn get_edge_by_label(
+        &self, si: SnapshotId, edge_id: EdgeId, label: LabelId, property_ids: Option<&Vec<PropertyId>>,
+    ) -> GraphResult<Option<RocksEdgeImpl>> {
+        let info = match self.edge_manager.get_edge_info(si, label) {
+            Ok(info) => info,
+            Err(e) if matches!(e.get_error_code(), TypeNotFound) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let edge_kinds = info.lock();
+        let mut edge_kind_iter = edge_kinds.iter_kinds();
+        while let Some(edge_kind_info) = edge_kind_iter.next() {
+            if edge_kind_info.is_alive_at(si) {
+                if let Some(edge) = self.get_edge_from_relation(
+                    si,
+                    edge_id,
+                    &edge_kind_info.get_type().into(),
+                    property_ids,
+                )? {
+                    return Ok(Some(edge));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Record the offsets of `source` consumed by the data written at `si`. It must be called after
+    /// all the data of `si` has been written, so the offsets never run ahead of the store.
+    pub fn commit_ingest_offsets(
+        &self, si: SnapshotId, source: &str, offsets: Vec<(i32, i64)>,
+    ) -> GraphResult<()> {
+        self.check_si_guard(si)?;
+        let offsets = IngestOffsets { si, offsets };
+        self.meta
+            .write_ingest_offsets(source, &offsets)
+            .map(|_| self.update_si_guard(si))
+    }
+
+    pub fn get_ingest_offsets(&self, source: &str) -> GraphResult<Option<IngestOffsets>> {
+        self.meta.read_ingest_offsets(source)
+    }
+
+    /// Get the dedup entry of a client supplied dedup id, `None` means the mutation identified by it
+    /// hasn't been written or its entry has been purged.
+    pub fn get_dedup_entry(&self, dedup_id: &str) -> GraphResult<Option<DedupEntry>> {
+        self.meta.read_dedup_id(dedup_id)
+    }
+
+    /// Record the dedup ids of mutations written at `si`. It must be called after the mutations
+    /// are written, so a crash in between results in a retry instead of a lost write.
+    pub fn record_dedup_ids(&self, si: SnapshotId, dedup_ids: &[String]) -> GraphResult<()> {
+        self.meta
+            .write_dedup_ids(si, dedup_ids, current_time_millis())
+    }
+
+    /// Purge the dedup entries older than `retention`, returns the number of purged entries.
+    pub fn purge_dedup_ids(&self, retention: Duration) -> GraphResult<usize> {
+        let before_ms = current_time_millis().saturating_sub(retention.as_millis() as u64);
+        self.meta.purge_dedup_ids(before_ms)
+    }
+
+    /// Approximate size in bytes of all the active and unflushed memtables.
+    pub fn get_memtable_size(&self) -> GraphResult<u64> {
+        self.storage
+            .get_int_property("rocksdb.cur-size-all-mem-tables")
+            .map(|size| size.unwrap_or(0))
+    }
+
+    /// Whether rocksdb has stopped writes, e.g. too many memtables are waiting to be flushed.
+    pub fn is_write_stopped(&self) -> GraphResult<bool> {
+        self.storage
+            .get_int_property("rocksdb.is-write-stopped")
+            .map(|stopped| stopped.unwrap_or(0) > 0)
+    }
+
     fn get_vertex_from_label(
         &self, si: SnapshotId, vertex_id: VertexId, label_id: LabelId,
         property_ids: Option<&Vec<PropertyId>>,
@@ -856,10 +1693,31 @@ impl GraphStore {
         property_ids: Option<&Vec<PropertyId>>,
     ) -> GraphResult<Option<RocksEdgeImpl>> {
         debug!("get_edge_from_relation, {:?}, {:?}", edge_id, edge_relation);
-        let si = si as i64;
+        let si = self.read_si(si);
         let info = self
             .edge_manager
             .get_edge_kind(si, &edge_relation.into())?;
+        if let Some(ref sealed) = self.sealed {
+            let data = match sealed.find_edge_kind(edge_relation) {
+                Some(kind) => sealed.get_edge(kind, edge_id)?,
+                None => None,
+            };
+            return match data {
+                Some(data) => {
+                    let decoder = info.get_decoder(si, get_codec_version(&data))?;
+                    let columns = Self::parse_columns(property_ids);
+                    let edge = RocksEdgeImpl::with_columns(
+                        edge_id,
+                        info.get_type().into(),
+                        Some(decoder),
+                        RawBytes::new(&data),
+                        columns,
+                    );
+                    Ok(Some(edge))
+                }
+                None => Ok(None),
+            };
+        }
         if let Some(table) = info.get_table(si) {
             let key = edge_key(table.id, edge_id.into(), EdgeDirection::Out, si - table.start_si);
             let mut iter = self.storage.scan_from(&key)?;
@@ -887,6 +1745,7 @@ impl GraphStore {
         label_id: Option<LabelId>, condition: Option<&Condition>, property_ids: Option<&Vec<PropertyId>>,
     ) -> GraphResult<Records<RocksEdgeImpl>> {
         debug!("query_edges {:?}, {:?}, {:?} {:?}", vertex_id, label_id, property_ids, direction);
+        let si = self.read_si(si);
         let with_prop = property_ids.is_some();
         let mut iter = match label_id {
             Some(label_id) => {
@@ -895,15 +1754,7 @@ impl GraphStore {
                     .get_edge_info(si as i64, label_id as i32)
                 {
                     Ok(edge_info) => {
-                        let scan = EdgeTypeScan::new(
-                            self.storage.clone(),
-                            si,
-                            edge_info,
-                            vertex_id,
-                            direction,
-                            with_prop,
-                        );
-                        scan.into_iter()
+                        self.scan_edge_label(si, edge_info, vertex_id, direction, with_prop)?
                     }
                     Err(e) => {
                         if let TypeNotFound = e.get_error_code() {
@@ -921,9 +1772,7 @@ impl GraphStore {
                 let mut iter = edge_mgr.get_all_edges();
                 let mut res: Records<RocksEdgeImpl> = Box::new(::std::iter::empty());
                 while let Some(info) = next_edge_info(si, &mut iter) {
-                    let label_iter =
-                        EdgeTypeScan::new(self.storage.clone(), si, info, vertex_id, direction, with_prop)
-                            .into_iter();
+                    let label_iter = self.scan_edge_label(si, info, vertex_id, direction, with_prop)?;
                     res = Box::new(res.chain(label_iter));
                 }
                 res
@@ -954,6 +1803,36 @@ impl GraphStore {
         self.storage.delete_range(&start_key, &end_key)
     }
 
+    // the snapshot to read at, which is the snapshot sealed at if the store serves a sealed graph;
+    fn read_si(&self, si: SnapshotId) -> SnapshotId {
+        self.sealed
+            .as_ref()
+            .map_or(si, |sealed| sealed.snapshot_id())
+    }
+
+    fn get_sealed_vertex(
+        &self, sealed: &SealedGraph, si: SnapshotId, vertex_id: VertexId, label_id: Option<LabelId>,
+        property_ids: Option<&Vec<PropertyId>>,
+    ) -> GraphResult<Option<RocksVertexImpl>> {
+        let labels: Vec<LabelId> = label_id.into_iter().collect();
+        for info in self.get_vertex_infos(si, &labels)? {
+            let label = info.get_label() as LabelId;
+            if let Some(data) = sealed.get_vertex(label, vertex_id)? {
+                let decoder = info.get_decoder(si, get_codec_version(&data))?;
+                let columns = Self::parse_columns(property_ids);
+                let vertex = RocksVertexImpl::with_columns(
+                    vertex_id,
+                    label,
+                    Some(decoder),
+                    RawBytes::new(&data),
+                    columns,
+                );
+                return Ok(Some(vertex));
+            }
+        }
+        Ok(None)
+    }
+
     fn parse_columns(property_ids: Option<&Vec<PropertyId>>) -> Option<HashSet<PropId>> {
         property_ids.map(|v| {
             v.to_owned()
@@ -1041,10 +1920,51 @@ fn clear_props(old: &mut HashMap<PropertyId, ValueRef>, prop_ids: &[PropertyId])
     }
 }
 
+// the entities read from a sealed graph copy their bytes out of the mapped file, as they own them;
+fn scan_sealed_vertices(
+    sealed: Arc<SealedGraph>, si: SnapshotId, info: Arc<VertexTypeInfo>, label: LabelId, with_prop: bool,
+) -> Records<RocksVertexImpl> {
+    Box::new((0..sealed.vertex_count(label)).map(move |i| -> GraphResult<RocksVertexImpl> {
+        let (id, data) = sealed.vertex_at(label, i)?;
+        let decoder = if with_prop { Some(info.get_decoder(si, get_codec_version(&data))?) } else { None };
+        Ok(RocksVertexImpl::new(id, label, decoder, RawBytes::new(&data)))
+    }))
+}
+
+fn scan_sealed_edges(
+    sealed: &Arc<SealedGraph>, si: SnapshotId, edge_info: &EdgeInfo, vertex_id: Option<VertexId>,
+    direction: EdgeDirection, with_prop: bool,
+) -> Records<RocksEdgeImpl> {
+    let mut iter: Records<RocksEdgeImpl> = Box::new(::std::iter::empty());
+    for kind_info in edge_info.lock().iter_kinds() {
+        if !kind_info.is_alive_at(si) {
+            continue;
+        }
+        let edge_kind: EdgeKind = kind_info.get_type().into();
+        let kind = match sealed.find_edge_kind(&edge_kind) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let sealed = sealed.clone();
+        let kind_info = kind_info.clone();
+        let edges = sealed.edges(kind, direction, vertex_id);
+        let edges = edges.map(move |edge| -> GraphResult<RocksEdgeImpl> {
+            let (edge_id, i) = edge?;
+            let data = sealed.edge_data(kind, direction, i)?;
+            let decoder =
+                if with_prop { Some(kind_info.get_decoder(si, get_codec_version(&data))?) } else { None };
+            Ok(RocksEdgeImpl::new(edge_id, edge_kind.clone(), decoder, RawBytes::new(&data)))
+        });
+        iter = Box::new(iter.chain(edges));
+    }
+    iter
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::tests;
     use super::*;
+    use crate::db::api::types::{Property, PropertyReader, PropertyValue, RocksVertex};
     use crate::db::util::fs;
 
     #[test]
@@ -1125,6 +2045,487 @@ mod tests {
         do_test(path, |graph| tests::graph::test_si_guard(graph));
     }
 
+    #[test]
+    fn test_composite_primary_key() {
+        let path = "test_composite_primary_key";
+        do_test(path, |graph| {
+            let label = 1;
+            let mut builder = TypeDefBuilder::new();
+            builder.version(1).set_label_id(label);
+            builder.add_property(1, 1, "name".to_string(), ValueType::String, None, true, String::new());
+            builder.add_property(2, 2, "age".to_string(), ValueType::Int, None, true, String::new());
+            builder.add_property(3, 3, "city".to_string(), ValueType::String, None, false, String::new());
+            graph
+                .create_vertex_type(1, 1, label, &builder.build(), 1)
+                .unwrap();
+
+            let mut properties = HashMap::new();
+            properties.insert(1, Value::string("tom"));
+            properties.insert(2, Value::int(20));
+            let pks = vec![properties[&1].as_bytes().to_vec(), properties[&2].as_bytes().to_vec()];
+            let id = get_vertex_id_by_primary_keys(label, pks.iter());
+            graph
+                .insert_overwrite_vertex(2, id, label, &properties)
+                .unwrap();
+            assert!(graph
+                .get_vertex(2, id, Some(label), None)
+                .unwrap()
+                .is_some());
+
+            // the id must be derived from the composite primary key, which must be complete;
+            assert!(graph
+                .insert_overwrite_vertex(3, id + 1, label, &properties)
+                .is_err());
+            let mut partial = HashMap::new();
+            partial.insert(1, Value::string("tom"));
+            assert!(graph
+                .insert_overwrite_vertex(3, id, label, &partial)
+                .is_err());
+
+            // the other properties can be updated, but not the primary key;
+            let mut updates = HashMap::new();
+            updates.insert(3, Value::string("beijing"));
+            graph
+                .insert_update_vertex(3, id, label, &updates)
+                .unwrap();
+            let mut updates = HashMap::new();
+            updates.insert(2, Value::int(21));
+            assert!(graph
+                .insert_update_vertex(4, id, label, &updates)
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn test_get_edge_by_label() {
+        let path = "test_get_edge_by_label";
+        do_test(path, |graph| {
+            let type_def = TypeDef::new_test();
+            graph
+                .create_vertex_type(1, 1, 1, &type_def, 1)
+                .unwrap();
+            graph
+                .create_vertex_type(2, 2, 2, &type_def, 2)
+                .unwrap();
+            graph
+                .create_edge_type(3, 3, 3, &type_def)
+                .unwrap();
+            let edge_kind = EdgeKind::new(3, 1, 2);
+            graph
+                .add_edge_kind(4, 4, &edge_kind, 3)
+                .unwrap();
+            let edge_id = EdgeId::new(10, 20, 1);
+            graph
+                .insert_overwrite_edge(5, edge_id, &edge_kind, true, &HashMap::<PropertyId, Value>::new())
+                .unwrap();
+
+            let edge = graph
+                .get_edge_by_label(5, edge_id, 3, None)
+                .unwrap()
+                .unwrap();
+            assert_eq!(RocksEdge::get_edge_id(&edge), &edge_id);
+            // the parallel edges and the edges of the other labels are not matched
+            assert!(graph
+                .get_edge_by_label(5, EdgeId::new(10, 20, 2), 3, None)
+                .unwrap()
+                .is_none());
+            assert!(graph
+                .get_edge_by_label(5, edge_id, 4, None)
+                .unwrap()
+                .is_none());
+        });
+    }
+
+    #[test]
+    fn test_out_only_edge_label() {
+        let path = "store_test/test_out_only_edge_label";
+        fs::rmr(path).unwrap();
+        let mut builder = GraphConfigBuilder::new();
+        builder.set_storage_engine("rocksdb");
+        builder.add_storage_option("store.data.path", path);
+        builder.add_storage_option("store.edge.out.only.labels", "knows");
+        let graph = GraphStore::open(&builder.build()).unwrap();
+
+        let type_def = TypeDef::new_test();
+        graph
+            .create_vertex_type(1, 1, 1, &type_def, 1)
+            .unwrap();
+        let mut edge_def = TypeDefBuilder::new();
+        edge_def.set_label("knows");
+        edge_def.add_property(1, 1, "weight".to_owned(), ValueType::Long, None, false, "".to_owned());
+        graph
+            .create_edge_type(2, 2, 2, &edge_def.build())
+            .unwrap();
+        let edge_kind = EdgeKind::new(2, 1, 1);
+        graph
+            .add_edge_kind(3, 3, &edge_kind, 3)
+            .unwrap();
+        assert!(graph.is_out_only_edge_label(2));
+        assert_eq!(graph.get_out_only_edge_labels(3), vec![2]);
+
+        let mut props = HashMap::<PropertyId, Value>::new();
+        props.insert(1, Value::long(5));
+        let e1 = EdgeId::new(10, 20, 1);
+        let e2 = EdgeId::new(11, 20, 2);
+        for (si, edge_id) in vec![(4, e1), (5, e2)] {
+            graph
+                .insert_overwrite_edge(si, edge_id, &edge_kind, true, &props)
+                .unwrap();
+            graph
+                .insert_overwrite_edge(si, edge_id, &edge_kind, false, &props)
+                .unwrap();
+        }
+        let in_edges = |si| {
+            let mut ids: Vec<EdgeId> = graph
+                .get_in_edges(si, 20, Some(2), None, None)
+                .unwrap()
+                .map(|e| *RocksEdge::get_edge_id(&e.unwrap()))
+                .collect();
+            ids.sort_by_key(|id| id.inner_id);
+            ids
+        };
+        assert_eq!(in_edges(4), vec![e1]);
+        assert_eq!(in_edges(5), vec![e1, e2]);
+
+        let e3 = EdgeId::new(12, 20, 3);
+        graph
+            .insert_update_edge(6, e3, &edge_kind, false, &props)
+            .unwrap();
+        for forward in vec![true, false] {
+            graph
+                .delete_edge(7, e1, &edge_kind, forward)
+                .unwrap();
+        }
+        assert_eq!(in_edges(6), vec![e1, e2, e3]);
+        assert_eq!(in_edges(7), vec![e2, e3]);
+
+        // the in edges are the reverse index entries without properties, unlike the out edges
+        let prop_ids = vec![1];
+        let in_edge = graph
+            .get_in_edges(7, 20, Some(2), None, Some(&prop_ids))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert!(in_edge.get_property(1).is_none());
+        let out_edge = graph
+            .get_out_edges(7, 11, Some(2), None, Some(&prop_ids))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let weight = out_edge.get_property(1).unwrap();
+        assert_eq!(*weight.get_property_value(), PropertyValue::Long(5));
+        assert!(graph
+            .get_sorted_edges(7, 20, EdgeDirection::In, 2, &SortRange::default(), None)
+            .is_err());
+        drop(graph);
+        fs::rmr(path).unwrap();
+    }
+
+    #[test]
+    fn test_sorted_edges() {
+        let path = "store_test/test_sorted_edges";
+        fs::rmr(path).unwrap();
+        let mut builder = GraphConfigBuilder::new();
+        builder.set_storage_engine("rocksdb");
+        builder.add_storage_option("store.data.path", path);
+        builder.add_storage_option("store.edge.sort.properties", "transfer:ts");
+        let graph = GraphStore::open(&builder.build()).unwrap();
+
+        let type_def = TypeDef::new_test();
+        graph
+            .create_vertex_type(1, 1, 1, &type_def, 1)
+            .unwrap();
+        let mut edge_def = TypeDefBuilder::new();
+        edge_def.set_label("transfer");
+        edge_def.add_property(1, 1, "ts".to_owned(), ValueType::Long, None, false, "".to_owned());
+        graph
+            .create_edge_type(2, 2, 2, &edge_def.build())
+            .unwrap();
+        let edge_kind = EdgeKind::new(2, 1, 1);
+        graph
+            .add_edge_kind(3, 3, &edge_kind, 3)
+            .unwrap();
+        assert_eq!(graph.get_sort_property(2), Some(1));
+
+        let insert = |si, edge_id, ts| {
+            let mut props = HashMap::new();
+            props.insert(1, Value::long(ts));
+            for forward in vec![true, false] {
+                graph
+                    .insert_update_edge(si, edge_id, &edge_kind, forward, &props)
+                    .unwrap();
+            }
+        };
+        let e1 = EdgeId::new(10, 20, 1);
+        let e2 = EdgeId::new(10, 21, 2);
+        let e3 = EdgeId::new(10, 22, 3);
+        insert(4, e1, 300);
+        insert(4, e2, 100);
+        insert(4, e3, 200);
+        let sorted = |si, vertex_id, direction, lower, upper, limit| -> Vec<EdgeId> {
+            let range = SortRange { lower, upper, limit };
+            graph
+                .get_sorted_edges(si, vertex_id, direction, 2, &range, None)
+                .unwrap()
+                .iter()
+                .map(|e| *RocksEdge::get_edge_id(e))
+                .collect()
+        };
+        assert_eq!(sorted(4, 10, EdgeDirection::Out, None, None, 0), vec![e1, e3, e2]);
+        assert_eq!(sorted(4, 10, EdgeDirection::Out, Some(150), Some(250), 0), vec![e3]);
+        // the latest edge
+        assert_eq!(sorted(4, 10, EdgeDirection::Out, None, None, 1), vec![e1]);
+        assert_eq!(sorted(4, 21, EdgeDirection::In, None, None, 0), vec![e2]);
+
+        // the edges updated or deleted are sorted by their values at the snapshots
+        insert(5, e2, 400);
+        graph
+            .delete_edge(6, e1, &edge_kind, true)
+            .unwrap();
+        assert_eq!(sorted(4, 10, EdgeDirection::Out, None, None, 0), vec![e1, e3, e2]);
+        assert_eq!(sorted(5, 10, EdgeDirection::Out, None, None, 0), vec![e2, e1, e3]);
+        assert_eq!(sorted(6, 10, EdgeDirection::Out, None, None, 2), vec![e2, e3]);
+        assert!(graph
+            .get_sorted_edges(6, 10, EdgeDirection::Out, 1, &SortRange::default(), None)
+            .is_err());
+        drop(graph);
+        fs::rmr(path).unwrap();
+    }
+
+    #[test]
+    fn test_edges_page() {
+        let path = "test_edges_page";
+        do_test(path, |graph| {
+            let type_def = TypeDef::new_test();
+            graph
+                .create_vertex_type(1, 1, 1, &type_def, 1)
+                .unwrap();
+            for label in vec![2, 3] {
+                graph
+                    .create_edge_type(label as i64, label as i64, label, &type_def)
+                    .unwrap();
+                graph
+                    .add_edge_kind(
+                        label as i64 + 2,
+                        label as i64 + 2,
+                        &EdgeKind::new(label, 1, 1),
+                        label as i64 + 10,
+                    )
+                    .unwrap();
+            }
+            let props = HashMap::<PropertyId, Value>::new();
+            let mut expected = Vec::new();
+            for label in vec![2, 3] {
+                for dst_id in 20..25 {
+                    let edge_id = EdgeId::new(10, dst_id, label as i64);
+                    graph
+                        .insert_overwrite_edge(6, edge_id, &EdgeKind::new(label, 1, 1), true, &props)
+                        .unwrap();
+                    expected.push(edge_id);
+                }
+            }
+            // the versions of the edges of a page are not read in the next page
+            graph
+                .insert_overwrite_edge(7, EdgeId::new(10, 22, 2), &EdgeKind::new(2, 1, 1), true, &props)
+                .unwrap();
+            graph
+                .delete_edge(7, EdgeId::new(10, 23, 2), &EdgeKind::new(2, 1, 1), true)
+                .unwrap();
+            expected.retain(|edge_id| *edge_id != EdgeId::new(10, 23, 2));
+
+            let mut edge_ids = Vec::new();
+            let mut token = None;
+            let mut pages = 0;
+            loop {
+                let page = graph
+                    .get_edges_page(7, 10, EdgeDirection::Out, &[], token.as_deref(), 3, None)
+                    .unwrap();
+                assert!(page.edges.len() <= 3);
+                edge_ids.extend(
+                    page.edges
+                        .iter()
+                        .map(|e| *RocksEdge::get_edge_id(e)),
+                );
+                pages += 1;
+                if !page.is_truncated() {
+                    break;
+                }
+                token = page.next_token;
+            }
+            assert_eq!(edge_ids, expected);
+            assert_eq!(pages, 3);
+
+            let page = graph
+                .get_edges_page(7, 10, EdgeDirection::Out, &[3], None, 5, None)
+                .unwrap();
+            assert_eq!(page.edges.len(), 5);
+            assert!(!page.is_truncated());
+            assert!(graph
+                .get_edges_page(7, 10, EdgeDirection::Out, &[], Some(&[1u8, 2][..]), 3, None)
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn test_scan_vertices() {
+        let path = "store_test/test_scan_vertices";
+        fs::rmr(path).unwrap();
+        let graph = create_empty_graph(path);
+        let type_def = TypeDef::new_test();
+        for label in 1..=3 {
+            graph
+                .create_vertex_type(label as i64, label as i64, label, &type_def, label as i64)
+                .unwrap();
+        }
+        let props = HashMap::<PropertyId, Value>::new();
+        for (label, id) in vec![(1, 10), (1, -5), (3, 7)] {
+            graph
+                .insert_overwrite_vertex(4, id, label, &props)
+                .unwrap();
+        }
+        let scan = |si: SnapshotId, labels: &[LabelId]| {
+            let mut ids: Vec<(LabelId, VertexId)> = graph
+                .scan_vertices(si, labels, None, None)
+                .unwrap()
+                .map(|v| {
+                    let v = v.unwrap();
+                    (RocksVertex::get_label_id(&v), RocksVertex::get_vertex_id(&v))
+                })
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(scan(4, &[1, 2, 3]), vec![(1, -5), (1, 10), (3, 7)]);
+        assert_eq!(scan(4, &[]), scan(4, &[1, 2, 3]));
+        assert_eq!(scan(4, &[2]), vec![]);
+        // the table of no vertex is skipped
+        assert!(!graph.label_bitmap.is_occupied(2).unwrap());
+
+        // the vertices written after the table is probed mark it
+        graph
+            .insert_overwrite_vertex(5, 20, 2, &props)
+            .unwrap();
+        assert!(graph.label_bitmap.is_occupied(2).unwrap());
+        assert_eq!(scan(5, &[2]), vec![(2, 20)]);
+        graph.delete_vertex(6, -5, 1).unwrap();
+        assert_eq!(scan(6, &[1]), vec![(1, 10)]);
+        assert_eq!(scan(5, &[1]), vec![(1, -5), (1, 10)]);
+        drop(graph);
+
+        // the tables are probed again when the store is opened
+        let graph = create_empty_graph(path);
+        assert!(graph.label_bitmap.is_occupied(1).unwrap());
+        assert!(graph.label_bitmap.is_occupied(2).unwrap());
+        assert_eq!(scan(5, &[1, 2, 3]), vec![(1, -5), (1, 10), (2, 20), (3, 7)]);
+        drop(graph);
+        fs::rmr(path).unwrap();
+    }
+
+    #[test]
+    fn test_seal() {
+        let path = "store_test/test_seal";
+        let sealed_path = "store_test/test_seal_sealed";
+        fs::rmr(path).unwrap();
+        fs::rmr(sealed_path).unwrap();
+        let mut builder = GraphConfigBuilder::new();
+        builder.set_storage_engine("rocksdb");
+        builder.add_storage_option("store.data.path", path);
+        builder.add_storage_option("store.edge.out.only.labels", "knows");
+        let graph = GraphStore::open(&builder.build()).unwrap();
+        let type_def = TypeDef::new_test();
+        graph
+            .create_vertex_type(1, 1, 1, &type_def, 1)
+            .unwrap();
+        let mut edge_def = TypeDefBuilder::new();
+        edge_def.set_label("knows");
+        graph
+            .create_edge_type(2, 2, 2, &edge_def.build())
+            .unwrap();
+        let edge_kind = EdgeKind::new(2, 1, 1);
+        graph
+            .add_edge_kind(3, 3, &edge_kind, 3)
+            .unwrap();
+        let props = HashMap::<PropertyId, Value>::new();
+        for id in vec![10, -5, 20, 11] {
+            graph
+                .insert_overwrite_vertex(4, id, 1, &props)
+                .unwrap();
+        }
+        let (e1, e2, e3) = (EdgeId::new(10, 20, 1), EdgeId::new(11, 20, 2), EdgeId::new(10, -5, 3));
+        for edge_id in vec![e1, e2, e3] {
+            graph
+                .insert_overwrite_edge(4, edge_id, &edge_kind, true, &props)
+                .unwrap();
+        }
+        // the vertex deleted and the edge written after the snapshot sealed at are not sealed
+        graph.delete_vertex(5, 11, 1).unwrap();
+        graph
+            .insert_overwrite_edge(6, EdgeId::new(20, 10, 4), &edge_kind, true, &props)
+            .unwrap();
+        graph.seal(5, sealed_path).unwrap();
+        drop(graph);
+
+        builder.add_storage_option("store.sealed.path", sealed_path);
+        let graph = GraphStore::open(&builder.build()).unwrap();
+        assert!(graph.is_sealed());
+        let vertices: Vec<VertexId> = graph
+            .scan_vertices(10, &[], None, Some(&vec![]))
+            .unwrap()
+            .map(|v| RocksVertex::get_vertex_id(&v.unwrap()))
+            .collect();
+        assert_eq!(vertices, vec![10, 20, -5]);
+        assert!(graph
+            .get_vertex(10, -5, Some(1), None)
+            .unwrap()
+            .is_some());
+        assert!(graph
+            .get_vertex(10, 11, None, None)
+            .unwrap()
+            .is_none());
+
+        let edges = |vertex_id, direction| {
+            let edges = match direction {
+                EdgeDirection::Out => graph.get_out_edges(10, vertex_id, Some(2), None, Some(&vec![])),
+                _ => graph.get_in_edges(10, vertex_id, Some(2), None, Some(&vec![])),
+            };
+            let ids: Vec<EdgeId> = edges
+                .unwrap()
+                .map(|e| *RocksEdge::get_edge_id(&e.unwrap()))
+                .collect();
+            ids
+        };
+        assert_eq!(edges(10, EdgeDirection::Out), vec![e1, e3]);
+        assert_eq!(edges(20, EdgeDirection::In), vec![e1, e2]);
+        assert_eq!(edges(-5, EdgeDirection::In), vec![e3]);
+        assert_eq!(edges(20, EdgeDirection::Out), vec![]);
+        assert_eq!(
+            graph
+                .scan_edge(10, None, None, None)
+                .unwrap()
+                .count(),
+            3
+        );
+        assert!(graph
+            .get_edge(10, e2, Some(&edge_kind), None)
+            .unwrap()
+            .is_some());
+        assert!(graph
+            .get_edge(10, EdgeId::new(10, 20, 2), None, None)
+            .unwrap()
+            .is_none());
+
+        // the writes are rejected
+        assert!(graph
+            .insert_overwrite_vertex(11, 30, 1, &props)
+            .is_err());
+        assert!(graph.begin_write().is_none());
+        drop(graph);
+        fs::rmr(path).unwrap();
+        fs::rmr(sealed_path).unwrap();
+    }
+
     #[test]
     fn test_backup_engine() {
         let test_dir = "store_test/test_backup_engine";
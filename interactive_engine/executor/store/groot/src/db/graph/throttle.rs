@@ -0,0 +1,207 @@
+//! Adaptive throttling of the writes by the backlog of flushes and compactions: the writes are delayed
+//! more as the backlog grows over its soft limits, and rejected with a time to retry after once it
+//! reaches any of its hard limits, before rocksdb stalls the writes by itself. The limits are set
+//! below those of rocksdb, so that the writers back off instead of blocking in the store. Both the
+//! delayed and the rejected writes are returned to the writers with the time to write after, which
+//! they wait for by themselves rather than the threads calling into the store.
+//!
+//! Enabled by `store.write.throttle.enabled`, with the limits of
+//! * `store.write.throttle.immutable.memtables.soft` and `.hard`, the memtables waiting to be flushed;
+//! * `store.write.throttle.level0.files.soft` and `.hard`, the files at level 0 waiting to be compacted;
+//! * `store.write.throttle.pending.compaction.mb.soft` and `.hard`, the bytes to be compacted.
+
+use std::time::Duration;
+
+use crate::db::api::*;
+use crate::db::storage::rocksdb::RocksDB;
+
+const DEFAULT_IMMUTABLE_MEMTABLES: (u64, u64) = (2, 4);
+const DEFAULT_LEVEL0_FILES: (u64, u64) = (10, 18);
+const DEFAULT_PENDING_COMPACTION_MB: (u64, u64) = (32 << 10, 128 << 10);
+const DEFAULT_MAX_DELAY_MS: u64 = 1000;
+const DEFAULT_RETRY_AFTER_MS: u64 = 1000;
+// the retry-after grows with the overshoot of the hard limits, up to this times of the base;
+const MAX_RETRY_AFTER_FACTOR: u64 = 10;
+
+/// The backlog of the flushes and compactions of the store.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WriteBacklog {
+    pub immutable_memtables: u64,
+    pub level0_files: u64,
+    pub pending_compaction_bytes: u64,
+}
+
+impl WriteBacklog {
+    pub fn of(storage: &RocksDB) -> GraphResult<Self> {
+        let get = |name: &str| -> GraphResult<u64> { Ok(storage.get_int_property(name)?.unwrap_or(0)) };
+        // the files at a level are given by a string property only
+        let level0_files = match storage.get_property("rocksdb.num-files-at-level0")? {
+            Some(files) => files.trim().parse::<u64>().map_err(|e| {
+                let msg = format!("invalid rocksdb.num-files-at-level0 `{}`: {}", files, e);
+                gen_graph_err!(GraphErrorCode::ExternalStorageError, msg, of)
+            })?,
+            None => 0,
+        };
+        Ok(WriteBacklog {
+            immutable_memtables: get("rocksdb.num-immutable-mem-table")?,
+            level0_files,
+            pending_compaction_bytes: get("rocksdb.estimate-pending-compaction-bytes")?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WriteAdmission {
+    Admit,
+    /// Write after the delay
+    Delay(Duration),
+    /// Don't write, but retry after the time
+    Reject(Duration),
+}
+
+#[derive(Clone, Debug)]
+pub struct WriteThrottle {
+    immutable_memtables: (u64, u64),
+    level0_files: (u64, u64),
+    pending_compaction_bytes: (u64, u64),
+    max_delay_ms: u64,
+    retry_after_ms: u64,
+}
+
+impl WriteThrottle {
+    pub fn from_config(config: &GraphConfig) -> GraphResult<Option<Self>> {
+        let get = |key: &str, default: u64| -> GraphResult<u64> {
+            match config.get_storage_option(key) {
+                Some(s) => s.parse::<u64>().map_err(|e| {
+                    let msg = format!("invalid {} `{}`: {}", key, s, e);
+                    gen_graph_err!(GraphErrorCode::InvalidData, msg, from_config)
+                }),
+                None => Ok(default),
+            }
+        };
+        let limits = |key: &str, default: (u64, u64)| -> GraphResult<(u64, u64)> {
+            let soft = get(&format!("{}.soft", key), default.0)?;
+            let hard = get(&format!("{}.hard", key), default.1)?;
+            if soft >= hard {
+                let msg =
+                    format!("the soft limit {} of {} is not below the hard limit {}", soft, key, hard);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, from_config));
+            }
+            Ok((soft, hard))
+        };
+        match config.get_storage_option("store.write.throttle.enabled") {
+            Some(enabled) if enabled == "true" => {}
+            _ => return Ok(None),
+        }
+        let (soft_mb, hard_mb) =
+            limits("store.write.throttle.pending.compaction.mb", DEFAULT_PENDING_COMPACTION_MB)?;
+        Ok(Some(WriteThrottle {
+            immutable_memtables: limits(
+                "store.write.throttle.immutable.memtables",
+                DEFAULT_IMMUTABLE_MEMTABLES,
+            )?,
+            level0_files: limits("store.write.throttle.level0.files", DEFAULT_LEVEL0_FILES)?,
+            pending_compaction_bytes: (soft_mb << 20, hard_mb << 20),
+            max_delay_ms: get("store.write.throttle.max.delay.ms", DEFAULT_MAX_DELAY_MS)?,
+            retry_after_ms: get("store.write.throttle.retry.after.ms", DEFAULT_RETRY_AFTER_MS)?,
+        }))
+    }
+
+    /// Admit, delay or reject a write under the backlog.
+    pub fn admit(&self, backlog: &WriteBacklog) -> WriteAdmission {
+        let usages = [
+            (backlog.immutable_memtables, self.immutable_memtables),
+            (backlog.level0_files, self.level0_files),
+            (backlog.pending_compaction_bytes, self.pending_compaction_bytes),
+        ];
+        // how far each over its hard limit, in times of the limit;
+        let overshoot = usages
+            .iter()
+            .filter(|(value, (_, hard))| value >= hard)
+            .map(|(value, (_, hard))| value / hard)
+            .max();
+        if let Some(overshoot) = overshoot {
+            let factor = overshoot.min(MAX_RETRY_AFTER_FACTOR);
+            return WriteAdmission::Reject(Duration::from_millis(self.retry_after_ms * factor));
+        }
+        // how far each between its soft and hard limits, in (0, 1);
+        let pressure = usages
+            .iter()
+            .filter(|(value, (soft, _))| value > soft)
+            .map(|(value, (soft, hard))| (value - soft) as f64 / (hard - soft) as f64)
+            .fold(0.0, f64::max);
+        if pressure > 0.0 {
+            WriteAdmission::Delay(Duration::from_millis((self.max_delay_ms as f64 * pressure) as u64))
+        } else {
+            WriteAdmission::Admit
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::db::util::fs;
+
+    fn throttle(options: &[(&str, &str)]) -> GraphResult<Option<WriteThrottle>> {
+        let mut builder = GraphConfigBuilder::new();
+        for (k, v) in options {
+            builder.add_storage_option(k, v);
+        }
+        WriteThrottle::from_config(&builder.build())
+    }
+
+    #[test]
+    fn test_write_throttle() {
+        assert!(throttle(&[]).unwrap().is_none());
+        let throttle = throttle(&[
+            ("store.write.throttle.enabled", "true"),
+            ("store.write.throttle.level0.files.soft", "10"),
+            ("store.write.throttle.level0.files.hard", "20"),
+        ])
+        .unwrap()
+        .unwrap();
+        let backlog = |level0_files| WriteBacklog { level0_files, ..WriteBacklog::default() };
+        assert_eq!(throttle.admit(&backlog(10)), WriteAdmission::Admit);
+        assert_eq!(throttle.admit(&backlog(15)), WriteAdmission::Delay(Duration::from_millis(500)));
+        assert_eq!(throttle.admit(&backlog(20)), WriteAdmission::Reject(Duration::from_millis(1000)));
+        assert_eq!(throttle.admit(&backlog(45)), WriteAdmission::Reject(Duration::from_millis(2000)));
+        // the most pressing of the backlogs
+        let backlog =
+            WriteBacklog { immutable_memtables: 3, level0_files: 12, pending_compaction_bytes: 0 };
+        assert_eq!(throttle.admit(&backlog), WriteAdmission::Delay(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_write_backlog() {
+        let path = "test_write_backlog";
+        fs::rmr(path).unwrap();
+        {
+            let mut options = HashMap::new();
+            options.insert("store.data.path".to_owned(), path.to_owned());
+            let db = RocksDB::open(&options).unwrap();
+            assert_eq!(WriteBacklog::of(&db).unwrap().level0_files, 0);
+            db.put(b"key", b"value").unwrap();
+            db.flush().unwrap();
+            assert_eq!(WriteBacklog::of(&db).unwrap().level0_files, 1);
+        }
+        fs::rmr(path).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_write_throttle() {
+        assert!(throttle(&[
+            ("store.write.throttle.enabled", "true"),
+            ("store.write.throttle.level0.files.soft", "20"),
+            ("store.write.throttle.level0.files.hard", "10"),
+        ])
+        .is_err());
+        assert!(throttle(&[
+            ("store.write.throttle.enabled", "true"),
+            ("store.write.throttle.max.delay.ms", "x")
+        ])
+        .is_err());
+    }
+}
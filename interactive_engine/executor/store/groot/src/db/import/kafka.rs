@@ -0,0 +1,645 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Ingest mutation records (see `mutation.rs`) from a kafka topic into the store, or records of
+//! other structures mapped to the properties of a label by a `RecordMapping`, which are inserted.
+//!
+//! The records are written into the partitions of the process like those of the streaming write
+//! service, at the latest snapshot id written into the partitions (see `GraphStore::get_write_si`),
+//! so the ingestion never runs ahead of the snapshots of the regular writes. The next offsets of
+//! every kafka partition are committed into all the partitions together with that snapshot id once
+//! the whole batch is written. On restart the consumer seeks to the earliest committed offsets, and
+//! if a batch fails it seeks back to the offsets of the last committed batch, so a batch is either
+//! written with its offsets committed, or replayed from the start. Replaying a batch is idempotent
+//! because vertex ids and edge ids are derived from the records. Offsets are also committed to the
+//! consumer group afterwards, which only serves monitoring tools.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use apache_avro::Schema as AvroSchema;
+use prometheus::{register_int_gauge_vec, IntGaugeVec};
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::message::Message;
+use rdkafka::{Offset, TopicPartitionList};
+use serde_json::Value as JsonValue;
+
+use super::mutation::{Mutation, MutationOp};
+use super::record_mapping::{avro_datum_to_json, CompiledMapping, MappedRecord, RecordMapping};
+use crate::db::api::*;
+use crate::db::graph::store::GraphStore;
+use crate::db::graph::IngestOffsets;
+use crate::db::service::stream_write::WritePartitions;
+
+lazy_static! {
+    static ref INGEST_LAG: IntGaugeVec = register_int_gauge_vec!(
+        "groot_kafka_ingest_lag",
+        "Records of the kafka partitions not ingested yet.",
+        &["source", "partition"]
+    )
+    .unwrap();
+}
+
+#[derive(Clone, Debug)]
+pub enum RecordFormat {
+    Json,
+    /// Avro datums written with `schema`. With `confluent_wire_format` every payload starts with
+    /// a magic byte and a 4 bytes schema id, which are skipped.
+    Avro {
+        schema: String,
+        confluent_wire_format: bool,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct KafkaIngestConfig {
+    pub brokers: String,
+    pub group_id: String,
+    pub topic: String,
+    pub format: RecordFormat,
+    /// the mapping of the records, which are mutation records if it's not set
+    pub mapping: Option<RecordMapping>,
+    pub batch_size: usize,
+    pub poll_timeout: Duration,
+}
+
+impl KafkaIngestConfig {
+    pub fn new(brokers: &str, group_id: &str, topic: &str) -> Self {
+        KafkaIngestConfig {
+            brokers: brokers.to_owned(),
+            group_id: group_id.to_owned(),
+            topic: topic.to_owned(),
+            format: RecordFormat::Json,
+            mapping: None,
+            batch_size: 1024,
+            poll_timeout: Duration::from_millis(100),
+        }
+    }
+
+    fn source_name(&self) -> String {
+        format!("kafka#{}#{}", self.group_id, self.topic)
+    }
+}
+
+#[derive(Default)]
+pub struct IngestMetrics {
+    pub consumed_records: AtomicU64,
+    pub applied_records: AtomicU64,
+    pub failed_records: AtomicU64,
+    pub committed_batches: AtomicU64,
+    // partition -> high watermark - committed offset
+    lag: Mutex<BTreeMap<i32, i64>>,
+}
+
+impl IngestMetrics {
+    pub fn partition_lag(&self) -> BTreeMap<i32, i64> {
+        self.lag.lock().unwrap().clone()
+    }
+
+    pub fn total_lag(&self) -> i64 {
+        self.lag.lock().unwrap().values().sum()
+    }
+}
+
+/// A record polled from a partition of the topic.
+pub struct PolledRecord {
+    pub partition: i32,
+    pub offset: i64,
+    pub payload: Option<Vec<u8>>,
+}
+
+/// The consumer of the assigned partitions of the topic.
+pub trait RecordConsumer {
+    fn poll(&self, timeout: Duration) -> GraphResult<Option<PolledRecord>>;
+
+    /// Seek the partition to `offset`, or to its beginning if it's `None`.
+    fn seek(&self, partition: i32, offset: Option<i64>) -> GraphResult<()>;
+
+    /// Commit the next offsets of the partitions to the consumer group.
+    fn commit(&self, positions: &BTreeMap<i32, i64>) -> GraphResult<()>;
+
+    fn high_watermark(&self, partition: i32) -> GraphResult<i64>;
+}
+
+pub struct TopicConsumer {
+    consumer: BaseConsumer,
+    topic: String,
+}
+
+impl RecordConsumer for TopicConsumer {
+    fn poll(&self, timeout: Duration) -> GraphResult<Option<PolledRecord>> {
+        match self.consumer.poll(timeout) {
+            Some(Ok(msg)) => Ok(Some(PolledRecord {
+                partition: msg.partition(),
+                offset: msg.offset(),
+                payload: msg.payload().map(|p| p.to_vec()),
+            })),
+            Some(Err(e)) => Err(kafka_err(e)),
+            None => Ok(None),
+        }
+    }
+
+    fn seek(&self, partition: i32, offset: Option<i64>) -> GraphResult<()> {
+        let offset = offset.map_or(Offset::Beginning, Offset::Offset);
+        self.consumer
+            .seek(&self.topic, partition, offset, Duration::from_secs(10))
+            .map_err(kafka_err)
+    }
+
+    fn commit(&self, positions: &BTreeMap<i32, i64>) -> GraphResult<()> {
+        let mut tpl = TopicPartitionList::new();
+        for (partition, offset) in positions.iter() {
+            tpl.add_partition_offset(&self.topic, *partition, Offset::Offset(*offset))
+                .map_err(kafka_err)?;
+        }
+        self.consumer
+            .commit(&tpl, CommitMode::Async)
+            .map_err(kafka_err)
+    }
+
+    fn high_watermark(&self, partition: i32) -> GraphResult<i64> {
+        self.consumer
+            .fetch_watermarks(&self.topic, partition, Duration::from_secs(1))
+            .map(|(_, high)| high)
+            .map_err(kafka_err)
+    }
+}
+
+enum RecordDecoder {
+    Json,
+    Avro { schema: AvroSchema, confluent_wire_format: bool },
+}
+
+impl RecordDecoder {
+    fn new(format: &RecordFormat) -> GraphResult<Self> {
+        match format {
+            RecordFormat::Json => Ok(RecordDecoder::Json),
+            RecordFormat::Avro { schema, confluent_wire_format } => {
+                let schema = AvroSchema::parse_str(schema).map_err(|e| {
+                    let msg = format!("invalid avro schema: {}", e);
+                    gen_graph_err!(GraphErrorCode::InvalidData, msg, new)
+                })?;
+                Ok(RecordDecoder::Avro { schema, confluent_wire_format: *confluent_wire_format })
+            }
+        }
+    }
+
+    fn decode(&self, payload: &[u8]) -> GraphResult<JsonValue> {
+        match self {
+            RecordDecoder::Json => decode_json(payload),
+            RecordDecoder::Avro { schema, confluent_wire_format } => {
+                avro_datum_to_json(schema, avro_datum(payload, *confluent_wire_format))
+            }
+        }
+    }
+
+    fn map(&self, payload: &[u8], mapping: &CompiledMapping) -> GraphResult<MappedRecord> {
+        match self {
+            RecordDecoder::Json => mapping.map_json(&decode_json(payload)?),
+            RecordDecoder::Avro { schema, confluent_wire_format } => {
+                mapping.map_avro(schema, avro_datum(payload, *confluent_wire_format))
+            }
+        }
+    }
+}
+
+fn decode_json(payload: &[u8]) -> GraphResult<JsonValue> {
+    serde_json::from_slice(payload).map_err(|e| {
+        let msg = format!("invalid json record: {}", e);
+        gen_graph_err!(GraphErrorCode::InvalidData, msg, decode_json)
+    })
+}
+
+fn avro_datum(payload: &[u8], confluent_wire_format: bool) -> &[u8] {
+    if confluent_wire_format && payload.len() > 5 {
+        &payload[5..]
+    } else {
+        payload
+    }
+}
+
+pub struct KafkaIngestor<C: RecordConsumer = TopicConsumer> {
+    config: KafkaIngestConfig,
+    partitions: Arc<dyn WritePartitions>,
+    consumer: C,
+    decoder: RecordDecoder,
+    metrics: Arc<IngestMetrics>,
+    // the assigned partitions of the topic
+    topic_partitions: Vec<i32>,
+    // partition -> next offset to consume
+    positions: BTreeMap<i32, i64>,
+    // the consumer has polled past `positions` by a failed batch, and seeks back before polling
+    needs_rewind: bool,
+}
+
+impl KafkaIngestor<TopicConsumer> {
+    pub fn new(config: KafkaIngestConfig, partitions: Arc<dyn WritePartitions>) -> GraphResult<Self> {
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &config.brokers)
+            .set("group.id", &config.group_id)
+            .set("enable.auto.commit", "false")
+            .set("enable.auto.offset.store", "false")
+            .create()
+            .map_err(kafka_err)?;
+        let metadata = consumer
+            .fetch_metadata(Some(&config.topic), Duration::from_secs(10))
+            .map_err(kafka_err)?;
+        let topic_partitions: Vec<i32> = metadata
+            .topics()
+            .iter()
+            .flat_map(|t| t.partitions().iter().map(|p| p.id()))
+            .collect();
+        if topic_partitions.is_empty() {
+            let msg = format!("topic {} has no partitions", config.topic);
+            return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, new));
+        }
+        let positions = committed_positions(&config.source_name(), partitions.get_partitions())?;
+        let mut assignment = TopicPartitionList::new();
+        for partition in topic_partitions.iter() {
+            let offset = positions
+                .get(partition)
+                .map_or(Offset::Beginning, |offset| Offset::Offset(*offset));
+            assignment
+                .add_partition_offset(&config.topic, *partition, offset)
+                .map_err(kafka_err)?;
+        }
+        consumer
+            .assign(&assignment)
+            .map_err(kafka_err)?;
+        let consumer = TopicConsumer { consumer, topic: config.topic.clone() };
+        Self::with_consumer(config, partitions, consumer, topic_partitions, positions)
+    }
+}
+
+impl<C: RecordConsumer> KafkaIngestor<C> {
+    /// Ingest by `consumer`, which has been assigned `topic_partitions` at `positions`.
+    pub fn with_consumer(
+        config: KafkaIngestConfig, partitions: Arc<dyn WritePartitions>, consumer: C,
+        topic_partitions: Vec<i32>, positions: BTreeMap<i32, i64>,
+    ) -> GraphResult<Self> {
+        let decoder = RecordDecoder::new(&config.format)?;
+        info!("kafka ingestor of {} starts at offsets {:?}", config.source_name(), positions);
+        Ok(KafkaIngestor {
+            config,
+            partitions,
+            consumer,
+            decoder,
+            metrics: Arc::new(IngestMetrics::default()),
+            topic_partitions,
+            positions,
+            needs_rewind: false,
+        })
+    }
+
+    pub fn metrics(&self) -> Arc<IngestMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Consume and apply at most one batch, returns the number of records consumed. Records that
+    /// can't be decoded or don't match the schema are skipped and counted as failed, while store
+    /// errors abort the batch without committing its offsets, and the consumer seeks back to
+    /// consume it again.
+    pub fn run_once(&mut self) -> GraphResult<usize> {
+        if self.needs_rewind {
+            self.rewind()?;
+        }
+        self.ingest_batch().map_err(|e| {
+            self.needs_rewind = true;
+            if let Err(seek_err) = self.rewind() {
+                warn!("seek back {} failed: {:?}", self.config.source_name(), seek_err);
+            }
+            e
+        })
+    }
+
+    /// Ingest until `stop` is set, a failed batch is retried after `poll_timeout`.
+    pub fn run(&mut self, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            match self.run_once() {
+                Ok(0) => self.update_lag(),
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("ingest {} failed: {:?}", self.config.source_name(), e);
+                    thread::sleep(self.config.poll_timeout);
+                }
+            }
+        }
+    }
+
+    fn ingest_batch(&mut self) -> GraphResult<usize> {
+        let mut batch = Vec::with_capacity(self.config.batch_size);
+        let mut positions = self.positions.clone();
+        while batch.len() < self.config.batch_size {
+            let timeout =
+                if batch.is_empty() { self.config.poll_timeout } else { Duration::from_millis(0) };
+            match self.consumer.poll(timeout)? {
+                Some(record) => {
+                    positions.insert(record.partition, record.offset + 1);
+                    batch.push(record.payload);
+                }
+                None => break,
+            }
+        }
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        let si = self.partitions.get_write_si();
+        let graph_def = self.partitions.get_graph_def()?;
+        // compiled for every batch, as the schema may have changed
+        let mapping = match self.config.mapping.as_ref() {
+            Some(mapping) => Some(mapping.compile(&graph_def)?),
+            None => None,
+        };
+        let mut applied = 0;
+        let mut failed = 0;
+        for payload in batch.iter() {
+            let mutation = payload
+                .as_ref()
+                .ok_or_else(|| gen_graph_err!(GraphErrorCode::InvalidData, "empty payload".to_owned()))
+                .and_then(|p| match mapping.as_ref() {
+                    Some(mapping) => self
+                        .decoder
+                        .map(p, mapping)
+                        .and_then(|record| mapping.to_mutation(record, MutationOp::Insert)),
+                    None => self
+                        .decoder
+                        .decode(p)
+                        .and_then(|json| Mutation::from_json(&json, &graph_def)),
+                });
+            match mutation {
+                Ok(mutation) => {
+                    self.partitions.apply_mutation(&mutation, si)?;
+                    applied += 1;
+                }
+                Err(e) => {
+                    warn!("skip invalid record of {}: {:?}", self.config.source_name(), e);
+                    failed += 1;
+                }
+            }
+        }
+        let offsets: Vec<(i32, i64)> = positions
+            .iter()
+            .map(|(p, o)| (*p, *o))
+            .collect();
+        for graph in self.partitions.get_partitions() {
+            graph.commit_ingest_offsets(si, &self.config.source_name(), offsets.clone())?;
+        }
+        self.positions = positions;
+        if let Err(e) = self.consumer.commit(&self.positions) {
+            warn!("commit consumer group offsets of {} failed: {:?}", self.config.source_name(), e);
+        }
+        self.metrics
+            .consumed_records
+            .fetch_add(batch.len() as u64, Ordering::Relaxed);
+        self.metrics
+            .applied_records
+            .fetch_add(applied, Ordering::Relaxed);
+        self.metrics
+            .failed_records
+            .fetch_add(failed, Ordering::Relaxed);
+        self.metrics
+            .committed_batches
+            .fetch_add(1, Ordering::Relaxed);
+        self.update_lag();
+        Ok(batch.len())
+    }
+
+    /// Seek every assigned partition back to the offsets of the last committed batch.
+    fn rewind(&mut self) -> GraphResult<()> {
+        for partition in self.topic_partitions.iter() {
+            self.consumer
+                .seek(*partition, self.positions.get(partition).cloned())?;
+        }
+        self.needs_rewind = false;
+        Ok(())
+    }
+
+    fn update_lag(&self) {
+        let source = self.config.source_name();
+        let mut lag = self.metrics.lag.lock().unwrap();
+        for partition in self.topic_partitions.iter() {
+            match self.consumer.high_watermark(*partition) {
+                Ok(high) => {
+                    let offset = self
+                        .positions
+                        .get(partition)
+                        .cloned()
+                        .unwrap_or(0);
+                    let partition_lag = (high - offset).max(0);
+                    lag.insert(*partition, partition_lag);
+                    INGEST_LAG
+                        .with_label_values(&[&source, &partition.to_string()])
+                        .set(partition_lag);
+                }
+                Err(e) => debug!("fetch watermarks of partition {} failed: {:?}", partition, e),
+            }
+        }
+    }
+}
+
+/// The offsets committed into the partitions, which are the earliest ones if the partitions were
+/// stopped in the middle of a commit, and none if any of the partitions has not committed yet.
+fn committed_positions(source: &str, partitions: Vec<Arc<GraphStore>>) -> GraphResult<BTreeMap<i32, i64>> {
+    let mut earliest: Option<IngestOffsets> = None;
+    for graph in partitions {
+        match graph.get_ingest_offsets(source)? {
+            Some(offsets) => {
+                if earliest
+                    .as_ref()
+                    .map_or(true, |e| offsets.si < e.si)
+                {
+                    earliest = Some(offsets);
+                }
+            }
+            None => return Ok(BTreeMap::new()),
+        }
+    }
+    Ok(earliest.map_or_else(BTreeMap::new, |c| c.offsets.into_iter().collect()))
+}
+
+fn kafka_err(e: rdkafka::error::KafkaError) -> GraphError {
+    let msg = format!("kafka error: {}", e);
+    gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::api::multi_version_graph::MultiVersionGraph;
+    use crate::db::api::types::{Property, PropertyReader, PropertyValue};
+    use crate::db::util::fs;
+
+    /// The records of partition 0 of a topic, at the offsets of their indexes.
+    struct TestConsumer {
+        records: Vec<Vec<u8>>,
+        next: Mutex<usize>,
+        seeks: Mutex<Vec<(i32, Option<i64>)>>,
+    }
+
+    impl RecordConsumer for TestConsumer {
+        fn poll(&self, _timeout: Duration) -> GraphResult<Option<PolledRecord>> {
+            let mut next = self.next.lock().unwrap();
+            let record = self
+                .records
+                .get(*next)
+                .map(|payload| PolledRecord {
+                    partition: 0,
+                    offset: *next as i64,
+                    payload: Some(payload.clone()),
+                });
+            if record.is_some() {
+                *next += 1;
+            }
+            Ok(record)
+        }
+
+        fn seek(&self, partition: i32, offset: Option<i64>) -> GraphResult<()> {
+            *self.next.lock().unwrap() = offset.unwrap_or(0) as usize;
+            self.seeks
+                .lock()
+                .unwrap()
+                .push((partition, offset));
+            Ok(())
+        }
+
+        fn commit(&self, _positions: &BTreeMap<i32, i64>) -> GraphResult<()> {
+            Ok(())
+        }
+
+        fn high_watermark(&self, _partition: i32) -> GraphResult<i64> {
+            Ok(self.records.len() as i64)
+        }
+    }
+
+    /// A single partition, which fails the writes of the vertices above `writable` as if they are
+    /// in other processes.
+    struct TestPartitions {
+        graph: Arc<GraphStore>,
+        writable: Mutex<VertexId>,
+    }
+
+    impl WritePartitions for TestPartitions {
+        fn get_write_partition(&self, vertex_id: VertexId) -> Option<Arc<GraphStore>> {
+            if vertex_id <= *self.writable.lock().unwrap() {
+                Some(self.graph.clone())
+            } else {
+                None
+            }
+        }
+
+        fn get_partitions(&self) -> Vec<Arc<GraphStore>> {
+            vec![self.graph.clone()]
+        }
+    }
+
+    fn person(id: i64, name: &str) -> Vec<u8> {
+        json!({"op": "insert", "kind": "vertex", "label": "person", "id": id, "properties": {"name": name}})
+            .to_string()
+            .into_bytes()
+    }
+
+    fn get_name(graph: &GraphStore, id: VertexId) -> Option<String> {
+        let vertex = graph
+            .get_vertex(MAX_SI, id, Some(1), Some(&vec![]))
+            .unwrap()?;
+        match vertex.get_property(1)?.get_property_value() {
+            PropertyValue::String(name) => Some(name.clone()),
+            v => panic!("unexpected name {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_rewind_failed_batch() {
+        let path = "store_test/test_kafka_rewind_failed_batch";
+        fs::rmr(path).unwrap();
+        let mut builder = GraphConfigBuilder::new();
+        builder.set_storage_engine("rocksdb");
+        builder.add_storage_option("store.data.path", path);
+        let graph = Arc::new(GraphStore::open(&builder.build()).unwrap());
+        let mut type_def = TypeDefBuilder::new();
+        type_def.set_label_id(1).set_label("person");
+        type_def.add_property(1, 1, "name".to_owned(), ValueType::String, None, false, String::new());
+        graph
+            .create_vertex_type(1, 1, 1, &type_def.build(), 1)
+            .unwrap();
+
+        let partitions = Arc::new(TestPartitions { graph: graph.clone(), writable: Mutex::new(2) });
+        let consumer = TestConsumer {
+            records: vec![person(1, "marko"), person(2, "vadas"), person(3, "josh")],
+            next: Mutex::new(0),
+            seeks: Mutex::new(vec![]),
+        };
+        let mut config = KafkaIngestConfig::new("localhost:9092", "groot", "person");
+        config.batch_size = 2;
+        let source = config.source_name();
+        let mut ingestor =
+            KafkaIngestor::with_consumer(config, partitions.clone(), consumer, vec![0], BTreeMap::new())
+                .unwrap();
+
+        assert_eq!(ingestor.run_once().unwrap(), 2);
+        assert_eq!(
+            graph
+                .get_ingest_offsets(&source)
+                .unwrap()
+                .unwrap()
+                .offsets,
+            vec![(0, 2)]
+        );
+        // the write of vertex 3 fails, the consumer seeks back to the committed offset
+        assert!(ingestor.run_once().is_err());
+        assert_eq!(*ingestor.consumer.seeks.lock().unwrap(), vec![(0, Some(2))]);
+        assert_eq!(
+            graph
+                .get_ingest_offsets(&source)
+                .unwrap()
+                .unwrap()
+                .offsets,
+            vec![(0, 2)]
+        );
+        assert_eq!(get_name(&graph, 3), None);
+
+        // the failed record is consumed again once it can be written
+        *partitions.writable.lock().unwrap() = 3;
+        assert_eq!(ingestor.run_once().unwrap(), 1);
+        assert_eq!(
+            graph
+                .get_ingest_offsets(&source)
+                .unwrap()
+                .unwrap()
+                .offsets,
+            vec![(0, 3)]
+        );
+        assert_eq!(get_name(&graph, 1), Some("marko".to_owned()));
+        assert_eq!(get_name(&graph, 3), Some("josh".to_owned()));
+        assert_eq!(
+            ingestor
+                .metrics()
+                .applied_records
+                .load(Ordering::Relaxed),
+            3
+        );
+        assert_eq!(ingestor.metrics().partition_lag().get(&0), Some(&0));
+        assert_eq!(ingestor.run_once().unwrap(), 0);
+
+        drop(ingestor);
+        drop(partitions);
+        drop(graph);
+        fs::rmr(path).unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Vertex / edge mutation records used by streaming ingestion. A record is a json object like
+//!
+//! ```text
+//! {"op":"insert","kind":"vertex","label":"person","properties":{"id":1,"name":"marko"}}
+//! {"op":"update","kind":"edge","label":"knows","src_label":"person","src":{"id":1},
+//!  "dst_label":"person","dst":{"id":2},"properties":{"weight":0.5}}
+//! ```
+//!
+//! Vertex ids are either given by a numeric `id` field or hashed from the primary key properties,
+//! and edge endpoints accept both forms as well. Edges without an `id` get an inner id hashed from
+//! their label and endpoints, so replaying a record always writes the same edge.
+//!
+//! A record may carry a client supplied `dedup_id`. Writers that remember these ids, like the
+//! streaming write service, skip records whose id has been written already.
+
+use std::collections::HashMap;
+
+use byteorder::{BigEndian, WriteBytesExt};
+use serde_json::Value as JsonValue;
+
+use super::record_mapping::{json_to_property, property_to_value};
+use crate::db::api::multi_version_graph::MultiVersionGraph;
+use crate::db::api::*;
+use crate::db::graph::{get_vertex_id_by_primary_keys, hash64};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MutationOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+#[derive(Clone)]
+pub enum Mutation {
+    Vertex { op: MutationOp, id: VertexId, label: LabelId, properties: HashMap<PropertyId, Value> },
+    Edge { op: MutationOp, id: EdgeId, kind: EdgeKind, properties: HashMap<PropertyId, Value> },
+}
+
+impl Mutation {
+    pub fn from_json(json: &JsonValue, graph_def: &GraphDef) -> GraphResult<Self> {
+        let op = match get_str(json, "op")?.to_lowercase().as_str() {
+            "insert" | "upsert" => MutationOp::Insert,
+            "update" => MutationOp::Update,
+            "delete" => MutationOp::Delete,
+            x => return Err(invalid(format!("unknown mutation op `{}`", x))),
+        };
+        let type_def = find_type_def(graph_def, get_str(json, "label")?)?;
+        let properties = match json.get("properties") {
+            Some(props) => to_properties(props, type_def, graph_def)?,
+            None => HashMap::new(),
+        };
+        match get_str(json, "kind")?.to_lowercase().as_str() {
+            "vertex" => {
+                let id = match json.get("id") {
+                    Some(id) => as_vertex_id(id)?,
+                    None => pk_vertex_id(type_def, &properties)?,
+                };
+                Ok(Mutation::Vertex { op, id, label: type_def.get_label_id(), properties })
+            }
+            "edge" => {
+                let (src_id, src_label) = resolve_endpoint(json, "src", graph_def)?;
+                let (dst_id, dst_label) = resolve_endpoint(json, "dst", graph_def)?;
+                let kind = EdgeKind::new(type_def.get_label_id(), src_label, dst_label);
+                let inner_id = match json.get("id") {
+                    Some(id) => as_vertex_id(id)?,
+                    None => edge_inner_id(&kind, src_id, dst_id),
+                };
+                Ok(Mutation::Edge { op, id: EdgeId::new(src_id, dst_id, inner_id), kind, properties })
+            }
+            x => Err(invalid(format!("unknown mutation kind `{}`", x))),
+        }
+    }
+
+    /// Apply the mutation at `si`, edges are written in both directions.
+    pub fn apply<G: MultiVersionGraph>(&self, graph: &G, si: SnapshotId) -> GraphResult<()> {
+        self.apply_direction(graph, si, true)?;
+        if let Mutation::Edge { .. } = self {
+            self.apply_direction(graph, si, false)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the mutation at `si`, where an edge is written in the out direction if `forward`, i.e.
+    /// to the partition of its source, or in the in direction otherwise, i.e. to the partition of its
+    /// destination. `forward` is ignored by vertices.
+    pub fn apply_direction<G: MultiVersionGraph>(
+        &self, graph: &G, si: SnapshotId, forward: bool,
+    ) -> GraphResult<()> {
+        match self {
+            Mutation::Vertex { op, id, label, properties } => match op {
+                MutationOp::Insert => graph.insert_overwrite_vertex(si, *id, *label, properties),
+                MutationOp::Update => graph.insert_update_vertex(si, *id, *label, properties),
+                MutationOp::Delete => graph.delete_vertex(si, *id, *label),
+            },
+            Mutation::Edge { op, id, kind, properties } => match op {
+                MutationOp::Insert => graph.insert_overwrite_edge(si, *id, kind, forward, properties),
+                MutationOp::Update => graph.insert_update_edge(si, *id, kind, forward, properties),
+                MutationOp::Delete => graph.delete_edge(si, *id, kind, forward),
+            },
+        }
+    }
+}
+
+/// Get the client supplied dedup id of a mutation record.
+pub fn dedup_id(json: &JsonValue) -> Option<&str> {
+    json.get("dedup_id").and_then(|id| id.as_str())
+}
+
+/// Convert a json value to a groot value of `r#type`.
+pub fn json_to_value(json: &JsonValue, r#type: ValueType) -> GraphResult<Value> {
+    let property = json_to_property(json, r#type)?;
+    property_to_value(&property).ok_or_else(|| invalid(format!("cannot convert {} to {:?}", json, r#type)))
+}
+
+pub(crate) fn find_type_def<'a>(graph_def: &'a GraphDef, label: &str) -> GraphResult<&'a TypeDef> {
+    graph_def
+        .label_to_types
+        .values()
+        .find(|t| t.get_label() == label)
+        .ok_or_else(|| {
+            let msg = format!("label `{}` not found in schema", label);
+            gen_graph_err!(GraphErrorCode::TypeNotFound, msg, find_type_def, label)
+        })
+}
+
+/// Convert a json object to properties of `type_def`, null values and unknown properties are
+/// rejected so a schema mismatch is never silently dropped.
+pub(crate) fn to_properties(
+    props: &JsonValue, type_def: &TypeDef, graph_def: &GraphDef,
+) -> GraphResult<HashMap<PropertyId, Value>> {
+    let object = props
+        .as_object()
+        .ok_or_else(|| invalid(format!("properties must be a json object: {}", props)))?;
+    let mut properties = HashMap::new();
+    for (name, json) in object {
+        let prop_def = graph_def
+            .property_name_to_id
+            .get(name)
+            .and_then(|id| type_def.get_prop_def(*id))
+            .ok_or_else(|| {
+                invalid(format!("property `{}` not found in `{}`", name, type_def.get_label()))
+            })?;
+        properties.insert(prop_def.id, json_to_value(json, prop_def.r#type)?);
+    }
+    Ok(properties)
+}
+
+/// Hash the label and the endpoints of an edge into its inner id, so the same edge always gets the
+/// same id.
+pub(crate) fn edge_inner_id(kind: &EdgeKind, src_id: VertexId, dst_id: VertexId) -> i64 {
+    let mut bytes = Vec::with_capacity(20);
+    bytes
+        .write_i32::<BigEndian>(kind.edge_label_id)
+        .unwrap();
+    bytes.write_i64::<BigEndian>(src_id).unwrap();
+    bytes.write_i64::<BigEndian>(dst_id).unwrap();
+    hash64(&bytes, bytes.len())
+}
+
+/// Hash the primary key properties in declaration order into a vertex id, the same way as
+/// `get_vertex_id_by_primary_keys` does for bulk loaded data.
+pub(crate) fn pk_vertex_id(
+    type_def: &TypeDef, properties: &HashMap<PropertyId, Value>,
+) -> GraphResult<VertexId> {
+    let pk_ids = type_def.get_pk_prop_ids();
+    if pk_ids.is_empty() {
+        return Err(invalid(format!("`{}` has no primary key, an `id` is required", type_def.get_label())));
+    }
+    let mut pks = Vec::with_capacity(pk_ids.len());
+    for id in pk_ids {
+        let value = properties.get(&id).ok_or_else(|| {
+            invalid(format!("primary key #{} of `{}` is missing", id, type_def.get_label()))
+        })?;
+        pks.push(value.as_bytes().to_vec());
+    }
+    Ok(get_vertex_id_by_primary_keys(type_def.get_label_id(), pks.iter()))
+}
+
+fn resolve_endpoint(json: &JsonValue, key: &str, graph_def: &GraphDef) -> GraphResult<(VertexId, LabelId)> {
+    let type_def = find_type_def(graph_def, get_str(json, &format!("{}_label", key))?)?;
+    let endpoint = json
+        .get(key)
+        .ok_or_else(|| invalid(format!("`{}` not found in {}", key, json)))?;
+    let id = if endpoint.is_object() {
+        let pks = to_properties(endpoint, type_def, graph_def)?;
+        pk_vertex_id(type_def, &pks)?
+    } else {
+        as_vertex_id(endpoint)?
+    };
+    Ok((id, type_def.get_label_id()))
+}
+
+fn as_vertex_id(json: &JsonValue) -> GraphResult<VertexId> {
+    json.as_i64()
+        .ok_or_else(|| invalid(format!("id must be an integer: {}", json)))
+}
+
+fn get_str<'a>(json: &'a JsonValue, key: &str) -> GraphResult<&'a str> {
+    json.get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| invalid(format!("`{}` not found in {}", key, json)))
+}
+
+fn invalid(msg: String) -> GraphError {
+    gen_graph_err!(GraphErrorCode::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_to_value() {
+        let v = json_to_value(&json!(29), ValueType::Int).unwrap();
+        assert_eq!(v.get_int().unwrap(), 29);
+        let v = json_to_value(&json!("marko"), ValueType::String).unwrap();
+        assert_eq!(v.get_str().unwrap(), "marko");
+        let v = json_to_value(&json!([1, 2, 3]), ValueType::LongList).unwrap();
+        assert_eq!(v.get_long_list().unwrap().len(), 3);
+        let v = json_to_value(&json!(0.5), ValueType::Double).unwrap();
+        assert_eq!(v.get_double().unwrap(), 0.5);
+        assert!(json_to_value(&json!(1 << 40), ValueType::Int).is_err());
+        assert!(json_to_value(&json!("x"), ValueType::Long).is_err());
+        assert!(json_to_value(&json!(null), ValueType::String).is_err());
+    }
+}
@@ -0,0 +1,771 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Import csv files following the header conventions of `neo4j-admin import`, e.g.
+//!
+//! ```text
+//! personId:ID(Person),name,age:int,emails:string[],:LABEL
+//! :START_ID(Person),:END_ID(Person),since:long,:TYPE
+//! ```
+//!
+//! The groot schema is derived from the headers and the labels / types found in the data, so no
+//! hand-written mapping file is required. A file either starts with its header line, or is split
+//! into a header file holding only the header line and data files without one, like
+//! `--nodes=header.csv,part1.csv` of `neo4j-admin import`. Quoted fields must not span multiple
+//! lines. Groot vertices have a single label, so a row with multiple labels is rejected.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+use std::path::{Path, PathBuf};
+
+use crate::db::api::multi_version_graph::MultiVersionGraph;
+use crate::db::api::*;
+use crate::db::graph::get_vertex_id_by_primary_keys;
+
+pub const DEFAULT_DELIMITER: char = ',';
+pub const DEFAULT_ARRAY_DELIMITER: char = ';';
+
+/// Column role parsed from a header field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Neo4jColumn {
+    /// `name:ID(space)`, the id is also stored as a property if the column is named.
+    Id {
+        name: Option<String>,
+        id_space: String,
+    },
+    Label,
+    StartId {
+        id_space: String,
+    },
+    EndId {
+        id_space: String,
+    },
+    Type,
+    Property {
+        name: String,
+        r#type: ValueType,
+    },
+    Ignore,
+}
+
+impl Neo4jColumn {
+    pub fn parse(field: &str) -> GraphResult<Self> {
+        let field = field.trim();
+        let (name, type_str) = match field.rfind(':') {
+            Some(pos) => (&field[..pos], &field[pos + 1..]),
+            None => (field, "string"),
+        };
+        let (keyword, id_space) = match (type_str.find('('), type_str.ends_with(')')) {
+            (Some(pos), true) => (&type_str[..pos], type_str[pos + 1..type_str.len() - 1].to_owned()),
+            _ => (type_str, String::new()),
+        };
+        let column = match keyword.to_uppercase().as_str() {
+            "ID" => Neo4jColumn::Id {
+                name: if name.is_empty() { None } else { Some(name.to_owned()) },
+                id_space,
+            },
+            "LABEL" => Neo4jColumn::Label,
+            "START_ID" => Neo4jColumn::StartId { id_space },
+            "END_ID" => Neo4jColumn::EndId { id_space },
+            "TYPE" => Neo4jColumn::Type,
+            "IGNORE" => Neo4jColumn::Ignore,
+            _ => {
+                if name.is_empty() {
+                    let msg = format!("property column `{}` without a name", field);
+                    return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, parse, field));
+                }
+                Neo4jColumn::Property { name: name.to_owned(), r#type: parse_value_type(keyword)? }
+            }
+        };
+        Ok(column)
+    }
+}
+
+/// Map a neo4j property type to a groot value type. Temporal and spatial types are kept as
+/// strings, and arrays of types without a list counterpart are kept as string lists.
+fn parse_value_type(type_str: &str) -> GraphResult<ValueType> {
+    let lower = type_str.to_lowercase();
+    let value_type = match lower.as_str() {
+        "int" => ValueType::Int,
+        "long" => ValueType::Long,
+        "float" => ValueType::Float,
+        "double" => ValueType::Double,
+        "boolean" => ValueType::Bool,
+        "byte" | "short" => ValueType::Short,
+        "char" => ValueType::Char,
+        "string" | "date" | "localtime" | "time" | "localdatetime" | "datetime" | "duration" | "point" => {
+            ValueType::String
+        }
+        "int[]" | "short[]" | "byte[]" => ValueType::IntList,
+        "long[]" => ValueType::LongList,
+        "float[]" => ValueType::FloatList,
+        "double[]" => ValueType::DoubleList,
+        x if x.ends_with("[]") => ValueType::StringList,
+        _ => {
+            let msg = format!("unsupported neo4j property type `{}`", type_str);
+            return Err(gen_graph_err!(GraphErrorCode::NotSupported, msg, parse_value_type, type_str));
+        }
+    };
+    Ok(value_type)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Neo4jFileKind {
+    Node,
+    Relationship,
+}
+
+#[derive(Clone, Debug)]
+pub struct Neo4jCsvHeader {
+    pub kind: Neo4jFileKind,
+    pub columns: Vec<Neo4jColumn>,
+}
+
+impl Neo4jCsvHeader {
+    pub fn parse(line: &str, delimiter: char) -> GraphResult<Self> {
+        let mut columns = Vec::new();
+        for field in split_record(line, delimiter) {
+            columns.push(Neo4jColumn::parse(&field)?);
+        }
+        let count = |f: &dyn Fn(&Neo4jColumn) -> bool| columns.iter().filter(|c| f(c)).count();
+        let id_count = count(&|c| matches!(c, Neo4jColumn::Id { .. }));
+        let start_count = count(&|c| matches!(c, Neo4jColumn::StartId { .. }));
+        let end_count = count(&|c| matches!(c, Neo4jColumn::EndId { .. }));
+        let kind = match (id_count, start_count, end_count) {
+            (1, 0, 0) => Neo4jFileKind::Node,
+            (0, 1, 1) => Neo4jFileKind::Relationship,
+            _ => {
+                let msg = format!(
+                    "header must contain either one :ID column or one :START_ID and one :END_ID column: {}",
+                    line
+                );
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, parse));
+            }
+        };
+        Ok(Neo4jCsvHeader { kind, columns })
+    }
+
+    fn properties(&self) -> impl Iterator<Item = (&String, ValueType)> {
+        self.columns.iter().filter_map(|c| match c {
+            Neo4jColumn::Id { name: Some(name), .. } => Some((name, ValueType::String)),
+            Neo4jColumn::Property { name, r#type } => Some((name, *r#type)),
+            _ => None,
+        })
+    }
+}
+
+/// Split a csv line into fields, honoring double quoted fields with `""` as escaped quote.
+pub fn split_record(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut field));
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Parse a raw field into a value of `r#type`, empty fields mean the property is absent.
+pub fn parse_value(raw: &str, r#type: ValueType, array_delimiter: char) -> GraphResult<Option<Value>> {
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    macro_rules! parse_num {
+        ($s:expr, $t:ty) => {
+            $s.trim().parse::<$t>().map_err(|e| {
+                let msg = format!("cannot parse `{}` as {:?}: {}", $s, r#type, e);
+                gen_graph_err!(GraphErrorCode::InvalidData, msg, parse_value)
+            })
+        };
+    }
+    macro_rules! parse_list {
+        ($t:ty) => {{
+            let mut list = Vec::new();
+            for item in raw.split(array_delimiter) {
+                list.push(parse_num!(item, $t)?);
+            }
+            list
+        }};
+    }
+    let value = match r#type {
+        ValueType::Bool => Value::bool(parse_num!(raw.to_lowercase(), bool)?),
+        ValueType::Char => match raw.as_bytes() {
+            [c] => Value::char(*c),
+            _ => {
+                let msg = format!("cannot parse `{}` as a single byte char", raw);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, parse_value));
+            }
+        },
+        ValueType::Short => Value::short(parse_num!(raw, i16)?),
+        ValueType::Int => Value::int(parse_num!(raw, i32)?),
+        ValueType::Long => Value::long(parse_num!(raw, i64)?),
+        ValueType::Float => Value::float(parse_num!(raw, f32)?),
+        ValueType::Double => Value::double(parse_num!(raw, f64)?),
+        ValueType::String => Value::string(raw),
+        ValueType::Bytes => Value::bytes(raw.as_bytes()),
+        ValueType::IntList => Value::int_list(&parse_list!(i32)),
+        ValueType::LongList => Value::long_list(&parse_list!(i64)),
+        ValueType::FloatList => Value::float_list(&parse_list!(f32)),
+        ValueType::DoubleList => Value::double_list(&parse_list!(f64)),
+        ValueType::StringList => {
+            let list: Vec<String> = raw
+                .split(array_delimiter)
+                .map(|s| s.to_owned())
+                .collect();
+            Value::string_list(&list)
+        }
+    };
+    Ok(Some(value))
+}
+
+#[derive(Clone, Debug)]
+pub struct Neo4jCsvImportConfig {
+    pub delimiter: char,
+    pub array_delimiter: char,
+}
+
+impl Default for Neo4jCsvImportConfig {
+    fn default() -> Self {
+        Neo4jCsvImportConfig { delimiter: DEFAULT_DELIMITER, array_delimiter: DEFAULT_ARRAY_DELIMITER }
+    }
+}
+
+struct Neo4jCsvFile {
+    /// the file holding only the header line, or `None` if the only data file starts with it
+    header: Option<PathBuf>,
+    paths: Vec<PathBuf>,
+    /// label (or relationship type) for rows without a :LABEL (:TYPE) column
+    default_label: Option<String>,
+}
+
+/// Schema derived from the csv files. Vertex labels and edge labels share the same id space, and
+/// properties with the same name share the same property id across labels, as groot requires.
+#[derive(Default)]
+pub struct Neo4jImportSchema {
+    pub vertex_types: BTreeMap<String, (LabelId, TypeDef)>,
+    pub edge_types: BTreeMap<String, (LabelId, TypeDef)>,
+    pub edge_kinds: Vec<EdgeKind>,
+    pub prop_ids: BTreeMap<String, PropertyId>,
+    // (id space, neo4j id) -> (vertex id, label id)
+    vertex_ids: HashMap<(String, String), (VertexId, LabelId)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Neo4jImportSummary {
+    pub vertex_count: usize,
+    pub edge_count: usize,
+    pub schema_version: i64,
+}
+
+pub struct Neo4jCsvImporter {
+    config: Neo4jCsvImportConfig,
+    node_files: Vec<Neo4jCsvFile>,
+    relationship_files: Vec<Neo4jCsvFile>,
+}
+
+impl Neo4jCsvImporter {
+    pub fn new(config: Neo4jCsvImportConfig) -> Self {
+        Neo4jCsvImporter { config, node_files: Vec::new(), relationship_files: Vec::new() }
+    }
+
+    pub fn add_node_file<P: Into<PathBuf>>(&mut self, path: P, default_label: Option<&str>) -> &mut Self {
+        let default_label = default_label.map(|s| s.to_owned());
+        self.node_files
+            .push(Neo4jCsvFile { header: None, paths: vec![path.into()], default_label });
+        self
+    }
+
+    /// Add node files split into a header file and data files without the header line.
+    pub fn add_node_files<P: Into<PathBuf>>(
+        &mut self, header: P, paths: Vec<P>, default_label: Option<&str>,
+    ) -> &mut Self {
+        let default_label = default_label.map(|s| s.to_owned());
+        let paths = paths.into_iter().map(|p| p.into()).collect();
+        self.node_files
+            .push(Neo4jCsvFile { header: Some(header.into()), paths, default_label });
+        self
+    }
+
+    pub fn add_relationship_file<P: Into<PathBuf>>(
+        &mut self, path: P, default_type: Option<&str>,
+    ) -> &mut Self {
+        let default_label = default_type.map(|s| s.to_owned());
+        self.relationship_files.push(Neo4jCsvFile {
+            header: None,
+            paths: vec![path.into()],
+            default_label,
+        });
+        self
+    }
+
+    /// Add relationship files split into a header file and data files without the header line.
+    pub fn add_relationship_files<P: Into<PathBuf>>(
+        &mut self, header: P, paths: Vec<P>, default_type: Option<&str>,
+    ) -> &mut Self {
+        let default_label = default_type.map(|s| s.to_owned());
+        let paths = paths.into_iter().map(|p| p.into()).collect();
+        self.relationship_files
+            .push(Neo4jCsvFile { header: Some(header.into()), paths, default_label });
+        self
+    }
+
+    /// Scan all the files and derive the groot schema from the headers and the labels found in
+    /// the data. Node files are scanned first to resolve the endpoints of relationships.
+    pub fn derive_schema(&self) -> GraphResult<Neo4jImportSchema> {
+        // label -> prop name -> type
+        let mut vertex_props: BTreeMap<String, BTreeMap<String, (ValueType, bool)>> = BTreeMap::new();
+        let mut edge_props: BTreeMap<String, BTreeMap<String, (ValueType, bool)>> = BTreeMap::new();
+        let mut vertex_ids = HashMap::new();
+        let mut vertex_labels: BTreeMap<String, LabelId> = BTreeMap::new();
+        let mut edge_kinds: BTreeSet<(String, LabelId, LabelId)> = BTreeSet::new();
+        let mut next_label_id = 1;
+
+        for file in &self.node_files {
+            let header = self.read_rows(file, Neo4jFileKind::Node, |header, row| {
+                let label = self.row_label(file, header, row)?;
+                let label_id = *vertex_labels.entry(label).or_insert_with(|| {
+                    next_label_id += 1;
+                    next_label_id - 1
+                });
+                let (id_space, id) = node_id(header, row);
+                let vertex_id =
+                    get_vertex_id_by_primary_keys(label_id, std::iter::once(&id.as_bytes().to_vec()));
+                vertex_ids.insert((id_space, id), (vertex_id, label_id));
+                Ok(())
+            })?;
+            for label in self.labels_of(file, &header)? {
+                merge_props(vertex_props.entry(label).or_default(), &header)?;
+            }
+        }
+
+        for file in &self.relationship_files {
+            let header = self.read_rows(file, Neo4jFileKind::Relationship, |header, row| {
+                let label = self.row_label(file, header, row)?;
+                let (src, dst) = edge_endpoints(header, row, &vertex_ids)?;
+                edge_kinds.insert((label, src.1, dst.1));
+                Ok(())
+            })?;
+            for label in self.labels_of(file, &header)? {
+                merge_props(edge_props.entry(label).or_default(), &header)?;
+            }
+        }
+
+        let mut schema = Neo4jImportSchema::default();
+        for (label, label_id) in vertex_labels {
+            let props = vertex_props.remove(&label).unwrap_or_default();
+            let type_def = build_type_def(&label, label_id, &props, &mut schema.prop_ids);
+            schema
+                .vertex_types
+                .insert(label, (label_id, type_def));
+        }
+        for (label, _, _) in edge_kinds.iter() {
+            if !schema.edge_types.contains_key(label) {
+                let label_id = next_label_id;
+                next_label_id += 1;
+                let props = edge_props.remove(label).unwrap_or_default();
+                let type_def = build_type_def(label, label_id, &props, &mut schema.prop_ids);
+                schema
+                    .edge_types
+                    .insert(label.clone(), (label_id, type_def));
+            }
+        }
+        for (label, src_label_id, dst_label_id) in edge_kinds {
+            let edge_label_id = schema.edge_types[&label].0;
+            schema
+                .edge_kinds
+                .push(EdgeKind::new(edge_label_id, src_label_id, dst_label_id));
+        }
+        schema.vertex_ids = vertex_ids;
+        Ok(schema)
+    }
+
+    /// Derive the schema, create the types in an empty `graph` and write all the data at `si`.
+    /// Every schema change bumps the schema version, starting from `schema_version + 1`.
+    pub fn import<G: MultiVersionGraph>(
+        &self, graph: &G, si: SnapshotId, schema_version: i64,
+    ) -> GraphResult<Neo4jImportSummary> {
+        let schema = self.derive_schema()?;
+        let mut summary = Neo4jImportSummary { schema_version, ..Default::default() };
+        let mut table_id = 1;
+        for (label_id, type_def) in schema.vertex_types.values() {
+            summary.schema_version += 1;
+            graph.create_vertex_type(si, summary.schema_version, *label_id, type_def, table_id)?;
+            table_id += 1;
+        }
+        for (label_id, type_def) in schema.edge_types.values() {
+            summary.schema_version += 1;
+            graph.create_edge_type(si, summary.schema_version, *label_id, type_def)?;
+        }
+        for edge_kind in &schema.edge_kinds {
+            summary.schema_version += 1;
+            graph.add_edge_kind(si, summary.schema_version, edge_kind, table_id)?;
+            table_id += 1;
+        }
+
+        for file in &self.node_files {
+            self.read_rows(file, Neo4jFileKind::Node, |header, row| {
+                let label = self.row_label(file, header, row)?;
+                let label_id = schema.vertex_types[&label].0;
+                let (id_space, id) = node_id(header, row);
+                let vertex_id = schema.vertex_ids[&(id_space, id)].0;
+                let props = self.row_properties(header, row, &schema.prop_ids)?;
+                graph.insert_overwrite_vertex(si, vertex_id, label_id, &props)?;
+                summary.vertex_count += 1;
+                Ok(())
+            })?;
+        }
+        let mut edge_inner_id = 0;
+        for file in &self.relationship_files {
+            self.read_rows(file, Neo4jFileKind::Relationship, |header, row| {
+                let label = self.row_label(file, header, row)?;
+                let label_id = schema.edge_types[&label].0;
+                let (src, dst) = edge_endpoints(header, row, &schema.vertex_ids)?;
+                let edge_kind = EdgeKind::new(label_id, src.1, dst.1);
+                edge_inner_id += 1;
+                let edge_id = EdgeId::new(src.0, dst.0, edge_inner_id);
+                let props = self.row_properties(header, row, &schema.prop_ids)?;
+                graph.insert_overwrite_edge(si, edge_id, &edge_kind, true, &props)?;
+                graph.insert_overwrite_edge(si, edge_id, &edge_kind, false, &props)?;
+                summary.edge_count += 1;
+                Ok(())
+            })?;
+        }
+        info!(
+            "neo4j csv import finished: {} vertices, {} edges, schema version {}",
+            summary.vertex_count, summary.edge_count, summary.schema_version
+        );
+        Ok(summary)
+    }
+
+    /// Read the header of `file` and call `f` for every data row, returns the header.
+    fn read_rows<F>(
+        &self, file: &Neo4jCsvFile, kind: Neo4jFileKind, mut f: F,
+    ) -> GraphResult<Neo4jCsvHeader>
+    where
+        F: FnMut(&Neo4jCsvHeader, &[String]) -> GraphResult<()>,
+    {
+        let header_path = file.header.as_ref().unwrap_or(&file.paths[0]);
+        let path = header_path.to_string_lossy().to_string();
+        let header = match open_lines(header_path)?.next() {
+            Some(Ok(line)) => Neo4jCsvHeader::parse(&line, self.config.delimiter)?,
+            _ => {
+                let msg = format!("{} has no header line", path);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, read_rows, path));
+            }
+        };
+        if header.kind != kind {
+            let msg = format!("{} is expected to be a {:?} file but found {:?}", path, kind, header.kind);
+            return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, read_rows, path));
+        }
+        // the header line is skipped if the data file starts with it
+        let skipped = if file.header.is_some() { 0 } else { 1 };
+        for data_path in file.paths.iter() {
+            let path = data_path.to_string_lossy().to_string();
+            for (line_no, line) in open_lines(data_path)?.enumerate().skip(skipped) {
+                let line = line.map_err(|e| {
+                    let msg = format!("read {} failed: {}", path, e);
+                    gen_graph_err!(GraphErrorCode::InvalidOperation, msg, read_rows, path)
+                })?;
+                if line.is_empty() {
+                    continue;
+                }
+                let row = split_record(&line, self.config.delimiter);
+                if row.len() != header.columns.len() {
+                    let msg = format!(
+                        "{}:{} has {} fields but the header has {} columns",
+                        path,
+                        line_no + 1,
+                        row.len(),
+                        header.columns.len()
+                    );
+                    return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, read_rows, path));
+                }
+                f(&header, &row)?;
+            }
+        }
+        Ok(header)
+    }
+
+    /// Groot vertices have a single label, so a row with multiple neo4j labels is rejected instead of
+    /// dropping all but one of them.
+    fn row_label(
+        &self, file: &Neo4jCsvFile, header: &Neo4jCsvHeader, row: &[String],
+    ) -> GraphResult<String> {
+        let labels: Vec<&str> = header
+            .columns
+            .iter()
+            .position(|c| *c == Neo4jColumn::Label || *c == Neo4jColumn::Type)
+            .map(|idx| {
+                row[idx]
+                    .split(self.config.array_delimiter)
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        match labels.as_slice() {
+            [label] => Ok((*label).to_owned()),
+            [] => file.default_label.clone().ok_or_else(|| {
+                let msg = format!("no label found for row {:?} in {:?}", row, file.paths);
+                gen_graph_err!(GraphErrorCode::InvalidData, msg, row_label)
+            }),
+            _ => {
+                let msg = format!(
+                    "row {:?} in {:?} has multiple labels {:?}, but a groot vertex has a single label",
+                    row, file.paths, labels
+                );
+                Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, row_label))
+            }
+        }
+    }
+
+    /// All the labels rows of `file` may have. The file is rescanned if labels come from the data.
+    fn labels_of(&self, file: &Neo4jCsvFile, header: &Neo4jCsvHeader) -> GraphResult<BTreeSet<String>> {
+        let mut labels = BTreeSet::new();
+        if header
+            .columns
+            .iter()
+            .any(|c| *c == Neo4jColumn::Label || *c == Neo4jColumn::Type)
+        {
+            self.read_rows(file, header.kind, |header, row| {
+                labels.insert(self.row_label(file, header, row)?);
+                Ok(())
+            })?;
+        } else if let Some(label) = file.default_label.as_ref() {
+            labels.insert(label.clone());
+        }
+        Ok(labels)
+    }
+
+    fn row_properties(
+        &self, header: &Neo4jCsvHeader, row: &[String], prop_ids: &BTreeMap<String, PropertyId>,
+    ) -> GraphResult<HashMap<PropertyId, Value>> {
+        let mut props = HashMap::new();
+        for (column, raw) in header.columns.iter().zip(row.iter()) {
+            let (name, r#type) = match column {
+                Neo4jColumn::Id { name: Some(name), .. } => (name, ValueType::String),
+                Neo4jColumn::Property { name, r#type } => (name, *r#type),
+                _ => continue,
+            };
+            if let Some(value) = parse_value(raw, r#type, self.config.array_delimiter)? {
+                props.insert(prop_ids[name], value);
+            }
+        }
+        Ok(props)
+    }
+}
+
+fn open_lines(path: &Path) -> GraphResult<Lines<BufReader<File>>> {
+    File::open(path)
+        .map(|f| BufReader::new(f).lines())
+        .map_err(|e| {
+            let path = path.to_string_lossy().to_string();
+            let msg = format!("open {} failed: {}", path, e);
+            gen_graph_err!(GraphErrorCode::InvalidOperation, msg, open_lines, path)
+        })
+}
+
+fn node_id(header: &Neo4jCsvHeader, row: &[String]) -> (String, String) {
+    for (column, raw) in header.columns.iter().zip(row.iter()) {
+        if let Neo4jColumn::Id { id_space, .. } = column {
+            return (id_space.clone(), raw.clone());
+        }
+    }
+    unreachable!("node header without :ID column")
+}
+
+fn edge_endpoints(
+    header: &Neo4jCsvHeader, row: &[String], vertex_ids: &HashMap<(String, String), (VertexId, LabelId)>,
+) -> GraphResult<((VertexId, LabelId), (VertexId, LabelId))> {
+    let mut src = None;
+    let mut dst = None;
+    for (column, raw) in header.columns.iter().zip(row.iter()) {
+        let (slot, id_space) = match column {
+            Neo4jColumn::StartId { id_space } => (&mut src, id_space),
+            Neo4jColumn::EndId { id_space } => (&mut dst, id_space),
+            _ => continue,
+        };
+        let key = (id_space.clone(), raw.clone());
+        match vertex_ids.get(&key) {
+            Some(v) => *slot = Some(*v),
+            None => {
+                let msg = format!("relationship endpoint {:?} not found in node files", key);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, edge_endpoints));
+            }
+        }
+    }
+    Ok((src.unwrap(), dst.unwrap()))
+}
+
+fn merge_props(
+    props: &mut BTreeMap<String, (ValueType, bool)>, header: &Neo4jCsvHeader,
+) -> GraphResult<()> {
+    for (name, r#type) in header.properties() {
+        let is_pk = header
+            .columns
+            .iter()
+            .any(|c| matches!(c, Neo4jColumn::Id { name: Some(n), .. } if n == name));
+        match props.get(name) {
+            Some((t, _)) if *t != r#type => {
+                let msg = format!("property `{}` has conflicting types {:?} and {:?}", name, t, r#type);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, merge_props));
+            }
+            Some(_) => {}
+            None => {
+                props.insert(name.clone(), (r#type, is_pk));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn build_type_def(
+    label: &str, label_id: LabelId, props: &BTreeMap<String, (ValueType, bool)>,
+    prop_ids: &mut BTreeMap<String, PropertyId>,
+) -> TypeDef {
+    let mut builder = TypeDefBuilder::new();
+    builder.set_label_id(label_id).set_label(label);
+    for (name, (r#type, pk)) in props {
+        let next_id = prop_ids.len() as PropertyId + 1;
+        let prop_id = *prop_ids.entry(name.clone()).or_insert(next_id);
+        builder.add_property(prop_id, prop_id, name.clone(), *r#type, None, *pk, String::new());
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::util::fs;
+
+    #[test]
+    fn test_parse_column() {
+        assert_eq!(
+            Neo4jColumn::parse("personId:ID(Person)").unwrap(),
+            Neo4jColumn::Id { name: Some("personId".to_owned()), id_space: "Person".to_owned() }
+        );
+        assert_eq!(
+            Neo4jColumn::parse(":ID").unwrap(),
+            Neo4jColumn::Id { name: None, id_space: String::new() }
+        );
+        assert_eq!(
+            Neo4jColumn::parse(":START_ID(Person)").unwrap(),
+            Neo4jColumn::StartId { id_space: "Person".to_owned() }
+        );
+        assert_eq!(Neo4jColumn::parse(":LABEL").unwrap(), Neo4jColumn::Label);
+        assert_eq!(Neo4jColumn::parse(":TYPE").unwrap(), Neo4jColumn::Type);
+        assert_eq!(
+            Neo4jColumn::parse("name").unwrap(),
+            Neo4jColumn::Property { name: "name".to_owned(), r#type: ValueType::String }
+        );
+        assert_eq!(
+            Neo4jColumn::parse("emails:string[]").unwrap(),
+            Neo4jColumn::Property { name: "emails".to_owned(), r#type: ValueType::StringList }
+        );
+        assert_eq!(
+            Neo4jColumn::parse("scores:long[]").unwrap(),
+            Neo4jColumn::Property { name: "scores".to_owned(), r#type: ValueType::LongList }
+        );
+        assert!(Neo4jColumn::parse(":int").is_err());
+        assert!(Neo4jColumn::parse("x:unknown").is_err());
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let header = Neo4jCsvHeader::parse("id:ID(Person),name,age:int,:LABEL", ',').unwrap();
+        assert_eq!(header.kind, Neo4jFileKind::Node);
+        assert_eq!(header.columns.len(), 4);
+        let header = Neo4jCsvHeader::parse(":START_ID,:END_ID,:TYPE,since:long", ',').unwrap();
+        assert_eq!(header.kind, Neo4jFileKind::Relationship);
+        assert!(Neo4jCsvHeader::parse("name,age:int", ',').is_err());
+    }
+
+    #[test]
+    fn test_split_record() {
+        assert_eq!(split_record("1,marko,29", ','), vec!["1", "marko", "29"]);
+        assert_eq!(split_record(r#"1,"a,b","say ""hi""""#, ','), vec!["1", "a,b", r#"say "hi""#]);
+        assert_eq!(split_record("1,,", ','), vec!["1", "", ""]);
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert!(parse_value("", ValueType::Int, ';')
+            .unwrap()
+            .is_none());
+        let v = parse_value("29", ValueType::Int, ';')
+            .unwrap()
+            .unwrap();
+        assert_eq!(v.get_int().unwrap(), 29);
+        let v = parse_value("1;2;3", ValueType::LongList, ';')
+            .unwrap()
+            .unwrap();
+        assert_eq!(v.get_long_list().unwrap().len(), 3);
+        let v = parse_value("a;b", ValueType::StringList, ';')
+            .unwrap()
+            .unwrap();
+        assert_eq!(v, Value::string_list(&["a".to_owned(), "b".to_owned()]));
+        assert!(parse_value("x", ValueType::Long, ';').is_err());
+    }
+
+    #[test]
+    fn test_header_files() {
+        let dir = "store_test/test_neo4j_csv_header_files";
+        fs::rmr(dir).unwrap();
+        std::fs::create_dir_all(dir).unwrap();
+        let write = |name: &str, text: &str| {
+            let path = format!("{}/{}", dir, name);
+            std::fs::write(&path, text).unwrap();
+            path
+        };
+        let header = write("persons_header.csv", "id:ID(Person),name,:LABEL\n");
+        let part1 = write("persons_1.csv", "1,marko,Person\n2,vadas,Person\n");
+        let part2 = write("persons_2.csv", "3,josh,Person\n");
+        let knows = write("knows.csv", ":START_ID(Person),:END_ID(Person),:TYPE\n1,2,KNOWS\n1,3,KNOWS\n");
+        let mut importer = Neo4jCsvImporter::new(Neo4jCsvImportConfig::default());
+        importer
+            .add_node_files(header.clone(), vec![part1.clone(), part2.clone()], None)
+            .add_relationship_file(knows, None);
+        let schema = importer.derive_schema().unwrap();
+        assert_eq!(schema.vertex_types.keys().collect::<Vec<_>>(), vec!["Person"]);
+        assert_eq!(schema.edge_types.keys().collect::<Vec<_>>(), vec!["KNOWS"]);
+        assert_eq!(schema.vertex_ids.len(), 3);
+
+        // a row of multiple labels is rejected instead of losing all but one of them
+        let multi_label = write("persons_3.csv", "4,peter,Person;Employee\n");
+        let mut importer = Neo4jCsvImporter::new(Neo4jCsvImportConfig::default());
+        importer.add_node_files(header, vec![part1, multi_label], None);
+        assert!(importer.derive_schema().is_err());
+        fs::rmr(dir).unwrap();
+    }
+}
@@ -0,0 +1,486 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Map fields of structured records (avro, protobuf or plain json) to typed properties with a
+//! declarative mapping, e.g.
+//!
+//! ```text
+//! {
+//!   "label": "person",
+//!   "properties": [
+//!     {"field": "user.id", "property": "id", "required": true},
+//!     {"field": "user.name", "property": "name"},
+//!     {"field": "age", "property": "age", "default": 0}
+//!   ]
+//! }
+//! ```
+//!
+//! Edge mappings additionally map the primary keys of both endpoints with `src` and `dst`. The
+//! mapping is checked against the graph schema once when compiled, and every record is validated
+//! against the property types when mapped. Records are normalized to json first, so the same
+//! mapping works for bulk load files and streaming ingestion alike: a mapped record is turned into
+//! a mutation by `CompiledMapping::to_mutation`, which the kafka ingestion applies, and bulk loads
+//! add to the sst files by `PartitionSstBuilder::add_mapped_record`.
+
+use std::collections::HashMap;
+
+use serde_json::Value as JsonValue;
+
+use super::mutation::{edge_inner_id, find_type_def, pk_vertex_id, Mutation, MutationOp};
+use crate::api::Property;
+use crate::db::api::*;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct FieldMapping {
+    /// Dot separated path of the field in the record, e.g. `user.address.city`.
+    pub field: String,
+    pub property: String,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<JsonValue>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EndpointMapping {
+    pub label: String,
+    pub properties: Vec<FieldMapping>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RecordMapping {
+    pub label: String,
+    #[serde(default)]
+    pub properties: Vec<FieldMapping>,
+    #[serde(default)]
+    pub src: Option<EndpointMapping>,
+    #[serde(default)]
+    pub dst: Option<EndpointMapping>,
+}
+
+impl RecordMapping {
+    pub fn from_json_str(text: &str) -> GraphResult<Self> {
+        serde_json::from_str(text).map_err(|e| {
+            let msg = format!("invalid record mapping: {}", e);
+            gen_graph_err!(GraphErrorCode::InvalidData, msg, from_json_str)
+        })
+    }
+
+    /// Resolve labels and properties of the mapping against `graph_def`.
+    pub fn compile(&self, graph_def: &GraphDef) -> GraphResult<CompiledMapping> {
+        let target = CompiledTarget::new(&self.label, &self.properties, graph_def)?;
+        let endpoints = match (&self.src, &self.dst) {
+            (Some(src), Some(dst)) => Some((
+                CompiledTarget::new(&src.label, &src.properties, graph_def)?,
+                CompiledTarget::new(&dst.label, &dst.properties, graph_def)?,
+            )),
+            (None, None) => None,
+            _ => {
+                let msg = format!("mapping of `{}` must have both `src` and `dst` or neither", self.label);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, compile));
+            }
+        };
+        Ok(CompiledMapping { target, endpoints })
+    }
+}
+
+struct CompiledField {
+    path: Vec<String>,
+    prop_id: PropertyId,
+    r#type: ValueType,
+    required: bool,
+    default: Option<Property>,
+}
+
+struct CompiledTarget {
+    type_def: TypeDef,
+    fields: Vec<CompiledField>,
+}
+
+impl CompiledTarget {
+    fn new(label: &str, fields: &[FieldMapping], graph_def: &GraphDef) -> GraphResult<Self> {
+        let type_def = find_type_def(graph_def, label)?.clone();
+        let mut compiled = Vec::with_capacity(fields.len());
+        for field in fields {
+            let prop_def = graph_def
+                .property_name_to_id
+                .get(&field.property)
+                .and_then(|id| type_def.get_prop_def(*id))
+                .ok_or_else(|| {
+                    let msg = format!("property `{}` not found in `{}`", field.property, label);
+                    gen_graph_err!(GraphErrorCode::InvalidData, msg, new)
+                })?;
+            let default = match field.default.as_ref() {
+                Some(v) => Some(json_to_property(v, prop_def.r#type)?),
+                None => None,
+            };
+            compiled.push(CompiledField {
+                path: field
+                    .field
+                    .split('.')
+                    .map(|s| s.to_owned())
+                    .collect(),
+                prop_id: prop_def.id,
+                r#type: prop_def.r#type,
+                required: field.required,
+                default,
+            });
+        }
+        Ok(CompiledTarget { type_def, fields: compiled })
+    }
+
+    fn map(&self, record: &JsonValue) -> GraphResult<Vec<(PropertyId, Property)>> {
+        let mut properties = Vec::with_capacity(self.fields.len());
+        for field in &self.fields {
+            let value = field
+                .path
+                .iter()
+                .try_fold(record, |v, key| v.get(key))
+                .filter(|v| !v.is_null());
+            let property = match (value, field.default.as_ref()) {
+                (Some(v), _) => res_unwrap!(json_to_property(v, field.r#type), map)?,
+                (None, Some(default)) => default.clone(),
+                (None, None) if field.required => {
+                    let msg = format!("required field `{}` is missing", field.path.join("."));
+                    return Err(gen_graph_err!(GraphErrorCode::InvalidData, msg, map));
+                }
+                (None, None) => continue,
+            };
+            properties.push((field.prop_id, property));
+        }
+        Ok(properties)
+    }
+
+    fn vertex_id(&self, properties: &[(PropertyId, Property)]) -> GraphResult<VertexId> {
+        pk_vertex_id(&self.type_def, &to_value_map(properties))
+    }
+}
+
+pub struct CompiledMapping {
+    target: CompiledTarget,
+    endpoints: Option<(CompiledTarget, CompiledTarget)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MappedRecord {
+    pub label: LabelId,
+    pub properties: Vec<(PropertyId, Property)>,
+    /// (src id, src label, dst id, dst label) of an edge record
+    pub endpoints: Option<(VertexId, LabelId, VertexId, LabelId)>,
+}
+
+impl MappedRecord {
+    pub fn value_map(&self) -> HashMap<PropertyId, Value> {
+        to_value_map(&self.properties)
+    }
+}
+
+impl CompiledMapping {
+    pub fn map_json(&self, record: &JsonValue) -> GraphResult<MappedRecord> {
+        let properties = self.target.map(record)?;
+        let endpoints = match self.endpoints.as_ref() {
+            Some((src, dst)) => {
+                let src_id = src.vertex_id(&src.map(record)?)?;
+                let dst_id = dst.vertex_id(&dst.map(record)?)?;
+                Some((src_id, src.type_def.get_label_id(), dst_id, dst.type_def.get_label_id()))
+            }
+            None => None,
+        };
+        Ok(MappedRecord { label: self.target.type_def.get_label_id(), properties, endpoints })
+    }
+
+    /// Map an avro datum written with `schema`.
+    #[cfg(feature = "avro")]
+    pub fn map_avro(&self, schema: &apache_avro::Schema, datum: &[u8]) -> GraphResult<MappedRecord> {
+        self.map_json(&avro_datum_to_json(schema, datum)?)
+    }
+
+    /// Map a protobuf message, fields are addressed by their names in the `.proto` file.
+    pub fn map_protobuf(&self, message: &dyn protobuf::Message) -> GraphResult<MappedRecord> {
+        self.map_json(&protobuf_to_json(message))
+    }
+
+    /// The mutation of a mapped record, whose ids are derived like those of the mutation records
+    /// without ids: a vertex id is hashed from the primary keys, and an edge inner id from the
+    /// label and the endpoints.
+    pub fn to_mutation(&self, record: MappedRecord, op: MutationOp) -> GraphResult<Mutation> {
+        let properties = record.value_map();
+        match record.endpoints {
+            Some((src_id, src_label, dst_id, dst_label)) => {
+                let kind = EdgeKind::new(record.label, src_label, dst_label);
+                let id = EdgeId::new(src_id, dst_id, edge_inner_id(&kind, src_id, dst_id));
+                Ok(Mutation::Edge { op, id, kind, properties })
+            }
+            None => {
+                let id = pk_vertex_id(&self.target.type_def, &properties)?;
+                Ok(Mutation::Vertex { op, id, label: record.label, properties })
+            }
+        }
+    }
+}
+
+/// Decode an avro datum written with `schema` to json.
+#[cfg(feature = "avro")]
+pub fn avro_datum_to_json(schema: &apache_avro::Schema, datum: &[u8]) -> GraphResult<JsonValue> {
+    use std::convert::TryFrom;
+
+    apache_avro::from_avro_datum(schema, &mut std::io::Cursor::new(datum), None)
+        .map_err(|e| e.to_string())
+        .and_then(|v| JsonValue::try_from(v).map_err(|e| e.to_string()))
+        .map_err(|e| {
+            let msg = format!("invalid avro record: {}", e);
+            gen_graph_err!(GraphErrorCode::InvalidData, msg, avro_datum_to_json)
+        })
+}
+
+/// Convert a protobuf message to json by reflection. Unset singular fields and map fields are
+/// omitted, enums are converted to their names.
+fn protobuf_to_json(message: &dyn protobuf::Message) -> JsonValue {
+    use protobuf::reflect::{ReflectFieldRef, ReflectValueRef};
+
+    fn value_to_json(value: ReflectValueRef) -> JsonValue {
+        match value {
+            ReflectValueRef::U32(v) => json!(v),
+            ReflectValueRef::U64(v) => json!(v),
+            ReflectValueRef::I32(v) => json!(v),
+            ReflectValueRef::I64(v) => json!(v),
+            ReflectValueRef::F32(v) => json!(v),
+            ReflectValueRef::F64(v) => json!(v),
+            ReflectValueRef::Bool(v) => json!(v),
+            ReflectValueRef::String(v) => json!(v),
+            ReflectValueRef::Bytes(v) => json!(v),
+            ReflectValueRef::Enum(v) => json!(v.name()),
+            ReflectValueRef::Message(m) => protobuf_to_json(m),
+        }
+    }
+
+    let mut object = serde_json::Map::new();
+    for field in message.descriptor().fields() {
+        let json = match field.get_reflect(message) {
+            ReflectFieldRef::Optional(Some(v)) => value_to_json(v),
+            ReflectFieldRef::Optional(None) | ReflectFieldRef::Map(_) => continue,
+            ReflectFieldRef::Repeated(r) => JsonValue::Array(
+                r.reflect_iter()
+                    .map(|v| value_to_json(v.as_ref()))
+                    .collect(),
+            ),
+        };
+        object.insert(field.name().to_owned(), json);
+    }
+    JsonValue::Object(object)
+}
+
+/// Convert a json value to a property of `r#type`. Numbers encoded as strings are accepted, as
+/// protobuf's json mapping writes 64 bits integers that way.
+pub fn json_to_property(json: &JsonValue, r#type: ValueType) -> GraphResult<Property> {
+    let mismatch = || {
+        let msg = format!("cannot convert {} to {:?}", json, r#type);
+        gen_graph_err!(GraphErrorCode::ValueTypeMismatch, msg, json_to_property)
+    };
+    fn as_i64(v: &JsonValue) -> Option<i64> {
+        v.as_i64()
+            .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+    }
+    fn as_f64(v: &JsonValue) -> Option<f64> {
+        v.as_f64()
+            .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+    }
+    macro_rules! int_of {
+        ($v:expr, $t:ty) => {
+            as_i64($v)
+                .and_then(|x| <$t as std::convert::TryFrom<i64>>::try_from(x).ok())
+                .ok_or_else(mismatch)?
+        };
+    }
+    macro_rules! list_of {
+        ($f:expr) => {{
+            let mut list = Vec::new();
+            for item in json.as_array().ok_or_else(mismatch)? {
+                list.push($f(item).ok_or_else(mismatch)?);
+            }
+            list
+        }};
+    }
+    let property = match r#type {
+        ValueType::Bool => Property::Bool(json.as_bool().ok_or_else(mismatch)?),
+        ValueType::Char => match json {
+            JsonValue::String(s) if s.len() == 1 => Property::Char(s.as_bytes()[0]),
+            _ => Property::Char(int_of!(json, u8)),
+        },
+        ValueType::Short => Property::Short(int_of!(json, i16)),
+        ValueType::Int => Property::Int(int_of!(json, i32)),
+        ValueType::Long => Property::Long(as_i64(json).ok_or_else(mismatch)?),
+        ValueType::Float => Property::Float(as_f64(json).ok_or_else(mismatch)? as f32),
+        ValueType::Double => Property::Double(as_f64(json).ok_or_else(mismatch)?),
+        ValueType::String => match json {
+            JsonValue::String(s) => Property::String(s.clone()),
+            JsonValue::Null | JsonValue::Array(_) | JsonValue::Object(_) => return Err(mismatch()),
+            x => Property::String(x.to_string()),
+        },
+        ValueType::Bytes => match json {
+            JsonValue::String(s) => Property::Bytes(s.as_bytes().to_vec()),
+            _ => Property::Bytes(list_of!(|v: &JsonValue| v.as_u64().map(|x| x as u8))),
+        },
+        ValueType::IntList => Property::ListInt(list_of!(|v: &JsonValue| as_i64(v).map(|x| x as i32))),
+        ValueType::LongList => Property::ListLong(list_of!(as_i64)),
+        ValueType::FloatList => Property::ListFloat(list_of!(|v: &JsonValue| as_f64(v).map(|x| x as f32))),
+        ValueType::DoubleList => Property::ListDouble(list_of!(as_f64)),
+        ValueType::StringList => {
+            Property::ListString(list_of!(|v: &JsonValue| v.as_str().map(|s| s.to_owned())))
+        }
+    };
+    Ok(property)
+}
+
+/// Encode a property as a store value, `Null` and `Unknown` have no value.
+pub fn property_to_value(property: &Property) -> Option<Value> {
+    let value = match property {
+        Property::Bool(v) => Value::bool(*v),
+        Property::Char(v) => Value::char(*v),
+        Property::Short(v) => Value::short(*v),
+        Property::Int(v) => Value::int(*v),
+        Property::Long(v) => Value::long(*v),
+        Property::Float(v) => Value::float(*v),
+        Property::Double(v) => Value::double(*v),
+        Property::Bytes(v) => Value::bytes(v),
+        Property::String(v) | Property::Date(v) => Value::string(v),
+        Property::ListInt(v) => Value::int_list(v),
+        Property::ListLong(v) => Value::long_list(v),
+        Property::ListFloat(v) => Value::float_list(v),
+        Property::ListDouble(v) => Value::double_list(v),
+        Property::ListString(v) => Value::string_list(v),
+        Property::ListBytes(_) | Property::Null | Property::Unknown => return None,
+    };
+    Some(value)
+}
+
+fn to_value_map(properties: &[(PropertyId, Property)]) -> HashMap<PropertyId, Value> {
+    properties
+        .iter()
+        .filter_map(|(id, p)| property_to_value(p).map(|v| (*id, v)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_to_property() {
+        assert_eq!(json_to_property(&json!(29), ValueType::Int).unwrap(), Property::Int(29));
+        assert_eq!(
+            json_to_property(&json!("9007199254740993"), ValueType::Long).unwrap(),
+            Property::Long(9007199254740993)
+        );
+        assert_eq!(
+            json_to_property(&json!(["a", "b"]), ValueType::StringList).unwrap(),
+            Property::ListString(vec!["a".to_owned(), "b".to_owned()])
+        );
+        assert!(json_to_property(&json!(70000), ValueType::Short).is_err());
+        assert!(json_to_property(&json!([1, "x"]), ValueType::LongList).is_err());
+    }
+
+    #[test]
+    fn test_parse_mapping() {
+        let mapping = RecordMapping::from_json_str(
+            r#"{"label": "knows",
+                "properties": [{"field": "meta.weight", "property": "weight", "default": 1.0}],
+                "src": {"label": "person", "properties": [{"field": "from", "property": "id", "required": true}]},
+                "dst": {"label": "person", "properties": [{"field": "to", "property": "id", "required": true}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(mapping.label, "knows");
+        assert_eq!(mapping.properties[0].field, "meta.weight");
+        assert!(!mapping.properties[0].required);
+        assert!(mapping.src.unwrap().properties[0].required);
+        assert!(RecordMapping::from_json_str(r#"{"properties": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_to_mutation() {
+        let mut graph_def = GraphDef::default();
+        let mut person = TypeDefBuilder::new();
+        person.set_label_id(1).set_label("person");
+        person.add_property(1, 1, "id".to_owned(), ValueType::Long, None, true, String::new());
+        person.add_property(2, 2, "name".to_owned(), ValueType::String, None, false, String::new());
+        graph_def.add_type(1, person.build()).unwrap();
+        let mut knows = TypeDefBuilder::new();
+        knows.set_label_id(2).set_label("knows");
+        knows.add_property(3, 3, "weight".to_owned(), ValueType::Double, None, false, String::new());
+        graph_def.add_type(2, knows.build()).unwrap();
+
+        // the ids are the same as those of the mutation records without ids
+        let mapping = RecordMapping::from_json_str(
+            r#"{"label": "person",
+                "properties": [{"field": "user.id", "property": "id"},
+                               {"field": "user.name", "property": "name"}]}"#,
+        )
+        .unwrap()
+        .compile(&graph_def)
+        .unwrap();
+        let record = mapping
+            .map_json(&json!({"user": {"id": 1, "name": "marko"}}))
+            .unwrap();
+        let mutation = mapping
+            .to_mutation(record, MutationOp::Insert)
+            .unwrap();
+        let expected = Mutation::from_json(
+            &json!({"op": "insert", "kind": "vertex", "label": "person",
+                    "properties": {"id": 1, "name": "marko"}}),
+            &graph_def,
+        )
+        .unwrap();
+        match (mutation, expected) {
+            (Mutation::Vertex { id, label, properties, .. }, Mutation::Vertex { id: expected_id, .. }) => {
+                assert_eq!(id, expected_id);
+                assert_eq!(label, 1);
+                assert_eq!(properties.get(&2).unwrap().get_str().unwrap(), "marko");
+            }
+            _ => panic!("vertex mutations expected"),
+        }
+
+        let mapping = RecordMapping::from_json_str(
+            r#"{"label": "knows",
+                "properties": [{"field": "weight", "property": "weight"}],
+                "src": {"label": "person", "properties": [{"field": "from", "property": "id"}]},
+                "dst": {"label": "person", "properties": [{"field": "to", "property": "id"}]}}"#,
+        )
+        .unwrap()
+        .compile(&graph_def)
+        .unwrap();
+        let record = mapping
+            .map_json(&json!({"from": 1, "to": 2, "weight": 0.5}))
+            .unwrap();
+        let mutation = mapping
+            .to_mutation(record, MutationOp::Insert)
+            .unwrap();
+        let expected = Mutation::from_json(
+            &json!({"op": "insert", "kind": "edge", "label": "knows", "src_label": "person", "src": {"id": 1},
+                    "dst_label": "person", "dst": {"id": 2}, "properties": {"weight": 0.5}}),
+            &graph_def,
+        )
+        .unwrap();
+        match (mutation, expected) {
+            (
+                Mutation::Edge { id, kind, .. },
+                Mutation::Edge { id: expected_id, kind: expected_kind, .. },
+            ) => {
+                assert_eq!(id, expected_id);
+                assert!(kind == expected_kind);
+            }
+            _ => panic!("edge mutations expected"),
+        }
+    }
+}
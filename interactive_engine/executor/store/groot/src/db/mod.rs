@@ -1,9 +1,12 @@
 #[macro_use]
 pub mod api;
 pub mod common;
+pub mod consensus;
 pub mod graph;
+pub mod import;
 #[allow(bare_trait_objects)]
 pub mod proto;
+pub mod service;
 pub mod storage;
 pub mod util;
 pub mod wrapper;
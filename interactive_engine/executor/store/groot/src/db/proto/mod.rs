@@ -4,3 +4,15 @@ pub mod schema_common;
 pub mod model;
 #[rustfmt::skip]
 pub mod schema;
+#[rustfmt::skip]
+pub mod stream_write_service;
+#[rustfmt::skip]
+pub mod stream_write_service_grpc;
+#[rustfmt::skip]
+pub mod neighbor_sampling_service;
+#[rustfmt::skip]
+pub mod neighbor_sampling_service_grpc;
+#[rustfmt::skip]
+pub mod raft_service;
+#[rustfmt::skip]
+pub mod raft_service_grpc;
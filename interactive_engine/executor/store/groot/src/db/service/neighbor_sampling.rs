@@ -0,0 +1,447 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Neighbor sampling service, see `neighbor_sampling_service.proto`.
+//!
+//! The sources of a hop are split into chunks of `chunk_size`, which are sampled by a dedicated pool
+//! of sampler threads, so a large batch is sampled in parallel and the grpc threads are never blocked
+//! by the store. Samplers are fed through bounded channels: once all of them are busy, the requests
+//! wait for their chunks to be taken. Every source is sampled by a random generator seeded by the
+//! request seed, the hop and the source, so a seeded request samples the same neighbors however its
+//! sources are chunked.
+//!
+//! Only the edges in the partitions of this process are expanded, the sources of the other partitions
+//! have no neighbor sampled. In a deployment of many stores, a client samples each hop from the stores
+//! of its sources.
+//!
+//! Started on `store.neighbor.sampling.port` by the store if it's set.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use futures::channel::{mpsc, oneshot};
+use futures::executor::block_on;
+use futures::{SinkExt, StreamExt};
+use grpcio::{Environment, RpcContext, Server, ServerBuilder, UnarySink};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::api::Vertex;
+use crate::db::api::multi_version_graph::MultiVersionGraph;
+use crate::db::api::types::{RocksEdge, RocksVertex};
+use crate::db::api::*;
+use crate::db::graph::store::GraphStore;
+use crate::db::import::record_mapping::property_to_value;
+use crate::db::proto::model::StorePropertyPb;
+use crate::db::proto::neighbor_sampling_service::{
+    SampleDirectionPb, SampleRequestPb, SampleResponsePb, SampleStrategyPb, SampledHopPb, SampledVertexPb,
+};
+use crate::db::proto::neighbor_sampling_service_grpc::{create_neighbor_sampling, NeighborSampling};
+
+/// The partitions of the store in this process, which the vertices are sampled from.
+pub trait SamplingPartitions: Send + Sync {
+    /// The partition of the vertex, `None` if it's not in this process.
+    fn get_vertex_partition(&self, vertex_id: VertexId) -> Option<Arc<GraphStore>>;
+
+    /// The latest snapshot readable by all the partitions, see `GraphStore::get_readable_si`.
+    fn get_readable_si(&self) -> SnapshotId;
+}
+
+#[derive(Clone, Debug)]
+pub struct NeighborSamplingConfig {
+    pub threads: usize,
+    /// number of chunks buffered by every sampler before the requests wait
+    pub queue_capacity: usize,
+    /// number of sources, or vertices to fetch, of a chunk
+    pub chunk_size: usize,
+    /// the most neighbors a request may sample in all its hops
+    pub max_sampled: usize,
+}
+
+impl Default for NeighborSamplingConfig {
+    fn default() -> Self {
+        NeighborSamplingConfig { threads: 4, queue_capacity: 64, chunk_size: 256, max_sampled: 1 << 20 }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct HopSpec {
+    index: usize,
+    label: Option<LabelId>,
+    direction: EdgeDirection,
+    fanout: usize,
+    with_replacement: bool,
+}
+
+enum SampleTask {
+    Expand {
+        si: SnapshotId,
+        sources: Vec<VertexId>,
+        hop: HopSpec,
+        seed: u64,
+        reply: oneshot::Sender<GraphResult<Vec<Vec<VertexId>>>>,
+    },
+    Fetch {
+        si: SnapshotId,
+        ids: Vec<VertexId>,
+        prop_ids: Vec<PropertyId>,
+        reply: oneshot::Sender<GraphResult<Vec<SampledVertexPb>>>,
+    },
+}
+
+/// Requests without a snapshot id are sampled at the latest snapshot readable by the partitions.
+#[derive(Clone)]
+pub struct NeighborSamplingService {
+    samplers: Arc<Vec<mpsc::Sender<SampleTask>>>,
+    next_sampler: Arc<AtomicUsize>,
+    partitions: Arc<dyn SamplingPartitions>,
+    config: NeighborSamplingConfig,
+}
+
+impl NeighborSamplingService {
+    pub fn new(config: NeighborSamplingConfig, partitions: Arc<dyn SamplingPartitions>) -> Self {
+        let threads = config.threads.max(1);
+        let mut samplers = Vec::with_capacity(threads);
+        for i in 0..threads {
+            let (tx, rx) = mpsc::channel(config.queue_capacity);
+            let sampler = Sampler { partitions: partitions.clone() };
+            thread::Builder::new()
+                .name(format!("neighbor-sampler-{}", i))
+                .spawn(move || sampler.run(rx))
+                .expect("spawn neighbor sampler failed");
+            samplers.push(tx);
+        }
+        NeighborSamplingService {
+            samplers: Arc::new(samplers),
+            next_sampler: Arc::new(AtomicUsize::new(0)),
+            partitions,
+            config,
+        }
+    }
+
+    async fn dispatch(&self, task: SampleTask) -> GraphResult<()> {
+        let idx = self
+            .next_sampler
+            .fetch_add(1, Ordering::Relaxed)
+            % self.samplers.len();
+        let mut sampler = self.samplers[idx].clone();
+        sampler.send(task).await.map_err(|_| {
+            let msg = format!("neighbor sampler#{} is gone", idx);
+            gen_graph_err!(GraphErrorCode::EngineError, msg, dispatch)
+        })
+    }
+
+    fn parse_hops(&self, req: &SampleRequestPb) -> GraphResult<Vec<HopSpec>> {
+        let with_replacement = req.get_strategy() == SampleStrategyPb::RANDOM_WITH_REPLACEMENT;
+        let mut sampled = 0_usize;
+        let mut sources = req.get_seeds().len();
+        let mut hops = Vec::with_capacity(req.get_hops().len());
+        for (index, hop) in req.get_hops().iter().enumerate() {
+            if hop.get_fanout() <= 0 {
+                let msg = format!("invalid fanout {} of hop#{}", hop.get_fanout(), index);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, parse_hops));
+            }
+            let fanout = hop.get_fanout() as usize;
+            sources = sources.saturating_mul(fanout);
+            sampled = sampled.saturating_add(sources);
+            if sampled > self.config.max_sampled {
+                let msg = format!(
+                    "up to {} neighbors sampled by hop#{}, more than the limit {}",
+                    sampled, index, self.config.max_sampled
+                );
+                return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, parse_hops));
+            }
+            let direction = match hop.get_direction() {
+                SampleDirectionPb::OUT => EdgeDirection::Out,
+                SampleDirectionPb::IN => EdgeDirection::In,
+            };
+            let label = if hop.get_edgeLabel() < 0 { None } else { Some(hop.get_edgeLabel()) };
+            hops.push(HopSpec { index, label, direction, fanout, with_replacement });
+        }
+        Ok(hops)
+    }
+
+    async fn sample_hops(&self, req: SampleRequestPb) -> GraphResult<SampleResponsePb> {
+        let hops = self.parse_hops(&req)?;
+        let si =
+            if req.get_snapshotId() > 0 { req.get_snapshotId() } else { self.partitions.get_readable_si() };
+        let seed = if req.get_randomSeed() == 0 { rand::random() } else { req.get_randomSeed() };
+        let chunk_size = self.config.chunk_size.max(1);
+        let mut resp = SampleResponsePb::new();
+        resp.set_snapshotId(si);
+        let mut sources = req.get_seeds().to_vec();
+        let mut sampled_ids = sources.clone();
+        for hop in hops {
+            let mut replies = Vec::with_capacity(sources.len() / chunk_size + 1);
+            for chunk in sources.chunks(chunk_size) {
+                let (tx, rx) = oneshot::channel();
+                let task = SampleTask::Expand { si, sources: chunk.to_vec(), hop, seed, reply: tx };
+                self.dispatch(task).await?;
+                replies.push(rx);
+            }
+            let mut offsets = Vec::with_capacity(sources.len() + 1);
+            let mut neighbors = Vec::new();
+            offsets.push(0);
+            for reply in replies {
+                for sampled in reply.await.map_err(canceled)?? {
+                    neighbors.extend(sampled);
+                    offsets.push(neighbors.len() as i32);
+                }
+            }
+            if req.get_fetchProperties() {
+                sampled_ids.extend_from_slice(&neighbors);
+            }
+            let mut hop_pb = SampledHopPb::new();
+            hop_pb.set_offsets(offsets);
+            hop_pb.set_neighbors(neighbors.clone());
+            resp.mut_hops().push(hop_pb);
+            sources = neighbors;
+        }
+        if req.get_fetchProperties() {
+            let mut seen = HashSet::with_capacity(sampled_ids.len());
+            sampled_ids.retain(|id| seen.insert(*id));
+            let mut replies = Vec::with_capacity(sampled_ids.len() / chunk_size + 1);
+            for chunk in sampled_ids.chunks(chunk_size) {
+                let (tx, rx) = oneshot::channel();
+                let prop_ids = req.get_propertyIds().to_vec();
+                let task = SampleTask::Fetch { si, ids: chunk.to_vec(), prop_ids, reply: tx };
+                self.dispatch(task).await?;
+                replies.push(rx);
+            }
+            for reply in replies {
+                resp.mut_vertices()
+                    .extend(reply.await.map_err(canceled)??);
+            }
+        }
+        resp.set_success(true);
+        Ok(resp)
+    }
+}
+
+impl NeighborSampling for NeighborSamplingService {
+    fn sample(&mut self, ctx: RpcContext, req: SampleRequestPb, sink: UnarySink<SampleResponsePb>) {
+        let service = self.clone();
+        ctx.spawn(async move {
+            let resp = match service.sample_hops(req).await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    debug!("sample neighbors failed: {:?}", e);
+                    let mut resp = SampleResponsePb::new();
+                    resp.set_errMsg(format!("{:?}", e));
+                    resp
+                }
+            };
+            if let Err(e) = sink.success(resp).await {
+                warn!("reply sampled neighbors failed: {:?}", e);
+            }
+        })
+    }
+}
+
+fn canceled(_: oneshot::Canceled) -> GraphError {
+    gen_graph_err!(GraphErrorCode::EngineError, "neighbor sampler dropped a task".to_owned(), sample)
+}
+
+/// The grpc server of the neighbor sampling service, which is shut down once it's stopped.
+pub struct NeighborSamplingServer {
+    server: Server,
+}
+
+impl NeighborSamplingServer {
+    pub fn start(
+        port: u16, config: NeighborSamplingConfig, partitions: Arc<dyn SamplingPartitions>,
+    ) -> GraphResult<Self> {
+        let service = NeighborSamplingService::new(config, partitions);
+        let env = Arc::new(Environment::new(1));
+        let mut server = ServerBuilder::new(env)
+            .register_service(create_neighbor_sampling(service))
+            .bind("0.0.0.0", port)
+            .build()
+            .map_err(|e| {
+                let msg = format!("start neighbor sampling service on port {} failed: {:?}", port, e);
+                gen_graph_err!(GraphErrorCode::InvalidOperation, msg, start)
+            })?;
+        server.start();
+        info!("neighbor sampling service is started on port {}", port);
+        Ok(NeighborSamplingServer { server })
+    }
+
+    pub fn stop(&mut self) {
+        if let Err(e) = block_on(self.server.shutdown()) {
+            warn!("shutdown neighbor sampling service failed: {:?}", e);
+        }
+    }
+}
+
+struct Sampler {
+    partitions: Arc<dyn SamplingPartitions>,
+}
+
+impl Sampler {
+    fn run(self, mut tasks: mpsc::Receiver<SampleTask>) {
+        while let Some(task) = block_on(tasks.next()) {
+            // the request may have failed in the meantime, then the result is dropped
+            match task {
+                SampleTask::Expand { si, sources, hop, seed, reply } => {
+                    let _ = reply.send(self.expand(si, &sources, &hop, seed));
+                }
+                SampleTask::Fetch { si, ids, prop_ids, reply } => {
+                    let _ = reply.send(self.fetch(si, &ids, &prop_ids));
+                }
+            }
+        }
+    }
+
+    fn expand(
+        &self, si: SnapshotId, sources: &[VertexId], hop: &HopSpec, seed: u64,
+    ) -> GraphResult<Vec<Vec<VertexId>>> {
+        let mut sampled = Vec::with_capacity(sources.len());
+        for src in sources {
+            let graph = match self.partitions.get_vertex_partition(*src) {
+                Some(graph) => graph,
+                None => {
+                    sampled.push(vec![]);
+                    continue;
+                }
+            };
+            // no property of the edges is read
+            let neighbors: Box<dyn Iterator<Item = GraphResult<VertexId>>> = match hop.direction {
+                EdgeDirection::In => Box::new(
+                    graph
+                        .get_in_edges(si, *src, hop.label, None, None)?
+                        .map(|e| e.map(|e| e.get_edge_id().src_id)),
+                ),
+                _ => Box::new(
+                    graph
+                        .get_out_edges(si, *src, hop.label, None, None)?
+                        .map(|e| e.map(|e| e.get_edge_id().dst_id)),
+                ),
+            };
+            let mut rng = StdRng::seed_from_u64(source_seed(seed, hop.index, *src));
+            sampled.push(sample_neighbors(neighbors, hop.fanout, hop.with_replacement, &mut rng)?);
+        }
+        Ok(sampled)
+    }
+
+    fn fetch(
+        &self, si: SnapshotId, ids: &[VertexId], prop_ids: &Vec<PropertyId>,
+    ) -> GraphResult<Vec<SampledVertexPb>> {
+        let mut vertices = Vec::with_capacity(ids.len());
+        for id in ids {
+            let graph = match self.partitions.get_vertex_partition(*id) {
+                Some(graph) => graph,
+                None => continue,
+            };
+            // an empty list of properties means all the properties
+            if let Some(v) = graph.get_vertex(si, *id, None, Some(prop_ids))? {
+                let mut vertex_pb = SampledVertexPb::new();
+                vertex_pb.set_id(*id);
+                vertex_pb.set_labelId(RocksVertex::get_label_id(&v));
+                for (prop_id, property) in v.get_properties() {
+                    if let Some(value) = property_to_value(&property) {
+                        let mut property_pb = StorePropertyPb::new();
+                        property_pb.set_property_id(prop_id as PropertyId);
+                        property_pb.set_property_value(value.to_proto()?);
+                        vertex_pb.mut_properties().push(property_pb);
+                    }
+                }
+                vertices.push(vertex_pb);
+            }
+        }
+        Ok(vertices)
+    }
+}
+
+/// The seed of the source in the hop, which is mixed by splitmix64 rather than a std hasher, as the
+/// same seed must sample the same neighbors by the stores of any build.
+fn source_seed(seed: u64, hop: usize, src: VertexId) -> u64 {
+    let seed = splitmix64(seed ^ hop as u64);
+    splitmix64(seed ^ src as u64)
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Sample `fanout` of the neighbors. Without replacement, the neighbors are read once by reservoir
+/// sampling in the memory of `fanout`; with replacement, all the neighbors are read to draw from.
+fn sample_neighbors<I: Iterator<Item = GraphResult<VertexId>>, R: Rng>(
+    neighbors: I, fanout: usize, with_replacement: bool, rng: &mut R,
+) -> GraphResult<Vec<VertexId>> {
+    if with_replacement {
+        let all = neighbors.collect::<GraphResult<Vec<_>>>()?;
+        if all.is_empty() {
+            return Ok(all);
+        }
+        return Ok((0..fanout)
+            .map(|_| all[rng.gen_range(0..all.len())])
+            .collect());
+    }
+    let mut reservoir = Vec::with_capacity(fanout);
+    for (i, neighbor) in neighbors.enumerate() {
+        let neighbor = neighbor?;
+        if i < fanout {
+            reservoir.push(neighbor);
+        } else {
+            let j = rng.gen_range(0..=i);
+            if j < fanout {
+                reservoir[j] = neighbor;
+            }
+        }
+    }
+    Ok(reservoir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(neighbors: Vec<VertexId>, fanout: usize, with_replacement: bool, seed: u64) -> Vec<VertexId> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        sample_neighbors(neighbors.into_iter().map(Ok), fanout, with_replacement, &mut rng).unwrap()
+    }
+
+    #[test]
+    fn test_sample_neighbors() {
+        let neighbors = (0..100).collect::<Vec<VertexId>>();
+        let sampled = sample(neighbors.clone(), 10, false, 7);
+        assert_eq!(sampled.len(), 10);
+        assert_eq!(sampled.iter().collect::<HashSet<_>>().len(), 10);
+        assert!(sampled.iter().all(|n| neighbors.contains(n)));
+        assert_eq!(sampled, sample(neighbors.clone(), 10, false, 7));
+        // all the neighbors if there are fewer than the fanout
+        assert_eq!(sample(vec![1, 2, 3], 10, false, 7), vec![1, 2, 3]);
+
+        let sampled = sample(vec![1, 2, 3], 10, true, 7);
+        assert_eq!(sampled.len(), 10);
+        assert!(sampled.iter().all(|n| *n >= 1 && *n <= 3));
+        assert!(sample(vec![], 10, true, 7).is_empty());
+    }
+
+    #[test]
+    fn test_source_seed() {
+        assert_eq!(source_seed(1, 0, 42), source_seed(1, 0, 42));
+        assert_ne!(source_seed(1, 0, 42), source_seed(1, 1, 42));
+        assert_ne!(source_seed(1, 0, 42), source_seed(2, 0, 42));
+        // stable across the builds and the platforms
+        assert_eq!(splitmix64(0), 16294208416658607535);
+        assert_eq!(source_seed(1, 0, 42), 9129838320742759465);
+    }
+}
@@ -0,0 +1,572 @@
+//
+//! Copyright 2020 Alibaba Group Holding Limited.
+//!
+//! Licensed under the Apache License, Version 2.0 (the "License");
+//! you may not use this file except in compliance with the License.
+//! You may obtain a copy of the License at
+//!
+//! http://www.apache.org/licenses/LICENSE-2.0
+//!
+//! Unless required by applicable law or agreed to in writing, software
+//! distributed under the License is distributed on an "AS IS" BASIS,
+//! WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//! See the License for the specific language governing permissions and
+//! limitations under the License.
+
+//! Bidirectional streaming write service, see `stream_write_service.proto`.
+//!
+//! Batches are dispatched to a fixed number of lanes by the hash of their ordering key. Every lane
+//! is a single thread applying its batches one by one, so batches of the same key are never
+//! reordered. Lanes are fed through bounded channels: while the memtables are above
+//! `max_memtable_bytes` or rocksdb has stopped writes, a lane holds its next batch, its channel
+//! fills up, the service stops reading the request stream and grpc flow control pushes back to
+//! the writer.
+//!
+//! The records are written into the partitions of the process, i.e. a vertex into the partition of
+//! its id and an edge into those of its source and destination in the out and in direction
+//! respectively, at the latest snapshot id written into the partitions, see
+//! `GraphStore::get_write_si`. A batch racing with a regular write of the next snapshot may fail
+//! then, and is resent by the writer as other failed batches.
+//!
+//! A batch is validated against the schema as a whole before any of its records is written, but a
+//! store error in the middle of a batch leaves the written records in place. Mutation records are
+//! idempotent, so the writer is expected to resend a failed batch. Records carrying a `dedup_id`
+//! are applied at most once within `dedup_retention`: their ids are persisted after the records
+//! are written, and records whose ids are found are skipped and counted as deduplicated. A batch
+//! whose records are all deduplicated is acked with the snapshot id they were written at.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::{SinkExt, StreamExt, TryStreamExt};
+use grpcio::{DuplexSink, Environment, RequestStream, RpcContext, Server, ServerBuilder, WriteFlags};
+
+use crate::db::api::*;
+use crate::db::graph::store::GraphStore;
+use crate::db::import::mutation::{dedup_id, Mutation};
+use crate::db::proto::stream_write_service::{StreamWriteAckPb, StreamWriteBatchPb};
+use crate::db::proto::stream_write_service_grpc::{create_stream_write, StreamWrite};
+use crate::db::util::time::current_time_millis;
+
+/// The partitions of the graph in this process, which the records are written into.
+pub trait WritePartitions: Send + Sync {
+    /// The partition of the vertex, `None` if it's not written in this process.
+    fn get_write_partition(&self, vertex_id: VertexId) -> Option<Arc<GraphStore>>;
+
+    /// All the partitions written in this process.
+    fn get_partitions(&self) -> Vec<Arc<GraphStore>>;
+
+    /// The partition of the vertex, or an error if it's not written in this process.
+    fn get_partition(&self, vertex_id: VertexId) -> GraphResult<Arc<GraphStore>> {
+        self.get_write_partition(vertex_id)
+            .ok_or_else(|| {
+                let msg = format!("partition of vertex {} is not in this process", vertex_id);
+                gen_graph_err!(GraphErrorCode::InvalidOperation, msg, get_partition)
+            })
+    }
+
+    /// The latest snapshot id written into any of the partitions.
+    fn get_write_si(&self) -> SnapshotId {
+        self.get_partitions()
+            .iter()
+            .map(|graph| graph.get_write_si())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The schema of the graph, which is the same in all the partitions.
+    fn get_graph_def(&self) -> GraphResult<GraphDef> {
+        match self.get_partitions().first() {
+            Some(graph) => graph.get_graph_def(),
+            None => {
+                let msg = "no partition is in this process".to_owned();
+                Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, get_graph_def))
+            }
+        }
+    }
+
+    /// Apply a vertex mutation to the partition of the vertex, and an edge mutation to those of its
+    /// source and destination in the out and in direction respectively.
+    fn apply_mutation(&self, mutation: &Mutation, si: SnapshotId) -> GraphResult<()> {
+        match mutation {
+            Mutation::Vertex { id, .. } => mutation.apply(self.get_partition(*id)?.as_ref(), si),
+            Mutation::Edge { id, .. } => {
+                mutation.apply_direction(self.get_partition(id.src_id)?.as_ref(), si, true)?;
+                mutation.apply_direction(self.get_partition(id.dst_id)?.as_ref(), si, false)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StreamWriteConfig {
+    pub lanes: usize,
+    /// number of batches buffered by every lane before the request stream is paused
+    pub lane_capacity: usize,
+    pub max_memtable_bytes: u64,
+    /// how long a lane sleeps before checking the memtables again
+    pub throttle_interval: Duration,
+    /// how long the dedup ids of written records are remembered
+    pub dedup_retention: Duration,
+}
+
+impl Default for StreamWriteConfig {
+    fn default() -> Self {
+        StreamWriteConfig {
+            lanes: 4,
+            lane_capacity: 16,
+            max_memtable_bytes: 512 << 20,
+            throttle_interval: Duration::from_millis(50),
+            dedup_retention: Duration::from_secs(24 * 3600),
+        }
+    }
+}
+
+struct LaneTask {
+    batch: StreamWriteBatchPb,
+    acks: mpsc::UnboundedSender<StreamWriteAckPb>,
+}
+
+#[derive(Clone)]
+pub struct StreamWriteService {
+    lanes: Arc<Vec<mpsc::Sender<LaneTask>>>,
+}
+
+impl StreamWriteService {
+    pub fn new(config: StreamWriteConfig, partitions: Arc<dyn WritePartitions>) -> Self {
+        let lane_num = config.lanes.max(1);
+        let last_purge_ms = Arc::new(AtomicU64::new(0));
+        let mut lanes = Vec::with_capacity(lane_num);
+        for i in 0..lane_num {
+            let (tx, rx) = mpsc::channel(config.lane_capacity);
+            let lane = Lane {
+                partitions: partitions.clone(),
+                last_purge_ms: last_purge_ms.clone(),
+                config: config.clone(),
+            };
+            thread::Builder::new()
+                .name(format!("stream-write-{}", i))
+                .spawn(move || lane.run(rx))
+                .expect("spawn stream write lane failed");
+            lanes.push(tx);
+        }
+        StreamWriteService { lanes: Arc::new(lanes) }
+    }
+}
+
+impl StreamWrite for StreamWriteService {
+    fn stream_write(
+        &mut self, ctx: RpcContext, mut stream: RequestStream<StreamWriteBatchPb>,
+        mut sink: DuplexSink<StreamWriteAckPb>,
+    ) {
+        // every stream owns its senders, so one stream can't use up the slots of the others
+        let mut lanes: Vec<mpsc::Sender<LaneTask>> = self.lanes.iter().cloned().collect();
+        let (ack_tx, mut ack_rx) = mpsc::unbounded();
+        let read = async move {
+            while let Some(batch) = stream.try_next().await? {
+                let idx = lane_of(batch.get_orderingKey(), lanes.len());
+                let task = LaneTask { batch, acks: ack_tx.clone() };
+                if lanes[idx].send(task).await.is_err() {
+                    error!("stream write lane#{} is gone", idx);
+                    break;
+                }
+            }
+            // `ack_tx` is dropped here, the ack stream ends once all pending batches are acked
+            Ok::<_, grpcio::Error>(())
+        };
+        let write = async move {
+            while let Some(ack) = ack_rx.next().await {
+                sink.send((ack, WriteFlags::default())).await?;
+            }
+            sink.close().await
+        };
+        ctx.spawn(async move {
+            let (read_res, write_res) = futures::join!(read, write);
+            if let Err(e) = read_res.and(write_res) {
+                warn!("stream write failed: {:?}", e);
+            }
+        })
+    }
+}
+
+/// The grpc server of the streaming write service, which is shut down once it's stopped.
+pub struct StreamWriteServer {
+    server: Server,
+}
+
+impl StreamWriteServer {
+    pub fn start(
+        port: u16, config: StreamWriteConfig, partitions: Arc<dyn WritePartitions>,
+    ) -> GraphResult<Self> {
+        let service = StreamWriteService::new(config, partitions);
+        let env = Arc::new(Environment::new(1));
+        let mut server = ServerBuilder::new(env)
+            .register_service(create_stream_write(service))
+            .bind("0.0.0.0", port)
+            .build()
+            .map_err(|e| {
+                let msg = format!("start stream write service on port {} failed: {:?}", port, e);
+                gen_graph_err!(GraphErrorCode::InvalidOperation, msg, start)
+            })?;
+        server.start();
+        info!("stream write service is started on port {}", port);
+        Ok(StreamWriteServer { server })
+    }
+
+    /// The port the server is bound to, which is picked by the system if it's started on port 0.
+    pub fn port(&self) -> u16 {
+        self.server
+            .bind_addrs()
+            .next()
+            .map_or(0, |(_, port)| port)
+    }
+
+    pub fn stop(&mut self) {
+        if let Err(e) = block_on(self.server.shutdown()) {
+            warn!("shutdown stream write service failed: {:?}", e);
+        }
+    }
+}
+
+struct Lane {
+    partitions: Arc<dyn WritePartitions>,
+    last_purge_ms: Arc<AtomicU64>,
+    config: StreamWriteConfig,
+}
+
+impl Lane {
+    fn run(self, mut tasks: mpsc::Receiver<LaneTask>) {
+        while let Some(task) = block_on(tasks.next()) {
+            self.wait_for_memtables();
+            let ack = self.apply(&task.batch);
+            // the stream may have been closed by the writer, then the ack is dropped
+            let _ = task.acks.unbounded_send(ack);
+            self.maybe_purge_dedup_ids();
+        }
+    }
+
+    fn wait_for_memtables(&self) {
+        loop {
+            let stalled = self
+                .partitions
+                .get_partitions()
+                .iter()
+                .try_fold(false, |stalled, graph| {
+                    let stopped = graph.is_write_stopped()?;
+                    let size = graph.get_memtable_size()?;
+                    Ok::<_, GraphError>(stalled || stopped || size > self.config.max_memtable_bytes)
+                });
+            match stalled {
+                Ok(false) => return,
+                Ok(true) => thread::sleep(self.config.throttle_interval),
+                Err(e) => {
+                    warn!("check memtables failed: {:?}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// The partition of the vertex of the mutation, or of the source of the edge, which keeps the
+    /// dedup id of the mutation.
+    fn get_owner(&self, mutation: &Mutation) -> GraphResult<Arc<GraphStore>> {
+        match mutation {
+            Mutation::Vertex { id, .. } => self.partitions.get_partition(*id),
+            Mutation::Edge { id, .. } => self.partitions.get_partition(id.src_id),
+        }
+    }
+
+    fn apply(&self, batch: &StreamWriteBatchPb) -> StreamWriteAckPb {
+        let si = self.partitions.get_write_si();
+        let res = self
+            .partitions
+            .get_graph_def()
+            .and_then(|graph_def| {
+                let mut mutations = Vec::with_capacity(batch.get_records().len());
+                // the dedup ids of the mutations by the partitions keeping them
+                let mut dedup_ids: Vec<(Arc<GraphStore>, Vec<String>)> = Vec::new();
+                let mut seen = HashSet::new();
+                // the latest snapshot id the deduplicated records were written at
+                let mut recorded_si = None;
+                for record in batch.get_records() {
+                    let json = serde_json::from_str(record).map_err(|e| {
+                        let msg = format!("invalid json record: {}", e);
+                        gen_graph_err!(GraphErrorCode::InvalidData, msg, apply)
+                    })?;
+                    let mutation = Mutation::from_json(&json, &graph_def)?;
+                    if let Some(id) = dedup_id(&json) {
+                        let owner = self.get_owner(&mutation)?;
+                        if !seen.insert(id.to_owned()) {
+                            continue;
+                        }
+                        if let Some(entry) = owner.get_dedup_entry(id)? {
+                            recorded_si = recorded_si.max(Some(entry.si));
+                            continue;
+                        }
+                        match dedup_ids
+                            .iter_mut()
+                            .find(|(graph, _)| Arc::ptr_eq(graph, &owner))
+                        {
+                            Some((_, ids)) => ids.push(id.to_owned()),
+                            None => dedup_ids.push((owner, vec![id.to_owned()])),
+                        }
+                    }
+                    mutations.push(mutation);
+                }
+                for mutation in mutations.iter() {
+                    self.partitions.apply_mutation(mutation, si)?;
+                }
+                for (graph, ids) in dedup_ids.iter() {
+                    graph.record_dedup_ids(si, ids)?;
+                }
+                let ack_si = if mutations.is_empty() { recorded_si.unwrap_or(si) } else { si };
+                Ok((mutations.len(), ack_si))
+            });
+        let mut ack = StreamWriteAckPb::new();
+        ack.set_batchId(batch.get_batchId());
+        ack.set_snapshotId(si);
+        match res {
+            Ok((applied, ack_si)) => {
+                ack.set_snapshotId(ack_si);
+                ack.set_success(true);
+                ack.set_applied(applied as i32);
+                ack.set_deduplicated((batch.get_records().len() - applied) as i32);
+            }
+            Err(e) => {
+                debug!("stream write batch#{} failed: {:?}", batch.get_batchId(), e);
+                ack.set_errMsg(format!("{:?}", e));
+            }
+        }
+        ack
+    }
+
+    /// Purge expired dedup entries, at most once every tenth of the retention among all lanes.
+    fn maybe_purge_dedup_ids(&self) {
+        let now = current_time_millis();
+        let last = self.last_purge_ms.load(Ordering::Relaxed);
+        let interval = self.config.dedup_retention.as_millis() as u64 / 10;
+        if now < last + interval
+            || self
+                .last_purge_ms
+                .compare_exchange(last, now, Ordering::SeqCst, Ordering::Relaxed)
+                .is_err()
+        {
+            return;
+        }
+        for graph in self.partitions.get_partitions() {
+            match graph.purge_dedup_ids(self.config.dedup_retention) {
+                Ok(purged) => debug!("purged {} expired dedup ids", purged),
+                Err(e) => warn!("purge dedup ids failed: {:?}", e),
+            }
+        }
+    }
+}
+
+fn lane_of(ordering_key: &str, lanes: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    ordering_key.hash(&mut hasher);
+    (hasher.finish() % lanes as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use grpcio::ChannelBuilder;
+
+    use super::*;
+    use crate::db::api::multi_version_graph::MultiVersionGraph;
+    use crate::db::api::types::{Property, PropertyReader, PropertyValue};
+    use crate::db::proto::stream_write_service_grpc::StreamWriteClient;
+    use crate::db::util::fs;
+
+    struct TestPartitions(Arc<GraphStore>);
+
+    impl WritePartitions for TestPartitions {
+        fn get_write_partition(&self, _vertex_id: VertexId) -> Option<Arc<GraphStore>> {
+            Some(self.0.clone())
+        }
+
+        fn get_partitions(&self) -> Vec<Arc<GraphStore>> {
+            vec![self.0.clone()]
+        }
+    }
+
+    fn create_graph(path: &str) -> Arc<GraphStore> {
+        fs::rmr(path).unwrap();
+        let mut builder = GraphConfigBuilder::new();
+        builder.set_storage_engine("rocksdb");
+        builder.add_storage_option("store.data.path", path);
+        let graph = GraphStore::open(&builder.build()).unwrap();
+        let mut type_def = TypeDefBuilder::new();
+        type_def.set_label_id(1).set_label("person");
+        type_def.add_property(1, 1, "name".to_owned(), ValueType::String, None, false, String::new());
+        graph
+            .create_vertex_type(1, 1, 1, &type_def.build(), 1)
+            .unwrap();
+        Arc::new(graph)
+    }
+
+    fn person(id: i64, name: &str) -> String {
+        json!({"op": "insert", "kind": "vertex", "label": "person", "id": id, "properties": {"name": name}})
+            .to_string()
+    }
+
+    fn dedup_person(id: i64, name: &str, dedup_id: &str) -> String {
+        json!({
+            "op": "insert", "kind": "vertex", "label": "person", "id": id,
+            "properties": {"name": name}, "dedup_id": dedup_id,
+        })
+        .to_string()
+    }
+
+    fn create_lane(graph: Arc<GraphStore>, dedup_retention: Duration) -> Lane {
+        let config = StreamWriteConfig { dedup_retention, ..StreamWriteConfig::default() };
+        Lane {
+            partitions: Arc::new(TestPartitions(graph)),
+            last_purge_ms: Arc::new(AtomicU64::new(0)),
+            config,
+        }
+    }
+
+    fn batch_of(batch_id: i64, records: Vec<String>) -> StreamWriteBatchPb {
+        let mut batch = StreamWriteBatchPb::new();
+        batch.set_batchId(batch_id);
+        batch.set_records(records.into());
+        batch
+    }
+
+    fn get_name(graph: &GraphStore, si: SnapshotId, id: VertexId) -> Option<String> {
+        let vertex = graph
+            .get_vertex(si, id, Some(1), Some(&vec![]))
+            .unwrap()?;
+        match vertex.get_property(1)?.get_property_value() {
+            PropertyValue::String(name) => Some(name.clone()),
+            v => panic!("unexpected name {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_stream_write_server() {
+        let path = "store_test/test_stream_write_server";
+        let graph = create_graph(path);
+        let partitions = Arc::new(TestPartitions(graph.clone()));
+        let mut server = StreamWriteServer::start(0, StreamWriteConfig::default(), partitions).unwrap();
+
+        let env = Arc::new(Environment::new(1));
+        let channel = ChannelBuilder::new(env).connect(&format!("127.0.0.1:{}", server.port()));
+        let client = StreamWriteClient::new(channel);
+        let (mut sink, mut receiver) = client.stream_write().unwrap();
+        let mut batch = StreamWriteBatchPb::new();
+        batch.set_batchId(7);
+        batch.set_orderingKey("person#1".to_owned());
+        batch.set_records(vec![person(1, "marko"), person(2, "vadas")].into());
+        let acks = block_on(async {
+            sink.send((batch, WriteFlags::default()))
+                .await
+                .unwrap();
+            sink.close().await.unwrap();
+            receiver.try_collect::<Vec<_>>().await.unwrap()
+        });
+        assert_eq!(acks.len(), 1);
+        let ack = &acks[0];
+        assert!(ack.get_success(), "{}", ack.get_errMsg());
+        assert_eq!(ack.get_batchId(), 7);
+        assert_eq!(ack.get_applied(), 2);
+        assert_eq!(ack.get_deduplicated(), 0);
+
+        let si = ack.get_snapshotId();
+        assert_eq!(get_name(&graph, si, 1), Some("marko".to_owned()));
+        assert_eq!(get_name(&graph, si, 2), Some("vadas".to_owned()));
+        assert_eq!(get_name(&graph, si, 3), None);
+
+        server.stop();
+        drop(graph);
+        fs::rmr(path).unwrap();
+    }
+
+    #[test]
+    fn test_dedup_records() {
+        let path = "store_test/test_stream_write_dedup_records";
+        let graph = create_graph(path);
+        let lane = create_lane(graph.clone(), Duration::from_secs(3600));
+
+        let first = lane.apply(&batch_of(1, vec![dedup_person(1, "marko", "r1")]));
+        assert!(first.get_success(), "{}", first.get_errMsg());
+        assert_eq!(first.get_applied(), 1);
+        assert_eq!(first.get_deduplicated(), 0);
+        assert_eq!(graph.get_dedup_entry("r1").unwrap().unwrap().si, first.get_snapshotId());
+
+        // a later write moves the store to the next snapshot
+        graph
+            .insert_overwrite_vertex(first.get_snapshotId() + 1, 2, 1, &HashMap::<PropertyId, Value>::new())
+            .unwrap();
+        // the resent record is not applied again, and the ack carries the snapshot it was written at
+        let second = lane.apply(&batch_of(2, vec![dedup_person(1, "vadas", "r1")]));
+        assert!(second.get_success(), "{}", second.get_errMsg());
+        assert_eq!(second.get_applied(), 0);
+        assert_eq!(second.get_deduplicated(), 1);
+        assert_eq!(second.get_snapshotId(), first.get_snapshotId());
+        assert_eq!(get_name(&graph, MAX_SI, 1), Some("marko".to_owned()));
+
+        // the same id twice in a batch is applied once as well
+        let third = lane.apply(&batch_of(
+            3,
+            vec![dedup_person(3, "josh", "r3"), dedup_person(3, "peter", "r3"), person(4, "lop")],
+        ));
+        assert!(third.get_success(), "{}", third.get_errMsg());
+        assert_eq!(third.get_applied(), 2);
+        assert_eq!(third.get_deduplicated(), 1);
+        assert_eq!(get_name(&graph, MAX_SI, 3), Some("josh".to_owned()));
+
+        drop(lane);
+        drop(graph);
+        fs::rmr(path).unwrap();
+    }
+
+    #[test]
+    fn test_purge_dedup_ids() {
+        let path = "store_test/test_stream_write_purge_dedup_ids";
+        let graph = create_graph(path);
+        let lane = create_lane(graph.clone(), Duration::from_millis(200));
+
+        let ack = lane.apply(&batch_of(1, vec![dedup_person(1, "marko", "r1")]));
+        assert!(ack.get_success(), "{}", ack.get_errMsg());
+        lane.maybe_purge_dedup_ids();
+        assert!(graph.get_dedup_entry("r1").unwrap().is_some());
+
+        thread::sleep(Duration::from_millis(400));
+        let ack = lane.apply(&batch_of(2, vec![dedup_person(2, "vadas", "r2")]));
+        assert!(ack.get_success(), "{}", ack.get_errMsg());
+        lane.maybe_purge_dedup_ids();
+        // only the ids older than the retention are removed
+        assert!(graph.get_dedup_entry("r1").unwrap().is_none());
+        assert!(graph.get_dedup_entry("r2").unwrap().is_some());
+
+        // the record is applied again once its id is purged
+        let ack = lane.apply(&batch_of(3, vec![dedup_person(1, "josh", "r1")]));
+        assert_eq!(ack.get_applied(), 1);
+        assert_eq!(get_name(&graph, MAX_SI, 1), Some("josh".to_owned()));
+
+        drop(lane);
+        drop(graph);
+        fs::rmr(path).unwrap();
+    }
+
+    #[test]
+    fn test_lane_of() {
+        for key in ["", "person#1", "person#2", "software#3"].iter() {
+            let lane = lane_of(key, 4);
+            assert!(lane < 4);
+            assert_eq!(lane, lane_of(key, 4));
+            assert_eq!(lane_of(key, 1), 0);
+        }
+    }
+}
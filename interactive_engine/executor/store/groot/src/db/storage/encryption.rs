@@ -0,0 +1,280 @@
+//! Encryption at rest of the values in storage, with AES-256-GCM.
+//!
+//! An encrypted value is `MAGIC | key id (u32, big endian) | nonce (12 bytes) | ciphertext | tag`, where
+//! the storage key of the value is authenticated as associated data, so a value can't be moved to
+//! another key. Keys are not encrypted as the order of keys is required by scans.
+//!
+//! Values without the magic header are treated as plaintext, i.e., the values written before the
+//! encryption is enabled, or ingested from external files; they are encrypted by the rewrite of
+//! key rotation, see `RocksDB::rewrite_encrypted`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+use crate::db::api::*;
+
+const MAGIC: &[u8] = b"\xE5GV";
+const KEY_ID_LEN: usize = 4;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + KEY_ID_LEN + NONCE_LEN;
+
+pub const DEFAULT_KEY_ENV: &str = "GROOT_ENCRYPTION_KEYS";
+
+/// Provide the data keys, e.g. from a KMS. A key is identified by an id, which is stored with
+/// the encrypted values; keys of previous ids should be kept to decrypt the values not yet rewritten.
+pub trait KeyProvider: Send + Sync {
+    /// The id of the key to encrypt new values with.
+    fn current_key_id(&self) -> GraphResult<u32>;
+
+    /// The 256-bit key of `key_id`.
+    fn get_key(&self, key_id: u32) -> GraphResult<[u8; 32]>;
+}
+
+/// Read keys from an environment variable in form of `id:hex[,id:hex]*`, and the key of the largest
+/// id is the current one.
+pub struct EnvKeyProvider {
+    keys: HashMap<u32, [u8; 32]>,
+}
+
+impl EnvKeyProvider {
+    pub fn from_env(name: &str) -> GraphResult<Self> {
+        let value = std::env::var(name).map_err(|e| {
+            let msg = format!("read encryption keys from env {} failed: {}", name, e);
+            gen_graph_err!(GraphErrorCode::InvalidOperation, msg, from_env, name)
+        })?;
+        Self::parse(&value)
+    }
+
+    pub fn parse(value: &str) -> GraphResult<Self> {
+        let mut keys = HashMap::new();
+        for entry in value.split(',').map(str::trim) {
+            let key = entry
+                .split_once(':')
+                .and_then(|(id, hex)| Some((id.trim().parse::<u32>().ok()?, decode_hex(hex.trim())?)));
+            match key {
+                Some((id, key)) => {
+                    keys.insert(id, key);
+                }
+                None => {
+                    let msg = "encryption key should be `id:hex` with 64 hex digits".to_string();
+                    return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, parse));
+                }
+            }
+        }
+        Ok(EnvKeyProvider { keys })
+    }
+}
+
+impl KeyProvider for EnvKeyProvider {
+    fn current_key_id(&self) -> GraphResult<u32> {
+        self.keys.keys().max().copied().ok_or_else(|| {
+            let msg = "no encryption key found".to_string();
+            gen_graph_err!(GraphErrorCode::InvalidOperation, msg, current_key_id)
+        })
+    }
+
+    fn get_key(&self, key_id: u32) -> GraphResult<[u8; 32]> {
+        self.keys.get(&key_id).copied().ok_or_else(|| {
+            let msg = format!("encryption key {} not found", key_id);
+            gen_graph_err!(GraphErrorCode::InvalidOperation, msg, get_key, key_id)
+        })
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut key = [0u8; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(key)
+}
+
+static KEY_PROVIDERS: RwLock<Vec<(String, Arc<dyn KeyProvider>)>> = RwLock::new(Vec::new());
+
+/// Register a key provider, e.g. a KMS client, to be used by the stores configured with
+/// `store.encryption.key.provider` of `name`; it should be registered before the stores are opened.
+pub fn register_key_provider(name: &str, provider: Arc<dyn KeyProvider>) {
+    let mut providers = KEY_PROVIDERS.write().unwrap();
+    providers.retain(|(n, _)| n != name);
+    providers.push((name.to_owned(), provider));
+}
+
+fn get_key_provider(name: &str) -> Option<Arc<dyn KeyProvider>> {
+    KEY_PROVIDERS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, provider)| provider.clone())
+}
+
+pub struct ValueCipher {
+    provider: Arc<dyn KeyProvider>,
+    current: AtomicU32,
+    ciphers: RwLock<HashMap<u32, Arc<Aes256Gcm>>>,
+}
+
+impl ValueCipher {
+    pub fn new(provider: Arc<dyn KeyProvider>) -> GraphResult<Self> {
+        let current = provider.current_key_id()?;
+        let cipher = ValueCipher {
+            provider,
+            current: AtomicU32::new(current),
+            ciphers: RwLock::new(HashMap::new()),
+        };
+        // fail fast if the current key is not available;
+        cipher.get_cipher(current)?;
+        Ok(cipher)
+    }
+
+    /// Create the cipher by the storage options, or `None` if `store.encryption.enabled` is not true.
+    pub fn from_options(options: &HashMap<String, String>) -> GraphResult<Option<Self>> {
+        let enabled = options
+            .get("store.encryption.enabled")
+            .map(|s| s.parse::<bool>().unwrap())
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+        let provider = match options
+            .get("store.encryption.key.provider")
+            .map(|s| s.as_str())
+            .unwrap_or("env")
+        {
+            "env" => {
+                let name = options
+                    .get("store.encryption.key.env")
+                    .map(|s| s.as_str())
+                    .unwrap_or(DEFAULT_KEY_ENV);
+                Arc::new(EnvKeyProvider::from_env(name)?) as Arc<dyn KeyProvider>
+            }
+            name => get_key_provider(name).ok_or_else(|| {
+                let msg = format!("encryption key provider {} is not registered", name);
+                gen_graph_err!(GraphErrorCode::InvalidOperation, msg, from_options, name)
+            })?,
+        };
+        Ok(Some(Self::new(provider)?))
+    }
+
+    pub fn current_key_id(&self) -> u32 {
+        self.current.load(Ordering::Acquire)
+    }
+
+    /// Fetch the current key id from the provider again, which may be rotated.
+    pub fn refresh(&self) -> GraphResult<u32> {
+        let current = self.provider.current_key_id()?;
+        self.get_cipher(current)?;
+        self.current.store(current, Ordering::Release);
+        Ok(current)
+    }
+
+    /// The id of the key which the value is encrypted with, or `None` if it's in plaintext.
+    pub fn key_id_of(value: &[u8]) -> Option<u32> {
+        if value.len() >= HEADER_LEN && value.starts_with(MAGIC) {
+            let mut id = [0u8; KEY_ID_LEN];
+            id.copy_from_slice(&value[MAGIC.len()..MAGIC.len() + KEY_ID_LEN]);
+            Some(u32::from_be_bytes(id))
+        } else {
+            None
+        }
+    }
+
+    fn get_cipher(&self, key_id: u32) -> GraphResult<Arc<Aes256Gcm>> {
+        if let Some(cipher) = self.ciphers.read().unwrap().get(&key_id) {
+            return Ok(cipher.clone());
+        }
+        let key = self.provider.get_key(key_id)?;
+        let cipher = Arc::new(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)));
+        self.ciphers
+            .write()
+            .unwrap()
+            .insert(key_id, cipher.clone());
+        Ok(cipher)
+    }
+
+    pub fn encrypt(&self, key: &[u8], value: &[u8]) -> GraphResult<Vec<u8>> {
+        let key_id = self.current_key_id();
+        let cipher = self.get_cipher(key_id)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: value, aad: key })
+            .map_err(|e| {
+                let msg = format!("encrypt value with key {} failed: {}", key_id, e);
+                gen_graph_err!(GraphErrorCode::ExternalStorageError, msg, encrypt)
+            })?;
+        let mut ret = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        ret.extend_from_slice(MAGIC);
+        ret.extend_from_slice(&key_id.to_be_bytes());
+        ret.extend_from_slice(&nonce);
+        ret.extend_from_slice(&ciphertext);
+        Ok(ret)
+    }
+
+    /// Decrypt the value of `key` into `buf`, the plaintext value is copied as is.
+    pub fn decrypt_into(&self, key: &[u8], value: &[u8], buf: &mut Vec<u8>) -> GraphResult<()> {
+        buf.clear();
+        match Self::key_id_of(value) {
+            Some(key_id) => {
+                let cipher = self.get_cipher(key_id)?;
+                let nonce = Nonce::from_slice(&value[MAGIC.len() + KEY_ID_LEN..HEADER_LEN]);
+                let plaintext = cipher
+                    .decrypt(nonce, Payload { msg: &value[HEADER_LEN..], aad: key })
+                    .map_err(|e| {
+                        let msg = format!("decrypt value with key {} failed: {}", key_id, e);
+                        gen_graph_err!(GraphErrorCode::InvalidData, msg, decrypt_into)
+                    })?;
+                buf.extend_from_slice(&plaintext);
+            }
+            None => buf.extend_from_slice(value),
+        }
+        Ok(())
+    }
+
+    pub fn decrypt(&self, key: &[u8], value: &[u8]) -> GraphResult<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.decrypt_into(key, value, &mut buf)?;
+        Ok(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY1: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    const KEY2: &str = "ffeeddccbbaa99887766554433221100ffeeddccbbaa99887766554433221100";
+
+    #[test]
+    fn test_value_cipher() {
+        let provider = EnvKeyProvider::parse(&format!("1:{}", KEY1)).unwrap();
+        let cipher = ValueCipher::new(Arc::new(provider)).unwrap();
+        let value = cipher.encrypt(b"k1", b"hello").unwrap();
+        assert_eq!(ValueCipher::key_id_of(&value), Some(1));
+        assert_eq!(cipher.decrypt(b"k1", &value).unwrap(), b"hello");
+        // the value is bound to its key;
+        assert!(cipher.decrypt(b"k2", &value).is_err());
+        let mut tampered = value.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(cipher.decrypt(b"k1", &tampered).is_err());
+        // plaintext is passed through;
+        assert_eq!(cipher.decrypt(b"k1", b"plain").unwrap(), b"plain");
+
+        // rotate to key 2, the values of key 1 are still readable;
+        let provider = EnvKeyProvider::parse(&format!("1:{}, 2:{}", KEY1, KEY2)).unwrap();
+        let rotated = ValueCipher::new(Arc::new(provider)).unwrap();
+        assert_eq!(rotated.current_key_id(), 2);
+        assert_eq!(rotated.decrypt(b"k1", &value).unwrap(), b"hello");
+        let value = rotated.encrypt(b"k1", b"world").unwrap();
+        assert_eq!(ValueCipher::key_id_of(&value), Some(2));
+        assert!(cipher.decrypt(b"k1", &value).is_err());
+
+        assert!(EnvKeyProvider::parse("1:0011").is_err());
+    }
+}
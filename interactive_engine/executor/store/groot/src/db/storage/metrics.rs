@@ -0,0 +1,183 @@
+//! Metrics of the storage in Prometheus format, i.e. reads and writes of the store, and the
+//! properties and statistics of rocksdb, which are collected when scraped.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{register_int_counter_vec, IntCounter, IntCounterVec, IntGaugeVec, Opts};
+
+use crate::db::storage::rocksdb::RocksDB;
+
+/// Properties of rocksdb exported as gauges, in (property, metric, help);
+const PROPERTIES: &[(&str, &str, &str)] = &[
+    ("rocksdb.num-running-compactions", "groot_rocksdb_running_compactions", "Compactions running."),
+    ("rocksdb.compaction-pending", "groot_rocksdb_compaction_pending", "1 if any compaction is pending."),
+    (
+        "rocksdb.estimate-pending-compaction-bytes",
+        "groot_rocksdb_pending_compaction_bytes",
+        "Estimated bytes to be rewritten by compactions.",
+    ),
+    ("rocksdb.num-running-flushes", "groot_rocksdb_running_flushes", "Flushes running."),
+    ("rocksdb.cur-size-all-mem-tables", "groot_rocksdb_memtable_bytes", "Size of all memtables."),
+    ("rocksdb.total-sst-files-size", "groot_rocksdb_sst_files_bytes", "Size of all sst files."),
+    ("rocksdb.block-cache-usage", "groot_rocksdb_block_cache_usage_bytes", "Memory used by block cache."),
+    (
+        "rocksdb.block-cache-capacity",
+        "groot_rocksdb_block_cache_capacity_bytes",
+        "Capacity of block cache.",
+    ),
+    (
+        "rocksdb.block-cache-pinned-usage",
+        "groot_rocksdb_block_cache_pinned_bytes",
+        "Memory pinned in block cache.",
+    ),
+    ("rocksdb.estimate-num-keys", "groot_rocksdb_estimate_keys", "Estimated number of keys."),
+];
+
+/// Tickers of rocksdb statistics exported as counters, available if `store.rocksdb.statistics.enabled`;
+const TICKERS: &[(&str, &str, &str)] = &[
+    ("rocksdb.block.cache.hit", "groot_rocksdb_block_cache_hit_total", "Block cache hits."),
+    ("rocksdb.block.cache.miss", "groot_rocksdb_block_cache_miss_total", "Block cache misses."),
+    ("rocksdb.compact.read.bytes", "groot_rocksdb_compact_read_bytes_total", "Bytes read by compactions."),
+    (
+        "rocksdb.compact.write.bytes",
+        "groot_rocksdb_compact_write_bytes_total",
+        "Bytes written by compactions.",
+    ),
+    ("rocksdb.stall.micros", "groot_rocksdb_stall_micros_total", "Time writes are stalled."),
+];
+
+lazy_static! {
+    static ref STORE_READS: IntCounterVec =
+        register_int_counter_vec!("groot_store_reads_total", "Reads of the store.", &["op"]).unwrap();
+    static ref STORE_WRITES: IntCounterVec =
+        register_int_counter_vec!("groot_store_writes_total", "Writes of the store.", &["op"]).unwrap();
+    pub static ref STORE_GET: IntCounter = STORE_READS.with_label_values(&["get"]);
+    pub static ref STORE_SCAN: IntCounter = STORE_READS.with_label_values(&["scan"]);
+    pub static ref STORE_PUT: IntCounter = STORE_WRITES.with_label_values(&["put"]);
+    pub static ref STORE_DELETE: IntCounter = STORE_WRITES.with_label_values(&["delete"]);
+    pub static ref STORE_DELETE_RANGE: IntCounter = STORE_WRITES.with_label_values(&["delete_range"]);
+    pub static ref STORE_LOAD: IntCounter = STORE_WRITES.with_label_values(&["load"]);
+    pub static ref STORE_COMPACT: IntCounter = STORE_WRITES.with_label_values(&["compact"]);
+    static ref STORE_WRITES_THROTTLED: IntCounterVec = register_int_counter_vec!(
+        "groot_store_writes_throttled_total",
+        "Writes delayed or rejected by the backlog of flushes and compactions.",
+        &["action"]
+    )
+    .unwrap();
+    pub static ref STORE_WRITES_DELAYED: IntCounter = STORE_WRITES_THROTTLED.with_label_values(&["delay"]);
+    pub static ref STORE_WRITES_REJECTED: IntCounter =
+        STORE_WRITES_THROTTLED.with_label_values(&["reject"]);
+    static ref ROCKSDB_INSTANCES: Arc<Mutex<Vec<(String, Weak<RocksDB>)>>> = {
+        let instances = Arc::new(Mutex::new(Vec::new()));
+        let collector = RocksDBCollector::new(instances.clone());
+        if let Err(e) = prometheus::register(Box::new(collector)) {
+            error!("register rocksdb metrics failed: {}", e);
+        }
+        instances
+    };
+}
+
+/// Export the metrics of the rocksdb at `path`, until it's dropped.
+pub fn register_rocksdb(path: &str, db: &Arc<RocksDB>) {
+    let mut instances = ROCKSDB_INSTANCES.lock().unwrap();
+    instances.retain(|(p, db)| p != path && db.strong_count() > 0);
+    instances.push((path.to_owned(), Arc::downgrade(db)));
+}
+
+struct RocksDBCollector {
+    instances: Arc<Mutex<Vec<(String, Weak<RocksDB>)>>>,
+    properties: Vec<IntGaugeVec>,
+    tickers: Vec<IntCounterVec>,
+}
+
+impl RocksDBCollector {
+    fn new(instances: Arc<Mutex<Vec<(String, Weak<RocksDB>)>>>) -> Self {
+        let properties = PROPERTIES
+            .iter()
+            .map(|(_, name, help)| IntGaugeVec::new(Opts::new(*name, *help), &["path"]).unwrap())
+            .collect();
+        let tickers = TICKERS
+            .iter()
+            .map(|(_, name, help)| IntCounterVec::new(Opts::new(*name, *help), &["path"]).unwrap())
+            .collect();
+        RocksDBCollector { instances, properties, tickers }
+    }
+}
+
+impl Collector for RocksDBCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        let properties = self.properties.iter().flat_map(|m| m.desc());
+        properties
+            .chain(self.tickers.iter().flat_map(|m| m.desc()))
+            .collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        // hold the lock so that the metrics of concurrent scrapes are not mixed;
+        let instances = self.instances.lock().unwrap();
+        self.properties.iter().for_each(|m| m.reset());
+        self.tickers.iter().for_each(|m| m.reset());
+        for (path, db) in instances.iter() {
+            let db = match db.upgrade() {
+                Some(db) => db,
+                None => continue,
+            };
+            for ((property, _, _), gauge) in PROPERTIES.iter().zip(self.properties.iter()) {
+                if let Ok(Some(value)) = db.get_int_property(property) {
+                    gauge
+                        .with_label_values(&[path])
+                        .set(value as i64);
+                }
+            }
+            if let Some(stats) = db.get_statistics() {
+                let values = parse_tickers(&stats);
+                for ((ticker, _, _), counter) in TICKERS.iter().zip(self.tickers.iter()) {
+                    if let Some(value) = values.get(ticker) {
+                        counter
+                            .with_label_values(&[path])
+                            .inc_by(*value);
+                    }
+                }
+            }
+        }
+        let properties = self.properties.iter().flat_map(|m| m.collect());
+        properties
+            .chain(self.tickers.iter().flat_map(|m| m.collect()))
+            .collect()
+    }
+}
+
+/// Parse the tickers in the statistics dump of rocksdb, in lines of `name COUNT : value`;
+fn parse_tickers(stats: &str) -> HashMap<&str, u64> {
+    let mut tickers = HashMap::new();
+    for line in stats.lines() {
+        let mut parts = line.split_whitespace();
+        if let (Some(name), Some("COUNT"), Some(":"), Some(value)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        {
+            if let Ok(value) = value.parse::<u64>() {
+                tickers.insert(name, value);
+            }
+        }
+    }
+    tickers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tickers() {
+        let stats = "rocksdb.block.cache.miss COUNT : 12\n\
+                     rocksdb.block.cache.hit COUNT : 345\n\
+                     rocksdb.db.get.micros P50 : 1.000000 P95 : 2.000000 COUNT : 7 SUM : 9\n";
+        let tickers = parse_tickers(stats);
+        assert_eq!(tickers.get("rocksdb.block.cache.hit"), Some(&345));
+        assert_eq!(tickers.get("rocksdb.block.cache.miss"), Some(&12));
+        assert_eq!(tickers.get("rocksdb.db.get.micros"), None);
+    }
+}
@@ -1,3 +1,5 @@
+pub mod encryption;
+pub mod metrics;
 pub mod rocksdb;
 use std::ptr::null;
 
@@ -1,22 +1,77 @@
 use std::collections::HashMap;
 use std::path::Path;
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use ::rocksdb::backup::{BackupEngine, BackupEngineOptions, RestoreOptions};
-use ::rocksdb::{DBRawIterator, Env, IngestExternalFileOptions, Options, ReadOptions, DB};
+use ::rocksdb::{
+    BlockBasedOptions, Cache, ColumnFamily, ColumnFamilyDescriptor, DBCompressionType, DBRawIterator, Env,
+    IngestExternalFileOptions, Options, ReadOptions, DB,
+};
 use crossbeam_epoch::{self as epoch, Atomic, Guard, Owned, Shared};
-use rocksdb::WriteBatch;
+use rocksdb::{WriteBatch, WriteBatchIterator};
 
 use super::{StorageIter, StorageRes};
 use crate::db::api::*;
+use crate::db::storage::encryption::ValueCipher;
+use crate::db::storage::metrics::*;
 use crate::db::storage::{KvPair, RawBytes};
 
+const REWRITE_BATCH_SIZE: usize = 1024;
+const COPY_BATCH_SIZE: usize = 1024;
+
 pub struct RocksDB {
     db: Atomic<Arc<DB>>,
     options: HashMap<String, String>,
     is_secondary: bool,
+    /// Encrypt the values if `store.encryption.enabled`, see `encryption` for details.
+    cipher: Option<Arc<ValueCipher>>,
+    /// Held by writers in read mode, and by the rewrite of key rotation in write mode, so that a
+    /// value rewritten with the new key is never overwritten by a stale one.
+    rewrite_lock: RwLock<()>,
+    rewriting: AtomicBool,
+    /// The options the db is opened with if `store.rocksdb.statistics.enabled`, to dump the statistics.
+    statistics: Option<Options>,
+    /// The column families besides the default one, see `store.rocksdb.column.families`.
+    column_families: Vec<String>,
+    /// The column families of the labels by `store.rocksdb.label.placement`, e.g. `knows:huge`.
+    label_placement: HashMap<String, String>,
+    /// The column families of the tables placed out of the default one, see `place_table`.
+    placement: RwLock<HashMap<i64, String>>,
+    /// The block cache shared by all the column families if `store.rocksdb.block.cache.mb`.
+    block_cache: Option<BlockCache>,
+}
+
+/// A block cache shared by the column families, of which the capacity can be adjusted at runtime.
+pub struct BlockCache {
+    cache: Cache,
+    capacity: AtomicUsize,
+}
+
+impl BlockCache {
+    pub fn new(capacity: usize) -> Self {
+        BlockCache { cache: Cache::new_lru_cache(capacity), capacity: AtomicUsize::new(capacity) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Acquire)
+    }
+
+    pub fn usage(&self) -> usize {
+        self.cache.get_usage()
+    }
+
+    pub fn pinned_usage(&self) -> usize {
+        self.cache.get_pinned_usage()
+    }
+
+    /// The blocks are evicted in background if the usage exceeds the new capacity.
+    pub fn set_capacity(&self, capacity: usize) {
+        // the clone shares the same cache;
+        self.cache.clone().set_capacity(capacity);
+        self.capacity.store(capacity, Ordering::Release);
+    }
 }
 
 pub struct RocksDBBackupEngine {
@@ -26,28 +81,63 @@ pub struct RocksDBBackupEngine {
 
 impl RocksDB {
     pub fn open(options: &HashMap<String, String>) -> GraphResult<Self> {
-        let opts = init_options(options);
+        let cipher = ValueCipher::from_options(options)?.map(Arc::new);
+        let block_cache = shared_block_cache(options);
+        let cache = block_cache.as_ref().map(|c| &c.cache);
+        let opts = init_options(options, cache);
         let path = options
             .get("store.data.path")
             .expect("invalid config, missing store.data.path");
-        let db = DB::open(&opts, path).map_err(|e| {
+        let label_placement = parse_label_placement(options)?;
+        // the column families not configured any more are still opened, as some tables are placed there;
+        let cf_names = column_family_names(options, path);
+        let descriptors = cf_names
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(name, init_cf_options(options, name, cache)));
+        let db = DB::open_cf_descriptors(&opts, path, descriptors).map_err(|e| {
             let msg = format!("open rocksdb at {} failed: {}", path, e.into_string());
             gen_graph_err!(GraphErrorCode::ExternalStorageError, msg, open, options, path)
         })?;
-        let ret = RocksDB { db: Atomic::new(Arc::new(db)), options: options.clone(), is_secondary: false };
+        let mut ret = RocksDB::new(db, options, false, cipher);
+        ret.column_families = cf_names;
+        ret.label_placement = label_placement;
+        ret.block_cache = block_cache;
+        if statistics_enabled(options) {
+            ret.statistics = Some(opts);
+        }
         Ok(ret)
     }
 
     pub fn open_as_secondary(options: &HashMap<String, String>) -> GraphResult<Self> {
+        let cipher = ValueCipher::from_options(options)?.map(Arc::new);
         let db = RocksDB::open_helper(options, false).map_err(|e| {
             let msg = format!("open rocksdb at {:?}, error: {:?}", options, e);
             gen_graph_err!(GraphErrorCode::ExternalStorageError, msg, open_as_secondary)
         })?;
 
-        let ret = RocksDB { db: Atomic::new(Arc::new(db)), options: options.clone(), is_secondary: true };
+        let mut ret = RocksDB::new(db, options, true, cipher);
+        ret.column_families = existing_column_families(options.get("store.data.path").unwrap());
         Ok(ret)
     }
 
+    fn new(
+        db: DB, options: &HashMap<String, String>, is_secondary: bool, cipher: Option<Arc<ValueCipher>>,
+    ) -> Self {
+        RocksDB {
+            db: Atomic::new(Arc::new(db)),
+            options: options.clone(),
+            is_secondary,
+            cipher,
+            rewrite_lock: RwLock::new(()),
+            rewriting: AtomicBool::new(false),
+            statistics: None,
+            column_families: Vec::new(),
+            label_placement: HashMap::new(),
+            placement: RwLock::new(HashMap::new()),
+            block_cache: None,
+        }
+    }
+
     pub fn open_helper(options: &HashMap<String, String>, reopen: bool) -> Result<DB, ::rocksdb::Error> {
         let path = options
             .get("store.data.path")
@@ -63,7 +153,76 @@ impl RocksDB {
         }
         let opts = init_secondary_options(options);
         info!("Opening secondary at {}, {}", path, sec_path);
-        DB::open_as_secondary(&opts, path, &sec_path)
+        // the column families are created by the primary;
+        DB::open_cf_as_secondary(&opts, path, &sec_path, existing_column_families(path))
+    }
+
+    /// The column family configured for the tables of the label, if it's not the default one.
+    pub fn column_family_of_label(&self, label: &str) -> Option<&str> {
+        self.label_placement
+            .get(label)
+            .map(|cf| cf.as_str())
+    }
+
+    /// Place a table, i.e. its vertices or its edges of both directions, in the column family, so
+    /// that its keys are read and written there; the tables not placed are in the default column
+    /// family. A table should be placed before it's written, and never moved after.
+    pub fn place_table(&self, table_id: i64, cf: &str) -> GraphResult<()> {
+        if !self
+            .column_families
+            .iter()
+            .any(|name| name == cf)
+        {
+            let msg = format!("column family {} is not configured", cf);
+            return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, place_table, table_id));
+        }
+        self.placement
+            .write()
+            .unwrap()
+            .insert(table_id, cf.to_owned());
+        Ok(())
+    }
+
+    // the keys of the tables start with the table prefix in big endian, see `bin::vertex_key`;
+    fn table_cf_name(&self, key: &[u8]) -> Option<String> {
+        if self.column_families.is_empty() || key.len() < 8 {
+            return None;
+        }
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&key[0..8]);
+        let table_id = i64::from_be_bytes(prefix) >> 1;
+        self.placement
+            .read()
+            .unwrap()
+            .get(&table_id)
+            .cloned()
+    }
+
+    fn table_cf<'d>(&self, db: &'d DB, key: &[u8]) -> Option<&'d ColumnFamily> {
+        self.table_cf_name(key)
+            .and_then(|name| db.cf_handle(&name))
+    }
+
+    // the default column family as `None`, and the others;
+    fn all_cfs<'d>(&self, db: &'d DB) -> Vec<Option<&'d ColumnFamily>> {
+        let mut cfs = vec![None];
+        cfs.extend(
+            self.column_families
+                .iter()
+                .filter_map(|name| db.cf_handle(name))
+                .map(Some),
+        );
+        cfs
+    }
+
+    fn cf_names(&self) -> Vec<Option<&str>> {
+        let mut names = vec![None];
+        names.extend(
+            self.column_families
+                .iter()
+                .map(|name| Some(name.as_str())),
+        );
+        names
     }
 
     fn get_db<'g>(&self, guard: &'g Guard) -> Shared<'g, Arc<DB>> {
@@ -99,11 +258,19 @@ impl RocksDB {
     }
 
     pub fn get(&self, key: &[u8]) -> GraphResult<Option<StorageRes>> {
+        STORE_GET.inc();
         let guard = epoch::pin();
         let db_shared = self.get_db(&guard);
         if let Some(db) = unsafe { db_shared.as_ref() } {
-            match db.get(key) {
-                Ok(Some(v)) => Ok(Some(StorageRes::RocksDB(v))),
+            let res = match self.table_cf(db, key) {
+                Some(cf) => db.get_cf(cf, key),
+                None => db.get(key),
+            };
+            match res {
+                Ok(Some(v)) => match self.cipher.as_ref() {
+                    Some(cipher) => Ok(Some(StorageRes::RocksDB(cipher.decrypt(key, &v)?))),
+                    None => Ok(Some(StorageRes::RocksDB(v))),
+                },
                 Ok(None) => Ok(None),
                 Err(e) => {
                     let msg = format!("rocksdb.get failed because {}", e.into_string());
@@ -126,7 +293,21 @@ impl RocksDB {
         let guard = epoch::pin();
         let db_shared = self.get_db(&guard);
         if let Some(db) = unsafe { db_shared.as_ref() } {
-            db.put(key, val).map_err(|e| {
+            STORE_PUT.inc();
+            let _lock = self.rewrite_lock.read().unwrap();
+            let encrypted;
+            let val = match self.cipher.as_ref() {
+                Some(cipher) => {
+                    encrypted = cipher.encrypt(key, val)?;
+                    encrypted.as_slice()
+                }
+                None => val,
+            };
+            let res = match self.table_cf(db, key) {
+                Some(cf) => db.put_cf(cf, key, val),
+                None => db.put(key, val),
+            };
+            res.map_err(|e| {
                 let msg = format!("rocksdb.put failed because {}", e.into_string());
                 gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
             })
@@ -145,7 +326,13 @@ impl RocksDB {
         let guard = epoch::pin();
         let db_shared = self.get_db(&guard);
         if let Some(db) = unsafe { db_shared.as_ref() } {
-            db.delete(key).map_err(|e| {
+            STORE_DELETE.inc();
+            let _lock = self.rewrite_lock.read().unwrap();
+            let res = match self.table_cf(db, key) {
+                Some(cf) => db.delete_cf(cf, key),
+                None => db.delete(key),
+            };
+            res.map_err(|e| {
                 let msg = format!("rocksdb.delete failed because {}", e.into_string());
                 gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
             })
@@ -157,10 +344,17 @@ impl RocksDB {
     }
 
     pub fn scan_prefix(&self, prefix: &[u8]) -> GraphResult<StorageIter> {
+        STORE_SCAN.inc();
         let guard = epoch::pin();
         let db_shared = self.get_db(&guard);
         if let Some(db) = unsafe { db_shared.as_ref() } {
-            Ok(StorageIter::RocksDB(RocksDBIter::new_prefix(db.clone(), prefix, guard)))
+            Ok(StorageIter::RocksDB(RocksDBIter::new_prefix(
+                db.clone(),
+                self.table_cf_name(prefix),
+                prefix,
+                guard,
+                self.cipher.clone(),
+            )))
         } else {
             let msg = format!("rocksdb.scan_prefix failed because the acquired db is `None`");
             let err = gen_graph_err!(GraphErrorCode::ExternalStorageError, msg);
@@ -169,10 +363,17 @@ impl RocksDB {
     }
 
     pub fn scan_from(&self, start: &[u8]) -> GraphResult<StorageIter> {
+        STORE_SCAN.inc();
         let guard = epoch::pin();
         let db_shared = self.get_db(&guard);
         if let Some(db) = unsafe { db_shared.as_ref() } {
-            Ok(StorageIter::RocksDB(RocksDBIter::new_start(db.clone(), start, guard)))
+            Ok(StorageIter::RocksDB(RocksDBIter::new_start(
+                db.clone(),
+                self.table_cf_name(start),
+                start,
+                guard,
+                self.cipher.clone(),
+            )))
         } else {
             let msg = format!("rocksdb.scan_from failed because the acquired db is `None`");
             let err = gen_graph_err!(GraphErrorCode::ExternalStorageError, msg);
@@ -181,10 +382,18 @@ impl RocksDB {
     }
 
     pub fn scan_range(&self, start: &[u8], end: &[u8]) -> GraphResult<StorageIter> {
+        STORE_SCAN.inc();
         let guard = epoch::pin();
         let db_shared = self.get_db(&guard);
         if let Some(db) = unsafe { db_shared.as_ref() } {
-            Ok(StorageIter::RocksDB(RocksDBIter::new_range(db.clone(), start, end, guard)))
+            Ok(StorageIter::RocksDB(RocksDBIter::new_range(
+                db.clone(),
+                self.table_cf_name(start),
+                start,
+                end,
+                guard,
+                self.cipher.clone(),
+            )))
         } else {
             let msg = format!("rocksdb.new_range failed because the acquired db is `None`");
             let err = gen_graph_err!(GraphErrorCode::ExternalStorageError, msg);
@@ -192,6 +401,14 @@ impl RocksDB {
         }
     }
 
+    /// Scan the keys of the prefix from `start`, e.g. to resume a scan after the last key read.
+    pub fn scan_prefix_from(&self, prefix: &[u8], start: &[u8]) -> GraphResult<StorageIter> {
+        match bytes_upper_bound(prefix) {
+            Some(end) => self.scan_range(start, &end),
+            None => self.scan_from(start),
+        }
+    }
+
     pub fn delete_range(&self, start: &[u8], end: &[u8]) -> GraphResult<()> {
         if self.is_secondary {
             info!("Cannot delete_range in secondary instance");
@@ -201,8 +418,14 @@ impl RocksDB {
         let guard = epoch::pin();
         let db_shared = self.get_db(&guard);
         if let Some(db) = unsafe { db_shared.as_ref() } {
+            STORE_DELETE_RANGE.inc();
+            let _lock = self.rewrite_lock.read().unwrap();
             // db.delete_file_in_range(start, end);
-            batch.delete_range(start, end);
+            let cf = self.table_cf(db, start);
+            match cf {
+                Some(cf) => batch.delete_range_cf(cf, start, end),
+                None => batch.delete_range(start, end),
+            }
             db.write(batch).map_err(|e| {
                 let msg = format!("rocksdb.delete_range failed because {}", e.into_string());
                 gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
@@ -215,7 +438,10 @@ impl RocksDB {
                 val = conf_str.parse::<bool>().unwrap();
             }
             if !val {
-                db.compact_range(Option::Some(start), Option::Some(end))
+                match cf {
+                    Some(cf) => db.compact_range_cf(cf, Option::Some(start), Option::Some(end)),
+                    None => db.compact_range(Option::Some(start), Option::Some(end)),
+                }
             }
             Ok(())
         } else {
@@ -234,7 +460,13 @@ impl RocksDB {
         let db_shared = self.get_db(&guard);
 
         if let Some(db) = unsafe { db_shared.as_ref() } {
-            db.compact_range(None::<&[u8]>, None::<&[u8]>);
+            STORE_COMPACT.inc();
+            for cf in self.all_cfs(db) {
+                match cf {
+                    Some(cf) => db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>),
+                    None => db.compact_range(None::<&[u8]>, None::<&[u8]>),
+                }
+            }
             info!("compacted rocksdb");
             Ok(())
         } else {
@@ -244,6 +476,37 @@ impl RocksDB {
         }
     }
 
+    /// Flush all the memtables to sst files, and wait until the flush is done.
+    pub fn flush(&self) -> GraphResult<()> {
+        if self.is_secondary {
+            info!("Cannot flush in secondary instance");
+            return Ok(());
+        }
+        let guard = epoch::pin();
+        let db_shared = self.get_db(&guard);
+
+        if let Some(db) = unsafe { db_shared.as_ref() } {
+            for cf in self.all_cfs(db) {
+                let res = match cf {
+                    Some(cf) => db.flush_cf(cf),
+                    None => db.flush(),
+                };
+                res.map_err(|e| {
+                    let msg = format!("rocksdb.flush failed because {}", e.into_string());
+                    gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+                })?;
+            }
+            info!("flushed rocksdb");
+            Ok(())
+        } else {
+            let msg = format!("rocksdb.flush failed because the acquired db is `None`");
+            let err = gen_graph_err!(GraphErrorCode::ExternalStorageError, msg);
+            Err(err)
+        }
+    }
+
+    /// Ingest the sst files into the default column family, so the tables of bulk loads are never
+    /// placed in other column families.
     pub fn load(&self, files: &[&str]) -> GraphResult<()> {
         if self.is_secondary {
             info!("Cannot ingest in secondary instance");
@@ -254,6 +517,8 @@ impl RocksDB {
         let guard = epoch::pin();
         let db_shared = self.get_db(&guard);
         if let Some(db) = unsafe { db_shared.as_ref() } {
+            STORE_LOAD.inc();
+            let _lock = self.rewrite_lock.read().unwrap();
             db.ingest_external_file_opts(&options, files.to_vec())
                 .map_err(|e| {
                     let msg = format!("rocksdb.load file {:?} failed because {}", files, e.into_string());
@@ -299,11 +564,22 @@ impl RocksDB {
         }
     }
 
+    /// The cipher of the values if `store.encryption.enabled`, e.g. to encrypt the copies of them.
+    pub fn cipher(&self) -> Option<Arc<ValueCipher>> {
+        self.cipher.clone()
+    }
+
     pub fn new_scan(&self, prefix: &[u8]) -> GraphResult<Box<dyn Iterator<Item = KvPair> + Send>> {
         let guard = epoch::pin();
         let db_shared = self.get_db(&guard);
         if let Some(db) = unsafe { db_shared.as_ref() } {
-            Ok(Box::new(Scan::new(db.clone(), prefix, guard)))
+            Ok(Box::new(Scan::new(
+                db.clone(),
+                self.table_cf_name(prefix),
+                prefix,
+                guard,
+                self.cipher.clone(),
+            )))
         } else {
             let msg = format!("rocksdb.new_scan failed because the acquired db is `None`");
             let err = gen_graph_err!(GraphErrorCode::ExternalStorageError, msg);
@@ -350,6 +626,353 @@ impl RocksDB {
         }
         Ok(())
     }
+
+    /// Get an integer property of rocksdb, e.g. `rocksdb.cur-size-all-mem-tables`.
+    pub fn get_int_property(&self, name: &str) -> GraphResult<Option<u64>> {
+        let guard = epoch::pin();
+        let db_shared = self.get_db(&guard);
+        if let Some(db) = unsafe { db_shared.as_ref() } {
+            db.property_int_value(name).map_err(|e| {
+                let msg = format!("rocksdb.property_int_value {} failed because {}", name, e.into_string());
+                gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+            })
+        } else {
+            let msg = format!("rocksdb.property_int_value failed because the acquired db is `None`");
+            let err = gen_graph_err!(GraphErrorCode::ExternalStorageError, msg);
+            Err(err)
+        }
+    }
+
+    /// Get a property of rocksdb which is not an integer one, e.g. `rocksdb.num-files-at-level0`.
+    pub fn get_property(&self, name: &str) -> GraphResult<Option<String>> {
+        let guard = epoch::pin();
+        let db_shared = self.get_db(&guard);
+        if let Some(db) = unsafe { db_shared.as_ref() } {
+            db.property_value(name).map_err(|e| {
+                let msg = format!("rocksdb.property_value {} failed because {}", name, e.into_string());
+                gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+            })
+        } else {
+            let msg = format!("rocksdb.property_value failed because the acquired db is `None`");
+            let err = gen_graph_err!(GraphErrorCode::ExternalStorageError, msg);
+            Err(err)
+        }
+    }
+
+    pub fn get_block_cache(&self) -> Option<&BlockCache> {
+        self.block_cache.as_ref()
+    }
+
+    /// Resize the shared block cache, e.g. to give the memory to the memtables under heavy writes.
+    pub fn set_block_cache_capacity(&self, capacity: usize) -> GraphResult<()> {
+        match self.block_cache.as_ref() {
+            Some(cache) => {
+                info!("set block cache capacity from {} to {}", cache.capacity(), capacity);
+                cache.set_capacity(capacity);
+                Ok(())
+            }
+            None => {
+                let msg = "no shared block cache, see store.rocksdb.block.cache.mb".to_owned();
+                Err(gen_graph_err!(
+                    GraphErrorCode::InvalidOperation,
+                    msg,
+                    set_block_cache_capacity,
+                    capacity
+                ))
+            }
+        }
+    }
+
+    /// Dump the statistics of rocksdb, if `store.rocksdb.statistics.enabled`.
+    pub fn get_statistics(&self) -> Option<String> {
+        self.statistics
+            .as_ref()
+            .and_then(|opts| opts.get_statistics())
+    }
+
+    /// The sequence number of the last write, see `copy_updates_to`.
+    pub fn latest_sequence_number(&self) -> GraphResult<u64> {
+        Ok(self
+            .current_db("latest_sequence_number")?
+            .latest_sequence_number())
+    }
+
+    /// Copy the entries of which `filter` accepts the key to `target` as they are, i.e. the encrypted
+    /// values are not decrypted, and the entries are kept in the column families of the same names;
+    /// `throttle` is called with the bytes of each batch copied. Return the number of entries copied.
+    pub fn copy_to<F>(&self, target: &RocksDB, filter: F, throttle: &mut dyn FnMut(u64)) -> GraphResult<u64>
+    where
+        F: Fn(&[u8]) -> bool,
+    {
+        let db = self.current_db("copy_to")?;
+        let target_db = target.current_db("copy_to")?;
+        let mut count = 0;
+        for name in self.cf_names() {
+            let target_cf = match name {
+                Some(name) => match target_db.cf_handle(name) {
+                    Some(cf) => Some(cf),
+                    None => {
+                        let msg = format!("rocksdb.copy_to failed because {} is not in the target", name);
+                        return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg));
+                    }
+                },
+                None => None,
+            };
+            let mut bytes = 0;
+            let mut batch = WriteBatch::default();
+            let mut iter = raw_iterator(&db, name, ReadOptions::default());
+            iter.seek_to_first();
+            while iter.valid() {
+                let key = iter.key().unwrap();
+                if filter(key) {
+                    let value = iter.value().unwrap();
+                    bytes += (key.len() + value.len()) as u64;
+                    match target_cf {
+                        Some(cf) => batch.put_cf(cf, key, value),
+                        None => batch.put(key, value),
+                    }
+                    if batch.len() >= COPY_BATCH_SIZE {
+                        count += batch.len() as u64;
+                        write_batch(&target_db, std::mem::take(&mut batch), "copy_to")?;
+                        throttle(bytes);
+                        bytes = 0;
+                    }
+                }
+                iter.next();
+            }
+            iter.status().map_err(|e| {
+                let msg = format!("rocksdb.copy_to failed because {}", e.into_string());
+                gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+            })?;
+            count += batch.len() as u64;
+            write_batch(&target_db, batch, "copy_to")?;
+            throttle(bytes);
+        }
+        Ok(count)
+    }
+
+    /// Replay the writes after the sequence number `since` of which `filter` accepts the key to
+    /// `target`, the wal should be kept since then, e.g. by `store.rocksdb.wal.ttl.seconds`. Return
+    /// the sequence number to replay from next time and the number of writes replayed. The writes
+    /// to the column families other than the default one can't be replayed, so it fails if some
+    /// tables are placed there.
+    pub fn copy_updates_to<F>(&self, target: &RocksDB, since: u64, filter: F) -> GraphResult<(u64, u64)>
+    where
+        F: Fn(&[u8]) -> bool,
+    {
+        if !self.placement.read().unwrap().is_empty() {
+            let msg =
+                format!("rocksdb.copy_updates_to is not supported with tables placed in column families");
+            return Err(gen_graph_err!(GraphErrorCode::NotSupported, msg));
+        }
+        let db = self.current_db("copy_updates_to")?;
+        let target_db = target.current_db("copy_updates_to")?;
+        let updates = db.get_updates_since(since).map_err(|e| {
+            let msg = format!("rocksdb.get_updates_since {} failed because {}", since, e.into_string());
+            gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+        })?;
+        let mut next = since;
+        let mut count = 0;
+        for update in updates {
+            let (seq, batch) = update.map_err(|e| {
+                let msg = format!("rocksdb.copy_updates_to failed because {}", e.into_string());
+                gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+            })?;
+            let mut filtered = FilteredBatch { filter: &filter, batch: WriteBatch::default() };
+            batch.iterate(&mut filtered);
+            count += filtered.batch.len() as u64;
+            write_batch(&target_db, filtered.batch, "copy_updates_to")?;
+            next = seq;
+        }
+        Ok((next, count))
+    }
+
+    /// Delete the entries of which `filter` accepts the key, return the number of entries deleted.
+    pub fn delete_if<F>(&self, filter: F) -> GraphResult<u64>
+    where
+        F: Fn(&[u8]) -> bool,
+    {
+        if self.is_secondary {
+            info!("Cannot delete in secondary instance");
+            return Ok(0);
+        }
+        let db = self.current_db("delete_if")?;
+        let mut count = 0;
+        for cf in self.all_cfs(&db) {
+            let mut batch = WriteBatch::default();
+            let mut iter = match cf {
+                Some(cf) => db.raw_iterator_cf(cf),
+                None => db.raw_iterator(),
+            };
+            iter.seek_to_first();
+            while iter.valid() {
+                let key = iter.key().unwrap();
+                if filter(key) {
+                    match cf {
+                        Some(cf) => batch.delete_cf(cf, key),
+                        None => batch.delete(key),
+                    }
+                    if batch.len() >= COPY_BATCH_SIZE {
+                        count += batch.len() as u64;
+                        let _lock = self.rewrite_lock.read().unwrap();
+                        write_batch(&db, std::mem::take(&mut batch), "delete_if")?;
+                    }
+                }
+                iter.next();
+            }
+            iter.status().map_err(|e| {
+                let msg = format!("rocksdb.delete_if failed because {}", e.into_string());
+                gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+            })?;
+            count += batch.len() as u64;
+            let _lock = self.rewrite_lock.read().unwrap();
+            write_batch(&db, batch, "delete_if")?;
+        }
+        STORE_DELETE.inc_by(count);
+        Ok(count)
+    }
+
+    fn current_db(&self, op: &str) -> GraphResult<Arc<DB>> {
+        let guard = epoch::pin();
+        let db_shared = self.get_db(&guard);
+        match unsafe { db_shared.as_ref() } {
+            Some(db) => Ok(db.clone()),
+            None => {
+                let msg = format!("rocksdb.{} failed because the acquired db is `None`", op);
+                Err(gen_graph_err!(GraphErrorCode::ExternalStorageError, msg))
+            }
+        }
+    }
+
+    /// Rewrite the values not encrypted with the current key, i.e. after the key is rotated, or the
+    /// encryption is enabled on existing data; return the number of values rewritten.
+    pub fn rewrite_encrypted(&self) -> GraphResult<u64> {
+        if self.is_secondary {
+            info!("Cannot rewrite in secondary instance");
+            return Ok(0);
+        }
+        let cipher = match self.cipher.as_ref() {
+            Some(cipher) => cipher,
+            None => {
+                let msg = format!("rocksdb.rewrite_encrypted failed because encryption is not enabled");
+                return Err(gen_graph_err!(GraphErrorCode::NotSupported, msg));
+            }
+        };
+        if self.rewriting.swap(true, Ordering::SeqCst) {
+            let msg = format!("rocksdb.rewrite_encrypted failed because another rewrite is running");
+            return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg));
+        }
+        let res = self.rewrite_with(cipher);
+        self.rewriting.store(false, Ordering::SeqCst);
+        res
+    }
+
+    fn rewrite_with(&self, cipher: &ValueCipher) -> GraphResult<u64> {
+        let current = cipher.refresh()?;
+        let db = {
+            let guard = epoch::pin();
+            let db_shared = self.get_db(&guard);
+            match unsafe { db_shared.as_ref() } {
+                Some(db) => db.clone(),
+                None => {
+                    let msg = format!("rocksdb.rewrite_encrypted failed because the acquired db is `None`");
+                    return Err(gen_graph_err!(GraphErrorCode::ExternalStorageError, msg));
+                }
+            }
+        };
+        info!("rewrite values with encryption key {}", current);
+        let mut count = 0;
+        for cf in self.all_cfs(&db) {
+            let mut keys = Vec::with_capacity(REWRITE_BATCH_SIZE);
+            let mut iter = match cf {
+                Some(cf) => db.raw_iterator_cf(cf),
+                None => db.raw_iterator(),
+            };
+            iter.seek_to_first();
+            while iter.valid() {
+                if ValueCipher::key_id_of(iter.value().unwrap()) != Some(current) {
+                    keys.push(iter.key().unwrap().to_vec());
+                    if keys.len() >= REWRITE_BATCH_SIZE {
+                        count += self.rewrite_batch(&db, cf, cipher, current, &mut keys)?;
+                    }
+                }
+                iter.next();
+            }
+            iter.status().map_err(|e| {
+                let msg = format!("rocksdb.rewrite_encrypted failed because {}", e.into_string());
+                gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+            })?;
+            count += self.rewrite_batch(&db, cf, cipher, current, &mut keys)?;
+        }
+        info!("rewrote {} values with encryption key {}", count, current);
+        Ok(count)
+    }
+
+    fn rewrite_batch(
+        &self, db: &DB, cf: Option<&ColumnFamily>, cipher: &ValueCipher, current: u32,
+        keys: &mut Vec<Vec<u8>>,
+    ) -> GraphResult<u64> {
+        let _lock = self.rewrite_lock.write().unwrap();
+        let mut batch = WriteBatch::default();
+        let mut count = 0;
+        for key in keys.drain(..) {
+            // read again, as the value may be updated or deleted since it's scanned;
+            let value = match cf {
+                Some(cf) => db.get_cf(cf, &key),
+                None => db.get(&key),
+            };
+            let value = value.map_err(|e| {
+                let msg = format!("rocksdb.get failed because {}", e.into_string());
+                gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+            })?;
+            if let Some(value) = value {
+                if ValueCipher::key_id_of(&value) != Some(current) {
+                    let plain = cipher.decrypt(&key, &value)?;
+                    let encrypted = cipher.encrypt(&key, &plain)?;
+                    match cf {
+                        Some(cf) => batch.put_cf(cf, &key, encrypted),
+                        None => batch.put(&key, encrypted),
+                    }
+                    count += 1;
+                }
+            }
+        }
+        db.write(batch).map_err(|e| {
+            let msg = format!("rocksdb.rewrite_encrypted failed because {}", e.into_string());
+            gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+        })?;
+        Ok(count)
+    }
+}
+
+fn write_batch(db: &DB, batch: WriteBatch, op: &str) -> GraphResult<()> {
+    if batch.len() == 0 {
+        return Ok(());
+    }
+    db.write(batch).map_err(|e| {
+        let msg = format!("rocksdb.{} failed because {}", op, e.into_string());
+        gen_graph_err!(GraphErrorCode::ExternalStorageError, msg)
+    })
+}
+
+/// Collect the writes of a batch of which the filter accepts the key.
+struct FilteredBatch<'a, F> {
+    filter: &'a F,
+    batch: WriteBatch,
+}
+
+impl<'a, F: Fn(&[u8]) -> bool> WriteBatchIterator for FilteredBatch<'a, F> {
+    fn put(&mut self, key: Box<[u8]>, value: Box<[u8]>) {
+        if (self.filter)(&key) {
+            self.batch.put(key, value);
+        }
+    }
+
+    fn delete(&mut self, key: Box<[u8]>) {
+        if (self.filter)(&key) {
+            self.batch.delete(key);
+        }
+    }
 }
 
 pub struct Scan<'a> {
@@ -357,8 +980,10 @@ pub struct Scan<'a> {
 }
 
 impl<'a> Scan<'a> {
-    pub fn new(db: Arc<DB>, prefix: &[u8], guard: Guard) -> Self {
-        Scan { inner_iter: RocksDBIter::new_prefix(db, prefix, guard) }
+    pub fn new(
+        db: Arc<DB>, cf: Option<String>, prefix: &[u8], guard: Guard, cipher: Option<Arc<ValueCipher>>,
+    ) -> Self {
+        Scan { inner_iter: RocksDBIter::new_prefix(db, cf, prefix, guard, cipher) }
     }
 }
 
@@ -450,9 +1075,10 @@ fn init_secondary_options(options: &HashMap<String, String>) -> Options {
 }
 
 #[allow(unused_variables)]
-fn init_options(options: &HashMap<String, String>) -> Options {
+fn init_options(options: &HashMap<String, String>, block_cache: Option<&Cache>) -> Options {
     let mut opts = Options::default();
     opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
     opts.set_max_background_jobs(6);
     opts.set_write_buffer_size(256 << 20);
     opts.set_max_open_files(-1);
@@ -473,6 +1099,11 @@ fn init_options(options: &HashMap<String, String>) -> Options {
         opts.set_wal_dir(Path::new(conf_str));
     }
 
+    // keep the wal to replay the writes in partition migrations
+    if let Some(conf_str) = options.get("store.rocksdb.wal.ttl.seconds") {
+        opts.set_wal_ttl_seconds(conf_str.parse().unwrap());
+    }
+
     if let Some(conf_str) = options.get("store.rocksdb.write.buffer.mb") {
         let size_mb: usize = conf_str.parse().unwrap();
         let size_bytes = size_mb * 1024 * 1024;
@@ -496,31 +1127,178 @@ fn init_options(options: &HashMap<String, String>) -> Options {
         let check = conf_str.parse().unwrap();
         opts.set_paranoid_checks(check);
     }
+    if statistics_enabled(options) {
+        opts.enable_statistics();
+    }
+    if block_cache.is_some() {
+        opts.set_block_based_table_factory(&block_table_options(block_cache));
+    }
+    opts
+}
+
+/// The block cache shared by all the column families, if `store.rocksdb.block.cache.mb` is set,
+/// otherwise each column family has a block cache of its own.
+fn shared_block_cache(options: &HashMap<String, String>) -> Option<BlockCache> {
+    options
+        .get("store.rocksdb.block.cache.mb")
+        .map(|conf_str| {
+            let size_mb: usize = conf_str.parse().unwrap();
+            BlockCache::new(size_mb * 1024 * 1024)
+        })
+}
+
+fn block_table_options(block_cache: Option<&Cache>) -> BlockBasedOptions {
+    let mut table_opts = BlockBasedOptions::default();
+    if let Some(cache) = block_cache {
+        table_opts.set_block_cache(cache);
+    }
+    table_opts
+}
+
+/// The column families configured by `store.rocksdb.column.families`, e.g. `huge,small`.
+fn configured_column_families(options: &HashMap<String, String>) -> Vec<String> {
+    options
+        .get("store.rocksdb.column.families")
+        .map(|s| {
+            s.split(',')
+                .map(|name| name.trim().to_owned())
+                .filter(|name| !name.is_empty() && name != "default")
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The column families besides the default one, i.e. those configured and those already in the db
+/// at `path`.
+fn column_family_names(options: &HashMap<String, String>, path: &str) -> Vec<String> {
+    let mut names = configured_column_families(options);
+    for name in existing_column_families(path) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn existing_column_families(path: &str) -> Vec<String> {
+    // fails if the db doesn't exist yet;
+    DB::list_cf(&Options::default(), path)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| name != "default")
+        .collect()
+}
+
+/// The options of a column family, overridden by `store.rocksdb.cf.<name>.*`, e.g.
+/// `store.rocksdb.cf.huge.compression=zstd` and `store.rocksdb.cf.huge.block.size.kb=64`; note the
+/// compressions should be enabled as features of rocksdb, e.g. zstd is deactivated by default.
+/// The keys in a block are stored by their suffixes after the prefixes shared with the keys before,
+/// but at every `block.restart.interval` keys, e.g. the edges of a vertex share their table and
+/// vertex id, so that a larger interval stores the ids of the edges in fewer bytes.
+fn init_cf_options(options: &HashMap<String, String>, name: &str, block_cache: Option<&Cache>) -> Options {
+    let mut opts = init_options(options, block_cache);
+    let get = |key: &str| options.get(&format!("store.rocksdb.cf.{}.{}", name, key));
+    if let Some(conf_str) = get("compression") {
+        let compression = match conf_str.to_lowercase().as_str() {
+            "none" => DBCompressionType::None,
+            "snappy" => DBCompressionType::Snappy,
+            "zlib" => DBCompressionType::Zlib,
+            "lz4" => DBCompressionType::Lz4,
+            "lz4hc" => DBCompressionType::Lz4hc,
+            "zstd" => DBCompressionType::Zstd,
+            unknown => panic!("invalid config, unknown compression {} of column family {}", unknown, name),
+        };
+        opts.set_compression_type(compression);
+    }
+    let block_size_kb = get("block.size.kb").map(|s| s.parse::<usize>().unwrap());
+    let restart_interval = get("block.restart.interval").map(|s| s.parse::<i32>().unwrap());
+    if block_size_kb.is_some() || restart_interval.is_some() {
+        let mut table_opts = block_table_options(block_cache);
+        if let Some(size_kb) = block_size_kb {
+            table_opts.set_block_size(size_kb * 1024);
+        }
+        if let Some(interval) = restart_interval {
+            table_opts.set_block_restart_interval(interval);
+        }
+        opts.set_block_based_table_factory(&table_opts);
+    }
+    if let Some(conf_str) = get("write.buffer.mb") {
+        let size_mb: usize = conf_str.parse().unwrap();
+        opts.set_write_buffer_size(size_mb * 1024 * 1024);
+    }
+    if let Some(conf_str) = get("level0.compaction.trigger") {
+        opts.set_level_zero_file_num_compaction_trigger(conf_str.parse().unwrap());
+    }
+    if let Some(conf_str) = get("max.level.base.mb") {
+        let size_mb: u64 = conf_str.parse().unwrap();
+        opts.set_max_bytes_for_level_base(size_mb * 1024 * 1024);
+    }
+    if let Some(conf_str) = get("disable.auto.compactions") {
+        opts.set_disable_auto_compactions(conf_str.parse().unwrap());
+    }
     opts
 }
 
+/// Parse `store.rocksdb.label.placement`, e.g. `person:small,knows:huge`, of which the column
+/// families should be configured by `store.rocksdb.column.families`.
+fn parse_label_placement(options: &HashMap<String, String>) -> GraphResult<HashMap<String, String>> {
+    let mut placement = HashMap::new();
+    let conf_str = match options.get("store.rocksdb.label.placement") {
+        Some(conf_str) => conf_str,
+        None => return Ok(placement),
+    };
+    let configured = configured_column_families(options);
+    for item in conf_str
+        .split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+    {
+        match item.split_once(':') {
+            Some((label, cf)) if configured.iter().any(|name| name == cf.trim()) => {
+                placement.insert(label.trim().to_owned(), cf.trim().to_owned());
+            }
+            _ => {
+                let msg =
+                    format!("invalid label placement {}, or its column family is not configured", item);
+                return Err(gen_graph_err!(GraphErrorCode::InvalidOperation, msg, parse_label_placement));
+            }
+        }
+    }
+    Ok(placement)
+}
+
+fn statistics_enabled(options: &HashMap<String, String>) -> bool {
+    options
+        .get("store.rocksdb.statistics.enabled")
+        .map(|s| s.parse::<bool>().unwrap())
+        .unwrap_or(false)
+}
+
 pub struct RocksDBIter<'a> {
     _db: Arc<DB>,
     inner: Option<DBRawIterator<'a>>,
     just_seeked: bool,
     _guard: Guard,
+    cipher: Option<Arc<ValueCipher>>,
+    /// The decrypted value of the current entry.
+    value: Vec<u8>,
 }
 
 unsafe impl Send for RocksDBIter<'_> {}
 
 impl<'a> RocksDBIter<'a> {
-    fn new_prefix(db: Arc<DB>, prefix: &[u8], guard: Guard) -> Self {
+    fn new_prefix(
+        db: Arc<DB>, cf: Option<String>, prefix: &[u8], guard: Guard, cipher: Option<Arc<ValueCipher>>,
+    ) -> Self {
         let db_ptr = Arc::into_raw(db.clone()) as *const DB;
-        let mut db_iter = Self { _db: db, inner: None, just_seeked: true, _guard: guard };
+        let mut db_iter =
+            Self { _db: db, inner: None, just_seeked: true, _guard: guard, cipher, value: Vec::new() };
         let db_ref = unsafe { &*db_ptr };
-        let mut iter = match bytes_upper_bound(prefix) {
-            Some(upper) => {
-                let mut option = ReadOptions::default();
-                option.set_iterate_upper_bound(upper);
-                db_ref.raw_iterator_opt(option)
-            }
-            None => db_ref.raw_iterator(),
-        };
+        let mut option = ReadOptions::default();
+        if let Some(upper) = bytes_upper_bound(prefix) {
+            option.set_iterate_upper_bound(upper);
+        }
+        let mut iter = raw_iterator(db_ref, cf.as_deref(), option);
         iter.seek(prefix);
 
         db_iter.inner = Some(iter);
@@ -528,24 +1306,31 @@ impl<'a> RocksDBIter<'a> {
         db_iter
     }
 
-    fn new_start(db: Arc<DB>, start: &[u8], guard: Guard) -> Self {
+    fn new_start(
+        db: Arc<DB>, cf: Option<String>, start: &[u8], guard: Guard, cipher: Option<Arc<ValueCipher>>,
+    ) -> Self {
         let db_ptr = Arc::into_raw(db.clone()) as *const DB;
-        let mut db_iter = Self { _db: db, inner: None, just_seeked: true, _guard: guard };
+        let mut db_iter =
+            Self { _db: db, inner: None, just_seeked: true, _guard: guard, cipher, value: Vec::new() };
         let db_ref = unsafe { &*db_ptr };
-        let mut iter = db_ref.raw_iterator();
+        let mut iter = raw_iterator(db_ref, cf.as_deref(), ReadOptions::default());
         iter.seek(start);
         db_iter.inner = Some(iter);
 
         db_iter
     }
 
-    fn new_range(db: Arc<DB>, start: &[u8], end: &[u8], guard: Guard) -> Self {
+    fn new_range(
+        db: Arc<DB>, cf: Option<String>, start: &[u8], end: &[u8], guard: Guard,
+        cipher: Option<Arc<ValueCipher>>,
+    ) -> Self {
         let db_ptr = Arc::into_raw(db.clone()) as *const DB;
-        let mut db_iter = Self { _db: db, inner: None, just_seeked: true, _guard: guard };
+        let mut db_iter =
+            Self { _db: db, inner: None, just_seeked: true, _guard: guard, cipher, value: Vec::new() };
         let db_ref = unsafe { &*db_ptr };
         let mut option = ReadOptions::default();
         option.set_iterate_upper_bound(end.to_vec());
-        let mut iter = db_ref.raw_iterator_opt(option);
+        let mut iter = raw_iterator(db_ref, cf.as_deref(), option);
         iter.seek(start);
 
         db_iter.inner = Some(iter);
@@ -553,8 +1338,11 @@ impl<'a> RocksDBIter<'a> {
         db_iter
     }
 
+    /// The next entry, where the entries of which the value can't be decrypted are logged and
+    /// skipped, like other corrupt data of the scans.
     pub fn next(&mut self) -> Option<(&[u8], &[u8])> {
-        if let Some(inner) = &mut self.inner {
+        let inner = self.inner.as_mut()?;
+        loop {
             if !inner.valid() {
                 return None;
             }
@@ -565,17 +1353,34 @@ impl<'a> RocksDBIter<'a> {
                 inner.next();
             }
 
-            if inner.valid() {
-                Some((inner.key().unwrap(), inner.value().unwrap()))
-            } else {
-                None
+            if !inner.valid() {
+                return None;
             }
-        } else {
-            None
+            let cipher = match self.cipher.as_ref() {
+                Some(cipher) => cipher,
+                None => break,
+            };
+            let key = inner.key().unwrap();
+            match cipher.decrypt_into(key, inner.value().unwrap(), &mut self.value) {
+                Ok(()) => break,
+                Err(e) => error!("skip the value of key {:?} which can't be decrypted: {:?}", key, e),
+            }
+        }
+        let key = inner.key().unwrap();
+        match self.cipher {
+            Some(_) => Some((key, self.value.as_slice())),
+            None => Some((key, inner.value().unwrap())),
         }
     }
 }
 
+fn raw_iterator<'a>(db: &'a DB, cf: Option<&str>, option: ReadOptions) -> DBRawIterator<'a> {
+    match cf.and_then(|name| db.cf_handle(name)) {
+        Some(cf) => db.raw_iterator_cf_opt(cf, option),
+        None => db.raw_iterator_opt(option),
+    }
+}
+
 fn bytes_upper_bound(bytes: &[u8]) -> Option<Vec<u8>> {
     for i in (0..bytes.len()).rev() {
         if bytes[i] != u8::MAX {
@@ -594,6 +1399,7 @@ fn bytes_upper_bound(bytes: &[u8]) -> Option<Vec<u8>> {
 mod tests {
     use super::*;
     use crate::db::common::bytes::transform;
+    use crate::db::storage::encryption::{self, EnvKeyProvider};
     use crate::db::util::fs;
 
     #[test]
@@ -657,4 +1463,163 @@ mod tests {
         }
         fs::rmr(path).unwrap();
     }
+
+    #[test]
+    fn test_rocksdb_encryption() {
+        let path = "test_rocksdb_encryption";
+        fs::rmr(path).unwrap();
+        {
+            let key1 = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+            let key2 = "ffeeddccbbaa99887766554433221100ffeeddccbbaa99887766554433221100";
+            let provider = EnvKeyProvider::parse(&format!("1:{}", key1)).unwrap();
+            encryption::register_key_provider("test_rocksdb_encryption", Arc::new(provider));
+            let mut config = HashMap::new();
+            config.insert("store.data.path".to_owned(), path.to_owned());
+            let plain_db = RocksDB::open(&config).unwrap();
+            plain_db.put(b"aaa#0", b"v0").unwrap();
+            drop(plain_db);
+
+            config.insert("store.encryption.enabled".to_owned(), "true".to_owned());
+            config.insert("store.encryption.key.provider".to_owned(), "test_rocksdb_encryption".to_owned());
+            let db = RocksDB::open(&config).unwrap();
+            db.put(b"aaa#1", b"v1").unwrap();
+            assert_eq!(db.get(b"aaa#0").unwrap().unwrap().as_bytes(), b"v0");
+            assert_eq!(db.get(b"aaa#1").unwrap().unwrap().as_bytes(), b"v1");
+            let raw = db.get_raw(b"aaa#1");
+            assert_eq!(ValueCipher::key_id_of(&raw), Some(1));
+
+            drop(db);
+
+            // rotate to key 2;
+            let provider = EnvKeyProvider::parse(&format!("1:{},2:{}", key1, key2)).unwrap();
+            encryption::register_key_provider("test_rocksdb_encryption", Arc::new(provider));
+            let db = RocksDB::open(&config).unwrap();
+            assert_eq!(db.rewrite_encrypted().unwrap(), 2);
+            assert_eq!(db.rewrite_encrypted().unwrap(), 0);
+            assert_eq!(ValueCipher::key_id_of(&db.get_raw(b"aaa#0")), Some(2));
+            let mut iter = db.scan_prefix(b"aaa").unwrap();
+            assert_eq!(iter.next().unwrap(), (&b"aaa#0"[..], &b"v0"[..]));
+            assert_eq!(iter.next().unwrap(), (&b"aaa#1"[..], &b"v1"[..]));
+            assert!(iter.next().is_none());
+            drop(iter);
+            drop(db);
+
+            // the values of a lost key are skipped by the scans instead of panicking;
+            let provider = EnvKeyProvider::parse(&format!("1:{}", key1)).unwrap();
+            encryption::register_key_provider("test_rocksdb_encryption", Arc::new(provider));
+            let db = RocksDB::open(&config).unwrap();
+            db.put(b"aaa#2", b"v2").unwrap();
+            assert!(db.get(b"aaa#0").is_err());
+            let mut iter = db.scan_prefix(b"aaa").unwrap();
+            assert_eq!(iter.next().unwrap(), (&b"aaa#2"[..], &b"v2"[..]));
+            assert!(iter.next().is_none());
+        }
+        fs::rmr(path).unwrap();
+    }
+
+    #[test]
+    fn test_rocksdb_column_family() {
+        let path = "test_rocksdb_column_family";
+        fs::rmr(path).unwrap();
+        {
+            let mut config = HashMap::new();
+            config.insert("store.data.path".to_owned(), path.to_owned());
+            config.insert("store.rocksdb.column.families".to_owned(), "huge".to_owned());
+            config.insert("store.rocksdb.cf.huge.compression".to_owned(), "lz4".to_owned());
+            config.insert("store.rocksdb.label.placement".to_owned(), "knows:huge".to_owned());
+            let db = RocksDB::open(&config).unwrap();
+            assert_eq!(db.column_family_of_label("knows"), Some("huge"));
+            assert_eq!(db.column_family_of_label("person"), None);
+            assert!(db.place_table(7, "small").is_err());
+            db.place_table(7, "huge").unwrap();
+            let in_edge = transform::i64_to_vec((7i64 << 1 | 1).to_be());
+            let other = transform::i64_to_vec((8i64 << 1).to_be());
+            db.put(&in_edge, b"v7").unwrap();
+            db.put(&other, b"v8").unwrap();
+            assert_eq!(db.get(&in_edge).unwrap().unwrap().as_bytes(), b"v7");
+            assert!(db.get_raw_cf(None, &in_edge).is_none());
+            assert_eq!(db.get_raw_cf(Some("huge"), &in_edge).unwrap(), b"v7");
+            assert!(db.get_raw_cf(Some("huge"), &other).is_none());
+            let mut iter = db.scan_prefix(&in_edge).unwrap();
+            assert_eq!(iter.next().unwrap(), (&in_edge[..], &b"v7"[..]));
+            assert!(iter.next().is_none());
+            drop(iter);
+            drop(db);
+
+            config.insert("store.rocksdb.label.placement".to_owned(), "knows:small".to_owned());
+            assert!(RocksDB::open(&config).is_err());
+
+            // the existing column families are opened even if they are not configured;
+            config.remove("store.rocksdb.column.families");
+            config.remove("store.rocksdb.label.placement");
+            let db = RocksDB::open(&config).unwrap();
+            db.place_table(7, "huge").unwrap();
+            assert_eq!(db.get(&in_edge).unwrap().unwrap().as_bytes(), b"v7");
+            assert_eq!(db.delete_if(|_| true).unwrap(), 2);
+            assert!(db.get(&in_edge).unwrap().is_none());
+        }
+        fs::rmr(path).unwrap();
+    }
+
+    #[test]
+    fn test_rocksdb_shared_block_cache() {
+        let path = "test_rocksdb_shared_block_cache";
+        fs::rmr(path).unwrap();
+        {
+            let mut config = HashMap::new();
+            config.insert("store.data.path".to_owned(), path.to_owned());
+            config.insert("store.rocksdb.column.families".to_owned(), "huge".to_owned());
+            config.insert("store.rocksdb.cf.huge.block.size.kb".to_owned(), "16".to_owned());
+            let db = RocksDB::open(&config).unwrap();
+            assert!(db.get_block_cache().is_none());
+            assert!(db.set_block_cache_capacity(1 << 20).is_err());
+            drop(db);
+
+            config.insert("store.rocksdb.block.cache.mb".to_owned(), "4".to_owned());
+            let db = RocksDB::open(&config).unwrap();
+            db.place_table(7, "huge").unwrap();
+            let edge = transform::i64_to_vec((7i64 << 1).to_be());
+            let vertex = transform::i64_to_vec((8i64 << 1).to_be());
+            db.put(&edge, b"v7").unwrap();
+            db.put(&vertex, b"v8").unwrap();
+            db.flush().unwrap();
+            assert_eq!(db.get(&edge).unwrap().unwrap().as_bytes(), b"v7");
+            assert_eq!(db.get(&vertex).unwrap().unwrap().as_bytes(), b"v8");
+            let cache = db.get_block_cache().unwrap();
+            assert_eq!(cache.capacity(), 4 << 20);
+            assert!(cache.usage() > 0);
+            assert_eq!(
+                db.get_int_property("rocksdb.block-cache-capacity")
+                    .unwrap(),
+                Some(4 << 20)
+            );
+            db.set_block_cache_capacity(1 << 20).unwrap();
+            assert_eq!(cache.capacity(), 1 << 20);
+            assert_eq!(
+                db.get_int_property("rocksdb.block-cache-capacity")
+                    .unwrap(),
+                Some(1 << 20)
+            );
+        }
+        fs::rmr(path).unwrap();
+    }
+
+    impl RocksDB {
+        fn get_raw_cf(&self, cf: Option<&str>, key: &[u8]) -> Option<Vec<u8>> {
+            let guard = epoch::pin();
+            let db = unsafe { self.get_db(&guard).as_ref() }.unwrap();
+            match cf {
+                Some(cf) => db
+                    .get_cf(db.cf_handle(cf).unwrap(), key)
+                    .unwrap(),
+                None => db.get(key).unwrap(),
+            }
+        }
+
+        fn get_raw(&self, key: &[u8]) -> Vec<u8> {
+            let guard = epoch::pin();
+            let db = unsafe { self.get_db(&guard).as_ref() }.unwrap();
+            db.get(key).unwrap().unwrap()
+        }
+    }
 }
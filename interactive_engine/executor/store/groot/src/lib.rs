@@ -29,6 +29,8 @@ extern crate log;
 extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
+extern crate lazy_static;
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -405,6 +405,25 @@ public class GrootClient implements Writer {
         return response.getSuccess();
     }
 
+    /** Resize the block cache shared by the column families of each store partition. */
+    public boolean setBlockCacheCapacity(long capacityBytes) {
+        SetBlockCacheCapacityRequest request =
+                SetBlockCacheCapacityRequest.newBuilder().setCapacityBytes(capacityBytes).build();
+        SetBlockCacheCapacityResponse response = this.clientStub.setBlockCacheCapacity(request);
+        return response.getSuccess();
+    }
+
+    /**
+     * Seal the graph at the snapshot, e.g. the one its bulk load is committed at, into the path,
+     * which the stores serve read-only once restarted with {@code store.sealed.path} set to it.
+     */
+    public boolean sealGraph(long snapshotId, String path) {
+        SealGraphRequest request =
+                SealGraphRequest.newBuilder().setSnapshotId(snapshotId).setPath(path).build();
+        SealGraphResponse response = this.clientStub.sealGraph(request);
+        return response.getSuccess();
+    }
+
     public boolean reopenSecondary() {
         ReopenSecondaryRequest request = ReopenSecondaryRequest.newBuilder().build();
         ReopenSecondaryResponse response = this.clientStub.reopenSecondary(request);
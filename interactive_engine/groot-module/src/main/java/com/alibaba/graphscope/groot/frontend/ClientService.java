@@ -491,6 +491,101 @@ public class ClientService extends ClientGrpc.ClientImplBase {
         }
     }
 
+    @Override
+    public void setBlockCacheCapacity(
+            SetBlockCacheCapacityRequest request,
+            StreamObserver<SetBlockCacheCapacityResponse> responseObserver) {
+        long capacityBytes = request.getCapacityBytes();
+        logger.info("Set block cache capacity to " + capacityBytes);
+        int storeCount = this.metaService.getStoreCount();
+        AtomicInteger counter = new AtomicInteger(storeCount);
+        AtomicBoolean finished = new AtomicBoolean(false);
+        for (int i = 0; i < storeCount; i++) {
+            this.frontendStoreClients
+                    .getClient(i)
+                    .setBlockCacheCapacity(
+                            capacityBytes,
+                            new CompletionCallback<Void>() {
+                                @Override
+                                public void onCompleted(Void res) {
+                                    if (!finished.get() && counter.decrementAndGet() == 0) {
+                                        finish(null);
+                                    }
+                                }
+
+                                @Override
+                                public void onError(Throwable t) {
+                                    logger.error("failed set block cache capacity", t);
+                                    finish(t);
+                                }
+
+                                private void finish(Throwable t) {
+                                    if (finished.getAndSet(true)) {
+                                        return;
+                                    }
+                                    if (t != null) {
+                                        responseObserver.onError(t);
+                                    } else {
+                                        SetBlockCacheCapacityResponse res =
+                                                SetBlockCacheCapacityResponse.newBuilder()
+                                                        .setSuccess(true)
+                                                        .build();
+                                        responseObserver.onNext(res);
+                                        responseObserver.onCompleted();
+                                    }
+                                }
+                            });
+        }
+    }
+
+    @Override
+    public void sealGraph(
+            SealGraphRequest request, StreamObserver<SealGraphResponse> responseObserver) {
+        long snapshotId = request.getSnapshotId();
+        String path = request.getPath();
+        logger.info("Seal graph at snapshot {} to {}", snapshotId, path);
+        int storeCount = this.metaService.getStoreCount();
+        AtomicInteger counter = new AtomicInteger(storeCount);
+        AtomicBoolean finished = new AtomicBoolean(false);
+        for (int i = 0; i < storeCount; i++) {
+            this.frontendStoreClients
+                    .getClient(i)
+                    .sealGraph(
+                            snapshotId,
+                            path,
+                            new CompletionCallback<Void>() {
+                                @Override
+                                public void onCompleted(Void res) {
+                                    if (!finished.get() && counter.decrementAndGet() == 0) {
+                                        finish(null);
+                                    }
+                                }
+
+                                @Override
+                                public void onError(Throwable t) {
+                                    logger.error("failed seal graph", t);
+                                    finish(t);
+                                }
+
+                                private void finish(Throwable t) {
+                                    if (finished.getAndSet(true)) {
+                                        return;
+                                    }
+                                    if (t != null) {
+                                        responseObserver.onError(t);
+                                    } else {
+                                        SealGraphResponse res =
+                                                SealGraphResponse.newBuilder()
+                                                        .setSuccess(true)
+                                                        .build();
+                                        responseObserver.onNext(res);
+                                        responseObserver.onCompleted();
+                                    }
+                                }
+                            });
+        }
+    }
+
     @Override
     public void getStoreState(
             GetStoreStateRequest request, StreamObserver<GetStoreStateResponse> responseObserver) {
@@ -96,6 +96,51 @@ public class FrontendStoreClient extends RpcClient {
                         });
     }
 
+    public void setBlockCacheCapacity(long capacityBytes, CompletionCallback<Void> callback) {
+        getStub()
+                .setBlockCacheCapacity(
+                        SetBlockCacheCapacityRequest.newBuilder()
+                                .setCapacityBytes(capacityBytes)
+                                .build(),
+                        new StreamObserver<>() {
+                            @Override
+                            public void onNext(SetBlockCacheCapacityResponse value) {
+                                callback.onCompleted(null);
+                            }
+
+                            @Override
+                            public void onError(Throwable t) {
+                                callback.onError(t);
+                            }
+
+                            @Override
+                            public void onCompleted() {}
+                        });
+    }
+
+    public void sealGraph(long snapshotId, String path, CompletionCallback<Void> callback) {
+        getStub()
+                .sealGraph(
+                        SealGraphRequest.newBuilder()
+                                .setSnapshotId(snapshotId)
+                                .setPath(path)
+                                .build(),
+                        new StreamObserver<>() {
+                            @Override
+                            public void onNext(SealGraphResponse value) {
+                                callback.onCompleted(null);
+                            }
+
+                            @Override
+                            public void onError(Throwable t) {
+                                callback.onError(t);
+                            }
+
+                            @Override
+                            public void onCompleted() {}
+                        });
+    }
+
     public void reopenSecondary(CompletionCallback<Void> callback) {
         getStub()
                 .reopenSecondary(
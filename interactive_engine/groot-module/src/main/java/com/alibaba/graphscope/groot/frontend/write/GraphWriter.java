@@ -38,6 +38,7 @@ public class GraphWriter {
     private LongHistogram writeHistogram;
     private final SnapshotCache snapshotCache;
     private final EdgeIdGenerator edgeIdGenerator;
+    private final VertexIdGenerator vertexIdGenerator;
     private final AtomicLong lastWrittenSnapshotId = new AtomicLong(0L);
 
     private final KafkaAppender kafkaAppender;
@@ -50,6 +51,8 @@ public class GraphWriter {
             Configs configs) {
         this.snapshotCache = snapshotCache;
         this.edgeIdGenerator = edgeIdGenerator;
+        // the auto increment ids of vertices share the ranges allocated for edges
+        this.vertexIdGenerator = new VertexIdGenerator(configs, edgeIdGenerator::getNextId);
         initMetrics();
         this.kafkaAppender = appender;
     }
@@ -282,6 +285,7 @@ public class GraphWriter {
                 parseRawProperties(vertexDef, vertexRecordKey.getProperties());
         Map<Integer, PropertyValue> propertyVals = parseRawProperties(vertexDef, properties);
         propertyVals.putAll(pkVals);
+        vertexIdGenerator.fillPrimaryKey(vertexDef, propertyVals);
         long hashId = getPrimaryKeysHashId(labelId, propertyVals, vertexDef);
         batchBuilder.addOperation(
                 new OverwriteVertexOperation(
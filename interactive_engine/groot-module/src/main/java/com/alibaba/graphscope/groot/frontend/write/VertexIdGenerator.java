@@ -0,0 +1,132 @@
+package com.alibaba.graphscope.groot.frontend.write;
+
+import com.alibaba.graphscope.groot.common.config.CommonConfig;
+import com.alibaba.graphscope.groot.common.config.Configs;
+import com.alibaba.graphscope.groot.common.config.FrontendConfig;
+import com.alibaba.graphscope.groot.common.exception.InvalidDataException;
+import com.alibaba.graphscope.groot.common.schema.api.GraphElement;
+import com.alibaba.graphscope.groot.common.schema.api.GraphProperty;
+import com.alibaba.graphscope.groot.common.schema.wrapper.DataType;
+import com.alibaba.graphscope.groot.common.schema.wrapper.PropertyValue;
+
+import java.util.HashMap;
+import java.util.List;
+import java.util.Map;
+import java.util.function.LongSupplier;
+
+/**
+ * Generate the primary keys of the vertices inserted without them, by the strategies configured
+ * per label in {@link FrontendConfig#VERTEX_ID_STRATEGY}, e.g.
+ * `person:auto_increment,event:snowflake`. The primary key of such a label should be a single
+ * long property, and the vertex id is derived from it as usual, so the vertex can be updated or
+ * deleted by the generated key later.
+ */
+public class VertexIdGenerator {
+
+    public enum Strategy {
+        /** Ids in the ranges allocated by the coordinator, unique among all the frontends. */
+        AUTO_INCREMENT,
+        /** Ids of the timestamp in milliseconds, the frontend node and a sequence in it. */
+        SNOWFLAKE,
+    }
+
+    private final Map<String, Strategy> strategies;
+    private final LongSupplier autoIncrement;
+    private final SnowflakeIdGenerator snowflake;
+
+    public VertexIdGenerator(Configs configs, LongSupplier autoIncrement) {
+        this.strategies = parseStrategies(FrontendConfig.VERTEX_ID_STRATEGY.get(configs));
+        this.autoIncrement = autoIncrement;
+        this.snowflake = new SnowflakeIdGenerator(CommonConfig.NODE_IDX.get(configs));
+    }
+
+    /**
+     * Fill the primary key of the vertex into `propertyVals` if it's missing and the label has a
+     * strategy configured.
+     */
+    public void fillPrimaryKey(GraphElement vertexDef, Map<Integer, PropertyValue> propertyVals) {
+        Strategy strategy = this.strategies.get(vertexDef.getLabel());
+        if (strategy == null) {
+            return;
+        }
+        List<GraphProperty> pks = vertexDef.getPrimaryKeyList();
+        if (pks.size() != 1 || pks.get(0).getDataType() != DataType.LONG) {
+            throw new InvalidDataException(
+                    "vertex id strategy "
+                            + strategy
+                            + " of label ["
+                            + vertexDef.getLabel()
+                            + "] requires a single long primary key");
+        }
+        int pkId = pks.get(0).getId();
+        if (propertyVals.containsKey(pkId)) {
+            return;
+        }
+        long id =
+                strategy == Strategy.AUTO_INCREMENT
+                        ? this.autoIncrement.getAsLong()
+                        : this.snowflake.nextId();
+        propertyVals.put(pkId, new PropertyValue(DataType.LONG, id));
+    }
+
+    static Map<String, Strategy> parseStrategies(String conf) {
+        Map<String, Strategy> strategies = new HashMap<>();
+        for (String item : conf.split(",")) {
+            item = item.trim();
+            if (item.isEmpty()) {
+                continue;
+            }
+            String[] parts = item.split(":");
+            if (parts.length != 2) {
+                throw new IllegalArgumentException("invalid vertex id strategy [" + item + "]");
+            }
+            strategies.put(parts[0].trim(), Strategy.valueOf(parts[1].trim().toUpperCase()));
+        }
+        return strategies;
+    }
+
+    /**
+     * Ids of 41 bits of milliseconds since 2020-01-01, 10 bits of the node and 12 bits of a
+     * sequence in the millisecond, which are increasing in a node as long as its clock is not
+     * turned back.
+     */
+    static class SnowflakeIdGenerator {
+        static final long EPOCH_MS = 1577836800000L;
+        static final int NODE_BITS = 10;
+        static final int SEQUENCE_BITS = 12;
+
+        private final long node;
+        private long lastMs = -1L;
+        private long sequence = 0L;
+
+        SnowflakeIdGenerator(int node) {
+            this.node = node & ((1L << NODE_BITS) - 1);
+        }
+
+        synchronized long nextId() {
+            long ms = currentMs();
+            // wait for the clock turned back to catch up, so that the ids never repeat
+            while (ms < this.lastMs) {
+                ms = currentMs();
+            }
+            if (ms == this.lastMs) {
+                this.sequence = (this.sequence + 1) & ((1L << SEQUENCE_BITS) - 1);
+                if (this.sequence == 0) {
+                    while (ms <= this.lastMs) {
+                        ms = currentMs();
+                    }
+                }
+            } else {
+                this.sequence = 0L;
+            }
+            this.lastMs = ms;
+            return ((ms - EPOCH_MS) << (NODE_BITS + SEQUENCE_BITS))
+                    | (this.node << SEQUENCE_BITS)
+                    | this.sequence;
+        }
+
+        long currentMs() {
+            return System.currentTimeMillis();
+        }
+    }
+}
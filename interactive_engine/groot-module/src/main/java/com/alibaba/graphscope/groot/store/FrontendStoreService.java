@@ -89,6 +89,34 @@ public class FrontendStoreService extends FrontendStoreServiceGrpc.FrontendStore
                 });
     }
 
+    @Override
+    public void setBlockCacheCapacity(
+            SetBlockCacheCapacityRequest request,
+            StreamObserver<SetBlockCacheCapacityResponse> responseObserver) {
+        try {
+            this.storeService.setBlockCacheCapacity(request.getCapacityBytes());
+            responseObserver.onNext(
+                    SetBlockCacheCapacityResponse.newBuilder().setSuccess(true).build());
+            responseObserver.onCompleted();
+        } catch (IOException e) {
+            responseObserver.onError(
+                    Status.INTERNAL.withDescription(e.getMessage()).asRuntimeException());
+        }
+    }
+
+    @Override
+    public void sealGraph(
+            SealGraphRequest request, StreamObserver<SealGraphResponse> responseObserver) {
+        try {
+            this.storeService.sealGraph(request.getSnapshotId(), request.getPath());
+            responseObserver.onNext(SealGraphResponse.newBuilder().setSuccess(true).build());
+            responseObserver.onCompleted();
+        } catch (IOException e) {
+            responseObserver.onError(
+                    Status.INTERNAL.withDescription(e.getMessage()).asRuntimeException());
+        }
+    }
+
     @Override
     public void reopenSecondary(
             ReopenSecondaryRequest request,
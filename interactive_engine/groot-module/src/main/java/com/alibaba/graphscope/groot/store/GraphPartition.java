@@ -20,6 +20,7 @@ import com.alibaba.graphscope.proto.groot.GraphDefPb;
 
 import java.io.Closeable;
 import java.io.IOException;
+import java.util.List;
 
 public interface GraphPartition extends Closeable {
 
@@ -46,4 +47,28 @@ public interface GraphPartition extends Closeable {
     void reopenSecondary(long wait_sec) throws IOException;
 
     void compact() throws IOException;
+
+    /** Resize the block cache shared by the column families, at runtime. */
+    void setBlockCacheCapacity(long capacityBytes) throws IOException;
+
+    /**
+     * Map the external string ids of the label to vertex ids, the ids are allocated for those not
+     * mapped yet. The external ids should be owned by this partition, see {@link
+     * StoreService#mapExternalIds}.
+     */
+    long[] mapExternalIds(int labelId, List<String> externalIds) throws IOException;
+
+    /** The external id of the vertex, or null if its id is not mapped. */
+    String getExternalId(int labelId, long vertexId) throws IOException;
+
+    void rotateEncryptionKey() throws IOException;
+
+    /** Stop accepting writes and flush the memtables before shutdown. */
+    void drain() throws IOException;
+
+    /**
+     * Seal the graph of the partition at the snapshot, e.g. after its bulk load, into the path,
+     * which the partition serves read-only once reopened with {@code store.sealed.path}.
+     */
+    void seal(long snapshotId, String path) throws IOException;
 }
@@ -18,6 +18,8 @@ import com.alibaba.graphscope.groot.common.config.CommonConfig;
 import com.alibaba.graphscope.groot.common.config.Configs;
 import com.alibaba.graphscope.groot.common.config.StoreConfig;
 import com.alibaba.graphscope.groot.common.exception.GrootException;
+import com.alibaba.graphscope.groot.common.util.PartitionUtils;
+import com.alibaba.graphscope.groot.common.util.PkHashUtils;
 import com.alibaba.graphscope.groot.common.util.ThreadFactoryUtils;
 import com.alibaba.graphscope.groot.meta.MetaService;
 import com.alibaba.graphscope.groot.operation.OperationBatch;
@@ -38,9 +40,12 @@ import org.slf4j.LoggerFactory;
 import java.io.File;
 import java.io.FileNotFoundException;
 import java.io.IOException;
+import java.nio.charset.StandardCharsets;
 import java.nio.file.Files;
 import java.nio.file.Path;
 import java.nio.file.Paths;
+import java.util.ArrayList;
+import java.util.Collections;
 import java.util.HashMap;
 import java.util.List;
 import java.util.Map;
@@ -55,6 +60,7 @@ import java.util.concurrent.ThreadPoolExecutor;
 import java.util.concurrent.TimeUnit;
 import java.util.concurrent.atomic.AtomicBoolean;
 import java.util.concurrent.atomic.AtomicInteger;
+import java.util.concurrent.atomic.AtomicLong;
 
 public class StoreService {
     private static final Logger logger = LoggerFactory.getLogger(StoreService.class);
@@ -63,6 +69,7 @@ public class StoreService {
     private final int storeId;
     private final int writeThreadCount;
     private final int compactThreadCount;
+    private final long maxThrottledWaitMs;
     private final MetaService metaService;
     private Map<Integer, GraphPartition> idToPartition;
     private ExecutorService writeExecutor;
@@ -84,6 +91,7 @@ public class StoreService {
         this.enableGc = StoreConfig.STORE_GC_ENABLE.get(storeConfigs);
         this.writeThreadCount = StoreConfig.STORE_WRITE_THREAD_COUNT.get(storeConfigs);
         this.compactThreadCount = StoreConfig.STORE_COMPACT_THREAD_NUM.get(storeConfigs);
+        this.maxThrottledWaitMs = StoreConfig.STORE_WRITE_THROTTLE_MAX_WAIT_MS.get(storeConfigs);
         this.metaService = metaService;
         this.isSecondary = CommonConfig.SECONDARY_INSTANCE_ENABLED.get(storeConfigs);
     }
@@ -215,19 +223,29 @@ public class StoreService {
         List<Map<Integer, OperationBatch>> dataBatch = storeDataBatch.getDataBatch();
         AtomicBoolean hasDdl = new AtomicBoolean(false);
         int maxRetry = 10;
+        long throttleDeadline = start + this.maxThrottledWaitMs;
         for (Map<Integer, OperationBatch> partitionToBatch : dataBatch) {
             while (!shouldStop && partitionToBatch.size() != 0 && maxRetry > 0) {
-                partitionToBatch = writeStore(snapshotId, partitionToBatch, hasDdl);
-                maxRetry--;
+                AtomicBoolean failed = new AtomicBoolean(false);
+                partitionToBatch = writeStore(snapshotId, partitionToBatch, hasDdl, failed);
+                // the writes throttled by the store are retried without using up the retries of
+                // the failed ones, until they have been throttled for the max wait
+                if (failed.get() || System.currentTimeMillis() > throttleDeadline) {
+                    maxRetry--;
+                }
             }
         }
         return hasDdl.get();
     }
 
     private Map<Integer, OperationBatch> writeStore(
-            long snapshotId, Map<Integer, OperationBatch> partitionToBatch, AtomicBoolean hasDdl)
+            long snapshotId,
+            Map<Integer, OperationBatch> partitionToBatch,
+            AtomicBoolean hasDdl,
+            AtomicBoolean failed)
             throws ExecutionException, InterruptedException {
         Map<Integer, OperationBatch> batchNeedRetry = new ConcurrentHashMap<>();
+        AtomicLong retryAfterMs = new AtomicLong(0L);
         AtomicInteger counter = new AtomicInteger(partitionToBatch.size());
         CompletableFuture<Object> future = new CompletableFuture<>();
         for (Map.Entry<Integer, OperationBatch> e : partitionToBatch.entrySet()) {
@@ -257,7 +275,20 @@ public class StoreService {
                                         System.currentTimeMillis() - start, attrs.build());
                                 this.writeCounter.add(batch.getOperationCount(), attrs.build());
                             }
+                        } catch (WriteThrottledException ex) {
+                            logger.warn(
+                                    "write to partition [{}] throttled, snapshotId [{}], retry"
+                                            + " after [{}] ms",
+                                    partitionId,
+                                    snapshotId,
+                                    ex.getRetryAfterMs());
+                            retryAfterMs.accumulateAndGet(ex.getRetryAfterMs(), Math::max);
+                            batchNeedRetry.put(partitionId, batch);
+                            attrs.put("success", false).put("message", ex.getMessage());
+                            this.writeHistogram.record(
+                                    System.currentTimeMillis() - start, attrs.build());
                         } catch (Exception ex) {
+                            failed.set(true);
                             logger.error(
                                     "write to partition [{}] failed, snapshotId [{}].",
                                     partitionId,
@@ -284,7 +315,8 @@ public class StoreService {
         future.get();
         if (batchNeedRetry.size() > 0) {
             try {
-                Thread.sleep(1000L);
+                // back off as long as the store asks, or a while for the failed writes
+                Thread.sleep(Math.max(retryAfterMs.get(), failed.get() ? 1000L : 0L));
             } catch (InterruptedException e) {
                 // Ignore
             }
@@ -454,6 +486,61 @@ public class StoreService {
         }
     }
 
+    /**
+     * Seal the graph of each partition at the snapshot into the sub directory of the partition id,
+     * e.g. after a bulk load, see {@link GraphPartition#seal}.
+     */
+    public void sealGraph(long snapshotId, String dir) throws IOException {
+        if (isSecondary) {
+            throw new IOException("secondary store cannot seal the graph");
+        }
+        for (GraphPartition partition : this.idToPartition.values()) {
+            String path = Paths.get(dir, String.valueOf(partition.getId())).toString();
+            logger.info(
+                    "seal partition {} at snapshot {} to {}", partition.getId(), snapshotId, path);
+            partition.seal(snapshotId, path);
+        }
+    }
+
+    public void setBlockCacheCapacity(long capacityBytes) throws IOException {
+        logger.info("set block cache capacity of partitions to {}", capacityBytes);
+        for (GraphPartition partition : this.idToPartition.values()) {
+            partition.setBlockCacheCapacity(capacityBytes);
+        }
+    }
+
+    /**
+     * Map the external string ids of the label to vertex ids by the partitions owning them, i.e.
+     * those of the ids hashed as single primary keys, which should be all in this store.
+     */
+    public long[] mapExternalIds(int labelId, List<String> externalIds) throws IOException {
+        int partitionCount = CommonConfig.PARTITION_COUNT.get(storeConfigs);
+        Map<Integer, List<Integer>> partitionToIndices = new HashMap<>();
+        for (int i = 0; i < externalIds.size(); i++) {
+            byte[] bytes = externalIds.get(i).getBytes(StandardCharsets.UTF_8);
+            long key = PkHashUtils.hash(labelId, Collections.singletonList(bytes));
+            int partitionId = PartitionUtils.getPartitionIdFromKey(key, partitionCount);
+            partitionToIndices.computeIfAbsent(partitionId, k -> new ArrayList<>()).add(i);
+        }
+        long[] ids = new long[externalIds.size()];
+        for (Map.Entry<Integer, List<Integer>> entry : partitionToIndices.entrySet()) {
+            GraphPartition partition = this.idToPartition.get(entry.getKey());
+            if (partition == null) {
+                throw new IOException("partition [" + entry.getKey() + "] is not in this store");
+            }
+            List<Integer> indices = entry.getValue();
+            List<String> partitionIds = new ArrayList<>(indices.size());
+            for (int i : indices) {
+                partitionIds.add(externalIds.get(i));
+            }
+            long[] mapped = partition.mapExternalIds(labelId, partitionIds);
+            for (int i = 0; i < indices.size(); i++) {
+                ids[indices.get(i)] = mapped[i];
+            }
+        }
+        return ids;
+    }
+
     public void tryCatchUpWithPrimary() throws IOException {
         if (!isSecondary) {
             return;
@@ -0,0 +1,34 @@
+/**
+ * Copyright 2020 Alibaba Group Holding Limited.
+ *
+ * <p>Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file
+ * except in compliance with the License. You may obtain a copy of the License at
+ *
+ * <p>http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * <p>Unless required by applicable law or agreed to in writing, software distributed under the
+ * License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+ * express or implied. See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package com.alibaba.graphscope.groot.store;
+
+import java.io.IOException;
+
+/**
+ * A write rejected by the store under the backlog of flushes and compactions, which is to be
+ * retried after {@link #getRetryAfterMs()}.
+ */
+public class WriteThrottledException extends IOException {
+
+    private final long retryAfterMs;
+
+    public WriteThrottledException(String message, long retryAfterMs) {
+        super(message);
+        this.retryAfterMs = retryAfterMs;
+    }
+
+    public long getRetryAfterMs() {
+        return retryAfterMs;
+    }
+}
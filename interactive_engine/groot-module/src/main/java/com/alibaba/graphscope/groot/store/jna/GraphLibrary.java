@@ -51,4 +51,23 @@ public interface GraphLibrary extends Library {
     JnaResponse reopenSecondary(Pointer storePointer, long wait_sec);
 
     JnaResponse compact(Pointer storePointer);
+
+    JnaResponse setBlockCacheCapacity(Pointer storePointer, long capacityBytes);
+
+    JnaResponse mapExternalIds(Pointer storePointer, int labelId, byte[] data, int len);
+
+    JnaResponse getExternalId(Pointer storePointer, int labelId, long vertexId);
+
+    JnaResponse rotateEncryptionKey(Pointer storePointer);
+
+    JnaResponse drainGraphStore(Pointer storePointer);
+
+    JnaResponse sealGraphStore(Pointer storePointer, long snapshotId, String path);
+
+    JnaResponse pullReplicationLog(Pointer storePointer, long sinceSnapshotId, long maxBytes);
+
+    JnaResponse applyReplicationLog(
+            Pointer storePointer, long leaderSnapshotId, byte[] data, int len);
+
+    long getReplicaStaleness(Pointer storePointer);
 }
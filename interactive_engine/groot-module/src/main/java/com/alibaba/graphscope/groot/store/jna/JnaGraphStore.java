@@ -18,6 +18,7 @@ import com.alibaba.graphscope.groot.common.config.Configs;
 import com.alibaba.graphscope.groot.common.config.StoreConfig;
 import com.alibaba.graphscope.groot.operation.OperationBatch;
 import com.alibaba.graphscope.groot.store.GraphPartition;
+import com.alibaba.graphscope.groot.store.WriteThrottledException;
 import com.alibaba.graphscope.groot.store.backup.GraphPartitionBackup;
 import com.alibaba.graphscope.groot.store.external.ExternalStorage;
 import com.alibaba.graphscope.proto.groot.GraphDefPb;
@@ -28,9 +29,13 @@ import org.slf4j.Logger;
 import org.slf4j.LoggerFactory;
 
 import java.io.IOException;
+import java.nio.ByteBuffer;
+import java.nio.charset.StandardCharsets;
 import java.nio.file.Files;
 import java.nio.file.Path;
 import java.nio.file.Paths;
+import java.util.ArrayList;
+import java.util.List;
 
 public class JnaGraphStore implements GraphPartition {
     private static final Logger logger = LoggerFactory.getLogger(JnaGraphStore.class);
@@ -62,6 +67,11 @@ public class JnaGraphStore implements GraphPartition {
             Path walPath = Paths.get(walDir, "" + partitionId);
             builder.put(StoreConfig.STORE_WAL_DIR.getKey(), walPath.toString());
         }
+        String sealedDir = StoreConfig.STORE_SEALED_PATH.get(configs);
+        if (!sealedDir.isEmpty()) {
+            Path sealedPath = Paths.get(sealedDir, "" + partitionId);
+            builder.put(StoreConfig.STORE_SEALED_PATH.getKey(), sealedPath.toString());
+        }
         if (CommonConfig.SECONDARY_INSTANCE_ENABLED.get(configs)) {
             if (!Files.isDirectory(secondPath)) {
                 Files.createDirectories(secondPath);
@@ -79,6 +89,8 @@ public class JnaGraphStore implements GraphPartition {
             Files.createDirectories(backupPath);
         }
 
+        // the ids mapped from the external ids are allocated by partitions, see `id_mapping`
+        builder.put("store.partition.id", String.valueOf(partitionId));
         byte[] configBytes = builder.build().toProto().toByteArray();
         this.pointer = GraphLibrary.INSTANCE.openGraphStore(configBytes, configBytes.length);
         this.partitionId = partitionId;
@@ -102,6 +114,9 @@ public class JnaGraphStore implements GraphPartition {
                         this.pointer, snapshotId, dataBytes, dataBytes.length)) {
             if (!response.success()) {
                 String errMsg = response.getErrMsg();
+                if (response.getRetryAfterMs() > 0) {
+                    throw new WriteThrottledException(errMsg, response.getRetryAfterMs());
+                }
                 throw new IOException(errMsg);
             }
             return response.hasDdl();
@@ -179,6 +194,90 @@ public class JnaGraphStore implements GraphPartition {
         }
     }
 
+    @Override
+    public long[] mapExternalIds(int labelId, List<String> externalIds) throws IOException {
+        ensurePointer();
+        List<byte[]> encoded = new ArrayList<>(externalIds.size());
+        int len = 0;
+        for (String externalId : externalIds) {
+            byte[] bytes = externalId.getBytes(StandardCharsets.UTF_8);
+            encoded.add(bytes);
+            len += 4 + bytes.length;
+        }
+        ByteBuffer buffer = ByteBuffer.allocate(len);
+        for (byte[] bytes : encoded) {
+            buffer.putInt(bytes.length);
+            buffer.put(bytes);
+        }
+        try (JnaResponse response =
+                GraphLibrary.INSTANCE.mapExternalIds(
+                        this.pointer, labelId, buffer.array(), len)) {
+            if (!response.success()) {
+                throw new IOException(response.getErrMsg());
+            }
+            long[] ids = new long[externalIds.size()];
+            if (ids.length > 0) {
+                ByteBuffer.wrap(response.getData()).asLongBuffer().get(ids);
+            }
+            return ids;
+        }
+    }
+
+    @Override
+    public String getExternalId(int labelId, long vertexId) throws IOException {
+        ensurePointer();
+        try (JnaResponse response =
+                GraphLibrary.INSTANCE.getExternalId(this.pointer, labelId, vertexId)) {
+            if (!response.success()) {
+                throw new IOException(response.getErrMsg());
+            }
+            byte[] data = response.getData();
+            return data == null ? null : new String(data, StandardCharsets.UTF_8);
+        }
+    }
+
+    @Override
+    public void setBlockCacheCapacity(long capacityBytes) throws IOException {
+        ensurePointer();
+        try (JnaResponse response =
+                GraphLibrary.INSTANCE.setBlockCacheCapacity(this.pointer, capacityBytes)) {
+            if (!response.success()) {
+                throw new IOException(response.getErrMsg());
+            }
+        }
+    }
+
+    @Override
+    public void rotateEncryptionKey() throws IOException {
+        ensurePointer();
+        try (JnaResponse response = GraphLibrary.INSTANCE.rotateEncryptionKey(this.pointer)) {
+            if (!response.success()) {
+                throw new IOException(response.getErrMsg());
+            }
+        }
+    }
+
+    @Override
+    public void drain() throws IOException {
+        ensurePointer();
+        try (JnaResponse response = GraphLibrary.INSTANCE.drainGraphStore(this.pointer)) {
+            if (!response.success()) {
+                throw new IOException(response.getErrMsg());
+            }
+        }
+    }
+
+    @Override
+    public void seal(long snapshotId, String path) throws IOException {
+        ensurePointer();
+        try (JnaResponse response =
+                GraphLibrary.INSTANCE.sealGraphStore(this.pointer, snapshotId, path)) {
+            if (!response.success()) {
+                throw new IOException(response.getErrMsg());
+            }
+        }
+    }
+
     private void ensurePointer() throws IOException {
         if (this.pointer == null) {
             throw new IOException("JNA pointer is null");
@@ -19,7 +19,16 @@ import com.sun.jna.Structure;
 import java.io.Closeable;
 import java.io.IOException;
 
-@Structure.FieldOrder({"success", "hasDdl", "errMsg", "data", "len"})
+@Structure.FieldOrder({
+    "success",
+    "hasDdl",
+    "errMsg",
+    "data",
+    "len",
+    "retryAfterMs",
+    "errCode",
+    "retryable"
+})
 public class JnaResponse extends Structure implements Closeable {
 
     public int success;
@@ -27,6 +36,9 @@ public class JnaResponse extends Structure implements Closeable {
     public String errMsg;
     public Pointer data;
     public int len;
+    public long retryAfterMs;
+    public int errCode;
+    public int retryable;
 
     public boolean success() {
         return success == 1;
@@ -40,6 +52,21 @@ public class JnaResponse extends Structure implements Closeable {
         return errMsg;
     }
 
+    /** When to retry the rejected request after, or 0 if it's not to be retried. */
+    public long getRetryAfterMs() {
+        return retryAfterMs;
+    }
+
+    /** The code of the error of the store, in 41xx, or 0 if it's not of the store. */
+    public int getErrCode() {
+        return errCode;
+    }
+
+    /** Whether the failed request may succeed if it's done again. */
+    public boolean isRetryable() {
+        return retryable == 1;
+    }
+
     public byte[] getData() {
         if (this.data != null) {
             return this.data.getByteArray(0, this.len);
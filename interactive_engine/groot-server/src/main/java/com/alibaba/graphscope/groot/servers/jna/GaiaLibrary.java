@@ -28,6 +28,20 @@ public interface GaiaLibrary extends Library {
 
     void updatePartitionRouting(Pointer engine, int partitionId, int serverId);
 
+    void removePartition(Pointer engine, int partitionId);
+
+    void addFollowerPartition(Pointer engine, int partitionId, Pointer graph);
+
+    void removeFollowerPartition(Pointer engine, int partitionId);
+
+    void updatePartitionReplica(Pointer engine, int partitionId, int serverId, long stalenessMs);
+
+    boolean switchPartitionRouting(Pointer engine, String routingJson);
+
+    boolean proposeDdl(Pointer engine, long snapshotId, byte[] data, int len);
+
+    int getPartitionServer(Pointer engine, int partitionId);
+
     void stopEngine(Pointer engine);
 
     void updatePeerView(Pointer pointer, String peerViewString);
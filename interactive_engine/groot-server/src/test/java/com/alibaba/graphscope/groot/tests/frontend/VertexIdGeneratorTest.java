@@ -0,0 +1,98 @@
+package com.alibaba.graphscope.groot.tests.frontend;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+import static org.junit.jupiter.api.Assertions.assertFalse;
+import static org.junit.jupiter.api.Assertions.assertThrows;
+import static org.junit.jupiter.api.Assertions.assertTrue;
+import static org.mockito.Mockito.mock;
+import static org.mockito.Mockito.when;
+
+import com.alibaba.graphscope.groot.common.config.CommonConfig;
+import com.alibaba.graphscope.groot.common.config.Configs;
+import com.alibaba.graphscope.groot.common.config.FrontendConfig;
+import com.alibaba.graphscope.groot.common.exception.InvalidDataException;
+import com.alibaba.graphscope.groot.common.schema.api.GraphElement;
+import com.alibaba.graphscope.groot.common.schema.api.GraphProperty;
+import com.alibaba.graphscope.groot.common.schema.wrapper.DataType;
+import com.alibaba.graphscope.groot.common.schema.wrapper.PropertyValue;
+import com.alibaba.graphscope.groot.frontend.write.VertexIdGenerator;
+
+import org.junit.Test;
+
+import java.util.Collections;
+import java.util.HashMap;
+import java.util.HashSet;
+import java.util.Map;
+import java.util.Set;
+import java.util.concurrent.atomic.AtomicLong;
+
+public class VertexIdGeneratorTest {
+
+    private static GraphElement mockVertexDef(String label, DataType pkType) {
+        GraphProperty pk = mock(GraphProperty.class);
+        when(pk.getId()).thenReturn(1);
+        when(pk.getDataType()).thenReturn(pkType);
+        GraphElement vertexDef = mock(GraphElement.class);
+        when(vertexDef.getLabel()).thenReturn(label);
+        when(vertexDef.getPrimaryKeyList()).thenReturn(Collections.singletonList(pk));
+        return vertexDef;
+    }
+
+    private static VertexIdGenerator newGenerator(AtomicLong counter) {
+        Configs configs =
+                Configs.newBuilder()
+                        .put(
+                                FrontendConfig.VERTEX_ID_STRATEGY.getKey(),
+                                "person:auto_increment, event:SNOWFLAKE")
+                        .put(CommonConfig.NODE_IDX.getKey(), "3")
+                        .build();
+        return new VertexIdGenerator(configs, counter::getAndIncrement);
+    }
+
+    @Test
+    public void testAutoIncrement() {
+        VertexIdGenerator generator = newGenerator(new AtomicLong(100L));
+        GraphElement person = mockVertexDef("person", DataType.LONG);
+        for (long expected = 100L; expected < 103L; expected++) {
+            Map<Integer, PropertyValue> propertyVals = new HashMap<>();
+            generator.fillPrimaryKey(person, propertyVals);
+            assertEquals(expected, propertyVals.get(1).getValue());
+        }
+
+        // the primary keys given are kept
+        Map<Integer, PropertyValue> propertyVals = new HashMap<>();
+        propertyVals.put(1, new PropertyValue(DataType.LONG, 7L));
+        generator.fillPrimaryKey(person, propertyVals);
+        assertEquals(7L, propertyVals.get(1).getValue());
+    }
+
+    @Test
+    public void testSnowflake() {
+        VertexIdGenerator generator = newGenerator(new AtomicLong());
+        GraphElement event = mockVertexDef("event", DataType.LONG);
+        Set<Long> ids = new HashSet<>();
+        long last = -1L;
+        for (int i = 0; i < 10000; i++) {
+            Map<Integer, PropertyValue> propertyVals = new HashMap<>();
+            generator.fillPrimaryKey(event, propertyVals);
+            long id = (long) propertyVals.get(1).getValue();
+            assertTrue(id > last);
+            assertEquals(3L, (id >> 12) & 1023L);
+            ids.add(id);
+            last = id;
+        }
+        assertEquals(10000, ids.size());
+    }
+
+    @Test
+    public void testLabelsWithoutStrategy() {
+        VertexIdGenerator generator = newGenerator(new AtomicLong());
+        Map<Integer, PropertyValue> propertyVals = new HashMap<>();
+        generator.fillPrimaryKey(mockVertexDef("software", DataType.LONG), propertyVals);
+        assertFalse(propertyVals.containsKey(1));
+
+        GraphElement person = mockVertexDef("person", DataType.STRING);
+        assertThrows(
+                InvalidDataException.class, () -> generator.fillPrimaryKey(person, propertyVals));
+    }
+}